@@ -1,10 +1,27 @@
-use std::error::Error;
+use std::{error::Error, path::PathBuf};
 
 use vergen::{BuildBuilder, CargoBuilder, Emitter, RustcBuilder};
 use vergen_gitcl::GitclBuilder;
 
 fn main() {
     emit_build_info().expect("failed to emit build information");
+    export_ffi_symbols();
+}
+
+/// Exports the symbols of `alumet-ffi` from the agent binary, so that the dynamic plugins can use them.
+fn export_ffi_symbols() {
+    // `--dynamic-list` is supported by the GNU and LLVM linkers, which are used on Linux.
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("linux") {
+        return;
+    }
+    // The list of symbols is generated by the build script of `alumet-ffi`, and given to us as cargo metadata.
+    let bindings_dir = std::env::var("DEP_ALUMET_H_BINDINGS_DIR")
+        .expect("cargo metadata BINDINGS_DIR should be set for 'links' alumet_h");
+    let symbols = PathBuf::from(bindings_dir).join("alumet-symbols.txt");
+    println!(
+        "cargo:rustc-link-arg-bin=alumet-agent=-Wl,--dynamic-list={}",
+        symbols.display()
+    );
 }
 
 /// Emit cargo instructions that allow the crate to access
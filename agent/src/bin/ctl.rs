@@ -0,0 +1,107 @@
+//! `alumet-ctl`: controls a running Alumet agent.
+//!
+//! The agent must be started with the `socket-control` plugin, which provides the control socket.
+
+#[cfg(unix)]
+fn main() -> anyhow::Result<()> {
+    use alumet_agent::ctl::ControlClient;
+    use clap::Parser;
+
+    let args = cli::Cli::parse();
+    let command = args.command.to_socket_command()?;
+    let mut client = ControlClient::connect(&args.socket)?;
+    for line in client.send(&command)? {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn main() -> std::process::ExitCode {
+    eprintln!("alumet-ctl is only available on Unix systems.");
+    std::process::ExitCode::FAILURE
+}
+
+#[cfg(unix)]
+mod cli {
+    use std::path::PathBuf;
+
+    use clap::{Parser, Subcommand, ValueEnum};
+
+    /// Control a running Alumet agent, through the socket of the `socket-control` plugin.
+    ///
+    /// Element patterns have the form `kind/plugin/element`, where `kind` is `source`, `transform` or `output`,
+    /// and the plugin and element names can use wildcards, for instance `source/rapl/*`.
+    /// The kind alone is also accepted, for instance `source`.
+    #[derive(Parser)]
+    pub struct Cli {
+        /// Path to the control socket, as set in the config of the socket-control plugin.
+        #[arg(long, short, env = "ALUMET_CONTROL_SOCKET", default_value = "alumet-control.sock")]
+        pub socket: PathBuf,
+
+        #[command(subcommand)]
+        pub command: Command,
+    }
+
+    #[derive(Subcommand)]
+    pub enum Command {
+        /// List the elements of the pipeline.
+        List {
+            /// Only list the elements that match this pattern.
+            pattern: Option<String>,
+        },
+        /// Pause sources, transforms or outputs.
+        Pause { pattern: String },
+        /// Resume sources, transforms or outputs.
+        Resume { pattern: String },
+        /// Stop and remove sources or outputs.
+        Remove { pattern: String },
+        /// Change the poll interval of sources.
+        SetInterval {
+            pattern: String,
+            /// The new interval, for instance `500ms` or `10s`.
+            interval: String,
+        },
+        /// Poll sources now (only works for sources that accept manual triggers).
+        Trigger { pattern: String },
+        /// Enable or disable all the elements of a plugin.
+        Plugin { name: String, action: PluginAction },
+        /// Show the number of elements, the health of the plugins and the errors of the elements.
+        Stats,
+        /// Stop the agent.
+        Shutdown,
+    }
+
+    #[derive(Clone, Copy, ValueEnum)]
+    pub enum PluginAction {
+        Enable,
+        Disable,
+    }
+
+    impl Command {
+        /// Returns the command to send to the control socket.
+        pub fn to_socket_command(&self) -> anyhow::Result<String> {
+            let command = match self {
+                Command::List { pattern: None } => String::from("list"),
+                Command::List { pattern: Some(pat) } => format!("list {pat}"),
+                Command::Pause { pattern } => format!("control {pattern} pause"),
+                Command::Resume { pattern } => format!("control {pattern} resume"),
+                Command::Remove { pattern } => format!("control {pattern} stop"),
+                Command::SetInterval { pattern, interval } => {
+                    // check the interval here, for a better error message
+                    humantime::parse_duration(interval)
+                        .map_err(|e| anyhow::anyhow!("invalid interval '{interval}': {e}"))?;
+                    format!("control {pattern} set-period {interval}")
+                }
+                Command::Trigger { pattern } => format!("control {pattern} trigger-now"),
+                Command::Plugin { name, action } => match action {
+                    PluginAction::Enable => format!("plugin {name} enable"),
+                    PluginAction::Disable => format!("plugin {name} disable"),
+                },
+                Command::Stats => String::from("stats"),
+                Command::Shutdown => String::from("shutdown"),
+            };
+            Ok(command)
+        }
+    }
+}
@@ -1,21 +1,37 @@
-use std::{process::ExitCode, str::FromStr, time::Duration};
+use std::{path::Path, process::ExitCode, str::FromStr, time::Duration};
 
 use alumet::{
     agent::{
         self,
-        config::{AutoDefaultConfigProvider, DefaultConfigProvider, NoDefaultConfigProvider, merge_override},
+        builder::CheckError,
+        config::{
+            AutoDefaultConfigProvider, DefaultConfigProvider, NoDefaultConfigProvider, merge_override, parse_assignment,
+        },
         exec,
         plugin::{PluginFilter, PluginSet, UnknownPluginInConfigPolicy},
-        watch,
+        reload::{self, ConfigWatcher},
     },
-    pipeline,
-    plugin::PluginMetadata,
+    pipeline::{self, elements::output::builder::OutputBuilder, naming::PluginName},
+    plugin::{PluginMetadata, schema, secret},
     static_plugins,
 };
-use alumet_agent::{exec_hints, init_logger};
+#[cfg(windows)]
+use alumet_agent::service;
+#[cfg(unix)]
+use alumet_agent::{daemon, exec_hints};
+use alumet_agent::{
+    init_logger,
+    logging::{self, LoggingConfig},
+    plugin_dir,
+    profiles::{PROFILES, Profile},
+    top::{self, TopOutput, TopState},
+    vault::VaultProvider,
+};
 use anyhow::Context;
 use clap::{Args, FromArgMatches};
 use cli::{ConfigArgs, ConfigCommand, PluginsArgs, PluginsCommand};
+#[cfg(windows)]
+use cli::{ServiceArgs, ServiceCommand};
 use config::GeneralConfig;
 
 const BINARY: &str = env!("CARGO_BIN_NAME");
@@ -30,13 +46,25 @@ fn load_plugins_metadata() -> Vec<PluginMetadata> {
         plugin_mongodb::MongoDbPlugin,
         plugin_relay::client::RelayClientPlugin,
         plugin_relay::server::RelayServerPlugin,
+        plugin_replay::ReplayPlugin,
         plugin_opentelemetry::OpenTelemetryPlugin,
         plugin_aggregation::AggregationPlugin,
         plugin_energy_attribution::EnergyAttributionPlugin,
         plugin_energy_estimation_tdp::EnergyEstimationTdpPlugin,
+        plugin_power_model::PowerModelPlugin,
+        plugin_ml_power_model::MlPowerModelPlugin,
         plugin_elasticsearch::ElasticSearchPlugin,
         plugin_kwollect_input::KwollectPluginInput,
         plugin_kwollect_output::KwollectPlugin,
+        plugin_g5k_wattmetre::G5kWattmetrePlugin,
+        plugin_mqtt::MqttPlugin,
+        plugin_redfish::RedfishPlugin,
+        plugin_script::ScriptPlugin,
+        plugin_rest_pdu::RestPduPlugin,
+        plugin_smart_plug::SmartPlugPlugin,
+        plugin_snmp_pdu::SnmpPduPlugin,
+        plugin_sysinfo::SysinfoPlugin,
+        plugin_wasm::WasmPlugin,
     ];
 
     // plugins that only work on Linux
@@ -44,24 +72,70 @@ fn load_plugins_metadata() -> Vec<PluginMetadata> {
     {
         plugins.extend(static_plugins![
             plugin_socket_control::SocketControlPlugin,
+            plugin_docker::DockerPlugin,
             plugin_k8s::K8sPlugin,
             plugin_slurm::SlurmPlugin,
             plugin_oar::OarPlugin,
             plugin_raw_cgroups::RawCgroupPlugin,
+            plugin_systemd::SystemdPlugin,
             plugin_grace_hopper::GraceHopperPlugin,
+            plugin_arm_power::ArmPowerPlugin,
             plugin_rapl::RaplPlugin,
             plugin_perf::PerfPlugin,
+            plugin_ebpf::EbpfPlugin,
             plugin_procfs::ProcfsPlugin,
+            plugin_filesystem::FilesystemPlugin,
+            plugin_psi::PsiPlugin,
             plugin_nvidia_nvml::NvmlPlugin,
+            plugin_amdgpu::AmdGpuPlugin,
+            plugin_intel_gpu::IntelGpuPlugin,
+            plugin_ipmi::IpmiPlugin,
+            plugin_nvme::NvmePlugin,
+            plugin_sata::SataPlugin,
+            plugin_serial_wattmeter::SerialWattmeterPlugin,
+            plugin_hwmon::HwmonPlugin,
+            plugin_i2c_power::I2cPowerPlugin,
+            plugin_battery::BatteryPlugin,
+            plugin_thermal::ThermalPlugin,
+            plugin_cpufreq::CpufreqPlugin,
+            plugin_resctrl::ResctrlPlugin,
+            plugin_numa::NumaPlugin,
+            plugin_infiniband::InfinibandPlugin,
+            plugin_fpga::FpgaPlugin,
+            plugin_modbus::ModbusPlugin,
             plugin_process_to_cgroup_bridge::ProcessToCgroupBridgePlugin,
             plugin_nvidia_jetson::JetsonPlugin,
+            plugin_raspberry_pi::RaspberryPiPlugin,
             plugin_quarch::QuarchPlugin,
         ]);
     }
 
+    // plugins that only work on macOS
+    #[cfg(target_os = "macos")]
+    plugins.extend(static_plugins![plugin_apple_silicon::AppleSiliconPlugin]);
+
+    // plugins that only work on Windows
+    #[cfg(windows)]
+    plugins.extend(static_plugins![plugin_windows::WindowsPlugin]);
+
+    // plugins that depend on optional features
+    #[cfg(feature = "python")]
+    plugins.extend(static_plugins![plugin_python::PythonPlugin]);
+
     plugins
 }
 
+fn main() -> anyhow::Result<ExitCode> {
+    // When started by the Windows service manager, run under its control (and log to the event log).
+    #[cfg(windows)]
+    if service::is_service_launch() {
+        return service::run(run_agent);
+    }
+
+    init_logger();
+    run_agent()
+}
+
 /// Main agent function.
 ///
 /// The steps are:
@@ -74,8 +148,11 @@ fn load_plugins_metadata() -> Vec<PluginMetadata> {
 ///
 /// About errors: we use `anyhow::Result` and `context` instead of `expect` to get
 /// nicer error messages (`expect` prints errors with `Debug`).
-fn main() -> anyhow::Result<ExitCode> {
-    init_logger();
+fn run_agent() -> anyhow::Result<ExitCode> {
+    // Allow plugin configs to reference secrets stored in Vault.
+    if let Some(vault) = VaultProvider::from_env().context("invalid Vault settings")? {
+        secret::register_provider("vault", vault);
+    }
 
     // Load plugins metadata.
     let mut plugins = PluginSet::from(load_plugins_metadata());
@@ -91,28 +168,61 @@ fn main() -> anyhow::Result<ExitCode> {
     // Special flags like --help will exit. In other cases, we continue.
     print_welcome();
 
+    // Add the dynamic plugins, if any. They are disabled until the config (or the CLI args) enables them.
+    let dynamic_plugins = match &args.common.plugins_dir {
+        Some(dir) => {
+            let policy = plugin_dir::trust_policy(
+                args.common.plugins_allowlist.as_deref(),
+                &args.common.plugins_trusted_key,
+            )
+            .context("invalid trust settings for the dynamic plugins")?;
+            plugin_dir::add_plugins_from_dir(dir, policy.as_ref(), &mut plugins)?
+        }
+        None => Vec::new(),
+    };
+
     // If the CLI args override the list of enabled plugins, we need to know it now,
     // because that will change how some "no config" commands work (such as config regen).
     if let Some(enabled_plugins) = &args.common.plugins {
         plugins.enable_only(enabled_plugins);
     }
+    let profile = args.common.profile.as_deref().and_then(Profile::find);
+    if let Some(profile) = profile {
+        log::info!("Using profile {}: {}", profile.name, profile.description);
+        plugins.enable_only(profile.plugins);
+    }
 
     // Run CLI commands that run before the config is loaded.
     if run_command_no_config(&args, &plugins)? {
         return Ok(ExitCode::SUCCESS);
     }
 
+    // upgrade the config file, if requested
+    if args.common.migrate_config {
+        migrate_config_file(&args.common.config, &plugins)?;
+    }
+
     // parse config file
     let config_override = parse_config_overrides(&args).context("invalid config overrides")?;
     let default_config_provider: Box<dyn DefaultConfigProvider> = if args.common.no_default_config {
         Box::new(NoDefaultConfigProvider)
+    } else if let Some(profile) = profile {
+        // default config of the plugins, adjusted by the profile
+        let provider = AutoDefaultConfigProvider::new(&plugins, config::GeneralConfig::default);
+        Box::new(move || {
+            let mut config = provider.default_config()?;
+            merge_override(&mut config, profile.config()?);
+            Ok(config)
+        })
     } else {
         Box::new(AutoDefaultConfigProvider::new(&plugins, config::GeneralConfig::default))
     };
+    let config_overlays = args.common.config_overlay.clone().unwrap_or_default();
     let mut config = agent::config::Loader::parse_file(&args.common.config)
         .or_default_boxed(default_config_provider, true)
         .substitute_env_variables(true)
-        .with_override(config_override)
+        .with_overlays(&config_overlays)
+        .with_override(config_override.clone())
         .load()
         .context("could not load config file")?;
 
@@ -121,13 +231,34 @@ fn main() -> anyhow::Result<ExitCode> {
     let plugins_config_order = plugins
         .extract_config(
             &mut config,
-            args.common.plugins.is_none(),
+            args.common.plugins.is_none() && profile.is_none(),
             UnknownPluginInConfigPolicy::Error,
         )
         .context("invalid plugins config")?;
+    plugin_dir::disable_unconfigured(&dynamic_plugins, &mut plugins);
+    if matches!(args.command, Some(cli::Command::Batch(_))) {
+        // the measurements to process are provided by the replay plugin
+        plugins.set_plugin_enabled("replay", true);
+    }
+
+    // Check the config, if requested, without starting anything.
+    if let Some(cli::Command::Config(ConfigArgs {
+        command: ConfigCommand::Check,
+    })) = args.command
+    {
+        return Ok(check_config(config, plugins));
+    }
 
     // Extract non-plugin config.
     let config = config.try_into::<GeneralConfig>().context("invalid general config")?;
+    let mut logging_config = config.logging.clone();
+    if matches!(args.command, Some(cli::Command::Top(_))) {
+        // The terminal is used by the live view: only keep the log file, if any.
+        logging_config.get_or_insert_with(LoggingConfig::default).stderr = false;
+    }
+    if let Some(logging) = &logging_config {
+        logging::configure_logger(logging).context("invalid logging config")?;
+    }
 
     // Reorder the plugins according to the configuration.
     plugins.reorder_partial(&plugins_config_order);
@@ -137,20 +268,92 @@ fn main() -> anyhow::Result<ExitCode> {
         return Ok(ExitCode::SUCCESS);
     }
 
+    // Detach from the terminal, if requested. This must be done before starting the pipeline (which spawns threads).
+    #[cfg(unix)]
+    let (_pid_file, credentials) = start_daemon(&args).context("could not start the daemon")?;
+
+    // If enabled, watch the config file in order to reconfigure the plugins on the fly.
+    let config_watcher = match config.config_reload_interval {
+        Some(interval) if matches!(args.command, None | Some(cli::Command::Run)) => {
+            let file = args.common.config.clone();
+            let load = move || {
+                let config = agent::config::Loader::parse_file(&file)
+                    .substitute_env_variables(true)
+                    .with_overlays(&config_overlays)
+                    .with_override(config_override.clone())
+                    .load_with_files()?;
+                Ok(config)
+            };
+            let watcher = ConfigWatcher::new(&args.common.config, interval.into_inner(), load)
+                .context("could not watch the config file")?;
+            Some(watcher)
+        }
+        _ => None,
+    };
+
     // begin the creation of the pipeline (we have some settings to apply to it)
     let mut pipeline = pipeline::Builder::new();
     apply_pipeline_settings(&args, &config, &mut pipeline);
 
+    // the live view receives the measurements through an additional output
+    let top_state = TopState::default();
+    if matches!(args.command, Some(cli::Command::Top(_))) {
+        let state = top_state.clone();
+        pipeline
+            .add_output_builder(
+                PluginName(String::from("top")),
+                "view",
+                OutputBuilder::Blocking(Box::new(move |_| Ok(Box::new(TopOutput::new(state))))),
+            )
+            .context("could not add the output of the live view")?;
+    }
+
+    // Restrict the process before the plugins start, so that every thread of the pipeline is restricted.
+    // The libraries of the dynamic plugins have already been loaded: only the trust policy applies to their loading.
+    #[cfg(target_os = "linux")]
+    if let Some(sandbox) = &config.sandbox {
+        if dynamic_plugins.iter().any(|name| plugins.is_plugin_enabled(name)) {
+            if args.common.plugins_allowlist.is_none() && args.common.plugins_trusted_key.is_empty() {
+                log::warn!(
+                    "The dynamic plugins have been loaded without verification, before the sandbox was applied. Use --plugins-allowlist or --plugins-trusted-key to only load trusted plugins."
+                );
+            }
+            alumet_agent::sandbox::apply(sandbox).context("could not sandbox the dynamic plugins")?;
+        } else {
+            log::debug!("No dynamic plugin is enabled, the sandbox is not applied.");
+        }
+    }
+
     // start Alumet with the pipeline and plugins
-    let agent = agent::Builder::from_pipeline(plugins, pipeline)
-        .build_and_start()
-        .context("startup failure")?;
+    let mut agent_builder = agent::Builder::from_pipeline(plugins, pipeline);
+    if let Some(timeouts) = &config.plugin_timeouts {
+        agent_builder = agent_builder.plugin_timeouts(timeouts.into());
+    }
+    if matches!(args.command, Some(cli::Command::Batch(_))) {
+        agent_builder = agent_builder.after_plugins_start(warn_unbounded_sources);
+    }
+    let agent = agent_builder.build_and_start().context("startup failure")?;
+
+    // Stop the pipeline when the service manager asks to.
+    #[cfg(windows)]
+    service::set_control_handle(agent.pipeline.control_handle());
+
+    // The privileged resources have been opened by the sources, we can now switch to an unprivileged user.
+    #[cfg(unix)]
+    if let Some(credentials) = credentials {
+        daemon::drop_privileges(&credentials).context("could not drop privileges")?;
+        log::info!("Now running as user {} and group {}.", credentials.uid, credentials.gid);
+    }
 
     // run the provided command, the default is Run
     match args.command.take().unwrap_or(cli::Command::Run) {
         cli::Command::Run => {
             // execute the pipeline until Alumet is externally stopped (e.g. by Ctrl+C)
-            agent.wait_for_shutdown(Duration::MAX).context("error while running")?;
+            match config_watcher {
+                Some(watcher) => reload::run_with_reload(agent, watcher, Duration::MAX),
+                None => agent.wait_for_shutdown(Duration::MAX),
+            }
+            .context("error while running")?;
         }
         cli::Command::Exec(exec_args) => {
             let timeout = Duration::from_secs(5);
@@ -173,6 +376,7 @@ fn main() -> anyhow::Result<ExitCode> {
                         return Ok(ExitCode::FAILURE);
                     }
                 }
+                #[cfg(unix)]
                 Err(err @ exec::ExecError::ProcessSpawn(program, e)) => {
                     // print some helpful hints for common problems
                     match e.kind() {
@@ -190,7 +394,26 @@ fn main() -> anyhow::Result<ExitCode> {
                 Err(err) => panic!("{err}"),
             }
         }
+        cli::Command::Batch(_) => {
+            // the pipeline stops by itself once the recorded measurements have been processed
+            agent
+                .wait_for_shutdown(Duration::MAX)
+                .context("error while processing the measurements")?;
+            log::info!("All the measurements have been processed.");
+        }
+        cli::Command::Top(top_args) => {
+            let handle = agent.pipeline.control_handle();
+            let res = top::run_ui(top_state, handle.clone(), top_args.refresh);
+            handle.shutdown();
+            agent
+                .wait_for_shutdown(Duration::from_secs(5))
+                .context("error while shutting down")?;
+            res.context("error in the live view")?;
+        }
+        #[cfg(target_os = "linux")]
         cli::Command::Watch(process) => {
+            use alumet::agent::watch;
+
             let shutdown_timeout = Duration::from_secs(5);
             let res = watch::watch_process(agent, process.pid, shutdown_timeout);
 
@@ -238,6 +461,44 @@ fn run_command_no_config(args: &cli::Cli, plugins: &PluginSet) -> anyhow::Result
             log::info!("Default configuration file written to: {file}");
             Ok(true)
         }
+        Some(Command::Config(ConfigArgs {
+            command: ConfigCommand::Schema,
+        })) => {
+            // print the schema of the config, for every plugin
+            let schema = schema::agent_config_schema(plugins.metadata(PluginFilter::Any), None)?;
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            Ok(true)
+        }
+        Some(Command::Plugins(PluginsArgs {
+            command: PluginsCommand::Profiles,
+            ..
+        })) => {
+            println!("Available profiles:");
+            for p in PROFILES {
+                println!("- {}: {}", p.name, p.description);
+                println!("  plugins: {}", p.plugins.join(", "));
+            }
+            println!("\nUse the --profile flag to select a profile.");
+            Ok(true)
+        }
+        #[cfg(windows)]
+        Some(Command::Service(ServiceArgs { ref command })) => {
+            match command {
+                ServiceCommand::Install => {
+                    service::install(Path::new(&args.common.config))?;
+                    log::info!(
+                        "Service {} installed, with the config file {}.",
+                        service::SERVICE_NAME,
+                        args.common.config
+                    );
+                }
+                ServiceCommand::Uninstall => {
+                    service::uninstall()?;
+                    log::info!("Service {} uninstalled.", service::SERVICE_NAME);
+                }
+            }
+            Ok(true)
+        }
         Some(Command::Plugins(PluginsArgs {
             status: false,
             command: PluginsCommand::List,
@@ -281,6 +542,84 @@ fn run_command_no_measurement(args: &cli::Cli, _config: &GeneralConfig, plugins:
     }
 }
 
+/// Upgrades the config file to the latest format of each plugin.
+fn migrate_config_file(file: &str, plugins: &PluginSet) -> anyhow::Result<()> {
+    let migrated = agent::config::migrate_config_file(Path::new(file), plugins.metadata(PluginFilter::Any))
+        .context("config migration failed")?;
+    if migrated.is_empty() {
+        log::info!("The config file is up to date, no migration needed.");
+    }
+    for (plugin, old, new) in migrated {
+        log::info!("Migrated the config of plugin {plugin} from version {old} to version {new}.");
+    }
+    Ok(())
+}
+
+/// Forks into the background and writes the PID file, if requested by the CLI args.
+///
+/// Returns the PID file (which is removed when dropped) and the credentials of the user to switch to.
+#[cfg(unix)]
+fn start_daemon(args: &cli::Cli) -> anyhow::Result<(Option<daemon::PidFile>, Option<daemon::Credentials>)> {
+    // Look up the user before detaching, so that errors are printed to the terminal.
+    let credentials = match &args.common.user {
+        Some(user) => Some(daemon::Credentials::lookup(user, args.common.group.as_deref())?),
+        None => None,
+    };
+
+    if args.common.daemon {
+        if !matches!(args.command, None | Some(cli::Command::Run)) {
+            return Err(anyhow::anyhow!("--daemon can only be used with the run command"));
+        }
+        log::info!("Detaching from the terminal.");
+        daemon::daemonize().context("fork failed")?;
+    }
+
+    let pid_file = match &args.common.pid_file {
+        Some(path) => {
+            if let Some(credentials) = &credentials {
+                let dir = match path.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir,
+                    _ => Path::new("."),
+                };
+                daemon::prepare_owned_dir(dir, credentials).context("invalid location of the PID file")?;
+            }
+            Some(daemon::PidFile::create(path)?)
+        }
+        None => None,
+    };
+    Ok((pid_file, credentials))
+}
+
+/// Checks the general options and the config of every enabled plugin, and prints the errors.
+fn check_config(config: toml::Table, plugins: PluginSet) -> ExitCode {
+    let mut n_errors = 0;
+    if let Err(e) = config.try_into::<GeneralConfig>() {
+        log::error!("Invalid general config: {e}");
+        n_errors += 1;
+    }
+    let n_plugins = plugins.metadata(PluginFilter::Enabled).count();
+    match agent::Builder::new(plugins).check() {
+        Ok(()) => (),
+        Err(CheckError::Dependencies(e)) => {
+            log::error!("Invalid plugin dependencies: {e}");
+            n_errors += 1;
+        }
+        Err(CheckError::InvalidConfig(errors)) => {
+            for (plugin, error) in &errors {
+                log::error!("Invalid config for plugin {plugin}: {error:#}");
+            }
+            n_errors += errors.len();
+        }
+    }
+    if n_errors == 0 {
+        log::info!("The configuration is valid ({n_plugins} plugins enabled).");
+        ExitCode::SUCCESS
+    } else {
+        log::error!("The configuration contains {n_errors} error(s).");
+        ExitCode::FAILURE
+    }
+}
+
 /// Setup the measurement pipeline according to CLI args and config file.
 fn apply_pipeline_settings(args: &cli::Cli, config: &GeneralConfig, pipeline: &mut pipeline::Builder) {
     // config file
@@ -290,6 +629,9 @@ fn apply_pipeline_settings(args: &cli::Cli, config: &GeneralConfig, pipeline: &m
     if let Some(source_channel_size) = config.source_channel_size {
         *pipeline.source_channel_size() = source_channel_size;
     }
+    if let Some(health_metrics_interval) = config.health_metrics_interval {
+        *pipeline.health_metrics_interval() = Some(health_metrics_interval.into_inner());
+    }
 
     // cli arguments
     if let Some(max_update_interval) = args.common.max_update_interval {
@@ -302,6 +644,20 @@ fn apply_pipeline_settings(args: &cli::Cli, config: &GeneralConfig, pipeline: &m
         // the "exec" command requires event-based source trigger
         pipeline.trigger_constraints_mut().allow_manual_trigger = true;
     }
+    if matches!(args.command, Some(cli::Command::Batch(_))) {
+        *pipeline.shutdown_when_sources_finish() = true;
+    }
+}
+
+/// In batch mode, warns about the sources that will not stop by themselves.
+fn warn_unbounded_sources(pipeline: &mut pipeline::Builder) {
+    for source in pipeline.inspect().sources() {
+        if source.plugin() != "replay" {
+            log::warn!(
+                "Source {source} does not replay recorded measurements: the batch will not end before it stops. Use --plugins to disable its plugin."
+            );
+        }
+    }
 }
 
 /// Parses the config overrides provided on the command line, and merges them into a single table.
@@ -311,18 +667,39 @@ fn parse_config_overrides(args: &cli::Cli) -> anyhow::Result<toml::Table> {
         for o in overrides {
             let parsed_override =
                 toml::Table::from_str(o).with_context(|| format!("config override is not a valid TOML table: {o}"))?;
-            // TODO we could make overrides a bit easier to use by turning
-            // `key=value` to `key='value'` automatically (if value is not a number nor boolean)
             merge_override(&mut config_override, parsed_override);
         }
     }
 
+    // Dotted-key assignments.
+    for assignment in &args.common.set {
+        let parsed_override = parse_assignment(assignment)?;
+        merge_override(&mut config_override, parsed_override);
+    }
+
     // Special case `--output` for easier local use.
     if let Some(output) = &args.common.output_file {
         let o = plugin_config_override("csv", "output_path", toml::Value::String(output.to_owned()));
         merge_override(&mut config_override, o);
     }
 
+    // Special case `batch`: the files to process.
+    if let Some(cli::Command::Batch(batch)) = &args.command {
+        let files = batch
+            .input
+            .iter()
+            .map(|path| toml::Value::String(path.to_string_lossy().into_owned()))
+            .collect();
+        merge_override(
+            &mut config_override,
+            plugin_config_override("replay", "input_files", toml::Value::Array(files)),
+        );
+        merge_override(
+            &mut config_override,
+            plugin_config_override("replay", "speed", toml::Value::Float(batch.speed)),
+        );
+    }
+
     // Special case `--relay-out`.
     if let Some(addr) = &args.common.relay_out {
         let o = plugin_config_override("relay-client", "relay_server", toml::Value::String(addr.to_owned()));
@@ -383,8 +760,9 @@ fn agent_version() -> String {
 /// To apply "advanced" tweaks, we combine the "derive" and "builder" APIs of clap.
 /// See https://docs.rs/clap/latest/clap/_derive/index.html#mixing-builder-and-derive-apis
 mod cli {
+    use alumet_agent::profiles::Profile;
     use clap::{Args, Parser, Subcommand};
-    use std::time::Duration;
+    use std::{path::PathBuf, time::Duration};
 
     // NOTE: the doc comment attached to `Cli` is used by clap as the description of
     // the application. It is displayed at the start of the help message.
@@ -410,13 +788,30 @@ mod cli {
         Exec(ExecArgs),
 
         /// Watch a PID and observe it until its end
+        #[cfg(target_os = "linux")]
         Watch(Process),
 
+        /// Run the agent and show the measurements live in the terminal, like `top`.
+        ///
+        /// The logs are not written to the terminal, but they are still written to the log file, if any.
+        Top(TopArgs),
+
+        /// Process recorded measurements with the transforms and outputs, then exit.
+        ///
+        /// The files are replayed by the `replay` plugin, which is enabled automatically.
+        /// The agent exits once all the measurements have been written by the outputs.
+        /// Use `--plugins` to select the transforms and outputs, for instance `--plugins aggregation,csv`.
+        Batch(BatchArgs),
+
         /// Manipulate the configuration.
         Config(ConfigArgs),
 
         /// Get plugins information.
         Plugins(PluginsArgs),
+
+        /// Manage the Windows service of the agent.
+        #[cfg(windows)]
+        Service(ServiceArgs),
     }
 
     /// CLI arguments for the `exec` command.
@@ -438,7 +833,29 @@ mod cli {
         pub args: Vec<String>,
     }
 
+    /// CLI arguments for the `batch` command.
+    #[derive(Args)]
+    pub struct BatchArgs {
+        /// CSV files to process, as written by the csv plugin.
+        #[arg(required = true)]
+        pub input: Vec<PathBuf>,
+
+        /// Speed of the replay: 0 processes the measurements as fast as possible,
+        /// 1 replays them with their original timing, 2 is twice as fast, etc.
+        #[arg(long, default_value_t = 0.0)]
+        pub speed: f64,
+    }
+
+    /// CLI arguments for the `top` command.
+    #[derive(Args)]
+    pub struct TopArgs {
+        /// Time between two refreshes of the screen.
+        #[arg(long, default_value = "1s", value_parser = humantime_serde::re::humantime::parse_duration)]
+        pub refresh: Duration,
+    }
+
     /// CLI arguments for the `watch` command.
+    #[cfg(target_os = "linux")]
     #[derive(Args)]
     pub struct Process {
         /// The PID to watch.
@@ -457,6 +874,37 @@ mod cli {
         ///
         /// If the file exists, it will be overwritten.
         Regen,
+
+        /// Check the configuration file and stop.
+        ///
+        /// The enabled plugins are initialized with their configuration, but they are not started.
+        /// All the errors are reported at once.
+        Check,
+
+        /// Print the JSON Schema of the configuration file and stop.
+        ///
+        /// The schema describes the options of every available plugin.
+        /// It can be used by editors to provide completion and validation.
+        Schema,
+    }
+
+    #[cfg(windows)]
+    #[derive(Args)]
+    pub struct ServiceArgs {
+        #[command(subcommand)]
+        pub command: ServiceCommand,
+    }
+
+    #[cfg(windows)]
+    #[derive(Subcommand)]
+    pub enum ServiceCommand {
+        /// Install the agent as a service that starts automatically, and stop.
+        ///
+        /// The service uses the config file given by `--config`. Requires administrator privileges.
+        Install,
+
+        /// Stop and remove the service, and stop.
+        Uninstall,
     }
 
     #[derive(Args)]
@@ -476,6 +924,9 @@ mod cli {
     pub enum PluginsCommand {
         /// Print the available plugins.
         List,
+
+        /// Print the built-in profiles.
+        Profiles,
     }
 
     /// Common CLI arguments.
@@ -503,22 +954,74 @@ mod cli {
         #[arg(long, env = "ALUMET_CONFIG", default_value = "alumet-config.toml")]
         pub config: String,
 
+        /// If set, upgrades the config file to the latest format of each plugin before loading it.
+        ///
+        /// A copy of the original file is saved with the `.bak` extension.
+        #[arg(long, default_value_t = false)]
+        pub migrate_config: bool,
+
         /// If set, the config file must exist, otherwise the agent will fail to start with an error.
         #[arg(long, default_value_t = false)]
         pub no_default_config: bool,
 
+        /// Additional config files, merged on top of the main config file, in order.
+        ///
+        /// If a path is a directory, all the `.toml` files it contains are merged, sorted by name.
+        /// The overlays override the main config file, which overrides the files listed in its `include` option.
+        #[arg(long)]
+        pub config_overlay: Option<Vec<String>>,
+
         /// Config options overrides.
         ///
         /// Use dots to separate TOML levels, ex. `plugins.rapl.poll_interval='1ms'`
         #[arg(long)]
         pub config_override: Option<Vec<String>>,
 
+        /// Sets a config option, ex. `--set plugins.csv.output_path=/tmp/x.csv`.
+        ///
+        /// Can be repeated. Unlike `--config-override`, the value does not need to be quoted
+        /// (it is parsed as a string if it is not a valid TOML value).
+        /// The options are applied after the config overrides.
+        #[arg(long = "set", value_name = "KEY=VALUE")]
+        pub set: Vec<String>,
+
         /// List of plugins to enable, separated by commas, ex. `csv,rapl`.
         ///
         /// All the other plugins will be disabled.
         #[arg(long, value_delimiter = ',')]
         pub plugins: Option<Vec<String>>,
 
+        /// Built-in profile to use: enables a curated set of plugins.
+        ///
+        /// If the config file does not exist, it is generated with the settings of the profile.
+        /// Use `plugins profiles` to list the available profiles.
+        #[arg(long, conflicts_with = "plugins", value_parser = clap::builder::PossibleValuesParser::new(Profile::names()))]
+        pub profile: Option<String>,
+
+        /// Directory that contains dynamic plugins (shared libraries), loaded on startup.
+        ///
+        /// A dynamic plugin is only started if the config file contains its section.
+        /// Plugins built against an incompatible version of the Alumet bindings are skipped.
+        #[arg(long, env = "ALUMET_PLUGINS_DIR")]
+        pub plugins_dir: Option<PathBuf>,
+
+        /// Allowlist of the dynamic plugins that can be loaded, in the format of `sha256sum`.
+        ///
+        /// If an allowlist or a trusted key is set, the plugins that are neither listed nor signed are not loaded.
+        #[arg(long, env = "ALUMET_PLUGINS_ALLOWLIST", requires = "plugins_dir")]
+        pub plugins_allowlist: Option<PathBuf>,
+
+        /// Public ed25519 key (in hexadecimal) that signs trusted dynamic plugins. Can be repeated.
+        ///
+        /// The signature of `plugin.so` (64 raw bytes) must be stored in `plugin.so.sig`.
+        #[arg(
+            long,
+            env = "ALUMET_PLUGINS_TRUSTED_KEYS",
+            value_delimiter = ',',
+            requires = "plugins_dir"
+        )]
+        pub plugins_trusted_key: Vec<String>,
+
         /// Maximum amount of time between two updates of the sources' commands.
         ///
         /// A lower value means that the latency of source commands will be lower,
@@ -552,6 +1055,41 @@ mod cli {
         /// Address and/or port that the relay server should listen to (relay-server plugin).
         #[arg(long)]
         pub relay_in: Option<String>,
+
+        /// If set, the agent forks into the background after loading its config.
+        ///
+        /// Stdout and stderr are redirected to `/dev/null`, unless they are redirected to a file.
+        #[cfg(unix)]
+        #[arg(long, default_value_t = false)]
+        pub daemon: bool,
+
+        /// Path to a file where the PID of the agent is written.
+        ///
+        /// The file is removed when the agent stops.
+        /// With `--user`, the file must be in a directory owned by this user, for instance `/run/alumet/alumet.pid`
+        /// (the directory is created if it does not exist).
+        #[cfg(unix)]
+        #[arg(long, env = "ALUMET_PID_FILE")]
+        pub pid_file: Option<PathBuf>,
+
+        /// User to switch to after the startup (name or id).
+        ///
+        /// The agent starts as root in order to open the privileged resources (powercap, perf events, i2c, ...),
+        /// then drops its privileges. Resources that are opened later (e.g. after a config reload) are opened
+        /// as this user.
+        #[cfg(unix)]
+        #[arg(long, env = "ALUMET_USER")]
+        pub user: Option<String>,
+
+        /// Group to switch to after the startup (name or id), defaults to the primary group of the user.
+        #[cfg(unix)]
+        #[arg(long, env = "ALUMET_GROUP", requires = "user")]
+        pub group: Option<String>,
+
+        /// Set by the Windows service manager when it starts the agent.
+        #[cfg(windows)]
+        #[arg(long, hide = true, default_value_t = false)]
+        pub windows_service: bool,
     }
 }
 
@@ -563,6 +1101,10 @@ mod cli {
 mod config {
     use std::time::Duration;
 
+    use alumet::agent::timeout::PluginTimeouts;
+    use alumet_agent::logging::LoggingConfig;
+    #[cfg(target_os = "linux")]
+    use alumet_agent::sandbox::SandboxConfig;
     use serde::{Deserialize, Serialize};
 
     /// General config options, which are not specific to a particular plugin.
@@ -571,5 +1113,40 @@ mod config {
         // TODO move these to an "advanced" table
         pub max_update_interval: Option<humantime_serde::Serde<Duration>>,
         pub source_channel_size: Option<usize>,
+        /// If set, measures the health status of the plugins at this interval.
+        pub health_metrics_interval: Option<humantime_serde::Serde<Duration>>,
+        /// If set, checks the config file at this interval and reconfigures the plugins whose config has changed.
+        pub config_reload_interval: Option<humantime_serde::Serde<Duration>>,
+        /// Log levels, log file and rotation.
+        pub logging: Option<LoggingConfig>,
+        /// Restrictions applied to the process when dynamic plugins are enabled, once their libraries are loaded.
+        #[cfg(target_os = "linux")]
+        pub sandbox: Option<SandboxConfig>,
+        /// Maximum duration of the lifecycle hooks of the plugins.
+        pub plugin_timeouts: Option<PluginTimeoutsConfig>,
+    }
+
+    /// Maximum duration of each lifecycle hook of the plugins. A missing value means the default timeout.
+    #[derive(Deserialize, Serialize, Default)]
+    #[serde(deny_unknown_fields)]
+    pub struct PluginTimeoutsConfig {
+        pub init: Option<humantime_serde::Serde<Duration>>,
+        pub start: Option<humantime_serde::Serde<Duration>>,
+        pub post_pipeline_start: Option<humantime_serde::Serde<Duration>>,
+        pub stop: Option<humantime_serde::Serde<Duration>>,
+    }
+
+    impl From<&PluginTimeoutsConfig> for PluginTimeouts {
+        fn from(config: &PluginTimeoutsConfig) -> Self {
+            let default = PluginTimeouts::default();
+            let or_default =
+                |d: Option<humantime_serde::Serde<Duration>>, default| d.map_or(default, |d| d.into_inner());
+            PluginTimeouts {
+                init: or_default(config.init, default.init),
+                start: or_default(config.start, default.start),
+                post_pipeline_start: or_default(config.post_pipeline_start, default.post_pipeline_start),
+                stop: or_default(config.stop, default.stop),
+            }
+        }
     }
 }
@@ -0,0 +1,110 @@
+//! Client of the control socket, used by `alumet-ctl`.
+//!
+//! The control socket is opened by the `socket-control` plugin. The client sends one command per line,
+//! and the agent responds with the output of the command, followed by a line that contains `ok` on success,
+//! or `error: <MESSAGE>` on failure.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{Context, anyhow};
+
+/// Maximum time to wait for the response of the agent.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A connection to the control socket of a running agent.
+pub struct ControlClient {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl ControlClient {
+    /// Connects to the control socket at the given path.
+    pub fn connect(socket_path: &Path) -> anyhow::Result<Self> {
+        let stream = UnixStream::connect(socket_path).with_context(|| {
+            format!("could not connect to {socket_path:?}, is the agent running with the socket-control plugin?")
+        })?;
+        stream.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+        let writer = stream.try_clone()?;
+        Ok(Self {
+            reader: BufReader::new(stream),
+            writer,
+        })
+    }
+
+    /// Sends a command to the agent and returns its output, line by line.
+    ///
+    /// If the agent reports an error, returns it.
+    pub fn send(&mut self, command: &str) -> anyhow::Result<Vec<String>> {
+        if command.contains('\n') {
+            return Err(anyhow!("invalid command {command:?}: it must fit on a single line"));
+        }
+        writeln!(self.writer, "{command}").context("could not send the command")?;
+        self.writer.flush()?;
+
+        let mut output = Vec::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let n = self
+                .reader
+                .read_line(&mut line)
+                .context("could not read the response of the agent")?;
+            if n == 0 {
+                return Err(anyhow!("the agent closed the connection before responding"));
+            }
+            let line = line.trim_end_matches('\n');
+            if line == "ok" {
+                return Ok(output);
+            }
+            if let Some(error) = line.strip_prefix("error: ") {
+                return Err(anyhow!("{error}"));
+            }
+            output.push(line.to_owned());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        os::unix::net::UnixListener,
+    };
+
+    use super::ControlClient;
+
+    #[test]
+    fn send() {
+        let tmp = tempfile::tempdir().unwrap();
+        let socket_path = tmp.path().join("control.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        // fake agent
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            for line in BufReader::new(stream).lines() {
+                let response = match line.unwrap().as_str() {
+                    "list" => "source/a/b\noutput/c/d\nok\n",
+                    "shutdown" => "ok\n",
+                    _ => "error: unknown command\n",
+                };
+                writer.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let mut client = ControlClient::connect(&socket_path).unwrap();
+        assert_eq!(client.send("list").unwrap(), vec!["source/a/b", "output/c/d"]);
+        assert_eq!(client.send("shutdown").unwrap(), Vec::<String>::new());
+        let err = client.send("bad").unwrap_err();
+        assert_eq!(err.to_string(), "unknown command");
+        assert!(client.send("two\nlines").is_err());
+        drop(client);
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,306 @@
+//! Daemon mode: run in the background, write a PID file and drop privileges.
+//!
+//! The typical sequence is:
+//! 1. [`daemonize`] before starting the measurement pipeline, because forking a multi-threaded process
+//!    only keeps the calling thread;
+//! 2. [`PidFile::create`] in the daemon process, in a directory that is prepared by [`prepare_owned_dir`]
+//!    if the privileges will be dropped;
+//! 3. start the pipeline as root, in order to open the privileged resources (powercap, perf events, i2c, ...);
+//! 4. [`drop_privileges`] to continue as an unprivileged user.
+//!
+//! Once the privileges have been dropped, the resources that are opened later (for instance
+//! when a source is created by a config reload) are opened as the unprivileged user.
+
+use std::{
+    ffi::CString,
+    fs::File,
+    io::{self, Write},
+    os::{
+        fd::AsRawFd,
+        unix::fs::{MetadataExt, OpenOptionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, anyhow};
+
+/// Detaches the process from the terminal and continues in the background.
+///
+/// The parent process exits immediately with a success code, and the function returns in the daemon process.
+/// Stdin is redirected to `/dev/null`, as well as stdout and stderr if they are attached to a terminal.
+/// The working directory is not changed, so that relative paths of the config keep working.
+pub fn daemonize() -> io::Result<()> {
+    // first fork: the daemon is not a process group leader, so it can start a new session
+    fork_and_exit_parent()?;
+    // new session, without controlling terminal
+    if unsafe { libc::setsid() } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // second fork: the daemon is not a session leader, so it cannot acquire a terminal again
+    fork_and_exit_parent()?;
+    unsafe { libc::umask(0o022) };
+
+    // Keep the outputs that are redirected to a file, for instance with `alumet-agent --daemon 2>agent.log`.
+    let dev_null = File::options().read(true).write(true).open("/dev/null")?;
+    for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        if (fd == libc::STDIN_FILENO || unsafe { libc::isatty(fd) } == 1)
+            && unsafe { libc::dup2(dev_null.as_raw_fd(), fd) } < 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn fork_and_exit_parent() -> io::Result<()> {
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(()),
+        _ => unsafe { libc::_exit(0) },
+    }
+}
+
+/// File that contains the PID of the agent, removed when dropped.
+///
+/// The file is locked with `flock` for the whole life of the agent.
+pub struct PidFile {
+    path: PathBuf,
+    /// Keeps the lock, which is released when the file is closed.
+    _file: File,
+}
+
+impl PidFile {
+    /// Writes the PID of the current process to the file at `path`.
+    ///
+    /// Fails if the file is locked by another agent, or if it refers to a running process.
+    /// The check and the write happen under the lock, so that two agents that start
+    /// at the same time cannot both succeed.
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let already_running = |path: &Path| match running_pid(path) {
+            Some(pid) => anyhow!("the agent is already running with PID {pid} (see {path:?})"),
+            None => anyhow!("the agent is already running (see {path:?})"),
+        };
+
+        let mut file = match File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o644)
+            .open(path)
+        {
+            Ok(file) => file,
+            // the file may belong to an agent of another user
+            Err(_) if running_pid(path).is_some() => return Err(already_running(path)),
+            Err(e) => return Err(e).with_context(|| format!("could not create the PID file {path:?}")),
+        };
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } < 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+                return Err(already_running(path));
+            }
+            return Err(err).with_context(|| format!("could not lock the PID file {path:?}"));
+        }
+        // the previous agent may not have locked the file
+        if running_pid(path).is_some() {
+            return Err(already_running(path));
+        }
+
+        file.set_len(0)
+            .with_context(|| format!("could not truncate {path:?}"))?;
+        writeln!(file, "{}", std::process::id()).with_context(|| format!("could not write to {path:?}"))?;
+        Ok(Self {
+            path: path.to_owned(),
+            _file: file,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Returns the PID that is written in the file at `path`, if it refers to a running process.
+fn running_pid(path: &Path) -> Option<libc::pid_t> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let pid = content.trim().parse::<libc::pid_t>().ok()?;
+    process_exists(pid).then_some(pid)
+}
+
+/// Checks whether a process exists, without sending a signal to it.
+fn process_exists(pid: libc::pid_t) -> bool {
+    // EPERM means that the process exists but belongs to another user.
+    let res = unsafe { libc::kill(pid, 0) };
+    res == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!("Could not remove the PID file {:?}: {e}", self.path);
+        }
+    }
+}
+
+/// A user (and group) to switch to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub user: Option<CString>,
+    pub uid: libc::uid_t,
+    pub gid: libc::gid_t,
+}
+
+impl Credentials {
+    /// Looks up the user and group, given by name or numeric id.
+    ///
+    /// If `group` is not set, the primary group of the user is used.
+    pub fn lookup(user: &str, group: Option<&str>) -> anyhow::Result<Self> {
+        let (user, uid, primary_gid) = lookup_user(user)?;
+        let gid = match group {
+            Some(group) => lookup_group(group)?,
+            None => primary_gid.with_context(|| format!("user {uid} does not exist, please specify a group"))?,
+        };
+        Ok(Self { user, uid, gid })
+    }
+}
+
+fn lookup_user(user: &str) -> anyhow::Result<(Option<CString>, libc::uid_t, Option<libc::gid_t>)> {
+    let name = CString::new(user).context("invalid user name")?;
+    let passwd = unsafe { libc::getpwnam(name.as_ptr()) };
+    if !passwd.is_null() {
+        let (uid, gid) = unsafe { ((*passwd).pw_uid, (*passwd).pw_gid) };
+        return Ok((Some(name), uid, Some(gid)));
+    }
+    let uid: libc::uid_t = user.parse().with_context(|| format!("unknown user {user}"))?;
+    let passwd = unsafe { libc::getpwuid(uid) };
+    if passwd.is_null() {
+        Ok((None, uid, None))
+    } else {
+        let (name, gid) = unsafe { (std::ffi::CStr::from_ptr((*passwd).pw_name).to_owned(), (*passwd).pw_gid) };
+        Ok((Some(name), uid, Some(gid)))
+    }
+}
+
+fn lookup_group(group: &str) -> anyhow::Result<libc::gid_t> {
+    let name = CString::new(group).context("invalid group name")?;
+    let entry = unsafe { libc::getgrnam(name.as_ptr()) };
+    if entry.is_null() {
+        group.parse().with_context(|| format!("unknown group {group}"))
+    } else {
+        Ok(unsafe { (*entry).gr_gid })
+    }
+}
+
+/// Switches the process to the given user and group.
+///
+/// The supplementary groups are set to the groups of the user, so that the agent keeps
+/// the access that is granted by a group membership (for instance, `i2c`).
+/// This affects all the threads of the process.
+pub fn drop_privileges(credentials: &Credentials) -> io::Result<()> {
+    // Groups first: once the user is changed, we lose the right to change the groups.
+    let res = match &credentials.user {
+        Some(name) => unsafe { libc::initgroups(name.as_ptr(), credentials.gid as _) },
+        None => unsafe { libc::setgroups(0, std::ptr::null()) },
+    };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if unsafe { libc::setgid(credentials.gid) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if unsafe { libc::setuid(credentials.uid) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Ensures that the given user can create and remove files in `dir`, for instance the PID file.
+///
+/// Removing a file requires the write permission on its directory, not on the file itself.
+/// If `dir` does not exist, it is created and given to the user. Otherwise, it must be owned by the user.
+pub fn prepare_owned_dir(dir: &Path, credentials: &Credentials) -> anyhow::Result<()> {
+    match std::fs::metadata(dir) {
+        Ok(metadata) if metadata.uid() == credentials.uid => Ok(()),
+        Ok(metadata) => Err(anyhow!(
+            "{dir:?} is owned by user {}, not by user {}: the files that it contains could not be removed after dropping privileges",
+            metadata.uid(),
+            credentials.uid
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            std::fs::create_dir_all(dir).with_context(|| format!("could not create {dir:?}"))?;
+            std::os::unix::fs::chown(dir, Some(credentials.uid), Some(credentials.gid))
+                .with_context(|| format!("could not change the owner of {dir:?}"))
+        }
+        Err(e) => Err(anyhow!("could not read the metadata of {dir:?}: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::AsRawFd;
+
+    use super::{Credentials, PidFile, prepare_owned_dir};
+
+    #[test]
+    fn lookup() {
+        let root = Credentials::lookup("root", None).unwrap();
+        assert_eq!(root.uid, 0);
+        assert_eq!(root.gid, 0);
+        assert_eq!(Credentials::lookup("0", Some("0")).unwrap(), root);
+        assert!(Credentials::lookup("unknown_alumet_user", None).is_err());
+        assert!(Credentials::lookup("root", Some("unknown_alumet_group")).is_err());
+    }
+
+    #[test]
+    fn pid_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("alumet.pid");
+        let pid_file = PidFile::create(&path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.trim(), std::process::id().to_string());
+
+        // the current process is running, a second agent should not start
+        assert!(PidFile::create(&path).is_err());
+
+        drop(pid_file);
+        assert!(!path.exists());
+
+        // PID 1 always exists, even if we are not allowed to send signals to it
+        std::fs::write(&path, "1\n").unwrap();
+        assert!(PidFile::create(&path).is_err());
+
+        // stale PID file
+        std::fs::write(&path, "999999999\n").unwrap();
+        let pid_file = PidFile::create(&path).unwrap();
+        drop(pid_file);
+
+        // another agent is starting: it holds the lock but has not written its PID yet
+        let other = std::fs::File::create(&path).unwrap();
+        assert_eq!(unsafe { libc::flock(other.as_raw_fd(), libc::LOCK_EX) }, 0);
+        assert!(PidFile::create(&path).is_err());
+        drop(other);
+        let _pid_file = PidFile::create(&path).unwrap();
+    }
+
+    #[test]
+    fn owned_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let current = Credentials {
+            user: None,
+            uid: unsafe { libc::getuid() },
+            gid: unsafe { libc::getgid() },
+        };
+        prepare_owned_dir(tmp.path(), &current).unwrap();
+
+        let new_dir = tmp.path().join("run/alumet");
+        prepare_owned_dir(&new_dir, &current).unwrap();
+        assert!(new_dir.is_dir());
+
+        let other = Credentials {
+            user: None,
+            uid: current.uid + 1,
+            gid: current.gid,
+        };
+        assert!(prepare_owned_dir(tmp.path(), &other).is_err());
+    }
+}
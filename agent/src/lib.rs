@@ -1,8 +1,20 @@
 use std::path::PathBuf;
 
-use env_logger::Env;
-
+#[cfg(unix)]
+pub mod ctl;
+#[cfg(unix)]
+pub mod daemon;
+#[cfg(unix)]
 pub mod exec_hints;
+pub mod logging;
+pub mod plugin_dir;
+pub mod profiles;
+#[cfg(target_os = "linux")]
+pub mod sandbox;
+#[cfg(windows)]
+pub mod service;
+pub mod top;
+pub mod vault;
 pub mod word_distance;
 
 /// Returns the absolute path of the currently running executable.
@@ -34,7 +46,8 @@ pub fn relative_exe_path() -> std::io::Result<PathBuf> {
 
 /// Initializes the global logger.
 ///
-/// Call this first!
+/// Call this first! The logs are written to stderr, according to the `RUST_LOG` environment variable.
+/// Use [`logging::configure_logger`] to apply the logging options of the config file.
 ///
 /// # Example
 ///
@@ -48,5 +61,5 @@ pub fn relative_exe_path() -> std::io::Result<PathBuf> {
 /// }
 /// ```
 pub fn init_logger() {
-    env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
+    logging::install();
 }
@@ -0,0 +1,381 @@
+//! Logging configuration of the agent: levels, log file and rotation.
+//!
+//! The logger is initialized with [`init_logger`](crate::init_logger) at the very beginning, with the
+//! settings of the `RUST_LOG` environment variable, and reconfigured with [`configure_logger`] once the
+//! config file has been loaded.
+//!
+//! The logs can be written as text or as JSON (one object per line). If `spans` is enabled, the duration
+//! of each operation of the pipeline (see the `tracing` spans of [`alumet::pipeline`]) is logged with the
+//! target `alumet::span`, with the fields `span`, `element` and `duration_us`.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{OnceLock, RwLock},
+    time::Instant,
+};
+
+use anyhow::{Context, anyhow};
+use env_logger::{Env, Target, WriteStyle};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use tracing::{Subscriber, field::Visit, span};
+use tracing_subscriber::{Layer, layer::Context as LayerContext, prelude::*, registry::LookupSpan};
+
+/// Logging options, in the `[logging]` section of the config.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Default level: `off`, `error`, `warn`, `info`, `debug` or `trace`.
+    pub level: String,
+    /// Level of specific modules, for instance `"alumet::pipeline" = "debug"`.
+    pub modules: BTreeMap<String, String>,
+    /// If true, writes the logs to stderr.
+    pub stderr: bool,
+    /// If set, writes the logs to this file.
+    pub file: Option<PathBuf>,
+    /// Maximum size of the log file, in bytes, before it is rotated.
+    pub max_size: u64,
+    /// How many rotated files to keep (`agent.log.1`, `agent.log.2`, ...).
+    pub max_files: u32,
+    /// Format of the logs.
+    pub format: LogFormat,
+    /// If true, logs the duration of each poll, transform and write operation.
+    pub spans: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable text.
+    #[default]
+    Text,
+    /// One JSON object per line, with the fields `timestamp`, `level`, `target`, `message`
+    /// and the structured fields of the log record, if any.
+    Json,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: String::from("info"),
+            modules: BTreeMap::new(),
+            stderr: true,
+            file: None,
+            max_size: 10 * 1024 * 1024,
+            max_files: 5,
+            format: LogFormat::Text,
+            spans: false,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// Prepares a logger with the levels of the config.
+    ///
+    /// The `RUST_LOG` environment variable, if set, takes precedence over the config.
+    fn builder(&self) -> anyhow::Result<env_logger::Builder> {
+        let mut builder = env_logger::Builder::new();
+        builder.filter_level(parse_level(&self.level)?);
+        for (module, level) in &self.modules {
+            builder.filter_module(module, parse_level(level)?);
+        }
+        if let Ok(filters) = std::env::var("RUST_LOG") {
+            builder.parse_filters(&filters);
+        }
+        if self.format == LogFormat::Json {
+            builder.format(format_json);
+        }
+        Ok(builder)
+    }
+}
+
+fn format_json(buf: &mut env_logger::fmt::Formatter, record: &log::Record) -> io::Result<()> {
+    struct Fields<'a>(&'a mut serde_json::Map<String, serde_json::Value>);
+
+    impl<'kvs> log::kv::VisitSource<'kvs> for Fields<'_> {
+        fn visit_pair(&mut self, key: log::kv::Key<'kvs>, value: log::kv::Value<'kvs>) -> Result<(), log::kv::Error> {
+            let value = match value.to_u64() {
+                Some(n) => serde_json::Value::from(n),
+                None => serde_json::Value::from(value.to_string()),
+            };
+            self.0.insert(key.to_string(), value);
+            Ok(())
+        }
+    }
+
+    let mut obj = serde_json::Map::new();
+    obj.insert(String::from("timestamp"), buf.timestamp().to_string().into());
+    obj.insert(String::from("level"), record.level().as_str().into());
+    obj.insert(String::from("target"), record.target().into());
+    obj.insert(String::from("message"), record.args().to_string().into());
+    let _ = record.key_values().visit(&mut Fields(&mut obj));
+    writeln!(buf, "{}", serde_json::Value::Object(obj))
+}
+
+fn parse_level(level: &str) -> anyhow::Result<LevelFilter> {
+    LevelFilter::from_str(level).map_err(|_| anyhow!("invalid log level: {level}"))
+}
+
+/// Logger that can be reconfigured after its installation.
+struct AgentLogger {
+    outputs: RwLock<Vec<env_logger::Logger>>,
+}
+
+static LOGGER: OnceLock<AgentLogger> = OnceLock::new();
+
+impl log::Log for AgentLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.outputs.read().unwrap().iter().any(|l| l.enabled(metadata))
+    }
+
+    fn log(&self, record: &log::Record) {
+        for output in self.outputs.read().unwrap().iter() {
+            output.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        for output in self.outputs.read().unwrap().iter() {
+            output.flush();
+        }
+    }
+}
+
+/// Installs the global logger, which writes to stderr according to `RUST_LOG` (default level: `info`).
+pub(crate) fn install() {
+    let stderr = env_logger::Builder::from_env(Env::default().default_filter_or("info")).build();
+    let max_level = stderr.filter();
+    let logger = LOGGER.get_or_init(|| AgentLogger {
+        outputs: RwLock::new(vec![stderr]),
+    });
+    log::set_logger(logger).expect("the logger should be initialized only once");
+    log::set_max_level(max_level);
+}
+
+/// Applies the logging config to the global logger.
+///
+/// Does nothing if the logger has not been installed by [`init_logger`](crate::init_logger),
+/// for instance when the agent runs as a Windows service (which logs to the event log).
+pub fn configure_logger(config: &LoggingConfig) -> anyhow::Result<()> {
+    let Some(logger) = LOGGER.get() else {
+        return Ok(());
+    };
+
+    let mut outputs = Vec::new();
+    if config.stderr {
+        outputs.push(config.builder()?.build());
+    }
+    if let Some(path) = &config.file {
+        let file = RotatingFile::open(path, config.max_size, config.max_files)
+            .with_context(|| format!("could not open the log file {path:?}"))?;
+        let mut builder = config.builder()?;
+        builder
+            .target(Target::Pipe(Box::new(file)))
+            .write_style(WriteStyle::Never);
+        outputs.push(builder.build());
+    }
+
+    let max_level = outputs.iter().map(|l| l.filter()).max().unwrap_or(LevelFilter::Off);
+    *logger.outputs.write().unwrap() = outputs;
+    log::set_max_level(max_level);
+
+    if config.spans {
+        let subscriber = tracing_subscriber::registry().with(SpanLogger);
+        tracing::subscriber::set_global_default(subscriber).context("could not install the tracing subscriber")?;
+    }
+    Ok(())
+}
+
+/// Target of the logs that contain the duration of the spans.
+pub const SPAN_TARGET: &str = "alumet::span";
+
+/// Forwards the `tracing` spans to the logger: when a span closes, logs its duration.
+struct SpanLogger;
+
+/// Data attached to each span by [`SpanLogger`].
+struct SpanTiming {
+    element: String,
+    start: Instant,
+}
+
+/// Extracts the name of the element from the fields of a span.
+#[derive(Default)]
+struct ElementVisitor(String);
+
+impl Visit for ElementVisitor {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if matches!(field.name(), "source" | "transform" | "output") {
+            self.0 = format!("{value:?}");
+        }
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanLogger {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: LayerContext<'_, S>) {
+        let mut visitor = ElementVisitor::default();
+        attrs.record(&mut visitor);
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanTiming {
+                element: visitor.0,
+                start: Instant::now(),
+            });
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: LayerContext<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        if let Some(timing) = span.extensions().get::<SpanTiming>() {
+            let name = span.name();
+            let element = &timing.element;
+            let duration_us = timing.start.elapsed().as_micros() as u64;
+            log::info!(
+                target: SPAN_TARGET,
+                span = name, element = element.as_str(), duration_us;
+                "{name} {element}: {duration_us} µs"
+            );
+        }
+    }
+}
+
+/// A file that is rotated when it reaches a maximum size.
+///
+/// On rotation, `file` is renamed to `file.1`, `file.1` to `file.2`, and so on.
+/// At most `max_files` rotated files are kept.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: u32,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Opens the file in append mode, or creates it.
+    pub fn open(path: &Path, max_size: u64, max_files: u32) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_owned(),
+            max_size,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, n: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{n}"));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files == 0 {
+            self.file.set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+        // On Windows, rename fails if the destination exists: remove the oldest file first.
+        ignore_not_found(std::fs::remove_file(self.rotated_path(self.max_files)))?;
+        for n in (1..self.max_files).rev() {
+            ignore_not_found(std::fs::rename(self.rotated_path(n), self.rotated_path(n + 1)))?;
+        }
+        std::fs::rename(&self.path, self.rotated_path(1))?;
+        self.file = File::options().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn ignore_not_found(res: io::Result<()>) -> io::Result<()> {
+    match res {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        res => res,
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{LoggingConfig, RotatingFile};
+
+    #[test]
+    fn rotation() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("agent.log");
+        let read = |name: &str| std::fs::read_to_string(tmp.path().join(name)).ok();
+
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        file.write_all(b"aaaaaaaa\n").unwrap();
+        file.write_all(b"bbbbbbbb\n").unwrap();
+        assert_eq!(read("agent.log").as_deref(), Some("bbbbbbbb\n"));
+        assert_eq!(read("agent.log.1").as_deref(), Some("aaaaaaaa\n"));
+
+        file.write_all(b"cccccccc\n").unwrap();
+        file.write_all(b"dddddddd\n").unwrap();
+        assert_eq!(read("agent.log").as_deref(), Some("dddddddd\n"));
+        assert_eq!(read("agent.log.1").as_deref(), Some("cccccccc\n"));
+        assert_eq!(read("agent.log.2").as_deref(), Some("bbbbbbbb\n"));
+        assert_eq!(read("agent.log.3"), None);
+
+        // the size of an existing file is taken into account
+        drop(file);
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        file.write_all(b"eeeeeeee\n").unwrap();
+        assert_eq!(read("agent.log").as_deref(), Some("eeeeeeee\n"));
+        assert_eq!(read("agent.log.1").as_deref(), Some("dddddddd\n"));
+    }
+
+    #[test]
+    fn no_rotated_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("agent.log");
+        let mut file = RotatingFile::open(&path, 10, 0).unwrap();
+        file.write_all(b"aaaaaaaa\n").unwrap();
+        file.write_all(b"bbbbbbbb\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "bbbbbbbb\n");
+        assert!(!tmp.path().join("agent.log.1").exists());
+    }
+
+    #[test]
+    fn invalid_level() {
+        let config = LoggingConfig {
+            modules: [(String::from("alumet"), String::from("verbose"))].into(),
+            ..Default::default()
+        };
+        assert!(config.builder().is_err());
+
+        let config: LoggingConfig = toml::from_str(
+            r#"
+            level = "warn"
+            file = "agent.log"
+            modules = { "alumet::pipeline" = "debug" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.max_files, 5);
+        assert!(config.builder().is_ok());
+    }
+}
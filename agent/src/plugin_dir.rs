@@ -0,0 +1,135 @@
+//! Discovery of dynamic plugins in a directory.
+//!
+//! The shared libraries of the plugins directory are loaded on startup. This allows to add a plugin
+//! to an existing deployment by dropping its file in the directory and adding its section to the config,
+//! instead of recompiling the agent.
+//!
+//! Optionally, the libraries are verified before being loaded: only the libraries that are listed
+//! in an allowlist of checksums, or that are signed by a trusted key, are loaded. See [`TrustPolicy`].
+
+use std::path::Path;
+
+use alumet::agent::plugin::{PluginInfo, PluginSet};
+use alumet_ffi::trust::TrustPolicy;
+use anyhow::Context;
+
+/// Builds the trust policy of the dynamic plugins from an allowlist file (in the format of `sha256sum`)
+/// and a list of trusted ed25519 public keys.
+///
+/// Returns `None` if there is neither an allowlist nor a trusted key, in which case every plugin is loaded.
+pub fn trust_policy(allowlist: Option<&Path>, trusted_keys: &[String]) -> anyhow::Result<Option<TrustPolicy>> {
+    if allowlist.is_none() && trusted_keys.is_empty() {
+        return Ok(None);
+    }
+    let mut policy = TrustPolicy::new();
+    if let Some(file) = allowlist {
+        let content = std::fs::read_to_string(file)
+            .with_context(|| format!("could not read the plugins allowlist {}", file.display()))?;
+        policy
+            .allow_checksums_from(&content)
+            .with_context(|| format!("invalid plugins allowlist {}", file.display()))?;
+    }
+    for key in trusted_keys {
+        policy.trust_key(key)?;
+    }
+    Ok(Some(policy))
+}
+
+/// Loads the dynamic plugins of `dir` and adds them to the set, disabled.
+///
+/// The plugins that cannot be loaded, for instance because they have been built against an incompatible ABI
+/// or because they are not trusted by the `policy`, are skipped with a warning, as well as the plugins whose name is already taken by another plugin.
+/// Like the other plugins, the discovered plugins are enabled by the config or by the command line.
+///
+/// Returns the names of the plugins that have been added.
+pub fn add_plugins_from_dir(
+    dir: &Path,
+    policy: Option<&TrustPolicy>,
+    plugins: &mut PluginSet,
+) -> anyhow::Result<Vec<String>> {
+    let loaded = alumet_ffi::dynload::load_dir(dir, policy)
+        .with_context(|| format!("could not read the plugins directory {}", dir.display()))?;
+    let mut added = Vec::with_capacity(loaded.len());
+    for (file, res) in loaded {
+        match res {
+            Ok(metadata) if plugins.get_plugin(&metadata.name).is_some() => {
+                log::warn!(
+                    "Skipping dynamic plugin {}: plugin {} already exists.",
+                    file.display(),
+                    metadata.name
+                );
+            }
+            Ok(metadata) => {
+                log::info!(
+                    "Found dynamic plugin {} v{} in {}.",
+                    metadata.name,
+                    metadata.version,
+                    file.display()
+                );
+                added.push(metadata.name.clone());
+                plugins.add_plugin(PluginInfo {
+                    metadata,
+                    enabled: false,
+                    config: None,
+                });
+            }
+            Err(e) => log::warn!("Skipping dynamic plugin {}: {e}", file.display()),
+        }
+    }
+    Ok(added)
+}
+
+/// Disables the dynamic plugins that have no section in the config.
+///
+/// Call this after [`PluginSet::extract_config`].
+pub fn disable_unconfigured(dynamic_plugins: &[String], plugins: &mut PluginSet) {
+    for name in dynamic_plugins {
+        if let Some(plugin) = plugins.get_plugin_mut(name)
+            && plugin.enabled
+            && plugin.config.is_none()
+        {
+            log::warn!("Dynamic plugin {name} has no section in the config, it will not be started.");
+            plugin.enabled = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alumet::{
+        agent::plugin::{PluginInfo, PluginSet},
+        plugin::PluginMetadata,
+    };
+
+    use super::disable_unconfigured;
+
+    fn plugin(name: &str, config: Option<toml::Table>) -> PluginInfo {
+        PluginInfo {
+            metadata: PluginMetadata {
+                name: name.to_owned(),
+                version: String::from("0.1.0"),
+                init: Box::new(|_| unreachable!()),
+                default_config: Box::new(|| Ok(None)),
+                dependencies: Vec::new(),
+                config_schema: Box::new(|| Ok(None)),
+                config_migrations: Vec::new(),
+            },
+            enabled: true,
+            config,
+        }
+    }
+
+    #[test]
+    fn unconfigured_plugins_are_disabled() {
+        let mut plugins = PluginSet::new();
+        plugins.add_plugin(plugin("configured", Some(toml::Table::new())));
+        plugins.add_plugin(plugin("unconfigured", None));
+        plugins.add_plugin(plugin("static", None));
+
+        let dynamic = vec![String::from("configured"), String::from("unconfigured")];
+        disable_unconfigured(&dynamic, &mut plugins);
+        assert!(plugins.is_plugin_enabled("configured"));
+        assert!(!plugins.is_plugin_enabled("unconfigured"));
+        assert!(plugins.is_plugin_enabled("static"));
+    }
+}
@@ -0,0 +1,137 @@
+//! Built-in agent profiles.
+//!
+//! A profile selects a curated set of plugins for a typical deployment, with sensible settings.
+//! When the configuration file does not exist, the agent generates it from the default
+//! configuration of the selected plugins, with the settings of the profile on top.
+
+/// A named set of plugins and settings.
+pub struct Profile {
+    /// Name of the profile, used on the command line.
+    pub name: &'static str,
+    /// Short description of the profile.
+    pub description: &'static str,
+    /// Plugins enabled by the profile.
+    pub plugins: &'static [&'static str],
+    /// Settings that override the default configuration of the plugins, in TOML.
+    pub config: &'static str,
+}
+
+/// All the built-in profiles.
+pub const PROFILES: &[Profile] = &[
+    Profile {
+        name: "hpc-node",
+        description: "compute node of a HPC cluster managed by Slurm: energy, hardware counters and per-job measurements",
+        plugins: &["rapl", "perf", "procfs", "slurm", "csv"],
+        config: r#"
+            [plugins.rapl]
+            poll_interval = "1s"
+            flush_interval = "5s"
+
+            [plugins.perf]
+            poll_interval = "1s"
+            flush_interval = "5s"
+
+            [plugins.slurm]
+            poll_interval = "1s"
+            jobs_monitoring_level = "job"
+
+            [plugins.procfs.processes]
+            enabled = false
+        "#,
+    },
+    Profile {
+        name: "k8s-node",
+        description: "node of a Kubernetes cluster: energy and per-pod measurements, exposed to Prometheus",
+        plugins: &["rapl", "procfs", "k8s", "prometheus-exporter"],
+        config: r#"
+            [plugins.rapl]
+            poll_interval = "1s"
+            flush_interval = "5s"
+
+            [plugins.k8s]
+            k8s_node = "${NODE_NAME}"
+            k8s_api_url = "https://kubernetes.default.svc:443"
+            poll_interval = "5s"
+
+            [plugins.procfs.processes]
+            enabled = false
+        "#,
+    },
+    Profile {
+        name: "edge-jetson",
+        description: "NVIDIA Jetson edge device: onboard power sensors and system usage, with a low overhead",
+        plugins: &["jetson", "procfs", "csv"],
+        config: r#"
+            [plugins.jetson]
+            poll_interval = "1s"
+            flush_interval = "10s"
+
+            [plugins.procfs.kernel]
+            poll_interval = "10s"
+
+            [plugins.procfs.memory]
+            poll_interval = "10s"
+
+            [plugins.procfs.network]
+            poll_interval = "10s"
+
+            [plugins.procfs.processes]
+            enabled = false
+        "#,
+    },
+    Profile {
+        name: "developer-laptop",
+        description: "laptop or workstation: energy and per-process measurements, written to a CSV file",
+        plugins: &["rapl", "procfs", "csv"],
+        config: r#"
+            [plugins.rapl]
+            poll_interval = "1s"
+            flush_interval = "5s"
+
+            [plugins.csv]
+            output_path = "alumet-output.csv"
+            force_flush = true
+        "#,
+    },
+];
+
+impl Profile {
+    /// Returns the built-in profile with the given name, if it exists.
+    pub fn find(name: &str) -> Option<&'static Profile> {
+        PROFILES.iter().find(|p| p.name == name)
+    }
+
+    /// Returns the names of all the built-in profiles.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        PROFILES.iter().map(|p| p.name)
+    }
+
+    /// Parses the settings of the profile.
+    pub fn config(&self) -> Result<toml::Table, toml::de::Error> {
+        self.config.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PROFILES, Profile};
+
+    #[test]
+    fn valid_profiles() {
+        for profile in PROFILES {
+            let config = profile
+                .config()
+                .unwrap_or_else(|e| panic!("invalid config in {}: {e}", profile.name));
+            let plugins = config["plugins"].as_table().unwrap();
+            for plugin in plugins.keys() {
+                assert!(
+                    profile.plugins.contains(&plugin.as_str()),
+                    "profile {} configures {plugin} but does not enable it",
+                    profile.name
+                );
+            }
+        }
+        assert!(Profile::find("hpc-node").is_some());
+        assert!(Profile::find("unknown").is_none());
+    }
+}
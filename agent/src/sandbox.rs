@@ -0,0 +1,201 @@
+//! Hardening of the agent process, to limit what the dynamic plugins can do.
+//!
+//! Dynamic plugins run native code in the process of the agent. When they are enabled and the config
+//! contains a `[sandbox]` section, [`apply`] restricts the whole process, before the plugins start:
+//! - Landlock rules only allow access to the files and TCP ports that are declared in the config;
+//! - a seccomp filter denies the system calls that the agent never needs, such as `mount`, `ptrace` or `reboot`.
+//!
+//! Both mechanisms are inherited by the threads and the child processes, and cannot be removed.
+//! Landlock is applied in "best effort" mode: on older kernels, some rules (e.g. the network rules,
+//! which require Linux 6.7) are not enforced, and a warning is logged.
+//!
+//! The sandbox does not cover the loading of the plugins. The libraries are loaded before the config is parsed,
+//! because the config depends on the available plugins: their constructors and their metadata functions run
+//! without restriction. Use a trust policy (an allowlist of checksums or trusted keys, see [`crate::plugin_dir`])
+//! to control which libraries are loaded. The sandbox applies to everything that runs afterwards,
+//! from the initialization of the plugins onwards.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, anyhow};
+use landlock::{
+    ABI, Access, AccessFs, AccessNet, NetPort, Ruleset, RulesetAttr, RulesetCreatedAttr, RulesetStatus,
+    path_beneath_rules,
+};
+use seccompiler::{BpfProgram, SeccompAction, SeccompFilter};
+use serde::{Deserialize, Serialize};
+
+/// Sandbox options, in the `[sandbox]` section of the config.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Files and directories that can be read (and executed).
+    pub read_paths: Vec<PathBuf>,
+    /// Files and directories that can be read and written.
+    pub write_paths: Vec<PathBuf>,
+    /// TCP ports that the agent can connect to. If empty, the outgoing connections are not restricted.
+    pub connect_ports: Vec<u16>,
+    /// TCP ports that the agent can listen on. If empty, the listening ports are not restricted.
+    pub bind_ports: Vec<u16>,
+    /// If true, denies the dangerous system calls with a seccomp filter.
+    pub seccomp: bool,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            read_paths: ["/proc", "/sys", "/dev", "/etc", "/usr", "/lib", "/lib64"]
+                .into_iter()
+                .map(PathBuf::from)
+                .collect(),
+            write_paths: Vec::new(),
+            connect_ports: Vec::new(),
+            bind_ports: Vec::new(),
+            seccomp: true,
+        }
+    }
+}
+
+/// Version of the Landlock ABI to use.
+///
+/// The next version restricts the `ioctl` on devices, which some sources need (i2c, GPUs, ...).
+const LANDLOCK_ABI: ABI = ABI::V4;
+
+/// System calls denied by the seccomp filter.
+const DENIED_SYSCALLS: &[libc::c_long] = &[
+    // kernel modules and kexec
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_kexec_load,
+    libc::SYS_kexec_file_load,
+    // system administration
+    libc::SYS_reboot,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_acct,
+    libc::SYS_settimeofday,
+    libc::SYS_clock_settime,
+    // namespaces
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    // access to the memory of other processes
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    // kernel keyrings
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_keyctl,
+];
+
+/// Applies the sandbox to the current process.
+///
+/// Call this before starting the pipeline: Landlock only restricts the calling thread and its future children.
+pub fn apply(config: &SandboxConfig) -> anyhow::Result<()> {
+    restrict_access(config).context("could not apply the Landlock rules")?;
+    if config.seccomp {
+        let filter = seccomp_filter()?;
+        seccompiler::apply_filter_all_threads(&filter).context("could not apply the seccomp filter")?;
+        log::info!(
+            "Seccomp filter applied: {} system calls are denied.",
+            DENIED_SYSCALLS.len()
+        );
+    }
+    Ok(())
+}
+
+fn restrict_access(config: &SandboxConfig) -> anyhow::Result<()> {
+    for path in config.read_paths.iter().chain(&config.write_paths) {
+        if !path.exists() {
+            log::warn!("Sandbox: {} does not exist, it will not be accessible.", path.display());
+        }
+    }
+    let connect = config
+        .connect_ports
+        .iter()
+        .map(|port| Ok(NetPort::new(*port, AccessNet::ConnectTcp)));
+    let bind = config
+        .bind_ports
+        .iter()
+        .map(|port| Ok(NetPort::new(*port, AccessNet::BindTcp)));
+
+    // Only restrict the kinds of network access for which ports are declared: an empty list
+    // would otherwise deny every connection, including those of the static plugins.
+    let mut ruleset = Ruleset::default().handle_access(AccessFs::from_all(LANDLOCK_ABI))?;
+    if !config.connect_ports.is_empty() {
+        ruleset = ruleset.handle_access(AccessNet::ConnectTcp)?;
+    }
+    if !config.bind_ports.is_empty() {
+        ruleset = ruleset.handle_access(AccessNet::BindTcp)?;
+    }
+    let status = ruleset
+        .create()?
+        .add_rules(path_beneath_rules(
+            &config.read_paths,
+            AccessFs::from_read(LANDLOCK_ABI),
+        ))?
+        .add_rules(path_beneath_rules(
+            &config.write_paths,
+            AccessFs::from_all(LANDLOCK_ABI),
+        ))?
+        .add_rules(connect.chain(bind).collect::<Vec<Result<_, landlock::RulesetError>>>())?
+        .restrict_self()?;
+    match status.ruleset {
+        RulesetStatus::FullyEnforced => log::info!("Landlock rules applied."),
+        RulesetStatus::PartiallyEnforced => {
+            log::warn!("Landlock rules partially applied: the kernel does not support all of them.")
+        }
+        RulesetStatus::NotEnforced => log::warn!("Landlock rules not applied: the kernel does not support Landlock."),
+    }
+    Ok(())
+}
+
+/// Builds the seccomp filter for the current architecture.
+fn seccomp_filter() -> anyhow::Result<BpfProgram> {
+    let arch = std::env::consts::ARCH
+        .try_into()
+        .map_err(|_| anyhow!("seccomp is not supported on {}", std::env::consts::ARCH))?;
+    #[allow(clippy::useless_conversion, reason = "c_long is i32 on 32-bit targets")]
+    let rules = DENIED_SYSCALLS.iter().map(|s| (i64::from(*s), Vec::new())).collect();
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EPERM as u32),
+        arch,
+    )?;
+    Ok(filter.try_into()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{SandboxConfig, seccomp_filter};
+
+    #[test]
+    fn config() {
+        let config: SandboxConfig = toml::from_str(
+            r#"
+            write_paths = ["/var/log/alumet"]
+            connect_ports = [8086]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.write_paths, vec![PathBuf::from("/var/log/alumet")]);
+        assert_eq!(config.connect_ports, vec![8086]);
+        assert!(config.read_paths.contains(&PathBuf::from("/sys")));
+        assert!(config.bind_ports.is_empty());
+        assert!(config.seccomp);
+    }
+
+    #[test]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn seccomp() {
+        let filter = seccomp_filter().unwrap();
+        assert!(!filter.is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+//! Windows service support.
+//!
+//! `alumet-agent service install` registers the agent as a service that starts automatically with the system.
+//! When the service manager starts the agent, it passes the `--windows-service` flag: the agent then reports
+//! its status to the service manager, stops the pipeline when it receives a stop request, and writes its logs
+//! to the Windows event log (instead of stderr, which is not visible).
+
+use std::{
+    ffi::OsString,
+    path::Path,
+    process::ExitCode,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use alumet::pipeline::control::AnonymousControlHandle;
+use anyhow::Context;
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode, ServiceInfo,
+        ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult},
+    service_dispatcher,
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+
+/// Name of the service, also used as the source of the event log.
+pub const SERVICE_NAME: &str = "alumet-agent";
+
+/// Flag that is passed to the agent by the service manager.
+pub const SERVICE_FLAG: &str = "--windows-service";
+
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+
+/// The main function of the agent, run by the service.
+static AGENT_MAIN: OnceLock<fn() -> anyhow::Result<ExitCode>> = OnceLock::new();
+
+/// Stops the pipeline on request of the service manager.
+static STOP: Mutex<StopState> = Mutex::new(StopState {
+    requested: false,
+    handle: None,
+});
+
+struct StopState {
+    requested: bool,
+    handle: Option<AnonymousControlHandle>,
+}
+
+/// Returns `true` if the agent has been started by the service manager.
+pub fn is_service_launch() -> bool {
+    std::env::args_os().any(|arg| arg == SERVICE_FLAG)
+}
+
+/// Runs `agent_main` under the control of the service manager.
+///
+/// This function blocks until the service stops.
+pub fn run(agent_main: fn() -> anyhow::Result<ExitCode>) -> anyhow::Result<ExitCode> {
+    eventlog::init(SERVICE_NAME, log::Level::Info).context("could not initialize the event log")?;
+    let _ = AGENT_MAIN.set(agent_main);
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main).context("could not start the service dispatcher")?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Registers the control handle of the pipeline, to stop it when the service is stopped.
+///
+/// If the service has been stopped during the startup of the agent, the pipeline is stopped immediately.
+pub fn set_control_handle(handle: AnonymousControlHandle) {
+    let mut state = STOP.lock().unwrap();
+    if state.requested {
+        handle.shutdown();
+    }
+    state.handle = Some(handle);
+}
+
+fn request_stop() {
+    let mut state = STOP.lock().unwrap();
+    state.requested = true;
+    if let Some(handle) = &state.handle {
+        handle.shutdown();
+    }
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        log::error!("Service failure: {e:?}");
+    }
+}
+
+fn run_service() -> anyhow::Result<()> {
+    let status_handle = service_control_handler::register(SERVICE_NAME, |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            log::info!("Stop requested by the service manager.");
+            request_stop();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })
+    .context("could not register the service control handler")?;
+
+    status_handle.set_service_status(service_status(ServiceState::Running, ServiceExitCode::NO_ERROR))?;
+    let agent_main = AGENT_MAIN
+        .get()
+        .expect("AGENT_MAIN should be set before starting the service");
+    let exit_code = match agent_main() {
+        Ok(code) if code == ExitCode::SUCCESS => ServiceExitCode::NO_ERROR,
+        Ok(_) => ServiceExitCode::ServiceSpecific(1),
+        Err(e) => {
+            log::error!("{e:?}");
+            ServiceExitCode::ServiceSpecific(1)
+        }
+    };
+    status_handle.set_service_status(service_status(ServiceState::Stopped, exit_code))?;
+    Ok(())
+}
+
+fn service_status(state: ServiceState, exit_code: ServiceExitCode) -> ServiceStatus {
+    let controls_accepted = match state {
+        ServiceState::Running => ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        _ => ServiceControlAccept::empty(),
+    };
+    ServiceStatus {
+        service_type: SERVICE_TYPE,
+        current_state: state,
+        controls_accepted,
+        exit_code,
+        checkpoint: 0,
+        wait_hint: Duration::ZERO,
+        process_id: None,
+    }
+}
+
+/// Installs the agent as a service that starts automatically, with the given config file.
+///
+/// The service runs as `LocalSystem`. Requires administrator privileges.
+pub fn install(config: &Path) -> anyhow::Result<()> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .context("could not connect to the service manager")?;
+    let config = std::path::absolute(config).with_context(|| format!("invalid config path {config:?}"))?;
+    let info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from("Alumet agent"),
+        service_type: SERVICE_TYPE,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()?,
+        launch_arguments: vec![
+            OsString::from(SERVICE_FLAG),
+            OsString::from("--config"),
+            config.into_os_string(),
+            OsString::from("run"),
+        ],
+        dependencies: Vec::new(),
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+        .context("could not create the service")?;
+    service.set_description("Collects energy and performance metrics with Alumet.")?;
+    eventlog::register(SERVICE_NAME).context("could not register the event log source")?;
+    Ok(())
+}
+
+/// Stops and removes the service.
+pub fn uninstall() -> anyhow::Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .context("could not connect to the service manager")?;
+    let service = manager
+        .open_service(
+            SERVICE_NAME,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )
+        .context("could not open the service")?;
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop().context("could not stop the service")?;
+    }
+    service.delete().context("could not delete the service")?;
+    eventlog::deregister(SERVICE_NAME).context("could not deregister the event log source")?;
+    Ok(())
+}
@@ -0,0 +1,282 @@
+//! Live view of the measurements in the terminal, like `top`.
+//!
+//! [`TopOutput`] receives the measurements of the pipeline and keeps the recent values of each series
+//! (metric, resource, consumer and attributes). [`run_ui`] displays the last value of each series,
+//! with a sparkline of its history, until the user quits or the pipeline stops.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use alumet::{
+    measurement::{MeasurementBuffer, MeasurementPoint, WrappedMeasurementValue},
+    pipeline::{
+        Output,
+        control::AnonymousControlHandle,
+        elements::{error::WriteError, output::OutputContext},
+    },
+};
+use ratatui::{
+    Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style, Stylize},
+    text::Line,
+    widgets::{Block, Row, Table, TableState},
+};
+
+/// Number of values kept for each series, and thus width of the sparklines.
+pub const HISTORY_LEN: usize = 40;
+
+const SPARK_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Identifies a series of measurements.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SeriesKey {
+    pub metric: String,
+    pub resource: String,
+    pub consumer: String,
+    pub attributes: String,
+}
+
+/// Recent values of a series.
+#[derive(Debug, Clone, Default)]
+pub struct Series {
+    pub unit: String,
+    pub history: VecDeque<f64>,
+}
+
+/// The recent values of every series, shared between the output and the UI.
+#[derive(Debug, Clone, Default)]
+pub struct TopState(Arc<Mutex<BTreeMap<SeriesKey, Series>>>);
+
+/// Output that feeds the [`TopState`].
+pub struct TopOutput {
+    state: TopState,
+}
+
+impl TopState {
+    /// Adds a value to a series, and discards the oldest value if the history is full.
+    pub fn push(&self, key: SeriesKey, unit: &str, value: f64) {
+        let mut series = self.0.lock().unwrap();
+        let series = series.entry(key).or_default();
+        if series.unit != unit {
+            series.unit = unit.to_owned();
+        }
+        if series.history.len() == HISTORY_LEN {
+            series.history.pop_front();
+        }
+        series.history.push_back(value);
+    }
+
+    /// Returns a copy of all the series, sorted by key.
+    pub fn snapshot(&self) -> Vec<(SeriesKey, Series)> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(k, s)| (k.clone(), s.clone()))
+            .collect()
+    }
+}
+
+impl TopOutput {
+    pub fn new(state: TopState) -> Self {
+        Self { state }
+    }
+}
+
+impl Output for TopOutput {
+    fn write(&mut self, measurements: &MeasurementBuffer, ctx: &OutputContext) -> Result<(), WriteError> {
+        for m in measurements {
+            let (metric, unit) = match ctx.metrics.by_id(&m.metric) {
+                Some(metric) => (metric.name.clone(), metric.unit.to_string()),
+                None => (format!("{:?}", m.metric), String::new()),
+            };
+            let value = match m.value {
+                WrappedMeasurementValue::F64(v) => v,
+                WrappedMeasurementValue::U64(v) => v as f64,
+            };
+            self.state.push(series_key(metric, m), &unit, value);
+        }
+        Ok(())
+    }
+}
+
+fn series_key(metric: String, m: &MeasurementPoint) -> SeriesKey {
+    fn resource_string(kind: &str, id: impl std::fmt::Display) -> String {
+        let id = id.to_string();
+        if id.is_empty() {
+            kind.to_owned()
+        } else {
+            format!("{kind} {id}")
+        }
+    }
+    let mut attributes = String::new();
+    for (key, value) in m.attributes() {
+        if !attributes.is_empty() {
+            attributes.push_str(", ");
+        }
+        let _ = write!(attributes, "{key}={value}");
+    }
+    SeriesKey {
+        metric,
+        resource: resource_string(m.resource.kind(), m.resource.id_display()),
+        consumer: resource_string(m.consumer.kind(), m.consumer.id_display()),
+        attributes,
+    }
+}
+
+/// Draws the values as a line of bars, scaled between the minimum and the maximum.
+pub fn sparkline(values: &VecDeque<f64>) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|v| {
+            if max > min {
+                let i = ((v - min) / (max - min) * (SPARK_CHARS.len() - 1) as f64).round() as usize;
+                SPARK_CHARS[i.min(SPARK_CHARS.len() - 1)]
+            } else {
+                SPARK_CHARS[0]
+            }
+        })
+        .collect()
+}
+
+/// Formats a value with a reasonable precision.
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{value:.0}")
+    } else if value.abs() >= 100.0 {
+        format!("{value:.1}")
+    } else {
+        format!("{value:.3}")
+    }
+}
+
+/// Displays the measurements in the terminal, until the user quits or the pipeline is shut down.
+pub fn run_ui(state: TopState, control: AnonymousControlHandle, refresh: Duration) -> anyhow::Result<()> {
+    let mut terminal = ratatui::init();
+    let mut table_state = TableState::default();
+    let res = (|| {
+        loop {
+            if control.is_shutdown_requested() {
+                return Ok(());
+            }
+            let series = state.snapshot();
+            terminal.draw(|frame| draw(frame, &series, &mut table_state))?;
+
+            if event::poll(refresh)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+            {
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+                    KeyCode::Down | KeyCode::Char('j') => table_state.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => table_state.select_previous(),
+                    KeyCode::PageDown => table_state.scroll_down_by(10),
+                    KeyCode::PageUp => table_state.scroll_up_by(10),
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    })();
+    ratatui::restore();
+    res
+}
+
+fn draw(frame: &mut Frame, series: &[(SeriesKey, Series)], table_state: &mut TableState) {
+    let [main, help] = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+
+    let header = Row::new([
+        "Metric",
+        "Resource",
+        "Consumer",
+        "Attributes",
+        "Value",
+        "Unit",
+        "History",
+    ])
+    .style(Style::new().add_modifier(Modifier::BOLD));
+    let rows = series.iter().map(|(key, s)| {
+        let last = s.history.back().copied().unwrap_or_default();
+        Row::new([
+            key.metric.clone(),
+            key.resource.clone(),
+            key.consumer.clone(),
+            key.attributes.clone(),
+            format_value(last),
+            s.unit.clone(),
+            sparkline(&s.history),
+        ])
+    });
+    let widths = [
+        Constraint::Fill(2),
+        Constraint::Fill(1),
+        Constraint::Fill(1),
+        Constraint::Fill(1),
+        Constraint::Length(14),
+        Constraint::Length(6),
+        Constraint::Length(HISTORY_LEN as u16),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .row_highlight_style(Style::new().reversed())
+        .block(Block::bordered().title(format!(" Alumet: {} series ", series.len())));
+    frame.render_stateful_widget(table, main, table_state);
+    frame.render_widget(Line::from(" q: quit   ↑/↓: scroll").dim(), help);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::{HISTORY_LEN, SeriesKey, TopState, format_value, sparkline};
+
+    fn key(metric: &str) -> SeriesKey {
+        SeriesKey {
+            metric: metric.to_owned(),
+            resource: String::from("local_machine"),
+            consumer: String::from("local_machine"),
+            attributes: String::new(),
+        }
+    }
+
+    #[test]
+    fn history() {
+        let state = TopState::default();
+        for i in 0..(HISTORY_LEN + 5) {
+            state.push(key("b"), "W", i as f64);
+        }
+        state.push(key("a"), "J", 1.5);
+
+        let snapshot = state.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot[0].0.metric, "a");
+        assert_eq!(snapshot[0].1.unit, "J");
+        let history = &snapshot[1].1.history;
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history.front(), Some(&5.0));
+        assert_eq!(history.back(), Some(&((HISTORY_LEN + 4) as f64)));
+    }
+
+    #[test]
+    fn sparklines() {
+        assert_eq!(sparkline(&VecDeque::from([0.0, 7.0, 3.5, 7.0])), "▁█▅█");
+        assert_eq!(sparkline(&VecDeque::from([2.0, 2.0])), "▁▁");
+        assert_eq!(sparkline(&VecDeque::new()), "");
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(format_value(12.0), "12");
+        assert_eq!(format_value(123.456), "123.5");
+        assert_eq!(format_value(1.23456), "1.235");
+    }
+}
@@ -0,0 +1,115 @@
+//! Secret provider for HashiCorp Vault.
+//!
+//! Secrets are referenced as `secret://vault/<path>#<field>`, where `<path>` is the API path of the secret
+//! (without the `/v1/` prefix), for instance `secret://vault/secret/data/alumet/influxdb#token`.
+//! Both versions of the KV secrets engine are supported.
+//!
+//! The address of the Vault server and the token are read from the standard environment variables
+//! `VAULT_ADDR` and `VAULT_TOKEN`.
+
+use std::sync::OnceLock;
+
+use alumet::plugin::secret::SecretProvider;
+use anyhow::{Context, anyhow};
+
+/// Fetches secrets from a Vault server.
+pub struct VaultProvider {
+    address: String,
+    token: String,
+    /// The blocking client spawns a thread, therefore it is only created when the first secret is fetched,
+    /// which happens after the agent has forked into the background (in daemon mode).
+    client: OnceLock<reqwest::blocking::Client>,
+}
+
+impl VaultProvider {
+    pub fn new(address: String, token: String) -> Self {
+        Self {
+            address: address.trim_end_matches('/').to_owned(),
+            token,
+            client: OnceLock::new(),
+        }
+    }
+
+    fn client(&self) -> anyhow::Result<&reqwest::blocking::Client> {
+        if let Some(client) = self.client.get() {
+            return Ok(client);
+        }
+        let client = reqwest::blocking::Client::builder()
+            .build()
+            .context("failed to build the HTTP client")?;
+        Ok(self.client.get_or_init(|| client))
+    }
+
+    /// Creates a provider from the environment variables `VAULT_ADDR` and `VAULT_TOKEN`.
+    ///
+    /// Returns `None` if `VAULT_ADDR` is not set.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(address) = std::env::var("VAULT_ADDR") else {
+            return Ok(None);
+        };
+        let token = std::env::var("VAULT_TOKEN").context("VAULT_ADDR is set but VAULT_TOKEN is missing")?;
+        Ok(Some(Self::new(address, token)))
+    }
+}
+
+impl SecretProvider for VaultProvider {
+    fn get(&self, path: &str) -> anyhow::Result<String> {
+        let (path, field) = path
+            .split_once('#')
+            .context("the field of the Vault secret is missing, expected <path>#<field>")?;
+        let url = format!("{}/v1/{path}", self.address);
+        let response: serde_json::Value = self
+            .client()?
+            .get(&url)
+            .header("X-Vault-Token", &self.token)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("request to {url} failed"))?
+            .json()
+            .context("invalid response from Vault")?;
+
+        // KV v2 nests the secret in data.data, KV v1 uses data directly
+        let data = &response["data"];
+        let secret = data["data"].get(field).or_else(|| data.get(field));
+        match secret {
+            Some(serde_json::Value::String(value)) => Ok(value.clone()),
+            Some(_) => Err(anyhow!("field {field} of the Vault secret is not a string")),
+            None => Err(anyhow!("field {field} not found in the Vault secret")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alumet::plugin::secret::SecretProvider;
+
+    use super::VaultProvider;
+
+    #[test]
+    fn kv_v2() {
+        let mut server = mockito::Server::new();
+        let mock = server
+            .mock("GET", "/v1/secret/data/alumet")
+            .match_header("X-Vault-Token", "root")
+            .with_body(r#"{"data": {"data": {"token": "abcd"}, "metadata": {"version": 1}}}"#)
+            .create();
+
+        let vault = VaultProvider::new(server.url(), String::from("root"));
+        assert_eq!(vault.get("secret/data/alumet#token").unwrap(), "abcd");
+        assert!(vault.get("secret/data/alumet#password").is_err());
+        assert!(vault.get("secret/data/alumet").is_err());
+        mock.expect(2).assert();
+    }
+
+    #[test]
+    fn kv_v1() {
+        let mut server = mockito::Server::new();
+        server
+            .mock("GET", "/v1/kv/alumet")
+            .with_body(r#"{"data": {"password": "1234"}}"#)
+            .create();
+
+        let vault = VaultProvider::new(server.url(), String::from("root"));
+        assert_eq!(vault.get("kv/alumet#password").unwrap(), "1234");
+    }
+}
@@ -5,7 +5,7 @@ use anyhow::Context;
 
 use common::{
     empty_temp_dir,
-    run::{run_agent, run_agent_tee},
+    run::{ChildGuard, command_run_agent, run_agent, run_agent_tee},
     tests,
 };
 use indoc::indoc;
@@ -109,6 +109,75 @@ fn args_output_exec() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn batch() -> anyhow::Result<()> {
+    let tmp = empty_temp_dir()?;
+    let tmp_dir = tmp.0.path();
+    let file_in = tmp_dir.join("recorded.csv");
+    let file_out = tmp_dir.join("processed.csv");
+    let file_conf = tmp_dir.join("agent-config.toml");
+    let recorded = indoc! {"
+        metric;timestamp;value;resource_kind;resource_id;consumer_kind;consumer_id;__late_attributes
+        rapl_consumed_energy_J;2025-01-01T12:00:00Z;12.5;cpu_package;0;local_machine;;
+        rapl_consumed_energy_J;2025-01-01T12:00:01Z;3;cpu_package;0;local_machine;;
+    "};
+    std::fs::write(&file_in, recorded)?;
+
+    // the agent should process the file and exit by itself
+    let output = run_agent_tee(
+        AGENT_BIN,
+        &[
+            "--config",
+            file_conf.to_str().unwrap(),
+            "--plugins",
+            "csv",
+            "--output-file",
+            file_out.to_str().unwrap(),
+            "batch",
+            file_in.to_str().unwrap(),
+        ],
+        tmp_dir,
+    )?;
+    assert!(output.status.success(), "alumet-agent batch should succeed");
+
+    let processed = std::fs::read_to_string(&file_out)?;
+    assert_eq!(processed, recorded);
+    Ok(())
+}
+
+#[test]
+fn args_set_exec() -> anyhow::Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let conf = tmp_dir.path().join("config.toml");
+    let out = tmp_dir.path().join("set-output.csv");
+
+    // Check that --set overrides the options of the plugins
+    let conf_path_str = conf.to_str().unwrap();
+    let set_output = format!("plugins.csv.output_path={}", out.to_str().unwrap());
+    let output = run_agent_tee(
+        AGENT_BIN,
+        &[
+            "--config",
+            conf_path_str,
+            "--plugins",
+            "procfs,csv",
+            "--set",
+            &set_output,
+            "--set",
+            "plugins.csv.append_unit_to_metric_name=false",
+            "exec",
+            "sleep",
+            "1",
+        ],
+        tmp_dir.path(),
+    )?;
+    assert!(output.status.success(), "alumet-agent --set KEY=VALUE should work");
+
+    let alumet_out = std::fs::read_to_string(&out).with_context(|| format!("failed to read {out:?}"))?;
+    assert!(alumet_out.contains("value"));
+    Ok(())
+}
+
 #[test]
 fn plugin_enabled_with_missing_config_should_use_default() -> anyhow::Result<()> {
     let tmp_dir = tempfile::tempdir()?;
@@ -145,3 +214,190 @@ fn plugin_enabled_with_bad_config_should_fail() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn config_check() -> anyhow::Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let conf = tmp_dir.path().join("config.toml");
+    let conf_path_str = conf.to_str().unwrap();
+
+    // valid config (the default config of the plugin is used)
+    std::fs::write(&conf, "")?;
+    let output = run_agent_tee(
+        AGENT_BIN,
+        &["--plugins", "csv", "--config", conf_path_str, "config", "check"],
+        tmp_dir.path(),
+    )?;
+    assert!(output.status.success(), "check should succeed");
+
+    // two invalid sections, both should be reported
+    std::fs::write(
+        &conf,
+        "max_update_interval = 123\n[plugins.csv]\nappend_unit_to_metric_name = 'yes'\n",
+    )?;
+    let output = run_agent_tee(
+        AGENT_BIN,
+        &["--plugins", "csv", "--config", conf_path_str, "config", "check"],
+        tmp_dir.path(),
+    )?;
+    assert!(!output.status.success(), "check should fail");
+
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.contains("Invalid general config"), "{stderr}");
+    assert!(stderr.contains("plugins.csv.append_unit_to_metric_name"), "{stderr}");
+    assert!(stderr.contains("2 error(s)"), "{stderr}");
+    Ok(())
+}
+
+#[test]
+fn regen_config_with_profile() -> anyhow::Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let conf = tmp_dir.path().join("config.toml");
+
+    let conf_path_str = conf.to_str().unwrap();
+    let output = run_agent_tee(
+        AGENT_BIN,
+        &[
+            "--profile",
+            "developer-laptop",
+            "--config",
+            conf_path_str,
+            "config",
+            "regen",
+        ],
+        tmp_dir.path(),
+    )?;
+    assert!(output.status.success(), "command should succeed");
+
+    // only the plugins of the profile are configured, with the settings of the profile
+    let config: toml::Table = std::fs::read_to_string(conf)?.parse()?;
+    let plugins = config["plugins"].as_table().unwrap();
+    let mut names: Vec<&str> = plugins.keys().map(|k| k.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["csv", "procfs", "rapl"]);
+    assert_eq!(plugins["csv"]["force_flush"].as_bool(), Some(true));
+    Ok(())
+}
+
+#[test]
+fn log_file() -> anyhow::Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let conf = tmp_dir.path().join("config.toml");
+    let log = tmp_dir.path().join("agent.log");
+    std::fs::write(
+        &conf,
+        format!(
+            "[logging]\nlevel = \"info\"\nstderr = false\nfile = {:?}\n",
+            log.to_str().unwrap()
+        ),
+    )?;
+
+    let conf_path_str = conf.to_str().unwrap();
+    let output = run_agent_tee(
+        AGENT_BIN,
+        &["--plugins", "procfs", "--config", conf_path_str, "exec", "sleep", "0"],
+        tmp_dir.path(),
+    )?;
+    assert!(output.status.success(), "command should succeed");
+
+    // the logs that follow the loading of the config are written to the file, and only to the file
+    let logs = std::fs::read_to_string(&log).with_context(|| format!("failed to read {log:?}"))?;
+    assert!(logs.contains("All plugins have stopped"), "{logs}");
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(!stderr.contains("All plugins have stopped"), "{stderr}");
+    Ok(())
+}
+
+#[test]
+fn log_json_spans() -> anyhow::Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let conf = tmp_dir.path().join("config.toml");
+    let log = tmp_dir.path().join("agent.log");
+    std::fs::write(
+        &conf,
+        format!(
+            "[logging]\nformat = \"json\"\nspans = true\nfile = {:?}\n",
+            log.to_str().unwrap()
+        ),
+    )?;
+
+    let conf_path_str = conf.to_str().unwrap();
+    let output = run_agent_tee(
+        AGENT_BIN,
+        &["--plugins", "procfs", "--config", conf_path_str, "exec", "sleep", "0"],
+        tmp_dir.path(),
+    )?;
+    assert!(output.status.success(), "command should succeed");
+
+    // every line is a JSON object, and the polls of the sources are logged with their duration
+    let logs = std::fs::read_to_string(&log).with_context(|| format!("failed to read {log:?}"))?;
+    let records: Vec<serde_json::Value> = logs
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()
+        .with_context(|| format!("invalid JSON logs: {logs}"))?;
+    let poll = records
+        .iter()
+        .find(|r| r["target"] == "alumet::span" && r["span"] == "poll")
+        .with_context(|| format!("no poll span in the logs: {logs}"))?;
+    assert!(
+        poll["element"].as_str().unwrap().starts_with("sources/procfs/"),
+        "{poll}"
+    );
+    assert!(poll["duration_us"].is_u64(), "{poll}");
+    Ok(())
+}
+
+#[test]
+fn ctl() -> anyhow::Result<()> {
+    let tmp_dir = tempfile::tempdir()?;
+    let conf = tmp_dir.path().join("config.toml");
+    let socket = tmp_dir.path().join("control.sock");
+    std::fs::write(
+        &conf,
+        format!(
+            "[plugins.socket-control]\nsocket_path = {:?}\n",
+            socket.to_str().unwrap()
+        ),
+    )?;
+
+    let conf_path_str = conf.to_str().unwrap();
+    let agent = command_run_agent(AGENT_BIN, &["--plugins", "socket-control", "--config", conf_path_str])?
+        .current_dir(tmp_dir.path())
+        .spawn()?;
+    let mut agent = ChildGuard::new(agent);
+    for _ in 0..100 {
+        if socket.exists() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+
+    let socket_str = socket.to_str().unwrap();
+    let ctl = |args: &[&str]| -> anyhow::Result<std::process::Output> {
+        let mut full_args = vec!["--socket", socket_str];
+        full_args.extend(args);
+        Ok(command_run_agent("alumet-ctl", &full_args)?.output()?)
+    };
+
+    let output = ctl(&["stats"])?;
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(
+        stdout.starts_with("elements: 0 sources, 0 transforms, 1 outputs"),
+        "{stdout}"
+    );
+
+    let output = ctl(&["list", "source"])?;
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8(output.stdout)?, "");
+
+    let output = ctl(&["set-interval", "source", "forever"])?;
+    assert!(!output.status.success(), "{output:?}");
+
+    let output = ctl(&["shutdown"])?;
+    assert!(output.status.success(), "{output:?}");
+    let status = agent.take().wait()?;
+    assert!(status.success(), "the agent should stop properly");
+    Ok(())
+}
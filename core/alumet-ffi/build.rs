@@ -7,6 +7,8 @@ fn main() {
     println!("cargo::rerun-if-env-changed=SKIP_BINDGEN");
     println!("cargo::rerun-if-env-changed=BINDGEN_OUT_DIR");
     println!("cargo::rerun-if-env-changed=ADDITIONAL_TARGET_DIR");
+    // The bindings and the list of symbols depend on the API, regenerate them when it changes.
+    println!("cargo::rerun-if-changed=src");
 
     if env::var_os("SKIP_BINDGEN").is_some() {
         return;
@@ -60,17 +62,65 @@ fn main() {
     // Write the list of symbols for the linker (useful during the compilation of the agent)
     bindings.generate_symfile(sym_file_path);
 
+    // Hash the declarations of the API, so that the plugin loader can detect the plugins that
+    // have been built against another version of the bindings.
+    let mut header = Vec::new();
+    bindings.write(&mut header);
+    let header = String::from_utf8(header).expect("the C bindings should be valid UTF-8");
+    let abi_hash = abi_hash(&header);
+    println!("cargo::rustc-env=ALUMET_ABI_HASH={abi_hash:016x}");
+    let header = with_abi_hash(header, abi_hash);
+
     // Write the C bindings.
-    bindings.write_to_file(out_file_path);
+    fs::write(out_file_path, &header).unwrap();
     println!("C-compatible API generated");
 
     // Copy to additional dir
     if let Some(out_dir) = additional_out_dir {
         let out_file_path = out_dir.join("alumet.h");
         let sym_file_path = out_dir.join("alumet-symbols.txt");
+        fs::create_dir_all(&out_dir).unwrap();
         bindings.generate_symfile(sym_file_path);
-        bindings.write_to_file(out_file_path);
+        fs::write(out_file_path, &header).unwrap();
+    }
+}
+
+/// Computes a hash of the declarations of a C header, ignoring the comments and the whitespace.
+///
+/// This is FNV-1a, which is stable across Rust versions (unlike the hasher of the standard library).
+fn abi_hash(header: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut rest = header;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("/*") {
+            rest = comment.split_once("*/").map_or("", |(_, after)| after);
+        } else if let Some(comment) = rest.strip_prefix("//") {
+            rest = comment.split_once('\n').map_or("", |(_, after)| after);
+        } else {
+            let c = rest.chars().next().unwrap();
+            rest = &rest[c.len_utf8()..];
+            if !c.is_whitespace() {
+                let mut bytes = [0; 4];
+                for b in c.encode_utf8(&mut bytes).bytes() {
+                    hash ^= b as u64;
+                    hash = hash.wrapping_mul(0x100000001b3);
+                }
+            }
+        }
+    }
+    hash
+}
+
+/// Adds the definition of `ALUMET_ABI_HASH` at the end of the header, before the trailer.
+fn with_abi_hash(mut header: String, hash: u64) -> String {
+    // When cbindgen expands the macros, this script runs again and generates empty bindings, without trailer.
+    if let Some(trailer) = header.rfind("#endif") {
+        let definition = format!(
+            "/**\n * Hash of the declarations of this header, see `PLUGIN_ABI_HASH`.\n */\n#define ALUMET_ABI_HASH 0x{hash:016x}ULL\n\n"
+        );
+        header.insert_str(trailer, &definition);
     }
+    header
 }
 
 /// Enable flag RUSTC_BOOTSTRAP, which allows to use nightly API on the stable compiler.
@@ -0,0 +1,66 @@
+//! Compatibility of the Application Binary Interface (ABI) between the agent and the dynamic plugins.
+
+use std::fmt::Display;
+
+/// Version of the ABI.
+///
+/// The changes of the declarations of the API (functions, structs, enums...) are detected by the hash
+/// of the bindings, `ALUMET_ABI_HASH`. Increase this number when the API changes in a way that does not
+/// appear in its declarations, for instance when a function starts to take the ownership of its argument.
+pub const ALUMET_ABI_VERSION: u32 = 1;
+
+/// The ABI that a plugin has been built against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Abi {
+    pub version: u32,
+    /// Hash of the declarations of the bindings.
+    pub hash: u64,
+}
+
+impl Abi {
+    /// Returns the ABI of the current bindings.
+    ///
+    /// If the bindings have not been generated (because `SKIP_BINDGEN` was set), the hash is zero.
+    pub const fn current() -> Abi {
+        let hash = match option_env!("ALUMET_ABI_HASH") {
+            Some(hash) => match u64::from_str_radix(hash, 16) {
+                Ok(hash) => hash,
+                Err(_) => panic!("ALUMET_ABI_HASH should be a valid hash"),
+            },
+            None => 0,
+        };
+        Abi {
+            version: ALUMET_ABI_VERSION,
+            hash,
+        }
+    }
+
+    /// Checks whether a plugin built against the ABI `plugin` can be loaded by an agent that uses this ABI.
+    pub fn can_load(&self, plugin: &Abi) -> bool {
+        // If our hash is unknown, only the version can be checked.
+        self.version == plugin.version && (self.hash == 0 || self.hash == plugin.hash)
+    }
+}
+
+impl Display for Abi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{} (hash {:016x})", self.version, self.hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Abi;
+
+    #[test]
+    fn compatibility() {
+        let current = Abi { version: 2, hash: 123 };
+        assert!(current.can_load(&Abi { version: 2, hash: 123 }));
+        assert!(!current.can_load(&Abi { version: 1, hash: 123 }));
+        assert!(!current.can_load(&Abi { version: 2, hash: 456 }));
+
+        let unknown_hash = Abi { version: 2, hash: 0 };
+        assert!(unknown_hash.can_load(&Abi { version: 2, hash: 456 }));
+        assert!(!unknown_hash.can_load(&Abi { version: 1, hash: 456 }));
+    }
+}
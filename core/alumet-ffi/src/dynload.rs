@@ -3,12 +3,18 @@
 use std::{
     collections::HashMap,
     ffi::{CStr, c_char},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::Arc,
 };
 
+use anyhow::anyhow;
+
 use libc::c_void;
 use libloading::{Library, Symbol};
 
+use super::abi::Abi;
+use super::panic::{PluginHealth, panic_message};
+use super::trust::{TrustError, TrustPolicy};
 use alumet::plugin::{AlumetPluginStart, AlumetPostStart, ConfigTable, Plugin, PluginMetadata};
 use alumet::plugin::{
     AlumetPreStart,
@@ -25,8 +31,12 @@ struct DylibPlugin {
     // the library must stay loaded for the symbols to be valid
     _library: Library,
     instance: *mut c_void,
+    health: Arc<PluginHealth>,
 }
 
+// Like the pipeline elements, the plugin instance must not use thread-local storage.
+unsafe impl Send for DylibPlugin {}
+
 impl Plugin for DylibPlugin {
     fn name(&self) -> &str {
         &self.name
@@ -37,13 +47,19 @@ impl Plugin for DylibPlugin {
     }
 
     fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
-        (self.start_fn)(self.instance, alumet); // TODO error handling for ffi
+        let (start, instance) = (self.start_fn, self.instance);
+        self.health
+            .while_starting(|| self.health.call("plugin_start", || start(instance, alumet)))?;
+        // a function of the API may have panicked (and marked the plugin as failed) during the startup
+        if self.health.has_failed() {
+            return Err(anyhow!("plugin {} failed to start", self.name));
+        }
         Ok(())
     }
 
     fn stop(&mut self) -> anyhow::Result<()> {
-        (self.stop_fn)(self.instance); // TODO error handling for ffi
-        Ok(())
+        let (stop, instance) = (self.stop_fn, self.instance);
+        self.health.call("plugin_stop", || stop(instance))
     }
 
     fn pre_pipeline_start(&mut self, _alumet: &mut AlumetPreStart) -> anyhow::Result<()> {
@@ -65,7 +81,16 @@ impl Drop for DylibPlugin {
         //
         // **Rule of thumb**: Rust allocations are deallocated by Rust code,
         // C allocations (malloc) are deallocated by C code (free).
-        unsafe { (self.drop_fn)(self.instance) };
+        //
+        // This is done even if the plugin has failed, and a panic must not escape from `drop`.
+        let (drop, instance) = (self.drop_fn, self.instance);
+        if let Err(payload) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe { drop(instance) })) {
+            log::error!(
+                "plugin_drop of plugin {} panicked: {}",
+                self.name,
+                panic_message(payload)
+            );
+        }
     }
 }
 
@@ -81,6 +106,12 @@ pub enum LoadError {
         plugin_alumet_version: Version,
         current_alumet_version: Version,
     },
+    /// The plugin has been built against bindings that are not compatible with the current ones.
+    ///
+    /// `plugin_abi` is `None` if the plugin does not declare its ABI.
+    IncompatibleAbi { plugin_abi: Option<Abi>, current_abi: Abi },
+    /// The library does not comply with the [`TrustPolicy`], it has not been loaded.
+    Untrusted(TrustError),
     /// `plugin_init` failed.
     PluginInit,
 }
@@ -97,6 +128,8 @@ pub struct PluginRegistry {
 /// - `PLUGIN_NAME: *const c_char`: the name of the plugin, as a null-terminated string
 /// - `PLUGIN_VERSION: *const c_char`: the version of the plugin, of the form "x.y.z" where x,y,z are integers
 /// - `ALUMET_VERSION: *const c_char`: the version of alumet that this plugin requires, of the form "x.y.z"
+/// - `PLUGIN_ABI_VERSION: u32`: the value of `ALUMET_ABI_VERSION` in the bindings that the plugin uses
+/// - `PLUGIN_ABI_HASH: u64`: the value of `ALUMET_ABI_HASH` in the bindings that the plugin uses
 /// - `plugin_init: PluginInitFn`: see [`super::PluginInitFn`]
 /// - `plugin_start: PluginStartFn`: see [`super::PluginStartFn`]
 /// - `plugin_stop: PluginStopFn`: see [`super::PluginStopFn`]
@@ -111,23 +144,33 @@ pub struct PluginRegistry {
 /// pub static PLUGIN_VERSION: &[u8] = b"0.0.1\0";
 /// #[unsafe(no_mangle)]
 /// pub static ALUMET_VERSION: &[u8] = b"0.1.0\0";
+/// #[unsafe(no_mangle)]
+/// pub static PLUGIN_ABI_VERSION: u32 = alumet_ffi::abi::ALUMET_ABI_VERSION;
+/// #[unsafe(no_mangle)]
+/// pub static PLUGIN_ABI_HASH: u64 = alumet_ffi::abi::Abi::current().hash;
 ///
 /// #[unsafe(no_mangle)]
-/// pub extern "C" fn plugin_init(config: &ConfigTable) -> *mut MyPluginStruct {}
+/// pub extern "C-unwind" fn plugin_init(config: &ConfigTable) -> *mut MyPluginStruct {}
 /// #[unsafe(no_mangle)]
-/// pub extern "C" fn plugin_start(plugin: &mut MyPluginStruct, alumet: &mut AlumetPluginStart) {}
+/// pub extern "C-unwind" fn plugin_start(plugin: &mut MyPluginStruct, alumet: &mut AlumetPluginStart) {}
 /// #[unsafe(no_mangle)]
-/// pub extern "C" fn plugin_stop(plugin: &mut MyPluginStruct) {}
+/// pub extern "C-unwind" fn plugin_stop(plugin: &mut MyPluginStruct) {}
 /// #[unsafe(no_mangle)]
-/// pub extern "C" fn plugin_drop(plugin: *mut MyPluginStruct) {}
+/// pub extern "C-unwind" fn plugin_drop(plugin: *mut MyPluginStruct) {}
 /// ```
 ///
+///
+/// The functions are declared `extern "C-unwind"`: if they panic, the panic is caught by the agent,
+/// which marks the plugin as failed and stops its sources, transforms and outputs.
+///
 /// # Declaration in C
 /// Declaring such variables and symbols in the C language would look like the following:
 /// ```ignore
 /// PLUGIN_API const char *PLUGIN_NAME = "my-plugin";
 /// PLUGIN_API const char *PLUGIN_VERSION = "0.0.1";
 /// PLUGIN_API const char *ALUMET_VERSION = "0.1.0";
+/// PLUGIN_API const uint32_t PLUGIN_ABI_VERSION = ALUMET_ABI_VERSION;
+/// PLUGIN_API const uint64_t PLUGIN_ABI_HASH = ALUMET_ABI_HASH;
 ///
 /// PLUGIN_API MyPluginStruct *plugin_init(const ConfigTable *config) {}
 /// PLUGIN_API void plugin_start(MyPluginStruct *plugin, AlumetPluginStart *alumet) {}
@@ -204,12 +247,33 @@ pub fn load_cdylib(file: &Path) -> Result<PluginMetadata, LoadError> {
         });
     }
 
+    // check that the plugin uses the same ABI, otherwise calling its functions is undefined behavior
+    let sym_abi_version: Option<Symbol<*const u32>> = unsafe { lib.get(b"PLUGIN_ABI_VERSION\0") }.ok();
+    let sym_abi_hash: Option<Symbol<*const u64>> = unsafe { lib.get(b"PLUGIN_ABI_HASH\0") }.ok();
+    let plugin_abi = match (sym_abi_version, sym_abi_hash) {
+        (Some(version), Some(hash)) => Some(unsafe {
+            Abi {
+                version: **version,
+                hash: **hash,
+            }
+        }),
+        _ => None,
+    };
+    let current_abi = Abi::current();
+    if !plugin_abi.is_some_and(|abi| current_abi.can_load(&abi)) {
+        return Err(LoadError::IncompatibleAbi {
+            plugin_abi,
+            current_abi,
+        });
+    }
+
     // extract the function pointers from the Symbol, to get around lifetime constraints
     let init_fn = *sym_init;
     let start_fn = *sym_start;
     let stop_fn = *sym_stop;
     let drop_fn = *sym_drop;
     let default_config_fn = sym_default_config.map(|sym| *sym);
+    let default_config_name = name.clone();
 
     // wrap the plugin info in a Rust struct, to allow the plugin to be initialized later
     let initializable_info = PluginMetadata {
@@ -217,7 +281,8 @@ pub fn load_cdylib(file: &Path) -> Result<PluginMetadata, LoadError> {
         version: version.clone(),
         init: Box::new(move |config| {
             // initialize the plugin
-            let external_plugin = init_fn(&config.0);
+            let health = PluginHealth::new(name.clone());
+            let external_plugin = health.call("plugin_init", || init_fn(&config.0))?;
             log::debug!("init called from Rust");
 
             if external_plugin.is_null() {
@@ -233,6 +298,7 @@ pub fn load_cdylib(file: &Path) -> Result<PluginMetadata, LoadError> {
                 drop_fn,
                 _library: lib,
                 instance: external_plugin,
+                health,
             };
             Ok(Box::new(plugin))
         }),
@@ -240,17 +306,68 @@ pub fn load_cdylib(file: &Path) -> Result<PluginMetadata, LoadError> {
             Some(f) => Box::new(move || {
                 let mut config_to_fill = toml::Table::new();
                 log::debug!("filling default config");
-                f(&mut config_to_fill);
+                PluginHealth::new(default_config_name.clone())
+                    .call("plugin_default_config", || f(&mut config_to_fill))?;
                 log::debug!("default config filled");
                 Ok(Some(ConfigTable(config_to_fill)))
             }),
             None => Box::new(|| Ok(None)),
         },
+        dependencies: Vec::new(),
+        // the C API does not provide any schema
+        config_schema: Box::new(|| Ok(None)),
+        config_migrations: Vec::new(),
     };
 
     Ok(initializable_info)
 }
 
+/// Checks a shared library against the trust policy, and loads it if it is trusted.
+///
+/// The library is verified before being opened, therefore an untrusted library does not get the opportunity
+/// to run any code. See [`load_cdylib`].
+pub fn load_trusted_cdylib(file: &Path, policy: &TrustPolicy) -> Result<PluginMetadata, LoadError> {
+    policy.verify(file).map_err(LoadError::Untrusted)?;
+    load_cdylib(file)
+}
+
+/// Loads the dynamic plugins of a directory.
+///
+/// Every file that has the extension of shared libraries on this platform (`.so` on Linux) is loaded
+/// with [`load_cdylib`], in the order of the file names. Subdirectories are ignored.
+/// If a trust `policy` is given, the files are verified before being loaded, with [`load_trusted_cdylib`].
+///
+/// The result of each file is returned separately, so that an invalid plugin does not prevent the others
+/// from being loaded.
+pub fn load_dir(
+    dir: &Path,
+    policy: Option<&TrustPolicy>,
+) -> std::io::Result<Vec<(PathBuf, Result<PluginMetadata, LoadError>)>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION)
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    log::debug!("found {} shared libraries in {}", files.len(), dir.display());
+    Ok(files
+        .into_iter()
+        .map(|file| {
+            let res = match policy {
+                Some(policy) => load_trusted_cdylib(&file, policy),
+                None => load_cdylib(&file),
+            };
+            (file, res)
+        })
+        .collect())
+}
+
 impl std::error::Error for LoadError {}
 impl std::fmt::Display for LoadError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -258,6 +375,21 @@ impl std::fmt::Display for LoadError {
             LoadError::LibraryLoad(err) => write!(f, "failed to load shared library: {err}"),
             LoadError::InvalidSymbol(name, err) => write!(f, "invalid value for symbol {name}: {err}"),
             LoadError::PluginInit => write!(f, "plugin_init returned NULL"),
+            LoadError::Untrusted(err) => write!(f, "refusing to load untrusted library: {err}"),
+            LoadError::IncompatibleAbi {
+                plugin_abi: Some(plugin_abi),
+                current_abi,
+            } => write!(
+                f,
+                "plugin built against incompatible Alumet ABI {plugin_abi}, the current ABI is {current_abi}: rebuild the plugin with the current bindings"
+            ),
+            LoadError::IncompatibleAbi {
+                plugin_abi: None,
+                current_abi,
+            } => write!(
+                f,
+                "plugin built against incompatible Alumet ABI: it does not declare PLUGIN_ABI_VERSION and PLUGIN_ABI_HASH, rebuild the plugin with the current bindings (ABI {current_abi})"
+            ),
             LoadError::IncompatiblePlugin {
                 plugin_alumet_version,
                 current_alumet_version,
@@ -292,3 +424,28 @@ impl PluginRegistry {
         // `&mut dyn Plugin + 'static` to `&mut dyn Plugin + 'a`
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::trust::TrustPolicy;
+
+    use super::{LoadError, load_dir};
+
+    #[test]
+    fn load_dir_skips_other_files() {
+        let dir = std::env::temp_dir().join("alumet-test-load-dir");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("README.txt"), "not a plugin").unwrap();
+        let lib = dir.join(format!("invalid.{}", std::env::consts::DLL_EXTENSION));
+        std::fs::write(&lib, "not a library either").unwrap();
+
+        let loaded = load_dir(&dir, None).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].0, lib);
+        assert!(matches!(loaded[0].1, Err(LoadError::LibraryLoad(_))));
+
+        // with a policy, the library is not even opened
+        let loaded = load_dir(&dir, Some(&TrustPolicy::new())).unwrap();
+        assert!(matches!(loaded[0].1, Err(LoadError::Untrusted(_))));
+    }
+}
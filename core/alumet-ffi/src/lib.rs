@@ -26,29 +26,38 @@ use time::Timestamp;
 #[cfg(feature = "dynamic")]
 pub mod dynload;
 
+pub mod abi;
 pub mod config;
 pub mod metrics;
+mod panic;
 pub mod pipeline;
 pub mod plugin;
 pub mod resources;
 pub mod string;
 pub mod time;
+#[cfg(feature = "dynamic")]
+pub mod trust;
 pub mod units;
 
 // ====== Function types ======
-pub type PluginInitFn = extern "C" fn(config: *const toml::Table) -> *mut c_void;
-pub type PluginDefaultConfigFn = extern "C" fn(config: *mut toml::Table);
-pub type PluginStartFn = extern "C" fn(instance: *mut c_void, alumet: *mut AlumetPluginStart);
-pub type PluginStopFn = extern "C" fn(instance: *mut c_void);
+// The functions of the plugins are "C-unwind": a plugin written in Rust can panic, the panic
+// is then caught by the agent (see the `panic` module). A plugin written in C never unwinds.
+pub type PluginInitFn = extern "C-unwind" fn(config: *const toml::Table) -> *mut c_void;
+pub type PluginDefaultConfigFn = extern "C-unwind" fn(config: *mut toml::Table);
+pub type PluginStartFn = extern "C-unwind" fn(instance: *mut c_void, alumet: *mut AlumetPluginStart);
+pub type PluginStopFn = extern "C-unwind" fn(instance: *mut c_void);
 
-pub type DropFn = unsafe extern "C" fn(instance: *mut c_void);
-pub type NullableDropFn = Option<unsafe extern "C" fn(instance: *mut c_void)>;
+pub type DropFn = unsafe extern "C-unwind" fn(instance: *mut c_void);
+pub type NullableDropFn = Option<unsafe extern "C-unwind" fn(instance: *mut c_void)>;
 
-pub type SourcePollFn = extern "C" fn(instance: *mut c_void, buffer: *mut MeasurementAccumulator, timestamp: Timestamp);
+pub type SourcePollFn =
+    extern "C-unwind" fn(instance: *mut c_void, buffer: *mut MeasurementAccumulator, timestamp: Timestamp);
 pub type TransformApplyFn =
-    extern "C" fn(instance: *mut c_void, buffer: *mut MeasurementBuffer, ctx: *const FfiTransformContext);
+    extern "C-unwind" fn(instance: *mut c_void, buffer: *mut MeasurementBuffer, ctx: *const FfiTransformContext);
 pub type OutputWriteFn =
-    extern "C" fn(instance: *mut c_void, buffer: *const MeasurementBuffer, ctx: *const FfiOutputContext);
+    extern "C-unwind" fn(instance: *mut c_void, buffer: *const MeasurementBuffer, ctx: *const FfiOutputContext);
+pub type OutputLaggedFn = extern "C-unwind" fn(instance: *mut c_void, lost_buffers: u64);
+pub type NullableOutputLaggedFn = Option<extern "C-unwind" fn(instance: *mut c_void, lost_buffers: u64)>;
 
 // ====== OutputContext ======
 
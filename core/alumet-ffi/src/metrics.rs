@@ -11,7 +11,7 @@ use alumet::{
 };
 
 use super::{
-    FfiOutputContext,
+    FfiOutputContext, FfiTransformContext,
     resources::{FfiConsumerId, FfiResourceId},
     string::{AStr, AString},
     time::Timestamp,
@@ -25,6 +25,13 @@ pub extern "C" fn metric_name<'a>(metric: RawMetricId, ctx: &'a FfiOutputContext
     AStr::from(name)
 }
 
+#[unsafe(no_mangle)]
+pub extern "C" fn transform_metric_name<'a>(metric: RawMetricId, ctx: &'a FfiTransformContext) -> AStr<'a> {
+    let metrics: &MetricRegistry = unsafe { &*ctx.inner }.metrics;
+    let name: &str = &metrics.by_id(&metric).unwrap().name;
+    AStr::from(name)
+}
+
 // ====== MeasurementPoint ffi ======
 
 #[unsafe(no_mangle)]
@@ -158,6 +165,28 @@ pub extern "C" fn mpoint_consumer_id(point: &MeasurementPoint) -> AString {
     point.consumer.id_display().to_string().into()
 }
 
+// setters, for transforms
+
+#[unsafe(no_mangle)]
+pub extern "C" fn mpoint_set_metric(point: &mut MeasurementPoint, metric: RawMetricId) {
+    point.metric = metric;
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn mpoint_set_timestamp(point: &mut MeasurementPoint, timestamp: Timestamp) {
+    point.timestamp = timestamp.into();
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn mpoint_set_value_u64(point: &mut MeasurementPoint, value: u64) {
+    point.value = WrappedMeasurementValue::U64(value);
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn mpoint_set_value_f64(point: &mut MeasurementPoint, value: f64) {
+    point.value = WrappedMeasurementValue::F64(value);
+}
+
 #[repr(C)]
 #[allow(unused)]
 pub enum FfiMeasurementValue {
@@ -184,6 +213,8 @@ pub extern "C" fn mbuffer_reserve(buf: &mut MeasurementBuffer, additional: usize
 }
 
 pub type ForeachPointFn = unsafe extern "C" fn(*mut c_void, *const MeasurementPoint);
+pub type ForeachPointMutFn = unsafe extern "C" fn(*mut c_void, *mut MeasurementPoint);
+pub type RetainPointFn = unsafe extern "C" fn(*mut c_void, *const MeasurementPoint) -> bool;
 
 /// Iterates on a [`MeasurementBuffer`] by calling `f(data, point)` for each point of the buffer.
 #[unsafe(no_mangle)]
@@ -193,6 +224,33 @@ pub unsafe extern "C" fn mbuffer_foreach(buf: &MeasurementBuffer, data: *mut c_v
     }
 }
 
+/// Iterates on a [`MeasurementBuffer`] by calling `f(data, point)` for each point of the buffer.
+/// Unlike [`mbuffer_foreach`], `f` can modify the points, which is useful in transforms.
+///
+/// # Safety
+/// `f` must not keep the pointer to the point after it returns.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mbuffer_foreach_mut(buf: &mut MeasurementBuffer, data: *mut c_void, f: ForeachPointMutFn) {
+    for point in buf.iter_mut() {
+        unsafe { f(data, point) };
+    }
+}
+
+/// Removes the points of a [`MeasurementBuffer`] for which `f(data, point)` returns `false`.
+///
+/// # Safety
+/// `f` must not keep the pointer to the point after it returns.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mbuffer_retain(buf: &mut MeasurementBuffer, data: *mut c_void, f: RetainPointFn) {
+    buf.retain(|point| unsafe { f(data, point) });
+}
+
+/// Removes all the points of a [`MeasurementBuffer`].
+#[unsafe(no_mangle)]
+pub extern "C" fn mbuffer_clear(buf: &mut MeasurementBuffer) {
+    buf.clear();
+}
+
 /// Adds a measurement to the buffer.
 /// The point is consumed in the operation, you must **not** use it afterwards.
 #[unsafe(no_mangle)]
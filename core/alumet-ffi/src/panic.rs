@@ -0,0 +1,159 @@
+//! Containment of the panics that occur in the code of dynamic plugins.
+//!
+//! A panic must never unwind through a frame that does not support it, otherwise the agent aborts.
+//! Therefore:
+//! - the functions of the plugins are called through `extern "C-unwind"` pointers, inside [`catch_unwind`],
+//!   so that a plugin written in Rust can unwind back to the agent;
+//! - the functions exported to the plugins catch their own panics instead of unwinding into the plugin.
+//!
+//! When a panic is caught, the plugin is marked as failed: its sources, transforms and outputs stop
+//! (they return a fatal error to the pipeline), while the elements of the other plugins keep running.
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    panic::{AssertUnwindSafe, catch_unwind},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::anyhow;
+
+/// Health of a dynamic plugin, shared by the plugin and its pipeline elements.
+#[derive(Debug)]
+pub(crate) struct PluginHealth {
+    name: String,
+    failed: AtomicBool,
+}
+
+thread_local! {
+    /// The plugin that is being started on this thread, if any.
+    static STARTING_PLUGIN: RefCell<Option<Arc<PluginHealth>>> = const { RefCell::new(None) };
+}
+
+impl PluginHealth {
+    pub fn new(name: String) -> Arc<Self> {
+        Arc::new(Self {
+            name,
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    /// Returns the plugin that is being started on this thread.
+    ///
+    /// The elements registered by the plugin during its startup share its health.
+    pub fn starting() -> Arc<Self> {
+        STARTING_PLUGIN.with_borrow(|p| p.clone()).unwrap_or_else(|| {
+            log::warn!("Pipeline element registered outside of plugin_start, it will not be tied to its plugin.");
+            Self::new(String::from("unknown"))
+        })
+    }
+
+    /// Runs `f` with this plugin registered as the plugin that is being started on this thread.
+    pub fn while_starting<R>(self: &Arc<Self>, f: impl FnOnce() -> R) -> R {
+        let previous = STARTING_PLUGIN.replace(Some(self.clone()));
+        let res = f();
+        STARTING_PLUGIN.set(previous);
+        res
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Marks the plugin as failed. All its pipeline elements will stop.
+    pub fn fail(&self) {
+        if !self.failed.swap(true, Ordering::Relaxed) {
+            log::error!(
+                "Dynamic plugin {} has failed, its sources, transforms and outputs will be stopped. There is probably a bug in the plugin!",
+                self.name
+            );
+        }
+    }
+
+    /// Calls a function of the plugin, and catches the panics.
+    ///
+    /// If `f` panics, the plugin is marked as failed and an error is returned.
+    /// If the plugin has already failed, `f` is not called.
+    pub fn call<R>(&self, what: &str, f: impl FnOnce() -> R) -> anyhow::Result<R> {
+        if self.has_failed() {
+            return Err(anyhow!("plugin {} has failed, refusing to call {what}", self.name));
+        }
+        catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+            self.fail();
+            anyhow!("{what} of plugin {} panicked: {}", self.name, panic_message(payload))
+        })
+    }
+}
+
+/// Runs a function exported to the plugins, and prevents its panics from unwinding into the plugin.
+///
+/// If `f` panics, the plugin that is being started is marked as failed and `fallback` is returned.
+pub(crate) fn contain<R>(function: &str, fallback: R, f: impl FnOnce() -> R) -> R {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => res,
+        Err(payload) => {
+            log::error!("{function} panicked: {}", panic_message(payload));
+            STARTING_PLUGIN.with_borrow(|p| {
+                if let Some(plugin) = p {
+                    plugin.fail();
+                }
+            });
+            fallback
+        }
+    }
+}
+
+/// Extracts the message of a panic, and drops the payload.
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    let msg = if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        String::from("(no message)")
+    };
+    // dropping the payload may, in turn, panic
+    if let Err(panic2) = catch_unwind(AssertUnwindSafe(move || drop(payload))) {
+        std::mem::forget(panic2);
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PluginHealth, contain};
+
+    #[test]
+    fn call() {
+        let plugin = PluginHealth::new(String::from("test"));
+        assert_eq!(plugin.call("f", || 1).unwrap(), 1);
+        assert!(!plugin.has_failed());
+
+        let err = plugin.call("f", || -> u32 { panic!("oops") }).unwrap_err();
+        assert_eq!(err.to_string(), "f of plugin test panicked: oops");
+        assert!(plugin.has_failed());
+
+        // once failed, the plugin is not called anymore
+        let err = plugin.call("g", || 1).unwrap_err();
+        assert_eq!(err.to_string(), "plugin test has failed, refusing to call g");
+    }
+
+    #[test]
+    fn contain_marks_the_starting_plugin() {
+        let plugin = PluginHealth::new(String::from("test"));
+        let res = plugin.while_starting(|| {
+            assert_eq!(PluginHealth::starting().name(), "test");
+            contain("alumet_test", 0, || -> u32 { panic!("bad argument") })
+        });
+        assert_eq!(res, 0);
+        assert!(plugin.has_failed());
+        assert_eq!(PluginHealth::starting().name(), "unknown");
+    }
+}
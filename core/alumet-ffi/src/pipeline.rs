@@ -1,31 +1,48 @@
+use std::sync::Arc;
+
 use libc::c_void;
 
-use super::{DropFn, FfiOutputContext, FfiTransformContext, OutputWriteFn, SourcePollFn, TransformApplyFn};
+use super::panic::{PluginHealth, panic_message};
+use super::{
+    DropFn, FfiOutputContext, FfiTransformContext, OutputLaggedFn, OutputWriteFn, SourcePollFn, TransformApplyFn,
+};
 use alumet::{
     measurement::{MeasurementAccumulator, MeasurementBuffer},
+    metrics::online::MetricReader,
     pipeline::{
         self,
         elements::{
             error,
-            output::{self, error::WriteError},
+            output::{self, AsyncOutputStream, error::WriteError, interface::StreamRecvError},
             transform::{self, TransformError},
         },
     },
 };
+use futures::StreamExt;
 
 pub(crate) struct FfiSource {
+    pub plugin: Arc<PluginHealth>,
     pub data: *mut c_void,
     pub poll_fn: SourcePollFn,
     pub drop_fn: Option<DropFn>,
 }
 pub(crate) struct FfiTransform {
+    pub plugin: Arc<PluginHealth>,
     pub data: *mut c_void,
     pub apply_fn: TransformApplyFn,
     pub drop_fn: Option<DropFn>,
 }
 pub(crate) struct FfiOutput {
+    pub plugin: Arc<PluginHealth>,
+    pub data: *mut c_void,
+    pub write_fn: OutputWriteFn,
+    pub drop_fn: Option<DropFn>,
+}
+pub(crate) struct FfiAsyncOutput {
+    pub plugin: Arc<PluginHealth>,
     pub data: *mut c_void,
     pub write_fn: OutputWriteFn,
+    pub lagged_fn: Option<OutputLaggedFn>,
     pub drop_fn: Option<DropFn>,
 }
 // To be safely `Send`, sources/transforms/outputs may not use any thread-local storage,
@@ -34,6 +51,7 @@ pub(crate) struct FfiOutput {
 unsafe impl Send for FfiSource {}
 unsafe impl Send for FfiTransform {}
 unsafe impl Send for FfiOutput {}
+unsafe impl Send for FfiAsyncOutput {}
 
 impl pipeline::Source for FfiSource {
     fn poll(
@@ -41,8 +59,10 @@ impl pipeline::Source for FfiSource {
         into: &mut MeasurementAccumulator,
         time: alumet::measurement::Timestamp,
     ) -> Result<(), error::PollError> {
-        (self.poll_fn)(self.data, into, time.into());
-        Ok(())
+        let (poll, data) = (self.poll_fn, self.data);
+        self.plugin
+            .call("source_poll_fn", || poll(data, into, time.into()))
+            .map_err(error::PollError::Fatal)
     }
 }
 impl pipeline::Transform for FfiTransform {
@@ -52,36 +72,83 @@ impl pipeline::Transform for FfiTransform {
         ctx: &transform::TransformContext,
     ) -> Result<(), TransformError> {
         let ffi_ctx = FfiTransformContext { inner: ctx };
-        (self.apply_fn)(self.data, measurements, &ffi_ctx);
-        Ok(())
+        let (apply, data) = (self.apply_fn, self.data);
+        self.plugin
+            .call("transform_apply_fn", || apply(data, measurements, &ffi_ctx))
+            .map_err(TransformError::Fatal)
     }
 }
 impl pipeline::Output for FfiOutput {
     fn write(&mut self, measurements: &MeasurementBuffer, ctx: &output::OutputContext) -> Result<(), WriteError> {
         let ffi_ctx = FfiOutputContext { inner: ctx };
-        (self.write_fn)(self.data, measurements, &ffi_ctx);
+        let (write, data) = (self.write_fn, self.data);
+        self.plugin
+            .call("output_write_fn", || write(data, measurements, &ffi_ctx))
+            .map_err(WriteError::Fatal)
+    }
+}
+
+impl FfiAsyncOutput {
+    /// Calls the foreign callbacks for each item of the measurement stream, until the stream ends
+    /// or the plugin fails.
+    pub async fn run(self, mut stream: AsyncOutputStream, metrics: MetricReader) -> anyhow::Result<()> {
+        while let Some(received) = stream.0.next().await {
+            match received {
+                Ok(measurements) => {
+                    let registry = metrics.read().await;
+                    let ctx = output::OutputContext { metrics: &registry };
+                    let ffi_ctx = FfiOutputContext { inner: &ctx };
+                    // The foreign code may block: tell the runtime to move the other tasks to other threads.
+                    tokio::task::block_in_place(|| {
+                        self.plugin.call("output_write_fn", || {
+                            (self.write_fn)(self.data, &measurements, &ffi_ctx)
+                        })
+                    })?;
+                }
+                Err(StreamRecvError::Lagged(n)) => match self.lagged_fn {
+                    Some(lagged) => self.plugin.call("output_lagged_fn", || lagged(self.data, n))?,
+                    None => log::warn!("{n} measurement buffers were lost because this output was too slow!"),
+                },
+                Err(e) => log::error!("unexpected error in async output: {e:?}"),
+            }
+        }
         Ok(())
     }
 }
 
 impl Drop for FfiSource {
     fn drop(&mut self) {
-        if let Some(drop) = self.drop_fn {
-            unsafe { drop(self.data) };
-        }
+        drop_element(&self.plugin, self.drop_fn, self.data);
     }
 }
 impl Drop for FfiTransform {
     fn drop(&mut self) {
-        if let Some(drop) = self.drop_fn {
-            unsafe { drop(self.data) };
-        }
+        drop_element(&self.plugin, self.drop_fn, self.data);
     }
 }
 impl Drop for FfiOutput {
     fn drop(&mut self) {
-        if let Some(drop) = self.drop_fn {
-            unsafe { drop(self.data) };
-        }
+        drop_element(&self.plugin, self.drop_fn, self.data);
+    }
+}
+impl Drop for FfiAsyncOutput {
+    fn drop(&mut self) {
+        drop_element(&self.plugin, self.drop_fn, self.data);
+    }
+}
+
+/// Calls the drop function of an element, if any.
+///
+/// It is called even if the plugin has failed, to give it a chance to free its resources.
+fn drop_element(plugin: &PluginHealth, drop_fn: Option<DropFn>, data: *mut c_void) {
+    if let Some(drop) = drop_fn
+        && let Err(payload) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| unsafe { drop(data) }))
+    {
+        log::error!(
+            "drop function of plugin {} panicked: {}",
+            plugin.name(),
+            panic_message(payload)
+        );
+        plugin.fail();
     }
 }
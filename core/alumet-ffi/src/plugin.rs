@@ -7,11 +7,17 @@ use alumet::metrics::def::RawMetricId;
 use alumet::pipeline::elements::source::trigger;
 use alumet::{plugin::AlumetPluginStart, units::Unit};
 
-use super::pipeline::{FfiOutput, FfiTransform};
+use super::panic::{PluginHealth, contain};
+use super::pipeline::{FfiAsyncOutput, FfiOutput, FfiTransform};
 use super::time::TimeDuration;
 use super::units::FfiUnit;
 use super::{NullableDropFn, SourcePollFn, pipeline::FfiSource, string::AStr};
-use super::{OutputWriteFn, TransformApplyFn};
+use super::{NullableOutputLaggedFn, OutputWriteFn, TransformApplyFn};
+
+/// Metric id returned when the creation of a metric fails.
+///
+/// The plugin is then marked as failed, and its startup is aborted.
+const INVALID_METRIC_ID: u64 = u64::MAX;
 
 #[unsafe(no_mangle)]
 pub extern "C" fn alumet_create_metric(
@@ -22,12 +28,14 @@ pub extern "C" fn alumet_create_metric(
     description: AStr,
 ) -> RawMetricId {
     // todo handle errors (how to pass them to FFI properly?)
-    let name = (&name).into();
-    let description = (&description).into();
-    let unit = Unit::from(unit);
-    alumet
-        .create_metric_untyped(name, value_type, unit, description)
-        .unwrap()
+    contain("alumet_create_metric", RawMetricId::from_u64(INVALID_METRIC_ID), || {
+        let name = (&name).into();
+        let description = (&description).into();
+        let unit = Unit::from(unit);
+        alumet
+            .create_metric_untyped(name, value_type, unit, description)
+            .unwrap()
+    })
 }
 
 #[unsafe(no_mangle)]
@@ -39,12 +47,18 @@ pub unsafe extern "C" fn alumet_create_metric_c(
     description: *const c_char,
 ) -> RawMetricId {
     // todo handle errors (how to pass them to C properly?)
-    let name = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
-    let description = unsafe { CStr::from_ptr(description) }.to_str().unwrap();
-    let unit = Unit::from(unit);
-    alumet
-        .create_metric_untyped(name, value_type, unit, description)
-        .unwrap()
+    contain(
+        "alumet_create_metric_c",
+        RawMetricId::from_u64(INVALID_METRIC_ID),
+        || {
+            let name = unsafe { CStr::from_ptr(name) }.to_str().unwrap();
+            let description = unsafe { CStr::from_ptr(description) }.to_str().unwrap();
+            let unit = Unit::from(unit);
+            alumet
+                .create_metric_untyped(name, value_type, unit, description)
+                .unwrap()
+        },
+    )
 }
 
 #[unsafe(no_mangle)]
@@ -57,20 +71,23 @@ pub extern "C" fn alumet_add_source(
     source_drop_fn: NullableDropFn,
 ) {
     let source = Box::new(FfiSource {
+        plugin: PluginHealth::starting(),
         data: source_data,
         poll_fn: source_poll_fn,
         drop_fn: source_drop_fn,
     });
-    alumet
-        .add_source(
-            "fixme", // TODO update the API to ask for a name or generate one
-            source,
-            trigger::builder::time_interval(poll_interval.into())
-                .flush_interval(flush_interval.into())
-                .build()
-                .unwrap(),
-        )
-        .expect("FIXME: the C API only supports one source per plugin for the moment");
+    contain("alumet_add_source", (), || {
+        alumet
+            .add_source(
+                "fixme", // TODO update the API to ask for a name or generate one
+                source,
+                trigger::builder::time_interval(poll_interval.into())
+                    .flush_interval(flush_interval.into())
+                    .build()
+                    .unwrap(),
+            )
+            .expect("FIXME: the C API only supports one source per plugin for the moment");
+    })
 }
 
 #[unsafe(no_mangle)]
@@ -81,13 +98,16 @@ pub extern "C" fn alumet_add_transform(
     transform_drop_fn: NullableDropFn,
 ) {
     let transform = Box::new(FfiTransform {
+        plugin: PluginHealth::starting(),
         data: transform_data,
         apply_fn: transform_apply_fn,
         drop_fn: transform_drop_fn,
     });
-    alumet
-        .add_transform("fixme", transform)
-        .expect("FIXME: the C API only supports one transform per plugin for the moment");
+    contain("alumet_add_transform", (), || {
+        alumet
+            .add_transform("fixme", transform)
+            .expect("FIXME: the C API only supports one transform per plugin for the moment");
+    })
 }
 
 #[unsafe(no_mangle)]
@@ -98,11 +118,44 @@ pub extern "C" fn alumet_add_output(
     output_drop_fn: NullableDropFn,
 ) {
     let output = Box::new(FfiOutput {
+        plugin: PluginHealth::starting(),
         data: output_data,
         write_fn: output_write_fn,
         drop_fn: output_drop_fn,
     });
-    alumet
-        .add_blocking_output("fixme", output)
-        .expect("FIXME: the C API only supports one output per plugin for the moment");
+    contain("alumet_add_output", (), || {
+        alumet
+            .add_blocking_output("fixme", output)
+            .expect("FIXME: the C API only supports one output per plugin for the moment");
+    })
+}
+
+/// Registers an async output, which runs on the async runtime of the pipeline instead of a dedicated thread.
+///
+/// `output_write_fn` is called for each buffer of the measurement stream.
+/// If the output is too slow, some buffers are lost: `output_lagged_fn` is then called with the number
+/// of lost buffers, if it is not null.
+#[unsafe(no_mangle)]
+pub extern "C" fn alumet_add_async_output(
+    alumet: &mut AlumetPluginStart,
+    output_data: *mut c_void,
+    output_write_fn: OutputWriteFn,
+    output_lagged_fn: NullableOutputLaggedFn,
+    output_drop_fn: NullableDropFn,
+) {
+    let output = FfiAsyncOutput {
+        plugin: PluginHealth::starting(),
+        data: output_data,
+        write_fn: output_write_fn,
+        lagged_fn: output_lagged_fn,
+        drop_fn: output_drop_fn,
+    };
+    contain("alumet_add_async_output", (), || {
+        alumet
+            .add_async_output_builder("fixme_async", move |ctx, stream| {
+                let metrics = ctx.metrics_reader();
+                Ok(Box::pin(output.run(stream, metrics)))
+            })
+            .expect("FIXME: the C API only supports one async output per plugin for the moment");
+    })
 }
@@ -0,0 +1,207 @@
+//! Verification of the shared libraries of dynamic plugins, before loading them.
+//!
+//! Loading a shared library runs its code, with the privileges of the agent. To only load vetted plugins,
+//! a [`TrustPolicy`] accepts a library if:
+//! - its SHA-256 checksum is in the allowlist, or
+//! - it is signed by a trusted ed25519 key: the signature (64 raw bytes) is stored next to the library,
+//!   in a file with the same name and the additional extension `.sig`, for instance `plugin.so.sig`.
+//!
+//! The file is read once, and the checks are applied to its content. Since the library is loaded
+//! from its path afterwards, the plugins directory must only be writable by trusted users.
+
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, anyhow};
+use ring::{
+    digest::{SHA256, digest},
+    signature::{ED25519, UnparsedPublicKey},
+};
+
+/// Rules that decide whether a shared library can be loaded.
+#[derive(Default)]
+pub struct TrustPolicy {
+    /// SHA-256 checksums of the allowed libraries.
+    checksums: HashSet<[u8; 32]>,
+    /// ed25519 public keys of the trusted signers.
+    keys: Vec<[u8; 32]>,
+}
+
+/// A shared library that does not comply with the [`TrustPolicy`].
+#[derive(Debug)]
+pub enum TrustError {
+    /// The library cannot be read.
+    Io(PathBuf, std::io::Error),
+    /// The library is neither allowed by its checksum, nor signed by a trusted key.
+    Untrusted { file: PathBuf, sha256: String },
+}
+
+impl TrustPolicy {
+    /// Creates a policy that trusts nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the library whose SHA-256 checksum is `sha256`, in hexadecimal.
+    pub fn allow_checksum(&mut self, sha256: &str) -> anyhow::Result<()> {
+        let checksum = decode_hex::<32>(sha256).with_context(|| format!("invalid SHA-256 checksum {sha256}"))?;
+        self.checksums.insert(checksum);
+        Ok(())
+    }
+
+    /// Allows the libraries listed in an allowlist, in the format of `sha256sum`.
+    ///
+    /// Each line contains a checksum, followed by the name of the file, which is only informative.
+    /// Empty lines and lines that begin with `#` are ignored.
+    pub fn allow_checksums_from(&mut self, allowlist: &str) -> anyhow::Result<()> {
+        for (i, line) in allowlist.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let checksum = line.split_whitespace().next().unwrap_or_default();
+            self.allow_checksum(checksum)
+                .with_context(|| format!("invalid allowlist entry at line {}", i + 1))?;
+        }
+        Ok(())
+    }
+
+    /// Trusts the libraries signed by the ed25519 public key `public_key`, in hexadecimal.
+    pub fn trust_key(&mut self, public_key: &str) -> anyhow::Result<()> {
+        let key = decode_hex::<32>(public_key).with_context(|| format!("invalid ed25519 public key {public_key}"))?;
+        self.keys.push(key);
+        Ok(())
+    }
+
+    /// Checks that the library `file` can be loaded.
+    pub fn verify(&self, file: &Path) -> Result<(), TrustError> {
+        let content = std::fs::read(file).map_err(|e| TrustError::Io(file.to_owned(), e))?;
+        let checksum = digest(&SHA256, &content);
+        if self.checksums.contains(checksum.as_ref()) {
+            log::debug!("{} is allowed by its checksum", file.display());
+            return Ok(());
+        }
+        if !self.keys.is_empty() {
+            let mut sig_file = file.as_os_str().to_owned();
+            sig_file.push(".sig");
+            match std::fs::read(&sig_file) {
+                Ok(signature) => {
+                    let signed = self.keys.iter().any(|key| {
+                        UnparsedPublicKey::new(&ED25519, key)
+                            .verify(&content, &signature)
+                            .is_ok()
+                    });
+                    if signed {
+                        log::debug!("{} is signed by a trusted key", file.display());
+                        return Ok(());
+                    }
+                    log::warn!("{} is not signed by a trusted key", file.display());
+                }
+                Err(e) => log::debug!("no signature for {}: {e}", file.display()),
+            }
+        }
+        Err(TrustError::Untrusted {
+            file: file.to_owned(),
+            sha256: encode_hex(checksum.as_ref()),
+        })
+    }
+}
+
+impl std::error::Error for TrustError {}
+impl Display for TrustError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrustError::Io(file, err) => write!(f, "failed to read {}: {err}", file.display()),
+            TrustError::Untrusted { file, sha256 } => write!(
+                f,
+                "{} is not trusted: its checksum {sha256} is not allowed, and it is not signed by a trusted key",
+                file.display()
+            ),
+        }
+    }
+}
+
+fn decode_hex<const N: usize>(hex: &str) -> anyhow::Result<[u8; N]> {
+    if !hex.is_ascii() || hex.len() != N * 2 {
+        return Err(anyhow!("expected {} hexadecimal digits, got {}", N * 2, hex.len()));
+    }
+    let mut res = [0; N];
+    for (i, byte) in res.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)?;
+    }
+    Ok(res)
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::{
+        rand::SystemRandom,
+        signature::{Ed25519KeyPair, KeyPair},
+    };
+
+    use super::{TrustError, TrustPolicy, encode_hex};
+
+    #[test]
+    fn checksum_allowlist() {
+        let dir = std::env::temp_dir().join("alumet-test-trust-checksum");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("plugin.so");
+        std::fs::write(&file, "hello").unwrap();
+
+        let mut policy = TrustPolicy::new();
+        assert!(matches!(policy.verify(&file), Err(TrustError::Untrusted { .. })));
+
+        // sha256("hello")
+        let allowlist = "
+            # vetted plugins
+            2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  plugin.so
+        ";
+        policy.allow_checksums_from(allowlist).unwrap();
+        policy.verify(&file).unwrap();
+
+        std::fs::write(&file, "hello, modified").unwrap();
+        assert!(matches!(policy.verify(&file), Err(TrustError::Untrusted { .. })));
+
+        assert!(policy.allow_checksum("2cf24dba").is_err());
+        assert!(policy.allow_checksums_from("not-hex  plugin.so").is_err());
+    }
+
+    #[test]
+    fn signature() {
+        let dir = std::env::temp_dir().join("alumet-test-trust-signature");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("plugin.so");
+        let sig_file = dir.join("plugin.so.sig");
+        std::fs::write(&file, "hello").unwrap();
+
+        let rng = SystemRandom::new();
+        let gen_key = || Ed25519KeyPair::from_pkcs8(Ed25519KeyPair::generate_pkcs8(&rng).unwrap().as_ref()).unwrap();
+        let trusted = gen_key();
+        let other = gen_key();
+        let mut policy = TrustPolicy::new();
+        policy.trust_key(&encode_hex(trusted.public_key().as_ref())).unwrap();
+
+        // no signature
+        let _ = std::fs::remove_file(&sig_file);
+        assert!(policy.verify(&file).is_err());
+
+        // signed by an unknown key
+        std::fs::write(&sig_file, other.sign(b"hello")).unwrap();
+        assert!(policy.verify(&file).is_err());
+
+        // signed by the trusted key
+        std::fs::write(&sig_file, trusted.sign(b"hello")).unwrap();
+        policy.verify(&file).unwrap();
+
+        // the signature does not match the content
+        std::fs::write(&file, "hello, modified").unwrap();
+        assert!(policy.verify(&file).is_err());
+    }
+}
@@ -0,0 +1,59 @@
+//! End-to-end benchmarks of the measurement pipeline.
+//!
+//! A synthetic source produces N metrics × M resources on every poll, the points go through the
+//! transforms and are counted by a null output. The throughput is reported in points per second.
+//!
+//! Run with `cargo bench -p alumet --features test`.
+
+use std::time::{Duration, Instant};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    test::load::SyntheticLoad,
+};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Poll as fast as possible, so that the pipeline is the bottleneck.
+const FREQUENCY: f64 = 100_000.0;
+
+/// Limits the number of buffers waiting in the pipeline, because the source is faster than the pipeline.
+const SOURCE_CHANNEL_SIZE: usize = 64;
+
+fn end_to_end(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pipeline");
+    for (metrics, resources, transforms) in [(1, 1, 0), (10, 100, 0), (10, 100, 4)] {
+        let load = SyntheticLoad {
+            metrics,
+            resources,
+            frequency: FREQUENCY,
+            transforms,
+        };
+        let (plugin, counter) = load.plugin();
+        let agent = agent::Builder::new(PluginSet::from(vec![plugin]))
+            .before_operation_begin(|pipeline| *pipeline.source_channel_size() = SOURCE_CHANNEL_SIZE)
+            .build_and_start()
+            .expect("the agent should start");
+
+        let points_per_poll = load.points_per_poll() as u64;
+        group.throughput(Throughput::Elements(points_per_poll));
+        let id = BenchmarkId::new("end_to_end", format!("{metrics}x{resources}/{transforms}_transforms"));
+        group.bench_function(id, |b| {
+            b.iter_custom(|polls| {
+                // Measure the time it takes for `polls` polls to reach the output.
+                let target = counter.get() + polls * points_per_poll;
+                let t0 = Instant::now();
+                assert!(counter.wait_for(target, TIMEOUT), "the pipeline is stuck");
+                t0.elapsed()
+            })
+        });
+
+        agent.pipeline.control_handle().shutdown();
+        agent.wait_for_shutdown(TIMEOUT).expect("the agent should stop");
+    }
+    group.finish();
+}
+
+criterion_group!(benches, end_to_end);
+criterion_main!(benches);
@@ -4,24 +4,43 @@ use std::{collections::HashMap, ops::DerefMut, time::Duration};
 
 use anyhow::{Context, anyhow};
 use thiserror::Error;
+use tokio::sync::mpsc;
 
 use crate::agent::plugin::PluginInfo;
+use crate::metrics::duplicate::DuplicateReaction;
+use crate::pipeline::control::messages::{PluginBody, PluginRequestMessage, RequestMessage};
+use crate::pipeline::control::request::{self, ElementListFilter};
 use crate::pipeline::error::PipelineError;
+use crate::pipeline::naming::ElementKind;
 use crate::plugin::phases::PreStartAction;
+use crate::plugin::rust::InvalidConfig;
 use crate::plugin::{AlumetPluginStart, AlumetPostStart, ConfigTable, Plugin};
+use crate::plugin::{migration, schema};
 use crate::{
     pipeline::{self, naming::PluginName},
     plugin::{AlumetPreStart, phases::PostStartAction},
 };
 
-use super::plugin::PluginSet;
+use super::plugin::{DependencyError, PluginSet};
+use super::timeout::{HookTimeout, PluginTimeouts, Watchdog, run_detached};
 
 /// An Agent that has been started.
 pub struct RunningAgent {
     pub pipeline: pipeline::MeasurementPipeline,
     pub initialized_plugins: Vec<Box<dyn Plugin>>,
+    /// Plugins that have been disabled while the agent is running, see [`RunningAgent::disable_plugin`].
+    disabled_plugins: Vec<Box<dyn Plugin>>,
+    /// Requests to enable or disable a plugin, received by the pipeline.
+    plugin_requests: mpsc::Receiver<PluginRequestMessage>,
+    /// Names of the metric listeners, as `(plugin, listener)` pairs.
+    /// They keep running when their plugin is disabled.
+    metric_listeners: Vec<(String, String)>,
+    timeouts: PluginTimeouts,
 }
 
+/// Maximum duration of the control requests sent by the agent to the pipeline.
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Agent builder.
 ///
 /// Refer to the [documentation of the parent module](super) for an example.
@@ -34,6 +53,9 @@ pub struct Builder {
 
     /// Functions called during the agent startup.
     callbacks: Callbacks,
+
+    /// Maximum duration of the lifecycle hooks of the plugins.
+    timeouts: PluginTimeouts,
 }
 
 struct Callbacks {
@@ -43,6 +65,179 @@ struct Callbacks {
     after_operation_begin: Box<dyn FnOnce(&mut pipeline::MeasurementPipeline)>,
 }
 
+/// Initializes one plugin.
+///
+/// Returns the initialized plugin, or an error. If `init` does not complete within `timeout`,
+/// the error is a [`HookTimeout`].
+fn init_plugin(p: PluginInfo, timeout: Duration) -> anyhow::Result<Box<dyn Plugin>> {
+    let name = p.metadata.name;
+    let version = p.metadata.version;
+    let config = match p.config {
+        Some(mut config) => {
+            // upgrade the config provided by the user, if it is outdated
+            if let Some((old, new)) = migration::migrate(&mut config, &p.metadata.config_migrations)
+                .with_context(|| format!("failed to migrate the config of plugin {name} v{version}"))?
+            {
+                log::warn!(
+                    "The config of plugin {name} is outdated (version {old}), it has been migrated to version {new}. Use --migrate-config to update the config file."
+                );
+            }
+            migration::take_version(&mut config).context(InvalidConfig)?;
+
+            // check the config
+            let config = ConfigTable(config);
+            let schema = (p.metadata.config_schema)()
+                .with_context(|| format!("failed to generate config schema of plugin {name} v{version}"))?;
+            if let Some(schema) = schema {
+                schema::validate(&schema, &config, &format!("plugins.{name}")).context(InvalidConfig)?;
+            }
+            Some(config)
+        }
+        None => {
+            // no config has been provided for this plugin, use its default config
+            (p.metadata.default_config)()
+                .with_context(|| format!("failed to generate default config of plugin {name} v{version}"))?
+        }
+    };
+    let config = config.unwrap_or_default();
+    log::debug!("Initializing plugin {name} v{version} with config {config:?}...");
+
+    // call init
+    let init = p.metadata.init;
+    let initialized = run_detached(&name, "init", timeout, move || init(config))?
+        .with_context(|| format!("plugin failed to initialize: {} v{}", name, version))?;
+
+    // check that the plugin corresponds to its metadata
+    if (initialized.name(), initialized.version()) != (&name, &version) {
+        return Err(anyhow!(
+            "invalid plugin: metadata is '{name}' v{version} but the plugin's methods return '{name}' v{version}"
+        ));
+    }
+    Ok(initialized)
+}
+
+/// Starts a plugin, i.e. calls [`Plugin::start`] with the right context.
+fn start_plugin(
+    p: &mut dyn Plugin,
+    pipeline_builder: &mut pipeline::Builder,
+    pre_start_actions: &mut Vec<(PluginName, Box<dyn PreStartAction>)>,
+    post_start_actions: &mut Vec<(PluginName, Box<dyn PostStartAction>)>,
+) -> anyhow::Result<()> {
+    let name = p.name().to_owned();
+    let version = p.version().to_owned();
+    log::debug!("Starting plugin {name} v{version}...");
+
+    let mut ctx = AlumetPluginStart {
+        current_plugin: PluginName(name.clone()),
+        pipeline_builder,
+        pre_start_actions,
+        post_start_actions,
+    };
+    p.start(&mut ctx)
+        .with_context(|| format!("plugin failed to start: {name} v{version}"))
+}
+
+/// Executes the pre-pipeline-start phase of a plugin, i.e. calls [`Plugin::pre_pipeline_start`] with the right context.
+fn pre_pipeline_start(
+    p: &mut dyn Plugin,
+    pipeline_builder: &mut pipeline::Builder,
+    actions: &mut HashMap<PluginName, Vec<Box<dyn PreStartAction>>>,
+) -> anyhow::Result<()> {
+    let name = p.name().to_owned();
+    let version = p.version().to_owned();
+    log::debug!("Running pre-pipeline-start hook for plugin {name} v{version}...");
+
+    // Prepare the context.
+    let pname = PluginName(name.clone());
+    let mut ctx = AlumetPreStart {
+        current_plugin: pname.clone(),
+        pipeline_builder,
+    };
+
+    // Call pre_pipeline_start.
+    p.pre_pipeline_start(&mut ctx)
+        .with_context(|| format!("plugin pre_pipeline_start failed: {} v{}", p.name(), p.version()))?;
+
+    // Run the additional actions registered by the plugin, if any.
+    if let Some(actions) = actions.remove(&pname) {
+        for f in actions {
+            (f)(&mut ctx).with_context(|| format!("plugin post-pipeline-start action failed: {name} v{version}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Executes the post-pipeline-start phase of a plugin, i.e. calls [`Plugin::post_pipeline_start`] with the right context.
+///
+/// Plugins can also register post-pipeline-start actions in the form of closures, we run these too.
+fn post_pipeline_start(
+    p: &mut dyn Plugin,
+    pipeline: &mut pipeline::MeasurementPipeline,
+    actions: &mut HashMap<PluginName, Vec<Box<dyn PostStartAction>>>,
+) -> anyhow::Result<()> {
+    let name = p.name().to_owned();
+    let version = p.version().to_owned();
+    log::debug!("Running post-pipeline-start hook for plugin {name} v{version}...");
+
+    // Prepare the context.
+    let pname = PluginName(name.clone());
+    let mut ctx = AlumetPostStart {
+        current_plugin: pname.clone(),
+        pipeline,
+    };
+
+    // Call post_pipeline_start.
+    p.post_pipeline_start(&mut ctx)
+        .with_context(|| format!("plugin post_pipeline_start method failed: {name} v{version}"))?;
+
+    // Run the additional actions registered by the plugin, if any.
+    if let Some(actions) = actions.remove(&pname) {
+        for f in actions {
+            (f)(&mut ctx).with_context(|| format!("plugin post-pipeline-start action failed: {name} v{version}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Groups all pre or post-start actions by plugin.
+fn group_plugin_actions<BoxedAction>(
+    post_start_actions: Vec<(PluginName, BoxedAction)>,
+    n_plugins: usize,
+) -> HashMap<PluginName, Vec<BoxedAction>> {
+    let mut res = HashMap::with_capacity(n_plugins);
+    for (plugin, action) in post_start_actions {
+        let plugin_actions: &mut Vec<_> = res.entry(plugin).or_default();
+        plugin_actions.push(action);
+    }
+    res
+}
+
+/// The enabled plugins cannot be started with their configuration, see [`Builder::check`].
+#[derive(Debug, Error)]
+pub enum CheckError {
+    /// The dependencies of the enabled plugins cannot be satisfied.
+    Dependencies(#[from] DependencyError),
+    /// The configuration of some plugins is invalid.
+    ///
+    /// Contains the name of each plugin that failed the check, and the corresponding error.
+    InvalidConfig(Vec<(String, anyhow::Error)>),
+}
+
+impl std::fmt::Display for CheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckError::Dependencies(e) => write!(f, "invalid plugin dependencies: {e}"),
+            CheckError::InvalidConfig(errors) => {
+                write!(f, "invalid configuration for {} plugin(s):", errors.len())?;
+                for (plugin, error) in errors {
+                    write!(f, "\n- {plugin}: {error:#}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 /// An error was detected while shutting the agent down.
 ///
 /// See also [`PipelineError`].
@@ -96,9 +291,18 @@ impl Builder {
             plugins,
             pipeline_builder,
             callbacks: Callbacks::default(),
+            timeouts: PluginTimeouts::default(),
         }
     }
 
+    /// Sets the maximum duration of the lifecycle hooks of the plugins.
+    ///
+    /// See the [`timeout`](super::timeout) module.
+    pub fn plugin_timeouts(mut self, timeouts: PluginTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
     /// Sets a function to run after the plugins have been initialized.
     ///
     /// There can be only one callback. If this function is called more than once,
@@ -135,144 +339,53 @@ impl Builder {
         self
     }
 
-    /// Builds and starts the underlying measurement pipeline and the enabled plugins.
-    pub fn build_and_start(self) -> anyhow::Result<RunningAgent> {
-        /// Initializes one plugin.
-        ///
-        /// Returns the initialized plugin, or an error.
-        fn init_plugin(p: PluginInfo) -> anyhow::Result<Box<dyn Plugin>> {
-            let name = p.metadata.name;
-            let version = p.metadata.version;
-            let config = match p.config {
-                Some(config) => Some(ConfigTable(config)),
-                None => {
-                    // no config has been provided for this plugin, use its default config
-                    (p.metadata.default_config)()
-                        .with_context(|| format!("failed to generate default config of plugin {name} v{version}"))?
-                }
-            };
-            let config = config.unwrap_or_default();
-            log::debug!("Initializing plugin {name} v{version} with config {config:?}...");
-
-            // call init
-            let initialized = (p.metadata.init)(config)
-                .with_context(|| format!("plugin failed to initialize: {} v{}", name, version))?;
-
-            // check that the plugin corresponds to its metadata
-            if (initialized.name(), initialized.version()) != (&name, &version) {
-                return Err(anyhow!(
-                    "invalid plugin: metadata is '{name}' v{version} but the plugin's methods return '{name}' v{version}"
-                ));
-            }
-            Ok(initialized)
-        }
-
-        /// Starts a plugin, i.e. calls [`Plugin::start`] with the right context.
-        fn start_plugin(
-            p: &mut dyn Plugin,
-            pipeline_builder: &mut pipeline::Builder,
-            pre_start_actions: &mut Vec<(PluginName, Box<dyn PreStartAction>)>,
-            post_start_actions: &mut Vec<(PluginName, Box<dyn PostStartAction>)>,
-        ) -> anyhow::Result<()> {
-            let name = p.name().to_owned();
-            let version = p.version().to_owned();
-            log::debug!("Starting plugin {name} v{version}...");
-
-            let mut ctx = AlumetPluginStart {
-                current_plugin: PluginName(name.clone()),
-                pipeline_builder,
-                pre_start_actions,
-                post_start_actions,
-            };
-            p.start(&mut ctx)
-                .with_context(|| format!("plugin failed to start: {name} v{version}"))
-        }
-
-        /// Executes the pre-pipeline-start phase of a plugin, i.e. calls [`Plugin::pre_pipeline_start`] with the right context.
-        fn pre_pipeline_start(
-            p: &mut dyn Plugin,
-            pipeline_builder: &mut pipeline::Builder,
-            actions: &mut HashMap<PluginName, Vec<Box<dyn PreStartAction>>>,
-        ) -> anyhow::Result<()> {
-            let name = p.name().to_owned();
-            let version = p.version().to_owned();
-            log::debug!("Running pre-pipeline-start hook for plugin {name} v{version}...");
-
-            // Prepare the context.
-            let pname = PluginName(name.clone());
-            let mut ctx = AlumetPreStart {
-                current_plugin: pname.clone(),
-                pipeline_builder,
-            };
-
-            // Call pre_pipeline_start.
-            p.pre_pipeline_start(&mut ctx)
-                .with_context(|| format!("plugin pre_pipeline_start failed: {} v{}", p.name(), p.version()))?;
-
-            // Run the additional actions registered by the plugin, if any.
-            if let Some(actions) = actions.remove(&pname) {
-                for f in actions {
-                    (f)(&mut ctx)
-                        .with_context(|| format!("plugin post-pipeline-start action failed: {name} v{version}"))?;
-                }
-            }
-            Ok(())
-        }
-
-        /// Executes the post-pipeline-start phase of a plugin, i.e. calls [`Plugin::post_pipeline_start`] with the right context.
-        ///
-        /// Plugins can also register post-pipeline-start actions in the form of closures, we run these too.
-        fn post_pipeline_start(
-            p: &mut dyn Plugin,
-            pipeline: &mut pipeline::MeasurementPipeline,
-            actions: &mut HashMap<PluginName, Vec<Box<dyn PostStartAction>>>,
-        ) -> anyhow::Result<()> {
-            let name = p.name().to_owned();
-            let version = p.version().to_owned();
-            log::debug!("Running post-pipeline-start hook for plugin {name} v{version}...");
-
-            // Prepare the context.
-            let pname = PluginName(name.clone());
-            let mut ctx = AlumetPostStart {
-                current_plugin: pname.clone(),
-                pipeline,
-            };
-
-            // Call post_pipeline_start.
-            p.post_pipeline_start(&mut ctx)
-                .with_context(|| format!("plugin post_pipeline_start method failed: {name} v{version}"))?;
-
-            // Run the additional actions registered by the plugin, if any.
-            if let Some(actions) = actions.remove(&pname) {
-                for f in actions {
-                    (f)(&mut ctx)
-                        .with_context(|| format!("plugin post-pipeline-start action failed: {name} v{version}"))?;
-                }
-            }
+    /// Checks the dependencies and the configuration of the enabled plugins, without starting anything.
+    ///
+    /// The dependencies are checked first, like in [`build_and_start`](Self::build_and_start).
+    /// Then, each enabled plugin is initialized with its configuration, and dropped.
+    /// The plugins are not started and the measurement pipeline is not built.
+    ///
+    /// Unlike `build_and_start`, this method does not stop at the first invalid configuration: every plugin
+    /// is checked, and all the errors are returned.
+    pub fn check(self) -> Result<(), CheckError> {
+        let mut plugins = self.plugins;
+        plugins.sort_by_dependencies()?;
+        let (enabled_plugins, _disabled_plugins) = plugins.into_partition();
+        let timeout = self.timeouts.init;
+        let errors: Vec<_> = enabled_plugins
+            .into_iter()
+            .filter_map(|p| {
+                let name = p.metadata.name.clone();
+                log::debug!("Checking the config of plugin {name}...");
+                init_plugin(p, timeout).err().map(|e| (name, e))
+            })
+            .collect();
+        if errors.is_empty() {
             Ok(())
+        } else {
+            Err(CheckError::InvalidConfig(errors))
         }
+    }
 
-        /// Groups all pre or post-start actions by plugin.
-        fn group_plugin_actions<BoxedAction>(
-            post_start_actions: Vec<(PluginName, BoxedAction)>,
-            n_plugins: usize,
-        ) -> HashMap<PluginName, Vec<BoxedAction>> {
-            let mut res = HashMap::with_capacity(n_plugins);
-            for (plugin, action) in post_start_actions {
-                let plugin_actions: &mut Vec<_> = res.entry(plugin).or_default();
-                plugin_actions.push(action);
-            }
-            res
-        }
-
-        // Find which plugins are enabled.
+    /// Builds and starts the underlying measurement pipeline and the enabled plugins.
+    pub fn build_and_start(self) -> anyhow::Result<RunningAgent> {
+        // Find which plugins are enabled, and initialize them after their dependencies.
         log::info!("Initializing the plugins...");
-        let (enabled_plugins, disabled_plugins): (Vec<PluginInfo>, Vec<PluginInfo>) = self.plugins.into_partition();
+        let mut plugins = self.plugins;
+        plugins.sort_by_dependencies()?;
+        let (enabled_plugins, disabled_plugins): (Vec<PluginInfo>, Vec<PluginInfo>) = plugins.into_partition();
 
         // Initialize the plugins that are enabled.
-        let initialized_plugins: anyhow::Result<Vec<Box<dyn Plugin>>> =
-            enabled_plugins.into_iter().map(init_plugin).collect();
-        let mut initialized_plugins = initialized_plugins?;
+        // A plugin that does not initialize in time is disabled, the other errors abort the startup.
+        let timeouts = self.timeouts;
+        let mut initialized_plugins = Vec::with_capacity(enabled_plugins.len());
+        for p in enabled_plugins {
+            match init_plugin(p, timeouts.init) {
+                Ok(plugin) => initialized_plugins.push(plugin),
+                Err(e) if e.is::<HookTimeout>() => log::error!("{e}. The plugin is disabled."),
+                Err(e) => return Err(e),
+            }
+        }
         let n_plugins = initialized_plugins.len();
         match n_plugins {
             0 if disabled_plugins.is_empty() => log::warn!(
@@ -291,13 +404,27 @@ impl Builder {
         let mut pipeline_builder = self.pipeline_builder;
         let mut pre_start_actions = Vec::new();
         let mut post_start_actions = Vec::new();
+        let mut late_plugins = Vec::new();
         for plugin in initialized_plugins.iter_mut() {
+            let watchdog = Watchdog::new(plugin.name(), "start", timeouts.start);
             start_plugin(
                 plugin.deref_mut(),
                 &mut pipeline_builder,
                 &mut pre_start_actions,
                 &mut post_start_actions,
             )?;
+            if let Err(e) = watchdog.finish() {
+                // The plugin is probably in a bad state, don't use it.
+                log::error!("{e}. The plugin is disabled.");
+                let pname = PluginName(plugin.name().to_owned());
+                pipeline_builder.remove_plugin_elements(&pname);
+                pre_start_actions.retain(|(p, _)| p != &pname);
+                post_start_actions.retain(|(p, _)| p != &pname);
+                late_plugins.push(pname.0);
+            }
+        }
+        for plugin in initialized_plugins.extract_if(.., |p| late_plugins.iter().any(|name| name == p.name())) {
+            let _ = stop_plugin(plugin, timeouts.stop);
         }
         print_stats(&mut pipeline_builder, &initialized_plugins, &disabled_plugins);
         (self.callbacks.after_plugins_start)(&mut pipeline_builder);
@@ -311,7 +438,11 @@ impl Builder {
         (self.callbacks.before_operation_begin)(&mut pipeline_builder);
 
         // Build and start the pipeline.
+        // The requests to enable or disable a plugin are forwarded to the agent by the pipeline.
         log::info!("Starting the measurement pipeline...");
+        let (plugin_requests_tx, plugin_requests) = mpsc::channel(16);
+        pipeline_builder.plugin_requests = Some(plugin_requests_tx);
+        let metric_listeners = pipeline_builder.metric_listener_names().cloned().collect();
         let mut pipeline = pipeline_builder.build().context("Pipeline failed to build")?;
         log::info!("🔥 ALUMET measurement pipeline has started.");
 
@@ -319,7 +450,17 @@ impl Builder {
         log::info!("Running post-pipeline-start hooks...");
         let mut post_actions_per_plugin = group_plugin_actions(post_start_actions, n_plugins);
         for plugin in initialized_plugins.iter_mut() {
+            let watchdog = Watchdog::new(plugin.name(), "post_pipeline_start", timeouts.post_pipeline_start);
             post_pipeline_start(plugin.deref_mut(), &mut pipeline, &mut post_actions_per_plugin)?;
+            if let Err(e) = watchdog.finish() {
+                // The elements of the plugin are already running, stop them.
+                log::error!("{e}. The elements of the plugin are stopped.");
+                let request = request::plugin(plugin.name()).stop_elements();
+                let handle = pipeline.control_handle();
+                if let Err(e) = pipeline.async_runtime().block_on(handle.dispatch(request, None)) {
+                    log::error!("Could not disable the elements of plugin {}: {e}", plugin.name());
+                }
+            }
         }
         (self.callbacks.after_operation_begin)(&mut pipeline);
 
@@ -328,6 +469,10 @@ impl Builder {
         let agent = RunningAgent {
             pipeline,
             initialized_plugins,
+            disabled_plugins: Vec::new(),
+            plugin_requests,
+            metric_listeners,
+            timeouts,
         };
         Ok(agent)
     }
@@ -345,11 +490,236 @@ impl Builder {
 }
 
 impl RunningAgent {
-    /// Waits until the measurement pipeline stops, then stops the plugins.
+    /// Applies a new configuration to a running plugin, by calling its [`reconfigure`](Plugin::reconfigure) method.
+    ///
+    /// Returns an error if the plugin is not running, or if the reconfiguration fails.
+    pub fn reconfigure_plugin(&mut self, plugin_name: &str, config: ConfigTable) -> anyhow::Result<()> {
+        let plugin = self
+            .initialized_plugins
+            .iter_mut()
+            .find(|p| p.name() == plugin_name)
+            .with_context(|| format!("plugin {plugin_name} is not running"))?;
+        let version = plugin.version().to_owned();
+        log::debug!("Reconfiguring plugin {plugin_name} v{version} with config {config:?}...");
+
+        let mut ctx = AlumetPostStart {
+            current_plugin: PluginName(plugin_name.to_owned()),
+            pipeline: &mut self.pipeline,
+        };
+        plugin
+            .reconfigure(config, &mut ctx)
+            .with_context(|| format!("plugin failed to reconfigure: {plugin_name} v{version}"))
+    }
+
+    /// Disables a running plugin: its sources and outputs are stopped, its transforms are disabled,
+    /// and the plugin is [stopped](Plugin::stop).
+    ///
+    /// The plugin can be started again with [`enable_plugin`](Self::enable_plugin).
+    /// Disabling a plugin that is already disabled does nothing.
+    pub fn disable_plugin(&mut self, plugin_name: &str) -> anyhow::Result<()> {
+        let Some(i) = self.initialized_plugins.iter().position(|p| p.name() == plugin_name) else {
+            return match self.disabled_plugins.iter().any(|p| p.name() == plugin_name) {
+                true => Ok(()),
+                false => Err(anyhow!("plugin {plugin_name} is not running")),
+            };
+        };
+        log::info!("Disabling plugin {plugin_name}...");
+
+        // Plugin::stop expects the elements of the plugin to be stopped and dropped before it is called.
+        self.stop_plugin_elements(plugin_name)?;
+        let plugin = self.initialized_plugins.remove(i);
+        let plugin = stop_plugin_for_restart(plugin, self.timeouts.stop)?;
+        self.disabled_plugins.push(plugin);
+        log::info!("Plugin {plugin_name} disabled.");
+        Ok(())
+    }
+
+    /// Enables a plugin that has been disabled by [`disable_plugin`](Self::disable_plugin).
+    ///
+    /// The plugin is started like on startup, and the sources and outputs that it registers are added to
+    /// the running pipeline. Transforms cannot be added to a running pipeline: the existing transforms
+    /// of the plugin are enabled again. If the plugin registers a transform or a metric listener that
+    /// it did not have before, an error is returned and the plugin stays disabled.
+    ///
+    /// Enabling a plugin that is running does nothing.
+    pub fn enable_plugin(&mut self, plugin_name: &str) -> anyhow::Result<()> {
+        let Some(i) = self.disabled_plugins.iter().position(|p| p.name() == plugin_name) else {
+            return match self.initialized_plugins.iter().any(|p| p.name() == plugin_name) {
+                true => Ok(()),
+                false => Err(anyhow!("plugin {plugin_name} has not been disabled")),
+            };
+        };
+        log::info!("Enabling plugin {plugin_name}...");
+
+        let mut plugin = self.disabled_plugins.remove(i);
+        match self.restart_plugin(plugin.deref_mut()) {
+            Ok(()) => {
+                self.initialized_plugins.push(plugin);
+                log::info!("Plugin {plugin_name} enabled.");
+                Ok(())
+            }
+            Err(e) => {
+                // The plugin may have added some elements before failing, stop them and the plugin.
+                if let Err(e) = self.stop_plugin_elements(plugin_name) {
+                    log::error!("Could not stop the elements of plugin {plugin_name}: {e:?}");
+                }
+                match stop_plugin_for_restart(plugin, self.timeouts.stop) {
+                    Ok(plugin) => self.disabled_plugins.push(plugin),
+                    Err(e) => log::error!("{e:?}"),
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Starts a plugin that has been stopped, and adds its elements to the running pipeline.
+    fn restart_plugin(&mut self, plugin: &mut dyn Plugin) -> anyhow::Result<()> {
+        let name = plugin.name().to_owned();
+        let rt = self.pipeline.async_runtime().clone();
+
+        // Start the plugin with a new pipeline builder, which knows the metrics of the pipeline:
+        // the metrics that the plugin registers again keep their id.
+        let mut builder = pipeline::Builder::new();
+        builder.metrics = self.pipeline.metrics_reader().blocking_read().clone();
+        let n_metrics = builder.metrics.len();
+        let mut pre_start_actions = Vec::new();
+        let mut post_start_actions = Vec::new();
+        let watchdog = Watchdog::new(&name, "start", self.timeouts.start);
+        start_plugin(plugin, &mut builder, &mut pre_start_actions, &mut post_start_actions)?;
+        watchdog.finish()?;
+        pre_pipeline_start(plugin, &mut builder, &mut group_plugin_actions(pre_start_actions, 1))?;
+
+        // Transforms and metric listeners cannot be added to a running pipeline:
+        // the plugin can only register again those that it had before being disabled.
+        let handle = self.pipeline.control_handle();
+        let filter = ElementListFilter::kind(ElementKind::Transform).plugin(&name);
+        let transforms = rt.block_on(handle.send_wait(request::list_elements(filter), CONTROL_TIMEOUT))?;
+        let mut missing = Vec::new();
+        for (plugin, transform) in builder.transform_names() {
+            if !transforms.iter().any(|t| &t.element == transform) {
+                missing.push(format!("transform {plugin}/{transform}"));
+            }
+        }
+        for key @ (plugin, listener) in builder.metric_listener_names() {
+            if !self.metric_listeners.contains(key) {
+                missing.push(format!("metric listener {plugin}/{listener}"));
+            }
+        }
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "plugin {name} registered new elements that cannot be added to the running pipeline: {}",
+                missing.join(", ")
+            ));
+        }
+
+        // Register the new metrics in the pipeline, with the ids that the plugin knows.
+        let mut new_metrics: Vec<_> = builder
+            .metrics
+            .iter()
+            .filter(|(id, _)| id.0 >= n_metrics)
+            .map(|(id, m)| (*id, m.clone()))
+            .collect();
+        if !new_metrics.is_empty() {
+            new_metrics.sort_by_key(|(id, _)| id.0);
+            let (ids, metrics): (Vec<_>, Vec<_>) = new_metrics.into_iter().unzip();
+            let sender = self.pipeline.metrics_sender();
+            let registered = rt
+                .block_on(sender.create_metrics(metrics, DuplicateReaction::Error))
+                .map_err(|e| anyhow!("failed to register the metrics of plugin {name}: {e}"))?;
+            for (expected, res) in ids.into_iter().zip(registered) {
+                if res? != expected {
+                    return Err(anyhow!(
+                        "another metric has been registered while plugin {name} was starting, the metric ids are inconsistent"
+                    ));
+                }
+            }
+        }
+
+        // Build the sources and outputs, add them to the pipeline, and enable the transforms.
+        let request = builder.into_creation_request(&self.pipeline)?;
+        let plugin_handle = handle.clone().with_plugin(PluginName(name.clone()));
+        rt.block_on(plugin_handle.send_wait(request, CONTROL_TIMEOUT))?;
+        rt.block_on(handle.send_wait(request::plugin(&name).enable_transforms(), CONTROL_TIMEOUT))?;
+
+        let watchdog = Watchdog::new(&name, "post_pipeline_start", self.timeouts.post_pipeline_start);
+        post_pipeline_start(
+            plugin,
+            &mut self.pipeline,
+            &mut group_plugin_actions(post_start_actions, 1),
+        )?;
+        watchdog.finish()?;
+        Ok(())
+    }
+
+    /// Stops the sources and outputs of a plugin, disables its transforms, and waits for the sources
+    /// and outputs to finish.
+    fn stop_plugin_elements(&self, plugin_name: &str) -> anyhow::Result<()> {
+        let handle = self.pipeline.control_handle();
+        let timeout = self.timeouts.stop;
+        self.pipeline.async_runtime().block_on(async {
+            let request = request::plugin(plugin_name).stop_elements();
+            handle.send_wait(request, CONTROL_TIMEOUT).await?;
+
+            // The sources and outputs are no longer listed when they have finished.
+            let finished = async {
+                loop {
+                    let filter = ElementListFilter::kind_any().plugin(plugin_name);
+                    let elements = handle
+                        .send_wait(request::list_elements(filter), CONTROL_TIMEOUT)
+                        .await?;
+                    if elements.iter().all(|e| e.kind == ElementKind::Transform) {
+                        return anyhow::Ok(());
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            };
+            tokio::time::timeout(timeout, finished)
+                .await
+                .with_context(|| format!("the elements of plugin {plugin_name} did not stop within {timeout:?}"))?
+        })
+    }
+
+    /// Handles a request to enable or disable a plugin, and sends the response to the pipeline.
+    pub(crate) fn handle_plugin_request(&mut self, request: PluginRequestMessage) {
+        let RequestMessage { response_tx, body } = request;
+        let (plugin, result) = match body {
+            PluginBody::Enable(plugin) => {
+                let res = self.enable_plugin(&plugin.0);
+                (plugin, res)
+            }
+            PluginBody::Disable(plugin) => {
+                let res = self.disable_plugin(&plugin.0);
+                (plugin, res)
+            }
+        };
+        if let Err(e) = &result {
+            log::error!("Plugin request failed: {e:?}");
+        }
+        if let Some(tx) = response_tx {
+            let _ = tx.send(result.map_err(|e| PipelineError::for_plugin(plugin, e)));
+        }
+    }
+
+    /// Waits for the next request to enable or disable a plugin.
+    ///
+    /// Returns `None` when the pipeline is requested to shut down.
+    pub(crate) async fn next_plugin_request(&mut self) -> Option<PluginRequestMessage> {
+        let control_handle = self.pipeline.control_handle();
+        tokio::select! {
+            _ = control_handle.shutdown_requested() => None,
+            request = self.plugin_requests.recv() => request,
+        }
+    }
+
+    /// Handles the requests to enable or disable a plugin until the pipeline stops,
+    /// then stops the plugins.
     ///
     /// See the [module documentation](super).
-    pub fn wait_for_shutdown(self, timeout: Duration) -> Result<(), ShutdownError> {
-        use std::panic::{AssertUnwindSafe, catch_unwind};
+    pub fn wait_for_shutdown(mut self, timeout: Duration) -> Result<(), ShutdownError> {
+        let rt = self.pipeline.async_runtime().clone();
+        while let Some(request) = rt.block_on(self.next_plugin_request()) {
+            self.handle_plugin_request(request);
+        }
 
         let mut errors = Vec::new();
 
@@ -371,41 +741,10 @@ impl RunningAgent {
 
         // Stop all the plugins, even if some of them fail to stop properly.
         log::info!("Stopping the plugins...");
-        for mut plugin in self.initialized_plugins {
+        for plugin in self.initialized_plugins {
             let name = plugin.name().to_owned();
-            let version = plugin.version().to_owned();
-            log::info!("Stopping plugin {name} v{version}");
-
-            // If a plugin panics, we still want to try to stop the other plugins.
-            match catch_unwind(AssertUnwindSafe(move || {
-                plugin.stop()
-                // plugin is dropped here
-            })) {
-                Ok(Ok(())) => (),
-                Ok(Err(e)) => {
-                    log::error!("Error while stopping plugin {name} v{version}. {e:?}");
-                    errors.push(AgentShutdownError::Plugin(name));
-                }
-                Err(panic_payload) => {
-                    log::error!(
-                        "PANIC while stopping plugin {name} v{version}. There is probably a bug in the plugin!
-                        Please check the implementation of stop (and drop if Drop is implemented for the plugin type)."
-                    );
-                    errors.push(AgentShutdownError::Plugin(name.clone()));
-
-                    // dropping the panic payload may, in turn, panic!
-                    let dropped = catch_unwind(AssertUnwindSafe(move || {
-                        drop(panic_payload);
-                    }));
-                    if let Err(panic2) = dropped {
-                        log::error!(
-                            "PANIC while dropping panic payload generated while stopping plugin {name} v{version}."
-                        );
-                        // We cannot drop it, forget it.
-                        // Alumet will stop after this anyway, but the plugin should be fixed.
-                        std::mem::forget(panic2);
-                    }
-                }
+            if stop_plugin(plugin, self.timeouts.stop).is_err() {
+                errors.push(AgentShutdownError::Plugin(name));
             }
         }
         log::info!("All plugins have stopped.");
@@ -418,6 +757,73 @@ impl RunningAgent {
     }
 }
 
+/// Stops a plugin on a separate thread, with a timeout, and returns it so that it can be started again.
+fn stop_plugin_for_restart(mut plugin: Box<dyn Plugin>, timeout: Duration) -> anyhow::Result<Box<dyn Plugin>> {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    let name = plugin.name().to_owned();
+    let version = plugin.version().to_owned();
+    log::info!("Stopping plugin {name} v{version}");
+
+    let stopped = run_detached(&name, "stop", timeout, move || {
+        catch_unwind(AssertUnwindSafe(move || plugin.stop().map(|()| plugin)))
+    })?;
+    match stopped {
+        Ok(res) => res.with_context(|| format!("plugin failed to stop: {name} v{version}")),
+        Err(_panic_payload) => Err(anyhow!(
+            "PANIC while stopping plugin {name} v{version}. There is probably a bug in the plugin!"
+        )),
+    }
+}
+
+/// Stops a plugin and drops it, on a separate thread, with a timeout.
+///
+/// The errors are logged.
+fn stop_plugin(mut plugin: Box<dyn Plugin>, timeout: Duration) -> Result<(), ()> {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    let name = plugin.name().to_owned();
+    let version = plugin.version().to_owned();
+    log::info!("Stopping plugin {name} v{version}");
+
+    // If a plugin panics, we still want to try to stop the other plugins.
+    let stopped = run_detached(&name, "stop", timeout, move || {
+        catch_unwind(AssertUnwindSafe(move || {
+            plugin.stop()
+            // plugin is dropped here
+        }))
+    });
+    match stopped {
+        Ok(Ok(Ok(()))) => Ok(()),
+        Ok(Ok(Err(e))) => {
+            log::error!("Error while stopping plugin {name} v{version}. {e:?}");
+            Err(())
+        }
+        Ok(Err(panic_payload)) => {
+            log::error!(
+                "PANIC while stopping plugin {name} v{version}. There is probably a bug in the plugin!
+                Please check the implementation of stop (and drop if Drop is implemented for the plugin type)."
+            );
+
+            // dropping the panic payload may, in turn, panic!
+            let dropped = catch_unwind(AssertUnwindSafe(move || {
+                drop(panic_payload);
+            }));
+            if let Err(panic2) = dropped {
+                log::error!("PANIC while dropping panic payload generated while stopping plugin {name} v{version}.");
+                // We cannot drop it, forget it.
+                // Alumet will stop after this anyway, but the plugin should be fixed.
+                std::mem::forget(panic2);
+            }
+            Err(())
+        }
+        Err(e) => {
+            log::error!("{e}. The plugin is abandoned.");
+            Err(())
+        }
+    }
+}
+
 /// Prints some statistics after the plugin start-up phase.
 fn print_stats(
     pipeline_builder: &mut pipeline::Builder,
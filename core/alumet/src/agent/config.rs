@@ -42,19 +42,58 @@
 //!
 //! // TODO use the config
 //! ```
+//!
+//! # Includes and overlays
+//!
+//! The configuration can be split into multiple files. The `include` option of the main
+//! config file lists files, or directories of `.toml` files, that are merged in order,
+//! before the main config file. Relative paths are resolved against the directory of the main config file.
+//!
+//! ```toml
+//! include = ["common.toml", "conf.d"]
+//!
+//! [plugins.a]
+//! plugin_a_option = "value"
+//! ```
+//!
+//! Additional overlays can be given to the [`Loader`], see [`Loader::with_overlay`]
+//! (the agent exposes them with `--config-overlay`).
+//! This allows to share a common configuration between multiple machines, and to override
+//! some options (intervals, outputs, ...) on each machine.
+//!
+//! When an option is set in multiple places, the last one wins. The configuration is merged in this order:
+//! 1. the included files, in the order of the `include` option;
+//! 2. the main config file, which overrides its includes;
+//! 3. the overlays, in the order in which they are given;
+//! 4. the overrides, see [`Loader::with_override`].
+//!
+//! # Environment variables
+//!
+//! When enabled with [`Loader::substitute_env_variables`], the patterns `${VAR_NAME}` and
+//! `${VAR_NAME:-fallback}` are replaced by the value of the corresponding environment variables,
+//! before the configuration is parsed. See [`substitute_env`].
+//!
+//! ```toml
+//! [plugins.relay-client]
+//! client_name = "${NODE_NAME}"
+//! relay_server = "${RELAY_SERVER:-localhost:50051}"
+//! ```
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::{borrow::Cow, env::VarError};
 
-use anyhow::anyhow;
+use anyhow::{Context, anyhow};
 use indexmap::IndexMap;
 use serde::Serialize;
 
 use super::plugin::{PluginFilter, PluginSet};
-use crate::plugin::PluginMetadata;
+use crate::plugin::{PluginMetadata, migration};
 use error::*;
 
+/// Name of the config option that lists the files to include.
+const INCLUDE_KEY: &str = "include";
+
 /// Loads the agent configuration from a TOML file.
 pub struct Loader<'d> {
     /// File that contains the configuration.
@@ -63,6 +102,8 @@ pub struct Loader<'d> {
     default_provider: Option<Box<dyn DefaultConfigProvider + 'd>>,
     /// Should the default config be saved after generation?
     save_default: bool,
+    /// Additional files (or directories) that are merged with the config.
+    overlays: Vec<PathBuf>,
     /// Additional values that override the content of the config.
     overrides: Option<toml::Table>,
     /// Should environment variable substitution be applied before deserializing?
@@ -108,6 +149,7 @@ impl<'d> Loader<'d> {
             file: config_file.into(),
             default_provider: None,
             save_default: false,
+            overlays: Vec::new(),
             overrides: None,
             substitute_env: false,
         }
@@ -137,6 +179,25 @@ impl<'d> Loader<'d> {
         self
     }
 
+    /// Overrides the content of the configuration by [merging](merge_override) it
+    /// with the content of another file.
+    ///
+    /// If `path` is a directory, every `.toml` file it contains is merged, in the lexicographic
+    /// order of the file names (like a `conf.d/` directory).
+    ///
+    /// Multiple overlays can be set. They are applied in order, after the main config (and the files listed
+    /// in its `include` option), and before the overrides set by [`with_override`](Self::with_override).
+    pub fn with_overlay<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.overlays.push(path.into());
+        self
+    }
+
+    /// Adds multiple overlays, see [`with_overlay`](Self::with_overlay).
+    pub fn with_overlays<P: Into<PathBuf>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.overlays.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
     /// Overrides the content of the configuration by [merging](merge_override) it
     /// with another config.
     ///
@@ -159,23 +220,79 @@ impl<'d> Loader<'d> {
     }
 
     /// Loads the configuration with the provided settings.
-    pub fn load(mut self) -> Result<toml::Table, LoadError> {
-        self.load_impl().map_err(|e| LoadError {
-            config_file: self.file,
-            kind: e,
-        })
+    pub fn load(self) -> Result<toml::Table, LoadError> {
+        self.load_with_files().map(|(config, _)| config)
     }
 
-    fn load_impl(&mut self) -> Result<toml::Table, LoadErrorCause> {
+    /// Loads the configuration with the provided settings, and returns the paths that have been read.
+    ///
+    /// The paths are the main config file, followed by the included files and the overlays,
+    /// in the order in which they have been merged. The directories of `.toml` files are listed
+    /// too, before their content, so that the addition or removal of a file can be detected.
+    pub fn load_with_files(mut self) -> Result<(toml::Table, Vec<PathBuf>), LoadError> {
+        let mut files = Vec::new();
+        match self.load_impl(&mut files) {
+            Ok(config) => Ok((config, files)),
+            Err(e) => Err(LoadError {
+                config_file: self.file,
+                kind: e,
+            }),
+        }
+    }
+
+    fn load_impl(&mut self, files: &mut Vec<PathBuf>) -> Result<toml::Table, LoadErrorCause> {
         let config_content = self.read_config_or_default()?;
-        let config_content = substitute_env(&config_content)?;
-        let mut parsed_config = toml::Table::from_str(&config_content)?;
+        let mut main_config = self.parse(&config_content)?;
+        files.push(self.file.clone());
+
+        // Merge the included files first, so that the main config overrides them.
+        let base_dir = self.file.parent().unwrap_or(Path::new(""));
+        let includes = take_includes(&mut main_config, base_dir)?;
+        let mut parsed_config = toml::Table::new();
+        self.merge_overlays(&mut parsed_config, &includes, files)?;
+        merge_override(&mut parsed_config, main_config);
+
+        // Then merge the overlays and the overrides, which override the main config.
+        let overlays = std::mem::take(&mut self.overlays);
+        self.merge_overlays(&mut parsed_config, &overlays, files)?;
         if let Some(overrides) = self.overrides.take() {
             merge_override(&mut parsed_config, overrides);
         }
         Ok(parsed_config)
     }
 
+    fn merge_overlays(
+        &self,
+        config: &mut toml::Table,
+        paths: &[PathBuf],
+        files: &mut Vec<PathBuf>,
+    ) -> Result<(), LoadErrorCause> {
+        for path in paths {
+            let content = overlay_files(path).map_err(|e| LoadErrorCause::overlay(path, LoadErrorCause::Read(e)))?;
+            if path.is_dir() {
+                files.push(path.clone());
+            }
+            for file in content {
+                let overlay = std::fs::read_to_string(&file)
+                    .map_err(LoadErrorCause::Read)
+                    .and_then(|content| self.parse(&content))
+                    .map_err(|e| LoadErrorCause::overlay(&file, e))?;
+                merge_override(config, overlay);
+                files.push(file);
+            }
+        }
+        Ok(())
+    }
+
+    fn parse(&self, config_content: &str) -> Result<toml::Table, LoadErrorCause> {
+        let config_content = if self.substitute_env {
+            substitute_env(config_content)?
+        } else {
+            Cow::Borrowed(config_content)
+        };
+        Ok(toml::Table::from_str(&config_content)?)
+    }
+
     fn read_config_or_default(&mut self) -> Result<String, LoadErrorCause> {
         match std::fs::read_to_string(&self.file) {
             Ok(s) => Ok(s),
@@ -203,6 +320,43 @@ impl<'d> Loader<'d> {
     }
 }
 
+/// Removes the `include` option from the config and returns the paths that it contains.
+///
+/// Relative paths are resolved against `base_dir`.
+fn take_includes(config: &mut toml::Table, base_dir: &Path) -> Result<Vec<PathBuf>, BadTypeError> {
+    let paths = match config.remove(INCLUDE_KEY) {
+        None => return Ok(Vec::new()),
+        Some(toml::Value::String(path)) => vec![toml::Value::String(path)],
+        Some(toml::Value::Array(paths)) => paths,
+        Some(bad) => return Err(BadTypeError::new(String::from(INCLUDE_KEY), "string or array", bad)),
+    };
+    paths
+        .into_iter()
+        .enumerate()
+        .map(|(i, path)| match path {
+            toml::Value::String(path) => Ok(base_dir.join(path)),
+            bad => Err(BadTypeError::new(format!("{INCLUDE_KEY}[{i}]"), "string", bad)),
+        })
+        .collect()
+}
+
+/// Lists the files of an overlay: the path itself if it is a file,
+/// or the `.toml` files that it contains (sorted by name) if it is a directory.
+fn overlay_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let file = entry?.path();
+        if file.is_file() && file.extension().is_some_and(|ext| ext == "toml") {
+            files.push(file);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
 impl<'f, F: Fn() -> anyhow::Result<toml::Table> + 'f> DefaultConfigProvider for F {
     fn default_config(&self) -> anyhow::Result<toml::Table> {
         let table = self()?;
@@ -243,8 +397,11 @@ impl DefaultConfigProvider for NoDefaultConfigProvider {
 /// Replaces the pattern `${VAR_NAME}` by the value of the `VAR_NAME` environment
 /// variable.
 ///
+/// A fallback value can be given with `${VAR_NAME:-fallback}`: it is used when the
+/// variable does not exist or is empty. The fallback cannot contain a closing brace `}`.
+///
 /// The pattern can be escaped to prevent its replacement: `\${NOT_A_VAR}`.
-/// If a variable does not exist or is invalid, returns an error.
+/// If a variable does not exist (and has no fallback) or is invalid, returns an error.
 ///
 pub fn substitute_env(mut input: &'_ str) -> Result<Cow<'_, str>, InvalidSubstitutionError> {
     // Look for the first substitution.
@@ -278,17 +435,27 @@ pub fn substitute_env(mut input: &'_ str) -> Result<Cow<'_, str>, InvalidSubstit
                     return Err(InvalidSubstitutionError::WrongSyntax);
                 }
                 Some(end) => {
-                    // correct substitution syntax: "${VAR_NAME}"
-                    let env_var_name = &input[2..end];
-                    match std::env::var(env_var_name) {
-                        Ok(env_var_value) => {
+                    // correct substitution syntax: "${VAR_NAME}" or "${VAR_NAME:-fallback}"
+                    let (env_var_name, fallback) = match input[2..end].split_once(":-") {
+                        Some((name, fallback)) => (name, Some(fallback)),
+                        None => (&input[2..end], None),
+                    };
+                    match (std::env::var(env_var_name), fallback) {
+                        (Ok(env_var_value), Some(fallback)) if env_var_value.is_empty() => {
+                            // The variable is set but empty: use the fallback.
+                            res.push_str(fallback);
+                        }
+                        (Ok(env_var_value), _) => {
                             // We have found the environment variable: substitute.
                             res.push_str(&env_var_value);
                         }
-                        Err(VarError::NotPresent) => {
+                        (Err(VarError::NotPresent), Some(fallback)) => {
+                            res.push_str(fallback);
+                        }
+                        (Err(VarError::NotPresent), None) => {
                             return Err(InvalidSubstitutionError::Missing(env_var_name.to_owned()));
                         }
-                        Err(VarError::NotUnicode(_)) => {
+                        (Err(VarError::NotUnicode(_)), _) => {
                             return Err(InvalidSubstitutionError::InvalidValue(env_var_name.to_owned()));
                         }
                     }
@@ -319,6 +486,73 @@ pub fn substitute_env(mut input: &'_ str) -> Result<Cow<'_, str>, InvalidSubstit
     Ok(Cow::Owned(res))
 }
 
+/// Parses an assignment of the form `path.to.key=value` into a TOML table that can be
+/// used as an override, see [`merge_override`].
+///
+/// The key follows the TOML syntax for dotted keys, for instance `plugins."relay-client".relay_server`.
+/// It ends at the first `=` that is not quoted, hence a quoted key can contain `=`.
+/// If the value is a valid TOML value (number, boolean, array, quoted string, ...), it is parsed as such.
+/// Otherwise, it is taken as a string, which means that `key=some text` and `key='some text'` are equivalent.
+///
+/// # Example
+/// ```
+/// use alumet::agent::config::parse_assignment;
+///
+/// let table = parse_assignment("plugins.csv.output_path=/tmp/x.csv").unwrap();
+/// assert_eq!(table["plugins"]["csv"]["output_path"].as_str(), Some("/tmp/x.csv"));
+///
+/// let table = parse_assignment("plugins.rapl.no_perf_events=true").unwrap();
+/// assert_eq!(table["plugins"]["rapl"]["no_perf_events"].as_bool(), Some(true));
+/// ```
+pub fn parse_assignment(assignment: &str) -> Result<toml::Table, InvalidAssignmentError> {
+    let (key, value) =
+        split_assignment(assignment).ok_or_else(|| InvalidAssignmentError::MissingValue(assignment.to_owned()))?;
+    let (key, value) = (key.trim(), value.trim());
+
+    // Let the TOML parser handle the dotted (and possibly quoted) key.
+    let mut res =
+        toml::Table::from_str(&format!("{key} = 0")).map_err(|_| InvalidAssignmentError::InvalidKey(key.to_owned()))?;
+    let value = match toml::Table::from_str(&format!("v = {value}")) {
+        Ok(mut t) => t.remove("v").unwrap(),
+        Err(_) => toml::Value::String(value.to_owned()),
+    };
+
+    // Replace the placeholder by the actual value.
+    let mut table = &mut res;
+    loop {
+        let (_, entry) = table.iter_mut().next().unwrap();
+        match entry {
+            toml::Value::Table(t) => table = t,
+            leaf => {
+                *leaf = value;
+                break;
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// Splits `key=value` on the first `=` that is not in a quoted part of the key.
+fn split_assignment(assignment: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in assignment.char_indices() {
+        match (quote, c) {
+            // escape sequences are only allowed in basic strings, i.e. "..."
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '=') => return Some((&assignment[..i], &assignment[i + 1..])),
+            _ => (),
+        }
+        escaped = false;
+    }
+    None
+}
+
 /// Merges two toml tables by overriding the content of `original`
 /// with the content of `overrides`.
 ///
@@ -465,6 +699,44 @@ pub fn extract_plugins_config(config: &mut toml::Table) -> Result<IndexMap<Strin
     Ok(res)
 }
 
+/// Upgrades the configuration of the given plugins to their latest format, in a configuration file.
+///
+/// Each plugin section `plugins.<name>` is migrated according to the
+/// [`config_migrations`](PluginMetadata::config_migrations) of the plugin.
+/// If at least one section has been migrated, the original file is copied to `<file>.bak`
+/// and the file is rewritten (the comments are not preserved). Environment variables are not substituted.
+///
+/// Returns the name, old version and new version of each plugin whose config has been migrated.
+pub fn migrate_config_file<'p>(
+    file: &Path,
+    plugins: impl IntoIterator<Item = &'p PluginMetadata>,
+) -> anyhow::Result<Vec<(String, u32, u32)>> {
+    let content = std::fs::read_to_string(file).with_context(|| format!("could not read {}", file.display()))?;
+    let mut config = toml::Table::from_str(&content).with_context(|| format!("invalid TOML in {}", file.display()))?;
+
+    let mut migrated = Vec::new();
+    if let Some(toml::Value::Table(sections)) = config.get_mut("plugins") {
+        for p in plugins {
+            if let Some(toml::Value::Table(section)) = sections.get_mut(&p.name) {
+                let res = migration::migrate(section, &p.config_migrations)
+                    .with_context(|| format!("failed to migrate the config of plugin {}", p.name))?;
+                if let Some((old, new)) = res {
+                    migrated.push((p.name.clone(), old, new));
+                }
+            }
+        }
+    }
+
+    if !migrated.is_empty() {
+        let mut backup = file.as_os_str().to_owned();
+        backup.push(".bak");
+        std::fs::copy(file, &backup).context("could not backup the config file")?;
+        std::fs::write(file, toml::to_string_pretty(&config)?)
+            .with_context(|| format!("could not write {}", file.display()))?;
+    }
+    Ok(migrated)
+}
+
 /// Generates a table containing the default configuration of each plugin.
 pub fn generate_plugin_configs<'p, I: IntoIterator<Item = &'p PluginMetadata>>(
     plugins: I,
@@ -478,7 +750,15 @@ pub fn generate_plugin_configs<'p, I: IntoIterator<Item = &'p PluginMetadata>>(
             source: e,
         })?;
 
-        if let Some(config) = plugin_config {
+        if let Some(mut config) = plugin_config {
+            // stamp the config with its version, so that it can be migrated later
+            let version = migration::latest_version(&p.config_migrations);
+            if version > 1 {
+                config.0.insert(
+                    String::from(migration::CONFIG_VERSION_KEY),
+                    toml::Value::Integer(version.into()),
+                );
+            }
             table.insert(p.name.clone(), toml::Value::Table(config.0));
         }
     }
@@ -486,7 +766,10 @@ pub fn generate_plugin_configs<'p, I: IntoIterator<Item = &'p PluginMetadata>>(
 }
 
 pub mod error {
-    use std::{io, path::PathBuf};
+    use std::{
+        io,
+        path::{Path, PathBuf},
+    };
     use thiserror::Error;
 
     /// [`Loader::load`](super::Loader::load) failed.
@@ -525,6 +808,27 @@ pub mod error {
         /// (after environment variable substitution).
         #[error("invalid TOML config")]
         InvalidToml(#[from] toml::de::Error),
+
+        /// The `include` option has an invalid value.
+        #[error("invalid include option")]
+        InvalidInclude(#[from] BadTypeError),
+
+        /// An included file or an overlay could not be loaded.
+        #[error("could not load '{}'", file.display())]
+        Overlay {
+            file: PathBuf,
+            #[source]
+            cause: Box<LoadErrorCause>,
+        },
+    }
+
+    impl LoadErrorCause {
+        pub(super) fn overlay(file: &Path, cause: LoadErrorCause) -> Self {
+            Self::Overlay {
+                file: file.to_path_buf(),
+                cause: Box::new(cause),
+            }
+        }
     }
 
     /// Environment variable substitution failed.
@@ -544,6 +848,17 @@ pub mod error {
         WrongSyntax,
     }
 
+    /// An assignment `key=value` could not be parsed.
+    #[derive(Error, Debug, PartialEq)]
+    pub enum InvalidAssignmentError {
+        /// There is no `=` in the assignment.
+        #[error("invalid assignment '{0}', expected key=value")]
+        MissingValue(String),
+        /// The key is not a valid TOML key.
+        #[error("invalid key '{0}'")]
+        InvalidKey(String),
+    }
+
     /// A value of the TOML configuration had an unexpected type.
     #[derive(Error, Debug)]
     #[error("unexpected type for {path}: expected {expected}, got {actual}")]
@@ -701,6 +1016,36 @@ mod tests_substitute_env {
         assert_eq!(expected, substitute_env(&input).unwrap());
     }
 
+    #[test]
+    fn fallback() {
+        // the variable exists: the fallback is ignored
+        let input = format!("a = '${{{ENV_VAR_NAME}:-default}}'");
+        let expected = format!("a = '{ENV_VAR_VALUE}'");
+        assert_eq!(expected, substitute_env(&input).unwrap());
+
+        // the variable does not exist: the fallback is used
+        let input = "a = '${ALUMET_TEST_UNDEFINED_VAR:-default value}'";
+        assert_eq!("a = 'default value'", substitute_env(input).unwrap());
+
+        // the fallback can be empty
+        let input = "a = '${ALUMET_TEST_UNDEFINED_VAR:-}'";
+        assert_eq!("a = ''", substitute_env(input).unwrap());
+
+        // the fallback can be escaped like the rest
+        let input = format!("a = '${{ALUMET_TEST_UNDEFINED_VAR:-x}}{ESCAPED_SUBST}'");
+        let expected = format!("a = 'x{SUBSTITUTION}'");
+        assert_eq!(expected, substitute_env(&input).unwrap());
+
+        // without a fallback, a missing variable is an error
+        let input = "a = '${ALUMET_TEST_UNDEFINED_VAR}'";
+        assert_eq!(
+            substitute_env(input),
+            Err(InvalidSubstitutionError::Missing(String::from(
+                "ALUMET_TEST_UNDEFINED_VAR"
+            )))
+        );
+    }
+
     #[test]
     fn unclosed() {
         let input = "${";
@@ -716,3 +1061,122 @@ mod tests_substitute_env {
         assert_eq!(substitute_env(input), Err(InvalidSubstitutionError::WrongSyntax));
     }
 }
+
+#[cfg(test)]
+mod tests_overlays {
+    use super::Loader;
+
+    #[test]
+    fn includes_and_overlays() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        std::fs::create_dir_all(dir.join("conf.d")).unwrap();
+
+        let main = dir.join("main.toml");
+        std::fs::write(
+            &main,
+            "include = ['common.toml', 'conf.d']\nname = 'main'\n[plugins.a]\nx = 1\ny = 1\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("common.toml"), "[plugins.a]\nx = 2\nz = 2\n").unwrap();
+        // applied in lexicographic order, the other files are ignored
+        std::fs::write(dir.join("conf.d/10-first.toml"), "[plugins.a]\nz = 3\n").unwrap();
+        std::fs::write(dir.join("conf.d/20-second.toml"), "[plugins.b]\nw = 4\n").unwrap();
+        std::fs::write(dir.join("conf.d/README"), "not a config").unwrap();
+        let node = dir.join("node.toml");
+        std::fs::write(&node, "[plugins.a]\ny = 5\n").unwrap();
+
+        let (config, files) = Loader::parse_file(&main).with_overlay(&node).load_with_files().unwrap();
+        // the main config overrides its includes, the overlay overrides the main config
+        let expected: toml::Table =
+            toml::from_str("name = 'main'\n[plugins.a]\nx = 1\ny = 5\nz = 3\n[plugins.b]\nw = 4\n").unwrap();
+        assert_eq!(config, expected);
+        assert_eq!(
+            files,
+            vec![
+                main,
+                dir.join("common.toml"),
+                dir.join("conf.d"),
+                dir.join("conf.d/10-first.toml"),
+                dir.join("conf.d/20-second.toml"),
+                node
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_overlay() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("main.toml");
+        std::fs::write(&main, "include = 'does-not-exist.toml'\n").unwrap();
+
+        let err = anyhow::Error::from(Loader::parse_file(&main).load().unwrap_err());
+        assert!(format!("{err:#}").contains("does-not-exist.toml"), "{err:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests_assignment {
+    use super::{InvalidAssignmentError, parse_assignment};
+
+    #[test]
+    fn values() {
+        let parse = |s| parse_assignment(s).unwrap();
+        assert_eq!(parse("a=1"), toml::toml! { a = 1 });
+        assert_eq!(parse("a = 1.5"), toml::toml! { a = 1.5 });
+        assert_eq!(parse("a=false"), toml::toml! { a = false });
+        assert_eq!(parse("a=[1, 2]"), toml::toml! { a = [1, 2] });
+        assert_eq!(parse("a='1ms'"), toml::toml! { a = "1ms" });
+        assert_eq!(parse("a=1ms"), toml::toml! { a = "1ms" });
+        assert_eq!(parse("a=/tmp/x.csv"), toml::toml! { a = "/tmp/x.csv" });
+        assert_eq!(parse("a=x=y"), toml::toml! { a = "x=y" });
+        assert_eq!(parse("a="), toml::toml! { a = "" });
+    }
+
+    #[test]
+    fn keys() {
+        let parse = |s| parse_assignment(s).unwrap();
+        assert_eq!(
+            parse("plugins.csv.output_path=/tmp/x.csv"),
+            toml::toml! {
+                [plugins.csv]
+                output_path = "/tmp/x.csv"
+            }
+        );
+        assert_eq!(
+            parse("plugins.\"relay-client\".relay_server=localhost:50051"),
+            toml::toml! {
+                [plugins.relay-client]
+                relay_server = "localhost:50051"
+            }
+        );
+        assert_eq!(
+            parse("'plugins.\"a=b\".x'=1"),
+            toml::toml! {
+                "plugins.\"a=b\".x" = 1
+            }
+        );
+        assert_eq!(
+            parse("plugins.\"a=b\".x=1"),
+            toml::toml! {
+                [plugins."a=b"]
+                x = 1
+            }
+        );
+        assert_eq!(
+            parse("plugins.\"a\\\"=b\".x=1"),
+            toml::toml! {
+                [plugins."a\"=b"]
+                x = 1
+            }
+        );
+        assert_eq!(
+            parse_assignment("no value"),
+            Err(InvalidAssignmentError::MissingValue(String::from("no value")))
+        );
+        assert_eq!(
+            parse_assignment("a..b=1"),
+            Err(InvalidAssignmentError::InvalidKey(String::from("a..b")))
+        );
+    }
+}
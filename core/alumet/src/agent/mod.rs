@@ -65,11 +65,15 @@
 //!
 //! Use the [`config`] module to manage a TOML configuration file that contains both
 //! the general agent options and the configuration of each plugin.
+//! The [`reload`] module allows to apply the changes made to this file while the agent is running.
 
 pub mod builder;
 pub mod config;
 pub mod exec;
 pub mod plugin;
+pub mod reload;
+pub mod timeout;
+#[cfg(target_os = "linux")]
 pub mod watch;
 
 pub use builder::{Builder, RunningAgent};
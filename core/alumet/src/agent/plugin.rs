@@ -3,6 +3,7 @@
 use indexmap::IndexMap;
 
 use anyhow::{Context, anyhow};
+use thiserror::Error;
 
 use crate::plugin::PluginMetadata;
 
@@ -85,6 +86,20 @@ pub enum UnknownPluginInConfigPolicy {
     Ignore,
 }
 
+/// The dependencies of the plugins cannot be satisfied.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DependencyError {
+    /// A required dependency is in the set, but is disabled.
+    #[error("plugin {plugin} requires plugin {dependency}, which is not enabled")]
+    Disabled { plugin: String, dependency: String },
+    /// A required dependency is not in the set.
+    #[error("plugin {plugin} requires plugin {dependency}, which is not available in this agent")]
+    Unknown { plugin: String, dependency: String },
+    /// The dependencies form a cycle.
+    #[error("circular dependency between plugins: {}", .0.join(", "))]
+    Cycle(Vec<String>),
+}
+
 impl PluginInfo {
     fn new(metadata: PluginMetadata) -> Self {
         Self {
@@ -261,6 +276,78 @@ impl PluginSet {
             self.0.insert(plugin_name, info);
         }
     }
+
+    /// Reorders the enabled plugins so that every plugin comes after its dependencies.
+    ///
+    /// The relative order of the plugins that do not depend on each other is preserved.
+    /// Disabled plugins are moved after the enabled ones.
+    ///
+    /// Returns an error if a required dependency is not enabled, or if there is a dependency cycle.
+    /// Optional dependencies that are not enabled are ignored.
+    pub fn sort_by_dependencies(&mut self) -> Result<(), DependencyError> {
+        let enabled: Vec<usize> = (0..self.0.len()).filter(|i| self.0[*i].enabled).collect();
+
+        // Build the dependency graph, restricted to the enabled plugins.
+        // `dependents[i]` contains the plugins that depend on the plugin at index `i`.
+        let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); self.0.len()];
+        let mut n_pending_deps: Vec<usize> = vec![0; self.0.len()];
+        for &i in &enabled {
+            let plugin = &self.0[i];
+            for dep in &plugin.metadata.dependencies {
+                match self.0.get_full(&dep.plugin) {
+                    Some((j, _, dep_info)) if dep_info.enabled => {
+                        dependents[j].push(i);
+                        n_pending_deps[i] += 1;
+                    }
+                    Some(_) if dep.required => {
+                        return Err(DependencyError::Disabled {
+                            plugin: plugin.metadata.name.clone(),
+                            dependency: dep.plugin.clone(),
+                        });
+                    }
+                    None if dep.required => {
+                        return Err(DependencyError::Unknown {
+                            plugin: plugin.metadata.name.clone(),
+                            dependency: dep.plugin.clone(),
+                        });
+                    }
+                    _ => log::debug!(
+                        "optional dependency {} of plugin {} is not enabled",
+                        dep.plugin,
+                        plugin.metadata.name
+                    ),
+                }
+            }
+        }
+
+        // Topological sort (Kahn's algorithm). To preserve the original order as much as possible,
+        // we always pick the ready plugin that has the lowest index.
+        let mut ready: std::collections::BTreeSet<usize> =
+            enabled.iter().copied().filter(|i| n_pending_deps[*i] == 0).collect();
+        let mut order: Vec<String> = Vec::with_capacity(self.0.len());
+        while let Some(i) = ready.pop_first() {
+            order.push(self.0[i].metadata.name.clone());
+            for &d in &dependents[i] {
+                n_pending_deps[d] -= 1;
+                if n_pending_deps[d] == 0 {
+                    ready.insert(d);
+                }
+            }
+        }
+        if order.len() < enabled.len() {
+            let cycle = enabled
+                .into_iter()
+                .filter(|i| n_pending_deps[*i] > 0)
+                .map(|i| self.0[i].metadata.name.clone())
+                .collect();
+            return Err(DependencyError::Cycle(cycle));
+        }
+
+        // Disabled plugins go last, in their original order.
+        order.extend(self.0.values().filter(|p| !p.enabled).map(|p| p.metadata.name.clone()));
+        self.reorder_partial(&order);
+        Ok(())
+    }
 }
 
 impl PluginFilter {
@@ -329,8 +416,9 @@ mod tests {
 
         use crate::plugin::rust::AlumetPlugin;
 
-        use super::super::{PluginInfo, PluginMetadata, PluginSet, UnknownPluginInConfigPolicy};
+        use super::super::{DependencyError, PluginInfo, PluginMetadata, PluginSet, UnknownPluginInConfigPolicy};
         use super::MyPlugin;
+        use crate::plugin::PluginDependency;
 
         fn plugin_set() -> PluginSet {
             let mut set = PluginSet::new();
@@ -530,6 +618,65 @@ mod tests {
                 vec![String::from("zwei")]
             );
         }
+
+        fn plugin_with_deps(name: &str, enabled: bool, dependencies: Vec<PluginDependency>) -> PluginInfo {
+            let mut metadata = altered_static_metadata::<MyPlugin>(name);
+            metadata.dependencies = dependencies;
+            PluginInfo {
+                metadata,
+                enabled,
+                config: None,
+            }
+        }
+
+        #[test]
+        fn sort_by_dependencies() {
+            let mut set = PluginSet::new();
+            set.add_plugin(plugin_with_deps("a", true, vec![PluginDependency::required("c")]));
+            set.add_plugin(plugin_with_deps("b", false, vec![]));
+            set.add_plugin(plugin_with_deps("c", true, vec![PluginDependency::optional("b")]));
+            set.add_plugin(plugin_with_deps("d", true, vec![PluginDependency::optional("a")]));
+            set.add_plugin(plugin_with_deps("e", true, vec![]));
+
+            set.sort_by_dependencies().expect("dependencies should be satisfied");
+            assert_eq!(set.0.keys().collect::<Vec<_>>(), vec!["c", "a", "d", "e", "b"]);
+        }
+
+        #[test]
+        fn sort_by_dependencies_missing() {
+            let mut set = PluginSet::new();
+            set.add_plugin(plugin_with_deps("a", true, vec![PluginDependency::required("b")]));
+            set.add_plugin(plugin_with_deps("b", false, vec![]));
+            assert_eq!(
+                set.sort_by_dependencies(),
+                Err(DependencyError::Disabled {
+                    plugin: String::from("a"),
+                    dependency: String::from("b")
+                })
+            );
+
+            let mut set = PluginSet::new();
+            set.add_plugin(plugin_with_deps("a", true, vec![PluginDependency::required("zzz")]));
+            assert_eq!(
+                set.sort_by_dependencies(),
+                Err(DependencyError::Unknown {
+                    plugin: String::from("a"),
+                    dependency: String::from("zzz")
+                })
+            );
+        }
+
+        #[test]
+        fn sort_by_dependencies_cycle() {
+            let mut set = PluginSet::new();
+            set.add_plugin(plugin_with_deps("a", true, vec![PluginDependency::required("b")]));
+            set.add_plugin(plugin_with_deps("b", true, vec![PluginDependency::optional("a")]));
+            set.add_plugin(plugin_with_deps("c", true, vec![]));
+            assert_eq!(
+                set.sort_by_dependencies(),
+                Err(DependencyError::Cycle(vec![String::from("a"), String::from("b")]))
+            );
+        }
     }
 
     struct MyPlugin;
@@ -0,0 +1,227 @@
+//! Reloading of the plugins configuration while the agent is running.
+//!
+//! A [`ConfigWatcher`] periodically checks whether the configuration files (the main file, its includes
+//! and the overlays) have been modified. When one of them has, the configuration is loaded again,
+//! and the plugin sections that have changed are delivered to the corresponding plugins,
+//! via [`Plugin::reconfigure`](crate::plugin::Plugin::reconfigure).
+//! This allows plugins to adjust their settings (intervals, filters, credentials, ...) live,
+//! instead of requiring a full restart of the agent.
+//!
+//! Only the configuration of the plugins that are running is reloaded. Enabling or disabling a plugin,
+//! as well as modifying the general options of the agent, still requires a restart.
+//!
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use alumet::agent::{self, reload::{self, ConfigWatcher}};
+//!
+//! # fn f(agent: agent::RunningAgent) -> anyhow::Result<()> {
+//! let file = "alumet-config.toml";
+//! let load = move || Ok(agent::config::Loader::parse_file(file).load_with_files()?);
+//! let watcher = ConfigWatcher::new(file, Duration::from_secs(5), load)?;
+//! reload::run_with_reload(agent, watcher, Duration::MAX)?;
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context;
+use indexmap::IndexMap;
+
+use crate::plugin::{ConfigTable, migration::CONFIG_VERSION_KEY};
+
+use super::{RunningAgent, builder::ShutdownError, config::extract_plugins_config};
+
+/// Loads the whole configuration of the agent, with the same settings as on startup
+/// (environment variables, overrides, etc.), and returns the paths that have been read.
+///
+/// See [`Loader::load_with_files`](super::config::Loader::load_with_files).
+pub type ConfigLoadFn = dyn FnMut() -> anyhow::Result<(toml::Table, Vec<PathBuf>)>;
+
+/// Watches the configuration files and detects changes in the plugin sections.
+pub struct ConfigWatcher {
+    file: PathBuf,
+    check_interval: Duration,
+    load: Box<ConfigLoadFn>,
+    /// Files that have been read by the last load, with their modification time.
+    watched_files: Vec<(PathBuf, Option<SystemTime>)>,
+    plugin_configs: IndexMap<String, toml::Table>,
+}
+
+impl ConfigWatcher {
+    /// Creates a new watcher for the given config file.
+    ///
+    /// The files returned by `load` are checked every `check_interval`. When one of them has been modified,
+    /// `load` is called to get the new configuration. `load` is also called once, here, to get the initial
+    /// configuration and the list of files to watch.
+    pub fn new<F: FnMut() -> anyhow::Result<(toml::Table, Vec<PathBuf>)> + 'static>(
+        file: impl Into<PathBuf>,
+        check_interval: Duration,
+        load: F,
+    ) -> anyhow::Result<Self> {
+        let mut res = Self {
+            file: file.into(),
+            check_interval,
+            load: Box::new(load),
+            watched_files: Vec::new(),
+            plugin_configs: IndexMap::new(),
+        };
+        res.plugin_configs = res.load_plugin_configs()?;
+        Ok(res)
+    }
+
+    /// Checks whether the configuration files have been modified since the last check.
+    ///
+    /// If one of them has, returns the name and new configuration of each plugin whose section has changed.
+    pub fn poll_changes(&mut self) -> anyhow::Result<Vec<(String, toml::Table)>> {
+        let mut modified = false;
+        for (file, last_modified) in &mut self.watched_files {
+            let time = modification_time(file);
+            if time != *last_modified {
+                *last_modified = time;
+                modified = true;
+            }
+        }
+        if !modified {
+            return Ok(Vec::new());
+        }
+
+        let new_configs = self.load_plugin_configs()?;
+        let changes = new_configs
+            .iter()
+            .filter(|(plugin, config)| self.plugin_configs.get(*plugin) != Some(config))
+            .map(|(plugin, config)| (plugin.clone(), config.clone()))
+            .collect();
+        self.plugin_configs = new_configs;
+        Ok(changes)
+    }
+
+    fn load_plugin_configs(&mut self) -> anyhow::Result<IndexMap<String, toml::Table>> {
+        let (mut config, files) = (self.load)().with_context(|| format!("failed to load {}", self.file.display()))?;
+        self.watched_files = files
+            .into_iter()
+            .map(|file| {
+                let last_modified = modification_time(&file);
+                (file, last_modified)
+            })
+            .collect();
+        let plugins = extract_plugins_config(&mut config)?;
+        Ok(plugins
+            .into_iter()
+            .map(|(plugin, (_enabled, mut config))| {
+                // migrations are only applied on startup
+                config.remove(CONFIG_VERSION_KEY);
+                (plugin, config)
+            })
+            .collect())
+    }
+}
+
+fn modification_time(file: &Path) -> Option<SystemTime> {
+    std::fs::metadata(file).and_then(|m| m.modified()).ok()
+}
+
+/// Runs the agent until the pipeline is shut down, reconfiguring the plugins when their config changes.
+///
+/// Like [`RunningAgent::wait_for_shutdown`], this function handles the requests to enable or disable a plugin.
+/// Errors that occur while reloading the config are logged, they do not stop the agent.
+/// After the pipeline is shut down, the agent must stop within `shutdown_timeout`, or an error is returned.
+pub fn run_with_reload(
+    mut agent: RunningAgent,
+    mut watcher: ConfigWatcher,
+    shutdown_timeout: Duration,
+) -> Result<(), ShutdownError> {
+    let rt = agent.pipeline.async_runtime().clone();
+    loop {
+        let event = rt.block_on(async {
+            tokio::select! {
+                request = agent.next_plugin_request() => Some(request),
+                _ = tokio::time::sleep(watcher.check_interval) => None,
+            }
+        });
+        match event {
+            Some(Some(request)) => {
+                agent.handle_plugin_request(request);
+                continue;
+            }
+            Some(None) => break, // shutdown
+            None => (),          // time to check the config files
+        }
+        match watcher.poll_changes() {
+            Ok(changes) => {
+                for (plugin, config) in changes {
+                    if !agent.initialized_plugins.iter().any(|p| p.name() == plugin) {
+                        log::warn!("The config of plugin {plugin} has changed, but the plugin is not running.");
+                        continue;
+                    }
+                    log::info!("The config of plugin {plugin} has changed, reconfiguring the plugin...");
+                    if let Err(e) = agent.reconfigure_plugin(&plugin, ConfigTable(config)) {
+                        log::error!("Failed to apply the new config of plugin {plugin}: {e:?}");
+                    }
+                }
+            }
+            Err(e) => log::error!("Failed to reload the configuration: {e:?}"),
+        }
+    }
+    agent.wait_for_shutdown(shutdown_timeout)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ConfigWatcher;
+    use crate::agent::config::Loader;
+
+    #[test]
+    fn detect_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.toml");
+        std::fs::write(&file, "[plugins.a]\nx = 1\n[plugins.b]\ny = 2\n").unwrap();
+
+        let file_clone = file.clone();
+        let load = move || {
+            let config = std::fs::read_to_string(&file_clone)?.parse::<toml::Table>()?;
+            Ok((config, vec![file_clone.clone()]))
+        };
+        let mut watcher = ConfigWatcher::new(&file, Duration::from_secs(1), load).unwrap();
+        assert!(watcher.poll_changes().unwrap().is_empty());
+
+        // modify the config of b, and force a new modification time
+        std::fs::write(&file, "[plugins.a]\nx = 1\n[plugins.b]\ny = 3\n").unwrap();
+        watcher.watched_files[0].1 = None;
+        let changes = watcher.poll_changes().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "b");
+        assert_eq!(changes[0].1.get("y"), Some(&toml::Value::Integer(3)));
+
+        // no modification
+        assert!(watcher.poll_changes().unwrap().is_empty());
+    }
+
+    #[test]
+    fn detect_changes_in_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("config.toml");
+        let included = dir.path().join("included.toml");
+        std::fs::write(&file, "include = 'included.toml'\n[plugins.a]\nx = 1\n").unwrap();
+        std::fs::write(&included, "[plugins.b]\ny = 2\n").unwrap();
+
+        let file_clone = file.clone();
+        let load = move || Ok(Loader::parse_file(&file_clone).load_with_files()?);
+        let mut watcher = ConfigWatcher::new(&file, Duration::from_secs(1), load).unwrap();
+        let watched: Vec<_> = watcher.watched_files.iter().map(|(f, _)| f.clone()).collect();
+        assert_eq!(watched, vec![file.clone(), included.clone()]);
+
+        // modify the included file only, and force a new modification time
+        std::fs::write(&included, "[plugins.b]\ny = 3\n").unwrap();
+        watcher.watched_files[1].1 = None;
+        let changes = watcher.poll_changes().unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "b");
+        assert_eq!(changes[0].1.get("y"), Some(&toml::Value::Integer(3)));
+    }
+}
@@ -0,0 +1,173 @@
+//! Timeouts of the lifecycle hooks of the plugins.
+//!
+//! A plugin can hang in one of its hooks, for instance when it probes a sensor on a dead i2c bus.
+//! [`PluginTimeouts`] bounds the duration of each hook: when a hook takes too long, the plugin fails
+//! but the rest of the agent keeps running.
+//!
+//! - [`init`](crate::plugin::PluginMetadata::init) and [`stop`](crate::plugin::Plugin::stop) run on a separate thread.
+//!   When their timeout expires, the agent stops waiting for them and continues without the plugin.
+//!   The thread is abandoned.
+//! - [`start`](crate::plugin::Plugin::start) and [`post_pipeline_start`](crate::plugin::Plugin::post_pipeline_start)
+//!   borrow the pipeline, they run on the thread of the agent. When their timeout expires, an error is logged.
+//!   When they return, the plugin is failed: the elements that it has registered are removed from the pipeline
+//!   (`start`) or disabled (`post_pipeline_start`). A hook that never returns blocks the startup of the agent.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+/// Maximum duration of each lifecycle hook of the plugins.
+///
+/// Use [`Duration::MAX`] to disable a timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PluginTimeouts {
+    pub init: Duration,
+    pub start: Duration,
+    pub post_pipeline_start: Duration,
+    pub stop: Duration,
+}
+
+impl Default for PluginTimeouts {
+    fn default() -> Self {
+        Self {
+            init: Duration::from_secs(30),
+            start: Duration::from_secs(30),
+            post_pipeline_start: Duration::from_secs(30),
+            stop: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A lifecycle hook of a plugin did not complete in time.
+#[derive(Debug, Error)]
+#[error("{hook} of plugin {plugin} did not complete within {timeout:?}")]
+pub struct HookTimeout {
+    pub plugin: String,
+    pub hook: &'static str,
+    pub timeout: Duration,
+}
+
+/// Runs a hook on a new thread, and waits at most `timeout` for its result.
+///
+/// If the hook panics, the panic is propagated to the calling thread.
+/// If the timeout expires, the thread is abandoned.
+pub(crate) fn run_detached<T: Send + 'static>(
+    plugin: &str,
+    hook: &'static str,
+    timeout: Duration,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, HookTimeout> {
+    let (tx, rx) = mpsc::sync_channel(1);
+    thread::Builder::new()
+        .name(format!("{plugin}-{hook}"))
+        .spawn(move || {
+            let res = panic::catch_unwind(AssertUnwindSafe(f));
+            // the receiver is gone if the timeout has expired
+            let _ = tx.send(res);
+        })
+        .expect("failed to spawn a thread for the plugin hook");
+
+    match rx.recv_timeout(timeout) {
+        Ok(Ok(res)) => Ok(res),
+        Ok(Err(panic_payload)) => panic::resume_unwind(panic_payload),
+        Err(RecvTimeoutError::Timeout) => Err(HookTimeout {
+            plugin: plugin.to_owned(),
+            hook,
+            timeout,
+        }),
+        Err(RecvTimeoutError::Disconnected) => unreachable!("the hook thread always sends its result"),
+    }
+}
+
+/// Watches a hook that runs on the current thread, and logs an error if it does not complete in time.
+pub(crate) struct Watchdog {
+    plugin: String,
+    hook: &'static str,
+    timeout: Duration,
+    begin: Instant,
+    /// Dropped when the hook completes, which stops the watchdog thread.
+    _done: Option<mpsc::Sender<()>>,
+}
+
+impl Watchdog {
+    pub fn new(plugin: &str, hook: &'static str, timeout: Duration) -> Self {
+        let done = (timeout != Duration::MAX).then(|| {
+            let (tx, rx) = mpsc::channel::<()>();
+            let (plugin, timeout) = (plugin.to_owned(), timeout);
+            thread::Builder::new()
+                .name(format!("{plugin}-watchdog"))
+                .spawn(move || {
+                    if let Err(RecvTimeoutError::Timeout) = rx.recv_timeout(timeout) {
+                        log::error!(
+                            "Plugin {plugin} is blocked: {hook} did not complete within {timeout:?}. The plugin will be disabled when {hook} returns."
+                        );
+                    }
+                })
+                .expect("failed to spawn the watchdog thread");
+            tx
+        });
+        Self {
+            plugin: plugin.to_owned(),
+            hook,
+            timeout,
+            begin: Instant::now(),
+            _done: done,
+        }
+    }
+
+    /// Stops watching the hook, and checks that it has completed in time.
+    pub fn finish(self) -> Result<(), HookTimeout> {
+        if self.begin.elapsed() > self.timeout {
+            Err(HookTimeout {
+                plugin: self.plugin,
+                hook: self.hook,
+                timeout: self.timeout,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::{Watchdog, run_detached};
+
+    #[test]
+    fn detached() {
+        assert_eq!(run_detached("p", "init", Duration::from_secs(5), || 42).unwrap(), 42);
+
+        let err = run_detached("p", "init", Duration::from_millis(50), || {
+            thread::sleep(Duration::from_secs(5));
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "init of plugin p did not complete within 50ms");
+    }
+
+    #[test]
+    #[should_panic(expected = "init panicked")]
+    fn detached_panic() {
+        let _ = run_detached("p", "init", Duration::MAX, || panic!("init panicked"));
+    }
+
+    #[test]
+    fn watchdog() {
+        let watchdog = Watchdog::new("p", "start", Duration::from_secs(5));
+        assert!(watchdog.finish().is_ok());
+
+        let watchdog = Watchdog::new("p", "start", Duration::from_millis(10));
+        thread::sleep(Duration::from_millis(50));
+        let err = watchdog.finish().unwrap_err();
+        assert_eq!(err.hook, "start");
+
+        let watchdog = Watchdog::new("p", "start", Duration::MAX);
+        assert!(watchdog.finish().is_ok());
+    }
+}
@@ -466,6 +466,12 @@ impl MeasurementBuffer {
         self.points.clear();
     }
 
+    /// Retains only the measurements for which `f` returns `true`.
+    /// See [`Vec::retain`].
+    pub fn retain(&mut self, f: impl FnMut(&MeasurementPoint) -> bool) {
+        self.points.retain(f);
+    }
+
     /// Creates an iterator on the buffer's content.
     pub fn iter(&self) -> impl Iterator<Item = &MeasurementPoint> {
         self.points.iter()
@@ -608,4 +614,25 @@ mod tests {
             assert_eq!(c, c_different_order);
         }
     }
+
+    mod measurement_buffer {
+        use super::*;
+
+        #[test]
+        fn retain() {
+            let mut buf = MeasurementBuffer::new();
+            for value in 0..10 {
+                buf.push(MeasurementPoint::new_untyped(
+                    UNIX_EPOCH.into(),
+                    RawMetricId::from_u64(0),
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    WrappedMeasurementValue::U64(value),
+                ));
+            }
+            buf.retain(|p| p.value.as_u64() % 2 == 0);
+            let values: Vec<u64> = buf.iter().map(|p| p.value.as_u64()).collect();
+            assert_eq!(values, vec![0, 2, 4, 6, 8]);
+        }
+    }
 }
@@ -1,4 +1,6 @@
 //! Construction of measurement pipelines.
+use std::marker::PhantomData;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, anyhow};
@@ -10,7 +12,9 @@ use tokio::{
 };
 use tokio_util::sync::CancellationToken;
 
-use crate::measurement::MeasurementBuffer;
+use crate::measurement::{MeasurementBuffer, WrappedMeasurementType};
+use crate::metrics::def::{Metric, TypedMetricId};
+use crate::metrics::duplicate::{DuplicateCriteria, DuplicateReaction};
 use crate::metrics::online::listener::MetricListenerBuilder;
 use crate::metrics::online::{MetricReader, MetricRegistryControl, MetricSender};
 use crate::metrics::registry::MetricRegistry;
@@ -20,10 +24,14 @@ use crate::pipeline::elements::output::control::OutputControl;
 use crate::pipeline::elements::source::control::SourceControl;
 use crate::pipeline::elements::transform::control::TransformControl;
 use crate::pipeline::util::channel;
+use crate::plugin::health::{HEALTH_METRIC_NAME, HealthRegistry, HealthSource};
+use crate::units::Unit;
 
+use super::clock::{self, Clock};
 use super::elements::output::builder::OutputBuilder;
-use super::elements::source::builder::SourceBuilder;
-use super::elements::source::trigger::TriggerConstraints;
+use super::elements::source::builder::{ManagedSource, SourceBuilder};
+use super::elements::source::control::TaskState;
+use super::elements::source::trigger::{TriggerConstraints, TriggerSpec};
 use super::elements::transform::builder::TransformBuilder;
 use super::error::PipelineError;
 use super::naming::{
@@ -32,7 +40,7 @@ use super::naming::{
 };
 use super::{
     control::key::{OutputKey, SourceKey, TransformKey},
-    control::{AnonymousControlHandle, PipelineControl},
+    control::{self, AnonymousControlHandle, PipelineControl, request::CreationRequest},
     util,
 };
 
@@ -44,6 +52,7 @@ pub struct MeasurementPipeline {
     metrics: (MetricSender, MetricReader),
     pipeline_control_task: JoinHandle<Result<(), PipelineError>>,
     metrics_control_task: JoinHandle<()>,
+    pub(crate) health: HealthRegistry,
 }
 
 /// A Builder for [`MeasurementPipeline`].
@@ -75,6 +84,20 @@ pub struct Builder {
     pub(crate) metrics: MetricRegistry,
     metric_listeners: Namespace2<Box<dyn MetricListenerBuilder>>,
 
+    /// Health reports of the plugins.
+    pub(crate) health: HealthRegistry,
+    /// Poll interval of the source that measures the health of the plugins, if enabled.
+    health_metrics_interval: Option<Duration>,
+
+    /// Shut the pipeline down when all the sources have finished.
+    shutdown_when_sources_finish: bool,
+
+    /// Clock used by the triggers and to timestamp the measurements.
+    clock: Arc<dyn Clock>,
+
+    /// Forwards the requests to enable or disable plugins, set by the agent.
+    pub(crate) plugin_requests: Option<mpsc::Sender<control::messages::PluginRequestMessage>>,
+
     // tokio::Runtime settings.
     threads_normal: Option<usize>,
     threads_high_priority: Option<usize>,
@@ -100,6 +123,11 @@ impl Builder {
             allow_simplified_pipeline: true,
             metrics: MetricRegistry::new(),
             metric_listeners: Namespace2::new(),
+            health: HealthRegistry::default(),
+            health_metrics_interval: None,
+            shutdown_when_sources_finish: false,
+            clock: clock::system(),
+            plugin_requests: None,
             threads_normal: None, // default to the number of cores
             threads_high_priority: None,
         }
@@ -124,6 +152,33 @@ impl Builder {
         &mut self.allow_simplified_pipeline
     }
 
+    /// Returns a mutable reference to the poll interval of the `alumet/health` source.
+    ///
+    /// If it is set to `Some(interval)`, the pipeline measures the health status reported by
+    /// the plugins (see [`health`](crate::plugin::health)) every `interval`, as the metric `alumet_plugin_health`.
+    /// The default is `None`: the health status is not measured.
+    pub fn health_metrics_interval(&mut self) -> &mut Option<Duration> {
+        &mut self.health_metrics_interval
+    }
+
+    /// Returns a mutable reference to the "shutdown when the sources finish" setting.
+    ///
+    /// If it is set to `true`, the pipeline shuts down once all its sources have finished,
+    /// after the transforms and outputs have processed the remaining measurements.
+    /// This is useful to process a bounded input, such as recorded measurements.
+    /// The default is `false`: the pipeline runs until it is explicitly shut down.
+    pub fn shutdown_when_sources_finish(&mut self) -> &mut bool {
+        &mut self.shutdown_when_sources_finish
+    }
+
+    /// Returns a mutable reference to the clock of the pipeline.
+    ///
+    /// The clock triggers the managed sources and timestamps their measurements.
+    /// The default is the [`SystemClock`](clock::SystemClock). Tests can use a [`MockClock`](clock::MockClock) instead.
+    pub fn clock(&mut self) -> &mut Arc<dyn Clock> {
+        &mut self.clock
+    }
+
     /// Registers a listener that will be notified of the metrics that are created while the pipeline is running,
     /// with a dedicated builder.
     pub fn add_metric_listener_builder(
@@ -178,6 +233,27 @@ impl Builder {
         }
     }
 
+    /// Returns the names of the transforms, as `(plugin, transform)` pairs.
+    pub(crate) fn transform_names(&self) -> impl Iterator<Item = &(String, String)> {
+        self.transforms.flat_keys()
+    }
+
+    /// Returns the names of the metric listeners, as `(plugin, listener)` pairs.
+    pub(crate) fn metric_listener_names(&self) -> impl Iterator<Item = &(String, String)> {
+        self.metric_listeners.flat_keys()
+    }
+
+    /// Removes all the sources, transforms, outputs and metric listeners of a plugin.
+    ///
+    /// The metrics created by the plugin are kept.
+    pub(crate) fn remove_plugin_elements(&mut self, plugin: &PluginName) {
+        self.sources.remove_all(&plugin.0);
+        self.transforms.remove_all(&plugin.0);
+        self.outputs.remove_all(&plugin.0);
+        self.metric_listeners.remove_all(&plugin.0);
+        self.default_transforms_order.retain(|t| t.plugin() != plugin.0);
+    }
+
     /// Sets the number of non-high-priority threads to use.
     ///
     /// # Default
@@ -277,6 +353,35 @@ impl Builder {
             Ok(res)
         }
 
+        // Measure the health of the plugins, if requested.
+        if let Some(poll_interval) = self.health_metrics_interval {
+            let metric = Metric {
+                name: String::from(HEALTH_METRIC_NAME),
+                description: String::from("health status of the plugin: 0 = healthy, 1 = degraded, 2 = unhealthy"),
+                value_type: WrappedMeasurementType::U64,
+                unit: Unit::Unity.into(),
+            };
+            let metric_id = self
+                .metrics
+                .register(metric, DuplicateCriteria::Incompatible, DuplicateReaction::Error)
+                .context("could not register the health metric")?;
+            let source = HealthSource {
+                registry: self.health.clone(),
+                metric: TypedMetricId(metric_id, PhantomData),
+            };
+            let trigger_spec = TriggerSpec::at_interval(poll_interval);
+            let builder = SourceBuilder::Managed(Box::new(move |_| {
+                Ok(ManagedSource {
+                    trigger_spec,
+                    initial_state: TaskState::Run,
+                    source: Box::new(source),
+                })
+            }));
+            self.sources
+                .add(String::from("alumet"), String::from("health"), builder)
+                .context("could not add the health source")?;
+        }
+
         // Tokio runtime backed by "real-time" high priority threads.
         let rt_priority: Option<Runtime> = if self.threads_high_priority == Some(0) {
             None
@@ -340,7 +445,12 @@ impl Builder {
 
             // Outputs
             let out_rx_provider = channel::ReceiverProvider::from(in_rx);
-            output_control = OutputControl::new(out_rx_provider, rt_handle.clone(), metrics_r.clone());
+            output_control = OutputControl::new(
+                out_rx_provider,
+                rt_handle.clone(),
+                metrics_r.clone(),
+                self.health.clone(),
+            );
             output_control
                 .blocking_create_outputs(self.outputs)
                 .context("output creation failed")?;
@@ -353,7 +463,12 @@ impl Builder {
 
             // Outputs
             let out_rx_provider = channel::ReceiverProvider::from(out_tx.clone());
-            output_control = OutputControl::new(out_rx_provider, rt_handle.clone(), metrics_r.clone());
+            output_control = OutputControl::new(
+                out_rx_provider,
+                rt_handle.clone(),
+                metrics_r.clone(),
+                self.health.clone(),
+            );
             output_control
                 .blocking_create_outputs(self.outputs)
                 .context("output creation failed")?;
@@ -361,25 +476,40 @@ impl Builder {
             // Transforms
             let order = self.transforms_order.unwrap_or(self.default_transforms_order);
             let transforms = take_transforms_in_order(self.transforms, order)?;
-            transform_control =
-                TransformControl::with_transforms(transforms, metrics_r.clone(), in_rx, out_tx, rt_handle)?;
+            transform_control = TransformControl::with_transforms(
+                transforms,
+                metrics_r.clone(),
+                in_rx,
+                out_tx,
+                rt_handle,
+                self.health.clone(),
+            )?;
         };
 
         // Sources, last in order not to loose any measurement if they start measuring right away.
         let mut source_control = SourceControl::new(
             self.trigger_constraints,
+            self.clock,
             pipeline_shutdown.clone(),
             in_tx,
             rt_handle.clone(),
             rt_priority.as_ref().unwrap_or(&rt_normal).handle().clone(),
             (metrics_r.clone(), metrics_tx.clone()),
+            self.health.clone(),
         );
         source_control
             .blocking_create_sources(self.sources)
             .context("source creation failed")?;
 
         // Pipeline control
-        let control = PipelineControl::new(source_control, transform_control, output_control);
+        let control = PipelineControl::new(
+            source_control,
+            transform_control,
+            output_control,
+            self.health.clone(),
+            self.shutdown_when_sources_finish,
+            self.plugin_requests,
+        );
         let (control_handle, control_join) = control.start(pipeline_shutdown, pipeline_shutdown_finalize, rt_handle);
 
         // Done!
@@ -390,9 +520,112 @@ impl Builder {
             metrics: (metrics_tx, metrics_r),
             pipeline_control_task: control_join,
             metrics_control_task: metrics_join,
+            health: self.health,
         })
     }
 
+    /// Builds the sources and outputs, in order to add them to a pipeline that is already running.
+    ///
+    /// The elements are built on the current thread, because their builders are not `Send`.
+    /// The returned request creates them in `pipeline`, it must be sent with a handle of the plugin that
+    /// has registered the elements. The metrics must have been registered in `pipeline` beforehand.
+    ///
+    /// Transforms and metric listeners cannot be added to a running pipeline: they are not part of the request.
+    /// Use [`transform_names`](Self::transform_names) and [`metric_listener_names`](Self::metric_listener_names)
+    /// to check that they already exist in the pipeline.
+    pub(crate) fn into_creation_request(self, pipeline: &MeasurementPipeline) -> anyhow::Result<CreationRequest> {
+        use crate::pipeline::elements::output::{self, AsyncOutputStream};
+        use crate::pipeline::elements::source;
+        use futures::StreamExt;
+
+        let mut request = control::request::create_many();
+        let metrics_r = pipeline.metrics_reader();
+        let metrics_tx = pipeline.metrics_sender();
+
+        for ((_, name), builder) in self.sources {
+            let mut ctx = source::builder::BuildContext {
+                metrics: &self.metrics,
+                metrics_r: &metrics_r,
+                metrics_tx: &metrics_tx,
+            };
+            match builder {
+                SourceBuilder::Managed(build) => {
+                    let source = build(&mut ctx).with_context(|| format!("managed source creation failed: {name}"))?;
+                    request.add_source_with_state(&name, source.source, source.trigger_spec, source.initial_state);
+                }
+                SourceBuilder::Autonomous(build) => {
+                    // The source gets its own channel and token, which are connected to the ones of the pipeline
+                    // when the source is created by the control loop.
+                    let (tx, mut rx) = mpsc::channel::<MeasurementBuffer>(self.source_channel_size);
+                    let token = CancellationToken::new();
+                    let source = build(&mut ctx, token.clone(), tx)
+                        .with_context(|| format!("autonomous source creation failed: {name}"))?;
+                    request.add_autonomous_source_builder(&name, move |_, pipeline_token, pipeline_tx| {
+                        let forward = async move {
+                            while let Some(buf) = rx.recv().await {
+                                if pipeline_tx.send(buf).await.is_err() {
+                                    break;
+                                }
+                            }
+                        };
+                        Ok(Box::pin(async move {
+                            let cancel = async {
+                                tokio::select! {
+                                    _ = pipeline_token.cancelled() => token.cancel(),
+                                    _ = token.cancelled() => (),
+                                }
+                            };
+                            let run = async {
+                                let res = source.await;
+                                token.cancel();
+                                res
+                            };
+                            let (res, _, _) = tokio::join!(run, forward, cancel);
+                            res
+                        }))
+                    });
+                }
+            }
+        }
+
+        for ((_, name), builder) in self.outputs {
+            let mut ctx = output::builder::OutputBuildContext {
+                metrics_r: &metrics_r,
+                metrics: &self.metrics,
+                runtime: pipeline.async_runtime().clone(),
+            };
+            match builder {
+                OutputBuilder::Blocking(build) => {
+                    let output = build(&mut ctx).with_context(|| format!("output creation failed: {name}"))?;
+                    request.add_blocking_output(&name, output);
+                }
+                OutputBuilder::Async(build) => {
+                    // The output gets its own stream, which is fed by the stream that the control loop provides.
+                    let (tx, rx) = mpsc::channel(self.source_channel_size);
+                    let stream = AsyncOutputStream(Box::pin(tokio_stream::wrappers::ReceiverStream::new(rx)));
+                    let output = {
+                        let _guard = pipeline.async_runtime().enter();
+                        build(&mut ctx, stream).with_context(|| format!("output creation failed: {name}"))?
+                    };
+                    request.add_async_output_builder(&name, move |_, mut pipeline_stream| {
+                        let forward = async move {
+                            while let Some(item) = pipeline_stream.0.next().await {
+                                if tx.send(item).await.is_err() {
+                                    break;
+                                }
+                            }
+                        };
+                        Ok(Box::pin(async move {
+                            let (res, _) = tokio::join!(output, forward);
+                            res
+                        }))
+                    });
+                }
+            }
+        }
+        Ok(request.build())
+    }
+
     /// Inspects the current state of the builder.
     ///
     /// # Example
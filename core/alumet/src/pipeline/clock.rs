@@ -0,0 +1,189 @@
+//! Clocks of the measurement pipeline.
+//!
+//! The pipeline reads the time from a [`Clock`]: the triggers of the managed sources wait on it, and the
+//! measurements are timestamped with it. By default, the pipeline uses the [`SystemClock`].
+//!
+//! In tests, use a [`MockClock`] to control the time. The sources are only polled when the clock is
+//! advanced, and every poll gets a predictable timestamp.
+//!
+//! # Example
+//! ```no_run
+//! use std::{sync::Arc, time::Duration};
+//!
+//! use alumet::pipeline;
+//! use alumet::pipeline::clock::MockClock;
+//!
+//! let clock = MockClock::new();
+//! let mut builder = pipeline::Builder::new();
+//! *builder.clock() = Arc::new(clock.clone());
+//! // register sources, build the pipeline, etc.
+//! // ...
+//!
+//! // trigger the sources that poll every second, three times
+//! for _ in 0..3 {
+//!     clock.advance(Duration::from_secs(1));
+//! }
+//! ```
+
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use tokio::sync::watch;
+
+use super::elements::source::trigger::BoxFuture;
+use crate::measurement::Timestamp;
+
+/// A source of time for the pipeline.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Returns the current time, which is used to timestamp the measurements.
+    fn now(&self) -> Timestamp;
+
+    /// Returns the current instant of the monotonic clock, which is used by the triggers.
+    fn instant(&self) -> Instant;
+
+    /// Waits until the monotonic clock reaches `deadline`.
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()>;
+
+    /// Returns `true` if the clock follows the time of the operating system.
+    ///
+    /// On Linux, the triggers use a more precise timer (timerfd) with such clocks.
+    fn is_system_clock(&self) -> bool {
+        false
+    }
+}
+
+/// The clock of the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep_until(deadline.into()))
+    }
+
+    fn is_system_clock(&self) -> bool {
+        true
+    }
+}
+
+/// A clock that only moves when it is [advanced](Self::advance).
+///
+/// Cloning a `MockClock` returns a handle to the same clock.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    inner: Arc<MockClockInner>,
+}
+
+#[derive(Debug)]
+struct MockClockInner {
+    start_time: SystemTime,
+    start_instant: Instant,
+    /// Time elapsed since the creation of the clock.
+    elapsed: watch::Sender<Duration>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockClock {
+    /// Creates a clock that starts at the current time of the system.
+    pub fn new() -> Self {
+        Self::starting_at(SystemTime::now())
+    }
+
+    /// Creates a clock that starts at the given time.
+    pub fn starting_at(time: SystemTime) -> Self {
+        Self {
+            inner: Arc::new(MockClockInner {
+                start_time: time,
+                start_instant: Instant::now(),
+                elapsed: watch::Sender::new(Duration::ZERO),
+            }),
+        }
+    }
+
+    /// Moves the clock forward, and wakes up the triggers whose deadline has been reached.
+    pub fn advance(&self, duration: Duration) {
+        self.inner.elapsed.send_modify(|elapsed| *elapsed += duration);
+    }
+
+    /// Returns the time elapsed since the creation of the clock.
+    pub fn elapsed(&self) -> Duration {
+        *self.inner.elapsed.borrow()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::from(self.inner.start_time + self.elapsed())
+    }
+
+    fn instant(&self) -> Instant {
+        self.inner.start_instant + self.elapsed()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> BoxFuture<'static, ()> {
+        let remaining = deadline.saturating_duration_since(self.inner.start_instant);
+        let mut elapsed = self.inner.elapsed.subscribe();
+        Box::pin(async move {
+            // The sender lives as long as the clock, which outlives the pipeline.
+            let _ = elapsed.wait_for(|elapsed| *elapsed >= remaining).await;
+        })
+    }
+}
+
+/// Returns the default clock of the pipeline.
+pub(crate) fn system() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use super::{Clock, MockClock};
+
+    #[test]
+    fn mock_clock() {
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let clock = MockClock::starting_at(t0);
+        let i0 = clock.instant();
+        assert_eq!(clock.now(), t0.into());
+
+        clock.clone().advance(Duration::from_millis(1500));
+        assert_eq!(clock.elapsed(), Duration::from_millis(1500));
+        assert_eq!(clock.now(), (t0 + Duration::from_millis(1500)).into());
+        assert_eq!(clock.instant() - i0, Duration::from_millis(1500));
+    }
+
+    #[tokio::test]
+    async fn mock_sleep() {
+        let clock = MockClock::new();
+        let deadline = clock.instant() + Duration::from_secs(10);
+        let sleep = tokio::spawn(clock.sleep_until(deadline));
+
+        clock.advance(Duration::from_secs(5));
+        tokio::task::yield_now().await;
+        assert!(!sleep.is_finished());
+
+        clock.advance(Duration::from_secs(5));
+        tokio::time::timeout(Duration::from_secs(1), sleep)
+            .await
+            .expect("the sleep should end when the clock reaches the deadline")
+            .unwrap();
+    }
+}
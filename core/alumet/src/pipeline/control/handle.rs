@@ -74,6 +74,16 @@ impl AnonymousControlHandle {
         self.shutdown_token.cancel();
     }
 
+    /// Returns `true` if the shutdown of the pipeline has been requested.
+    pub fn is_shutdown_requested(&self) -> bool {
+        self.shutdown_token.is_cancelled()
+    }
+
+    /// Waits until the shutdown of the pipeline is requested.
+    pub(crate) async fn shutdown_requested(&self) {
+        self.shutdown_token.cancelled().await
+    }
+
     /// Sends a control request to the pipeline, without waiting for a response.
     ///
     /// # Errors
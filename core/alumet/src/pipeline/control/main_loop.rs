@@ -3,6 +3,7 @@ use crate::pipeline::control::messages::RequestMessage;
 use crate::pipeline::error::PipelineError;
 
 use crate::pipeline::elements::{output, source, transform};
+use crate::plugin::health::HealthRegistry;
 
 use anyhow::anyhow;
 use tokio::runtime;
@@ -18,6 +19,11 @@ pub(crate) struct PipelineControl {
     sources: source::control::SourceControl,
     transforms: transform::control::TransformControl,
     outputs: output::control::OutputControl,
+    health: HealthRegistry,
+    /// If true, shut the pipeline down when all the sources have finished.
+    shutdown_when_sources_finish: bool,
+    /// Forwards the plugin requests to the agent, if the pipeline is run by an agent.
+    plugin_requests: Option<mpsc::Sender<messages::PluginRequestMessage>>,
 }
 
 impl PipelineControl {
@@ -25,11 +31,17 @@ impl PipelineControl {
         sources: source::control::SourceControl,
         transforms: transform::control::TransformControl,
         outputs: output::control::OutputControl,
+        health: HealthRegistry,
+        shutdown_when_sources_finish: bool,
+        plugin_requests: Option<mpsc::Sender<messages::PluginRequestMessage>>,
     ) -> Self {
         Self {
             sources,
             transforms,
             outputs,
+            health,
+            shutdown_when_sources_finish,
+            plugin_requests,
         }
     }
 
@@ -102,6 +114,27 @@ impl PipelineControl {
                 };
                 send_response(result, response_tx)
             }
+            messages::ControlRequest::Health(RequestMessage { response_tx, body: () }) => {
+                send_response(Ok(self.health.snapshot()), response_tx)
+            }
+            messages::ControlRequest::Failures(RequestMessage { response_tx, body: () }) => {
+                send_response(Ok(self.health.failures()), response_tx)
+            }
+            messages::ControlRequest::Plugin(msg) => {
+                // The agent responds when it has handled the request. Don't wait for it here:
+                // the agent sends other requests to the pipeline while it enables or disables the plugin.
+                let Some(plugin_requests) = &self.plugin_requests else {
+                    let err = anyhow!("the pipeline is not run by an agent, its plugins cannot be enabled or disabled");
+                    return send_response(Err(PipelineError::internal(err)), msg.response_tx);
+                };
+                match plugin_requests.try_send(msg) {
+                    Ok(()) => Ok(()),
+                    Err(mpsc::error::TrySendError::Full(msg) | mpsc::error::TrySendError::Closed(msg)) => {
+                        let err = anyhow!("the agent cannot handle the plugin request {:?}", msg.body);
+                        send_response(Err(PipelineError::internal(err)), msg.response_tx)
+                    }
+                }
+            }
         }
     }
 
@@ -197,6 +230,10 @@ impl PipelineControl {
 
                 res = self.sources.join_next_task(), if self.sources.has_task() => {
                     task_finished(res, "source", &mut last_error);
+                    if self.shutdown_when_sources_finish && !self.sources.has_task() {
+                        log::info!("All the sources have finished, shutting down...");
+                        init_shutdown.cancel();
+                    }
                 },
                 res = self.transforms.join_next_task(), if self.transforms.has_task() => {
                     task_finished(res, "transform", &mut last_error);
@@ -4,8 +4,9 @@ use crate::pipeline::{
     elements::{output, source, transform},
     error::PipelineError,
     matching::ElementNamePattern,
-    naming::ElementName,
+    naming::{ElementName, PluginName},
 };
+use crate::plugin::health::{FailureCount, HealthReport};
 
 pub type Receiver = mpsc::Receiver<ControlRequest>;
 pub type Sender = mpsc::Sender<ControlRequest>;
@@ -14,14 +15,17 @@ pub type Sender = mpsc::Sender<ControlRequest>;
 pub enum ControlRequest {
     NoResult(RequestMessage<EmptyResponseBody, ()>),
     Introspect(RequestMessage<IntrospectionBody, IntrospectionResponse>),
+    Health(RequestMessage<(), HealthResponse>),
+    Failures(RequestMessage<(), FailuresResponse>),
+    Plugin(PluginRequestMessage),
 }
 
 pub type ResponseSender<R> = oneshot::Sender<Result<R, PipelineError>>;
 
 #[derive(Debug)]
 pub struct RequestMessage<Body, Response> {
-    pub(super) response_tx: Option<ResponseSender<Response>>,
-    pub(super) body: Body,
+    pub(crate) response_tx: Option<ResponseSender<Response>>,
+    pub(crate) body: Body,
 }
 
 #[derive(Debug)]
@@ -37,9 +41,24 @@ pub enum SpecificBody {
     Output(output::control::ControlMessage),
 }
 
+/// Enables or disables a plugin.
+///
+/// Plugins are owned by the agent, not by the pipeline, therefore this request is forwarded to the agent.
+#[derive(Debug)]
+pub enum PluginBody {
+    Enable(PluginName),
+    Disable(PluginName),
+}
+
+pub type PluginRequestMessage = RequestMessage<PluginBody, ()>;
+
 #[derive(Debug)]
 pub enum IntrospectionBody {
     ListElements(ElementNamePattern),
 }
 
 pub type IntrospectionResponse = Vec<ElementName>;
+
+pub type HealthResponse = Vec<(PluginName, HealthReport)>;
+
+pub type FailuresResponse = Vec<(ElementName, FailureCount)>;
@@ -3,7 +3,7 @@ pub mod handle;
 pub mod key;
 mod main_loop;
 pub mod matching;
-mod messages;
+pub(crate) mod messages;
 pub mod request;
 
 pub use handle::{AnonymousControlHandle, PluginControlHandle};
@@ -20,14 +20,18 @@ use crate::pipeline::{error::PipelineError, naming::PluginName};
 
 pub mod any;
 mod create;
+mod health;
 pub(super) mod introspect;
 mod output;
+mod plugin;
 pub mod source;
 mod transform;
 
 pub use create::{CreationRequest, MultiCreationRequestBuilder, SingleCreationRequestBuilder, create_many, create_one};
+pub use health::{ElementFailuresRequest, PluginHealthRequest, element_failures, plugin_health};
 pub use introspect::{ElementListFilter, IntrospectionRequest, list_elements};
 pub use output::{OutputRequest, OutputRequestBuilder, RemainingDataStrategy, output};
+pub use plugin::{PluginRequest, PluginRequestBuilder, plugin};
 pub use source::{SourceRequest, SourceRequestBuilder, source};
 use tokio::sync::oneshot;
 pub use transform::{TransformRequest, TransformRequestBuilder, transform};
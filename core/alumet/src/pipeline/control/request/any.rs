@@ -8,7 +8,8 @@ use crate::pipeline::{
 
 use super::{
     AnonymousControlRequest, CreationRequest, DirectResponseReceiver, PluginControlRequest, ResponseReceiver, create,
-    introspect::IntrospectionRequest, output::OutputRequest, source::SourceRequest, transform::TransformRequest,
+    introspect::IntrospectionRequest, output::OutputRequest, plugin::PluginRequest, source::SourceRequest,
+    transform::TransformRequest,
 };
 
 #[derive(Debug)]
@@ -22,6 +23,7 @@ enum ControlRequestImpl {
     Source(SourceRequest),
     Transform(TransformRequest),
     Introspect(IntrospectionRequest),
+    Plugin(PluginRequest),
 }
 
 #[derive(Debug)]
@@ -86,6 +88,7 @@ impl AnonymousControlRequest for AnyAnonymousControlRequest {
             ControlRequestImpl::Source(req) => AnonymousControlRequest::serialize(req),
             ControlRequestImpl::Transform(req) => AnonymousControlRequest::serialize(req),
             ControlRequestImpl::Introspect(req) => AnonymousControlRequest::serialize(req),
+            ControlRequestImpl::Plugin(req) => AnonymousControlRequest::serialize(req),
         }
     }
 
@@ -107,6 +110,10 @@ impl AnonymousControlRequest for AnyAnonymousControlRequest {
                 let (req, rx) = AnonymousControlRequest::serialize_with_response(req);
                 (req, ResponseDiscarder::from(rx))
             }
+            ControlRequestImpl::Plugin(req) => {
+                let (req, rx) = AnonymousControlRequest::serialize_with_response(req);
+                (req, ResponseDiscarder::from(rx))
+            }
         }
     }
 }
@@ -152,6 +159,11 @@ impl From<OutputRequest> for AnyAnonymousControlRequest {
         Self(ControlRequestImpl::Output(value))
     }
 }
+impl From<PluginRequest> for AnyAnonymousControlRequest {
+    fn from(value: PluginRequest) -> Self {
+        Self(ControlRequestImpl::Plugin(value))
+    }
+}
 impl From<IntrospectionRequest> for AnyAnonymousControlRequest {
     fn from(value: IntrospectionRequest) -> Self {
         Self(ControlRequestImpl::Introspect(value))
@@ -6,7 +6,7 @@ use crate::pipeline::{
     elements::{
         output::{
             self,
-            builder::{BlockingOutputBuilder, SendOutputBuilder},
+            builder::{AsyncOutputBuilder, BlockingOutputBuilder, SendOutputBuilder},
         },
         source::{
             self,
@@ -102,6 +102,15 @@ impl SingleCreationRequestBuilder {
         self.inner.add_blocking_output_builder(name, builder);
         self.inner.build()
     }
+
+    pub fn add_async_output_builder<F: AsyncOutputBuilder + Send + 'static>(
+        &mut self,
+        name: &str,
+        builder: F,
+    ) -> CreationRequest {
+        self.inner.add_async_output_builder(name, builder);
+        self.inner.build()
+    }
 }
 
 impl MultiCreationRequestBuilder {
@@ -159,6 +168,11 @@ impl MultiCreationRequestBuilder {
         let builder = SendOutputBuilder::Blocking(Box::new(builder));
         self.outputs.push((name.to_string(), builder));
     }
+
+    pub fn add_async_output_builder<F: AsyncOutputBuilder + Send + 'static>(&mut self, name: &str, builder: F) {
+        let builder = SendOutputBuilder::Async(Box::new(builder));
+        self.outputs.push((name.to_string(), builder));
+    }
 }
 
 impl CreationRequest {
@@ -0,0 +1,68 @@
+use tokio::sync::oneshot;
+
+use crate::pipeline::control::messages;
+
+use super::{AnonymousControlRequest, DirectResponseReceiver};
+
+/// Creates a request that returns the last health report of each plugin.
+///
+/// Plugins that have never reported their health are not included in the response.
+/// See [`health`](crate::plugin::health).
+pub fn plugin_health() -> PluginHealthRequest {
+    PluginHealthRequest
+}
+
+#[derive(Debug)]
+pub struct PluginHealthRequest;
+
+impl AnonymousControlRequest for PluginHealthRequest {
+    type OkResponse = messages::HealthResponse;
+    type Receiver = DirectResponseReceiver<Self::OkResponse>;
+
+    fn serialize(self) -> messages::ControlRequest {
+        messages::ControlRequest::Health(messages::RequestMessage {
+            response_tx: None,
+            body: (),
+        })
+    }
+
+    fn serialize_with_response(self) -> (messages::ControlRequest, Self::Receiver) {
+        let (tx, rx) = oneshot::channel();
+        let req = messages::ControlRequest::Health(messages::RequestMessage {
+            response_tx: Some(tx),
+            body: (),
+        });
+        (req, DirectResponseReceiver(rx))
+    }
+}
+
+/// Creates a request that returns the number of errors of each pipeline element.
+///
+/// Elements that have never failed are not included in the response.
+pub fn element_failures() -> ElementFailuresRequest {
+    ElementFailuresRequest
+}
+
+#[derive(Debug)]
+pub struct ElementFailuresRequest;
+
+impl AnonymousControlRequest for ElementFailuresRequest {
+    type OkResponse = messages::FailuresResponse;
+    type Receiver = DirectResponseReceiver<Self::OkResponse>;
+
+    fn serialize(self) -> messages::ControlRequest {
+        messages::ControlRequest::Failures(messages::RequestMessage {
+            response_tx: None,
+            body: (),
+        })
+    }
+
+    fn serialize_with_response(self) -> (messages::ControlRequest, Self::Receiver) {
+        let (tx, rx) = oneshot::channel();
+        let req = messages::ControlRequest::Failures(messages::RequestMessage {
+            response_tx: Some(tx),
+            body: (),
+        });
+        (req, DirectResponseReceiver(rx))
+    }
+}
@@ -63,6 +63,12 @@ impl ElementListFilter {
     }
 }
 
+impl From<ElementNamePattern> for ElementListFilter {
+    fn from(pattern: ElementNamePattern) -> Self {
+        Self { pattern }
+    }
+}
+
 impl IntrospectionRequest {
     fn into_body(self) -> messages::IntrospectionBody {
         messages::IntrospectionBody::ListElements(self.list_filter.pattern)
@@ -0,0 +1,148 @@
+use tokio::sync::oneshot;
+
+use crate::pipeline::{
+    control::messages,
+    elements::{output, source, transform},
+    matching::{OutputNamePattern, SourceNamePattern, StringPattern, TransformNamePattern},
+    naming::PluginName,
+};
+
+use super::DirectResponseReceiver;
+
+pub struct PluginRequestBuilder {
+    plugin: String,
+}
+
+/// A request that applies to a whole plugin.
+#[derive(Debug)]
+pub struct PluginRequest {
+    body: PluginRequestBody,
+}
+
+#[derive(Debug)]
+enum PluginRequestBody {
+    /// Enables or disables the plugin, this is handled by the agent.
+    Plugin(messages::PluginBody),
+    /// Applies to the elements of the plugin, this is handled by the pipeline.
+    Elements(Vec<messages::SpecificBody>),
+}
+
+/// Returns a builder that allows to build a request for controlling a plugin and all its elements at once.
+///
+/// The elements are selected by the plugin part of their name, therefore the request applies to
+/// the sources, transforms and outputs that have been registered by the plugin, whether they have been
+/// created during the startup phase or later on.
+pub fn plugin(plugin: impl Into<String>) -> PluginRequestBuilder {
+    PluginRequestBuilder { plugin: plugin.into() }
+}
+
+impl PluginRequestBuilder {
+    /// Disables the plugin: its sources and outputs are stopped, its transforms are disabled,
+    /// and the plugin is [stopped](crate::plugin::Plugin::stop).
+    ///
+    /// The resources held by the sources and outputs are freed, because they are dropped.
+    /// Transforms cannot be removed from a running pipeline, they are kept but skipped.
+    ///
+    /// The plugin can be started again with [`enable`](Self::enable).
+    ///
+    /// This request is handled by the [agent](crate::agent) that runs the pipeline. It fails if the
+    /// plugin is not running, or if the pipeline is not run by an agent.
+    pub fn disable(self) -> PluginRequest {
+        PluginRequest {
+            body: PluginRequestBody::Plugin(messages::PluginBody::Disable(PluginName(self.plugin))),
+        }
+    }
+
+    /// Enables a plugin that has been disabled: the plugin is [started](crate::plugin::Plugin::start) again,
+    /// the sources and outputs that it registers are added to the pipeline, and its transforms are enabled.
+    ///
+    /// Enabling a plugin that is running does nothing.
+    ///
+    /// This request is handled by the [agent](crate::agent) that runs the pipeline. It fails if the
+    /// plugin is unknown, if the pipeline is not run by an agent, or if the plugin registers a transform
+    /// or a metric listener that it did not have before, because they cannot be added to a running pipeline.
+    pub fn enable(self) -> PluginRequest {
+        PluginRequest {
+            body: PluginRequestBody::Plugin(messages::PluginBody::Enable(PluginName(self.plugin))),
+        }
+    }
+
+    /// Stops the sources and outputs of the plugin, and disables its transforms.
+    ///
+    /// Unlike [`disable`](Self::disable), the plugin itself is not stopped.
+    pub(crate) fn stop_elements(self) -> PluginRequest {
+        PluginRequest {
+            body: PluginRequestBody::Elements(vec![
+                messages::SpecificBody::Source(source::control::ControlMessage::Configure(
+                    source::control::ConfigureMessage {
+                        matcher: self.source_pattern().into(),
+                        command: source::control::ConfigureCommand::Stop,
+                    },
+                )),
+                messages::SpecificBody::Transform(transform::control::ControlMessage {
+                    matcher: self.transform_pattern().into(),
+                    new_state: transform::control::TaskState::Disabled,
+                }),
+                messages::SpecificBody::Output(output::control::ControlMessage::Configure(
+                    output::control::ConfigureMessage {
+                        matcher: self.output_pattern().into(),
+                        // StopFinish would wait for the pipeline to shut down
+                        new_state: output::control::TaskState::StopNow,
+                    },
+                )),
+            ]),
+        }
+    }
+
+    /// Enables the transforms of the plugin.
+    pub(crate) fn enable_transforms(self) -> PluginRequest {
+        PluginRequest {
+            body: PluginRequestBody::Elements(vec![messages::SpecificBody::Transform(
+                transform::control::ControlMessage {
+                    matcher: self.transform_pattern().into(),
+                    new_state: transform::control::TaskState::Enabled,
+                },
+            )]),
+        }
+    }
+
+    fn source_pattern(&self) -> SourceNamePattern {
+        SourceNamePattern::new(StringPattern::Exact(self.plugin.clone()), StringPattern::Any)
+    }
+
+    fn transform_pattern(&self) -> TransformNamePattern {
+        TransformNamePattern::new(StringPattern::Exact(self.plugin.clone()), StringPattern::Any)
+    }
+
+    fn output_pattern(&self) -> OutputNamePattern {
+        OutputNamePattern::new(StringPattern::Exact(self.plugin.clone()), StringPattern::Any)
+    }
+}
+
+impl PluginRequest {
+    fn into_request(self, response_tx: Option<messages::ResponseSender<()>>) -> messages::ControlRequest {
+        match self.body {
+            PluginRequestBody::Plugin(body) => {
+                messages::ControlRequest::Plugin(messages::RequestMessage { response_tx, body })
+            }
+            PluginRequestBody::Elements(messages) => messages::ControlRequest::NoResult(messages::RequestMessage {
+                response_tx,
+                body: messages::EmptyResponseBody::Mixed(messages),
+            }),
+        }
+    }
+}
+
+impl super::AnonymousControlRequest for PluginRequest {
+    type OkResponse = ();
+    type Receiver = DirectResponseReceiver<()>;
+
+    fn serialize(self) -> messages::ControlRequest {
+        self.into_request(None)
+    }
+
+    fn serialize_with_response(self) -> (messages::ControlRequest, Self::Receiver) {
+        let (tx, rx) = oneshot::channel();
+        (self.into_request(Some(tx)), DirectResponseReceiver(rx))
+    }
+}
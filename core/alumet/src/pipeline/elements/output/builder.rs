@@ -77,10 +77,10 @@ impl<F> AsyncOutputBuilder for F where
 }
 
 /// Context provided when building new outputs.
-pub(super) struct OutputBuildContext<'a> {
-    pub(super) metrics_r: &'a MetricReader,
-    pub(super) metrics: &'a MetricRegistry,
-    pub(super) runtime: runtime::Handle,
+pub(crate) struct OutputBuildContext<'a> {
+    pub(crate) metrics_r: &'a MetricReader,
+    pub(crate) metrics: &'a MetricRegistry,
+    pub(crate) runtime: runtime::Handle,
 }
 
 pub trait BlockingOutputBuildContext {
@@ -7,7 +7,7 @@ use std::sync::{
 use tokio::{
     runtime,
     sync::Notify,
-    task::{JoinError, JoinSet},
+    task::{self, JoinError, JoinSet},
 };
 
 use crate::pipeline::elements::output::{AsyncOutputStream, run::run_async_output};
@@ -18,6 +18,7 @@ use crate::pipeline::util::{
     stream::{ControlledStream, SharedStreamState, StreamState},
 };
 use crate::pipeline::{control::matching::OutputMatcher, matching::ElementNamePattern, naming::ElementKind};
+use crate::plugin::health::HealthRegistry;
 use crate::{measurement::MeasurementBuffer, pipeline::error::PipelineError};
 use crate::{metrics::online::MetricReader, pipeline::naming::ElementName};
 
@@ -99,7 +100,10 @@ pub(crate) struct OutputControl {
 
 struct TaskManager {
     spawned_tasks: JoinSet<Result<(), PipelineError>>,
-    controllers: Vec<(OutputName, SingleOutputController)>,
+    /// Controllers for each output, by name, with the id of the output task.
+    ///
+    /// The controller of an output is removed when its task finishes.
+    controllers: Vec<(OutputName, task::Id, SingleOutputController)>,
 
     rx_provider: channel::ReceiverProvider,
 
@@ -107,10 +111,18 @@ struct TaskManager {
     rt_normal: runtime::Handle,
 
     metrics: MetricReader,
+
+    /// Counts the errors of the outputs.
+    health: HealthRegistry,
 }
 
 impl OutputControl {
-    pub fn new(rx_provider: channel::ReceiverProvider, rt_normal: runtime::Handle, metrics: MetricReader) -> Self {
+    pub fn new(
+        rx_provider: channel::ReceiverProvider,
+        rt_normal: runtime::Handle,
+        metrics: MetricReader,
+        health: HealthRegistry,
+    ) -> Self {
         Self {
             tasks: TaskManager {
                 spawned_tasks: JoinSet::new(),
@@ -118,6 +130,7 @@ impl OutputControl {
                 rx_provider,
                 rt_normal,
                 metrics: metrics.clone(),
+                health,
             },
             metrics,
         }
@@ -179,10 +192,14 @@ impl OutputControl {
     }
 
     pub async fn join_next_task(&mut self) -> Result<Result<(), PipelineError>, JoinError> {
-        match self.tasks.spawned_tasks.join_next().await {
-            Some(res) => res,
+        let (id, res) = match self.tasks.spawned_tasks.join_next_with_id().await {
+            Some(Ok((id, res))) => (id, Ok(res)),
+            Some(Err(e)) => (e.id(), Err(e)),
             None => unreachable!("join_next_task must be guarded by has_task to prevent an infinite loop"),
-        }
+        };
+        // The output no longer exists, forget its controller.
+        self.tasks.controllers.retain(|(_, task_id, _)| *task_id != id);
+        res
     }
 
     pub fn has_task(&self) -> bool {
@@ -210,7 +227,7 @@ impl OutputControl {
 
     pub fn list_elements(&self, buf: &mut Vec<ElementName>, pat: &ElementNamePattern) {
         if pat.kind == None || pat.kind == Some(ElementKind::Output) {
-            buf.extend(self.tasks.controllers.iter().filter_map(|(name, _)| {
+            buf.extend(self.tasks.controllers.iter().filter_map(|(name, _, _)| {
                 if pat.matches(name) {
                     Some(name.to_owned().into())
                 } else {
@@ -244,30 +261,31 @@ impl TaskManager {
         let output = builder(ctx).context("output creation failed")?;
 
         // Create the necessary context.
-        let rx = self.rx_provider.get(); // to receive measurements
+        let rx = self.rx_provider.get()?; // to receive measurements
         let metrics = self.metrics.clone(); // to read metric definitions
+        let health = self.health.clone(); // to count the errors
 
         // Create and store the task controller.
         let config = Arc::new(SharedOutputConfig::new());
         let shared_config = config.clone();
         let control = SingleOutputController::Blocking(config);
-        self.controllers.push((name.clone(), control));
 
         // Put the output in a Mutex to overcome the lack of tokio::spawn_scoped.
         let guarded_output = Arc::new(Mutex::new(output));
 
         // Spawn the task on the runtime.
-        match rx {
+        let task_handle = match rx {
             // Specialize on the kind of receiver at compile-time (for performance).
             channel::ReceiverEnum::Broadcast(rx) => {
-                let task = run_blocking_output(name, guarded_output, rx, metrics, shared_config);
-                self.spawned_tasks.spawn_on(task, &self.rt_normal);
+                let task = run_blocking_output(name.clone(), guarded_output, rx, metrics, shared_config, health);
+                self.spawned_tasks.spawn_on(task, &self.rt_normal)
             }
             channel::ReceiverEnum::Single(rx) => {
-                let task = run_blocking_output(name, guarded_output, rx, metrics, shared_config);
-                self.spawned_tasks.spawn_on(task, &self.rt_normal);
+                let task = run_blocking_output(name.clone(), guarded_output, rx, metrics, shared_config, health);
+                self.spawned_tasks.spawn_on(task, &self.rt_normal)
             }
-        }
+        };
+        self.controllers.push((name, task_handle.id(), control));
 
         Ok(())
     }
@@ -291,7 +309,7 @@ impl TaskManager {
         }
 
         // For async outputs, we need to build the stream first
-        let rx = self.rx_provider.get();
+        let rx = self.rx_provider.get()?;
         let (stream, state) = match rx {
             channel::ReceiverEnum::Broadcast(receiver) => box_controlled_stream(receiver.into_stream()),
             channel::ReceiverEnum::Single(receiver) => box_controlled_stream(receiver.into_stream()),
@@ -303,18 +321,18 @@ impl TaskManager {
             builder(ctx, stream).context("output creation failed")
         }?;
 
-        // Create and store the task controller
+        // Create the task controller
         let control = SingleOutputController::Async(state);
-        self.controllers.push((name.clone(), control));
 
-        // Spawn the output
-        let task = run_async_output(name, output);
-        self.spawned_tasks.spawn_on(task, &self.rt_normal);
+        // Spawn the output and store its controller
+        let task = run_async_output(name.clone(), output, self.health.clone());
+        let task_handle = self.spawned_tasks.spawn_on(task, &self.rt_normal);
+        self.controllers.push((name, task_handle.id(), control));
         Ok(())
     }
 
     fn reconfigure(&mut self, msg: ConfigureMessage) {
-        for (name, output_config) in &mut self.controllers {
+        for (name, _, output_config) in &mut self.controllers {
             if msg.matcher.matches(name) {
                 output_config.set_state(msg.new_state);
             }
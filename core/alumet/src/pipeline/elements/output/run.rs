@@ -11,23 +11,30 @@ use crate::{
         naming::OutputName,
         util::channel::{self, RecvError},
     },
+    plugin::health::HealthRegistry,
 };
 
 use super::{BoxedAsyncOutput, Output, OutputContext, control, error::WriteError};
 
-pub async fn run_async_output(name: OutputName, output: BoxedAsyncOutput) -> Result<(), PipelineError> {
+pub(crate) async fn run_async_output(
+    name: OutputName,
+    output: BoxedAsyncOutput,
+    health: HealthRegistry,
+) -> Result<(), PipelineError> {
     output.await.map_err(|e| {
         log::error!("Error when asynchronously writing to {name} (will stop running): {e:?}");
+        health.record_failure(&name, true);
         PipelineError::for_element(name, e)
     })
 }
 
-pub async fn run_blocking_output<Rx: channel::MeasurementReceiver>(
+pub(crate) async fn run_blocking_output<Rx: channel::MeasurementReceiver>(
     name: OutputName,
     guarded_output: Arc<Mutex<Box<dyn Output>>>,
     mut rx: Rx,
     metrics_reader: MetricReader,
     config: Arc<control::SharedOutputConfig>,
+    health: HealthRegistry,
 ) -> Result<(), PipelineError> {
     /// If `measurements` is an `Ok`, build an [`OutputContext`] and call `output.write(&measurements, &ctx)`.
     /// Otherwise, handle the error.
@@ -35,12 +42,15 @@ pub async fn run_blocking_output<Rx: channel::MeasurementReceiver>(
         name: &OutputName,
         output: Arc<Mutex<Box<dyn Output>>>,
         metrics_r: MetricReader,
+        health: &HealthRegistry,
         maybe_measurements: Result<MeasurementBuffer, channel::RecvError>,
     ) -> anyhow::Result<ControlFlow<()>> {
         match maybe_measurements {
             Ok(measurements) => {
                 log::trace!("writing {} measurements to {name}", measurements.len());
+                let span = tracing::debug_span!("write", output = %name, measurements = measurements.len());
                 let res = tokio::task::spawn_blocking(move || {
+                    let _span = span.entered();
                     let ctx = OutputContext {
                         metrics: &metrics_r.blocking_read(),
                     };
@@ -51,10 +61,12 @@ pub async fn run_blocking_output<Rx: channel::MeasurementReceiver>(
                     Ok(()) => Ok(ControlFlow::Continue(())),
                     Err(WriteError::CanRetry(e)) => {
                         log::error!("Non-fatal error when writing to {name} (will retry): {e:#}");
+                        health.record_failure(name, false);
                         Ok(ControlFlow::Continue(()))
                     }
                     Err(WriteError::Fatal(e)) => {
                         log::error!("Fatal error when writing to {name} (will stop running): {e:?}");
+                        health.record_failure(name, true);
                         Err(e.context(format!("fatal error when writing to {name}")))
                     }
                 }
@@ -100,7 +112,7 @@ pub async fn run_blocking_output<Rx: channel::MeasurementReceiver>(
                 }
             },
             measurements = rx.recv(), if receive => {
-                let res = write_measurements(&name, guarded_output.clone(), metrics_reader.clone(), measurements)
+                let res = write_measurements(&name, guarded_output.clone(), metrics_reader.clone(), &health, measurements)
                     .await
                     .map_err(|e| PipelineError::for_element(name.clone(), e))?;
                 if res.is_break() {
@@ -125,7 +137,7 @@ pub async fn run_blocking_output<Rx: channel::MeasurementReceiver>(
                     Err(RecvError::Lagged(n)) => format!("Err(Lagged({n}))"),
                 }
             );
-            let res = write_measurements(&name, guarded_output.clone(), metrics_reader.clone(), received)
+            let res = write_measurements(&name, guarded_output.clone(), metrics_reader.clone(), &health, received)
                 .await
                 .map_err(|e| PipelineError::for_element(name.clone(), e))?;
             if res.is_break() {
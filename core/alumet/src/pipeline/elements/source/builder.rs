@@ -147,10 +147,10 @@ pub struct ManagedSource {
     pub source: Box<dyn Source>,
 }
 
-pub(super) struct BuildContext<'a> {
-    pub(super) metrics: &'a MetricRegistry,
-    pub(super) metrics_r: &'a MetricReader,
-    pub(super) metrics_tx: &'a MetricSender,
+pub(crate) struct BuildContext<'a> {
+    pub(crate) metrics: &'a MetricRegistry,
+    pub(crate) metrics_r: &'a MetricReader,
+    pub(crate) metrics_tx: &'a MetricSender,
 }
 
 /// Context accessible when building a managed source.
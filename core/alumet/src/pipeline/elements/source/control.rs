@@ -1,20 +1,23 @@
 use std::fmt::Debug;
+use std::sync::Arc;
 
 use anyhow::Context;
 use num_enum::{FromPrimitive, IntoPrimitive};
 use tokio::runtime;
 use tokio::sync::mpsc;
-use tokio::task::{JoinError, JoinSet};
+use tokio::task::{self, JoinError, JoinSet};
 use tokio_util::sync::CancellationToken;
 
 use crate::measurement::MeasurementBuffer;
 use crate::metrics::online::{MetricReader, MetricSender};
+use crate::pipeline::clock::Clock;
 use crate::pipeline::control::matching::SourceMatcher;
 use crate::pipeline::elements::source::run::{run_autonomous, run_managed};
 use crate::pipeline::error::PipelineError;
 use crate::pipeline::matching::{ElementNamePattern, SourceNamePattern};
 use crate::pipeline::naming::{ElementKind, ElementName};
 use crate::pipeline::naming::{SourceName, namespace::Namespace2};
+use crate::plugin::health::HealthRegistry;
 
 use super::builder;
 use super::trigger::{Trigger, TriggerConstraints, TriggerSpec};
@@ -100,8 +103,10 @@ struct TaskManager {
     /// Collection of managed and autonomous source tasks.
     spawned_tasks: JoinSet<Result<(), PipelineError>>,
 
-    /// Controllers for each source, by name.
-    controllers: Vec<(SourceName, super::task_controller::SingleSourceController)>,
+    /// Controllers for each source, by name, with the id of the source task.
+    ///
+    /// The controller of a source is removed when its task finishes.
+    controllers: Vec<(SourceName, task::Id, super::task_controller::SingleSourceController)>,
 
     /// Cancelled when the pipeline shuts down.
     ///
@@ -111,6 +116,9 @@ struct TaskManager {
     /// Constraints to apply to the new source triggers.
     trigger_constraints: TriggerConstraints,
 
+    /// Clock of the pipeline, for the triggers and timestamps.
+    clock: Arc<dyn Clock>,
+
     /// Sends measurements from Sources.
     ///
     /// This is used for creating new sources.
@@ -122,16 +130,22 @@ struct TaskManager {
 
     /// Handle of the "priority" async runtime. Used for creating new sources.
     rt_priority: runtime::Handle,
+
+    /// Counts the errors of the sources.
+    health: HealthRegistry,
 }
 
 impl SourceControl {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         trigger_constraints: TriggerConstraints,
+        clock: Arc<dyn Clock>,
         shutdown_token: CancellationToken,
         in_tx: mpsc::Sender<MeasurementBuffer>,
         rt_normal: runtime::Handle,
         rt_priority: runtime::Handle,
         metrics: (MetricReader, MetricSender),
+        health: HealthRegistry,
     ) -> Self {
         Self {
             tasks: TaskManager {
@@ -139,9 +153,11 @@ impl SourceControl {
                 controllers: Vec::new(),
                 shutdown_token,
                 trigger_constraints,
+                clock,
                 in_tx,
                 rt_normal,
                 rt_priority,
+                health,
             },
             metrics,
         }
@@ -212,10 +228,14 @@ impl SourceControl {
     }
 
     pub async fn join_next_task(&mut self) -> Result<Result<(), PipelineError>, JoinError> {
-        match self.tasks.spawned_tasks.join_next().await {
-            Some(res) => res,
+        let (id, res) = match self.tasks.spawned_tasks.join_next_with_id().await {
+            Some(Ok((id, res))) => (id, Ok(res)),
+            Some(Err(e)) => (e.id(), Err(e)),
             None => unreachable!("join_next_task must be guarded by has_task to prevent an infinite loop"),
-        }
+        };
+        // The source no longer exists, forget its controller.
+        self.tasks.controllers.retain(|(_, task_id, _)| *task_id != id);
+        res
     }
 
     pub fn has_task(&self) -> bool {
@@ -224,7 +244,7 @@ impl SourceControl {
 
     pub fn list_elements(&self, buf: &mut Vec<ElementName>, pat: &ElementNamePattern) {
         if pat.kind == None || pat.kind == Some(ElementKind::Source) {
-            buf.extend(self.tasks.controllers.iter().filter_map(|(name, _)| {
+            buf.extend(self.tasks.controllers.iter().filter_map(|(name, _, _)| {
                 if pat.matches(name) {
                     Some(name.to_owned().into())
                 } else {
@@ -297,33 +317,39 @@ impl TaskManager {
                 // Some triggers need to be built with an executor available, therefore we use `Handle::enter()`.
                 let trigger = {
                     let _guard = runtime.enter();
-                    Trigger::new(source.trigger_spec).context("error in Trigger::new")?
+                    Trigger::new(source.trigger_spec, &self.clock).context("error in Trigger::new")?
                 };
                 log::trace!("new trigger created from the spec: {trigger:?}");
 
                 // Create a controller to control the async task.
-                let (controller, config) = super::task_controller::new_managed(trigger, source.initial_state);
-                self.controllers.push((name.clone(), controller));
+                let (controller, config) =
+                    super::task_controller::new_managed(trigger, source.initial_state, self.clock.clone());
                 log::trace!("new controller initialized");
 
                 // Create the future (async task).
-                let source_task = run_managed(name.clone(), source.source, self.in_tx.clone(), config);
+                let source_task = run_managed(
+                    name.clone(),
+                    source.source,
+                    self.in_tx.clone(),
+                    config,
+                    self.health.clone(),
+                );
                 log::trace!("source task created: {name}");
 
                 // Spawn the future (execute the async task on the thread pool)
                 #[cfg(not(tokio_unstable))]
-                {
-                    self.spawned_tasks.spawn_on(source_task, runtime);
-                }
+                let task_handle = self.spawned_tasks.spawn_on(source_task, runtime);
                 #[cfg(tokio_unstable)]
-                {
+                let task_handle = {
                     // Give a proper name to the tokio's task, so that it's easier to debug (in particular with tokio-console).
                     // For now, this is an unstable API of tokio.
                     self.spawned_tasks
                         .build_task()
                         .name(name.to_string().as_str())
-                        .spawn_on(source_task, runtime);
-                }
+                        .spawn_on(source_task, runtime)
+                        .context("failed to spawn the source task")?
+                };
+                self.controllers.push((name.clone(), task_handle.id(), controller));
             }
             builder::SourceBuilder::Autonomous(build) => {
                 let token = self.shutdown_token.child_token();
@@ -331,12 +357,12 @@ impl TaskManager {
                 let source = build(ctx, token.clone(), tx).context("autonomous source creation failed")?;
                 log::trace!("New autonomous source: {}", name);
 
-                let source_task = run_autonomous(name.clone(), source);
+                let source_task = run_autonomous(name.clone(), source, self.health.clone());
                 let controller = super::task_controller::new_autonomous(token);
-                self.controllers.push((name.clone(), controller));
                 log::trace!("new controller initialized");
 
-                self.spawned_tasks.spawn_on(source_task, &self.rt_normal);
+                let task_handle = self.spawned_tasks.spawn_on(source_task, &self.rt_normal);
+                self.controllers.push((name.clone(), task_handle.id(), controller));
             }
         };
         log::trace!("source task spawned on the runtime: {name}");
@@ -355,7 +381,7 @@ impl TaskManager {
             }
         };
 
-        for (name, source_controller) in &mut self.controllers {
+        for (name, _, source_controller) in &mut self.controllers {
             if msg.matcher.matches(name) {
                 source_controller.reconfigure(&command);
             }
@@ -364,7 +390,7 @@ impl TaskManager {
 
     fn trigger_manually(&mut self, msg: TriggerMessage) {
         let mut matches = 0;
-        for (name, source_controller) in &mut self.controllers {
+        for (name, _, source_controller) in &mut self.controllers {
             if msg.matcher.matches(name) {
                 matches += 1;
                 source_controller.trigger_now();
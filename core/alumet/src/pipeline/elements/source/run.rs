@@ -8,6 +8,7 @@ use crate::measurement::{MeasurementBuffer, Timestamp};
 use crate::pipeline::error::PipelineError;
 use crate::pipeline::naming::SourceName;
 use crate::pipeline::util::coop::TriggerCoop;
+use crate::plugin::health::HealthRegistry;
 
 use super::control::TaskState;
 use super::error::PollError;
@@ -19,6 +20,7 @@ pub(crate) async fn run_managed(
     mut source: Box<dyn Source>,
     tx: mpsc::Sender<MeasurementBuffer>,
     config: Arc<super::task_controller::SharedSourceConfig>,
+    health: HealthRegistry,
 ) -> Result<(), PipelineError> {
     /// Flushes the measurement and returns a new buffer.
     async fn flush(
@@ -134,8 +136,12 @@ pub(crate) async fn run_managed(
         match reason {
             TriggerReason::Triggered => {
                 // poll the source
-                let timestamp = Timestamp::now();
-                match source.poll(&mut buffer.as_accumulator(), timestamp) {
+                let timestamp = config.clock.now();
+                let res = {
+                    let _span = tracing::debug_span!("poll", source = %source_name).entered();
+                    source.poll(&mut buffer.as_accumulator(), timestamp)
+                };
+                match res {
                     Ok(()) => (),
                     Err(PollError::NormalStop) => {
                         log::info!("Source {source_name} stopped itself.");
@@ -143,9 +149,11 @@ pub(crate) async fn run_managed(
                     }
                     Err(PollError::CanRetry(e)) => {
                         log::error!("Non-fatal error when polling {source_name} (will retry): {e:#}");
+                        health.record_failure(&source_name, false);
                     }
                     Err(PollError::Fatal(e)) => {
                         log::error!("Fatal error when polling {source_name} (will stop running): {e:?}");
+                        health.record_failure(&source_name, true);
                         return Err(PipelineError::for_element(source_name, e));
                     }
                 };
@@ -205,7 +213,11 @@ pub(crate) async fn run_managed(
     Ok(())
 }
 
-pub async fn run_autonomous(source_name: SourceName, source: AutonomousSource) -> Result<(), PipelineError> {
+pub(crate) async fn run_autonomous(
+    source_name: SourceName,
+    source: AutonomousSource,
+    health: HealthRegistry,
+) -> Result<(), PipelineError> {
     match source.await {
         Ok(_) => {
             log::debug!("{source_name} stops.");
@@ -213,6 +225,7 @@ pub async fn run_autonomous(source_name: SourceName, source: AutonomousSource) -
         }
         Err(e) => {
             log::error!("Error in autonomous source {source_name} (will stop running): {e:?}");
+            health.record_failure(&source_name, true);
             Err(PipelineError::for_element(source_name, e))
         }
     }
@@ -8,6 +8,7 @@ use tokio_util::sync::CancellationToken;
 
 use super::control::{Reconfiguration, TaskState};
 use super::trigger::{ManualTrigger, Trigger};
+use crate::pipeline::clock::Clock;
 
 /// A controller for a single source.
 pub enum SingleSourceController {
@@ -30,11 +31,13 @@ pub struct SharedSourceConfig {
     pub atomic_state: AtomicU8,
     pub new_trigger: Mutex<Option<Trigger>>,
     pub manual_trigger: Option<ManualTrigger>,
+    pub clock: Arc<dyn Clock>,
 }
 
 pub fn new_managed(
     initial_trigger: Trigger,
     initial_state: TaskState,
+    clock: Arc<dyn Clock>,
 ) -> (SingleSourceController, Arc<SharedSourceConfig>) {
     let manual_trigger = initial_trigger.manual_trigger();
     let config = Arc::new(SharedSourceConfig {
@@ -42,6 +45,7 @@ pub fn new_managed(
         atomic_state: AtomicU8::new(initial_state as u8),
         new_trigger: Mutex::new(Some(initial_trigger)),
         manual_trigger,
+        clock,
     });
     (SingleSourceController::Managed(config.clone()), config)
 }
@@ -66,7 +70,7 @@ impl SingleSourceController {
                         shared.atomic_state.store(*new_state as u8, Ordering::Relaxed);
                     }
                     Reconfiguration::SetTrigger(new_spec) => {
-                        let trigger = Trigger::new(new_spec.to_owned()).unwrap();
+                        let trigger = Trigger::new(new_spec.to_owned(), &shared.clock).unwrap();
                         *shared.new_trigger.lock().unwrap() = Some(trigger);
                     }
                 }
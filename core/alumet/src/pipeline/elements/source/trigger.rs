@@ -7,6 +7,8 @@ use std::{future::Future, pin::Pin};
 
 use tokio::sync::Notify;
 
+use crate::pipeline::clock::Clock;
+
 /// A boxed future, from the `futures` crate.
 pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
@@ -190,10 +192,11 @@ impl From<bool> for Interruptible {
 }
 
 impl Trigger {
-    pub fn new(spec: TriggerSpec) -> Result<Self, std::io::Error> {
-        let interruptible = Interruptible::from(spec.interruptible);
+    pub fn new(spec: TriggerSpec, clock: &Arc<dyn Clock>) -> Result<Self, std::io::Error> {
+        // A clock that is not the system clock may never advance: make sure that the source can stop anyway.
+        let interruptible = Interruptible::from(spec.interruptible || !clock.is_system_clock());
         let manual_only = matches!(spec.mechanism, TriggerMechanismSpec::ManualOnly);
-        let mechanism = TriggerMechanism::try_from(spec.mechanism)?;
+        let mechanism = TriggerMechanism::new(spec.mechanism, clock)?;
         let inner = if spec.allow_manual_trigger && !manual_only {
             let manual = TriggerMechanism::Manual(Arc::new(Notify::new()));
             TriggerImpl::Double(mechanism, manual, interruptible)
@@ -278,9 +281,11 @@ impl Trigger {
 /// Useful because some mechanisms, like tokio_timerfd::Interval, are not cloneable,
 /// and we need cloneable values for working with the watch channel in
 /// the implementation of the pipeline.
+///
+/// The start of a time interval is optional: by default, the first poll happens as soon as the trigger is created.
 #[derive(Debug, Clone)]
 enum TriggerMechanismSpec {
-    TimeInterval(time::Instant, time::Duration),
+    TimeInterval(Option<time::Instant>, time::Duration),
     #[allow(unused)]
     Future(fn() -> BoxFuture<'static, SourceTriggerOutput>),
     ManualOnly,
@@ -296,9 +301,10 @@ enum TriggerMechanism {
     #[cfg(target_os = "linux")]
     Timerfd(tokio_timerfd::Interval),
 
-    /// A trigger based on [`tokio::time::sleep`].
-    #[allow(dead_code)]
-    Sleep(tokio::time::Instant, tokio::time::Duration),
+    /// A trigger based on the [`Clock`] of the pipeline.
+    ///
+    /// Contains the clock, the next deadline and the poll interval.
+    Sleep(Arc<dyn Clock>, time::Instant, time::Duration),
 
     /// A "manual" trigger based on [`tokio::sync::Notify`].
     Manual(Arc<Notify>),
@@ -310,22 +316,17 @@ enum TriggerMechanism {
     Future(fn() -> BoxFuture<'static, SourceTriggerOutput>),
 }
 
-impl TryFrom<TriggerMechanismSpec> for TriggerMechanism {
-    type Error = std::io::Error;
-
-    fn try_from(value: TriggerMechanismSpec) -> Result<Self, Self::Error> {
-        Ok(match value {
+impl TriggerMechanism {
+    fn new(spec: TriggerMechanismSpec, clock: &Arc<dyn Clock>) -> Result<Self, std::io::Error> {
+        Ok(match spec {
             TriggerMechanismSpec::TimeInterval(at, duration) => {
-                // Use timerfd if possible, fallback to `tokio::time::sleep`.
+                let at = at.unwrap_or_else(|| clock.instant());
+                // Use timerfd if possible, fallback to the clock.
                 #[cfg(target_os = "linux")]
-                {
-                    TriggerMechanism::Timerfd(tokio_timerfd::Interval::new(at, duration)?)
-                }
-
-                #[cfg(not(target_os = "linux"))]
-                {
-                    TriggerMechanism::Sleep(at.into(), duration.into())
+                if clock.is_system_clock() {
+                    return Ok(TriggerMechanism::Timerfd(tokio_timerfd::Interval::new(at, duration)?));
                 }
+                TriggerMechanism::Sleep(clock.clone(), at, duration)
             }
             TriggerMechanismSpec::Future(f) => TriggerMechanism::Future(f),
             TriggerMechanismSpec::ManualOnly => TriggerMechanism::Manual(Arc::new(Notify::new())),
@@ -343,11 +344,17 @@ impl TriggerMechanism {
                 interval.next().await.unwrap()?;
                 Ok(())
             }
-            TriggerMechanism::Sleep(start, period) => {
-                let start = *start;
-                let now = tokio::time::Instant::now();
-                let deadline = if start > now { start } else { now + *period };
-                tokio::time::sleep_until(deadline).await;
+            TriggerMechanism::Sleep(clock, next, period) => {
+                clock.sleep_until(*next).await;
+                *next += *period;
+                if clock.is_system_clock() {
+                    // Skip the missed ticks (e.g. after a suspend). Other clocks are expected to be
+                    // advanced on purpose, for tests: they get a poll for every tick.
+                    let now = clock.instant();
+                    if *next < now {
+                        *next = now + *period;
+                    }
+                }
                 Ok(())
             }
             TriggerMechanism::Future(f) => f().await,
@@ -361,7 +368,7 @@ impl fmt::Debug for TriggerMechanism {
         match self {
             #[cfg(target_os = "linux")]
             Self::Timerfd(_) => f.write_str("TriggerMechanism::Timerfd"),
-            Self::Sleep(_, _) => f.write_str("TriggerMechanism::Sleep"),
+            Self::Sleep(clock, _, _) => write!(f, "TriggerMechanism::Sleep({clock:?})"),
             Self::Future(ptr) => write!(f, "TriggerMechanism::Future({ptr:?})"),
             Self::Manual(notify) => write!(f, "TriggerMechanism::Manual({:p})", *notify),
         }
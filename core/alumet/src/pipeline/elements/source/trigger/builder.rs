@@ -108,7 +108,7 @@ impl TriggerSpecBuilder {
 impl TimeTriggerBuilder {
     pub fn new(poll_interval: Duration) -> Self {
         Self(TriggerSpecBuilder::new(TriggerMechanismSpec::TimeInterval(
-            Instant::now(),
+            None,
             poll_interval,
         )))
     }
@@ -121,9 +121,12 @@ impl TimeTriggerBuilder {
     }
 
     /// Start polling at the given time.
+    ///
+    /// The time is read from the [`Clock`](crate::pipeline::clock::Clock) of the pipeline.
+    /// By default, the first poll happens as soon as the source starts.
     pub fn starting_at(&mut self, start: Instant) -> &mut Self {
         match &mut self.0.mechanism {
-            TriggerMechanismSpec::TimeInterval(instant, _) => *instant = start,
+            TriggerMechanismSpec::TimeInterval(instant, _) => *instant = Some(start),
             _ => unreachable!(),
         }
         self
@@ -14,6 +14,7 @@ use crate::pipeline::control::matching::TransformMatcher;
 use crate::pipeline::error::PipelineError;
 use crate::pipeline::matching::ElementNamePattern;
 use crate::pipeline::naming::{ElementKind, ElementName, TransformName};
+use crate::plugin::health::HealthRegistry;
 
 use super::Transform;
 use super::builder::{BuildContext, TransformBuilder};
@@ -51,6 +52,7 @@ impl TransformControl {
         rx: mpsc::Receiver<MeasurementBuffer>,
         tx: broadcast::Sender<MeasurementBuffer>,
         rt_normal: &runtime::Handle,
+        health: HealthRegistry,
     ) -> anyhow::Result<Self> {
         let metrics_r = metrics.blocking_read();
         let mut built = Vec::with_capacity(transforms.len());
@@ -61,7 +63,7 @@ impl TransformControl {
                 .inspect_err(|e| log::error!("Failed to build transform {full_name}: {e:#}"))?;
             built.push((full_name, transform));
         }
-        let tasks = TaskManager::spawn(built, metrics.clone(), rx, tx, rt_normal);
+        let tasks = TaskManager::spawn(built, metrics.clone(), rx, tx, rt_normal, health);
         Ok(Self { tasks })
     }
 
@@ -114,6 +116,7 @@ impl TaskManager {
         rx: mpsc::Receiver<MeasurementBuffer>,
         tx: broadcast::Sender<MeasurementBuffer>,
         rt_normal: &runtime::Handle,
+        health: HealthRegistry,
     ) -> Self {
         let mut active_bitset: u64 = 0;
         let mut names_by_bitset_position = Vec::with_capacity(transforms.len());
@@ -126,7 +129,7 @@ impl TaskManager {
         // Start the transforms task.
         let mut set = JoinSet::new();
         let active_bitset = Arc::new(AtomicU64::new(active_bitset));
-        let task = run_all_in_order(transforms, rx, tx, active_bitset.clone(), metrics_r);
+        let task = run_all_in_order(transforms, rx, tx, active_bitset.clone(), metrics_r, health);
         set.spawn_on(task, rt_normal);
         Self {
             spawned_tasks: set,
@@ -12,16 +12,18 @@ use crate::{
     measurement::MeasurementBuffer,
     metrics::online::MetricReader,
     pipeline::{error::PipelineError, naming::TransformName},
+    plugin::health::HealthRegistry,
 };
 
 use super::{Transform, TransformContext, error::TransformError};
 
-pub async fn run_all_in_order(
+pub(crate) async fn run_all_in_order(
     mut transforms: Vec<(TransformName, Box<dyn Transform>)>,
     mut rx: mpsc::Receiver<MeasurementBuffer>,
     tx: broadcast::Sender<MeasurementBuffer>,
     active_flags: Arc<AtomicU64>,
     metrics_reader: MetricReader,
+    health: HealthRegistry,
 ) -> Result<(), PipelineError> {
     log::trace!(
         "Running transforms: {}",
@@ -50,13 +52,19 @@ pub async fn run_all_in_order(
             for (i, (name, t)) in &mut transforms.iter_mut().enumerate() {
                 let t_flag = 1 << i;
                 if current_flags & t_flag != 0 {
-                    match t.apply(&mut measurements, &ctx) {
+                    let res = {
+                        let _span = tracing::debug_span!("transform", transform = %name).entered();
+                        t.apply(&mut measurements, &ctx)
+                    };
+                    match res {
                         Ok(()) => (),
                         Err(TransformError::UnexpectedInput(e)) => {
                             log::error!("Transform {name} received unexpected measurements: {e:#}");
+                            health.record_failure(&*name, false);
                         }
                         Err(TransformError::Fatal(e)) => {
                             log::error!("Fatal error in transform {name} (this breaks the transform task!): {e:?}");
+                            health.record_failure(&*name, true);
                             return Err(PipelineError::for_element(name.to_owned(), e));
                         }
                     }
@@ -9,8 +9,17 @@
 //! 3. Build and start the pipeline with [`Builder::build()`].
 //! 4. Stop the pipeline by calling [`pipeline.control_handle().shutdown()`](control::AnonymousControlHandle::shutdown).
 //! 5. Finalize the shutdown with [`pipeline.wait_for_shutdown()`](MeasurementPipeline::wait_for_shutdown).
+//!
+//! # Tracing
+//! The operations of the elements are instrumented with [`tracing`] spans, at the `DEBUG` level:
+//! - `poll`, with the name of the source in the `source` field;
+//! - `transform`, with the name of the transform in the `transform` field;
+//! - `write`, with the name of the output in the `output` field and the number of measurements in `measurements`.
+//!
+//! Install a `tracing` subscriber to measure the latency of each step.
 
 pub mod builder;
+pub mod clock;
 pub mod control;
 pub mod elements;
 pub mod error;
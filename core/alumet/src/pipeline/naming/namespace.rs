@@ -46,6 +46,11 @@ impl<V> Namespace2<V> {
         self.map.remove(&(String::from(key), String::from(subkey)))
     }
 
+    /// Removes all the values of a namespace.
+    pub fn remove_all(&mut self, key: &str) {
+        self.map.retain(|(k, _), _| k != key);
+    }
+
     /// Gets the total number of values in all namespaces.
     pub fn total_count(&self) -> usize {
         self.map.len()
@@ -89,13 +89,17 @@ impl MeasurementReceiver for mpsc::Receiver<MeasurementBuffer> {
 // providers
 
 impl ReceiverProvider {
-    pub fn get(&mut self) -> ReceiverEnum {
+    /// Returns a new receiver.
+    ///
+    /// In the simplified pipeline, there is only one receiver: `get` fails if it has already been taken.
+    pub fn get(&mut self) -> anyhow::Result<ReceiverEnum> {
         match &mut self.0 {
-            ProviderEnum::Broadcast(tx) => ReceiverEnum::Broadcast(tx.subscribe()),
-            ProviderEnum::Single(rx) => ReceiverEnum::Single(
-                rx.take()
-                    .expect("ProviderEnum::get called but the single MeasurementReceiver has already been taken"),
-            ),
+            ProviderEnum::Broadcast(tx) => Ok(ReceiverEnum::Broadcast(tx.subscribe())),
+            ProviderEnum::Single(rx) => rx.take().map(ReceiverEnum::Single).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "the pipeline has been simplified and cannot accept more outputs, use allow_simplified_pipeline to disable this optimization"
+                )
+            }),
         }
     }
 }
@@ -39,8 +39,39 @@
 //!     Ok(())
 //! });
 //! ```
+//!
+//! # Custom events
+//!
+//! Plugins can also exchange their own events, by defining a type that implements [`Event`]
+//! and using the bus returned by [`bus`]. There is one global bus per event type.
+//!
+//! ```no_run
+//! use alumet::plugin::event::{self, Event};
+//!
+//! /// A job has been started by the job scheduler.
+//! #[derive(Clone, Debug)]
+//! pub struct JobStarted {
+//!     pub job_id: u64,
+//! }
+//!
+//! impl Event for JobStarted {}
+//!
+//! // in the plugin that reacts to the event
+//! event::bus::<JobStarted>().subscribe(|event| {
+//!     log::info!("job {} started", event.job_id);
+//!     Ok(())
+//! });
+//!
+//! // in the plugin that detects the jobs
+//! event::bus::<JobStarted>().publish(JobStarted { job_id: 1234 });
+//! ```
+//!
+//! To share an event type between several plugins, define it in a crate that they all depend on.
+//! Only the plugins that are statically linked to the agent share the buses, see [`bus`].
 
 use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
     ops::Deref,
     sync::{Mutex, OnceLock},
 };
@@ -153,6 +184,31 @@ pub fn end_consumer_measurement() -> &'static EventBus<EndConsumerMeasurement> {
         .end_consumer_measurement
 }
 
+/// Global variable containing the event buses of custom event types, indexed by the type of the event.
+///
+/// The buses are leaked: like the other global buses, they live until the end of the program.
+static CUSTOM_EVENT_BUSES: OnceLock<Mutex<HashMap<TypeId, &'static (dyn Any + Send + Sync)>>> = OnceLock::new();
+
+/// Returns the global event bus for the event type `E`.
+///
+/// The bus is created on the first call. Subsequent calls with the same type `E`
+/// return the same bus, if they are made by statically linked plugins.
+///
+/// # Dynamic plugins
+/// The buses are stored in a global variable of the `alumet` crate, and indexed by [`TypeId`].
+/// A dynamic plugin (loaded from a shared library) contains its own copy of the `alumet` crate,
+/// hence its own buses, and the `TypeId` of an event type is not guaranteed to be the same in the
+/// plugin and in the agent. Therefore, the custom events of a dynamic plugin are not shared with
+/// the other plugins.
+pub fn bus<E: Event + 'static>() -> &'static EventBus<E> {
+    let mut buses = CUSTOM_EVENT_BUSES.get_or_init(Default::default).lock().unwrap();
+    let bus = *buses
+        .entry(TypeId::of::<E>())
+        .or_insert_with(|| Box::leak(Box::new(EventBus::<E>::default())));
+    bus.downcast_ref::<EventBus<E>>()
+        .expect("the bus registered for an event type should have the corresponding type")
+}
+
 /// Event occurring when new [resource consumers](ResourceConsumer) are detected
 /// and should be measured.
 #[derive(Clone)]
@@ -185,6 +241,11 @@ mod tests {
 
     impl Event for TestEvent {}
 
+    #[derive(Clone)]
+    struct OtherEvent;
+
+    impl Event for OtherEvent {}
+
     #[test]
     fn test() {
         let bus: EventBus<TestEvent> = EventBus::default();
@@ -209,4 +270,22 @@ mod tests {
         bus.publish(TestEvent(10));
         assert_eq!(11, event_count.load(Ordering::SeqCst));
     }
+
+    #[test]
+    fn custom_global_bus() {
+        let count = Arc::new(AtomicU32::new(0));
+        let cloned_count = count.clone();
+        super::bus::<TestEvent>().subscribe(move |event| {
+            cloned_count.fetch_add(event.0, Ordering::SeqCst);
+            Ok(())
+        });
+
+        // same type => same bus
+        super::bus::<TestEvent>().publish(TestEvent(5));
+        assert_eq!(5, count.load(Ordering::SeqCst));
+
+        // other type => other bus
+        super::bus::<OtherEvent>().publish(OtherEvent);
+        assert_eq!(5, count.load(Ordering::SeqCst));
+    }
 }
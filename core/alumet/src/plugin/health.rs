@@ -0,0 +1,269 @@
+//! Health reporting for plugins.
+//!
+//! A plugin can be running without crashing, but still fail to do its job,
+//! for instance because one of its sensors has disappeared. To let the operators know,
+//! the plugin can report its health with a [`HealthReporter`].
+//!
+//! The agent keeps the last report of each plugin. Reports can be queried through the
+//! [control API](crate::pipeline::control::request::plugin_health) and, if enabled,
+//! as measurements of the `alumet_plugin_health` metric.
+//!
+//! The agent also counts the errors of each pipeline element, see
+//! [`element_failures`](crate::pipeline::control::request::element_failures).
+//!
+//! # Example
+//! ```no_run
+//! use alumet::plugin::AlumetPluginStart;
+//! use alumet::plugin::health::HealthStatus;
+//!
+//! # fn f(alumet: &mut AlumetPluginStart) {
+//! let health = alumet.health_reporter();
+//!
+//! // later, in a source
+//! health.report(HealthStatus::Degraded, "sensor /dev/sensor0 is not available anymore");
+//! # }
+//! ```
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::{
+    measurement::{AttributeValue, MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{
+        Source,
+        elements::error::PollError,
+        naming::{ElementName, PluginName},
+    },
+    resources::{Resource, ResourceConsumer},
+};
+
+/// Name of the metric that contains the health status of the plugins.
+pub const HEALTH_METRIC_NAME: &str = "alumet_plugin_health";
+
+/// Health status of a plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HealthStatus {
+    /// Everything is working as expected.
+    Healthy,
+    /// The plugin is working, but some of its features are not.
+    Degraded,
+    /// The plugin is not working.
+    Unhealthy,
+}
+
+/// The last health report of a plugin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    /// Optional message that explains the status.
+    pub message: Option<String>,
+    /// When the report has been made.
+    pub time: SystemTime,
+}
+
+/// Number of errors that occurred in a pipeline element.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FailureCount {
+    /// Errors after which the element continued to run.
+    pub recoverable: u64,
+    /// Errors that stopped the element.
+    pub fatal: u64,
+}
+
+/// Allows a plugin to report its health to the agent.
+///
+/// `HealthReporter` can be cloned and sent to other threads, such as the ones that run the sources.
+#[derive(Debug, Clone)]
+pub struct HealthReporter {
+    plugin: PluginName,
+    registry: HealthRegistry,
+}
+
+/// Stores the last health report of each plugin, and the failure count of each element.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct HealthRegistry {
+    reports: Arc<Mutex<BTreeMap<String, HealthReport>>>,
+    failures: Arc<Mutex<BTreeMap<String, (ElementName, FailureCount)>>>,
+}
+
+impl HealthStatus {
+    /// Returns the numerical value of the status, as used by the `alumet_plugin_health` metric.
+    ///
+    /// `Healthy` is `0`, `Degraded` is `1` and `Unhealthy` is `2`.
+    pub fn as_u64(&self) -> u64 {
+        match self {
+            HealthStatus::Healthy => 0,
+            HealthStatus::Degraded => 1,
+            HealthStatus::Unhealthy => 2,
+        }
+    }
+}
+
+impl Display for HealthStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Unhealthy => "unhealthy",
+        };
+        f.write_str(s)
+    }
+}
+
+impl HealthReporter {
+    pub(crate) fn new(plugin: PluginName, registry: HealthRegistry) -> Self {
+        Self { plugin, registry }
+    }
+
+    /// Reports the health of the plugin, with a message that explains the status.
+    ///
+    /// The report replaces the previous one.
+    pub fn report(&self, status: HealthStatus, message: impl Into<String>) {
+        self.report_impl(status, Some(message.into()));
+    }
+
+    /// Reports that the plugin is healthy.
+    pub fn healthy(&self) {
+        self.report_impl(HealthStatus::Healthy, None);
+    }
+
+    fn report_impl(&self, status: HealthStatus, message: Option<String>) {
+        let plugin = &self.plugin.0;
+        match (&status, &message) {
+            (HealthStatus::Healthy, _) => log::debug!("plugin {plugin} is {status}"),
+            (_, Some(msg)) => log::warn!("plugin {plugin} is {status}: {msg}"),
+            (_, None) => log::warn!("plugin {plugin} is {status}"),
+        }
+        let report = HealthReport {
+            status,
+            message,
+            time: SystemTime::now(),
+        };
+        self.registry.reports.lock().unwrap().insert(plugin.clone(), report);
+    }
+}
+
+impl HealthRegistry {
+    /// Returns the last report of each plugin, sorted by plugin name.
+    ///
+    /// Plugins that have never reported their health are not included.
+    pub fn snapshot(&self) -> Vec<(PluginName, HealthReport)> {
+        self.reports
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(plugin, report)| (PluginName(plugin.clone()), report.clone()))
+            .collect()
+    }
+
+    /// Counts an error of a pipeline element.
+    pub fn record_failure<'a>(&self, element: impl Into<&'a ElementName>, fatal: bool) {
+        let element = element.into();
+        let mut failures = self.failures.lock().unwrap();
+        let (_, count) = failures
+            .entry(element.to_string())
+            .or_insert_with(|| (element.clone(), FailureCount::default()));
+        if fatal {
+            count.fatal += 1;
+        } else {
+            count.recoverable += 1;
+        }
+    }
+
+    /// Returns the failure count of each element, sorted by element name.
+    ///
+    /// Elements that have never failed are not included.
+    pub fn failures(&self) -> Vec<(ElementName, FailureCount)> {
+        self.failures.lock().unwrap().values().cloned().collect()
+    }
+}
+
+/// Source that measures the health status of each plugin.
+pub(crate) struct HealthSource {
+    pub registry: HealthRegistry,
+    pub metric: TypedMetricId<u64>,
+}
+
+impl Source for HealthSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for (plugin, report) in self.registry.snapshot() {
+            let mut point = MeasurementPoint::new(
+                timestamp,
+                self.metric,
+                Resource::LocalMachine,
+                ResourceConsumer::LocalMachine,
+                report.status.as_u64(),
+            )
+            .with_attr("plugin", AttributeValue::String(plugin.0));
+            if let Some(message) = report.message {
+                point = point.with_attr("message", AttributeValue::String(message));
+            }
+            measurements.push(point);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pipeline::naming::{PluginName, SourceName};
+
+    use super::{FailureCount, HealthRegistry, HealthReporter, HealthStatus};
+
+    #[test]
+    fn last_report_wins() {
+        let registry = HealthRegistry::default();
+        let a = HealthReporter::new(PluginName(String::from("a")), registry.clone());
+        let b = HealthReporter::new(PluginName(String::from("b")), registry.clone());
+        assert!(registry.snapshot().is_empty());
+
+        b.report(HealthStatus::Unhealthy, "no sensor");
+        a.healthy();
+        a.report(HealthStatus::Degraded, "sensor 2 is missing");
+
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        let (plugin, report) = &snapshot[0];
+        assert_eq!(plugin.0, "a");
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert_eq!(report.message.as_deref(), Some("sensor 2 is missing"));
+        let (plugin, report) = &snapshot[1];
+        assert_eq!(plugin.0, "b");
+        assert_eq!(report.status, HealthStatus::Unhealthy);
+    }
+
+    #[test]
+    fn failure_count() {
+        let registry = HealthRegistry::default();
+        let a = SourceName::new(String::from("p"), String::from("a"));
+        let b = SourceName::new(String::from("p"), String::from("b"));
+        assert!(registry.failures().is_empty());
+
+        registry.record_failure(&b, true);
+        registry.record_failure(&a, false);
+        registry.record_failure(&a, false);
+
+        let failures = registry.failures();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].0, a.into());
+        assert_eq!(
+            failures[0].1,
+            FailureCount {
+                recoverable: 2,
+                fatal: 0
+            }
+        );
+        assert_eq!(failures[1].0, b.into());
+        assert_eq!(
+            failures[1].1,
+            FailureCount {
+                recoverable: 0,
+                fatal: 1
+            }
+        );
+    }
+}
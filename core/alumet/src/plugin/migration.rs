@@ -0,0 +1,156 @@
+//! Migration of plugin configurations between versions.
+//!
+//! When the configuration format of a plugin changes (renamed keys, new structure, ...),
+//! the plugin can declare a list of [`ConfigMigration`]s. Each migration upgrades the config
+//! to a new version, and the version of the configuration is stored in the `config_version`
+//! key of the plugin section (a section without this key is at version 1).
+//!
+//! The agent applies the migrations automatically when it loads an outdated configuration,
+//! and can rewrite the configuration file to the latest format.
+//!
+//! # Example
+//! ```
+//! use alumet::plugin::migration::{self, ConfigMigration};
+//!
+//! let migrations = vec![
+//!     // version 2 renames `interval` to `poll_interval`
+//!     ConfigMigration::new(2, |config| migration::rename_key(config, "interval", "poll_interval")),
+//! ];
+//!
+//! let mut config: toml::Table = toml::from_str("interval = '1s'").unwrap();
+//! let migrated = migration::migrate(&mut config, &migrations).unwrap();
+//! assert_eq!(migrated, Some((1, 2)));
+//! assert_eq!(config.get("poll_interval").and_then(|v| v.as_str()), Some("1s"));
+//! assert_eq!(config.get("config_version").and_then(|v| v.as_integer()), Some(2));
+//! ```
+use anyhow::{Context, anyhow};
+
+/// Name of the key that holds the version of a plugin configuration.
+pub const CONFIG_VERSION_KEY: &str = "config_version";
+
+/// Function that upgrades a configuration, in place.
+pub type MigrateFn = fn(&mut toml::Table) -> anyhow::Result<()>;
+
+/// A migration that upgrades a plugin configuration to a given version.
+#[derive(Clone, Debug)]
+pub struct ConfigMigration {
+    /// The version of the configuration after the migration.
+    pub to_version: u32,
+    /// Upgrades the configuration from the previous version to `to_version`.
+    pub migrate: MigrateFn,
+}
+
+impl ConfigMigration {
+    pub fn new(to_version: u32, migrate: MigrateFn) -> Self {
+        Self { to_version, migrate }
+    }
+}
+
+/// Returns the latest version of the configuration, according to the given migrations.
+pub fn latest_version(migrations: &[ConfigMigration]) -> u32 {
+    migrations.iter().map(|m| m.to_version).max().unwrap_or(1)
+}
+
+/// Removes the version key from the configuration and returns its value.
+///
+/// If the key is absent, the config is at version 1.
+pub fn take_version(config: &mut toml::Table) -> anyhow::Result<u32> {
+    match config.remove(CONFIG_VERSION_KEY) {
+        None => Ok(1),
+        Some(toml::Value::Integer(v)) => u32::try_from(v).with_context(|| format!("invalid {CONFIG_VERSION_KEY}: {v}")),
+        Some(bad) => Err(anyhow!(
+            "invalid {CONFIG_VERSION_KEY}: expected an integer, got {}",
+            bad.type_str()
+        )),
+    }
+}
+
+/// Applies the migrations that are needed to bring `config` to the latest version.
+///
+/// Returns the old and new versions if the config has been migrated, or `None` if it was up to date.
+/// After a migration, the `config_version` key contains the new version.
+/// If the config is already up to date, it is not modified.
+pub fn migrate(config: &mut toml::Table, migrations: &[ConfigMigration]) -> anyhow::Result<Option<(u32, u32)>> {
+    let had_version = config.contains_key(CONFIG_VERSION_KEY);
+    let current = take_version(config)?;
+    let latest = latest_version(migrations);
+    if current >= latest {
+        if had_version {
+            config.insert(String::from(CONFIG_VERSION_KEY), toml::Value::Integer(current.into()));
+        }
+        return Ok(None);
+    }
+
+    let mut migrations: Vec<&ConfigMigration> = migrations.iter().filter(|m| m.to_version > current).collect();
+    migrations.sort_by_key(|m| m.to_version);
+    for m in migrations {
+        (m.migrate)(config).with_context(|| format!("migration to config version {} failed", m.to_version))?;
+    }
+    config.insert(String::from(CONFIG_VERSION_KEY), toml::Value::Integer(latest.into()));
+    Ok(Some((current, latest)))
+}
+
+/// Renames a key of the configuration, if it exists.
+///
+/// Utility function for writing migrations.
+pub fn rename_key(config: &mut toml::Table, old: &str, new: &str) -> anyhow::Result<()> {
+    if let Some(value) = config.remove(old) {
+        if config.contains_key(new) {
+            return Err(anyhow!("cannot rename {old} to {new}: {new} already exists"));
+        }
+        config.insert(new.to_owned(), value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConfigMigration, latest_version, migrate, rename_key};
+
+    fn migrations() -> Vec<ConfigMigration> {
+        vec![
+            ConfigMigration::new(3, |config| {
+                config.insert(String::from("added"), toml::Value::Boolean(true));
+                Ok(())
+            }),
+            ConfigMigration::new(2, |config| rename_key(config, "a", "b")),
+        ]
+    }
+
+    #[test]
+    fn from_v1() {
+        let mut config = toml::toml! { a = 1 };
+        let res = migrate(&mut config, &migrations()).unwrap();
+        assert_eq!(res, Some((1, 3)));
+        assert_eq!(config, toml::toml! { b = 1 added = true config_version = 3 });
+    }
+
+    #[test]
+    fn from_v2() {
+        let mut config = toml::toml! { a = 1 config_version = 2 };
+        let res = migrate(&mut config, &migrations()).unwrap();
+        assert_eq!(res, Some((2, 3)));
+        assert_eq!(config, toml::toml! { a = 1 added = true config_version = 3 });
+    }
+
+    #[test]
+    fn up_to_date() {
+        let mut config = toml::toml! { b = 1 config_version = 3 };
+        let res = migrate(&mut config, &migrations()).unwrap();
+        assert_eq!(res, None);
+        assert_eq!(config, toml::toml! { b = 1 config_version = 3 });
+
+        // no migration at all
+        assert_eq!(latest_version(&[]), 1);
+        let mut config = toml::toml! { a = 1 };
+        assert_eq!(migrate(&mut config, &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_version() {
+        let mut config = toml::toml! { config_version = "two" };
+        assert!(migrate(&mut config, &migrations()).is_err());
+        let mut config = toml::toml! { config_version = -1 };
+        assert!(migrate(&mut config, &migrations()).is_err());
+    }
+}
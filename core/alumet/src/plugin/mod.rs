@@ -23,6 +23,9 @@
 //! arguments that allow to interact with the running measurement pipeline (which is not possible
 //! in `start`, since the pipeline is not fully constructed nor started at this point).
 //!
+//! During the operation phase, the configuration of the plugin can be modified without restarting
+//! the agent: [`reconfigure`](Plugin::reconfigure) is then called with the new configuration.
+//!
 //! 5. **Stop**: when the pipeline is stopped, the elements registered by the plugin are stopped and dropped.
 //! Then, [`stop`](Plugin::stop) is called.
 //!
@@ -93,11 +96,16 @@
 //!
 use std::fmt::Debug;
 
+use self::migration::ConfigMigration;
 use self::rust::AlumetPlugin;
 
 pub mod event;
+pub mod health;
+pub mod migration;
 pub(crate) mod phases;
 pub mod rust;
+pub mod schema;
+pub mod secret;
 pub mod util;
 pub mod version;
 
@@ -110,7 +118,9 @@ pub struct PluginMetadata {
     /// Version of the plugin, should follow semantic versioning (of the form `x.y.z`).
     pub version: String,
     /// Function that initializes the plugin.
-    pub init: Box<dyn FnOnce(ConfigTable) -> anyhow::Result<Box<dyn Plugin>>>,
+    ///
+    /// It runs on a separate thread, see [`agent::timeout`](crate::agent::timeout).
+    pub init: Box<dyn FnOnce(ConfigTable) -> anyhow::Result<Box<dyn Plugin>> + Send>,
     /// Function that returns a default configuration for the plugin, or None
     /// if the plugin has no configurable option.
     ///
@@ -118,6 +128,64 @@ pub struct PluginMetadata {
     /// Alumet agent, in case it does not exist. In other cases, the default
     /// config returned by this function is not used, including when
     pub default_config: Box<dyn Fn() -> anyhow::Result<Option<ConfigTable>>>,
+    /// Other plugins that this plugin depends on.
+    ///
+    /// The agent uses this list to check that the required plugins are enabled,
+    /// and to initialize and start the dependencies before the plugins that depend on them.
+    pub dependencies: Vec<PluginDependency>,
+    /// Function that returns the JSON Schema of the plugin configuration, or None
+    /// if the configuration should not be validated.
+    ///
+    /// See the [`schema`] module.
+    pub config_schema: Box<dyn Fn() -> anyhow::Result<Option<serde_json::Value>>>,
+    /// Migrations that upgrade old configurations of the plugin to the latest format.
+    ///
+    /// See the [`migration`] module.
+    pub config_migrations: Vec<ConfigMigration>,
+}
+
+/// A dependency of a plugin on another plugin.
+///
+/// A plugin is always initialized and started _after_ its dependencies.
+/// If a required dependency is not enabled, the agent fails to start with an error
+/// that indicates which plugin is missing.
+///
+/// # Example
+/// ```
+/// use alumet::plugin::PluginDependency;
+///
+/// let dependencies = vec![
+///     // `rapl` must be enabled
+///     PluginDependency::required("rapl"),
+///     // if `procfs` is enabled, start it before us
+///     PluginDependency::optional("procfs"),
+/// ];
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginDependency {
+    /// Name of the plugin that is depended on.
+    pub plugin: String,
+    /// If `true`, the dependency must be enabled.
+    /// If `false`, the dependency only constrains the startup order (if it is enabled).
+    pub required: bool,
+}
+
+impl PluginDependency {
+    /// Declares a mandatory dependency on a plugin.
+    pub fn required(plugin: impl Into<String>) -> Self {
+        Self {
+            plugin: plugin.into(),
+            required: true,
+        }
+    }
+
+    /// Declares an optional dependency on a plugin.
+    pub fn optional(plugin: impl Into<String>) -> Self {
+        Self {
+            plugin: plugin.into(),
+            required: false,
+        }
+    }
 }
 
 impl PluginMetadata {
@@ -128,6 +196,9 @@ impl PluginMetadata {
             version: P::version().to_owned(),
             init: Box::new(|conf| P::init(conf).map(|p| p as _)),
             default_config: Box::new(P::default_config),
+            dependencies: P::dependencies(),
+            config_schema: Box::new(P::config_schema),
+            config_migrations: P::config_migrations(),
         }
     }
 }
@@ -137,6 +208,7 @@ impl Debug for PluginMetadata {
         f.debug_struct("PluginMetadata")
             .field("name", &self.name)
             .field("version", &self.version)
+            .field("dependencies", &self.dependencies)
             .finish()
     }
 }
@@ -179,7 +251,10 @@ pub struct ConfigTable(pub toml::Table);
 ///
 /// If you are writing a plugin in Rust, implement [`AlumetPlugin`] instead.
 /// If you are writing a plugin in C, you need to define the right symbols in your shared library.
-pub trait Plugin {
+///
+/// Plugins are `Send` because the agent calls some of their methods on separate threads,
+/// see [`agent::timeout`](crate::agent::timeout).
+pub trait Plugin: Send {
     /// The name of the plugin. It must be unique: two plugins cannot have the same name.
     fn name(&self) -> &str;
 
@@ -210,4 +285,14 @@ pub trait Plugin {
     ///
     /// It can be used, for instance, to obtain a [`ScopedControlHandle`](crate::pipeline::control::ScopedControlHandle).
     fn post_pipeline_start(&mut self, alumet: &mut AlumetPostStart) -> anyhow::Result<()>;
+
+    /// Applies a new configuration to the plugin, while the measurement pipeline is running.
+    ///
+    /// This method is called when the configuration of the plugin has been modified
+    /// (see [`agent::reload`](crate::agent::reload)).
+    /// By default, the plugin does not support reconfiguration and an error is returned.
+    fn reconfigure(&mut self, config: ConfigTable, alumet: &mut AlumetPostStart) -> anyhow::Result<()> {
+        let _ = (config, alumet);
+        Err(anyhow::anyhow!(rust::ReconfigureUnsupported))
+    }
 }
@@ -16,6 +16,7 @@ use crate::pipeline::elements::source::trigger::TriggerSpec;
 use crate::pipeline::elements::{output, source, transform};
 use crate::pipeline::naming::{PluginName, namespace::DuplicateNameError};
 use crate::pipeline::{self, Output, Source, Transform};
+use crate::plugin::health::HealthReporter;
 use crate::units::PrefixedUnit;
 
 /// Structure passed to plugins for the start-up phase.
@@ -363,6 +364,13 @@ impl<'a> AlumetPluginStart<'a> {
         let plugin = self.current_plugin_name();
         self.pre_start_actions.push((plugin, Box::new(action)));
     }
+
+    /// Returns a handle that allows the plugin to report its health to the agent.
+    ///
+    /// See the [`health`](crate::plugin::health) module.
+    pub fn health_reporter(&self) -> HealthReporter {
+        HealthReporter::new(self.current_plugin_name(), self.pipeline_builder.health.clone())
+    }
 }
 
 /// Structure passed to plugins for the pre start-up phase.
@@ -440,6 +448,11 @@ impl<'a> AlumetPostStart<'a> {
         self.pipeline.async_runtime().clone()
     }
 
+    /// Returns a handle that allows the plugin to report its health to the agent.
+    pub fn health_reporter(&self) -> HealthReporter {
+        HealthReporter::new(self.current_plugin.clone(), self.pipeline.health.clone())
+    }
+
     /// Runs a future to completion on the underlying async runtime.
     ///
     /// It is fine to block the thread in `post_pipeline_start`,
@@ -6,13 +6,13 @@ use anyhow::{Context, anyhow};
 
 use crate::plugin::{AlumetPluginStart, Plugin};
 
-use super::{AlumetPostStart, ConfigTable, phases::AlumetPreStart};
+use super::{AlumetPostStart, ConfigTable, PluginDependency, migration::ConfigMigration, phases::AlumetPreStart};
 
 /// Trait for Alumet plugins written in Rust.
 ///
 /// Implement this trait to define your plugin.
 /// See the [plugin module documentation](super#static-plugins).
-pub trait AlumetPlugin {
+pub trait AlumetPlugin: Send {
     // Note: add `where Self: Sized` to make this trait "object safe", if necessary in the future.
 
     /// The name of the plugin. It must be unique: two plugins cannot have the same name.
@@ -53,6 +53,47 @@ pub trait AlumetPlugin {
     /// ```
     fn default_config() -> anyhow::Result<Option<ConfigTable>>;
 
+    /// Returns the JSON Schema of the plugin configuration.
+    ///
+    /// If a schema is declared, it is used to validate the configuration before calling [`init`](Self::init).
+    /// By default, no schema is declared and the configuration is not validated: the exported
+    /// schema is then [inferred](super::schema::infer_schema) from the [default config](Self::default_config).
+    ///
+    /// The inferred schema only describes the shape of the default config, which rejects
+    /// valid configs that use another variant of an enum. To opt into the validation,
+    /// return a schema that covers every accepted shape.
+    fn config_schema() -> anyhow::Result<Option<serde_json::Value>> {
+        Ok(None)
+    }
+
+    /// Returns the migrations that upgrade old configurations of the plugin to the latest format.
+    ///
+    /// Declare a migration each time the configuration format changes in a way that is not
+    /// backward compatible (renamed keys, new structure, ...). By default, there is no migration.
+    /// See the [`migration`](super::migration) module.
+    fn config_migrations() -> Vec<ConfigMigration> {
+        Vec::new()
+    }
+
+    /// Returns the plugins that this plugin depends on.
+    ///
+    /// The dependencies are initialized and started before this plugin.
+    /// By default, a plugin has no dependency.
+    ///
+    /// # Example
+    /// ```ignore
+    /// use alumet::plugin::PluginDependency;
+    ///
+    /// impl AlumetPlugin for MyPlugin {
+    ///     fn dependencies() -> Vec<PluginDependency> {
+    ///         vec![PluginDependency::required("rapl")]
+    ///     }
+    /// }
+    /// ```
+    fn dependencies() -> Vec<PluginDependency> {
+        Vec::new()
+    }
+
     /// Starts the plugin, allowing it to register metrics, sources and outputs.
     ///
     /// # Plugin restart
@@ -84,6 +125,32 @@ pub trait AlumetPlugin {
         let _ = alumet; // do nothing by default
         Ok(())
     }
+
+    /// Applies a new configuration to the plugin, while the measurement pipeline is running.
+    ///
+    /// This method is called when the section of the plugin in the configuration file has changed,
+    /// if the agent watches its configuration (see [`agent::reload`](crate::agent::reload)).
+    /// The plugin can use `alumet` to adjust its elements, for instance to change the poll interval
+    /// of its sources with a [`ControlHandle`](crate::pipeline::control::PluginControlHandle).
+    ///
+    /// By default, reconfiguration is not supported: the new config is ignored and an error is returned,
+    /// which tells the user that the agent must be restarted to apply the changes.
+    ///
+    /// # Example
+    /// ```ignore
+    /// impl AlumetPlugin for MyPlugin {
+    ///     fn reconfigure(&mut self, config: ConfigTable, alumet: &mut AlumetPostStart) -> anyhow::Result<()> {
+    ///         let config: Config = deserialize_config(config)?;
+    ///         let request = request::source(self.source_key.clone()).set_trigger(TriggerSpec::at_interval(config.poll_interval));
+    ///         alumet.block_on(alumet.pipeline_control().send_wait(request, TIMEOUT))?;
+    ///         Ok(())
+    ///     }
+    /// }
+    /// ```
+    fn reconfigure(&mut self, config: ConfigTable, alumet: &mut AlumetPostStart) -> anyhow::Result<()> {
+        let _ = (config, alumet);
+        Err(anyhow!(ReconfigureUnsupported))
+    }
 }
 
 // Every AlumetPlugin is a Plugin :)
@@ -111,6 +178,10 @@ impl<P: AlumetPlugin> Plugin for P {
     fn post_pipeline_start(&mut self, alumet: &mut AlumetPostStart) -> anyhow::Result<()> {
         AlumetPlugin::post_pipeline_start(self, alumet)
     }
+
+    fn reconfigure(&mut self, config: ConfigTable, alumet: &mut AlumetPostStart) -> anyhow::Result<()> {
+        AlumetPlugin::reconfigure(self, config, alumet)
+    }
 }
 
 pub fn deserialize_config<'de, T: serde::de::Deserialize<'de>>(config: ConfigTable) -> anyhow::Result<T> {
@@ -153,3 +224,17 @@ impl std::fmt::Display for InvalidConfig {
         write!(f, "invalid configuration")
     }
 }
+
+/// Signals that a plugin does not support [reconfiguration](AlumetPlugin::reconfigure).
+#[derive(Debug)]
+pub struct ReconfigureUnsupported;
+
+impl std::error::Error for ReconfigureUnsupported {}
+impl std::fmt::Display for ReconfigureUnsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the plugin does not support reconfiguration, restart the agent to apply the new config"
+        )
+    }
+}
@@ -0,0 +1,397 @@
+//! JSON Schemas of plugin configurations.
+//!
+//! Each plugin can describe the structure of its configuration with a [JSON Schema](https://json-schema.org/).
+//! The agent uses this schema to validate the configuration before initializing the plugin,
+//! which allows to report errors with the full path of the faulty key (e.g. `plugins.csv.output_path`).
+//! The schemas can also be exported, so that editors and other tools can offer completion.
+//!
+//! Only the plugins that declare their schema, by overriding
+//! [`AlumetPlugin::config_schema`](super::rust::AlumetPlugin::config_schema), get their configuration validated.
+//! For the other plugins, the exported schema is [inferred](infer_schema) from their default configuration.
+//! The schema can be written with the help of the `schemars` crate:
+//!
+//! ```ignore
+//! impl AlumetPlugin for MyPlugin {
+//!     fn config_schema() -> anyhow::Result<Option<serde_json::Value>> {
+//!         let schema = schemars::schema_for!(Config);
+//!         Ok(Some(serde_json::to_value(schema)?))
+//!     }
+//! }
+//! ```
+//!
+//! # Supported keywords
+//! The validator supports the subset of JSON Schema that is useful for TOML configurations:
+//! `type`, `properties`, `additionalProperties`, `required`, `items`, `enum`, `const`,
+//! `anyOf`, `oneOf`, `allOf` and local references (`$ref` to `#/$defs/...` or `#/definitions/...`).
+//! Other keywords are ignored.
+use std::fmt::Display;
+
+use serde_json::{Map, Value, json};
+use thiserror::Error;
+
+use super::{ConfigTable, PluginMetadata, migration::CONFIG_VERSION_KEY};
+
+/// Infers a JSON Schema from a configuration, usually the default configuration of a plugin.
+///
+/// The schema describes the type of each key, and uses the values of the configuration as defaults.
+/// Keys that are not in the configuration are allowed, because optional settings are usually
+/// absent from the default configuration.
+pub fn infer_schema(config: &ConfigTable) -> Value {
+    fn infer_table(table: &toml::Table) -> Value {
+        let properties: Map<String, Value> = table.iter().map(|(k, v)| (k.clone(), infer_value(v))).collect();
+        json!({ "type": "object", "properties": properties })
+    }
+
+    fn infer_value(value: &toml::Value) -> Value {
+        match value {
+            toml::Value::String(s) => json!({ "type": "string", "default": s }),
+            toml::Value::Integer(i) => json!({ "type": "integer", "default": i }),
+            // integers are also valid floats
+            toml::Value::Float(f) => json!({ "type": "number", "default": f }),
+            toml::Value::Boolean(b) => json!({ "type": "boolean", "default": b }),
+            toml::Value::Datetime(d) => json!({ "type": "string", "format": "date-time", "default": d.to_string() }),
+            toml::Value::Array(values) => match values.first() {
+                Some(first) => {
+                    let mut items = infer_value(first);
+                    if let Some(obj) = items.as_object_mut() {
+                        obj.remove("default");
+                    }
+                    json!({ "type": "array", "items": items })
+                }
+                None => json!({ "type": "array" }),
+            },
+            toml::Value::Table(table) => infer_table(table),
+        }
+    }
+
+    let mut schema = infer_table(&config.0);
+    schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    schema
+}
+
+/// Generates the JSON Schema of an agent configuration file that contains the given plugins.
+///
+/// The schema of a plugin that does not declare one is inferred from its default configuration.
+/// Each plugin has its own section `plugins.<name>`, which accepts the keys `enabled`
+/// and `config_version` (see [`migration`](super::migration)) in addition to the keys of the plugin's schema.
+/// The general options of the agent are described by `general_options`, which should be
+/// the schema of an object, or `None` if they are not known.
+pub fn agent_config_schema<'p>(
+    plugins: impl IntoIterator<Item = &'p PluginMetadata>,
+    general_options: Option<Value>,
+) -> anyhow::Result<Value> {
+    let mut plugin_schemas = Map::new();
+    for plugin in plugins {
+        let schema = match (plugin.config_schema)()? {
+            Some(schema) => Some(schema),
+            None => (plugin.default_config)()?.as_ref().map(infer_schema),
+        };
+        let mut schema = schema.unwrap_or_else(|| json!({ "type": "object" }));
+        if let Some(obj) = schema.as_object_mut() {
+            obj.remove("$schema");
+            let properties = obj.entry("properties").or_insert_with(|| json!({}));
+            properties["enabled"] = json!({ "type": "boolean", "default": true });
+            properties[CONFIG_VERSION_KEY] = json!({ "type": "integer", "minimum": 1 });
+        }
+        plugin_schemas.insert(plugin.name.clone(), schema);
+    }
+
+    let mut schema = general_options.unwrap_or_else(|| json!({ "type": "object" }));
+    schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    schema["properties"]["plugins"] = json!({ "type": "object", "properties": plugin_schemas });
+    Ok(schema)
+}
+
+/// Validates a configuration against a JSON Schema.
+///
+/// All the problems are reported at once. The paths of the errors start with `path_prefix`,
+/// which is usually `plugins.<name>`.
+pub fn validate(schema: &Value, config: &ConfigTable, path_prefix: &str) -> Result<(), ValidationError> {
+    let mut validator = Validator {
+        root: schema,
+        errors: Vec::new(),
+    };
+    validator.check(schema, &toml::Value::Table(config.0.clone()), path_prefix);
+    if validator.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError {
+            errors: validator.errors,
+        })
+    }
+}
+
+/// The configuration does not match its schema.
+#[derive(Debug, Error)]
+#[error("{}", format_errors(.errors))]
+pub struct ValidationError {
+    pub errors: Vec<KeyError>,
+}
+
+/// A problem detected at a particular key of the configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyError {
+    /// Full path of the key, for instance `plugins.csv.output_path`.
+    pub path: String,
+    /// What is wrong.
+    pub message: String,
+}
+
+fn format_errors(errors: &[KeyError]) -> String {
+    let lines: Vec<String> = errors.iter().map(|e| format!("- {e}")).collect();
+    format!("the configuration does not match its schema:\n{}", lines.join("\n"))
+}
+
+impl Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+struct Validator<'s> {
+    root: &'s Value,
+    errors: Vec<KeyError>,
+}
+
+impl<'s> Validator<'s> {
+    fn error(&mut self, path: &str, message: String) {
+        self.errors.push(KeyError {
+            path: path.to_owned(),
+            message,
+        });
+    }
+
+    /// Resolves a local reference like `#/$defs/Config`.
+    fn resolve(&self, reference: &str) -> Option<&'s Value> {
+        let pointer = reference.strip_prefix('#')?;
+        self.root.pointer(pointer)
+    }
+
+    /// Checks `value` against `schema`, without reporting errors.
+    fn matches(&self, schema: &'s Value, value: &toml::Value) -> bool {
+        let mut sub = Validator {
+            root: self.root,
+            errors: Vec::new(),
+        };
+        sub.check(schema, value, "");
+        sub.errors.is_empty()
+    }
+
+    fn check(&mut self, schema: &'s Value, value: &toml::Value, path: &str) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return self.error(path, String::from("this key is not allowed")),
+            Value::Object(obj) => obj,
+            _ => return,
+        };
+
+        if let Some(Value::String(reference)) = schema.get("$ref") {
+            match self.resolve(reference) {
+                Some(target) => self.check(target, value, path),
+                None => log::warn!("unresolved reference in config schema: {reference}"),
+            }
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let type_ok = match expected {
+                Value::String(t) => type_matches(t, value),
+                Value::Array(types) => types.iter().filter_map(Value::as_str).any(|t| type_matches(t, value)),
+                _ => true,
+            };
+            if !type_ok {
+                let expected = match expected {
+                    Value::Array(types) => types.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" or "),
+                    other => other.as_str().unwrap_or_default().to_owned(),
+                };
+                // don't check the rest, it would only produce confusing errors
+                return self.error(path, format!("expected {expected}, found {}", value.type_str()));
+            }
+        }
+
+        if let Some(Value::Array(allowed)) = schema.get("enum")
+            && !allowed.iter().any(|a| toml_equals_json(value, a))
+        {
+            let allowed: Vec<String> = allowed.iter().map(|a| a.to_string()).collect();
+            self.error(
+                path,
+                format!("invalid value {value}, expected one of {}", allowed.join(", ")),
+            );
+        }
+        if let Some(constant) = schema.get("const")
+            && !toml_equals_json(value, constant)
+        {
+            self.error(path, format!("invalid value {value}, expected {constant}"));
+        }
+
+        for keyword in ["anyOf", "oneOf"] {
+            if let Some(Value::Array(alternatives)) = schema.get(keyword)
+                && !alternatives.iter().any(|alt| self.matches(alt, value))
+            {
+                self.error(
+                    path,
+                    String::from("the value does not match any of the allowed variants"),
+                );
+            }
+        }
+        if let Some(Value::Array(all)) = schema.get("allOf") {
+            for sub_schema in all {
+                self.check(sub_schema, value, path);
+            }
+        }
+
+        match value {
+            toml::Value::Table(table) => self.check_table(schema, table, path),
+            toml::Value::Array(values) => {
+                if let Some(items) = schema.get("items") {
+                    for (i, v) in values.iter().enumerate() {
+                        self.check(items, v, &format!("{path}[{i}]"));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn check_table(&mut self, schema: &'s Map<String, Value>, table: &toml::Table, path: &str) {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !table.contains_key(key) {
+                    self.error(&join_path(path, key), String::from("missing required key"));
+                }
+            }
+        }
+        for (key, v) in table {
+            let key_path = join_path(path, key);
+            match properties.and_then(|p| p.get(key)) {
+                Some(key_schema) => self.check(key_schema, v, &key_path),
+                None => {
+                    if let Some(additional) = schema.get("additionalProperties") {
+                        match additional {
+                            Value::Bool(false) => self.error(&key_path, String::from("unknown key")),
+                            other => self.check(other, v, &key_path),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn type_matches(json_type: &str, value: &toml::Value) -> bool {
+    matches!(
+        (json_type, value),
+        ("string", toml::Value::String(_) | toml::Value::Datetime(_))
+            | ("integer", toml::Value::Integer(_))
+            | ("number", toml::Value::Integer(_) | toml::Value::Float(_))
+            | ("boolean", toml::Value::Boolean(_))
+            | ("array", toml::Value::Array(_))
+            | ("object", toml::Value::Table(_))
+    )
+}
+
+fn toml_equals_json(value: &toml::Value, json: &Value) -> bool {
+    match (value, json) {
+        (toml::Value::String(a), Value::String(b)) => a == b,
+        (toml::Value::Integer(a), Value::Number(b)) => b.as_i64() == Some(*a),
+        (toml::Value::Float(a), Value::Number(b)) => b.as_f64() == Some(*a),
+        (toml::Value::Boolean(a), Value::Bool(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{infer_schema, validate};
+    use crate::plugin::ConfigTable;
+
+    fn config(s: &str) -> ConfigTable {
+        ConfigTable(toml::from_str(s).unwrap())
+    }
+
+    #[test]
+    fn inferred_schema() {
+        let default = config(
+            r#"
+            poll_interval = "1s"
+            count = 2
+            ratio = 0.5
+            list = ["a"]
+            [nested]
+            enabled = true
+            "#,
+        );
+        let schema = infer_schema(&default);
+        assert_eq!(schema["properties"]["poll_interval"]["type"], json!("string"));
+        assert_eq!(schema["properties"]["count"]["default"], json!(2));
+        assert_eq!(schema["properties"]["list"]["items"], json!({ "type": "string" }));
+        assert_eq!(
+            schema["properties"]["nested"]["properties"]["enabled"]["type"],
+            json!("boolean")
+        );
+
+        // the default config is valid
+        validate(&schema, &default, "plugins.test").unwrap();
+
+        // integers are valid floats, unknown keys are allowed
+        validate(&schema, &config("ratio = 1\nunknown = 0"), "plugins.test").unwrap();
+
+        // wrong types are reported with their full path
+        let err = validate(
+            &schema,
+            &config("count = \"2\"\nlist = [1]\nnested = { enabled = 0 }"),
+            "plugins.test",
+        )
+        .unwrap_err();
+        let errors: Vec<String> = err.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "plugins.test.count: expected integer, found string",
+                "plugins.test.list[0]: expected string, found integer",
+                "plugins.test.nested.enabled: expected boolean, found integer",
+            ]
+        );
+    }
+
+    #[test]
+    fn explicit_schema() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "mode": { "enum": ["a", "b"] },
+                "inner": { "$ref": "#/$defs/Inner" },
+            },
+            "required": ["mode"],
+            "additionalProperties": false,
+            "$defs": {
+                "Inner": { "type": "object", "properties": { "x": { "type": ["integer", "null"] } } }
+            }
+        });
+        validate(&schema, &config("mode = \"a\"\ninner = { x = 1 }"), "p").unwrap();
+
+        let err = validate(&schema, &config("mode = \"c\"\ninner = { x = 1.5 }\nother = 1"), "p").unwrap_err();
+        let errors: Vec<String> = err.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "p.mode: invalid value \"c\", expected one of \"a\", \"b\"",
+                "p.inner.x: expected integer or null, found float",
+                "p.other: unknown key",
+            ]
+        );
+
+        let err = validate(&schema, &config(""), "p").unwrap_err();
+        assert_eq!(err.errors[0].to_string(), "p.mode: missing required key");
+    }
+}
@@ -0,0 +1,245 @@
+//! Secret values in plugin configurations.
+//!
+//! Passwords, tokens and other credentials should not be written directly in the configuration file.
+//! Instead, a configuration option of type [`Secret`] can reference a secret with a URI of the form
+//! `secret://<provider>/<path>`, which is resolved when the configuration is deserialized.
+//!
+//! The following providers are always available:
+//! - `file`: reads the secret from a file, e.g. `secret://file/etc/alumet/influxdb-token`
+//!   (the path is absolute, the trailing newline is removed).
+//! - `env`: reads the secret from an environment variable, e.g. `secret://env/INFLUXDB_TOKEN`.
+//!
+//! Additional providers can be registered with [`register_provider`].
+//! A string that does not start with `secret://` is used as is, which allows to keep
+//! inline values for testing purposes.
+//!
+//! # Example
+//! ```
+//! use alumet::plugin::secret::Secret;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Config {
+//!     host: String,
+//!     token: Secret,
+//! }
+//!
+//! # unsafe { std::env::set_var("EXAMPLE_TOKEN", "abcd") };
+//! let config: Config = toml::from_str(r#"
+//!     host = "http://localhost:8086"
+//!     token = "secret://env/EXAMPLE_TOKEN"
+//! "#).unwrap();
+//!
+//! assert_eq!(config.token.expose(), "abcd");
+//! // the value never appears in the logs
+//! assert_eq!(format!("{:?}", config.token), "Secret(secret://env/EXAMPLE_TOKEN)");
+//! ```
+use std::{
+    collections::HashMap,
+    fmt,
+    path::Path,
+    sync::{Arc, LazyLock, RwLock},
+};
+
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the URIs that reference a secret.
+pub const SECRET_URI_PREFIX: &str = "secret://";
+
+/// A secret value, such as a password or an API token.
+///
+/// The value is hidden from the `Debug` and `Display` implementations, use [`expose`](Self::expose)
+/// to get it. When serialized, only the reference to the secret (`secret://...`) is written,
+/// unless the secret has been given inline.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Secret {
+    value: String,
+    reference: Option<String>,
+}
+
+/// Resolves references to secrets of a particular kind.
+pub trait SecretProvider: Send + Sync {
+    /// Returns the secret that is stored at `path`.
+    ///
+    /// `path` is the part of the URI that follows `secret://<provider>/`.
+    fn get(&self, path: &str) -> anyhow::Result<String>;
+}
+
+/// Reads secrets from files.
+pub struct FileProvider;
+
+/// Reads secrets from environment variables.
+pub struct EnvProvider;
+
+static PROVIDERS: LazyLock<RwLock<HashMap<String, Arc<dyn SecretProvider>>>> = LazyLock::new(|| {
+    let mut providers: HashMap<String, Arc<dyn SecretProvider>> = HashMap::new();
+    providers.insert(String::from("file"), Arc::new(FileProvider));
+    providers.insert(String::from("env"), Arc::new(EnvProvider));
+    RwLock::new(providers)
+});
+
+/// Registers a new provider of secrets, which will handle the URIs `secret://<name>/...`.
+///
+/// If a provider with the same name already exists, it is replaced.
+pub fn register_provider(name: impl Into<String>, provider: impl SecretProvider + 'static) {
+    PROVIDERS.write().unwrap().insert(name.into(), Arc::new(provider));
+}
+
+impl Secret {
+    /// Creates a secret from an inline value.
+    pub fn new(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            reference: None,
+        }
+    }
+
+    /// Parses a configuration value: if it is a reference to a secret, resolves it,
+    /// otherwise uses it as an inline secret.
+    pub fn resolve(config_value: &str) -> anyhow::Result<Self> {
+        let Some(uri) = config_value.strip_prefix(SECRET_URI_PREFIX) else {
+            return Ok(Self::new(config_value));
+        };
+        let (provider_name, path) = uri
+            .split_once('/')
+            .with_context(|| format!("invalid secret reference {config_value}, expected secret://<provider>/<path>"))?;
+        let provider = PROVIDERS
+            .read()
+            .unwrap()
+            .get(provider_name)
+            .cloned()
+            .with_context(|| format!("unknown secret provider '{provider_name}' in {config_value}"))?;
+        let value = provider
+            .get(path)
+            .with_context(|| format!("failed to get secret {config_value}"))?;
+        Ok(Self {
+            value,
+            reference: Some(config_value.to_owned()),
+        })
+    }
+
+    /// Returns the value of the secret.
+    ///
+    /// Be careful not to log it!
+    pub fn expose(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns the reference to this secret, or `None` if it has been given inline.
+    pub fn reference(&self) -> Option<&str> {
+        self.reference.as_deref()
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({self})")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.reference {
+            Some(reference) => f.write_str(reference),
+            None => f.write_str("***"),
+        }
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.reference {
+            Some(reference) => serializer.serialize_str(reference),
+            None => serializer.serialize_str(&self.value),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let config_value = String::deserialize(deserializer)?;
+        Secret::resolve(&config_value).map_err(|e| serde::de::Error::custom(format!("{e:#}")))
+    }
+}
+
+impl SecretProvider for FileProvider {
+    fn get(&self, path: &str) -> anyhow::Result<String> {
+        let path = Path::new("/").join(path);
+        let content = std::fs::read_to_string(&path).with_context(|| format!("could not read {}", path.display()))?;
+        Ok(content.trim_end_matches(['\n', '\r']).to_owned())
+    }
+}
+
+impl SecretProvider for EnvProvider {
+    fn get(&self, path: &str) -> anyhow::Result<String> {
+        std::env::var(path).map_err(|e| anyhow!("environment variable {path}: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{Secret, SecretProvider, register_provider};
+
+    #[test]
+    fn inline() {
+        let secret = Secret::resolve("my password").unwrap();
+        assert_eq!(secret.expose(), "my password");
+        assert_eq!(secret.reference(), None);
+        assert_eq!(format!("{secret:?}"), "Secret(***)");
+        assert_eq!(format!("{secret}"), "***");
+    }
+
+    #[test]
+    fn file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"token123\n").unwrap();
+
+        let reference = format!("secret://file{}", file.path().display());
+        let secret = Secret::resolve(&reference).unwrap();
+        assert_eq!(secret.expose(), "token123");
+        assert_eq!(secret.reference(), Some(reference.as_str()));
+        assert!(!format!("{secret:?}").contains("token123"));
+    }
+
+    #[test]
+    fn env() {
+        let secret = Secret::resolve("secret://env/CARGO_PKG_NAME").unwrap();
+        assert_eq!(secret.expose(), env!("CARGO_PKG_NAME"));
+
+        let err = Secret::resolve("secret://env/ALUMET_TEST_UNDEFINED_SECRET").unwrap_err();
+        assert!(format!("{err:#}").contains("ALUMET_TEST_UNDEFINED_SECRET"));
+    }
+
+    #[test]
+    fn custom_provider() {
+        struct Reversed;
+        impl SecretProvider for Reversed {
+            fn get(&self, path: &str) -> anyhow::Result<String> {
+                Ok(path.chars().rev().collect())
+            }
+        }
+        register_provider("reversed", Reversed);
+        let secret = Secret::resolve("secret://reversed/abc").unwrap();
+        assert_eq!(secret.expose(), "cba");
+
+        assert!(Secret::resolve("secret://unknown/abc").is_err());
+        assert!(Secret::resolve("secret://no-path").is_err());
+    }
+
+    #[test]
+    fn serde() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Config {
+            token: Secret,
+        }
+        let config: Config = toml::from_str("token = 'secret://env/CARGO_PKG_NAME'").unwrap();
+        assert_eq!(config.token.expose(), env!("CARGO_PKG_NAME"));
+
+        // the value is not written back, only the reference
+        let serialized = toml::to_string(&config).unwrap();
+        assert_eq!(serialized, "token = \"secret://env/CARGO_PKG_NAME\"\n");
+    }
+}
@@ -195,6 +195,13 @@ impl ResourceConsumer {
     pub fn normalize(self) -> Result<Self, InvalidConsumerError> {
         match self {
             ResourceConsumer::Custom { kind, id } => match kind.as_ref() {
+                "local_machine" => {
+                    if id.is_empty() {
+                        Ok(ResourceConsumer::LocalMachine)
+                    } else {
+                        Err(InvalidConsumerError::InvalidId(kind))
+                    }
+                }
                 "process" => {
                     let pid = id.parse().map_err(|_| InvalidConsumerError::InvalidId(kind))?;
                     Ok(ResourceConsumer::Process { pid })
@@ -0,0 +1,363 @@
+use std::{sync::mpsc, time::Duration};
+
+use anyhow::{Context, anyhow};
+
+use crate::{
+    measurement::{MeasurementBuffer, MeasurementPoint, MeasurementType},
+    metrics::{TypedMetricId, error::MetricCreationError, registry::MetricRegistry},
+    pipeline::{
+        self, MeasurementPipeline, Output,
+        builder::ShutdownError,
+        control::request,
+        elements::{
+            error::WriteError,
+            output::{OutputContext, builder::OutputBuilder},
+            source::{builder::SourceBuilder, trigger},
+        },
+        naming::{PluginName, SourceName},
+    },
+    plugin::{
+        AlumetPluginStart, AlumetPostStart, AlumetPreStart, Plugin,
+        phases::{PostStartAction, PreStartAction},
+    },
+    test::Recording,
+    units::PrefixedUnit,
+};
+
+// The harness adds a source and an output to the pipeline, under this special plugin name.
+const HARNESS_PLUGIN_NAME: &str = "_test_harness";
+const INPUT_SOURCE_NAME: &str = "input";
+const CAPTURE_OUTPUT_NAME: &str = "capture";
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Minimal measurement pipeline, to test plugins without an agent.
+///
+/// `PipelineHarness` starts the plugins that you give it and runs their sources, transforms and outputs
+/// in a real pipeline, in the current process. Two elements are added to the pipeline:
+/// - an _input_ source, which sends the measurements that you [`inject`](RunningHarness::inject);
+/// - a _capture_ output, which collects every measurement buffer that reaches the outputs.
+///
+/// The managed sources are not triggered by a timer: they only poll when you call [`poll`](RunningHarness::poll).
+/// Every poll or injection produces exactly one buffer, which goes through the transforms and is then
+/// captured. Autonomous sources run normally, their measurements are captured as well.
+///
+/// Unlike [`RuntimeExpectations`](super::RuntimeExpectations), the harness does not disable any element:
+/// you check the measurements as they come out of the pipeline.
+///
+/// # Example
+/// ```no_run
+/// use alumet::pipeline::naming::SourceName;
+/// use alumet::test::PipelineHarness;
+///
+/// # fn f() -> anyhow::Result<()> {
+/// let plugin = todo!();
+///
+/// let mut harness = PipelineHarness::new();
+/// harness.add_plugin(plugin)?;
+/// let mut running = harness.start()?;
+///
+/// // trigger the source and check what comes out of the pipeline
+/// let out = running.poll(SourceName::from_str("my-plugin", "my-source"))?;
+/// let point = out.points("my_metric").next().expect("the source should measure my_metric");
+/// assert!(point.attributes().any(|(key, _)| key == "domain"));
+///
+/// running.stop()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct PipelineHarness {
+    pipeline_builder: pipeline::Builder,
+    plugins: Vec<Box<dyn Plugin>>,
+    pre_start_actions: Vec<(PluginName, Box<dyn PreStartAction>)>,
+    post_start_actions: Vec<(PluginName, Box<dyn PostStartAction>)>,
+    timeout: Duration,
+}
+
+/// A [`PipelineHarness`] whose pipeline is running.
+pub struct RunningHarness {
+    pipeline: MeasurementPipeline,
+    plugins: Vec<Box<dyn Plugin>>,
+    input_tx: tokio::sync::mpsc::Sender<MeasurementBuffer>,
+    capture_rx: mpsc::Receiver<Captured>,
+    timeout: Duration,
+}
+
+/// Measurements captured at the end of the pipeline.
+#[derive(Clone)]
+pub struct Captured {
+    measurements: MeasurementBuffer,
+    metrics: MetricRegistry,
+}
+
+impl Default for PipelineHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PipelineHarness {
+    pub fn new() -> Self {
+        Self {
+            pipeline_builder: pipeline::Builder::new(),
+            plugins: Vec::new(),
+            pre_start_actions: Vec::new(),
+            post_start_actions: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Returns a mutable reference to the maximum duration of each operation on the pipeline.
+    ///
+    /// The default is 5 seconds.
+    pub fn timeout(&mut self) -> &mut Duration {
+        &mut self.timeout
+    }
+
+    /// Returns a mutable reference to the builder of the underlying pipeline.
+    pub fn pipeline(&mut self) -> &mut pipeline::Builder {
+        &mut self.pipeline_builder
+    }
+
+    /// Creates a new metric, for instance to [`inject`](RunningHarness::inject) measurements later.
+    pub fn create_metric<T: MeasurementType>(
+        &mut self,
+        name: impl Into<String>,
+        unit: impl Into<PrefixedUnit>,
+    ) -> Result<TypedMetricId<T>, MetricCreationError> {
+        let mut ctx = self.start_context(PluginName(HARNESS_PLUGIN_NAME.to_owned()));
+        ctx.create_metric(name, unit, "")
+    }
+
+    /// Starts a plugin, i.e. calls its [`start`](Plugin::start) method.
+    ///
+    /// The plugins are started in the order in which they are added.
+    pub fn add_plugin(&mut self, mut plugin: Box<dyn Plugin>) -> anyhow::Result<()> {
+        let name = plugin.name().to_owned();
+        let version = plugin.version().to_owned();
+        let mut ctx = self.start_context(PluginName(name.clone()));
+        plugin
+            .start(&mut ctx)
+            .with_context(|| format!("plugin failed to start: {name} v{version}"))?;
+        self.plugins.push(plugin);
+        Ok(())
+    }
+
+    fn start_context(&mut self, plugin: PluginName) -> AlumetPluginStart<'_> {
+        AlumetPluginStart {
+            current_plugin: plugin,
+            pipeline_builder: &mut self.pipeline_builder,
+            pre_start_actions: &mut self.pre_start_actions,
+            post_start_actions: &mut self.post_start_actions,
+        }
+    }
+
+    /// Builds and starts the pipeline.
+    ///
+    /// The pre-pipeline-start and post-pipeline-start hooks of the plugins are called, like in an agent.
+    pub fn start(self) -> anyhow::Result<RunningHarness> {
+        let Self {
+            mut pipeline_builder,
+            mut plugins,
+            mut pre_start_actions,
+            mut post_start_actions,
+            timeout,
+        } = self;
+
+        // pre-pipeline-start actions
+        for plugin in plugins.iter_mut() {
+            let name = PluginName(plugin.name().to_owned());
+            let mut ctx = AlumetPreStart {
+                current_plugin: name.clone(),
+                pipeline_builder: &mut pipeline_builder,
+            };
+            plugin
+                .pre_pipeline_start(&mut ctx)
+                .with_context(|| format!("plugin pre_pipeline_start failed: {}", name.0))?;
+            for (_, action) in pre_start_actions.extract_if(.., |(p, _)| p == &name) {
+                action(&mut ctx).with_context(|| format!("plugin pre-pipeline-start action failed: {}", name.0))?;
+            }
+        }
+
+        // Only poll the managed sources on demand.
+        pipeline_builder.replace_sources(|name, builder| match builder {
+            SourceBuilder::Managed(builder) => SourceBuilder::Managed(Box::new(move |ctx| {
+                let mut source = builder(ctx)?;
+                source.trigger_spec = trigger::builder::manual() // don't trigger with a timer, only manually
+                    .flush_rounds(1) // flush immediately
+                    .update_rounds(1) // update asap
+                    .build()?;
+                log::trace!("trigger of {name} replaced by: {:?}", source.trigger_spec);
+                Ok(source)
+            })),
+            a @ SourceBuilder::Autonomous(_) => a,
+        });
+
+        // Add the input source.
+        let (input_tx, mut input_rx) = tokio::sync::mpsc::channel::<MeasurementBuffer>(16);
+        let input = SourceBuilder::Autonomous(Box::new(move |_ctx, cancel, tx| {
+            Ok(Box::pin(async move {
+                loop {
+                    tokio::select! {
+                        biased;
+                        _ = cancel.cancelled() => break,
+                        m = input_rx.recv() => match m {
+                            Some(measurements) => tx.send(measurements).await?,
+                            None => break,
+                        }
+                    }
+                }
+                Ok(())
+            }))
+        }));
+        pipeline_builder.add_source_builder(PluginName(HARNESS_PLUGIN_NAME.to_owned()), INPUT_SOURCE_NAME, input)?;
+
+        // Add the capture output.
+        let (capture_tx, capture_rx) = mpsc::channel();
+        let capture = OutputBuilder::Blocking(Box::new(move |_ctx| Ok(Box::new(CaptureOutput(capture_tx)))));
+        pipeline_builder.add_output_builder(
+            PluginName(HARNESS_PLUGIN_NAME.to_owned()),
+            CAPTURE_OUTPUT_NAME,
+            capture,
+        )?;
+
+        let mut pipeline = pipeline_builder.build().context("Pipeline failed to build")?;
+
+        // post-pipeline-start actions
+        for plugin in plugins.iter_mut() {
+            let name = PluginName(plugin.name().to_owned());
+            let mut ctx = AlumetPostStart {
+                current_plugin: name.clone(),
+                pipeline: &mut pipeline,
+            };
+            plugin
+                .post_pipeline_start(&mut ctx)
+                .with_context(|| format!("plugin post_pipeline_start method failed: {}", name.0))?;
+            for (_, action) in post_start_actions.extract_if(.., |(p, _)| p == &name) {
+                action(&mut ctx).with_context(|| format!("plugin post-pipeline-start action failed: {}", name.0))?;
+            }
+        }
+
+        Ok(RunningHarness {
+            pipeline,
+            plugins,
+            input_tx,
+            capture_rx,
+            timeout,
+        })
+    }
+}
+
+impl RunningHarness {
+    /// Returns the underlying pipeline.
+    pub fn pipeline(&self) -> &MeasurementPipeline {
+        &self.pipeline
+    }
+
+    /// Returns a copy of the metrics that are currently registered.
+    pub fn metrics(&self) -> MetricRegistry {
+        self.pipeline.metrics_reader().blocking_read().clone()
+    }
+
+    /// Triggers a managed source and waits for its measurements to reach the end of the pipeline.
+    pub fn poll(&mut self, source: SourceName) -> anyhow::Result<Captured> {
+        let control = self.pipeline.control_handle();
+        let trigger = request::source(source.clone()).trigger_now();
+        self.pipeline
+            .async_runtime()
+            .block_on(control.dispatch(trigger, self.timeout))
+            .with_context(|| format!("failed to trigger {source}"))?;
+        self.next_output()
+    }
+
+    /// Sends measurements through the pipeline and waits for them to reach the end of the pipeline.
+    ///
+    /// The measurements go through all the transforms, and are written to all the outputs.
+    pub fn inject(&mut self, measurements: MeasurementBuffer) -> anyhow::Result<Captured> {
+        self.input_tx
+            .blocking_send(measurements)
+            .map_err(|_| anyhow!("the input source of the harness has stopped"))?;
+        self.next_output()
+    }
+
+    /// Waits for the next measurement buffer that reaches the end of the pipeline.
+    ///
+    /// This is useful to test autonomous sources.
+    pub fn next_output(&mut self) -> anyhow::Result<Captured> {
+        self.capture_rx
+            .recv_timeout(self.timeout)
+            .with_context(|| format!("no measurement captured within {:?}", self.timeout))
+    }
+
+    /// Shuts the pipeline down and stops the plugins.
+    pub fn stop(self) -> anyhow::Result<()> {
+        drop(self.input_tx);
+        self.pipeline.control_handle().shutdown();
+        match self.pipeline.wait_for_shutdown(Some(self.timeout)) {
+            Ok(()) => (),
+            Err(ShutdownError::Pipeline(e)) => return Err(anyhow::Error::new(e).context("error in the pipeline")),
+            Err(ShutdownError::TimeoutExpired) => return Err(anyhow!("pipeline shutdown timeout expired")),
+        }
+        for mut plugin in self.plugins {
+            let name = plugin.name().to_owned();
+            plugin
+                .stop()
+                .with_context(|| format!("error while shutting down plugin {name}"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Captured {
+    /// Returns the captured measurements.
+    pub fn measurements(&self) -> &MeasurementBuffer {
+        &self.measurements
+    }
+
+    /// Returns the metrics that were registered when the measurements were captured.
+    pub fn metrics(&self) -> &MetricRegistry {
+        &self.metrics
+    }
+
+    pub fn len(&self) -> usize {
+        self.measurements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.measurements.is_empty()
+    }
+
+    /// Returns the name of the metric of a measurement point.
+    pub fn metric_name(&self, point: &MeasurementPoint) -> Option<&str> {
+        self.metrics.by_id(&point.metric).map(|m| m.name.as_str())
+    }
+
+    /// Returns a [`Recording`] of the captured points, to use its assertion helpers.
+    pub fn recording(&self) -> Recording {
+        let recording = Recording::new();
+        recording.record(&self.measurements, &self.metrics);
+        recording
+    }
+
+    /// Iterates on the captured points of the metric `name`.
+    pub fn points<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a MeasurementPoint> {
+        self.measurements
+            .iter()
+            .filter(move |p| self.metric_name(p) == Some(name))
+    }
+}
+
+/// Output that sends the measurements to the harness.
+struct CaptureOutput(mpsc::Sender<Captured>);
+
+impl Output for CaptureOutput {
+    fn write(&mut self, measurements: &MeasurementBuffer, ctx: &OutputContext) -> Result<(), WriteError> {
+        let captured = Captured {
+            measurements: measurements.clone(),
+            metrics: ctx.metrics.clone(),
+        };
+        // the receiver is gone if the harness has been dropped
+        let _ = self.0.send(captured);
+        Ok(())
+    }
+}
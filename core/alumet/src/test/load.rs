@@ -0,0 +1,208 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    measurement::{MeasurementAccumulator, MeasurementBuffer, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{
+        Output, Source, Transform,
+        elements::{
+            error::{PollError, TransformError, WriteError},
+            output::OutputContext,
+            source::trigger::TriggerSpec,
+            transform::TransformContext,
+        },
+    },
+    plugin::{AlumetPluginStart, AlumetPostStart, AlumetPreStart, Plugin, PluginMetadata},
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+
+const PLUGIN_NAME: &str = "synthetic-load";
+
+/// A synthetic measurement load: `metrics` × `resources` points, polled `frequency` times per second.
+///
+/// The load is generated by a plugin, which registers:
+/// - a [`SyntheticSource`], which measures every metric on every resource;
+/// - `transforms` [`NoopTransform`]s;
+/// - a [`NullOutput`], which counts the points that reach the end of the pipeline.
+///
+/// This is useful to benchmark the pipeline and to find the maximum load that it can sustain.
+///
+/// # Example
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use alumet::agent::{self, plugin::PluginSet};
+/// use alumet::test::load::SyntheticLoad;
+///
+/// let load = SyntheticLoad {
+///     metrics: 10,
+///     resources: 100,
+///     frequency: 1000.0,
+///     transforms: 1,
+/// };
+/// let (plugin, counter) = load.plugin();
+/// let agent = agent::Builder::new(PluginSet::from(vec![plugin])).build_and_start().unwrap();
+///
+/// // wait for 1000 polls
+/// assert!(counter.wait_for(1000 * load.points_per_poll() as u64, Duration::from_secs(10)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SyntheticLoad {
+    /// Number of metrics.
+    pub metrics: usize,
+    /// Number of resources, measured for each metric.
+    pub resources: usize,
+    /// Poll frequency of the source, in Hertz.
+    pub frequency: f64,
+    /// Number of transforms that the measurements go through.
+    pub transforms: usize,
+}
+
+/// Counts the points written by a [`NullOutput`].
+///
+/// Cloning a `PointCounter` returns a handle to the same counter.
+#[derive(Debug, Clone, Default)]
+pub struct PointCounter(Arc<AtomicU64>);
+
+/// A source that produces a fixed number of points per poll.
+pub struct SyntheticSource {
+    metrics: Vec<TypedMetricId<u64>>,
+    resources: u32,
+    polls: u64,
+}
+
+/// A transform that does nothing.
+pub struct NoopTransform;
+
+/// An output that counts the points and discards them.
+pub struct NullOutput(PointCounter);
+
+struct SyntheticLoadPlugin {
+    load: SyntheticLoad,
+    counter: PointCounter,
+}
+
+impl SyntheticLoad {
+    /// Number of points produced by each poll of the source.
+    pub fn points_per_poll(&self) -> usize {
+        self.metrics * self.resources
+    }
+
+    /// Returns a plugin that generates the load, and a counter of the points that reach its output.
+    pub fn plugin(&self) -> (PluginMetadata, PointCounter) {
+        let counter = PointCounter::default();
+        let plugin = SyntheticLoadPlugin {
+            load: self.clone(),
+            counter: counter.clone(),
+        };
+        let metadata = PluginMetadata {
+            name: PLUGIN_NAME.to_owned(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            init: Box::new(move |_| Ok(Box::new(plugin))),
+            default_config: Box::new(|| Ok(None)),
+            dependencies: Vec::new(),
+            config_schema: Box::new(|| Ok(None)),
+            config_migrations: Vec::new(),
+        };
+        (metadata, counter)
+    }
+}
+
+impl PointCounter {
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Waits until the counter reaches `n`, or until the timeout expires.
+    ///
+    /// Returns `true` if the counter has reached `n`.
+    pub fn wait_for(&self, n: u64, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.get() < n {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_micros(50));
+        }
+        true
+    }
+}
+
+impl Plugin for SyntheticLoadPlugin {
+    fn name(&self) -> &str {
+        PLUGIN_NAME
+    }
+
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        let metrics = (0..self.load.metrics)
+            .map(|i| alumet.create_metric::<u64>(format!("synthetic_{i}"), Unit::Unity, "synthetic metric"))
+            .collect::<Result<Vec<_>, _>>()?;
+        let source = SyntheticSource {
+            metrics,
+            resources: u32::try_from(self.load.resources)?,
+            polls: 0,
+        };
+        let poll_interval = Duration::from_secs_f64(1.0 / self.load.frequency);
+        alumet.add_source("source", Box::new(source), TriggerSpec::at_interval(poll_interval))?;
+        for i in 0..self.load.transforms {
+            alumet.add_transform(&format!("noop_{i}"), Box::new(NoopTransform))?;
+        }
+        alumet.add_blocking_output("null", Box::new(NullOutput(self.counter.clone())))?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn pre_pipeline_start(&mut self, _alumet: &mut AlumetPreStart) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn post_pipeline_start(&mut self, _alumet: &mut AlumetPostStart) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl Source for SyntheticSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, t: Timestamp) -> Result<(), PollError> {
+        self.polls += 1;
+        for metric in &self.metrics {
+            for id in 0..self.resources {
+                measurements.push(MeasurementPoint::new(
+                    t,
+                    *metric,
+                    Resource::CpuCore { id },
+                    ResourceConsumer::LocalMachine,
+                    self.polls,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Transform for NoopTransform {
+    fn apply(&mut self, _measurements: &mut MeasurementBuffer, _ctx: &TransformContext) -> Result<(), TransformError> {
+        Ok(())
+    }
+}
+
+impl Output for NullOutput {
+    fn write(&mut self, measurements: &MeasurementBuffer, _ctx: &OutputContext) -> Result<(), WriteError> {
+        (self.0).0.fetch_add(measurements.len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+}
@@ -28,11 +28,22 @@
 //! alumet = {version = "version", features = ["test"]}
 //! ```
 
+/// Minimal measurement pipeline for the integration tests of plugins.
+pub mod harness;
+
+/// Synthetic measurement load, for benchmarks.
+pub mod load;
+
+/// Outputs that record the measurements, and assertions on the recordings.
+pub mod recording;
+
 /// Tests performed while the measurement pipeline is running.
 pub mod runtime;
 
 /// Tests performed at startup.
 pub mod startup;
 
+pub use harness::PipelineHarness;
+pub use recording::Recording;
 pub use runtime::RuntimeExpectations;
 pub use startup::StartupExpectations;
@@ -0,0 +1,221 @@
+use std::{
+    fmt, fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+    metrics::registry::MetricRegistry,
+    pipeline::{
+        Output,
+        elements::{error::WriteError, output::OutputContext},
+    },
+};
+
+/// Set this environment variable to create or update the snapshot files.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "ALUMET_UPDATE_SNAPSHOTS";
+
+/// Measurements recorded by a [`RecordingOutput`], with assertion helpers.
+///
+/// Cloning a `Recording` returns a handle to the same recording.
+///
+/// # Example
+/// ```no_run
+/// use alumet::test::recording::Recording;
+///
+/// let recording = Recording::new();
+/// // add `recording.output()` to the pipeline, e.g. with `alumet.add_blocking_output`
+/// // run the pipeline...
+///
+/// recording.assert_count("cpu_energy", 2);
+/// recording.assert_values_near("cpu_energy", 12.5, 0.1);
+/// recording.assert_snapshot("tests/snapshots/cpu_energy.txt");
+/// ```
+#[derive(Clone, Default)]
+pub struct Recording {
+    points: Arc<Mutex<Vec<RecordedPoint>>>,
+}
+
+/// An output that records every measurement that it receives.
+pub struct RecordingOutput {
+    recording: Recording,
+}
+
+/// A measurement point, with its metric and attributes in a form that is easy to compare.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedPoint {
+    /// Name of the metric.
+    pub metric: String,
+    pub timestamp: Timestamp,
+    pub value: WrappedMeasurementValue,
+    /// The resource, as `kind` or `kind:id`.
+    pub resource: String,
+    /// The consumer, as `kind` or `kind:id`.
+    pub consumer: String,
+    /// The attributes, sorted by key.
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns an output that records the measurements into this recording.
+    pub fn output(&self) -> RecordingOutput {
+        RecordingOutput {
+            recording: self.clone(),
+        }
+    }
+
+    /// Records measurements.
+    pub fn record(&self, measurements: &MeasurementBuffer, metrics: &MetricRegistry) {
+        let new_points = measurements.iter().map(|p| RecordedPoint::new(p, metrics));
+        self.points.lock().unwrap().extend(new_points);
+    }
+
+    /// Returns all the points recorded so far.
+    pub fn points(&self) -> Vec<RecordedPoint> {
+        self.points.lock().unwrap().clone()
+    }
+
+    /// Returns the points of the metric `name`.
+    pub fn points_of(&self, name: &str) -> Vec<RecordedPoint> {
+        let points = self.points.lock().unwrap();
+        points.iter().filter(|p| p.metric == name).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.lock().unwrap().is_empty()
+    }
+
+    /// Forgets the points recorded so far.
+    pub fn clear(&self) {
+        self.points.lock().unwrap().clear();
+    }
+
+    /// Returns a normalized textual representation of the recording.
+    ///
+    /// There is one line per point, see the [`Display`](fmt::Display) implementation of [`RecordedPoint`].
+    /// The lines are sorted, so that the snapshot does not depend on the order in which the points arrive.
+    /// The timestamps are not included.
+    pub fn snapshot(&self) -> String {
+        let mut lines: Vec<String> = self.points.lock().unwrap().iter().map(|p| p.to_string()).collect();
+        lines.sort();
+        lines.into_iter().map(|l| l + "\n").collect()
+    }
+
+    /// Checks that the [`snapshot`](Self::snapshot) of the recording is equal to the content of a file.
+    ///
+    /// If the environment variable `ALUMET_UPDATE_SNAPSHOTS` is set, writes the file instead.
+    ///
+    /// # Panics
+    /// Panics if the snapshot is different from the content of the file, or if the file does not exist.
+    #[track_caller]
+    pub fn assert_snapshot(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let actual = self.snapshot();
+        if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).unwrap();
+            }
+            fs::write(path, actual).unwrap_or_else(|e| panic!("failed to write the snapshot {}: {e}", path.display()));
+            return;
+        }
+        let expected = fs::read_to_string(path).unwrap_or_else(|e| {
+            panic!(
+                "failed to read the snapshot {}: {e}\nRun the test with {UPDATE_SNAPSHOTS_ENV}=1 to create it.",
+                path.display()
+            )
+        });
+        assert!(
+            actual == expected,
+            "the recording does not match the snapshot {}\n--- expected\n{expected}--- actual\n{actual}Run the test with {UPDATE_SNAPSHOTS_ENV}=1 to update it.",
+            path.display()
+        );
+    }
+
+    /// Checks that the recording contains exactly `expected` points of the metric `name`.
+    #[track_caller]
+    pub fn assert_count(&self, name: &str, expected: usize) {
+        let actual = self.points_of(name).len();
+        assert_eq!(actual, expected, "wrong number of points for metric {name}");
+    }
+
+    /// Checks that there is at least one point of the metric `name`, and that all of them are equal to
+    /// `expected`, within `tolerance`.
+    #[track_caller]
+    pub fn assert_values_near(&self, name: &str, expected: f64, tolerance: f64) {
+        let points = self.points_of(name);
+        assert!(!points.is_empty(), "no point for metric {name}");
+        for p in points {
+            let value = p.value.as_f64();
+            assert!(
+                (value - expected).abs() <= tolerance,
+                "value of {p} is not within {expected} ± {tolerance}"
+            );
+        }
+    }
+}
+
+impl Output for RecordingOutput {
+    fn write(&mut self, measurements: &MeasurementBuffer, ctx: &OutputContext) -> Result<(), WriteError> {
+        self.recording.record(measurements, ctx.metrics);
+        Ok(())
+    }
+}
+
+impl RecordedPoint {
+    pub fn new(point: &MeasurementPoint, metrics: &MetricRegistry) -> Self {
+        let metric = match metrics.by_id(&point.metric) {
+            Some(m) => m.name.clone(),
+            None => format!("unknown({})", point.metric.as_u64()),
+        };
+        let mut attributes: Vec<(String, String)> =
+            point.attributes().map(|(k, v)| (k.to_owned(), v.to_string())).collect();
+        attributes.sort();
+        Self {
+            metric,
+            timestamp: point.timestamp,
+            value: point.value.clone(),
+            resource: kind_and_id(point.resource.kind(), point.resource.id_string()),
+            consumer: kind_and_id(point.consumer.kind(), point.consumer.id_string()),
+            attributes,
+        }
+    }
+
+    /// Returns the value of an attribute.
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+}
+
+fn kind_and_id(kind: &str, id: Option<String>) -> String {
+    match id {
+        Some(id) => format!("{kind}:{id}"),
+        None => kind.to_owned(),
+    }
+}
+
+/// Formats the point without its timestamp, as `metric{key=value,...} resource consumer = value`.
+impl fmt::Display for RecordedPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{{", self.metric)?;
+        for (i, (k, v)) in self.attributes.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{k}={v}")?;
+        }
+        write!(f, "}} {} {} = ", self.resource, self.consumer)?;
+        match &self.value {
+            WrappedMeasurementValue::F64(v) => write!(f, "{v:?}"),
+            WrappedMeasurementValue::U64(v) => write!(f, "{v}"),
+        }
+    }
+}
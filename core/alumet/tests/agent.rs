@@ -1,17 +1,22 @@
 use std::{
-    sync::{Arc, atomic::Ordering},
+    sync::{
+        Arc,
+        atomic::{AtomicI64, Ordering},
+    },
     time::Duration,
 };
 
 use alumet::{
     agent::{
         self,
-        config::{AutoDefaultConfigProvider, DefaultConfigProvider},
-        plugin::PluginSet,
+        builder::CheckError,
+        config::{AutoDefaultConfigProvider, DefaultConfigProvider, migrate_config_file},
+        plugin::{DependencyError, PluginFilter, PluginSet, UnknownPluginInConfigPolicy},
     },
     plugin::{
-        AlumetPluginStart, ConfigTable, PluginMetadata,
-        rust::{AlumetPlugin, serialize_config},
+        AlumetPluginStart, AlumetPostStart, ConfigTable, PluginDependency, PluginMetadata,
+        migration::{self, ConfigMigration},
+        rust::{AlumetPlugin, ReconfigureUnsupported, serialize_config},
     },
     static_plugins,
 };
@@ -85,12 +90,18 @@ fn test_plugin_lifecycle() {
             version: "0.0.1".to_owned(),
             init: Box::new(move |_| Ok(TestPlugin::init("plugin1", 98, state1_meta, c1_meta))),
             default_config: Box::new(|| Ok(None)),
+            dependencies: Vec::new(),
+            config_schema: Box::new(|| Ok(None)),
+            config_migrations: Vec::new(),
         },
         PluginMetadata {
             name: "plugin2".to_owned(),
             version: "0.0.1".to_owned(),
             init: Box::new(move |_| Ok(TestPlugin::init("plugin2", 1000, state2_meta, c2_meta))),
             default_config: Box::new(|| Ok(None)),
+            dependencies: Vec::new(),
+            config_schema: Box::new(|| Ok(None)),
+            config_migrations: Vec::new(),
         },
     ];
     let plugins = PluginSet::from(plugins);
@@ -205,6 +216,276 @@ fn test_plugin_lifecycle() {
     assert_eq!(transform2_out, output2_written);
 }
 
+#[test]
+fn reconfigure_plugin() {
+    static VALUE: AtomicI64 = AtomicI64::new(0);
+
+    struct ReconfigurablePlugin;
+    impl AlumetPlugin for ReconfigurablePlugin {
+        fn name() -> &'static str {
+            "reconfigurable"
+        }
+
+        fn version() -> &'static str {
+            "0.0.1"
+        }
+
+        fn init(_config: ConfigTable) -> anyhow::Result<Box<Self>> {
+            Ok(Box::new(Self))
+        }
+
+        fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+            Ok(None)
+        }
+
+        fn start(&mut self, _alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn stop(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn reconfigure(&mut self, config: ConfigTable, _alumet: &mut AlumetPostStart) -> anyhow::Result<()> {
+            let value = config.0.get("value").and_then(|v| v.as_integer()).unwrap();
+            VALUE.store(value, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    struct StaticPlugin;
+    impl AlumetPlugin for StaticPlugin {
+        fn name() -> &'static str {
+            "static"
+        }
+
+        fn version() -> &'static str {
+            "0.0.1"
+        }
+
+        fn init(_config: ConfigTable) -> anyhow::Result<Box<Self>> {
+            Ok(Box::new(Self))
+        }
+
+        fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+            Ok(None)
+        }
+
+        fn start(&mut self, _alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn stop(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    let plugins = PluginSet::from(static_plugins![ReconfigurablePlugin, StaticPlugin]);
+    let mut agent = agent::Builder::new(plugins).build_and_start().unwrap();
+
+    // the plugin supports reconfiguration
+    let config = ConfigTable(toml::Table::from_iter([(
+        String::from("value"),
+        toml::Value::Integer(42),
+    )]));
+    agent.reconfigure_plugin("reconfigurable", config).unwrap();
+    assert_eq!(VALUE.load(Ordering::Relaxed), 42);
+
+    // the plugin does not support reconfiguration
+    let err = agent.reconfigure_plugin("static", ConfigTable::default()).unwrap_err();
+    assert!(err.root_cause().is::<ReconfigureUnsupported>());
+
+    // the plugin does not exist
+    assert!(agent.reconfigure_plugin("unknown", ConfigTable::default()).is_err());
+
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn check_plugins_config() {
+    struct StrictPlugin;
+    impl AlumetPlugin for StrictPlugin {
+        fn name() -> &'static str {
+            "strict"
+        }
+
+        fn version() -> &'static str {
+            "0.0.1"
+        }
+
+        fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+            match config.0.get("mode").and_then(|v| v.as_str()) {
+                Some("fast" | "slow") => Ok(Box::new(Self)),
+                _ => Err(anyhow::anyhow!("invalid mode")),
+            }
+        }
+
+        fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+            Ok(Some(ConfigTable(toml! { mode = "fast" })))
+        }
+
+        fn config_schema() -> anyhow::Result<Option<serde_json::Value>> {
+            // opt into the validation with the inferred schema
+            let default_config = Self::default_config()?.unwrap();
+            Ok(Some(alumet::plugin::schema::infer_schema(&default_config)))
+        }
+
+        fn start(&mut self, _alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+            unreachable!("the check should not start the plugins")
+        }
+
+        fn stop(&mut self) -> anyhow::Result<()> {
+            unreachable!("the check should not start the plugins")
+        }
+    }
+
+    // valid config
+    let mut plugins = PluginSet::from(static_plugins![StrictPlugin, MyPlugin]);
+    plugins.set_plugin_enabled("name", false);
+    let mut config = toml! {
+        [plugins.strict]
+        mode = "slow"
+    };
+    plugins
+        .extract_config(&mut config, false, UnknownPluginInConfigPolicy::Error)
+        .unwrap();
+    agent::Builder::new(plugins).check().unwrap();
+
+    // type error detected by the schema
+    let mut plugins = PluginSet::from(static_plugins![StrictPlugin]);
+    let mut config = toml! {
+        [plugins.strict]
+        mode = 123
+    };
+    plugins
+        .extract_config(&mut config, false, UnknownPluginInConfigPolicy::Error)
+        .unwrap();
+    let err = agent::Builder::new(plugins).check().unwrap_err();
+    match &err {
+        CheckError::InvalidConfig(errors) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].0, "strict");
+        }
+        _ => panic!("unexpected error {err}"),
+    }
+    assert!(err.to_string().contains("plugins.strict.mode"), "{err}");
+
+    // error detected by the plugin
+    let mut plugins = PluginSet::from(static_plugins![StrictPlugin]);
+    let mut config = toml! {
+        [plugins.strict]
+        mode = "medium"
+    };
+    plugins
+        .extract_config(&mut config, false, UnknownPluginInConfigPolicy::Error)
+        .unwrap();
+    let err = agent::Builder::new(plugins).check().unwrap_err();
+    assert!(err.to_string().contains("invalid mode"), "{err}");
+
+    // missing dependency
+    let mut metadata = PluginMetadata::from_static::<StrictPlugin>();
+    metadata.dependencies = vec![PluginDependency::required("missing")];
+    let plugins = PluginSet::from(vec![metadata]);
+    let err = agent::Builder::new(plugins).check().unwrap_err();
+    assert!(
+        matches!(&err, CheckError::Dependencies(DependencyError::Unknown { dependency, .. }) if dependency == "missing"),
+        "{err}"
+    );
+}
+
+#[test]
+fn config_migration() {
+    struct MigratedPlugin;
+    impl AlumetPlugin for MigratedPlugin {
+        fn name() -> &'static str {
+            "migrated"
+        }
+
+        fn version() -> &'static str {
+            "0.2.0"
+        }
+
+        fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+            // the config should always be in the latest format, without the version
+            assert_eq!(config.0, toml! { poll_interval = "1s" });
+            Ok(Box::new(Self))
+        }
+
+        fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+            Ok(Some(ConfigTable(toml! { poll_interval = "1s" })))
+        }
+
+        fn config_migrations() -> Vec<ConfigMigration> {
+            vec![ConfigMigration::new(2, |config| {
+                migration::rename_key(config, "interval", "poll_interval")
+            })]
+        }
+
+        fn start(&mut self, _alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn stop(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    // the default config contains the version
+    let plugins = PluginSet::from(static_plugins![MigratedPlugin]);
+    let config = AutoDefaultConfigProvider::new(&plugins, toml::Table::new)
+        .default_config()
+        .unwrap();
+    assert_eq!(
+        config,
+        toml! {
+            [plugins.migrated]
+            poll_interval = "1s"
+            config_version = 2
+        }
+    );
+
+    // old configs are migrated on startup
+    for old_config in [toml! { interval = "1s" }, toml! { interval = "1s" config_version = 1 }] {
+        let mut plugins = PluginSet::from(static_plugins![MigratedPlugin]);
+        let mut config = toml::Table::from_iter([(
+            String::from("plugins"),
+            toml::Value::Table(toml::Table::from_iter([(
+                String::from("migrated"),
+                toml::Value::Table(old_config),
+            )])),
+        )]);
+        plugins
+            .extract_config(&mut config, false, UnknownPluginInConfigPolicy::Error)
+            .unwrap();
+        agent::Builder::new(plugins).check().unwrap();
+    }
+
+    // the config file can be rewritten
+    let dir = std::env::temp_dir().join("alumet-test-config-migration");
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("config.toml");
+    std::fs::write(&file, "[plugins.migrated]\ninterval = '1s'\n").unwrap();
+
+    let plugins = PluginSet::from(static_plugins![MigratedPlugin]);
+    let migrated = migrate_config_file(&file, plugins.metadata(PluginFilter::Any)).unwrap();
+    assert_eq!(migrated, vec![(String::from("migrated"), 1, 2)]);
+    let new_content: toml::Table = toml::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+    assert_eq!(
+        new_content,
+        toml! {
+            [plugins.migrated]
+            poll_interval = "1s"
+            config_version = 2
+        }
+    );
+    assert!(dir.join("config.toml.bak").exists());
+
+    // nothing to do the second time
+    let migrated = migrate_config_file(&file, plugins.metadata(PluginFilter::Any)).unwrap();
+    assert!(migrated.is_empty());
+}
+
 /// Sorts a vector of strings and returns it.
 fn sorted<A: AsRef<str> + Ord>(mut strings: Vec<A>) -> Vec<A> {
     strings.sort();
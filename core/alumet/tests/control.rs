@@ -2,6 +2,7 @@ use std::{collections::HashSet, time::Duration};
 
 use alumet::{
     agent::{self, plugin::PluginSet},
+    pipeline,
     pipeline::{
         Output, Source, Transform,
         control::{
@@ -11,13 +12,16 @@ use alumet::{
         elements::source::trigger::TriggerSpec,
         naming::{ElementKind, ElementName, PluginName},
     },
-    plugin::rust::AlumetPlugin,
+    plugin::{health::HealthStatus, rust::AlumetPlugin},
     static_plugins,
 };
 use anyhow::anyhow;
 
 const TIMEOUT: Duration = Duration::from_secs(1);
 
+/// Number of measurements received by the async output of [`MeasuringPlugin`].
+static ASYNC_OUTPUT_COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
 #[test]
 fn create_source() {
     let no_plugins = PluginSet::new();
@@ -219,6 +223,252 @@ fn list_filter() {
     );
 }
 
+#[test]
+fn plugin_health() {
+    let plugins = PluginSet::from(static_plugins![TestPlugin]);
+    let mut pipeline = pipeline::Builder::new();
+    *pipeline.health_metrics_interval() = Some(Duration::from_secs(1));
+
+    let agent = agent::Builder::from_pipeline(plugins, pipeline)
+        .build_and_start()
+        .unwrap();
+    let handle = agent.pipeline.control_handle();
+    let rt = current_thread_runtime();
+
+    // the report made by the plugin on startup should be available
+    let health = rt
+        .block_on(handle.send_wait(request::plugin_health(), TIMEOUT))
+        .expect("health request failed");
+    assert_eq!(health.len(), 1);
+    let (plugin, report) = &health[0];
+    assert_eq!(plugin, &PluginName(String::from("plugin")));
+    assert_eq!(report.status, HealthStatus::Degraded);
+    assert_eq!(report.message.as_deref(), Some("test report"));
+
+    // the health source should have been added
+    let list = rt
+        .block_on(handle.send_wait(
+            request::list_elements(ElementListFilter::kind(ElementKind::Source).plugin("alumet")),
+            TIMEOUT,
+        ))
+        .unwrap();
+    assert_eq!(
+        list,
+        vec![ElementName::from_str(ElementKind::Source, "alumet", "health")]
+    );
+}
+
+#[test]
+fn element_failures() {
+    let no_plugins = PluginSet::new();
+    let agent = agent::Builder::new(no_plugins).build_and_start().unwrap();
+    let handle = agent.pipeline.control_handle();
+    let handle = handle.with_plugin(PluginName(String::from("test")));
+    let rt = current_thread_runtime();
+
+    // no error yet
+    let failures = rt
+        .block_on(handle.send_wait(request::element_failures(), TIMEOUT))
+        .expect("failures request failed");
+    assert!(failures.is_empty());
+
+    // create a source that fails on every poll
+    let trigger = TriggerSpec::at_interval(Duration::from_millis(10));
+    let request = request::create_one().add_source("failing_source", Box::new(FailingSource), trigger);
+    rt.block_on(handle.send_wait(request, TIMEOUT))
+        .expect("creation request failed");
+    std::thread::sleep(Duration::from_millis(100));
+
+    let failures = rt
+        .block_on(handle.send_wait(request::element_failures(), TIMEOUT))
+        .expect("failures request failed");
+    assert_eq!(failures.len(), 1);
+    let (name, count) = &failures[0];
+    assert_eq!(
+        name,
+        &ElementName::from_str(ElementKind::Source, "test", "failing_source")
+    );
+    assert!(count.recoverable > 0);
+    assert_eq!(count.fatal, 0);
+}
+
+#[test]
+fn plugin_disable_enable() {
+    use alumet::pipeline::elements::output::builder::OutputBuilder;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let plugins = PluginSet::from(static_plugins![MeasuringPlugin]);
+
+    // count the measurements with an output that does not belong to the plugin
+    let mut pipeline = pipeline::Builder::new();
+    let output = CountingOutput(Default::default());
+    let count = output.0.clone();
+    pipeline
+        .add_output_builder(
+            PluginName(String::from("test")),
+            "count",
+            OutputBuilder::Blocking(Box::new(move |_| Ok(Box::new(output)))),
+        )
+        .unwrap();
+
+    let agent = agent::Builder::from_pipeline(plugins, pipeline)
+        .build_and_start()
+        .unwrap();
+    let handle = agent.pipeline.control_handle();
+    let rt = current_thread_runtime();
+
+    // the plugin requests are handled by the agent while it waits for the shutdown
+    let agent_thread = std::thread::spawn(move || agent.wait_for_shutdown(TIMEOUT));
+    let list_plugin_elements = || {
+        let filter = ElementListFilter::kind_any().plugin("measuring");
+        let list = rt
+            .block_on(handle.send_wait(request::list_elements(filter), TIMEOUT))
+            .unwrap();
+        HashSet::<ElementName>::from_iter(list)
+    };
+    let wait_for_measurements = |count: &AtomicUsize, previous_count: usize| {
+        let t0 = std::time::Instant::now();
+        while count.load(Ordering::Relaxed) <= previous_count {
+            assert!(t0.elapsed() < TIMEOUT, "the output should receive new measurements");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    };
+    let all_elements = HashSet::<ElementName>::from_iter(vec![
+        ElementName::from_str(ElementKind::Source, "measuring", "counter"),
+        ElementName::from_str(ElementKind::Source, "measuring", "ticker"),
+        ElementName::from_str(ElementKind::Transform, "measuring", "dummy_tr"),
+        ElementName::from_str(ElementKind::Output, "measuring", "dummy_out"),
+        ElementName::from_str(ElementKind::Output, "measuring", "async_out"),
+    ]);
+    wait_for_measurements(&count, 0);
+    wait_for_measurements(&ASYNC_OUTPUT_COUNT, 0);
+
+    rt.block_on(handle.send_wait(request::plugin("measuring").disable(), TIMEOUT))
+        .expect("disable request failed");
+
+    // the sources and outputs have been destroyed, the transform cannot be and is only disabled
+    assert_eq!(
+        list_plugin_elements(),
+        HashSet::<ElementName>::from_iter(vec![ElementName::from_str(
+            ElementKind::Transform,
+            "measuring",
+            "dummy_tr"
+        )])
+    );
+
+    // no measurement reaches the outputs while the plugin is disabled
+    let count_disabled = count.load(Ordering::Relaxed);
+    let async_count_disabled = ASYNC_OUTPUT_COUNT.load(Ordering::Relaxed);
+    std::thread::sleep(Duration::from_millis(100));
+    assert_eq!(count.load(Ordering::Relaxed), count_disabled);
+    assert_eq!(ASYNC_OUTPUT_COUNT.load(Ordering::Relaxed), async_count_disabled);
+
+    // disabling the plugin again does nothing
+    rt.block_on(handle.send_wait(request::plugin("measuring").disable(), TIMEOUT))
+        .expect("disable request failed");
+
+    rt.block_on(handle.send_wait(request::plugin("measuring").enable(), TIMEOUT))
+        .expect("enable request failed");
+
+    // the elements have been rebuilt by the plugin, and the measurements resume
+    assert_eq!(list_plugin_elements(), all_elements);
+    wait_for_measurements(&count, count_disabled);
+    wait_for_measurements(&ASYNC_OUTPUT_COUNT, async_count_disabled);
+
+    // a plugin that does not exist cannot be disabled
+    let res = rt.block_on(handle.send_wait(request::plugin("unknown").disable(), TIMEOUT));
+    assert!(matches!(res, Err(SendWaitError::Operation(_))));
+
+    handle.shutdown();
+    agent_thread
+        .join()
+        .unwrap()
+        .expect("the agent should stop without error");
+}
+
+#[test]
+fn plugin_enable_with_new_transform() {
+    let plugins = PluginSet::from(static_plugins![GrowingPlugin]);
+    let agent = agent::Builder::new(plugins).build_and_start().unwrap();
+    let handle = agent.pipeline.control_handle();
+    let rt = current_thread_runtime();
+    let agent_thread = std::thread::spawn(move || agent.wait_for_shutdown(TIMEOUT));
+
+    rt.block_on(handle.send_wait(request::plugin("growing").disable(), TIMEOUT))
+        .expect("disable request failed");
+
+    // the new transform cannot be added to the running pipeline: the plugin stays disabled
+    let res = rt.block_on(handle.send_wait(request::plugin("growing").enable(), TIMEOUT));
+    match res {
+        Err(SendWaitError::Operation(e)) => {
+            assert!(format!("{e:?}").contains("transform growing/late_tr"), "{e:?}")
+        }
+        res => panic!("unexpected result {res:?}"),
+    }
+    let filter = ElementListFilter::kind_any().plugin("growing");
+    let list = rt
+        .block_on(handle.send_wait(request::list_elements(filter), TIMEOUT))
+        .unwrap();
+    assert_eq!(
+        list,
+        vec![ElementName::from_str(ElementKind::Transform, "growing", "first_tr")]
+    );
+
+    handle.shutdown();
+    agent_thread
+        .join()
+        .unwrap()
+        .expect("the agent should stop without error");
+}
+
+#[test]
+fn shutdown_when_sources_finish() {
+    use alumet::measurement::{MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue};
+    use alumet::metrics::RawMetricId;
+    use alumet::pipeline::elements::{output::builder::OutputBuilder, source::builder::SourceBuilder};
+    use alumet::resources::{Resource, ResourceConsumer};
+
+    let mut pipeline = pipeline::Builder::new();
+    *pipeline.shutdown_when_sources_finish() = true;
+
+    // a source that sends 3 measurements, then stops
+    let source = SourceBuilder::Autonomous(Box::new(|_ctx, _cancel_token, tx| {
+        Ok(Box::pin(async move {
+            for i in 0..3 {
+                let point = MeasurementPoint::new_untyped(
+                    Timestamp::now(),
+                    RawMetricId::from_u64(0),
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    WrappedMeasurementValue::U64(i),
+                );
+                tx.send(MeasurementBuffer::from(vec![point])).await?;
+            }
+            Ok(())
+        }))
+    }));
+    pipeline
+        .add_source_builder(PluginName(String::from("test")), "bounded", source)
+        .unwrap();
+
+    let output = CountingOutput(Default::default());
+    let count = output.0.clone();
+    pipeline
+        .add_output_builder(
+            PluginName(String::from("test")),
+            "count",
+            OutputBuilder::Blocking(Box::new(move |_| Ok(Box::new(output)))),
+        )
+        .unwrap();
+
+    // the pipeline should stop by itself, after having written every measurement
+    let agent = agent::Builder::from_pipeline(PluginSet::new(), pipeline)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).expect("the pipeline should stop");
+    assert_eq!(count.load(std::sync::atomic::Ordering::Relaxed), 3);
+}
+
 fn current_thread_runtime() -> tokio::runtime::Runtime {
     tokio::runtime::Builder::new_current_thread()
         .enable_all()
@@ -227,9 +477,17 @@ fn current_thread_runtime() -> tokio::runtime::Runtime {
 }
 
 struct DummySource;
+struct FailingSource;
 struct DummyTransform;
 struct DummyOutput;
+struct CountingOutput(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+struct CounterSource(alumet::metrics::TypedMetricId<u64>);
 struct TestPlugin;
+struct MeasuringPlugin;
+/// A plugin that registers a new transform each time it starts.
+struct GrowingPlugin {
+    starts: usize,
+}
 
 impl Source for DummySource {
     fn poll(
@@ -241,6 +499,38 @@ impl Source for DummySource {
     }
 }
 
+impl Source for FailingSource {
+    fn poll(
+        &mut self,
+        _measurements: &mut alumet::measurement::MeasurementAccumulator,
+        _timestamp: alumet::measurement::Timestamp,
+    ) -> Result<(), alumet::pipeline::elements::error::PollError> {
+        Err(alumet::pipeline::elements::error::PollError::CanRetry(anyhow!(
+            "sensor not available"
+        )))
+    }
+}
+
+impl Source for CounterSource {
+    fn poll(
+        &mut self,
+        measurements: &mut alumet::measurement::MeasurementAccumulator,
+        timestamp: alumet::measurement::Timestamp,
+    ) -> Result<(), alumet::pipeline::elements::error::PollError> {
+        use alumet::resources::{Resource, ResourceConsumer};
+
+        let point = alumet::measurement::MeasurementPoint::new(
+            timestamp,
+            self.0,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            1,
+        );
+        measurements.push(point);
+        Ok(())
+    }
+}
+
 impl Transform for DummyTransform {
     fn apply(
         &mut self,
@@ -261,6 +551,18 @@ impl Output for DummyOutput {
     }
 }
 
+impl Output for CountingOutput {
+    fn write(
+        &mut self,
+        measurements: &alumet::measurement::MeasurementBuffer,
+        _ctx: &alumet::pipeline::elements::output::OutputContext,
+    ) -> Result<(), alumet::pipeline::elements::error::WriteError> {
+        self.0
+            .fetch_add(measurements.len(), std::sync::atomic::Ordering::Relaxed);
+        Ok(())
+    }
+}
+
 impl AlumetPlugin for TestPlugin {
     fn name() -> &'static str {
         "plugin"
@@ -286,6 +588,112 @@ impl AlumetPlugin for TestPlugin {
         )?;
         alumet.add_transform("dummy_tr", Box::new(DummyTransform))?;
         alumet.add_blocking_output("dummy_out", Box::new(DummyOutput))?;
+        alumet.health_reporter().report(HealthStatus::Degraded, "test report");
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl AlumetPlugin for GrowingPlugin {
+    fn name() -> &'static str {
+        "growing"
+    }
+
+    fn version() -> &'static str {
+        "0.1.0"
+    }
+
+    fn init(_config: alumet::plugin::ConfigTable) -> anyhow::Result<Box<Self>> {
+        Ok(Box::new(Self { starts: 0 }))
+    }
+
+    fn default_config() -> anyhow::Result<Option<alumet::plugin::ConfigTable>> {
+        Ok(None)
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        self.starts += 1;
+        alumet.add_source(
+            "dummy",
+            Box::new(DummySource),
+            TriggerSpec::at_interval(Duration::from_millis(10)),
+        )?;
+        alumet.add_transform("first_tr", Box::new(DummyTransform))?;
+        if self.starts > 1 {
+            alumet.add_transform("late_tr", Box::new(DummyTransform))?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl AlumetPlugin for MeasuringPlugin {
+    fn name() -> &'static str {
+        "measuring"
+    }
+
+    fn version() -> &'static str {
+        "0.1.0"
+    }
+
+    fn init(_config: alumet::plugin::ConfigTable) -> anyhow::Result<Box<Self>> {
+        Ok(Box::new(Self))
+    }
+
+    fn default_config() -> anyhow::Result<Option<alumet::plugin::ConfigTable>> {
+        Ok(None)
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let metric = alumet.create_metric::<u64>("counter", alumet::units::Unit::Unity, "a counter")?;
+        alumet.add_source(
+            "counter",
+            Box::new(CounterSource(metric)),
+            TriggerSpec::at_interval(Duration::from_millis(10)),
+        )?;
+        alumet.add_autonomous_source_builder("ticker", move |_ctx, cancel_token, tx| {
+            Ok(Box::pin(async move {
+                use alumet::measurement::{MeasurementBuffer, MeasurementPoint, Timestamp};
+                use alumet::resources::{Resource, ResourceConsumer};
+
+                let mut interval = tokio::time::interval(Duration::from_millis(10));
+                loop {
+                    tokio::select! {
+                        _ = cancel_token.cancelled() => return Ok(()),
+                        _ = interval.tick() => {
+                            let point = MeasurementPoint::new(
+                                Timestamp::now(),
+                                metric,
+                                Resource::LocalMachine,
+                                ResourceConsumer::LocalMachine,
+                                1,
+                            );
+                            tx.send(MeasurementBuffer::from(vec![point])).await?;
+                        }
+                    }
+                }
+            }))
+        })?;
+        alumet.add_transform("dummy_tr", Box::new(DummyTransform))?;
+        alumet.add_blocking_output("dummy_out", Box::new(DummyOutput))?;
+        alumet.add_async_output_builder("async_out", |_ctx, mut stream| {
+            Ok(Box::pin(async move {
+                use futures::StreamExt;
+
+                while let Some(res) = stream.0.next().await {
+                    if let Ok(buf) = res {
+                        ASYNC_OUTPUT_COUNT.fetch_add(buf.len(), std::sync::atomic::Ordering::Relaxed);
+                    }
+                }
+                Ok(())
+            }))
+        })?;
         Ok(())
     }
 
@@ -0,0 +1,156 @@
+use std::{
+    sync::{Arc, mpsc},
+    time::{Duration, SystemTime},
+};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    measurement::{MeasurementAccumulator, MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+    metrics::TypedMetricId,
+    pipeline::{
+        Output, Source,
+        clock::MockClock,
+        elements::{
+            error::{PollError, WriteError},
+            output::OutputContext,
+            source::trigger::TriggerSpec,
+        },
+    },
+    plugin::{AlumetPluginStart, AlumetPostStart, AlumetPreStart, Plugin, PluginMetadata},
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A plugin with a source that polls every second and flushes every three seconds.
+struct ClockPlugin {
+    polled_tx: Option<mpsc::Sender<()>>,
+    output_tx: Option<mpsc::Sender<MeasurementBuffer>>,
+}
+
+/// Counts its polls, and notifies the test.
+struct CountingSource {
+    metric: TypedMetricId<u64>,
+    count: u64,
+    polled_tx: mpsc::Sender<()>,
+}
+
+struct ChannelOutput(mpsc::Sender<MeasurementBuffer>);
+
+impl Plugin for ClockPlugin {
+    fn name(&self) -> &str {
+        "clock"
+    }
+
+    fn version(&self) -> &str {
+        "0.0.1"
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        let metric = alumet.create_metric::<u64>("polls", Unit::Unity, "number of polls")?;
+        let source = CountingSource {
+            metric,
+            count: 0,
+            polled_tx: self.polled_tx.take().unwrap(),
+        };
+        let trigger = TriggerSpec::builder(Duration::from_secs(1))
+            .flush_interval(Duration::from_secs(3))
+            .build()?;
+        alumet.add_source("counter", Box::new(source), trigger)?;
+        alumet.add_blocking_output("out", Box::new(ChannelOutput(self.output_tx.take().unwrap())))?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn pre_pipeline_start(&mut self, _alumet: &mut AlumetPreStart) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn post_pipeline_start(&mut self, _alumet: &mut AlumetPostStart) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl Source for CountingSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, t: Timestamp) -> Result<(), PollError> {
+        self.count += 1;
+        measurements.push(MeasurementPoint::new(
+            t,
+            self.metric,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            self.count,
+        ));
+        self.polled_tx.send(()).unwrap();
+        Ok(())
+    }
+}
+
+impl Output for ChannelOutput {
+    fn write(&mut self, measurements: &MeasurementBuffer, _ctx: &OutputContext) -> Result<(), WriteError> {
+        self.0.send(measurements.clone()).unwrap();
+        Ok(())
+    }
+}
+
+#[test]
+fn trigger_and_flush_with_mock_clock() {
+    let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let clock = MockClock::starting_at(t0);
+
+    let (polled_tx, polled_rx) = mpsc::channel();
+    let (output_tx, output_rx) = mpsc::channel();
+    let plugin = PluginMetadata {
+        name: String::from("clock"),
+        version: String::from("0.0.1"),
+        init: Box::new(move |_| {
+            Ok(Box::new(ClockPlugin {
+                polled_tx: Some(polled_tx),
+                output_tx: Some(output_tx),
+            }))
+        }),
+        default_config: Box::new(|| Ok(None)),
+        dependencies: Vec::new(),
+        config_schema: Box::new(|| Ok(None)),
+        config_migrations: Vec::new(),
+    };
+
+    let pipeline_clock = clock.clone();
+    let agent = agent::Builder::new(PluginSet::from(vec![plugin]))
+        .before_operation_begin(move |pipeline| *pipeline.clock() = Arc::new(pipeline_clock))
+        .build_and_start()
+        .unwrap();
+
+    // The first poll happens immediately, the next ones only when the clock moves.
+    polled_rx.recv_timeout(TIMEOUT).unwrap();
+    for _ in 0..2 {
+        assert!(polled_rx.recv_timeout(Duration::from_millis(100)).is_err());
+        clock.advance(Duration::from_secs(1));
+        polled_rx.recv_timeout(TIMEOUT).unwrap();
+    }
+
+    // The measurements are flushed every three polls, with the time of the clock.
+    let buffer = output_rx.recv_timeout(TIMEOUT).unwrap();
+    let points: Vec<_> = buffer.iter().map(|p| (p.timestamp, p.value.clone())).collect();
+    let expected: Vec<_> = (0..3)
+        .map(|i| {
+            (
+                Timestamp::from(t0 + Duration::from_secs(i)),
+                WrappedMeasurementValue::U64(i + 1),
+            )
+        })
+        .collect();
+    assert_eq!(points, expected);
+
+    // Advancing the clock by several intervals at once does not skip any poll.
+    clock.advance(Duration::from_secs(3));
+    let buffer = output_rx.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(buffer.len(), 3);
+
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
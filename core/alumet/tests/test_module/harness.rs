@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use alumet::{
+    measurement::{
+        AttributeValue, MeasurementAccumulator, MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue,
+    },
+    metrics::TypedMetricId,
+    pipeline::{
+        Source, Transform,
+        elements::{
+            error::PollError, error::TransformError, source::trigger::TriggerSpec, transform::TransformContext,
+        },
+        naming::SourceName,
+    },
+    plugin::{AlumetPluginStart, ConfigTable, rust::AlumetPlugin},
+    resources::{Resource, ResourceConsumer},
+    test::PipelineHarness,
+    units::Unit,
+};
+
+/// A plugin that measures the temperature of two fake sensors, and converts it to Fahrenheit.
+pub(super) struct ThermoPlugin;
+
+struct ThermoSource {
+    metric: TypedMetricId<f64>,
+}
+
+struct FahrenheitTransform;
+
+impl AlumetPlugin for ThermoPlugin {
+    fn name() -> &'static str {
+        "thermo"
+    }
+
+    fn version() -> &'static str {
+        "0.1.0"
+    }
+
+    fn init(_config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        Ok(Box::new(Self))
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        Ok(None)
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        let metric = alumet.create_metric::<f64>("temperature", Unit::DegreeCelsius, "temperature of the sensors")?;
+        alumet.add_source(
+            "sensors",
+            Box::new(ThermoSource { metric }),
+            TriggerSpec::at_interval(Duration::from_secs(3600)),
+        )?;
+        alumet.add_transform("fahrenheit", Box::new(FahrenheitTransform))?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl Source for ThermoSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, t: Timestamp) -> Result<(), PollError> {
+        for (sensor, value) in [("cpu", 50.0), ("gpu", 100.0)] {
+            measurements.push(
+                MeasurementPoint::new(
+                    t,
+                    self.metric,
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("sensor", sensor),
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Transform for FahrenheitTransform {
+    fn apply(&mut self, measurements: &mut MeasurementBuffer, _ctx: &TransformContext) -> Result<(), TransformError> {
+        for m in measurements.iter_mut() {
+            if let WrappedMeasurementValue::F64(v) = m.value {
+                m.value = WrappedMeasurementValue::F64(v * 1.8 + 32.0);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sensor_values<'a>(points: impl Iterator<Item = &'a MeasurementPoint>) -> Vec<(String, WrappedMeasurementValue)> {
+    points
+        .map(|p| {
+            let (_, sensor) = p.attributes().find(|(k, _)| *k == "sensor").expect("missing attribute");
+            let AttributeValue::Str(sensor) = sensor else {
+                panic!("unexpected attribute value {sensor:?}");
+            };
+            (sensor.to_string(), p.value.clone())
+        })
+        .collect()
+}
+
+#[test]
+fn harness_poll_source() {
+    let mut harness = PipelineHarness::new();
+    harness.add_plugin(Box::new(ThermoPlugin)).unwrap();
+    let mut running = harness.start().unwrap();
+
+    for _ in 0..2 {
+        let out = running.poll(SourceName::from_str("thermo", "sensors")).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(
+            sensor_values(out.points("temperature")),
+            vec![
+                (String::from("cpu"), WrappedMeasurementValue::F64(122.0)),
+                (String::from("gpu"), WrappedMeasurementValue::F64(212.0)),
+            ]
+        );
+    }
+
+    running.stop().unwrap();
+}
+
+#[test]
+fn harness_inject() {
+    let mut harness = PipelineHarness::new();
+    let metric = harness.create_metric::<f64>("injected", Unit::DegreeCelsius).unwrap();
+    harness.add_plugin(Box::new(ThermoPlugin)).unwrap();
+    let mut running = harness.start().unwrap();
+
+    let mut input = MeasurementBuffer::new();
+    input.push(
+        MeasurementPoint::new(
+            Timestamp::now(),
+            metric,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            0.0,
+        )
+        .with_attr("sensor", "room"),
+    );
+    let out = running.inject(input).unwrap();
+    assert_eq!(out.points("temperature").count(), 0);
+    assert_eq!(
+        sensor_values(out.points("injected")),
+        vec![(String::from("room"), WrappedMeasurementValue::F64(32.0))]
+    );
+
+    running.stop().unwrap();
+}
+
+#[test]
+fn harness_unknown_source() {
+    let mut harness = PipelineHarness::new();
+    *harness.timeout() = Duration::from_millis(200);
+    let mut running = harness.start().unwrap();
+    assert!(running.poll(SourceName::from_str("thermo", "sensors")).is_err());
+    running.stop().unwrap();
+}
@@ -1,5 +1,8 @@
 //! This file contains tests for the testing module.
 
+mod harness;
+mod recording;
+
 use std::{
     sync::atomic::{AtomicU64, Ordering},
     time::Duration,
@@ -0,0 +1,82 @@
+use alumet::{
+    pipeline::{elements::output::builder::OutputBuilder, naming::PluginName, naming::SourceName},
+    test::{PipelineHarness, Recording},
+};
+
+use super::harness::ThermoPlugin;
+
+/// Polls the sensors of [`ThermoPlugin`] twice, and returns what the recording output has received.
+fn record_thermo() -> Recording {
+    let recording = Recording::new();
+    let output = recording.output();
+
+    let mut harness = PipelineHarness::new();
+    harness.add_plugin(Box::new(ThermoPlugin)).unwrap();
+    harness
+        .pipeline()
+        .add_output_builder(
+            PluginName(String::from("test")),
+            "recording",
+            OutputBuilder::Blocking(Box::new(move |_| Ok(Box::new(output)))),
+        )
+        .unwrap();
+    let mut running = harness.start().unwrap();
+    for _ in 0..2 {
+        running.poll(SourceName::from_str("thermo", "sensors")).unwrap();
+    }
+    // the outputs run in parallel: wait for all of them to finish
+    running.stop().unwrap();
+    recording
+}
+
+#[test]
+fn recording_output() {
+    let recording = record_thermo();
+    assert_eq!(recording.len(), 4);
+    recording.assert_count("temperature", 4);
+    recording.assert_count("humidity", 0);
+
+    let gpu: Vec<_> = recording
+        .points_of("temperature")
+        .into_iter()
+        .filter(|p| p.attribute("sensor") == Some("gpu"))
+        .collect();
+    assert_eq!(gpu.len(), 2);
+    assert_eq!(gpu[0].resource, "local_machine");
+}
+
+#[test]
+fn recording_snapshot() {
+    let recording = record_thermo();
+    recording.assert_snapshot("tests/test_module/snapshots/thermo.txt");
+}
+
+#[test]
+fn recording_captured_values() {
+    let mut harness = PipelineHarness::new();
+    harness.add_plugin(Box::new(ThermoPlugin)).unwrap();
+    let mut running = harness.start().unwrap();
+    let recording = running
+        .poll(SourceName::from_str("thermo", "sensors"))
+        .unwrap()
+        .recording();
+    running.stop().unwrap();
+
+    recording.assert_count("temperature", 2);
+    recording.assert_values_near("temperature", 167.0, 45.0);
+}
+
+#[test]
+#[should_panic(expected = "is not within 167 ± 1")]
+fn recording_values_not_near() {
+    let mut harness = PipelineHarness::new();
+    harness.add_plugin(Box::new(ThermoPlugin)).unwrap();
+    let mut running = harness.start().unwrap();
+    let recording = running
+        .poll(SourceName::from_str("thermo", "sensors"))
+        .unwrap()
+        .recording();
+    running.stop().unwrap();
+
+    recording.assert_values_near("temperature", 167.0, 1.0);
+}
@@ -0,0 +1,104 @@
+use std::{thread, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet, timeout::PluginTimeouts},
+    measurement::{MeasurementAccumulator, Timestamp},
+    pipeline::{Source, elements::error::PollError, elements::source::trigger::TriggerSpec},
+    plugin::{AlumetPluginStart, AlumetPostStart, AlumetPreStart, Plugin, PluginMetadata},
+};
+
+/// A plugin that can take a long time to start.
+struct SlowPlugin {
+    name: &'static str,
+    start_delay: Duration,
+}
+
+struct NoopSource;
+
+impl Source for NoopSource {
+    fn poll(&mut self, _into: &mut MeasurementAccumulator, _time: Timestamp) -> Result<(), PollError> {
+        Ok(())
+    }
+}
+
+impl Plugin for SlowPlugin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn version(&self) -> &str {
+        "0.0.1"
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        alumet.add_source(
+            "noop",
+            Box::new(NoopSource),
+            TriggerSpec::at_interval(Duration::from_secs(1)),
+        )?;
+        thread::sleep(self.start_delay);
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn pre_pipeline_start(&mut self, _alumet: &mut AlumetPreStart) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn post_pipeline_start(&mut self, _alumet: &mut AlumetPostStart) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn metadata(name: &'static str, init_delay: Duration, start_delay: Duration) -> PluginMetadata {
+    PluginMetadata {
+        name: name.to_owned(),
+        version: "0.0.1".to_owned(),
+        init: Box::new(move |_| {
+            thread::sleep(init_delay);
+            Ok(Box::new(SlowPlugin { name, start_delay }))
+        }),
+        default_config: Box::new(|| Ok(None)),
+        dependencies: Vec::new(),
+        config_schema: Box::new(|| Ok(None)),
+        config_migrations: Vec::new(),
+    }
+}
+
+#[test]
+fn slow_plugins_are_disabled() {
+    let plugins = PluginSet::from(vec![
+        metadata("ok", Duration::ZERO, Duration::ZERO),
+        // blocked in init: the agent does not wait for it
+        metadata("slow-init", Duration::from_secs(3600), Duration::ZERO),
+        // blocked in start for a while: its source is removed
+        metadata("slow-start", Duration::ZERO, Duration::from_millis(500)),
+    ]);
+    let timeouts = PluginTimeouts {
+        init: Duration::from_millis(200),
+        start: Duration::from_millis(200),
+        ..Default::default()
+    };
+
+    let agent = agent::Builder::new(plugins)
+        .plugin_timeouts(timeouts)
+        .after_plugins_init(|plugins| {
+            let names: Vec<_> = plugins.iter().map(|p| p.name()).collect();
+            assert_eq!(names, vec!["ok", "slow-start"]);
+        })
+        .after_plugins_start(|builder| {
+            let sources: Vec<_> = builder.inspect().sources().iter().map(|s| s.to_string()).collect();
+            assert_eq!(sources, vec!["sources/ok/noop"]);
+        })
+        .build_and_start()
+        .expect("the agent should start without the slow plugins");
+
+    let plugins: Vec<_> = agent.initialized_plugins.iter().map(|p| p.name()).collect();
+    assert_eq!(plugins, vec!["ok"]);
+
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(Duration::from_secs(2)).unwrap();
+}
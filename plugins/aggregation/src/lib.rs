@@ -3,7 +3,7 @@ mod transform;
 
 use std::{
     collections::HashMap,
-    sync::{Arc, RwLock},
+    sync::{Arc, OnceLock, RwLock},
     time::Duration,
 };
 
@@ -28,6 +28,9 @@ pub struct AggregationPlugin {
 
     metrics_list: Vec<Metric>,
     old_ids: Vec<RawMetricId>,
+
+    /// Used to register the aggregated metrics of the late metrics, available after the start-up.
+    metrics_sender: Arc<OnceLock<MetricSender>>,
 }
 
 impl AlumetPlugin for AggregationPlugin {
@@ -50,6 +53,7 @@ impl AlumetPlugin for AggregationPlugin {
             metric_correspondence_table: Arc::new(RwLock::new(HashMap::<RawMetricId, RawMetricId>::new())),
             metrics_list: Vec::<Metric>::new(),
             old_ids: Vec::<RawMetricId>::new(),
+            metrics_sender: Arc::new(OnceLock::new()),
         }))
     }
 
@@ -67,20 +71,18 @@ impl AlumetPlugin for AggregationPlugin {
 
     fn pre_pipeline_start(&mut self, alumet: &mut alumet::plugin::AlumetPreStart) -> anyhow::Result<()> {
         let metrics = alumet.metrics();
+        let mut late_metrics = Vec::new();
 
         for metric_name in self.config.metrics.iter() {
-            let (raw_metric_id, metric) = metrics
-                .by_name(metric_name)
-                .with_context(|| format!("metric \"{}\" not found", &metric_name))?;
-            self.old_ids.push(raw_metric_id);
-            let new_metric = Metric {
-                name: format!("{metric_name}_{}", self.config.function.name()),
-                unit: metric.unit.clone(),
-                description: metric.description.clone(),
-                value_type: metric.value_type.clone(),
+            let Some((raw_metric_id, metric)) = metrics.by_name(metric_name) else {
+                if self.config.late_metrics {
+                    late_metrics.push(metric_name.clone());
+                    continue;
+                }
+                return Err(anyhow!("metric \"{}\" not found", &metric_name));
             };
-
-            self.metrics_list.push(new_metric);
+            self.old_ids.push(raw_metric_id);
+            self.metrics_list.push(aggregated_metric(metric, self.config.function));
         }
 
         if self.metrics_list.len() != self.old_ids.len() {
@@ -89,11 +91,15 @@ impl AlumetPlugin for AggregationPlugin {
             ));
         }
 
+        if !late_metrics.is_empty() {
+            self.listen_late_metrics(alumet, late_metrics)?;
+        }
+
         Ok(())
     }
 
     fn post_pipeline_start(&mut self, alumet: &mut alumet::plugin::AlumetPostStart) -> anyhow::Result<()> {
-        alumet.metrics_sender();
+        let _ = self.metrics_sender.set(alumet.metrics_sender());
 
         // Let's create a runtime to await async function and fill hashmap
         let rt = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
@@ -110,6 +116,54 @@ impl AlumetPlugin for AggregationPlugin {
     }
 }
 
+impl AggregationPlugin {
+    /// Aggregates the metrics of `names` when they are registered.
+    fn listen_late_metrics(
+        &self,
+        alumet: &mut alumet::plugin::AlumetPreStart,
+        names: Vec<String>,
+    ) -> anyhow::Result<()> {
+        let function = self.config.function;
+        let table = self.metric_correspondence_table.clone();
+        let metrics_sender = self.metrics_sender.clone();
+        alumet.add_metric_listener_builder("late_metrics", move |ctx| {
+            let rt = ctx.async_runtime().clone();
+            Ok(Box::new(move |new_metrics: Vec<(RawMetricId, Metric)>| {
+                let (old_ids, new_metrics): (Vec<_>, Vec<_>) = new_metrics
+                    .into_iter()
+                    .filter(|(_, metric)| names.contains(&metric.name))
+                    .map(|(id, metric)| (id, aggregated_metric(&metric, function)))
+                    .unzip();
+                if old_ids.is_empty() {
+                    return Ok(());
+                }
+                let mut metrics_sender = metrics_sender
+                    .get()
+                    .context("late metric registered before the start of the pipeline")?
+                    .clone();
+                let table = table.clone();
+                rt.spawn(async move {
+                    if let Err(e) = register_new_metrics(&mut metrics_sender, new_metrics, old_ids, table).await {
+                        log::error!("failed to register the aggregated metrics: {e:?}");
+                    }
+                });
+                Ok(())
+            }))
+        })?;
+        Ok(())
+    }
+}
+
+/// Returns the definition of the metric that contains the aggregated values of `metric`.
+fn aggregated_metric(metric: &Metric, function: aggregations::Function) -> Metric {
+    Metric {
+        name: format!("{}_{}", metric.name, function.name()),
+        unit: metric.unit.clone(),
+        description: metric.description.clone(),
+        value_type: metric.value_type.clone(),
+    }
+}
+
 async fn register_new_metrics(
     metric_sender: &mut MetricSender,
     new_metrics: Vec<Metric>,
@@ -151,6 +205,12 @@ struct Config {
     // Leave empty to apply function to every metrics. NO
     // TODO: manage all/* metrics P3
     metrics: Vec<String>,
+
+    /// If true, the metrics that do not exist at start-up are aggregated when they are registered,
+    /// for instance when a relay server receives them from its clients.
+    /// Otherwise, all the metrics must exist at start-up.
+    #[serde(default)]
+    late_metrics: bool,
 }
 
 impl Default for Config {
@@ -159,6 +219,7 @@ impl Default for Config {
             interval: Duration::from_secs(60),
             function: aggregations::Function::Sum,
             metrics: Vec::<String>::new(),
+            late_metrics: false,
         }
     }
 }
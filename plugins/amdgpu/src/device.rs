@@ -0,0 +1,337 @@
+//! Discovery of the AMD GPUs that are managed by the `amdgpu` driver.
+
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, anyhow};
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("only Linux is supported");
+
+/// PCI vendor id of AMD.
+const AMD_VENDOR_ID: &str = "0x1002";
+
+/// Represents an AMD GPU and the sysfs files that expose its telemetry.
+///
+/// ## Expected file layout on card {x}
+///
+/// ```txt
+/// /sys/class/drm/card{x}/device/
+/// |−− vendor
+/// |−− uevent
+/// |−− gpu_busy_percent
+/// |−− mem_busy_percent
+/// |−− mem_info_vram_used
+/// |−− hwmon/hwmon{y}/
+///     |−− power1_average (or power1_input)
+///     |−− energy1_input
+///     |−− temp{n}_input, temp{n}_label
+///     |−− freq{n}_input, freq{n}_label
+/// ```
+///
+/// Every file except `vendor` and `uevent` is optional: its availability depends on the GPU and on the version of the driver.
+#[derive(Debug)]
+pub struct AmdGpuDevice {
+    /// PCI address of the GPU, for instance `0000:03:00.0`.
+    pub bus_id: String,
+    pub sensors: Sensors,
+}
+
+/// The sysfs files that are available on a GPU.
+#[derive(Debug, Default)]
+pub struct Sensors {
+    /// Power, in microWatts.
+    pub power: Option<SysfsFile>,
+    /// Energy counter, in microJoules.
+    pub energy: Option<SysfsFile>,
+    /// Temperatures, in milli-degrees Celsius, with their label (`edge`, `junction`, `mem`).
+    pub temperatures: Vec<(String, SysfsFile)>,
+    /// Clock frequencies, in Hertz, with their label (`sclk`, `mclk`).
+    pub clocks: Vec<(String, SysfsFile)>,
+    /// GPU utilization, in percentage.
+    pub gpu_busy: Option<SysfsFile>,
+    /// Memory utilization, in percentage.
+    pub memory_busy: Option<SysfsFile>,
+    /// VRAM used, in bytes.
+    pub vram_used: Option<SysfsFile>,
+}
+
+/// A sysfs file that is opened once and read on each measurement.
+#[derive(Debug)]
+pub struct SysfsFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl SysfsFile {
+    /// Opens the file at `path`, if it exists.
+    fn open_if_exists(path: PathBuf) -> anyhow::Result<Option<Self>> {
+        if !std::fs::exists(&path)? {
+            return Ok(None);
+        }
+        let file = File::open(&path).with_context(|| format!("failed to open {path:?}"))?;
+        Ok(Some(Self { path, file }))
+    }
+
+    /// Reads the value of the file, which must be an unsigned integer.
+    pub fn read_u64(&mut self, buf: &mut String) -> anyhow::Result<u64> {
+        buf.clear();
+        self.file.rewind()?;
+        self.file
+            .read_to_string(buf)
+            .with_context(|| format!("failed to read {:?}", self.path))?;
+        let value = buf
+            .trim_ascii_end()
+            .parse()
+            .with_context(|| format!("invalid content {buf:?} in {:?}", self.path))?;
+        Ok(value)
+    }
+}
+
+impl AmdGpuDevice {
+    /// Inspects the device directory of a DRM card, e.g. `/sys/class/drm/card1/device`.
+    ///
+    /// Returns `None` if the card is not an AMD GPU.
+    pub fn at_sysfs(path: &Path) -> anyhow::Result<Option<Self>> {
+        let vendor_file = path.join("vendor");
+        let vendor =
+            std::fs::read_to_string(&vendor_file).with_context(|| format!("failed to read {vendor_file:?}"))?;
+        if vendor.trim_ascii_end() != AMD_VENDOR_ID {
+            return Ok(None);
+        }
+        let bus_id = read_bus_id(path)?;
+        let mut sensors = Sensors {
+            gpu_busy: SysfsFile::open_if_exists(path.join("gpu_busy_percent"))?,
+            memory_busy: SysfsFile::open_if_exists(path.join("mem_busy_percent"))?,
+            vram_used: SysfsFile::open_if_exists(path.join("mem_info_vram_used"))?,
+            ..Default::default()
+        };
+        if let Some(hwmon) = find_hwmon(path)? {
+            sensors.power = match SysfsFile::open_if_exists(hwmon.join("power1_average"))? {
+                Some(file) => Some(file),
+                None => SysfsFile::open_if_exists(hwmon.join("power1_input"))?,
+            };
+            sensors.energy = SysfsFile::open_if_exists(hwmon.join("energy1_input"))?;
+            sensors.temperatures = labelled_sensors(&hwmon, "temp")?;
+            sensors.clocks = labelled_sensors(&hwmon, "freq")?;
+        }
+        Ok(Some(Self { bus_id, sensors }))
+    }
+}
+
+/// Reads the PCI address of the device from its `uevent` file.
+fn read_bus_id(device_path: &Path) -> anyhow::Result<String> {
+    let uevent_file = device_path.join("uevent");
+    let uevent = std::fs::read_to_string(&uevent_file).with_context(|| format!("failed to read {uevent_file:?}"))?;
+    uevent
+        .lines()
+        .find_map(|line| line.strip_prefix("PCI_SLOT_NAME="))
+        .map(|id| id.trim().to_owned())
+        .with_context(|| format!("PCI_SLOT_NAME not found in {uevent_file:?}"))
+}
+
+/// Finds the hwmon directory of the device, e.g. `/sys/class/drm/card1/device/hwmon/hwmon3`.
+fn find_hwmon(device_path: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let hwmon_dir = device_path.join("hwmon");
+    if !std::fs::exists(&hwmon_dir)? {
+        return Ok(None);
+    }
+    for entry in std::fs::read_dir(&hwmon_dir).with_context(|| format!("failed to read dir {hwmon_dir:?}"))? {
+        let path = entry?.path();
+        if path.file_name().unwrap().to_string_lossy().starts_with("hwmon") {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+/// Opens the files `{prefix}{n}_input` of a hwmon directory, sorted by `n`, with their label.
+///
+/// The label is read from `{prefix}{n}_label`. If there is no label, `{prefix}{n}` is used instead.
+fn labelled_sensors(hwmon_path: &Path, prefix: &str) -> anyhow::Result<Vec<(String, SysfsFile)>> {
+    let mut indices = Vec::new();
+    for entry in std::fs::read_dir(hwmon_path).with_context(|| format!("failed to read dir {hwmon_path:?}"))? {
+        let file_name = entry?.file_name();
+        let index = file_name
+            .to_string_lossy()
+            .strip_prefix(prefix)
+            .and_then(|s| s.strip_suffix("_input"))
+            .and_then(|n| n.parse::<u32>().ok());
+        if let Some(n) = index {
+            indices.push(n);
+        }
+    }
+    indices.sort_unstable();
+
+    let mut sensors = Vec::with_capacity(indices.len());
+    for n in indices {
+        let label_file = hwmon_path.join(format!("{prefix}{n}_label"));
+        let label = match std::fs::read_to_string(&label_file) {
+            Ok(label) => label.trim_ascii_end().to_owned(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => format!("{prefix}{n}"),
+            Err(e) => return Err(anyhow!(e).context(format!("failed to read {label_file:?}"))),
+        };
+        if let Some(file) = SysfsFile::open_if_exists(hwmon_path.join(format!("{prefix}{n}_input")))? {
+            sensors.push((label, file));
+        }
+    }
+    Ok(sensors)
+}
+
+/// Explores the DRM cards and returns the AMD GPUs.
+///
+/// ## Expected file layout
+///
+/// Example of `drm_path`: `/sys/class/drm`
+/// Example of layout:
+/// ```txt
+/// /sys/class/drm/
+/// |
+/// |− card0
+///     |− device
+///         |− vendor
+///         |− …
+/// |− card0-DP-1 (connector, ignored)
+/// |− card1
+/// ```
+///
+/// The cards that cannot be inspected are logged and skipped.
+pub fn explore(drm_path: &Path) -> anyhow::Result<Vec<AmdGpuDevice>> {
+    let mut cards = Vec::new();
+    for entry in std::fs::read_dir(drm_path).with_context(|| format!("failed to read dir {drm_path:?}"))? {
+        let path = entry?.path();
+        let file_name = path.file_name().unwrap().to_string_lossy();
+        // only keep cardN, not the connectors (cardN-DP-1) nor the render nodes (renderDN)
+        if let Some(n) = file_name.strip_prefix("card").and_then(|n| n.parse::<u32>().ok()) {
+            cards.push((n, path.join("device")));
+        }
+    }
+    cards.sort_unstable_by_key(|(n, _)| *n);
+
+    let mut devices = Vec::with_capacity(cards.len());
+    for (_, path) in cards {
+        log::trace!("inspecting {path:?}");
+        match AmdGpuDevice::at_sysfs(&path) {
+            Ok(Some(device)) => devices.push(device),
+            Ok(None) => log::debug!("{path:?} is not an AMD GPU, skipping"),
+            Err(err) => log::error!("failed to inspect the DRM card {path:?}: {err:?}"),
+        }
+    }
+    Ok(devices)
+}
+
+impl Display for Sensors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut available = Vec::new();
+        if self.power.is_some() {
+            available.push(String::from("power"));
+        }
+        if self.energy.is_some() {
+            available.push(String::from("energy"));
+        }
+        for (label, _) in &self.temperatures {
+            available.push(format!("temperature({label})"));
+        }
+        for (label, _) in &self.clocks {
+            available.push(format!("clock({label})"));
+        }
+        if self.gpu_busy.is_some() {
+            available.push(String::from("gpu_utilization"));
+        }
+        if self.memory_busy.is_some() {
+            available.push(String::from("memory_utilization"));
+        }
+        if self.vram_used.is_some() {
+            available.push(String::from("memory_used"));
+        }
+        write!(f, "{}", available.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use super::explore;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_explore() {
+        let root = tempdir().unwrap();
+        let root = root.path();
+        // AMD GPU with all the sensors
+        write(root, "card1/device/vendor", "0x1002\n");
+        write(
+            root,
+            "card1/device/uevent",
+            "DRIVER=amdgpu\nPCI_SLOT_NAME=0000:03:00.0\n",
+        );
+        write(root, "card1/device/gpu_busy_percent", "12\n");
+        write(root, "card1/device/mem_busy_percent", "3\n");
+        write(root, "card1/device/mem_info_vram_used", "1048576\n");
+        write(root, "card1/device/hwmon/hwmon4/power1_average", "42000000\n");
+        write(root, "card1/device/hwmon/hwmon4/temp1_input", "45000\n");
+        write(root, "card1/device/hwmon/hwmon4/temp1_label", "edge\n");
+        write(root, "card1/device/hwmon/hwmon4/temp2_input", "50000\n");
+        write(root, "card1/device/hwmon/hwmon4/temp2_label", "junction\n");
+        write(root, "card1/device/hwmon/hwmon4/temp3_input", "40000\n");
+        write(root, "card1/device/hwmon/hwmon4/freq1_input", "800000000\n");
+        write(root, "card1/device/hwmon/hwmon4/freq1_label", "sclk\n");
+        // connector of the AMD GPU
+        write(root, "card1-DP-1/status", "disconnected\n");
+        // AMD GPU without hwmon
+        write(root, "card2/device/vendor", "0x1002\n");
+        write(root, "card2/device/uevent", "PCI_SLOT_NAME=0000:04:00.0\n");
+        // Intel GPU
+        write(root, "card0/device/vendor", "0x8086\n");
+        write(root, "card0/device/uevent", "PCI_SLOT_NAME=0000:00:02.0\n");
+
+        let devices = explore(root).unwrap();
+        assert_eq!(devices.len(), 2);
+
+        let gpu = &devices[0];
+        assert_eq!(gpu.bus_id, "0000:03:00.0");
+        assert!(gpu.sensors.power.is_some());
+        assert!(gpu.sensors.energy.is_none());
+        let temperatures: Vec<&str> = gpu.sensors.temperatures.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(temperatures, vec!["edge", "junction", "temp3"]);
+        let clocks: Vec<&str> = gpu.sensors.clocks.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(clocks, vec!["sclk"]);
+        assert_eq!(
+            gpu.sensors.to_string(),
+            "power, temperature(edge), temperature(junction), temperature(temp3), clock(sclk), gpu_utilization, memory_utilization, memory_used"
+        );
+
+        let gpu = &devices[1];
+        assert_eq!(gpu.bus_id, "0000:04:00.0");
+        assert_eq!(gpu.sensors.to_string(), "");
+    }
+
+    #[test]
+    fn test_power_input_fallback() {
+        let root = tempdir().unwrap();
+        let root = root.path();
+        write(root, "card0/device/vendor", "0x1002\n");
+        write(root, "card0/device/uevent", "PCI_SLOT_NAME=0000:c1:00.0\n");
+        write(root, "card0/device/hwmon/hwmon2/power1_input", "15000000\n");
+        write(root, "card0/device/hwmon/hwmon2/energy1_input", "123456\n");
+
+        let mut devices = explore(root).unwrap();
+        assert_eq!(devices.len(), 1);
+        let mut buf = String::new();
+        let sensors = &mut devices[0].sensors;
+        assert_eq!(sensors.power.as_mut().unwrap().read_u64(&mut buf).unwrap(), 15000000);
+        assert_eq!(sensors.energy.as_mut().unwrap().read_u64(&mut buf).unwrap(), 123456);
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::{metrics::Metrics, probe::AmdGpuSource};
+
+mod device;
+mod metrics;
+mod probe;
+
+pub struct AmdGpuPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for AmdGpuPlugin {
+    fn name() -> &'static str {
+        "amdgpu"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(AmdGpuPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let drm_path = PathBuf::from(&self.config.root_path);
+        let devices = device::explore(&drm_path)?;
+        if devices.is_empty() {
+            return Err(anyhow!(
+                "No AMD GPU found in {drm_path:?}. Is the amdgpu driver loaded?"
+            ));
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        for device in devices {
+            log::info!("Found AMD GPU {} with sensors: {}", device.bus_id, device.sensors);
+            let source_name = format!("device_{}", device.bus_id);
+            let trigger = TriggerSpec::builder(self.config.poll_interval)
+                .flush_interval(self.config.flush_interval)
+                .build()?;
+            let source = AmdGpuSource::new(device, metrics.clone());
+            alumet.add_source(&source_name, Box::new(source), trigger)?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Initial interval between two AMD GPU measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Initial interval between two flushing of AMD GPU measurements.
+    #[serde(with = "humantime_serde")]
+    pub flush_interval: Duration,
+
+    /// Path to the DRM devices.
+    pub root_path: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1), // 1Hz
+            flush_interval: Duration::from_secs(5),
+            root_path: "/sys/class/drm".to_string(),
+        }
+    }
+}
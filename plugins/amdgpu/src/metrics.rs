@@ -0,0 +1,65 @@
+use alumet::{
+    metrics::{TypedMetricId, error::MetricCreationError},
+    plugin::AlumetPluginStart,
+    units::{PrefixedUnit, Unit},
+};
+
+/// Contains the ids of the measured metrics.
+///
+/// The metrics follow the layout of the `nvml` plugin, with the `amdgpu_` prefix instead of `nvml_`.
+#[derive(Clone)]
+pub struct Metrics {
+    /// Energy consumed by the GPU since the previous measurement, in mJ.
+    pub total_energy_consumption: TypedMetricId<f64>,
+    /// Power of the GPU at the time of the measurement, in mW.
+    pub instant_power: TypedMetricId<u64>,
+    /// GPU temperature in °C.
+    pub temperature_gpu: TypedMetricId<u64>,
+    /// GPU rate utilization in percentage.
+    pub major_utilization_gpu: TypedMetricId<u64>,
+    /// GPU memory utilization in percentage.
+    pub major_utilization_memory: TypedMetricId<u64>,
+    /// GPU memory (VRAM) used in bytes.
+    pub memory_used: TypedMetricId<u64>,
+    /// Clock frequency in Hz.
+    pub clock_frequency: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    /// Creates new Alumet metrics for AMD GPU measurements and stores their ids in a `Metrics` structure.
+    pub fn new(alumet: &mut AlumetPluginStart) -> Result<Self, MetricCreationError> {
+        Ok(Self {
+            total_energy_consumption: alumet.create_metric(
+                "amdgpu_energy_consumption",
+                PrefixedUnit::milli(Unit::Joule),
+                "Energy consumption by the GPU since the previous measurement",
+            )?,
+            instant_power: alumet.create_metric(
+                "amdgpu_instant_power",
+                PrefixedUnit::milli(Unit::Watt),
+                "Instantaneous power of the GPU at the time of the measurement",
+            )?,
+            temperature_gpu: alumet.create_metric(
+                "amdgpu_temperature_gpu",
+                Unit::DegreeCelsius,
+                "Instantaneous temperature of the GPU at the time of the measurement",
+            )?,
+            major_utilization_gpu: alumet.create_metric(
+                "amdgpu_gpu_utilization",
+                Unit::Percent,
+                "GPU rate utilization",
+            )?,
+            major_utilization_memory: alumet.create_metric(
+                "amdgpu_memory_utilization",
+                Unit::Percent,
+                "GPU memory utilization",
+            )?,
+            memory_used: alumet.create_metric("amdgpu_memory_used", Unit::Byte, "GPU memory used")?,
+            clock_frequency: alumet.create_metric(
+                "amdgpu_clock_frequency",
+                Unit::Hertz,
+                "Current frequency of a GPU clock",
+            )?,
+        })
+    }
+}
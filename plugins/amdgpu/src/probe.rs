@@ -0,0 +1,154 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    pipeline::{Source, elements::error::PollError},
+    plugin::util::CounterDiff,
+    resources::{Resource, ResourceConsumer},
+};
+
+use crate::{device::AmdGpuDevice, metrics::Metrics};
+
+/// Measurement source that reads the sysfs files of an AMD GPU.
+pub struct AmdGpuSource {
+    /// Sysfs files of the GPU.
+    device: AmdGpuDevice,
+    /// Alumet metrics IDs.
+    metrics: Metrics,
+    /// Alumet resource ID.
+    resource: Resource,
+    /// Internal state to compute the difference between two increments of the energy counter.
+    energy_counter: CounterDiff,
+    /// Previous power, to estimate the energy when the GPU has no energy counter.
+    previous_power: Option<PowerMeasure>,
+    /// Buffer for reading the files.
+    buf: String,
+}
+
+struct PowerMeasure {
+    t: Timestamp,
+    /// Power in µW.
+    power: u64,
+}
+
+impl AmdGpuSource {
+    pub fn new(device: AmdGpuDevice, metrics: Metrics) -> Self {
+        let bus_id = std::borrow::Cow::Owned(device.bus_id.clone());
+        Self {
+            device,
+            metrics,
+            resource: Resource::Gpu { bus_id },
+            energy_counter: CounterDiff::with_max_value(u64::MAX),
+            previous_power: None,
+            buf: String::new(),
+        }
+    }
+}
+
+impl Source for AmdGpuSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let sensors = &mut self.device.sensors;
+        let buf = &mut self.buf;
+
+        // no consumer, we just monitor the device here
+        let consumer = ResourceConsumer::LocalMachine;
+
+        if let Some(energy_file) = &mut sensors.energy {
+            // the counter is in µJ
+            let energy = energy_file.read_u64(buf)?;
+            if let Some(diff) = self.energy_counter.update(energy).difference() {
+                measurements.push(MeasurementPoint::new(
+                    timestamp,
+                    self.metrics.total_energy_consumption,
+                    self.resource.clone(),
+                    consumer.clone(),
+                    diff as f64 / 1000.0,
+                ));
+            }
+        }
+
+        if let Some(power_file) = &mut sensors.power {
+            let power = power_file.read_u64(buf)?;
+            measurements.push(MeasurementPoint::new(
+                timestamp,
+                self.metrics.instant_power,
+                self.resource.clone(),
+                consumer.clone(),
+                power / 1000,
+            ));
+
+            // Estimate the energy consumption if there is no counter.
+            if sensors.energy.is_none() {
+                let current_power = PowerMeasure { t: timestamp, power };
+                if let Some(previous) = &self.previous_power {
+                    let energy = current_power.compute_energy(previous)?;
+                    measurements.push(MeasurementPoint::new(
+                        timestamp,
+                        self.metrics.total_energy_consumption,
+                        self.resource.clone(),
+                        consumer.clone(),
+                        energy,
+                    ));
+                }
+                self.previous_power = Some(current_power);
+            }
+        }
+
+        for (label, file) in &mut sensors.temperatures {
+            // the temperature is in m°C
+            let temperature = file.read_u64(buf)?;
+            measurements.push(
+                MeasurementPoint::new(
+                    timestamp,
+                    self.metrics.temperature_gpu,
+                    self.resource.clone(),
+                    consumer.clone(),
+                    temperature / 1000,
+                )
+                .with_attr("sensor", label.clone()),
+            );
+        }
+
+        for (label, file) in &mut sensors.clocks {
+            let frequency = file.read_u64(buf)?;
+            measurements.push(
+                MeasurementPoint::new(
+                    timestamp,
+                    self.metrics.clock_frequency,
+                    self.resource.clone(),
+                    consumer.clone(),
+                    frequency,
+                )
+                .with_attr("clock", label.clone()),
+            );
+        }
+
+        let gauges = [
+            (&mut sensors.gpu_busy, self.metrics.major_utilization_gpu),
+            (&mut sensors.memory_busy, self.metrics.major_utilization_memory),
+            (&mut sensors.vram_used, self.metrics.memory_used),
+        ];
+        for (file, metric) in gauges {
+            if let Some(file) = file {
+                let value = file.read_u64(buf)?;
+                measurements.push(MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    self.resource.clone(),
+                    consumer.clone(),
+                    value,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PowerMeasure {
+    /// Computes the energy consumed between the previous measurement and this one, in mJ.
+    ///
+    /// The energy is computed using a discrete integral with the formula: Energy = ((Power(t0) + Power(t1)) / 2) * Δt
+    fn compute_energy(&self, previous: &PowerMeasure) -> anyhow::Result<f64> {
+        let time_elapsed = self.t.duration_since(previous.t)?.as_secs_f64();
+        let energy_microjoules = ((self.power + previous.power) as f64) * 0.5 * time_elapsed;
+        Ok(energy_microjoules / 1000.0)
+    }
+}
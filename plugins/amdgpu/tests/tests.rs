@@ -0,0 +1,133 @@
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    measurement::MeasurementPoint,
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::{PrefixedUnit, Unit},
+};
+use plugin_amdgpu::{AmdGpuPlugin, Config};
+use std::{path::Path, time::Duration};
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const PLUGIN_NAME: &str = "amdgpu";
+const SOURCE_NAME: &str = "device_0000:03:00.0";
+const BUS_ID: &str = "0000:03:00.0";
+
+#[test]
+fn plugin_without_device() {
+    let root = tempdir().unwrap();
+    // Intel GPU only
+    write(root.path(), "card0/device/vendor", "0x8086\n");
+    write(root.path(), "card0/device/uevent", "PCI_SLOT_NAME=0000:00:02.0\n");
+
+    let agent = agent::Builder::new(plugins(root.path())).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no AMD GPU)")
+}
+
+#[test]
+fn plugin_with_mock_gpu() {
+    let root = tempdir().unwrap();
+    let device = root.path().join("card1/device");
+    write(&device, "vendor", "0x1002\n");
+    write(&device, "uevent", &format!("DRIVER=amdgpu\nPCI_SLOT_NAME={BUS_ID}\n"));
+    write(&device, "gpu_busy_percent", "57\n");
+    write(&device, "mem_busy_percent", "21\n");
+    write(&device, "mem_info_vram_used", "2147483648\n");
+    write(&device, "hwmon/hwmon3/power1_average", "120500000\n");
+    write(&device, "hwmon/hwmon3/energy1_input", "1000000\n");
+    write(&device, "hwmon/hwmon3/temp1_input", "45000\n");
+    write(&device, "hwmon/hwmon3/temp1_label", "edge\n");
+    write(&device, "hwmon/hwmon3/freq1_input", "2100000000\n");
+    write(&device, "hwmon/hwmon3/freq1_label", "sclk\n");
+
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("amdgpu_energy_consumption", PrefixedUnit::milli(Unit::Joule))
+        .expect_metric::<u64>("amdgpu_instant_power", PrefixedUnit::milli(Unit::Watt))
+        .expect_metric::<u64>("amdgpu_temperature_gpu", Unit::DegreeCelsius)
+        .expect_metric::<u64>("amdgpu_gpu_utilization", Unit::Percent)
+        .expect_metric::<u64>("amdgpu_memory_utilization", Unit::Percent)
+        .expect_metric::<u64>("amdgpu_memory_used", Unit::Byte)
+        .expect_metric::<u64>("amdgpu_clock_frequency", Unit::Hertz)
+        .expect_source(PLUGIN_NAME, SOURCE_NAME);
+
+    let source = SourceName::from_str(PLUGIN_NAME, SOURCE_NAME);
+    let energy_file = device.join("hwmon/hwmon3/energy1_input");
+    let runtime_expectation = RuntimeExpectations::new()
+        // call the source once, so that the energy counter has a previous value
+        .test_source(
+            source.clone(),
+            || {},
+            |ctx| {
+                let m = ctx.measurements();
+                let value_of = |name: &str| {
+                    let metric = ctx.metrics().by_name(name).unwrap().0;
+                    let points: Vec<&MeasurementPoint> = m.iter().filter(|p| p.metric == metric).collect();
+                    assert_eq!(points.len(), 1, "there should be one point for {name}");
+                    assert_eq!(points[0].resource, Resource::Gpu { bus_id: BUS_ID.into() });
+                    points[0].value.as_u64()
+                };
+                assert_eq!(value_of("amdgpu_instant_power"), 120500);
+                assert_eq!(value_of("amdgpu_temperature_gpu"), 45);
+                assert_eq!(value_of("amdgpu_clock_frequency"), 2100000000);
+                assert_eq!(value_of("amdgpu_gpu_utilization"), 57);
+                assert_eq!(value_of("amdgpu_memory_utilization"), 21);
+                assert_eq!(value_of("amdgpu_memory_used"), 2147483648);
+
+                let energy_metric = ctx.metrics().by_name("amdgpu_energy_consumption").unwrap().0;
+                assert!(
+                    m.iter().all(|p| p.metric != energy_metric),
+                    "no energy should be pushed on the first poll"
+                );
+
+                let temperature_metric = ctx.metrics().by_name("amdgpu_temperature_gpu").unwrap().0;
+                let temperature = m.iter().find(|p| p.metric == temperature_metric).unwrap();
+                assert!(
+                    temperature
+                        .attributes()
+                        .any(|(k, v)| k == "sensor" && v.to_string() == "edge")
+                );
+            },
+        )
+        // increase the energy counter and check the difference
+        .test_source(
+            source,
+            move || std::fs::write(&energy_file, "1250000\n").unwrap(),
+            |ctx| {
+                let energy_metric = ctx.metrics().by_name("amdgpu_energy_consumption").unwrap().0;
+                let energy = ctx.measurements().iter().find(|p| p.metric == energy_metric).unwrap();
+                assert_eq!(energy.value.as_f64(), 250.0);
+            },
+        );
+
+    let agent = agent::Builder::new(plugins(root.path()))
+        .with_expectations(startup_expectation)
+        .with_expectations(runtime_expectation)
+        .build_and_start()
+        .unwrap();
+
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn plugins(root_path: &Path) -> PluginSet {
+    let config = Config {
+        poll_interval: Duration::from_secs(1),
+        flush_interval: Duration::from_secs(1),
+        root_path: root_path.to_str().unwrap().to_string(),
+    };
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<AmdGpuPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
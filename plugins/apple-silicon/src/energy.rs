@@ -0,0 +1,131 @@
+//! Interpretation of the energy channels of IOReport.
+//!
+//! The "Energy Model" group of IOReport contains one channel per component of the SoC,
+//! with the energy consumed since the previous sample. The names of the channels depend on the chip:
+//! `CPU Energy`, `GPU Energy`, `ANE0`, `DRAM0`, and on the Ultra chips, one channel per die (`DIE_0_CPU Energy`).
+
+use std::collections::BTreeMap;
+
+/// Group of the energy channels.
+pub const ENERGY_GROUP: &str = "Energy Model";
+
+/// A channel of an IOReport sample.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub group: String,
+    pub name: String,
+    /// Unit of the value, like `mJ`.
+    pub unit: String,
+    pub value: i64,
+}
+
+/// Component of the SoC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Domain {
+    Cpu,
+    Gpu,
+    /// Apple Neural Engine.
+    Ane,
+    Dram,
+}
+
+impl Domain {
+    fn of_channel(name: &str) -> Option<Self> {
+        if name.ends_with("CPU Energy") {
+            Some(Domain::Cpu)
+        } else if name.ends_with("GPU Energy") {
+            Some(Domain::Gpu)
+        } else if name.starts_with("ANE") {
+            Some(Domain::Ane)
+        } else if name.starts_with("DRAM") {
+            Some(Domain::Dram)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Domain::Cpu => "cpu",
+            Domain::Gpu => "gpu",
+            Domain::Ane => "ane",
+            Domain::Dram => "dram",
+        }
+    }
+}
+
+/// Converts the value of an energy channel to Joules.
+fn joules(value: i64, unit: &str) -> Option<f64> {
+    let factor = match unit.trim() {
+        "mJ" => 1e-3,
+        "uJ" | "µJ" => 1e-6,
+        "nJ" => 1e-9,
+        _ => return None,
+    };
+    Some(value as f64 * factor)
+}
+
+/// Computes the energy consumed by each domain, in Joules, from the channels of a sample.
+///
+/// The channels of the other groups, and the energy channels that do not correspond to a domain
+/// (such as the channels of the individual CPU clusters), are ignored.
+pub fn energy_by_domain(channels: &[Channel]) -> BTreeMap<Domain, f64> {
+    let mut energy = BTreeMap::new();
+    for channel in channels.iter().filter(|c| c.group == ENERGY_GROUP) {
+        let Some(domain) = Domain::of_channel(&channel.name) else {
+            continue;
+        };
+        match joules(channel.value, &channel.unit) {
+            Some(j) => *energy.entry(domain).or_insert(0.0) += j,
+            None => log::warn!("Unknown unit {:?} of channel {}", channel.unit, channel.name),
+        }
+    }
+    energy
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn channel(group: &str, name: &str, unit: &str, value: i64) -> Channel {
+        Channel {
+            group: group.to_owned(),
+            name: name.to_owned(),
+            unit: unit.to_owned(),
+            value,
+        }
+    }
+
+    #[test]
+    fn energy_of_domains() {
+        let channels = [
+            channel(ENERGY_GROUP, "ECPU0", "mJ", 10),
+            channel(ENERGY_GROUP, "PCPU0", "mJ", 100),
+            channel(ENERGY_GROUP, "DIE_0_CPU Energy", "mJ", 1500),
+            channel(ENERGY_GROUP, "DIE_1_CPU Energy", "mJ", 500),
+            channel(ENERGY_GROUP, "GPU Energy", "nJ", 250_000_000),
+            channel(ENERGY_GROUP, "ANE0", "mJ", 0),
+            channel(ENERGY_GROUP, "DRAM0", "uJ", 75_000),
+            channel(ENERGY_GROUP, "DISP", "mJ", 42),
+            channel("CPU Stats", "CPU Energy", "mJ", 9999),
+        ];
+        let energy = energy_by_domain(&channels);
+        assert_eq!(
+            energy.into_iter().collect::<Vec<_>>(),
+            vec![
+                (Domain::Cpu, 2.0),
+                (Domain::Gpu, 0.25),
+                (Domain::Ane, 0.0),
+                (Domain::Dram, 0.075)
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_unit() {
+        let channels = [channel(ENERGY_GROUP, "CPU Energy", "kWh", 1)];
+        assert!(energy_by_domain(&channels).is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! Bindings to IOReport, the private library of macOS that exposes the energy counters of the Apple SoCs.
+//!
+//! This is the interface used by `powermetrics`. It does not require root privileges.
+
+use std::ffi::c_void;
+use std::ptr::{null, null_mut};
+
+use anyhow::anyhow;
+use core_foundation::{base::TCFType, string::CFString};
+use core_foundation_sys::{
+    array::{CFArrayGetCount, CFArrayGetValueAtIndex, CFArrayRef},
+    base::{CFRelease, CFTypeRef, kCFAllocatorDefault},
+    dictionary::{
+        CFDictionaryCreateMutableCopy, CFDictionaryGetCount, CFDictionaryGetValue, CFDictionaryRef,
+        CFMutableDictionaryRef,
+    },
+    string::CFStringRef,
+};
+
+use crate::energy::{Channel, ENERGY_GROUP};
+
+type IOReportSubscriptionRef = CFTypeRef;
+
+#[link(name = "IOReport", kind = "dylib")]
+unsafe extern "C" {
+    fn IOReportCopyChannelsInGroup(
+        group: CFStringRef,
+        subgroup: CFStringRef,
+        a: u64,
+        b: u64,
+        c: u64,
+    ) -> CFDictionaryRef;
+    fn IOReportCreateSubscription(
+        a: *const c_void,
+        channels: CFMutableDictionaryRef,
+        subscribed_channels: *mut CFMutableDictionaryRef,
+        b: u64,
+        c: CFTypeRef,
+    ) -> IOReportSubscriptionRef;
+    fn IOReportCreateSamples(
+        subscription: IOReportSubscriptionRef,
+        channels: CFMutableDictionaryRef,
+        a: CFTypeRef,
+    ) -> CFDictionaryRef;
+    fn IOReportCreateSamplesDelta(previous: CFDictionaryRef, current: CFDictionaryRef, a: CFTypeRef)
+    -> CFDictionaryRef;
+    fn IOReportChannelGetGroup(channel: CFDictionaryRef) -> CFStringRef;
+    fn IOReportChannelGetChannelName(channel: CFDictionaryRef) -> CFStringRef;
+    fn IOReportChannelGetUnitLabel(channel: CFDictionaryRef) -> CFStringRef;
+    fn IOReportSimpleGetIntegerValue(channel: CFDictionaryRef, index: i32) -> i64;
+}
+
+/// A subscription to the energy channels, that returns the energy consumed between two samples.
+pub struct EnergySampler {
+    subscription: IOReportSubscriptionRef,
+    channels: CFMutableDictionaryRef,
+    subscribed_channels: CFMutableDictionaryRef,
+    previous: CFDictionaryRef,
+}
+
+// SAFETY: the CoreFoundation objects are only accessed by the owner of the sampler
+unsafe impl Send for EnergySampler {}
+
+impl EnergySampler {
+    pub fn new() -> anyhow::Result<Self> {
+        let group = CFString::from_static_string(ENERGY_GROUP);
+        // SAFETY: the returned objects are checked before use, and released when the sampler is dropped
+        unsafe {
+            let channels = IOReportCopyChannelsInGroup(group.as_concrete_TypeRef(), null(), 0, 0, 0);
+            if channels.is_null() {
+                return Err(anyhow!(
+                    "IOReport has no \"{ENERGY_GROUP}\" channel (is this an Apple Silicon Mac?)"
+                ));
+            }
+            let size = CFDictionaryGetCount(channels);
+            let mutable_channels = CFDictionaryCreateMutableCopy(kCFAllocatorDefault, size, channels);
+            CFRelease(channels as CFTypeRef);
+
+            let mut subscribed_channels: CFMutableDictionaryRef = null_mut();
+            let subscription =
+                IOReportCreateSubscription(null(), mutable_channels, &mut subscribed_channels, 0, null());
+            if subscription.is_null() {
+                CFRelease(mutable_channels as CFTypeRef);
+                return Err(anyhow!("could not subscribe to the IOReport energy channels"));
+            }
+            let previous = IOReportCreateSamples(subscription, mutable_channels, null());
+            Ok(Self {
+                subscription,
+                channels: mutable_channels,
+                subscribed_channels,
+                previous,
+            })
+        }
+    }
+
+    /// Returns the energy channels, with the energy consumed since the previous call.
+    pub fn sample(&mut self) -> anyhow::Result<Vec<Channel>> {
+        // SAFETY: the samples are valid dictionaries (checked), released after use
+        unsafe {
+            let current = IOReportCreateSamples(self.subscription, self.channels, null());
+            if current.is_null() {
+                return Err(anyhow!("could not sample the IOReport energy channels"));
+            }
+            let delta = if self.previous.is_null() {
+                null()
+            } else {
+                let delta = IOReportCreateSamplesDelta(self.previous, current, null());
+                CFRelease(self.previous as CFTypeRef);
+                delta
+            };
+            self.previous = current;
+            if delta.is_null() {
+                return Ok(Vec::new());
+            }
+
+            let key = CFString::from_static_string("IOReportChannels");
+            let items = CFDictionaryGetValue(delta, key.as_concrete_TypeRef() as *const c_void) as CFArrayRef;
+            let mut channels = Vec::new();
+            if !items.is_null() {
+                for i in 0..CFArrayGetCount(items) {
+                    let item = CFArrayGetValueAtIndex(items, i) as CFDictionaryRef;
+                    channels.push(Channel {
+                        group: to_string(IOReportChannelGetGroup(item)),
+                        name: to_string(IOReportChannelGetChannelName(item)),
+                        unit: to_string(IOReportChannelGetUnitLabel(item)),
+                        value: IOReportSimpleGetIntegerValue(item, 0),
+                    });
+                }
+            }
+            CFRelease(delta as CFTypeRef);
+            Ok(channels)
+        }
+    }
+}
+
+impl Drop for EnergySampler {
+    fn drop(&mut self) {
+        // SAFETY: the objects have been created by IOReport and are owned by the sampler
+        unsafe {
+            if !self.previous.is_null() {
+                CFRelease(self.previous as CFTypeRef);
+            }
+            if !self.subscribed_channels.is_null() {
+                CFRelease(self.subscribed_channels as CFTypeRef);
+            }
+            CFRelease(self.channels as CFTypeRef);
+            CFRelease(self.subscription);
+        }
+    }
+}
+
+/// Copies a string that belongs to IOReport.
+///
+/// # Safety
+/// `s` must be null or a valid `CFStringRef`.
+unsafe fn to_string(s: CFStringRef) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        // SAFETY: the string is valid, and the "get rule" retains it while it is wrapped
+        unsafe { CFString::wrap_under_get_rule(s) }.to_string()
+    }
+}
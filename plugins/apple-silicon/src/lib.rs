@@ -0,0 +1,87 @@
+//! Measures the energy of the Apple Silicon SoCs (M1 and later), on macOS.
+//!
+//! On the other operating systems, this crate does not provide any plugin.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+mod energy;
+#[cfg(target_os = "macos")]
+mod ioreport;
+#[cfg(target_os = "macos")]
+mod source;
+
+#[cfg(target_os = "macos")]
+pub use plugin::AppleSiliconPlugin;
+
+#[cfg(target_os = "macos")]
+mod plugin {
+    use alumet::{
+        pipeline::elements::source::trigger::TriggerSpec,
+        plugin::{
+            AlumetPluginStart, ConfigTable,
+            rust::{AlumetPlugin, deserialize_config, serialize_config},
+        },
+    };
+    use anyhow::Context;
+
+    use crate::{
+        Config,
+        ioreport::EnergySampler,
+        source::{AppleEnergySource, Metrics},
+    };
+
+    pub struct AppleSiliconPlugin {
+        config: Config,
+    }
+
+    impl AlumetPlugin for AppleSiliconPlugin {
+        fn name() -> &'static str {
+            "apple-silicon"
+        }
+
+        fn version() -> &'static str {
+            env!("CARGO_PKG_VERSION")
+        }
+
+        fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+            let config = serialize_config(Config::default())?;
+            Ok(Some(config))
+        }
+
+        fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+            let config = deserialize_config(config)?;
+            Ok(Box::new(AppleSiliconPlugin { config }))
+        }
+
+        fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+            let sampler = EnergySampler::new().context("could not access the energy counters")?;
+            let metrics = Metrics::new(alumet)?;
+            let source = AppleEnergySource::new(sampler, metrics);
+            let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+            alumet.add_source("soc", Box::new(source), trigger)?;
+            Ok(())
+        }
+
+        fn stop(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
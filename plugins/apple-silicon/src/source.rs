@@ -0,0 +1,88 @@
+use std::time::Instant;
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+
+use crate::{
+    energy::{self, Domain},
+    ioreport::EnergySampler,
+};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    energy: TypedMetricId<f64>,
+    power: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            energy: alumet.create_metric(
+                "apple_consumed_energy",
+                Unit::Joule,
+                "Energy consumed by a component of the SoC since the previous measurement",
+            )?,
+            power: alumet.create_metric(
+                "apple_power",
+                Unit::Watt,
+                "Average power of a component of the SoC since the previous measurement",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the energy of the components of the Apple SoC.
+pub struct AppleEnergySource {
+    sampler: EnergySampler,
+    metrics: Metrics,
+    /// Time of the previous sample, to compute the average power.
+    previous: Instant,
+}
+
+impl AppleEnergySource {
+    pub fn new(sampler: EnergySampler, metrics: Metrics) -> Self {
+        Self {
+            sampler,
+            metrics,
+            previous: Instant::now(),
+        }
+    }
+}
+
+impl Source for AppleEnergySource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let channels = self.sampler.sample()?;
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.previous).as_secs_f64();
+        self.previous = now;
+
+        for (domain, joules) in energy::energy_by_domain(&channels) {
+            let resource = match domain {
+                Domain::Cpu => Resource::CpuPackage { id: 0 },
+                Domain::Dram => Resource::Dram { pkg_id: 0 },
+                Domain::Gpu | Domain::Ane => Resource::LocalMachine,
+            };
+            let point = |metric, value: f64| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    resource.clone(),
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("domain", domain.as_str())
+            };
+            measurements.push(point(self.metrics.energy, joules));
+            if elapsed > 0.0 {
+                measurements.push(point(self.metrics.power, joules / elapsed));
+            }
+        }
+        Ok(())
+    }
+}
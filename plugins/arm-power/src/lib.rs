@@ -0,0 +1,101 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::source::{ArmPowerSource, Metrics};
+
+mod sensors;
+mod source;
+
+/// Measures the power and energy of the Arm SoCs (Ampere, SCMI-based platforms), where RAPL is not available.
+pub struct ArmPowerPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for ArmPowerPlugin {
+    fn name() -> &'static str {
+        "arm-power"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(ArmPowerPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let mut chips = sensors::explore(&self.config.hwmon_path, &self.config.chips)
+            .context("could not find the power sensors")?;
+        for chip in &chips {
+            for sensor in &chip.sensors {
+                log::info!(
+                    "Found sensor {}/{} ({:?}, {:?}) in {}",
+                    chip.name,
+                    sensor.label,
+                    sensor.kind,
+                    sensor.resource,
+                    chip.device
+                );
+            }
+        }
+        chips.retain(|chip| !chip.sensors.is_empty());
+        if chips.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: no power or energy sensor of the chips {:?} found in {:?}",
+                self.config.chips,
+                self.config.hwmon_path
+            ));
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = ArmPowerSource::new(chips, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("sensors", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the hwmon chips.
+    pub hwmon_path: PathBuf,
+
+    /// Names of the hwmon chips that provide the power and energy of the SoC.
+    pub chips: Vec<String>,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            hwmon_path: PathBuf::from("/sys/class/hwmon"),
+            chips: vec![String::from("scmi_sensors"), String::from("apm_xgene")],
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Discovery of the power and energy sensors of the Arm SoCs, exposed by hwmon.
+//!
+//! The sensors are provided by the firmware through SCMI (driver `scmi-hwmon`, chip `scmi_sensors`),
+//! or by vendor drivers, like `xgene-hwmon` on the Ampere eMAG and Altra (chip `apm_xgene`).
+//! The labels of the sensors are chosen by the firmware, for instance `SoC power`, `CPU power` or `DDR`.
+
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::Path,
+};
+
+use alumet::resources::Resource;
+use anyhow::Context;
+
+/// Kind of sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorKind {
+    /// `power{N}_input`, in microwatts.
+    Power,
+    /// `energy{N}_input`, in microjoules.
+    Energy,
+}
+
+/// A power or energy sensor of a chip.
+#[derive(Debug)]
+pub struct Sensor {
+    pub kind: SensorKind,
+    /// Label of the sensor, or its channel (like `power1`) if the chip provides no label.
+    pub label: String,
+    pub resource: Resource,
+    file: File,
+}
+
+/// A hwmon chip that contains power or energy sensors.
+#[derive(Debug)]
+pub struct Chip {
+    /// Name of the chip, like `scmi_sensors`.
+    pub name: String,
+    /// Name of the hwmon device, like `hwmon2`.
+    pub device: String,
+    pub sensors: Vec<Sensor>,
+}
+
+impl Sensor {
+    /// Reads the raw value of the sensor, in microwatts or microjoules.
+    pub fn read(&mut self, buf: &mut String) -> std::io::Result<u64> {
+        buf.clear();
+        self.file.rewind()?;
+        self.file.read_to_string(buf)?;
+        buf.trim_ascii_end()
+            .parse()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Finds the chips named `names` in `hwmon_path`, and their power and energy sensors.
+///
+/// The chips are sorted by hwmon device. When there are several chips with the same name
+/// (one per socket), their sensors are attributed to the sockets in this order.
+pub fn explore(hwmon_path: &Path, names: &[String]) -> anyhow::Result<Vec<Chip>> {
+    let mut dirs = Vec::new();
+    for entry in std::fs::read_dir(hwmon_path).with_context(|| format!("failed to read dir {hwmon_path:?}"))? {
+        let path = entry?.path();
+        let Ok(name) = std::fs::read_to_string(path.join("name")) else {
+            continue;
+        };
+        let name = name.trim_ascii_end().to_owned();
+        if names.contains(&name) {
+            dirs.push((name, path));
+        }
+    }
+    dirs.sort_by_key(|(_, path)| device_number(path));
+
+    let mut chips: Vec<Chip> = Vec::with_capacity(dirs.len());
+    for (name, path) in dirs {
+        let socket = chips.iter().filter(|c| c.name == name).count() as u32;
+        let device = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let sensors = sensors_of_chip(&path, socket).with_context(|| format!("failed to analyze chip {path:?}"))?;
+        chips.push(Chip { name, device, sensors });
+    }
+    Ok(chips)
+}
+
+/// Extracts `N` from `.../hwmonN`, to sort `hwmon10` after `hwmon9`.
+fn device_number(path: &Path) -> u32 {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .and_then(|n| n.strip_prefix("hwmon"))
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(u32::MAX)
+}
+
+fn sensors_of_chip(dir: &Path, socket: u32) -> anyhow::Result<Vec<Sensor>> {
+    let mut channels = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let file_name = entry?.file_name();
+        let Some(channel) = file_name.to_str().and_then(|n| n.strip_suffix("_input")) else {
+            continue;
+        };
+        let kind = if channel.starts_with("power") {
+            SensorKind::Power
+        } else if channel.starts_with("energy") {
+            SensorKind::Energy
+        } else {
+            continue;
+        };
+        channels.push((kind, channel.to_owned()));
+    }
+    channels.sort_by(|a, b| a.1.cmp(&b.1));
+
+    let mut sensors = Vec::with_capacity(channels.len());
+    for (kind, channel) in channels {
+        let label = std::fs::read_to_string(dir.join(format!("{channel}_label")))
+            .map(|l| l.trim_ascii_end().to_owned())
+            .unwrap_or_else(|_| channel.clone());
+        let path = dir.join(format!("{channel}_input"));
+        match File::open(&path) {
+            Ok(file) => sensors.push(Sensor {
+                kind,
+                resource: resource_of_label(&label, socket),
+                label,
+                file,
+            }),
+            Err(e) => log::warn!("cannot open {path:?}, the sensor will not be measured: {e}"),
+        }
+    }
+    Ok(sensors)
+}
+
+/// Guesses the resource measured by a sensor, from its label.
+fn resource_of_label(label: &str, socket: u32) -> Resource {
+    let label = label.to_ascii_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| label.contains(w));
+    if has(&["ddr", "dram", "dimm", "mem"]) {
+        Resource::Dram { pkg_id: socket }
+    } else if has(&["cpu", "soc", "core", "cluster", "package"]) {
+        Resource::CpuPackage { id: socket }
+    } else {
+        Resource::LocalMachine
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn resources() {
+        assert_eq!(resource_of_label("SoC power", 0), Resource::CpuPackage { id: 0 });
+        assert_eq!(resource_of_label("CPU_CLUSTER1", 1), Resource::CpuPackage { id: 1 });
+        assert_eq!(resource_of_label("DDR", 1), Resource::Dram { pkg_id: 1 });
+        assert_eq!(resource_of_label("IO power", 0), Resource::LocalMachine);
+    }
+
+    #[test]
+    fn device_numbers() {
+        assert_eq!(device_number(Path::new("/sys/class/hwmon/hwmon10")), 10);
+        assert_eq!(device_number(Path::new("/sys/class/hwmon/other")), u32::MAX);
+    }
+}
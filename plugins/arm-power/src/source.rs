@@ -0,0 +1,102 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::ResourceConsumer,
+    units::Unit,
+};
+
+use crate::sensors::{Chip, SensorKind};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    power: TypedMetricId<f64>,
+    energy: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            power: alumet.create_metric("arm_power", Unit::Watt, "Power reported by a sensor of the SoC")?,
+            energy: alumet.create_metric(
+                "arm_consumed_energy",
+                Unit::Joule,
+                "Energy consumed since the previous measurement, reported by a sensor of the SoC",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the power and energy sensors of the Arm SoCs.
+pub struct ArmPowerSource {
+    chips: Vec<MeasuredChip>,
+    metrics: Metrics,
+    buf: String,
+}
+
+struct MeasuredChip {
+    chip: Chip,
+    /// The previous value of each energy sensor, to compute the difference.
+    previous_energy: Vec<Option<u64>>,
+}
+
+impl ArmPowerSource {
+    pub fn new(chips: Vec<Chip>, metrics: Metrics) -> Self {
+        let chips = chips
+            .into_iter()
+            .map(|chip| MeasuredChip {
+                previous_energy: vec![None; chip.sensors.len()],
+                chip,
+            })
+            .collect();
+        Self {
+            chips,
+            metrics,
+            buf: String::with_capacity(16),
+        }
+    }
+}
+
+impl Source for ArmPowerSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for c in &mut self.chips {
+            for (sensor, previous) in c.chip.sensors.iter_mut().zip(&mut c.previous_energy) {
+                // Some sensors are temporarily unavailable (e.g. ENODATA, EIO): skip them this time.
+                let raw = match sensor.read(&mut self.buf) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        log::debug!("failed to read sensor {}/{}: {e}", c.chip.device, sensor.label);
+                        continue;
+                    }
+                };
+                let point = |metric, value: f64| {
+                    MeasurementPoint::new(
+                        timestamp,
+                        metric,
+                        sensor.resource.clone(),
+                        ResourceConsumer::LocalMachine,
+                        value,
+                    )
+                    .with_attr("chip", c.chip.name.clone())
+                    .with_attr("device", c.chip.device.clone())
+                    .with_attr("sensor", sensor.label.clone())
+                };
+                match sensor.kind {
+                    SensorKind::Power => measurements.push(point(self.metrics.power, raw as f64 / 1e6)),
+                    SensorKind::Energy => {
+                        // Only push deltas, not the baseline value before the plugin starts.
+                        // The counter is reset when the firmware restarts: skip the measurement in that case.
+                        if let Some(prev) = *previous
+                            && raw >= prev
+                        {
+                            measurements.push(point(self.metrics.energy, (raw - prev) as f64 / 1e6));
+                        }
+                        *previous = Some(raw);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
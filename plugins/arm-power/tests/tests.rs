@@ -0,0 +1,114 @@
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_arm_power::{ArmPowerPlugin, Config};
+use pretty_assertions::assert_eq;
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_sensor() {
+    let root = tempdir().unwrap();
+    write_chip(&root.path().join("hwmon0"), "coretemp", &[("temp1_input", "45000")]);
+    let config = Config {
+        hwmon_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no power sensor)");
+}
+
+#[test]
+fn plugin_with_sensors() {
+    let root = tempdir().unwrap();
+    write_chip(
+        &root.path().join("hwmon0"),
+        "scmi_sensors",
+        &[
+            ("power1_input", "45500000"),
+            ("power1_label", "SoC power"),
+            ("energy1_input", "123456789"),
+            ("energy1_label", "DDR"),
+            ("temp1_input", "50000"),
+        ],
+    );
+    write_chip(&root.path().join("hwmon1"), "coretemp", &[("power1_input", "1000000")]);
+    write_chip(
+        &root.path().join("hwmon2"),
+        "apm_xgene",
+        &[
+            ("power1_input", "2000000"),
+            ("power2_input", "500000"),
+            ("power2_label", "IO power"),
+        ],
+    );
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        hwmon_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<f64>("arm_power", Unit::Watt)
+        .expect_metric::<f64>("arm_consumed_energy", Unit::Joule)
+        .expect_source("arm-power", "sensors");
+
+    let runtime = RuntimeExpectations::new().test_source(
+        SourceName::from_str("arm-power", "sensors"),
+        || {},
+        |ctx| {
+            let m = ctx.measurements();
+            let mut points: Vec<_> = m
+                .iter()
+                .map(|p| {
+                    let sensor = p.attributes().find(|(k, _)| *k == "sensor").unwrap().1.to_string();
+                    (sensor, p.resource.clone(), p.value.as_f64())
+                })
+                .collect();
+            points.sort_by(|a, b| a.0.cmp(&b.0));
+            // no energy at the first measurement, only the power
+            assert_eq!(
+                points,
+                vec![
+                    (String::from("IO power"), Resource::LocalMachine, 0.5),
+                    (String::from("SoC power"), Resource::CpuPackage { id: 0 }, 45.5),
+                    (String::from("power1"), Resource::LocalMachine, 2.0),
+                ]
+            );
+        },
+    );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn write_chip(path: &Path, name: &str, files: &[(&str, &str)]) {
+    std::fs::create_dir_all(path).unwrap();
+    std::fs::write(path.join("name"), name).unwrap();
+    for (file, content) in files {
+        std::fs::write(path.join(file), content).unwrap();
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<ArmPowerPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -0,0 +1,88 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::{
+    source::{BatterySource, Metrics},
+    supply::SupplyKind,
+};
+
+mod source;
+mod supply;
+
+/// Measures the batteries and AC adapters, with the `power_supply` class of the Linux kernel.
+pub struct BatteryPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for BatteryPlugin {
+    fn name() -> &'static str {
+        "battery"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(BatteryPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let supplies = supply::explore(&self.config.power_supply_path).context("could not find the power supplies")?;
+        if !supplies.iter().any(|s| s.kind == SupplyKind::Battery) {
+            return Err(anyhow!(
+                "nothing to measure: no battery found in {:?}",
+                self.config.power_supply_path
+            ));
+        }
+        for supply in &supplies {
+            log::info!("Found power supply {} ({:?})", supply.name, supply.kind);
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = BatterySource::new(supplies, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("power_supply", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the power supplies.
+    pub power_supply_path: PathBuf,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            power_supply_path: PathBuf::from("/sys/class/power_supply"),
+        }
+    }
+}
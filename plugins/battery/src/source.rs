@@ -0,0 +1,121 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+
+use crate::supply::{PowerSupply, SupplyKind};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    voltage: TypedMetricId<f64>,
+    current: TypedMetricId<f64>,
+    power: TypedMetricId<f64>,
+    charge: TypedMetricId<f64>,
+    energy: TypedMetricId<f64>,
+    capacity: TypedMetricId<u64>,
+    ac_online: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        let ampere_hour = Unit::Custom {
+            unique_name: "A.h".to_owned(),
+            display_name: "Ah".to_owned(),
+        };
+        Ok(Self {
+            voltage: alumet.create_metric("battery_voltage", Unit::Volt, "Voltage of the battery")?,
+            current: alumet.create_metric(
+                "battery_current",
+                Unit::Ampere,
+                "Current drawn from (or charging) the battery",
+            )?,
+            power: alumet.create_metric(
+                "battery_power",
+                Unit::Watt,
+                "Power drawn from (or charging) the battery",
+            )?,
+            charge: alumet.create_metric("battery_charge", ampere_hour, "Charge remaining in the battery")?,
+            energy: alumet.create_metric("battery_energy", Unit::WattHour, "Energy remaining in the battery")?,
+            capacity: alumet.create_metric(
+                "battery_capacity",
+                Unit::Percent,
+                "Charge level of the battery, relative to its full capacity",
+            )?,
+            ac_online: alumet.create_metric(
+                "ac_online",
+                Unit::Unity,
+                "1 if the AC adapter is plugged in, 0 otherwise",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the state of the batteries and AC adapters.
+pub struct BatterySource {
+    supplies: Vec<PowerSupply>,
+    metrics: Metrics,
+}
+
+impl BatterySource {
+    pub fn new(supplies: Vec<PowerSupply>, metrics: Metrics) -> Self {
+        Self { supplies, metrics }
+    }
+}
+
+impl Source for BatterySource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for supply in &self.supplies {
+            let point = |metric, value| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("supply", supply.name.clone())
+            };
+            match supply.kind {
+                SupplyKind::Mains => {
+                    if let Some(online) = supply.read_online() {
+                        measurements.push(point(self.metrics.ac_online, u64::from(online)));
+                    }
+                }
+                SupplyKind::Battery => {
+                    let state = supply.read_battery();
+                    let status = state.status.unwrap_or_else(|| String::from("Unknown"));
+                    let values = [
+                        (self.metrics.voltage, state.voltage),
+                        (self.metrics.current, state.current),
+                        (self.metrics.power, state.power),
+                        (self.metrics.charge, state.charge),
+                        (self.metrics.energy, state.energy),
+                    ];
+                    for (metric, value) in values {
+                        if let Some(value) = value {
+                            measurements.push(
+                                MeasurementPoint::new(
+                                    timestamp,
+                                    metric,
+                                    Resource::LocalMachine,
+                                    ResourceConsumer::LocalMachine,
+                                    value,
+                                )
+                                .with_attr("supply", supply.name.clone())
+                                .with_attr("status", status.clone()),
+                            );
+                        }
+                    }
+                    if let Some(capacity) = state.capacity {
+                        measurements.push(point(self.metrics.capacity, capacity).with_attr("status", status));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+//! Discovery and reading of the power supplies, such as batteries and AC adapters.
+//!
+//! See the [kernel documentation](https://www.kernel.org/doc/html/latest/power/power_supply_class.html).
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+/// Kind of power supply, given by the `type` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SupplyKind {
+    Battery,
+    /// AC adapter.
+    Mains,
+}
+
+/// A power supply, such as `/sys/class/power_supply/BAT0`.
+#[derive(Debug)]
+pub struct PowerSupply {
+    /// Name of the supply, like `BAT0` or `AC`.
+    pub name: String,
+    pub kind: SupplyKind,
+    path: PathBuf,
+}
+
+/// State of a battery, in the base units (V, A, W, Ah, Wh, %).
+///
+/// The batteries do not provide all the values: some report the current and the charge,
+/// others report the power and the energy.
+#[derive(Debug, Default, PartialEq)]
+pub struct BatteryState {
+    /// `Charging`, `Discharging`, `Full`, `Not charging` or `Unknown`.
+    pub status: Option<String>,
+    pub voltage: Option<f64>,
+    pub current: Option<f64>,
+    pub power: Option<f64>,
+    pub charge: Option<f64>,
+    pub energy: Option<f64>,
+    pub capacity: Option<u64>,
+}
+
+impl PowerSupply {
+    /// Analyzes the power supply at `path`.
+    ///
+    /// Returns `None` if the supply is neither a battery nor an AC adapter (e.g. a USB port or a peripheral).
+    pub fn at_sysfs(path: &Path) -> anyhow::Result<Option<Self>> {
+        let type_file = path.join("type");
+        let kind = std::fs::read_to_string(&type_file).with_context(|| format!("failed to read {type_file:?}"))?;
+        let kind = match kind.trim_ascii_end() {
+            "Battery" => SupplyKind::Battery,
+            "Mains" => SupplyKind::Mains,
+            _ => return Ok(None),
+        };
+        // the batteries of the peripherals (mouse, keyboard…) have scope "Device"
+        if kind == SupplyKind::Battery && read_string(path, "scope").as_deref() == Some("Device") {
+            return Ok(None);
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        Ok(Some(Self {
+            name,
+            kind,
+            path: path.to_path_buf(),
+        }))
+    }
+
+    /// Reads whether the AC adapter is plugged in.
+    pub fn read_online(&self) -> Option<bool> {
+        read_int(&self.path, "online").map(|v| v != 0)
+    }
+
+    /// Reads the state of the battery.
+    pub fn read_battery(&self) -> BatteryState {
+        // the values are in µV, µA, µW, µAh and µWh
+        let micro = |file| read_int(&self.path, file).map(|v| v as f64 * 1e-6);
+        let voltage = micro("voltage_now");
+        // Some drivers report a negative current (or power) when discharging: only the magnitude is kept.
+        let current = micro("current_now").map(f64::abs);
+        let power = micro("power_now").map(f64::abs).or_else(|| Some(voltage? * current?));
+        BatteryState {
+            status: read_string(&self.path, "status"),
+            voltage,
+            current,
+            power,
+            charge: micro("charge_now"),
+            energy: micro("energy_now"),
+            capacity: read_int(&self.path, "capacity").and_then(|v| u64::try_from(v).ok()),
+        }
+    }
+}
+
+fn read_string(dir: &Path, file: &str) -> Option<String> {
+    // missing files are normal, and some files return an error when the value is not available (e.g. ENODEV)
+    std::fs::read_to_string(dir.join(file))
+        .ok()
+        .map(|s| s.trim_ascii_end().to_owned())
+}
+
+fn read_int(dir: &Path, file: &str) -> Option<i64> {
+    read_string(dir, file)?.parse().ok()
+}
+
+/// Explores the power supplies of the machine.
+///
+/// ## Expected file layout
+///
+/// ```txt
+/// /sys/class/power_supply/
+/// |− AC
+///     |− type (Mains)
+///     |− online
+/// |− BAT0
+///     |− type (Battery)
+///     |− status
+///     |− voltage_now
+///     |− current_now
+///     |− …
+/// ```
+pub fn explore(power_supply_path: &Path) -> anyhow::Result<Vec<PowerSupply>> {
+    let mut supplies = Vec::new();
+    for entry in
+        std::fs::read_dir(power_supply_path).with_context(|| format!("failed to read dir {power_supply_path:?}"))?
+    {
+        let path = entry?.path();
+        match PowerSupply::at_sysfs(&path) {
+            Ok(Some(supply)) => supplies.push(supply),
+            Ok(None) => log::debug!("ignoring power supply {path:?}"),
+            Err(e) => log::warn!("failed to analyze power supply {path:?}: {e:#}"),
+        }
+    }
+    supplies.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(supplies)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn write_supply(root: &Path, name: &str, files: &[(&str, &str)]) {
+        let path = root.join(name);
+        std::fs::create_dir(&path).unwrap();
+        for (file, content) in files {
+            std::fs::write(path.join(file), format!("{content}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn explore_supplies() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        write_supply(root.path(), "AC", &[("type", "Mains"), ("online", "0")]);
+        write_supply(
+            root.path(),
+            "BAT0",
+            &[
+                ("type", "Battery"),
+                ("status", "Discharging"),
+                ("voltage_now", "12000000"),
+                ("current_now", "-1500000"),
+                ("charge_now", "3000000"),
+                ("capacity", "60"),
+            ],
+        );
+        write_supply(root.path(), "BAT1", &[("type", "Battery"), ("power_now", "8000000")]);
+        write_supply(root.path(), "hid-mouse", &[("type", "Battery"), ("scope", "Device")]);
+        write_supply(root.path(), "ucsi-source-psy-1", &[("type", "USB")]);
+
+        let supplies = explore(root.path())?;
+        let names: Vec<_> = supplies.iter().map(|s| (s.name.as_str(), s.kind)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("AC", SupplyKind::Mains),
+                ("BAT0", SupplyKind::Battery),
+                ("BAT1", SupplyKind::Battery)
+            ]
+        );
+
+        assert_eq!(supplies[0].read_online(), Some(false));
+        assert_eq!(
+            supplies[1].read_battery(),
+            BatteryState {
+                status: Some(String::from("Discharging")),
+                voltage: Some(12.0),
+                current: Some(1.5),
+                power: Some(18.0),
+                charge: Some(3.0),
+                energy: None,
+                capacity: Some(60),
+            }
+        );
+        assert_eq!(
+            supplies[2].read_battery(),
+            BatteryState {
+                power: Some(8.0),
+                ..Default::default()
+            }
+        );
+        Ok(())
+    }
+}
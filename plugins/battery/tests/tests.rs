@@ -0,0 +1,95 @@
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_battery::{BatteryPlugin, Config};
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_battery() {
+    let root = tempdir().unwrap();
+    write_supply(root.path(), "AC", &[("type", "Mains"), ("online", "1")]);
+    let config = Config {
+        power_supply_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no battery)");
+}
+
+#[test]
+fn plugin_with_battery() {
+    let root = tempdir().unwrap();
+    write_supply(root.path(), "AC", &[("type", "Mains"), ("online", "0")]);
+    write_supply(
+        root.path(),
+        "BAT0",
+        &[
+            ("type", "Battery"),
+            ("status", "Discharging"),
+            ("voltage_now", "11500000"),
+            ("power_now", "9200000"),
+            ("energy_now", "40000000"),
+            ("capacity", "80"),
+        ],
+    );
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        power_supply_path: root.path().to_path_buf(),
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<f64>("battery_power", Unit::Watt)
+        .expect_metric::<u64>("ac_online", Unit::Unity)
+        .expect_source("battery", "power_supply");
+
+    let runtime = RuntimeExpectations::new().test_source(
+        SourceName::from_str("battery", "power_supply"),
+        || {},
+        |ctx| {
+            let m = ctx.measurements();
+            let value_of = |name: &str| {
+                let metric = ctx.metrics().by_name(name).unwrap().0;
+                m.iter().find(|p| p.metric == metric).map(|p| p.value.clone())
+            };
+            assert_eq!(value_of("ac_online").unwrap().as_u64(), 0);
+            assert_eq!(value_of("battery_power").unwrap().as_f64(), 9.2);
+            assert_eq!(value_of("battery_energy").unwrap().as_f64(), 40.0);
+            assert_eq!(value_of("battery_capacity").unwrap().as_u64(), 80);
+            assert!(value_of("battery_current").is_none());
+        },
+    );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn write_supply(root: &Path, name: &str, files: &[(&str, &str)]) {
+    let path = root.join(name);
+    std::fs::create_dir_all(&path).unwrap();
+    for (file, content) in files {
+        std::fs::write(path.join(file), content).unwrap();
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<BatteryPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
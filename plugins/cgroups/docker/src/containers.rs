@@ -0,0 +1,314 @@
+use std::{
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alumet::measurement::AttributeValue;
+use anyhow::{Context, anyhow};
+use rustc_hash::FxHashMap;
+
+/// Maximum time to wait for the Docker daemon.
+const API_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Docker Engine API client (limited capabilities, just what we need).
+///
+/// The API is served over a Unix socket. We send HTTP/1.0 requests, so that the daemon
+/// closes the connection after the response and does not use the chunked encoding.
+#[derive(Clone)]
+pub struct DockerClient {
+    socket: PathBuf,
+}
+
+/// Relevant informations about a container.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ContainerInfos {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+}
+
+/// The container engine that created a cgroup, deduced from the cgroup's path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Runtime {
+    Docker,
+    Containerd,
+}
+
+/// Automatically-refreshed container registry: keep track of the containers managed by Docker.
+#[derive(Clone)]
+pub struct ContainerRegistry {
+    client: Option<DockerClient>,
+    containers: FxHashMap<String, ContainerInfos>,
+}
+
+/// Encoding/decoding of the Docker API responses.
+/// Fields that we don't need are not included, serde will skip them.
+mod api {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    pub struct ContainerSummary {
+        pub id: String,
+        pub names: Vec<String>,
+        pub image: String,
+    }
+}
+
+impl From<api::ContainerSummary> for ContainerInfos {
+    fn from(c: api::ContainerSummary) -> Self {
+        // Docker prefixes the names with a slash, like "/my-container"
+        let name = c
+            .names
+            .first()
+            .map(|n| n.trim_start_matches('/').to_owned())
+            .unwrap_or_default();
+        ContainerInfos {
+            id: c.id,
+            name,
+            image: c.image,
+        }
+    }
+}
+
+impl Runtime {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Runtime::Docker => "docker",
+            Runtime::Containerd => "containerd",
+        }
+    }
+}
+
+impl DockerClient {
+    pub fn new(socket: PathBuf) -> Self {
+        Self { socket }
+    }
+
+    /// Lists the running containers.
+    pub fn list_containers(&self) -> anyhow::Result<impl Iterator<Item = ContainerInfos>> {
+        let body = self.get("/containers/json")?;
+        let containers: Vec<api::ContainerSummary> =
+            serde_json::from_slice(&body).context("failed to parse json response")?;
+        Ok(containers.into_iter().map(ContainerInfos::from))
+    }
+
+    /// Sends a GET request and returns the body of the response.
+    fn get(&self, route: &str) -> anyhow::Result<Vec<u8>> {
+        let mut stream = UnixStream::connect(&self.socket)
+            .with_context(|| format!("failed to connect to {}", self.socket.display()))?;
+        stream.set_read_timeout(Some(API_TIMEOUT))?;
+        stream.set_write_timeout(Some(API_TIMEOUT))?;
+        write!(stream, "GET {route} HTTP/1.0\r\nHost: docker\r\n\r\n").context("failed to send http request")?;
+
+        let mut response = Vec::new();
+        stream
+            .read_to_end(&mut response)
+            .context("failed to read http response")?;
+        parse_response(response)
+    }
+}
+
+/// Checks the status of an HTTP response and returns its body.
+fn parse_response(mut response: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let header_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| anyhow!("incomplete http response"))?;
+    let headers = std::str::from_utf8(&response[..header_end]).context("invalid http headers")?;
+    let status_line = headers.lines().next().unwrap_or_default();
+    let status = status_line.split(' ').nth(1);
+    if status != Some("200") {
+        return Err(anyhow!("unexpected http response: {status_line}"));
+    }
+    Ok(response.split_off(header_end + 4))
+}
+
+impl ContainerRegistry {
+    pub fn new(client: Option<DockerClient>) -> Self {
+        Self {
+            client,
+            containers: Default::default(),
+        }
+    }
+
+    pub fn refresh(&mut self) -> anyhow::Result<()> {
+        if let Some(client) = &self.client {
+            let containers = client
+                .list_containers()
+                .context("failed to list the Docker containers")?;
+            self.containers = containers.map(|c| (c.id.clone(), c)).collect();
+        }
+        Ok(())
+    }
+
+    pub fn get(&mut self, container_id: &str) -> anyhow::Result<Option<ContainerInfos>> {
+        if let Some(infos) = self.containers.get(container_id) {
+            return Ok(Some(infos.to_owned()));
+        }
+
+        // We have no info about this container, ask the Docker API.
+        self.refresh()?;
+
+        // Is the container here? If not, it is not managed by Docker, or it has been deleted in the meantime.
+        Ok(self.containers.get(container_id).cloned())
+    }
+
+    /// Returns the attributes of the container that corresponds to the given cgroup.
+    ///
+    /// Returns an empty vec if the cgroup is not a container.
+    pub fn attributes_for_cgroup(&mut self, cgroup_fs_path: &Path) -> Vec<(String, AttributeValue)> {
+        let Some((runtime, container_id)) = extract_container_id_from_cgroup(cgroup_fs_path) else {
+            return Vec::new();
+        };
+        let mut attrs = vec![
+            ("container_id".into(), AttributeValue::String(container_id.clone())),
+            ("runtime".into(), AttributeValue::String(runtime.as_str().into())),
+        ];
+        let infos = self
+            .get(&container_id)
+            .inspect_err(|e| log::error!("failed to get infos for container {container_id}: {e:#}"))
+            .ok()
+            .flatten();
+        if let Some(infos) = infos {
+            attrs.push(("container_name".into(), AttributeValue::String(infos.name)));
+            attrs.push(("image".into(), AttributeValue::String(infos.image)));
+        }
+        attrs
+    }
+}
+
+/// Extracts the container id from a cgroup path (in the sysfs).
+///
+/// # Expected format
+///
+/// The supported formats are:
+/// - `…/docker-{container_id}.scope` (Docker with the systemd cgroup driver)
+/// - `…/docker/{container_id}` (Docker with the cgroupfs cgroup driver)
+/// - `…/cri-containerd-{container_id}.scope` and `…/nerdctl-{container_id}.scope` (containerd with the systemd cgroup driver)
+/// - `…/{namespace}/{container_id}` (containerd with the cgroupfs cgroup driver)
+///
+/// The container id is made of 64 hexadecimal characters.
+pub fn extract_container_id_from_cgroup(cgroup_fs_path: &Path) -> Option<(Runtime, String)> {
+    let cgroup_name = cgroup_fs_path.file_name()?.to_str()?;
+    let (runtime, id) = match cgroup_name.strip_suffix(".scope") {
+        Some(scope) => {
+            let (prefix, id) = scope.rsplit_once('-')?;
+            let runtime = match prefix {
+                "docker" => Runtime::Docker,
+                "cri-containerd" | "nerdctl" => Runtime::Containerd,
+                _ => return None,
+            };
+            (runtime, id)
+        }
+        None => {
+            let parent = cgroup_fs_path.parent()?.file_name()?;
+            let runtime = if parent == "docker" {
+                Runtime::Docker
+            } else {
+                Runtime::Containerd
+            };
+            (runtime, cgroup_name)
+        }
+    };
+    if id.len() != 64 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((runtime, id.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::BufRead, io::BufReader, os::unix::net::UnixListener, path::Path};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const ID: &str = "3f1c9b1f6f3e2a2c41e2b8b0a0c5e4d1f2a3b4c5d6e7f8091a2b3c4d5e6f7a8b";
+
+    #[test]
+    fn container_id_from_cgroup() {
+        let extract = |path: &str| extract_container_id_from_cgroup(Path::new(path));
+        let docker = Some((Runtime::Docker, ID.to_owned()));
+        let containerd = Some((Runtime::Containerd, ID.to_owned()));
+
+        assert_eq!(
+            extract(&format!("/sys/fs/cgroup/system.slice/docker-{ID}.scope")),
+            docker
+        );
+        assert_eq!(extract(&format!("/sys/fs/cgroup/docker/{ID}")), docker);
+        assert_eq!(
+            extract(&format!("/sys/fs/cgroup/system.slice/nerdctl-{ID}.scope")),
+            containerd
+        );
+        assert_eq!(extract(&format!("/sys/fs/cgroup/default/{ID}")), containerd);
+        assert_eq!(
+            extract(&format!(
+                "/sys/fs/cgroup/kubepods.slice/kubepods-pod1234.slice/cri-containerd-{ID}.scope"
+            )),
+            containerd
+        );
+
+        assert_eq!(extract("/sys/fs/cgroup/system.slice/docker.service"), None);
+        assert_eq!(extract("/sys/fs/cgroup/system.slice/session-2.scope"), None);
+        assert_eq!(
+            extract(&format!("/sys/fs/cgroup/system.slice/docker-{}.scope", &ID[..12])),
+            None
+        );
+        assert_eq!(
+            extract(&format!("/sys/fs/cgroup/docker/{}", ID.replace('a', "z"))),
+            None
+        );
+    }
+
+    #[test]
+    fn list_containers() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let socket = dir.path().join("docker.sock");
+        let listener = UnixListener::bind(&socket)?;
+
+        // fake Docker daemon that answers one request
+        let daemon = std::thread::spawn(move || -> anyhow::Result<String> {
+            let (stream, _) = listener.accept()?;
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line)?;
+            let body = format!(r#"[{{"Id":"{ID}","Names":["/web"],"Image":"nginx:1.27","State":"running"}}]"#);
+            write!(
+                reader.get_mut(),
+                "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{body}"
+            )?;
+            Ok(request_line)
+        });
+
+        let mut registry = ContainerRegistry::new(Some(DockerClient::new(socket)));
+        let infos = registry.get(ID)?;
+        assert_eq!(
+            infos,
+            Some(ContainerInfos {
+                id: ID.to_owned(),
+                name: String::from("web"),
+                image: String::from("nginx:1.27"),
+            })
+        );
+        let request_line = daemon.join().unwrap()?;
+        assert_eq!(request_line, "GET /containers/json HTTP/1.0\r\n");
+
+        // cached: no new request
+        assert!(registry.get(ID)?.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn http_errors() {
+        let res = parse_response(b"HTTP/1.0 404 Not Found\r\n\r\n{}".to_vec());
+        assert!(res.is_err());
+        let res = parse_response(b"HTTP/1.0 200 OK\r\n".to_vec());
+        assert!(res.is_err());
+        let res = parse_response(b"HTTP/1.0 200 OK\r\n\r\n[]".to_vec()).unwrap();
+        assert_eq!(res, b"[]");
+    }
+}
@@ -0,0 +1,179 @@
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::rust::{AlumetPlugin, deserialize_config, serialize_config},
+    units::Unit,
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    containers::{ContainerRegistry, DockerClient},
+    network::{NetworkSource, SharedContainers},
+    source::{RemovalCallback, SourceSetup},
+};
+use util_cgroups_plugins::{
+    cgroup_events::{CgroupReactor, NoCallback, ReactorCallbacks, ReactorConfig},
+    metrics::Metrics,
+};
+
+mod containers;
+mod network;
+mod source;
+
+/// Gathers metrics about the containers managed by Docker or containerd.
+///
+/// The containers are detected through their cgroups, and Docker is asked for their name and image.
+pub struct DockerPlugin {
+    config: Config,
+    starting_state: Option<StartingState>,
+    reactor: Option<CgroupReactor>,
+}
+
+impl AlumetPlugin for DockerPlugin {
+    fn name() -> &'static str {
+        "docker"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn init(config: alumet::plugin::ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(Self {
+            config,
+            starting_state: None,
+            reactor: None,
+        }))
+    }
+
+    fn default_config() -> anyhow::Result<Option<alumet::plugin::ConfigTable>> {
+        Ok(Some(serialize_config(Config::default())?))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let metrics = Metrics::create(alumet)?;
+        let reactor_config = ReactorConfig::default();
+
+        // prepare the Docker link, if Docker is installed (otherwise, only containerd is supported)
+        let client = if self.config.docker_socket.exists() {
+            Some(DockerClient::new(self.config.docker_socket.clone()))
+        } else {
+            log::warn!(
+                "Docker socket {} not found, the names and images of the containers will not be available.",
+                self.config.docker_socket.display()
+            );
+            None
+        };
+        let mut container_registry = ContainerRegistry::new(client);
+        match container_registry.refresh() {
+            Ok(()) => log::info!("List of containers refreshed."),
+            Err(e) => log::warn!("failed to list containers with the Docker API, is the daemon running? {e:#}"),
+        }
+
+        // measure the network of the containers in a single source
+        let network = if self.config.network {
+            let metric = alumet.create_metric::<u64>(
+                "container_network_delta",
+                Unit::Byte,
+                "Number of bytes received or transmitted by the container since the previous measurement",
+            )?;
+            let containers = SharedContainers::default();
+            let source = NetworkSource {
+                containers: containers.clone(),
+                metric,
+            };
+            let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+            alumet.add_source("network", Box::new(source), trigger)?;
+            Some(containers)
+        } else {
+            None
+        };
+
+        // store the state for later, because we cannot set up everything now
+        let starting_state = StartingState {
+            metrics,
+            reactor_config,
+            container_registry,
+            network,
+        };
+        self.starting_state = Some(starting_state);
+        Ok(())
+    }
+
+    fn post_pipeline_start(&mut self, alumet: &mut alumet::plugin::AlumetPostStart) -> anyhow::Result<()> {
+        // continue from the state that has been prepared in `start`
+        let s = self.starting_state.take().unwrap();
+
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        let probe_setup = SourceSetup {
+            trigger,
+            containers: s.container_registry,
+            network: s.network.clone(),
+        };
+
+        let reactor = CgroupReactor::new(
+            s.reactor_config,
+            s.metrics,
+            ReactorCallbacks {
+                probe_setup,
+                on_removal: RemovalCallback { network: s.network },
+                on_fs_mount: NoCallback,
+            },
+            alumet.pipeline_control(),
+        )
+        .context("failed to init CgroupReactor")?;
+
+        self.reactor = Some(reactor);
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        drop(self.reactor.take().unwrap());
+        Ok(())
+    }
+}
+
+struct StartingState {
+    metrics: Metrics,
+    reactor_config: ReactorConfig,
+    container_registry: ContainerRegistry,
+    network: Option<SharedContainers>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Config {
+    /// Path to the Unix socket of the Docker Engine API.
+    #[serde(default = "default_docker_socket")]
+    pub docker_socket: PathBuf,
+
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// If `true`, measures the network traffic of the containers.
+    #[serde(default = "default_true")]
+    pub network: bool,
+}
+
+#[cfg_attr(tarpaulin, ignore)]
+fn default_docker_socket() -> PathBuf {
+    PathBuf::from("/var/run/docker.sock")
+}
+
+#[cfg_attr(tarpaulin, ignore)]
+fn default_true() -> bool {
+    true
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            docker_socket: default_docker_socket(),
+            poll_interval: Duration::from_secs(5),
+            network: true,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use alumet::{
+    measurement::{AttributeValue, MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    resources::{Resource, ResourceConsumer},
+};
+use procfs::process::Process;
+use rustc_hash::FxHashMap;
+
+/// The containers whose network is measured, by cgroup path.
+///
+/// Shared between the cgroup callbacks, which add and remove containers, and the [`NetworkSource`].
+#[derive(Clone, Default)]
+pub struct SharedContainers(Arc<Mutex<FxHashMap<String, NetworkTarget>>>);
+
+/// A container whose network is measured.
+pub struct NetworkTarget {
+    /// Path to the container's cgroup in the sysfs.
+    cgroup_fs_path: PathBuf,
+    consumer: ResourceConsumer,
+    attributes: Vec<(String, AttributeValue)>,
+    /// Previous counters of each network interface, in bytes: `(received, transmitted)`.
+    previous: FxHashMap<String, (u64, u64)>,
+}
+
+impl SharedContainers {
+    pub fn insert(&self, cgroup_path: String, cgroup_fs_path: PathBuf, attributes: Vec<(String, AttributeValue)>) {
+        let target = NetworkTarget {
+            cgroup_fs_path,
+            consumer: ResourceConsumer::ControlGroup {
+                path: cgroup_path.clone().into(),
+            },
+            attributes,
+            previous: FxHashMap::default(),
+        };
+        self.0.lock().unwrap().insert(cgroup_path, target);
+    }
+
+    pub fn remove(&self, cgroup_path: &str) {
+        self.0.lock().unwrap().remove(cgroup_path);
+    }
+}
+
+/// Measures the network traffic of the containers.
+///
+/// The cgroups do not provide any network statistic: we read them in the network namespace
+/// of one of the container's processes, which is shared by all the processes of the container.
+pub struct NetworkSource {
+    pub containers: SharedContainers,
+    pub metric: TypedMetricId<u64>,
+}
+
+impl NetworkTarget {
+    /// Returns one of the processes of the container, if any.
+    fn any_process(&self) -> Option<Process> {
+        let procs = std::fs::read_to_string(self.cgroup_fs_path.join("cgroup.procs")).ok()?;
+        let pid = procs.lines().next()?.parse().ok()?;
+        Process::new(pid).ok()
+    }
+}
+
+impl Source for NetworkSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, t: Timestamp) -> Result<(), PollError> {
+        let mut containers = self.containers.0.lock().unwrap();
+        for target in containers.values_mut() {
+            // The container may have no process (yet), or its process may have just exited: skip it for now.
+            let Some(devices) = target.any_process().and_then(|p| p.dev_status().ok()) else {
+                continue;
+            };
+            for (interface, dev) in devices {
+                if interface == "lo" {
+                    continue;
+                }
+                let counters = (dev.recv_bytes, dev.sent_bytes);
+                // the counters are reset when the interface is recreated, skip the measurement in that case
+                if let Some((prev_recv, prev_sent)) = target.previous.insert(interface.clone(), counters)
+                    && let (Some(recv), Some(sent)) = (
+                        dev.recv_bytes.checked_sub(prev_recv),
+                        dev.sent_bytes.checked_sub(prev_sent),
+                    )
+                {
+                    let point = |direction: &'static str, value: u64| {
+                        MeasurementPoint::new(t, self.metric, Resource::LocalMachine, target.consumer.clone(), value)
+                            .with_attr_slice(&target.attributes)
+                            .with_attr("direction", direction)
+                            .with_attr("interface", interface.clone())
+                    };
+                    measurements.push(point("rx", recv));
+                    measurements.push(point("tx", sent));
+                }
+            }
+        }
+        Ok(())
+    }
+}
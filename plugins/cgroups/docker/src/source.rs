@@ -0,0 +1,69 @@
+use alumet::pipeline::elements::source::trigger::TriggerSpec;
+
+use util_cgroups::Cgroup;
+use util_cgroups_plugins::{
+    cgroup_events::{CgroupRemovalCallback, CgroupSetupCallback, ProbeSetup, SourceSettings},
+    metrics::{AugmentedMetrics, Metrics},
+};
+
+use crate::{containers::ContainerRegistry, network::SharedContainers};
+
+#[derive(Clone)]
+pub struct SourceSetup {
+    pub trigger: TriggerSpec,
+    pub containers: ContainerRegistry,
+    /// If the network is measured, the containers to give to the network source.
+    pub network: Option<SharedContainers>,
+}
+
+impl CgroupSetupCallback for SourceSetup {
+    fn setup_new_probe(&mut self, cgroup: &Cgroup, metrics: &Metrics) -> Option<ProbeSetup> {
+        // Retrieves associated attributes
+        let attrs = self.containers.attributes_for_cgroup(cgroup.fs_path());
+
+        if attrs.is_empty() {
+            // If empty, this is NOT a container
+            return None;
+        }
+
+        if let Some(network) = &self.network {
+            network.insert(
+                cgroup.canonical_path().to_owned(),
+                cgroup.fs_path().to_owned(),
+                attrs.clone(),
+            );
+        }
+
+        let metrics = AugmentedMetrics::with_common_attr_vec(metrics, attrs);
+
+        // setup the trigger according to the plugin's config
+        let trigger = self.trigger.clone();
+
+        // use the cgroup's "file stem" as the source name (it contains the container id)
+        let name = cgroup.fs_path().file_stem().unwrap().to_str().unwrap().to_string();
+
+        // ready!
+        let source_settings = SourceSettings { name, trigger };
+        Some(ProbeSetup {
+            metrics,
+            source_settings,
+        })
+    }
+}
+
+/// Stops measuring the network of the containers that are removed.
+#[derive(Clone)]
+pub struct RemovalCallback {
+    pub network: Option<SharedContainers>,
+}
+
+impl CgroupRemovalCallback for RemovalCallback {
+    fn on_cgroups_removed(&mut self, cgroups: Vec<Cgroup>) -> anyhow::Result<()> {
+        if let Some(network) = &self.network {
+            for cgroup in cgroups {
+                network.remove(cgroup.canonical_path());
+            }
+        }
+        Ok(())
+    }
+}
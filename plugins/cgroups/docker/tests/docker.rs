@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use alumet::{
+    agent::{
+        self,
+        plugin::{PluginInfo, PluginSet},
+    },
+    plugin::PluginMetadata,
+    test::StartupExpectations,
+    units::{PrefixedUnit, Unit},
+};
+use plugin_docker::{Config, DockerPlugin};
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+#[test]
+fn plugin_without_docker() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_default_env().try_init();
+
+    // Docker is not installed: the plugin should still start, and measure the containerd containers
+    let tmp = tempfile::tempdir()?;
+    let config = Config {
+        docker_socket: tmp.path().join("docker.sock"),
+        poll_interval: Duration::from_secs(1),
+        ..Default::default()
+    };
+
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<DockerPlugin>(),
+        enabled: true,
+        config: Some(config_to_toml_table(&config)),
+    });
+
+    let startup_expectations = StartupExpectations::new()
+        .expect_metric::<u64>("memory_usage", Unit::Byte)
+        .expect_metric::<u64>("cpu_time_delta", PrefixedUnit::nano(Unit::Second))
+        .expect_metric::<u64>("container_network_delta", Unit::Byte)
+        .expect_source("docker", "network");
+
+    let agent = agent::Builder::new(plugins)
+        .with_expectations(startup_expectations)
+        .build_and_start()?;
+
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT)?;
+    Ok(())
+}
+
+fn config_to_toml_table(config: &Config) -> toml::Table {
+    toml::Value::try_from(config).unwrap().as_table().unwrap().to_owned()
+}
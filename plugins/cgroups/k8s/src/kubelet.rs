@@ -0,0 +1,353 @@
+use std::{mem, time::Duration};
+
+use alumet::{
+    measurement::{AttributeValue, MeasurementBuffer, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::{PrefixedUnit, Unit},
+};
+use anyhow::Context;
+use rustc_hash::FxHashMap;
+use tokio::{sync::mpsc, time::MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use super::token::Token;
+use api::Summary;
+
+/// Kubelet API client (limited capabilities, just what we need).
+pub struct KubeletClient {
+    client: reqwest::Client,
+    auth_token: Token,
+    stats_summary_route: String,
+}
+
+/// Contains the ids of the metrics measured with the kubelet.
+#[derive(Clone, Copy)]
+pub struct KubeletMetrics {
+    cpu_time_delta: TypedMetricId<u64>,
+    memory_working_set: TypedMetricId<u64>,
+    memory_rss: TypedMetricId<u64>,
+    network_delta: TypedMetricId<u64>,
+}
+
+/// Encoding/decoding of the kubelet API responses.
+/// Fields that we don't need are not included, serde will skip them.
+mod api {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub struct Summary {
+        pub node: NodeStats,
+        #[serde(default)]
+        pub pods: Vec<PodStats>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct NodeStats {
+        pub node_name: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct PodStats {
+        pub pod_ref: PodReference,
+        pub cpu: Option<CpuStats>,
+        pub memory: Option<MemoryStats>,
+        pub network: Option<NetworkStats>,
+        #[serde(default)]
+        pub containers: Vec<ContainerStats>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct PodReference {
+        pub name: String,
+        pub namespace: String,
+        pub uid: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct ContainerStats {
+        pub name: String,
+        pub cpu: Option<CpuStats>,
+        pub memory: Option<MemoryStats>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct CpuStats {
+        /// Cumulative CPU time.
+        pub usage_core_nano_seconds: Option<u64>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct MemoryStats {
+        pub working_set_bytes: Option<u64>,
+        pub rss_bytes: Option<u64>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct NetworkStats {
+        /// Cumulative number of bytes received on the default interface.
+        pub rx_bytes: Option<u64>,
+        /// Cumulative number of bytes transmitted on the default interface.
+        pub tx_bytes: Option<u64>,
+    }
+}
+
+impl KubeletClient {
+    pub fn new(kubelet_url: &str, auth_token: Token) -> anyhow::Result<Self> {
+        // the kubelet usually has a self-signed certificate
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .context("failed to build http client")?;
+
+        let stats_summary_route = format!("{kubelet_url}/stats/summary");
+
+        Ok(Self {
+            auth_token,
+            client,
+            stats_summary_route,
+        })
+    }
+
+    pub async fn stats_summary(&self) -> anyhow::Result<Summary> {
+        // get the auth token, refreshed if needed
+        let token = self.auth_token.get_value().context("failed to get auth token")?;
+
+        // send and parse response
+        let response = self
+            .client
+            .get(&self.stats_summary_route)
+            .bearer_auth(token)
+            .send()
+            .await
+            .context("failed to send http request")?
+            .error_for_status()?;
+        log::trace!("response: {response:?}");
+        response.json().await.context("failed to parse json response")
+    }
+}
+
+impl KubeletMetrics {
+    pub fn create(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            cpu_time_delta: alumet.create_metric(
+                "pod_cpu_time_delta",
+                PrefixedUnit::nano(Unit::Second),
+                "Time spent by the pod (or container) on the CPU since the previous measurement",
+            )?,
+            memory_working_set: alumet.create_metric(
+                "pod_memory_working_set",
+                Unit::Byte,
+                "Memory used by the pod (or container) that cannot be reclaimed under pressure",
+            )?,
+            memory_rss: alumet.create_metric(
+                "pod_memory_rss",
+                Unit::Byte,
+                "Anonymous and swap cache memory used by the pod (or container)",
+            )?,
+            network_delta: alumet.create_metric(
+                "pod_network_delta",
+                Unit::Byte,
+                "Number of bytes received or transmitted by the pod since the previous measurement",
+            )?,
+        })
+    }
+}
+
+/// Measures the pods and their containers by polling the kubelet's `/stats/summary` endpoint,
+/// until `cancel_token` is cancelled.
+///
+/// Unlike the cgroup probes, this source does not need to access the cgroupfs of the node.
+pub async fn run(
+    client: KubeletClient,
+    metrics: KubeletMetrics,
+    poll_interval: Duration,
+    cancel_token: CancellationToken,
+    tx: mpsc::Sender<MeasurementBuffer>,
+) -> anyhow::Result<()> {
+    let mut counters = Counters::default();
+    let mut interval = tokio::time::interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            biased;
+            _ = cancel_token.cancelled() => break,
+            _ = interval.tick() => (),
+        };
+        // The kubelet may be temporarily unavailable: try again at the next measurement.
+        let summary = match client.stats_summary().await {
+            Ok(summary) => summary,
+            Err(e) => {
+                log::warn!("failed to get the stats summary from the kubelet: {e:#}");
+                continue;
+            }
+        };
+        let buffer = convert(summary, &metrics, &mut counters, Timestamp::now());
+        if !buffer.is_empty() {
+            tx.send(buffer).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Turns the statistics of the pods into measurement points.
+fn convert(summary: Summary, metrics: &KubeletMetrics, counters: &mut Counters, t: Timestamp) -> MeasurementBuffer {
+    let mut measurements = MeasurementBuffer::new();
+    let node = summary.node.node_name;
+    for pod in summary.pods {
+        // same attributes as the cgroup probes and the annotation transform
+        let pod_attrs: Vec<(String, AttributeValue)> = vec![
+            ("uid".into(), AttributeValue::String(pod.pod_ref.uid.clone())),
+            ("name".into(), AttributeValue::String(pod.pod_ref.name)),
+            ("namespace".into(), AttributeValue::String(pod.pod_ref.namespace)),
+            ("node".into(), AttributeValue::String(node.clone())),
+        ];
+        let consumer = ResourceConsumer::Custom {
+            kind: "pod".into(),
+            id: pod.pod_ref.uid.clone().into(),
+        };
+        let point = |metric, value, container: Option<&str>| {
+            let point = MeasurementPoint::new(t, metric, Resource::LocalMachine, consumer.clone(), value)
+                .with_attr_slice(&pod_attrs);
+            match container {
+                Some(name) => point.with_attr("container", name.to_owned()),
+                None => point,
+            }
+        };
+        let uid = &pod.pod_ref.uid;
+
+        // pod-level statistics
+        let cpu_time = pod.cpu.and_then(|cpu| cpu.usage_core_nano_seconds);
+        if let Some(delta) = cpu_time.and_then(|v| counters.update(uid, "", "cpu", v)) {
+            measurements.push(point(metrics.cpu_time_delta, delta, None));
+        }
+        if let Some(memory) = pod.memory {
+            if let Some(v) = memory.working_set_bytes {
+                measurements.push(point(metrics.memory_working_set, v, None));
+            }
+            if let Some(v) = memory.rss_bytes {
+                measurements.push(point(metrics.memory_rss, v, None));
+            }
+        }
+        if let Some(network) = pod.network {
+            let directions = [("rx", network.rx_bytes), ("tx", network.tx_bytes)];
+            for (direction, value) in directions {
+                if let Some(delta) = value.and_then(|v| counters.update(uid, "", direction, v)) {
+                    measurements.push(point(metrics.network_delta, delta, None).with_attr("direction", direction));
+                }
+            }
+        }
+
+        // container-level statistics
+        for container in pod.containers {
+            let name = Some(container.name.as_str());
+            let cpu_time = container.cpu.and_then(|cpu| cpu.usage_core_nano_seconds);
+            if let Some(delta) = cpu_time.and_then(|v| counters.update(uid, &container.name, "cpu", v)) {
+                measurements.push(point(metrics.cpu_time_delta, delta, name));
+            }
+            if let Some(memory) = container.memory {
+                if let Some(v) = memory.working_set_bytes {
+                    measurements.push(point(metrics.memory_working_set, v, name));
+                }
+                if let Some(v) = memory.rss_bytes {
+                    measurements.push(point(metrics.memory_rss, v, name));
+                }
+            }
+        }
+    }
+    // forget the pods and containers that no longer exist
+    counters.finish_round();
+    measurements
+}
+
+/// Computes the difference between the successive values of the cumulative counters.
+#[derive(Default)]
+struct Counters {
+    /// Values of the previous round, by `(pod uid, container name, counter name)`.
+    previous: FxHashMap<(String, String, &'static str), u64>,
+    /// Values of the current round.
+    current: FxHashMap<(String, String, &'static str), u64>,
+}
+
+impl Counters {
+    /// Stores the new value of a counter and returns its increment, if there is a previous value.
+    ///
+    /// Returns `None` if the counter has been reset (for instance, when a container restarts).
+    fn update(&mut self, pod_uid: &str, container: &str, counter: &'static str, value: u64) -> Option<u64> {
+        let key = (pod_uid.to_owned(), container.to_owned(), counter);
+        let delta = self.previous.get(&key).and_then(|prev| value.checked_sub(*prev));
+        self.current.insert(key, value);
+        delta
+    }
+
+    /// Ends a measurement round: the counters that have not been updated are forgotten.
+    fn finish_round(&mut self) {
+        self.previous = mem::take(&mut self.current);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Counters, api::Summary};
+
+    #[test]
+    fn parse_summary() {
+        let json = r#"{
+            "node": {"nodeName": "node-1", "cpu": {"usageCoreNanoSeconds": 999}},
+            "pods": [
+                {
+                    "podRef": {"name": "web", "namespace": "default", "uid": "00b506dc-87ee-462c-880d-3e41d0dacd0c"},
+                    "startTime": "2025-06-01T12:00:00Z",
+                    "cpu": {"time": "2025-06-01T12:10:00Z", "usageNanoCores": 1200, "usageCoreNanoSeconds": 5000},
+                    "memory": {"time": "2025-06-01T12:10:00Z", "workingSetBytes": 2048, "rssBytes": 1024},
+                    "network": {"name": "eth0", "rxBytes": 10, "txBytes": 20, "interfaces": []},
+                    "containers": [
+                        {"name": "nginx", "cpu": {"usageCoreNanoSeconds": 4000}, "memory": {"workingSetBytes": 1536}}
+                    ]
+                },
+                {
+                    "podRef": {"name": "starting", "namespace": "default", "uid": "11b506dc-87ee-462c-880d-3e41d0dacd0c"}
+                }
+            ]
+        }"#;
+        let summary: Summary = serde_json::from_str(json).unwrap();
+        assert_eq!(summary.node.node_name, "node-1");
+        assert_eq!(summary.pods.len(), 2);
+
+        let pod = &summary.pods[0];
+        assert_eq!(pod.pod_ref.name, "web");
+        assert_eq!(pod.cpu.as_ref().unwrap().usage_core_nano_seconds, Some(5000));
+        assert_eq!(pod.memory.as_ref().unwrap().rss_bytes, Some(1024));
+        assert_eq!(pod.network.as_ref().unwrap().tx_bytes, Some(20));
+        assert_eq!(pod.containers[0].name, "nginx");
+        assert_eq!(pod.containers[0].memory.as_ref().unwrap().rss_bytes, None);
+
+        let pod = &summary.pods[1];
+        assert!(pod.cpu.is_none());
+        assert!(pod.containers.is_empty());
+    }
+
+    #[test]
+    fn counters() {
+        let mut counters = Counters::default();
+        assert_eq!(counters.update("pod", "", "cpu", 100), None);
+        assert_eq!(counters.update("pod", "c", "cpu", 50), None);
+        counters.finish_round();
+
+        assert_eq!(counters.update("pod", "", "cpu", 150), Some(50));
+        // the container has restarted
+        assert_eq!(counters.update("pod", "c", "cpu", 10), None);
+        counters.finish_round();
+
+        // the container has been removed
+        counters.finish_round();
+        assert_eq!(counters.update("pod", "c", "cpu", 20), None);
+    }
+}
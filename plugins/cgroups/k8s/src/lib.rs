@@ -8,6 +8,7 @@ use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    kubelet::{KubeletClient, KubeletMetrics},
     pods::{ApiClient, AutoNodePodRegistry},
     token::{Token, TokenRetrievalConfig},
 };
@@ -20,6 +21,7 @@ use util_cgroups_plugins::{
     metrics::Metrics,
 };
 
+mod kubelet;
 mod pods;
 mod source;
 mod token;
@@ -52,14 +54,68 @@ impl AlumetPlugin for K8sPlugin {
         Ok(Some(serialize_config(Config::default())?))
     }
 
+    fn config_schema() -> anyhow::Result<Option<serde_json::Value>> {
+        let default_config = serialize_config(Config::default())?;
+        let mut schema = alumet::plugin::schema::infer_schema(&default_config);
+        // The token retrieval method is either a string, like "auto", or a table, like `{ file = "/path/to/token" }`.
+        schema["properties"]["token_retrieval"] = serde_json::json!({
+            "anyOf": [
+                { "enum": ["auto", "file", "kubectl"] },
+                {
+                    "type": "object",
+                    "properties": { "file": { "type": "string" } },
+                    "required": ["file"],
+                    "additionalProperties": false
+                },
+                {
+                    "type": "object",
+                    "properties": {
+                        "kubectl": {
+                            "type": "object",
+                            "properties": {
+                                "service_account": { "type": "string" },
+                                "namespace": { "type": "string" }
+                            }
+                        }
+                    },
+                    "required": ["kubectl"],
+                    "additionalProperties": false
+                }
+            ]
+        });
+        Ok(Some(schema))
+    }
+
     fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let api_token = Token::new(self.config.token_retrieval.clone().into());
+
+        // In kubelet mode, a single source measures all the pods and we don't need the cgroups.
+        if self.config.mode == Mode::Kubelet {
+            let metrics = KubeletMetrics::create(alumet)?;
+            let client = KubeletClient::new(&self.config.kubelet_url, api_token.clone())
+                .context("failed to create http client for communicating with the kubelet")?;
+            let poll_interval = self.config.poll_interval;
+            alumet.add_autonomous_source_builder("kubelet", move |_ctx, cancel_token, out_tx| {
+                Ok(Box::pin(kubelet::run(
+                    client,
+                    metrics,
+                    poll_interval,
+                    cancel_token,
+                    out_tx,
+                )))
+            })?;
+            if self.config.annotate_foreign_measurements {
+                log::warn!("annotate_foreign_measurements is only supported in cgroups mode, it will be ignored.");
+            }
+            return Ok(());
+        }
+
         let metrics = Metrics::create(alumet)?;
         let reactor_config = ReactorConfig::default();
         let mut shared_hierarchy = OptionalSharedHierarchy::default();
 
         // prepare K8S link and test it
         let node = self.config.k8s_node_name();
-        let api_token = Token::new(self.config.token_retrieval.clone().into());
         let api_client = ApiClient::new(&self.config.k8s_api_url, api_token)
             .context("failed to create http client for communicating with the K8S API")?;
         let mut pod_registry = AutoNodePodRegistry::new(node, api_client);
@@ -91,6 +147,11 @@ impl AlumetPlugin for K8sPlugin {
     }
 
     fn post_pipeline_start(&mut self, alumet: &mut alumet::plugin::AlumetPostStart) -> anyhow::Result<()> {
+        // In kubelet mode, the cgroups are not measured.
+        if self.config.mode == Mode::Kubelet {
+            return Ok(());
+        }
+
         // continue from the state that has been prepared in `start`
         let s = self.starting_state.take().unwrap();
 
@@ -117,7 +178,7 @@ impl AlumetPlugin for K8sPlugin {
     }
 
     fn stop(&mut self) -> anyhow::Result<()> {
-        drop(self.reactor.take().unwrap());
+        drop(self.reactor.take());
         Ok(())
     }
 }
@@ -137,6 +198,13 @@ pub struct Config {
     pub k8s_api_url: String,
     pub token_retrieval: TokenRetrievalConfig,
 
+    /// How to measure the pods.
+    #[serde(default)]
+    pub mode: Mode,
+    /// URL to the kubelet API, used in kubelet mode.
+    #[serde(default = "default_kubelet_url")]
+    pub kubelet_url: String,
+
     #[serde(with = "humantime_serde")]
     pub poll_interval: Duration,
     /// If `true`, adds attributes like `job_id` to the measurements produced by other plugins.
@@ -147,11 +215,27 @@ pub struct Config {
     pub annotate_foreign_measurements: bool,
 }
 
+/// How to measure the pods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    /// Read the cgroups of the pods on the node.
+    #[default]
+    Cgroups,
+    /// Poll the `/stats/summary` endpoint of the kubelet.
+    Kubelet,
+}
+
 #[cfg_attr(tarpaulin, ignore)]
 fn default_k8s_api_url() -> String {
     String::from("http://127.0.0.1:8080")
 }
 
+#[cfg_attr(tarpaulin, ignore)]
+fn default_kubelet_url() -> String {
+    String::from("https://127.0.0.1:10250")
+}
+
 impl Default for Config {
     #[cfg_attr(tarpaulin, ignore)]
     fn default() -> Self {
@@ -159,6 +243,8 @@ impl Default for Config {
             k8s_node: None,
             k8s_api_url: default_k8s_api_url(),
             token_retrieval: TokenRetrievalConfig::Simple(token::SimpleRetrievalMethod::Auto),
+            mode: Mode::default(),
+            kubelet_url: default_kubelet_url(),
             poll_interval: Duration::from_secs(5),
             annotate_foreign_measurements: false,
         }
@@ -176,3 +262,27 @@ impl Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alumet::plugin::{ConfigTable, rust::AlumetPlugin, schema};
+
+    use super::K8sPlugin;
+
+    #[test]
+    fn config_schema_accepts_token_retrieval_tables() {
+        let schema = K8sPlugin::config_schema().unwrap().unwrap();
+        let valid = [
+            "token_retrieval = \"auto\"",
+            "token_retrieval = { file = \"/path/to/token\" }",
+            "token_retrieval.kubectl = { service_account = \"alumet-reader\" }",
+        ];
+        for config in valid {
+            let config = ConfigTable(toml::from_str(config).unwrap());
+            schema::validate(&schema, &config, "plugins.k8s").unwrap();
+        }
+
+        let invalid = ConfigTable(toml::from_str("token_retrieval = { path = \"/path/to/token\" }").unwrap());
+        schema::validate(&schema, &invalid, "plugins.k8s").unwrap_err();
+    }
+}
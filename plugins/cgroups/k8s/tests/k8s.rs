@@ -10,6 +10,8 @@ use alumet::{
         naming::ElementKind,
     },
     plugin::PluginMetadata,
+    test::StartupExpectations,
+    units::{PrefixedUnit, Unit},
 };
 use anyhow::Context;
 use plugin_k8s::K8sPlugin;
@@ -111,6 +113,77 @@ fn test_k8s_cgroupv2() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_k8s_kubelet() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_default_env().try_init();
+
+    // find where to put the fake k8s token
+    let mut token_file = tempfile::NamedTempFile::new()?;
+    write!(&mut token_file, "{TOKEN_CONTENT}")?;
+    let token_file_path = token_file.path().to_str().unwrap();
+
+    // prepare fake kubelet api
+    let mut mock_server = mockito::Server::new();
+    let mock_server_url = mock_server.url();
+    let kubelet_mock = mock_server
+        .mock("GET", "/stats/summary")
+        .match_header("Authorization", format!("Bearer {TOKEN_CONTENT}").as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/json")
+        .with_body(
+            serde_json::json!({
+                "node": { "nodeName": POD_NODE },
+                "pods": [
+                    {
+                        "podRef": { "name": "pod1", "namespace": "default", "uid": POD_UID },
+                        "cpu": { "usageCoreNanoSeconds": 1000 },
+                        "memory": { "workingSetBytes": 4096, "rssBytes": 2048 },
+                        "containers": [ { "name": "app", "cpu": { "usageCoreNanoSeconds": 900 } } ]
+                    }
+                ]
+            })
+            .to_string(),
+        )
+        .expect_at_least(1)
+        .create();
+
+    // load plugins
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<K8sPlugin>(),
+        enabled: true,
+        config: Some(
+            toml::from_str(&format!(
+                r#"
+                    poll_interval = "100ms"
+                    mode = "kubelet"
+                    kubelet_url = "{mock_server_url}"
+                    token_retrieval.file = "{token_file_path}"
+                "#
+            ))
+            .unwrap(),
+        ),
+    });
+
+    // the cgroups are not needed in kubelet mode
+    let startup_expectations = StartupExpectations::new()
+        .expect_metric::<u64>("pod_cpu_time_delta", PrefixedUnit::nano(Unit::Second))
+        .expect_metric::<u64>("pod_memory_working_set", Unit::Byte)
+        .expect_source("k8s", "kubelet");
+    let agent = agent::Builder::new(plugins)
+        .with_expectations(startup_expectations)
+        .build_and_start()?;
+    std::thread::sleep(TOLERANCE);
+
+    // check that the kubelet has been polled
+    kubelet_mock.assert();
+
+    // stop the pipeline and wait for it to terminate
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT).context("error in shutdown")?;
+    Ok(())
+}
+
 fn mock_k8s_api_with_one_pod(server: &mut mockito::Server, expected_hits: usize) -> mockito::Mock {
     server
         .mock("GET", "/api/v1/pods?fieldSelector=spec.nodeName%3Dtest-node")
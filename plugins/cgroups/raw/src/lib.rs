@@ -5,9 +5,10 @@ use alumet::{
     plugin::rust::{AlumetPlugin, deserialize_config, serialize_config},
 };
 use anyhow::Context;
+use regex::RegexSet;
 use serde::{Deserialize, Serialize};
 
-use source::SourceSetup;
+use source::{CgroupFilter, SourceSetup};
 use util_cgroups_plugins::{
     cgroup_events::{CgroupReactor, NoCallback, ReactorCallbacks, ReactorConfig},
     metrics::Metrics,
@@ -21,6 +22,7 @@ mod source;
 /// It only interacts with the Linux control cgroups, [version 1](https://docs.kernel.org/admin-guide/cgroup-v1/cgroups.html) and/or [version 2](https://docs.kernel.org/admin-guide/cgroup-v2.html), depending on what is available on the system.
 pub struct RawCgroupPlugin {
     config: Config,
+    filter: CgroupFilter,
     starting_state: Option<StartingState>,
     reactor: Option<CgroupReactor>,
 }
@@ -35,9 +37,14 @@ impl AlumetPlugin for RawCgroupPlugin {
     }
 
     fn init(config: alumet::plugin::ConfigTable) -> anyhow::Result<Box<Self>> {
-        let config = deserialize_config(config)?;
+        let config: Config = deserialize_config(config)?;
+        let filter = CgroupFilter {
+            include: RegexSet::new(&config.include).context("invalid regex in `include`")?,
+            exclude: RegexSet::new(&config.exclude).context("invalid regex in `exclude`")?,
+        };
         Ok(Box::new(Self {
             config,
+            filter,
             starting_state: None,
             reactor: None,
         }))
@@ -62,6 +69,7 @@ impl AlumetPlugin for RawCgroupPlugin {
         let s = self.starting_state.take().unwrap();
         let probe_setup = SourceSetup {
             trigger: TriggerSpec::at_interval(self.config.poll_interval),
+            filter: self.filter.clone(),
         };
         let reactor = CgroupReactor::new(
             s.reactor_config,
@@ -93,6 +101,12 @@ struct StartingState {
 pub struct Config {
     #[serde(with = "humantime_serde")]
     pub poll_interval: Duration,
+    /// Regexes that select the cgroups to measure, by path. If empty, every cgroup is measured.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Regexes that exclude some cgroups from the measurement, by path.
+    #[serde(default)]
+    pub exclude: Vec<String>,
 }
 
 impl Default for Config {
@@ -100,6 +114,8 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             poll_interval: Duration::from_secs(5),
+            include: Vec::new(),
+            exclude: Vec::new(),
         }
     }
 }
@@ -1,5 +1,6 @@
 use alumet::pipeline::elements::source::trigger::TriggerSpec;
 
+use regex::RegexSet;
 use util_cgroups_plugins::{
     cgroup_events::{CgroupSetupCallback, ProbeSetup, SourceSettings},
     metrics::{AugmentedMetrics, Metrics},
@@ -8,6 +9,22 @@ use util_cgroups_plugins::{
 #[derive(Clone)]
 pub struct SourceSetup {
     pub trigger: TriggerSpec,
+    pub filter: CgroupFilter,
+}
+
+/// Selects the cgroups to measure, based on their path.
+#[derive(Clone)]
+pub struct CgroupFilter {
+    /// If not empty, only the cgroups that match one of these regexes are measured.
+    pub include: RegexSet,
+    /// The cgroups that match one of these regexes are not measured.
+    pub exclude: RegexSet,
+}
+
+impl CgroupFilter {
+    pub fn accepts(&self, cgroup_path: &str) -> bool {
+        (self.include.is_empty() || self.include.is_match(cgroup_path)) && !self.exclude.is_match(cgroup_path)
+    }
 }
 
 impl CgroupSetupCallback for SourceSetup {
@@ -16,6 +33,10 @@ impl CgroupSetupCallback for SourceSetup {
         cgroup: &util_cgroups::Cgroup,
         metrics: &Metrics,
     ) -> Option<util_cgroups_plugins::cgroup_events::ProbeSetup> {
+        if !self.filter.accepts(cgroup.canonical_path()) {
+            return None;
+        }
+
         // no custom attributes, this is the "raw" cgroup plugin :)
         let metrics = AugmentedMetrics::no_additional_attribute(metrics);
 
@@ -36,6 +36,7 @@ fn test_raw_cgroupv2() -> anyhow::Result<()> {
         enabled: true,
         config: Some(config_to_toml_table(&Config {
             poll_interval: Duration::from_secs(1),
+            ..Default::default()
         })),
     });
 
@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::rust::{AlumetPlugin, deserialize_config, serialize_config},
+};
+use anyhow::Context;
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+
+use source::{SourceSetup, UnitFilter};
+use util_cgroups_plugins::{
+    cgroup_events::{CgroupReactor, NoCallback, ReactorCallbacks, ReactorConfig},
+    metrics::Metrics,
+};
+
+mod source;
+mod units;
+
+/// Gathers metrics about the systemd units, such as the services.
+///
+/// systemd puts each unit in its own cgroup: this plugin measures the cgroups
+/// and adds the name of the unit to the measurements.
+pub struct SystemdPlugin {
+    config: Config,
+    filter: UnitFilter,
+    starting_state: Option<StartingState>,
+    reactor: Option<CgroupReactor>,
+}
+
+impl AlumetPlugin for SystemdPlugin {
+    fn name() -> &'static str {
+        "systemd"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn init(config: alumet::plugin::ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config: Config = deserialize_config(config)?;
+        let filter = UnitFilter {
+            unit_types: config.unit_types.clone(),
+            include: RegexSet::new(&config.include).context("invalid regex in `include`")?,
+            exclude: RegexSet::new(&config.exclude).context("invalid regex in `exclude`")?,
+        };
+        Ok(Box::new(Self {
+            config,
+            filter,
+            starting_state: None,
+            reactor: None,
+        }))
+    }
+
+    fn default_config() -> anyhow::Result<Option<alumet::plugin::ConfigTable>> {
+        Ok(Some(serialize_config(Config::default())?))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let metrics = Metrics::create(alumet)?;
+        let reactor_config = ReactorConfig::default();
+        let starting_state = StartingState {
+            metrics,
+            reactor_config,
+        };
+        self.starting_state = Some(starting_state);
+        Ok(())
+    }
+
+    fn post_pipeline_start(&mut self, alumet: &mut alumet::plugin::AlumetPostStart) -> anyhow::Result<()> {
+        let s = self.starting_state.take().unwrap();
+        let probe_setup = SourceSetup {
+            trigger: TriggerSpec::at_interval(self.config.poll_interval),
+            filter: self.filter.clone(),
+        };
+        let reactor = CgroupReactor::new(
+            s.reactor_config,
+            s.metrics,
+            ReactorCallbacks {
+                probe_setup,
+                on_removal: NoCallback,
+                on_fs_mount: NoCallback,
+            },
+            alumet.pipeline_control(),
+        )
+        .context("failed to init CgroupReactor")?;
+        self.reactor = Some(reactor);
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        drop(self.reactor.take().unwrap());
+        Ok(())
+    }
+}
+
+struct StartingState {
+    metrics: Metrics,
+    reactor_config: ReactorConfig,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Config {
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+    /// The types of units to measure, for example `service` or `scope`.
+    #[serde(default = "default_unit_types")]
+    pub unit_types: Vec<String>,
+    /// Regexes that select the units to measure, by name. If empty, every unit is measured.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Regexes that exclude some units from the measurement, by name.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+#[cfg_attr(tarpaulin, ignore)]
+fn default_unit_types() -> Vec<String> {
+    vec![String::from("service")]
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            unit_types: default_unit_types(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use alumet::pipeline::elements::source::trigger::TriggerSpec;
+
+use regex::RegexSet;
+use util_cgroups::Cgroup;
+use util_cgroups_plugins::{
+    cgroup_events::{CgroupSetupCallback, ProbeSetup, SourceSettings},
+    metrics::{AugmentedMetrics, Metrics},
+};
+
+use crate::units::unit_from_cgroup;
+
+#[derive(Clone)]
+pub struct SourceSetup {
+    pub trigger: TriggerSpec,
+    pub filter: UnitFilter,
+}
+
+/// Selects the units to measure.
+#[derive(Clone)]
+pub struct UnitFilter {
+    /// The types of units to measure, for example `service`.
+    pub unit_types: Vec<String>,
+    /// If not empty, only the units whose name matches one of these regexes are measured.
+    pub include: RegexSet,
+    /// The units whose name matches one of these regexes are not measured.
+    pub exclude: RegexSet,
+}
+
+impl UnitFilter {
+    pub fn accepts(&self, unit_type: &str, unit_name: &str) -> bool {
+        self.unit_types.iter().any(|t| t == unit_type)
+            && (self.include.is_empty() || self.include.is_match(unit_name))
+            && !self.exclude.is_match(unit_name)
+    }
+}
+
+impl CgroupSetupCallback for SourceSetup {
+    fn setup_new_probe(&mut self, cgroup: &Cgroup, metrics: &Metrics) -> Option<ProbeSetup> {
+        // only measure the cgroups that correspond to a systemd unit
+        let unit = unit_from_cgroup(cgroup.canonical_path())?;
+        if !self.filter.accepts(&unit.unit_type, &unit.name) {
+            return None;
+        }
+        log::debug!("measuring systemd unit {} ({})", unit.name, cgroup.unique_name());
+
+        let metrics = AugmentedMetrics::with_common_attr_vec(metrics, unit.attributes());
+
+        // setup the trigger according to the plugin's config
+        let trigger = self.trigger.clone();
+
+        // use the cgroup name as the source name, because the same unit can appear in several hierarchies (cgroup v1)
+        let name = cgroup.unique_name().to_string();
+
+        // ready!
+        let source_settings = SourceSettings { name, trigger };
+        Some(ProbeSetup {
+            metrics,
+            source_settings,
+        })
+    }
+}
@@ -0,0 +1,110 @@
+use alumet::measurement::AttributeValue;
+
+/// A systemd unit, deduced from the path of its cgroup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemdUnit {
+    /// Full name of the unit, for example `nginx.service`.
+    pub name: String,
+    /// Type of the unit, for example `service`.
+    pub unit_type: String,
+    /// Slice that contains the unit, for example `system.slice`.
+    pub slice: Option<String>,
+}
+
+/// The types of units that have a cgroup.
+const UNIT_TYPES_WITH_CGROUP: [&str; 5] = ["service", "scope", "slice", "socket", "mount"];
+
+impl SystemdUnit {
+    /// Returns the attributes to add to the measurements of the unit.
+    pub fn attributes(&self) -> Vec<(String, AttributeValue)> {
+        let mut attrs = vec![
+            ("unit".into(), AttributeValue::String(self.name.clone())),
+            ("unit_type".into(), AttributeValue::String(self.unit_type.clone())),
+        ];
+        if let Some(slice) = &self.slice {
+            attrs.push(("slice".into(), AttributeValue::String(slice.clone())));
+        }
+        attrs
+    }
+}
+
+/// Extracts the systemd unit from a cgroup path (in its hierarchy).
+///
+/// # Expected format
+///
+/// systemd names the cgroup of a unit after the unit, and nests it in the cgroup of its slice.
+/// For instance, the service `nginx.service` lives in `/system.slice/nginx.service`, and the
+/// services of the user 1000 live in `/user.slice/user-1000.slice/user@1000.service/app.slice/`.
+///
+/// Returns `None` if the cgroup is not a systemd unit, like the cgroups created by
+/// the services themselves (e.g. `/system.slice/docker.service/some-child`).
+pub fn unit_from_cgroup(cgroup_path: &str) -> Option<SystemdUnit> {
+    let mut components = cgroup_path.trim_end_matches('/').rsplit('/');
+    let name = components.next()?;
+    let (_, unit_type) = name.rsplit_once('.')?;
+    if !UNIT_TYPES_WITH_CGROUP.contains(&unit_type) {
+        return None;
+    }
+    let slice = components
+        .next()
+        .filter(|parent| parent.ends_with(".slice"))
+        .map(ToOwned::to_owned);
+    // The units are directly under a slice, except the root slice (`-.slice`) which is the root cgroup.
+    if slice.is_none() && unit_type != "slice" && cgroup_path.trim_matches('/') != name {
+        return None;
+    }
+    Some(SystemdUnit {
+        name: name.to_owned(),
+        unit_type: unit_type.to_owned(),
+        slice,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn unit(name: &str, unit_type: &str, slice: Option<&str>) -> Option<SystemdUnit> {
+        Some(SystemdUnit {
+            name: name.to_owned(),
+            unit_type: unit_type.to_owned(),
+            slice: slice.map(ToOwned::to_owned),
+        })
+    }
+
+    #[test]
+    fn unit_from_cgroup_path() {
+        assert_eq!(
+            unit_from_cgroup("/system.slice/nginx.service"),
+            unit("nginx.service", "service", Some("system.slice"))
+        );
+        assert_eq!(
+            unit_from_cgroup("/system.slice/system-getty.slice/getty@tty1.service"),
+            unit("getty@tty1.service", "service", Some("system-getty.slice"))
+        );
+        assert_eq!(
+            unit_from_cgroup("/user.slice/user-1000.slice/user@1000.service/app.slice/pipewire.service"),
+            unit("pipewire.service", "service", Some("app.slice"))
+        );
+        assert_eq!(
+            unit_from_cgroup("/user.slice/user-1000.slice/session-2.scope"),
+            unit("session-2.scope", "scope", Some("user-1000.slice"))
+        );
+        assert_eq!(unit_from_cgroup("/system.slice"), unit("system.slice", "slice", None));
+        assert_eq!(
+            unit_from_cgroup("/user.slice/user-1000.slice"),
+            unit("user-1000.slice", "slice", Some("user.slice"))
+        );
+
+        // not units
+        assert_eq!(unit_from_cgroup("/"), None);
+        assert_eq!(unit_from_cgroup("/system.slice/docker.service/child"), None);
+        assert_eq!(
+            unit_from_cgroup("/user.slice/user-1000.slice/user@1000.service/init.scope/x.service"),
+            None
+        );
+        assert_eq!(unit_from_cgroup("/kubepods/pod1234/abcd"), None);
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use alumet::{
+    agent::{
+        self,
+        plugin::{PluginInfo, PluginSet},
+    },
+    plugin::PluginMetadata,
+    test::StartupExpectations,
+    units::{PrefixedUnit, Unit},
+};
+use plugin_systemd::{Config, SystemdPlugin};
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+#[test]
+fn plugin_startup() -> anyhow::Result<()> {
+    let _ = env_logger::Builder::from_default_env().try_init();
+
+    let config = Config {
+        poll_interval: Duration::from_secs(1),
+        include: vec![String::from(r"^ssh")],
+        ..Default::default()
+    };
+
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<SystemdPlugin>(),
+        enabled: true,
+        config: Some(config_to_toml_table(&config)),
+    });
+
+    let startup_expectations = StartupExpectations::new()
+        .expect_metric::<u64>("memory_usage", Unit::Byte)
+        .expect_metric::<u64>("cpu_time_delta", PrefixedUnit::nano(Unit::Second));
+
+    let agent = agent::Builder::new(plugins)
+        .with_expectations(startup_expectations)
+        .build_and_start()?;
+
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT)?;
+    Ok(())
+}
+
+#[test]
+fn invalid_filter() {
+    let config = Config {
+        exclude: vec![String::from("(")],
+        ..Default::default()
+    };
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<SystemdPlugin>(),
+        enabled: true,
+        config: Some(config_to_toml_table(&config)),
+    });
+    let res = agent::Builder::new(plugins).build_and_start();
+    assert!(res.is_err(), "invalid regexes should be rejected");
+}
+
+fn config_to_toml_table(config: &Config) -> toml::Table {
+    toml::Value::try_from(config).unwrap().as_table().unwrap().to_owned()
+}
@@ -22,6 +22,12 @@ pub struct Metrics {
     pub memory_kernel_stack: TypedMetricId<u64>,
     /// Memory used to manage correspondence between virtual and physical addresses.
     pub memory_pagetables: TypedMetricId<u64>,
+    /// Bytes read from or written to block devices by the cgroup since last measurement.
+    pub io_delta: TypedMetricId<u64>,
+    /// Share of time in which the tasks of the cgroup were stalled, averaged by the kernel.
+    pub pressure_avg: TypedMetricId<f64>,
+    /// Time in which the tasks of the cgroup were stalled since last measurement.
+    pub pressure_stall_time_delta: TypedMetricId<u64>,
 }
 
 /// Used by probes to configure how cgroup measurements will be mapped to Alumet measurement points.
@@ -61,6 +67,12 @@ pub struct AugmentedMetrics {
     pub memory_kernel_stack: AugmentedMetric<u64>,
     /// Memory used to manage correspondence between virtual and physical addresses.
     pub memory_pagetables: AugmentedMetric<u64>,
+    /// Bytes read from or written to block devices by the cgroup since last measurement.
+    pub io_delta: AugmentedMetric<u64>,
+    /// Share of time in which the tasks of the cgroup were stalled, averaged by the kernel.
+    pub pressure_avg: AugmentedMetric<f64>,
+    /// Time in which the tasks of the cgroup were stalled since last measurement.
+    pub pressure_stall_time_delta: AugmentedMetric<u64>,
 
     /// Common attributes, added to the points of all metrics.
     pub common_attrs: Vec<(String, AttributeValue)>,
@@ -104,6 +116,21 @@ impl Metrics {
             Unit::Byte,
             "Amount of memory allocated for page tables (which map virtual addresses to physical addresses).",
         )?;
+        let io_delta = alumet.create_metric::<u64>(
+            "cgroup_io_delta",
+            Unit::Byte,
+            "Number of bytes read from or written to block devices by the cgroup since the previous measurement.",
+        )?;
+        let pressure_avg = alumet.create_metric::<f64>(
+            "cgroup_pressure_avg",
+            Unit::Percent,
+            "Share of time in which the tasks of the cgroup were stalled on a resource, averaged over a window.",
+        )?;
+        let pressure_stall_time_delta = alumet.create_metric::<u64>(
+            "cgroup_pressure_stall_time_delta",
+            PrefixedUnit::micro(Unit::Second),
+            "Time in which the tasks of the cgroup were stalled on a resource since the previous measurement.",
+        )?;
         Ok(Self {
             cpu_time_delta,
             cpu_percent,
@@ -112,6 +139,9 @@ impl Metrics {
             memory_file,
             memory_kernel_stack,
             memory_pagetables,
+            io_delta,
+            pressure_avg,
+            pressure_stall_time_delta,
         })
     }
 }
@@ -126,6 +156,9 @@ impl AugmentedMetrics {
             memory_file: AugmentedMetric::simple(metrics.memory_file),
             memory_kernel_stack: AugmentedMetric::simple(metrics.memory_kernel_stack),
             memory_pagetables: AugmentedMetric::simple(metrics.memory_pagetables),
+            io_delta: AugmentedMetric::simple(metrics.io_delta),
+            pressure_avg: AugmentedMetric::simple(metrics.pressure_avg),
+            pressure_stall_time_delta: AugmentedMetric::simple(metrics.pressure_stall_time_delta),
             common_attrs: Vec::new(),
         }
     }
@@ -152,6 +185,9 @@ impl AugmentedMetrics {
             memory_file: AugmentedMetric::simple(metrics.memory_file),
             memory_kernel_stack: AugmentedMetric::simple(metrics.memory_kernel_stack),
             memory_pagetables: AugmentedMetric::simple(metrics.memory_pagetables),
+            io_delta: AugmentedMetric::simple(metrics.io_delta),
+            pressure_avg: AugmentedMetric::simple(metrics.pressure_avg),
+            pressure_stall_time_delta: AugmentedMetric::simple(metrics.pressure_stall_time_delta),
             common_attrs,
         }
     }
@@ -3,9 +3,12 @@ use alumet::{
     pipeline::{Source, elements::error::PollError},
     resources::{Resource, ResourceConsumer},
 };
+use rustc_hash::FxHashMap;
 use util_cgroups::{
     Cgroup,
-    measure::v2::{V2Collector, cpu::CpuStatCollectorSettings, memory::MemoryStatCollectorSettings},
+    measure::v2::{
+        V2Collector, cpu::CpuStatCollectorSettings, memory::MemoryStatCollectorSettings, pressure::PressureStats,
+    },
 };
 
 use super::{
@@ -15,6 +18,10 @@ use super::{
 pub struct CgroupV2Probe {
     consumer: ResourceConsumer,
     delta_counters: CpuDeltaCounters,
+    /// Previous I/O counters of each device, in bytes: `(read, written)`.
+    previous_io: FxHashMap<String, (u64, u64)>,
+    /// Previous total stall time of each resource and kind, in microseconds.
+    previous_stall: FxHashMap<(&'static str, &'static str), u64>,
     metrics: AugmentedMetrics,
     collector: V2Collector,
     io_buf: Vec<u8>,
@@ -36,6 +43,8 @@ impl CgroupV2Probe {
         Ok(Self {
             consumer,
             delta_counters: Default::default(),
+            previous_io: FxHashMap::default(),
+            previous_stall: FxHashMap::default(),
             metrics,
             collector,
             io_buf,
@@ -54,6 +63,40 @@ impl CgroupV2Probe {
             .with_attr_slice(&metric.attributes)
             .with_attr_slice(&self.metrics.common_attrs)
     }
+
+    /// Pushes the measurements of a pressure file, `pressure` being the name of the resource (`cpu`, `memory` or `io`).
+    fn push_pressure(
+        &mut self,
+        measurements: &mut MeasurementAccumulator,
+        t: Timestamp,
+        resource: &Resource,
+        pressure: &'static str,
+        stats: PressureStats,
+    ) {
+        for (kind, stall) in [("some", stats.some), ("full", stats.full)] {
+            let Some(stall) = stall else {
+                continue;
+            };
+            for (window, avg) in [("10s", stall.avg10), ("60s", stall.avg60), ("300s", stall.avg300)] {
+                measurements.push(
+                    self.new_point(&self.metrics.pressure_avg, t, resource, avg)
+                        .with_attr("pressure", pressure)
+                        .with_attr("kind", kind)
+                        .with_attr("window", window),
+                );
+            }
+            // Only push deltas, not the baseline value before the plugin starts
+            if let Some(prev) = self.previous_stall.insert((pressure, kind), stall.total)
+                && let Some(delta) = stall.total.checked_sub(prev)
+            {
+                measurements.push(
+                    self.new_point(&self.metrics.pressure_stall_time_delta, t, resource, delta)
+                        .with_attr("pressure", pressure)
+                        .with_attr("kind", kind),
+                );
+            }
+        }
+    }
 }
 
 impl Source for CgroupV2Probe {
@@ -154,6 +197,43 @@ impl Source for CgroupV2Probe {
                 measurements.push(self.new_point(&self.metrics.memory_pagetables, t, &resource, value));
             }
         }
+
+        // I/O statistics
+        if let Some(io_stat) = data.io_stat {
+            for dev in io_stat.devices {
+                let counters = (dev.read_bytes, dev.write_bytes);
+                // the counters of a device are reset when it is removed and added again, skip the measurement in that case
+                if let Some((prev_read, prev_write)) = self.previous_io.insert(dev.device.clone(), counters)
+                    && let (Some(read), Some(write)) = (
+                        dev.read_bytes.checked_sub(prev_read),
+                        dev.write_bytes.checked_sub(prev_write),
+                    )
+                {
+                    let device = dev.device;
+                    measurements.push(
+                        self.new_point(&self.metrics.io_delta, t, &resource, read)
+                            .with_attr("kind", "read")
+                            .with_attr("device", device.clone()),
+                    );
+                    measurements.push(
+                        self.new_point(&self.metrics.io_delta, t, &resource, write)
+                            .with_attr("kind", "write")
+                            .with_attr("device", device),
+                    );
+                }
+            }
+        }
+
+        // Pressure Stall Information
+        if let Some(stats) = data.cpu_pressure {
+            self.push_pressure(measurements, t, &resource, "cpu", stats);
+        }
+        if let Some(stats) = data.memory_pressure {
+            self.push_pressure(measurements, t, &resource, "memory", stats);
+        }
+        if let Some(stats) = data.io_pressure {
+            self.push_pressure(measurements, t, &resource, "io", stats);
+        }
         Ok(())
     }
 }
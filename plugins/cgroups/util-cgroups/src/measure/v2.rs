@@ -6,6 +6,12 @@ pub mod cpu;
 /// Memory statistics for cgroup v2.
 pub mod memory;
 
+/// I/O statistics for cgroup v2.
+pub mod io;
+
+/// Pressure Stall Information (PSI) for cgroup v2.
+pub mod pressure;
+
 /// Small zero-cost wrapper around line index.
 mod line_index;
 
@@ -36,7 +42,9 @@ mod common {
 
     use super::{
         cpu::{CpuStatCollector, CpuStats},
+        io::{IoStatCollector, IoStats},
         memory::{MemoryCurrentCollector, MemoryStatCollector, MemoryStats},
+        pressure::{PressureCollector, PressureStats},
     };
 
     /// Collects cgroup v2 measurements.
@@ -44,12 +52,20 @@ mod common {
         memory_current: Option<MemoryCurrentCollector>,
         memory_stat: Option<MemoryStatCollector>,
         cpu_stat: Option<CpuStatCollector>,
+        io_stat: Option<IoStatCollector>,
+        cpu_pressure: Option<PressureCollector>,
+        memory_pressure: Option<PressureCollector>,
+        io_pressure: Option<PressureCollector>,
     }
 
     pub struct V2Stats {
         pub memory_current: Option<u64>,
         pub memory_stat: Option<MemoryStats>,
         pub cpu_stat: Option<CpuStats>,
+        pub io_stat: Option<IoStats>,
+        pub cpu_pressure: Option<PressureStats>,
+        pub memory_pressure: Option<PressureStats>,
+        pub io_pressure: Option<PressureStats>,
     }
 
     impl V2Collector {
@@ -71,6 +87,7 @@ mod common {
             let memory_current_file = cgroup_path.join("memory.current");
             let memory_stat_file = cgroup_path.join("memory.stat");
             let cpu_stat_file = cgroup_path.join("cpu.stat");
+            let io_stat_file = cgroup_path.join("io.stat");
 
             let prepare_memory_current = || -> anyhow::Result<Option<MemoryCurrentCollector>> {
                 match MemoryCurrentCollector::new(&memory_current_file) {
@@ -117,12 +134,47 @@ mod common {
                 }
             };
 
+            let prepare_io_stat = || -> anyhow::Result<Option<IoStatCollector>> {
+                match IoStatCollector::new(&io_stat_file) {
+                    Ok(res) => Ok(Some(res)),
+                    Err(e) if e.kind() == ErrorKind::NotFound => {
+                        // the file does not exist (the io controller is not enabled), ignore
+                        log::warn!(
+                            "{} does not exist, some metrics will not be available",
+                            io_stat_file.display()
+                        );
+                        Ok(None)
+                    }
+                    Err(e) => Err(e.into()),
+                }
+            };
+
+            let prepare_pressure = |file: &str| -> anyhow::Result<Option<PressureCollector>> {
+                let pressure_file = cgroup_path.join(file);
+                match PressureCollector::new(&pressure_file) {
+                    Ok(res) => Ok(Some(res)),
+                    Err(e) if e.kind() == ErrorKind::NotFound => {
+                        // the file does not exist (PSI is disabled in the kernel), ignore
+                        log::warn!(
+                            "{} does not exist, some metrics will not be available",
+                            pressure_file.display()
+                        );
+                        Ok(None)
+                    }
+                    Err(e) => Err(e.into()),
+                }
+            };
+
             let error_msg = || format!("collector creation failed for cgroup {}", cgroup.unique_name());
 
             Ok(Self {
                 memory_current: prepare_memory_current().with_context(error_msg)?,
                 memory_stat: prepare_memory_stat(io_buf).with_context(error_msg)?,
                 cpu_stat: prepare_cpu_stat(io_buf).with_context(error_msg)?,
+                io_stat: prepare_io_stat().with_context(error_msg)?,
+                cpu_pressure: prepare_pressure("cpu.pressure").with_context(error_msg)?,
+                memory_pressure: prepare_pressure("memory.pressure").with_context(error_msg)?,
+                io_pressure: prepare_pressure("io.pressure").with_context(error_msg)?,
             })
         }
 
@@ -133,11 +185,19 @@ mod common {
             let memory_current = self.memory_current.as_mut().map(|c| c.measure(io_buf)).transpose()?;
             let memory_stat = self.memory_stat.as_mut().map(|c| c.measure(io_buf)).transpose()?;
             let cpu_stat = self.cpu_stat.as_mut().map(|c| c.measure(io_buf)).transpose()?;
+            let io_stat = self.io_stat.as_mut().map(|c| c.measure(io_buf)).transpose()?;
+            let cpu_pressure = self.cpu_pressure.as_mut().map(|c| c.measure(io_buf)).transpose()?;
+            let memory_pressure = self.memory_pressure.as_mut().map(|c| c.measure(io_buf)).transpose()?;
+            let io_pressure = self.io_pressure.as_mut().map(|c| c.measure(io_buf)).transpose()?;
 
             Ok(V2Stats {
                 memory_current,
                 memory_stat,
                 cpu_stat,
+                io_stat,
+                cpu_pressure,
+                memory_pressure,
+                io_pressure,
             })
         }
     }
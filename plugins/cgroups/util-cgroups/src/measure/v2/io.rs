@@ -0,0 +1,122 @@
+use std::{fs::File, io, path::Path};
+
+use crate::measure::parse::read_fully;
+
+/// Collects measurements from `io.stat`.
+pub struct IoStatCollector {
+    file: File,
+}
+
+/// Represents the measurements extracted from the `io.stat` file.
+#[derive(Debug, Default)]
+pub struct IoStats {
+    /// Statistics of each block device that has been used by the cgroup.
+    pub devices: Vec<DeviceIoStats>,
+}
+
+/// I/O statistics of a block device.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DeviceIoStats {
+    /// Device number, in the format `major:minor`.
+    pub device: String,
+    /// Number of bytes read.
+    pub read_bytes: u64,
+    /// Number of bytes written.
+    pub write_bytes: u64,
+}
+
+impl IoStatCollector {
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(Self { file })
+    }
+
+    /// Collects measurements from the underlying "file", using `io_buf` as an intermediary I/O buffer.
+    pub fn measure(&mut self, io_buf: &mut Vec<u8>) -> io::Result<IoStats> {
+        read_fully(&mut self.file, io_buf)?;
+        // SAFETY: the content is generated by the kernel and is always valid ASCII (hence valid UTF-8)
+        unsafe { parse_io_stat(io_buf) }
+    }
+}
+
+/// Parses the content of `io.stat`.
+///
+/// # Input format
+/// ```text
+/// 8:16 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0
+/// 8:0 rbytes=90430464 wbytes=299008000 rios=8950 wios=1252 dbytes=50331648 dios=3021
+/// ```
+///
+/// # Safety
+/// The bytes passed in must be valid UTF-8.
+unsafe fn parse_io_stat(io_buf: &[u8]) -> io::Result<IoStats> {
+    let content = unsafe { std::str::from_utf8_unchecked(io_buf) };
+    let mut res = IoStats::default();
+    for line in content.lines() {
+        let mut fields = line.split_ascii_whitespace();
+        let Some(device) = fields.next() else {
+            continue; // empty line
+        };
+        let mut stats = DeviceIoStats {
+            device: device.to_owned(),
+            ..Default::default()
+        };
+        for field in fields {
+            if let Some((key, value)) = field.split_once('=') {
+                let dest = match key {
+                    "rbytes" => &mut stats.read_bytes,
+                    "wbytes" => &mut stats.write_bytes,
+                    _ => continue,
+                };
+                *dest = value.parse().map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+            }
+        }
+        res.devices.push(stats);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeviceIoStats, IoStatCollector};
+
+    #[test]
+    fn collect_io_stat() -> anyhow::Result<()> {
+        let tmp = tempfile::NamedTempFile::new()?;
+        std::fs::write(
+            tmp.path(),
+            "8:16 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0\n\
+             259:0 rbytes=90430464 wbytes=299008000 rios=8950 wios=1252 dbytes=50331648 dios=3021\n",
+        )?;
+
+        let mut io_buf = Vec::new();
+        let mut collector = IoStatCollector::new(tmp.path())?;
+        let io_stats = collector.measure(&mut io_buf)?;
+        assert_eq!(
+            io_stats.devices,
+            vec![
+                DeviceIoStats {
+                    device: String::from("8:16"),
+                    read_bytes: 1459200,
+                    write_bytes: 314773504,
+                },
+                DeviceIoStats {
+                    device: String::from("259:0"),
+                    read_bytes: 90430464,
+                    write_bytes: 299008000,
+                },
+            ]
+        );
+
+        // no I/O yet
+        std::fs::write(tmp.path(), "")?;
+        let io_stats = collector.measure(&mut io_buf)?;
+        assert!(io_stats.devices.is_empty());
+
+        // bad value
+        std::fs::write(tmp.path(), "8:0 rbytes=abc wbytes=0\n")?;
+        let err = collector.measure(&mut io_buf).expect_err("should fail");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        Ok(())
+    }
+}
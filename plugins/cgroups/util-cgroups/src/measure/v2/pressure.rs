@@ -0,0 +1,132 @@
+use std::{fs::File, io, path::Path};
+
+use crate::measure::parse::read_fully;
+
+/// Collects measurements from a pressure file: `cpu.pressure`, `memory.pressure` or `io.pressure`.
+pub struct PressureCollector {
+    file: File,
+}
+
+/// Represents the measurements extracted from a pressure file.
+///
+/// See the documentation of the [Pressure Stall Information](https://docs.kernel.org/accounting/psi.html).
+#[derive(Debug, Default, PartialEq)]
+pub struct PressureStats {
+    /// Share of time in which at least some tasks are stalled.
+    pub some: Option<StallStats>,
+    /// Share of time in which all non-idle tasks are stalled simultaneously.
+    pub full: Option<StallStats>,
+}
+
+/// Stall statistics of one line of a pressure file.
+#[derive(Debug, Default, PartialEq)]
+pub struct StallStats {
+    /// Percentage of stalled time over the last 10 seconds.
+    pub avg10: f64,
+    /// Percentage of stalled time over the last 60 seconds.
+    pub avg60: f64,
+    /// Percentage of stalled time over the last 300 seconds.
+    pub avg300: f64,
+    /// Total stalled time, in microseconds.
+    pub total: u64,
+}
+
+impl PressureCollector {
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        Ok(Self { file })
+    }
+
+    /// Collects measurements from the underlying "file", using `io_buf` as an intermediary I/O buffer.
+    pub fn measure(&mut self, io_buf: &mut Vec<u8>) -> io::Result<PressureStats> {
+        read_fully(&mut self.file, io_buf)?;
+        // SAFETY: the content is generated by the kernel and is always valid ASCII (hence valid UTF-8)
+        unsafe { parse_pressure(io_buf) }
+    }
+}
+
+/// Parses the content of a pressure file.
+///
+/// # Input format
+/// ```text
+/// some avg10=0.12 avg60=0.30 avg300=0.27 total=1473224
+/// full avg10=0.00 avg60=0.10 avg300=0.09 total=623451
+/// ```
+///
+/// # Safety
+/// The bytes passed in must be valid UTF-8.
+unsafe fn parse_pressure(io_buf: &[u8]) -> io::Result<PressureStats> {
+    let content = unsafe { std::str::from_utf8_unchecked(io_buf) };
+    let invalid = || io::Error::from(io::ErrorKind::InvalidData);
+    let mut res = PressureStats::default();
+    for line in content.lines() {
+        let mut fields = line.split_ascii_whitespace();
+        let dest = match fields.next() {
+            Some("some") => &mut res.some,
+            Some("full") => &mut res.full,
+            _ => continue,
+        };
+        let mut stats = StallStats::default();
+        for field in fields {
+            if let Some((key, value)) = field.split_once('=') {
+                match key {
+                    "avg10" => stats.avg10 = value.parse().map_err(|_| invalid())?,
+                    "avg60" => stats.avg60 = value.parse().map_err(|_| invalid())?,
+                    "avg300" => stats.avg300 = value.parse().map_err(|_| invalid())?,
+                    "total" => stats.total = value.parse().map_err(|_| invalid())?,
+                    _ => continue,
+                }
+            }
+        }
+        *dest = Some(stats);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PressureCollector, PressureStats, StallStats};
+
+    #[test]
+    fn collect_pressure() -> anyhow::Result<()> {
+        let tmp = tempfile::NamedTempFile::new()?;
+        std::fs::write(
+            tmp.path(),
+            "some avg10=0.12 avg60=0.30 avg300=0.27 total=1473224\n\
+             full avg10=0.00 avg60=0.10 avg300=0.09 total=623451\n",
+        )?;
+
+        let mut io_buf = Vec::new();
+        let mut collector = PressureCollector::new(tmp.path())?;
+        let stats = collector.measure(&mut io_buf)?;
+        assert_eq!(
+            stats,
+            PressureStats {
+                some: Some(StallStats {
+                    avg10: 0.12,
+                    avg60: 0.30,
+                    avg300: 0.27,
+                    total: 1473224,
+                }),
+                full: Some(StallStats {
+                    avg10: 0.0,
+                    avg60: 0.10,
+                    avg300: 0.09,
+                    total: 623451,
+                }),
+            }
+        );
+
+        // old kernels don't report the "full" line of the cpu
+        std::fs::write(tmp.path(), "some avg10=1.50 avg60=0.00 avg300=0.00 total=10\n")?;
+        let stats = collector.measure(&mut io_buf)?;
+        assert_eq!(stats.some.map(|s| s.total), Some(10));
+        assert_eq!(stats.full, None);
+
+        // bad value
+        std::fs::write(tmp.path(), "some avg10=abc avg60=0.00 avg300=0.00 total=10\n")?;
+        let err = collector.measure(&mut io_buf).expect_err("should fail");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        Ok(())
+    }
+}
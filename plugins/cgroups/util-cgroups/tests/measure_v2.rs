@@ -34,6 +34,17 @@ pub fn test_new_and_measure() -> anyhow::Result<()> {
     let data_mem_cur = "852";
     let mut file3 = File::create(file_path)?;
     file3.write_all(data_mem_cur.as_bytes())?;
+    // file 4
+    let file_path = root.path().join("io.stat");
+    let data_io = "8:0 rbytes=4096 wbytes=8192 rios=1 wios=2 dbytes=0 dios=0\n";
+    let mut file4 = File::create(file_path)?;
+    file4.write_all(data_io.as_bytes())?;
+    // file 5
+    let file_path = root.path().join("memory.pressure");
+    let data_pressure = "some avg10=1.25 avg60=0.50 avg300=0.10 total=4000\n\
+            full avg10=0.75 avg60=0.25 avg300=0.05 total=2000\n";
+    let mut file5 = File::create(file_path)?;
+    file5.write_all(data_pressure.as_bytes())?;
 
     let hierarchy = CgroupHierarchy::manually_unchecked(root.path(), CgroupVersion::V2, vec!["cpu", "memory"]);
     let cgroup = Cgroup::from_fs_path(&hierarchy, root.path().to_path_buf());
@@ -51,9 +62,14 @@ pub fn test_new_and_measure() -> anyhow::Result<()> {
     assert!(v2stat.cpu_stat.is_some());
     assert!(v2stat.memory_stat.is_some());
     assert!(v2stat.memory_current.is_some());
+    assert!(v2stat.io_stat.is_some());
+    assert!(v2stat.memory_pressure.is_some());
+    assert!(v2stat.cpu_pressure.is_none());
     let cpu_stat = v2stat.cpu_stat.unwrap();
     let mem_stat = v2stat.memory_stat.unwrap();
     let mem_cur = v2stat.memory_current.unwrap();
+    let io_stat = v2stat.io_stat.unwrap();
+    let memory_pressure = v2stat.memory_pressure.unwrap();
 
     assert_eq!(cpu_stat.system.unwrap_or(0), 456);
     assert_eq!(cpu_stat.user.unwrap_or(0), 123);
@@ -66,6 +82,18 @@ pub fn test_new_and_measure() -> anyhow::Result<()> {
 
     assert_eq!(mem_cur, 852);
 
+    assert_eq!(io_stat.devices.len(), 1);
+    assert_eq!(io_stat.devices[0].device, "8:0");
+    assert_eq!(io_stat.devices[0].read_bytes, 4096);
+    assert_eq!(io_stat.devices[0].write_bytes, 8192);
+
+    let some = memory_pressure.some.unwrap();
+    let full = memory_pressure.full.unwrap();
+    assert_eq!(some.avg10, 1.25);
+    assert_eq!(some.total, 4000);
+    assert_eq!(full.avg300, 0.05);
+    assert_eq!(full.total, 2000);
+
     Ok(())
 }
 
@@ -0,0 +1,177 @@
+//! Discovery and reading of the frequency (cpufreq) and idle states (cpuidle) of the CPU cores.
+//!
+//! See the kernel documentation of [cpufreq](https://docs.kernel.org/admin-guide/pm/cpufreq.html)
+//! and [cpuidle](https://docs.kernel.org/admin-guide/pm/cpuidle.html).
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+/// A CPU core, such as `/sys/devices/system/cpu/cpu0`.
+#[derive(Debug)]
+pub struct CpuCore {
+    /// Id of the core.
+    pub id: u32,
+    /// File that contains the current frequency of the core, in kHz.
+    pub frequency: Option<PathBuf>,
+    /// Idle states (C-states) of the core.
+    pub idle_states: Vec<IdleState>,
+}
+
+/// An idle state of a CPU core, such as `/sys/devices/system/cpu/cpu0/cpuidle/state1`.
+#[derive(Debug)]
+pub struct IdleState {
+    /// Name of the state, like `POLL`, `C1` or `C6`.
+    pub name: String,
+    path: PathBuf,
+}
+
+/// Counters of an idle state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdleCounters {
+    /// Total time spent in the state, in microseconds.
+    pub time_us: u64,
+    /// Number of times the state has been entered.
+    pub usage: u64,
+}
+
+impl CpuCore {
+    /// Reads the current frequency of the core, in kHz.
+    pub fn read_frequency(&self) -> Option<anyhow::Result<u64>> {
+        self.frequency.as_deref().map(read_u64)
+    }
+}
+
+impl IdleState {
+    pub fn read_counters(&self) -> anyhow::Result<IdleCounters> {
+        Ok(IdleCounters {
+            time_us: read_u64(&self.path.join("time"))?,
+            usage: read_u64(&self.path.join("usage"))?,
+        })
+    }
+}
+
+fn read_u64(path: &Path) -> anyhow::Result<u64> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    content
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid value in {}: {content:?}", path.display()))
+}
+
+/// Explores the CPU cores and their frequency and idle states.
+///
+/// ## Expected file layout
+///
+/// ```txt
+/// /sys/devices/system/cpu/
+/// |− cpu0
+///     |− cpufreq
+///         |− scaling_cur_freq
+///     |− cpuidle
+///         |− state0
+///             |− name
+///             |− time
+///             |− usage
+///         |− …
+/// |− …
+/// ```
+///
+/// The offline cores, which have no `cpufreq` nor `cpuidle` directory, are ignored.
+pub fn explore(cpu_path: &Path) -> anyhow::Result<Vec<CpuCore>> {
+    let mut cores = Vec::new();
+    for entry in std::fs::read_dir(cpu_path).with_context(|| format!("failed to read dir {cpu_path:?}"))? {
+        let entry = entry?;
+        let Some(id) = entry
+            .file_name()
+            .to_str()
+            .and_then(|n| n.strip_prefix("cpu")?.parse().ok())
+        else {
+            continue;
+        };
+        let path = entry.path();
+
+        let frequency = Some(path.join("cpufreq/scaling_cur_freq")).filter(|f| f.exists());
+
+        let mut idle_states = Vec::new();
+        if let Ok(ls) = std::fs::read_dir(path.join("cpuidle")) {
+            for state in ls.filter_map(|e| e.ok()) {
+                if !state.file_name().to_string_lossy().starts_with("state") {
+                    continue;
+                }
+                let state_path = state.path();
+                match std::fs::read_to_string(state_path.join("name")) {
+                    Ok(name) => idle_states.push(IdleState {
+                        name: name.trim().to_owned(),
+                        path: state_path,
+                    }),
+                    Err(e) => log::warn!("failed to read the name of the idle state {state_path:?}: {e}"),
+                }
+            }
+        }
+        idle_states.sort_by(|a, b| a.path.cmp(&b.path));
+
+        if frequency.is_some() || !idle_states.is_empty() {
+            cores.push(CpuCore {
+                id,
+                frequency,
+                idle_states,
+            });
+        }
+    }
+    cores.sort_by_key(|c| c.id);
+    Ok(cores)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn write_files(dir: &Path, files: &[(&str, &str)]) {
+        std::fs::create_dir_all(dir).unwrap();
+        for (file, content) in files {
+            std::fs::write(dir.join(file), format!("{content}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn explore_cpus() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let root = root.path();
+        write_files(&root.join("cpu0/cpufreq"), &[("scaling_cur_freq", "2400000")]);
+        write_files(
+            &root.join("cpu0/cpuidle/state0"),
+            &[("name", "POLL"), ("time", "150"), ("usage", "12")],
+        );
+        write_files(
+            &root.join("cpu0/cpuidle/state1"),
+            &[("name", "C1"), ("time", "987654"), ("usage", "3456")],
+        );
+        write_files(&root.join("cpu1/cpufreq"), &[("scaling_cur_freq", "800000")]);
+        // offline core
+        std::fs::create_dir(root.join("cpu2"))?;
+        // not a core
+        write_files(&root.join("cpufreq"), &[("boost", "1")]);
+        write_files(root, &[("online", "0-1")]);
+
+        let cores = explore(root)?;
+        assert_eq!(cores.iter().map(|c| c.id).collect::<Vec<_>>(), vec![0, 1]);
+
+        assert_eq!(cores[0].read_frequency().unwrap()?, 2400000);
+        let names: Vec<_> = cores[0].idle_states.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["POLL", "C1"]);
+        assert_eq!(
+            cores[0].idle_states[1].read_counters()?,
+            IdleCounters {
+                time_us: 987654,
+                usage: 3456
+            }
+        );
+
+        assert_eq!(cores[1].read_frequency().unwrap()?, 800000);
+        assert!(cores[1].idle_states.is_empty());
+        Ok(())
+    }
+}
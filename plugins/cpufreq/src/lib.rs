@@ -0,0 +1,109 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::source::{CpuStatesSource, Metrics};
+
+mod cpu;
+mod source;
+
+/// Measures the frequency and the idle states (C-states) of the CPU cores,
+/// with the cpufreq and cpuidle subsystems of the Linux kernel.
+pub struct CpufreqPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for CpufreqPlugin {
+    fn name() -> &'static str {
+        "cpufreq"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(CpufreqPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let mut cores = cpu::explore(&self.config.cpu_path).context("could not find the CPU cores")?;
+        for core in &mut cores {
+            if !self.config.frequency {
+                core.frequency = None;
+            }
+            if !self.config.idle_states {
+                core.idle_states.clear();
+            }
+        }
+        cores.retain(|core| core.frequency.is_some() || !core.idle_states.is_empty());
+        if cores.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: no cpufreq or cpuidle data found in {:?}",
+                self.config.cpu_path
+            ));
+        }
+        for core in &cores {
+            let states: Vec<&str> = core.idle_states.iter().map(|s| s.name.as_str()).collect();
+            log::debug!(
+                "Found CPU core {} (frequency: {}, idle states: {states:?})",
+                core.id,
+                core.frequency.is_some(),
+            );
+        }
+        log::info!("Found {} CPU cores to measure.", cores.len());
+
+        let metrics = Metrics::new(alumet)?;
+        let source = CpuStatesSource::new(cores, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("cpu_states", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the CPU cores.
+    pub cpu_path: PathBuf,
+
+    /// `true` to measure the current frequency of the cores.
+    pub frequency: bool,
+
+    /// `true` to measure the residency of the cores in their idle states.
+    pub idle_states: bool,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            cpu_path: PathBuf::from("/sys/devices/system/cpu"),
+            frequency: true,
+            idle_states: true,
+        }
+    }
+}
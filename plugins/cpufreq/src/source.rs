@@ -0,0 +1,97 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::{PrefixedUnit, Unit},
+};
+
+use crate::cpu::{CpuCore, IdleCounters};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    frequency: TypedMetricId<u64>,
+    idle_time: TypedMetricId<u64>,
+    idle_entries: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            frequency: alumet.create_metric(
+                "cpu_frequency",
+                PrefixedUnit::kilo(Unit::Hertz),
+                "Current frequency of the CPU core",
+            )?,
+            idle_time: alumet.create_metric(
+                "cpu_idle_time",
+                PrefixedUnit::micro(Unit::Second),
+                "Time spent by the CPU core in the idle state since the previous measurement",
+            )?,
+            idle_entries: alumet.create_metric(
+                "cpu_idle_entries",
+                Unit::Unity,
+                "Number of times the CPU core entered the idle state since the previous measurement",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the frequency and idle states of the CPU cores.
+pub struct CpuStatesSource {
+    cores: Vec<MeasuredCore>,
+    metrics: Metrics,
+}
+
+struct MeasuredCore {
+    core: CpuCore,
+    /// The previous counters of each idle state, to compute the difference.
+    previous: Vec<Option<IdleCounters>>,
+}
+
+impl CpuStatesSource {
+    pub fn new(cores: Vec<CpuCore>, metrics: Metrics) -> Self {
+        let cores = cores
+            .into_iter()
+            .map(|core| MeasuredCore {
+                previous: vec![None; core.idle_states.len()],
+                core,
+            })
+            .collect();
+        Self { cores, metrics }
+    }
+}
+
+impl Source for CpuStatesSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for c in &mut self.cores {
+            let point = |metric, value: u64| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    Resource::CpuCore { id: c.core.id },
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+            };
+
+            if let Some(frequency) = c.core.read_frequency() {
+                measurements.push(point(self.metrics.frequency, frequency?));
+            }
+
+            for (state, previous) in c.core.idle_states.iter().zip(c.previous.iter_mut()) {
+                let counters = state.read_counters()?;
+                // Only push deltas, not the baseline value before the plugin starts
+                if let Some(prev) = previous {
+                    let time = counters.time_us.saturating_sub(prev.time_us);
+                    let entries = counters.usage.saturating_sub(prev.usage);
+                    measurements.push(point(self.metrics.idle_time, time).with_attr("state", state.name.clone()));
+                    measurements.push(point(self.metrics.idle_entries, entries).with_attr("state", state.name.clone()));
+                }
+                *previous = Some(counters);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::{PrefixedUnit, Unit},
+};
+use plugin_cpufreq::{Config, CpufreqPlugin};
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_cpu() {
+    let root = tempdir().unwrap();
+    std::fs::create_dir(root.path().join("cpu0")).unwrap();
+    let config = Config {
+        cpu_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(
+        agent.is_err(),
+        "the plugin should fail to start (no cpufreq or cpuidle)"
+    );
+}
+
+#[test]
+fn plugin_with_frequency_and_idle_states() {
+    let root = tempdir().unwrap();
+    let cpu_path = root.path().to_path_buf();
+    write_files(&cpu_path.join("cpu0/cpufreq"), &[("scaling_cur_freq", "2400000")]);
+    write_files(
+        &cpu_path.join("cpu0/cpuidle/state1"),
+        &[("name", "C6"), ("time", "1000"), ("usage", "10")],
+    );
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        cpu_path: cpu_path.clone(),
+        frequency: true,
+        idle_states: true,
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<u64>("cpu_frequency", PrefixedUnit::kilo(Unit::Hertz))
+        .expect_metric::<u64>("cpu_idle_time", PrefixedUnit::micro(Unit::Second))
+        .expect_metric::<u64>("cpu_idle_entries", Unit::Unity)
+        .expect_source("cpufreq", "cpu_states");
+
+    let source = SourceName::from_str("cpufreq", "cpu_states");
+    let runtime = RuntimeExpectations::new()
+        .test_source(
+            source.clone(),
+            || {},
+            |ctx| {
+                // first measurement: only the frequency, because the idle time is a difference
+                let m = ctx.measurements();
+                assert_eq!(m.len(), 1);
+                let point = m.iter().next().unwrap();
+                assert_eq!(point.resource, Resource::CpuCore { id: 0 });
+                assert_eq!(point.value.as_u64(), 2400000);
+            },
+        )
+        .test_source(
+            source,
+            move || {
+                write_files(
+                    &cpu_path.join("cpu0/cpuidle/state1"),
+                    &[("time", "1500"), ("usage", "12")],
+                );
+            },
+            |ctx| {
+                let m = ctx.measurements();
+                let value_of = |name: &str| {
+                    let metric = ctx.metrics().by_name(name).unwrap().0;
+                    m.iter().find(|p| p.metric == metric).map(|p| p.value.as_u64()).unwrap()
+                };
+                assert_eq!(value_of("cpu_idle_time"), 500);
+                assert_eq!(value_of("cpu_idle_entries"), 2);
+            },
+        );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn write_files(dir: &Path, files: &[(&str, &str)]) {
+    std::fs::create_dir_all(dir).unwrap();
+    for (file, content) in files {
+        std::fs::write(dir.join(file), content).unwrap();
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<CpufreqPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -0,0 +1,298 @@
+//! Minimal wrappers around the `bpf` system call: maps, programs and tracepoint attachment.
+
+use std::{
+    ffi::CString,
+    io,
+    marker::PhantomData,
+    mem::size_of,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    path::Path,
+};
+
+use anyhow::{Context, anyhow};
+
+use crate::program::Insn;
+
+// commands of the bpf system call
+const BPF_MAP_CREATE: u32 = 0;
+const BPF_MAP_LOOKUP_ELEM: u32 = 1;
+const BPF_MAP_DELETE_ELEM: u32 = 3;
+const BPF_MAP_GET_NEXT_KEY: u32 = 4;
+const BPF_PROG_LOAD: u32 = 5;
+
+const BPF_MAP_TYPE_HASH: u32 = 1;
+const BPF_MAP_TYPE_PERCPU_ARRAY: u32 = 6;
+const BPF_PROG_TYPE_TRACEPOINT: u32 = 5;
+
+/// Size of the buffer that receives the log of the verifier, when a program is rejected.
+const VERIFIER_LOG_SIZE: usize = 64 * 1024;
+
+/// Directories where the tracing filesystem is usually mounted.
+const TRACEFS_PATHS: [&str; 2] = ["/sys/kernel/tracing", "/sys/kernel/debug/tracing"];
+
+#[repr(C)]
+#[derive(Default)]
+struct MapCreateAttr {
+    map_type: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+    map_flags: u32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct MapElemAttr {
+    map_fd: u32,
+    _pad: u32,
+    key: u64,
+    value_or_next_key: u64,
+    flags: u64,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+    kern_version: u32,
+    prog_flags: u32,
+}
+
+fn bpf<T>(cmd: u32, attr: &mut T) -> io::Result<libc::c_long> {
+    // SAFETY: attr is a valid bpf_attr for the command, and its size is given to the kernel
+    let res = unsafe { libc::syscall(libc::SYS_bpf, cmd, attr as *mut T, size_of::<T>() as libc::c_uint) };
+    if res < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(res)
+    }
+}
+
+fn bpf_fd<T>(cmd: u32, attr: &mut T) -> io::Result<OwnedFd> {
+    let fd = bpf(cmd, attr)?;
+    // SAFETY: the commands that create an object return a new file descriptor
+    Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+}
+
+/// A key of a BPF map, which is copied as is to the kernel.
+pub trait MapKey: Copy + Default {}
+impl MapKey for u32 {}
+impl MapKey for u64 {}
+
+/// A BPF hash map, with `u64` values.
+pub struct HashMap<K: MapKey> {
+    fd: OwnedFd,
+    _key: PhantomData<K>,
+}
+
+impl<K: MapKey> HashMap<K> {
+    pub fn create(max_entries: u32) -> io::Result<Self> {
+        let mut attr = MapCreateAttr {
+            map_type: BPF_MAP_TYPE_HASH,
+            key_size: size_of::<K>() as u32,
+            value_size: size_of::<u64>() as u32,
+            max_entries,
+            map_flags: 0,
+        };
+        let fd = bpf_fd(BPF_MAP_CREATE, &mut attr)?;
+        Ok(Self { fd, _key: PhantomData })
+    }
+
+    /// Returns the value associated to `key`, if any.
+    pub fn get(&self, key: K) -> io::Result<Option<u64>> {
+        let mut value = 0u64;
+        let mut attr = self.elem_attr(&key, &mut value as *mut u64 as u64);
+        match bpf(BPF_MAP_LOOKUP_ELEM, &mut attr) {
+            Ok(_) => Ok(Some(value)),
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn remove(&self, key: K) -> io::Result<()> {
+        let mut attr = self.elem_attr(&key, 0);
+        match bpf(BPF_MAP_DELETE_ELEM, &mut attr) {
+            Err(e) if e.raw_os_error() != Some(libc::ENOENT) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns all the entries of the map.
+    ///
+    /// The map can be modified by the BPF program during the iteration: the entries that are
+    /// removed in the meantime are skipped.
+    pub fn entries(&self) -> io::Result<Vec<(K, u64)>> {
+        let mut entries = Vec::new();
+        let mut key: Option<K> = None;
+        loop {
+            let mut next = K::default();
+            let mut attr = MapElemAttr {
+                map_fd: self.fd.as_raw_fd() as u32,
+                // a null key returns the first key of the map
+                key: key.as_ref().map_or(0, |k| k as *const K as u64),
+                value_or_next_key: &mut next as *mut K as u64,
+                ..Default::default()
+            };
+            match bpf(BPF_MAP_GET_NEXT_KEY, &mut attr) {
+                Ok(_) => (),
+                Err(e) if e.raw_os_error() == Some(libc::ENOENT) => break,
+                Err(e) => return Err(e),
+            }
+            if let Some(value) = self.get(next)? {
+                entries.push((next, value));
+            }
+            key = Some(next);
+        }
+        Ok(entries)
+    }
+
+    fn elem_attr(&self, key: &K, value: u64) -> MapElemAttr {
+        MapElemAttr {
+            map_fd: self.fd.as_raw_fd() as u32,
+            key: key as *const K as u64,
+            value_or_next_key: value,
+            ..Default::default()
+        }
+    }
+}
+
+impl<K: MapKey> AsRawFd for HashMap<K> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// A BPF array that contains one `u64` value per CPU.
+pub struct PerCpuArray {
+    fd: OwnedFd,
+}
+
+impl PerCpuArray {
+    pub fn create(max_entries: u32) -> io::Result<Self> {
+        let mut attr = MapCreateAttr {
+            map_type: BPF_MAP_TYPE_PERCPU_ARRAY,
+            key_size: size_of::<u32>() as u32,
+            value_size: size_of::<u64>() as u32,
+            max_entries,
+            map_flags: 0,
+        };
+        let fd = bpf_fd(BPF_MAP_CREATE, &mut attr)?;
+        Ok(Self { fd })
+    }
+}
+
+impl AsRawFd for PerCpuArray {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// A BPF program loaded in the kernel.
+pub struct Program {
+    fd: OwnedFd,
+}
+
+impl Program {
+    /// Loads a tracepoint program.
+    ///
+    /// If the verifier rejects the program, its log is included in the error.
+    pub fn load_tracepoint(insns: &[Insn], license: &str) -> anyhow::Result<Self> {
+        let license = CString::new(license)?;
+        let mut attr = ProgLoadAttr {
+            prog_type: BPF_PROG_TYPE_TRACEPOINT,
+            insn_cnt: insns.len() as u32,
+            insns: insns.as_ptr() as u64,
+            license: license.as_ptr() as u64,
+            ..Default::default()
+        };
+        match bpf_fd(BPF_PROG_LOAD, &mut attr) {
+            Ok(fd) => Ok(Self { fd }),
+            Err(e) if e.raw_os_error() == Some(libc::EPERM) => {
+                Err(e).context("permission denied, CAP_BPF and CAP_PERFMON (or root) are required")
+            }
+            Err(_) => {
+                // load it again with the verifier log, to explain the failure
+                let mut log = vec![0u8; VERIFIER_LOG_SIZE];
+                attr.log_level = 1;
+                attr.log_size = log.len() as u32;
+                attr.log_buf = log.as_mut_ptr() as u64;
+                let e = bpf_fd(BPF_PROG_LOAD, &mut attr).err();
+                let end = log.iter().position(|b| *b == 0).unwrap_or(log.len());
+                let log = String::from_utf8_lossy(&log[..end]);
+                Err(anyhow!("the BPF program was rejected ({e:?}), verifier log:\n{log}"))
+            }
+        }
+    }
+
+    /// Attaches the program to a tracepoint, for instance `sched/sched_switch`.
+    ///
+    /// The program is detached when the returned [`TracepointLink`] is dropped.
+    pub fn attach_tracepoint(&self, category: &str, name: &str) -> anyhow::Result<TracepointLink> {
+        use perf_event_open_sys as sys;
+
+        let id = tracepoint_id(category, name)?;
+        let mut attr = sys::bindings::perf_event_attr::default();
+        attr.type_ = sys::bindings::PERF_TYPE_TRACEPOINT;
+        attr.size = size_of::<sys::bindings::perf_event_attr>() as u32;
+        attr.config = id;
+        attr.__bindgen_anon_1.sample_period = 1;
+        attr.__bindgen_anon_2.wakeup_events = 1;
+
+        // A BPF program attached to a tracepoint runs on every CPU, even if the perf event is opened on only one CPU.
+        // SAFETY: attr is a valid perf_event_attr
+        let fd = unsafe {
+            sys::perf_event_open(
+                &mut attr,
+                -1,
+                0,
+                -1,
+                sys::bindings::PERF_FLAG_FD_CLOEXEC as libc::c_ulong,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("perf_event_open failed for tracepoint {category}/{name}"));
+        }
+        // SAFETY: perf_event_open returned a new file descriptor
+        let perf_fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // SAFETY: perf_fd is a valid perf event, and self.fd a valid BPF program
+        unsafe {
+            if sys::ioctls::SET_BPF(perf_fd.as_raw_fd(), self.fd.as_raw_fd() as u32) < 0 {
+                return Err(io::Error::last_os_error()).context("could not attach the BPF program to the tracepoint");
+            }
+            if sys::ioctls::ENABLE(perf_fd.as_raw_fd(), 0) < 0 {
+                return Err(io::Error::last_os_error()).context("could not enable the tracepoint");
+            }
+        }
+        Ok(TracepointLink { _perf_fd: perf_fd })
+    }
+}
+
+/// The attachment of a program to a tracepoint.
+pub struct TracepointLink {
+    _perf_fd: OwnedFd,
+}
+
+/// Returns the id of a tracepoint, as given by the tracing filesystem.
+fn tracepoint_id(category: &str, name: &str) -> anyhow::Result<u64> {
+    for tracefs in TRACEFS_PATHS {
+        let path = Path::new(tracefs).join("events").join(category).join(name).join("id");
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            return content
+                .trim()
+                .parse()
+                .with_context(|| format!("invalid tracepoint id in {path:?}"));
+        }
+    }
+    Err(anyhow!(
+        "tracepoint {category}/{name} not found, is the tracing filesystem mounted on {}?",
+        TRACEFS_PATHS[0]
+    ))
+}
@@ -0,0 +1,115 @@
+//! Resolution of the cgroup ids returned by `bpf_get_current_cgroup_id`.
+
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+};
+
+/// Magic number of the cgroup v2 filesystem, see `statfs(2)`.
+const CGROUP2_SUPER_MAGIC: i64 = 0x63677270;
+
+/// Maps the ids of the cgroups (v2) to their path.
+///
+/// The id of a cgroup is the inode number of its directory in the cgroup filesystem.
+pub struct CgroupResolver {
+    root: PathBuf,
+    paths: HashMap<u64, String>,
+}
+
+impl CgroupResolver {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            paths: HashMap::new(),
+        }
+    }
+
+    /// Returns the canonical path of the cgroup, such as `/system.slice/ssh.service`.
+    ///
+    /// If the id is unknown and `refreshed` is false, the hierarchy is walked again and `refreshed` is set to true.
+    /// This allows to walk the hierarchy at most once per measurement.
+    pub fn resolve(&mut self, id: u64, refreshed: &mut bool) -> Option<&str> {
+        if !self.paths.contains_key(&id) && !*refreshed {
+            self.refresh();
+            *refreshed = true;
+        }
+        self.paths.get(&id).map(String::as_str)
+    }
+
+    /// Walks the cgroup hierarchy to find the id of every cgroup.
+    pub fn refresh(&mut self) {
+        self.paths.clear();
+        let mut stack = vec![self.root.clone()];
+        while let Some(dir) = stack.pop() {
+            let Ok(metadata) = std::fs::metadata(&dir) else {
+                // the cgroup has been removed in the meantime
+                continue;
+            };
+            self.paths.insert(metadata.ino(), canonical_path(&self.root, &dir));
+            if let Ok(entries) = std::fs::read_dir(&dir) {
+                for entry in entries.flatten() {
+                    if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                        stack.push(entry.path());
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Finds the mount point of the cgroup v2 filesystem: `root` itself, or `root/unified` in the "hybrid" mode of systemd.
+pub fn find_cgroup2_root(root: &Path) -> Option<PathBuf> {
+    [root.to_owned(), root.join("unified")]
+        .into_iter()
+        .find(|path| is_cgroup2(path))
+}
+
+fn is_cgroup2(path: &Path) -> bool {
+    let Ok(c_path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is a valid C string and stat a valid statfs struct
+    let res = unsafe { libc::statfs(c_path.as_ptr(), &mut stat) };
+    res == 0 && stat.f_type as i64 == CGROUP2_SUPER_MAGIC
+}
+
+fn canonical_path(root: &Path, dir: &Path) -> String {
+    let relative = dir.strip_prefix(root).unwrap_or(dir);
+    format!("/{}", relative.to_string_lossy())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::MetadataExt;
+
+    use super::CgroupResolver;
+
+    #[test]
+    fn resolve() {
+        let root = tempfile::tempdir().unwrap();
+        let service = root.path().join("system.slice/ssh.service");
+        std::fs::create_dir_all(&service).unwrap();
+        let root_id = std::fs::metadata(root.path()).unwrap().ino();
+        let service_id = std::fs::metadata(&service).unwrap().ino();
+
+        let mut resolver = CgroupResolver::new(root.path().to_owned());
+        let mut refreshed = false;
+        assert_eq!(resolver.resolve(root_id, &mut refreshed), Some("/"));
+        assert!(refreshed);
+        assert_eq!(
+            resolver.resolve(service_id, &mut refreshed),
+            Some("/system.slice/ssh.service")
+        );
+
+        // created after the walk: not found until the next refresh
+        let user = root.path().join("user.slice");
+        std::fs::create_dir(&user).unwrap();
+        let user_id = std::fs::metadata(&user).unwrap().ino();
+        assert_eq!(resolver.resolve(user_id, &mut refreshed), None);
+        let mut refreshed = false;
+        assert_eq!(resolver.resolve(user_id, &mut refreshed), Some("/user.slice"));
+    }
+}
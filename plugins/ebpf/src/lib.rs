@@ -0,0 +1,126 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{os::fd::AsRawFd, path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+    units::{PrefixedUnit, Unit},
+};
+
+use crate::{
+    bpf::{PerCpuArray, Program},
+    cgroup::CgroupResolver,
+    program::Maps,
+    source::CpuTimeSource,
+};
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("This plugin only works on Linux.");
+
+mod bpf;
+mod cgroup;
+mod program;
+mod source;
+
+pub struct EbpfPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for EbpfPlugin {
+    fn name() -> &'static str {
+        "ebpf"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(EbpfPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        let metric = alumet.create_metric(
+            "ebpf_cpu_time_delta",
+            PrefixedUnit::nano(Unit::Second),
+            "Time spent executing on the CPU since the previous measurement, measured with eBPF",
+        )?;
+
+        let last_switch =
+            PerCpuArray::create(1).context("could not create the BPF map of the last context switches")?;
+        let process_time =
+            bpf::HashMap::create(self.config.max_processes).context("could not create the BPF map of the processes")?;
+        let cgroup_time =
+            bpf::HashMap::create(self.config.max_cgroups).context("could not create the BPF map of the cgroups")?;
+        let insns = program::sched_switch_program(&Maps {
+            last_switch: last_switch.as_raw_fd(),
+            process_time: process_time.as_raw_fd(),
+            cgroup_time: cgroup_time.as_raw_fd(),
+        });
+        let program = Program::load_tracepoint(&insns, "GPL").context("could not load the BPF program")?;
+        // the program keeps a reference to its maps, and the perf event a reference to the program
+        let link = program.attach_tracepoint("sched", "sched_switch")?;
+
+        let cgroups = cgroup::find_cgroup2_root(&self.config.cgroup_root).map(CgroupResolver::new);
+        if cgroups.is_none() {
+            log::warn!(
+                "No cgroup v2 filesystem found in {:?}, the CPU time of the cgroups will not be measured.",
+                self.config.cgroup_root
+            );
+        }
+        let source = CpuTimeSource::new(metric, process_time, cgroup_time, cgroups, link);
+        let trigger = TriggerSpec::builder(self.config.poll_interval)
+            .flush_interval(self.config.flush_interval)
+            .build()?;
+        alumet.add_source("cpu_time", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Interval between two flushing of the measurements.
+    #[serde(with = "humantime_serde")]
+    pub flush_interval: Duration,
+
+    /// Maximum number of processes tracked at the same time.
+    pub max_processes: u32,
+
+    /// Maximum number of cgroups tracked at the same time.
+    pub max_cgroups: u32,
+
+    /// Mount point of the cgroup filesystem, used to find the path of the cgroups.
+    /// In the "hybrid" mode, the cgroup v2 filesystem is found in its `unified` subdirectory.
+    pub cgroup_root: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            flush_interval: Duration::from_secs(5),
+            max_processes: 32768,
+            max_cgroups: 4096,
+            cgroup_root: PathBuf::from("/sys/fs/cgroup"),
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! The BPF program that measures the CPU time of the processes and cgroups.
+//!
+//! The program is attached to the `sched/sched_switch` tracepoint. When a task leaves a CPU,
+//! the time elapsed since the previous context switch on this CPU is the time during which the task ran.
+//! This duration is added to the CPU time of its process (tgid) and of its cgroup, in two hash maps.
+//! The idle task is ignored.
+//!
+//! The program does not read the arguments of the tracepoint, whose layout depends on the kernel version.
+//! It is written in BPF assembly, in order to avoid the need of a BPF compiler.
+
+use std::collections::HashMap;
+
+// instruction classes
+const BPF_LDX: u8 = 0x01;
+const BPF_ST: u8 = 0x02;
+const BPF_STX: u8 = 0x03;
+const BPF_JMP: u8 = 0x05;
+const BPF_ALU64: u8 = 0x07;
+// sizes
+const BPF_W: u8 = 0x00;
+const BPF_DW: u8 = 0x18;
+// modes
+const BPF_IMM: u8 = 0x00;
+const BPF_MEM: u8 = 0x60;
+const BPF_ATOMIC: u8 = 0xc0;
+// operations
+const BPF_ADD: u8 = 0x00;
+const BPF_SUB: u8 = 0x10;
+const BPF_RSH: u8 = 0x70;
+const BPF_MOV: u8 = 0xb0;
+const BPF_JEQ: u8 = 0x10;
+const BPF_JNE: u8 = 0x50;
+const BPF_CALL: u8 = 0x80;
+const BPF_EXIT: u8 = 0x90;
+// source of the operand
+const BPF_K: u8 = 0x00;
+const BPF_X: u8 = 0x08;
+
+/// Marks a 64-bit immediate load as the file descriptor of a map.
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+
+// helper functions
+const MAP_LOOKUP_ELEM: i32 = 1;
+const MAP_UPDATE_ELEM: i32 = 2;
+const KTIME_GET_NS: i32 = 5;
+const GET_CURRENT_PID_TGID: i32 = 14;
+const GET_CURRENT_CGROUP_ID: i32 = 80;
+
+/// Flag of `map_update_elem`: only create the element if it does not exist.
+const BPF_NOEXIST: i32 = 1;
+
+// registers
+const R0: u8 = 0;
+const R1: u8 = 1;
+const R2: u8 = 2;
+const R3: u8 = 3;
+const R4: u8 = 4;
+/// Current time, then duration of the time slice.
+const R6: u8 = 6;
+const R7: u8 = 7;
+/// Frame pointer (read-only).
+const R10: u8 = 10;
+
+// stack slots (offsets from the frame pointer)
+const STACK_CPU_KEY: i16 = -4;
+const STACK_PID_KEY: i16 = -8;
+const STACK_VALUE: i16 = -16;
+const STACK_CGROUP_KEY: i16 = -24;
+
+/// A BPF instruction, as expected by the kernel.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Insn {
+    code: u8,
+    /// Destination register (low 4 bits) and source register (high 4 bits).
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+impl Insn {
+    fn new(code: u8, dst: u8, src: u8, off: i16, imm: i32) -> Self {
+        Self {
+            code,
+            regs: (src << 4) | dst,
+            off,
+            imm,
+        }
+    }
+}
+
+/// File descriptors of the maps used by the program.
+pub struct Maps {
+    /// `PERCPU_ARRAY` with one `u64` entry: time of the last context switch on the CPU.
+    pub last_switch: i32,
+    /// `HASH` from the tgid (`u32`) to the CPU time (`u64`, in nanoseconds).
+    pub process_time: i32,
+    /// `HASH` from the cgroup id (`u64`) to the CPU time (`u64`, in nanoseconds).
+    pub cgroup_time: i32,
+}
+
+/// Generates the program.
+pub fn sched_switch_program(maps: &Maps) -> Vec<Insn> {
+    let mut asm = Assembler::default();
+    // r6 = now, compute the time elapsed since the previous switch on this CPU
+    asm.call(KTIME_GET_NS);
+    asm.mov_reg(R6, R0);
+    asm.store_imm_w(R10, STACK_CPU_KEY, 0);
+    asm.lookup(maps.last_switch, STACK_CPU_KEY);
+    asm.jump_if_imm(BPF_JEQ, R0, 0, "exit");
+    asm.load_dw(R7, R0, 0);
+    asm.store_dw(R0, 0, R6);
+    asm.jump_if_imm(BPF_JEQ, R7, 0, "exit");
+    asm.alu_reg(BPF_SUB, R6, R7);
+
+    // the task that leaves the CPU is still the current task: get its process, ignore the idle task
+    asm.call(GET_CURRENT_PID_TGID);
+    asm.alu_imm(BPF_RSH, R0, 32);
+    asm.jump_if_imm(BPF_JEQ, R0, 0, "exit");
+    asm.store_w(R10, STACK_PID_KEY, R0);
+    asm.add_to_map(maps.process_time, STACK_PID_KEY, "process");
+
+    asm.call(GET_CURRENT_CGROUP_ID);
+    asm.store_dw(R10, STACK_CGROUP_KEY, R0);
+    asm.add_to_map(maps.cgroup_time, STACK_CGROUP_KEY, "cgroup");
+
+    asm.label("exit");
+    asm.mov_imm(R0, 0);
+    asm.exit();
+    asm.finish()
+}
+
+/// Generates the instructions with symbolic jumps.
+#[derive(Default)]
+struct Assembler {
+    insns: Vec<Insn>,
+    labels: HashMap<String, usize>,
+    /// Jump instructions and their target label.
+    jumps: Vec<(usize, String)>,
+}
+
+impl Assembler {
+    fn push(&mut self, insn: Insn) {
+        self.insns.push(insn);
+    }
+
+    fn label(&mut self, name: &str) {
+        let previous = self.labels.insert(name.to_owned(), self.insns.len());
+        debug_assert!(previous.is_none(), "duplicate label {name}");
+    }
+
+    fn mov_reg(&mut self, dst: u8, src: u8) {
+        self.push(Insn::new(BPF_ALU64 | BPF_MOV | BPF_X, dst, src, 0, 0));
+    }
+
+    fn mov_imm(&mut self, dst: u8, imm: i32) {
+        self.push(Insn::new(BPF_ALU64 | BPF_MOV | BPF_K, dst, 0, 0, imm));
+    }
+
+    fn alu_reg(&mut self, op: u8, dst: u8, src: u8) {
+        self.push(Insn::new(BPF_ALU64 | op | BPF_X, dst, src, 0, 0));
+    }
+
+    fn alu_imm(&mut self, op: u8, dst: u8, imm: i32) {
+        self.push(Insn::new(BPF_ALU64 | op | BPF_K, dst, 0, 0, imm));
+    }
+
+    fn load_dw(&mut self, dst: u8, src: u8, off: i16) {
+        self.push(Insn::new(BPF_LDX | BPF_MEM | BPF_DW, dst, src, off, 0));
+    }
+
+    fn store_dw(&mut self, dst: u8, off: i16, src: u8) {
+        self.push(Insn::new(BPF_STX | BPF_MEM | BPF_DW, dst, src, off, 0));
+    }
+
+    fn store_w(&mut self, dst: u8, off: i16, src: u8) {
+        self.push(Insn::new(BPF_STX | BPF_MEM | BPF_W, dst, src, off, 0));
+    }
+
+    fn store_imm_w(&mut self, dst: u8, off: i16, imm: i32) {
+        self.push(Insn::new(BPF_ST | BPF_MEM | BPF_W, dst, 0, off, imm));
+    }
+
+    /// `lock *(u64 *)(dst + off) += src`
+    fn atomic_add_dw(&mut self, dst: u8, off: i16, src: u8) {
+        self.push(Insn::new(BPF_STX | BPF_ATOMIC | BPF_DW, dst, src, off, BPF_ADD as i32));
+    }
+
+    fn load_map_fd(&mut self, dst: u8, fd: i32) {
+        // 64-bit immediate load, which spans two instructions
+        self.push(Insn::new(BPF_DW | BPF_IMM, dst, BPF_PSEUDO_MAP_FD, 0, fd));
+        self.push(Insn::new(0, 0, 0, 0, 0));
+    }
+
+    /// `r2 = fp + off`
+    fn stack_pointer(&mut self, dst: u8, off: i16) {
+        self.mov_reg(dst, R10);
+        self.alu_imm(BPF_ADD, dst, off as i32);
+    }
+
+    fn jump_if_imm(&mut self, op: u8, dst: u8, imm: i32, label: &str) {
+        self.jumps.push((self.insns.len(), label.to_owned()));
+        self.push(Insn::new(BPF_JMP | op | BPF_K, dst, 0, 0, imm));
+    }
+
+    fn call(&mut self, helper: i32) {
+        self.push(Insn::new(BPF_JMP | BPF_CALL, 0, 0, 0, helper));
+    }
+
+    fn exit(&mut self) {
+        self.push(Insn::new(BPF_JMP | BPF_EXIT, 0, 0, 0, 0));
+    }
+
+    /// `r0 = map_lookup_elem(map, fp + key)`
+    fn lookup(&mut self, map_fd: i32, key: i16) {
+        self.load_map_fd(R1, map_fd);
+        self.stack_pointer(R2, key);
+        self.call(MAP_LOOKUP_ELEM);
+    }
+
+    /// Adds r6 to the value associated to the key stored on the stack, creating the entry if needed.
+    ///
+    /// Clobbers r0 to r5 and the value slot of the stack.
+    fn add_to_map(&mut self, map_fd: i32, key: i16, name: &str) {
+        let found = format!("{name}_found");
+        let done = format!("{name}_done");
+        self.lookup(map_fd, key);
+        self.jump_if_imm(BPF_JNE, R0, 0, &found);
+        // insert the first value
+        self.store_dw(R10, STACK_VALUE, R6);
+        self.load_map_fd(R1, map_fd);
+        self.stack_pointer(R2, key);
+        self.stack_pointer(R3, STACK_VALUE);
+        self.mov_imm(R4, BPF_NOEXIST);
+        self.call(MAP_UPDATE_ELEM);
+        self.jump_if_imm(BPF_JEQ, R0, 0, &done);
+        // the entry has been created on another CPU in the meantime, or the map is full
+        self.lookup(map_fd, key);
+        self.jump_if_imm(BPF_JEQ, R0, 0, &done);
+        self.label(&found);
+        self.atomic_add_dw(R0, 0, R6);
+        self.label(&done);
+    }
+
+    /// Resolves the jumps and returns the instructions.
+    fn finish(mut self) -> Vec<Insn> {
+        for (i, label) in self.jumps {
+            let target = self.labels[&label];
+            // the offset is relative to the next instruction
+            self.insns[i].off = (target as isize - i as isize - 1) as i16;
+        }
+        self.insns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jumps() {
+        let mut asm = Assembler::default();
+        asm.label("start");
+        asm.jump_if_imm(BPF_JEQ, R0, 0, "end");
+        asm.mov_imm(R0, 1);
+        asm.jump_if_imm(BPF_JNE, R0, 0, "start");
+        asm.label("end");
+        asm.exit();
+        let insns = asm.finish();
+        assert_eq!(insns[0], Insn::new(0x15, 0, 0, 2, 0));
+        assert_eq!(insns[1], Insn::new(0xb7, 0, 0, 0, 1));
+        assert_eq!(insns[2], Insn::new(0x55, 0, 0, -3, 0));
+        assert_eq!(insns[3], Insn::new(0x95, 0, 0, 0, 0));
+    }
+
+    #[test]
+    fn encoding() {
+        let mut asm = Assembler::default();
+        asm.load_map_fd(R1, 42);
+        asm.store_w(R10, -8, R0);
+        asm.atomic_add_dw(R0, 0, R6);
+        asm.alu_imm(BPF_RSH, R0, 32);
+        let insns = asm.finish();
+        assert_eq!(std::mem::size_of::<Insn>(), 8);
+        assert_eq!(insns[0], Insn::new(0x18, 1, 1, 0, 42));
+        assert_eq!(insns[0].regs, 0x11);
+        assert_eq!(insns[1], Insn::new(0, 0, 0, 0, 0));
+        assert_eq!(insns[2], Insn::new(0x63, 10, 0, -8, 0));
+        assert_eq!(insns[2].regs, 0x0a);
+        assert_eq!(insns[3], Insn::new(0xdb, 0, 6, 0, 0));
+        assert_eq!(insns[4], Insn::new(0x77, 0, 0, 0, 32));
+    }
+
+    #[test]
+    fn program() {
+        let maps = Maps {
+            last_switch: 3,
+            process_time: 4,
+            cgroup_time: 5,
+        };
+        let insns = sched_switch_program(&maps);
+        // every jump stays in the program
+        for (i, insn) in insns.iter().enumerate() {
+            if insn.code & 0x07 == BPF_JMP && insn.code & 0xf0 != BPF_CALL && insn.code & 0xf0 != BPF_EXIT {
+                let target = i as isize + 1 + insn.off as isize;
+                assert!(
+                    target > i as isize && target < insns.len() as isize,
+                    "invalid jump at {i}"
+                );
+            }
+        }
+        assert_eq!(insns.last().unwrap().code, BPF_JMP | BPF_EXIT);
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::HashMap, path::Path};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    resources::{Resource, ResourceConsumer},
+};
+use anyhow::Context;
+
+use crate::{
+    bpf::{self, MapKey, TracepointLink},
+    cgroup::CgroupResolver,
+};
+
+/// Measurement source that reads the CPU time accumulated by the BPF program.
+pub struct CpuTimeSource {
+    metric: TypedMetricId<u64>,
+    process_time: bpf::HashMap<u32>,
+    cgroup_time: bpf::HashMap<u64>,
+    /// Values of the previous measurement, to compute the increments.
+    previous_process_time: HashMap<u32, u64>,
+    previous_cgroup_time: HashMap<u64, u64>,
+    /// Finds the path of the cgroups, `None` if there is no cgroup v2 filesystem.
+    cgroups: Option<CgroupResolver>,
+    /// Keeps the program attached while the source exists.
+    _link: TracepointLink,
+}
+
+impl CpuTimeSource {
+    pub fn new(
+        metric: TypedMetricId<u64>,
+        process_time: bpf::HashMap<u32>,
+        cgroup_time: bpf::HashMap<u64>,
+        cgroups: Option<CgroupResolver>,
+        link: TracepointLink,
+    ) -> Self {
+        Self {
+            metric,
+            process_time,
+            cgroup_time,
+            previous_process_time: HashMap::new(),
+            previous_cgroup_time: HashMap::new(),
+            cgroups,
+            _link: link,
+        }
+    }
+}
+
+impl Source for CpuTimeSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let mut point = |consumer, value| {
+            measurements.push(MeasurementPoint::new(
+                timestamp,
+                self.metric,
+                Resource::LocalMachine,
+                consumer,
+                value,
+            ))
+        };
+
+        let processes = self
+            .process_time
+            .entries()
+            .context("could not read the CPU time of the processes")?;
+        for (pid, delta) in increments(processes, &mut self.previous_process_time) {
+            if delta > 0 {
+                point(ResourceConsumer::Process { pid }, delta);
+            }
+            // remove the processes that have exited, to free some space in the map
+            if !Path::new(&format!("/proc/{pid}")).exists() {
+                remove(&self.process_time, pid, &mut self.previous_process_time)?;
+            }
+        }
+
+        let Some(resolver) = &mut self.cgroups else {
+            return Ok(());
+        };
+        let cgroups = self
+            .cgroup_time
+            .entries()
+            .context("could not read the CPU time of the cgroups")?;
+        let mut refreshed = false;
+        for (id, delta) in increments(cgroups, &mut self.previous_cgroup_time) {
+            match resolver.resolve(id, &mut refreshed) {
+                Some(path) => {
+                    if delta > 0 {
+                        let consumer = ResourceConsumer::ControlGroup {
+                            path: path.to_owned().into(),
+                        };
+                        point(consumer, delta);
+                    }
+                }
+                // the cgroup has been removed
+                None => remove(&self.cgroup_time, id, &mut self.previous_cgroup_time)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Computes the increments of the counters since the previous measurement, and updates `previous`.
+///
+/// The counters that were not in `previous` are new, and start at zero.
+fn increments<K: MapKey + Eq + std::hash::Hash>(
+    counters: Vec<(K, u64)>,
+    previous: &mut HashMap<K, u64>,
+) -> Vec<(K, u64)> {
+    counters
+        .into_iter()
+        .map(|(key, value)| {
+            let prev = previous.insert(key, value).unwrap_or(0);
+            (key, value.saturating_sub(prev))
+        })
+        .collect()
+}
+
+fn remove<K: MapKey + Eq + std::hash::Hash>(
+    map: &bpf::HashMap<K>,
+    key: K,
+    previous: &mut HashMap<K, u64>,
+) -> anyhow::Result<()> {
+    map.remove(key).context("could not remove an entry of a BPF map")?;
+    previous.remove(&key);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::increments;
+
+    #[test]
+    fn counter_increments() {
+        let mut previous = HashMap::new();
+        let res = increments(vec![(1u32, 100), (2, 50)], &mut previous);
+        assert_eq!(res, vec![(1, 100), (2, 50)]);
+        let res = increments(vec![(1u32, 150), (2, 50), (3, 10)], &mut previous);
+        assert_eq!(res, vec![(1, 50), (2, 0), (3, 10)]);
+        assert_eq!(previous, HashMap::from([(1, 150), (2, 50), (3, 10)]));
+    }
+}
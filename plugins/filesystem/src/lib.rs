@@ -0,0 +1,90 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::source::{FilesystemSource, Metrics, Usage};
+
+mod source;
+
+/// Measures the capacity and the usage of filesystems.
+pub struct FilesystemPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for FilesystemPlugin {
+    fn name() -> &'static str {
+        "filesystem"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(FilesystemPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let mut mount_points = Vec::with_capacity(self.config.mount_points.len());
+        for path in &self.config.mount_points {
+            match Usage::of(path) {
+                Ok(usage) => {
+                    log::info!("Found filesystem {path:?} ({} bytes)", usage.total_bytes);
+                    mount_points.push(path.to_owned());
+                }
+                Err(e) => log::warn!("{path:?} will not be measured: {e:#}"),
+            }
+        }
+        if mount_points.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: none of the mount points {:?} is accessible",
+                self.config.mount_points
+            ));
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = FilesystemSource::new(mount_points, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("usage", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// The mount points of the filesystems to measure.
+    pub mount_points: Vec<PathBuf>,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(30),
+            mount_points: vec![PathBuf::from("/")],
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::Context;
+use nix::sys::statvfs::{Statvfs, statvfs};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    space: TypedMetricId<u64>,
+    inodes: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            space: alumet.create_metric("filesystem_space", Unit::Byte, "Space of the filesystem, by kind")?,
+            inodes: alumet.create_metric(
+                "filesystem_inodes",
+                Unit::Unity,
+                "Number of inodes (files, directories, etc.) of the filesystem, by kind",
+            )?,
+        })
+    }
+}
+
+/// Capacity and usage of a filesystem.
+#[derive(Debug, PartialEq)]
+pub struct Usage {
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub available_bytes: u64,
+    pub total_inodes: u64,
+    pub used_inodes: u64,
+    pub available_inodes: u64,
+}
+
+impl Usage {
+    /// Queries the usage of the filesystem that contains `path`.
+    pub fn of(path: &Path) -> anyhow::Result<Self> {
+        let stat = statvfs(path).with_context(|| format!("statvfs failed on {path:?}"))?;
+        Ok(Self::from(stat))
+    }
+}
+
+impl From<Statvfs> for Usage {
+    // the types of the fields depend on the platform, they are not always u64
+    #[allow(clippy::unnecessary_cast)]
+    fn from(stat: Statvfs) -> Self {
+        // The sizes are expressed in fragments, whose size is `f_frsize`.
+        // `used` is computed like df: the blocks reserved for root are neither used nor available.
+        let fragment_size = stat.fragment_size() as u64;
+        let total_blocks = stat.blocks() as u64;
+        let free_blocks = stat.blocks_free() as u64;
+        let total_inodes = stat.files() as u64;
+        let free_inodes = stat.files_free() as u64;
+        Self {
+            total_bytes: total_blocks * fragment_size,
+            used_bytes: total_blocks.saturating_sub(free_blocks) * fragment_size,
+            available_bytes: stat.blocks_available() as u64 * fragment_size,
+            total_inodes,
+            used_inodes: total_inodes.saturating_sub(free_inodes),
+            available_inodes: stat.files_available() as u64,
+        }
+    }
+}
+
+/// Measurement source that queries the usage of the filesystems.
+pub struct FilesystemSource {
+    mount_points: Vec<PathBuf>,
+    metrics: Metrics,
+}
+
+impl FilesystemSource {
+    pub fn new(mount_points: Vec<PathBuf>, metrics: Metrics) -> Self {
+        Self { mount_points, metrics }
+    }
+}
+
+impl Source for FilesystemSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for mount_point in &self.mount_points {
+            let usage = Usage::of(mount_point)?;
+            let mount_point = mount_point.to_string_lossy().into_owned();
+            let point = |metric, value: u64, kind: &'static str| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("mount_point", mount_point.clone())
+                .with_attr("kind", kind)
+            };
+            measurements.push(point(self.metrics.space, usage.total_bytes, "total"));
+            measurements.push(point(self.metrics.space, usage.used_bytes, "used"));
+            measurements.push(point(self.metrics.space, usage.available_bytes, "available"));
+            // some filesystems, like btrfs, have no fixed number of inodes and report 0
+            if usage.total_inodes > 0 {
+                measurements.push(point(self.metrics.inodes, usage.total_inodes, "total"));
+                measurements.push(point(self.metrics.inodes, usage.used_inodes, "used"));
+                measurements.push(point(self.metrics.inodes, usage.available_inodes, "available"));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_filesystem::{Config, FilesystemPlugin};
+use pretty_assertions::assert_eq;
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_mount_point() {
+    let root = tempdir().unwrap();
+    let config = Config {
+        mount_points: vec![root.path().join("missing")],
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(
+        agent.is_err(),
+        "the plugin should fail to start (no accessible mount point)"
+    );
+}
+
+#[test]
+fn plugin_with_mount_points() {
+    let root = tempdir().unwrap();
+    let mount_point = root.path().to_string_lossy().into_owned();
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        // the missing mount point is ignored
+        mount_points: vec![root.path().to_path_buf(), root.path().join("missing")],
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<u64>("filesystem_space", Unit::Byte)
+        .expect_metric::<u64>("filesystem_inodes", Unit::Unity)
+        .expect_source("filesystem", "usage");
+
+    let runtime = RuntimeExpectations::new().test_source(
+        SourceName::from_str("filesystem", "usage"),
+        || {},
+        move |ctx| {
+            let m = ctx.measurements();
+            let metrics = ctx.metrics();
+            let space: Vec<_> = m
+                .iter()
+                .filter(|p| metrics.by_id(&p.metric).unwrap().name == "filesystem_space")
+                .map(|p| {
+                    let attr = |key: &str| p.attributes().find(|(k, _)| *k == key).unwrap().1.to_string();
+                    assert_eq!(attr("mount_point"), mount_point);
+                    (attr("kind"), p.value.as_u64())
+                })
+                .collect();
+            let kinds: Vec<_> = space.iter().map(|(kind, _)| kind.as_str()).collect();
+            assert_eq!(kinds, vec!["total", "used", "available"]);
+            let (total, used, available) = (space[0].1, space[1].1, space[2].1);
+            assert!(total > 0);
+            assert!(used + available <= total);
+        },
+    );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<FilesystemPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
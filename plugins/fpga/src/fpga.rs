@@ -0,0 +1,284 @@
+//! Discovery and reading of the sensors of the datacenter FPGA boards, with the sysfs of their drivers.
+//!
+//! - Xilinx Alveo boards (XRT drivers): the management function (`xclmgmt`) exposes the sensors of the
+//!   Xilinx management controller (XMC) in a `xmc.*` directory, and the user function (`xocl`) exposes
+//!   the statistics of the compute units in `kds_custat`. These are the values displayed by `xbutil`.
+//! - Intel PAC and Agilex boards (DFL drivers, used by OPAE): the FPGA management engine (FME)
+//!   exposes its power and temperature as hwmon devices.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, anyhow};
+
+/// Power rails of the Alveo boards, measured by the XMC: voltage in mV, current in mA.
+const XMC_RAILS: [&str; 4] = ["12v_pex", "12v_aux", "3v3_pex", "3v3_aux"];
+
+/// An FPGA board, identified by its PCI slot, such as `0000:3b:00`.
+#[derive(Debug, Default)]
+pub struct Board {
+    pub id: String,
+    /// Directory of the Xilinx management controller.
+    xmc: Option<PathBuf>,
+    /// Power of the Intel FME, in µW.
+    fme_power: Option<PathBuf>,
+    /// Temperature of the Intel FME, in m°C.
+    fme_temperature: Option<PathBuf>,
+    /// Statistics of the compute units of a Xilinx board.
+    cu_stats: Option<PathBuf>,
+}
+
+impl Board {
+    pub fn has_sensors(&self) -> bool {
+        self.xmc.is_some() || self.fme_power.is_some() || self.fme_temperature.is_some()
+    }
+
+    pub fn has_compute_units(&self) -> bool {
+        self.cu_stats.is_some()
+    }
+
+    /// Stops the measurement of the compute units.
+    pub fn ignore_compute_units(&mut self) {
+        self.cu_stats = None;
+    }
+
+    /// Reads the power of the board, in Watts.
+    pub fn read_power(&self) -> Option<anyhow::Result<f64>> {
+        if let Some(xmc) = &self.xmc {
+            return Some(read_xmc_power(xmc));
+        }
+        self.fme_power
+            .as_deref()
+            .map(|path| Ok(read_u64(path)? as f64 / 1_000_000.0))
+    }
+
+    /// Reads the temperature of the FPGA, in °C.
+    pub fn read_temperature(&self) -> Option<anyhow::Result<f64>> {
+        if let Some(xmc) = &self.xmc {
+            return Some(read_u64(&xmc.join("xmc_fpga_temp")).map(|t| t as f64));
+        }
+        self.fme_temperature
+            .as_deref()
+            .map(|path| Ok(read_u64(path)? as f64 / 1000.0))
+    }
+
+    /// Reads the number of executions of each compute unit, by address of the unit.
+    pub fn read_cu_executions(&self) -> Option<anyhow::Result<Vec<(String, u64)>>> {
+        self.cu_stats.as_deref().map(|path| {
+            let content =
+                std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+            parse_cu_stats(&content).with_context(|| format!("invalid content of {}", path.display()))
+        })
+    }
+}
+
+fn read_xmc_power(xmc: &Path) -> anyhow::Result<f64> {
+    let mut microwatts = 0;
+    for rail in XMC_RAILS {
+        let voltage = xmc.join(format!("xmc_{rail}_vol"));
+        let current = xmc.join(format!("xmc_{rail}_curr"));
+        // not every board has every rail
+        if voltage.exists() && current.exists() {
+            microwatts += read_u64(&voltage)? * read_u64(&current)?;
+        }
+    }
+    Ok(microwatts as f64 / 1_000_000.0)
+}
+
+/// Parses the statistics of the compute units, one line per unit: `CU[@0x1800000] : 42 status : 4`.
+fn parse_cu_stats(content: &str) -> anyhow::Result<Vec<(String, u64)>> {
+    content
+        .lines()
+        .filter(|line| line.starts_with("CU["))
+        .map(|line| {
+            let (address, rest) = line
+                .strip_prefix("CU[@")
+                .and_then(|l| l.split_once(']'))
+                .ok_or_else(|| anyhow!("invalid line {line:?}"))?;
+            let usage = rest
+                .trim_start_matches([' ', ':'])
+                .split_whitespace()
+                .next()
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| anyhow!("invalid line {line:?}"))?;
+            Ok((address.to_owned(), usage))
+        })
+        .collect()
+}
+
+fn read_u64(path: &Path) -> anyhow::Result<u64> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    content
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid value in {}: {content:?}", path.display()))
+}
+
+/// Explores the PCI devices to find the FPGA boards.
+///
+/// ## Expected file layout
+///
+/// ```txt
+/// /sys/bus/pci/devices/
+/// |− 0000:3b:00.0                 (Xilinx, management function)
+///     |− xmc.u.12582912
+///         |− xmc_12v_pex_vol
+///         |− xmc_12v_pex_curr
+///         |− xmc_fpga_temp
+///         |− …
+/// |− 0000:3b:00.1                 (Xilinx, user function)
+///     |− kds_custat
+/// |− 0000:5e:00.0                 (Intel)
+///     |− fpga_region
+///         |− region0
+///             |− dfl-fme.0
+///                 |− hwmon
+///                     |− hwmon3
+///                         |− name (dfl_fme_power)
+///                         |− power1_input
+///                     |− hwmon4
+///                         |− name (dfl_fme_thermal)
+///                         |− temp1_input
+/// ```
+///
+/// The functions of the same device (same PCI slot) belong to the same board.
+pub fn explore(pci_path: &Path) -> anyhow::Result<Vec<Board>> {
+    let mut boards: BTreeMap<String, Board> = BTreeMap::new();
+    for entry in std::fs::read_dir(pci_path).with_context(|| format!("failed to read dir {pci_path:?}"))? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let slot = name.rsplit_once('.').map_or(name.as_str(), |(slot, _function)| slot);
+        let path = entry.path();
+
+        let xmc = find_xmc(&path);
+        let cu_stats = Some(path.join("kds_custat")).filter(|p| p.is_file());
+        let (fme_power, fme_temperature) = find_fme_hwmon(&path);
+        if xmc.is_none() && cu_stats.is_none() && fme_power.is_none() && fme_temperature.is_none() {
+            continue;
+        }
+
+        let board = boards.entry(slot.to_owned()).or_insert_with(|| Board {
+            id: slot.to_owned(),
+            ..Default::default()
+        });
+        board.xmc = board.xmc.take().or(xmc);
+        board.cu_stats = board.cu_stats.take().or(cu_stats);
+        board.fme_power = board.fme_power.take().or(fme_power);
+        board.fme_temperature = board.fme_temperature.take().or(fme_temperature);
+    }
+    Ok(boards.into_values().collect())
+}
+
+fn find_xmc(device: &Path) -> Option<PathBuf> {
+    std::fs::read_dir(device)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .find(|e| e.file_name().to_string_lossy().starts_with("xmc") && e.path().join("xmc_12v_pex_vol").is_file())
+        .map(|e| e.path())
+}
+
+fn find_fme_hwmon(device: &Path) -> (Option<PathBuf>, Option<PathBuf>) {
+    let (mut power, mut temperature) = (None, None);
+    let subdirs = |path: &Path| -> Vec<PathBuf> {
+        std::fs::read_dir(path)
+            .map(|ls| ls.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default()
+    };
+    for region in subdirs(&device.join("fpga_region")) {
+        for fme in subdirs(&region) {
+            if !fme
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with("dfl-fme"))
+            {
+                continue;
+            }
+            for hwmon in subdirs(&fme.join("hwmon")) {
+                match std::fs::read_to_string(hwmon.join("name")).as_deref().map(str::trim) {
+                    Ok("dfl_fme_power") => power = Some(hwmon.join("power1_input")).filter(|p| p.is_file()),
+                    Ok("dfl_fme_thermal") => temperature = Some(hwmon.join("temp1_input")).filter(|p| p.is_file()),
+                    _ => (),
+                }
+            }
+        }
+    }
+    (power, temperature)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn write_files(dir: &Path, files: &[(&str, &str)]) {
+        std::fs::create_dir_all(dir).unwrap();
+        for (file, content) in files {
+            std::fs::write(dir.join(file), format!("{content}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn explore_boards() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let root = root.path();
+        // Xilinx Alveo
+        write_files(
+            &root.join("0000:3b:00.0/xmc.u.12582912"),
+            &[
+                ("xmc_12v_pex_vol", "12100"),
+                ("xmc_12v_pex_curr", "2000"),
+                ("xmc_12v_aux_vol", "12000"),
+                ("xmc_12v_aux_curr", "500"),
+                ("xmc_3v3_pex_vol", "3300"),
+                ("xmc_fpga_temp", "45"),
+            ],
+        );
+        write_files(
+            &root.join("0000:3b:00.1"),
+            &[(
+                "kds_custat",
+                "CU[@0x1800000] : 42 status : 4\nCU[@0x1810000] : 0 status : 4",
+            )],
+        );
+        // Intel PAC
+        let fme = root.join("0000:5e:00.0/fpga_region/region0/dfl-fme.0");
+        write_files(
+            &fme.join("hwmon/hwmon3"),
+            &[("name", "dfl_fme_power"), ("power1_input", "45500000")],
+        );
+        write_files(
+            &fme.join("hwmon/hwmon4"),
+            &[("name", "dfl_fme_thermal"), ("temp1_input", "52000")],
+        );
+        // other devices
+        write_files(&root.join("0000:00:1f.0"), &[("vendor", "0x8086")]);
+
+        let boards = explore(root)?;
+        let ids: Vec<_> = boards.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, vec!["0000:3b:00", "0000:5e:00"]);
+
+        let alveo = &boards[0];
+        assert!(alveo.has_sensors() && alveo.has_compute_units());
+        assert_eq!(alveo.read_power().unwrap()?, 30.2);
+        assert_eq!(alveo.read_temperature().unwrap()?, 45.0);
+        assert_eq!(
+            alveo.read_cu_executions().unwrap()?,
+            vec![(String::from("0x1800000"), 42), (String::from("0x1810000"), 0)]
+        );
+
+        let pac = &boards[1];
+        assert!(pac.has_sensors() && !pac.has_compute_units());
+        assert_eq!(pac.read_power().unwrap()?, 45.5);
+        assert_eq!(pac.read_temperature().unwrap()?, 52.0);
+        assert!(pac.read_cu_executions().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_cu_stats() {
+        assert!(parse_cu_stats("CU[@0x1800000] : many status : 4").is_err());
+        assert_eq!(parse_cu_stats("").unwrap(), vec![]);
+    }
+}
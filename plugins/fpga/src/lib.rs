@@ -0,0 +1,100 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::source::{FpgaSource, Metrics};
+
+mod fpga;
+mod source;
+
+/// Measures the power, temperature and activity of the datacenter FPGA boards (Xilinx Alveo, Intel PAC),
+/// with the sysfs of their drivers.
+pub struct FpgaPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for FpgaPlugin {
+    fn name() -> &'static str {
+        "fpga"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(FpgaPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let mut boards = fpga::explore(&self.config.pci_path).context("could not find the FPGA boards")?;
+        if !self.config.compute_units {
+            boards.iter_mut().for_each(|b| b.ignore_compute_units());
+        }
+        boards.retain(|b| b.has_sensors() || b.has_compute_units());
+        if boards.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: no FPGA board found in {:?}",
+                self.config.pci_path
+            ));
+        }
+        for board in &boards {
+            log::debug!(
+                "Found FPGA board {} (sensors: {}, compute units: {})",
+                board.id,
+                board.has_sensors(),
+                board.has_compute_units()
+            );
+        }
+        log::info!("Found {} FPGA boards to measure.", boards.len());
+
+        let metrics = Metrics::new(alumet)?;
+        let source = FpgaSource::new(boards, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("boards", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the PCI devices.
+    pub pci_path: PathBuf,
+
+    /// `true` to measure the executions of the compute units (Xilinx boards only).
+    pub compute_units: bool,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            pci_path: PathBuf::from("/sys/bus/pci/devices"),
+            compute_units: true,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+
+use crate::fpga::Board;
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    power: TypedMetricId<f64>,
+    temperature: TypedMetricId<f64>,
+    cu_executions: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            power: alumet.create_metric("fpga_power", Unit::Watt, "Power consumed by the FPGA board")?,
+            temperature: alumet.create_metric("fpga_temperature", Unit::DegreeCelsius, "Temperature of the FPGA")?,
+            cu_executions: alumet.create_metric(
+                "fpga_cu_executions",
+                Unit::Unity,
+                "Number of executions completed by a compute unit of the FPGA since the previous measurement",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the sensors of the FPGA boards.
+pub struct FpgaSource {
+    boards: Vec<MeasuredBoard>,
+    metrics: Metrics,
+}
+
+struct MeasuredBoard {
+    board: Board,
+    /// The previous number of executions of each compute unit, to compute the difference.
+    previous_executions: HashMap<String, u64>,
+}
+
+impl FpgaSource {
+    pub fn new(boards: Vec<Board>, metrics: Metrics) -> Self {
+        let boards = boards
+            .into_iter()
+            .map(|board| MeasuredBoard {
+                board,
+                previous_executions: HashMap::new(),
+            })
+            .collect();
+        Self { boards, metrics }
+    }
+}
+
+impl Source for FpgaSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for b in &mut self.boards {
+            let resource = Resource::Custom {
+                kind: "fpga".into(),
+                id: b.board.id.clone().into(),
+            };
+            let point = |metric, value| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    resource.clone(),
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+            };
+
+            if let Some(power) = b.board.read_power() {
+                measurements.push(point(self.metrics.power, power?));
+            }
+            if let Some(temperature) = b.board.read_temperature() {
+                measurements.push(point(self.metrics.temperature, temperature?));
+            }
+            if let Some(executions) = b.board.read_cu_executions() {
+                for (cu, count) in executions? {
+                    // Only push deltas, not the baseline value before the plugin starts
+                    if let Some(previous) = b.previous_executions.insert(cu.clone(), count) {
+                        // the counters are reset when a new bitstream is loaded
+                        let delta = count.saturating_sub(previous);
+                        measurements.push(
+                            MeasurementPoint::new(
+                                timestamp,
+                                self.metrics.cu_executions,
+                                resource.clone(),
+                                ResourceConsumer::LocalMachine,
+                                delta,
+                            )
+                            .with_attr("cu", cu),
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_fpga::{Config, FpgaPlugin};
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_board() {
+    let root = tempdir().unwrap();
+    write_files(&root.path().join("0000:00:1f.0"), &[("vendor", "0x8086")]);
+    let config = Config {
+        pci_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no FPGA)");
+}
+
+#[test]
+fn plugin_with_alveo_board() {
+    let root = tempdir().unwrap();
+    let pci_path = root.path().to_path_buf();
+    write_files(
+        &pci_path.join("0000:3b:00.0/xmc.u.12582912"),
+        &[
+            ("xmc_12v_pex_vol", "12000"),
+            ("xmc_12v_pex_curr", "1500"),
+            ("xmc_fpga_temp", "40"),
+        ],
+    );
+    write_files(
+        &pci_path.join("0000:3b:00.1"),
+        &[("kds_custat", "CU[@0x1800000] : 10 status : 4\n")],
+    );
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        pci_path: pci_path.clone(),
+        compute_units: true,
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<f64>("fpga_power", Unit::Watt)
+        .expect_metric::<f64>("fpga_temperature", Unit::DegreeCelsius)
+        .expect_metric::<u64>("fpga_cu_executions", Unit::Unity)
+        .expect_source("fpga", "boards");
+
+    let source = SourceName::from_str("fpga", "boards");
+    let runtime = RuntimeExpectations::new()
+        .test_source(
+            source.clone(),
+            || {},
+            |ctx| {
+                // first measurement: no executions, because they are differences
+                let m = ctx.measurements();
+                assert_eq!(m.len(), 2);
+                let power = ctx.metrics().by_name("fpga_power").unwrap().0;
+                let power = m.iter().find(|p| p.metric == power).unwrap();
+                assert_eq!(power.value.as_f64(), 18.0);
+                assert_eq!(
+                    power.resource,
+                    Resource::Custom {
+                        kind: "fpga".into(),
+                        id: "0000:3b:00".into()
+                    }
+                );
+            },
+        )
+        .test_source(
+            source,
+            move || {
+                write_files(
+                    &pci_path.join("0000:3b:00.1"),
+                    &[("kds_custat", "CU[@0x1800000] : 25 status : 4\n")],
+                );
+            },
+            |ctx| {
+                let m = ctx.measurements();
+                let executions = ctx.metrics().by_name("fpga_cu_executions").unwrap().0;
+                let point = m.iter().find(|p| p.metric == executions).unwrap();
+                assert_eq!(point.value.as_u64(), 15);
+            },
+        );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn write_files(dir: &Path, files: &[(&str, &str)]) {
+    std::fs::create_dir_all(dir).unwrap();
+    for (file, content) in files {
+        std::fs::write(dir.join(file), content).unwrap();
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<FpgaPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
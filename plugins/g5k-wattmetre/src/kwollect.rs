@@ -0,0 +1,193 @@
+//! Client of the metrics API of Grid'5000, provided by Kwollect.
+//!
+//! See <https://www.grid5000.fr/w/Monitoring_Using_Kwollect>.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use alumet::plugin::secret::Secret;
+use anyhow::Context;
+use chrono::DateTime;
+use reqwest::Client;
+use serde::Deserialize;
+
+/// Name of the Kwollect metric that contains the power measured by the OmegaWatt wattmetres.
+pub const WATTMETRE_METRIC: &str = "wattmetre_power_watt";
+
+/// A value of the wattmetre, for one node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerPoint {
+    pub node: String,
+    /// Port of the wattmetre, like `wattmetre1-port6`.
+    pub port: Option<String>,
+    /// Time of the measurement, given by the wattmetre.
+    pub timestamp: SystemTime,
+    pub watts: f64,
+}
+
+/// A value returned by the API.
+#[derive(Deserialize)]
+struct RawPoint {
+    timestamp: String,
+    device_id: String,
+    value: f64,
+    #[serde(default)]
+    labels: HashMap<String, serde_json::Value>,
+}
+
+pub struct KwollectClient {
+    http: Client,
+    /// URL of the metrics of the site, like `https://api.grid5000.fr/stable/sites/lyon/metrics`.
+    metrics_url: String,
+    login: Option<String>,
+    password: Option<Secret>,
+}
+
+impl KwollectClient {
+    pub fn new(
+        api_url: &str,
+        site: &str,
+        login: Option<String>,
+        password: Option<Secret>,
+        timeout: Duration,
+    ) -> anyhow::Result<Self> {
+        let http = Client::builder().timeout(timeout).build()?;
+        let metrics_url = format!("{}/sites/{site}/metrics", api_url.trim_end_matches('/'));
+        Ok(Self {
+            http,
+            metrics_url,
+            login,
+            password,
+        })
+    }
+
+    /// Fetches the power of `nodes` between `start` and `end` (inclusive), with a precision of one second.
+    pub async fn fetch_power(
+        &self,
+        nodes: &[String],
+        start: SystemTime,
+        end: SystemTime,
+    ) -> anyhow::Result<Vec<PowerPoint>> {
+        let start = unix_seconds(start).to_string();
+        let end = (unix_seconds(end) + 1).to_string();
+        let nodes = nodes.join(",");
+        let mut request = self.http.get(&self.metrics_url).query(&[
+            ("nodes", nodes.as_str()),
+            ("metrics", WATTMETRE_METRIC),
+            ("start_time", start.as_str()),
+            ("end_time", end.as_str()),
+        ]);
+        if let Some(login) = &self.login {
+            request = request.basic_auth(login, self.password.as_ref().map(Secret::expose));
+        }
+        let response = request.send().await?.error_for_status()?;
+        let points: Vec<RawPoint> = response.json().await.context("invalid response of the metrics API")?;
+        points.into_iter().map(TryFrom::try_from).collect()
+    }
+}
+
+impl TryFrom<RawPoint> for PowerPoint {
+    type Error = anyhow::Error;
+
+    fn try_from(p: RawPoint) -> Result<Self, Self::Error> {
+        let timestamp = DateTime::parse_from_rfc3339(&p.timestamp)
+            .with_context(|| format!("invalid timestamp {:?}", p.timestamp))?;
+        let port = p.labels.get("_device_orig").and_then(|v| match v {
+            serde_json::Value::String(s) => Some(s.clone()),
+            // several ports can power the same node
+            serde_json::Value::Array(ports) => {
+                Some(ports.iter().filter_map(|p| p.as_str()).collect::<Vec<_>>().join(","))
+            }
+            _ => None,
+        });
+        Ok(PowerPoint {
+            node: p.device_id,
+            port,
+            timestamp: timestamp.into(),
+            watts: p.value,
+        })
+    }
+}
+
+fn unix_seconds(t: SystemTime) -> u64 {
+    t.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::{Matcher, Server};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn fetch_power() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/sites/lyon/metrics")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("nodes".into(), "taurus-7,taurus-8".into()),
+                Matcher::UrlEncoded("metrics".into(), "wattmetre_power_watt".into()),
+                Matcher::UrlEncoded("start_time".into(), "1754379876".into()),
+                Matcher::UrlEncoded("end_time".into(), "1754379887".into()),
+            ]))
+            .match_header("authorization", Matcher::Any)
+            .with_body(
+                json!([
+                    {
+                        "timestamp": "2025-08-05T09:44:36.52+02:00",
+                        "device_id": "taurus-7",
+                        "metric_id": "wattmetre_power_watt",
+                        "value": 131.7,
+                        "labels": { "_device_orig": "wattmetre1-port6" }
+                    },
+                    {
+                        "timestamp": "2025-08-05T07:44:36Z",
+                        "device_id": "taurus-8",
+                        "metric_id": "wattmetre_power_watt",
+                        "value": 98.25,
+                        "labels": { "_device_orig": ["wattmetre1-port7", "wattmetre1-port8"] }
+                    }
+                ])
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = KwollectClient::new(
+            &format!("{}/", server.url()),
+            "lyon",
+            Some(String::from("user")),
+            Some(Secret::new("secret")),
+            Duration::from_secs(1),
+        )
+        .unwrap();
+        let start = SystemTime::UNIX_EPOCH + Duration::from_millis(1754379876_500);
+        let end = SystemTime::UNIX_EPOCH + Duration::from_secs(1754379886);
+        let nodes = [String::from("taurus-7"), String::from("taurus-8")];
+        let points = client.fetch_power(&nodes, start, end).await.unwrap();
+        mock.assert_async().await;
+
+        let t = SystemTime::UNIX_EPOCH + Duration::from_secs(1754379876);
+        assert_eq!(
+            points,
+            vec![
+                PowerPoint {
+                    node: String::from("taurus-7"),
+                    port: Some(String::from("wattmetre1-port6")),
+                    timestamp: t + Duration::from_millis(520),
+                    watts: 131.7,
+                },
+                PowerPoint {
+                    node: String::from("taurus-8"),
+                    port: Some(String::from("wattmetre1-port7,wattmetre1-port8")),
+                    timestamp: t,
+                    watts: 98.25,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use alumet::{
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+        secret::Secret,
+    },
+    units::Unit,
+};
+
+use crate::{kwollect::KwollectClient, source::SourceSettings};
+
+mod kwollect;
+mod source;
+
+/// Measures the power of Grid'5000 nodes with the OmegaWatt wattmetres, while the pipeline runs.
+pub struct G5kWattmetrePlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for G5kWattmetrePlugin {
+    fn name() -> &'static str {
+        "g5k-wattmetre"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(G5kWattmetrePlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        let hostname = hostname::get()?.to_string_lossy().into_owned();
+        let (local_node, local_site) = parse_fqdn(&hostname);
+
+        let site = match (&self.config.site, local_site) {
+            (Some(site), _) => site.clone(),
+            (None, Some(site)) => site.to_owned(),
+            (None, None) => {
+                return Err(anyhow!(
+                    "the Grid'5000 site cannot be deduced from the hostname {hostname:?}, please set `site` in the configuration"
+                ));
+            }
+        };
+        let nodes = if self.config.nodes.is_empty() {
+            vec![local_node.to_owned()]
+        } else {
+            self.config.nodes.clone()
+        };
+        log::info!("Measuring the wattmetres of {nodes:?} on site {site}");
+
+        let client = KwollectClient::new(
+            &self.config.api_url,
+            &site,
+            self.config.login.clone(),
+            self.config.password.clone(),
+            self.config.timeout,
+        )
+        .context("failed to create the HTTP client")?;
+        let metric = alumet.create_metric(
+            "wattmetre_power",
+            Unit::Watt,
+            "Power of a Grid'5000 node, measured by an external wattmetre",
+        )?;
+        let settings = SourceSettings {
+            poll_interval: self.config.poll_interval,
+            local_node: nodes.iter().any(|n| n == local_node).then(|| local_node.to_owned()),
+            nodes,
+        };
+        alumet.add_autonomous_source_builder("wattmetre", move |_ctx, cancel_token, out_tx| {
+            Ok(Box::pin(source::run(client, metric, settings, cancel_token, out_tx)))
+        })?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Splits a hostname like `taurus-7.lyon.grid5000.fr` into the node (`taurus-7`) and the site (`lyon`).
+fn parse_fqdn(hostname: &str) -> (&str, Option<&str>) {
+    let mut parts = hostname.split('.');
+    let node = parts.next().unwrap_or(hostname);
+    let site = parts.next().filter(|_| hostname.ends_with(".grid5000.fr"));
+    (node, site)
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two requests to the API.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// URL of the Grid'5000 API.
+    pub api_url: String,
+
+    /// Grid'5000 site of the nodes. If not set, the site of the local node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site: Option<String>,
+
+    /// Nodes to measure. If empty, the local node.
+    #[serde(default)]
+    pub nodes: Vec<String>,
+
+    /// Grid'5000 login, only required outside of Grid'5000.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub login: Option<String>,
+
+    /// Grid'5000 password, only required outside of Grid'5000.
+    /// It can reference a secret (`secret://...`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<Secret>,
+
+    /// Maximum time to wait for the response of the API.
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            api_url: String::from("https://api.grid5000.fr/stable"),
+            site: None,
+            nodes: Vec::new(),
+            login: None,
+            password: None,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_fqdn;
+
+    #[test]
+    fn fqdn() {
+        assert_eq!(parse_fqdn("taurus-7.lyon.grid5000.fr"), ("taurus-7", Some("lyon")));
+        assert_eq!(parse_fqdn("taurus-7"), ("taurus-7", None));
+        assert_eq!(parse_fqdn("laptop.example.com"), ("laptop", None));
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use alumet::{
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    resources::{Resource, ResourceConsumer},
+};
+use tokio::{sync::mpsc, time::MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::kwollect::{KwollectClient, PowerPoint};
+
+pub struct SourceSettings {
+    pub poll_interval: Duration,
+    pub nodes: Vec<String>,
+    /// The node on which Alumet runs, if it is measured, to use the `LocalMachine` resource for it.
+    pub local_node: Option<String>,
+}
+
+/// Fetches the new values of the wattmetres at regular intervals, until `cancel_token` is cancelled.
+pub async fn run(
+    client: KwollectClient,
+    metric: TypedMetricId<f64>,
+    settings: SourceSettings,
+    cancel_token: CancellationToken,
+    tx: mpsc::Sender<MeasurementBuffer>,
+) -> anyhow::Result<()> {
+    let mut cursor = Cursor::new(SystemTime::now());
+
+    let mut interval = tokio::time::interval(settings.poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            biased;
+            _ = cancel_token.cancelled() => break,
+            _ = interval.tick() => (),
+        };
+        // The API may be temporarily unavailable: the missing values are fetched at the next request.
+        let start = cursor.since(&settings.nodes);
+        let points = match client.fetch_power(&settings.nodes, start, SystemTime::now()).await {
+            Ok(points) => points,
+            Err(e) => {
+                log::warn!("Request to the metrics API of Grid'5000 failed: {e:#}");
+                continue;
+            }
+        };
+        let points = cursor.keep_new(points);
+        if points.is_empty() {
+            continue;
+        }
+        let mut buffer = MeasurementBuffer::with_capacity(points.len());
+        for p in points {
+            let resource = if settings.local_node.as_ref() == Some(&p.node) {
+                Resource::LocalMachine
+            } else {
+                Resource::custom("node", p.node.clone())
+            };
+            let point = MeasurementPoint::new(
+                Timestamp::from(p.timestamp),
+                metric,
+                resource,
+                ResourceConsumer::LocalMachine,
+                p.watts,
+            )
+            .with_attr("node", p.node);
+            buffer.push(match p.port {
+                Some(port) => point.with_attr("port", port),
+                None => point,
+            });
+        }
+        tx.send(buffer).await?;
+    }
+    Ok(())
+}
+
+/// Remembers the last value received for each node.
+///
+/// The wattmetres send their values to Kwollect with a delay of a few seconds, and not all at the same time.
+/// Therefore, each request starts at the oldest "last value", and the values that have already been received are ignored.
+struct Cursor {
+    /// Start of the measurement, for the nodes that have no value yet.
+    start: SystemTime,
+    last: HashMap<String, SystemTime>,
+}
+
+impl Cursor {
+    fn new(start: SystemTime) -> Self {
+        Self {
+            start,
+            last: HashMap::new(),
+        }
+    }
+
+    /// Returns the start time of the next request.
+    fn since(&self, nodes: &[String]) -> SystemTime {
+        nodes
+            .iter()
+            .map(|n| self.last.get(n).copied().unwrap_or(self.start))
+            .min()
+            .unwrap_or(self.start)
+    }
+
+    /// Keeps the values that have not been received yet, sorted by time, and updates the cursor.
+    fn keep_new(&mut self, mut points: Vec<PowerPoint>) -> Vec<PowerPoint> {
+        points.sort_by_key(|p| p.timestamp);
+        points.retain(|p| {
+            let last = self.last.get(&p.node).copied().unwrap_or(self.start);
+            // the first request can return values before the start, with a precision of one second
+            let is_new = p.timestamp > last || (p.timestamp == last && !self.last.contains_key(&p.node));
+            if is_new {
+                self.last.insert(p.node.clone(), p.timestamp);
+            }
+            is_new
+        });
+        points
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn point(node: &str, secs: u64) -> PowerPoint {
+        PowerPoint {
+            node: node.to_owned(),
+            port: None,
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(secs),
+            watts: secs as f64,
+        }
+    }
+
+    #[test]
+    fn cursor() {
+        let t = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        let nodes = [String::from("a"), String::from("b")];
+        let mut cursor = Cursor::new(t(100));
+        assert_eq!(cursor.since(&nodes), t(100));
+
+        let kept = cursor.keep_new(vec![point("a", 101), point("a", 99), point("a", 100), point("a", 102)]);
+        assert_eq!(kept, vec![point("a", 100), point("a", 101), point("a", 102)]);
+        // no value for b yet
+        assert_eq!(cursor.since(&nodes), t(100));
+
+        let kept = cursor.keep_new(vec![point("a", 101), point("a", 102), point("a", 103), point("b", 101)]);
+        assert_eq!(kept, vec![point("b", 101), point("a", 103)]);
+        assert_eq!(cursor.since(&nodes), t(101));
+    }
+}
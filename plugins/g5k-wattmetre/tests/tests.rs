@@ -0,0 +1,45 @@
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    plugin::PluginMetadata,
+    test::StartupExpectations,
+    units::Unit,
+};
+use plugin_g5k_wattmetre::{Config, G5kWattmetrePlugin};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const PLUGIN_NAME: &str = "g5k-wattmetre";
+
+#[test]
+fn plugin_with_unreachable_api() {
+    // nothing listens on the discard port: the requests fail, but the agent keeps running
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        api_url: String::from("http://127.0.0.1:9"),
+        site: Some(String::from("lyon")),
+        nodes: vec![String::from("taurus-7")],
+        timeout: Duration::from_secs(1),
+        ..Default::default()
+    };
+
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("wattmetre_power", Unit::Watt)
+        .expect_source(PLUGIN_NAME, "wattmetre");
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup_expectation)
+        .build_and_start()
+        .unwrap();
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<G5kWattmetrePlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -0,0 +1,263 @@
+//! Discovery and reading of the hwmon chips and their sensors.
+//!
+//! See the [kernel documentation](https://docs.kernel.org/hwmon/sysfs-interface.html).
+
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+/// Kind of hwmon sensor, given by the prefix of its files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorKind {
+    /// `temp{N}_input`, in millidegree Celsius.
+    Temperature,
+    /// `fan{N}_input`, in RPM.
+    Fan,
+    /// `in{N}_input`, in millivolts.
+    Voltage,
+    /// `curr{N}_input`, in milliamperes.
+    Current,
+    /// `power{N}_input` or `power{N}_average`, in microwatts.
+    Power,
+}
+
+/// A sensor of a hwmon chip, such as `temp1`.
+#[derive(Debug)]
+pub struct Sensor {
+    pub kind: SensorKind,
+    /// Name of the channel, like `temp1`.
+    pub channel: String,
+    /// Label of the sensor, like `Package id 0`, or the channel name if the chip provides no label.
+    pub label: String,
+    /// File that contains the value of the sensor.
+    file: File,
+}
+
+/// A hwmon chip, such as `/sys/class/hwmon/hwmon2`.
+#[derive(Debug)]
+pub struct Chip {
+    /// Name of the chip, like `coretemp`.
+    pub name: String,
+    /// Name of the hwmon device, like `hwmon2`. Unlike `name`, it is unique.
+    pub device: String,
+    pub sensors: Vec<Sensor>,
+}
+
+impl SensorKind {
+    fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "temp" => Some(Self::Temperature),
+            "fan" => Some(Self::Fan),
+            "in" => Some(Self::Voltage),
+            "curr" => Some(Self::Current),
+            "power" => Some(Self::Power),
+            _ => None,
+        }
+    }
+
+    /// The factor to apply to the raw value to obtain a value in the base unit (°C, RPM, V, A, W).
+    fn scale(&self) -> f64 {
+        match self {
+            SensorKind::Temperature | SensorKind::Voltage | SensorKind::Current => 1e-3,
+            SensorKind::Fan => 1.0,
+            SensorKind::Power => 1e-6,
+        }
+    }
+}
+
+impl Sensor {
+    /// Reads the value of the sensor, in the base unit of its kind (°C, RPM, V, A or W).
+    pub fn read(&mut self, buf: &mut String) -> std::io::Result<f64> {
+        buf.clear();
+        self.file.rewind()?;
+        self.file.read_to_string(buf)?;
+        let raw: i64 = buf
+            .trim_ascii_end()
+            .parse()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(raw as f64 * self.kind.scale())
+    }
+}
+
+/// Parses the name of a value file, like `temp1_input`, into its kind and channel.
+fn parse_value_file(file_name: &str) -> Option<(SensorKind, &str)> {
+    let (channel, suffix) = file_name.split_once('_')?;
+    let prefix = channel.trim_end_matches(|c: char| c.is_ascii_digit());
+    let kind = SensorKind::from_prefix(prefix)?;
+    if prefix.len() == channel.len() {
+        return None; // no channel number
+    }
+    let is_value = match kind {
+        SensorKind::Power => suffix == "input" || suffix == "average",
+        _ => suffix == "input",
+    };
+    is_value.then_some((kind, channel))
+}
+
+impl Chip {
+    /// Analyzes the hwmon chip at `path`.
+    pub fn at_sysfs(path: &Path) -> anyhow::Result<Self> {
+        let device = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        // Some old drivers put the attributes in the `device` subdirectory.
+        let dir = if path.join("name").exists() {
+            path.to_path_buf()
+        } else {
+            path.join("device")
+        };
+        let name_file = dir.join("name");
+        let name = std::fs::read_to_string(&name_file)
+            .with_context(|| format!("failed to read {name_file:?}"))?
+            .trim_ascii_end()
+            .to_owned();
+
+        let mut value_files: Vec<(SensorKind, String, PathBuf)> = Vec::new();
+        for entry in std::fs::read_dir(&dir).with_context(|| format!("failed to read dir {dir:?}"))? {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            let Some((kind, channel)) = file_name.to_str().and_then(parse_value_file) else {
+                continue;
+            };
+            // prefer powerN_input to powerN_average
+            if let Some(existing) = value_files.iter_mut().find(|(_, c, _)| c == channel) {
+                if file_name.to_str().is_some_and(|n| n.ends_with("_input")) {
+                    existing.2 = entry.path();
+                }
+                continue;
+            }
+            value_files.push((kind, channel.to_owned(), entry.path()));
+        }
+        value_files.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let mut sensors = Vec::with_capacity(value_files.len());
+        for (kind, channel, path) in value_files {
+            let label = std::fs::read_to_string(dir.join(format!("{channel}_label")))
+                .map(|l| l.trim_ascii_end().to_owned())
+                .unwrap_or_else(|_| channel.clone());
+            match File::open(&path) {
+                Ok(file) => sensors.push(Sensor {
+                    kind,
+                    channel,
+                    label,
+                    file,
+                }),
+                Err(e) => log::warn!("cannot open {path:?}, the sensor will not be measured: {e}"),
+            }
+        }
+        Ok(Self { name, device, sensors })
+    }
+}
+
+/// Explores a tree of hwmon chips.
+///
+/// ## Expected file layout
+///
+/// ```txt
+/// /sys/class/hwmon/
+/// |− hwmon0
+///     |− name
+///     |− temp1_input
+///     |− temp1_label
+///     |− fan1_input
+///     |− …
+/// |− hwmon1
+/// ```
+pub fn explore(hwmon_path: &Path) -> anyhow::Result<Vec<Chip>> {
+    let mut chips = Vec::new();
+    for entry in std::fs::read_dir(hwmon_path).with_context(|| format!("failed to read dir {hwmon_path:?}"))? {
+        let path = entry?.path();
+        match Chip::at_sysfs(&path) {
+            Ok(chip) => chips.push(chip),
+            Err(e) => log::warn!("failed to analyze hwmon chip {path:?}: {e:#}"),
+        }
+    }
+    chips.sort_by(|a, b| a.device.cmp(&b.device));
+    Ok(chips)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn value_file_names() {
+        assert_eq!(
+            parse_value_file("temp1_input"),
+            Some((SensorKind::Temperature, "temp1"))
+        );
+        assert_eq!(parse_value_file("in0_input"), Some((SensorKind::Voltage, "in0")));
+        assert_eq!(parse_value_file("fan12_input"), Some((SensorKind::Fan, "fan12")));
+        assert_eq!(parse_value_file("curr2_input"), Some((SensorKind::Current, "curr2")));
+        assert_eq!(parse_value_file("power1_average"), Some((SensorKind::Power, "power1")));
+        assert_eq!(parse_value_file("power1_input"), Some((SensorKind::Power, "power1")));
+
+        assert_eq!(parse_value_file("temp1_label"), None);
+        assert_eq!(parse_value_file("temp1_max"), None);
+        assert_eq!(parse_value_file("temp1_average"), None);
+        assert_eq!(parse_value_file("temp_input"), None);
+        assert_eq!(parse_value_file("intrusion0_alarm"), None);
+        assert_eq!(parse_value_file("name"), None);
+    }
+
+    #[test]
+    fn explore_chips() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let chip0 = root.path().join("hwmon0");
+        std::fs::create_dir(&chip0)?;
+        std::fs::write(chip0.join("name"), "coretemp\n")?;
+        std::fs::write(chip0.join("temp1_input"), "45000\n")?;
+        std::fs::write(chip0.join("temp1_label"), "Package id 0\n")?;
+        std::fs::write(chip0.join("temp1_max"), "100000\n")?;
+        std::fs::write(chip0.join("power1_average"), "1000\n")?;
+        std::fs::write(chip0.join("power1_input"), "12500000\n")?;
+
+        // old layout
+        let chip1 = root.path().join("hwmon1").join("device");
+        std::fs::create_dir_all(&chip1)?;
+        std::fs::write(chip1.join("name"), "nct6775\n")?;
+        std::fs::write(chip1.join("fan2_input"), "1200\n")?;
+        std::fs::write(chip1.join("in0_input"), "-1200\n")?;
+
+        let mut chips = explore(root.path())?;
+        assert_eq!(chips.len(), 2);
+        let mut buf = String::new();
+
+        let coretemp = &mut chips[0];
+        assert_eq!(coretemp.name, "coretemp");
+        assert_eq!(coretemp.device, "hwmon0");
+        let sensors: Vec<_> = coretemp
+            .sensors
+            .iter_mut()
+            .map(|s| (s.kind, s.label.clone(), s.read(&mut buf).unwrap()))
+            .collect();
+        assert_eq!(
+            sensors,
+            vec![
+                (SensorKind::Power, String::from("power1"), 12.5),
+                (SensorKind::Temperature, String::from("Package id 0"), 45.0),
+            ]
+        );
+
+        let nct = &mut chips[1];
+        assert_eq!(nct.name, "nct6775");
+        let sensors: Vec<_> = nct
+            .sensors
+            .iter_mut()
+            .map(|s| (s.kind, s.channel.clone(), s.read(&mut buf).unwrap()))
+            .collect();
+        assert_eq!(
+            sensors,
+            vec![
+                (SensorKind::Fan, String::from("fan2"), 1200.0),
+                (SensorKind::Voltage, String::from("in0"), -1.2),
+            ]
+        );
+        Ok(())
+    }
+}
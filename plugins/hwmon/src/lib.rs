@@ -0,0 +1,113 @@
+use anyhow::{Context, anyhow};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::source::{HwmonSource, Metrics};
+
+mod hwmon;
+mod source;
+
+/// Reads the sensors of the hardware monitoring chips (hwmon) of the Linux kernel.
+pub struct HwmonPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for HwmonPlugin {
+    fn name() -> &'static str {
+        "hwmon"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(HwmonPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let include = RegexSet::new(&self.config.include).context("invalid regex in `include`")?;
+        let exclude = RegexSet::new(&self.config.exclude).context("invalid regex in `exclude`")?;
+
+        let mut chips = hwmon::explore(&self.config.hwmon_path).context("could not find the hwmon chips")?;
+
+        // keep the selected sensors, identified by "chip/label"
+        for chip in &mut chips {
+            chip.sensors.retain(|sensor| {
+                let id = format!("{}/{}", chip.name, sensor.label);
+                (include.is_empty() || include.is_match(&id)) && !exclude.is_match(&id)
+            });
+            for sensor in &chip.sensors {
+                log::info!(
+                    "Found hwmon sensor {}/{} ({:?}) in {}",
+                    chip.name,
+                    sensor.label,
+                    sensor.kind,
+                    chip.device
+                );
+            }
+        }
+        chips.retain(|chip| !chip.sensors.is_empty());
+        if chips.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: no hwmon sensor found in {:?} (or all the sensors have been filtered out)",
+                self.config.hwmon_path
+            ));
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = HwmonSource::new(chips, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("sensors", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the hwmon chips.
+    pub hwmon_path: PathBuf,
+
+    /// Regular expressions that select the sensors to measure, by `chip/label`.
+    /// If empty, all the sensors are measured.
+    pub include: Vec<String>,
+
+    /// Regular expressions that exclude some sensors, by `chip/label`.
+    pub exclude: Vec<String>,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            hwmon_path: PathBuf::from("/sys/class/hwmon"),
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
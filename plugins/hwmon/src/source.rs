@@ -0,0 +1,101 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+
+use crate::hwmon::{Chip, SensorKind};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    temperature: TypedMetricId<f64>,
+    fan_speed: TypedMetricId<f64>,
+    voltage: TypedMetricId<f64>,
+    current: TypedMetricId<f64>,
+    power: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        let rpm = Unit::Custom {
+            unique_name: "{rotations}/min".to_owned(),
+            display_name: "RPM".to_owned(),
+        };
+        Ok(Self {
+            temperature: alumet.create_metric(
+                "hwmon_temperature",
+                Unit::DegreeCelsius,
+                "Temperature reported by a hwmon sensor",
+            )?,
+            fan_speed: alumet.create_metric(
+                "hwmon_fan_speed",
+                rpm,
+                "Rotation speed of a fan, reported by a hwmon sensor",
+            )?,
+            voltage: alumet.create_metric("hwmon_voltage", Unit::Volt, "Voltage reported by a hwmon sensor")?,
+            current: alumet.create_metric("hwmon_current", Unit::Ampere, "Current reported by a hwmon sensor")?,
+            power: alumet.create_metric("hwmon_power", Unit::Watt, "Power reported by a hwmon sensor")?,
+        })
+    }
+
+    fn for_kind(&self, kind: SensorKind) -> TypedMetricId<f64> {
+        match kind {
+            SensorKind::Temperature => self.temperature,
+            SensorKind::Fan => self.fan_speed,
+            SensorKind::Voltage => self.voltage,
+            SensorKind::Current => self.current,
+            SensorKind::Power => self.power,
+        }
+    }
+}
+
+/// Measurement source that reads the sensors of hwmon chips.
+pub struct HwmonSource {
+    chips: Vec<Chip>,
+    metrics: Metrics,
+    buf: String,
+}
+
+impl HwmonSource {
+    pub fn new(chips: Vec<Chip>, metrics: Metrics) -> Self {
+        Self {
+            chips,
+            metrics,
+            buf: String::with_capacity(16),
+        }
+    }
+}
+
+impl Source for HwmonSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for chip in &mut self.chips {
+            for sensor in &mut chip.sensors {
+                // Some sensors are temporarily unavailable (e.g. ENODATA, EIO): skip them this time.
+                let value = match sensor.read(&mut self.buf) {
+                    Ok(value) => value,
+                    Err(e) => {
+                        log::debug!("failed to read hwmon sensor {}/{}: {e}", chip.device, sensor.channel);
+                        continue;
+                    }
+                };
+                measurements.push(
+                    MeasurementPoint::new(
+                        timestamp,
+                        self.metrics.for_kind(sensor.kind),
+                        Resource::LocalMachine,
+                        ResourceConsumer::LocalMachine,
+                        value,
+                    )
+                    .with_attr("chip", chip.name.clone())
+                    .with_attr("device", chip.device.clone())
+                    .with_attr("channel", sensor.channel.clone())
+                    .with_attr("label", sensor.label.clone()),
+                );
+            }
+        }
+        Ok(())
+    }
+}
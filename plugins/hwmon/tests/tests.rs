@@ -0,0 +1,101 @@
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_hwmon::{Config, HwmonPlugin};
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_chip() {
+    let root = tempdir().unwrap();
+    let config = Config {
+        hwmon_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no hwmon chip)");
+}
+
+#[test]
+fn plugin_with_filters() {
+    let root = tempdir().unwrap();
+    write_chip(
+        &root.path().join("hwmon0"),
+        "coretemp",
+        &[
+            ("temp1_input", "45000"),
+            ("temp1_label", "Package id 0"),
+            ("temp2_input", "40000"),
+            ("temp2_label", "Core 0"),
+        ],
+    );
+    write_chip(
+        &root.path().join("hwmon1"),
+        "nct6775",
+        &[("fan1_input", "1200"), ("in0_input", "1100")],
+    );
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        hwmon_path: root.path().to_path_buf(),
+        include: vec![String::from("^coretemp/"), String::from("^nct6775/fan")],
+        exclude: vec![String::from("Core")],
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<f64>("hwmon_temperature", Unit::DegreeCelsius)
+        .expect_metric::<f64>("hwmon_voltage", Unit::Volt)
+        .expect_source("hwmon", "sensors");
+
+    let runtime = RuntimeExpectations::new().test_source(
+        SourceName::from_str("hwmon", "sensors"),
+        || {},
+        |ctx| {
+            let m = ctx.measurements();
+            let mut labels: Vec<_> = m
+                .iter()
+                .map(|p| {
+                    let label = p.attributes().find(|(k, _)| *k == "label").unwrap().1.to_string();
+                    (label, p.value.as_f64())
+                })
+                .collect();
+            labels.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(
+                labels,
+                vec![(String::from("Package id 0"), 45.0), (String::from("fan1"), 1200.0)]
+            );
+        },
+    );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn write_chip(path: &Path, name: &str, files: &[(&str, &str)]) {
+    std::fs::create_dir_all(path).unwrap();
+    std::fs::write(path.join("name"), name).unwrap();
+    for (file, content) in files {
+        std::fs::write(path.join(file), content).unwrap();
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<HwmonPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -0,0 +1,271 @@
+//! Decoding of the measurements of the power sensors: INA current/power monitors and PMBus regulators.
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::i2c::Bus;
+
+/// Model of a power sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChipModel {
+    /// Texas Instruments INA219.
+    Ina219,
+    /// Texas Instruments INA226 (and the pin-compatible INA230/INA231).
+    Ina226,
+    /// Texas Instruments INA3221, with three channels.
+    Ina3221,
+    /// Regulator or power supply that implements the PMBus standard (output of the regulator).
+    Pmbus,
+}
+
+/// Values measured on a power rail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RailReading {
+    pub voltage_volts: f64,
+    pub current_amperes: f64,
+    pub power_watts: f64,
+}
+
+/// A sensor that measures a power rail.
+#[derive(Debug)]
+pub struct Chip {
+    model: ChipModel,
+    address: u16,
+    /// Channel of the INA3221 (1 to 3) or PMBus page, if any.
+    channel: Option<u8>,
+    /// Resistance of the shunt resistor (INA only), in Ohms.
+    shunt_ohms: f64,
+}
+
+// INA registers (big endian)
+const INA_SHUNT_VOLTAGE: u8 = 0x01;
+const INA_BUS_VOLTAGE: u8 = 0x02;
+
+// PMBus commands (little endian)
+const PMBUS_PAGE: u8 = 0x00;
+const PMBUS_VOUT_MODE: u8 = 0x20;
+const PMBUS_READ_VOUT: u8 = 0x8b;
+const PMBUS_READ_IOUT: u8 = 0x8c;
+const PMBUS_READ_POUT: u8 = 0x96;
+
+impl Chip {
+    pub fn new(model: ChipModel, address: u16, channel: Option<u8>, shunt_ohms: f64) -> anyhow::Result<Self> {
+        match (model, channel) {
+            (ChipModel::Ina219 | ChipModel::Ina226, Some(_)) => {
+                return Err(anyhow!("the {model:?} has only one channel"));
+            }
+            (ChipModel::Ina3221, None) => return Err(anyhow!("the channel of the INA3221 (1 to 3) is required")),
+            (ChipModel::Ina3221, Some(c)) if !(1..=3).contains(&c) => {
+                return Err(anyhow!("invalid channel {c} of the INA3221 (1 to 3)"));
+            }
+            _ => (),
+        }
+        if model != ChipModel::Pmbus && shunt_ohms <= 0.0 {
+            return Err(anyhow!("invalid shunt resistor {shunt_ohms} Ohm"));
+        }
+        Ok(Self {
+            model,
+            address,
+            channel,
+            shunt_ohms,
+        })
+    }
+
+    /// Reads the voltage, current and power of the rail.
+    pub fn read(&self, bus: &mut dyn Bus) -> std::io::Result<RailReading> {
+        match self.model {
+            ChipModel::Ina219 => {
+                // bus voltage: bits 15-3, LSB 4 mV; shunt voltage: LSB 10 µV
+                let bus_voltage = f64::from(self.read_be(bus, INA_BUS_VOLTAGE)? >> 3) * 4e-3;
+                let shunt_voltage = f64::from(self.read_be(bus, INA_SHUNT_VOLTAGE)? as i16) * 10e-6;
+                Ok(self.ina_reading(bus_voltage, shunt_voltage))
+            }
+            ChipModel::Ina226 => {
+                // bus voltage: LSB 1.25 mV; shunt voltage: LSB 2.5 µV
+                let bus_voltage = f64::from(self.read_be(bus, INA_BUS_VOLTAGE)?) * 1.25e-3;
+                let shunt_voltage = f64::from(self.read_be(bus, INA_SHUNT_VOLTAGE)? as i16) * 2.5e-6;
+                Ok(self.ina_reading(bus_voltage, shunt_voltage))
+            }
+            ChipModel::Ina3221 => {
+                // two registers per channel, values in bits 15-3: bus voltage LSB 8 mV, shunt voltage LSB 40 µV
+                let offset = 2 * (self.channel.unwrap_or(1) - 1);
+                let bus_voltage = f64::from((self.read_be(bus, INA_BUS_VOLTAGE + offset)? as i16) >> 3) * 8e-3;
+                let shunt_voltage = f64::from((self.read_be(bus, INA_SHUNT_VOLTAGE + offset)? as i16) >> 3) * 40e-6;
+                Ok(self.ina_reading(bus_voltage, shunt_voltage))
+            }
+            ChipModel::Pmbus => {
+                if let Some(page) = self.channel {
+                    bus.write_register(self.address, PMBUS_PAGE, &[page])?;
+                }
+                let mut mode = [0u8];
+                bus.read_register(self.address, PMBUS_VOUT_MODE, &mut mode)?;
+                let voltage = linear16(self.read_le(bus, PMBUS_READ_VOUT)?, mode[0]).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::Unsupported,
+                        format!("unsupported VOUT_MODE {:#x}", mode[0]),
+                    )
+                })?;
+                Ok(RailReading {
+                    voltage_volts: voltage,
+                    current_amperes: linear11(self.read_le(bus, PMBUS_READ_IOUT)?),
+                    power_watts: linear11(self.read_le(bus, PMBUS_READ_POUT)?),
+                })
+            }
+        }
+    }
+
+    /// Computes the current and power from the voltages measured by an INA chip.
+    ///
+    /// The current is not read from the chip: this would require to write its calibration register.
+    fn ina_reading(&self, bus_voltage: f64, shunt_voltage: f64) -> RailReading {
+        let current = shunt_voltage / self.shunt_ohms;
+        RailReading {
+            voltage_volts: bus_voltage,
+            current_amperes: current,
+            power_watts: bus_voltage * current,
+        }
+    }
+
+    fn read_be(&self, bus: &mut dyn Bus, register: u8) -> std::io::Result<u16> {
+        let mut buf = [0u8; 2];
+        bus.read_register(self.address, register, &mut buf)?;
+        Ok(u16::from_be_bytes(buf))
+    }
+
+    fn read_le(&self, bus: &mut dyn Bus, register: u8) -> std::io::Result<u16> {
+        let mut buf = [0u8; 2];
+        bus.read_register(self.address, register, &mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+}
+
+/// Decodes a value in the PMBus LINEAR11 format: 5-bit exponent and 11-bit mantissa, both signed.
+fn linear11(raw: u16) -> f64 {
+    let exponent = (raw as i16) >> 11;
+    let mantissa = ((raw << 5) as i16) >> 5;
+    f64::from(mantissa) * 2f64.powi(i32::from(exponent))
+}
+
+/// Decodes a value in the PMBus LINEAR16 format: unsigned mantissa, with the exponent given by `VOUT_MODE`.
+///
+/// Returns `None` if the regulator uses another format (VID or direct).
+fn linear16(raw: u16, vout_mode: u8) -> Option<f64> {
+    if vout_mode >> 5 != 0 {
+        return None;
+    }
+    let exponent = ((vout_mode << 3) as i8) >> 3;
+    Some(f64::from(raw) * 2f64.powi(i32::from(exponent)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    /// Bus with the registers of one device, as they are transmitted on the wire.
+    #[derive(Default)]
+    struct FakeBus {
+        registers: HashMap<u8, Vec<u8>>,
+        writes: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl Bus for FakeBus {
+        fn read_register(&mut self, address: u16, register: u8, buf: &mut [u8]) -> std::io::Result<()> {
+            assert_eq!(address, 0x40);
+            let value = self.registers.get(&register).ok_or(std::io::ErrorKind::NotFound)?;
+            buf.copy_from_slice(value);
+            Ok(())
+        }
+
+        fn write_register(&mut self, address: u16, register: u8, data: &[u8]) -> std::io::Result<()> {
+            assert_eq!(address, 0x40);
+            self.writes.push((register, data.to_vec()));
+            Ok(())
+        }
+    }
+
+    fn bus(registers: &[(u8, &[u8])]) -> FakeBus {
+        FakeBus {
+            registers: registers.iter().map(|(r, v)| (*r, v.to_vec())).collect(),
+            writes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn ina219() {
+        // 12 V (3000 × 4 mV, shifted by 3 bits), 5 mV on 0.1 Ω: 50 mA
+        let mut bus = bus(&[(0x02, &(3000u16 << 3).to_be_bytes()), (0x01, &500i16.to_be_bytes())]);
+        let chip = Chip::new(ChipModel::Ina219, 0x40, None, 0.1).unwrap();
+        let reading = chip.read(&mut bus).unwrap();
+        assert_eq!(reading.voltage_volts, 12.0);
+        assert!((reading.current_amperes - 0.05).abs() < 1e-9);
+        assert!((reading.power_watts - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ina226() {
+        // 5 V (4000 × 1.25 mV), -2.5 mV on 0.01 Ω: -250 mA (reverse current)
+        let mut bus = bus(&[(0x02, &4000u16.to_be_bytes()), (0x01, &(-1000i16).to_be_bytes())]);
+        let chip = Chip::new(ChipModel::Ina226, 0x40, None, 0.01).unwrap();
+        let reading = chip.read(&mut bus).unwrap();
+        assert_eq!(reading.voltage_volts, 5.0);
+        assert!((reading.current_amperes + 0.25).abs() < 1e-9);
+        assert!((reading.power_watts + 1.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ina3221_channel() {
+        // channel 2: bus voltage 0x04 = 19 V (2375 × 8 mV), shunt voltage 0x03 = 4 mV (100 × 40 µV)
+        let mut bus = bus(&[
+            (0x04, &(2375u16 << 3).to_be_bytes()),
+            (0x03, &(100u16 << 3).to_be_bytes()),
+        ]);
+        let chip = Chip::new(ChipModel::Ina3221, 0x40, Some(2), 0.005).unwrap();
+        let reading = chip.read(&mut bus).unwrap();
+        assert_eq!(reading.voltage_volts, 19.0);
+        assert!((reading.current_amperes - 0.8).abs() < 1e-9);
+        assert!((reading.power_watts - 15.2).abs() < 1e-9);
+
+        assert!(Chip::new(ChipModel::Ina3221, 0x40, None, 0.1).is_err());
+        assert!(Chip::new(ChipModel::Ina3221, 0x40, Some(4), 0.1).is_err());
+        assert!(Chip::new(ChipModel::Ina219, 0x40, Some(1), 0.1).is_err());
+        assert!(Chip::new(ChipModel::Ina226, 0x40, None, 0.0).is_err());
+    }
+
+    #[test]
+    fn pmbus_page() {
+        let mut bus = bus(&[
+            // LINEAR16 with exponent -9: 0.8 V ≈ 410 / 512
+            (0x20, &[0x17]),
+            (0x8b, &410u16.to_le_bytes()),
+            // LINEAR11: exponent -2, mantissa 50: 12.5 A
+            (0x8c, &((0b11110u16 << 11) | 50).to_le_bytes()),
+            // LINEAR11: exponent 0, mantissa 10: 10 W
+            (0x96, &10u16.to_le_bytes()),
+        ]);
+        let chip = Chip::new(ChipModel::Pmbus, 0x40, Some(1), 0.0).unwrap();
+        let reading = chip.read(&mut bus).unwrap();
+        assert_eq!(
+            reading,
+            RailReading {
+                voltage_volts: 410.0 / 512.0,
+                current_amperes: 12.5,
+                power_watts: 10.0,
+            }
+        );
+        assert_eq!(bus.writes, vec![(0x00, vec![1])]);
+    }
+
+    #[test]
+    fn pmbus_formats() {
+        // negative mantissa
+        assert_eq!(linear11((0b00001u16 << 11) | 0x7ff), -2.0);
+        // VID mode is not supported
+        assert_eq!(linear16(100, 0x20 | 0x01), None);
+        assert_eq!(linear16(100, 0x00), Some(100.0));
+    }
+}
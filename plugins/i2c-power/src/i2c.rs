@@ -0,0 +1,101 @@
+//! Access to the I²C buses through the i2c-dev interface (`/dev/i2c-1`).
+//!
+//! The i2c-dev module must be loaded (`modprobe i2c-dev`).
+
+use std::{
+    fs::File,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+};
+
+// Definitions from linux/i2c.h and linux/i2c-dev.h
+
+/// Request code of the ioctl that performs combined transfers.
+const I2C_RDWR: u32 = 0x0707;
+/// Flag of the messages that read data from the device.
+const I2C_M_RD: u16 = 0x0001;
+
+#[repr(C)]
+struct I2cMsg {
+    addr: u16,
+    flags: u16,
+    len: u16,
+    buf: *mut u8,
+}
+
+#[repr(C)]
+struct I2cRdwrIoctlData {
+    msgs: *mut I2cMsg,
+    nmsgs: u32,
+}
+
+nix::ioctl_write_ptr_bad!(i2c_rdwr, I2C_RDWR, I2cRdwrIoctlData);
+
+/// Register-based access to the devices of an I²C bus.
+pub trait Bus {
+    /// Reads `buf.len()` bytes, starting at the given register of the device.
+    fn read_register(&mut self, address: u16, register: u8, buf: &mut [u8]) -> std::io::Result<()>;
+
+    /// Writes `data` to the given register of the device.
+    fn write_register(&mut self, address: u16, register: u8, data: &[u8]) -> std::io::Result<()>;
+}
+
+/// An I²C bus, like `/dev/i2c-1`.
+pub struct I2cDev {
+    file: File,
+}
+
+impl I2cDev {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = File::options().read(true).write(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    fn transfer(&mut self, msgs: &mut [I2cMsg]) -> std::io::Result<()> {
+        let data = I2cRdwrIoctlData {
+            msgs: msgs.as_mut_ptr(),
+            nmsgs: msgs.len() as u32,
+        };
+        // SAFETY: the messages point to buffers that are valid for their length, during the whole call
+        unsafe { i2c_rdwr(self.file.as_raw_fd(), &data) }.map_err(std::io::Error::from)?;
+        Ok(())
+    }
+}
+
+impl Bus for I2cDev {
+    fn read_register(&mut self, address: u16, register: u8, buf: &mut [u8]) -> std::io::Result<()> {
+        // write the register, then read with a repeated start condition
+        let mut register = [register];
+        self.transfer(&mut [
+            I2cMsg {
+                addr: address,
+                flags: 0,
+                len: 1,
+                buf: register.as_mut_ptr(),
+            },
+            I2cMsg {
+                addr: address,
+                flags: I2C_M_RD,
+                len: buf.len() as u16,
+                buf: buf.as_mut_ptr(),
+            },
+        ])
+    }
+
+    fn write_register(&mut self, address: u16, register: u8, data: &[u8]) -> std::io::Result<()> {
+        let mut message = Vec::with_capacity(1 + data.len());
+        message.push(register);
+        message.extend_from_slice(data);
+        self.transfer(&mut [I2cMsg {
+            addr: address,
+            flags: 0,
+            len: message.len() as u16,
+            buf: message.as_mut_ptr(),
+        }])
+    }
+}
+
+/// Returns the path of the character device of an I²C bus.
+pub fn bus_path(dev_path: &Path, bus: u32) -> PathBuf {
+    dev_path.join(format!("i2c-{bus}"))
+}
@@ -0,0 +1,151 @@
+use std::{
+    collections::{BTreeMap, btree_map::Entry},
+    path::PathBuf,
+    time::Duration,
+};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chip::Chip,
+    i2c::I2cDev,
+    source::{I2cSource, Metrics, Rail},
+};
+
+pub use chip::ChipModel;
+
+mod chip;
+mod i2c;
+mod source;
+
+pub struct I2cPowerPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for I2cPowerPlugin {
+    fn name() -> &'static str {
+        "i2c-power"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(I2cPowerPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        if self.config.rails.is_empty() {
+            return Err(anyhow!("nothing to measure: no rail configured"));
+        }
+
+        let mut buses = BTreeMap::new();
+        let mut rails = Vec::with_capacity(self.config.rails.len());
+        for rail in &self.config.rails {
+            let chip = Chip::new(rail.chip, rail.address, rail.channel, rail.shunt_resistor)
+                .with_context(|| format!("invalid configuration of rail {}", rail.name))?;
+            if let Entry::Vacant(entry) = buses.entry(rail.bus) {
+                let path = i2c::bus_path(&self.config.dev_path, rail.bus);
+                let dev = I2cDev::open(&path).with_context(|| format!("could not open the I2C bus {path:?}"))?;
+                entry.insert(dev);
+            }
+            // check the configuration now, rather than failing at each measurement
+            chip.read(buses.get_mut(&rail.bus).unwrap()).with_context(|| {
+                format!(
+                    "could not read rail {} ({:?} at address {:#04x} of bus {})",
+                    rail.name, rail.chip, rail.address, rail.bus
+                )
+            })?;
+            rails.push(Rail {
+                name: rail.name.clone(),
+                bus: rail.bus,
+                address: rail.address,
+                chip,
+            });
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = I2cSource::new(buses, rails, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("rails", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Directory that contains the character devices of the I2C buses (`i2c-N`).
+    pub dev_path: PathBuf,
+
+    /// The power rails to measure.
+    pub rails: Vec<RailConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RailConfig {
+    /// Name of the rail, added to the measurements as an attribute.
+    pub name: String,
+
+    /// Number of the I2C bus (N in `/dev/i2c-N`).
+    pub bus: u32,
+
+    /// Address of the sensor on the bus.
+    pub address: u16,
+
+    /// Model of the sensor.
+    pub chip: ChipModel,
+
+    /// Channel of the INA3221 (1 to 3) or page of the PMBus device.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<u8>,
+
+    /// Resistance of the shunt resistor of the INA sensors, in Ohms.
+    #[serde(default = "default_shunt_resistor")]
+    pub shunt_resistor: f64,
+}
+
+fn default_shunt_resistor() -> f64 {
+    0.1
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            dev_path: PathBuf::from("/dev"),
+            rails: vec![RailConfig {
+                name: String::from("vdd_in"),
+                bus: 1,
+                address: 0x40,
+                chip: ChipModel::Ina219,
+                channel: None,
+                shunt_resistor: default_shunt_resistor(),
+            }],
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::Context;
+
+use crate::{
+    chip::Chip,
+    i2c::{Bus, I2cDev},
+};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    power: TypedMetricId<f64>,
+    voltage: TypedMetricId<f64>,
+    current: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            power: alumet.create_metric(
+                "i2c_rail_power",
+                Unit::Watt,
+                "Power of a rail measured by an I2C sensor",
+            )?,
+            voltage: alumet.create_metric(
+                "i2c_rail_voltage",
+                Unit::Volt,
+                "Voltage of a rail measured by an I2C sensor",
+            )?,
+            current: alumet.create_metric(
+                "i2c_rail_current",
+                Unit::Ampere,
+                "Current of a rail measured by an I2C sensor",
+            )?,
+        })
+    }
+}
+
+/// A power rail, measured by a sensor on an I²C bus.
+pub struct Rail {
+    pub name: String,
+    pub bus: u32,
+    pub address: u16,
+    pub chip: Chip,
+}
+
+/// Measurement source that reads the power sensors of the I²C buses.
+pub struct I2cSource {
+    buses: BTreeMap<u32, I2cDev>,
+    rails: Vec<Rail>,
+    metrics: Metrics,
+}
+
+impl I2cSource {
+    pub fn new(buses: BTreeMap<u32, I2cDev>, rails: Vec<Rail>, metrics: Metrics) -> Self {
+        Self { buses, rails, metrics }
+    }
+}
+
+impl Source for I2cSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for rail in &self.rails {
+            let bus: &mut dyn Bus = self.buses.get_mut(&rail.bus).expect("every bus should be open");
+            let reading = rail
+                .chip
+                .read(bus)
+                .with_context(|| format!("failed to read the sensor of rail {}", rail.name))?;
+
+            let point = |metric, value: f64| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("rail", rail.name.clone())
+                .with_attr("i2c_bus", u64::from(rail.bus))
+                .with_attr("i2c_address", u64::from(rail.address))
+            };
+            measurements.push(point(self.metrics.power, reading.power_watts));
+            measurements.push(point(self.metrics.voltage, reading.voltage_volts));
+            measurements.push(point(self.metrics.current, reading.current_amperes));
+        }
+        Ok(())
+    }
+}
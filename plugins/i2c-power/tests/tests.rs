@@ -0,0 +1,45 @@
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    plugin::PluginMetadata,
+};
+use plugin_i2c_power::{ChipModel, Config, I2cPowerPlugin, RailConfig};
+
+#[test]
+fn plugin_without_rail() {
+    let config = Config {
+        rails: Vec::new(),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no rail)");
+}
+
+#[test]
+fn plugin_without_bus() {
+    let config = Config {
+        poll_interval: Duration::from_secs(1),
+        dev_path: PathBuf::from("/does-not-exist"),
+        rails: vec![RailConfig {
+            name: String::from("vdd_cpu"),
+            bus: 0,
+            address: 0x41,
+            chip: ChipModel::Ina3221,
+            channel: Some(2),
+            shunt_resistor: 0.005,
+        }],
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no I2C bus)");
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<I2cPowerPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
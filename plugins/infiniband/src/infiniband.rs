@@ -0,0 +1,176 @@
+//! Discovery and reading of the counters of the InfiniBand (and RDMA) ports.
+//!
+//! See the kernel documentation of the [sysfs interface](https://docs.kernel.org/admin-guide/abi-stable.html#abi-sys-class-infiniband).
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+/// Unit of the data counters: they count the number of octets divided by 4.
+const DATA_COUNTER_UNIT: u64 = 4;
+
+/// A port of an RDMA device, such as `/sys/class/infiniband/mlx5_0/ports/1`.
+#[derive(Debug)]
+pub struct Port {
+    /// Name of the device, like `mlx5_0`.
+    pub device: String,
+    /// Number of the port, starting at 1.
+    pub number: u32,
+    path: PathBuf,
+    /// Hardware counters of the port that are measured.
+    pub hw_counters: Vec<String>,
+}
+
+/// Counters of a port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortCounters {
+    pub received_bytes: u64,
+    pub transmitted_bytes: u64,
+    pub received_packets: u64,
+    pub transmitted_packets: u64,
+    /// Values of the hardware counters, in the same order as [`Port::hw_counters`].
+    pub hw: Vec<u64>,
+}
+
+impl Port {
+    /// Name of the port, like `mlx5_0/1`.
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.device, self.number)
+    }
+
+    pub fn read_counters(&self) -> anyhow::Result<PortCounters> {
+        let counters = self.path.join("counters");
+        let hw_counters = self.path.join("hw_counters");
+        Ok(PortCounters {
+            received_bytes: read_u64(&counters.join("port_rcv_data"))? * DATA_COUNTER_UNIT,
+            transmitted_bytes: read_u64(&counters.join("port_xmit_data"))? * DATA_COUNTER_UNIT,
+            received_packets: read_u64(&counters.join("port_rcv_packets"))?,
+            transmitted_packets: read_u64(&counters.join("port_xmit_packets"))?,
+            hw: self
+                .hw_counters
+                .iter()
+                .map(|name| read_u64(&hw_counters.join(name)))
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+}
+
+fn read_u64(path: &Path) -> anyhow::Result<u64> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    content
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid value in {}: {content:?}", path.display()))
+}
+
+/// Explores the ports of the RDMA devices, and selects the hardware counters among `hw_counters`.
+///
+/// ## Expected file layout
+///
+/// ```txt
+/// /sys/class/infiniband/
+/// |− mlx5_0
+///     |− ports
+///         |− 1
+///             |− counters
+///                 |− port_rcv_data
+///                 |− port_xmit_data
+///                 |− port_rcv_packets
+///                 |− port_xmit_packets
+///                 |− …
+///             |− hw_counters
+///                 |− …
+/// |− …
+/// ```
+///
+/// The ports that have no `counters` directory are ignored.
+/// The hardware counters that are not provided by the driver of a port are ignored for this port.
+pub fn explore(infiniband_path: &Path, hw_counters: &[String]) -> anyhow::Result<Vec<Port>> {
+    let mut ports = Vec::new();
+    let devices =
+        std::fs::read_dir(infiniband_path).with_context(|| format!("failed to read dir {infiniband_path:?}"))?;
+    for device in devices {
+        let device = device?;
+        let device_name = device.file_name().to_string_lossy().into_owned();
+        let Ok(device_ports) = std::fs::read_dir(device.path().join("ports")) else {
+            continue;
+        };
+        for port in device_ports.filter_map(|e| e.ok()) {
+            let Some(number) = port.file_name().to_str().and_then(|n| n.parse().ok()) else {
+                continue;
+            };
+            let path = port.path();
+            if !path.join("counters").is_dir() {
+                continue;
+            }
+            let hw_counters = hw_counters
+                .iter()
+                .filter(|name| path.join("hw_counters").join(name).is_file())
+                .cloned()
+                .collect();
+            ports.push(Port {
+                device: device_name.clone(),
+                number,
+                path,
+                hw_counters,
+            });
+        }
+    }
+    ports.sort_by(|a, b| (&a.device, a.number).cmp(&(&b.device, b.number)));
+    Ok(ports)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn write_files(dir: &Path, files: &[(&str, &str)]) {
+        std::fs::create_dir_all(dir).unwrap();
+        for (file, content) in files {
+            std::fs::write(dir.join(file), format!("{content}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn explore_ports() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let root = root.path();
+        let counters = [
+            ("port_rcv_data", "1000"),
+            ("port_xmit_data", "250"),
+            ("port_rcv_packets", "30"),
+            ("port_xmit_packets", "20"),
+            ("symbol_error", "0"),
+        ];
+        write_files(&root.join("mlx5_1/ports/1/counters"), &counters);
+        write_files(&root.join("mlx5_0/ports/2/counters"), &counters);
+        write_files(&root.join("mlx5_0/ports/1/counters"), &counters);
+        write_files(
+            &root.join("mlx5_0/ports/1/hw_counters"),
+            &[("rx_write_requests", "7"), ("out_of_buffer", "1")],
+        );
+        // device without counters (for instance, an iWARP device)
+        std::fs::create_dir_all(root.join("irdma0/ports/1"))?;
+
+        let hw_counters = vec![String::from("rx_write_requests"), String::from("np_cnp_sent")];
+        let ports = explore(root, &hw_counters)?;
+        let names: Vec<_> = ports.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["mlx5_0/1", "mlx5_0/2", "mlx5_1/1"]);
+
+        assert_eq!(ports[0].hw_counters, vec!["rx_write_requests"]);
+        assert!(ports[1].hw_counters.is_empty());
+        assert_eq!(
+            ports[0].read_counters()?,
+            PortCounters {
+                received_bytes: 4000,
+                transmitted_bytes: 1000,
+                received_packets: 30,
+                transmitted_packets: 20,
+                hw: vec![7],
+            }
+        );
+        Ok(())
+    }
+}
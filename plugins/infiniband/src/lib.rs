@@ -0,0 +1,91 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::source::{InfinibandSource, Metrics};
+
+mod infiniband;
+mod source;
+
+/// Measures the data and packets counters of the InfiniBand (and other RDMA) ports.
+pub struct InfinibandPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for InfinibandPlugin {
+    fn name() -> &'static str {
+        "infiniband"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(InfinibandPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let ports = infiniband::explore(&self.config.infiniband_path, &self.config.hw_counters)
+            .context("could not find the InfiniBand ports")?;
+        if ports.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: no port with counters found in {:?}",
+                self.config.infiniband_path
+            ));
+        }
+        for port in &ports {
+            log::debug!("Found port {} (hardware counters: {:?})", port.name(), port.hw_counters);
+        }
+        log::info!("Found {} InfiniBand ports to measure.", ports.len());
+
+        let metrics = Metrics::new(alumet)?;
+        let source = InfinibandSource::new(ports, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("ports", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the RDMA devices.
+    pub infiniband_path: PathBuf,
+
+    /// Names of the hardware counters (`hw_counters` directory) to measure, in addition to the standard counters.
+    pub hw_counters: Vec<String>,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            infiniband_path: PathBuf::from("/sys/class/infiniband"),
+            hw_counters: Vec::new(),
+        }
+    }
+}
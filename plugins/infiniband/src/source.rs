@@ -0,0 +1,102 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+
+use crate::infiniband::{Port, PortCounters};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    data_delta: TypedMetricId<u64>,
+    packets_delta: TypedMetricId<u64>,
+    hw_counter_delta: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            data_delta: alumet.create_metric(
+                "infiniband_data_delta",
+                Unit::Byte,
+                "Number of bytes received or transmitted by the port since the previous measurement",
+            )?,
+            packets_delta: alumet.create_metric(
+                "infiniband_packets_delta",
+                Unit::Unity,
+                "Number of packets received or transmitted by the port since the previous measurement",
+            )?,
+            hw_counter_delta: alumet.create_metric(
+                "infiniband_hw_counter_delta",
+                Unit::Unity,
+                "Increase of a hardware counter of the port since the previous measurement",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the counters of the InfiniBand ports.
+pub struct InfinibandSource {
+    ports: Vec<MeasuredPort>,
+    metrics: Metrics,
+}
+
+struct MeasuredPort {
+    port: Port,
+    /// The previous counters, to compute the difference.
+    previous: Option<PortCounters>,
+}
+
+impl InfinibandSource {
+    pub fn new(ports: Vec<Port>, metrics: Metrics) -> Self {
+        let ports = ports
+            .into_iter()
+            .map(|port| MeasuredPort { port, previous: None })
+            .collect();
+        Self { ports, metrics }
+    }
+}
+
+impl Source for InfinibandSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for p in &mut self.ports {
+            let counters = p.port.read_counters()?;
+
+            // Only push deltas, not the baseline value before the plugin starts
+            if let Some(prev) = &p.previous {
+                let resource = Resource::Custom {
+                    kind: "infiniband_port".into(),
+                    id: p.port.name().into(),
+                };
+                let point = |metric, value: u64| {
+                    MeasurementPoint::new(
+                        timestamp,
+                        metric,
+                        resource.clone(),
+                        ResourceConsumer::LocalMachine,
+                        value,
+                    )
+                };
+                // the counters are reset when the driver is reloaded: saturate instead of wrapping
+                let rx = counters.received_bytes.saturating_sub(prev.received_bytes);
+                let tx = counters.transmitted_bytes.saturating_sub(prev.transmitted_bytes);
+                measurements.push(point(self.metrics.data_delta, rx).with_attr("direction", "rx"));
+                measurements.push(point(self.metrics.data_delta, tx).with_attr("direction", "tx"));
+                let rx = counters.received_packets.saturating_sub(prev.received_packets);
+                let tx = counters.transmitted_packets.saturating_sub(prev.transmitted_packets);
+                measurements.push(point(self.metrics.packets_delta, rx).with_attr("direction", "rx"));
+                measurements.push(point(self.metrics.packets_delta, tx).with_attr("direction", "tx"));
+
+                for ((name, value), prev_value) in p.port.hw_counters.iter().zip(&counters.hw).zip(&prev.hw) {
+                    let delta = value.saturating_sub(*prev_value);
+                    measurements.push(point(self.metrics.hw_counter_delta, delta).with_attr("counter", name.clone()));
+                }
+            }
+            p.previous = Some(counters);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_infiniband::{Config, InfinibandPlugin};
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_port() {
+    let root = tempdir().unwrap();
+    std::fs::create_dir_all(root.path().join("mlx5_0/ports")).unwrap();
+    let config = Config {
+        infiniband_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no port)");
+}
+
+#[test]
+fn plugin_with_port() {
+    let root = tempdir().unwrap();
+    let port_path = root.path().join("mlx5_0/ports/1");
+    write_counters(&port_path, 100, 50);
+    std::fs::create_dir_all(port_path.join("hw_counters")).unwrap();
+    std::fs::write(port_path.join("hw_counters/rx_write_requests"), "10").unwrap();
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        infiniband_path: root.path().to_path_buf(),
+        hw_counters: vec![String::from("rx_write_requests")],
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<u64>("infiniband_data_delta", Unit::Byte)
+        .expect_metric::<u64>("infiniband_packets_delta", Unit::Unity)
+        .expect_metric::<u64>("infiniband_hw_counter_delta", Unit::Unity)
+        .expect_source("infiniband", "ports");
+
+    let source = SourceName::from_str("infiniband", "ports");
+    let runtime = RuntimeExpectations::new()
+        .test_source(
+            source.clone(),
+            || {},
+            |ctx| {
+                // first measurement: nothing, because the values are differences
+                assert_eq!(ctx.measurements().len(), 0);
+            },
+        )
+        .test_source(
+            source,
+            move || {
+                write_counters(&port_path, 1100, 52);
+                std::fs::write(port_path.join("hw_counters/rx_write_requests"), "15").unwrap();
+            },
+            |ctx| {
+                let m = ctx.measurements();
+                let data = ctx.metrics().by_name("infiniband_data_delta").unwrap().0;
+                let rx = m
+                    .iter()
+                    .find(|p| p.metric == data && p.attributes().any(|(_, v)| v.to_string() == "rx"))
+                    .unwrap();
+                assert_eq!(rx.value.as_u64(), 4000);
+                assert_eq!(
+                    rx.resource,
+                    Resource::Custom {
+                        kind: "infiniband_port".into(),
+                        id: "mlx5_0/1".into()
+                    }
+                );
+                let value_of = |name: &str| {
+                    let metric = ctx.metrics().by_name(name).unwrap().0;
+                    m.iter().find(|p| p.metric == metric).map(|p| p.value.as_u64()).unwrap()
+                };
+                assert_eq!(value_of("infiniband_packets_delta"), 2);
+                assert_eq!(value_of("infiniband_hw_counter_delta"), 5);
+            },
+        );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+/// Writes the standard counters of a port, with the same value in both directions.
+fn write_counters(port_path: &Path, data: u64, packets: u64) {
+    let dir = port_path.join("counters");
+    std::fs::create_dir_all(&dir).unwrap();
+    for (file, value) in [
+        ("port_rcv_data", data),
+        ("port_xmit_data", data),
+        ("port_rcv_packets", packets),
+        ("port_xmit_packets", packets),
+    ] {
+        std::fs::write(dir.join(file), value.to_string()).unwrap();
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<InfinibandPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -9,7 +9,10 @@ use alumet::{
             output::{OutputContext, error::WriteRetry},
         },
     },
-    plugin::rust::{AlumetPlugin, deserialize_config, serialize_config},
+    plugin::{
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+        secret::Secret,
+    },
 };
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
@@ -44,7 +47,7 @@ impl AlumetPlugin for InfluxDbPlugin {
         let config = self.config.take().unwrap();
 
         // Connect to InfluxDB to detect configuration errors early.
-        let influx_client = influxdb2::Client::new(config.host.clone(), config.token.clone());
+        let influx_client = influxdb2::Client::new(config.host.clone(), config.token.expose().to_owned());
         let rt = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
         log::info!("Testing connection to InfluxDB...");
         rt.block_on(influx_client.test_write(&config.org, &config.bucket))
@@ -211,8 +214,8 @@ fn ensure_valid_field_key<'a>(reserved_field: &'a str, field_key: &'a str) -> &'
 pub struct Config {
     /// Address of the host where InfluxDB is running
     pub host: String,
-    /// Token to write on the database
-    pub token: String,
+    /// Token to write on the database, can be a reference to a secret (`secret://...`)
+    pub token: Secret,
     /// Organisation where to write data
     pub org: String,
     /// Bucket where to write data   
@@ -241,7 +244,7 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             host: String::from("http://localhost:8086"),
-            token: String::from("FILL ME"),
+            token: Secret::new("FILL ME"),
             org: String::from("FILL ME"),
             bucket: String::from("FILL ME"),
             attributes_as: AttributeAs::Field,
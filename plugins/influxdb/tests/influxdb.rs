@@ -17,7 +17,7 @@ mod tests {
         },
         measurement::{MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue},
         pipeline::naming::OutputName,
-        plugin::PluginMetadata,
+        plugin::{PluginMetadata, secret::Secret},
         resources::{Resource, ResourceConsumer},
         test::{RuntimeExpectations, runtime::OutputCheckInputContext},
     };
@@ -67,7 +67,7 @@ mod tests {
 
         let source_config = Config {
             host: server.url(),
-            token: String::from(token),
+            token: Secret::new(token),
             org: String::from(org),
             bucket: String::from(bucket),
             attributes_as: AttributeAs::Field,
@@ -0,0 +1,387 @@
+//! Discovery of the Intel GPUs that are managed by the `i915` or `xe` driver.
+
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, anyhow};
+
+#[cfg(not(target_os = "linux"))]
+compile_error!("only Linux is supported");
+
+/// PCI vendor id of Intel.
+const INTEL_VENDOR_ID: &str = "0x8086";
+
+/// Represents an Intel GPU and the sysfs files that expose its telemetry.
+///
+/// ## Expected file layout on card {x}
+///
+/// With the `i915` driver:
+/// ```txt
+/// /sys/class/drm/card{x}/
+/// |−− gt/gt{k}/rps_act_freq_mhz (or gt_act_freq_mhz on older kernels)
+/// |−− gt/gt{k}/rc6_residency_ms (or power/rc6_residency_ms on older kernels)
+/// |−− device/
+///     |−− vendor
+///     |−− uevent
+///     |−− hwmon/hwmon{y}/
+///         |−− energy{n}_input, energy{n}_label
+///         |−− power{n}_input, power{n}_label
+/// ```
+///
+/// With the `xe` driver:
+/// ```txt
+/// /sys/class/drm/card{x}/device/
+/// |−− vendor
+/// |−− uevent
+/// |−− tile{t}/gt{k}/freq0/act_freq
+/// |−− tile{t}/gt{k}/gtidle/idle_residency_ms
+/// |−− hwmon/hwmon{y}/
+///     |−− energy{n}_input, energy{n}_label
+/// ```
+///
+/// Every file except `vendor` and `uevent` is optional: the hwmon interface is only provided
+/// by discrete GPUs (Arc, Flex, Max), and its content depends on the version of the driver.
+#[derive(Debug)]
+pub struct IntelGpuDevice {
+    /// PCI address of the GPU, for instance `0000:03:00.0`.
+    pub bus_id: String,
+    pub sensors: Sensors,
+}
+
+/// The sysfs files that are available on a GPU.
+#[derive(Debug, Default)]
+pub struct Sensors {
+    /// Energy counters, in microJoules, with their label (`card`, `pkg`).
+    pub energy: Vec<(String, SysfsFile)>,
+    /// Power, in microWatts, with their label.
+    pub power: Vec<(String, SysfsFile)>,
+    /// The graphics tiles (GT) of the GPU.
+    pub gts: Vec<GtSensors>,
+}
+
+/// The sysfs files of a graphics tile (GT).
+#[derive(Debug)]
+pub struct GtSensors {
+    /// Name of the GT, e.g. `gt0`.
+    pub name: String,
+    /// Actual frequency, in MHz.
+    pub frequency: Option<SysfsFile>,
+    /// Time spent in the RC6 (idle) state since the boot, in milliseconds.
+    pub rc6_residency: Option<SysfsFile>,
+}
+
+/// A sysfs file that is opened once and read on each measurement.
+#[derive(Debug)]
+pub struct SysfsFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl SysfsFile {
+    /// Opens the file at `path`, if it exists.
+    fn open_if_exists(path: PathBuf) -> anyhow::Result<Option<Self>> {
+        if !std::fs::exists(&path)? {
+            return Ok(None);
+        }
+        let file = File::open(&path).with_context(|| format!("failed to open {path:?}"))?;
+        Ok(Some(Self { path, file }))
+    }
+
+    /// Reads the value of the file, which must be an unsigned integer.
+    pub fn read_u64(&mut self, buf: &mut String) -> anyhow::Result<u64> {
+        buf.clear();
+        self.file.rewind()?;
+        self.file
+            .read_to_string(buf)
+            .with_context(|| format!("failed to read {:?}", self.path))?;
+        let value = buf
+            .trim_ascii_end()
+            .parse()
+            .with_context(|| format!("invalid content {buf:?} in {:?}", self.path))?;
+        Ok(value)
+    }
+}
+
+impl IntelGpuDevice {
+    /// Inspects a DRM card, e.g. `/sys/class/drm/card1`.
+    ///
+    /// Returns `None` if the card is not an Intel GPU.
+    pub fn at_sysfs(card_path: &Path) -> anyhow::Result<Option<Self>> {
+        let device_path = card_path.join("device");
+        let vendor_file = device_path.join("vendor");
+        let vendor =
+            std::fs::read_to_string(&vendor_file).with_context(|| format!("failed to read {vendor_file:?}"))?;
+        if vendor.trim_ascii_end() != INTEL_VENDOR_ID {
+            return Ok(None);
+        }
+        let bus_id = read_bus_id(&device_path)?;
+        let mut sensors = Sensors::default();
+        if let Some(hwmon) = find_hwmon(&device_path)? {
+            sensors.energy = labelled_sensors(&hwmon, "energy")?;
+            sensors.power = labelled_sensors(&hwmon, "power")?;
+        }
+        sensors.gts = find_xe_gts(&device_path)?;
+        if sensors.gts.is_empty() {
+            sensors.gts = find_i915_gts(card_path)?;
+        }
+        Ok(Some(Self { bus_id, sensors }))
+    }
+}
+
+/// Reads the PCI address of the device from its `uevent` file.
+fn read_bus_id(device_path: &Path) -> anyhow::Result<String> {
+    let uevent_file = device_path.join("uevent");
+    let uevent = std::fs::read_to_string(&uevent_file).with_context(|| format!("failed to read {uevent_file:?}"))?;
+    uevent
+        .lines()
+        .find_map(|line| line.strip_prefix("PCI_SLOT_NAME="))
+        .map(|id| id.trim().to_owned())
+        .with_context(|| format!("PCI_SLOT_NAME not found in {uevent_file:?}"))
+}
+
+/// Finds the hwmon directory of the device, e.g. `/sys/class/drm/card1/device/hwmon/hwmon3`.
+fn find_hwmon(device_path: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let hwmon_dir = device_path.join("hwmon");
+    if !std::fs::exists(&hwmon_dir)? {
+        return Ok(None);
+    }
+    for entry in std::fs::read_dir(&hwmon_dir).with_context(|| format!("failed to read dir {hwmon_dir:?}"))? {
+        let path = entry?.path();
+        if path.file_name().unwrap().to_string_lossy().starts_with("hwmon") {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+/// Lists the entries `{prefix}{n}` of a directory, sorted by `n`.
+fn numbered_entries(dir: &Path, prefix: &str) -> anyhow::Result<Vec<(u32, PathBuf)>> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("failed to read dir {dir:?}"))? {
+        let path = entry?.path();
+        let index = path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .strip_prefix(prefix)
+            .and_then(|n| n.parse::<u32>().ok());
+        if let Some(n) = index {
+            entries.push((n, path));
+        }
+    }
+    entries.sort_unstable_by_key(|(n, _)| *n);
+    Ok(entries)
+}
+
+/// Opens the files `{prefix}{n}_input` of a hwmon directory, sorted by `n`, with their label.
+///
+/// The label is read from `{prefix}{n}_label`. If there is no label, `{prefix}{n}` is used instead.
+fn labelled_sensors(hwmon_path: &Path, prefix: &str) -> anyhow::Result<Vec<(String, SysfsFile)>> {
+    let mut indices = Vec::new();
+    for entry in std::fs::read_dir(hwmon_path).with_context(|| format!("failed to read dir {hwmon_path:?}"))? {
+        let file_name = entry?.file_name();
+        let index = file_name
+            .to_string_lossy()
+            .strip_prefix(prefix)
+            .and_then(|s| s.strip_suffix("_input"))
+            .and_then(|n| n.parse::<u32>().ok());
+        if let Some(n) = index {
+            indices.push(n);
+        }
+    }
+    indices.sort_unstable();
+
+    let mut sensors = Vec::with_capacity(indices.len());
+    for n in indices {
+        let label_file = hwmon_path.join(format!("{prefix}{n}_label"));
+        let label = match std::fs::read_to_string(&label_file) {
+            Ok(label) => label.trim_ascii_end().to_owned(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => format!("{prefix}{n}"),
+            Err(e) => return Err(anyhow!(e).context(format!("failed to read {label_file:?}"))),
+        };
+        if let Some(file) = SysfsFile::open_if_exists(hwmon_path.join(format!("{prefix}{n}_input")))? {
+            sensors.push((label, file));
+        }
+    }
+    Ok(sensors)
+}
+
+/// Finds the GTs of a GPU managed by the `xe` driver, in `device/tile{t}/gt{k}`.
+fn find_xe_gts(device_path: &Path) -> anyhow::Result<Vec<GtSensors>> {
+    let mut gts = Vec::new();
+    for (_, tile) in numbered_entries(device_path, "tile")? {
+        for (k, gt) in numbered_entries(&tile, "gt")? {
+            gts.push(GtSensors {
+                name: format!("gt{k}"),
+                frequency: SysfsFile::open_if_exists(gt.join("freq0/act_freq"))?,
+                rc6_residency: SysfsFile::open_if_exists(gt.join("gtidle/idle_residency_ms"))?,
+            });
+        }
+    }
+    Ok(gts)
+}
+
+/// Finds the GTs of a GPU managed by the `i915` driver, in `gt/gt{k}`.
+///
+/// Older kernels do not have the `gt` directory: the files of the only GT are directly in the card directory.
+fn find_i915_gts(card_path: &Path) -> anyhow::Result<Vec<GtSensors>> {
+    let gt_dir = card_path.join("gt");
+    if std::fs::exists(&gt_dir)? {
+        let mut gts = Vec::new();
+        for (k, gt) in numbered_entries(&gt_dir, "gt")? {
+            gts.push(GtSensors {
+                name: format!("gt{k}"),
+                frequency: SysfsFile::open_if_exists(gt.join("rps_act_freq_mhz"))?,
+                rc6_residency: SysfsFile::open_if_exists(gt.join("rc6_residency_ms"))?,
+            });
+        }
+        return Ok(gts);
+    }
+
+    let gt = GtSensors {
+        name: String::from("gt0"),
+        frequency: SysfsFile::open_if_exists(card_path.join("gt_act_freq_mhz"))?,
+        rc6_residency: SysfsFile::open_if_exists(card_path.join("power/rc6_residency_ms"))?,
+    };
+    if gt.frequency.is_none() && gt.rc6_residency.is_none() {
+        return Ok(Vec::new());
+    }
+    Ok(vec![gt])
+}
+
+/// Explores the DRM cards and returns the Intel GPUs.
+///
+/// ## Expected file layout
+///
+/// Example of `drm_path`: `/sys/class/drm`
+/// Example of layout:
+/// ```txt
+/// /sys/class/drm/
+/// |
+/// |− card0
+///     |− device
+///         |− vendor
+///         |− …
+/// |− card0-eDP-1 (connector, ignored)
+/// |− card1
+/// ```
+///
+/// The cards that cannot be inspected are logged and skipped.
+pub fn explore(drm_path: &Path) -> anyhow::Result<Vec<IntelGpuDevice>> {
+    let mut devices = Vec::new();
+    // only keep cardN, not the connectors (cardN-eDP-1) nor the render nodes (renderDN)
+    for (_, path) in numbered_entries(drm_path, "card")? {
+        log::trace!("inspecting {path:?}");
+        match IntelGpuDevice::at_sysfs(&path) {
+            Ok(Some(device)) => devices.push(device),
+            Ok(None) => log::debug!("{path:?} is not an Intel GPU, skipping"),
+            Err(err) => log::error!("failed to inspect the DRM card {path:?}: {err:?}"),
+        }
+    }
+    Ok(devices)
+}
+
+impl Display for Sensors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut available = Vec::new();
+        for (label, _) in &self.energy {
+            available.push(format!("energy({label})"));
+        }
+        for (label, _) in &self.power {
+            available.push(format!("power({label})"));
+        }
+        for gt in &self.gts {
+            if gt.frequency.is_some() {
+                available.push(format!("frequency({})", gt.name));
+            }
+            if gt.rc6_residency.is_some() {
+                available.push(format!("rc6_residency({})", gt.name));
+            }
+        }
+        write!(f, "{}", available.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use super::explore;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_explore_i915() {
+        let root = tempdir().unwrap();
+        let root = root.path();
+        // integrated GPU on an older kernel: no hwmon, no gt directory
+        write(root, "card0/device/vendor", "0x8086\n");
+        write(root, "card0/device/uevent", "DRIVER=i915\nPCI_SLOT_NAME=0000:00:02.0\n");
+        write(root, "card0/gt_act_freq_mhz", "350\n");
+        write(root, "card0/power/rc6_residency_ms", "123456\n");
+        write(root, "card0-eDP-1/status", "connected\n");
+        // discrete GPU
+        write(root, "card1/device/vendor", "0x8086\n");
+        write(root, "card1/device/uevent", "DRIVER=i915\nPCI_SLOT_NAME=0000:03:00.0\n");
+        write(root, "card1/device/hwmon/hwmon5/energy1_input", "1000000\n");
+        write(root, "card1/gt/gt0/rps_act_freq_mhz", "2000\n");
+        write(root, "card1/gt/gt0/rc6_residency_ms", "42\n");
+        write(root, "card1/gt/gt1/rps_act_freq_mhz", "1800\n");
+        // AMD GPU
+        write(root, "card2/device/vendor", "0x1002\n");
+        write(root, "card2/device/uevent", "PCI_SLOT_NAME=0000:04:00.0\n");
+
+        let devices = explore(root).unwrap();
+        assert_eq!(devices.len(), 2);
+
+        assert_eq!(devices[0].bus_id, "0000:00:02.0");
+        assert_eq!(devices[0].sensors.to_string(), "frequency(gt0), rc6_residency(gt0)");
+
+        assert_eq!(devices[1].bus_id, "0000:03:00.0");
+        assert_eq!(
+            devices[1].sensors.to_string(),
+            "energy(energy1), frequency(gt0), rc6_residency(gt0), frequency(gt1)"
+        );
+    }
+
+    #[test]
+    fn test_explore_xe() {
+        let root = tempdir().unwrap();
+        let root = root.path();
+        write(root, "card0/device/vendor", "0x8086\n");
+        write(root, "card0/device/uevent", "DRIVER=xe\nPCI_SLOT_NAME=0000:4d:00.0\n");
+        write(root, "card0/device/hwmon/hwmon2/energy1_input", "1000000\n");
+        write(root, "card0/device/hwmon/hwmon2/energy1_label", "card\n");
+        write(root, "card0/device/hwmon/hwmon2/energy2_input", "900000\n");
+        write(root, "card0/device/hwmon/hwmon2/energy2_label", "pkg\n");
+        write(root, "card0/device/tile0/gt0/freq0/act_freq", "1600\n");
+        write(root, "card0/device/tile0/gt0/gtidle/idle_residency_ms", "5000\n");
+        write(root, "card0/device/tile0/gt1/freq0/act_freq", "1200\n");
+        write(root, "card0/device/tile0/gt1/gtidle/idle_residency_ms", "7000\n");
+
+        let mut devices = explore(root).unwrap();
+        assert_eq!(devices.len(), 1);
+        let sensors = &mut devices[0].sensors;
+        assert_eq!(
+            sensors.to_string(),
+            "energy(card), energy(pkg), frequency(gt0), rc6_residency(gt0), frequency(gt1), rc6_residency(gt1)"
+        );
+
+        let mut buf = String::new();
+        assert_eq!(sensors.energy[1].1.read_u64(&mut buf).unwrap(), 900000);
+        assert_eq!(
+            sensors.gts[1].frequency.as_mut().unwrap().read_u64(&mut buf).unwrap(),
+            1200
+        );
+    }
+}
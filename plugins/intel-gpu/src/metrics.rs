@@ -0,0 +1,46 @@
+use alumet::{
+    metrics::{TypedMetricId, error::MetricCreationError},
+    plugin::AlumetPluginStart,
+    units::{PrefixedUnit, Unit},
+};
+
+/// Contains the ids of the measured metrics.
+#[derive(Clone)]
+pub struct Metrics {
+    /// Energy consumed by the GPU since the previous measurement, in mJ.
+    pub total_energy_consumption: TypedMetricId<f64>,
+    /// Power of the GPU at the time of the measurement, in mW.
+    pub instant_power: TypedMetricId<u64>,
+    /// Actual frequency of a GT in MHz.
+    pub frequency: TypedMetricId<u64>,
+    /// Time spent by a GT in the RC6 state since the previous measurement, in ms.
+    pub rc6_residency: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    /// Creates new Alumet metrics for Intel GPU measurements and stores their ids in a `Metrics` structure.
+    pub fn new(alumet: &mut AlumetPluginStart) -> Result<Self, MetricCreationError> {
+        Ok(Self {
+            total_energy_consumption: alumet.create_metric(
+                "intel_gpu_energy_consumption",
+                PrefixedUnit::milli(Unit::Joule),
+                "Energy consumption by the GPU since the previous measurement",
+            )?,
+            instant_power: alumet.create_metric(
+                "intel_gpu_instant_power",
+                PrefixedUnit::milli(Unit::Watt),
+                "Instantaneous power of the GPU at the time of the measurement",
+            )?,
+            frequency: alumet.create_metric(
+                "intel_gpu_frequency",
+                PrefixedUnit::mega(Unit::Hertz),
+                "Actual frequency of a graphics tile",
+            )?,
+            rc6_residency: alumet.create_metric(
+                "intel_gpu_rc6_residency",
+                PrefixedUnit::milli(Unit::Second),
+                "Time spent by a graphics tile in the RC6 (idle) state since the previous measurement",
+            )?,
+        })
+    }
+}
@@ -0,0 +1,120 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    pipeline::{Source, elements::error::PollError},
+    plugin::util::CounterDiff,
+    resources::{Resource, ResourceConsumer},
+};
+
+use crate::{device::IntelGpuDevice, metrics::Metrics};
+
+/// Measurement source that reads the sysfs files of an Intel GPU.
+pub struct IntelGpuSource {
+    /// Sysfs files of the GPU.
+    device: IntelGpuDevice,
+    /// Alumet metrics IDs.
+    metrics: Metrics,
+    /// Alumet resource ID.
+    resource: Resource,
+    /// Internal state to compute the difference between two increments of the energy counters,
+    /// in the same order as `device.sensors.energy`.
+    energy_counters: Vec<CounterDiff>,
+    /// Internal state to compute the difference between two increments of the RC6 residency counters,
+    /// in the same order as `device.sensors.gts`.
+    rc6_counters: Vec<CounterDiff>,
+    /// Buffer for reading the files.
+    buf: String,
+}
+
+impl IntelGpuSource {
+    pub fn new(device: IntelGpuDevice, metrics: Metrics) -> Self {
+        let bus_id = std::borrow::Cow::Owned(device.bus_id.clone());
+        let energy_counters = (0..device.sensors.energy.len())
+            .map(|_| CounterDiff::with_max_value(u64::MAX))
+            .collect();
+        let rc6_counters = (0..device.sensors.gts.len())
+            .map(|_| CounterDiff::with_max_value(u64::MAX))
+            .collect();
+        Self {
+            device,
+            metrics,
+            resource: Resource::Gpu { bus_id },
+            energy_counters,
+            rc6_counters,
+            buf: String::new(),
+        }
+    }
+}
+
+impl Source for IntelGpuSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let sensors = &mut self.device.sensors;
+        let buf = &mut self.buf;
+
+        // no consumer, we just monitor the device here
+        let consumer = ResourceConsumer::LocalMachine;
+
+        for ((label, file), counter) in sensors.energy.iter_mut().zip(&mut self.energy_counters) {
+            // the counter is in µJ
+            let energy = file.read_u64(buf)?;
+            if let Some(diff) = counter.update(energy).difference() {
+                measurements.push(
+                    MeasurementPoint::new(
+                        timestamp,
+                        self.metrics.total_energy_consumption,
+                        self.resource.clone(),
+                        consumer.clone(),
+                        diff as f64 / 1000.0,
+                    )
+                    .with_attr("sensor", label.clone()),
+                );
+            }
+        }
+
+        for (label, file) in &mut sensors.power {
+            // the power is in µW
+            let power = file.read_u64(buf)?;
+            measurements.push(
+                MeasurementPoint::new(
+                    timestamp,
+                    self.metrics.instant_power,
+                    self.resource.clone(),
+                    consumer.clone(),
+                    power / 1000,
+                )
+                .with_attr("sensor", label.clone()),
+            );
+        }
+
+        for (gt, rc6_counter) in sensors.gts.iter_mut().zip(&mut self.rc6_counters) {
+            if let Some(file) = &mut gt.frequency {
+                let frequency = file.read_u64(buf)?;
+                measurements.push(
+                    MeasurementPoint::new(
+                        timestamp,
+                        self.metrics.frequency,
+                        self.resource.clone(),
+                        consumer.clone(),
+                        frequency,
+                    )
+                    .with_attr("gt", gt.name.clone()),
+                );
+            }
+            if let Some(file) = &mut gt.rc6_residency {
+                let residency = file.read_u64(buf)?;
+                if let Some(diff) = rc6_counter.update(residency).difference() {
+                    measurements.push(
+                        MeasurementPoint::new(
+                            timestamp,
+                            self.metrics.rc6_residency,
+                            self.resource.clone(),
+                            consumer.clone(),
+                            diff,
+                        )
+                        .with_attr("gt", gt.name.clone()),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
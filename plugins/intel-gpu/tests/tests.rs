@@ -0,0 +1,121 @@
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    measurement::{MeasurementBuffer, MeasurementPoint},
+    metrics::RawMetricId,
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::{PrefixedUnit, Unit},
+};
+use plugin_intel_gpu::{Config, IntelGpuPlugin};
+use std::{path::Path, time::Duration};
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const PLUGIN_NAME: &str = "intel-gpu";
+const SOURCE_NAME: &str = "device_0000:4d:00.0";
+const BUS_ID: &str = "0000:4d:00.0";
+
+#[test]
+fn plugin_without_device() {
+    let root = tempdir().unwrap();
+    // AMD GPU only
+    write(root.path(), "card0/device/vendor", "0x1002\n");
+    write(root.path(), "card0/device/uevent", "PCI_SLOT_NAME=0000:03:00.0\n");
+
+    let agent = agent::Builder::new(plugins(root.path())).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no Intel GPU)")
+}
+
+#[test]
+fn plugin_with_mock_xe_gpu() {
+    let root = tempdir().unwrap();
+    let device = root.path().join("card0/device");
+    write(&device, "vendor", "0x8086\n");
+    write(&device, "uevent", &format!("DRIVER=xe\nPCI_SLOT_NAME={BUS_ID}\n"));
+    write(&device, "hwmon/hwmon2/energy1_input", "1000000\n");
+    write(&device, "hwmon/hwmon2/energy1_label", "card\n");
+    write(&device, "tile0/gt0/freq0/act_freq", "1600\n");
+    write(&device, "tile0/gt0/gtidle/idle_residency_ms", "5000\n");
+
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("intel_gpu_energy_consumption", PrefixedUnit::milli(Unit::Joule))
+        .expect_metric::<u64>("intel_gpu_instant_power", PrefixedUnit::milli(Unit::Watt))
+        .expect_metric::<u64>("intel_gpu_frequency", PrefixedUnit::mega(Unit::Hertz))
+        .expect_metric::<u64>("intel_gpu_rc6_residency", PrefixedUnit::milli(Unit::Second))
+        .expect_source(PLUGIN_NAME, SOURCE_NAME);
+
+    let source = SourceName::from_str(PLUGIN_NAME, SOURCE_NAME);
+    let energy_file = device.join("hwmon/hwmon2/energy1_input");
+    let rc6_file = device.join("tile0/gt0/gtidle/idle_residency_ms");
+    let runtime_expectation = RuntimeExpectations::new()
+        // call the source once, so that the counters have a previous value
+        .test_source(
+            source.clone(),
+            || {},
+            |ctx| {
+                let m = ctx.measurements();
+                let frequency_metric = ctx.metrics().by_name("intel_gpu_frequency").unwrap().0;
+                let frequency = find_point(m, frequency_metric, "gt", "gt0");
+                assert_eq!(frequency.value.as_u64(), 1600);
+                assert_eq!(frequency.resource, Resource::Gpu { bus_id: BUS_ID.into() });
+
+                // the counters need two measurements
+                assert_eq!(m.len(), 1, "only the frequency should be measured on the first poll");
+            },
+        )
+        // increase the counters and check the differences
+        .test_source(
+            source,
+            move || {
+                std::fs::write(&energy_file, "1250000\n").unwrap();
+                std::fs::write(&rc6_file, "5800\n").unwrap();
+            },
+            |ctx| {
+                let m = ctx.measurements();
+                let energy_metric = ctx.metrics().by_name("intel_gpu_energy_consumption").unwrap().0;
+                let energy = find_point(m, energy_metric, "sensor", "card");
+                assert_eq!(energy.value.as_f64(), 250.0);
+
+                let rc6_metric = ctx.metrics().by_name("intel_gpu_rc6_residency").unwrap().0;
+                let rc6 = find_point(m, rc6_metric, "gt", "gt0");
+                assert_eq!(rc6.value.as_u64(), 800);
+            },
+        );
+
+    let agent = agent::Builder::new(plugins(root.path()))
+        .with_expectations(startup_expectation)
+        .with_expectations(runtime_expectation)
+        .build_and_start()
+        .unwrap();
+
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn plugins(root_path: &Path) -> PluginSet {
+    let config = Config {
+        poll_interval: Duration::from_secs(1),
+        flush_interval: Duration::from_secs(1),
+        root_path: root_path.to_str().unwrap().to_string(),
+    };
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<IntelGpuPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
+
+fn find_point<'a>(m: &'a MeasurementBuffer, metric: RawMetricId, key: &str, value: &str) -> &'a MeasurementPoint {
+    m.iter()
+        .find(|p| p.metric == metric && p.attributes().any(|(k, v)| k == key && v.to_string() == value))
+        .unwrap_or_else(|| panic!("no point with {key}={value}"))
+}
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
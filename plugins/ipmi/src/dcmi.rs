@@ -0,0 +1,101 @@
+//! DCMI power readings.
+
+use crate::ipmi::{IpmiError, IpmiTransport, NETFN_GROUP_EXTENSION};
+
+const CMD_GET_POWER_READING: u8 = 0x02;
+/// Group extension identification of DCMI.
+const DCMI_GROUP_ID: u8 = 0xdc;
+/// Mode of the power reading: system power statistics.
+const MODE_SYSTEM_POWER_STATISTICS: u8 = 0x01;
+
+/// Result of the DCMI "Get Power Reading" command.
+#[derive(Debug, PartialEq)]
+pub struct PowerReading {
+    /// Current power of the node, in Watts.
+    pub current: u16,
+    /// Minimum power over the sampling period, in Watts.
+    pub minimum: u16,
+    /// Maximum power over the sampling period, in Watts.
+    pub maximum: u16,
+    /// Average power over the sampling period, in Watts.
+    pub average: u16,
+    /// Is the power measurement active?
+    /// If not, the values above are meaningless.
+    pub active: bool,
+}
+
+/// Reads the power of the whole node, with the DCMI "Get Power Reading" command.
+pub fn get_power_reading(bmc: &mut impl IpmiTransport) -> Result<PowerReading, IpmiError> {
+    let response = bmc.request(
+        NETFN_GROUP_EXTENSION,
+        CMD_GET_POWER_READING,
+        &[DCMI_GROUP_ID, MODE_SYSTEM_POWER_STATISTICS, 0x00, 0x00],
+    )?;
+    parse_power_reading(&response)
+}
+
+fn parse_power_reading(data: &[u8]) -> Result<PowerReading, IpmiError> {
+    // group id, current (2), minimum (2), maximum (2), average (2), timestamp (4), period (4), state (1)
+    if data.len() < 18 || data[0] != DCMI_GROUP_ID {
+        return Err(IpmiError::InvalidResponse(format!(
+            "invalid DCMI power reading {data:02x?}"
+        )));
+    }
+    let u16_at = |i: usize| u16::from_le_bytes([data[i], data[i + 1]]);
+    Ok(PowerReading {
+        current: u16_at(1),
+        minimum: u16_at(3),
+        maximum: u16_at(5),
+        average: u16_at(7),
+        active: data[17] & 0x40 != 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{PowerReading, get_power_reading};
+    use crate::ipmi::{IpmiError, NETFN_GROUP_EXTENSION, mock::MockBmc};
+
+    #[test]
+    fn power_reading() {
+        let mut bmc = MockBmc::default();
+        bmc.respond(
+            NETFN_GROUP_EXTENSION,
+            0x02,
+            &[0xdc, 0x01, 0x00, 0x00],
+            &[
+                0xdc, // group id
+                0x2c, 0x01, // current: 300 W
+                0x64, 0x00, // minimum: 100 W
+                0xf4, 0x01, // maximum: 500 W
+                0xfa, 0x00, // average: 250 W
+                0x00, 0x00, 0x00, 0x00, // timestamp
+                0xe8, 0x03, 0x00, 0x00, // period
+                0x40, // active
+            ],
+        );
+        assert_eq!(
+            get_power_reading(&mut bmc).unwrap(),
+            PowerReading {
+                current: 300,
+                minimum: 100,
+                maximum: 500,
+                average: 250,
+                active: true,
+            }
+        );
+    }
+
+    #[test]
+    fn power_reading_unsupported() {
+        let mut bmc = MockBmc::default();
+        let err = get_power_reading(&mut bmc).expect_err("DCMI is not supported");
+        assert!(matches!(err, IpmiError::CompletionCode(0xc1)));
+
+        bmc.respond(NETFN_GROUP_EXTENSION, 0x02, &[0xdc, 0x01, 0x00, 0x00], &[0xdc, 0x2c]);
+        let err = get_power_reading(&mut bmc).expect_err("the response is too short");
+        assert!(matches!(err, IpmiError::InvalidResponse(_)));
+    }
+}
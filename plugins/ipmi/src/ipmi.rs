@@ -0,0 +1,225 @@
+//! Access to the BMC through the OpenIPMI driver (`/dev/ipmi0`).
+
+use std::{
+    fs::{File, OpenOptions},
+    os::fd::AsRawFd,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use nix::libc::{self, c_int, c_long, c_short, c_uint};
+
+/// Network function of the sensor and event commands.
+pub const NETFN_SENSOR_EVENT: u8 = 0x04;
+/// Network function of the storage commands (SDR repository).
+pub const NETFN_STORAGE: u8 = 0x0a;
+/// Network function of the DCMI commands (group extension).
+pub const NETFN_GROUP_EXTENSION: u8 = 0x2c;
+
+/// Completion code returned by the BMC when a reservation has been canceled.
+pub const CC_RESERVATION_CANCELED: u8 = 0xc5;
+
+#[derive(Debug, thiserror::Error)]
+pub enum IpmiError {
+    #[error("IPMI command failed with completion code {0:#04x}")]
+    CompletionCode(u8),
+    #[error("no response from the BMC after {0:?}")]
+    Timeout(Duration),
+    #[error("invalid response from the BMC: {0}")]
+    InvalidResponse(String),
+    #[error("IPMI I/O error")]
+    Io(#[from] std::io::Error),
+}
+
+/// A way to send requests to the BMC.
+pub trait IpmiTransport {
+    /// Sends a request to the BMC and waits for its response.
+    ///
+    /// On success, returns the data of the response, without the completion code.
+    fn request(&mut self, netfn: u8, cmd: u8, data: &[u8]) -> Result<Vec<u8>, IpmiError>;
+}
+
+// Definitions from linux/ipmi.h
+
+const IPMI_IOC_MAGIC: u8 = b'i';
+const IPMI_SYSTEM_INTERFACE_ADDR_TYPE: c_int = 0x0c;
+const IPMI_BMC_CHANNEL: c_short = 0xf;
+const IPMI_RESPONSE_RECV_TYPE: c_int = 1;
+const IPMI_MAX_MSG_LENGTH: usize = 272;
+
+#[repr(C)]
+struct IpmiMsg {
+    netfn: u8,
+    cmd: u8,
+    data_len: u16,
+    data: *mut u8,
+}
+
+#[repr(C)]
+struct IpmiReq {
+    addr: *mut u8,
+    addr_len: c_uint,
+    msgid: c_long,
+    msg: IpmiMsg,
+}
+
+#[repr(C)]
+struct IpmiRecv {
+    recv_type: c_int,
+    addr: *mut u8,
+    addr_len: c_uint,
+    msgid: c_long,
+    msg: IpmiMsg,
+}
+
+#[repr(C)]
+struct IpmiSystemInterfaceAddr {
+    addr_type: c_int,
+    channel: c_short,
+    lun: u8,
+}
+
+nix::ioctl_readwrite!(ipmictl_receive_msg_trunc, IPMI_IOC_MAGIC, 11, IpmiRecv);
+nix::ioctl_read!(ipmictl_send_command, IPMI_IOC_MAGIC, 13, IpmiReq);
+
+/// The BMC of the local machine, accessed through the OpenIPMI driver.
+pub struct OpenIpmiDevice {
+    file: File,
+    next_msgid: c_long,
+    timeout: Duration,
+}
+
+impl OpenIpmiDevice {
+    pub fn open(path: &Path, timeout: Duration) -> std::io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self {
+            file,
+            next_msgid: 0,
+            timeout,
+        })
+    }
+
+    fn send(&mut self, netfn: u8, cmd: u8, data: &[u8]) -> Result<c_long, IpmiError> {
+        let mut addr = IpmiSystemInterfaceAddr {
+            addr_type: IPMI_SYSTEM_INTERFACE_ADDR_TYPE,
+            channel: IPMI_BMC_CHANNEL,
+            lun: 0,
+        };
+        let mut data = data.to_vec();
+        let msgid = self.next_msgid;
+        self.next_msgid = self.next_msgid.wrapping_add(1);
+        let mut req = IpmiReq {
+            addr: (&raw mut addr).cast(),
+            addr_len: size_of::<IpmiSystemInterfaceAddr>() as c_uint,
+            msgid,
+            msg: IpmiMsg {
+                netfn,
+                cmd,
+                data_len: data.len() as u16,
+                data: data.as_mut_ptr(),
+            },
+        };
+        // SAFETY: the request points to valid buffers, which outlive the call
+        unsafe { ipmictl_send_command(self.file.as_raw_fd(), &mut req) }.map_err(std::io::Error::from)?;
+        Ok(msgid)
+    }
+
+    /// Waits until a message can be received, or until the deadline.
+    fn wait_readable(&self, deadline: Instant) -> Result<(), IpmiError> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let mut fds = [libc::pollfd {
+            fd: self.file.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        }];
+        // SAFETY: fds is a valid array of one pollfd
+        let res = unsafe { libc::poll(fds.as_mut_ptr(), 1, remaining.as_millis() as c_int) };
+        match res {
+            0 => Err(IpmiError::Timeout(self.timeout)),
+            n if n < 0 => Err(std::io::Error::last_os_error().into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn receive(&mut self) -> Result<(c_int, c_long, Vec<u8>), IpmiError> {
+        let mut addr = IpmiSystemInterfaceAddr {
+            addr_type: 0,
+            channel: 0,
+            lun: 0,
+        };
+        let mut data = vec![0u8; IPMI_MAX_MSG_LENGTH];
+        let mut recv = IpmiRecv {
+            recv_type: 0,
+            addr: (&raw mut addr).cast(),
+            addr_len: size_of::<IpmiSystemInterfaceAddr>() as c_uint,
+            msgid: 0,
+            msg: IpmiMsg {
+                netfn: 0,
+                cmd: 0,
+                data_len: data.len() as u16,
+                data: data.as_mut_ptr(),
+            },
+        };
+        // SAFETY: the structure points to valid buffers, which outlive the call
+        match unsafe { ipmictl_receive_msg_trunc(self.file.as_raw_fd(), &mut recv) } {
+            // the message has been truncated, but we can still use its beginning
+            Ok(_) | Err(nix::errno::Errno::EMSGSIZE) => (),
+            Err(e) => return Err(std::io::Error::from(e).into()),
+        }
+        data.truncate(recv.msg.data_len as usize);
+        Ok((recv.recv_type, recv.msgid, data))
+    }
+}
+
+impl IpmiTransport for OpenIpmiDevice {
+    fn request(&mut self, netfn: u8, cmd: u8, data: &[u8]) -> Result<Vec<u8>, IpmiError> {
+        let msgid = self.send(netfn, cmd, data)?;
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            self.wait_readable(deadline)?;
+            let (recv_type, recv_msgid, data) = self.receive()?;
+            if recv_type != IPMI_RESPONSE_RECV_TYPE || recv_msgid != msgid {
+                // response to a previous request that timed out, or event: ignore it
+                log::debug!("Ignoring IPMI message {recv_msgid} of type {recv_type}");
+                continue;
+            }
+            return match data.split_first() {
+                Some((0, data)) => Ok(data.to_vec()),
+                Some((cc, _)) => Err(IpmiError::CompletionCode(*cc)),
+                None => Err(IpmiError::InvalidResponse("empty response".to_owned())),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod mock {
+    use std::collections::HashMap;
+
+    use super::{IpmiError, IpmiTransport};
+
+    /// Fake BMC that returns predefined responses.
+    #[derive(Default)]
+    pub struct MockBmc {
+        /// Response (completion code and data) for each request (netfn, cmd, data).
+        pub responses: HashMap<(u8, u8, Vec<u8>), (u8, Vec<u8>)>,
+    }
+
+    impl MockBmc {
+        pub fn respond(&mut self, netfn: u8, cmd: u8, request: &[u8], response: &[u8]) {
+            self.responses
+                .insert((netfn, cmd, request.to_vec()), (0, response.to_vec()));
+        }
+    }
+
+    impl IpmiTransport for MockBmc {
+        fn request(&mut self, netfn: u8, cmd: u8, data: &[u8]) -> Result<Vec<u8>, IpmiError> {
+            match self.responses.get(&(netfn, cmd, data.to_vec())) {
+                Some((0, response)) => Ok(response.clone()),
+                Some((cc, _)) => Err(IpmiError::CompletionCode(*cc)),
+                // invalid command
+                None => Err(IpmiError::CompletionCode(0xc1)),
+            }
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{Context, anyhow};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::{
+    ipmi::{IpmiError, OpenIpmiDevice},
+    source::{IpmiSource, Metrics},
+};
+
+mod dcmi;
+mod ipmi;
+mod sdr;
+mod source;
+
+pub struct IpmiPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for IpmiPlugin {
+    fn name() -> &'static str {
+        "ipmi"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(IpmiPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let patterns = self
+            .config
+            .sdr_sensors
+            .iter()
+            .map(|p| Regex::new(p).with_context(|| format!("invalid sensor pattern '{p}'")))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let device = Path::new(&self.config.device);
+        let mut bmc = OpenIpmiDevice::open(device, self.config.timeout)
+            .with_context(|| format!("could not open {device:?}, are the ipmi_devintf and ipmi_si modules loaded?"))?;
+
+        // check that DCMI is supported
+        let dcmi = self.config.dcmi_power
+            && match dcmi::get_power_reading(&mut bmc) {
+                Ok(reading) if !reading.active => {
+                    log::warn!(
+                        "The DCMI power measurement is not active on this BMC, the node power will not be measured."
+                    );
+                    false
+                }
+                Ok(_) => true,
+                Err(IpmiError::CompletionCode(cc)) => {
+                    log::warn!(
+                        "DCMI is not supported by this BMC (completion code {cc:#04x}), the node power will not be measured."
+                    );
+                    false
+                }
+                Err(e) => return Err(e).context("DCMI power reading failed"),
+            };
+
+        // find the SDR sensors to measure
+        let sensors = if patterns.is_empty() {
+            Vec::new()
+        } else {
+            let all_sensors = sdr::read_analog_sensors(&mut bmc).context("could not read the SDR repository")?;
+            let selected: Vec<_> = all_sensors
+                .into_iter()
+                .filter(|s| patterns.iter().any(|p| p.is_match(&s.name)))
+                .collect();
+            for sensor in &selected {
+                log::info!("Found IPMI sensor {} ({:?})", sensor.name, sensor.unit);
+            }
+            selected
+        };
+
+        if !dcmi && sensors.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: DCMI is not available and no SDR sensor matches {:?}",
+                self.config.sdr_sensors
+            ));
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = IpmiSource::new(bmc, metrics, dcmi, sensors);
+        let trigger = TriggerSpec::builder(self.config.poll_interval)
+            .flush_interval(self.config.flush_interval)
+            .build()?;
+        alumet.add_source("bmc", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Initial interval between two IPMI measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Initial interval between two flushing of IPMI measurements.
+    #[serde(with = "humantime_serde")]
+    pub flush_interval: Duration,
+
+    /// Path to the device of the OpenIPMI driver.
+    pub device: String,
+
+    /// Maximum time to wait for the response of the BMC.
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+
+    /// Measure the power of the whole node with the DCMI "Get Power Reading" command.
+    pub dcmi_power: bool,
+
+    /// Regular expressions that select the SDR sensors to measure, by name.
+    pub sdr_sensors: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1), // 1Hz
+            flush_interval: Duration::from_secs(5),
+            device: "/dev/ipmi0".to_string(),
+            timeout: Duration::from_secs(2),
+            dcmi_power: true,
+            sdr_sensors: vec![String::from("(?i)inlet"), String::from("(?i)fan")],
+        }
+    }
+}
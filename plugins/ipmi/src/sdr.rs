@@ -0,0 +1,343 @@
+//! Sensor Data Records (SDR) and sensor readings.
+
+use crate::ipmi::{CC_RESERVATION_CANCELED, IpmiError, IpmiTransport, NETFN_SENSOR_EVENT, NETFN_STORAGE};
+
+const CMD_RESERVE_SDR_REPOSITORY: u8 = 0x22;
+const CMD_GET_SDR: u8 = 0x23;
+const CMD_GET_SENSOR_READING: u8 = 0x2d;
+
+/// Type of the records that describe analog sensors.
+const RECORD_TYPE_FULL_SENSOR: u8 = 0x01;
+/// Size of the record header.
+const HEADER_LEN: u8 = 5;
+/// Maximum number of bytes to read at once, many BMCs do not support more.
+const MAX_READ_LEN: u8 = 16;
+/// Id of the last record.
+const LAST_RECORD_ID: u16 = 0xffff;
+/// Slave address of the BMC, which owns the sensors that we can read directly.
+const BMC_SLAVE_ADDRESS: u8 = 0x20;
+
+/// Unit of an analog sensor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SensorUnit {
+    DegreeCelsius,
+    Volt,
+    Ampere,
+    Watt,
+    Rpm,
+}
+
+/// Description of an analog sensor, from a full sensor record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalogSensor {
+    pub number: u8,
+    pub name: String,
+    pub unit: SensorUnit,
+    conversion: Conversion,
+}
+
+/// Factors to convert a raw reading to a value in the unit of the sensor.
+///
+/// See section 36.3 of the IPMI specification.
+#[derive(Debug, Clone, PartialEq)]
+struct Conversion {
+    format: AnalogFormat,
+    linearization: u8,
+    m: i16,
+    b: i16,
+    b_exp: i8,
+    r_exp: i8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AnalogFormat {
+    Unsigned,
+    OnesComplement,
+    TwosComplement,
+}
+
+/// Reads the SDR repository of the BMC and returns the analog sensors that it owns.
+///
+/// The other sensors are ignored.
+pub fn read_analog_sensors(bmc: &mut impl IpmiTransport) -> Result<Vec<AnalogSensor>, IpmiError> {
+    let mut sensors = Vec::new();
+    let mut reservation = reserve(bmc)?;
+    let mut record_id = 0;
+    while record_id != LAST_RECORD_ID {
+        let (next_id, record) = match read_record(bmc, reservation, record_id) {
+            Err(IpmiError::CompletionCode(CC_RESERVATION_CANCELED)) => {
+                // the repository has changed, reserve it again and retry
+                reservation = reserve(bmc)?;
+                read_record(bmc, reservation, record_id)?
+            }
+            res => res?,
+        };
+        if let Some(sensor) = parse_full_sensor_record(&record) {
+            sensors.push(sensor);
+        }
+        record_id = next_id;
+    }
+    Ok(sensors)
+}
+
+fn reserve(bmc: &mut impl IpmiTransport) -> Result<u16, IpmiError> {
+    let response = bmc.request(NETFN_STORAGE, CMD_RESERVE_SDR_REPOSITORY, &[])?;
+    match response[..] {
+        [lsb, msb, ..] => Ok(u16::from_le_bytes([lsb, msb])),
+        _ => Err(IpmiError::InvalidResponse("invalid SDR reservation".to_owned())),
+    }
+}
+
+/// Reads an entire record, in small parts. Returns the id of the next record and the content of the record.
+fn read_record(bmc: &mut impl IpmiTransport, reservation: u16, record_id: u16) -> Result<(u16, Vec<u8>), IpmiError> {
+    let (next_id, mut record) = read_record_part(bmc, reservation, record_id, 0, HEADER_LEN)?;
+    if record.len() < HEADER_LEN as usize {
+        return Err(IpmiError::InvalidResponse(format!("invalid header of SDR {record_id}")));
+    }
+    let record_len = HEADER_LEN as usize + record[4] as usize;
+    while record.len() < record_len {
+        let len = (record_len - record.len()).min(MAX_READ_LEN as usize) as u8;
+        let (_, part) = read_record_part(bmc, reservation, record_id, record.len() as u8, len)?;
+        if part.is_empty() {
+            return Err(IpmiError::InvalidResponse(format!("SDR {record_id} is truncated")));
+        }
+        record.extend(part);
+    }
+    Ok((next_id, record))
+}
+
+fn read_record_part(
+    bmc: &mut impl IpmiTransport,
+    reservation: u16,
+    record_id: u16,
+    offset: u8,
+    len: u8,
+) -> Result<(u16, Vec<u8>), IpmiError> {
+    let [res_lsb, res_msb] = reservation.to_le_bytes();
+    let [id_lsb, id_msb] = record_id.to_le_bytes();
+    let response = bmc.request(
+        NETFN_STORAGE,
+        CMD_GET_SDR,
+        &[res_lsb, res_msb, id_lsb, id_msb, offset, len],
+    )?;
+    match response.split_at_checked(2) {
+        Some((next, data)) => Ok((u16::from_le_bytes([next[0], next[1]]), data.to_vec())),
+        None => Err(IpmiError::InvalidResponse(format!("invalid SDR {record_id}"))),
+    }
+}
+
+/// Parses a full sensor record (see section 43.1 of the IPMI specification).
+///
+/// Returns `None` if the record does not describe an analog sensor that is owned by the BMC,
+/// or if the unit of the sensor is not supported.
+fn parse_full_sensor_record(record: &[u8]) -> Option<AnalogSensor> {
+    if record.len() < 48 || record[3] != RECORD_TYPE_FULL_SENSOR {
+        return None;
+    }
+    let (owner_id, owner_lun) = (record[5], record[6] & 0x03);
+    if owner_id != BMC_SLAVE_ADDRESS || owner_lun != 0 {
+        return None;
+    }
+    let format = match record[20] >> 6 {
+        0b00 => AnalogFormat::Unsigned,
+        0b01 => AnalogFormat::OnesComplement,
+        0b10 => AnalogFormat::TwosComplement,
+        _ => return None, // no analog reading
+    };
+    let unit = match record[21] {
+        1 => SensorUnit::DegreeCelsius,
+        4 => SensorUnit::Volt,
+        5 => SensorUnit::Ampere,
+        6 => SensorUnit::Watt,
+        18 => SensorUnit::Rpm,
+        _ => return None,
+    };
+    let linearization = record[23] & 0x7f;
+    if linearization > 0x0b {
+        return None; // non-linear sensor, the conversion requires additional commands
+    }
+    let conversion = Conversion {
+        format,
+        linearization,
+        m: signed_10_bits(record[24], record[25]),
+        b: signed_10_bits(record[26], record[27]),
+        r_exp: signed_4_bits(record[29] >> 4),
+        b_exp: signed_4_bits(record[29] & 0x0f),
+    };
+    let name_len = (record[47] & 0x1f) as usize;
+    let name = record.get(48..48 + name_len)?;
+    let name = String::from_utf8_lossy(name).trim_end_matches('\0').trim().to_owned();
+    Some(AnalogSensor {
+        number: record[7],
+        name,
+        unit,
+        conversion,
+    })
+}
+
+/// Combines 8 low bits and 2 high bits (bits 7:6 of `high`) into a signed 10-bit integer.
+fn signed_10_bits(low: u8, high: u8) -> i16 {
+    let value = (((high as u16) & 0xc0) << 2 | low as u16) as i16;
+    // sign extension
+    (value << 6) >> 6
+}
+
+fn signed_4_bits(value: u8) -> i8 {
+    ((value << 4) as i8) >> 4
+}
+
+impl Conversion {
+    fn convert(&self, raw: u8) -> f64 {
+        let x = match self.format {
+            AnalogFormat::Unsigned => raw as f64,
+            AnalogFormat::OnesComplement if raw & 0x80 != 0 => -((!raw) as f64),
+            AnalogFormat::OnesComplement => raw as f64,
+            AnalogFormat::TwosComplement => raw as i8 as f64,
+        };
+        let b = self.b as f64 * 10f64.powi(self.b_exp as i32);
+        let y = (self.m as f64 * x + b) * 10f64.powi(self.r_exp as i32);
+        match self.linearization {
+            0x01 => y.ln(),
+            0x02 => y.log10(),
+            0x03 => y.log2(),
+            0x04 => y.exp(),
+            0x05 => 10f64.powf(y),
+            0x06 => y.exp2(),
+            0x07 => 1.0 / y,
+            0x08 => y * y,
+            0x09 => y * y * y,
+            0x0a => y.sqrt(),
+            0x0b => y.cbrt(),
+            _ => y,
+        }
+    }
+}
+
+impl AnalogSensor {
+    /// Reads the current value of the sensor, converted to its unit.
+    ///
+    /// Returns `None` if the reading is not available (for instance, when the device is powered off).
+    pub fn read(&self, bmc: &mut impl IpmiTransport) -> Result<Option<f64>, IpmiError> {
+        let response = bmc.request(NETFN_SENSOR_EVENT, CMD_GET_SENSOR_READING, &[self.number])?;
+        match response[..] {
+            [raw, flags, ..] => {
+                let unavailable = flags & 0x20 != 0 || flags & 0x40 == 0;
+                Ok((!unavailable).then(|| self.conversion.convert(raw)))
+            }
+            _ => Err(IpmiError::InvalidResponse(format!(
+                "invalid reading of sensor {}",
+                self.name
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::ipmi::mock::MockBmc;
+
+    /// Builds a full sensor record.
+    pub fn full_record(record_id: u16, number: u8, name: &str, base_unit: u8, m: i16, r_exp: i8) -> Vec<u8> {
+        let mut record = vec![0u8; 48];
+        record[0..2].copy_from_slice(&record_id.to_le_bytes());
+        record[2] = 0x51;
+        record[3] = RECORD_TYPE_FULL_SENSOR;
+        record[5] = BMC_SLAVE_ADDRESS;
+        record[7] = number;
+        record[21] = base_unit;
+        record[24] = (m & 0xff) as u8;
+        record[25] = ((m >> 2) & 0xc0) as u8;
+        record[29] = ((r_exp as u8) << 4) & 0xf0;
+        record[47] = 0xc0 | name.len() as u8;
+        record.extend(name.as_bytes());
+        record[4] = (record.len() - HEADER_LEN as usize) as u8;
+        record
+    }
+
+    /// Configures the mock BMC to serve the given records, in order.
+    pub fn serve_records(bmc: &mut MockBmc, records: &[Vec<u8>]) {
+        bmc.respond(NETFN_STORAGE, CMD_RESERVE_SDR_REPOSITORY, &[], &[0x01, 0x00]);
+        for (i, record) in records.iter().enumerate() {
+            let id = if i == 0 {
+                0
+            } else {
+                u16::from_le_bytes([record[0], record[1]])
+            };
+            let next = records
+                .get(i + 1)
+                .map(|r| u16::from_le_bytes([r[0], r[1]]))
+                .unwrap_or(LAST_RECORD_ID);
+            let mut offset = 0;
+            while offset < record.len() {
+                let len = if offset == 0 {
+                    HEADER_LEN as usize
+                } else {
+                    (record.len() - offset).min(MAX_READ_LEN as usize)
+                };
+                let [id_lsb, id_msb] = id.to_le_bytes();
+                let mut response = next.to_le_bytes().to_vec();
+                response.extend(&record[offset..offset + len]);
+                bmc.respond(
+                    NETFN_STORAGE,
+                    CMD_GET_SDR,
+                    &[0x01, 0x00, id_lsb, id_msb, offset as u8, len as u8],
+                    &response,
+                );
+                offset += len;
+            }
+        }
+    }
+
+    #[test]
+    fn sdr_repository() {
+        let mut bmc = MockBmc::default();
+        let mut discrete = full_record(3, 0x10, "PS Status", 0, 0, 0);
+        discrete[20] = 0xc0; // no analog reading
+        serve_records(
+            &mut bmc,
+            &[
+                full_record(1, 0x04, "Inlet Temp", 1, 1, 0),
+                full_record(2, 0x30, "FAN1", 18, 60, 0),
+                discrete,
+                full_record(4, 0x50, "12V", 4, 6, -2),
+            ],
+        );
+        let sensors = read_analog_sensors(&mut bmc).unwrap();
+        let summary: Vec<_> = sensors.iter().map(|s| (s.number, s.name.as_str(), s.unit)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0x04, "Inlet Temp", SensorUnit::DegreeCelsius),
+                (0x30, "FAN1", SensorUnit::Rpm),
+                (0x50, "12V", SensorUnit::Volt),
+            ]
+        );
+
+        bmc.respond(NETFN_SENSOR_EVENT, CMD_GET_SENSOR_READING, &[0x04], &[24, 0xc0]);
+        bmc.respond(NETFN_SENSOR_EVENT, CMD_GET_SENSOR_READING, &[0x30], &[80, 0xc0]);
+        bmc.respond(NETFN_SENSOR_EVENT, CMD_GET_SENSOR_READING, &[0x50], &[200, 0xe0]);
+        assert_eq!(sensors[0].read(&mut bmc).unwrap(), Some(24.0));
+        assert_eq!(sensors[1].read(&mut bmc).unwrap(), Some(4800.0));
+        assert_eq!(sensors[2].read(&mut bmc).unwrap(), None, "the reading is unavailable");
+    }
+
+    #[test]
+    fn conversion() {
+        let conversion = Conversion {
+            format: AnalogFormat::TwosComplement,
+            linearization: 0,
+            m: 6,
+            b: -5,
+            b_exp: 1,
+            r_exp: -2,
+        };
+        // (6 * -2 + -5 * 10) / 100
+        assert_eq!(conversion.convert(0xfe), -0.62);
+        assert_eq!(signed_10_bits(0xff, 0xc0), -1);
+        assert_eq!(signed_10_bits(0x3c, 0x00), 60);
+        assert_eq!(signed_4_bits(0x0e), -2);
+    }
+}
@@ -0,0 +1,123 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::Context;
+
+use crate::{
+    dcmi,
+    ipmi::IpmiTransport,
+    sdr::{AnalogSensor, SensorUnit},
+};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    /// Power of the whole node, given by DCMI, in W.
+    dcmi_power: TypedMetricId<u64>,
+    temperature: TypedMetricId<f64>,
+    fan_speed: TypedMetricId<f64>,
+    voltage: TypedMetricId<f64>,
+    current: TypedMetricId<f64>,
+    power: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        let rpm = Unit::Custom {
+            unique_name: "{rotations}/min".to_owned(),
+            display_name: "RPM".to_owned(),
+        };
+        Ok(Self {
+            dcmi_power: alumet.create_metric(
+                "ipmi_dcmi_power",
+                Unit::Watt,
+                "Power of the whole node, reported by the BMC with DCMI",
+            )?,
+            temperature: alumet.create_metric(
+                "ipmi_temperature",
+                Unit::DegreeCelsius,
+                "Temperature of an IPMI sensor",
+            )?,
+            fan_speed: alumet.create_metric(
+                "ipmi_fan_speed",
+                rpm,
+                "Rotation speed of a fan, reported by an IPMI sensor",
+            )?,
+            voltage: alumet.create_metric("ipmi_voltage", Unit::Volt, "Voltage reported by an IPMI sensor")?,
+            current: alumet.create_metric("ipmi_current", Unit::Ampere, "Current reported by an IPMI sensor")?,
+            power: alumet.create_metric("ipmi_power", Unit::Watt, "Power reported by an IPMI sensor")?,
+        })
+    }
+
+    fn for_unit(&self, unit: SensorUnit) -> TypedMetricId<f64> {
+        match unit {
+            SensorUnit::DegreeCelsius => self.temperature,
+            SensorUnit::Rpm => self.fan_speed,
+            SensorUnit::Volt => self.voltage,
+            SensorUnit::Ampere => self.current,
+            SensorUnit::Watt => self.power,
+        }
+    }
+}
+
+/// Measurement source that queries the BMC of the node.
+pub struct IpmiSource<T: IpmiTransport> {
+    bmc: T,
+    metrics: Metrics,
+    /// Read the power of the node with DCMI?
+    dcmi: bool,
+    /// SDR sensors to read.
+    sensors: Vec<AnalogSensor>,
+}
+
+impl<T: IpmiTransport> IpmiSource<T> {
+    pub fn new(bmc: T, metrics: Metrics, dcmi: bool, sensors: Vec<AnalogSensor>) -> Self {
+        Self {
+            bmc,
+            metrics,
+            dcmi,
+            sensors,
+        }
+    }
+}
+
+impl<T: IpmiTransport + Send> Source for IpmiSource<T> {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        if self.dcmi {
+            let reading = dcmi::get_power_reading(&mut self.bmc).context("DCMI power reading failed")?;
+            // if the measurement is not active, the value is meaningless
+            if reading.active {
+                measurements.push(MeasurementPoint::new(
+                    timestamp,
+                    self.metrics.dcmi_power,
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    reading.current as u64,
+                ));
+            }
+        }
+
+        for sensor in &self.sensors {
+            let value = sensor
+                .read(&mut self.bmc)
+                .with_context(|| format!("failed to read IPMI sensor {}", sensor.name))?;
+            if let Some(value) = value {
+                measurements.push(
+                    MeasurementPoint::new(
+                        timestamp,
+                        self.metrics.for_unit(sensor.unit),
+                        Resource::LocalMachine,
+                        ResourceConsumer::LocalMachine,
+                        value,
+                    )
+                    .with_attr("sensor", sensor.name.clone()),
+                );
+            }
+        }
+        Ok(())
+    }
+}
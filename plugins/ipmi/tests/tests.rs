@@ -0,0 +1,35 @@
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    plugin::PluginMetadata,
+};
+use plugin_ipmi::{Config, IpmiPlugin};
+
+#[test]
+fn plugin_without_device() {
+    let config = Config {
+        device: String::from("/dev/nonexistent-ipmi-device"),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no IPMI device)");
+}
+
+#[test]
+fn plugin_with_invalid_pattern() {
+    let config = Config {
+        sdr_sensors: vec![String::from("(unclosed")],
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (invalid regex)");
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<IpmiPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -0,0 +1,160 @@
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
+use alumet::measurement::{AttributeValue, MeasurementPoint};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Config {
+    /// Path to the ONNX model.
+    pub model_path: PathBuf,
+    /// The model is evaluated once per window, for each consumer.
+    #[serde(with = "humantime_serde")]
+    pub window: Duration,
+
+    /// The resource whose power is estimated.
+    #[serde(default = "default_resource_kind")]
+    pub resource_kind: String,
+    #[serde(default)]
+    pub resource_id: String,
+
+    /// The features of the model, in the order of its input columns.
+    pub features: Vec<FeatureConfig>,
+}
+
+/// Configuration for one feature (input column) of the model.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeatureConfig {
+    /// How the measurements of the window are combined.
+    #[serde(default)]
+    pub aggregation: Aggregation,
+    /// If `true`, the feature is computed for each consumer.
+    /// If `false`, it is computed on all the measurements, and is the same for every consumer.
+    #[serde(default = "default_true")]
+    pub per_consumer: bool,
+    #[serde(flatten)]
+    pub series: SeriesConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    #[default]
+    Sum,
+    Mean,
+    Last,
+}
+
+/// Selects the measurement points of a timeseries.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SeriesConfig {
+    pub metric: String,
+
+    pub resource_kind: Option<String>,
+    pub resource_id: Option<String>,
+
+    pub consumer_kind: Option<String>,
+    pub consumer_id: Option<String>,
+
+    #[serde(flatten)]
+    pub attributes: BTreeMap<String, FilterAttributeValue>,
+}
+
+fn default_resource_kind() -> String {
+    String::from("local_machine")
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum FilterAttributeValue {
+    UInt(u64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl FilterAttributeValue {
+    pub fn matches(&self, value: &AttributeValue) -> bool {
+        match (self, value) {
+            (FilterAttributeValue::UInt(a), AttributeValue::U64(b)) => a == b,
+            (FilterAttributeValue::Float(a), AttributeValue::F64(b)) => a == b,
+            (FilterAttributeValue::Bool(a), AttributeValue::Bool(b)) => a == b,
+            (FilterAttributeValue::String(a), AttributeValue::Str(b)) => a == b,
+            (FilterAttributeValue::String(a), AttributeValue::String(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl SeriesConfig {
+    /// Returns `true` if the point belongs to the timeseries (the metric is not checked).
+    pub fn accept_point(&self, point: &MeasurementPoint) -> bool {
+        let matches = |filter: &Option<String>, actual: String| filter.as_ref().is_none_or(|f| *f == actual);
+        matches(&self.resource_kind, point.resource.kind().to_owned())
+            && matches(&self.resource_id, point.resource.id_display().to_string())
+            && matches(&self.consumer_kind, point.consumer.kind().to_owned())
+            && matches(&self.consumer_id, point.consumer.id_display().to_string())
+            && self.attributes.iter().all(|(k, v)| {
+                point
+                    .attributes()
+                    .find(|(k2, _)| k2 == k)
+                    .is_some_and(|(_, v2)| v.matches(v2))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_config() {
+        let config = r#"
+            model_path = "/etc/alumet/model.onnx"
+            window = "5s"
+
+            [[features]]
+            metric = "cpu_time_delta"
+            kind = "user"
+
+            [[features]]
+            metric = "memory_usage"
+            kind = "resident"
+            aggregation = "mean"
+
+            [[features]]
+            metric = "rapl_consumed_energy"
+            domain = "package_total"
+            per_consumer = false
+        "#;
+        let config: Config = toml::from_str(config).unwrap();
+        assert_eq!(config.model_path, PathBuf::from("/etc/alumet/model.onnx"));
+        assert_eq!(config.window, Duration::from_secs(5));
+        assert_eq!(config.resource_kind, "local_machine");
+
+        let features: Vec<_> = config
+            .features
+            .iter()
+            .map(|f| (f.series.metric.as_str(), f.aggregation, f.per_consumer))
+            .collect();
+        assert_eq!(
+            features,
+            vec![
+                ("cpu_time_delta", Aggregation::Sum, true),
+                ("memory_usage", Aggregation::Mean, true),
+                ("rapl_consumed_energy", Aggregation::Sum, false),
+            ]
+        );
+        assert_eq!(
+            config.features[2].series.attributes,
+            BTreeMap::from([(
+                String::from("domain"),
+                FilterAttributeValue::String(String::from("package_total"))
+            )])
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use alumet::{
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config},
+    },
+    resources::Resource,
+    units::Unit,
+};
+use anyhow::{Context, anyhow};
+
+use model::OnnxModel;
+use transform::{Feature, MlPowerTransform};
+
+pub use config::Config;
+
+mod config;
+mod model;
+mod transform;
+mod window;
+
+pub struct MlPowerModelPlugin {
+    config: Option<Config>,
+}
+
+impl AlumetPlugin for MlPowerModelPlugin {
+    fn name() -> &'static str {
+        "ml-power-model"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(Self { config: Some(config) }))
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        Ok(None)
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        let config = self.config.take().unwrap();
+        if config.features.is_empty() {
+            return Err(anyhow!("the model has no feature"));
+        }
+        if config.window.is_zero() {
+            return Err(anyhow!("the window must not be empty"));
+        }
+        let resource = Resource::parse(config.resource_kind, config.resource_id).context("invalid resource")?;
+        // load the model now, to detect the errors as soon as possible
+        let model = OnnxModel::load(&config.model_path, config.features.len())?;
+
+        let result_metric = alumet.create_metric::<f64>(
+            "ml_estimated_power",
+            Unit::Watt,
+            "Power estimated by the ONNX model, per consumer",
+        )?;
+
+        // create the transform, in a builder because we need the metric registry
+        let feature_configs = config.features;
+        let window = config.window;
+        alumet.add_transform_builder("model", move |ctx| {
+            let mut features = Vec::with_capacity(feature_configs.len());
+            for (i, feature) in feature_configs.into_iter().enumerate() {
+                let metric_name = &feature.series.metric;
+                let (metric, _) = ctx
+                    .metric_by_name(metric_name)
+                    .with_context(|| format!("could not find metric '{metric_name}' for feature {i}; check that you have enabled the required sources"))?;
+                features.push(Feature {
+                    metric,
+                    filter: feature.series,
+                    aggregation: feature.aggregation,
+                    per_consumer: feature.per_consumer,
+                });
+            }
+            let transform = MlPowerTransform::new(model, features, window, resource, result_metric);
+            Ok(Box::new(transform))
+        })?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+//! Inference with ONNX models.
+
+use std::path::Path;
+
+use anyhow::{Context, anyhow};
+use tract_onnx::prelude::*;
+
+/// A model that takes one row of features, of shape `[1, n_features]`, and outputs a power in Watts.
+pub struct OnnxModel {
+    plan: TypedSimplePlan<TypedModel>,
+    n_features: usize,
+}
+
+impl OnnxModel {
+    /// Loads and optimizes the model.
+    ///
+    /// Fails if the model cannot take `n_features` features as input.
+    pub fn load(path: &Path, n_features: usize) -> anyhow::Result<Self> {
+        let plan = tract_onnx::onnx()
+            .model_for_path(path)
+            .and_then(|model| model.with_input_fact(0, f32::fact([1, n_features]).into()))
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .map_err(|e| anyhow!("{e:?}"))
+            .with_context(|| format!("failed to load the ONNX model {path:?} with {n_features} features"))?;
+        Ok(Self { plan, n_features })
+    }
+
+    /// Evaluates the model on one row of features.
+    pub fn predict(&self, features: &[f32]) -> anyhow::Result<f64> {
+        let input = Tensor::from_shape(&[1, self.n_features], features).map_err(|e| anyhow!("{e:?}"))?;
+        let outputs = self
+            .plan
+            .run(tvec!(input.into()))
+            .map_err(|e| anyhow!("inference failed: {e:?}"))?;
+        let output = outputs
+            .first()
+            .context("the model has no output")?
+            .cast_to::<f64>()
+            .map_err(|e| anyhow!("unexpected output type: {e:?}"))?;
+        let power = output
+            .as_slice::<f64>()
+            .map_err(|e| anyhow!("{e:?}"))?
+            .first()
+            .copied()
+            .context("the output of the model is empty")?;
+        Ok(power)
+    }
+}
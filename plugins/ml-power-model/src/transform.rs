@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use alumet::{
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp},
+    metrics::{RawMetricId, TypedMetricId},
+    pipeline::{
+        Transform,
+        elements::{error::TransformError, transform::TransformContext},
+    },
+    resources::Resource,
+};
+
+use crate::{
+    config::{Aggregation, SeriesConfig},
+    model::OnnxModel,
+    window::Window,
+};
+
+/// Estimates the power of each consumer by running the model on the features of each window.
+pub struct MlPowerTransform {
+    model: OnnxModel,
+    features: Vec<Feature>,
+    window_duration: Duration,
+    window: Option<Window>,
+    resource: Resource,
+    result_metric: TypedMetricId<f64>,
+}
+
+/// A feature (input column) of the model.
+pub struct Feature {
+    pub metric: RawMetricId,
+    pub filter: SeriesConfig,
+    pub aggregation: Aggregation,
+    pub per_consumer: bool,
+}
+
+impl Feature {
+    fn matches(&self, point: &MeasurementPoint) -> bool {
+        point.metric == self.metric && self.filter.accept_point(point)
+    }
+}
+
+impl MlPowerTransform {
+    pub fn new(
+        model: OnnxModel,
+        features: Vec<Feature>,
+        window_duration: Duration,
+        resource: Resource,
+        result_metric: TypedMetricId<f64>,
+    ) -> Self {
+        Self {
+            model,
+            features,
+            window_duration,
+            window: None,
+            resource,
+            result_metric,
+        }
+    }
+
+    /// Returns the window that contains `t`, after evaluating the windows that end before `t`.
+    fn window_at(&mut self, t: Timestamp, estimates: &mut Vec<MeasurementPoint>) -> anyhow::Result<&mut Window> {
+        let n_features = self.features.len();
+        let window = self.window.get_or_insert_with(|| Window::new(t, n_features));
+        let end = window.start + self.window_duration;
+        if t >= end {
+            let window = self.window.take().unwrap();
+            estimates.extend(self.evaluate(&window, end)?);
+            // skip the empty windows
+            let elapsed = t.duration_since(end).unwrap_or_default();
+            let n_skipped = (elapsed.as_nanos() / self.window_duration.as_nanos()) as u32;
+            self.window = Some(Window::new(end + self.window_duration * n_skipped, n_features));
+        }
+        Ok(self.window.as_mut().unwrap())
+    }
+
+    fn evaluate(&self, window: &Window, end: Timestamp) -> anyhow::Result<Vec<MeasurementPoint>> {
+        let features: Vec<_> = self.features.iter().map(|f| (f.per_consumer, f.aggregation)).collect();
+        let mut res = Vec::new();
+        for (consumer, row) in window.rows(&features) {
+            let power = self.model.predict(&row)?;
+            log::trace!("estimated {power} W for {consumer:?} from features {row:?}");
+            res.push(MeasurementPoint::new(
+                end,
+                self.result_metric,
+                self.resource.clone(),
+                consumer,
+                power.max(0.0),
+            ));
+        }
+        Ok(res)
+    }
+}
+
+impl Transform for MlPowerTransform {
+    fn apply(&mut self, measurements: &mut MeasurementBuffer, _ctx: &TransformContext) -> Result<(), TransformError> {
+        let mut estimates = Vec::new();
+        for point in measurements.iter() {
+            for i in 0..self.features.len() {
+                if !self.features[i].matches(point) {
+                    continue;
+                }
+                let per_consumer = self.features[i].per_consumer;
+                let value = point.value.as_f64();
+                let window = self.window_at(point.timestamp, &mut estimates)?;
+                if per_consumer {
+                    window.add_consumer(i, &point.consumer, value);
+                } else {
+                    window.add_node(i, value);
+                }
+            }
+        }
+        for point in estimates {
+            measurements.push(point);
+        }
+        Ok(())
+    }
+}
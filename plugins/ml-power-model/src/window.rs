@@ -0,0 +1,160 @@
+//! Aggregation of the features over a window of time.
+
+use std::collections::HashMap;
+
+use alumet::{measurement::Timestamp, resources::ResourceConsumer};
+
+use crate::config::Aggregation;
+
+/// Accumulates the measurements of a feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Accumulator {
+    sum: f64,
+    count: u32,
+    last: f64,
+}
+
+impl Accumulator {
+    pub fn add(&mut self, value: f64) {
+        self.sum += value;
+        self.count += 1;
+        self.last = value;
+    }
+
+    /// Returns the aggregated value, or zero if there is no measurement.
+    pub fn value(&self, aggregation: Aggregation) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        match aggregation {
+            Aggregation::Sum => self.sum,
+            Aggregation::Mean => self.sum / self.count as f64,
+            Aggregation::Last => self.last,
+        }
+    }
+}
+
+/// The measurements of the features during a window.
+pub struct Window {
+    pub start: Timestamp,
+    /// The features that are the same for every consumer.
+    node: Vec<Accumulator>,
+    /// The features of each consumer, in the order of their first measurement.
+    consumers: Vec<(ResourceConsumer, Vec<Accumulator>)>,
+    consumer_index: HashMap<ResourceConsumer, usize>,
+}
+
+impl Window {
+    pub fn new(start: Timestamp, n_features: usize) -> Self {
+        Self {
+            start,
+            node: vec![Accumulator::default(); n_features],
+            consumers: Vec::new(),
+            consumer_index: HashMap::new(),
+        }
+    }
+
+    /// Adds a measurement of a feature that is the same for every consumer.
+    pub fn add_node(&mut self, feature: usize, value: f64) {
+        self.node[feature].add(value);
+    }
+
+    /// Adds a measurement of a feature of a consumer.
+    pub fn add_consumer(&mut self, feature: usize, consumer: &ResourceConsumer, value: f64) {
+        let index = match self.consumer_index.get(consumer) {
+            Some(i) => *i,
+            None => {
+                let i = self.consumers.len();
+                self.consumers
+                    .push((consumer.clone(), vec![Accumulator::default(); self.node.len()]));
+                self.consumer_index.insert(consumer.clone(), i);
+                i
+            }
+        };
+        self.consumers[index].1[feature].add(value);
+    }
+
+    /// Returns the rows of features to give to the model, one per consumer.
+    ///
+    /// `features` gives, for each feature, whether it is computed per consumer and how it is aggregated.
+    /// If no feature is computed per consumer, there is only one row, for the `LocalMachine`.
+    pub fn rows(&self, features: &[(bool, Aggregation)]) -> Vec<(ResourceConsumer, Vec<f32>)> {
+        let row = |accumulators: Option<&Vec<Accumulator>>| {
+            features
+                .iter()
+                .enumerate()
+                .map(|(i, (per_consumer, aggregation))| {
+                    let acc = if *per_consumer {
+                        accumulators.map(|a| a[i]).unwrap_or_default()
+                    } else {
+                        self.node[i]
+                    };
+                    acc.value(*aggregation) as f32
+                })
+                .collect()
+        };
+        if features.iter().any(|(per_consumer, _)| *per_consumer) {
+            self.consumers
+                .iter()
+                .map(|(consumer, accumulators)| (consumer.clone(), row(Some(accumulators))))
+                .collect()
+        } else if self.node.iter().any(|acc| acc.count > 0) {
+            vec![(ResourceConsumer::LocalMachine, row(None))]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn aggregation() {
+        let mut acc = Accumulator::default();
+        assert_eq!(acc.value(Aggregation::Mean), 0.0);
+        acc.add(1.0);
+        acc.add(5.0);
+        assert_eq!(acc.value(Aggregation::Sum), 6.0);
+        assert_eq!(acc.value(Aggregation::Mean), 3.0);
+        assert_eq!(acc.value(Aggregation::Last), 5.0);
+    }
+
+    #[test]
+    fn rows_per_consumer() {
+        let p1 = ResourceConsumer::Process { pid: 1 };
+        let p2 = ResourceConsumer::Process { pid: 2 };
+        let mut window = Window::new(Timestamp::from_unix_timestamp(0, 0), 3);
+        window.add_consumer(0, &p2, 10.0);
+        window.add_consumer(0, &p1, 20.0);
+        window.add_consumer(0, &p1, 30.0);
+        window.add_consumer(1, &p1, 4.0);
+        window.add_node(2, 100.0);
+        window.add_node(2, 50.0);
+
+        let features = [
+            (true, Aggregation::Sum),
+            (true, Aggregation::Last),
+            (false, Aggregation::Mean),
+        ];
+        assert_eq!(
+            window.rows(&features),
+            vec![(p2, vec![10.0, 0.0, 75.0]), (p1, vec![50.0, 4.0, 75.0])]
+        );
+    }
+
+    #[test]
+    fn rows_of_node() {
+        let mut window = Window::new(Timestamp::from_unix_timestamp(0, 0), 1);
+        let features = [(false, Aggregation::Sum)];
+        assert_eq!(window.rows(&features), vec![]);
+        window.add_node(0, 2.5);
+        assert_eq!(
+            window.rows(&features),
+            vec![(ResourceConsumer::LocalMachine, vec![2.5])]
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Integration tests for the ML power model transform.
+
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{
+        self,
+        plugin::{PluginInfo, PluginSet},
+    },
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+    metrics::{RawMetricId, registry::MetricRegistry},
+    pipeline::naming::TransformName,
+    plugin::PluginMetadata,
+    resources::{Resource, ResourceConsumer},
+    test::RuntimeExpectations,
+    units::Unit,
+};
+use plugin_ml_power_model::{Config, MlPowerModelPlugin};
+use pretty_assertions::assert_eq;
+use prost::Message;
+use tract_onnx::pb;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_model() {
+    let root = tempfile::tempdir().unwrap();
+    let config = config(&root.path().join("missing.onnx"));
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no model)");
+}
+
+#[test]
+fn estimate_per_consumer() {
+    let root = tempfile::tempdir().unwrap();
+    let model_path = root.path().join("model.onnx");
+    // power = 2 * cpu + 0.5 * energy + 10
+    write_linear_model(&model_path, &[2.0, 0.5], 10.0);
+    let transform = TransformName::from_str("ml-power-model", "model");
+
+    fn point(metric: RawMetricId, t: u64, consumer: ResourceConsumer, value: f64) -> MeasurementPoint {
+        MeasurementPoint::new_untyped(
+            Timestamp::from_unix_timestamp(1_700_000_000 + t, 0),
+            metric,
+            Resource::LocalMachine,
+            consumer,
+            WrappedMeasurementValue::F64(value),
+        )
+    }
+
+    let runtime = RuntimeExpectations::new()
+        .create_metric::<f64>("cpu_time_delta", Unit::Second)
+        .create_metric::<f64>("rapl_consumed_energy", Unit::Joule)
+        .test_transform(
+            transform,
+            |input| {
+                let metrics = TestMetrics::find_in(input.metrics());
+                let p1 = ResourceConsumer::Process { pid: 1 };
+                let p2 = ResourceConsumer::Process { pid: 2 };
+                let mut buf = MeasurementBuffer::new();
+                buf.push(point(metrics.cpu_time_delta, 0, p1.clone(), 1.0));
+                buf.push(point(metrics.cpu_time_delta, 0, p2.clone(), 3.0));
+                buf.push(point(metrics.energy, 0, ResourceConsumer::LocalMachine, 20.0));
+                buf.push(point(metrics.cpu_time_delta, 5, p1.clone(), 1.0));
+                buf.push(point(metrics.energy, 5, ResourceConsumer::LocalMachine, 20.0));
+                // starts the next window
+                buf.push(point(metrics.cpu_time_delta, 10, p1, 8.0));
+                buf
+            },
+            |output| {
+                let metrics = TestMetrics::find_in(output.metrics());
+                let estimations: Vec<_> = output
+                    .measurements()
+                    .iter()
+                    .filter(|p| p.metric == metrics.estimated_power)
+                    .cloned()
+                    .collect();
+                assert_eq!(
+                    estimations,
+                    vec![
+                        point(metrics.estimated_power, 10, ResourceConsumer::Process { pid: 1 }, 34.0),
+                        point(metrics.estimated_power, 10, ResourceConsumer::Process { pid: 2 }, 36.0),
+                    ]
+                );
+            },
+        );
+
+    let agent = agent::Builder::new(plugins(config(&model_path)))
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn config(model_path: &Path) -> Config {
+    let config = format!(
+        r#"
+        model_path = "{}"
+        window = "10s"
+
+        [[features]]
+        metric = "cpu_time_delta"
+
+        [[features]]
+        metric = "rapl_consumed_energy"
+        per_consumer = false
+        "#,
+        model_path.display()
+    );
+    toml::from_str(&config).unwrap()
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<MlPowerModelPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
+
+/// Writes an ONNX model that computes `features * weights + bias`.
+fn write_linear_model(path: &Path, weights: &[f32], bias: f32) {
+    let float_tensor = |name: &str, dims: Vec<i64>, data: Vec<f32>| pb::TensorProto {
+        name: name.to_owned(),
+        dims,
+        data_type: pb::tensor_proto::DataType::Float as i32,
+        float_data: data,
+        ..Default::default()
+    };
+    let value_info = |name: &str, dims: &[i64]| pb::ValueInfoProto {
+        name: name.to_owned(),
+        r#type: Some(pb::TypeProto {
+            value: Some(pb::type_proto::Value::TensorType(pb::type_proto::Tensor {
+                elem_type: pb::tensor_proto::DataType::Float as i32,
+                shape: Some(pb::TensorShapeProto {
+                    dim: dims
+                        .iter()
+                        .map(|d| pb::tensor_shape_proto::Dimension {
+                            value: Some(pb::tensor_shape_proto::dimension::Value::DimValue(*d)),
+                            ..Default::default()
+                        })
+                        .collect(),
+                }),
+            })),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let node = |op_type: &str, inputs: [&str; 2], output: &str| pb::NodeProto {
+        op_type: op_type.to_owned(),
+        input: inputs.iter().map(|s| s.to_string()).collect(),
+        output: vec![output.to_owned()],
+        ..Default::default()
+    };
+
+    let n = weights.len() as i64;
+    let model = pb::ModelProto {
+        ir_version: 7,
+        opset_import: vec![pb::OperatorSetIdProto {
+            domain: String::new(),
+            version: 13,
+        }],
+        graph: Some(pb::GraphProto {
+            name: String::from("linear"),
+            node: vec![
+                node("MatMul", ["features", "weights"], "product"),
+                node("Add", ["product", "bias"], "power"),
+            ],
+            initializer: vec![
+                float_tensor("weights", vec![n, 1], weights.to_vec()),
+                float_tensor("bias", vec![1], vec![bias]),
+            ],
+            input: vec![value_info("features", &[1, n])],
+            output: vec![value_info("power", &[1, 1])],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    std::fs::write(path, model.encode_to_vec()).unwrap();
+}
+
+struct TestMetrics {
+    cpu_time_delta: RawMetricId,
+    energy: RawMetricId,
+    estimated_power: RawMetricId,
+}
+
+impl TestMetrics {
+    fn find_in(metrics: &MetricRegistry) -> Self {
+        Self {
+            cpu_time_delta: metrics.by_name("cpu_time_delta").unwrap().0,
+            energy: metrics.by_name("rapl_consumed_energy").unwrap().0,
+            estimated_power: metrics.by_name("ml_estimated_power").unwrap().0,
+        }
+    }
+}
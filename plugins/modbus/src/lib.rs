@@ -0,0 +1,279 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf, str::FromStr, time::Duration};
+
+use alumet::{
+    metrics::TypedMetricId,
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+    resources::Resource,
+    units::PrefixedUnit,
+};
+
+use crate::{
+    modbus::{ModbusTransport, RtuTransport, SerialSettings, TcpTransport},
+    source::{ModbusSource, Register},
+};
+pub use crate::{
+    modbus::{Parity, RegisterKind},
+    register::{DataType, WordOrder},
+};
+
+mod modbus;
+mod register;
+mod source;
+
+pub struct ModbusPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for ModbusPlugin {
+    fn name() -> &'static str {
+        "modbus"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(ModbusPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        if self.config.devices.is_empty() {
+            return Err(anyhow!("no Modbus device configured"));
+        }
+        // the same metric can be measured on several devices
+        let mut metrics: HashMap<String, (TypedMetricId<f64>, PrefixedUnit)> = HashMap::new();
+
+        for device in &self.config.devices {
+            let mut registers = Vec::with_capacity(device.registers.len());
+            for r in &device.registers {
+                let unit = PrefixedUnit::from_str(&r.unit)
+                    .with_context(|| format!("invalid unit '{}' for metric {}", r.unit, r.metric))?;
+                let metric = match metrics.get(&r.metric) {
+                    Some((id, existing_unit)) if *existing_unit == unit => *id,
+                    Some((_, existing_unit)) => {
+                        return Err(anyhow!(
+                            "metric {} has two different units: {existing_unit} and {unit}",
+                            r.metric
+                        ));
+                    }
+                    None => {
+                        let description = format!("Value of a Modbus register, measured in {unit}");
+                        let id = alumet.create_metric(&r.metric, unit.clone(), description)?;
+                        metrics.insert(r.metric.clone(), (id, unit));
+                        id
+                    }
+                };
+                registers.push(Register {
+                    metric,
+                    kind: r.kind,
+                    address: r.address,
+                    data_type: r.data_type,
+                    word_order: r.word_order,
+                    scale: r.scale,
+                });
+            }
+
+            let transport: Box<dyn ModbusTransport + Send> = match &device.transport {
+                TransportConfig::Tcp { address } => Box::new(TcpTransport::new(address.clone(), device.timeout)),
+                TransportConfig::Rtu {
+                    port,
+                    baud_rate,
+                    parity,
+                    stop_bits,
+                } => {
+                    let settings = SerialSettings {
+                        baud_rate: *baud_rate,
+                        parity: *parity,
+                        stop_bits: *stop_bits,
+                    };
+                    let transport = RtuTransport::open(port, &settings, device.timeout)
+                        .with_context(|| format!("could not open serial port {port:?}"))?;
+                    Box::new(transport)
+                }
+            };
+            let resource = match &device.node {
+                Some(node) => Resource::custom("node", node.clone()),
+                None => Resource::LocalMachine,
+            };
+            let source = ModbusSource::new(transport, device.unit_id, device.name.clone(), resource, registers);
+            let trigger = TriggerSpec::builder(self.config.poll_interval)
+                .flush_interval(self.config.flush_interval)
+                .build()?;
+            alumet.add_source(&device.name, Box::new(source), trigger)?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two Modbus measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Interval between two flushing of Modbus measurements.
+    #[serde(with = "humantime_serde")]
+    pub flush_interval: Duration,
+
+    /// The Modbus devices to measure.
+    pub devices: Vec<DeviceConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceConfig {
+    /// Name of the device, used as the name of its source.
+    pub name: String,
+
+    /// How to communicate with the device.
+    pub transport: TransportConfig,
+
+    /// Modbus address of the device (unit identifier).
+    #[serde(default = "default_unit_id")]
+    pub unit_id: u8,
+
+    /// Maximum time to wait for the response of the device.
+    #[serde(with = "humantime_serde", default = "default_timeout")]
+    pub timeout: Duration,
+
+    /// Name of the node measured by the device, if any, used as the id of the measured resource.
+    /// If not set, the resource is the local machine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+
+    /// The registers to read.
+    pub registers: Vec<RegisterConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(tag = "protocol", rename_all = "lowercase", deny_unknown_fields)]
+pub enum TransportConfig {
+    /// Modbus TCP.
+    Tcp {
+        /// Address of the device, such as `192.168.1.50:502`.
+        address: String,
+    },
+    /// Modbus RTU, on a serial line.
+    Rtu {
+        /// Path to the serial port, such as `/dev/ttyUSB0`.
+        port: PathBuf,
+        #[serde(default = "default_baud_rate")]
+        baud_rate: u32,
+        #[serde(default = "default_parity")]
+        parity: Parity,
+        #[serde(default = "default_stop_bits")]
+        stop_bits: u8,
+    },
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegisterConfig {
+    /// Name of the metric.
+    pub metric: String,
+    /// Address of the (first) register, starting at 0.
+    pub address: u16,
+    /// Kind of register.
+    #[serde(default = "default_register_kind")]
+    pub kind: RegisterKind,
+    /// Type of the value stored in the register(s).
+    #[serde(default = "default_data_type")]
+    pub data_type: DataType,
+    /// Order of the registers, for the values that span several registers.
+    #[serde(default)]
+    pub word_order: WordOrder,
+    /// Factor applied to the raw value, for instance `0.01` if the register contains hundredths.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Unit of the value, after scaling, such as `W`, `kW.h` or `V`.
+    pub unit: String,
+}
+
+fn default_unit_id() -> u8 {
+    1
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(1)
+}
+
+fn default_baud_rate() -> u32 {
+    9600
+}
+
+fn default_parity() -> Parity {
+    // the default parity of the Modbus specification
+    Parity::Even
+}
+
+fn default_stop_bits() -> u8 {
+    1
+}
+
+fn default_register_kind() -> RegisterKind {
+    RegisterKind::Holding
+}
+
+fn default_data_type() -> DataType {
+    DataType::U16
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            flush_interval: Duration::from_secs(5),
+            devices: vec![DeviceConfig {
+                name: String::from("meter"),
+                transport: TransportConfig::Tcp {
+                    address: String::from("192.168.1.50:502"),
+                },
+                unit_id: default_unit_id(),
+                timeout: default_timeout(),
+                node: None,
+                registers: vec![
+                    RegisterConfig {
+                        metric: String::from("meter_active_power"),
+                        address: 0,
+                        kind: RegisterKind::Holding,
+                        data_type: DataType::I32,
+                        word_order: WordOrder::Big,
+                        scale: 0.1,
+                        unit: String::from("W"),
+                    },
+                    RegisterConfig {
+                        metric: String::from("meter_active_energy"),
+                        address: 2,
+                        kind: RegisterKind::Holding,
+                        data_type: DataType::U32,
+                        word_order: WordOrder::Big,
+                        scale: 1.0,
+                        unit: String::from("W.h"),
+                    },
+                ],
+            }],
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//! Minimal Modbus client, which only reads registers, over TCP or RTU (serial line).
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    net::TcpStream,
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+    time::Duration,
+};
+
+use nix::sys::termios::{self, BaudRate, ControlFlags, SetArg, SpecialCharacterIndices};
+use serde::{Deserialize, Serialize};
+
+const FC_READ_HOLDING_REGISTERS: u8 = 0x03;
+const FC_READ_INPUT_REGISTERS: u8 = 0x04;
+/// Bit set in the function code of the exception responses.
+const EXCEPTION_BIT: u8 = 0x80;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModbusError {
+    #[error("Modbus I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("serial port configuration failed")]
+    Serial(#[from] nix::Error),
+    #[error("the Modbus device returned exception {0:#04x}")]
+    Exception(u8),
+    #[error("invalid Modbus response: {0}")]
+    InvalidResponse(&'static str),
+}
+
+/// Kind of register to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegisterKind {
+    /// Holding registers, read with function code 3.
+    Holding,
+    /// Input registers, read with function code 4.
+    Input,
+}
+
+impl RegisterKind {
+    fn function_code(self) -> u8 {
+        match self {
+            RegisterKind::Holding => FC_READ_HOLDING_REGISTERS,
+            RegisterKind::Input => FC_READ_INPUT_REGISTERS,
+        }
+    }
+}
+
+/// A way to send requests to a Modbus device.
+pub trait ModbusTransport {
+    /// Reads `count` consecutive registers, starting at `address`.
+    fn read_registers(
+        &mut self,
+        unit_id: u8,
+        kind: RegisterKind,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, ModbusError>;
+}
+
+/// Encodes the PDU (protocol data unit) of a read request.
+fn read_request_pdu(kind: RegisterKind, address: u16, count: u16) -> [u8; 5] {
+    let [a0, a1] = address.to_be_bytes();
+    let [c0, c1] = count.to_be_bytes();
+    [kind.function_code(), a0, a1, c0, c1]
+}
+
+/// Decodes the PDU of a read response.
+fn parse_response_pdu(kind: RegisterKind, count: u16, pdu: &[u8]) -> Result<Vec<u16>, ModbusError> {
+    match pdu {
+        [fc, code] if *fc == kind.function_code() | EXCEPTION_BIT => Err(ModbusError::Exception(*code)),
+        [fc, byte_count, data @ ..] if *fc == kind.function_code() => {
+            if *byte_count as usize != 2 * count as usize || data.len() != *byte_count as usize {
+                return Err(ModbusError::InvalidResponse("wrong number of registers"));
+            }
+            Ok(data.chunks_exact(2).map(|r| u16::from_be_bytes([r[0], r[1]])).collect())
+        }
+        _ => Err(ModbusError::InvalidResponse("unexpected function code")),
+    }
+}
+
+// ---- Modbus TCP
+
+/// A Modbus TCP device, such as a meter with an Ethernet port or a Modbus gateway.
+pub struct TcpTransport {
+    address: String,
+    timeout: Duration,
+    /// The connection, opened on the first request, and closed after an error.
+    stream: Option<TcpStream>,
+    next_transaction_id: u16,
+}
+
+impl TcpTransport {
+    pub fn new(address: String, timeout: Duration) -> Self {
+        Self {
+            address,
+            timeout,
+            stream: None,
+            next_transaction_id: 0,
+        }
+    }
+
+    fn connect(&mut self) -> Result<&mut TcpStream, ModbusError> {
+        if self.stream.is_none() {
+            let stream = TcpStream::connect(&self.address)?;
+            stream.set_read_timeout(Some(self.timeout))?;
+            stream.set_write_timeout(Some(self.timeout))?;
+            stream.set_nodelay(true)?;
+            self.stream = Some(stream);
+        }
+        Ok(self.stream.as_mut().unwrap())
+    }
+
+    fn transaction(&mut self, unit_id: u8, pdu: &[u8]) -> Result<Vec<u8>, ModbusError> {
+        let transaction_id = self.next_transaction_id;
+        self.next_transaction_id = self.next_transaction_id.wrapping_add(1);
+
+        // MBAP header: transaction id, protocol id (0), length of the rest, unit id
+        let mut request = Vec::with_capacity(7 + pdu.len());
+        request.extend(transaction_id.to_be_bytes());
+        request.extend(0u16.to_be_bytes());
+        request.extend((pdu.len() as u16 + 1).to_be_bytes());
+        request.push(unit_id);
+        request.extend(pdu);
+
+        let stream = self.connect()?;
+        stream.write_all(&request)?;
+        let mut header = [0u8; 7];
+        stream.read_exact(&mut header)?;
+        let length = u16::from_be_bytes([header[4], header[5]]) as usize;
+        if length < 2 {
+            return Err(ModbusError::InvalidResponse("invalid length"));
+        }
+        let mut response = vec![0u8; length - 1];
+        stream.read_exact(&mut response)?;
+        if u16::from_be_bytes([header[0], header[1]]) != transaction_id || header[6] != unit_id {
+            return Err(ModbusError::InvalidResponse("unexpected transaction or unit id"));
+        }
+        Ok(response)
+    }
+}
+
+impl ModbusTransport for TcpTransport {
+    fn read_registers(
+        &mut self,
+        unit_id: u8,
+        kind: RegisterKind,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, ModbusError> {
+        let pdu = read_request_pdu(kind, address, count);
+        match self.transaction(unit_id, &pdu) {
+            Ok(response) => parse_response_pdu(kind, count, &response),
+            Err(e) => {
+                // the stream may contain a partial response: reconnect at the next request
+                self.stream = None;
+                Err(e)
+            }
+        }
+    }
+}
+
+// ---- Modbus RTU
+
+/// Parity of the serial line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+/// Settings of the serial line.
+#[derive(Debug, Clone)]
+pub struct SerialSettings {
+    pub baud_rate: u32,
+    pub parity: Parity,
+    pub stop_bits: u8,
+}
+
+/// A Modbus RTU device, connected to a serial port (usually through a RS-485 adapter).
+pub struct RtuTransport {
+    port: File,
+}
+
+impl RtuTransport {
+    pub fn open(path: &Path, settings: &SerialSettings, timeout: Duration) -> Result<Self, ModbusError> {
+        let port = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(nix::libc::O_NOCTTY)
+            .open(path)?;
+
+        let mut tty = termios::tcgetattr(&port)?;
+        termios::cfmakeraw(&mut tty);
+        let baud_rate = baud_rate(settings.baud_rate)
+            .ok_or_else(|| std::io::Error::other(format!("unsupported baud rate {}", settings.baud_rate)))?;
+        termios::cfsetspeed(&mut tty, baud_rate)?;
+        tty.control_flags |= ControlFlags::CLOCAL | ControlFlags::CREAD;
+        tty.control_flags
+            .set(ControlFlags::PARENB, settings.parity != Parity::None);
+        tty.control_flags
+            .set(ControlFlags::PARODD, settings.parity == Parity::Odd);
+        tty.control_flags.set(ControlFlags::CSTOPB, settings.stop_bits == 2);
+        // read() returns after the timeout (in tenths of seconds) if no byte is received
+        let deciseconds = timeout.as_millis().div_ceil(100).clamp(1, 255) as u8;
+        tty.control_chars[SpecialCharacterIndices::VMIN as usize] = 0;
+        tty.control_chars[SpecialCharacterIndices::VTIME as usize] = deciseconds;
+        termios::tcsetattr(&port, SetArg::TCSANOW, &tty)?;
+        Ok(Self { port })
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), ModbusError> {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.port.read(&mut buf[n..])? {
+                0 => return Err(std::io::Error::from(std::io::ErrorKind::TimedOut).into()),
+                read => n += read,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn baud_rate(value: u32) -> Option<BaudRate> {
+    Some(match value {
+        1200 => BaudRate::B1200,
+        2400 => BaudRate::B2400,
+        4800 => BaudRate::B4800,
+        9600 => BaudRate::B9600,
+        19200 => BaudRate::B19200,
+        38400 => BaudRate::B38400,
+        57600 => BaudRate::B57600,
+        115200 => BaudRate::B115200,
+        _ => return None,
+    })
+}
+
+/// Computes the CRC of a Modbus RTU frame.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xffff;
+    for b in data {
+        crc ^= u16::from(*b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xa001 } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+/// Builds a RTU frame: unit id, PDU, CRC (little endian).
+fn rtu_frame(unit_id: u8, pdu: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(pdu.len() + 3);
+    frame.push(unit_id);
+    frame.extend(pdu);
+    frame.extend(crc16(&frame).to_le_bytes());
+    frame
+}
+
+impl ModbusTransport for RtuTransport {
+    fn read_registers(
+        &mut self,
+        unit_id: u8,
+        kind: RegisterKind,
+        address: u16,
+        count: u16,
+    ) -> Result<Vec<u16>, ModbusError> {
+        // discard the remains of the previous exchanges, if any
+        termios::tcflush(&self.port, termios::FlushArg::TCIFLUSH)?;
+        self.port
+            .write_all(&rtu_frame(unit_id, &read_request_pdu(kind, address, count)))?;
+
+        // unit id, function code, byte count or exception code
+        let mut frame = vec![0u8; 3];
+        self.read_exact(&mut frame)?;
+        let remaining = if frame[1] & EXCEPTION_BIT != 0 {
+            2
+        } else {
+            frame[2] as usize + 2
+        };
+        frame.resize(3 + remaining, 0);
+        self.read_exact(&mut frame[3..])?;
+
+        let (content, crc) = frame.split_at(frame.len() - 2);
+        if crc16(content).to_le_bytes() != crc {
+            return Err(ModbusError::InvalidResponse("wrong CRC"));
+        }
+        if content[0] != unit_id {
+            return Err(ModbusError::InvalidResponse("unexpected unit id"));
+        }
+        parse_response_pdu(kind, count, &content[1..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn crc() {
+        // read 10 holding registers at address 0 of device 1
+        let frame = rtu_frame(1, &read_request_pdu(RegisterKind::Holding, 0, 10));
+        assert_eq!(frame, vec![0x01, 0x03, 0x00, 0x00, 0x00, 0x0a, 0xc5, 0xcd]);
+    }
+
+    #[test]
+    fn response_pdu() {
+        let registers = parse_response_pdu(RegisterKind::Input, 2, &[0x04, 0x04, 0x12, 0x34, 0xab, 0xcd]).unwrap();
+        assert_eq!(registers, vec![0x1234, 0xabcd]);
+
+        let err = parse_response_pdu(RegisterKind::Input, 2, &[0x84, 0x02]).unwrap_err();
+        assert!(matches!(err, ModbusError::Exception(0x02)));
+
+        let err = parse_response_pdu(RegisterKind::Input, 2, &[0x04, 0x02, 0x12, 0x34]).unwrap_err();
+        assert!(matches!(err, ModbusError::InvalidResponse(_)));
+
+        let err = parse_response_pdu(RegisterKind::Input, 1, &[0x03, 0x02, 0x12, 0x34]).unwrap_err();
+        assert!(matches!(err, ModbusError::InvalidResponse(_)));
+    }
+
+    #[test]
+    fn tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 12];
+            stream.read_exact(&mut request).unwrap();
+            // transaction 0, protocol 0, length 6, unit 7, read 2 holding registers at 0x0100
+            assert_eq!(request, [0, 0, 0, 0, 0, 6, 7, 0x03, 0x01, 0x00, 0x00, 0x02]);
+            stream
+                .write_all(&[0, 0, 0, 0, 0, 7, 7, 0x03, 0x04, 0x00, 0x01, 0x00, 0x02])
+                .unwrap();
+
+            // exception: illegal data address
+            stream.read_exact(&mut request).unwrap();
+            stream.write_all(&[0, 1, 0, 0, 0, 3, 7, 0x83, 0x02]).unwrap();
+        });
+
+        let mut device = TcpTransport::new(address, Duration::from_secs(1));
+        let registers = device.read_registers(7, RegisterKind::Holding, 0x0100, 2).unwrap();
+        assert_eq!(registers, vec![1, 2]);
+        let err = device.read_registers(7, RegisterKind::Holding, 0xffff, 2).unwrap_err();
+        assert!(matches!(err, ModbusError::Exception(0x02)));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,86 @@
+//! Decoding of the values stored in Modbus registers.
+
+use serde::{Deserialize, Serialize};
+
+/// Type of the value stored in one or several consecutive registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataType {
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+}
+
+/// Order of the 16-bit registers in a value that spans several registers.
+///
+/// Modbus only specifies the order of the bytes in a register (big endian).
+/// Most meters store the most significant register first, but some of them do the opposite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WordOrder {
+    /// Most significant register first.
+    #[default]
+    Big,
+    /// Least significant register first.
+    Little,
+}
+
+impl DataType {
+    /// Number of registers used by a value of this type.
+    pub fn register_count(self) -> u16 {
+        match self {
+            DataType::U16 | DataType::I16 => 1,
+            DataType::U32 | DataType::I32 | DataType::F32 => 2,
+            DataType::U64 | DataType::I64 => 4,
+        }
+    }
+
+    /// Decodes a value from its registers.
+    ///
+    /// `registers` must contain exactly [`register_count`](Self::register_count) registers.
+    pub fn decode(self, registers: &[u16], word_order: WordOrder) -> f64 {
+        debug_assert_eq!(registers.len(), self.register_count() as usize);
+        // concatenate the registers, most significant first
+        let concat = |acc: u64, r: &u16| (acc << 16) | u64::from(*r);
+        let bits = match word_order {
+            WordOrder::Big => registers.iter().fold(0, concat),
+            WordOrder::Little => registers.iter().rev().fold(0, concat),
+        };
+        match self {
+            DataType::U16 | DataType::U32 | DataType::U64 => bits as f64,
+            DataType::I16 => bits as u16 as i16 as f64,
+            DataType::I32 => bits as u32 as i32 as f64,
+            DataType::I64 => bits as i64 as f64,
+            DataType::F32 => f64::from(f32::from_bits(bits as u32)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DataType, WordOrder};
+
+    #[test]
+    fn decode() {
+        assert_eq!(DataType::U16.decode(&[0xfffe], WordOrder::Big), 65534.0);
+        assert_eq!(DataType::I16.decode(&[0xfffe], WordOrder::Big), -2.0);
+        assert_eq!(DataType::U32.decode(&[0x0001, 0x0002], WordOrder::Big), 65538.0);
+        assert_eq!(DataType::U32.decode(&[0x0002, 0x0001], WordOrder::Little), 65538.0);
+        assert_eq!(DataType::I32.decode(&[0xffff, 0xff38], WordOrder::Big), -200.0);
+        assert_eq!(
+            DataType::U64.decode(&[0x0000, 0x0001, 0x0000, 0x0000], WordOrder::Big),
+            4294967296.0
+        );
+        assert_eq!(
+            DataType::I64.decode(&[0xffff, 0xffff, 0xffff, 0xffff], WordOrder::Big),
+            -1.0
+        );
+        // 230.5 = 0x43668000
+        assert_eq!(DataType::F32.decode(&[0x4366, 0x8000], WordOrder::Big), 230.5);
+        assert_eq!(DataType::F32.decode(&[0x8000, 0x4366], WordOrder::Little), 230.5);
+    }
+}
@@ -0,0 +1,75 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    resources::{Resource, ResourceConsumer},
+};
+use anyhow::Context;
+
+use crate::{
+    modbus::{ModbusTransport, RegisterKind},
+    register::{DataType, WordOrder},
+};
+
+/// A value to read at each measurement.
+pub struct Register {
+    pub metric: TypedMetricId<f64>,
+    pub kind: RegisterKind,
+    pub address: u16,
+    pub data_type: DataType,
+    pub word_order: WordOrder,
+    pub scale: f64,
+}
+
+/// Measurement source that reads the registers of a Modbus device.
+pub struct ModbusSource {
+    transport: Box<dyn ModbusTransport + Send>,
+    unit_id: u8,
+    /// Name of the device, added to the measurements as an attribute.
+    name: String,
+    resource: Resource,
+    registers: Vec<Register>,
+}
+
+impl ModbusSource {
+    pub fn new(
+        transport: Box<dyn ModbusTransport + Send>,
+        unit_id: u8,
+        name: String,
+        resource: Resource,
+        registers: Vec<Register>,
+    ) -> Self {
+        Self {
+            transport,
+            unit_id,
+            name,
+            resource,
+            registers,
+        }
+    }
+}
+
+impl Source for ModbusSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for r in &self.registers {
+            // Serial lines and gateways are not always reliable: try again at the next measurement.
+            let registers = self
+                .transport
+                .read_registers(self.unit_id, r.kind, r.address, r.data_type.register_count())
+                .with_context(|| format!("failed to read register {} of Modbus device {}", r.address, self.name))
+                .map_err(PollError::CanRetry)?;
+            let value = r.data_type.decode(&registers, r.word_order) * r.scale;
+            measurements.push(
+                MeasurementPoint::new(
+                    timestamp,
+                    r.metric,
+                    self.resource.clone(),
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("device", self.name.clone()),
+            );
+        }
+        Ok(())
+    }
+}
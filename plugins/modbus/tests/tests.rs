@@ -0,0 +1,125 @@
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::{PrefixedUnit, Unit},
+};
+use plugin_modbus::{
+    Config, DataType, DeviceConfig, ModbusPlugin, RegisterConfig, RegisterKind, TransportConfig, WordOrder,
+};
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    time::Duration,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const PLUGIN_NAME: &str = "modbus";
+
+#[test]
+fn plugin_with_fake_tcp_meter() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || fake_meter(listener));
+
+    let config = Config {
+        poll_interval: Duration::from_secs(1),
+        flush_interval: Duration::from_secs(1),
+        devices: vec![DeviceConfig {
+            name: String::from("meter"),
+            transport: TransportConfig::Tcp { address },
+            unit_id: 1,
+            timeout: Duration::from_secs(1),
+            node: Some(String::from("node-1")),
+            registers: vec![
+                RegisterConfig {
+                    metric: String::from("meter_active_power"),
+                    address: 0x10,
+                    kind: RegisterKind::Input,
+                    data_type: DataType::I32,
+                    word_order: WordOrder::Big,
+                    scale: 0.1,
+                    unit: String::from("W"),
+                },
+                RegisterConfig {
+                    metric: String::from("meter_active_energy"),
+                    address: 0x20,
+                    kind: RegisterKind::Holding,
+                    data_type: DataType::U32,
+                    word_order: WordOrder::Little,
+                    scale: 1.0,
+                    unit: String::from("kW.h"),
+                },
+            ],
+        }],
+    };
+
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("meter_active_power", Unit::Watt)
+        .expect_metric::<f64>("meter_active_energy", PrefixedUnit::kilo(Unit::WattHour))
+        .expect_source(PLUGIN_NAME, "meter");
+
+    let runtime_expectation = RuntimeExpectations::new().test_source(
+        SourceName::from_str(PLUGIN_NAME, "meter"),
+        || {},
+        |ctx| {
+            let m = ctx.measurements();
+            let power_metric = ctx.metrics().by_name("meter_active_power").unwrap().0;
+            let energy_metric = ctx.metrics().by_name("meter_active_energy").unwrap().0;
+            assert_eq!(m.len(), 2);
+            let power = m.iter().find(|p| p.metric == power_metric).unwrap();
+            assert_eq!(power.value.as_f64(), 1234.5);
+            assert_eq!(power.resource, Resource::custom("node", "node-1"));
+            let energy = m.iter().find(|p| p.metric == energy_metric).unwrap();
+            assert_eq!(energy.value.as_f64(), 65538.0);
+        },
+    );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup_expectation)
+        .with_expectations(runtime_expectation)
+        .build_and_start()
+        .unwrap();
+
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+#[test]
+fn plugin_with_invalid_unit() {
+    let mut config = Config::default();
+    config.devices[0].registers[0].unit = String::from("watts");
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (invalid unit)");
+}
+
+/// Answers the Modbus TCP requests like a meter.
+fn fake_meter(listener: TcpListener) {
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut request = [0u8; 12];
+    while stream.read_exact(&mut request).is_ok() {
+        let (transaction, unit, function, address) = (&request[..2], request[6], request[7], request[9]);
+        let data: &[u8] = match (function, address) {
+            // input register 0x10, i32: 12345 (1234.5 W)
+            (0x04, 0x10) => &[0x00, 0x00, 0x30, 0x39],
+            // holding register 0x20, u32 with the least significant register first: 65538 kWh
+            (0x03, 0x20) => &[0x00, 0x02, 0x00, 0x01],
+            _ => panic!("unexpected request {request:?}"),
+        };
+        let mut response = transaction.to_vec();
+        response.extend([0, 0, 0, 3 + data.len() as u8, unit, function, data.len() as u8]);
+        response.extend(data);
+        stream.write_all(&response).unwrap();
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<ModbusPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -4,7 +4,10 @@ use alumet::{
         Output,
         elements::{error::WriteError, output::OutputContext},
     },
-    plugin::rust::{AlumetPlugin, deserialize_config, serialize_config},
+    plugin::{
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+        secret::Secret,
+    },
 };
 
 use mongodb::{
@@ -178,7 +181,7 @@ struct Config {
     database: String,
     collection: String,
     username: Option<String>,
-    password: Option<String>,
+    password: Option<Secret>,
 }
 
 impl Default for Config {
@@ -189,7 +192,7 @@ impl Default for Config {
             database: String::from("FILL ME"),
             collection: String::from("FILL ME"),
             username: Some(String::from("FILL ME")),
-            password: Some(String::from("FILL ME")),
+            password: Some(Secret::new("FILL ME")),
         }
     }
 }
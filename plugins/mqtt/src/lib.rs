@@ -0,0 +1,191 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    time::Duration,
+};
+
+use alumet::{
+    metrics::TypedMetricId,
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+        secret::Secret,
+    },
+    units::PrefixedUnit,
+};
+
+use crate::{mapping::Mapping, mqtt::ConnectOptions};
+pub use mapping::TimestampFormat;
+
+mod mapping;
+mod mqtt;
+mod source;
+
+pub struct MqttPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for MqttPlugin {
+    fn name() -> &'static str {
+        "mqtt"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(MqttPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        if self.config.mappings.is_empty() {
+            return Err(anyhow!("no MQTT mapping configured"));
+        }
+        // the same metric can be obtained from several topics
+        let mut metrics: HashMap<String, (TypedMetricId<f64>, PrefixedUnit)> = HashMap::new();
+
+        let mut mappings = Vec::with_capacity(self.config.mappings.len());
+        for m in &self.config.mappings {
+            let pointers = std::iter::once(&m.value)
+                .chain(&m.timestamp)
+                .chain(m.attributes.values());
+            for pointer in pointers {
+                if !pointer.is_empty() && !pointer.starts_with('/') {
+                    return Err(anyhow!(
+                        "invalid JSON pointer '{pointer}' for metric {}: it must be empty or start with '/'",
+                        m.metric
+                    ));
+                }
+            }
+            let unit = PrefixedUnit::from_str(&m.unit)
+                .with_context(|| format!("invalid unit '{}' for metric {}", m.unit, m.metric))?;
+            let metric = match metrics.get(&m.metric) {
+                Some((id, existing_unit)) if *existing_unit == unit => *id,
+                Some((_, existing_unit)) => {
+                    return Err(anyhow!(
+                        "metric {} has two different units: {existing_unit} and {unit}",
+                        m.metric
+                    ));
+                }
+                None => {
+                    let description = format!("Value received with MQTT, measured in {unit}");
+                    let id = alumet.create_metric(&m.metric, unit.clone(), description)?;
+                    metrics.insert(m.metric.clone(), (id, unit));
+                    id
+                }
+            };
+            mappings.push(Mapping {
+                topic: m.topic.clone(),
+                metric,
+                value: m.value.clone(),
+                timestamp: m.timestamp.clone(),
+                timestamp_format: m.timestamp_format,
+                attributes: m.attributes.clone().into_iter().collect(),
+            });
+        }
+
+        let client_id = match &self.config.client_id {
+            Some(id) => id.clone(),
+            None => {
+                let hostname = hostname::get().context("could not get the hostname")?;
+                format!("alumet-{}", hostname.to_string_lossy())
+            }
+        };
+        let options = ConnectOptions {
+            client_id,
+            username: self.config.username.clone(),
+            password: self.config.password.clone(),
+            keep_alive: self.config.keep_alive,
+        };
+        let broker = self.config.broker.clone();
+        alumet.add_autonomous_source_builder("subscriber", move |_ctx, cancel_token, out_tx| {
+            Ok(Box::pin(source::run(broker, options, mappings, cancel_token, out_tx)))
+        })?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Address of the MQTT broker, such as `localhost:1883`.
+    pub broker: String,
+
+    /// Identifier of the MQTT client. If not set, `alumet-<hostname>` is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+
+    /// Name of the MQTT user, if the broker requires an authentication.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    /// Password of the MQTT user, which can reference a secret (`secret://...`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<Secret>,
+
+    /// Maximum interval between two packets sent to the broker, which pings it when there is nothing else to send.
+    /// Zero disables the keep alive mechanism.
+    #[serde(with = "humantime_serde")]
+    pub keep_alive: Duration,
+
+    /// How to convert the received messages to measurements.
+    pub mappings: Vec<MappingConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct MappingConfig {
+    /// Topic to subscribe to. It can contain the wildcards `+` (one level) and `#` (any number of levels).
+    pub topic: String,
+    /// Name of the metric.
+    pub metric: String,
+    /// Unit of the value, such as `W`, `kW.h` or `V`.
+    pub unit: String,
+    /// JSON pointer to the value in the payload, such as `/ENERGY/Power`.
+    /// An empty pointer selects the whole payload, for the messages that only contain a number.
+    #[serde(default)]
+    pub value: String,
+    /// JSON pointer to the timestamp in the payload. If not set, the time of reception is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// Format of the timestamp.
+    #[serde(default)]
+    pub timestamp_format: TimestampFormat,
+    /// Attributes to add to the measurements: name of the attribute and JSON pointer to its value in the payload.
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            broker: String::from("localhost:1883"),
+            client_id: None,
+            username: None,
+            password: None,
+            keep_alive: Duration::from_secs(30),
+            mappings: vec![MappingConfig {
+                topic: String::from("tele/+/SENSOR"),
+                metric: String::from("smart_plug_power"),
+                unit: String::from("W"),
+                value: String::from("/ENERGY/Power"),
+                timestamp: None,
+                timestamp_format: TimestampFormat::default(),
+                attributes: BTreeMap::new(),
+            }],
+        }
+    }
+}
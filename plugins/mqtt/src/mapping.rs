@@ -0,0 +1,191 @@
+//! Conversion of the MQTT messages to measurement points.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use alumet::{
+    measurement::{AttributeValue, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    resources::{Resource, ResourceConsumer},
+};
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+/// Format of the timestamps found in the payloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    /// A string in the RFC 3339 format, such as `2025-06-01T12:00:00.5+02:00`.
+    #[default]
+    Rfc3339,
+    /// A number of seconds since the Unix epoch, with an optional fractional part.
+    UnixSeconds,
+    /// A number of milliseconds since the Unix epoch.
+    UnixMillis,
+}
+
+/// How to convert the messages of some topics to measurement points.
+pub struct Mapping {
+    /// Topic filter, which can contain the wildcards `+` and `#`.
+    pub topic: String,
+    pub metric: TypedMetricId<f64>,
+    /// JSON pointer to the value.
+    pub value: String,
+    /// JSON pointer to the timestamp, if any.
+    pub timestamp: Option<String>,
+    pub timestamp_format: TimestampFormat,
+    /// Attributes to add to the measurement points: name and JSON pointer.
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Mapping {
+    /// Converts a message into a measurement point.
+    pub fn convert(&self, topic: &str, payload: &Value) -> anyhow::Result<MeasurementPoint> {
+        let value = payload
+            .pointer(&self.value)
+            .and_then(as_f64)
+            .ok_or_else(|| anyhow!("no numeric value at {:?}", self.value))?;
+        let timestamp = match &self.timestamp {
+            Some(pointer) => {
+                let t = payload
+                    .pointer(pointer)
+                    .ok_or_else(|| anyhow!("no timestamp at {pointer:?}"))?;
+                parse_timestamp(t, self.timestamp_format)?
+            }
+            None => Timestamp::now(),
+        };
+        let mut point = MeasurementPoint::new(
+            timestamp,
+            self.metric,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            value,
+        )
+        .with_attr("topic", topic.to_owned());
+        for (name, pointer) in &self.attributes {
+            if let Some(attr) = payload.pointer(pointer).and_then(attribute_value) {
+                point = point.with_attr(name.clone(), attr);
+            }
+        }
+        Ok(point)
+    }
+}
+
+/// Returns true if the topic matches the filter, as specified by MQTT.
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    // the topics that start with $ are reserved, and not matched by the wildcards at the first level
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+    let mut topic_levels = topic.split('/');
+    for filter_level in filter.split('/') {
+        match (filter_level, topic_levels.next()) {
+            // matches the parent level too: "a/#" matches "a"
+            ("#", _) => return true,
+            ("+", Some(_)) => (),
+            (f, Some(t)) if f == t => (),
+            _ => return false,
+        }
+    }
+    topic_levels.next().is_none()
+}
+
+/// Converts a JSON value to a number. Numbers stored in strings are parsed.
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        _ => None,
+    }
+}
+
+fn attribute_value(value: &Value) -> Option<AttributeValue> {
+    match value {
+        Value::String(s) => Some(AttributeValue::String(s.clone())),
+        Value::Bool(b) => Some(AttributeValue::Bool(*b)),
+        Value::Number(n) => n
+            .as_u64()
+            .map(AttributeValue::U64)
+            .or_else(|| n.as_f64().map(AttributeValue::F64)),
+        _ => None,
+    }
+}
+
+fn parse_timestamp(value: &Value, format: TimestampFormat) -> anyhow::Result<Timestamp> {
+    let time: SystemTime = match format {
+        TimestampFormat::Rfc3339 => {
+            let s = value.as_str().ok_or_else(|| anyhow!("the timestamp is not a string"))?;
+            OffsetDateTime::parse(s, &Rfc3339)
+                .with_context(|| format!("invalid RFC 3339 timestamp {s:?}"))?
+                .into()
+        }
+        TimestampFormat::UnixSeconds | TimestampFormat::UnixMillis => {
+            let n = as_f64(value).ok_or_else(|| anyhow!("the timestamp is not a number"))?;
+            let secs = if format == TimestampFormat::UnixMillis {
+                n / 1000.0
+            } else {
+                n
+            };
+            let since_epoch = Duration::try_from_secs_f64(secs).with_context(|| format!("invalid timestamp {n}"))?;
+            UNIX_EPOCH + since_epoch
+        }
+    };
+    Ok(Timestamp::from(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn topic_matching() {
+        assert!(topic_matches("a/b", "a/b"));
+        assert!(!topic_matches("a/b", "a/c"));
+        assert!(!topic_matches("a/b", "a/b/c"));
+        assert!(!topic_matches("a/b/c", "a/b"));
+        assert!(topic_matches("a/+/c", "a/b/c"));
+        assert!(!topic_matches("a/+", "a/b/c"));
+        assert!(topic_matches("a/#", "a/b/c"));
+        assert!(topic_matches("a/#", "a"));
+        assert!(topic_matches("#", "a/b"));
+        assert!(topic_matches("+/+", "/b"));
+        assert!(!topic_matches("#", "$SYS/uptime"));
+        assert!(topic_matches("$SYS/#", "$SYS/uptime"));
+    }
+
+    #[test]
+    fn timestamps() {
+        let expected = Timestamp::from(UNIX_EPOCH + Duration::from_millis(1_750_000_000_500));
+        let t = parse_timestamp(&json!("2025-06-15T15:06:40.5Z"), TimestampFormat::Rfc3339).unwrap();
+        assert_eq!(t, expected);
+        let t = parse_timestamp(&json!(1_750_000_000.5), TimestampFormat::UnixSeconds).unwrap();
+        assert_eq!(t, expected);
+        let t = parse_timestamp(&json!(1_750_000_000_500u64), TimestampFormat::UnixMillis).unwrap();
+        assert_eq!(t, expected);
+        let t = parse_timestamp(&json!("1750000000500"), TimestampFormat::UnixMillis).unwrap();
+        assert_eq!(t, expected);
+
+        assert!(parse_timestamp(&json!(12), TimestampFormat::Rfc3339).is_err());
+        assert!(parse_timestamp(&json!(-1), TimestampFormat::UnixSeconds).is_err());
+    }
+
+    #[test]
+    fn values_and_attributes() {
+        assert_eq!(as_f64(&json!(12)), Some(12.0));
+        assert_eq!(as_f64(&json!("12.5")), Some(12.5));
+        assert_eq!(as_f64(&json!(true)), Some(1.0));
+        assert_eq!(as_f64(&json!(null)), None);
+        assert_eq!(as_f64(&json!({"a": 1})), None);
+
+        assert_eq!(
+            attribute_value(&json!("plug-1")),
+            Some(AttributeValue::String(String::from("plug-1")))
+        );
+        assert_eq!(attribute_value(&json!(3)), Some(AttributeValue::U64(3)));
+        assert_eq!(attribute_value(&json!(-3)), Some(AttributeValue::F64(-3.0)));
+        assert_eq!(attribute_value(&json!([1])), None);
+    }
+}
@@ -0,0 +1,485 @@
+//! Minimal asynchronous MQTT 3.1.1 client, which subscribes to topics and receives the published messages.
+//!
+//! The subscriptions use QoS 0 ("at most once"), which is enough for measurements.
+
+use std::time::Duration;
+
+use alumet::plugin::secret::Secret;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+const PROTOCOL_LEVEL_3_1_1: u8 = 4;
+
+// packet types
+const CONNECT: u8 = 1;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const PUBACK: u8 = 4;
+const SUBSCRIBE: u8 = 8;
+const SUBACK: u8 = 9;
+const PINGREQ: u8 = 12;
+const PINGRESP: u8 = 13;
+const DISCONNECT: u8 = 14;
+
+// connect flags
+const FLAG_USERNAME: u8 = 0x80;
+const FLAG_PASSWORD: u8 = 0x40;
+const FLAG_CLEAN_SESSION: u8 = 0x02;
+
+/// Maximum value of the "remaining length" field of a packet.
+const MAX_REMAINING_LENGTH: usize = 268_435_455;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MqttError {
+    #[error("MQTT I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("the MQTT broker refused the connection with return code {0}")]
+    ConnectionRefused(u8),
+    #[error("the MQTT broker refused the subscription to {0}")]
+    SubscriptionRefused(String),
+    #[error("invalid MQTT packet: {0}")]
+    InvalidPacket(&'static str),
+}
+
+/// Options of the MQTT connection.
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub keep_alive: Duration,
+}
+
+/// A message published on a topic.
+#[derive(Debug, PartialEq)]
+pub struct Message {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+/// A packet received from the broker.
+#[derive(Debug, PartialEq)]
+enum Packet {
+    ConnAck {
+        return_code: u8,
+    },
+    Publish {
+        message: Message,
+        packet_id: Option<u16>,
+    },
+    SubAck {
+        packet_id: u16,
+        return_codes: Vec<u8>,
+    },
+    PingResp,
+    /// A packet that the client does not need to handle.
+    Other(u8),
+}
+
+/// A connection to an MQTT broker.
+pub struct MqttClient {
+    stream: TcpStream,
+    /// Bytes received from the broker, which do not form a complete packet yet.
+    buffer: Vec<u8>,
+}
+
+impl MqttClient {
+    /// Connects to the broker, for instance `localhost:1883`.
+    pub async fn connect(address: &str, options: &ConnectOptions) -> Result<Self, MqttError> {
+        let stream = TcpStream::connect(address).await?;
+        stream.set_nodelay(true)?;
+        let mut client = Self {
+            stream,
+            buffer: Vec::new(),
+        };
+        client.send(&encode_connect(options)).await?;
+        match client.read_packet().await? {
+            Packet::ConnAck { return_code: 0 } => Ok(client),
+            Packet::ConnAck { return_code } => Err(MqttError::ConnectionRefused(return_code)),
+            _ => Err(MqttError::InvalidPacket("expected CONNACK")),
+        }
+    }
+
+    /// Subscribes to the given topic filters, which can contain the wildcards `+` and `#`.
+    ///
+    /// Returns the messages that are received before the acknowledgment of the subscription.
+    pub async fn subscribe(&mut self, filters: &[String]) -> Result<Vec<Message>, MqttError> {
+        const PACKET_ID: u16 = 1;
+        self.send(&encode_subscribe(PACKET_ID, filters)).await?;
+        let mut early_messages = Vec::new();
+        loop {
+            match self.read_packet().await? {
+                Packet::SubAck {
+                    packet_id: PACKET_ID,
+                    return_codes,
+                } => {
+                    if let Some(i) = return_codes.iter().position(|code| *code == 0x80) {
+                        let filter = filters.get(i).cloned().unwrap_or_default();
+                        return Err(MqttError::SubscriptionRefused(filter));
+                    }
+                    return Ok(early_messages);
+                }
+                Packet::Publish { message, packet_id } => {
+                    self.acknowledge(packet_id).await?;
+                    early_messages.push(message);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Waits for the next message.
+    ///
+    /// This function is cancel-safe for the QoS 0 messages: if the future is dropped before its completion,
+    /// no message is lost.
+    ///
+    /// The other packets, such as the responses to [`ping`](Self::ping), are handled internally.
+    pub async fn next_message(&mut self) -> Result<Message, MqttError> {
+        loop {
+            if let Packet::Publish { message, packet_id } = self.read_packet().await? {
+                self.acknowledge(packet_id).await?;
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Sends a ping to the broker, to keep the connection alive.
+    pub async fn ping(&mut self) -> Result<(), MqttError> {
+        self.send(&[PINGREQ << 4, 0]).await
+    }
+
+    /// Closes the connection.
+    pub async fn disconnect(mut self) -> Result<(), MqttError> {
+        self.send(&[DISCONNECT << 4, 0]).await?;
+        self.stream.shutdown().await?;
+        Ok(())
+    }
+
+    async fn acknowledge(&mut self, packet_id: Option<u16>) -> Result<(), MqttError> {
+        // only for QoS 1: we never subscribe with QoS 2
+        if let Some(id) = packet_id {
+            let [id0, id1] = id.to_be_bytes();
+            self.send(&[PUBACK << 4, 2, id0, id1]).await?;
+        }
+        Ok(())
+    }
+
+    async fn send(&mut self, packet: &[u8]) -> Result<(), MqttError> {
+        self.stream.write_all(packet).await?;
+        Ok(())
+    }
+
+    async fn read_packet(&mut self) -> Result<Packet, MqttError> {
+        loop {
+            // the buffer is only modified when a complete packet has been received, or by read_buf,
+            // which is cancel-safe
+            if let Some((body_start, packet_len)) = frame(&self.buffer)? {
+                let packet = decode_packet(self.buffer[0], &self.buffer[body_start..packet_len]);
+                self.buffer.drain(..packet_len);
+                return packet;
+            }
+            if self.stream.read_buf(&mut self.buffer).await? == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+        }
+    }
+}
+
+/// Finds the first packet in the buffer.
+///
+/// Returns the start of its body and its total length, or `None` if the packet is incomplete.
+fn frame(buffer: &[u8]) -> Result<Option<(usize, usize)>, MqttError> {
+    let mut remaining_length = 0usize;
+    for i in 0..4 {
+        let Some(b) = buffer.get(1 + i) else {
+            return Ok(None);
+        };
+        remaining_length |= ((b & 0x7f) as usize) << (7 * i);
+        if b & 0x80 == 0 {
+            let body_start = 2 + i;
+            let packet_len = body_start + remaining_length;
+            return Ok((buffer.len() >= packet_len).then_some((body_start, packet_len)));
+        }
+    }
+    Err(MqttError::InvalidPacket("invalid remaining length"))
+}
+
+fn encode_remaining_length(mut len: usize, out: &mut Vec<u8>) {
+    debug_assert!(len <= MAX_REMAINING_LENGTH);
+    loop {
+        let mut b = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            b |= 0x80;
+        }
+        out.push(b);
+        if len == 0 {
+            break;
+        }
+    }
+}
+
+fn encode_string(s: &str, out: &mut Vec<u8>) {
+    out.extend((s.len() as u16).to_be_bytes());
+    out.extend(s.as_bytes());
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![header];
+    encode_remaining_length(body.len(), &mut out);
+    out.extend(body);
+    out
+}
+
+fn encode_connect(options: &ConnectOptions) -> Vec<u8> {
+    let mut flags = FLAG_CLEAN_SESSION;
+    if options.username.is_some() {
+        flags |= FLAG_USERNAME;
+    }
+    if options.password.is_some() {
+        flags |= FLAG_PASSWORD;
+    }
+    let keep_alive = options.keep_alive.as_secs().min(u16::MAX as u64) as u16;
+
+    let mut body = Vec::new();
+    encode_string("MQTT", &mut body);
+    body.push(PROTOCOL_LEVEL_3_1_1);
+    body.push(flags);
+    body.extend(keep_alive.to_be_bytes());
+    encode_string(&options.client_id, &mut body);
+    if let Some(username) = &options.username {
+        encode_string(username, &mut body);
+    }
+    if let Some(password) = &options.password {
+        encode_string(password.expose(), &mut body);
+    }
+    packet(CONNECT << 4, &body)
+}
+
+fn encode_subscribe(packet_id: u16, filters: &[String]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend(packet_id.to_be_bytes());
+    for filter in filters {
+        encode_string(filter, &mut body);
+        body.push(0); // QoS 0
+    }
+    // the flags of SUBSCRIBE are reserved and must be 0b0010
+    packet((SUBSCRIBE << 4) | 0x02, &body)
+}
+
+fn decode_u16(data: &[u8]) -> Result<(u16, &[u8]), MqttError> {
+    match data {
+        [a, b, rest @ ..] => Ok((u16::from_be_bytes([*a, *b]), rest)),
+        _ => Err(MqttError::InvalidPacket("truncated packet")),
+    }
+}
+
+fn decode_packet(header: u8, body: &[u8]) -> Result<Packet, MqttError> {
+    let packet = match header >> 4 {
+        CONNACK => match body {
+            [_session_present, return_code] => Packet::ConnAck {
+                return_code: *return_code,
+            },
+            _ => return Err(MqttError::InvalidPacket("invalid CONNACK")),
+        },
+        PUBLISH => {
+            let qos = (header >> 1) & 0x03;
+            let (topic_len, rest) = decode_u16(body)?;
+            let topic_len = topic_len as usize;
+            if rest.len() < topic_len {
+                return Err(MqttError::InvalidPacket("truncated topic"));
+            }
+            let (topic, rest) = rest.split_at(topic_len);
+            let topic = String::from_utf8(topic.to_vec()).map_err(|_| MqttError::InvalidPacket("invalid topic"))?;
+            let (packet_id, payload) = if qos > 0 {
+                let (id, rest) = decode_u16(rest)?;
+                (Some(id), rest)
+            } else {
+                (None, rest)
+            };
+            Packet::Publish {
+                message: Message {
+                    topic,
+                    payload: payload.to_vec(),
+                },
+                packet_id,
+            }
+        }
+        SUBACK => {
+            let (packet_id, return_codes) = decode_u16(body)?;
+            Packet::SubAck {
+                packet_id,
+                return_codes: return_codes.to_vec(),
+            }
+        }
+        PINGRESP => Packet::PingResp,
+        other => Packet::Other(other),
+    };
+    Ok(packet)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn connect() {
+        let options = ConnectOptions {
+            client_id: String::from("alumet"),
+            username: Some(String::from("user")),
+            password: Some(Secret::new("pw")),
+            keep_alive: Duration::from_secs(30),
+        };
+        #[rustfmt::skip]
+        let expected = vec![
+            0x10, 28,
+            0, 4, b'M', b'Q', b'T', b'T',
+            4,
+            0xc2,
+            0, 30,
+            0, 6, b'a', b'l', b'u', b'm', b'e', b't',
+            0, 4, b'u', b's', b'e', b'r',
+            0, 2, b'p', b'w',
+        ];
+        assert_eq!(encode_connect(&options), expected);
+    }
+
+    #[test]
+    fn subscribe() {
+        let filters = vec![String::from("a/+"), String::from("b/#")];
+        #[rustfmt::skip]
+        let expected = vec![
+            0x82, 14,
+            0, 1,
+            0, 3, b'a', b'/', b'+', 0,
+            0, 3, b'b', b'/', b'#', 0,
+        ];
+        assert_eq!(encode_subscribe(1, &filters), expected);
+    }
+
+    #[test]
+    fn remaining_length() {
+        for (len, expected) in [
+            (0, vec![0x00]),
+            (127, vec![0x7f]),
+            (128, vec![0x80, 0x01]),
+            (16_383, vec![0xff, 0x7f]),
+            (2_097_152, vec![0x80, 0x80, 0x80, 0x01]),
+        ] {
+            let mut out = Vec::new();
+            encode_remaining_length(len, &mut out);
+            assert_eq!(out, expected, "wrong encoding of {len}");
+        }
+    }
+
+    #[test]
+    fn framing() {
+        assert_eq!(frame(&[]).unwrap(), None);
+        assert_eq!(frame(&[0xd0]).unwrap(), None);
+        assert_eq!(frame(&[0xd0, 0x00]).unwrap(), Some((2, 2)));
+        assert_eq!(frame(&[0x30, 0x03, 0, 1]).unwrap(), None);
+        assert_eq!(frame(&[0x30, 0x03, 0, 1, b'a', 0xd0]).unwrap(), Some((2, 5)));
+        assert_eq!(frame(&[0x30, 0x80]).unwrap(), None);
+        assert_eq!(frame(&[0x30, 0x80, 0x01]).unwrap(), None);
+        assert!(frame(&[0x30, 0xff, 0xff, 0xff, 0xff]).is_err());
+    }
+
+    #[test]
+    fn publish() {
+        // QoS 0
+        let body = [0, 3, b'a', b'/', b'b', b'4', b'2'];
+        assert_eq!(
+            decode_packet(0x30, &body).unwrap(),
+            Packet::Publish {
+                message: Message {
+                    topic: String::from("a/b"),
+                    payload: b"42".to_vec(),
+                },
+                packet_id: None,
+            }
+        );
+        // QoS 1, retained
+        let body = [0, 1, b'a', 0, 7, b'{', b'}'];
+        assert_eq!(
+            decode_packet(0x33, &body).unwrap(),
+            Packet::Publish {
+                message: Message {
+                    topic: String::from("a"),
+                    payload: b"{}".to_vec(),
+                },
+                packet_id: Some(7),
+            }
+        );
+        assert!(decode_packet(0x30, &[0, 10, b'a']).is_err());
+    }
+
+    #[tokio::test]
+    async fn session_with_fake_broker() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let broker = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = Vec::new();
+            assert_eq!(read_header(&mut stream, &mut buffer).await, CONNECT << 4);
+            stream.write_all(&[CONNACK << 4, 2, 0, 0]).await.unwrap();
+            assert_eq!(read_header(&mut stream, &mut buffer).await, (SUBSCRIBE << 4) | 0x02);
+            // a retained message, sent before the acknowledgment of the subscription
+            stream
+                .write_all(&[PUBLISH << 4, 5, 0, 1, b'a', b'1', b'2'])
+                .await
+                .unwrap();
+            stream.write_all(&[SUBACK << 4, 3, 0, 1, 0]).await.unwrap();
+            // a QoS 1 message, split in two parts
+            stream.write_all(&[(PUBLISH << 4) | 0x02, 7, 0, 1, b'b']).await.unwrap();
+            stream.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            stream.write_all(&[0, 9, b'3', b'4']).await.unwrap();
+            assert_eq!(read_header(&mut stream, &mut buffer).await, PUBACK << 4);
+            assert_eq!(read_header(&mut stream, &mut buffer).await, PINGREQ << 4);
+            stream.write_all(&[PINGRESP << 4, 0]).await.unwrap();
+            stream
+                .write_all(&[PUBLISH << 4, 5, 0, 1, b'c', b'5', b'6'])
+                .await
+                .unwrap();
+            assert_eq!(read_header(&mut stream, &mut buffer).await, DISCONNECT << 4);
+        });
+
+        let options = ConnectOptions {
+            client_id: String::from("test"),
+            username: None,
+            password: None,
+            keep_alive: Duration::from_secs(30),
+        };
+        let mut client = MqttClient::connect(&address, &options).await.unwrap();
+        let early = client.subscribe(&[String::from("#")]).await.unwrap();
+        assert_eq!(early, vec![message("a", "12")]);
+        assert_eq!(client.next_message().await.unwrap(), message("b", "34"));
+        client.ping().await.unwrap();
+        assert_eq!(client.next_message().await.unwrap(), message("c", "56"));
+        client.disconnect().await.unwrap();
+        broker.await.unwrap();
+    }
+
+    fn message(topic: &str, payload: &str) -> Message {
+        Message {
+            topic: topic.to_owned(),
+            payload: payload.as_bytes().to_vec(),
+        }
+    }
+
+    /// Reads the next packet sent by the client and returns its first byte.
+    async fn read_header(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> u8 {
+        loop {
+            if let Some((_, packet_len)) = frame(buffer).unwrap() {
+                let header = buffer[0];
+                buffer.drain(..packet_len);
+                return header;
+            }
+            assert_ne!(stream.read_buf(buffer).await.unwrap(), 0, "unexpected EOF");
+        }
+    }
+}
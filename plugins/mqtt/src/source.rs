@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use alumet::measurement::MeasurementBuffer;
+use tokio::{sync::mpsc, time::MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    mapping::{Mapping, topic_matches},
+    mqtt::{ConnectOptions, Message, MqttClient},
+};
+
+/// Time to wait before reconnecting to the broker, after a failure.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Receives the messages of the broker and converts them to measurements, until `cancel_token` is cancelled.
+///
+/// The connection is re-established if it fails.
+pub async fn run(
+    broker: String,
+    options: ConnectOptions,
+    mappings: Vec<Mapping>,
+    cancel_token: CancellationToken,
+    tx: mpsc::Sender<MeasurementBuffer>,
+) -> anyhow::Result<()> {
+    let mut filters: Vec<String> = mappings.iter().map(|m| m.topic.clone()).collect();
+    filters.sort();
+    filters.dedup();
+    loop {
+        let res = tokio::select! {
+            biased;
+            _ = cancel_token.cancelled() => return Ok(()),
+            res = MqttClient::connect(&broker, &options) => res,
+        };
+        match res {
+            Ok(client) => {
+                log::info!("Connected to the MQTT broker {broker}.");
+                match receive(client, &options, &filters, &mappings, &cancel_token, &tx).await {
+                    Ok(()) => return Ok(()),
+                    Err(e) => log::warn!("Connection to the MQTT broker {broker} lost: {e:#}"),
+                }
+            }
+            Err(e) => log::warn!("Could not connect to the MQTT broker {broker}: {e:#}"),
+        }
+        tokio::select! {
+            biased;
+            _ = cancel_token.cancelled() => return Ok(()),
+            _ = tokio::time::sleep(RECONNECT_DELAY) => (),
+        }
+    }
+}
+
+/// Receives the messages until `cancel_token` is cancelled (returns `Ok`) or the connection fails (returns `Err`).
+async fn receive(
+    mut client: MqttClient,
+    options: &ConnectOptions,
+    filters: &[String],
+    mappings: &[Mapping],
+    cancel_token: &CancellationToken,
+    tx: &mpsc::Sender<MeasurementBuffer>,
+) -> anyhow::Result<()> {
+    for message in client.subscribe(filters).await? {
+        send(&message, mappings, tx).await?;
+    }
+
+    // ping the broker well before the end of the keep alive period
+    // (a keep alive of zero disables the mechanism)
+    let ping_period = (options.keep_alive / 2).max(Duration::from_secs(1));
+    let mut ping_interval = tokio::time::interval(ping_period);
+    ping_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ping_interval.tick().await;
+    loop {
+        tokio::select! {
+            biased;
+            _ = cancel_token.cancelled() => break,
+            message = client.next_message() => send(&message?, mappings, tx).await?,
+            _ = ping_interval.tick(), if !options.keep_alive.is_zero() => client.ping().await?,
+        }
+    }
+
+    if let Err(e) = client.disconnect().await {
+        log::warn!("Could not disconnect from the MQTT broker: {e:#}");
+    }
+    Ok(())
+}
+
+async fn send(message: &Message, mappings: &[Mapping], tx: &mpsc::Sender<MeasurementBuffer>) -> anyhow::Result<()> {
+    let buffer = convert(message, mappings);
+    if !buffer.is_empty() {
+        tx.send(buffer).await?;
+    }
+    Ok(())
+}
+
+/// Converts a message with every mapping that matches its topic.
+///
+/// Invalid messages are logged and ignored.
+fn convert(message: &Message, mappings: &[Mapping]) -> MeasurementBuffer {
+    let mut buffer = MeasurementBuffer::new();
+    let mut matching = mappings
+        .iter()
+        .filter(|m| topic_matches(&m.topic, &message.topic))
+        .peekable();
+    if matching.peek().is_none() {
+        return buffer;
+    }
+    let payload: serde_json::Value = match serde_json::from_slice(&message.payload) {
+        Ok(payload) => payload,
+        Err(e) => {
+            log::warn!("Ignoring the message on topic {}: invalid JSON: {e}", message.topic);
+            return buffer;
+        }
+    };
+    for mapping in matching {
+        match mapping.convert(&message.topic, &payload) {
+            Ok(point) => buffer.push(point),
+            Err(e) => log::warn!("Ignoring the message on topic {}: {e:#}", message.topic),
+        }
+    }
+    buffer
+}
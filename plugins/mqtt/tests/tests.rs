@@ -0,0 +1,81 @@
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    plugin::PluginMetadata,
+    test::StartupExpectations,
+    units::{PrefixedUnit, Unit},
+};
+use plugin_mqtt::{Config, MappingConfig, MqttPlugin, TimestampFormat};
+use std::{collections::BTreeMap, time::Duration};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_with_unreachable_broker() {
+    // nothing listens on the discard port: the connection fails, but the agent keeps running
+    let config = Config {
+        broker: String::from("127.0.0.1:9"),
+        client_id: Some(String::from("test")),
+        mappings: vec![
+            mapping("tele/+/SENSOR", "plug_power", "W", "/ENERGY/Power"),
+            mapping("tele/+/SENSOR", "plug_energy", "kW.h", "/ENERGY/Total"),
+            mapping("meters/+/power", "plug_power", "W", ""),
+        ],
+        ..Default::default()
+    };
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("plug_power", Unit::Watt)
+        .expect_metric::<f64>("plug_energy", PrefixedUnit::kilo(Unit::WattHour))
+        .expect_source("mqtt", "subscriber");
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup_expectation)
+        .build_and_start()
+        .unwrap();
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+#[test]
+fn metric_with_two_units() {
+    let config = Config {
+        mappings: vec![
+            mapping("a", "plug_power", "W", ""),
+            mapping("b", "plug_power", "mW", ""),
+        ],
+        ..Default::default()
+    };
+    let res = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(res.is_err());
+}
+
+#[test]
+fn invalid_pointer() {
+    let config = Config {
+        mappings: vec![mapping("a", "plug_power", "W", "ENERGY.Power")],
+        ..Default::default()
+    };
+    let res = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(res.is_err());
+}
+
+fn mapping(topic: &str, metric: &str, unit: &str, value: &str) -> MappingConfig {
+    MappingConfig {
+        topic: topic.to_owned(),
+        metric: metric.to_owned(),
+        unit: unit.to_owned(),
+        value: value.to_owned(),
+        timestamp: None,
+        timestamp_format: TimestampFormat::default(),
+        attributes: BTreeMap::new(),
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<MqttPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
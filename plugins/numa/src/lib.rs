@@ -0,0 +1,105 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::source::{Metrics, NumaSource};
+
+mod node;
+mod source;
+
+/// Measures the memory usage and the allocations of each NUMA node.
+pub struct NumaPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for NumaPlugin {
+    fn name() -> &'static str {
+        "numa"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(NumaPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let nodes = node::explore(&self.config.sysfs_path).context("could not find the NUMA nodes")?;
+        if nodes.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: no NUMA node found in {:?}",
+                self.config.sysfs_path
+            ));
+        }
+        for node in &nodes {
+            match node.package {
+                Some(pkg) => log::info!("Found NUMA node {} (package {pkg})", node.id),
+                None => log::info!("Found NUMA node {} (without CPU)", node.id),
+            }
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = NumaSource::new(nodes, self.config.meminfo.clone(), self.config.vmstat.clone(), metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("nodes", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the system devices, which contains the `node` and `cpu` directories.
+    pub sysfs_path: PathBuf,
+
+    /// The entries to read from the `meminfo` file of each node.
+    pub meminfo: Vec<String>,
+
+    /// The entries to read from the `vmstat` file of each node.
+    pub vmstat: Vec<String>,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            sysfs_path: PathBuf::from("/sys/devices/system"),
+            meminfo: ["MemTotal", "MemFree", "MemUsed", "FilePages", "AnonPages"]
+                .map(String::from)
+                .to_vec(),
+            vmstat: [
+                "nr_dirty",
+                "nr_writeback",
+                "workingset_refault_anon",
+                "workingset_refault_file",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Discovery of the NUMA nodes and parsing of their statistics.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use alumet::resources::Resource;
+use anyhow::Context;
+
+/// A NUMA node, such as `/sys/devices/system/node/node0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumaNode {
+    pub id: u32,
+    pub path: PathBuf,
+    /// The CPU package that contains the CPUs of the node, if the node has CPUs.
+    pub package: Option<u32>,
+}
+
+impl NumaNode {
+    /// The resource that represents the memory of the node.
+    ///
+    /// The memory of a node with CPUs is the DRAM of their package, which allows to compare it with the
+    /// `dram` domain of RAPL. The nodes without CPU (for instance CXL memory expanders) get a custom resource.
+    pub fn resource(&self) -> Resource {
+        match self.package {
+            Some(pkg_id) => Resource::Dram { pkg_id },
+            None => Resource::custom("numa_node", self.id.to_string()),
+        }
+    }
+}
+
+/// Explores the NUMA nodes.
+///
+/// ## Expected file layout
+///
+/// ```txt
+/// /sys/devices/system/
+/// |− node
+///     |− node0
+///         |− cpulist
+///         |− meminfo
+///         |− numastat
+///         |− vmstat
+///     |− …
+/// |− cpu
+///     |− cpu0
+///         |− topology
+///             |− physical_package_id
+/// ```
+pub fn explore(sysfs_path: &Path) -> anyhow::Result<Vec<NumaNode>> {
+    let node_path = sysfs_path.join("node");
+    let mut nodes = Vec::new();
+    for entry in std::fs::read_dir(&node_path).with_context(|| format!("failed to read dir {node_path:?}"))? {
+        let entry = entry?;
+        let id = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix("node")?.parse::<u32>().ok());
+        let Some(id) = id else {
+            continue;
+        };
+        let path = entry.path();
+        // cpulist looks like "0-7,16-23", or is empty
+        let first_cpu = std::fs::read_to_string(path.join("cpulist"))
+            .ok()
+            .and_then(|list| list.trim().split([',', '-']).next()?.parse::<u32>().ok());
+        let package = first_cpu.and_then(|cpu| {
+            let path = sysfs_path.join(format!("cpu/cpu{cpu}/topology/physical_package_id"));
+            std::fs::read_to_string(path).ok()?.trim().parse::<u32>().ok()
+        });
+        nodes.push(NumaNode { id, path, package });
+    }
+    nodes.sort_by_key(|n| n.id);
+    Ok(nodes)
+}
+
+/// Parses the `meminfo` file of a node, and returns the values in bytes.
+///
+/// The lines look like `Node 0 MemTotal:       32768000 kB`.
+pub fn parse_meminfo(content: &str) -> HashMap<&str, u64> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_ascii_whitespace().skip(2);
+            let key = fields.next()?.strip_suffix(':')?;
+            let value: u64 = fields.next()?.parse().ok()?;
+            let value = match fields.next() {
+                Some("kB") => value * 1024,
+                _ => value,
+            };
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// Parses a file of counters such as `vmstat` or `numastat`, whose lines look like `numa_hit 123456`.
+pub fn parse_counters(content: &str) -> HashMap<&str, u64> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(' ')?;
+            Some((key, value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn meminfo() {
+        let content = "\
+Node 1 MemTotal:       65842704 kB
+Node 1 MemFree:        60342116 kB
+Node 1 Active(anon):     118204 kB
+Node 1 HugePages_Total:     0
+";
+        let res = parse_meminfo(content);
+        assert_eq!(
+            res,
+            HashMap::from([
+                ("MemTotal", 65842704 * 1024),
+                ("MemFree", 60342116 * 1024),
+                ("Active(anon)", 118204 * 1024),
+                ("HugePages_Total", 0),
+            ])
+        );
+    }
+
+    #[test]
+    fn counters() {
+        let content = "numa_hit 2406355\nnuma_miss 0\nnuma_foreign 0\ninterleave_hit 2139\nlocal_node 2389425\nother_node 16930\n";
+        let res = parse_counters(content);
+        assert_eq!(res.len(), 6);
+        assert_eq!(res["numa_hit"], 2406355);
+        assert_eq!(res["other_node"], 16930);
+    }
+
+    #[test]
+    fn explore_nodes() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let root = root.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("node/node1/cpulist", "4-7\n");
+        write("node/node0/cpulist", "0-3\n");
+        write("node/node2/cpulist", "\n");
+        write("node/possible", "0-2\n");
+        write("cpu/cpu0/topology/physical_package_id", "0\n");
+        write("cpu/cpu4/topology/physical_package_id", "0\n");
+
+        let nodes = explore(root)?;
+        let res: Vec<_> = nodes.iter().map(|n| (n.id, n.resource())).collect();
+        assert_eq!(
+            res,
+            vec![
+                (0, Resource::Dram { pkg_id: 0 }),
+                (1, Resource::Dram { pkg_id: 0 }),
+                (2, Resource::custom("numa_node", "2")),
+            ]
+        );
+        assert!(explore(&root.join("missing")).is_err());
+        Ok(())
+    }
+}
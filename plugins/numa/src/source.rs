@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::ResourceConsumer,
+    units::Unit,
+};
+use anyhow::Context;
+
+use crate::node::{NumaNode, parse_counters, parse_meminfo};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    memory: TypedMetricId<u64>,
+    vmstat: TypedMetricId<u64>,
+    allocations: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            memory: alumet.create_metric("numa_memory", Unit::Byte, "Memory of the NUMA node, by kind")?,
+            vmstat: alumet.create_metric(
+                "numa_vmstat",
+                Unit::Unity,
+                "Virtual memory statistic of the NUMA node (raw value of the node's vmstat)",
+            )?,
+            allocations: alumet.create_metric(
+                "numa_allocations",
+                Unit::Unity,
+                "Number of pages allocated on the NUMA node since the previous measurement, by outcome",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the statistics of the NUMA nodes.
+pub struct NumaSource {
+    nodes: Vec<MeasuredNode>,
+    /// Entries of `meminfo` to measure.
+    meminfo: Vec<String>,
+    /// Entries of `vmstat` to measure.
+    vmstat: Vec<String>,
+    metrics: Metrics,
+}
+
+struct MeasuredNode {
+    node: NumaNode,
+    /// The previous allocation counters, to compute the difference.
+    previous_numastat: Option<HashMap<String, u64>>,
+}
+
+impl NumaSource {
+    pub fn new(nodes: Vec<NumaNode>, meminfo: Vec<String>, vmstat: Vec<String>, metrics: Metrics) -> Self {
+        let nodes = nodes
+            .into_iter()
+            .map(|node| MeasuredNode {
+                node,
+                previous_numastat: None,
+            })
+            .collect();
+        Self {
+            nodes,
+            meminfo,
+            vmstat,
+            metrics,
+        }
+    }
+}
+
+fn read(node: &NumaNode, file: &str) -> anyhow::Result<String> {
+    let path = node.path.join(file);
+    std::fs::read_to_string(&path).with_context(|| format!("failed to read {path:?}"))
+}
+
+impl Source for NumaSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for n in &mut self.nodes {
+            let resource = n.node.resource();
+            let point = |metric, value: u64, key: &'static str, kind: &str| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    resource.clone(),
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("numa_node", n.node.id as u64)
+                .with_attr(key, kind.to_owned())
+            };
+
+            if !self.meminfo.is_empty() {
+                let content = read(&n.node, "meminfo")?;
+                let meminfo = parse_meminfo(&content);
+                for entry in &self.meminfo {
+                    if let Some(value) = meminfo.get(entry.as_str()) {
+                        measurements.push(point(self.metrics.memory, *value, "kind", entry));
+                    }
+                }
+            }
+
+            if !self.vmstat.is_empty() {
+                let content = read(&n.node, "vmstat")?;
+                let vmstat = parse_counters(&content);
+                for entry in &self.vmstat {
+                    if let Some(value) = vmstat.get(entry.as_str()) {
+                        measurements.push(point(self.metrics.vmstat, *value, "counter", entry));
+                    }
+                }
+            }
+
+            let content = read(&n.node, "numastat")?;
+            let numastat: HashMap<String, u64> = parse_counters(&content)
+                .into_iter()
+                .map(|(k, v)| (k.to_owned(), v))
+                .collect();
+            // Only push deltas, not the baseline value before the plugin starts
+            if let Some(prev) = &n.previous_numastat {
+                let mut deltas: Vec<_> = numastat
+                    .iter()
+                    .filter_map(|(k, v)| Some((k, v.saturating_sub(*prev.get(k)?))))
+                    .collect();
+                deltas.sort();
+                for (kind, delta) in deltas {
+                    measurements.push(point(self.metrics.allocations, delta, "kind", kind));
+                }
+            }
+            n.previous_numastat = Some(numastat);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_numa::{Config, NumaPlugin};
+use pretty_assertions::assert_eq;
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_node() {
+    let root = tempdir().unwrap();
+    std::fs::create_dir(root.path().join("node")).unwrap();
+    let config = Config {
+        sysfs_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no NUMA node)");
+}
+
+#[test]
+fn plugin_with_nodes() {
+    let root = tempdir().unwrap();
+    write(root.path(), "cpu/cpu0/topology/physical_package_id", "0");
+    write(root.path(), "node/node0/cpulist", "0-3");
+    write(
+        root.path(),
+        "node/node0/meminfo",
+        "Node 0 MemTotal:       1000 kB\nNode 0 MemFree:         400 kB\nNode 0 MemUsed:         600 kB\n",
+    );
+    write(root.path(), "node/node0/vmstat", "nr_free_pages 100\nnr_dirty 7\n");
+    write(root.path(), "node/node0/numastat", "numa_hit 50\nnuma_miss 2\n");
+    write(root.path(), "node/node1/cpulist", "");
+    write(root.path(), "node/node1/meminfo", "Node 1 MemTotal:       2000 kB\n");
+    write(root.path(), "node/node1/vmstat", "nr_dirty 0\n");
+    write(root.path(), "node/node1/numastat", "numa_hit 10\n");
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        sysfs_path: root.path().to_path_buf(),
+        meminfo: vec![String::from("MemTotal"), String::from("MemUsed")],
+        vmstat: vec![String::from("nr_dirty")],
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<u64>("numa_memory", Unit::Byte)
+        .expect_metric::<u64>("numa_vmstat", Unit::Unity)
+        .expect_metric::<u64>("numa_allocations", Unit::Unity)
+        .expect_source("numa", "nodes");
+
+    let runtime = RuntimeExpectations::new().test_source(
+        SourceName::from_str("numa", "nodes"),
+        || {},
+        |ctx| {
+            let m = ctx.measurements();
+            let metrics = ctx.metrics();
+            let points: Vec<_> = m
+                .iter()
+                .map(|p| {
+                    let name = metrics.by_id(&p.metric).unwrap().name.clone();
+                    let kind = p
+                        .attributes()
+                        .find(|(k, _)| *k == "kind" || *k == "counter")
+                        .unwrap()
+                        .1
+                        .to_string();
+                    (name, p.resource.clone(), kind, p.value.as_u64())
+                })
+                .collect();
+            let dram = Resource::Dram { pkg_id: 0 };
+            let node1 = Resource::custom("numa_node", "1");
+            // no allocation at the first measurement, only the memory
+            assert_eq!(
+                points,
+                vec![
+                    (
+                        String::from("numa_memory"),
+                        dram.clone(),
+                        String::from("MemTotal"),
+                        1024000
+                    ),
+                    (
+                        String::from("numa_memory"),
+                        dram.clone(),
+                        String::from("MemUsed"),
+                        614400
+                    ),
+                    (String::from("numa_vmstat"), dram, String::from("nr_dirty"), 7),
+                    (
+                        String::from("numa_memory"),
+                        node1.clone(),
+                        String::from("MemTotal"),
+                        2048000
+                    ),
+                    (String::from("numa_vmstat"), node1, String::from("nr_dirty"), 0),
+                ]
+            );
+        },
+    );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<NumaPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
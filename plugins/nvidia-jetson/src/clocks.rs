@@ -0,0 +1,287 @@
+//! Clock frequencies and power mode (NVPModel) of the device.
+
+use std::path::{Path, PathBuf};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::elements::error::PollError,
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::{Context, anyhow};
+use rustc_hash::FxHashMap;
+
+pub const ROOTFS: &str = "/";
+
+/// Directory that contains the CPUs, relative to the sysfs root.
+const SYSFS_CPUS: &str = "devices/system/cpu";
+/// Directory that contains the devfreq devices (including the GPU), relative to the sysfs root.
+const SYSFS_DEVFREQ: &str = "class/devfreq";
+/// Suffixes of the name of the GPU devfreq device, depending on the model of the Jetson.
+const GPU_DEVFREQ_SUFFIXES: [&str; 4] = [".ga10b", ".gv11b", ".gp10b", ".gpu"];
+/// Files that provide the frequency of the memory controller, in Hz, relative to the sysfs root.
+/// The first one that exists is used.
+const EMC_RATE_FILES: [&str; 2] = [
+    "kernel/debug/bpmp/debug/clk/emc/rate",
+    crate::utilization::EMC_RATE_FILE,
+];
+/// File that contains the active power mode, relative to the root of the filesystem.
+const NVPMODEL_STATUS: &str = "var/lib/nvpmodel/status";
+/// Definition of the power modes, relative to the root of the filesystem.
+const NVPMODEL_CONF: &str = "etc/nvpmodel.conf";
+
+/// Detected sources of frequency and power mode data.
+#[derive(Debug, PartialEq)]
+pub struct ClockFiles {
+    /// Current frequency of each CPU core, in kHz.
+    pub cpus: Vec<(u32, PathBuf)>,
+    /// Current frequency of the GPU, in Hz.
+    pub gpu: Option<PathBuf>,
+    /// Current frequency of the memory controller, in Hz.
+    pub emc: Option<PathBuf>,
+    /// Active power mode.
+    pub nvpmodel_status: Option<PathBuf>,
+    /// Names of the power modes, by id.
+    pub nvpmodel_names: FxHashMap<u64, String>,
+}
+
+/// Finds the files that provide the clock frequencies and the power mode.
+///
+/// Returns an error if none of them exists.
+pub fn detect_clock_files(sysfs_root: &Path, rootfs: &Path) -> anyhow::Result<ClockFiles> {
+    let mut cpus = Vec::new();
+    if let Ok(ls) = std::fs::read_dir(sysfs_root.join(SYSFS_CPUS)) {
+        for entry in ls.filter_map(|e| e.ok()) {
+            let id = entry
+                .file_name()
+                .to_str()
+                .and_then(|n| n.strip_prefix("cpu")?.parse().ok());
+            let freq = entry.path().join("cpufreq/scaling_cur_freq");
+            if let Some(id) = id
+                && freq.exists()
+            {
+                cpus.push((id, freq));
+            }
+        }
+    }
+    cpus.sort_by_key(|(id, _)| *id);
+
+    let gpu = std::fs::read_dir(sysfs_root.join(SYSFS_DEVFREQ)).ok().and_then(|ls| {
+        ls.filter_map(|e| e.ok())
+            .find(|e| {
+                let name = e.file_name();
+                let name = name.to_string_lossy();
+                GPU_DEVFREQ_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+            })
+            .map(|e| e.path().join("cur_freq"))
+            .filter(|path| path.exists())
+    });
+
+    let emc = EMC_RATE_FILES
+        .iter()
+        .map(|f| sysfs_root.join(f))
+        .find(|path| path.exists());
+
+    let nvpmodel_status = Some(rootfs.join(NVPMODEL_STATUS)).filter(|path| path.exists());
+    let nvpmodel_names = match std::fs::read_to_string(rootfs.join(NVPMODEL_CONF)) {
+        Ok(conf) => parse_nvpmodel_conf(&conf),
+        Err(_) => FxHashMap::default(),
+    };
+
+    if cpus.is_empty() && gpu.is_none() && emc.is_none() && nvpmodel_status.is_none() {
+        return Err(anyhow!("no clock frequency nor power mode is available"));
+    }
+    Ok(ClockFiles {
+        cpus,
+        gpu,
+        emc,
+        nvpmodel_status,
+        nvpmodel_names,
+    })
+}
+
+/// Parses the definition of the power modes, for instance `< POWER_MODEL ID=0 NAME=MAXN >`.
+fn parse_nvpmodel_conf(content: &str) -> FxHashMap<u64, String> {
+    let mut names = FxHashMap::default();
+    for line in content.lines() {
+        let Some(model) = line.trim().strip_prefix("< POWER_MODEL") else {
+            continue;
+        };
+        let mut id = None;
+        let mut name = None;
+        for field in model.split_whitespace() {
+            if let Some(value) = field.strip_prefix("ID=") {
+                id = value.parse().ok();
+            } else if let Some(value) = field.strip_prefix("NAME=") {
+                name = Some(value.to_owned());
+            }
+        }
+        if let (Some(id), Some(name)) = (id, name) {
+            names.insert(id, name);
+        }
+    }
+    names
+}
+
+/// Parses the status of nvpmodel, for instance `pmode:0002 fmode:quiet`, and returns the id of the power mode.
+fn parse_nvpmodel_status(content: &str) -> anyhow::Result<u64> {
+    content
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix("pmode:"))
+        .with_context(|| format!("no power mode in nvpmodel status '{content}'"))?
+        .parse()
+        .with_context(|| format!("invalid power mode in nvpmodel status '{content}'"))
+}
+
+fn read_u64(path: &Path) -> anyhow::Result<u64> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+    content
+        .trim_ascii_end()
+        .parse()
+        .with_context(|| format!("failed to parse {}: '{content}'", path.display()))
+}
+
+/// Measurement source that reads the clock frequencies and the power mode of a Jetson device.
+pub struct JetsonClockSource {
+    frequency_metric: TypedMetricId<u64>,
+    nvpmodel_metric: TypedMetricId<u64>,
+    files: ClockFiles,
+}
+
+impl JetsonClockSource {
+    pub fn new(files: ClockFiles, alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        let frequency_metric = alumet.create_metric(
+            "clock_frequency",
+            Unit::Hertz,
+            "current frequency of a clock (see attributes for clock info)",
+        )?;
+        let nvpmodel_metric = alumet.create_metric("nvpmodel_mode", Unit::Unity, "id of the active power mode")?;
+        Ok(Self {
+            frequency_metric,
+            nvpmodel_metric,
+            files,
+        })
+    }
+}
+
+impl alumet::pipeline::Source for JetsonClockSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let consumer = ResourceConsumer::LocalMachine;
+
+        for (id, path) in &self.files.cpus {
+            // the cores can be disabled by nvpmodel, in which case their frequency cannot be read
+            let Ok(khz) = read_u64(path) else {
+                continue;
+            };
+            measurements.push(
+                MeasurementPoint::new(
+                    timestamp,
+                    self.frequency_metric,
+                    Resource::CpuCore { id: *id },
+                    consumer.clone(),
+                    khz * 1000,
+                )
+                .with_attr("clock", "cpu"),
+            );
+        }
+
+        for (clock, path) in [("gpu", &self.files.gpu), ("emc", &self.files.emc)] {
+            if let Some(path) = path {
+                let hz = read_u64(path)?;
+                measurements.push(
+                    MeasurementPoint::new(
+                        timestamp,
+                        self.frequency_metric,
+                        Resource::LocalMachine,
+                        consumer.clone(),
+                        hz,
+                    )
+                    .with_attr("clock", clock),
+                );
+            }
+        }
+
+        if let Some(path) = &self.files.nvpmodel_status {
+            let content =
+                std::fs::read_to_string(path).with_context(|| format!("could not read {}", path.display()))?;
+            let mode = parse_nvpmodel_status(&content)?;
+            let mut point =
+                MeasurementPoint::new(timestamp, self.nvpmodel_metric, Resource::LocalMachine, consumer, mode);
+            if let Some(name) = self.files.nvpmodel_names.get(&mode) {
+                point = point.with_attr("nvpmodel_name", name.clone());
+            }
+            measurements.push(point);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const NVPMODEL_CONF: &str = "
+< PARAM TYPE=FILE NAME=CPU_ONLINE >
+CORE_0 /sys/devices/system/cpu/cpu0/online
+
+< POWER_MODEL ID=0 NAME=MAXN >
+CPU_ONLINE CORE_0 1
+
+< POWER_MODEL ID=1 NAME=15W >
+CPU_ONLINE CORE_0 1
+
+< PM_CONFIG DEFAULT=2 >
+";
+
+    #[test]
+    fn nvpmodel_conf() {
+        let names = parse_nvpmodel_conf(NVPMODEL_CONF);
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[&0], "MAXN");
+        assert_eq!(names[&1], "15W");
+    }
+
+    #[test]
+    fn nvpmodel_status() {
+        assert_eq!(parse_nvpmodel_status("pmode:0002 fmode:quiet\n").unwrap(), 2);
+        assert_eq!(parse_nvpmodel_status("pmode:0000\n").unwrap(), 0);
+        parse_nvpmodel_status("").expect_err("should fail");
+    }
+
+    #[test]
+    fn detect_files() {
+        let tmp = tempdir().unwrap();
+        let sysfs = tmp.path().join("sys");
+        let rootfs = tmp.path().join("root");
+        assert!(detect_clock_files(&sysfs, &rootfs).is_err());
+
+        for cpu in ["cpu0", "cpu1", "cpu10"] {
+            let dir = sysfs.join(SYSFS_CPUS).join(cpu).join("cpufreq");
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("scaling_cur_freq"), "1190400\n").unwrap();
+        }
+        // not a CPU
+        std::fs::create_dir_all(sysfs.join(SYSFS_CPUS).join("cpufreq")).unwrap();
+        let gpu = sysfs.join(SYSFS_DEVFREQ).join("17000000.ga10b");
+        std::fs::create_dir_all(&gpu).unwrap();
+        std::fs::write(gpu.join("cur_freq"), "306000000\n").unwrap();
+        std::fs::create_dir_all(sysfs.join(SYSFS_DEVFREQ).join("15340000.vic")).unwrap();
+        std::fs::create_dir_all(rootfs.join("var/lib/nvpmodel")).unwrap();
+        std::fs::write(rootfs.join(NVPMODEL_STATUS), "pmode:0001\n").unwrap();
+        std::fs::create_dir_all(rootfs.join("etc")).unwrap();
+        std::fs::write(rootfs.join(super::NVPMODEL_CONF), NVPMODEL_CONF).unwrap();
+
+        let files = detect_clock_files(&sysfs, &rootfs).unwrap();
+        let cpu_freq = |id: u32| (id, sysfs.join(format!("{SYSFS_CPUS}/cpu{id}/cpufreq/scaling_cur_freq")));
+        assert_eq!(files.cpus, vec![cpu_freq(0), cpu_freq(1), cpu_freq(10)]);
+        assert_eq!(files.gpu, Some(gpu.join("cur_freq")));
+        assert_eq!(files.emc, None);
+        assert_eq!(files.nvpmodel_status, Some(rootfs.join(NVPMODEL_STATUS)));
+        assert_eq!(files.nvpmodel_names.len(), 2);
+    }
+}
@@ -10,9 +10,10 @@ use modern::ModernInaExplorer;
 use old::OldInaExplorer;
 use serde::{Deserialize, Serialize};
 
-mod common;
+pub mod common;
 pub mod modern;
 pub mod old;
+pub mod selection;
 
 /// Detected INA sensor.
 #[derive(Debug, PartialEq)]
@@ -116,20 +117,69 @@ impl Default for InaSysfsPath<'_> {
     }
 }
 
+/// Detects the INA sensors and selects the channels to measure.
+///
+/// Unlike [`InaSysfsPath`], this struct owns its data, which allows to repeat the detection at runtime.
+#[derive(Debug, Clone)]
+pub struct InaDetector {
+    pub sysfs_ina_modern: String,
+    pub sysfs_ina_old: String,
+    pub selection: selection::ChannelSelection,
+}
+
+impl InaDetector {
+    pub fn new(paths: InaSysfsPath, selection: selection::ChannelSelection) -> Self {
+        Self {
+            sysfs_ina_modern: paths.sysfs_ina_modern.to_owned(),
+            sysfs_ina_old: paths.sysfs_ina_old.to_owned(),
+            selection,
+        }
+    }
+
+    /// Returns the sorted list of the INA sensors, with the selected channels only.
+    ///
+    /// See [`detect_ina_sensors`].
+    pub fn detect(&self) -> anyhow::Result<(Vec<InaSensor>, Vec<anyhow::Error>)> {
+        let paths = InaSysfsPath {
+            sysfs_ina_modern: &self.sysfs_ina_modern,
+            sysfs_ina_old: &self.sysfs_ina_old,
+        };
+        let (mut sensors, errs) = detect_ina_sensors(paths)?;
+        sort_sensors_recursively(&mut sensors);
+        self.selection.apply(&mut sensors);
+        Ok((sensors, errs))
+    }
+}
+
 /// Returns a list of all the INA sensors available on the machine.
 ///
-/// This function supports multiple version of the NVIDIA Jetpack SDK.
+/// This function supports multiple version of the NVIDIA Jetpack SDK:
+/// the legacy layout (`ina3221x` driver, JetPack 4.x) and the modern layout (`ina3221` driver, JetPack 5+),
+/// including the naming scheme of the upstream driver used on Orin devices.
+/// If both hierarchies exist, the sensors of both are returned.
 pub fn detect_ina_sensors(paths: InaSysfsPath) -> anyhow::Result<(Vec<InaSensor>, Vec<anyhow::Error>)> {
-    if let Ok(true) = std::fs::exists(paths.sysfs_ina_modern) {
-        explore_ina_devices(ModernInaExplorer::new(paths.sysfs_ina_modern))
-    } else if let Ok(true) = std::fs::exists(paths.sysfs_ina_old) {
-        explore_ina_devices(OldInaExplorer::new(paths.sysfs_ina_old))
-    } else {
-        Err(anyhow::Error::msg(format!(
+    let modern_exists = matches!(std::fs::exists(paths.sysfs_ina_modern), Ok(true));
+    let old_exists = matches!(std::fs::exists(paths.sysfs_ina_old), Ok(true));
+    if !modern_exists && !old_exists {
+        return Err(anyhow::Error::msg(format!(
             "no INA-3221 sensor detected: neither {} nor {} exist",
             paths.sysfs_ina_modern, paths.sysfs_ina_old
-        )))
+        )));
+    }
+
+    let mut sensors = Vec::new();
+    let mut errors = Vec::new();
+    if modern_exists {
+        let (s, e) = explore_ina_devices(ModernInaExplorer::new(paths.sysfs_ina_modern))?;
+        sensors.extend(s);
+        errors.extend(e);
+    }
+    if old_exists {
+        let (s, e) = explore_ina_devices(OldInaExplorer::new(paths.sysfs_ina_old))?;
+        sensors.extend(s);
+        errors.extend(e);
     }
+    Ok((sensors, errors))
 }
 
 pub fn explore_ina_devices(explorer: impl InaExplorer) -> anyhow::Result<(Vec<InaSensor>, Vec<anyhow::Error>)> {
@@ -202,7 +252,7 @@ fn detect_device_channels(
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{collections::HashMap, path::Path};
 
     use alumet::units::{PrefixedUnit, Unit};
     use pretty_assertions::assert_eq;
@@ -215,7 +265,36 @@ mod tests {
         old::OldInaExplorer,
     };
 
-    use super::{InaChannel, InaRailMetric, InaSensor, explore_ina_devices, sort_sensors_recursively};
+    use super::{
+        InaChannel, InaRailMetric, InaSensor, InaSysfsPath, detect_ina_sensors, explore_ina_devices,
+        sort_sensors_recursively,
+    };
+
+    /// Creates a sysfs tree from a recording of the `testdata` directory.
+    ///
+    /// Each line of the recording is `<path>: <content>`, empty lines and lines starting with `#` are ignored.
+    fn create_recorded_tree(root: &Path, recording: &str) {
+        for line in recording.lines().filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let (path, content) = line.split_once(": ").expect("invalid line in the recording");
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, format!("{content}\n")).unwrap();
+        }
+    }
+
+    /// Returns the `(i2c address, channel id, label, metric names)` of the detected channels, sorted.
+    fn summarize(mut sensors: Vec<InaSensor>) -> Vec<(u32, u32, Option<String>, Vec<String>)> {
+        sort_sensors_recursively(&mut sensors);
+        sensors
+            .into_iter()
+            .flat_map(|s| {
+                let addr = s.metadata.i2c_address;
+                s.channels
+                    .into_iter()
+                    .map(move |c| (addr, c.id, c.label, c.metrics.into_iter().map(|m| m.name).collect()))
+            })
+            .collect()
+    }
 
     #[test]
     fn ina_modern() {
@@ -571,6 +650,91 @@ mod tests {
         }
     }
 
+    #[test]
+    fn recorded_xavier_nx_jetpack4() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path().join("ina3221x");
+        create_recorded_tree(&root, include_str!("../testdata/xavier-nx-jetpack4.txt"));
+
+        let missing_root = tmp.path().join("ina3221");
+        let paths = InaSysfsPath {
+            sysfs_ina_modern: missing_root.to_str().unwrap(),
+            sysfs_ina_old: root.to_str().unwrap(),
+        };
+        let (sensors, errs) = detect_ina_sensors(paths).expect("detection failed");
+        assert!(errs.is_empty(), "detection failed: {errs:?}");
+
+        let all_metrics = vec![
+            METRIC_CURRENT.to_owned(),
+            METRIC_POWER.to_owned(),
+            METRIC_VOLTAGE.to_owned(),
+        ];
+        assert_eq!(
+            summarize(sensors),
+            vec![
+                (0x40, 0, Some("VDD_IN".to_owned()), all_metrics.clone()),
+                (0x40, 1, Some("VDD_CPU_GPU_CV".to_owned()), all_metrics.clone()),
+                (0x40, 2, Some("VDD_SOC".to_owned()), all_metrics),
+            ]
+        );
+    }
+
+    #[test]
+    fn recorded_orin_agx_jetpack6() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path().join("ina3221");
+        create_recorded_tree(&root, include_str!("../testdata/orin-agx-jetpack6.txt"));
+
+        let missing_root = tmp.path().join("ina3221x");
+        let paths = InaSysfsPath {
+            sysfs_ina_modern: root.to_str().unwrap(),
+            sysfs_ina_old: missing_root.to_str().unwrap(),
+        };
+        let (sensors, errs) = detect_ina_sensors(paths).expect("detection failed");
+        assert!(errs.is_empty(), "detection failed: {errs:?}");
+
+        // the shunt voltages (in4_input to in7_input) must not appear as channels or metrics
+        let current_voltage = vec![METRIC_CURRENT.to_owned(), METRIC_VOLTAGE.to_owned()];
+        assert_eq!(
+            summarize(sensors),
+            vec![
+                (0x40, 1, Some("VDD_GPU_SOC".to_owned()), current_voltage.clone()),
+                (0x40, 2, Some("VDD_CPU_CV".to_owned()), current_voltage.clone()),
+                (0x40, 3, Some("VIN_SYS_5V0".to_owned()), current_voltage.clone()),
+                (
+                    0x40,
+                    7,
+                    Some("sum of shunt voltages".to_owned()),
+                    vec![METRIC_CURRENT.to_owned()]
+                ),
+                (0x41, 1, Some("NC".to_owned()), vec![]),
+                (0x41, 2, Some("VDDQ_VDD2_1V8AO".to_owned()), current_voltage),
+            ]
+        );
+    }
+
+    #[test]
+    fn both_layouts() {
+        let tmp = tempdir().unwrap();
+        let modern_root = tmp.path().join("ina3221");
+        let old_root = tmp.path().join("ina3221x");
+        create_recorded_tree(&modern_root, include_str!("../testdata/orin-agx-jetpack6.txt"));
+        create_recorded_tree(&old_root, include_str!("../testdata/xavier-nx-jetpack4.txt"));
+
+        let paths = InaSysfsPath {
+            sysfs_ina_modern: modern_root.to_str().unwrap(),
+            sysfs_ina_old: old_root.to_str().unwrap(),
+        };
+        let (sensors, errs) = detect_ina_sensors(paths).expect("detection failed");
+        assert!(errs.is_empty(), "detection failed: {errs:?}");
+        let mut devices: Vec<String> = sensors
+            .iter()
+            .map(|s| s.metadata.path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        devices.sort();
+        assert_eq!(devices, vec!["hwmon1", "hwmon2", "iio:device0"]);
+    }
+
     #[test]
     fn no_ina() {
         let tmp = tempdir().unwrap();
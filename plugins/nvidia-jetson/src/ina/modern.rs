@@ -10,11 +10,21 @@ use super::{EntryAnalysis, InaDeviceMetadata, InaExplorer};
 pub const SYSFS_INA_MODERN: &str = "/sys/bus/i2c/drivers/ina3221";
 
 /// Detect the available INA sensors, assuming that Nvidia Jetpack version >= 5.0 is installed.
+///
+/// Recent releases (JetPack 5.1+ on Orin, for instance) use the upstream `ina3221` hwmon driver,
+/// which exposes more nodes than the channel files `in{N}_label`, `in{N}_input` and `curr{N}_input`:
+/// - `in4_input` to `in6_input` are the shunt voltages of the channels 1 to 3,
+/// - `in7_input` and `curr4_input` are the shunt voltage and current of the summation channel, labelled by `in7_label`.
+///
+/// The explorer recognizes these nodes, so that they do not create fake channels.
 pub struct ModernInaExplorer {
     sysfs_root: PathBuf,
     entry_analyzer: ChannelEntryAnalyzer,
 }
 
+/// Id of the summation channel of the upstream `ina3221` driver.
+const SUMMATION_CHANNEL_ID: u32 = 7;
+
 impl ModernInaExplorer {
     pub fn new(sysfs_root: impl Into<PathBuf>) -> Self {
         Self {
@@ -74,10 +84,46 @@ impl InaExplorer for ModernInaExplorer {
     }
 
     fn analyze_entry(&self, channel_entry_path: &Path) -> anyhow::Result<EntryAnalysis> {
+        if let Some(analysis) = analyze_upstream_driver_entry(channel_entry_path)? {
+            return Ok(analysis);
+        }
         self.entry_analyzer.analyze_entry(channel_entry_path)
     }
 }
 
+/// Analyzes the nodes that are specific to the upstream `ina3221` driver.
+///
+/// The upstream driver is recognized by the `shunt{N}_resistor` files of its channels.
+/// Returns `None` if the entry is not one of these nodes, in which case it's a regular channel entry.
+fn analyze_upstream_driver_entry(channel_entry_path: &Path) -> anyhow::Result<Option<EntryAnalysis>> {
+    let filename = channel_entry_path.file_name().unwrap().to_str().unwrap();
+    let dir = channel_entry_path.parent().unwrap();
+    let has_shunt_resistor = |channel_id: u32| dir.join(format!("shunt{channel_id}_resistor")).exists();
+
+    let analysis = match filename {
+        // shunt voltages of the channels 1 to 3
+        "in4_input" if has_shunt_resistor(1) => Some(EntryAnalysis::Ignore),
+        "in5_input" if has_shunt_resistor(2) => Some(EntryAnalysis::Ignore),
+        "in6_input" if has_shunt_resistor(3) => Some(EntryAnalysis::Ignore),
+        // shunt voltage of the summation channel
+        "in7_input" if (1..=3).any(has_shunt_resistor) => Some(EntryAnalysis::Ignore),
+        // current of the summation channel, which is labelled by in7_label
+        "curr4_input" if (1..=3).any(has_shunt_resistor) => {
+            let content = std::fs::read_to_string(channel_entry_path)?;
+            if content.is_empty() {
+                return Err(anyhow::Error::msg("empty file"));
+            }
+            Some(EntryAnalysis::MeasurementNode {
+                channel_id: SUMMATION_CHANNEL_ID,
+                unit: PrefixedUnit::milli(Unit::Ampere),
+                metric_name: METRIC_CURRENT.to_string(),
+            })
+        }
+        _ => None,
+    };
+    Ok(analysis)
+}
+
 /// Parses `/sys/bus/i2c/drivers/ina3221/1-0040/hwmon/hwmon2`.
 /// Extracts `40` (hexadecimal i2c address) and `2` (device id).
 fn parse_device_entry(entry_path: &Path) -> Option<InaDeviceMetadata> {
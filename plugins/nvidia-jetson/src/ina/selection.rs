@@ -0,0 +1,210 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use super::{InaChannel, InaSensor};
+
+/// Selection of the INA channels to measure.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChannelSelection {
+    /// If set, only measure these channels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<ChannelSelector>>,
+
+    /// Do not measure these channels (applied after `include`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<ChannelSelector>,
+
+    /// New labels of the channels.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rename: BTreeMap<ChannelSelector, String>,
+}
+
+/// Identifies one INA channel, either by its label (`VDD_IN`),
+/// or by the I2C address of its sensor and its id (`0x40:1`).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ChannelSelector {
+    Label(String),
+    Address { i2c_address: u32, channel_id: u32 },
+}
+
+impl ChannelSelection {
+    /// Removes the channels that are not selected, and renames the remaining ones.
+    ///
+    /// The sensors that do not have any channel left are removed.
+    pub fn apply(&self, sensors: &mut Vec<InaSensor>) {
+        for sensor in sensors.iter_mut() {
+            let i2c_address = sensor.metadata.i2c_address;
+            sensor.channels.retain(|chan| {
+                let included = match &self.include {
+                    Some(include) => include.iter().any(|s| s.matches(i2c_address, chan)),
+                    None => true,
+                };
+                included && !self.exclude.iter().any(|s| s.matches(i2c_address, chan))
+            });
+            for chan in &mut sensor.channels {
+                // the selectors are matched against the original label, not the new one
+                let new_label = self
+                    .rename
+                    .iter()
+                    .find(|(s, _)| s.matches(i2c_address, chan))
+                    .map(|(_, label)| label.clone());
+                if let Some(label) = new_label {
+                    chan.label = Some(label);
+                }
+            }
+        }
+        sensors.retain(|s| !s.channels.is_empty());
+    }
+}
+
+impl ChannelSelector {
+    /// Checks whether this selector matches the given channel of the sensor at `i2c_address`.
+    pub fn matches(&self, i2c_address: u32, channel: &InaChannel) -> bool {
+        match self {
+            ChannelSelector::Label(label) => channel.label.as_ref() == Some(label),
+            ChannelSelector::Address {
+                i2c_address: addr,
+                channel_id,
+            } => *addr == i2c_address && *channel_id == channel.id,
+        }
+    }
+}
+
+impl FromStr for ChannelSelector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((addr, id)) = s.split_once(':') else {
+            return Ok(ChannelSelector::Label(s.to_owned()));
+        };
+        let i2c_address = match addr.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => addr.parse(),
+        }
+        .with_context(|| format!("invalid I2C address in channel selector '{s}'"))?;
+        let channel_id = id
+            .parse()
+            .with_context(|| format!("invalid channel id in channel selector '{s}'"))?;
+        Ok(ChannelSelector::Address {
+            i2c_address,
+            channel_id,
+        })
+    }
+}
+
+impl TryFrom<String> for ChannelSelector {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ChannelSelector> for String {
+    fn from(value: ChannelSelector) -> Self {
+        value.to_string()
+    }
+}
+
+impl Display for ChannelSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChannelSelector::Label(label) => write!(f, "{label}"),
+            ChannelSelector::Address {
+                i2c_address,
+                channel_id,
+            } => write!(f, "{i2c_address:#x}:{channel_id}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::ina::{InaChannel, InaDeviceMetadata, InaSensor};
+
+    use super::{ChannelSelection, ChannelSelector};
+
+    fn sensor(i2c_address: u32, labels: &[&str]) -> InaSensor {
+        InaSensor {
+            metadata: InaDeviceMetadata {
+                path: PathBuf::from(format!("/sys/bus/i2c/drivers/ina3221/1-{i2c_address:04x}")),
+                i2c_address,
+                number: 0,
+            },
+            channels: labels
+                .iter()
+                .enumerate()
+                .map(|(i, label)| InaChannel {
+                    label: Some(label.to_string()),
+                    ..InaChannel::new(i as u32 + 1)
+                })
+                .collect(),
+        }
+    }
+
+    fn labels(sensors: &[InaSensor]) -> Vec<Vec<&str>> {
+        sensors
+            .iter()
+            .map(|s| s.channels.iter().map(|c| c.label.as_deref().unwrap()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn parse_selector() {
+        assert_eq!(
+            "VDD_IN".parse::<ChannelSelector>().unwrap(),
+            ChannelSelector::Label("VDD_IN".to_owned())
+        );
+        let addr = ChannelSelector::Address {
+            i2c_address: 0x40,
+            channel_id: 2,
+        };
+        assert_eq!("0x40:2".parse::<ChannelSelector>().unwrap(), addr);
+        assert_eq!("64:2".parse::<ChannelSelector>().unwrap(), addr);
+        assert_eq!(addr.to_string(), "0x40:2");
+        "0x40:x".parse::<ChannelSelector>().expect_err("invalid channel id");
+        "0xZZ:1".parse::<ChannelSelector>().expect_err("invalid address");
+    }
+
+    #[test]
+    fn include_exclude_rename() {
+        let all = || {
+            vec![
+                sensor(0x40, &["VDD_IN", "VDD_CPU_GPU_CV", "VDD_SOC"]),
+                sensor(0x41, &["VDD_5V0_IO_SYS", "VDD_3V3_SYS"]),
+            ]
+        };
+
+        let mut sensors = all();
+        ChannelSelection::default().apply(&mut sensors);
+        assert_eq!(sensors, all());
+
+        let selection: ChannelSelection = toml::from_str(
+            r#"
+            include = ["VDD_IN", "0x40:2", "VDD_3V3_SYS"]
+            exclude = ["0x41:2"]
+            rename = { "VDD_IN" = "total", "0x40:2" = "cpu_gpu_cv" }
+            "#,
+        )
+        .unwrap();
+        let mut sensors = all();
+        selection.apply(&mut sensors);
+        assert_eq!(labels(&sensors), vec![vec!["total", "cpu_gpu_cv"]]);
+
+        let selection: ChannelSelection = toml::from_str(r#"exclude = ["VDD_SOC", "VDD_5V0_IO_SYS"]"#).unwrap();
+        let mut sensors = all();
+        selection.apply(&mut sensors);
+        assert_eq!(
+            labels(&sensors),
+            vec![vec!["VDD_IN", "VDD_CPU_GPU_CV"], vec!["VDD_3V3_SYS"]]
+        );
+    }
+}
@@ -1,9 +1,12 @@
+mod clocks;
 mod ina;
 mod source;
+mod thermal;
+mod utilization;
 
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{path::Path, time::Duration};
 
 use alumet::{
     pipeline::elements::source::trigger::TriggerSpec,
@@ -33,6 +36,88 @@ impl JetsonPlugin {
     fn sysfs_paths(&self) -> ina::InaSysfsPath<'_> {
         ina::InaSysfsPath::default()
     }
+
+    #[cfg(test)]
+    fn sysfs_thermal(&self) -> &Path {
+        Path::new(&self.config.sysfs_thermal)
+    }
+
+    #[cfg(not(test))]
+    fn sysfs_thermal(&self) -> &Path {
+        Path::new(thermal::SYSFS_THERMAL)
+    }
+
+    #[cfg(test)]
+    fn sysfs_and_procfs(&self) -> (&Path, &Path) {
+        (Path::new(&self.config.sysfs), Path::new(&self.config.procfs))
+    }
+
+    #[cfg(not(test))]
+    fn sysfs_and_procfs(&self) -> (&Path, &Path) {
+        (Path::new(utilization::SYSFS_ROOT), Path::new(utilization::PROCFS_ROOT))
+    }
+
+    #[cfg(test)]
+    fn rootfs(&self) -> &Path {
+        Path::new(&self.config.rootfs)
+    }
+
+    #[cfg(not(test))]
+    fn rootfs(&self) -> &Path {
+        Path::new(clocks::ROOTFS)
+    }
+
+    /// Adds a source that measures the clock frequencies and the power mode.
+    fn add_clock_source(&self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let (sysfs, _) = self.sysfs_and_procfs();
+        let files = clocks::detect_clock_files(sysfs, self.rootfs())?;
+        if files.nvpmodel_status.is_none() {
+            log::warn!("The power mode (nvpmodel) is not available, it will not be measured.");
+        }
+        let source = clocks::JetsonClockSource::new(files, alumet)?;
+        let trigger = TriggerSpec::builder(self.config.poll_interval)
+            .flush_interval(self.config.flush_interval)
+            .build()?;
+        alumet.add_source("clocks", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    /// Adds a source that measures the utilization of the GPU, memory controller, CPU and RAM.
+    fn add_utilization_source(&self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let (sysfs, procfs) = self.sysfs_and_procfs();
+        let files = utilization::detect_utilization_files(sysfs, procfs)?;
+        if files.gr3d_load.is_none() {
+            log::warn!("The GPU (GR3D) load is not available, it will not be measured.");
+        }
+        if files.emc.is_none() {
+            log::warn!(
+                "The EMC activity is not available (are the permissions set properly?), it will not be measured."
+            );
+        }
+        let source = utilization::JetsonUtilizationSource::open_files(files, alumet)?;
+        let trigger = TriggerSpec::builder(self.config.poll_interval)
+            .flush_interval(self.config.flush_interval)
+            .build()?;
+        alumet.add_source("utilization", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    /// Adds a source that measures the temperature of the thermal zones.
+    fn add_thermal_source(&self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let (zones, errs) = thermal::detect_thermal_zones(self.sysfs_thermal())?;
+        for err in errs {
+            log::debug!("Skipping thermal zone: {err:#}");
+        }
+        for zone in &zones {
+            log::info!("Found thermal zone {}: {}", zone.id, zone.zone_type);
+        }
+        let source = thermal::JetsonThermalSource::open_zones(zones, alumet)?;
+        let trigger = TriggerSpec::builder(self.config.poll_interval)
+            .flush_interval(self.config.flush_interval)
+            .build()?;
+        alumet.add_source("thermal_zones", Box::new(source), trigger)?;
+        Ok(())
+    }
 }
 
 impl AlumetPlugin for JetsonPlugin {
@@ -76,6 +161,15 @@ impl AlumetPlugin for JetsonPlugin {
             ));
         }
 
+        // only keep the channels that are selected in the config
+        let detector = ina::InaDetector::new(self.sysfs_paths(), self.config.channels.clone());
+        detector.selection.apply(&mut sensors);
+        if sensors.is_empty() {
+            return Err(anyhow::Error::msg(
+                "all the INA-3221 channels are excluded by the configuration (see the `channels` section)",
+            ));
+        }
+
         // print valid sensors
         for sensor in &sensors {
             log::info!("Found INA-3221 sensor {}", sensor.metadata);
@@ -87,11 +181,32 @@ impl AlumetPlugin for JetsonPlugin {
         }
 
         // prepare the measurement source
-        let source = source::JetsonInaSource::open_sensors(sensors, alumet)?;
+        let redetection = self
+            .config
+            .redetect_sensors
+            .then(|| source::SensorRedetection::new(detector, self.config.redetection_interval));
+        let source = source::JetsonInaSource::open_sensors(sensors, redetection, alumet)?;
         let trigger = TriggerSpec::builder(self.config.poll_interval)
             .flush_interval(self.config.flush_interval)
             .build()?;
         alumet.add_source("builtin_ina_sensor", Box::new(source), trigger)?;
+
+        // the temperatures are useful, but not essential: don't fail if they are not available
+        if self.config.thermal_zones
+            && let Err(e) = self.add_thermal_source(alumet)
+        {
+            log::warn!("The temperature of the thermal zones will not be measured: {e:#}");
+        }
+        if self.config.utilization
+            && let Err(e) = self.add_utilization_source(alumet)
+        {
+            log::warn!("The utilization of the device will not be measured: {e:#}");
+        }
+        if self.config.clocks
+            && let Err(e) = self.add_clock_source(alumet)
+        {
+            log::warn!("The clock frequencies and power mode will not be measured: {e:#}");
+        }
         Ok(())
     }
 
@@ -111,11 +226,79 @@ struct Config {
     #[serde(with = "humantime_serde")]
     flush_interval: Duration,
 
+    /// Also measure the temperature of the thermal zones (CPU, GPU, SOC, etc.).
+    #[serde(default = "default_true")]
+    thermal_zones: bool,
+
+    /// Also measure the utilization of the GPU (GR3D), memory controller (EMC), CPU cores and RAM, like `tegrastats`.
+    #[serde(default = "default_true")]
+    utilization: bool,
+
+    /// Also measure the frequencies of the CPU cores, GPU and memory controller (EMC), and the active power mode (nvpmodel).
+    #[serde(default = "default_true")]
+    clocks: bool,
+
+    /// Detect the INA sensors again from time to time, to support hotplug (driver reload, device tree overlay changes, etc.).
+    #[serde(default = "default_true")]
+    redetect_sensors: bool,
+
+    /// Interval between two detections of the INA sensors, if `redetect_sensors` is enabled.
+    #[serde(with = "humantime_serde", default = "default_redetection_interval")]
+    redetection_interval: Duration,
+
+    /// Selection of the INA channels to measure (all by default), and new labels for the channels.
+    #[serde(default)]
+    channels: ina::selection::ChannelSelection,
+
     #[cfg(test)]
     sysfs_ina_modern: String,
 
     #[cfg(test)]
     sysfs_ina_old: String,
+
+    #[cfg(test)]
+    #[serde(default = "default_sysfs_thermal")]
+    sysfs_thermal: String,
+
+    #[cfg(test)]
+    #[serde(default = "default_sysfs")]
+    sysfs: String,
+
+    #[cfg(test)]
+    #[serde(default = "default_procfs")]
+    procfs: String,
+
+    #[cfg(test)]
+    #[serde(default = "default_rootfs")]
+    rootfs: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_redetection_interval() -> Duration {
+    Duration::from_secs(10)
+}
+
+#[cfg(test)]
+fn default_sysfs_thermal() -> String {
+    thermal::SYSFS_THERMAL.to_string()
+}
+
+#[cfg(test)]
+fn default_sysfs() -> String {
+    utilization::SYSFS_ROOT.to_string()
+}
+
+#[cfg(test)]
+fn default_procfs() -> String {
+    utilization::PROCFS_ROOT.to_string()
+}
+
+#[cfg(test)]
+fn default_rootfs() -> String {
+    clocks::ROOTFS.to_string()
 }
 
 impl Default for Config {
@@ -123,10 +306,24 @@ impl Default for Config {
         Self {
             poll_interval: Duration::from_secs(1), // 1Hz
             flush_interval: Duration::from_secs(5),
+            thermal_zones: true,
+            utilization: true,
+            clocks: true,
+            redetect_sensors: true,
+            redetection_interval: default_redetection_interval(),
+            channels: ina::selection::ChannelSelection::default(),
             #[cfg(test)]
             sysfs_ina_modern: ina::modern::SYSFS_INA_MODERN.to_string(),
             #[cfg(test)]
             sysfs_ina_old: ina::old::SYSFS_INA_OLD.to_string(),
+            #[cfg(test)]
+            sysfs_thermal: default_sysfs_thermal(),
+            #[cfg(test)]
+            sysfs: default_sysfs(),
+            #[cfg(test)]
+            procfs: default_procfs(),
+            #[cfg(test)]
+            rootfs: default_rootfs(),
         }
     }
 }
@@ -201,6 +398,7 @@ mod tests {
         let startup = StartupExpectations::new()
             .expect_metric::<u64>("input_current", PrefixedUnit::milli(Unit::Ampere))
             .expect_metric::<u64>("input_voltage", PrefixedUnit::milli(Unit::Volt))
+            .expect_metric::<f64>("input_energy", Unit::Joule)
             .expect_source("jetson", "builtin_ina_sensor");
 
         let runtime = RuntimeExpectations::new().test_source(
@@ -259,6 +457,349 @@ mod tests {
         agent.wait_for_shutdown(TIMEOUT).expect("pipeline should run fine");
     }
 
+    #[test]
+    fn test_plugin_with_thermal_zones() {
+        let tmp = tempdir().unwrap();
+
+        // Create the fake sensor directories
+        let root = tmp.path().join("test-alumet-plugin-nvidia/ina-modern");
+        let hwmon0 = root.join("1-0040/hwmon/hwmon0");
+        std::fs::create_dir_all(&hwmon0).unwrap();
+        std::fs::write(hwmon0.join("in0_label"), "Sensor 0, channel 0").unwrap();
+        std::fs::write(hwmon0.join("curr0_input"), "0").unwrap();
+        std::fs::write(hwmon0.join("in0_input"), "1").unwrap();
+
+        // Create the fake thermal zones
+        let thermal = tmp.path().join("test-alumet-plugin-nvidia/thermal");
+        for (id, zone_type, temp) in [(0, "cpu-thermal", "48500"), (1, "gpu-thermal", "46000")] {
+            let zone = thermal.join(format!("thermal_zone{id}"));
+            std::fs::create_dir_all(&zone).unwrap();
+            std::fs::write(zone.join("type"), zone_type).unwrap();
+            std::fs::write(zone.join("temp"), temp).unwrap();
+        }
+
+        // Create the config
+        let sysfs_root = root.to_str().unwrap();
+        let sysfs_thermal = thermal.to_str().unwrap();
+        let config = toml::from_str(&format!(
+            r#"
+                poll_interval = "1s"
+                flush_interval = "1s"
+                sysfs_ina_modern = "{sysfs_root}"
+                sysfs_ina_old = ""
+                sysfs_thermal = "{sysfs_thermal}"
+            "#
+        ))
+        .unwrap();
+
+        // Start Alumet with the plugin.
+        let mut plugins = PluginSet::new();
+        plugins.add_plugin(PluginInfo {
+            metadata: PluginMetadata::from_static::<JetsonPlugin>(),
+            enabled: true,
+            config: Some(config),
+        });
+
+        let startup = StartupExpectations::new()
+            .expect_metric::<f64>("thermal_zone_temperature", Unit::DegreeCelsius)
+            .expect_source("jetson", "builtin_ina_sensor")
+            .expect_source("jetson", "thermal_zones");
+
+        let runtime = RuntimeExpectations::new().test_source(
+            SourceName::from_str("jetson", "thermal_zones"),
+            || {},
+            |out| {
+                let temperatures: HashMap<String, f64> = out
+                    .measurements()
+                    .iter()
+                    .map(|m| {
+                        let zone_type = m
+                            .attributes()
+                            .find(|attr| attr.0 == "thermal_zone_type")
+                            .expect("missing attribute thermal_zone_type")
+                            .1
+                            .to_string();
+                        (zone_type, m.value.as_f64())
+                    })
+                    .collect();
+                assert_eq!(
+                    temperatures,
+                    HashMap::from_iter([(String::from("cpu-thermal"), 48.5), (String::from("gpu-thermal"), 46.0)])
+                );
+            },
+        );
+
+        let agent = alumet::agent::Builder::new(plugins)
+            .with_expectations(startup)
+            .with_expectations(runtime)
+            .build_and_start()
+            .expect("agent should start");
+        agent.wait_for_shutdown(TIMEOUT).expect("pipeline should run fine");
+    }
+
+    #[test]
+    fn test_plugin_with_utilization() {
+        let tmp = tempdir().unwrap();
+
+        // Create the fake sensor directories
+        let root = tmp.path().join("test-alumet-plugin-nvidia/ina-modern");
+        let hwmon0 = root.join("1-0040/hwmon/hwmon0");
+        std::fs::create_dir_all(&hwmon0).unwrap();
+        std::fs::write(hwmon0.join("in0_label"), "Sensor 0, channel 0").unwrap();
+        std::fs::write(hwmon0.join("curr0_input"), "0").unwrap();
+        std::fs::write(hwmon0.join("in0_input"), "1").unwrap();
+
+        // Create the fake utilization files
+        let sysfs = tmp.path().join("test-alumet-plugin-nvidia/sys");
+        let procfs = tmp.path().join("test-alumet-plugin-nvidia/proc");
+        std::fs::create_dir_all(sysfs.join("devices/gpu.0")).unwrap();
+        std::fs::create_dir_all(sysfs.join("kernel/actmon_avg_activity")).unwrap();
+        std::fs::create_dir_all(sysfs.join("kernel/debug/clk/emc")).unwrap();
+        std::fs::create_dir_all(&procfs).unwrap();
+        std::fs::write(sysfs.join("devices/gpu.0/load"), "625").unwrap();
+        std::fs::write(sysfs.join("kernel/actmon_avg_activity/mc_all"), "400000").unwrap();
+        std::fs::write(sysfs.join("kernel/debug/clk/emc/clk_rate"), "1600000000").unwrap();
+        std::fs::write(procfs.join("stat"), "cpu0 400 0 200 3900 50 0 0 0 0 0\n").unwrap();
+        std::fs::write(procfs.join("meminfo"), "MemTotal: 8000 kB\nMemAvailable: 6000 kB\n").unwrap();
+
+        // Create the config
+        let sysfs_root = root.to_str().unwrap();
+        let config = toml::from_str(&format!(
+            r#"
+                poll_interval = "1s"
+                flush_interval = "1s"
+                thermal_zones = false
+                sysfs_ina_modern = "{sysfs_root}"
+                sysfs_ina_old = ""
+                sysfs = "{}"
+                procfs = "{}"
+            "#,
+            sysfs.to_str().unwrap(),
+            procfs.to_str().unwrap()
+        ))
+        .unwrap();
+
+        // Start Alumet with the plugin.
+        let mut plugins = PluginSet::new();
+        plugins.add_plugin(PluginInfo {
+            metadata: PluginMetadata::from_static::<JetsonPlugin>(),
+            enabled: true,
+            config: Some(config),
+        });
+
+        let startup = StartupExpectations::new()
+            .expect_metric::<f64>("gr3d_utilization", Unit::Percent)
+            .expect_metric::<f64>("emc_utilization", Unit::Percent)
+            .expect_metric::<f64>("cpu_core_utilization", Unit::Percent)
+            .expect_metric::<u64>("ram_used", Unit::Byte)
+            .expect_source("jetson", "utilization");
+
+        let runtime = RuntimeExpectations::new().test_source(
+            SourceName::from_str("jetson", "utilization"),
+            || {},
+            |out| {
+                let value_of = |name: &str| {
+                    let metric = out.metrics().by_name(name).unwrap().0;
+                    let points: Vec<_> = out.measurements().iter().filter(|m| m.metric == metric).collect();
+                    assert_eq!(points.len(), 1, "there should be one point for {name}");
+                    points[0].value.clone()
+                };
+                assert_eq!(value_of("gr3d_utilization").as_f64(), 62.5);
+                assert_eq!(value_of("emc_utilization").as_f64(), 25.0);
+                assert_eq!(value_of("ram_used").as_u64(), 2000 * 1024);
+
+                // the CPU utilization needs two measurements
+                let cpu_metric = out.metrics().by_name("cpu_core_utilization").unwrap().0;
+                assert!(out.measurements().iter().all(|m| m.metric != cpu_metric));
+            },
+        );
+
+        let agent = alumet::agent::Builder::new(plugins)
+            .with_expectations(startup)
+            .with_expectations(runtime)
+            .build_and_start()
+            .expect("agent should start");
+        agent.wait_for_shutdown(TIMEOUT).expect("pipeline should run fine");
+    }
+
+    #[test]
+    fn test_plugin_with_clocks() {
+        let tmp = tempdir().unwrap();
+
+        // Create the fake sensor directories
+        let root = tmp.path().join("test-alumet-plugin-nvidia/ina-modern");
+        let hwmon0 = root.join("1-0040/hwmon/hwmon0");
+        std::fs::create_dir_all(&hwmon0).unwrap();
+        std::fs::write(hwmon0.join("in0_label"), "Sensor 0, channel 0").unwrap();
+        std::fs::write(hwmon0.join("curr0_input"), "0").unwrap();
+        std::fs::write(hwmon0.join("in0_input"), "1").unwrap();
+
+        // Create the fake clock and nvpmodel files
+        let sysfs = tmp.path().join("test-alumet-plugin-nvidia/sys");
+        let rootfs = tmp.path().join("test-alumet-plugin-nvidia/root");
+        std::fs::create_dir_all(sysfs.join("devices/system/cpu/cpu0/cpufreq")).unwrap();
+        std::fs::create_dir_all(sysfs.join("class/devfreq/17000000.ga10b")).unwrap();
+        std::fs::create_dir_all(rootfs.join("var/lib/nvpmodel")).unwrap();
+        std::fs::create_dir_all(rootfs.join("etc")).unwrap();
+        std::fs::write(
+            sysfs.join("devices/system/cpu/cpu0/cpufreq/scaling_cur_freq"),
+            "1190400\n",
+        )
+        .unwrap();
+        std::fs::write(sysfs.join("class/devfreq/17000000.ga10b/cur_freq"), "306000000\n").unwrap();
+        std::fs::write(rootfs.join("var/lib/nvpmodel/status"), "pmode:0001\n").unwrap();
+        std::fs::write(rootfs.join("etc/nvpmodel.conf"), "< POWER_MODEL ID=1 NAME=15W >\n").unwrap();
+
+        // Create the config
+        let sysfs_root = root.to_str().unwrap();
+        let config = toml::from_str(&format!(
+            r#"
+                poll_interval = "1s"
+                flush_interval = "1s"
+                thermal_zones = false
+                utilization = false
+                sysfs_ina_modern = "{sysfs_root}"
+                sysfs_ina_old = ""
+                sysfs = "{}"
+                rootfs = "{}"
+            "#,
+            sysfs.to_str().unwrap(),
+            rootfs.to_str().unwrap()
+        ))
+        .unwrap();
+
+        // Start Alumet with the plugin.
+        let mut plugins = PluginSet::new();
+        plugins.add_plugin(PluginInfo {
+            metadata: PluginMetadata::from_static::<JetsonPlugin>(),
+            enabled: true,
+            config: Some(config),
+        });
+
+        let startup = StartupExpectations::new()
+            .expect_metric::<u64>("clock_frequency", Unit::Hertz)
+            .expect_metric::<u64>("nvpmodel_mode", Unit::Unity)
+            .expect_source("jetson", "clocks");
+
+        let runtime = RuntimeExpectations::new().test_source(
+            SourceName::from_str("jetson", "clocks"),
+            || {},
+            |out| {
+                let frequency_metric = out.metrics().by_name("clock_frequency").unwrap().0;
+                let frequencies: HashMap<String, u64> = out
+                    .measurements()
+                    .iter()
+                    .filter(|m| m.metric == frequency_metric)
+                    .map(|m| {
+                        let clock = m.attributes().find(|(k, _)| *k == "clock").unwrap().1.to_string();
+                        (clock, m.value.as_u64())
+                    })
+                    .collect();
+                assert_eq!(
+                    frequencies,
+                    HashMap::from([("cpu".to_owned(), 1_190_400_000), ("gpu".to_owned(), 306_000_000)])
+                );
+
+                let nvpmodel_metric = out.metrics().by_name("nvpmodel_mode").unwrap().0;
+                let mode = out.measurements().iter().find(|m| m.metric == nvpmodel_metric).unwrap();
+                assert_eq!(mode.value.as_u64(), 1);
+                assert_eq!(
+                    mode.attributes().collect::<Vec<_>>(),
+                    vec![("nvpmodel_name", &AttributeValue::String("15W".to_owned()))]
+                );
+            },
+        );
+
+        let agent = alumet::agent::Builder::new(plugins)
+            .with_expectations(startup)
+            .with_expectations(runtime)
+            .build_and_start()
+            .expect("agent should start");
+        agent.wait_for_shutdown(TIMEOUT).expect("pipeline should run fine");
+    }
+
+    #[test]
+    fn test_plugin_with_hotplug() {
+        let tmp = tempdir().unwrap();
+
+        // Create the fake sensor directories, with only one sensor at the beginning
+        let root = tmp.path().join("test-alumet-plugin-nvidia/ina-modern");
+        fn create_sensor(root: &Path, i2c_dir: &str, hwmon: &str) {
+            let hwmon = root.join(i2c_dir).join("hwmon").join(hwmon);
+            std::fs::create_dir_all(&hwmon).unwrap();
+            std::fs::write(hwmon.join("in1_label"), "VDD_IN").unwrap();
+            std::fs::write(hwmon.join("curr1_input"), "100").unwrap();
+            std::fs::write(hwmon.join("in1_input"), "5000").unwrap();
+        }
+        create_sensor(&root, "1-0040", "hwmon0");
+
+        // Create the config
+        let sysfs_root = root.to_str().unwrap();
+        let config = toml::from_str(&format!(
+            r#"
+                poll_interval = "1s"
+                flush_interval = "1s"
+                thermal_zones = false
+                utilization = false
+                clocks = false
+                redetection_interval = "0s"
+                sysfs_ina_modern = "{sysfs_root}"
+                sysfs_ina_old = ""
+            "#
+        ))
+        .unwrap();
+
+        // Start Alumet with the plugin.
+        let mut plugins = PluginSet::new();
+        plugins.add_plugin(PluginInfo {
+            metadata: PluginMetadata::from_static::<JetsonPlugin>(),
+            enabled: true,
+            config: Some(config),
+        });
+
+        /// Returns the I2C addresses of the sensors that have been measured.
+        fn measured_sensors(out: &alumet::test::runtime::SourceCheckOutputContext) -> HashSet<u64> {
+            let current_metric = out.metrics().by_name("input_current").unwrap().0;
+            out.measurements()
+                .iter()
+                .filter(|m| m.metric == current_metric)
+                .map(
+                    |m| match m.attributes().find(|(k, _)| *k == "ina_i2c_address").unwrap().1 {
+                        AttributeValue::U64(addr) => *addr,
+                        _ => panic!("ina_i2c_address should be a U64"),
+                    },
+                )
+                .collect()
+        }
+
+        let source = SourceName::from_str("jetson", "builtin_ina_sensor");
+        let (root_1, root_2) = (root.clone(), root.clone());
+        let runtime = RuntimeExpectations::new()
+            .test_source(
+                source.clone(),
+                || {},
+                |out| assert_eq!(measured_sensors(out), HashSet::from([0x40])),
+            )
+            // a new sensor appears
+            .test_source(
+                source.clone(),
+                move || create_sensor(&root_1, "1-0041", "hwmon1"),
+                |out| assert_eq!(measured_sensors(out), HashSet::from([0x40, 0x41])),
+            )
+            // the first sensor disappears
+            .test_source(
+                source,
+                move || std::fs::remove_dir_all(root_2.join("1-0040")).unwrap(),
+                |out| assert_eq!(measured_sensors(out), HashSet::from([0x41])),
+            );
+
+        let agent = alumet::agent::Builder::new(plugins)
+            .with_expectations(runtime)
+            .build_and_start()
+            .expect("agent should start");
+        agent.wait_for_shutdown(TIMEOUT).expect("pipeline should run fine");
+    }
+
     #[test]
     fn bad_permissions_1() {
         let tmp = tempdir().unwrap();
@@ -371,4 +912,41 @@ mod tests {
         let agent = alumet::agent::Builder::new(plugins).build_and_start();
         assert!(agent.is_err(), "plugin should not start");
     }
+
+    #[test]
+    fn all_channels_excluded() {
+        let tmp = tempdir().unwrap();
+
+        // Create the fake sensor directories
+        let root = tmp.path().join("test-alumet-plugin-nvidia/ina-modern");
+        let hwmon0 = root.join("1-0040/hwmon/hwmon0");
+        std::fs::create_dir_all(&hwmon0).unwrap();
+        std::fs::write(hwmon0.join("in0_label"), "VDD_IN").unwrap();
+        std::fs::write(hwmon0.join("curr0_input"), "0").unwrap();
+        std::fs::write(hwmon0.join("in0_input"), "1").unwrap();
+
+        // Create the config
+        let sysfs_root = root.to_str().unwrap();
+        let config = toml::from_str(&format!(
+            r#"
+                poll_interval = "1s"
+                flush_interval = "1s"
+                sysfs_ina_modern = "{sysfs_root}"
+                sysfs_ina_old = ""
+                channels.include = ["VDD_SOC"]
+            "#
+        ))
+        .unwrap();
+
+        // Start Alumet with the plugin.
+        let mut plugins = PluginSet::new();
+        plugins.add_plugin(PluginInfo {
+            metadata: PluginMetadata::from_static::<JetsonPlugin>(),
+            enabled: true,
+            config: Some(config),
+        });
+
+        let agent = alumet::agent::Builder::new(plugins).build_and_start();
+        assert!(agent.is_err(), "plugin should not start");
+    }
 }
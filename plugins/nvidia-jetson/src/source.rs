@@ -1,6 +1,8 @@
 use std::{
     fs::File,
     io::{Read, Seek},
+    path::PathBuf,
+    time::Duration,
 };
 
 use alumet::{
@@ -9,18 +11,46 @@ use alumet::{
     pipeline::elements::error::PollError,
     plugin::AlumetPluginStart,
     resources::{Resource, ResourceConsumer},
+    units::{PrefixedUnit, Unit},
 };
 use anyhow::{Context, anyhow};
 
-use super::ina::InaSensor;
+use super::ina::{
+    InaDetector, InaSensor,
+    common::{METRIC_CURRENT, METRIC_POWER, METRIC_VOLTAGE},
+};
+
+pub const METRIC_ENERGY: &str = "input_energy";
 
 /// Measurement source that queries the embedded INA3221 sensor of a Jetson device.
 pub struct JetsonInaSource {
     opened_sensors: Vec<OpenedInaSensor>,
+    metrics: InaMetrics,
+    /// If set, the sensors are detected again from time to time, to support hotplug.
+    redetection: Option<SensorRedetection>,
+}
+
+/// Ids of the metrics registered in Alumet.
+///
+/// All the metrics are created when the plugin starts, because new sensors can appear at runtime.
+struct InaMetrics {
+    current: TypedMetricId<u64>,
+    voltage: TypedMetricId<u64>,
+    power: TypedMetricId<u64>,
+    energy: TypedMetricId<f64>,
+}
+
+/// Periodic detection of the INA sensors, to add and remove sensors at runtime
+/// (driver reload, device tree overlay changes, etc.).
+pub struct SensorRedetection {
+    detector: InaDetector,
+    interval: Duration,
+    last_detection: Timestamp,
 }
 
 /// A sensor that has been "opened" for reading.
 pub struct OpenedInaSensor {
+    path: PathBuf,
     i2c_address: u32,
     device_number: u32,
     channels: Vec<OpenedInaChannel>,
@@ -31,6 +61,8 @@ pub struct OpenedInaChannel {
     id: u32,
     label: String,
     metrics: Vec<OpenedInaMetric>,
+    /// Energy consumed on the channel, if its power is known.
+    energy: Option<EnergyIntegrator>,
 }
 
 /// A channel metric that has been "opened" for reading.
@@ -42,87 +74,319 @@ pub struct OpenedInaMetric {
     resource_id: Resource,
     /// The virtual file in the sysfs, opened for reading.
     file: File,
+    /// What this metric measures, to compute the power of the channel.
+    kind: MetricKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MetricKind {
+    /// Current in mA.
+    Current,
+    /// Voltage in mV.
+    Voltage,
+    /// Power in mW.
+    Power,
+}
+
+/// Integrates the power of a channel over time, to obtain its energy consumption.
+#[derive(Default)]
+struct EnergyIntegrator {
+    /// Total energy consumed since the first measurement, in Joules.
+    total: f64,
+    /// Previous power measurement (timestamp, power in mW).
+    previous: Option<(Timestamp, f64)>,
+}
+
+impl EnergyIntegrator {
+    /// Updates the total energy with a new power measurement, in mW, using the trapezoidal rule.
+    ///
+    /// If the clock has gone backwards since the previous measurement (e.g. because of an NTP adjustment),
+    /// the interval is skipped and the integration restarts from the new measurement.
+    ///
+    /// Returns the total energy consumed since the first measurement, in Joules.
+    fn update(&mut self, timestamp: Timestamp, power_mw: f64) -> f64 {
+        if let Some((previous_t, previous_power)) = self.previous {
+            match timestamp.duration_since(previous_t) {
+                Ok(time_elapsed) => {
+                    let energy_millijoules = (previous_power + power_mw) * 0.5 * time_elapsed.as_secs_f64();
+                    self.total += energy_millijoules / 1000.0;
+                }
+                Err(e) => log::warn!(
+                    "The clock has gone backwards by {:?}, the energy of this interval is not measured.",
+                    e.duration()
+                ),
+            }
+        }
+        self.previous = Some((timestamp, power_mw));
+        self.total
+    }
+}
+
+impl InaMetrics {
+    fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        let mut create = |name: &str, unit: PrefixedUnit| {
+            alumet
+                .create_metric(name, unit, format!("{name} (see attributes for channel info)"))
+                .with_context(|| format!("could not create metric {name}"))
+        };
+        Ok(Self {
+            current: create(METRIC_CURRENT, PrefixedUnit::milli(Unit::Ampere))?,
+            voltage: create(METRIC_VOLTAGE, PrefixedUnit::milli(Unit::Volt))?,
+            power: create(METRIC_POWER, PrefixedUnit::milli(Unit::Watt))?,
+            energy: alumet.create_metric(
+                METRIC_ENERGY,
+                Unit::Joule,
+                "energy consumed on the channel's line since the start of the measurement (see attributes for channel info)",
+            )?,
+        })
+    }
+
+    /// Returns the metric that corresponds to a file of an INA channel.
+    fn by_name(&self, name: &str) -> Option<(TypedMetricId<u64>, MetricKind)> {
+        match name {
+            METRIC_CURRENT => Some((self.current, MetricKind::Current)),
+            METRIC_VOLTAGE => Some((self.voltage, MetricKind::Voltage)),
+            METRIC_POWER => Some((self.power, MetricKind::Power)),
+            _ => None,
+        }
+    }
+}
+
+impl SensorRedetection {
+    pub fn new(detector: InaDetector, interval: Duration) -> Self {
+        Self {
+            detector,
+            interval,
+            last_detection: Timestamp::now(),
+        }
+    }
+
+    fn is_due(&self, timestamp: Timestamp) -> bool {
+        // if the clock has changed, detect the sensors again to be safe
+        timestamp
+            .duration_since(self.last_detection)
+            .map_or(true, |elapsed| elapsed >= self.interval)
+    }
 }
 
 impl JetsonInaSource {
-    pub fn open_sensors(sensors: Vec<InaSensor>, alumet: &mut AlumetPluginStart) -> anyhow::Result<JetsonInaSource> {
+    pub fn open_sensors(
+        sensors: Vec<InaSensor>,
+        redetection: Option<SensorRedetection>,
+        alumet: &mut AlumetPluginStart,
+    ) -> anyhow::Result<JetsonInaSource> {
         if sensors.is_empty() {
             return Err(anyhow!("Cannot construct a JetsonInaSource without any sensor."));
         }
 
-        let mut opened_sensors = Vec::with_capacity(4);
-        for sensor in sensors {
-            let mut sensor_opened_channels = Vec::with_capacity(sensor.channels.len());
-            for channel in sensor.channels {
-                let channel_label = channel.label.unwrap_or_else(|| format!("channel_{}", channel.id));
-                let metrics: anyhow::Result<Vec<OpenedInaMetric>> = channel
-                    .metrics
-                    .into_iter()
-                    .map(|m| {
-                        // Open the file for the measurement operation.
-                        let file = File::open(&m.path)
-                            .with_context(|| format!("could not open virtual file {}", m.path.display()))?;
-
-                        // Create a metric in Alumet. Duplicates are ok (Alumet will only create each metric once).
-                        let metric_id = alumet
-                            .create_metric(&m.name, m.unit, format!("{} (see attributes for channel info)", m.name))
-                            .with_context(|| format!("could not create metric for channel '{channel_label}'"))?;
-
-                        Ok(OpenedInaMetric {
-                            metric_id,
-                            resource_id: Resource::LocalMachine,
-                            file,
-                        })
+        let metrics = InaMetrics::new(alumet)?;
+        let opened_sensors = sensors
+            .into_iter()
+            .map(|sensor| OpenedInaSensor::open(sensor, &metrics))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(JetsonInaSource {
+            opened_sensors,
+            metrics,
+            redetection,
+        })
+    }
+}
+
+impl OpenedInaSensor {
+    fn open(sensor: InaSensor, metrics: &InaMetrics) -> anyhow::Result<Self> {
+        let mut opened_channels = Vec::with_capacity(sensor.channels.len());
+        for channel in sensor.channels {
+            let channel_label = channel.label.unwrap_or_else(|| format!("channel_{}", channel.id));
+            let opened_metrics: anyhow::Result<Vec<OpenedInaMetric>> = channel
+                .metrics
+                .into_iter()
+                .map(|m| {
+                    // Open the file for the measurement operation.
+                    let file = File::open(&m.path)
+                        .with_context(|| format!("could not open virtual file {}", m.path.display()))?;
+
+                    let (metric_id, kind) = metrics
+                        .by_name(&m.name)
+                        .with_context(|| format!("unknown metric {} for channel '{channel_label}'", m.name))?;
+                    Ok(OpenedInaMetric {
+                        metric_id,
+                        resource_id: Resource::LocalMachine,
+                        file,
+                        kind,
                     })
-                    .collect();
-                let opened_chan = OpenedInaChannel {
-                    id: channel.id,
-                    label: channel_label,
-                    metrics: metrics?,
+                })
+                .collect();
+            let opened_metrics = opened_metrics?;
+
+            // The power is given by the sensor, or computed from the current and voltage.
+            let has = |kind| opened_metrics.iter().any(|m| m.kind == kind);
+            let energy = (has(MetricKind::Power) || (has(MetricKind::Current) && has(MetricKind::Voltage)))
+                .then(EnergyIntegrator::default);
+
+            opened_channels.push(OpenedInaChannel {
+                id: channel.id,
+                label: channel_label,
+                metrics: opened_metrics,
+                energy,
+            });
+        }
+        Ok(OpenedInaSensor {
+            path: sensor.metadata.path,
+            i2c_address: sensor.metadata.i2c_address,
+            device_number: sensor.metadata.number,
+            channels: opened_channels,
+        })
+    }
+
+    fn poll(
+        &mut self,
+        metrics: &InaMetrics,
+        measurements: &mut MeasurementAccumulator,
+        timestamp: Timestamp,
+        reading_buf: &mut Vec<u8>,
+    ) -> anyhow::Result<()> {
+        for chan in &mut self.channels {
+            let (mut current, mut voltage, mut power) = (None, None, None);
+            for m in &mut chan.metrics {
+                // read the file from the beginning
+                reading_buf.clear();
+                m.file.rewind()?;
+                m.file.read_to_end(reading_buf)?;
+
+                // parse the content of the file
+                let content = std::str::from_utf8(reading_buf)?;
+                let value: u64 = content
+                    .trim_end()
+                    .parse()
+                    .with_context(|| format!("failed to parse {:?}: '{content}", m.file))?;
+                match m.kind {
+                    MetricKind::Current => current = Some(value),
+                    MetricKind::Voltage => voltage = Some(value),
+                    MetricKind::Power => power = Some(value),
+                }
+
+                // produce a measurement point
+                let consumer = ResourceConsumer::LocalMachine;
+                measurements.push(
+                    MeasurementPoint::new(timestamp, m.metric_id, m.resource_id.clone(), consumer, value)
+                        .with_attr("ina_device_number", AttributeValue::U64(self.device_number.into()))
+                        .with_attr("ina_i2c_address", AttributeValue::U64(self.i2c_address.into()))
+                        .with_attr("ina_channel_id", AttributeValue::U64(chan.id.into()))
+                        .with_attr("ina_channel_label", AttributeValue::String(chan.label.clone())),
+                );
+            }
+
+            // integrate the power to obtain the energy
+            if let Some(energy) = &mut chan.energy {
+                let power_mw = match (power, current, voltage) {
+                    (Some(p), _, _) => p as f64,
+                    // mA * mV = µW
+                    (None, Some(i), Some(u)) => (i * u) as f64 / 1000.0,
+                    _ => unreachable!("the energy is only computed when the power is available"),
                 };
-                sensor_opened_channels.push(opened_chan);
+                let total = energy.update(timestamp, power_mw);
+                let consumer = ResourceConsumer::LocalMachine;
+                measurements.push(
+                    MeasurementPoint::new(timestamp, metrics.energy, Resource::LocalMachine, consumer, total)
+                        .with_attr("ina_device_number", AttributeValue::U64(self.device_number.into()))
+                        .with_attr("ina_i2c_address", AttributeValue::U64(self.i2c_address.into()))
+                        .with_attr("ina_channel_id", AttributeValue::U64(chan.id.into()))
+                        .with_attr("ina_channel_label", AttributeValue::String(chan.label.clone())),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Detects the sensors again, opens the new ones and forgets the ones that have disappeared.
+///
+/// The sensors whose channels have not changed are kept as is, in order to preserve their energy counters.
+fn update_sensors(opened_sensors: &mut Vec<OpenedInaSensor>, metrics: &InaMetrics, detector: &InaDetector) {
+    let sensors = match detector.detect() {
+        Ok((sensors, _)) => sensors,
+        Err(e) => {
+            // no sysfs hierarchy at all, the driver has probably been unloaded
+            log::debug!("INA-3221 sensors re-detection failed: {e:#}");
+            Vec::new()
+        }
+    };
+
+    let mut previous: Vec<OpenedInaSensor> = std::mem::take(opened_sensors);
+    for sensor in sensors {
+        let unchanged = previous.iter().position(|s| {
+            s.path == sensor.metadata.path && s.channels.iter().map(|c| c.id).eq(sensor.channels.iter().map(|c| c.id))
+        });
+        match unchanged {
+            Some(i) => opened_sensors.push(previous.swap_remove(i)),
+            None => {
+                let metadata = sensor.metadata.clone();
+                match OpenedInaSensor::open(sensor, metrics) {
+                    Ok(opened) => {
+                        log::info!("New INA-3221 sensor detected: {metadata}");
+                        opened_sensors.push(opened);
+                    }
+                    Err(e) => log::warn!("Could not open the new INA-3221 sensor {metadata}: {e:#}"),
+                }
             }
-            opened_sensors.push(OpenedInaSensor {
-                i2c_address: sensor.metadata.i2c_address,
-                device_number: sensor.metadata.number,
-                channels: sensor_opened_channels,
-            })
         }
-        Ok(JetsonInaSource { opened_sensors })
     }
+    for removed in previous {
+        log::info!("INA-3221 sensor removed: {}", removed.path.display());
+    }
+    opened_sensors.sort_by_key(|s| s.i2c_address);
 }
 
 impl alumet::pipeline::Source for JetsonInaSource {
     fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
-        let mut reading_buf = Vec::with_capacity(8);
+        if let Some(redetection) = &mut self.redetection
+            && redetection.is_due(timestamp)
+        {
+            update_sensors(&mut self.opened_sensors, &self.metrics, &redetection.detector);
+            redetection.last_detection = timestamp;
+        }
 
-        for sensor in &mut self.opened_sensors {
-            for chan in &mut sensor.channels {
-                for m in &mut chan.metrics {
-                    // read the file from the beginning
-                    reading_buf.clear();
-                    m.file.rewind()?;
-                    m.file.read_to_end(&mut reading_buf)?;
-
-                    // parse the content of the file
-                    let content = std::str::from_utf8(&reading_buf)?;
-                    let value: u64 = content
-                        .trim_end()
-                        .parse()
-                        .with_context(|| format!("failed to parse {:?}: '{content}", m.file))?;
-
-                    // produce a measurement point
-                    let consumer = ResourceConsumer::LocalMachine;
-                    measurements.push(
-                        MeasurementPoint::new(timestamp, m.metric_id, m.resource_id.clone(), consumer, value)
-                            .with_attr("ina_device_number", AttributeValue::U64(sensor.device_number.into()))
-                            .with_attr("ina_i2c_address", AttributeValue::U64(sensor.i2c_address.into()))
-                            .with_attr("ina_channel_id", AttributeValue::U64(chan.id.into()))
-                            .with_attr("ina_channel_label", AttributeValue::String(chan.label.clone())),
+        let mut reading_buf = Vec::with_capacity(8);
+        let mut i = 0;
+        while i < self.opened_sensors.len() {
+            let sensor = &mut self.opened_sensors[i];
+            match sensor.poll(&self.metrics, measurements, timestamp, &mut reading_buf) {
+                Ok(()) => i += 1,
+                Err(e) if self.redetection.is_some() => {
+                    // The sensor has probably disappeared, forget it until the next detection.
+                    log::warn!(
+                        "INA-3221 sensor {} could not be read, it will be detected again later: {e:#}",
+                        sensor.path.display()
                     );
+                    self.opened_sensors.remove(i);
                 }
+                Err(e) => return Err(e.into()),
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use alumet::measurement::Timestamp;
+
+    use super::EnergyIntegrator;
+
+    #[test]
+    fn energy_integration() {
+        let t0 = Timestamp::from(SystemTime::UNIX_EPOCH);
+        let mut energy = EnergyIntegrator::default();
+        assert_eq!(energy.update(t0, 1000.0), 0.0);
+        // (1 W + 3 W) / 2 during 2 s
+        assert_eq!(energy.update(t0 + Duration::from_secs(2), 3000.0), 4.0);
+        // 3 W during 500 ms
+        assert_eq!(energy.update(t0 + Duration::from_millis(2500), 3000.0), 5.5);
+        // the clock goes backwards: the interval is skipped, and the integration continues from there
+        assert_eq!(energy.update(t0 + Duration::from_secs(1), 3000.0), 5.5);
+        // 3 W during 1 s
+        assert_eq!(energy.update(t0 + Duration::from_secs(2), 3000.0), 8.5);
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use alumet::{
+    measurement::{AttributeValue, MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::elements::error::PollError,
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::{Context, anyhow};
+
+pub const SYSFS_THERMAL: &str = "/sys/class/thermal";
+pub const METRIC_TEMPERATURE: &str = "thermal_zone_temperature";
+
+/// Detected thermal zone.
+#[derive(Debug, PartialEq)]
+pub struct ThermalZone {
+    /// Number of the zone, `N` in `thermal_zoneN`.
+    pub id: u32,
+    /// Type of the zone, for instance `cpu-thermal`, `gpu-thermal`, `soc0-thermal` or `tj-thermal`.
+    pub zone_type: String,
+    /// Path to the file that contains the temperature, in milli-degrees Celsius.
+    pub temp_path: PathBuf,
+}
+
+/// Returns a list of all the thermal zones whose temperature can be read, sorted by id.
+///
+/// The zones that cannot be read are returned as errors.
+pub fn detect_thermal_zones(sysfs_root: &Path) -> anyhow::Result<(Vec<ThermalZone>, Vec<anyhow::Error>)> {
+    let mut zones = Vec::new();
+    let mut errors = Vec::new();
+
+    let ls = std::fs::read_dir(sysfs_root).with_context(|| format!("could not list the content of {sysfs_root:?}"))?;
+    for entry in ls.into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let Some(id) = parse_zone_id(&path) else {
+            // cooling devices, etc.
+            continue;
+        };
+        match analyze_zone(id, &path) {
+            Ok(zone) => zones.push(zone),
+            Err(err) => errors.push(err.context(format!("failed to analyze thermal zone {path:?}"))),
+        }
+    }
+    zones.sort_by_key(|z| z.id);
+    Ok((zones, errors))
+}
+
+/// Parses `/sys/class/thermal/thermal_zone2` and extracts `2`.
+fn parse_zone_id(path: &Path) -> Option<u32> {
+    path.file_name()?.to_str()?.strip_prefix("thermal_zone")?.parse().ok()
+}
+
+fn analyze_zone(id: u32, path: &Path) -> anyhow::Result<ThermalZone> {
+    let zone_type = std::fs::read_to_string(path.join("type"))?.trim_ascii().to_owned();
+    let temp_path = path.join("temp");
+
+    // test the file, if it doesn't work, don't measure it in the future
+    let content = std::fs::read_to_string(&temp_path)?;
+    parse_temperature(&content)?;
+    Ok(ThermalZone {
+        id,
+        zone_type,
+        temp_path,
+    })
+}
+
+/// Parses the content of a `temp` file, in milli-degrees Celsius, and returns the temperature in degrees Celsius.
+fn parse_temperature(content: &str) -> anyhow::Result<f64> {
+    let millidegrees: i64 = content
+        .trim_ascii_end()
+        .parse()
+        .with_context(|| format!("invalid temperature '{content}'"))?;
+    Ok(millidegrees as f64 / 1000.0)
+}
+
+/// Measurement source that reads the temperature of the thermal zones of a Jetson device.
+pub struct JetsonThermalSource {
+    metric: TypedMetricId<f64>,
+    opened_zones: Vec<OpenedThermalZone>,
+}
+
+/// A thermal zone that has been "opened" for reading.
+struct OpenedThermalZone {
+    id: u32,
+    zone_type: String,
+    file: File,
+}
+
+impl JetsonThermalSource {
+    pub fn open_zones(zones: Vec<ThermalZone>, alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        if zones.is_empty() {
+            return Err(anyhow!(
+                "Cannot construct a JetsonThermalSource without any thermal zone."
+            ));
+        }
+        let metric = alumet.create_metric(
+            METRIC_TEMPERATURE,
+            Unit::DegreeCelsius,
+            "temperature of the thermal zone (see attributes for zone info)",
+        )?;
+        let opened_zones = zones
+            .into_iter()
+            .map(|z| {
+                let file = File::open(&z.temp_path)
+                    .with_context(|| format!("could not open virtual file {}", z.temp_path.display()))?;
+                Ok(OpenedThermalZone {
+                    id: z.id,
+                    zone_type: z.zone_type,
+                    file,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { metric, opened_zones })
+    }
+}
+
+impl alumet::pipeline::Source for JetsonThermalSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let mut content = String::with_capacity(8);
+        for zone in &mut self.opened_zones {
+            // read the file from the beginning
+            content.clear();
+            zone.file.rewind()?;
+            zone.file.read_to_string(&mut content)?;
+            let value = parse_temperature(&content)
+                .with_context(|| format!("failed to parse the temperature of thermal zone {}", zone.id))?;
+
+            measurements.push(
+                MeasurementPoint::new(
+                    timestamp,
+                    self.metric,
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("thermal_zone_id", AttributeValue::U64(zone.id.into()))
+                .with_attr("thermal_zone_type", AttributeValue::String(zone.zone_type.clone())),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    use super::{ThermalZone, detect_thermal_zones, parse_temperature};
+
+    #[test]
+    fn thermal_zones() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path().join("thermal");
+        let zones = [
+            ("thermal_zone0", "cpu-thermal", "48500"),
+            ("thermal_zone1", "gpu-thermal", "46250"),
+            ("thermal_zone10", "tj-thermal", "49000"),
+            ("thermal_zone5", "soc0-thermal", "-1500"),
+        ];
+        for (dir, zone_type, temp) in zones {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("type"), format!("{zone_type}\n")).unwrap();
+            std::fs::write(root.join(dir).join("temp"), format!("{temp}\n")).unwrap();
+        }
+        // disabled zone, the temperature is not available
+        std::fs::create_dir_all(root.join("thermal_zone3")).unwrap();
+        std::fs::write(root.join("thermal_zone3/type"), "cv0-thermal\n").unwrap();
+        std::fs::write(root.join("thermal_zone3/temp"), "").unwrap();
+        // not a thermal zone
+        std::fs::create_dir_all(root.join("cooling_device0")).unwrap();
+        std::fs::write(root.join("cooling_device0/type"), "pwm-fan\n").unwrap();
+
+        let (zones, errs) = detect_thermal_zones(&root).unwrap();
+        assert_eq!(errs.len(), 1, "the disabled zone should be reported");
+        let zone = |id: u32, zone_type: &str| ThermalZone {
+            id,
+            zone_type: zone_type.to_owned(),
+            temp_path: root.join(format!("thermal_zone{id}/temp")),
+        };
+        assert_eq!(
+            zones,
+            vec![
+                zone(0, "cpu-thermal"),
+                zone(1, "gpu-thermal"),
+                zone(5, "soc0-thermal"),
+                zone(10, "tj-thermal"),
+            ]
+        );
+    }
+
+    #[test]
+    fn temperature() {
+        assert_eq!(parse_temperature("48500\n").unwrap(), 48.5);
+        assert_eq!(parse_temperature("-1500").unwrap(), -1.5);
+        parse_temperature("").expect_err("should fail");
+    }
+}
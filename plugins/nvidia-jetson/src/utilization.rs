@@ -0,0 +1,365 @@
+//! Utilization metrics, equivalent to the ones reported by `tegrastats`.
+
+use std::{
+    fs::File,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::elements::error::PollError,
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::{Context, anyhow};
+use rustc_hash::FxHashMap;
+
+pub const SYSFS_ROOT: &str = "/sys";
+pub const PROCFS_ROOT: &str = "/proc";
+
+/// Files that provide the load of the GPU (GR3D), in per mille, relative to the sysfs root.
+/// The first one that exists is used.
+const GR3D_LOAD_FILES: [&str; 4] = [
+    "devices/gpu.0/load",
+    "devices/platform/gpu.0/load",
+    "devices/platform/17000000.ga10b/load",
+    "devices/platform/17000000.gv11b/load",
+];
+/// Average activity of the memory controller, in kHz, relative to the sysfs root.
+const EMC_ACTIVITY_FILE: &str = "kernel/actmon_avg_activity/mc_all";
+/// Frequency of the memory controller, in Hz, relative to the sysfs root.
+pub(crate) const EMC_RATE_FILE: &str = "kernel/debug/clk/emc/clk_rate";
+
+/// Detected sources of utilization data.
+#[derive(Debug, PartialEq)]
+pub struct UtilizationFiles {
+    /// GR3D (GPU) load, in per mille.
+    pub gr3d_load: Option<PathBuf>,
+    /// EMC activity (kHz) and rate (Hz).
+    pub emc: Option<(PathBuf, PathBuf)>,
+    /// Kernel statistics, for the CPU utilization.
+    pub proc_stat: PathBuf,
+    /// Memory information, for the RAM usage.
+    pub meminfo: PathBuf,
+}
+
+/// Finds the files that provide the utilization data.
+///
+/// The GR3D and EMC files are optional, because they depend on the model of the Jetson and on the permissions.
+pub fn detect_utilization_files(sysfs_root: &Path, procfs_root: &Path) -> anyhow::Result<UtilizationFiles> {
+    let gr3d_load = GR3D_LOAD_FILES
+        .iter()
+        .map(|f| sysfs_root.join(f))
+        .find(|path| path.exists());
+    let emc_activity = sysfs_root.join(EMC_ACTIVITY_FILE);
+    let emc_rate = sysfs_root.join(EMC_RATE_FILE);
+    let emc = (emc_activity.exists() && emc_rate.exists()).then_some((emc_activity, emc_rate));
+
+    let proc_stat = procfs_root.join("stat");
+    let meminfo = procfs_root.join("meminfo");
+    for path in [&proc_stat, &meminfo] {
+        if !path.exists() {
+            return Err(anyhow!("{path:?} does not exist"));
+        }
+    }
+    Ok(UtilizationFiles {
+        gr3d_load,
+        emc,
+        proc_stat,
+        meminfo,
+    })
+}
+
+/// Cumulated time spent by a CPU core, in clock ticks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CpuTimes {
+    busy: u64,
+    total: u64,
+}
+
+/// Parses the content of `/proc/stat` and returns the times of each CPU core.
+///
+/// The offline cores are not listed in `/proc/stat`.
+fn parse_proc_stat(content: &str) -> anyhow::Result<Vec<(u32, CpuTimes)>> {
+    let mut res = Vec::new();
+    for line in content.lines() {
+        let mut fields = line.split_ascii_whitespace();
+        let Some(core_id) = fields.next().and_then(|f| f.strip_prefix("cpu")) else {
+            continue;
+        };
+        if core_id.is_empty() {
+            // this is the line of the total of all the cores
+            continue;
+        }
+        let core_id: u32 = core_id.parse().with_context(|| format!("invalid line '{line}'"))?;
+        // user nice system idle iowait irq softirq steal (guest and guest_nice are included in user and nice)
+        let values = fields
+            .take(8)
+            .map(|f| f.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("invalid line '{line}'"))?;
+        if values.len() < 5 {
+            return Err(anyhow!("invalid line '{line}': not enough values"));
+        }
+        let total: u64 = values.iter().sum();
+        let idle = values[3] + values[4];
+        res.push((
+            core_id,
+            CpuTimes {
+                busy: total - idle,
+                total,
+            },
+        ));
+    }
+    Ok(res)
+}
+
+/// Parses the content of `/proc/meminfo` and returns the used memory, in bytes.
+///
+/// Like `tegrastats`, the used memory is `MemTotal - MemAvailable`.
+fn parse_meminfo(content: &str) -> anyhow::Result<u64> {
+    let field_kb = |name: &str| -> anyhow::Result<u64> {
+        let line = content
+            .lines()
+            .find(|l| l.starts_with(name))
+            .with_context(|| format!("{name} not found in meminfo"))?;
+        let value = line[name.len()..]
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .with_context(|| format!("invalid line '{line}'"))?;
+        Ok(value)
+    };
+    let total = field_kb("MemTotal:")?;
+    let available = field_kb("MemAvailable:")?;
+    Ok(total.saturating_sub(available) * 1024)
+}
+
+/// Computes the utilization of a CPU core between two measurements, in percentage.
+fn cpu_utilization(previous: CpuTimes, current: CpuTimes) -> Option<f64> {
+    let total = current.total.checked_sub(previous.total)?;
+    let busy = current.busy.checked_sub(previous.busy)?;
+    if total == 0 {
+        return None;
+    }
+    Some(100.0 * busy as f64 / total as f64)
+}
+
+/// Measurement source that reports the utilization of the GPU (GR3D), memory controller (EMC), CPU cores and RAM.
+pub struct JetsonUtilizationSource {
+    metrics: UtilizationMetrics,
+    gr3d_load: Option<File>,
+    emc: Option<(File, File)>,
+    proc_stat: File,
+    meminfo: File,
+    /// Times of the CPU cores at the previous measurement.
+    previous_cpu_times: FxHashMap<u32, CpuTimes>,
+    buf: String,
+}
+
+struct UtilizationMetrics {
+    gr3d_utilization: TypedMetricId<f64>,
+    emc_utilization: TypedMetricId<f64>,
+    cpu_utilization: TypedMetricId<f64>,
+    ram_used: TypedMetricId<u64>,
+}
+
+impl JetsonUtilizationSource {
+    pub fn open_files(files: UtilizationFiles, alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        let open = |path: &Path| File::open(path).with_context(|| format!("could not open file {}", path.display()));
+        let metrics = UtilizationMetrics {
+            gr3d_utilization: alumet.create_metric(
+                "gr3d_utilization",
+                Unit::Percent,
+                "utilization of the GPU (GR3D)",
+            )?,
+            emc_utilization: alumet.create_metric(
+                "emc_utilization",
+                Unit::Percent,
+                "utilization of the external memory controller (EMC)",
+            )?,
+            cpu_utilization: alumet.create_metric(
+                "cpu_core_utilization",
+                Unit::Percent,
+                "utilization of a CPU core since the previous measurement",
+            )?,
+            ram_used: alumet.create_metric("ram_used", Unit::Byte, "RAM used (shared by the CPU and the GPU)")?,
+        };
+        Ok(Self {
+            metrics,
+            // the optional files are often restricted to root: skip them instead of failing
+            gr3d_load: files.gr3d_load.as_deref().and_then(open_optional),
+            emc: files
+                .emc
+                .as_ref()
+                .and_then(|(activity, rate)| Some((open_optional(activity)?, open_optional(rate)?))),
+            proc_stat: open(&files.proc_stat)?,
+            meminfo: open(&files.meminfo)?,
+            previous_cpu_times: FxHashMap::default(),
+            buf: String::new(),
+        })
+    }
+}
+
+fn open_optional(path: &Path) -> Option<File> {
+    File::open(path)
+        .inspect_err(|e| log::warn!("could not open file {}, it will not be measured: {e}", path.display()))
+        .ok()
+}
+
+/// Reads a file from the beginning.
+fn read_file<'a>(file: &mut File, buf: &'a mut String) -> anyhow::Result<&'a str> {
+    buf.clear();
+    file.rewind()?;
+    file.read_to_string(buf)?;
+    Ok(buf.as_str())
+}
+
+fn read_u64(file: &mut File, buf: &mut String) -> anyhow::Result<u64> {
+    let content = read_file(file, buf)?;
+    let value = content
+        .trim_ascii_end()
+        .parse()
+        .with_context(|| format!("failed to parse {file:?}: '{content}'"))?;
+    Ok(value)
+}
+
+impl alumet::pipeline::Source for JetsonUtilizationSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let buf = &mut self.buf;
+        let consumer = ResourceConsumer::LocalMachine;
+
+        if let Some(file) = &mut self.gr3d_load {
+            // the load is in per mille
+            let load = read_u64(file, buf)?;
+            measurements.push(MeasurementPoint::new(
+                timestamp,
+                self.metrics.gr3d_utilization,
+                Resource::LocalMachine,
+                consumer.clone(),
+                load as f64 / 10.0,
+            ));
+        }
+
+        if let Some((activity_file, rate_file)) = &mut self.emc {
+            let activity_khz = read_u64(activity_file, buf)?;
+            let rate_hz = read_u64(rate_file, buf)?;
+            if rate_hz > 0 {
+                measurements.push(MeasurementPoint::new(
+                    timestamp,
+                    self.metrics.emc_utilization,
+                    Resource::LocalMachine,
+                    consumer.clone(),
+                    100.0 * (activity_khz * 1000) as f64 / rate_hz as f64,
+                ));
+            }
+        }
+
+        let cpu_times = parse_proc_stat(read_file(&mut self.proc_stat, buf)?)?;
+        let mut current_cpu_times = FxHashMap::default();
+        for (core_id, times) in cpu_times {
+            let utilization = self
+                .previous_cpu_times
+                .get(&core_id)
+                .and_then(|previous| cpu_utilization(*previous, times));
+            if let Some(utilization) = utilization {
+                measurements.push(MeasurementPoint::new(
+                    timestamp,
+                    self.metrics.cpu_utilization,
+                    Resource::CpuCore { id: core_id },
+                    consumer.clone(),
+                    utilization,
+                ));
+            }
+            current_cpu_times.insert(core_id, times);
+        }
+        self.previous_cpu_times = current_cpu_times;
+
+        let ram_used = parse_meminfo(read_file(&mut self.meminfo, buf)?)?;
+        measurements.push(MeasurementPoint::new(
+            timestamp,
+            self.metrics.ram_used,
+            Resource::LocalMachine,
+            consumer,
+            ram_used,
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const PROC_STAT: &str = "cpu  1000 0 500 8000 100 0 0 0 0 0
+cpu0 400 0 200 3900 50 0 0 0 0 0
+cpu1 600 0 300 4100 50 0 0 0 0 0
+intr 123456
+ctxt 789
+";
+
+    #[test]
+    fn proc_stat() {
+        let times = parse_proc_stat(PROC_STAT).unwrap();
+        assert_eq!(
+            times,
+            vec![
+                (0, CpuTimes { busy: 600, total: 4550 }),
+                (1, CpuTimes { busy: 900, total: 5050 }),
+            ]
+        );
+        parse_proc_stat("cpu0 1 2\n").expect_err("not enough values");
+    }
+
+    #[test]
+    fn utilization_of_cpu() {
+        let previous = CpuTimes { busy: 600, total: 4550 };
+        let current = CpuTimes { busy: 650, total: 4750 };
+        assert_eq!(cpu_utilization(previous, current), Some(25.0));
+        assert_eq!(cpu_utilization(previous, previous), None);
+    }
+
+    #[test]
+    fn meminfo() {
+        let content = "MemTotal:       31990008 kB
+MemFree:        24859236 kB
+MemAvailable:   27643144 kB
+Buffers:           40976 kB
+";
+        assert_eq!(parse_meminfo(content).unwrap(), (31990008 - 27643144) * 1024);
+        parse_meminfo("MemTotal: 12 kB\n").expect_err("MemAvailable is missing");
+    }
+
+    #[test]
+    fn detect_files() {
+        let tmp = tempdir().unwrap();
+        let sysfs = tmp.path().join("sys");
+        let procfs = tmp.path().join("proc");
+        std::fs::create_dir_all(sysfs.join("devices/platform/17000000.ga10b")).unwrap();
+        std::fs::write(sysfs.join("devices/platform/17000000.ga10b/load"), "125\n").unwrap();
+        std::fs::create_dir_all(&procfs).unwrap();
+
+        // no procfs files
+        detect_utilization_files(&sysfs, &procfs).expect_err("should fail");
+
+        std::fs::write(procfs.join("stat"), PROC_STAT).unwrap();
+        std::fs::write(procfs.join("meminfo"), "").unwrap();
+        let files = detect_utilization_files(&sysfs, &procfs).unwrap();
+        assert_eq!(
+            files,
+            UtilizationFiles {
+                gr3d_load: Some(sysfs.join("devices/platform/17000000.ga10b/load")),
+                emc: None,
+                proc_stat: procfs.join("stat"),
+                meminfo: procfs.join("meminfo"),
+            }
+        );
+    }
+}
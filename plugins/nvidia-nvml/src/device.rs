@@ -3,7 +3,8 @@ use nvml_wrapper::{Device, Nvml};
 use nvml_wrapper_sys::bindings::nvmlDevice_t;
 use std::sync::Arc;
 
-use super::features::OptionalFeatures;
+use super::features::{MigFeatures, OptionalFeatures};
+use super::nvml_ext::RawNvml;
 
 /// Detected NVML devices.
 pub struct NvmlDevices {
@@ -21,6 +22,22 @@ pub struct ManagedDevice {
     pub features: OptionalFeatures,
     /// PCI bus ID of the device.
     pub bus_id: String,
+    /// The MIG (Multi-Instance GPU) devices that partition this device, if MIG is enabled.
+    pub mig_devices: Vec<MigDevice>,
+    /// Access to the NVML functions that `lib` does not provide, if the library could be loaded.
+    pub raw_nvml: Option<Arc<RawNvml>>,
+}
+
+/// A MIG (Multi-Instance GPU) device, that is, a GPU instance of a parent [`ManagedDevice`].
+pub struct MigDevice {
+    /// A pointer to the MIG device, as returned by NVML.
+    pub handle: nvmlDevice_t,
+    /// Status of the optional features: which feature is available on this MIG device?
+    pub features: MigFeatures,
+    /// UUID of the MIG device, for instance `MIG-1a2b3c4d-...`.
+    pub uuid: String,
+    /// Id of the GPU instance in the parent device.
+    pub gpu_instance_id: u32,
 }
 
 /// Statistics about the device detection.
@@ -39,6 +56,11 @@ impl NvmlDevices {
             "NVML initialization failed, please check your driver (do you have a dekstop/server NVidia GPU?)",
         )?);
 
+        let raw_nvml = RawNvml::load()
+            .inspect_err(|e| log::warn!("MIG devices and NVLink links will not be measured: {e:?}"))
+            .ok()
+            .map(Arc::new);
+
         let count = nvml.device_count().context("could not get device count")?;
         let mut devices = Vec::with_capacity(count as usize);
         for i in 0..count {
@@ -55,11 +77,20 @@ impl NvmlDevices {
                         let bus_id = pci_info
                             .with_context(|| format!("failed to get the bus ID of device {i}"))?
                             .bus_id;
+                        let mig_devices = match &raw_nvml {
+                            Some(raw_nvml) => detect_mig_devices(raw_nvml, &nvml, handle).unwrap_or_else(|e| {
+                                log::warn!("Failed to detect the MIG devices of GPU {bus_id}, only the GPU will be measured: {e:?}");
+                                Vec::new()
+                            }),
+                            None => Vec::new(),
+                        };
                         let d = ManagedDevice {
                             lib,
                             handle,
                             features,
                             bus_id,
+                            mig_devices,
+                            raw_nvml: raw_nvml.clone(),
                         };
                         Some(d)
                     } else {
@@ -98,10 +129,33 @@ impl NvmlDevices {
     }
 }
 
+/// Detects the MIG devices of a parent device, if MIG is enabled on it.
+fn detect_mig_devices(raw_nvml: &RawNvml, nvml: &Nvml, parent: nvmlDevice_t) -> anyhow::Result<Vec<MigDevice>> {
+    if !raw_nvml.is_mig_enabled(parent)? {
+        return Ok(Vec::new());
+    }
+    let mut res = Vec::new();
+    for handle in raw_nvml.mig_devices(parent)? {
+        let device = unsafe { Device::new(handle, nvml) };
+        res.push(MigDevice {
+            handle,
+            features: MigFeatures::detect_on(&device)?,
+            uuid: device.uuid()?,
+            gpu_instance_id: raw_nvml.gpu_instance_id(handle)?,
+        });
+    }
+    Ok(res)
+}
+
 impl ManagedDevice {
     pub fn as_wrapper(&self) -> Device<'_> {
         unsafe { Device::new(self.handle, &self.lib) }
     }
+
+    /// Returns a wrapper around one of the MIG devices of this device.
+    pub fn mig_wrapper(&self, mig_device: &MigDevice) -> Device<'_> {
+        unsafe { Device::new(mig_device.handle, &self.lib) }
+    }
 }
 
 #[cfg(test)]
@@ -141,6 +195,8 @@ mod tests {
             handle,
             features: OptionalFeatures::detect_on(&device).expect("Detect features"),
             bus_id,
+            mig_devices: Vec::new(),
+            raw_nvml: None,
         };
 
         let wrapped_device = managed_device.as_wrapper();
@@ -1,4 +1,9 @@
-use nvml_wrapper::{Device, enum_wrappers::device::TemperatureSensor, error::NvmlError};
+use nvml_wrapper::{
+    Device,
+    enum_wrappers::device::{PcieUtilCounter, TemperatureSensor},
+    error::NvmlError,
+};
+use nvml_wrapper_sys::bindings::NVML_NVLINK_MAX_LINKS;
 use std::fmt::Display;
 
 use crate::nvml_ext::DeviceExt;
@@ -22,6 +27,8 @@ pub struct OptionalFeatures {
     pub temperature_gpu: bool,
     /// GPU rate utilization.
     pub major_utilization: bool,
+    /// GPU memory usage.
+    pub memory_info: bool,
     /// GPU video decoding property.
     pub decoder_utilization: bool,
     /// GPU video encoding property.
@@ -32,6 +39,10 @@ pub struct OptionalFeatures {
     pub running_compute_processes: AvailableVersion,
     /// Relevant currently running graphical processes data.
     pub running_graphics_processes: AvailableVersion,
+    /// PCIe throughput.
+    pub pcie_throughput: bool,
+    /// The NVLink links that are active.
+    pub nvlink_links: Vec<u32>,
 }
 
 impl OptionalFeatures {
@@ -42,11 +53,14 @@ impl OptionalFeatures {
             instant_power: is_supported(device.power_usage())?,
             temperature_gpu: is_supported(device.temperature(TemperatureSensor::Gpu))?,
             major_utilization: is_supported(device.utilization_rates())?,
+            memory_info: is_supported(device.memory_info())?,
             decoder_utilization: is_supported(device.decoder_utilization())?,
             encoder_utilization: is_supported(device.encoder_utilization())?,
             process_utilization_stats: is_supported(device.fixed_process_utilization_stats(0))?,
             running_compute_processes: check_running_compute_processes(device)?,
             running_graphics_processes: check_running_graphics_processes(device)?,
+            pcie_throughput: is_supported(device.pcie_throughput(PcieUtilCounter::Send))?,
+            nvlink_links: active_nvlink_links(device)?,
         })
     }
 
@@ -58,6 +72,7 @@ impl OptionalFeatures {
         self.total_energy_consumption
             || self.instant_power
             || self.major_utilization
+            || self.memory_info
             || self.decoder_utilization
             || self.encoder_utilization
             || self.temperature_gpu
@@ -78,6 +93,9 @@ impl Display for OptionalFeatures {
         if self.major_utilization {
             available.push("major_utilization");
         }
+        if self.memory_info {
+            available.push("memory_info");
+        }
         if self.decoder_utilization {
             available.push("decoder_utilization");
         }
@@ -100,6 +118,51 @@ impl Display for OptionalFeatures {
             AvailableVersion::V2 => available.push("running_graphics_processes(v2)"),
             AvailableVersion::None => (),
         };
+        if self.pcie_throughput {
+            available.push("pcie_throughput");
+        }
+        let nvlink = format!("nvlink({} links)", self.nvlink_links.len());
+        if !self.nvlink_links.is_empty() {
+            available.push(&nvlink);
+        }
+        write!(f, "{}", available.join(", "))
+    }
+}
+
+/// Indicates which features are available on a given MIG (Multi-Instance GPU) device.
+#[derive(Debug)]
+pub struct MigFeatures {
+    /// GPU instance rate utilization.
+    pub major_utilization: bool,
+    /// GPU instance memory usage.
+    pub memory_info: bool,
+    /// Relevant currently running computing processes data.
+    pub running_compute_processes: bool,
+}
+
+impl MigFeatures {
+    /// Detect the features available on the given MIG device.
+    pub fn detect_on(device: &Device) -> Result<Self, NvmlError> {
+        Ok(Self {
+            major_utilization: is_supported(device.utilization_rates())?,
+            memory_info: is_supported(device.memory_info())?,
+            running_compute_processes: is_supported(device.running_compute_processes_count())?,
+        })
+    }
+}
+
+impl Display for MigFeatures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut available = Vec::new();
+        if self.major_utilization {
+            available.push("major_utilization");
+        }
+        if self.memory_info {
+            available.push("memory_info");
+        }
+        if self.running_compute_processes {
+            available.push("running_compute_processes");
+        }
         write!(f, "{}", available.join(", "))
     }
 }
@@ -128,6 +191,21 @@ fn check_running_graphics_processes(device: &Device) -> Result<AvailableVersion,
     }
 }
 
+/// Lists the NVLink links that are active on this NVML device.
+fn active_nvlink_links(device: &Device) -> Result<Vec<u32>, NvmlError> {
+    let mut links = Vec::new();
+    for link in 0..NVML_NVLINK_MAX_LINKS {
+        match device.link_wrapper_for(link).is_active() {
+            Ok(true) => links.push(link),
+            Ok(false) => (),
+            // the device does not support NVLink, or has less links than the maximum
+            Err(NvmlError::NotSupported | NvmlError::InvalidArg) => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(links)
+}
+
 /// Checks if a feature is supported by the available GPU by inspecting the return type of an NVML function.
 ///
 /// # Example
@@ -156,16 +234,19 @@ mod tests {
             total_energy_consumption: true,
             instant_power: true,
             major_utilization: true,
+            memory_info: true,
             decoder_utilization: true,
             encoder_utilization: true,
             process_utilization_stats: true,
             temperature_gpu: true,
             running_compute_processes: AvailableVersion::Latest,
             running_graphics_processes: AvailableVersion::Latest,
+            pcie_throughput: false,
+            nvlink_links: Vec::new(),
         };
         assert_eq!(
             format!("{}", features),
-            "total_energy_consumption, instant_power, major_utilization, decoder_utilization, encoder_utilization, process_utilization_stats, temperature_gpu, running_compute_processes(latest), running_graphics_processes(latest)"
+            "total_energy_consumption, instant_power, major_utilization, memory_info, decoder_utilization, encoder_utilization, process_utilization_stats, temperature_gpu, running_compute_processes(latest), running_graphics_processes(latest)"
         );
     }
 
@@ -177,12 +258,15 @@ mod tests {
             total_energy_consumption: false,
             instant_power: false,
             major_utilization: false,
+            memory_info: false,
             decoder_utilization: true,
             encoder_utilization: true,
             process_utilization_stats: false,
             temperature_gpu: true,
             running_compute_processes: AvailableVersion::None,
             running_graphics_processes: AvailableVersion::None,
+            pcie_throughput: false,
+            nvlink_links: Vec::new(),
         };
         assert_eq!(
             format!("{}", features),
@@ -198,12 +282,15 @@ mod tests {
             total_energy_consumption: false,
             instant_power: false,
             major_utilization: false,
+            memory_info: false,
             decoder_utilization: false,
             encoder_utilization: false,
             process_utilization_stats: false,
             temperature_gpu: false,
             running_compute_processes: AvailableVersion::V2,
             running_graphics_processes: AvailableVersion::V2,
+            pcie_throughput: false,
+            nvlink_links: Vec::new(),
         };
         assert_eq!(
             format!("{}", features),
@@ -211,6 +298,37 @@ mod tests {
         );
     }
 
+    // Test `fmt` function with the interconnect features
+    #[test]
+    fn test_fmt_with_interconnect() {
+        let features = OptionalFeatures {
+            total_energy_consumption: false,
+            instant_power: false,
+            major_utilization: false,
+            memory_info: false,
+            decoder_utilization: false,
+            encoder_utilization: false,
+            process_utilization_stats: false,
+            temperature_gpu: false,
+            running_compute_processes: AvailableVersion::None,
+            running_graphics_processes: AvailableVersion::None,
+            pcie_throughput: true,
+            nvlink_links: vec![0, 1, 3],
+        };
+        assert_eq!(format!("{}", features), "pcie_throughput, nvlink(3 links)");
+    }
+
+    // Test `fmt` function of the MIG features
+    #[test]
+    fn test_fmt_mig_features() {
+        let features = MigFeatures {
+            major_utilization: false,
+            memory_info: true,
+            running_compute_processes: true,
+        };
+        assert_eq!(format!("{}", features), "memory_info, running_compute_processes");
+    }
+
     // Test `has_any` function to check existence of a real device
     #[ignore = "NO GPU"]
     #[test]
@@ -229,12 +347,15 @@ mod tests {
             total_energy_consumption: false,
             instant_power: false,
             major_utilization: false,
+            memory_info: false,
             decoder_utilization: false,
             encoder_utilization: false,
             process_utilization_stats: false,
             temperature_gpu: false,
             running_compute_processes: AvailableVersion::None,
             running_graphics_processes: AvailableVersion::None,
+            pcie_throughput: false,
+            nvlink_links: Vec::new(),
         };
         assert!(!features.has_any());
     }
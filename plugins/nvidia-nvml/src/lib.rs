@@ -71,10 +71,19 @@ impl AlumetPlugin for NvmlPlugin {
                     device_name,
                     device.features
                 );
+                for mig_device in &device.mig_devices {
+                    log::info!(
+                        "Found MIG device {} (GPU instance {} of {}) with features: {}",
+                        mig_device.uuid,
+                        mig_device.gpu_instance_id,
+                        device.bus_id,
+                        mig_device.features
+                    );
+                }
             }
         }
         let source_provider = match self.config.mode {
-            Mode::Full => SourceProvider::Full(FullMetrics::new(alumet)?),
+            Mode::Full => SourceProvider::Full(FullMetrics::new(alumet, self.config.interconnect_metrics)?),
             Mode::Minimal => SourceProvider::Minimal(MinimalMetrics::new(alumet)?),
         };
 
@@ -122,6 +131,10 @@ struct Config {
     ///
     /// On some GPUs, the "full" mode is too slow for high frequencies (100 Hz can be hard to reach in full mode).
     mode: Mode,
+
+    /// In "full" mode, also measure the NVLink and PCIe throughput (if supported by the GPU).
+    #[serde(default)]
+    interconnect_metrics: bool,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -144,6 +157,7 @@ impl Default for Config {
             flush_interval: Duration::from_secs(5),
             skip_failed_devices: true,
             mode: Mode::Full,
+            interconnect_metrics: false,
         }
     }
 }
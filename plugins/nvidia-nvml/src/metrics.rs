@@ -17,6 +17,8 @@ pub struct FullMetrics {
     pub major_utilization_gpu: TypedMetricId<u64>,
     /// GPU memory utilization in percentage
     pub major_utilization_memory: TypedMetricId<u64>,
+    /// GPU memory used in bytes.
+    pub memory_used: TypedMetricId<u64>,
     /// GPU video decoding property in percentage.
     pub decoder_utilization: TypedMetricId<u64>,
     /// Get the current utilization and sampling size for the decoder in μs.
@@ -31,6 +33,23 @@ pub struct FullMetrics {
     pub running_compute_processes: TypedMetricId<u64>,
     /// Relevant currently running graphical processes data in percentage.
     pub running_graphics_processes: TypedMetricId<u64>,
+    /// GPU memory used by a process in bytes.
+    pub process_memory_used: TypedMetricId<u64>,
+    /// Optional NVLink and PCIe metrics.
+    pub interconnect: Option<InterconnectMetrics>,
+}
+
+/// Contains the ids of the NVLink and PCIe metrics.
+#[derive(Clone)]
+pub struct InterconnectMetrics {
+    /// Data transmitted through a NVLink link since the previous measurement, in bytes.
+    pub nvlink_transmitted_data: TypedMetricId<u64>,
+    /// Data received through a NVLink link since the previous measurement, in bytes.
+    pub nvlink_received_data: TypedMetricId<u64>,
+    /// PCIe transmit throughput in KB/s.
+    pub pcie_tx_throughput: TypedMetricId<u64>,
+    /// PCIe receive throughput in KB/s.
+    pub pcie_rx_throughput: TypedMetricId<u64>,
 }
 
 #[derive(Clone)]
@@ -43,7 +62,13 @@ pub struct MinimalMetrics {
 
 impl FullMetrics {
     /// Creates new Alumet metrics for NVML measurements and stores their ids in a `Metrics` structure.
-    pub fn new(alumet: &mut AlumetPluginStart) -> Result<Self, MetricCreationError> {
+    ///
+    /// The NVLink and PCIe metrics are only created if `interconnect` is true.
+    pub fn new(alumet: &mut AlumetPluginStart, interconnect: bool) -> Result<Self, MetricCreationError> {
+        let interconnect = match interconnect {
+            true => Some(InterconnectMetrics::new(alumet)?),
+            false => None,
+        };
         Ok(Self {
             total_energy_consumption: alumet.create_metric(
                 "nvml_energy_consumption",
@@ -65,6 +90,7 @@ impl FullMetrics {
                 Unit::Percent,
                 "GPU rate utilization",
             )?,
+            memory_used: alumet.create_metric("nvml_memory_used", Unit::Byte, "GPU memory used")?,
             decoder_sampling_period_us: alumet.create_metric(
                 "nvml_decoder_sampling_period",
                 PrefixedUnit::micro(Unit::Second),
@@ -107,6 +133,43 @@ impl FullMetrics {
                 Unit::Percent,
                 "Utilization of the GPU streaming multiprocessors by the process",
             )?,
+            process_memory_used: alumet.create_metric(
+                "nvml_process_memory_used",
+                Unit::Byte,
+                "GPU memory used by the process",
+            )?,
+            interconnect,
+        })
+    }
+}
+
+impl InterconnectMetrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> Result<Self, MetricCreationError> {
+        let bytes_per_second = Unit::Custom {
+            unique_name: String::from("By/s"),
+            display_name: String::from("B/s"),
+        };
+        Ok(Self {
+            nvlink_transmitted_data: alumet.create_metric(
+                "nvml_nvlink_transmitted_data",
+                Unit::Byte,
+                "Data transmitted through the NVLink link since the previous measurement",
+            )?,
+            nvlink_received_data: alumet.create_metric(
+                "nvml_nvlink_received_data",
+                Unit::Byte,
+                "Data received through the NVLink link since the previous measurement",
+            )?,
+            pcie_tx_throughput: alumet.create_metric(
+                "nvml_pcie_tx_throughput",
+                PrefixedUnit::kilo(bytes_per_second.clone()),
+                "PCIe transmit throughput of the GPU",
+            )?,
+            pcie_rx_throughput: alumet.create_metric(
+                "nvml_pcie_rx_throughput",
+                PrefixedUnit::kilo(bytes_per_second),
+                "PCIe receive throughput of the GPU",
+            )?,
         })
     }
 }
@@ -1,6 +1,19 @@
-//! "Extends" the nvml_wrapper crate to fix some functions.
+//! "Extends" the nvml_wrapper crate to fix some functions, and to provide some functions that it lacks.
 
-use nvml_wrapper::{Device, error::NvmlError, struct_wrappers::device::ProcessUtilizationSample};
+use anyhow::Context;
+use nvml_wrapper::{
+    Device,
+    error::{NvmlError, nvml_sym, nvml_try},
+    struct_wrappers::device::ProcessUtilizationSample,
+};
+use nvml_wrapper_sys::bindings::{
+    NVML_DEVICE_MIG_ENABLE, NvmlLib,
+    field_id::{NVML_FI_DEV_NVLINK_THROUGHPUT_DATA_RX, NVML_FI_DEV_NVLINK_THROUGHPUT_DATA_TX},
+    nvmlDevice_t, nvmlFieldValue_t,
+};
+
+/// Name of the NVML library, as loaded by `nvml_wrapper`.
+const NVML_LIB_NAME: &str = "libnvidia-ml.so";
 
 /// Extension trait for NVML `Device`.
 pub trait DeviceExt {
@@ -28,3 +41,101 @@ impl DeviceExt for Device<'_> {
         }
     }
 }
+
+/// The NVML functions that are not provided by `nvml_wrapper`: MIG (Multi-Instance GPU) and per-link NVLink counters.
+pub struct RawNvml {
+    lib: NvmlLib,
+}
+
+// SAFETY: NVML is thread-safe according to its documentation.
+unsafe impl Send for RawNvml {}
+unsafe impl Sync for RawNvml {}
+
+impl RawNvml {
+    /// Loads the NVML library a second time, to access its raw functions.
+    ///
+    /// The library must have been initialized with [`nvml_wrapper::Nvml::init`] before calling the functions.
+    pub fn load() -> anyhow::Result<Self> {
+        let lib = unsafe { NvmlLib::new(NVML_LIB_NAME) }.with_context(|| format!("failed to load {NVML_LIB_NAME}"))?;
+        Ok(Self { lib })
+    }
+
+    /// Returns `true` if the MIG mode is currently enabled on the device.
+    pub fn is_mig_enabled(&self, device: nvmlDevice_t) -> Result<bool, NvmlError> {
+        let sym = nvml_sym(self.lib.nvmlDeviceGetMigMode.as_ref())?;
+        let mut current_mode = 0;
+        let mut pending_mode = 0;
+        match nvml_try(unsafe { sym(device, &mut current_mode, &mut pending_mode) }) {
+            Ok(()) => Ok(current_mode == NVML_DEVICE_MIG_ENABLE),
+            // the device does not support MIG at all
+            Err(NvmlError::NotSupported) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the handles of the MIG devices that exist on the parent `device`.
+    pub fn mig_devices(&self, device: nvmlDevice_t) -> Result<Vec<nvmlDevice_t>, NvmlError> {
+        let max_count_sym = nvml_sym(self.lib.nvmlDeviceGetMaxMigDeviceCount.as_ref())?;
+        let by_index_sym = nvml_sym(self.lib.nvmlDeviceGetMigDeviceHandleByIndex.as_ref())?;
+
+        let mut max_count = 0;
+        nvml_try(unsafe { max_count_sym(device, &mut max_count) })?;
+
+        let mut mig_devices = Vec::new();
+        for i in 0..max_count {
+            let mut mig_device = std::ptr::null_mut();
+            match nvml_try(unsafe { by_index_sym(device, i, &mut mig_device) }) {
+                Ok(()) => mig_devices.push(mig_device),
+                // no MIG device at this index
+                Err(NvmlError::NotFound) => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(mig_devices)
+    }
+
+    /// Returns the id of the GPU instance of a MIG device.
+    pub fn gpu_instance_id(&self, mig_device: nvmlDevice_t) -> Result<u32, NvmlError> {
+        let sym = nvml_sym(self.lib.nvmlDeviceGetGpuInstanceId.as_ref())?;
+        let mut id = 0;
+        nvml_try(unsafe { sym(mig_device, &mut id) })?;
+        Ok(id)
+    }
+
+    /// Reads the data counters of some NVLink links, in KiB.
+    ///
+    /// Returns the `(transmitted, received)` data of each link, in the same order as `links`.
+    pub fn nvlink_throughput(&self, device: nvmlDevice_t, links: &[u32]) -> Result<Vec<(u64, u64)>, NvmlError> {
+        let sym = nvml_sym(self.lib.nvmlDeviceGetFieldValues.as_ref())?;
+        let mut values: Vec<nvmlFieldValue_t> = links
+            .iter()
+            .flat_map(|&link| {
+                [
+                    field_request(NVML_FI_DEV_NVLINK_THROUGHPUT_DATA_TX, link),
+                    field_request(NVML_FI_DEV_NVLINK_THROUGHPUT_DATA_RX, link),
+                ]
+            })
+            .collect();
+        nvml_try(unsafe { sym(device, values.len() as i32, values.as_mut_ptr()) })?;
+        values
+            .chunks_exact(2)
+            .map(|tx_rx| Ok((field_value_u64(&tx_rx[0])?, field_value_u64(&tx_rx[1])?)))
+            .collect()
+    }
+}
+
+/// Prepares the request of a field value, for `nvmlDeviceGetFieldValues`.
+fn field_request(field_id: u32, scope_id: u32) -> nvmlFieldValue_t {
+    // SAFETY: nvmlFieldValue_t is a plain C struct, for which zero is a valid value.
+    let mut value: nvmlFieldValue_t = unsafe { std::mem::zeroed() };
+    value.fieldId = field_id;
+    value.scopeId = scope_id;
+    value
+}
+
+/// Extracts an unsigned integer from a field value returned by `nvmlDeviceGetFieldValues`.
+fn field_value_u64(value: &nvmlFieldValue_t) -> Result<u64, NvmlError> {
+    nvml_try(value.nvmlReturn)?;
+    // SAFETY: the NVLink throughput fields are of type unsigned long long
+    Ok(unsafe { value.value.ullVal })
+}
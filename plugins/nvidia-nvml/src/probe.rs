@@ -1,6 +1,12 @@
 use anyhow::{Context, anyhow};
-use nvml_wrapper::{enum_wrappers::device::TemperatureSensor, error::NvmlError};
-use std::time::SystemTime;
+use nvml_wrapper::{
+    Device,
+    enum_wrappers::device::{PcieUtilCounter, TemperatureSensor},
+    enums::device::UsedGpuMemory,
+    error::NvmlError,
+    struct_wrappers::device::ProcessInfo,
+};
+use std::{collections::BTreeMap, time::SystemTime};
 
 use alumet::{
     measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
@@ -32,6 +38,11 @@ pub struct FullSource {
     metrics: FullMetrics,
     /// Alumet resource ID.
     resource: Resource,
+    /// Alumet resource IDs of the MIG devices, in the same order as `device.mig_devices`.
+    mig_resources: Vec<Resource>,
+    /// Internal state to compute the difference between two increments of the NVLink `(tx, rx)` counters,
+    /// in the same order as `device.features.nvlink_links`.
+    nvlink_counters: Vec<(CounterDiff, CounterDiff)>,
 
     /// Last poll timestamp
     last_poll_timestamp: Option<Timestamp>,
@@ -55,11 +66,29 @@ unsafe impl Send for MinimalSource {}
 impl FullSource {
     pub fn new(device: ManagedDevice, metrics: FullMetrics) -> Result<Self, NvmlError> {
         let bus_id = std::borrow::Cow::Owned(device.bus_id.clone());
+        let mig_resources = device
+            .mig_devices
+            .iter()
+            .map(|mig| Resource::custom("gpu_instance", mig.uuid.clone()))
+            .collect();
+        let nvlink_counters = device
+            .features
+            .nvlink_links
+            .iter()
+            .map(|_| {
+                (
+                    CounterDiff::with_max_value(u64::MAX),
+                    CounterDiff::with_max_value(u64::MAX),
+                )
+            })
+            .collect();
         Ok(FullSource {
             energy_counter: CounterDiff::with_max_value(u64::MAX),
             device,
             metrics,
             resource: Resource::Gpu { bus_id },
+            mig_resources,
+            nvlink_counters,
             last_poll_timestamp: None,
         })
     }
@@ -132,6 +161,17 @@ impl Source for FullSource {
             ));
         }
 
+        // Get the memory used in bytes
+        if features.memory_info {
+            measurements.push(MeasurementPoint::new(
+                timestamp,
+                self.metrics.memory_used,
+                self.resource.clone(),
+                consumer.clone(),
+                device.memory_info()?.used,
+            ));
+        }
+
         // Get the current utilization and sampling size in μs for the decoder
         if features.decoder_utilization {
             let u = device.decoder_utilization()?;
@@ -170,33 +210,37 @@ impl Source for FullSource {
             ));
         }
 
-        let n_compute_processes = match features.running_compute_processes {
-            AvailableVersion::Latest => Some(device.running_compute_processes_count()?),
-            AvailableVersion::V2 => Some(device.running_compute_processes_count_v2()?),
-            AvailableVersion::None => None,
-        };
-        if let Some(n) = n_compute_processes {
+        let compute_processes = running_compute_processes(&device, &features.running_compute_processes)?;
+        if let Some(processes) = &compute_processes {
             measurements.push(MeasurementPoint::new(
                 timestamp,
                 self.metrics.running_compute_processes,
                 self.resource.clone(),
                 consumer.clone(),
-                n as u64,
+                processes.len() as u64,
             ));
         }
 
-        let n_graphic_processes = match features.running_graphics_processes {
-            AvailableVersion::Latest => Some(device.running_graphics_processes_count()?),
-            AvailableVersion::V2 => Some(device.running_graphics_processes_count_v2()?),
-            AvailableVersion::None => None,
-        };
-        if let Some(n) = n_graphic_processes {
+        let graphics_processes = running_graphics_processes(&device, &features.running_graphics_processes)?;
+        if let Some(processes) = &graphics_processes {
             measurements.push(MeasurementPoint::new(
                 timestamp,
                 self.metrics.running_graphics_processes,
                 self.resource.clone(),
                 consumer.clone(),
-                n as u64,
+                processes.len() as u64,
+            ));
+        }
+
+        // Get the memory used by each process, in bytes
+        let all_processes = compute_processes.iter().chain(graphics_processes.iter()).flatten();
+        for (pid, used_bytes) in memory_per_process(all_processes) {
+            measurements.push(MeasurementPoint::new(
+                timestamp,
+                self.metrics.process_memory_used,
+                self.resource.clone(),
+                ResourceConsumer::Process { pid },
+                used_bytes,
             ));
         }
 
@@ -251,10 +295,128 @@ impl Source for FullSource {
             self.last_poll_timestamp = Some(timestamp);
         }
 
+        // Collection of the interconnect measurements, if enabled
+        if let Some(metrics) = &self.metrics.interconnect {
+            // Get the PCIe throughput in KB/s, sampled over 20ms
+            if features.pcie_throughput {
+                measurements.push(MeasurementPoint::new(
+                    timestamp,
+                    metrics.pcie_tx_throughput,
+                    self.resource.clone(),
+                    consumer.clone(),
+                    device.pcie_throughput(PcieUtilCounter::Send)? as u64,
+                ));
+                measurements.push(MeasurementPoint::new(
+                    timestamp,
+                    metrics.pcie_rx_throughput,
+                    self.resource.clone(),
+                    consumer.clone(),
+                    device.pcie_throughput(PcieUtilCounter::Receive)? as u64,
+                ));
+            }
+
+            // Get the data transmitted and received through each NVLink link, in bytes
+            if let Some(raw_nvml) = &self.device.raw_nvml
+                && !features.nvlink_links.is_empty()
+            {
+                let links = &features.nvlink_links;
+                let throughput = raw_nvml.nvlink_throughput(self.device.handle, links)?;
+                for ((link, (tx_kib, rx_kib)), (tx_counter, rx_counter)) in
+                    links.iter().zip(throughput).zip(&mut self.nvlink_counters)
+                {
+                    let diffs = [
+                        (metrics.nvlink_transmitted_data, tx_counter.update(tx_kib).difference()),
+                        (metrics.nvlink_received_data, rx_counter.update(rx_kib).difference()),
+                    ];
+                    for (metric, diff) in diffs {
+                        if let Some(kib) = diff {
+                            measurements.push(
+                                MeasurementPoint::new(
+                                    timestamp,
+                                    metric,
+                                    self.resource.clone(),
+                                    consumer.clone(),
+                                    kib * 1024,
+                                )
+                                .with_attr("link", *link as u64),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        // Collection of the MIG devices measurements, with the MIG device as the resource
+        for (mig_device, resource) in self.device.mig_devices.iter().zip(&self.mig_resources) {
+            let mig_features = &mig_device.features;
+            let mig = self.device.mig_wrapper(mig_device);
+            let mut mig_points = Vec::new();
+
+            if mig_features.major_utilization {
+                let u = mig.utilization_rates()?;
+                mig_points.push((self.metrics.major_utilization_gpu, u.gpu as u64));
+                mig_points.push((self.metrics.major_utilization_memory, u.memory as u64));
+            }
+            if mig_features.memory_info {
+                mig_points.push((self.metrics.memory_used, mig.memory_info()?.used));
+            }
+            if mig_features.running_compute_processes {
+                let n = mig.running_compute_processes_count()?;
+                mig_points.push((self.metrics.running_compute_processes, n as u64));
+            }
+
+            for (metric, value) in mig_points {
+                measurements.push(
+                    MeasurementPoint::new(timestamp, metric, resource.clone(), consumer.clone(), value)
+                        .with_attr("gpu_bus_id", self.device.bus_id.clone())
+                        .with_attr("gpu_instance_id", mig_device.gpu_instance_id as u64),
+                );
+            }
+        }
+
         Ok(())
     }
 }
 
+/// Lists the compute processes running on the device, with the available version of the NVML function.
+fn running_compute_processes(
+    device: &Device,
+    version: &AvailableVersion,
+) -> Result<Option<Vec<ProcessInfo>>, NvmlError> {
+    match version {
+        AvailableVersion::Latest => device.running_compute_processes().map(Some),
+        AvailableVersion::V2 => device.running_compute_processes_v2().map(Some),
+        AvailableVersion::None => Ok(None),
+    }
+}
+
+/// Lists the graphics processes running on the device, with the available version of the NVML function.
+fn running_graphics_processes(
+    device: &Device,
+    version: &AvailableVersion,
+) -> Result<Option<Vec<ProcessInfo>>, NvmlError> {
+    match version {
+        AvailableVersion::Latest => device.running_graphics_processes().map(Some),
+        AvailableVersion::V2 => device.running_graphics_processes_v2().map(Some),
+        AvailableVersion::None => Ok(None),
+    }
+}
+
+/// Computes the GPU memory used by each process, in bytes.
+///
+/// A process that does both compute and graphics work is listed twice by NVML, with the same memory usage.
+/// Processes whose memory usage is not available are ignored.
+fn memory_per_process<'a>(processes: impl IntoIterator<Item = &'a ProcessInfo>) -> BTreeMap<u32, u64> {
+    let mut res = BTreeMap::new();
+    for p in processes {
+        if let UsedGpuMemory::Used(bytes) = p.used_gpu_memory {
+            let used = res.entry(p.pid).or_insert(0);
+            *used = bytes.max(*used);
+        }
+    }
+    res
+}
+
 struct PowerMeasure {
     t: Timestamp,
     power: u32,
@@ -322,3 +484,32 @@ impl PowerMeasure {
         Ok(energy_consumed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use nvml_wrapper::{enums::device::UsedGpuMemory, struct_wrappers::device::ProcessInfo};
+
+    use super::memory_per_process;
+
+    fn process(pid: u32, used_gpu_memory: UsedGpuMemory) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            used_gpu_memory,
+            gpu_instance_id: None,
+            compute_instance_id: None,
+        }
+    }
+
+    #[test]
+    fn test_memory_per_process() {
+        let processes = [
+            process(10, UsedGpuMemory::Used(1024)),
+            process(11, UsedGpuMemory::Unavailable),
+            process(12, UsedGpuMemory::Used(2048)),
+            // the same process, listed as a graphics process
+            process(10, UsedGpuMemory::Used(1024)),
+        ];
+        let memory: Vec<_> = memory_per_process(&processes).into_iter().collect();
+        assert_eq!(memory, vec![(10, 1024), (12, 2048)]);
+    }
+}
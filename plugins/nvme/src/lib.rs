@@ -0,0 +1,115 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::{
+    nvme::NvmeController,
+    source::{Metrics, NvmeSource},
+};
+
+mod nvme;
+mod source;
+
+/// Directory that contains the NVMe controllers in the sysfs.
+const SYS_CLASS_NVME: &str = "/sys/class/nvme";
+
+pub struct NvmePlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for NvmePlugin {
+    fn name() -> &'static str {
+        "nvme"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(NvmePlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let paths = if self.config.devices.is_empty() {
+            nvme::find_controllers(Path::new(SYS_CLASS_NVME))
+                .with_context(|| format!("could not list the NVMe controllers in {SYS_CLASS_NVME}"))?
+        } else {
+            self.config.devices.clone()
+        };
+
+        // open the controllers and check that we can read their SMART log
+        let mut controllers = Vec::with_capacity(paths.len());
+        for path in paths {
+            let controller = match NvmeController::open(&path) {
+                Ok(c) => c,
+                Err(e) => {
+                    log::warn!("Could not open NVMe controller {path:?}, it will not be measured: {e}");
+                    continue;
+                }
+            };
+            match controller.smart_log() {
+                Ok(_) => {
+                    log::info!("Found NVMe controller {}", controller.name);
+                    controllers.push(controller);
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Could not read the SMART log of {path:?} (root privileges are usually required), it will not be measured: {e}"
+                    );
+                }
+            }
+        }
+        if controllers.is_empty() {
+            return Err(anyhow!("nothing to measure: no NVMe controller is available"));
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = NvmeSource::new(controllers, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("smart", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Paths to the NVMe controllers to measure, like `/dev/nvme0`.
+    /// If empty, all the controllers are measured.
+    pub devices: Vec<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(10),
+            devices: Vec::new(),
+        }
+    }
+}
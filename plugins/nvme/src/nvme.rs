@@ -0,0 +1,169 @@
+//! Access to the NVMe controllers through the admin commands of the Linux driver (`/dev/nvme0`).
+
+use std::{
+    fs::File,
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+};
+
+// Definitions from linux/nvme_ioctl.h and the NVMe specification
+
+const NVME_IOCTL_MAGIC: u8 = b'N';
+/// Opcode of the admin command "Get Log Page".
+const NVME_ADMIN_GET_LOG_PAGE: u8 = 0x02;
+/// Identifier of the "SMART / Health Information" log page.
+const NVME_LOG_SMART: u32 = 0x02;
+/// Namespace id that designates the whole controller.
+const NVME_NSID_ALL: u32 = 0xffff_ffff;
+/// Size of the SMART log page, in bytes.
+pub const SMART_LOG_SIZE: usize = 512;
+/// Size of a "data unit" in the SMART log page, in bytes.
+const DATA_UNIT_SIZE: u128 = 512 * 1000;
+
+#[repr(C)]
+#[derive(Default)]
+struct NvmePassthruCmd {
+    opcode: u8,
+    flags: u8,
+    rsvd1: u16,
+    nsid: u32,
+    cdw2: u32,
+    cdw3: u32,
+    metadata: u64,
+    addr: u64,
+    metadata_len: u32,
+    data_len: u32,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+    timeout_ms: u32,
+    result: u32,
+}
+
+nix::ioctl_readwrite!(nvme_ioctl_admin_cmd, NVME_IOCTL_MAGIC, 0x41, NvmePassthruCmd);
+
+/// A NVMe controller, like `/dev/nvme0`.
+pub struct NvmeController {
+    /// Name of the controller, like `nvme0`.
+    pub name: String,
+    file: File,
+}
+
+/// Content of the "SMART / Health Information" log page (only the fields that we need).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmartLog {
+    /// Composite temperature of the controller, in Kelvin.
+    pub temperature_kelvin: u16,
+    /// Remaining spare capacity, in percent.
+    pub available_spare: u8,
+    /// Estimation of the life of the device that has been used, in percent (can exceed 100).
+    pub percentage_used: u8,
+    /// Number of bytes read by the host, with a granularity of 512 000 bytes.
+    pub bytes_read: u128,
+    /// Number of bytes written by the host, with a granularity of 512 000 bytes.
+    pub bytes_written: u128,
+    pub power_cycles: u128,
+}
+
+impl NvmeController {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = File::open(path)?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        Ok(Self { name, file })
+    }
+
+    /// Reads the SMART log page of the controller.
+    pub fn smart_log(&self) -> std::io::Result<SmartLog> {
+        let mut data = [0u8; SMART_LOG_SIZE];
+        let dwords = (SMART_LOG_SIZE / 4) as u32;
+        let mut cmd = NvmePassthruCmd {
+            opcode: NVME_ADMIN_GET_LOG_PAGE,
+            nsid: NVME_NSID_ALL,
+            addr: data.as_mut_ptr() as u64,
+            data_len: SMART_LOG_SIZE as u32,
+            // number of dwords to read (0-based) and log page identifier
+            cdw10: ((dwords - 1) << 16) | NVME_LOG_SMART,
+            ..Default::default()
+        };
+        // SAFETY: the command points to a valid buffer of `data_len` bytes, which outlives the call
+        let status = unsafe { nvme_ioctl_admin_cmd(self.file.as_raw_fd(), &mut cmd) }.map_err(std::io::Error::from)?;
+        if status != 0 {
+            // positive values are NVMe status codes
+            return Err(std::io::Error::other(format!(
+                "NVMe command failed with status {status:#x}"
+            )));
+        }
+        Ok(SmartLog::parse(&data))
+    }
+}
+
+impl SmartLog {
+    /// Parses the SMART log page (little endian).
+    pub fn parse(data: &[u8; SMART_LOG_SIZE]) -> Self {
+        let u128_at = |offset: usize| u128::from_le_bytes(data[offset..offset + 16].try_into().unwrap());
+        Self {
+            temperature_kelvin: u16::from_le_bytes([data[1], data[2]]),
+            available_spare: data[3],
+            percentage_used: data[5],
+            bytes_read: u128_at(32) * DATA_UNIT_SIZE,
+            bytes_written: u128_at(48) * DATA_UNIT_SIZE,
+            power_cycles: u128_at(112),
+        }
+    }
+
+    pub fn temperature_celsius(&self) -> f64 {
+        f64::from(self.temperature_kelvin) - 273.15
+    }
+}
+
+/// Lists the NVMe controllers of the machine, with the sysfs.
+pub fn find_controllers(sys_class_nvme: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut devices = Vec::new();
+    for entry in std::fs::read_dir(sys_class_nvme)? {
+        let name = entry?.file_name();
+        devices.push(Path::new("/dev").join(name));
+    }
+    devices.sort();
+    Ok(devices)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parse_smart_log() {
+        let mut data = [0u8; SMART_LOG_SIZE];
+        data[1..3].copy_from_slice(&310u16.to_le_bytes());
+        data[3] = 100;
+        data[5] = 7;
+        data[32..48].copy_from_slice(&1000u128.to_le_bytes());
+        data[48..64].copy_from_slice(&2500u128.to_le_bytes());
+        data[112..128].copy_from_slice(&42u128.to_le_bytes());
+
+        let log = SmartLog::parse(&data);
+        assert_eq!(
+            log,
+            SmartLog {
+                temperature_kelvin: 310,
+                available_spare: 100,
+                percentage_used: 7,
+                bytes_read: 512_000_000,
+                bytes_written: 1_280_000_000,
+                power_cycles: 42,
+            }
+        );
+        assert!((log.temperature_celsius() - 36.85).abs() < 1e-9);
+    }
+
+    #[test]
+    fn passthru_cmd_layout() {
+        // must match `struct nvme_passthru_cmd` in linux/nvme_ioctl.h
+        assert_eq!(size_of::<NvmePassthruCmd>(), 72);
+    }
+}
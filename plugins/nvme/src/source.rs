@@ -0,0 +1,123 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::Context;
+
+use crate::nvme::{NvmeController, SmartLog};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    temperature: TypedMetricId<f64>,
+    data_delta: TypedMetricId<u64>,
+    power_cycles: TypedMetricId<u64>,
+    percentage_used: TypedMetricId<u64>,
+    available_spare: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            temperature: alumet.create_metric(
+                "nvme_temperature",
+                Unit::DegreeCelsius,
+                "Composite temperature of the NVMe controller",
+            )?,
+            data_delta: alumet.create_metric(
+                "nvme_data_delta",
+                Unit::Byte,
+                "Number of bytes read or written by the host since the previous measurement (granularity: 512 kB)",
+            )?,
+            power_cycles: alumet.create_metric(
+                "nvme_power_cycles",
+                Unit::Unity,
+                "Number of power cycles of the NVMe controller",
+            )?,
+            percentage_used: alumet.create_metric(
+                "nvme_percentage_used",
+                Unit::Percent,
+                "Estimation of the life of the NVMe device that has been used (can exceed 100%)",
+            )?,
+            available_spare: alumet.create_metric(
+                "nvme_available_spare",
+                Unit::Percent,
+                "Remaining spare capacity of the NVMe device",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the SMART log of NVMe controllers.
+pub struct NvmeSource {
+    controllers: Vec<MeasuredController>,
+    metrics: Metrics,
+}
+
+struct MeasuredController {
+    controller: NvmeController,
+    /// The previous SMART log, to compute the difference.
+    previous: Option<SmartLog>,
+}
+
+impl NvmeSource {
+    pub fn new(controllers: Vec<NvmeController>, metrics: Metrics) -> Self {
+        let controllers = controllers
+            .into_iter()
+            .map(|controller| MeasuredController {
+                controller,
+                previous: None,
+            })
+            .collect();
+        Self { controllers, metrics }
+    }
+}
+
+impl Source for NvmeSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for c in &mut self.controllers {
+            let name = &c.controller.name;
+            let log = c
+                .controller
+                .smart_log()
+                .with_context(|| format!("failed to read the SMART log of {name}"))?;
+
+            let resource = Resource::Custom {
+                kind: "nvme".into(),
+                id: name.clone().into(),
+            };
+            measurements.push(MeasurementPoint::new(
+                timestamp,
+                self.metrics.temperature,
+                resource.clone(),
+                ResourceConsumer::LocalMachine,
+                log.temperature_celsius(),
+            ));
+            let point = |metric, value: u64| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    resource.clone(),
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+            };
+            measurements.push(point(self.metrics.power_cycles, log.power_cycles as u64));
+            measurements.push(point(self.metrics.percentage_used, u64::from(log.percentage_used)));
+            measurements.push(point(self.metrics.available_spare, u64::from(log.available_spare)));
+
+            // Only push deltas, not the baseline value before the plugin starts
+            if let Some(prev) = &c.previous {
+                let read = log.bytes_read.saturating_sub(prev.bytes_read) as u64;
+                let written = log.bytes_written.saturating_sub(prev.bytes_written) as u64;
+                measurements.push(point(self.metrics.data_delta, read).with_attr("direction", "read"));
+                measurements.push(point(self.metrics.data_delta, written).with_attr("direction", "write"));
+            }
+            c.previous = Some(log);
+        }
+        Ok(())
+    }
+}
@@ -19,7 +19,7 @@ use itertools::Itertools;
 use perf_event::events::{Cache, Hardware, Software};
 use serde::{Deserialize, Serialize};
 
-use crate::source::{Observable, PerfEventSourceBuilder};
+use crate::source::{Observable, PerfEventSource, PerfEventSourceBuilder};
 
 #[cfg(not(target_os = "linux"))]
 compile_error!("This plugin only works on Linux.");
@@ -64,6 +64,8 @@ impl AlumetPlugin for PerfPlugin {
                 .map(|e| events::parse_software(&e))
                 .try_collect()
                 .context("invalid software event in config")?,
+            system: config.system,
+            cgroups: config.cgroups,
             cache_events: config
                 .cache_events
                 .into_iter()
@@ -105,6 +107,27 @@ impl AlumetPlugin for PerfPlugin {
         config.hardware_metrics = hardware_metrics;
         config.software_metrics = software_metrics;
         config.cache_metrics = cache_metrics;
+
+        // Observe the system and the configured cgroups from the start.
+        let mut observables = Vec::new();
+        match config.system {
+            SystemMode::None => (),
+            SystemMode::Total => observables.push((Observable::System { per_cpu: false }, String::from("system"))),
+            SystemMode::PerCpu => observables.push((Observable::System { per_cpu: true }, String::from("system"))),
+        }
+        for path in &config.cgroups {
+            let fd = File::open(path).with_context(|| format!("could not open cgroup {path}"))?;
+            let observable = Observable::Cgroup { path: path.clone(), fd };
+            observables.push((observable, format!("source-cgroup[{path}]")));
+        }
+        for (o, source_name) in observables {
+            log::info!("Starting to observe {o:?}...");
+            let source = build_source(&config, o)?;
+            let trigger = TriggerSpec::builder(config.poll_interval)
+                .flush_interval(config.flush_interval)
+                .build()?;
+            alumet.add_source(&source_name, Box::new(source), trigger)?;
+        }
         Ok(())
     }
 
@@ -136,33 +159,11 @@ impl AlumetPlugin for PerfPlugin {
                 if let Some((o, source_name)) = observable {
                     log::info!("Starting to observe {o:?}...");
                     let config = config_cloned.lock().unwrap();
-                    let mut builder = PerfEventSourceBuilder::observe(o)?;
-                    for (event, metric) in config.hardware_events.iter().zip(&config.hardware_metrics) {
-                        builder.add(event.event, *metric).with_context(|| {
-                            format!(
-                                "could not configure hardware event {} (code {})",
-                                event.name, event.event.0
-                            )
-                        })?;
-                    }
-                    for (event, metric) in config.software_events.iter().zip(&config.software_metrics) {
-                        builder.add(event.event, *metric).with_context(|| {
-                            format!(
-                                "could not configure software event {} (code {})",
-                                event.name, event.event.0
-                            )
-                        })?;
-                    }
-                    for (event, metric) in config.cache_events.iter().zip(&config.cache_metrics) {
-                        builder
-                            .add(event.event.clone(), *metric)
-                            .with_context(|| format!("could not configure cache event {}", event.name))?;
-                    }
+                    let source = build_source(&config, o)?;
                     let poll_interval = config.poll_interval;
                     let flush_interval = config.flush_interval;
                     drop(config);
 
-                    let source = builder.build()?;
                     let trigger = TriggerSpec::builder(poll_interval)
                         .flush_interval(flush_interval)
                         .build()?;
@@ -193,6 +194,25 @@ struct Config {
     hardware_events: Vec<String>,
     software_events: Vec<String>,
     cache_events: Vec<String>,
+
+    /// Observe the whole system?
+    #[serde(default)]
+    system: SystemMode,
+    /// Paths of the cgroups to observe from the start, such as `/sys/fs/cgroup/system.slice`.
+    #[serde(default)]
+    cgroups: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+enum SystemMode {
+    /// Only observe the processes and cgroups.
+    #[default]
+    None,
+    /// Observe the whole system, with one measurement per event.
+    Total,
+    /// Observe the whole system, with one measurement per event and per CPU.
+    PerCpu,
 }
 
 impl Default for Config {
@@ -208,6 +228,8 @@ impl Default for Config {
             ],
             software_events: vec![],
             cache_events: vec!["LL_READ_MISS".to_owned()],
+            system: SystemMode::None,
+            cgroups: vec![],
         }
     }
 }
@@ -217,6 +239,9 @@ struct ParsedConfig {
     poll_interval: Duration,
     flush_interval: Duration,
 
+    system: SystemMode,
+    cgroups: Vec<String>,
+
     hardware_events: Vec<NamedPerfEvent<Hardware>>,
     software_events: Vec<NamedPerfEvent<Software>>,
     cache_events: Vec<NamedPerfEvent<Cache>>,
@@ -224,3 +249,30 @@ struct ParsedConfig {
     software_metrics: Vec<TypedMetricId<u64>>,
     cache_metrics: Vec<TypedMetricId<u64>>,
 }
+
+/// Builds a source that measures the configured perf events on `observable`.
+fn build_source(config: &ParsedConfig, observable: Observable) -> anyhow::Result<PerfEventSource> {
+    let mut builder = PerfEventSourceBuilder::observe(observable)?;
+    for (event, metric) in config.hardware_events.iter().zip(&config.hardware_metrics) {
+        builder.add(event.event, *metric).with_context(|| {
+            format!(
+                "could not configure hardware event {} (code {})",
+                event.name, event.event.0
+            )
+        })?;
+    }
+    for (event, metric) in config.software_events.iter().zip(&config.software_metrics) {
+        builder.add(event.event, *metric).with_context(|| {
+            format!(
+                "could not configure software event {} (code {})",
+                event.name, event.event.0
+            )
+        })?;
+    }
+    for (event, metric) in config.cache_events.iter().zip(&config.cache_metrics) {
+        builder
+            .add(event.event.clone(), *metric)
+            .with_context(|| format!("could not configure cache event {}", event.name))?;
+    }
+    Ok(builder.build()?)
+}
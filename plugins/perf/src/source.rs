@@ -1,5 +1,5 @@
 //! Source of measurements based on Linux perf_events.
-use std::{fs::File, io};
+use std::{fs::File, io, time::Duration};
 
 use alumet::{
     measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
@@ -23,10 +23,65 @@ pub enum Observable {
     /// Unlike processes, cgroups cannot be monitored with `cpu = -1`, a specific cpu id is required
     /// for `perf_event_open` (see https://github.com/torvalds/linux/blob/2c8159388952f530bd260e097293ccc0209240be/kernel/events/core.c#L12487)
     Cgroup { path: String, fd: File },
+    /// Observe the whole system.
+    ///
+    /// Like cgroups, the system is monitored on each cpu separately (`pid = -1` requires a specific cpu id).
+    /// If `per_cpu` is false, the counts of all the cpus are added up.
+    System { per_cpu: bool },
+}
+
+impl Observable {
+    /// Sets the target of the perf event: what to observe, and on which cpu.
+    fn configure<'a>(&'a self, builder: &mut perf_event::Builder<'a>, cpu_id: Option<u32>) {
+        match (self, cpu_id) {
+            (Observable::Process { pid }, _) => {
+                builder.observe_pid(*pid).any_cpu();
+            }
+            (Observable::Cgroup { fd, .. }, Some(cpu_id)) => {
+                builder.observe_cgroup(fd).one_cpu(cpu_id as usize);
+            }
+            (Observable::System { .. }, Some(cpu_id)) => {
+                builder.any_pid().one_cpu(cpu_id as usize);
+            }
+            (_, None) => unreachable!("cgroups and the system must be observed on a specific cpu"),
+        }
+    }
+
+    /// Returns the resource and consumer of the measurements obtained on the given cpu.
+    fn perimeter(&self, cpu_id: Option<u32>) -> (Resource, ResourceConsumer) {
+        match self {
+            Observable::Process { pid } => (
+                Resource::LocalMachine,
+                ResourceConsumer::Process {
+                    pid: u32::try_from(*pid).unwrap(),
+                },
+            ),
+            Observable::Cgroup { path, .. } => (
+                Resource::LocalMachine,
+                ResourceConsumer::ControlGroup {
+                    path: path.to_owned().into(),
+                },
+            ),
+            Observable::System { per_cpu: true } => (
+                Resource::CpuCore {
+                    id: cpu_id.expect("the system must be observed on a specific cpu"),
+                },
+                ResourceConsumer::LocalMachine,
+            ),
+            Observable::System { per_cpu: false } => (Resource::LocalMachine, ResourceConsumer::LocalMachine),
+        }
+    }
+
+    /// Should the counts of the groups (one group per cpu) be added up?
+    fn aggregates_cpus(&self) -> bool {
+        !matches!(self, Observable::System { per_cpu: true })
+    }
 }
 
 pub struct PerfEventSource {
     event_groups: Vec<EventGroup>,
+    /// If true, the counts of all the groups are added up, and reported as one measurement per event.
+    aggregate: bool,
 }
 
 struct EventGroup {
@@ -35,33 +90,116 @@ struct EventGroup {
     observed_consumer: ResourceConsumer,
     cpu_id: Option<u32>,
     counters: Vec<(perf_event::Counter, TypedMetricId<u64>)>,
+    /// Values obtained by the previous read, to compute the increments.
+    previous: Option<GroupSnapshot>,
+}
+
+struct GroupSnapshot {
+    time_enabled: Duration,
+    time_running: Duration,
+    counts: Vec<u64>,
+}
+
+impl EventGroup {
+    /// Reads the counters and returns their (estimated) increments since the previous read,
+    /// in the same order as `self.counters`.
+    ///
+    /// Returns `None` if the group did not run at all since the previous read.
+    fn read_increments(&mut self) -> io::Result<Option<Vec<u64>>> {
+        let data = self.perf_group.read()?;
+        let snapshot = GroupSnapshot {
+            time_enabled: data.time_enabled().unwrap_or_default(),
+            time_running: data.time_running().unwrap_or_default(),
+            counts: self.counters.iter().map(|(counter, _)| data[counter]).collect(),
+        };
+        let (enabled, running, increments) = match &self.previous {
+            Some(prev) => (
+                snapshot.time_enabled.saturating_sub(prev.time_enabled),
+                snapshot.time_running.saturating_sub(prev.time_running),
+                snapshot
+                    .counts
+                    .iter()
+                    .zip(&prev.counts)
+                    .map(|(count, prev_count)| count.saturating_sub(*prev_count))
+                    .collect_vec(),
+            ),
+            // the counters have been enabled just before the first read, and started at zero
+            None => (snapshot.time_enabled, snapshot.time_running, snapshot.counts.clone()),
+        };
+        self.previous = Some(snapshot);
+
+        if running < enabled {
+            log::trace!(
+                "perf events multiplexed on cpu {:?}: time_enabled={enabled:?}, time_running={running:?}",
+                self.cpu_id
+            );
+        }
+        let scaled: Option<Vec<u64>> = increments
+            .into_iter()
+            .map(|n| scale_multiplexed(n, enabled, running))
+            .collect();
+        Ok(scaled)
+    }
+}
+
+/// Estimates the number of events that occurred during an interval, from the count obtained with multiplexing.
+///
+/// When there are more events than hardware counters, the kernel multiplexes the event groups:
+/// each group only counts during a fraction of the interval (`running` out of `enabled`).
+/// Like `perf stat`, the count is extrapolated to the whole interval.
+fn scale_multiplexed(count: u64, enabled: Duration, running: Duration) -> Option<u64> {
+    if running.is_zero() {
+        // the group has never been scheduled on the PMU, nothing can be estimated
+        None
+    } else if running >= enabled {
+        Some(count)
+    } else {
+        let scaled = count as f64 * enabled.as_secs_f64() / running.as_secs_f64();
+        Some(scaled.round() as u64)
+    }
 }
 
 impl Source for PerfEventSource {
     fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let mut totals: Option<Vec<u64>> = None;
         for group in &mut self.event_groups {
             // read all counters in the group
-            let counts = group.perf_group.read()?;
-
-            // get some metadata about the measurement perimeter
-            let resource = &group.observed_resource;
-            let consumer = &group.observed_consumer;
+            let Some(increments) = group.read_increments()? else {
+                log::debug!(
+                    "perf events of {:?} not scheduled on cpu {:?}, skipping",
+                    group.observed_consumer,
+                    group.cpu_id
+                );
+                continue;
+            };
 
-            // TODO: check time_enabled and time_running to detect issues
-            log::trace!(
-                "Got perf_events measurements: time_enabled={:?}, time_running={:?}",
-                counts.time_enabled(),
-                counts.time_running()
-            );
+            if self.aggregate {
+                match &mut totals {
+                    Some(totals) => totals.iter_mut().zip(&increments).for_each(|(t, n)| *t += n),
+                    None => totals = Some(increments),
+                }
+            } else {
+                // for each counter, push its value
+                for ((_, alumet_metric), value) in group.counters.iter().zip(increments) {
+                    measurements.push(MeasurementPoint::new(
+                        timestamp,
+                        *alumet_metric,
+                        group.observed_resource.clone(),
+                        group.observed_consumer.clone(),
+                        value,
+                    ))
+                }
+            }
+        }
 
-            // for each counter, push its value
-            for (perf_counter, alumet_metric) in &group.counters {
-                let value = counts[perf_counter];
+        // push the sum of all the groups (the groups contain the same events, in the same order)
+        if let (Some(totals), Some(group)) = (totals, self.event_groups.first()) {
+            for ((_, alumet_metric), value) in group.counters.iter().zip(totals) {
                 measurements.push(MeasurementPoint::new(
                     timestamp,
                     *alumet_metric,
-                    resource.clone(),
-                    consumer.clone(),
+                    group.observed_resource.clone(),
+                    group.observed_consumer.clone(),
                     value,
                 ))
             }
@@ -94,6 +232,29 @@ impl PerfEventSourceBuilder {
         event: E,
         alumet_metric: TypedMetricId<u64>,
     ) -> anyhow::Result<&mut Self> {
+        if self.groups.is_empty() {
+            self.groups = self.new_groups()?;
+        }
+
+        // add to the group(s)
+        for group in &mut self.groups {
+            // the event params must be the same as the group's params
+            let mut event_builder = perf_event::Builder::new(event.clone());
+            self.observable.configure(&mut event_builder, group.cpu_id);
+
+            let counter = group.perf_group.add(&event_builder).with_context(|| {
+                format!(
+                    "perf_group.add(event_builder), group resource={:?}, consumer={:?}, cpu={:?}",
+                    group.observed_resource, group.observed_consumer, group.cpu_id
+                )
+            })?;
+            group.counters.push((counter, alumet_metric))
+        }
+        Ok(self)
+    }
+
+    /// Creates the (empty) group(s) of events: one group for a process, one group per cpu otherwise.
+    fn new_groups(&self) -> anyhow::Result<Vec<EventGroup>> {
         // Returns a new [`perf_event::Builder`] configured to build a group of perf events.
         fn new_group_builder<'a>() -> perf_event::Builder<'a> {
             use perf_event::ReadFormat;
@@ -106,101 +267,34 @@ impl PerfEventSourceBuilder {
             builder
         }
 
-        if self.groups.is_empty() {
-            // create the group(s)
-            match &self.observable {
-                Observable::Process { pid } => {
-                    // Observe the process on any cpu.
-
-                    // build group
-                    let mut perf_group = new_group_builder()
-                        .observe_pid(*pid)
-                        .any_cpu()
-                        .build_group()
-                        .with_context(|| format!("build_group with observe_pid({pid}).any_cpu()"))?;
-
-                    // add event (the params must be the same)
-                    let counter = perf_group
-                        .add(perf_event::Builder::new(event).observe_pid(*pid).any_cpu())
-                        .with_context(|| format!("perf_group.add with observe_pid({pid}).any_cpu()"))?;
-
-                    // add metadata
-                    let group_with_info = EventGroup {
-                        perf_group,
-                        observed_resource: Resource::LocalMachine,
-                        observed_consumer: ResourceConsumer::Process {
-                            pid: u32::try_from(*pid).unwrap(),
-                        },
-                        cpu_id: None,
-                        counters: vec![(counter, alumet_metric)],
-                    };
-
-                    // done
-                    self.groups = vec![group_with_info];
-                }
-                Observable::Cgroup { path, fd } => {
-                    // Observe the cgroup on each cpu separately (this is a restriction of perf_event_open).
-
-                    // build one group per cpu
-                    let mut groups = Vec::new();
-                    for cpu_id in &self.online_cpus {
-                        let cpu_id = *cpu_id as usize;
-
-                        // build group
-                        let mut perf_group = new_group_builder()
-                            .observe_cgroup(fd)
-                            .one_cpu(cpu_id)
-                            .build_group()
-                            .with_context(|| format!("build_group with observe_cgroup({path}).one_cpu({cpu_id})"))?;
-
-                        // add event (the params must be the same)
-                        let counter = perf_group
-                            .add(
-                                perf_event::Builder::new(event.clone())
-                                    .observe_cgroup(fd)
-                                    .one_cpu(cpu_id),
-                            )
-                            .with_context(|| format!("perf_group.add with observe_cgroup({path}).one_cpu({cpu_id})"))?;
-
-                        let group_with_info = EventGroup {
-                            perf_group,
-                            observed_resource: Resource::LocalMachine,
-                            observed_consumer: ResourceConsumer::ControlGroup {
-                                path: path.to_owned().into(),
-                            },
-                            cpu_id: None,
-                            counters: vec![(counter, alumet_metric)],
-                        };
-                        groups.push(group_with_info);
-                    }
-                    self.groups = groups;
-                }
+        let cpus: Vec<Option<u32>> = match &self.observable {
+            // observe the process on any cpu
+            Observable::Process { .. } => vec![None],
+            // observe the cgroup or the system on each cpu separately (this is a restriction of perf_event_open)
+            Observable::Cgroup { .. } | Observable::System { .. } => {
+                self.online_cpus.iter().copied().map(Some).collect()
             }
-        } else {
-            // add to the group(s)
-            for group in &mut self.groups {
-                let mut event_builder = perf_event::Builder::new(event.clone());
-
-                // Compute the event params to be the same as the group's params.
-                match &self.observable {
-                    Observable::Process { pid } => {
-                        event_builder.observe_pid(*pid).any_cpu();
-                    }
-                    Observable::Cgroup { path: _, fd } => {
-                        event_builder.observe_cgroup(fd).one_cpu(group.cpu_id.unwrap() as usize);
-                    }
-                }
+        };
 
-                let counter = group.perf_group.add(&event_builder).with_context(|| {
-                    format!(
-                        "existing perf_group.add(event_builder), group resource={:?}, consumer={:?}, cpu={:?}",
-                        group.observed_resource, group.observed_consumer, group.cpu_id
-                    )
-                })?;
-                group.counters.push((counter, alumet_metric))
-            }
+        let mut groups = Vec::with_capacity(cpus.len());
+        for cpu_id in cpus {
+            let mut builder = new_group_builder();
+            self.observable.configure(&mut builder, cpu_id);
+            let perf_group = builder
+                .build_group()
+                .with_context(|| format!("build_group for {:?} on cpu {cpu_id:?}", self.observable))?;
+
+            let (observed_resource, observed_consumer) = self.observable.perimeter(cpu_id);
+            groups.push(EventGroup {
+                perf_group,
+                observed_resource,
+                observed_consumer,
+                cpu_id,
+                counters: Vec::new(),
+                previous: None,
+            });
         }
-        Ok(self)
+        Ok(groups)
     }
 
     pub fn build(mut self) -> io::Result<PerfEventSource> {
@@ -220,6 +314,27 @@ impl PerfEventSourceBuilder {
 
         Ok(PerfEventSource {
             event_groups: self.groups,
+            aggregate: self.observable.aggregates_cpus(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::scale_multiplexed;
+
+    #[test]
+    fn multiplexing() {
+        let ms = Duration::from_millis;
+        // not multiplexed
+        assert_eq!(scale_multiplexed(1000, ms(100), ms(100)), Some(1000));
+        // counted during a quarter of the interval
+        assert_eq!(scale_multiplexed(1000, ms(100), ms(25)), Some(4000));
+        assert_eq!(scale_multiplexed(0, ms(100), ms(25)), Some(0));
+        // never scheduled
+        assert_eq!(scale_multiplexed(0, ms(100), ms(0)), None);
+        assert_eq!(scale_multiplexed(0, ms(0), ms(0)), None);
+    }
+}
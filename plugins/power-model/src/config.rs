@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use alumet::measurement::{AttributeValue, MeasurementPoint};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginConfig {
+    /// The models indexed by name.
+    /// Note that the name of the model will be the name of the produced metric.
+    pub models: BTreeMap<String, ModelConfig>,
+}
+
+/// Configuration for one power model.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ModelConfig {
+    /// The resource whose power is estimated.
+    #[serde(default = "default_resource_kind")]
+    pub resource_kind: String,
+    #[serde(default)]
+    pub resource_id: String,
+
+    /// The power consumed when all the inputs are zero, in Watts.
+    pub intercept: f64,
+    /// The inputs of the model, with their coefficients.
+    pub inputs: BTreeMap<String, InputConfig>,
+
+    /// If set, the coefficients are fitted at runtime against this reference.
+    pub calibration: Option<CalibrationConfig>,
+}
+
+/// Configuration for one input of a model.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InputConfig {
+    /// The coefficients of the polynomial, by increasing degree: `[a, b]` means `a*x + b*x^2`.
+    pub coefficients: Vec<f64>,
+    #[serde(flatten)]
+    pub series: SeriesConfig,
+}
+
+/// Configuration of the reference that is used to calibrate a model.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CalibrationConfig {
+    /// Minimum number of samples before the coefficients are updated.
+    #[serde(default = "default_min_samples")]
+    pub min_samples: usize,
+    /// Weight of the previous samples, between 0 (excluded) and 1.
+    /// The lower the factor, the faster the model forgets the old samples.
+    #[serde(default = "default_forgetting_factor")]
+    pub forgetting_factor: f64,
+    /// The measured power (or energy) that the model must follow.
+    #[serde(flatten)]
+    pub series: SeriesConfig,
+}
+
+/// Selects the measurement points of a timeseries.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SeriesConfig {
+    pub metric: String,
+
+    pub resource_kind: Option<String>,
+    pub resource_id: Option<String>,
+
+    pub consumer_kind: Option<String>,
+    pub consumer_id: Option<String>,
+
+    #[serde(flatten)]
+    pub attributes: BTreeMap<String, FilterAttributeValue>,
+}
+
+fn default_resource_kind() -> String {
+    String::from("local_machine")
+}
+
+fn default_min_samples() -> usize {
+    30
+}
+
+fn default_forgetting_factor() -> f64 {
+    0.999
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum FilterAttributeValue {
+    UInt(u64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+}
+
+impl FilterAttributeValue {
+    pub fn matches(&self, value: &AttributeValue) -> bool {
+        match (self, value) {
+            (FilterAttributeValue::UInt(a), AttributeValue::U64(b)) => a == b,
+            (FilterAttributeValue::Float(a), AttributeValue::F64(b)) => a == b,
+            (FilterAttributeValue::Bool(a), AttributeValue::Bool(b)) => a == b,
+            (FilterAttributeValue::String(a), AttributeValue::Str(b)) => a == b,
+            (FilterAttributeValue::String(a), AttributeValue::String(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl SeriesConfig {
+    /// Returns `true` if the point belongs to the timeseries (the metric is not checked).
+    pub fn accept_point(&self, point: &MeasurementPoint) -> bool {
+        let matches = |filter: &Option<String>, actual: String| filter.as_ref().is_none_or(|f| *f == actual);
+        matches(&self.resource_kind, point.resource.kind().to_owned())
+            && matches(&self.resource_id, point.resource.id_display().to_string())
+            && matches(&self.consumer_kind, point.consumer.kind().to_owned())
+            && matches(&self.consumer_id, point.consumer.id_display().to_string())
+            && self.attributes.iter().all(|(k, v)| {
+                point
+                    .attributes()
+                    .find(|(k2, _)| k2 == k)
+                    .is_some_and(|(_, v2)| v.matches(v2))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_config() {
+        let config = r#"
+            [models.estimated_node_power]
+            intercept = 45.0
+
+            [models.estimated_node_power.inputs]
+            cpu = { metric = "cpu_usage_percent", kind = "total", coefficients = [0.9, 0.001] }
+            memory = { metric = "memory_usage", kind = "used", coefficients = [2e-9] }
+
+            [models.estimated_node_power.calibration]
+            metric = "rapl_consumed_energy"
+            domain = "package_total"
+            min_samples = 10
+
+            [models.estimated_gpu_power]
+            resource_kind = "gpu"
+            resource_id = "0"
+            intercept = 20.0
+            inputs.usage = { metric = "gpu_utilization", coefficients = [2.5] }
+        "#;
+        let config: PluginConfig = toml::from_str(config).unwrap();
+
+        let node = &config.models["estimated_node_power"];
+        assert_eq!(node.resource_kind, "local_machine");
+        assert_eq!(node.resource_id, "");
+        assert_eq!(node.intercept, 45.0);
+        assert_eq!(node.inputs["cpu"].coefficients, vec![0.9, 0.001]);
+        assert_eq!(node.inputs["cpu"].series.metric, "cpu_usage_percent");
+        assert_eq!(
+            node.inputs["cpu"].series.attributes,
+            BTreeMap::from([(
+                String::from("kind"),
+                FilterAttributeValue::String(String::from("total"))
+            )])
+        );
+        assert_eq!(node.inputs["memory"].coefficients, vec![2e-9]);
+        let calibration = node.calibration.as_ref().unwrap();
+        assert_eq!(calibration.series.metric, "rapl_consumed_energy");
+        assert_eq!(calibration.min_samples, 10);
+        assert_eq!(calibration.forgetting_factor, 0.999);
+        assert_eq!(calibration.series.attributes.len(), 1);
+
+        let gpu = &config.models["estimated_gpu_power"];
+        assert_eq!(gpu.resource_kind, "gpu");
+        assert_eq!(gpu.resource_id, "0");
+        assert_eq!(gpu.inputs["usage"].coefficients, vec![2.5]);
+        assert!(gpu.calibration.is_none());
+    }
+}
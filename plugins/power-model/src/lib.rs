@@ -0,0 +1,142 @@
+use alumet::{
+    metrics::{Metric, registry::MetricRegistry},
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config},
+    },
+    resources::Resource,
+    units::{Unit, UnitPrefix},
+};
+use anyhow::{Context, anyhow};
+
+use config::{ModelConfig, PluginConfig};
+use model::{Calibrator, Model};
+use transform::{Calibration, PowerModelTransform, ReferenceKind, Series};
+
+mod config;
+mod model;
+mod transform;
+
+pub struct PowerModelPlugin {
+    config: Option<PluginConfig>,
+}
+
+impl AlumetPlugin for PowerModelPlugin {
+    fn name() -> &'static str {
+        "power-model"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(Self { config: Some(config) }))
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        Ok(None)
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        let config = self.config.take().unwrap();
+        if config.models.is_empty() {
+            return Err(anyhow!("nothing to estimate: no model is configured"));
+        }
+        for (name, model_config) in config.models {
+            let resource = Resource::parse(model_config.resource_kind.clone(), model_config.resource_id.clone())
+                .with_context(|| format!("invalid resource for model {name}"))?;
+            let result_metric = alumet.create_metric::<f64>(
+                &name,
+                Unit::Watt,
+                "Power estimated by a model from the utilization of the resource",
+            )?;
+
+            // create the transform, in a builder because we need the metric registry
+            alumet.add_transform_builder(&format!("model/{name}"), move |ctx| {
+                let (model, inputs, calibration) = prepare(model_config, ctx.metrics())
+                    .with_context(|| format!("failed to prepare the power model {name}; check that you have enabled the required sources and that the configuration is correct"))?;
+                let transform = PowerModelTransform::new(name, model, inputs, resource, result_metric, calibration);
+                Ok(Box::new(transform))
+            })?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Builds the model and finds the metrics that it uses.
+fn prepare(config: ModelConfig, metrics: &MetricRegistry) -> anyhow::Result<(Model, Vec<Series>, Option<Calibration>)> {
+    let mut coefficients = Vec::with_capacity(config.inputs.len());
+    let mut inputs = Vec::with_capacity(config.inputs.len());
+    for (input_name, input) in config.inputs {
+        let metric_name = &input.series.metric;
+        let (metric, _) = metrics
+            .by_name(metric_name)
+            .with_context(|| format!("could not find metric '{metric_name}' for input '{input_name}'"))?;
+        coefficients.push(input.coefficients);
+        inputs.push(Series {
+            metric,
+            filter: input.series,
+        });
+    }
+    if inputs.is_empty() {
+        return Err(anyhow!("the model has no input"));
+    }
+    let model = Model {
+        intercept: config.intercept,
+        coefficients,
+    };
+
+    let calibration = match config.calibration {
+        Some(calibration) => {
+            let factor = calibration.forgetting_factor;
+            if !(factor > 0.0 && factor <= 1.0) {
+                return Err(anyhow!("invalid forgetting_factor {factor}: it must be in ]0, 1]"));
+            }
+            let metric_name = &calibration.series.metric;
+            let (metric, definition) = metrics
+                .by_name(metric_name)
+                .with_context(|| format!("could not find metric '{metric_name}' for the calibration"))?;
+            let kind = reference_kind(definition)?;
+            Some(Calibration {
+                reference: Series {
+                    metric,
+                    filter: calibration.series,
+                },
+                kind,
+                calibrator: Calibrator::new(model.n_parameters(), factor, calibration.min_samples),
+                previous_timestamp: None,
+            })
+        }
+        None => None,
+    };
+    Ok((model, inputs, calibration))
+}
+
+/// Determines how to convert the reference measurements to Watts, based on the unit of the metric.
+fn reference_kind(metric: &Metric) -> anyhow::Result<ReferenceKind> {
+    let scale = match metric.unit.prefix {
+        UnitPrefix::Nano => 1e-9,
+        UnitPrefix::Micro => 1e-6,
+        UnitPrefix::Milli => 1e-3,
+        UnitPrefix::Plain => 1.0,
+        UnitPrefix::Kilo => 1e3,
+        UnitPrefix::Mega => 1e6,
+        UnitPrefix::Giga => 1e9,
+    };
+    match metric.unit.base_unit {
+        Unit::Watt => Ok(ReferenceKind::Power { scale }),
+        Unit::Joule => Ok(ReferenceKind::Energy { scale }),
+        Unit::WattHour => Ok(ReferenceKind::Energy { scale: scale * 3600.0 }),
+        _ => Err(anyhow!(
+            "the calibration metric '{}' must be a power or an energy, not {}",
+            metric.name,
+            metric.unit.display_name()
+        )),
+    }
+}
@@ -0,0 +1,185 @@
+//! Polynomial power models and their calibration.
+
+/// A polynomial model: `power = intercept + sum_i sum_k coefficients[i][k] * x_i^(k+1)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    pub intercept: f64,
+    /// The coefficients of each input, by increasing degree.
+    pub coefficients: Vec<Vec<f64>>,
+}
+
+impl Model {
+    /// Number of parameters of the model, including the intercept.
+    pub fn n_parameters(&self) -> usize {
+        1 + self.coefficients.iter().map(Vec::len).sum::<usize>()
+    }
+
+    /// Returns the terms of the polynomial for the given inputs: `[1, x_1, x_1^2, ..., x_n, x_n^2, ...]`.
+    pub fn features(&self, inputs: &[f64]) -> Vec<f64> {
+        let mut features = Vec::with_capacity(self.n_parameters());
+        features.push(1.0);
+        for (x, coefficients) in inputs.iter().zip(&self.coefficients) {
+            let mut term = 1.0;
+            for _ in coefficients {
+                term *= x;
+                features.push(term);
+            }
+        }
+        features
+    }
+
+    /// Estimates the power for the given inputs.
+    pub fn estimate(&self, inputs: &[f64]) -> f64 {
+        let parameters = std::iter::once(&self.intercept).chain(self.coefficients.iter().flatten());
+        self.features(inputs).iter().zip(parameters).map(|(f, p)| f * p).sum()
+    }
+
+    /// Replaces the parameters of the model, in the order of [`Model::features`].
+    pub fn set_parameters(&mut self, parameters: &[f64]) {
+        let (intercept, mut rest) = parameters.split_first().expect("no parameter");
+        self.intercept = *intercept;
+        for coefficients in &mut self.coefficients {
+            let (current, next) = rest.split_at(coefficients.len());
+            coefficients.copy_from_slice(current);
+            rest = next;
+        }
+    }
+}
+
+/// Fits the parameters of a model with the least squares method, on the samples received so far.
+///
+/// The old samples are progressively forgotten, so that the model follows the changes of the machine.
+pub struct Calibrator {
+    /// `X^T X`, weighted by the forgetting factor.
+    xtx: Vec<Vec<f64>>,
+    /// `X^T y`, weighted by the forgetting factor.
+    xty: Vec<f64>,
+    forgetting_factor: f64,
+    n_samples: usize,
+    min_samples: usize,
+}
+
+impl Calibrator {
+    pub fn new(n_parameters: usize, forgetting_factor: f64, min_samples: usize) -> Self {
+        Self {
+            xtx: vec![vec![0.0; n_parameters]; n_parameters],
+            xty: vec![0.0; n_parameters],
+            forgetting_factor,
+            n_samples: 0,
+            min_samples: min_samples.max(n_parameters),
+        }
+    }
+
+    /// Adds a sample: the `features` of the inputs and the measured power `y`.
+    pub fn add(&mut self, features: &[f64], y: f64) {
+        for (i, fi) in features.iter().enumerate() {
+            for (j, fj) in features.iter().enumerate() {
+                self.xtx[i][j] = self.forgetting_factor * self.xtx[i][j] + fi * fj;
+            }
+            self.xty[i] = self.forgetting_factor * self.xty[i] + fi * y;
+        }
+        self.n_samples += 1;
+    }
+
+    /// Computes the parameters that minimize the squared error.
+    ///
+    /// Returns `None` if there are not enough samples, or if the samples do not determine the parameters
+    /// (for instance, when an input has always had the same value).
+    pub fn solve(&self) -> Option<Vec<f64>> {
+        if self.n_samples < self.min_samples {
+            return None;
+        }
+        solve_linear_system(self.xtx.clone(), self.xty.clone())
+    }
+}
+
+/// Solves `a * x = b` with the Gaussian elimination (with partial pivoting).
+fn solve_linear_system(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    const EPSILON: f64 = 1e-9;
+    let n = b.len();
+    let scale = a.iter().flatten().fold(0.0_f64, |m, v| m.max(v.abs()));
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() <= EPSILON * scale.max(1.0) {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = a[row][col] / a[col][col];
+            let pivot_row = a[col].clone();
+            for (value, pivot_value) in a[row].iter_mut().zip(pivot_row).skip(col) {
+                *value -= factor * pivot_value;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn estimate() {
+        let mut model = Model {
+            intercept: 40.0,
+            coefficients: vec![vec![0.5, 0.01], vec![2.0]],
+        };
+        assert_eq!(model.n_parameters(), 4);
+        assert_eq!(model.features(&[10.0, 3.0]), vec![1.0, 10.0, 100.0, 3.0]);
+        assert_eq!(model.estimate(&[10.0, 3.0]), 40.0 + 5.0 + 1.0 + 6.0);
+
+        model.set_parameters(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(
+            model,
+            Model {
+                intercept: 1.0,
+                coefficients: vec![vec![2.0, 3.0], vec![4.0]],
+            }
+        );
+    }
+
+    #[test]
+    fn calibration() {
+        // the real power is 30 + 1.5 * cpu + 0.5 * memory
+        let model = Model {
+            intercept: 0.0,
+            coefficients: vec![vec![0.0], vec![0.0]],
+        };
+        let mut calibrator = Calibrator::new(model.n_parameters(), 1.0, 5);
+        for (cpu, memory) in [(0.0, 1.0), (10.0, 2.0), (50.0, 2.0), (100.0, 8.0)] {
+            calibrator.add(&model.features(&[cpu, memory]), 30.0 + 1.5 * cpu + 0.5 * memory);
+        }
+        // not enough samples yet
+        assert_eq!(calibrator.solve(), None);
+
+        calibrator.add(&model.features(&[70.0, 4.0]), 30.0 + 1.5 * 70.0 + 0.5 * 4.0);
+        let parameters = calibrator.solve().unwrap();
+        for (actual, expected) in parameters.iter().zip([30.0, 1.5, 0.5]) {
+            assert!((actual - expected).abs() < 1e-6, "{parameters:?}");
+        }
+    }
+
+    #[test]
+    fn calibration_without_variation() {
+        // the memory never changes: its coefficient cannot be determined
+        let model = Model {
+            intercept: 0.0,
+            coefficients: vec![vec![0.0], vec![0.0]],
+        };
+        let mut calibrator = Calibrator::new(model.n_parameters(), 1.0, 1);
+        for cpu in [0.0, 10.0, 50.0, 100.0] {
+            calibrator.add(&model.features(&[cpu, 4.0]), 30.0 + 1.5 * cpu);
+        }
+        assert_eq!(calibrator.solve(), None);
+    }
+}
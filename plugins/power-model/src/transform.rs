@@ -0,0 +1,151 @@
+use alumet::{
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp},
+    metrics::{RawMetricId, TypedMetricId},
+    pipeline::{
+        Transform,
+        elements::{error::TransformError, transform::TransformContext},
+    },
+    resources::{Resource, ResourceConsumer},
+};
+
+use crate::{
+    config::SeriesConfig,
+    model::{Calibrator, Model},
+};
+
+/// Estimates the power of a resource from the latest values of the model inputs.
+pub struct PowerModelTransform {
+    name: String,
+    model: Model,
+    inputs: Vec<Series>,
+    /// The latest value of each input.
+    values: Vec<Option<f64>>,
+    resource: Resource,
+    result_metric: TypedMetricId<f64>,
+    calibration: Option<Calibration>,
+}
+
+/// A timeseries used by the model.
+pub struct Series {
+    pub metric: RawMetricId,
+    pub filter: SeriesConfig,
+}
+
+impl Series {
+    fn matches(&self, point: &MeasurementPoint) -> bool {
+        point.metric == self.metric && self.filter.accept_point(point)
+    }
+}
+
+pub struct Calibration {
+    pub reference: Series,
+    pub kind: ReferenceKind,
+    pub calibrator: Calibrator,
+    /// Timestamp of the previous reference point, to turn energy into power.
+    pub previous_timestamp: Option<Timestamp>,
+}
+
+/// How to obtain a power, in Watts, from the reference measurements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReferenceKind {
+    /// The reference is a power: multiply it by the scale.
+    Power { scale: f64 },
+    /// The reference is the energy consumed since the previous measurement:
+    /// multiply it by the scale (to get Joules) and divide it by the elapsed time.
+    Energy { scale: f64 },
+}
+
+impl PowerModelTransform {
+    pub fn new(
+        name: String,
+        model: Model,
+        inputs: Vec<Series>,
+        resource: Resource,
+        result_metric: TypedMetricId<f64>,
+        calibration: Option<Calibration>,
+    ) -> Self {
+        let values = vec![None; inputs.len()];
+        Self {
+            name,
+            model,
+            inputs,
+            values,
+            resource,
+            result_metric,
+            calibration,
+        }
+    }
+
+    /// Returns the latest values of the inputs, if all of them are known.
+    fn input_values(&self) -> Option<Vec<f64>> {
+        self.values.iter().copied().collect()
+    }
+
+    fn estimate(&self, timestamp: Timestamp) -> Option<MeasurementPoint> {
+        let inputs = self.input_values()?;
+        // the polynomial can go below zero outside of its domain of validity
+        let power = self.model.estimate(&inputs).max(0.0);
+        Some(MeasurementPoint::new(
+            timestamp,
+            self.result_metric,
+            self.resource.clone(),
+            ResourceConsumer::LocalMachine,
+            power,
+        ))
+    }
+
+    fn calibrate(&mut self, point: &MeasurementPoint) {
+        let Some(calibration) = &mut self.calibration else {
+            return;
+        };
+        let value = point.value.as_f64();
+        let power = match calibration.kind {
+            ReferenceKind::Power { scale } => value * scale,
+            ReferenceKind::Energy { scale } => {
+                let previous = calibration.previous_timestamp.replace(point.timestamp);
+                let elapsed = previous.and_then(|t| point.timestamp.duration_since(t).ok());
+                match elapsed {
+                    Some(dt) if !dt.is_zero() => value * scale / dt.as_secs_f64(),
+                    _ => return,
+                }
+            }
+        };
+        let Some(inputs) = self.values.iter().copied().collect::<Option<Vec<f64>>>() else {
+            return;
+        };
+        calibration.calibrator.add(&self.model.features(&inputs), power);
+        if let Some(parameters) = calibration.calibrator.solve() {
+            self.model.set_parameters(&parameters);
+            log::debug!("model {} calibrated: {:?}", self.name, self.model);
+        }
+    }
+}
+
+impl Transform for PowerModelTransform {
+    fn apply(&mut self, measurements: &mut MeasurementBuffer, _ctx: &TransformContext) -> Result<(), TransformError> {
+        let mut estimates = Vec::new();
+        // The inputs measured at the same time produce only one estimation.
+        let mut pending: Option<Timestamp> = None;
+
+        for point in measurements.iter() {
+            if let Some(i) = self.inputs.iter().position(|input| input.matches(point)) {
+                if let Some(t) = pending.filter(|t| *t != point.timestamp) {
+                    estimates.extend(self.estimate(t));
+                }
+                self.values[i] = Some(point.value.as_f64());
+                pending = Some(point.timestamp);
+            }
+            if self.calibration.as_ref().is_some_and(|c| c.reference.matches(point)) {
+                self.calibrate(point);
+            }
+        }
+        if let Some(t) = pending {
+            estimates.extend(self.estimate(t));
+        }
+
+        for point in estimates {
+            measurements.push(point);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+//! Integration tests for the power model transform.
+
+use std::time::Duration;
+
+use alumet::{
+    agent::{
+        self,
+        plugin::{PluginInfo, PluginSet},
+    },
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+    metrics::{RawMetricId, registry::MetricRegistry},
+    pipeline::naming::TransformName,
+    plugin::PluginMetadata,
+    resources::{Resource, ResourceConsumer},
+    test::RuntimeExpectations,
+    units::Unit,
+};
+use plugin_power_model::PowerModelPlugin;
+use pretty_assertions::assert_eq;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+const CONFIG: &str = r#"
+    [models.estimated_power]
+    intercept = 50.0
+    inputs.cpu = { metric = "cpu_usage_percent", kind = "total", coefficients = [1.0] }
+
+    [models.estimated_power.calibration]
+    metric = "wattmeter_power"
+    min_samples = 3
+    forgetting_factor = 1.0
+"#;
+
+#[test]
+fn estimate_and_calibrate() {
+    let transform = TransformName::from_str("power-model", "model/estimated_power");
+
+    fn point(metric: RawMetricId, t: u64, value: f64) -> MeasurementPoint {
+        MeasurementPoint::new_untyped(
+            Timestamp::from_unix_timestamp(1_700_000_000 + t, 0),
+            metric,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            WrappedMeasurementValue::F64(value),
+        )
+    }
+
+    fn usage(metrics: &TestMetrics, t: u64, kind: &'static str, value: f64) -> MeasurementPoint {
+        point(metrics.cpu_usage_percent, t, value).with_attr("kind", kind)
+    }
+
+    fn estimations(metrics: &TestMetrics, measurements: &MeasurementBuffer) -> Vec<(Timestamp, f64)> {
+        measurements
+            .iter()
+            .filter(|p| p.metric == metrics.estimated_power)
+            .map(|p| (p.timestamp, p.value.as_f64()))
+            .collect()
+    }
+
+    let runtime = RuntimeExpectations::new()
+        .create_metric::<f64>("cpu_usage_percent", Unit::Percent)
+        .create_metric::<f64>("wattmeter_power", Unit::Watt)
+        // the configured coefficients are used before the calibration
+        .test_transform(
+            transform.clone(),
+            |input| {
+                let metrics = TestMetrics::find_in(input.metrics());
+                let mut buf = MeasurementBuffer::new();
+                buf.push(usage(&metrics, 0, "total", 10.0));
+                buf.push(usage(&metrics, 0, "user", 8.0));
+                buf.push(usage(&metrics, 1, "total", 20.0));
+                buf
+            },
+            |output| {
+                let metrics = TestMetrics::find_in(output.metrics());
+                let m = output.measurements();
+                assert_eq!(m.len(), 5, "input measurements should be kept");
+                assert_eq!(
+                    estimations(&metrics, m),
+                    vec![
+                        (point(metrics.estimated_power, 0, 0.0).timestamp, 60.0),
+                        (point(metrics.estimated_power, 1, 0.0).timestamp, 70.0),
+                    ]
+                );
+            },
+        )
+        // the real power is 30 + 2 * cpu: the model is updated after 3 samples
+        .test_transform(
+            transform.clone(),
+            |input| {
+                let metrics = TestMetrics::find_in(input.metrics());
+                let mut buf = MeasurementBuffer::new();
+                for (t, cpu) in [(2, 0.0), (3, 50.0), (4, 100.0)] {
+                    buf.push(usage(&metrics, t, "total", cpu));
+                    buf.push(point(metrics.wattmeter_power, t, 30.0 + 2.0 * cpu));
+                }
+                buf
+            },
+            |output| {
+                let metrics = TestMetrics::find_in(output.metrics());
+                let estimations = estimations(&metrics, output.measurements());
+                let values: Vec<f64> = estimations.iter().map(|(_, v)| (v * 1e6).round() / 1e6).collect();
+                assert_eq!(values, vec![50.0, 100.0, 230.0]);
+            },
+        );
+
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<PowerModelPlugin>(),
+        enabled: true,
+        config: Some(toml::from_str(CONFIG).unwrap()),
+    });
+
+    let agent = agent::Builder::new(plugins)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+
+    // wait for the agent to stop (it is automatically stopped by RuntimeExpectations)
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+struct TestMetrics {
+    cpu_usage_percent: RawMetricId,
+    wattmeter_power: RawMetricId,
+    estimated_power: RawMetricId,
+}
+
+impl TestMetrics {
+    fn find_in(metrics: &MetricRegistry) -> Self {
+        Self {
+            cpu_usage_percent: metrics.by_name("cpu_usage_percent").unwrap().0,
+            wattmeter_power: metrics.by_name("wattmeter_power").unwrap().0,
+            estimated_power: metrics.by_name("estimated_power").unwrap().0,
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! System-level disk I/O metrics read from `/proc/diskstats`.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Seek},
+    path::{Path, PathBuf},
+};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::{TypedMetricId, error::MetricCreationError},
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::{PrefixedUnit, Unit},
+};
+use anyhow::Context;
+use procfs::{DiskStat, DiskStats, FromBufRead};
+use regex::Regex;
+
+/// Size of a sector in `/proc/diskstats`, regardless of the actual sector size of the device.
+const SECTOR_SIZE: u64 = 512;
+
+/// Reads disk I/O statistics from /proc/diskstats.
+pub struct DiskStatsProbe {
+    /// A reader opened to /proc/diskstats.
+    reader: BufReader<File>,
+    /// Path to the directory that contains the block devices in the sysfs.
+    sys_block_path: PathBuf,
+    filter: DiskFilter,
+    /// The previously measured stats of each device, to compute the difference.
+    previous_stats: HashMap<String, DiskStat>,
+    /// Whether each device is a partition (cached, it does not change).
+    partitions: HashMap<String, bool>,
+    metrics: DiskMetrics,
+}
+
+/// Selects the block devices to measure.
+pub struct DiskFilter {
+    /// `true` to measure the partitions as well as the whole disks.
+    pub partitions: bool,
+    /// The devices whose name matches this regex are not measured.
+    pub exclude_regex: Option<Regex>,
+}
+
+pub struct DiskMetrics {
+    bytes: TypedMetricId<u64>,
+    operations: TypedMetricId<u64>,
+    io_time: TypedMetricId<u64>,
+    queue_time: TypedMetricId<u64>,
+}
+
+impl DiskMetrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> Result<Self, MetricCreationError> {
+        Ok(Self {
+            bytes: alumet.create_metric(
+                "disk_bytes",
+                Unit::Byte,
+                "Number of bytes (read/write) per block device",
+            )?,
+            operations: alumet.create_metric(
+                "disk_operations",
+                Unit::Unity,
+                "Number of completed I/O operations (read/write) per block device",
+            )?,
+            io_time: alumet.create_metric(
+                "disk_io_time",
+                PrefixedUnit::milli(Unit::Second),
+                "Time spent by the block device doing I/O operations",
+            )?,
+            queue_time: alumet.create_metric(
+                "disk_queue_time",
+                PrefixedUnit::milli(Unit::Second),
+                "Time spent by the I/O requests in the queue of the block device, weighted by the number of requests",
+            )?,
+        })
+    }
+}
+
+/// Difference between two measurements of the statistics of a block device.
+#[derive(Debug, PartialEq, Eq)]
+struct DiskStatDelta {
+    read_bytes: u64,
+    write_bytes: u64,
+    reads: u64,
+    writes: u64,
+    io_time_ms: u64,
+    queue_time_ms: u64,
+}
+
+impl DiskStatDelta {
+    /// Computes the difference between `prev` and `now`.
+    ///
+    /// Returns `None` if a counter has decreased, which happens when the device is recreated.
+    fn compute_diff(prev: &DiskStat, now: &DiskStat) -> Option<Self> {
+        Some(Self {
+            read_bytes: now.sectors_read.checked_sub(prev.sectors_read)? * SECTOR_SIZE,
+            write_bytes: now.sectors_written.checked_sub(prev.sectors_written)? * SECTOR_SIZE,
+            reads: now.reads.checked_sub(prev.reads)?,
+            writes: now.writes.checked_sub(prev.writes)?,
+            io_time_ms: now.time_in_progress.checked_sub(prev.time_in_progress)?,
+            queue_time_ms: now
+                .weighted_time_in_progress
+                .checked_sub(prev.weighted_time_in_progress)?,
+        })
+    }
+}
+
+impl DiskStatsProbe {
+    pub fn new(
+        metrics: DiskMetrics,
+        filter: DiskFilter,
+        proc_diskstats_path: &str,
+        sys_block_path: impl Into<PathBuf>,
+    ) -> anyhow::Result<Self> {
+        let file = File::open(proc_diskstats_path).with_context(|| format!("could not open {proc_diskstats_path}"))?;
+        Ok(Self {
+            reader: BufReader::new(file),
+            sys_block_path: sys_block_path.into(),
+            filter,
+            previous_stats: HashMap::new(),
+            partitions: HashMap::new(),
+            metrics,
+        })
+    }
+
+    fn accepts(&mut self, device: &str) -> bool {
+        if let Some(regex) = &self.filter.exclude_regex
+            && regex.is_match(device)
+        {
+            return false;
+        }
+        if self.filter.partitions {
+            return true;
+        }
+        let sys_block_path = &self.sys_block_path;
+        let is_partition = *self
+            .partitions
+            .entry(device.to_owned())
+            .or_insert_with(|| is_partition(sys_block_path, device));
+        !is_partition
+    }
+}
+
+/// Checks whether the block device is a partition of a disk, according to the sysfs.
+fn is_partition(sys_block_path: &Path, device: &str) -> bool {
+    // The name of the device in the sysfs uses '!' instead of '/', e.g. "cciss!c0d0".
+    let sysfs_name = device.replace('/', "!");
+    sys_block_path.join(sysfs_name).join("partition").exists()
+}
+
+impl Source for DiskStatsProbe {
+    fn poll(&mut self, acc: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        self.reader.rewind()?;
+        let now = DiskStats::from_buf_read(&mut self.reader)?;
+        let mut new_stats = HashMap::with_capacity(now.0.len());
+        for stat in now.0 {
+            if !self.accepts(&stat.name) {
+                continue;
+            }
+            // Only push deltas, not the baseline value before the plugin starts
+            let delta = self
+                .previous_stats
+                .get(&stat.name)
+                .and_then(|prev| DiskStatDelta::compute_diff(prev, &stat));
+            if let Some(delta) = delta {
+                let point = |metric, value: u64| {
+                    MeasurementPoint::new(
+                        timestamp,
+                        metric,
+                        Resource::LocalMachine,
+                        ResourceConsumer::LocalMachine,
+                        value,
+                    )
+                    .with_attr("device", stat.name.clone())
+                };
+                acc.push(point(self.metrics.bytes, delta.read_bytes).with_attr("direction", "read"));
+                acc.push(point(self.metrics.bytes, delta.write_bytes).with_attr("direction", "write"));
+                acc.push(point(self.metrics.operations, delta.reads).with_attr("direction", "read"));
+                acc.push(point(self.metrics.operations, delta.writes).with_attr("direction", "write"));
+                acc.push(point(self.metrics.io_time, delta.io_time_ms));
+                acc.push(point(self.metrics.queue_time, delta.queue_time_ms));
+            }
+            new_stats.insert(stat.name.clone(), stat);
+        }
+        // forget the devices that have disappeared
+        self.previous_stats = new_stats;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn disk_stat_delta() {
+        let prev = DiskStat::from_line("   8       0 sda 100 5 2000 50 40 2 800 30 0 70 90 0 0 0 0 0 0").unwrap();
+        let now = DiskStat::from_line("   8       0 sda 110 5 2100 55 48 3 1000 36 1 80 105 0 0 0 0 0 0").unwrap();
+        assert_eq!(
+            DiskStatDelta::compute_diff(&prev, &now),
+            Some(DiskStatDelta {
+                read_bytes: 100 * 512,
+                write_bytes: 200 * 512,
+                reads: 10,
+                writes: 8,
+                io_time_ms: 10,
+                queue_time_ms: 15,
+            })
+        );
+        // the device has been recreated, its counters have been reset
+        assert_eq!(DiskStatDelta::compute_diff(&now, &prev), None);
+    }
+}
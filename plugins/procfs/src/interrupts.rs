@@ -0,0 +1,303 @@
+//! Interrupt and softirq counters read from `/proc/interrupts` and `/proc/softirqs`.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::{TypedMetricId, error::MetricCreationError},
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::{Context, anyhow};
+use regex::Regex;
+
+/// Reads the number of interrupts from /proc/interrupts and /proc/softirqs.
+pub struct InterruptsProbe {
+    interrupts: CounterFile,
+    softirqs: CounterFile,
+    filter: InterruptFilter,
+    /// `true` to measure each CPU, `false` to sum the counters of all the CPUs.
+    per_cpu: bool,
+    metrics: InterruptsMetrics,
+}
+
+/// A file that contains interrupt counters, and the previous values of the counters.
+struct CounterFile {
+    path: PathBuf,
+    /// The previously measured counters, by interrupt name, to compute the difference.
+    previous: Option<HashMap<String, Vec<u32>>>,
+}
+
+/// Selects the interrupts to measure.
+pub struct InterruptFilter {
+    /// If set, only the interrupts whose name or description matches this regex are measured.
+    pub include_regex: Option<Regex>,
+    /// The interrupts whose name or description matches this regex are not measured.
+    pub exclude_regex: Option<Regex>,
+}
+
+impl InterruptFilter {
+    fn accepts(&self, name: &str, description: &str) -> bool {
+        let matches = |r: &Regex| r.is_match(name) || r.is_match(description);
+        self.include_regex.as_ref().is_none_or(matches) && !self.exclude_regex.as_ref().is_some_and(matches)
+    }
+}
+
+pub struct InterruptsMetrics {
+    interrupts: TypedMetricId<u64>,
+    softirqs: TypedMetricId<u64>,
+}
+
+impl InterruptsMetrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> Result<Self, MetricCreationError> {
+        Ok(Self {
+            interrupts: alumet.create_metric(
+                "kernel_interrupts",
+                Unit::Unity,
+                "number of hardware interrupts since the previous measurement, per interrupt",
+            )?,
+            softirqs: alumet.create_metric(
+                "kernel_softirqs",
+                Unit::Unity,
+                "number of software interrupts since the previous measurement, per kind",
+            )?,
+        })
+    }
+}
+
+/// Counters of an interrupt, as written in `/proc/interrupts` or `/proc/softirqs`.
+#[derive(Debug, PartialEq)]
+struct InterruptCounters {
+    /// Name of the interrupt: number of the IRQ (`42`), architecture-specific interrupt (`LOC`) or softirq (`NET_RX`).
+    name: String,
+    /// Description of the interrupt, such as the chip and the device (empty for the softirqs).
+    description: String,
+    /// Number of interrupts handled by each CPU, in the order of the header.
+    ///
+    /// Some architecture-specific interrupts (such as `ERR`) only have one global counter.
+    counts: Vec<u32>,
+}
+
+/// Parses the content of `/proc/interrupts` or `/proc/softirqs`.
+///
+/// Returns the ids of the CPUs (given by the header) and the counters of each interrupt.
+///
+/// # Input format
+/// ```text
+///            CPU0       CPU1
+///   0:         36          0   IO-APIC   2-edge      timer
+///  LOC:    1234567    2345678   Local timer interrupts
+///  ERR:          0
+/// ```
+fn parse_interrupts(content: &str) -> anyhow::Result<(Vec<u32>, Vec<InterruptCounters>)> {
+    let mut lines = content.lines();
+    let header = lines.next().context("missing header")?;
+    let cpus = header
+        .split_ascii_whitespace()
+        .map(|cpu| {
+            cpu.strip_prefix("CPU")
+                .and_then(|id| id.parse().ok())
+                .ok_or_else(|| anyhow!("invalid CPU {cpu:?} in header"))
+        })
+        .collect::<anyhow::Result<Vec<u32>>>()?;
+
+    let mut res = Vec::new();
+    for line in lines {
+        let Some((name, rest)) = line.split_once(':') else {
+            continue;
+        };
+        let mut fields = rest.split_ascii_whitespace().peekable();
+        let mut counts = Vec::with_capacity(cpus.len());
+        // the description can contain numbers (such as the hwirq), stop at the number of CPUs
+        while counts.len() < cpus.len()
+            && let Some(count) = fields.peek().and_then(|f| f.parse().ok())
+        {
+            counts.push(count);
+            fields.next();
+        }
+        let description = fields.collect::<Vec<_>>().join(" ");
+        res.push(InterruptCounters {
+            name: name.trim().to_owned(),
+            description,
+            counts,
+        });
+    }
+    Ok((cpus, res))
+}
+
+impl InterruptsProbe {
+    pub fn new(
+        metrics: InterruptsMetrics,
+        filter: InterruptFilter,
+        per_cpu: bool,
+        interrupts_path: impl Into<PathBuf>,
+        softirqs_path: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            interrupts: CounterFile {
+                path: interrupts_path.into(),
+                previous: None,
+            },
+            softirqs: CounterFile {
+                path: softirqs_path.into(),
+                previous: None,
+            },
+            filter,
+            per_cpu,
+            metrics,
+        }
+    }
+}
+
+impl CounterFile {
+    /// Reads the file and pushes the difference between the current counters and the previous ones.
+    fn measure(
+        &mut self,
+        acc: &mut MeasurementAccumulator,
+        timestamp: Timestamp,
+        metric: TypedMetricId<u64>,
+        name_attr: &'static str,
+        filter: &InterruptFilter,
+        per_cpu: bool,
+    ) -> anyhow::Result<()> {
+        let content = std::fs::read_to_string(&self.path).with_context(|| format!("could not read {:?}", self.path))?;
+        let (cpus, interrupts) =
+            parse_interrupts(&content).with_context(|| format!("invalid content in {:?}", self.path))?;
+        let mut new_counts = HashMap::with_capacity(interrupts.len());
+        for interrupt in interrupts {
+            if !filter.accepts(&interrupt.name, &interrupt.description) {
+                continue;
+            }
+            // Only push deltas, not the baseline value before the plugin starts
+            // (skip the interrupts whose number of counters has changed, i.e. when a CPU has been hotplugged)
+            let prev = self.previous.as_ref().and_then(|p| p.get(&interrupt.name));
+            if let Some(prev) = prev
+                && prev.len() == interrupt.counts.len()
+            {
+                // the counters are 32-bit and wrap around
+                let deltas: Vec<u64> = prev
+                    .iter()
+                    .zip(&interrupt.counts)
+                    .map(|(prev, now)| now.wrapping_sub(*prev) as u64)
+                    .collect();
+                let point = |resource, value| {
+                    let point =
+                        MeasurementPoint::new(timestamp, metric, resource, ResourceConsumer::LocalMachine, value)
+                            .with_attr(name_attr, interrupt.name.clone());
+                    if interrupt.description.is_empty() {
+                        point
+                    } else {
+                        point.with_attr("description", interrupt.description.clone())
+                    }
+                };
+                if per_cpu && deltas.len() == cpus.len() {
+                    for (cpu, delta) in cpus.iter().zip(deltas) {
+                        acc.push(point(Resource::CpuCore { id: *cpu }, delta));
+                    }
+                } else {
+                    acc.push(point(Resource::LocalMachine, deltas.iter().sum()));
+                }
+            }
+            new_counts.insert(interrupt.name, interrupt.counts);
+        }
+        self.previous = Some(new_counts);
+        Ok(())
+    }
+}
+
+impl Source for InterruptsProbe {
+    fn poll(&mut self, acc: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        self.interrupts.measure(
+            acc,
+            timestamp,
+            self.metrics.interrupts,
+            "irq",
+            &self.filter,
+            self.per_cpu,
+        )?;
+        self.softirqs.measure(
+            acc,
+            timestamp,
+            self.metrics.softirqs,
+            "kind",
+            &self.filter,
+            self.per_cpu,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use regex::Regex;
+
+    use super::{InterruptCounters, InterruptFilter, parse_interrupts};
+
+    fn counters(name: &str, description: &str, counts: Vec<u32>) -> InterruptCounters {
+        InterruptCounters {
+            name: name.to_owned(),
+            description: description.to_owned(),
+            counts,
+        }
+    }
+
+    #[test]
+    fn interrupts() {
+        let content = "           CPU0       CPU2       \n  \
+                         0:         36          0   IO-APIC   2-edge      timer\n \
+                        27:          5          7   GICv3  27 Level     arch_timer\n \
+                       LOC:    1234567    2345678   Local timer interrupts\n \
+                       ERR:          3\n";
+        let (cpus, res) = parse_interrupts(content).unwrap();
+        assert_eq!(cpus, vec![0, 2]);
+        assert_eq!(
+            res,
+            vec![
+                counters("0", "IO-APIC 2-edge timer", vec![36, 0]),
+                counters("27", "GICv3 27 Level arch_timer", vec![5, 7]),
+                counters("LOC", "Local timer interrupts", vec![1234567, 2345678]),
+                counters("ERR", "", vec![3]),
+            ]
+        );
+        assert!(parse_interrupts("          CPU0  GPU1\n").is_err());
+    }
+
+    #[test]
+    fn softirqs() {
+        let content = "                    CPU0       CPU1\n          \
+                                 HI:          0          1\n       \
+                              TIMER:    1092041    1092042\n";
+        let (cpus, res) = parse_interrupts(content).unwrap();
+        assert_eq!(cpus, vec![0, 1]);
+        assert_eq!(
+            res,
+            vec![
+                counters("HI", "", vec![0, 1]),
+                counters("TIMER", "", vec![1092041, 1092042]),
+            ]
+        );
+    }
+
+    #[test]
+    fn interrupt_filter() {
+        let all = InterruptFilter {
+            include_regex: None,
+            exclude_regex: None,
+        };
+        assert!(all.accepts("LOC", "Local timer interrupts"));
+
+        let filter = InterruptFilter {
+            include_regex: Some(Regex::new("nvme|^LOC$|^NET_").unwrap()),
+            exclude_regex: Some(Regex::new("nvme0q0").unwrap()),
+        };
+        assert!(filter.accepts("LOC", "Local timer interrupts"));
+        assert!(filter.accepts("130", "PCI-MSIX-0000:01:00.0 1-edge nvme0q1"));
+        assert!(filter.accepts("NET_RX", ""));
+        assert!(!filter.accepts("129", "PCI-MSIX-0000:01:00.0 0-edge nvme0q0"));
+        assert!(!filter.accepts("0", "IO-APIC 2-edge timer"));
+        assert!(!filter.accepts("TIMER", ""));
+    }
+}
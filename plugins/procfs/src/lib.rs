@@ -11,11 +11,16 @@ use anyhow::Context;
 use procfs::{Current, CurrentSI};
 use rlimit::{Resource, getrlimit, setrlimit};
 
+mod disk;
+mod interrupts;
 mod kernel;
+mod load;
 mod memory;
 mod network;
+mod numa;
 mod process;
 mod serde_regex;
+mod sockets;
 
 pub struct ProcfsPlugin {
     config: Option<config::Config>,
@@ -48,12 +53,24 @@ impl AlumetPlugin for ProcfsPlugin {
         if config.kernel.enabled {
             start_kernel_probe(config.kernel, alumet)?;
         }
+        if config.load.enabled {
+            start_load_probe(config.load, alumet)?;
+        }
         if config.memory.enabled {
             start_memory_probe(config.memory, alumet)?;
         }
         if config.network.enabled {
             start_network_probe(config.network, alumet)?;
         }
+        if config.disk.enabled {
+            start_disk_probe(config.disk, alumet)?;
+        }
+        if config.sockets.enabled {
+            start_sockets_probe(config.sockets, alumet)?;
+        }
+        if config.interrupts.enabled {
+            start_interrupts_probe(config.interrupts, alumet)?;
+        }
         if config.processes.enabled {
             let metrics = process::ProcessMetrics {
                 metric_cpu_time_delta: alumet
@@ -69,6 +86,27 @@ impl AlumetPlugin for ProcfsPlugin {
                 metric_memory_usage: alumet
                     .create_metric("memory_usage", Unit::Byte, "Memory usage")
                     .context("unable to register metric memory for process probe")?,
+                metric_io_delta: alumet
+                    .create_metric(
+                        "io_delta",
+                        Unit::Byte,
+                        "Number of bytes read from or written to the storage since the previous measurement",
+                    )
+                    .context("unable to register metric io_delta for process probe")?,
+                metric_page_faults_delta: alumet
+                    .create_metric(
+                        "page_faults_delta",
+                        Unit::Unity,
+                        "Number of page faults since the previous measurement",
+                    )
+                    .context("unable to register metric page_faults_delta for process probe")?,
+                metric_numa_memory: alumet
+                    .create_metric(
+                        "memory_numa_usage",
+                        Unit::Byte,
+                        "Memory of the process that is placed on a NUMA node",
+                    )
+                    .context("unable to register metric memory_numa_usage for process probe")?,
             };
             match config.processes.strategy {
                 config::ProcessWatchStrategy::SystemWatcher => {
@@ -101,18 +139,82 @@ fn start_kernel_probe(
     Ok(())
 }
 
+fn start_load_probe(
+    config_load: config::LoadMonitoring,
+    alumet: &mut alumet::plugin::AlumetPluginStart<'_>,
+) -> Result<(), anyhow::Error> {
+    let trigger = TriggerSpec::at_interval(config_load.poll_interval);
+    let metrics = load::LoadMetrics::new(alumet).context("unable to register metrics for load probe")?;
+    let source = load::LoadProbe::new(metrics, procfs::LoadAverage::PATH, procfs::Uptime::PATH);
+    alumet.add_source("load", Box::new(source), trigger)?;
+    Ok(())
+}
+
 fn start_network_probe(
     config_network: config::NetworkMonitoring,
     alumet: &mut alumet::plugin::AlumetPluginStart<'_>,
 ) -> Result<(), anyhow::Error> {
     let trigger = TriggerSpec::at_interval(config_network.poll_interval);
     let metrics = network::NetworkMetrics::new(alumet).context("unable to register metrics for network probe")?;
-    let source = network::NetworkProbe::new(metrics, procfs::net::InterfaceDeviceStatus::PATH)
+    let filter = network::InterfaceFilter {
+        include_regex: config_network.include_regex,
+        exclude_regex: config_network.exclude_regex,
+    };
+    let source = network::NetworkProbe::new(metrics, filter, procfs::net::InterfaceDeviceStatus::PATH)
         .context("unable to create network probe")?;
     alumet.add_source("network", Box::new(source), trigger)?;
     Ok(())
 }
 
+fn start_disk_probe(
+    config_disk: config::DiskMonitoring,
+    alumet: &mut alumet::plugin::AlumetPluginStart<'_>,
+) -> Result<(), anyhow::Error> {
+    let trigger = TriggerSpec::at_interval(config_disk.poll_interval);
+    let metrics = disk::DiskMetrics::new(alumet).context("unable to register metrics for disk probe")?;
+    let filter = disk::DiskFilter {
+        partitions: config_disk.partitions,
+        exclude_regex: config_disk.exclude_regex,
+    };
+    let source = disk::DiskStatsProbe::new(metrics, filter, procfs::DiskStats::PATH, "/sys/class/block")
+        .context("unable to create disk probe")?;
+    alumet.add_source("disk", Box::new(source), trigger)?;
+    Ok(())
+}
+
+fn start_sockets_probe(
+    config_sockets: config::SocketsMonitoring,
+    alumet: &mut alumet::plugin::AlumetPluginStart<'_>,
+) -> Result<(), anyhow::Error> {
+    let trigger = TriggerSpec::at_interval(config_sockets.poll_interval);
+    let metrics = sockets::SocketsMetrics::new(alumet).context("unable to register metrics for sockets probe")?;
+    let source = sockets::SocketsProbe::new(metrics, "/proc/net/snmp", "/proc/net/sockstat");
+    alumet.add_source("sockets", Box::new(source), trigger)?;
+    Ok(())
+}
+
+fn start_interrupts_probe(
+    config_interrupts: config::InterruptsMonitoring,
+    alumet: &mut alumet::plugin::AlumetPluginStart<'_>,
+) -> Result<(), anyhow::Error> {
+    let trigger = TriggerSpec::at_interval(config_interrupts.poll_interval);
+    let metrics =
+        interrupts::InterruptsMetrics::new(alumet).context("unable to register metrics for interrupts probe")?;
+    let filter = interrupts::InterruptFilter {
+        include_regex: config_interrupts.include_regex,
+        exclude_regex: config_interrupts.exclude_regex,
+    };
+    let source = interrupts::InterruptsProbe::new(
+        metrics,
+        filter,
+        config_interrupts.per_cpu,
+        "/proc/interrupts",
+        "/proc/softirqs",
+    );
+    alumet.add_source("interrupts", Box::new(source), trigger)?;
+    Ok(())
+}
+
 fn start_memory_probe(
     config_memory: config::MeminfoMonitoring,
     alumet: &mut alumet::plugin::AlumetPluginStart<'_>,
@@ -148,10 +250,13 @@ fn start_process_watcher(
                 pid: group.pid.map(|x| i32::try_from(x).unwrap_or(-1)),
                 ppid: group.ppid.map(|x| i32::try_from(x).unwrap_or(-1)),
                 exe_regex: group.exe_regex,
+                name_regex: group.name_regex,
+                cgroup_regex: group.cgroup_regex,
             };
             let settings = process::MonitoringSettings {
                 poll_interval: group.poll_interval,
                 flush_interval: group.flush_interval,
+                numa: group.numa,
             };
             (filter, settings)
         })
@@ -181,6 +286,7 @@ fn setup_process_event_listener(
     let settings = process::MonitoringSettings {
         poll_interval: config_processes.events.poll_interval,
         flush_interval: config_processes.events.flush_interval,
+        numa: config_processes.events.numa,
     };
 
     alumet.on_pipeline_start(move |ctx| {
@@ -222,8 +328,16 @@ mod config {
     #[serde(deny_unknown_fields)]
     pub struct Config {
         pub kernel: KernelStatsMonitoring,
+        #[serde(default)]
+        pub load: LoadMonitoring,
         pub memory: MeminfoMonitoring,
         pub network: NetworkMonitoring,
+        #[serde(default)]
+        pub disk: DiskMonitoring,
+        #[serde(default)]
+        pub sockets: SocketsMonitoring,
+        #[serde(default)]
+        pub interrupts: InterruptsMonitoring,
         pub processes: ProcessMonitoring,
     }
 
@@ -235,12 +349,65 @@ mod config {
         pub poll_interval: Duration,
     }
 
+    #[derive(Serialize, Deserialize)]
+    pub struct LoadMonitoring {
+        #[serde(default = "default_enabled")]
+        pub enabled: bool,
+        #[serde(with = "humantime_serde")]
+        pub poll_interval: Duration,
+    }
+
     #[derive(Serialize, Deserialize)]
     pub struct NetworkMonitoring {
         #[serde(default = "default_enabled")]
         pub enabled: bool,
         #[serde(with = "humantime_serde")]
         pub poll_interval: Duration,
+        /// Only measure the interfaces whose name matches this regex.
+        #[serde(default, with = "serde_regex::option")]
+        pub include_regex: Option<Regex>,
+        /// Do not measure the interfaces whose name matches this regex.
+        #[serde(default, with = "serde_regex::option")]
+        pub exclude_regex: Option<Regex>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct DiskMonitoring {
+        #[serde(default = "default_enabled")]
+        pub enabled: bool,
+        #[serde(with = "humantime_serde")]
+        pub poll_interval: Duration,
+        /// `true` to measure the partitions as well as the whole disks.
+        #[serde(default)]
+        pub partitions: bool,
+        /// Do not measure the block devices whose name matches this regex.
+        #[serde(default, with = "serde_regex::option")]
+        pub exclude_regex: Option<Regex>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct SocketsMonitoring {
+        #[serde(default = "default_enabled")]
+        pub enabled: bool,
+        #[serde(with = "humantime_serde")]
+        pub poll_interval: Duration,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct InterruptsMonitoring {
+        #[serde(default = "default_enabled")]
+        pub enabled: bool,
+        #[serde(with = "humantime_serde")]
+        pub poll_interval: Duration,
+        /// `true` to measure each CPU, `false` to sum the interrupts of all the CPUs.
+        #[serde(default)]
+        pub per_cpu: bool,
+        /// Only measure the interrupts whose name or description matches this regex.
+        #[serde(default, with = "serde_regex::option")]
+        pub include_regex: Option<Regex>,
+        /// Do not measure the interrupts whose name or description matches this regex.
+        #[serde(default, with = "serde_regex::option")]
+        pub exclude_regex: Option<Regex>,
     }
 
     #[derive(Serialize, Deserialize)]
@@ -286,6 +453,9 @@ mod config {
         pub poll_interval: Duration,
         #[serde(with = "humantime_serde")]
         pub flush_interval: Duration,
+        /// `true` to measure the placement of the memory on the NUMA nodes.
+        #[serde(default)]
+        pub numa: bool,
     }
 
     #[derive(Serialize, Deserialize)]
@@ -300,6 +470,14 @@ mod config {
         #[serde(with = "serde_regex::option")]
         pub exe_regex: Option<Regex>,
 
+        /// Only monitor the processes whose name (as in `/proc/<pid>/comm`) matches this regex.
+        #[serde(default, with = "serde_regex::option")]
+        pub name_regex: Option<Regex>,
+
+        /// Only monitor the processes that belong to a cgroup whose path matches this regex.
+        #[serde(default, with = "serde_regex::option")]
+        pub cgroup_regex: Option<Regex>,
+
         /// How frequently should the processes information be refreshed.
         #[serde(with = "humantime_serde")]
         pub poll_interval: Duration,
@@ -307,6 +485,10 @@ mod config {
         /// How frequently should the processes information be flushed to the rest of the pipeline.
         #[serde(with = "humantime_serde")]
         pub flush_interval: Duration,
+
+        /// `true` to measure the placement of the memory on the NUMA nodes (reads `/proc/<pid>/numa_maps`).
+        #[serde(default)]
+        pub numa: bool,
     }
 
     impl Default for KernelStatsMonitoring {
@@ -318,11 +500,55 @@ mod config {
         }
     }
 
+    impl Default for LoadMonitoring {
+        fn default() -> Self {
+            Self {
+                enabled: true,
+                poll_interval: Duration::from_secs(5),
+            }
+        }
+    }
+
     impl Default for NetworkMonitoring {
         fn default() -> Self {
             Self {
                 enabled: true,
                 poll_interval: Duration::from_secs(5),
+                include_regex: None,
+                exclude_regex: None,
+            }
+        }
+    }
+
+    impl Default for DiskMonitoring {
+        fn default() -> Self {
+            Self {
+                enabled: true,
+                poll_interval: Duration::from_secs(5),
+                partitions: false,
+                // virtual devices that are not backed by a disk
+                exclude_regex: Some(Regex::new(r"^(loop|ram|zram)\d+$").unwrap()),
+            }
+        }
+    }
+
+    impl Default for SocketsMonitoring {
+        fn default() -> Self {
+            Self {
+                enabled: true,
+                poll_interval: Duration::from_secs(5),
+            }
+        }
+    }
+
+    impl Default for InterruptsMonitoring {
+        fn default() -> Self {
+            Self {
+                enabled: true,
+                poll_interval: Duration::from_secs(5),
+                per_cpu: false,
+                include_regex: None,
+                exclude_regex: None,
             }
         }
     }
@@ -359,12 +585,16 @@ mod config {
                     pid: None,
                     ppid: None,
                     exe_regex: None, // any process; Regex::new(".*").unwrap() would also work
+                    name_regex: None,
+                    cgroup_regex: None,
                     poll_interval: Duration::from_secs(2),
                     flush_interval: Duration::from_secs(4),
+                    numa: false,
                 }],
                 events: EventModeProcessMonitoring {
                     poll_interval: Duration::from_secs(1),
                     flush_interval: Duration::from_secs(4),
+                    numa: false,
                 },
             }
         }
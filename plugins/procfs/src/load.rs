@@ -0,0 +1,169 @@
+//! System load and uptime, read from `/proc/loadavg` and `/proc/uptime`.
+
+use std::path::PathBuf;
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::{TypedMetricId, error::MetricCreationError},
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::{Context, anyhow};
+
+/// Reads the load averages and the uptime of the system.
+pub struct LoadProbe {
+    loadavg_path: PathBuf,
+    uptime_path: PathBuf,
+    metrics: LoadMetrics,
+}
+
+pub struct LoadMetrics {
+    load_average: TypedMetricId<f64>,
+    n_threads: TypedMetricId<u64>,
+    uptime: TypedMetricId<f64>,
+}
+
+impl LoadMetrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> Result<Self, MetricCreationError> {
+        Ok(Self {
+            load_average: alumet.create_metric(
+                "kernel_load_average",
+                Unit::Unity,
+                "average number of runnable or blocked tasks, over a window",
+            )?,
+            n_threads: alumet.create_metric(
+                "kernel_n_threads",
+                Unit::Unity,
+                "number of threads (and processes) that currently exist on the system",
+            )?,
+            uptime: alumet.create_metric("kernel_uptime", Unit::Second, "time elapsed since the system booted")?,
+        })
+    }
+}
+
+/// Content of `/proc/loadavg`.
+#[derive(Debug, PartialEq)]
+struct LoadAverage {
+    one: f64,
+    five: f64,
+    fifteen: f64,
+    n_threads: u64,
+}
+
+/// Parses the content of `/proc/loadavg`.
+///
+/// # Input format
+/// ```text
+/// 0.75 0.62 0.48 2/1154 52871
+/// ```
+fn parse_loadavg(content: &str) -> anyhow::Result<LoadAverage> {
+    let mut fields = content.split_ascii_whitespace();
+    let mut next = || fields.next().ok_or_else(|| anyhow!("missing field in {content:?}"));
+    let one = next()?.parse().context("invalid load average (1m)")?;
+    let five = next()?.parse().context("invalid load average (5m)")?;
+    let fifteen = next()?.parse().context("invalid load average (15m)")?;
+    let (_runnable, n_threads) = next()?
+        .split_once('/')
+        .with_context(|| format!("invalid number of threads in {content:?}"))?;
+    let n_threads = n_threads.parse().context("invalid number of threads")?;
+    Ok(LoadAverage {
+        one,
+        five,
+        fifteen,
+        n_threads,
+    })
+}
+
+/// Parses the content of `/proc/uptime` and returns the uptime, in seconds.
+///
+/// # Input format
+/// ```text
+/// 350735.47 2789162.30
+/// ```
+fn parse_uptime(content: &str) -> anyhow::Result<f64> {
+    let uptime = content
+        .split_ascii_whitespace()
+        .next()
+        .with_context(|| format!("missing uptime in {content:?}"))?;
+    uptime.parse().context("invalid uptime")
+}
+
+impl LoadProbe {
+    pub fn new(metrics: LoadMetrics, loadavg_path: impl Into<PathBuf>, uptime_path: impl Into<PathBuf>) -> Self {
+        Self {
+            loadavg_path: loadavg_path.into(),
+            uptime_path: uptime_path.into(),
+            metrics,
+        }
+    }
+}
+
+impl Source for LoadProbe {
+    fn poll(&mut self, acc: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let content = std::fs::read_to_string(&self.loadavg_path)
+            .with_context(|| format!("could not read {:?}", self.loadavg_path))?;
+        let load = parse_loadavg(&content)?;
+        let content = std::fs::read_to_string(&self.uptime_path)
+            .with_context(|| format!("could not read {:?}", self.uptime_path))?;
+        let uptime = parse_uptime(&content)?;
+
+        for (window, value) in [("1m", load.one), ("5m", load.five), ("15m", load.fifteen)] {
+            acc.push(
+                MeasurementPoint::new(
+                    timestamp,
+                    self.metrics.load_average,
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("window", window),
+            );
+        }
+        acc.push(MeasurementPoint::new(
+            timestamp,
+            self.metrics.n_threads,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            load.n_threads,
+        ));
+        acc.push(MeasurementPoint::new(
+            timestamp,
+            self.metrics.uptime,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            uptime,
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{LoadAverage, parse_loadavg, parse_uptime};
+
+    #[test]
+    fn loadavg() {
+        assert_eq!(
+            parse_loadavg("0.75 0.62 0.48 2/1154 52871\n").unwrap(),
+            LoadAverage {
+                one: 0.75,
+                five: 0.62,
+                fifteen: 0.48,
+                n_threads: 1154,
+            }
+        );
+        assert!(parse_loadavg("0.75 0.62\n").is_err());
+        assert!(parse_loadavg("0.75 0.62 0.48 1154 52871\n").is_err());
+    }
+
+    #[test]
+    fn uptime() {
+        assert_eq!(parse_uptime("350735.47 2789162.30\n").unwrap(), 350735.47);
+        assert!(parse_uptime("").is_err());
+        assert!(parse_uptime("abc 0.0").is_err());
+    }
+}
@@ -19,15 +19,32 @@ use procfs::{
     FromBufRead,
     net::{DeviceStatus, InterfaceDeviceStatus},
 };
+use regex::Regex;
 
 /// Reads network metrics from /proc/net/dev
 pub struct NetworkProbe {
     path: PathBuf,
     reader: ProcNetReader,
     previous: Option<InterfaceDeviceStatus>,
+    filter: InterfaceFilter,
     metrics: NetworkMetrics,
 }
 
+/// Selects the network interfaces to measure.
+pub struct InterfaceFilter {
+    /// If set, only the interfaces whose name matches this regex are measured.
+    pub include_regex: Option<Regex>,
+    /// The interfaces whose name matches this regex are not measured.
+    pub exclude_regex: Option<Regex>,
+}
+
+impl InterfaceFilter {
+    fn accepts(&self, interface: &str) -> bool {
+        self.include_regex.as_ref().is_none_or(|r| r.is_match(interface))
+            && !self.exclude_regex.as_ref().is_some_and(|r| r.is_match(interface))
+    }
+}
+
 /// How to read `/proc/net/dev`.
 enum ProcNetReader {
     /// Optimized version, where we open the file only once.
@@ -83,7 +100,7 @@ impl ProcNetReader {
 }
 
 impl NetworkProbe {
-    pub fn new(metrics: NetworkMetrics, path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+    pub fn new(metrics: NetworkMetrics, filter: InterfaceFilter, path: impl Into<PathBuf>) -> anyhow::Result<Self> {
         let path = path.into();
         let mut file = File::open(&path).with_context(|| format!("cannot open {path:?}"))?;
         let reader = match file.rewind() {
@@ -100,6 +117,7 @@ impl NetworkProbe {
             path,
             reader,
             previous: None,
+            filter,
             metrics,
         })
     }
@@ -111,6 +129,9 @@ impl Source for NetworkProbe {
         // Only push deltas, not the baseline value before the plugin starts
         if let Some(ref prev) = self.previous {
             for (if_name, now_stats) in &now.0 {
+                if !self.filter.accepts(if_name) {
+                    continue;
+                }
                 // Also consider the first value when a new interface appears at runtime
                 let prev_stats = match prev.0.get(if_name) {
                     Some(p) => p,
@@ -237,3 +258,30 @@ impl Source for NetworkProbe {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+
+    use super::InterfaceFilter;
+
+    #[test]
+    fn interface_filter() {
+        let all = InterfaceFilter {
+            include_regex: None,
+            exclude_regex: None,
+        };
+        assert!(all.accepts("lo"));
+        assert!(all.accepts("eth0"));
+
+        let filter = InterfaceFilter {
+            include_regex: Some(Regex::new("^(eth|en)").unwrap()),
+            exclude_regex: Some(Regex::new("^eth1$").unwrap()),
+        };
+        assert!(filter.accepts("eth0"));
+        assert!(filter.accepts("enp3s0"));
+        assert!(!filter.accepts("eth1"));
+        assert!(!filter.accepts("lo"));
+        assert!(!filter.accepts("docker0"));
+    }
+}
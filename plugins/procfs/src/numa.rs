@@ -0,0 +1,132 @@
+//! NUMA placement of the memory of the processes.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+/// Parses the content of `/proc/<pid>/numa_maps` and returns the memory of the process
+/// that is placed on each NUMA node, in bytes.
+///
+/// Each line describes a memory mapping, for instance:
+/// ```txt
+/// 7f2b1c000000 default anon=512 dirty=512 N0=256 N1=256 kernelpagesize_kB=4
+/// ```
+/// where `N<node>=<pages>` is the number of pages of the mapping on the node.
+///
+/// See <https://man7.org/linux/man-pages/man7/numa.7.html>.
+pub fn parse_numa_maps(content: &str, default_page_size: u64) -> BTreeMap<u32, u64> {
+    let mut bytes_per_node = BTreeMap::new();
+    for line in content.lines() {
+        let mut pages_per_node = Vec::new();
+        let mut page_size = default_page_size;
+        for field in line.split_ascii_whitespace() {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+            if let Some(node) = key.strip_prefix('N') {
+                if let (Ok(node), Ok(pages)) = (node.parse::<u32>(), value.parse::<u64>()) {
+                    pages_per_node.push((node, pages));
+                }
+            } else if key == "kernelpagesize_kB"
+                && let Ok(kb) = value.parse::<u64>()
+            {
+                page_size = kb * 1024;
+            }
+        }
+        for (node, pages) in pages_per_node {
+            *bytes_per_node.entry(node).or_insert(0) += pages * page_size;
+        }
+    }
+    bytes_per_node
+}
+
+/// Finds the CPU package of each NUMA node, by looking at the package of the first CPU of the node.
+///
+/// The nodes that have no CPU (for instance CXL memory expanders) are not in the map.
+///
+/// ## Expected file layout
+///
+/// ```txt
+/// /sys/devices/system/
+/// |− node
+///     |− node0
+///         |− cpulist
+///     |− node1
+///         |− cpulist
+/// |− cpu
+///     |− cpu0
+///         |− topology
+///             |− physical_package_id
+/// ```
+pub fn node_packages(sysfs_system: &Path) -> HashMap<u32, u32> {
+    let mut res = HashMap::new();
+    let Ok(nodes) = std::fs::read_dir(sysfs_system.join("node")) else {
+        return res;
+    };
+    for entry in nodes.filter_map(|e| e.ok()) {
+        let node = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.strip_prefix("node")?.parse::<u32>().ok());
+        let Some(node) = node else {
+            continue;
+        };
+        // cpulist looks like "0-7,16-23", or is empty
+        let first_cpu = std::fs::read_to_string(entry.path().join("cpulist"))
+            .ok()
+            .and_then(|list| list.trim().split([',', '-']).next()?.parse::<u32>().ok());
+        let package = first_cpu.and_then(|cpu| {
+            let path = sysfs_system.join(format!("cpu/cpu{cpu}/topology/physical_package_id"));
+            std::fs::read_to_string(path).ok()?.trim().parse::<u32>().ok()
+        });
+        if let Some(package) = package {
+            res.insert(node, package);
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn numa_maps() {
+        let content = "\
+00400000 default file=/usr/bin/cat mapped=7 N0=7 kernelpagesize_kB=4
+7f2b1c000000 default anon=512 dirty=512 active=0 N0=256 N1=256 kernelpagesize_kB=4
+7f2b40000000 bind:1 anon=2 dirty=2 N1=2 kernelpagesize_kB=2048
+7ffd8a1e3000 default stack anon=3 dirty=3 N0=3
+7ffd8a1f9000 default
+";
+        let res = parse_numa_maps(content, 4096);
+        assert_eq!(
+            res,
+            BTreeMap::from([(0, (7 + 256 + 3) * 4096), (1, 256 * 4096 + 2 * 2048 * 1024)])
+        );
+        assert!(parse_numa_maps("", 4096).is_empty());
+    }
+
+    #[test]
+    fn packages_of_nodes() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("node/node0/cpulist", "0-3,8-11\n");
+        write("node/node1/cpulist", "4-7,12-15\n");
+        write("node/node2/cpulist", "\n");
+        write("node/possible", "0-2\n");
+        write("cpu/cpu0/topology/physical_package_id", "0\n");
+        write("cpu/cpu4/topology/physical_package_id", "1\n");
+
+        assert_eq!(node_packages(root), HashMap::from([(0, 0), (1, 1)]));
+        assert!(node_packages(&root.join("missing")).is_empty());
+    }
+}
@@ -3,7 +3,9 @@
 use std::{
     collections::HashMap,
     fs::File,
-    io::{self, BufReader, Seek},
+    io::{self, BufReader, Read, Seek},
+    path::Path,
+    sync::Arc,
     time::Duration,
 };
 
@@ -26,6 +28,8 @@ use anyhow::Context;
 use procfs::{self, FromRead, ProcError, process::Process};
 use regex::Regex;
 
+use crate::numa;
+
 /// Reads process stats from `/proc/<pid>` for some `<pid>`.
 struct ProcessStatsProbe {
     /// The process id, as reported by the kernel.
@@ -40,9 +44,17 @@ struct ProcessStatsProbe {
     reader_stat: BufReader<File>,
     /// A reader opened to `/proc/<pid>/statm`
     reader_statm: BufReader<File>,
+    /// A reader opened to `/proc/<pid>/io`, if we are allowed to read it.
+    reader_io: Option<BufReader<File>>,
+    /// A reader opened to `/proc/<pid>/numa_maps`, if the NUMA placement is measured and we are allowed to read it.
+    reader_numa_maps: Option<BufReader<File>>,
+    /// The CPU package of each NUMA node.
+    node_packages: Arc<HashMap<u32, u32>>,
 
     /// The previously measured stats, to compute the difference.
     previous_general_stats: Option<(Timestamp, procfs::process::Stat)>,
+    /// The previously measured I/O stats, to compute the difference.
+    previous_io_stats: Option<procfs::process::Io>,
     /// If true, push the first statistics (i.e. push even is `previous_general_stats` is empty).
     ///
     /// Useful when we measure a process that has just been started.
@@ -61,13 +73,19 @@ impl ProcessStatsProbe {
         ns_per_ticks: u64,
         push_first_stats: bool,
         metrics: ProcessMetrics,
+        node_packages: Option<Arc<HashMap<u32, u32>>>,
     ) -> Result<Self, procfs::ProcError> {
+        let reader_numa_maps = node_packages.as_ref().and_then(|_| open_numa_maps(&process));
         Ok(Self {
             pid: process.pid,
             ns_per_ticks,
             reader_stat: BufReader::new(process.open_relative("stat")?),
             reader_statm: BufReader::new(process.open_relative("statm")?),
+            reader_io: open_io_stats(&process),
+            reader_numa_maps,
+            node_packages: node_packages.unwrap_or_default(),
             previous_general_stats: None,
+            previous_io_stats: None,
             push_first_stats,
             metrics,
             page_size: procfs::page_size(),
@@ -79,6 +97,32 @@ impl ProcessStatsProbe {
     }
 }
 
+/// Opens `/proc/<pid>/io`, which requires the permission to ptrace the process.
+///
+/// Returns `None` if the file cannot be opened: the I/O of the process is not measured in that case.
+fn open_io_stats(process: &Process) -> Option<BufReader<File>> {
+    match process.open_relative("io") {
+        Ok(file) => Some(BufReader::new(file)),
+        Err(e) => {
+            log::debug!("cannot read the I/O statistics of process {}: {e}", process.pid);
+            None
+        }
+    }
+}
+
+/// Opens `/proc/<pid>/numa_maps`, which requires the permission to ptrace the process.
+///
+/// Returns `None` if the file cannot be opened: the NUMA placement of the process is not measured in that case.
+fn open_numa_maps(process: &Process) -> Option<BufReader<File>> {
+    match process.open_relative("numa_maps") {
+        Ok(file) => Some(BufReader::new(file)),
+        Err(e) => {
+            log::debug!("cannot read the NUMA placement of process {}: {e}", process.pid);
+            None
+        }
+    }
+}
+
 fn stop_if_proc_not_found(err: ProcError) -> PollError {
     match err {
         ProcError::NotFound(_) => PollError::NormalStop,
@@ -111,8 +155,8 @@ impl Source for ProcessStatsProbe {
         // TODO how to report the state of the process in the timeseries?
         // let state = now.state()?;
 
-        // Compute CPU usage in the last time slice.
-        let (prev_t, cpu_usage) = match self.previous_general_stats.take() {
+        // Compute CPU usage and page faults in the last time slice.
+        let (prev_t, cpu_usage, faults) = match self.previous_general_stats.take() {
             Some((prev_t, prev_stat)) => (
                 Some(prev_t),
                 Some(DeltaCpuTime::compute_diff(
@@ -120,13 +164,29 @@ impl Source for ProcessStatsProbe {
                     &general_stats,
                     self.ns_per_ticks,
                 )),
+                Some((
+                    general_stats.minflt.saturating_sub(prev_stat.minflt),
+                    general_stats.majflt.saturating_sub(prev_stat.majflt),
+                )),
             ),
             None if self.push_first_stats => (
                 None,
                 Some(DeltaCpuTime::compute_first(&general_stats, self.ns_per_ticks)),
+                Some((general_stats.minflt, general_stats.majflt)),
             ),
-            None => (None, None),
+            None => (None, None, None),
         };
+        if let Some((minor, major)) = faults {
+            let metric = self.metrics.metric_page_faults_delta;
+            buffer.push(
+                MeasurementPoint::new(t, metric, Resource::LocalMachine, consumer.clone(), minor)
+                    .with_attr("kind", "minor"),
+            );
+            buffer.push(
+                MeasurementPoint::new(t, metric, Resource::LocalMachine, consumer.clone(), major)
+                    .with_attr("kind", "major"),
+            );
+        }
         if let Some(measurements) = cpu_usage {
             measurements.push_cpu_measurements(
                 self.metrics.metric_cpu_time_delta,
@@ -205,12 +265,55 @@ impl Source for ProcessStatsProbe {
                 t,
                 self.metrics.metric_memory_usage,
                 Resource::LocalMachine,
-                consumer,
+                consumer.clone(),
                 self.pages_to_bytes(memory_stats.size),
             )
             .with_attr("kind", "virtual"),
         );
 
+        // Measure the placement of the memory on the NUMA nodes.
+        if let Some(reader) = &mut self.reader_numa_maps {
+            reader.rewind().map_err(stop_if_io_not_found)?;
+            let mut content = String::new();
+            reader.read_to_string(&mut content).map_err(stop_if_io_not_found)?;
+            for (node, bytes) in numa::parse_numa_maps(&content, self.page_size) {
+                let resource = match self.node_packages.get(&node) {
+                    Some(pkg_id) => Resource::Dram { pkg_id: *pkg_id },
+                    None => Resource::custom("numa_node", node.to_string()),
+                };
+                buffer.push(
+                    MeasurementPoint::new(t, self.metrics.metric_numa_memory, resource, consumer.clone(), bytes)
+                        .with_attr("numa_node", node as u64),
+                );
+            }
+        }
+
+        // Compute the I/O in the last time slice.
+        if let Some(reader_io) = &mut self.reader_io {
+            reader_io.rewind().map_err(stop_if_io_not_found)?;
+            let io_stats = procfs::process::Io::from_read(reader_io).map_err(stop_if_proc_not_found)?;
+            let delta = match self.previous_io_stats.take() {
+                Some(prev) => Some((
+                    io_stats.read_bytes.saturating_sub(prev.read_bytes),
+                    io_stats.write_bytes.saturating_sub(prev.write_bytes),
+                )),
+                None if self.push_first_stats => Some((io_stats.read_bytes, io_stats.write_bytes)),
+                None => None,
+            };
+            if let Some((read, write)) = delta {
+                let metric = self.metrics.metric_io_delta;
+                buffer.push(
+                    MeasurementPoint::new(t, metric, Resource::LocalMachine, consumer.clone(), read)
+                        .with_attr("kind", "read"),
+                );
+                buffer.push(
+                    MeasurementPoint::new(t, metric, Resource::LocalMachine, consumer, write)
+                        .with_attr("kind", "write"),
+                );
+            }
+            self.previous_io_stats = Some(io_stats);
+        }
+
         Ok(())
     }
 }
@@ -298,6 +401,8 @@ pub struct ManualProcessMonitor {
 struct ProcessSourceSpawner {
     ns_per_ticks: u64,
     metrics: ProcessMetrics,
+    /// The CPU package of each NUMA node, shared by the sources.
+    node_packages: Arc<HashMap<u32, u32>>,
 }
 
 #[derive(Clone)]
@@ -305,6 +410,9 @@ pub struct ProcessMetrics {
     pub metric_cpu_time_delta: TypedMetricId<u64>,
     pub metric_cpu_percent: TypedMetricId<f64>,
     pub metric_memory_usage: TypedMetricId<u64>,
+    pub metric_io_delta: TypedMetricId<u64>,
+    pub metric_page_faults_delta: TypedMetricId<u64>,
+    pub metric_numa_memory: TypedMetricId<u64>,
 }
 
 #[derive(Debug)]
@@ -312,12 +420,16 @@ pub struct ProcessFilter {
     pub pid: Option<i32>,
     pub ppid: Option<i32>,
     pub exe_regex: Option<Regex>,
+    pub name_regex: Option<Regex>,
+    pub cgroup_regex: Option<Regex>,
 }
 
 #[derive(Debug)]
 pub struct MonitoringSettings {
     pub poll_interval: Duration,
     pub flush_interval: Duration,
+    /// `true` to measure the placement of the memory on the NUMA nodes.
+    pub numa: bool,
 }
 
 #[derive(PartialEq, Eq)]
@@ -337,10 +449,9 @@ impl ProcessFingerprint {
 
 impl ManualProcessMonitor {
     pub fn new(alumet_handle: PluginControlHandle, metrics: ProcessMetrics, settings: MonitoringSettings) -> Self {
-        let ns_per_ticks = ns_per_ticks();
         Self {
             alumet_handle,
-            source_spawner: ProcessSourceSpawner { ns_per_ticks, metrics },
+            source_spawner: ProcessSourceSpawner::new(metrics),
             settings,
         }
     }
@@ -392,13 +503,11 @@ impl ProcessWatcher {
         metrics: ProcessMetrics,
         groups: Vec<(ProcessFilter, MonitoringSettings)>,
     ) -> Self {
-        let tps = procfs::ticks_per_second();
-        let ns_per_ticks = ns_per_ticks();
         Self {
             watched_processes: HashMap::new(),
             alumet_handle,
             monitoring: MultiProcessMonitoring {
-                source_spawner: ProcessSourceSpawner { ns_per_ticks, metrics },
+                source_spawner: ProcessSourceSpawner::new(metrics),
                 groups,
             },
         }
@@ -491,6 +600,14 @@ impl MultiProcessMonitoring {
 }
 
 impl ProcessSourceSpawner {
+    fn new(metrics: ProcessMetrics) -> Self {
+        Self {
+            ns_per_ticks: ns_per_ticks(),
+            metrics,
+            node_packages: Arc::new(numa::node_packages(Path::new("/sys/devices/system"))),
+        }
+    }
+
     fn create_source_in(
         &self,
         p: Process,
@@ -509,8 +626,14 @@ impl ProcessSourceSpawner {
             })?;
         log::trace!("adding source {source_name} with trigger specification {trigger:?}");
         let source = Box::new(
-            ProcessStatsProbe::new(p, self.ns_per_ticks, true, self.metrics.clone())
-                .with_context(|| format!("failed to create source {source_name}"))?,
+            ProcessStatsProbe::new(
+                p,
+                self.ns_per_ticks,
+                true,
+                self.metrics.clone(),
+                settings.numa.then(|| self.node_packages.clone()),
+            )
+            .with_context(|| format!("failed to create source {source_name}"))?,
         );
         create_many.add_source(&source_name, source, trigger);
         Ok(())
@@ -542,12 +665,24 @@ impl ProcessFilter {
         if let Some(r) = &self.exe_regex {
             // cf. https://unix.stackexchange.com/questions/629869/getting-the-executable-name-in-linux-from-proc-and-detect-if-its-truncated
             // TODO in case of PermissionDenied error, print a hint about ptrace access mode PTRACE_MODE_READ_FSCREDS (requires cap SYS_PTRACE or process of same user)
-            if let Some(path_string) = p.exe()?.to_str() {
-                if r.is_match(path_string) {
-                    return Ok(false);
-                }
+            match p.exe()?.to_str() {
+                Some(path_string) if r.is_match(path_string) => (),
+                _ => return Ok(false),
             }
         }
+
+        if let Some(r) = &self.name_regex
+            && !r.is_match(&p.stat()?.comm)
+        {
+            return Ok(false);
+        }
+
+        // with cgroup v1, the process belongs to one cgroup per hierarchy: any of them can match
+        if let Some(r) = &self.cgroup_regex
+            && !p.cgroups()?.0.iter().any(|cgroup| r.is_match(&cgroup.pathname))
+        {
+            return Ok(false);
+        }
         Ok(true)
     }
 }
@@ -0,0 +1,291 @@
+//! System-level socket statistics read from `/proc/net/snmp` and `/proc/net/sockstat`.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::{TypedMetricId, error::MetricCreationError},
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::{Context, anyhow};
+
+/// Counters of `/proc/net/snmp` that are measured, with their metric and `kind` attribute.
+const SNMP_COUNTERS: [(&str, &str, SnmpMetric, &str); 13] = [
+    ("Tcp", "InSegs", SnmpMetric::TcpSegments, "received"),
+    ("Tcp", "OutSegs", SnmpMetric::TcpSegments, "sent"),
+    ("Tcp", "RetransSegs", SnmpMetric::TcpSegments, "retransmitted"),
+    ("Tcp", "InErrs", SnmpMetric::TcpSegments, "error"),
+    ("Tcp", "ActiveOpens", SnmpMetric::TcpConnectionEvents, "active_open"),
+    ("Tcp", "PassiveOpens", SnmpMetric::TcpConnectionEvents, "passive_open"),
+    ("Tcp", "AttemptFails", SnmpMetric::TcpConnectionEvents, "failed_attempt"),
+    ("Tcp", "EstabResets", SnmpMetric::TcpConnectionEvents, "reset"),
+    ("Udp", "InDatagrams", SnmpMetric::UdpDatagrams, "received"),
+    ("Udp", "OutDatagrams", SnmpMetric::UdpDatagrams, "sent"),
+    ("Udp", "NoPorts", SnmpMetric::UdpDatagrams, "no_port"),
+    ("Udp", "RcvbufErrors", SnmpMetric::UdpDatagrams, "receive_buffer_error"),
+    ("Udp", "SndbufErrors", SnmpMetric::UdpDatagrams, "send_buffer_error"),
+];
+
+#[derive(Clone, Copy)]
+enum SnmpMetric {
+    TcpSegments,
+    TcpConnectionEvents,
+    UdpDatagrams,
+}
+
+/// Reads socket statistics from /proc/net/snmp and /proc/net/sockstat.
+pub struct SocketsProbe {
+    snmp_path: PathBuf,
+    sockstat_path: PathBuf,
+    page_size: u64,
+    /// The previously measured counters of `/proc/net/snmp`, to compute the difference.
+    previous_snmp: Option<HashMap<(String, String), u64>>,
+    metrics: SocketsMetrics,
+}
+
+pub struct SocketsMetrics {
+    tcp_segments: TypedMetricId<u64>,
+    tcp_connection_events: TypedMetricId<u64>,
+    udp_datagrams: TypedMetricId<u64>,
+    tcp_connections: TypedMetricId<u64>,
+    sockets_in_use: TypedMetricId<u64>,
+    socket_memory: TypedMetricId<u64>,
+}
+
+impl SocketsMetrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> Result<Self, MetricCreationError> {
+        Ok(Self {
+            tcp_segments: alumet.create_metric(
+                "tcp_segments",
+                Unit::Unity,
+                "Number of TCP segments since the previous measurement, by kind",
+            )?,
+            tcp_connection_events: alumet.create_metric(
+                "tcp_connection_events",
+                Unit::Unity,
+                "Number of TCP connections opened, failed or reset since the previous measurement",
+            )?,
+            udp_datagrams: alumet.create_metric(
+                "udp_datagrams",
+                Unit::Unity,
+                "Number of UDP datagrams since the previous measurement, by kind",
+            )?,
+            tcp_connections: alumet.create_metric(
+                "tcp_connections",
+                Unit::Unity,
+                "Number of TCP connections, by state",
+            )?,
+            sockets_in_use: alumet.create_metric(
+                "sockets_in_use",
+                Unit::Unity,
+                "Number of sockets in use, by protocol",
+            )?,
+            socket_memory: alumet.create_metric(
+                "socket_memory",
+                Unit::Byte,
+                "Memory used by the buffers of the sockets, by protocol",
+            )?,
+        })
+    }
+}
+
+/// Parses the content of `/proc/net/snmp`, which is made of pairs of lines (header and values).
+///
+/// The negative values (such as `Tcp: MaxConn -1`) are ignored.
+///
+/// # Input format
+/// ```text
+/// Tcp: RtoAlgorithm RtoMin RtoMax MaxConn ActiveOpens PassiveOpens ...
+/// Tcp: 1 200 120000 -1 3456 789 ...
+/// ```
+fn parse_snmp(content: &str) -> anyhow::Result<HashMap<(String, String), u64>> {
+    let mut res = HashMap::new();
+    let mut lines = content.lines();
+    while let Some(header) = lines.next() {
+        let values = lines
+            .next()
+            .with_context(|| format!("missing values after {header:?}"))?;
+        let (protocol, keys) = header
+            .split_once(':')
+            .with_context(|| format!("invalid header {header:?}"))?;
+        let (protocol2, values) = values
+            .split_once(':')
+            .with_context(|| format!("invalid values {values:?}"))?;
+        if protocol != protocol2 {
+            return Err(anyhow!("header {protocol:?} followed by values {protocol2:?}"));
+        }
+        for (key, value) in keys.split_ascii_whitespace().zip(values.split_ascii_whitespace()) {
+            if let Ok(value) = value.parse::<u64>() {
+                res.insert((protocol.to_owned(), key.to_owned()), value);
+            }
+        }
+    }
+    Ok(res)
+}
+
+/// Parses the content of `/proc/net/sockstat`.
+///
+/// # Input format
+/// ```text
+/// sockets: used 1234
+/// TCP: inuse 10 orphan 0 tw 5 alloc 20 mem 3
+/// UDP: inuse 3 mem 1
+/// ```
+fn parse_sockstat(content: &str) -> anyhow::Result<HashMap<(String, String), u64>> {
+    let mut res = HashMap::new();
+    for line in content.lines() {
+        let Some((protocol, fields)) = line.split_once(':') else {
+            continue;
+        };
+        let mut fields = fields.split_ascii_whitespace();
+        while let (Some(key), Some(value)) = (fields.next(), fields.next()) {
+            let value = value
+                .parse()
+                .with_context(|| format!("invalid value for {key} in line {line:?}"))?;
+            res.insert((protocol.to_owned(), key.to_owned()), value);
+        }
+    }
+    Ok(res)
+}
+
+impl SocketsProbe {
+    pub fn new(metrics: SocketsMetrics, snmp_path: impl Into<PathBuf>, sockstat_path: impl Into<PathBuf>) -> Self {
+        Self {
+            snmp_path: snmp_path.into(),
+            sockstat_path: sockstat_path.into(),
+            page_size: procfs::page_size(),
+            previous_snmp: None,
+            metrics,
+        }
+    }
+}
+
+impl Source for SocketsProbe {
+    fn poll(&mut self, acc: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let point = |metric, value: u64, key: &'static str, attr: &'static str| {
+            MeasurementPoint::new(
+                timestamp,
+                metric,
+                Resource::LocalMachine,
+                ResourceConsumer::LocalMachine,
+                value,
+            )
+            .with_attr(key, attr)
+        };
+
+        // Counters of the TCP and UDP protocols.
+        let content =
+            std::fs::read_to_string(&self.snmp_path).with_context(|| format!("could not read {:?}", self.snmp_path))?;
+        let snmp = parse_snmp(&content).with_context(|| format!("invalid content in {:?}", self.snmp_path))?;
+        // Only push deltas, not the baseline value before the plugin starts
+        if let Some(prev) = &self.previous_snmp {
+            for (protocol, key, metric, kind) in SNMP_COUNTERS {
+                let entry = (protocol.to_owned(), key.to_owned());
+                // skip the counters that have decreased, which happens when they wrap around on 32-bit systems
+                if let (Some(now), Some(prev)) = (snmp.get(&entry), prev.get(&entry))
+                    && let Some(delta) = now.checked_sub(*prev)
+                {
+                    let metric = match metric {
+                        SnmpMetric::TcpSegments => self.metrics.tcp_segments,
+                        SnmpMetric::TcpConnectionEvents => self.metrics.tcp_connection_events,
+                        SnmpMetric::UdpDatagrams => self.metrics.udp_datagrams,
+                    };
+                    acc.push(point(metric, delta, "kind", kind));
+                }
+            }
+        }
+        if let Some(established) = snmp.get(&(String::from("Tcp"), String::from("CurrEstab"))) {
+            acc.push(point(
+                self.metrics.tcp_connections,
+                *established,
+                "state",
+                "established",
+            ));
+        }
+        self.previous_snmp = Some(snmp);
+
+        // Sockets in use and memory of the buffers.
+        let content = std::fs::read_to_string(&self.sockstat_path)
+            .with_context(|| format!("could not read {:?}", self.sockstat_path))?;
+        let sockstat =
+            parse_sockstat(&content).with_context(|| format!("invalid content in {:?}", self.sockstat_path))?;
+        let get = |protocol: &str, key: &str| sockstat.get(&(protocol.to_owned(), key.to_owned())).copied();
+        for (state, key) in [("time_wait", "tw"), ("orphan", "orphan")] {
+            if let Some(value) = get("TCP", key) {
+                acc.push(point(self.metrics.tcp_connections, value, "state", state));
+            }
+        }
+        for (protocol, sockstat_protocol, sockstat_key) in [
+            ("all", "sockets", "used"),
+            ("tcp", "TCP", "inuse"),
+            ("udp", "UDP", "inuse"),
+            ("raw", "RAW", "inuse"),
+        ] {
+            if let Some(value) = get(sockstat_protocol, sockstat_key) {
+                acc.push(point(self.metrics.sockets_in_use, value, "protocol", protocol));
+            }
+        }
+        for (protocol, sockstat_protocol) in [("tcp", "TCP"), ("udp", "UDP")] {
+            // the memory is expressed in pages
+            if let Some(pages) = get(sockstat_protocol, "mem") {
+                acc.push(point(
+                    self.metrics.socket_memory,
+                    pages * self.page_size,
+                    "protocol",
+                    protocol,
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{parse_snmp, parse_sockstat};
+
+    #[test]
+    fn snmp() {
+        let content = "Ip: Forwarding DefaultTTL\n\
+                       Ip: 1 64\n\
+                       Tcp: RtoAlgorithm MaxConn ActiveOpens CurrEstab RetransSegs\n\
+                       Tcp: 1 -1 3456 12 78\n\
+                       Udp: InDatagrams OutDatagrams\n\
+                       Udp: 100 200\n";
+        let res = parse_snmp(content).unwrap();
+        let get = |protocol: &str, key: &str| res.get(&(protocol.to_owned(), key.to_owned())).copied();
+        assert_eq!(get("Ip", "DefaultTTL"), Some(64));
+        assert_eq!(get("Tcp", "MaxConn"), None);
+        assert_eq!(get("Tcp", "ActiveOpens"), Some(3456));
+        assert_eq!(get("Tcp", "CurrEstab"), Some(12));
+        assert_eq!(get("Tcp", "RetransSegs"), Some(78));
+        assert_eq!(get("Udp", "OutDatagrams"), Some(200));
+        assert_eq!(res.len(), 8);
+
+        assert!(parse_snmp("Tcp: ActiveOpens\n").is_err());
+        assert!(parse_snmp("Tcp: ActiveOpens\nUdp: 1\n").is_err());
+    }
+
+    #[test]
+    fn sockstat() {
+        let content = "sockets: used 1234\n\
+                       TCP: inuse 10 orphan 0 tw 5 alloc 20 mem 3\n\
+                       UDP: inuse 3 mem 1\n\
+                       FRAG: inuse 0 memory 0\n";
+        let res = parse_sockstat(content).unwrap();
+        let get = |protocol: &str, key: &str| res.get(&(protocol.to_owned(), key.to_owned())).copied();
+        assert_eq!(get("sockets", "used"), Some(1234));
+        assert_eq!(get("TCP", "tw"), Some(5));
+        assert_eq!(get("TCP", "mem"), Some(3));
+        assert_eq!(get("UDP", "inuse"), Some(3));
+        assert_eq!(get("FRAG", "memory"), Some(0));
+        assert_eq!(res.len(), 10);
+
+        assert!(parse_sockstat("TCP: inuse abc\n").is_err());
+    }
+}
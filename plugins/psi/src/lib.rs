@@ -0,0 +1,97 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::source::{Metrics, PressureFile, PsiSource};
+
+mod pressure;
+mod source;
+
+/// Measures the Pressure Stall Information (PSI) of the system.
+pub struct PsiPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for PsiPlugin {
+    fn name() -> &'static str {
+        "psi"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(PsiPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let mut files = Vec::with_capacity(self.config.resources.len());
+        for name in &self.config.resources {
+            let path = self.config.pressure_path.join(name);
+            if path.is_file() {
+                files.push(PressureFile {
+                    name: name.to_owned(),
+                    path,
+                });
+            } else {
+                log::warn!("{path:?} does not exist, the pressure of {name} will not be measured");
+            }
+        }
+        if files.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: no pressure file found in {:?} (is PSI enabled in the kernel?)",
+                self.config.pressure_path
+            ));
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = PsiSource::new(files, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("pressure", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the directory that contains the pressure files.
+    pub pressure_path: PathBuf,
+
+    /// The resources to measure, which are the names of the pressure files.
+    pub resources: Vec<String>,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            pressure_path: PathBuf::from("/proc/pressure"),
+            resources: ["cpu", "memory", "io"].map(String::from).to_vec(),
+        }
+    }
+}
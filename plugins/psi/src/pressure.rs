@@ -0,0 +1,103 @@
+//! Parsing of the Pressure Stall Information files.
+
+use anyhow::{Context, anyhow};
+
+/// Stall statistics of one line of a pressure file.
+#[derive(Debug, Default, PartialEq)]
+pub struct StallStats {
+    /// Percentage of stalled time over the last 10 seconds.
+    pub avg10: f64,
+    /// Percentage of stalled time over the last 60 seconds.
+    pub avg60: f64,
+    /// Percentage of stalled time over the last 300 seconds.
+    pub avg300: f64,
+    /// Total stalled time, in microseconds.
+    pub total: u64,
+}
+
+/// Parses the content of a pressure file, such as `/proc/pressure/memory`.
+///
+/// Returns the statistics of each line, with its kind (`some` or `full`).
+///
+/// # Input format
+/// ```text
+/// some avg10=0.12 avg60=0.30 avg300=0.27 total=1473224
+/// full avg10=0.00 avg60=0.10 avg300=0.09 total=623451
+/// ```
+pub fn parse_pressure(content: &str) -> anyhow::Result<Vec<(&'static str, StallStats)>> {
+    let mut res = Vec::with_capacity(2);
+    for line in content.lines() {
+        let mut fields = line.split_ascii_whitespace();
+        let kind = match fields.next() {
+            Some("some") => "some",
+            Some("full") => "full",
+            _ => continue,
+        };
+        let mut stats = StallStats::default();
+        for field in fields {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| anyhow!("invalid field {field:?} in line {line:?}"))?;
+            let context = || format!("invalid value for {key} in line {line:?}");
+            match key {
+                "avg10" => stats.avg10 = value.parse().with_context(context)?,
+                "avg60" => stats.avg60 = value.parse().with_context(context)?,
+                "avg300" => stats.avg300 = value.parse().with_context(context)?,
+                "total" => stats.total = value.parse().with_context(context)?,
+                _ => (),
+            }
+        }
+        res.push((kind, stats));
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StallStats, parse_pressure};
+
+    #[test]
+    fn parse_some_and_full() {
+        let content = "some avg10=0.12 avg60=0.30 avg300=0.27 total=1473224\n\
+                       full avg10=0.00 avg60=0.10 avg300=0.09 total=623451\n";
+        assert_eq!(
+            parse_pressure(content).unwrap(),
+            vec![
+                (
+                    "some",
+                    StallStats {
+                        avg10: 0.12,
+                        avg60: 0.30,
+                        avg300: 0.27,
+                        total: 1473224,
+                    }
+                ),
+                (
+                    "full",
+                    StallStats {
+                        avg10: 0.0,
+                        avg60: 0.10,
+                        avg300: 0.09,
+                        total: 623451,
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_some_only() {
+        // before Linux 5.13, /proc/pressure/cpu only has a "some" line
+        let content = "some avg10=5.00 avg60=2.50 avg300=1.00 total=99\n";
+        let res = parse_pressure(content).unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].0, "some");
+        assert_eq!(res[0].1.total, 99);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(parse_pressure("some avg10=abc avg60=0.00 avg300=0.00 total=0\n").is_err());
+        assert!(parse_pressure("some avg10 avg60=0.00 avg300=0.00 total=0\n").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::{PrefixedUnit, Unit},
+};
+use anyhow::Context;
+
+use crate::pressure::parse_pressure;
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    avg: TypedMetricId<f64>,
+    stall_time_delta: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            avg: alumet.create_metric(
+                "pressure_avg",
+                Unit::Percent,
+                "Share of time in which the tasks were stalled on a resource, averaged over a window",
+            )?,
+            stall_time_delta: alumet.create_metric(
+                "pressure_stall_time_delta",
+                PrefixedUnit::micro(Unit::Second),
+                "Time in which the tasks were stalled on a resource since the previous measurement",
+            )?,
+        })
+    }
+}
+
+/// A resource whose pressure is measured, such as `cpu`.
+pub struct PressureFile {
+    /// Name of the resource.
+    pub name: String,
+    /// Path to the pressure file of the resource.
+    pub path: PathBuf,
+}
+
+/// Measurement source that reads the Pressure Stall Information of the system.
+pub struct PsiSource {
+    files: Vec<MeasuredFile>,
+    metrics: Metrics,
+}
+
+struct MeasuredFile {
+    file: PressureFile,
+    /// The previous total stall time of each kind, to compute the difference.
+    previous_totals: HashMap<&'static str, u64>,
+}
+
+impl PsiSource {
+    pub fn new(files: Vec<PressureFile>, metrics: Metrics) -> Self {
+        let files = files
+            .into_iter()
+            .map(|file| MeasuredFile {
+                file,
+                previous_totals: HashMap::new(),
+            })
+            .collect();
+        Self { files, metrics }
+    }
+}
+
+impl Source for PsiSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for f in &mut self.files {
+            let path = &f.file.path;
+            let content = std::fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+            let stats = parse_pressure(&content).with_context(|| format!("failed to parse {path:?}"))?;
+            for (kind, stall) in stats {
+                for (window, avg) in [("10s", stall.avg10), ("60s", stall.avg60), ("300s", stall.avg300)] {
+                    measurements.push(
+                        MeasurementPoint::new(
+                            timestamp,
+                            self.metrics.avg,
+                            Resource::LocalMachine,
+                            ResourceConsumer::LocalMachine,
+                            avg,
+                        )
+                        .with_attr("pressure", f.file.name.clone())
+                        .with_attr("kind", kind)
+                        .with_attr("window", window),
+                    );
+                }
+                // Only push deltas, not the baseline value before the plugin starts
+                if let Some(prev) = f.previous_totals.insert(kind, stall.total) {
+                    measurements.push(
+                        MeasurementPoint::new(
+                            timestamp,
+                            self.metrics.stall_time_delta,
+                            Resource::LocalMachine,
+                            ResourceConsumer::LocalMachine,
+                            stall.total.saturating_sub(prev),
+                        )
+                        .with_attr("pressure", f.file.name.clone())
+                        .with_attr("kind", kind),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
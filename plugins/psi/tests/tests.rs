@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::{PrefixedUnit, Unit},
+};
+use plugin_psi::{Config, PsiPlugin};
+use pretty_assertions::assert_eq;
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_psi() {
+    let root = tempdir().unwrap();
+    let config = Config {
+        pressure_path: root.path().join("pressure"),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no pressure file)");
+}
+
+#[test]
+fn plugin_with_pressure_files() {
+    let root = tempdir().unwrap();
+    std::fs::write(
+        root.path().join("cpu"),
+        "some avg10=12.50 avg60=6.00 avg300=1.25 total=1000\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.path().join("memory"),
+        "some avg10=0.50 avg60=0.00 avg300=0.00 total=200\nfull avg10=0.25 avg60=0.00 avg300=0.00 total=100\n",
+    )
+    .unwrap();
+    // no io file: it should be ignored
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        pressure_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<f64>("pressure_avg", Unit::Percent)
+        .expect_metric::<u64>("pressure_stall_time_delta", PrefixedUnit::micro(Unit::Second))
+        .expect_source("psi", "pressure");
+
+    let runtime = RuntimeExpectations::new().test_source(
+        SourceName::from_str("psi", "pressure"),
+        || {},
+        |ctx| {
+            let m = ctx.measurements();
+            let points: Vec<_> = m
+                .iter()
+                .map(|p| {
+                    let attr = |key: &str| p.attributes().find(|(k, _)| *k == key).unwrap().1.to_string();
+                    (attr("pressure"), attr("kind"), attr("window"), p.value.as_f64())
+                })
+                .collect();
+            let point = |pressure: &str, kind: &str, window: &str, value: f64| {
+                (pressure.to_owned(), kind.to_owned(), window.to_owned(), value)
+            };
+            // no stall time at the first measurement, only the averages
+            assert_eq!(
+                points,
+                vec![
+                    point("cpu", "some", "10s", 12.5),
+                    point("cpu", "some", "60s", 6.0),
+                    point("cpu", "some", "300s", 1.25),
+                    point("memory", "some", "10s", 0.5),
+                    point("memory", "some", "60s", 0.0),
+                    point("memory", "some", "300s", 0.0),
+                    point("memory", "full", "10s", 0.25),
+                    point("memory", "full", "60s", 0.0),
+                    point("memory", "full", "300s", 0.0),
+                ]
+            );
+        },
+    );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<PsiPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -0,0 +1,354 @@
+//! The `alumet` Python module, which gives the scripts access to the agent.
+
+use std::{str::FromStr, time::Duration};
+
+use alumet::{
+    measurement::{self, Timestamp, WrappedMeasurementType, WrappedMeasurementValue},
+    metrics::RawMetricId,
+    resources::{Resource, ResourceConsumer},
+    units::PrefixedUnit,
+};
+use anyhow::{Context, anyhow};
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::PyDict,
+};
+
+/// Registers the `alumet` module, so that the scripts can `import alumet`.
+pub fn register(py: Python<'_>) -> PyResult<()> {
+    let module = PyModule::new(py, "alumet")?;
+    module.add_class::<MeasurementPoint>()?;
+    module.add_class::<AlumetStart>()?;
+    module.add_function(wrap_pyfunction!(log_message, &module)?)?;
+    py.import("sys")?.getattr("modules")?.set_item("alumet", module)?;
+    Ok(())
+}
+
+/// Converts a Python exception to an error, with its traceback.
+pub fn python_error(py: Python<'_>, err: PyErr) -> anyhow::Error {
+    let traceback = err.traceback(py).and_then(|tb| tb.format().ok()).unwrap_or_default();
+    anyhow!("{traceback}{err}")
+}
+
+/// Writes a message to the logs of the agent.
+#[pyfunction(name = "log")]
+fn log_message(level: &str, message: &str) -> PyResult<()> {
+    let level = log::Level::from_str(level).map_err(|_| PyValueError::new_err(format!("invalid log level {level}")))?;
+    log::log!(level, "{message}");
+    Ok(())
+}
+
+/// Value of a measurement point.
+#[derive(FromPyObject, IntoPyObject, Clone, Debug)]
+pub enum Value {
+    U64(u64),
+    F64(f64),
+}
+
+/// Value of an attribute.
+///
+/// `Bool` comes first because a Python `bool` is also an `int`.
+#[derive(FromPyObject, IntoPyObject)]
+enum AttributeValue {
+    Bool(bool),
+    U64(u64),
+    F64(f64),
+    String(String),
+}
+
+/// A measurement point, as seen by the Python scripts.
+#[pyclass(module = "alumet")]
+pub struct MeasurementPoint {
+    /// Name of the metric.
+    #[pyo3(get, set)]
+    metric: String,
+    #[pyo3(get, set)]
+    value: Value,
+    /// Timestamp, in nanoseconds since the Unix epoch.
+    #[pyo3(get, set)]
+    timestamp: u64,
+    /// Kind and id of the resource.
+    #[pyo3(get, set)]
+    resource: (String, String),
+    /// Kind and id of the resource consumer.
+    #[pyo3(get, set)]
+    consumer: (String, String),
+    #[pyo3(get, set)]
+    attributes: Py<PyDict>,
+}
+
+#[pymethods]
+impl MeasurementPoint {
+    /// Creates a measurement point. By default, it is timestamped now and measures the local machine.
+    #[new]
+    #[pyo3(signature = (metric, value, timestamp=None, resource=None, consumer=None, attributes=None))]
+    fn new(
+        py: Python<'_>,
+        metric: String,
+        value: Value,
+        timestamp: Option<u64>,
+        resource: Option<(String, String)>,
+        consumer: Option<(String, String)>,
+        attributes: Option<Bound<'_, PyDict>>,
+    ) -> Self {
+        let local_machine = || (String::from("local_machine"), String::new());
+        Self {
+            metric,
+            value,
+            timestamp: timestamp.unwrap_or_else(|| unix_nanos(Timestamp::now())),
+            resource: resource.unwrap_or_else(local_machine),
+            consumer: consumer.unwrap_or_else(local_machine),
+            attributes: attributes.unwrap_or_else(|| PyDict::new(py)).unbind(),
+        }
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "MeasurementPoint(metric={:?}, value={:?}, timestamp={}, resource={:?}, consumer={:?}, attributes={})",
+            self.metric,
+            self.value,
+            self.timestamp,
+            self.resource,
+            self.consumer,
+            self.attributes.bind(py)
+        )
+    }
+}
+
+impl MeasurementPoint {
+    /// Converts a point of the pipeline.
+    pub fn from_alumet(py: Python<'_>, point: &measurement::MeasurementPoint, metric: String) -> PyResult<Self> {
+        let value = match point.value {
+            WrappedMeasurementValue::U64(v) => Value::U64(v),
+            WrappedMeasurementValue::F64(v) => Value::F64(v),
+        };
+        let attributes = PyDict::new(py);
+        for (key, value) in point.attributes() {
+            let value = match value {
+                measurement::AttributeValue::U64(v) => AttributeValue::U64(*v),
+                measurement::AttributeValue::F64(v) => AttributeValue::F64(*v),
+                measurement::AttributeValue::Bool(v) => AttributeValue::Bool(*v),
+                other => AttributeValue::String(other.to_string()),
+            };
+            attributes.set_item(key, value)?;
+        }
+        Ok(Self {
+            metric,
+            value,
+            timestamp: unix_nanos(point.timestamp),
+            resource: (
+                point.resource.kind().to_owned(),
+                point.resource.id_string().unwrap_or_default(),
+            ),
+            consumer: (
+                point.consumer.kind().to_owned(),
+                point.consumer.id_string().unwrap_or_default(),
+            ),
+            attributes: attributes.unbind(),
+        })
+    }
+
+    /// Converts the point to give it to the pipeline.
+    ///
+    /// `metric_id` finds the id of a metric by its name.
+    pub fn to_alumet(
+        &self,
+        py: Python<'_>,
+        metric_id: impl Fn(&str) -> Option<RawMetricId>,
+    ) -> anyhow::Result<measurement::MeasurementPoint> {
+        let metric = metric_id(&self.metric).with_context(|| format!("unknown metric {}", self.metric))?;
+        let timestamp =
+            Timestamp::from_unix_timestamp(self.timestamp / 1_000_000_000, (self.timestamp % 1_000_000_000) as u32);
+        let value = match self.value {
+            Value::U64(v) => WrappedMeasurementValue::U64(v),
+            Value::F64(v) => WrappedMeasurementValue::F64(v),
+        };
+        let (kind, id) = self.resource.clone();
+        let resource = Resource::parse(kind, id)?;
+        let (kind, id) = self.consumer.clone();
+        let consumer = ResourceConsumer::parse(kind, id)?;
+        let mut attributes = Vec::new();
+        for (key, value) in self.attributes.bind(py) {
+            let key = key.extract::<String>().map_err(|e| python_error(py, e))?;
+            let value = match value.extract::<AttributeValue>().map_err(|e| python_error(py, e))? {
+                AttributeValue::U64(v) => measurement::AttributeValue::U64(v),
+                AttributeValue::F64(v) => measurement::AttributeValue::F64(v),
+                AttributeValue::Bool(v) => measurement::AttributeValue::Bool(v),
+                AttributeValue::String(v) => measurement::AttributeValue::String(v),
+            };
+            attributes.push((key, value));
+        }
+        Ok(
+            measurement::MeasurementPoint::new_untyped(timestamp, metric, resource, consumer, value)
+                .with_attr_vec(attributes),
+        )
+    }
+}
+
+fn unix_nanos(timestamp: Timestamp) -> u64 {
+    let (secs, nanos) = timestamp.to_unix_timestamp();
+    secs * 1_000_000_000 + u64::from(nanos)
+}
+
+pub struct MetricDef {
+    pub name: String,
+    pub value_type: WrappedMeasurementType,
+    pub unit: PrefixedUnit,
+    pub description: String,
+}
+
+pub struct SourceDef {
+    pub name: String,
+    pub poll: Py<PyAny>,
+    pub poll_interval: Duration,
+    pub flush_interval: Duration,
+}
+
+/// Given to the `start` function of the scripts, to register their metrics and elements.
+///
+/// The registrations are applied when `start` returns.
+#[pyclass(module = "alumet")]
+#[derive(Default)]
+pub struct AlumetStart {
+    pub metrics: Vec<MetricDef>,
+    pub sources: Vec<SourceDef>,
+    pub transforms: Vec<(String, Py<PyAny>)>,
+    pub outputs: Vec<(String, Py<PyAny>)>,
+}
+
+#[pymethods]
+impl AlumetStart {
+    /// Creates a metric. `value_type` is either `"int"` or `"float"`.
+    #[pyo3(signature = (name, unit, description, value_type="float"))]
+    fn create_metric(&mut self, name: String, unit: &str, description: String, value_type: &str) -> PyResult<()> {
+        let unit = PrefixedUnit::from_str(unit)
+            .map_err(|e| PyValueError::new_err(format!("invalid unit '{unit}' for metric {name}: {e}")))?;
+        let value_type = match value_type {
+            "int" => WrappedMeasurementType::U64,
+            "float" => WrappedMeasurementType::F64,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "invalid value type '{other}' for metric {name}"
+                )));
+            }
+        };
+        self.metrics.push(MetricDef {
+            name,
+            value_type,
+            unit,
+            description,
+        });
+        Ok(())
+    }
+
+    /// Adds a source that calls `poll(timestamp)` every `poll_interval` seconds.
+    ///
+    /// `poll` returns a list of `MeasurementPoint`.
+    #[pyo3(signature = (name, poll, poll_interval, flush_interval=None))]
+    fn add_source(
+        &mut self,
+        name: String,
+        poll: Bound<'_, PyAny>,
+        poll_interval: f64,
+        flush_interval: Option<f64>,
+    ) -> PyResult<()> {
+        check_callable(&name, &poll)?;
+        let interval = |secs: f64| {
+            Duration::try_from_secs_f64(secs)
+                .ok()
+                .filter(|d| !d.is_zero())
+                .ok_or_else(|| PyValueError::new_err(format!("invalid interval {secs} for source {name}")))
+        };
+        let poll_interval = interval(poll_interval)?;
+        let flush_interval = match flush_interval {
+            Some(secs) => interval(secs)?.max(poll_interval),
+            None => poll_interval,
+        };
+        self.sources.push(SourceDef {
+            name,
+            poll: poll.unbind(),
+            poll_interval,
+            flush_interval,
+        });
+        Ok(())
+    }
+
+    /// Adds a transform that calls `apply(points)`, which returns the transformed points.
+    fn add_transform(&mut self, name: String, apply: Bound<'_, PyAny>) -> PyResult<()> {
+        check_callable(&name, &apply)?;
+        self.transforms.push((name, apply.unbind()));
+        Ok(())
+    }
+
+    /// Adds an output that calls `write(points)`.
+    fn add_output(&mut self, name: String, write: Bound<'_, PyAny>) -> PyResult<()> {
+        check_callable(&name, &write)?;
+        self.outputs.push((name, write.unbind()));
+        Ok(())
+    }
+}
+
+fn check_callable(name: &str, f: &Bound<'_, PyAny>) -> PyResult<()> {
+    if f.is_callable() {
+        Ok(())
+    } else {
+        Err(PyTypeError::new_err(format!("the function of {name} is not callable")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alumet::{
+        measurement::{self, AttributeValue, Timestamp, WrappedMeasurementValue},
+        metrics::RawMetricId,
+        resources::{Resource, ResourceConsumer},
+    };
+    use pyo3::Python;
+
+    use super::MeasurementPoint;
+
+    #[test]
+    fn point_roundtrip() {
+        let id = RawMetricId::from_u64(7);
+        let point = measurement::MeasurementPoint::new_untyped(
+            Timestamp::from_unix_timestamp(1_700_000_000, 123_456_789),
+            id,
+            Resource::CpuPackage { id: 0 },
+            ResourceConsumer::Process { pid: 42 },
+            WrappedMeasurementValue::F64(12.5),
+        )
+        .with_attr("domain", "package")
+        .with_attr("core_count", 8_u64)
+        .with_attr("rapl", true);
+
+        Python::attach(|py| {
+            let converted = MeasurementPoint::from_alumet(py, &point, String::from("energy")).unwrap();
+            assert_eq!(converted.timestamp, 1_700_000_000_123_456_789);
+            assert_eq!(converted.metric, "energy");
+            assert_eq!(converted.resource, (String::from("cpu_package"), String::from("0")));
+            assert_eq!(converted.consumer, (String::from("process"), String::from("42")));
+
+            let back = converted
+                .to_alumet(py, |name| (name == "energy").then_some(id))
+                .unwrap();
+            assert_eq!(back.timestamp, point.timestamp);
+            assert_eq!(back.metric, id);
+            assert_eq!(back.resource, point.resource);
+            assert_eq!(back.consumer, point.consumer);
+            assert_eq!(back.value, point.value);
+            let attributes: Vec<_> = back.attributes().collect();
+            assert_eq!(
+                attributes,
+                vec![
+                    ("domain", &AttributeValue::String(String::from("package"))),
+                    ("core_count", &AttributeValue::U64(8)),
+                    ("rapl", &AttributeValue::Bool(true)),
+                ]
+            );
+
+            // unknown metric
+            assert!(converted.to_alumet(py, |_| None).is_err());
+        });
+    }
+}
@@ -0,0 +1,136 @@
+//! Sources, transforms and outputs implemented in Python.
+//!
+//! Python code can only run while holding the Global Interpreter Lock (GIL), and may block for a long time.
+//! Sources and transforms run on the async runtime of the pipeline: they call the Python functions in
+//! [`tokio::task::block_in_place`], which moves the other tasks to other threads in the meantime.
+//! Outputs are blocking outputs, which already run on dedicated threads.
+
+use std::{collections::HashMap, sync::Arc};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementBuffer, Timestamp},
+    metrics::{RawMetricId, registry::MetricRegistry},
+    pipeline::{
+        Output, Source, Transform,
+        elements::{
+            error::{PollError, TransformError, WriteError},
+            output::OutputContext,
+            transform::TransformContext,
+        },
+    },
+};
+use anyhow::Context;
+use pyo3::{prelude::*, types::PyList};
+
+use crate::api::{MeasurementPoint, python_error};
+
+pub struct PythonSource {
+    pub script: String,
+    pub name: String,
+    pub poll: Py<PyAny>,
+    /// Metrics created by the script.
+    pub metrics: Arc<HashMap<String, RawMetricId>>,
+}
+
+pub struct PythonTransform {
+    pub script: String,
+    pub name: String,
+    pub apply: Py<PyAny>,
+}
+
+pub struct PythonOutput {
+    pub script: String,
+    pub name: String,
+    pub write: Py<PyAny>,
+}
+
+/// Converts the points of a buffer to give them to a script.
+fn points_to_python<'py>(
+    py: Python<'py>,
+    measurements: &MeasurementBuffer,
+    metrics: &MetricRegistry,
+) -> anyhow::Result<Bound<'py, PyList>> {
+    let points = PyList::empty(py);
+    for p in measurements.iter() {
+        let metric = metrics
+            .by_id(&p.metric)
+            .with_context(|| format!("unknown metric {:?}", p.metric))?;
+        let point = MeasurementPoint::from_alumet(py, p, metric.name.clone())?;
+        points.append(Bound::new(py, point)?)?;
+    }
+    Ok(points)
+}
+
+/// Extracts the points returned by a script. `None` is an empty list.
+fn points_from_python<'py>(
+    py: Python<'py>,
+    returned: &Bound<'py, PyAny>,
+    metric_id: impl Fn(&str) -> Option<RawMetricId>,
+) -> anyhow::Result<Vec<alumet::measurement::MeasurementPoint>> {
+    let points = returned
+        .extract::<Option<Vec<PyRef<'py, MeasurementPoint>>>>()
+        .map_err(|e| python_error(py, e))?;
+    points
+        .unwrap_or_default()
+        .iter()
+        .map(|p| p.to_alumet(py, &metric_id))
+        .collect()
+}
+
+impl Source for PythonSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let (secs, nanos) = timestamp.to_unix_timestamp();
+        let timestamp = secs * 1_000_000_000 + u64::from(nanos);
+        tokio::task::block_in_place(|| {
+            Python::attach(|py| {
+                let returned = self.poll.bind(py).call1((timestamp,)).map_err(|e| {
+                    let err = python_error(py, e).context(format!("source {} of {} failed", self.name, self.script));
+                    PollError::CanRetry(err)
+                })?;
+                let points = points_from_python(py, &returned, |name| self.metrics.get(name).copied())
+                    .with_context(|| format!("invalid points returned by source {} of {}", self.name, self.script))?;
+                for point in points {
+                    measurements.push(point);
+                }
+                Ok(())
+            })
+        })
+    }
+}
+
+impl Transform for PythonTransform {
+    fn apply(&mut self, measurements: &mut MeasurementBuffer, ctx: &TransformContext) -> Result<(), TransformError> {
+        tokio::task::block_in_place(|| {
+            Python::attach(|py| {
+                let points =
+                    points_to_python(py, measurements, ctx.metrics).map_err(TransformError::UnexpectedInput)?;
+                let returned = self.apply.bind(py).call1((points,)).map_err(|e| {
+                    let err = python_error(py, e).context(format!("transform {} of {} failed", self.name, self.script));
+                    TransformError::UnexpectedInput(err)
+                })?;
+                let points = points_from_python(py, &returned, |name| ctx.metrics.by_name(name).map(|(id, _)| id))
+                    .with_context(|| {
+                        format!("invalid points returned by transform {} of {}", self.name, self.script)
+                    })?;
+                measurements.clear();
+                for point in points {
+                    measurements.push(point);
+                }
+                Ok(())
+            })
+        })
+    }
+}
+
+impl Output for PythonOutput {
+    fn write(&mut self, measurements: &MeasurementBuffer, ctx: &OutputContext) -> Result<(), WriteError> {
+        Python::attach(|py| {
+            let points = points_to_python(py, measurements, ctx.metrics)?;
+            self.write.bind(py).call1((points,)).map_err(|e| {
+                let err = python_error(py, e).context(format!("output {} of {} failed", self.name, self.script));
+                WriteError::CanRetry(err)
+            })?;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,150 @@
+//! Host for plugins written in Python.
+//!
+//! A Python plugin is a script that defines a `start(alumet, config)` function.
+//! This function registers the metrics, sources, transforms and outputs of the script
+//! with the methods of `alumet` (see [`api::AlumetStart`]).
+use std::{collections::HashMap, ffi::CString, path::PathBuf, sync::Arc};
+
+use alumet::{
+    pipeline::elements::source::trigger,
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+use anyhow::Context;
+use pyo3::{prelude::*, types::PyModule};
+use serde::{Deserialize, Serialize};
+
+mod api;
+mod elements;
+
+use api::{AlumetStart, python_error};
+use elements::{PythonOutput, PythonSource, PythonTransform};
+
+pub struct PythonPlugin {
+    config: Config,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The Python scripts to load.
+    pub scripts: Vec<ScriptConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptConfig {
+    /// Path to the script (`.py` file).
+    pub path: PathBuf,
+    /// Name of the script, used as a prefix for the names of its elements.
+    /// Defaults to the name of the file, without its extension.
+    pub name: Option<String>,
+    /// Configuration of the script, given to its `start` function as a dict.
+    #[serde(default)]
+    pub config: toml::Table,
+}
+
+impl AlumetPlugin for PythonPlugin {
+    fn name() -> &'static str {
+        "python"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        Ok(Some(serialize_config(Config::default())?))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(Self { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        if self.config.scripts.is_empty() {
+            log::warn!("No Python script to load.");
+            return Ok(());
+        }
+        Python::attach(|py| api::register(py).map_err(|e| python_error(py, e)))
+            .context("failed to initialize the alumet Python module")?;
+        for script in &self.config.scripts {
+            let name = match &script.name {
+                Some(name) => name.clone(),
+                None => script
+                    .path
+                    .file_stem()
+                    .with_context(|| format!("invalid script path {}", script.path.display()))?
+                    .to_string_lossy()
+                    .into_owned(),
+            };
+            start_script(alumet, &name, script).with_context(|| format!("failed to start Python script {name}"))?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the `start` function of a script and adds its elements to the pipeline.
+fn start_script(alumet: &mut AlumetPluginStart, name: &str, script: &ScriptConfig) -> anyhow::Result<()> {
+    let code =
+        std::fs::read_to_string(&script.path).with_context(|| format!("failed to read {}", script.path.display()))?;
+    let code = CString::new(code)?;
+    let file_name = CString::new(script.path.to_string_lossy().into_owned())?;
+    let module_name = CString::new(name)?;
+    let config = serde_json::to_string(&script.config)?;
+
+    let registrations = Python::attach(|py| {
+        let run = || -> PyResult<AlumetStart> {
+            let module = PyModule::from_code(py, &code, &file_name, &module_name)?;
+            let config = py.import("json")?.call_method1("loads", (config,))?;
+            let start = Bound::new(py, AlumetStart::default())?;
+            module.getattr("start")?.call1((&start, config))?;
+            Ok(std::mem::take(&mut *start.borrow_mut()))
+        };
+        run().map_err(|e| python_error(py, e))
+    })?;
+
+    let mut metrics = HashMap::with_capacity(registrations.metrics.len());
+    for metric in registrations.metrics {
+        let id = alumet.create_metric_untyped(&metric.name, metric.value_type, metric.unit, &metric.description)?;
+        metrics.insert(metric.name, id);
+    }
+    let metrics = Arc::new(metrics);
+
+    for source in registrations.sources {
+        let trigger = trigger::builder::time_interval(source.poll_interval)
+            .flush_interval(source.flush_interval)
+            .build()?;
+        let element = PythonSource {
+            script: name.to_owned(),
+            name: source.name.clone(),
+            poll: source.poll,
+            metrics: metrics.clone(),
+        };
+        alumet.add_source(&format!("{name}-{}", source.name), Box::new(element), trigger)?;
+    }
+    for (transform, apply) in registrations.transforms {
+        let element = PythonTransform {
+            script: name.to_owned(),
+            name: transform.clone(),
+            apply,
+        };
+        alumet.add_transform(&format!("{name}-{transform}"), Box::new(element))?;
+    }
+    for (output, write) in registrations.outputs {
+        let element = PythonOutput {
+            script: name.to_owned(),
+            name: output.clone(),
+            write,
+        };
+        alumet.add_blocking_output(&format!("{name}-{output}"), Box::new(element))?;
+    }
+    Ok(())
+}
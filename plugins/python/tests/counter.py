@@ -0,0 +1,28 @@
+# Example plugin, used by the tests.
+import alumet
+
+factor = 1
+count = 0
+
+
+def start(plugin, config):
+    global factor
+    factor = config["factor"]
+    plugin.create_metric("counter", "1", "number of calls to the source", value_type="int")
+    plugin.add_source("counter", poll, poll_interval=0.01)
+    plugin.add_transform("multiply", multiply)
+    alumet.log("info", f"counter started with factor {factor}")
+
+
+def poll(timestamp):
+    global count
+    count += 1
+    return [alumet.MeasurementPoint("counter", count, timestamp)]
+
+
+def multiply(points):
+    for p in points:
+        if p.metric == "counter":
+            p.value *= factor
+            p.attributes["factor"] = factor
+    return points
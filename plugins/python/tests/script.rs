@@ -0,0 +1,84 @@
+//! Runs the example Python plugin of `tests/counter.py`.
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use alumet::{
+    agent::{
+        self,
+        plugin::{PluginInfo, PluginSet},
+    },
+    measurement::{AttributeValue, MeasurementBuffer, MeasurementPoint, WrappedMeasurementValue},
+    pipeline::{
+        self, Output,
+        elements::{
+            error::WriteError,
+            output::{OutputContext, builder::OutputBuilder},
+        },
+        naming::PluginName,
+    },
+    plugin::PluginMetadata,
+};
+use plugin_python::PythonPlugin;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct CollectingOutput(Arc<Mutex<Vec<MeasurementPoint>>>);
+
+impl Output for CollectingOutput {
+    fn write(&mut self, measurements: &MeasurementBuffer, _ctx: &OutputContext) -> Result<(), WriteError> {
+        self.0.lock().unwrap().extend(measurements.iter().cloned());
+        Ok(())
+    }
+}
+
+#[test]
+fn run_example_plugin() {
+    let _ = env_logger::Builder::from_default_env().try_init();
+
+    let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/counter.py");
+    let config = format!(
+        "[[scripts]]\npath = '{}'\n[scripts.config]\nfactor = 3",
+        script.display()
+    );
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<PythonPlugin>(),
+        enabled: true,
+        config: Some(toml::from_str(&config).unwrap()),
+    });
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let output = CollectingOutput(received.clone());
+    let mut pipeline = pipeline::Builder::new();
+    pipeline
+        .add_output_builder(
+            PluginName(String::from("test")),
+            "collect",
+            OutputBuilder::Blocking(Box::new(move |_| Ok(Box::new(output)))),
+        )
+        .unwrap();
+    let agent = agent::Builder::from_pipeline(plugins, pipeline)
+        .build_and_start()
+        .unwrap();
+
+    let start = Instant::now();
+    while received.lock().unwrap().len() < 5 {
+        assert!(start.elapsed() < TIMEOUT, "timeout: not enough measurements");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+
+    // the values produced by the source have been multiplied by the transform
+    for point in received.lock().unwrap().iter() {
+        let WrappedMeasurementValue::U64(value) = point.value else {
+            panic!("unexpected value {:?}", point.value);
+        };
+        assert!(value > 0 && value % 3 == 0, "value {value} should be a multiple of 3");
+        let attributes: Vec<_> = point.attributes().collect();
+        assert_eq!(attributes, vec![("factor", &AttributeValue::U64(3))]);
+    }
+}
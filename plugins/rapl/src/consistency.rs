@@ -125,7 +125,7 @@ pub(crate) fn mkstring<A: ToString>(elems: &[A], sep: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::check_domains_consistency;
+    use super::{check_domains_consistency, get_available_domains};
     use crate::{domains::RaplDomainType, perf_event::PowerEvent, powercap::PowerZone};
     use std::path::Path;
 
@@ -200,6 +200,33 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_powercap_error_falls_back_to_perf() -> anyhow::Result<()> {
+        let power_events = vec![PowerEvent {
+            name: "pkg".to_string(),
+            domain: RaplDomainType::Package,
+            code: 2,
+            unit: "Joules".to_string(),
+            scale: 2.3283064365386962890625e-10,
+        }];
+
+        let mut use_perf = false;
+        let mut use_powercap = true;
+        let (safe_subset, _) = get_available_domains(
+            Ok(power_events.clone()),
+            Err(anyhow::anyhow!("permission denied")),
+            true,
+            &mut use_perf,
+            &mut use_powercap,
+        )?;
+
+        assert!(use_perf);
+        assert!(!use_powercap);
+        assert_eq!(safe_subset.domains, vec![RaplDomainType::Package]);
+        assert_eq!(safe_subset.perf_events, power_events);
+        Ok(())
+    }
 }
 
 pub fn get_available_domains(
@@ -249,6 +276,11 @@ pub fn get_available_domains(
             log::warn!(
                 "The consistency of the RAPL domains reported by the different interfaces of the Linux kernel cannot be checked (this is useful to work around bugs in some kernel versions on some machines)."
             );
+            if !*use_perf {
+                log::warn!("Because of the previous error, I will use perf_events instead of powercap.");
+            }
+            *use_perf = true;
+            *use_powercap = false;
             (
                 SafeSubset::from_perf_only(power_events),
                 " (from perf_events)".to_string(),
@@ -1,6 +1,7 @@
 use std::{fmt, str::FromStr};
 
-use alumet::resources::Resource;
+use alumet::{metrics::TypedMetricId, resources::Resource};
+use enum_map::EnumMap;
 
 /// A known RAPL domain.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, enum_map::Enum)]
@@ -71,6 +72,39 @@ impl RaplDomainType {
     }
 }
 
+/// The metric to use for each RAPL domain.
+///
+/// Either all the domains share the same metric, or each available domain has its own metric.
+#[derive(Debug, Clone)]
+pub struct DomainMetrics(EnumMap<RaplDomainType, Option<TypedMetricId<f64>>>);
+
+impl DomainMetrics {
+    /// Uses the same metric for every domain.
+    pub fn single(metric: TypedMetricId<f64>) -> Self {
+        Self(EnumMap::from_fn(|_| Some(metric)))
+    }
+
+    /// Uses one metric per domain, created by `create_metric`.
+    pub fn per_domain(
+        domains: &[RaplDomainType],
+        mut create_metric: impl FnMut(RaplDomainType) -> anyhow::Result<TypedMetricId<f64>>,
+    ) -> anyhow::Result<Self> {
+        let mut metrics = EnumMap::default();
+        for &domain in domains {
+            metrics[domain] = Some(create_metric(domain)?);
+        }
+        Ok(Self(metrics))
+    }
+
+    /// Returns the metric of the given domain.
+    ///
+    /// # Panics
+    /// Panics if no metric has been created for this domain.
+    pub fn get(&self, domain: RaplDomainType) -> TypedMetricId<f64> {
+        self.0[domain].unwrap_or_else(|| panic!("no metric for RAPL domain {domain}"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
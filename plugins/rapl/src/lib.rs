@@ -13,6 +13,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     consistency::{SafeSubset, get_available_domains},
+    domains::DomainMetrics,
     perf_event::{PerfEventProbe, PowerEvent},
     powercap::{PowerZone, PowercapProbe},
 };
@@ -117,32 +118,41 @@ impl AlumetPlugin for RaplPlugin {
             consistency::mkstring(&available_domains.domains, ", ")
         );
 
-        // Create the metric.
-        let metric = alumet.create_metric::<f64>(
-            "rapl_consumed_energy",
-            Unit::Joule,
-            "Energy consumed since the previous measurement, as reported by RAPL.",
-        )?;
+        // Create the metrics.
+        let metrics = if self.config.metric_per_domain {
+            DomainMetrics::per_domain(&available_domains.domains, |domain| {
+                let metric = alumet.create_metric::<f64>(
+                    format!("rapl_{domain}_consumed_energy"),
+                    Unit::Joule,
+                    format!("Energy consumed by the RAPL domain {domain} since the previous measurement."),
+                )?;
+                Ok(metric)
+            })?
+        } else {
+            let metric = alumet.create_metric::<f64>(
+                "rapl_consumed_energy",
+                Unit::Joule,
+                "Energy consumed since the previous measurement, as reported by RAPL.",
+            )?;
+            DomainMetrics::single(metric)
+        };
 
         // Create the measurement source.
         let source = match (use_perf, use_powercap) {
             (true, true) => {
                 // prefer perf_events, fallback to powercap if it fails
-                setup_perf_events_probe_or_fallback(metric, &available_domains)?
+                setup_perf_events_probe_or_fallback(metrics, &available_domains)?
             }
             (true, false) => {
                 // only use perf
                 Box::new(
-                    PerfEventProbe::new(metric, &available_domains.perf_events)
+                    PerfEventProbe::new(metrics, &available_domains.perf_events)
                         .context("Failed to create RAPL probe based on perf_events")?,
                 )
             }
             (false, true) => {
-                // only use powercap
-                Box::new(
-                    PowercapProbe::new(metric, &available_domains.power_zones)
-                        .context("Failed to create RAPL probe based on powercap")?,
-                )
+                // use powercap, fallback to perf_events if the energy counters are not readable
+                setup_powercap_probe_or_fallback(metrics, &available_domains)?
             }
             (false, false) => {
                 // error: no available interface!
@@ -168,18 +178,36 @@ impl AlumetPlugin for RaplPlugin {
 }
 
 fn setup_perf_events_probe_or_fallback(
-    metric: alumet::metrics::TypedMetricId<f64>,
+    metrics: DomainMetrics,
     available_domains: &SafeSubset,
 ) -> anyhow::Result<Box<dyn Source>> {
-    match PerfEventProbe::new(metric, &available_domains.perf_events) {
+    match PerfEventProbe::new(metrics.clone(), &available_domains.perf_events) {
         Ok(probe) => Ok(Box::new(probe)),
         Err(_) => {
             log::warn!(
                 "I will fallback to the powercap sysfs, but perf_events is more efficient (see https://hal.science/hal-04420527)."
             );
-            let fallback = PowercapProbe::new(metric, &available_domains.power_zones)?;
+            let fallback = PowercapProbe::new(metrics, &available_domains.power_zones)?;
+            Ok(Box::new(fallback))
+        }
+    }
+}
+
+fn setup_powercap_probe_or_fallback(
+    metrics: DomainMetrics,
+    available_domains: &SafeSubset,
+) -> anyhow::Result<Box<dyn Source>> {
+    match PowercapProbe::new(metrics.clone(), &available_domains.power_zones) {
+        Ok(probe) => Ok(Box::new(probe)),
+        Err(e) if !available_domains.perf_events.is_empty() => {
+            // Since Linux 5.10 (mitigation of the PLATYPUS attack), the energy counters of powercap
+            // are only readable by root, but perf_events can be allowed for non-root users.
+            log::warn!("I will fallback to perf_events, because powercap is not usable: {e:#}");
+            let fallback = PerfEventProbe::new(metrics, &available_domains.perf_events)
+                .context("Failed to create RAPL probe based on perf_events, after the failure of powercap")?;
             Ok(Box::new(fallback))
         }
+        Err(e) => Err(e.context("Failed to create RAPL probe based on powercap")),
     }
 }
 
@@ -194,9 +222,16 @@ pub struct Config {
     #[serde(with = "humantime_serde")]
     pub flush_interval: Duration,
 
-    /// Set to true to disable perf_events and always use the powercap sysfs.
+    /// Set to true to disable perf_events and use the powercap sysfs.
+    ///
+    /// If the energy counters of powercap cannot be read, perf_events is used anyway.
     pub no_perf_events: bool,
 
+    /// Set to true to create one metric per RAPL domain (e.g. `rapl_dram_consumed_energy`)
+    /// instead of a single `rapl_consumed_energy` metric.
+    #[serde(default)]
+    pub metric_per_domain: bool,
+
     #[cfg(test)]
     pub perf_event_test_path: PathBuf,
     #[cfg(test)]
@@ -209,6 +244,7 @@ impl Default for Config {
             poll_interval: Duration::from_secs(1), // 1Hz
             flush_interval: Duration::from_secs(5),
             no_perf_events: false, // prefer perf_events
+            metric_per_domain: false,
 
             #[cfg(test)]
             perf_event_test_path: PathBuf::from(""),
@@ -251,6 +287,7 @@ mod tests {
             poll_interval: Duration::from_secs(1),
             flush_interval: Duration::from_secs(1),
             no_perf_events: true,
+            metric_per_domain: false,
             perf_event_test_path: Path::new("").to_path_buf(),
             powercap_test_path: base_path,
         };
@@ -276,6 +313,48 @@ mod tests {
         Ok(())
     }
 
+    /// This test ensures that every domain found in the Powercap mocks gets its own metric.
+    #[test]
+    fn test_startup_with_metric_per_domain() -> anyhow::Result<()> {
+        let mut plugins = PluginSet::new();
+
+        let tmp = create_valid_powercap_mock()?;
+        let base_path = tmp.path().to_owned();
+
+        let source_config = Config {
+            poll_interval: Duration::from_secs(1),
+            flush_interval: Duration::from_secs(1),
+            no_perf_events: true,
+            metric_per_domain: true,
+            perf_event_test_path: Path::new("").to_path_buf(),
+            powercap_test_path: base_path,
+        };
+        plugins.add_plugin(PluginInfo {
+            metadata: PluginMetadata::from_static::<RaplPlugin>(),
+            enabled: true,
+            config: Some(config_to_toml_table(&source_config)),
+        });
+
+        let startup_expectations = StartupExpectations::new()
+            .expect_metric::<f64>("rapl_package_consumed_energy", Unit::Joule)
+            .expect_metric::<f64>("rapl_pp0_consumed_energy", Unit::Joule)
+            .expect_metric::<f64>("rapl_pp1_consumed_energy", Unit::Joule)
+            .expect_metric::<f64>("rapl_dram_consumed_energy", Unit::Joule)
+            .expect_metric::<f64>("rapl_platform_consumed_energy", Unit::Joule)
+            .expect_source("rapl", "in");
+
+        let agent = agent::Builder::new(plugins)
+            .with_expectations(startup_expectations)
+            .build_and_start()
+            .unwrap();
+
+        std::thread::sleep(Duration::from_millis(200));
+        agent.pipeline.control_handle().shutdown();
+        agent.wait_for_shutdown(Duration::from_secs(10)).unwrap();
+
+        Ok(())
+    }
+
     #[test]
     fn test_runtime_with_powercap() -> anyhow::Result<()> {
         let mut plugins = PluginSet::new();
@@ -314,6 +393,7 @@ mod tests {
             poll_interval: Duration::from_secs(1),
             flush_interval: Duration::from_secs(1),
             no_perf_events: true,
+            metric_per_domain: false,
             perf_event_test_path: Path::new("").to_path_buf(),
             powercap_test_path: base_path.clone(),
         };
@@ -1,6 +1,5 @@
 use alumet::{
     measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
-    metrics::TypedMetricId,
     pipeline::elements::error::PollError,
     plugin::util::{CounterDiff, CounterDiffUpdate},
     resources::{Resource, ResourceConsumer},
@@ -17,7 +16,7 @@ use std::{
 use crate::total::DomainTotals;
 
 use super::cpus::{self, CpuId};
-use super::domains::RaplDomainType;
+use super::domains::{DomainMetrics, RaplDomainType};
 
 // See https://github.com/torvalds/linux/commit/4788e5b4b2338f85fa42a712a182d8afd65d7c58
 // for an explanation of the RAPL PMU driver.
@@ -56,8 +55,8 @@ struct OpenedPowerEvent {
 
 /// Energy probe based on perf_event for intel RAPL.
 pub struct PerfEventProbe {
-    /// Id of the metric to push.
-    metric: TypedMetricId<f64>,
+    /// Metrics to push, per domain.
+    metrics: DomainMetrics,
     /// Ready-to-use power events with additional metadata.
     events: Vec<OpenedPowerEvent>,
 }
@@ -96,15 +95,20 @@ pub fn all_power_events_from_path(base_path: &Path) -> Result<Vec<PowerEvent>> {
 impl PowerEventFactory {
     /// creates a new PowerEvent from an event base path. In case the path is not identified as a RAPL event one, None will be returned.
     /// (eg: /sys/devices/power/events/energy-cores)
+    ///
+    /// Events of unknown RAPL domains are skipped (None is returned).
     pub fn from_path(base_path: &Path) -> anyhow::Result<Option<PowerEvent>> {
         let name = match Self::name_from_base_path(base_path)? {
             Some(name) => name,
             None => return Ok(None),
         };
+        let Some(domain) = Self::domain_type_from_name(&name) else {
+            log::warn!("Unknown RAPL perf event {name} at {base_path:?}, it will be ignored.");
+            return Ok(None);
+        };
         let code = Self::code_from_base_path(base_path)?;
         let unit = Self::unit_from_base_path(base_path)?;
         let scale = Self::scale_from_base_path(base_path)?;
-        let domain = Self::domain_type_from_name(&name).with_context(|| format!("Unknown RAPL perf event {name}"))?;
 
         Ok(Some(PowerEvent {
             name,
@@ -251,8 +255,8 @@ impl OpenedPowerEvent {
 }
 
 impl PerfEventProbe {
-    /// creates a new PerfEventProbe by passing an Alumet metric ID for energy measurement and related power events
-    pub fn new(metric: TypedMetricId<f64>, power_events: &Vec<PowerEvent>) -> anyhow::Result<PerfEventProbe> {
+    /// creates a new PerfEventProbe by passing the Alumet metrics for energy measurement and related power events
+    pub fn new(metrics: DomainMetrics, power_events: &Vec<PowerEvent>) -> anyhow::Result<PerfEventProbe> {
         let all_cpus = cpus::online_cpus()?;
         let socket_cpus = cpus::cpus_to_monitor_with_perf()
         .context("I could not determine how to use perf_events to read RAPL energy counters. The Intel RAPL PMU module may not be enabled, is your Linux kernel too old?")?;
@@ -282,7 +286,10 @@ impl PerfEventProbe {
                         }
                     }
                 }
-                Ok(PerfEventProbe { metric, events: opened })
+                Ok(PerfEventProbe {
+                    metrics,
+                    events: opened,
+                })
             }
             Err(e) => {
                 Self::handle_insufficient_privileges(&e);
@@ -324,8 +331,14 @@ impl alumet::pipeline::Source for PerfEventProbe {
             if let Some(joules) = evt.read_counter_diff_in_joules()? {
                 let consumer = ResourceConsumer::LocalMachine;
                 measurements.push(
-                    MeasurementPoint::new(timestamp, self.metric, evt.resource.clone(), consumer, joules)
-                        .with_attr("domain", evt.domain.as_str()),
+                    MeasurementPoint::new(
+                        timestamp,
+                        self.metrics.get(evt.domain),
+                        evt.resource.clone(),
+                        consumer,
+                        joules,
+                    )
+                    .with_attr("domain", evt.domain.as_str()),
                 );
                 totals.push(evt.domain, joules);
             }
@@ -342,7 +355,7 @@ impl alumet::pipeline::Source for PerfEventProbe {
             measurements.push(
                 MeasurementPoint::new(
                     timestamp,
-                    self.metric,
+                    self.metrics.get(domain),
                     Resource::LocalMachine,
                     ResourceConsumer::LocalMachine,
                     total,
@@ -446,6 +459,10 @@ mod tests {
                 path: "events/energy-psys.unit",
                 entry_type: File("Joules"),
             },
+            Entry {
+                path: "events/energy-unknown",
+                entry_type: File("event=0x09"),
+            },
         ];
 
         create_mock_layout(base_path, &perf_event_entries)?;
@@ -10,14 +10,14 @@ use std::{
 
 use crate::total::DomainTotals;
 
-use super::domains::RaplDomainType;
+use super::domains::{DomainMetrics, RaplDomainType};
+use alumet::pipeline::elements::error::PollError;
 use alumet::plugin::util::{CounterDiff, CounterDiffUpdate};
 use alumet::resources::Resource;
 use alumet::{
     measurement::{AttributeValue, MeasurementAccumulator, MeasurementPoint, Timestamp},
     resources::ResourceConsumer,
 };
-use alumet::{metrics::TypedMetricId, pipeline::elements::error::PollError};
 use anyhow::{Context, anyhow};
 
 pub const POWERCAP_RAPL_PATH: &str = "/sys/devices/virtual/powercap/intel-rapl";
@@ -72,7 +72,7 @@ struct OpenedPowerZone {
 
 /// Powercap probe collects Alumet metrics related to power zones
 pub struct PowercapProbe {
-    metric: TypedMetricId<f64>,
+    metrics: DomainMetrics,
 
     /// Ready-to-use powercap zones with additional metadata
     zones: Vec<OpenedPowerZone>,
@@ -110,14 +110,16 @@ pub fn all_power_zones_from_path(path: &Path) -> anyhow::Result<PowerZoneHierarc
 impl PowerZoneFactory {
     /// creates a new PowerZone from a zone base path. In case the path is not identified as a zone path, None will be returned.
     /// (eg: /sys/devices/virtual/powercap/intel-rapl/intel-rapl:0)
+    ///
+    /// Zones of unknown RAPL domains are skipped (None is returned), along with their children.
     fn from_path(path: &Path) -> anyhow::Result<Option<PowerZone>> {
-        Ok(match Self::is_zone_path(path) {
-            true => Some(Self::get_zone_from_path(path)?),
-            false => None,
-        })
+        match Self::is_zone_path(path) {
+            true => Self::get_zone_from_path(path),
+            false => Ok(None),
+        }
     }
 
-    fn get_zone_from_path(path: &Path) -> anyhow::Result<PowerZone> {
+    fn get_zone_from_path(path: &Path) -> anyhow::Result<Option<PowerZone>> {
         let name_path = path.join("name");
         let name = fs::read_to_string(&name_path)?.trim().to_owned();
         let socket_id = match Self::socket_id_from_name(&name)? {
@@ -136,7 +138,10 @@ impl PowerZoneFactory {
                 }
             }
         };
-        let domain = Self::domain_from_name(&name).with_context(|| format!("Unknown RAPL powercap zone {name}"))?;
+        let Some(domain) = Self::domain_from_name(&name) else {
+            log::warn!("Unknown RAPL powercap zone {name} at {path:?}, it will be ignored.");
+            return Ok(None);
+        };
         let mut children: Vec<PowerZone> = Vec::new();
         for e in fs::read_dir(path)? {
             let entry = e?;
@@ -145,13 +150,13 @@ impl PowerZoneFactory {
                 children.push(child);
             }
         }
-        Ok(PowerZone {
+        Ok(Some(PowerZone {
             name,
             domain,
             path: path.to_path_buf(),
             socket_id,
             children,
-        })
+        }))
     }
 
     fn is_zone_path(path: &Path) -> bool {
@@ -289,8 +294,8 @@ impl OpenedPowerZone {
 }
 
 impl PowercapProbe {
-    /// creates a new PowercapProbe by passing an Alumet metric ID for energy measurement and related power zones
-    pub fn new(metric: TypedMetricId<f64>, zones: &Vec<PowerZone>) -> anyhow::Result<PowercapProbe> {
+    /// creates a new PowercapProbe by passing the Alumet metrics for energy measurement and related power zones
+    pub fn new(metrics: DomainMetrics, zones: &Vec<PowerZone>) -> anyhow::Result<PowercapProbe> {
         if zones.is_empty() {
             return Err(anyhow!("At least one power zone is required for PowercapProbe"))?;
         }
@@ -306,7 +311,7 @@ impl PowercapProbe {
             }
         }
 
-        Ok(PowercapProbe { metric, zones: opened })
+        Ok(PowercapProbe { metrics, zones: opened })
     }
 
     fn handle_insufficient_privileges(e: &anyhow::Error) {
@@ -314,6 +319,7 @@ impl PowercapProbe {
             I could not use the powercap sysfs to read RAPL energy counters: {e}.
             This is probably caused by insufficient privileges.
             Please check that you have read access to everything in '{POWERCAP_RAPL_PATH}'.
+            Note that since Linux 5.10, the energy counters are only readable by root by default (mitigation of the PLATYPUS attack).
         
             A solution could be:
                 sudo chmod a+r -R {POWERCAP_RAPL_PATH}"};
@@ -333,8 +339,14 @@ impl alumet::pipeline::Source for PowercapProbe {
             if let Some(joules) = zone.read_counter_diff_in_joules(&mut zone_reading_buf)? {
                 let consumer = ResourceConsumer::LocalMachine;
                 measurements.push(
-                    MeasurementPoint::new(timestamp, self.metric, zone.resource.clone(), consumer, joules)
-                        .with_attr("domain", zone.domain.as_str()),
+                    MeasurementPoint::new(
+                        timestamp,
+                        self.metrics.get(zone.domain),
+                        zone.resource.clone(),
+                        consumer,
+                        joules,
+                    )
+                    .with_attr("domain", zone.domain.as_str()),
                 );
                 totals.push(zone.domain, joules);
             };
@@ -346,7 +358,7 @@ impl alumet::pipeline::Source for PowercapProbe {
             measurements.push(
                 MeasurementPoint::new(
                     timestamp,
-                    self.metric,
+                    self.metrics.get(domain),
                     Resource::LocalMachine,
                     ResourceConsumer::LocalMachine,
                     total,
@@ -688,6 +700,51 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_unknown_zone_is_skipped() -> anyhow::Result<()> {
+        let tmp = tempdir()?;
+        let base_path = tmp.path();
+
+        use EntryType::*;
+
+        let entries = [
+            Entry {
+                path: "intel-rapl:0",
+                entry_type: Dir,
+            },
+            Entry {
+                path: "intel-rapl:0/name",
+                entry_type: File("package-0"),
+            },
+            Entry {
+                path: "intel-rapl:0/intel-rapl:0:0",
+                entry_type: Dir,
+            },
+            Entry {
+                path: "intel-rapl:0/intel-rapl:0:0/name",
+                entry_type: File("unknown-domain"),
+            },
+            Entry {
+                path: "intel-rapl:1",
+                entry_type: Dir,
+            },
+            Entry {
+                path: "intel-rapl:1/name",
+                entry_type: File("psys"),
+            },
+        ];
+
+        create_mock_layout(base_path, &entries)?;
+
+        let domains: Vec<_> = all_power_zones_from_path(base_path)?
+            .flat
+            .into_iter()
+            .map(|z| z.domain)
+            .collect();
+        assert_eq!(domains, vec![RaplDomainType::Package, RaplDomainType::Platform]);
+        Ok(())
+    }
+
     #[test]
     fn test_custom_format() {
         let zone = PowerZone {
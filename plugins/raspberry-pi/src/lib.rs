@@ -0,0 +1,101 @@
+mod source;
+mod vcgencmd;
+
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::{
+    source::{Metrics, RaspberryPiSource},
+    vcgencmd::Vcgencmd,
+};
+
+/// Measures the rails of the PMIC of the Raspberry Pi 5, and the temperature and core voltage of its SoC.
+pub struct RaspberryPiPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for RaspberryPiPlugin {
+    fn name() -> &'static str {
+        "raspberry-pi"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(RaspberryPiPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let vcgencmd = Vcgencmd::new(self.config.vcgencmd.clone());
+        let rails = vcgencmd
+            .pmic_rails()
+            .context("could not read the PMIC, are you running on a Raspberry Pi 5?")?;
+        if rails.is_empty() {
+            return Err(anyhow!("nothing to measure: the PMIC reported no rail"));
+        }
+        for rail in &rails {
+            log::info!("Found PMIC rail {}", rail.name);
+        }
+
+        // the state of the SoC is useful, but not essential: don't fail if it is not available
+        let soc = self.config.soc
+            && match vcgencmd.temperature().and_then(|_| vcgencmd.core_voltage()) {
+                Ok(_) => true,
+                Err(e) => {
+                    log::warn!("The temperature and core voltage of the SoC will not be measured: {e:#}");
+                    false
+                }
+            };
+
+        let metrics = Metrics::new(alumet)?;
+        let source = RaspberryPiSource::new(vcgencmd, metrics, soc);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("pmic", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the `vcgencmd` program.
+    pub vcgencmd: PathBuf,
+
+    /// Also measure the temperature and core voltage of the SoC.
+    pub soc: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            vcgencmd: PathBuf::from("vcgencmd"),
+            soc: true,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::Context;
+
+use crate::vcgencmd::Vcgencmd;
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    rail_power: TypedMetricId<f64>,
+    rail_voltage: TypedMetricId<f64>,
+    rail_current: TypedMetricId<f64>,
+    soc_temperature: TypedMetricId<f64>,
+    core_voltage: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            rail_power: alumet.create_metric("rpi_rail_power", Unit::Watt, "Power of a rail of the PMIC")?,
+            rail_voltage: alumet.create_metric("rpi_rail_voltage", Unit::Volt, "Voltage of a rail of the PMIC")?,
+            rail_current: alumet.create_metric("rpi_rail_current", Unit::Ampere, "Current of a rail of the PMIC")?,
+            soc_temperature: alumet.create_metric(
+                "rpi_soc_temperature",
+                Unit::DegreeCelsius,
+                "Temperature of the SoC",
+            )?,
+            core_voltage: alumet.create_metric("rpi_core_voltage", Unit::Volt, "Voltage of the cores of the SoC")?,
+        })
+    }
+}
+
+/// Measurement source that reads the rails of the PMIC and the state of the SoC.
+pub struct RaspberryPiSource {
+    vcgencmd: Vcgencmd,
+    metrics: Metrics,
+    /// Also measure the temperature and core voltage of the SoC.
+    soc: bool,
+}
+
+impl RaspberryPiSource {
+    pub fn new(vcgencmd: Vcgencmd, metrics: Metrics, soc: bool) -> Self {
+        Self { vcgencmd, metrics, soc }
+    }
+}
+
+impl Source for RaspberryPiSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let rails = self.vcgencmd.pmic_rails().context("failed to read the PMIC")?;
+        for rail in rails {
+            let resource = rail_resource(&rail.name);
+            let point = |metric, value: f64| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    resource.clone(),
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("rail", rail.name.clone())
+            };
+            if let Some(power) = rail.power() {
+                measurements.push(point(self.metrics.rail_power, power));
+            }
+            if let Some(voltage) = rail.voltage {
+                measurements.push(point(self.metrics.rail_voltage, voltage));
+            }
+            if let Some(current) = rail.current {
+                measurements.push(point(self.metrics.rail_current, current));
+            }
+        }
+
+        if self.soc {
+            let soc_point = |metric, value: f64| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    Resource::CpuPackage { id: 0 },
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+            };
+            let temperature = self.vcgencmd.temperature().context("failed to read the temperature")?;
+            measurements.push(soc_point(self.metrics.soc_temperature, temperature));
+            let voltage = self
+                .vcgencmd
+                .core_voltage()
+                .context("failed to read the core voltage")?;
+            measurements.push(soc_point(self.metrics.core_voltage, voltage));
+        }
+        Ok(())
+    }
+}
+
+/// Returns the resource powered by a rail.
+fn rail_resource(rail: &str) -> Resource {
+    if rail == "VDD_CORE" {
+        Resource::CpuPackage { id: 0 }
+    } else if rail.starts_with("DDR_") {
+        Resource::Dram { pkg_id: 0 }
+    } else {
+        Resource::LocalMachine
+    }
+}
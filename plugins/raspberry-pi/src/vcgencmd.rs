@@ -0,0 +1,193 @@
+//! Queries to the VideoCore firmware with `vcgencmd`.
+//!
+//! On the Raspberry Pi 5, `vcgencmd pmic_read_adc` returns the current and voltage of the rails of the PMIC (DA9091):
+//!
+//! ```text
+//!    3V7_WL_SW_A current(0)=0.00000000A
+//!      VDD_CORE_A current(7)=0.83431000A
+//!    3V7_WL_SW_V volt(8)=3.74432000V
+//!      VDD_CORE_V volt(15)=0.87790100V
+//!        EXT5V_V volt(24)=5.12016000V
+//! ```
+
+use std::{
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, anyhow};
+
+/// Current and voltage of a rail of the PMIC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rail {
+    /// Name of the rail, like `VDD_CORE`.
+    pub name: String,
+    /// Current in Amperes, if measured.
+    pub current: Option<f64>,
+    /// Voltage in Volts, if measured.
+    pub voltage: Option<f64>,
+}
+
+impl Rail {
+    /// Power of the rail in Watts, if both the current and the voltage are measured.
+    pub fn power(&self) -> Option<f64> {
+        Some(self.current? * self.voltage?)
+    }
+}
+
+/// Runs `vcgencmd`.
+pub struct Vcgencmd {
+    program: PathBuf,
+}
+
+impl Vcgencmd {
+    pub fn new(program: PathBuf) -> Self {
+        Self { program }
+    }
+
+    fn run(&self, args: &[&str]) -> anyhow::Result<String> {
+        let output = Command::new(&self.program)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .with_context(|| format!("{:?} should be executable", self.program))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "{:?} {} failed with {}: {}",
+                self.program,
+                args.join(" "),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+
+    /// Reads the rails of the PMIC (Raspberry Pi 5 only).
+    pub fn pmic_rails(&self) -> anyhow::Result<Vec<Rail>> {
+        let output = self.run(&["pmic_read_adc"])?;
+        parse_pmic_adc(&output)
+    }
+
+    /// Reads the temperature of the SoC, in degrees Celsius.
+    pub fn temperature(&self) -> anyhow::Result<f64> {
+        let output = self.run(&["measure_temp"])?;
+        parse_value(&output, "temp=", "'C")
+    }
+
+    /// Reads the voltage of the cores of the SoC, in Volts.
+    pub fn core_voltage(&self) -> anyhow::Result<f64> {
+        let output = self.run(&["measure_volts", "core"])?;
+        parse_value(&output, "volt=", "V")
+    }
+}
+
+/// Parses the output of `vcgencmd pmic_read_adc`.
+///
+/// The current and voltage of a rail have the same name, with a different suffix (`_A` or `_V`).
+/// The rails are returned in the order of their first appearance.
+pub fn parse_pmic_adc(output: &str) -> anyhow::Result<Vec<Rail>> {
+    let mut rails: Vec<Rail> = Vec::new();
+    for line in output.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let invalid = || anyhow!("invalid line in the output of pmic_read_adc: {line:?}");
+        let (name, measurement) = line.split_once(' ').ok_or_else(invalid)?;
+        let (_, value) = measurement.split_once('=').ok_or_else(invalid)?;
+        let (name, is_current) = if let Some(name) = name.strip_suffix("_A") {
+            (name, true)
+        } else if let Some(name) = name.strip_suffix("_V") {
+            (name, false)
+        } else {
+            return Err(invalid());
+        };
+        let value: f64 = value
+            .strip_suffix(if is_current { 'A' } else { 'V' })
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(invalid)?;
+
+        let rail = match rails.iter_mut().find(|r| r.name == name) {
+            Some(rail) => rail,
+            None => {
+                rails.push(Rail {
+                    name: name.to_owned(),
+                    current: None,
+                    voltage: None,
+                });
+                rails.last_mut().unwrap()
+            }
+        };
+        if is_current {
+            rail.current = Some(value);
+        } else {
+            rail.voltage = Some(value);
+        }
+    }
+    Ok(rails)
+}
+
+/// Parses an output like `temp=52.1'C`.
+fn parse_value(output: &str, prefix: &str, suffix: &str) -> anyhow::Result<f64> {
+    output
+        .trim()
+        .strip_prefix(prefix)
+        .and_then(|v| v.strip_suffix(suffix))
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| anyhow!("unexpected output of vcgencmd: {output:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn pmic_adc() {
+        let output = "
+             3V7_WL_SW_A current(0)=0.00000000A
+               3V3_SYS_A current(1)=0.06149280A
+              VDD_CORE_A current(7)=2.50000000A
+             3V7_WL_SW_V volt(8)=3.74432000V
+               3V3_SYS_V volt(9)=3.31036600V
+              VDD_CORE_V volt(15)=0.80000000V
+                 EXT5V_V volt(24)=5.12016000V
+        ";
+        let rails = parse_pmic_adc(output).unwrap();
+        assert_eq!(
+            rails,
+            vec![
+                Rail {
+                    name: String::from("3V7_WL_SW"),
+                    current: Some(0.0),
+                    voltage: Some(3.74432)
+                },
+                Rail {
+                    name: String::from("3V3_SYS"),
+                    current: Some(0.0614928),
+                    voltage: Some(3.310366)
+                },
+                Rail {
+                    name: String::from("VDD_CORE"),
+                    current: Some(2.5),
+                    voltage: Some(0.8)
+                },
+                Rail {
+                    name: String::from("EXT5V"),
+                    current: None,
+                    voltage: Some(5.12016)
+                },
+            ]
+        );
+        assert_eq!(rails[2].power(), Some(2.0));
+        assert_eq!(rails[3].power(), None);
+
+        parse_pmic_adc("VDD_CORE_A current(7)=abcA").unwrap_err();
+        parse_pmic_adc("error=1 error_msg=\"Command not registered\"").unwrap_err();
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(parse_value("temp=52.1'C\n", "temp=", "'C").unwrap(), 52.1);
+        assert_eq!(parse_value("volt=0.7200V\n", "volt=", "V").unwrap(), 0.72);
+        parse_value("error", "temp=", "'C").unwrap_err();
+    }
+}
@@ -0,0 +1,122 @@
+use std::{os::unix::fs::PermissionsExt, path::Path, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_raspberry_pi::{Config, RaspberryPiPlugin};
+use pretty_assertions::assert_eq;
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_pmic() {
+    let root = tempdir().unwrap();
+    let vcgencmd = root.path().join("vcgencmd");
+    write_script(&vcgencmd, "echo 'error=2 error_msg=\"Invalid arguments\"'; exit 1");
+    let config = Config {
+        vcgencmd,
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no PMIC)");
+}
+
+#[test]
+fn plugin_with_fake_vcgencmd() {
+    let root = tempdir().unwrap();
+    let vcgencmd = root.path().join("vcgencmd");
+    write_script(
+        &vcgencmd,
+        r#"
+case "$1" in
+  pmic_read_adc)
+    echo "      VDD_CORE_A current(7)=2.50000000A"
+    echo "      DDR_VDDQ_A current(4)=0.10000000A"
+    echo "      VDD_CORE_V volt(15)=0.80000000V"
+    echo "      DDR_VDDQ_V volt(12)=0.60000000V"
+    echo "         EXT5V_V volt(24)=5.00000000V"
+    ;;
+  measure_temp) echo "temp=52.5'C" ;;
+  measure_volts) echo "volt=0.7500V" ;;
+  *) exit 1 ;;
+esac
+"#,
+    );
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        vcgencmd,
+        soc: true,
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<f64>("rpi_rail_power", Unit::Watt)
+        .expect_metric::<f64>("rpi_rail_voltage", Unit::Volt)
+        .expect_metric::<f64>("rpi_rail_current", Unit::Ampere)
+        .expect_metric::<f64>("rpi_soc_temperature", Unit::DegreeCelsius)
+        .expect_metric::<f64>("rpi_core_voltage", Unit::Volt)
+        .expect_source("raspberry-pi", "pmic");
+
+    let runtime = RuntimeExpectations::new().test_source(
+        SourceName::from_str("raspberry-pi", "pmic"),
+        || {},
+        |ctx| {
+            let m = ctx.measurements();
+            let metrics = ctx.metrics();
+            let mut points: Vec<_> = m
+                .iter()
+                .map(|p| {
+                    let metric = metrics.by_id(&p.metric).unwrap().name.clone();
+                    let rail = p
+                        .attributes()
+                        .find(|(k, _)| *k == "rail")
+                        .map(|(_, v)| v.to_string())
+                        .unwrap_or_default();
+                    (metric, rail, p.value.as_f64())
+                })
+                .collect();
+            points.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+            let p = |metric: &str, rail: &str, value: f64| (metric.to_owned(), rail.to_owned(), value);
+            assert_eq!(
+                points,
+                vec![
+                    p("rpi_core_voltage", "", 0.75),
+                    p("rpi_rail_current", "DDR_VDDQ", 0.1),
+                    p("rpi_rail_current", "VDD_CORE", 2.5),
+                    p("rpi_rail_power", "DDR_VDDQ", 0.1 * 0.6),
+                    p("rpi_rail_power", "VDD_CORE", 2.0),
+                    p("rpi_rail_voltage", "DDR_VDDQ", 0.6),
+                    p("rpi_rail_voltage", "EXT5V", 5.0),
+                    p("rpi_rail_voltage", "VDD_CORE", 0.8),
+                    p("rpi_soc_temperature", "", 52.5),
+                ]
+            );
+        },
+    );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn write_script(path: &Path, body: &str) {
+    std::fs::write(path, format!("#!/bin/sh\n{body}\n")).unwrap();
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<RaspberryPiPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
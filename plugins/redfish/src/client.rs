@@ -0,0 +1,119 @@
+//! HTTP client of the Redfish API, with session authentication.
+
+use alumet::plugin::secret::Secret;
+use anyhow::{Context, anyhow};
+use reqwest::{Client, StatusCode, header::HeaderValue};
+use serde_json::{Value, json};
+
+const SESSIONS_PATH: &str = "/redfish/v1/SessionService/Sessions";
+const AUTH_TOKEN_HEADER: &str = "X-Auth-Token";
+
+/// A client of the Redfish service of a BMC.
+pub struct RedfishClient {
+    http: Client,
+    /// Base URL of the BMC, without the trailing slash, for instance `https://10.0.0.1`.
+    base_url: String,
+    username: String,
+    password: Secret,
+    session: Option<Session>,
+}
+
+/// A Redfish session, which avoids to send the credentials with each request.
+struct Session {
+    token: HeaderValue,
+    /// URL of the session, used to delete it.
+    location: Option<String>,
+}
+
+impl RedfishClient {
+    pub fn new(http: Client, base_url: &str, username: String, password: Secret) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            username,
+            password,
+            session: None,
+        }
+    }
+
+    /// Creates a new session.
+    pub async fn login(&mut self) -> anyhow::Result<()> {
+        let response = self
+            .http
+            .post(self.url(SESSIONS_PATH))
+            .json(&json!({ "UserName": self.username, "Password": self.password.expose() }))
+            .send()
+            .await?
+            .error_for_status()
+            .context("could not create a Redfish session")?;
+        let token = response
+            .headers()
+            .get(AUTH_TOKEN_HEADER)
+            .ok_or_else(|| anyhow!("the Redfish session has no {AUTH_TOKEN_HEADER}"))?
+            .clone();
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|l| l.to_str().ok())
+            .map(|l| self.url(l));
+        self.session = Some(Session { token, location });
+        Ok(())
+    }
+
+    /// Deletes the current session, if any.
+    pub async fn logout(&mut self) -> anyhow::Result<()> {
+        if let Some(Session {
+            token,
+            location: Some(location),
+        }) = self.session.take()
+        {
+            self.http
+                .delete(location)
+                .header(AUTH_TOKEN_HEADER, token)
+                .send()
+                .await?
+                .error_for_status()
+                .context("could not delete the Redfish session")?;
+        }
+        Ok(())
+    }
+
+    /// Gets a Redfish resource, such as `/redfish/v1/Chassis`.
+    ///
+    /// If the session has expired, logs in again and retries once.
+    pub async fn get(&mut self, path: &str) -> anyhow::Result<Value> {
+        if self.session.is_none() {
+            self.login().await?;
+        }
+        let mut response = self.send_get(path).await?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            log::debug!("Redfish session expired, logging in again");
+            self.login().await?;
+            response = self.send_get(path).await?;
+        }
+        let resource = response
+            .error_for_status()
+            .with_context(|| format!("could not get {path}"))?
+            .json()
+            .await
+            .with_context(|| format!("invalid resource {path}"))?;
+        Ok(resource)
+    }
+
+    async fn send_get(&self, path: &str) -> reqwest::Result<reqwest::Response> {
+        let mut request = self.http.get(self.url(path));
+        if let Some(session) = &self.session {
+            request = request.header(AUTH_TOKEN_HEADER, session.token.clone());
+        }
+        request.send().await
+    }
+
+    /// Returns the full URL of a path or URL given by the BMC.
+    fn url(&self, path: &str) -> String {
+        if path.starts_with("http://") || path.starts_with("https://") {
+            path.to_owned()
+        } else {
+            format!("{}{path}", self.base_url)
+        }
+    }
+}
@@ -0,0 +1,327 @@
+//! Discovery of the power and temperature readings provided by the Redfish service.
+//!
+//! Recent BMCs provide the `PowerSubsystem`, `EnvironmentMetrics` and `Sensors` resources,
+//! older ones only provide the deprecated `Power` and `Thermal` resources.
+//! When both are available, the recent resources are preferred.
+
+use serde_json::Value;
+
+use crate::client::RedfishClient;
+
+/// What a reading measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadingKind {
+    /// Power consumed by the chassis.
+    ChassisPower,
+    /// Input power of a power supply unit.
+    PsuInputPower,
+    /// Temperature of a sensor.
+    Temperature,
+}
+
+/// A value to read at each measurement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub kind: ReadingKind,
+    /// Id of the chassis.
+    pub chassis: String,
+    /// Name of the PSU or sensor, empty for the chassis power.
+    pub name: String,
+    /// Path of the resource that contains the value.
+    pub path: String,
+    /// JSON pointer to the value, in the resource.
+    pub pointer: String,
+}
+
+impl Reading {
+    fn new(kind: ReadingKind, chassis: &str, name: &str, path: &str, pointer: String) -> Self {
+        Self {
+            kind,
+            chassis: chassis.to_owned(),
+            name: name.to_owned(),
+            path: path.to_owned(),
+            pointer,
+        }
+    }
+
+    /// Extracts the value of the reading from its resource.
+    ///
+    /// Returns `None` if the value is absent or null, which happens when the sensor is unavailable.
+    pub fn extract(&self, resource: &Value) -> Option<f64> {
+        resource.pointer(&self.pointer).and_then(Value::as_f64)
+    }
+}
+
+/// Finds the readings of every chassis.
+pub async fn discover(client: &mut RedfishClient, temperatures: bool) -> anyhow::Result<Vec<Reading>> {
+    let mut readings = Vec::new();
+    let chassis_collection = client.get("/redfish/v1/Chassis").await?;
+    for chassis_path in members(&chassis_collection) {
+        let chassis = client.get(chassis_path).await?;
+        let id = chassis["Id"].as_str().unwrap_or(chassis_path);
+        discover_power(client, id, &chassis, &mut readings).await?;
+        if temperatures {
+            discover_temperatures(client, id, &chassis, &mut readings).await?;
+        }
+    }
+    Ok(readings)
+}
+
+async fn discover_power(
+    client: &mut RedfishClient,
+    id: &str,
+    chassis: &Value,
+    readings: &mut Vec<Reading>,
+) -> anyhow::Result<()> {
+    if let Some(subsystem_path) = link(chassis, "PowerSubsystem") {
+        if let Some(env_path) = link(chassis, "EnvironmentMetrics") {
+            let env = client.get(env_path).await?;
+            readings.extend(environment_power_reading(id, env_path, &env));
+        }
+        let subsystem = client.get(subsystem_path).await?;
+        if let Some(psus_path) = link(&subsystem, "PowerSupplies") {
+            let psus = client.get(psus_path).await?;
+            for psu_path in members(&psus) {
+                let psu = client.get(psu_path).await?;
+                readings.extend(psu_reading(id, &psu));
+            }
+        }
+    } else if let Some(power_path) = link(chassis, "Power") {
+        let power = client.get(power_path).await?;
+        readings.extend(legacy_power_readings(id, power_path, &power));
+    }
+    Ok(())
+}
+
+async fn discover_temperatures(
+    client: &mut RedfishClient,
+    id: &str,
+    chassis: &Value,
+    readings: &mut Vec<Reading>,
+) -> anyhow::Result<()> {
+    if let Some(sensors_path) = link(chassis, "Sensors") {
+        let sensors = client.get(sensors_path).await?;
+        for sensor_path in members(&sensors) {
+            let sensor = client.get(sensor_path).await?;
+            readings.extend(temperature_sensor_reading(id, sensor_path, &sensor));
+        }
+    } else if let Some(thermal_path) = link(chassis, "Thermal") {
+        let thermal = client.get(thermal_path).await?;
+        readings.extend(legacy_thermal_readings(id, thermal_path, &thermal));
+    }
+    Ok(())
+}
+
+/// Returns the paths of the members of a collection.
+fn members(collection: &Value) -> Vec<&str> {
+    collection["Members"]
+        .as_array()
+        .map(|members| members.iter().filter_map(|m| m["@odata.id"].as_str()).collect())
+        .unwrap_or_default()
+}
+
+/// Returns the path of a linked resource, such as `"Power": {"@odata.id": "/redfish/v1/Chassis/1/Power"}`.
+fn link<'a>(resource: &'a Value, name: &str) -> Option<&'a str> {
+    resource[name]["@odata.id"].as_str()
+}
+
+fn environment_power_reading(id: &str, path: &str, env: &Value) -> Option<Reading> {
+    let pointer = "/PowerWatts/Reading";
+    env.pointer(pointer)
+        .map(|_| Reading::new(ReadingKind::ChassisPower, id, "", path, pointer.to_owned()))
+}
+
+fn psu_reading(id: &str, psu: &Value) -> Option<Reading> {
+    // the readings of the PSU are in a separate resource
+    let metrics_path = link(psu, "Metrics")?;
+    let name = psu["Name"].as_str().or(psu["Id"].as_str())?;
+    Some(Reading::new(
+        ReadingKind::PsuInputPower,
+        id,
+        name,
+        metrics_path,
+        String::from("/InputPowerWatts/Reading"),
+    ))
+}
+
+fn legacy_power_readings(id: &str, path: &str, power: &Value) -> Vec<Reading> {
+    let mut readings = Vec::new();
+    // the first power control is the whole chassis
+    if power.pointer("/PowerControl/0/PowerConsumedWatts").is_some() {
+        readings.push(Reading::new(
+            ReadingKind::ChassisPower,
+            id,
+            "",
+            path,
+            String::from("/PowerControl/0/PowerConsumedWatts"),
+        ));
+    }
+    for (i, psu) in power["PowerSupplies"].as_array().into_iter().flatten().enumerate() {
+        if let Some(name) = psu["Name"].as_str()
+            && psu.get("PowerInputWatts").is_some()
+        {
+            readings.push(Reading::new(
+                ReadingKind::PsuInputPower,
+                id,
+                name,
+                path,
+                format!("/PowerSupplies/{i}/PowerInputWatts"),
+            ));
+        }
+    }
+    readings
+}
+
+fn temperature_sensor_reading(id: &str, path: &str, sensor: &Value) -> Option<Reading> {
+    if sensor["ReadingType"].as_str() != Some("Temperature") {
+        return None;
+    }
+    let name = sensor["Name"].as_str()?;
+    Some(Reading::new(
+        ReadingKind::Temperature,
+        id,
+        name,
+        path,
+        String::from("/Reading"),
+    ))
+}
+
+fn legacy_thermal_readings(id: &str, path: &str, thermal: &Value) -> Vec<Reading> {
+    let mut readings = Vec::new();
+    for (i, sensor) in thermal["Temperatures"].as_array().into_iter().flatten().enumerate() {
+        if let Some(name) = sensor["Name"].as_str()
+            && sensor.get("ReadingCelsius").is_some()
+        {
+            readings.push(Reading::new(
+                ReadingKind::Temperature,
+                id,
+                name,
+                path,
+                format!("/Temperatures/{i}/ReadingCelsius"),
+            ));
+        }
+    }
+    readings
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn legacy_power() {
+        let power = json!({
+            "PowerControl": [{ "Name": "System Power Control", "PowerConsumedWatts": 312 }],
+            "PowerSupplies": [
+                { "Name": "PSU1", "PowerInputWatts": 160.5 },
+                { "Name": "PSU2", "PowerInputWatts": null },
+                { "Name": "PSU3", "Status": { "State": "Absent" } }
+            ]
+        });
+        let path = "/redfish/v1/Chassis/1/Power";
+        let readings = legacy_power_readings("1", path, &power);
+        assert_eq!(
+            readings,
+            vec![
+                Reading::new(
+                    ReadingKind::ChassisPower,
+                    "1",
+                    "",
+                    path,
+                    String::from("/PowerControl/0/PowerConsumedWatts")
+                ),
+                Reading::new(
+                    ReadingKind::PsuInputPower,
+                    "1",
+                    "PSU1",
+                    path,
+                    String::from("/PowerSupplies/0/PowerInputWatts")
+                ),
+                Reading::new(
+                    ReadingKind::PsuInputPower,
+                    "1",
+                    "PSU2",
+                    path,
+                    String::from("/PowerSupplies/1/PowerInputWatts")
+                ),
+            ]
+        );
+        let values: Vec<_> = readings.iter().map(|r| r.extract(&power)).collect();
+        assert_eq!(values, vec![Some(312.0), Some(160.5), None]);
+    }
+
+    #[test]
+    fn legacy_thermal() {
+        let thermal = json!({
+            "Temperatures": [
+                { "Name": "Inlet Temp", "ReadingCelsius": 22 },
+                { "Name": "Exhaust Temp", "ReadingCelsius": 35.5 }
+            ],
+            "Fans": [{ "Name": "Fan1", "Reading": 6000 }]
+        });
+        let readings = legacy_thermal_readings("1", "/redfish/v1/Chassis/1/Thermal", &thermal);
+        let names: Vec<_> = readings.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Inlet Temp", "Exhaust Temp"]);
+        let values: Vec<_> = readings.iter().map(|r| r.extract(&thermal)).collect();
+        assert_eq!(values, vec![Some(22.0), Some(35.5)]);
+    }
+
+    #[test]
+    fn power_subsystem() {
+        let env = json!({ "PowerWatts": { "Reading": 374.2, "DataSourceUri": "/redfish/v1/Chassis/1/Sensors/Power" } });
+        let env_path = "/redfish/v1/Chassis/1/EnvironmentMetrics";
+        let reading = environment_power_reading("1", env_path, &env).unwrap();
+        assert_eq!(reading.kind, ReadingKind::ChassisPower);
+        assert_eq!(reading.extract(&env), Some(374.2));
+        assert_eq!(
+            environment_power_reading("1", env_path, &json!({ "TemperatureCelsius": {} })),
+            None
+        );
+
+        let psu = json!({
+            "Id": "0",
+            "Name": "Power Supply 0",
+            "Metrics": { "@odata.id": "/redfish/v1/Chassis/1/PowerSubsystem/PowerSupplies/0/Metrics" }
+        });
+        let reading = psu_reading("1", &psu).unwrap();
+        assert_eq!(reading.name, "Power Supply 0");
+        assert_eq!(
+            reading.path,
+            "/redfish/v1/Chassis/1/PowerSubsystem/PowerSupplies/0/Metrics"
+        );
+        let metrics = json!({ "InputPowerWatts": { "Reading": 187 } });
+        assert_eq!(reading.extract(&metrics), Some(187.0));
+    }
+
+    #[test]
+    fn sensors() {
+        let collection = json!({
+            "Members": [
+                { "@odata.id": "/redfish/v1/Chassis/1/Sensors/InletTemp" },
+                { "@odata.id": "/redfish/v1/Chassis/1/Sensors/Fan1" }
+            ]
+        });
+        assert_eq!(
+            members(&collection),
+            vec![
+                "/redfish/v1/Chassis/1/Sensors/InletTemp",
+                "/redfish/v1/Chassis/1/Sensors/Fan1"
+            ]
+        );
+
+        let inlet = json!({ "Name": "Inlet Temperature", "ReadingType": "Temperature", "Reading": 21.5 });
+        let path = "/redfish/v1/Chassis/1/Sensors/InletTemp";
+        let reading = temperature_sensor_reading("1", path, &inlet).unwrap();
+        assert_eq!(reading.name, "Inlet Temperature");
+        assert_eq!(reading.extract(&inlet), Some(21.5));
+
+        let fan = json!({ "Name": "Fan 1", "ReadingType": "Rotational", "Reading": 6000 });
+        assert_eq!(
+            temperature_sensor_reading("1", "/redfish/v1/Chassis/1/Sensors/Fan1", &fan),
+            None
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use alumet::plugin::{
+    AlumetPluginStart, ConfigTable,
+    rust::{AlumetPlugin, deserialize_config, serialize_config},
+    secret::Secret,
+};
+
+use crate::{
+    client::RedfishClient,
+    source::{Metrics, SourceSettings},
+};
+
+mod client;
+mod discovery;
+mod source;
+
+pub struct RedfishPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for RedfishPlugin {
+    fn name() -> &'static str {
+        "redfish"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(RedfishPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        let http = reqwest::Client::builder()
+            .timeout(self.config.timeout)
+            .danger_accept_invalid_certs(self.config.accept_invalid_certs)
+            .build()
+            .context("could not build the HTTP client")?;
+        let client = RedfishClient::new(
+            http,
+            &self.config.url,
+            self.config.username.clone(),
+            self.config.password.clone(),
+        );
+        let metrics = Metrics::new(alumet)?;
+        let settings = SourceSettings {
+            poll_interval: self.config.poll_interval,
+            temperatures: self.config.temperatures,
+        };
+        alumet.add_autonomous_source_builder("bmc", move |_ctx, cancel_token, out_tx| {
+            Ok(Box::pin(source::run(client, metrics, settings, cancel_token, out_tx)))
+        })?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two Redfish measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Base URL of the BMC, for instance `https://10.0.0.1`.
+    pub url: String,
+
+    /// Name of the Redfish user.
+    pub username: String,
+
+    /// Password of the Redfish user, which can reference a secret (`secret://...`).
+    pub password: Secret,
+
+    /// Accept the invalid TLS certificates, such as the self-signed certificates of most BMCs.
+    pub accept_invalid_certs: bool,
+
+    /// Maximum time to wait for the response of the BMC.
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+
+    /// Measure the temperatures, in addition to the power?
+    pub temperatures: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            url: String::from("https://localhost"),
+            username: String::from("root"),
+            password: Secret::new(""),
+            accept_invalid_certs: false,
+            timeout: Duration::from_secs(10),
+            temperatures: true,
+        }
+    }
+}
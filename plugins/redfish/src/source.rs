@@ -0,0 +1,145 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use alumet::{
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use tokio::{sync::mpsc, time::MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    client::RedfishClient,
+    discovery::{self, Reading, ReadingKind},
+};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    chassis_power: TypedMetricId<f64>,
+    psu_input_power: TypedMetricId<f64>,
+    temperature: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            chassis_power: alumet.create_metric(
+                "redfish_chassis_power",
+                Unit::Watt,
+                "Power consumed by a chassis, reported by the BMC",
+            )?,
+            psu_input_power: alumet.create_metric(
+                "redfish_psu_input_power",
+                Unit::Watt,
+                "Input power of a power supply unit, reported by the BMC",
+            )?,
+            temperature: alumet.create_metric(
+                "redfish_temperature",
+                Unit::DegreeCelsius,
+                "Temperature of a sensor, reported by the BMC",
+            )?,
+        })
+    }
+}
+
+pub struct SourceSettings {
+    pub poll_interval: Duration,
+    pub temperatures: bool,
+}
+
+/// Discovers the readings of the BMC, then measures them at regular intervals, until `cancel_token` is cancelled.
+pub async fn run(
+    mut client: RedfishClient,
+    metrics: Metrics,
+    settings: SourceSettings,
+    cancel_token: CancellationToken,
+    tx: mpsc::Sender<MeasurementBuffer>,
+) -> anyhow::Result<()> {
+    let readings = tokio::select! {
+        biased;
+        _ = cancel_token.cancelled() => return Ok(()),
+        readings = discovery::discover(&mut client, settings.temperatures) => readings?,
+    };
+    if readings.is_empty() {
+        log::warn!("The Redfish service provides no power or temperature reading.");
+    }
+    for r in &readings {
+        log::info!("Found Redfish reading {:?} {} of chassis {}", r.kind, r.name, r.chassis);
+    }
+    let resources = group_by_resource(readings);
+
+    let mut interval = tokio::time::interval(settings.poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            biased;
+            _ = cancel_token.cancelled() => break,
+            _ = interval.tick() => (),
+        };
+        let buffer = poll(&mut client, &metrics, &resources).await;
+        if !buffer.is_empty() {
+            tx.send(buffer).await?;
+        }
+    }
+
+    if let Err(e) = client.logout().await {
+        log::warn!("{e:#}");
+    }
+    Ok(())
+}
+
+/// Groups the readings by resource, in order to get each resource only once per measurement.
+fn group_by_resource(readings: Vec<Reading>) -> BTreeMap<String, Vec<Reading>> {
+    let mut resources: BTreeMap<String, Vec<Reading>> = BTreeMap::new();
+    for reading in readings {
+        resources.entry(reading.path.clone()).or_default().push(reading);
+    }
+    resources
+}
+
+async fn poll(
+    client: &mut RedfishClient,
+    metrics: &Metrics,
+    resources: &BTreeMap<String, Vec<Reading>>,
+) -> MeasurementBuffer {
+    let mut buffer = MeasurementBuffer::new();
+    for (path, readings) in resources {
+        // BMCs are not very reliable: skip the resource and try again at the next measurement
+        let resource = match client.get(path).await {
+            Ok(resource) => resource,
+            Err(e) => {
+                log::warn!("Redfish measurement failed: {e:#}");
+                continue;
+            }
+        };
+        let timestamp = Timestamp::now();
+        for reading in readings {
+            if let Some(value) = reading.extract(&resource) {
+                buffer.push(measurement_point(metrics, reading, timestamp, value));
+            }
+        }
+    }
+    buffer
+}
+
+fn measurement_point(metrics: &Metrics, reading: &Reading, timestamp: Timestamp, value: f64) -> MeasurementPoint {
+    let (metric, name_attr) = match reading.kind {
+        ReadingKind::ChassisPower => (metrics.chassis_power, None),
+        ReadingKind::PsuInputPower => (metrics.psu_input_power, Some("psu")),
+        ReadingKind::Temperature => (metrics.temperature, Some("sensor")),
+    };
+    let point = MeasurementPoint::new(
+        timestamp,
+        metric,
+        Resource::LocalMachine,
+        ResourceConsumer::LocalMachine,
+        value,
+    )
+    .with_attr("chassis", reading.chassis.clone());
+    match name_attr {
+        Some(key) => point.with_attr(key, reading.name.clone()),
+        None => point,
+    }
+}
@@ -0,0 +1,42 @@
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    plugin::PluginMetadata,
+    test::StartupExpectations,
+    units::Unit,
+};
+use plugin_redfish::{Config, RedfishPlugin};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_with_unreachable_bmc() {
+    // nothing listens on the discard port: the discovery fails, but the agent keeps running
+    let config = Config {
+        url: String::from("http://127.0.0.1:9"),
+        timeout: Duration::from_secs(1),
+        ..Default::default()
+    };
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("redfish_chassis_power", Unit::Watt)
+        .expect_metric::<f64>("redfish_psu_input_power", Unit::Watt)
+        .expect_metric::<f64>("redfish_temperature", Unit::DegreeCelsius)
+        .expect_source("redfish", "bmc");
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup_expectation)
+        .build_and_start()
+        .unwrap();
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<RedfishPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -0,0 +1,7 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use a vendored protoc, so that building Alumet does not require to install protobuf.
+    // SAFETY: the build script is single-threaded.
+    unsafe { std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?) };
+    tonic_prost_build::compile_protos("proto/relay.proto")?;
+    Ok(())
+}
@@ -0,0 +1,365 @@
+//! gRPC client of the relay.
+
+use std::{collections::VecDeque, time::Duration};
+
+use alumet::{
+    measurement::MeasurementBuffer,
+    metrics::{Metric, RawMetricId, online::MetricReader},
+    pipeline::elements::output::interface::StreamRecvError,
+};
+use futures::StreamExt;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{
+    Code, Status, Streaming,
+    codec::CompressionEncoding,
+    transport::{Channel, ClientTlsConfig, Endpoint},
+};
+
+use crate::{
+    compression::Compression,
+    grpc::{
+        self,
+        proto::{self, ClientMessage, ServerMessage, client_message, relay_client::RelayClient, server_message},
+    },
+};
+
+use super::{
+    output::{AlumetLink, CONNECT_TIMEOUT, Settings},
+    queue::SendQueue,
+    retry::RetryState,
+};
+
+/// Maximum number of batches that have been sent but not acknowledged by the server.
+const MAX_IN_FLIGHT: usize = 16;
+
+/// Maximum amount of time to wait for the server to acknowledge the last batches, when the output stops.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Exports Alumet measurements to a relay server via gRPC.
+///
+/// The batches of measurements are kept until the server acknowledges them, and sent again after a reconnection
+/// if the acknowledgement has not been received.
+pub struct GrpcOutput {
+    settings: Settings,
+    tls: Option<ClientTlsConfig>,
+    alumet: AlumetLink,
+    /// The stream opened with the relay server, or `None` if it has been lost.
+    connection: Option<Connection>,
+    /// Delays between the attempts to reconnect to the server.
+    reconnect_retry: RetryState,
+    /// When to attempt to reconnect to the server, if the connection has been lost.
+    next_reconnect: tokio::time::Instant,
+    /// Measurements that have not been sent yet.
+    queue: SendQueue,
+    /// Batches that have been sent, but not acknowledged yet, with their sequence number.
+    in_flight: VecDeque<(u64, MeasurementBuffer)>,
+    /// Sequence number of the next batch.
+    next_sequence: u64,
+}
+
+/// A bidirectional stream opened with the relay server.
+struct Connection {
+    requests: mpsc::Sender<ClientMessage>,
+    responses: Streaming<ServerMessage>,
+}
+
+impl GrpcOutput {
+    /// Opens a stream to a remote relay server.
+    pub async fn connect(
+        alumet: AlumetLink,
+        settings: Settings,
+        tls: Option<ClientTlsConfig>,
+    ) -> Result<GrpcOutput, Status> {
+        log::info!("Connecting to relay server {} with gRPC...", settings.server_address);
+        if settings.compression == Compression::Lz4 {
+            log::warn!("gRPC does not support lz4, the measurements will not be compressed. Use zstd instead.");
+        }
+
+        let mut retry_state = RetryState::new(&settings.init_retry);
+        let mut res = connect_to_server(&settings, tls.as_ref(), &alumet.metrics_reader).await;
+        while let Err(e) = res {
+            if !retry_state.can_retry() || is_fatal(&e) {
+                return Err(e);
+            }
+            log::error!("Connection failed: {} - retrying...", e.message());
+            retry_state.after_attempt().await;
+            res = connect_to_server(&settings, tls.as_ref(), &alumet.metrics_reader).await;
+        }
+        log::info!("Successfully connected to relay server.");
+
+        Ok(GrpcOutput {
+            queue: SendQueue::new(settings.buffer.clone()),
+            reconnect_retry: RetryState::new(&settings.reconnect_retry),
+            settings,
+            tls,
+            alumet,
+            connection: Some(res.unwrap()),
+            next_reconnect: tokio::time::Instant::now(),
+            in_flight: VecDeque::new(),
+            next_sequence: 0,
+        })
+    }
+
+    /// Sends the pending batches, in order, as long as the connection works and the server keeps up.
+    async fn send_pending(&mut self) {
+        while self.in_flight.len() < MAX_IN_FLIGHT {
+            let Some(connection) = &self.connection else {
+                // the batches will be sent after the reconnection
+                return;
+            };
+            let Some(buffer) = self.queue.pop_front() else {
+                return;
+            };
+            let sequence = self.next_sequence;
+            self.next_sequence += 1;
+            let msg = ClientMessage {
+                content: Some(client_message::Content::Measurements(grpc::buffer_to_proto(
+                    sequence, &buffer,
+                ))),
+            };
+            self.in_flight.push_back((sequence, buffer));
+            if connection.requests.send(msg).await.is_err() {
+                // the stream has been closed, the error will be received with the responses
+                self.disconnect("the stream has been closed");
+            }
+        }
+    }
+
+    /// Sends metric definitions.
+    async fn send_metrics(&mut self, metrics_buf: &mut Vec<Vec<(RawMetricId, Metric)>>) {
+        let Some(connection) = &self.connection else {
+            // All the metrics of the registry will be sent after the reconnection.
+            metrics_buf.clear();
+            return;
+        };
+        let metrics = metrics_buf
+            .drain(..)
+            .flatten()
+            .map(|(id, def)| grpc::metric_to_proto(id, &def))
+            .collect();
+        let msg = register_metrics(metrics);
+        if connection.requests.send(msg).await.is_err() {
+            // All the metrics of the registry will be sent after the reconnection.
+            self.disconnect("the stream has been closed");
+        }
+    }
+
+    /// Handles a message of the server.
+    async fn handle_response(&mut self, response: Result<Option<ServerMessage>, Status>) -> Result<(), Status> {
+        match response {
+            Ok(Some(ServerMessage {
+                content: Some(server_message::Content::Ack(ack)),
+            })) => {
+                // The server processes the batches in order: every batch up to this one has been received.
+                while self.in_flight.front().is_some_and(|(seq, _)| *seq <= ack.sequence) {
+                    self.in_flight.pop_front();
+                }
+                self.send_pending().await;
+            }
+            Ok(Some(msg)) => {
+                log::warn!("Unexpected message from the relay server: {msg:?}");
+            }
+            Ok(None) => self.disconnect("the server has closed the stream"),
+            Err(status) if is_fatal(&status) => return Err(status),
+            Err(status) => self.disconnect(&status.to_string()),
+        }
+        Ok(())
+    }
+
+    /// Drops the connection. The batches that have not been acknowledged will be sent again after the reconnection.
+    fn disconnect(&mut self, reason: &str) {
+        log::error!("The connection to the relay server has been lost: {reason}");
+        self.connection = None;
+        self.queue.requeue(self.in_flight.drain(..).map(|(_, buffer)| buffer));
+        self.reconnect_retry = RetryState::new(&self.settings.reconnect_retry);
+        self.next_reconnect = tokio::time::Instant::now();
+    }
+
+    /// Attempts to reconnect to the server, and sends the pending batches if it succeeds.
+    async fn reconnect(&mut self) -> Result<(), Status> {
+        log::info!("Reconnecting to relay server {}...", self.settings.server_address);
+        match connect_to_server(&self.settings, self.tls.as_ref(), &self.alumet.metrics_reader).await {
+            Ok(connection) => {
+                log::info!(
+                    "Successfully reconnected to relay server, sending {} pending measurements.",
+                    self.queue.pending_len()
+                );
+                self.connection = Some(connection);
+                self.send_pending().await;
+                Ok(())
+            }
+            Err(e) if is_fatal(&e) => Err(e),
+            Err(e) => {
+                let delay = self.reconnect_retry.next_delay();
+                log::error!("Reconnection failed: {} - retrying in {delay:?}...", e.message());
+                self.next_reconnect = tokio::time::Instant::now() + delay;
+                Ok(())
+            }
+        }
+    }
+
+    /// Sends the remaining batches and waits for the server to acknowledge them, for a limited amount of time.
+    async fn flush(&mut self) -> Result<(), Status> {
+        self.queue.enqueue_buffer();
+        let deadline = tokio::time::Instant::now() + FLUSH_TIMEOUT;
+        self.send_pending().await;
+        while !self.in_flight.is_empty() {
+            let Some(connection) = &mut self.connection else {
+                break;
+            };
+            match tokio::time::timeout_at(deadline, connection.responses.message()).await {
+                Ok(response) => self.handle_response(response).await?,
+                Err(_) => break,
+            }
+        }
+        let lost = self.queue.pending_len() + self.in_flight.iter().map(|(_, b)| b.len()).sum::<usize>();
+        if lost > 0 {
+            log::warn!("{lost} measurements may have been lost because the relay server is unreachable.");
+        }
+        Ok(())
+    }
+
+    /// Continuously polls new measurements and metrics, and sends them via gRPC.
+    pub async fn send_loop(mut self) -> anyhow::Result<()> {
+        loop {
+            let mut metrics_buf = Vec::with_capacity(8);
+            let disconnected = self.connection.is_none();
+            tokio::select! {
+                biased;
+                n_metrics = self.alumet.in_metrics.recv_many(&mut metrics_buf, 8) => {
+                    if n_metrics == 0 {
+                        log::trace!("in_metrics closed => stopping the GrpcOutput");
+                        break; // the metrics channel has been closed, which means that Alumet is shutting down
+                    }
+                    self.send_metrics(&mut metrics_buf).await;
+                }
+                _ = tokio::time::sleep_until(self.next_reconnect), if disconnected => {
+                    self.reconnect().await?;
+                }
+                response = next_response(&mut self.connection), if !disconnected => {
+                    self.handle_response(response).await?;
+                }
+                measurements = self.alumet.in_measurements.0.next() => {
+                    match measurements {
+                        Some(Ok(buf)) => {
+                            if self.queue.push(buf) {
+                                self.send_pending().await;
+                            }
+                        }
+                        Some(Err(StreamRecvError::Lagged(n))) => {
+                            log::warn!("{n} measurement buffers were lost because this output was too slow!");
+                        }
+                        Some(Err(e)) => {
+                            log::error!("unexpected error in async gRPC-based relay output: {e:?}");
+                        }
+                        None => {
+                            // When the measurement channel closes, it's time to stop.
+                            log::trace!("in_measurements closed => stopping the GrpcOutput");
+                            break
+                        }
+                    };
+                },
+            };
+        }
+
+        // Send what remains in the buffers, if possible.
+        self.flush().await?;
+        Ok(())
+    }
+}
+
+/// Returns the next message of the server, or waits forever if there is no connection.
+async fn next_response(connection: &mut Option<Connection>) -> Result<Option<ServerMessage>, Status> {
+    match connection {
+        Some(connection) => connection.responses.message().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Returns `true` if the error cannot be fixed by reconnecting.
+fn is_fatal(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unauthenticated | Code::PermissionDenied | Code::InvalidArgument | Code::Unimplemented
+    )
+}
+
+fn register_metrics(metrics: Vec<proto::Metric>) -> ClientMessage {
+    ClientMessage {
+        content: Some(client_message::Content::RegisterMetrics(proto::RegisterMetrics {
+            metrics,
+        })),
+    }
+}
+
+async fn connect_to_server(
+    settings: &Settings,
+    tls: Option<&ClientTlsConfig>,
+    metrics_reader: &MetricReader,
+) -> Result<Connection, Status> {
+    // open the HTTP/2 connection
+    log::debug!("Opening gRPC channel...");
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    let endpoint = Endpoint::from_shared(format!("{scheme}://{}", settings.server_address))
+        .map_err(|e| Status::invalid_argument(format!("invalid server address: {e}")))?
+        .connect_timeout(CONNECT_TIMEOUT);
+    let endpoint = match tls {
+        Some(tls) => endpoint
+            .tls_config(tls.clone())
+            .map_err(|e| Status::invalid_argument(format!("invalid TLS config: {e}")))?,
+        None => endpoint,
+    };
+    let channel: Channel = endpoint
+        .connect()
+        .await
+        .map_err(|e| Status::unavailable(format!("connection failed: {e}")))?;
+    let mut client =
+        RelayClient::new(channel).max_decoding_message_size(crate::protocol::MAX_MESSAGE_BODY_SIZE as usize);
+    if settings.compression == Compression::Zstd {
+        client = client.send_compressed(CompressionEncoding::Zstd);
+    }
+
+    // The server reads the first message before responding, so it must be queued before opening the stream.
+    let (requests, rx) = mpsc::channel(MAX_IN_FLIGHT + 2);
+    let hello = ClientMessage {
+        content: Some(client_message::Content::Hello(proto::Hello {
+            client_name: settings.client_name.clone(),
+            alumet_core_version: String::from(alumet::VERSION),
+            relay_plugin_version: String::from(crate::PLUGIN_VERSION),
+            token: settings.auth_token.as_ref().map(|token| token.expose().to_owned()),
+            hostname: settings.hostname.clone(),
+            labels: settings.labels.iter().cloned().collect(),
+        })),
+    };
+    // send the metric definitions (for metrics that are known at this point)
+    let metrics = metrics_reader
+        .read()
+        .await
+        .iter()
+        .map(|(id, def)| grpc::metric_to_proto(*id, def))
+        .collect();
+    for msg in [hello, register_metrics(metrics)] {
+        requests.try_send(msg).expect("the channel should have enough capacity");
+    }
+
+    // open the stream and wait for the server to accept us
+    log::debug!("Opening gRPC stream...");
+    let mut responses = client.stream(ReceiverStream::new(rx)).await?.into_inner();
+    match responses.message().await? {
+        Some(ServerMessage {
+            content: Some(server_message::Content::Welcome(welcome)),
+        }) => {
+            log::info!(
+                "Connected to Alumet relay server running Alumet v{}, relay plugin v{}, with gRPC.",
+                welcome.server_alumet_core_version,
+                welcome.server_relay_plugin_version,
+            );
+            Ok(Connection { requests, responses })
+        }
+        other => {
+            log::error!("Cannot connect: received unexpected response from server: {other:?}");
+            Err(Status::unimplemented("unexpected response from server"))
+        }
+    }
+}
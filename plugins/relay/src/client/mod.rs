@@ -1,5 +1,7 @@
+mod grpc;
 mod output;
 mod plugin;
+mod queue;
 mod retry;
 
 pub use plugin::RelayClientPlugin;
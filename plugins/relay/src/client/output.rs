@@ -1,28 +1,37 @@
-use std::{
-    future::Future,
-    io,
-    time::{Duration, Instant},
-};
+use std::{future::Future, io, time::Duration};
 
 use alumet::{
     measurement::MeasurementBuffer,
     metrics::{Metric, RawMetricId, online::MetricReader},
     pipeline::elements::output::{AsyncOutputStream, interface::StreamRecvError},
+    plugin::secret::Secret,
 };
 use futures::StreamExt;
+use rustls::pki_types::ServerName;
 use tokio::{net::TcpStream, sync::mpsc};
+use tokio_rustls::TlsConnector;
+
+use crate::{client::retry::RetryState, compression::Compression, protocol, serde_impl, tls::RelayStream};
 
-use crate::{client::retry::RetryState, protocol, serde_impl};
+use super::{queue::SendQueue, retry::ExponentialRetryPolicy};
 
-use super::retry::ExponentialRetryPolicy;
+/// Maximum amount of time to wait for the TCP connection to be established.
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Exports Alumet measurements to a relay server via TCP.
 pub struct TcpOutput {
     settings: Settings,
     alumet: AlumetLink,
-    out_relay: protocol::MessageStream<TcpStream>,
-    buffer: MeasurementBuffer,
-    buffer_last_send: Instant,
+    /// The connection to the relay server, or `None` if it has been lost.
+    out_relay: Option<protocol::MessageStream<RelayStream>>,
+    /// Compression algorithm negotiated with the server.
+    compression: Compression,
+    /// Delays between the attempts to reconnect to the server.
+    reconnect_retry: RetryState,
+    /// When to attempt to reconnect to the server, if the connection has been lost.
+    next_reconnect: tokio::time::Instant,
+    /// Measurements that have not been sent yet.
+    queue: SendQueue,
 }
 
 /// Links between the Alumet pipeline and the relay output.
@@ -39,15 +48,38 @@ pub struct AlumetLink {
 pub struct Settings {
     pub client_name: String,
     pub server_address: String,
+    /// Secret token that authenticates the client, if required by the server.
+    pub auth_token: Option<Secret>,
+    /// Compression algorithm to use, if the server accepts it.
+    pub compression: Compression,
+    /// Hostname of the node, sent to the server with the labels.
+    pub hostname: String,
+    /// Labels of the node, that the server adds to the measurements of the client.
+    pub labels: Vec<(String, String)>,
+    /// TLS settings of the TCP transport.
+    /// The gRPC transport has its own TLS settings.
+    pub tls: Option<TlsSettings>,
     pub buffer: BufferSettings,
-    pub msg_retry: ExponentialRetryPolicy,
+    /// Policy applied to the first connection: if it fails too many times, the output fails.
     pub init_retry: ExponentialRetryPolicy,
+    /// Policy applied when the connection is lost: the output tries to reconnect forever, with these delays.
+    pub reconnect_retry: ExponentialRetryPolicy,
+}
+
+pub struct TlsSettings {
+    pub connector: TlsConnector,
+    /// The name to check against the server certificate.
+    pub server_name: ServerName<'static>,
 }
 
+#[derive(Clone)]
 pub struct BufferSettings {
     pub initial_capacity: usize,
     pub max_length: usize,
     pub timeout: Duration,
+    /// Maximum number of measurements to keep while the server is unreachable.
+    /// When this limit is reached, the oldest measurements are dropped.
+    pub max_pending: usize,
 }
 
 pub enum RetryAction {
@@ -66,7 +98,7 @@ impl TcpOutput {
 
         // --- connecting
         let mut retry_state = RetryState::new(&settings.init_retry);
-        let mut res = connect_to_server(&settings.server_address, &settings.client_name, &alumet.metrics_reader).await;
+        let mut res = connect_to_server(&settings, &alumet.metrics_reader).await;
         while let Err(e) = res {
             if !retry_state.can_retry() {
                 return Err(e);
@@ -76,79 +108,62 @@ impl TcpOutput {
             match retry_action(&e) {
                 RetryAction::Fail => return Err(e),
                 RetryAction::RetryOp | RetryAction::Reconnect => {
-                    res = connect_to_server(&settings.server_address, &settings.client_name, &alumet.metrics_reader)
-                        .await;
+                    res = connect_to_server(&settings, &alumet.metrics_reader).await;
                 }
             }
         }
         // ---
 
-        let out_relay = res.unwrap();
+        let (out_relay, compression) = res.unwrap();
         log::info!("Successfully connected to relay server.");
 
         // Create a buffer for sending measurements in a more efficient way.
-        let buffer = MeasurementBuffer::with_capacity(settings.buffer.initial_capacity);
+        let queue = SendQueue::new(settings.buffer.clone());
+        let reconnect_retry = RetryState::new(&settings.reconnect_retry);
 
         Ok(TcpOutput {
             settings,
             alumet,
-            out_relay,
-            buffer,
-            buffer_last_send: Instant::now(),
+            out_relay: Some(out_relay),
+            compression,
+            reconnect_retry,
+            next_reconnect: tokio::time::Instant::now(),
+            queue,
         })
     }
 
-    /// Serialize the measurements and send the result via TCP.
-    async fn send_measurements(&mut self, mut measurements: MeasurementBuffer) -> Result<(), protocol::Error> {
-        let now = Instant::now();
-        let size_limit_reached = self.buffer.len() + measurements.len() > self.settings.buffer.max_length;
-        let timeout_expired = (now - self.buffer_last_send) > self.settings.buffer.timeout;
-
-        log::trace!("size_limit_reached={size_limit_reached}, timeout_expired={timeout_expired}, now={now:?}");
-
-        if !size_limit_reached {
-            self.buffer.merge(&mut measurements);
+    /// Buffers the measurements, and sends them via TCP when the buffer is full or when the timeout has expired.
+    async fn send_measurements(&mut self, measurements: MeasurementBuffer) -> Result<(), protocol::Error> {
+        if self.queue.push(measurements) {
+            self.send_pending().await?;
         }
-        // TODO it would be better to use a transform step for the buffering, wouldn't it?
+        Ok(())
+    }
 
-        if size_limit_reached || timeout_expired {
-            self.buffer_last_send = now;
+    /// Sends the pending buffers via TCP, in order, as long as the connection works.
+    async fn send_pending(&mut self) -> Result<(), protocol::Error> {
+        while let Some(buffer) = self.queue.front() {
+            let Some(out_relay) = &mut self.out_relay else {
+                // the buffers will be sent after the reconnection
+                return Ok(());
+            };
+            let content = match self.compression {
+                Compression::None => protocol::MessageEnum::SendMeasurements(protocol::SendMeasurements {
+                    buf: serde_impl::SerdeMeasurementBuffer::Borrowed(buffer),
+                }),
+                algorithm => protocol::MessageEnum::SendCompressedMeasurements(
+                    protocol::SendCompressedMeasurements::compress(algorithm, buffer)?,
+                ),
+            };
             let msg = protocol::MessageBody {
                 sender: self.settings.client_name.clone(),
-                content: protocol::MessageEnum::SendMeasurements(protocol::SendMeasurements {
-                    buf: serde_impl::SerdeMeasurementBuffer::Borrowed(&self.buffer),
-                }),
+                content,
             };
-            // --- writing
-            let mut retry_state = RetryState::new(&self.settings.msg_retry);
-            let mut res = self.out_relay.write_message(&msg).await;
-            while let Err(e) = res {
-                if !retry_state.can_retry() {
-                    return Err(e);
-                }
-                log::error!("Sending measurements failed: {e:?} - retrying...");
-                retry_state.after_attempt().await;
-                match retry_action(&e) {
-                    RetryAction::Fail => return Err(e),
-                    RetryAction::RetryOp => res = self.out_relay.write_message(&msg).await,
-                    RetryAction::Reconnect => {
-                        res = async {
-                            self.out_relay = connect_to_server(
-                                &self.settings.server_address,
-                                &self.settings.client_name,
-                                &self.alumet.metrics_reader,
-                            )
-                            .await?;
-                            self.out_relay.write_message(&msg).await
-                        }
-                        .await;
-                    }
+            match out_relay.write_message(&msg).await {
+                Ok(()) => {
+                    self.queue.pop_front();
                 }
-            }
-            // ---
-            self.buffer.clear();
-            if size_limit_reached {
-                self.buffer.merge(&mut measurements);
+                Err(e) => self.handle_write_error(e, "measurements")?,
             }
         }
         Ok(())
@@ -156,6 +171,12 @@ impl TcpOutput {
 
     /// Sends metric definitions via TCP.
     async fn send_metrics(&mut self, metrics_buf: &mut Vec<Vec<(RawMetricId, Metric)>>) -> Result<(), protocol::Error> {
+        let Some(out_relay) = &mut self.out_relay else {
+            // All the metrics of the registry will be sent after the reconnection.
+            metrics_buf.clear();
+            return Ok(());
+        };
+
         let iterable = metrics_buf.drain(..).flatten();
         let to_send: Vec<_> = iterable.into_iter().map(protocol::Metric::from).collect();
 
@@ -163,35 +184,50 @@ impl TcpOutput {
             sender: self.settings.client_name.clone(),
             content: protocol::MessageEnum::RegisterMetrics(protocol::RegisterMetrics { metrics: to_send }),
         };
+        if let Err(e) = out_relay.write_message(&msg).await {
+            // All the metrics of the registry will be sent after the reconnection.
+            self.handle_write_error(e, "metrics")?;
+        }
+        Ok(())
+    }
 
-        // NOTE: To make this code generic on the operation, we need either a macro,
-        // or the upcoming async closures (https://github.com/rust-lang/rust/pull/132706).
-        let mut retry_state = RetryState::new(&self.settings.msg_retry);
-        let mut res = self.out_relay.write_message(&msg).await;
-        while let Err(e) = res {
-            if !retry_state.can_retry() {
-                return Err(e);
+    /// Drops the connection after a failed write, unless the error cannot be fixed by reconnecting.
+    fn handle_write_error(&mut self, e: protocol::Error, what: &str) -> Result<(), protocol::Error> {
+        match retry_action(&e) {
+            RetryAction::Fail => Err(e),
+            RetryAction::RetryOp | RetryAction::Reconnect => {
+                log::error!("Sending {what} failed: {e:?} - the connection to the relay server has been lost.");
+                self.out_relay = None;
+                self.reconnect_retry = RetryState::new(&self.settings.reconnect_retry);
+                self.next_reconnect = tokio::time::Instant::now();
+                Ok(())
             }
-            log::error!("Sending metrics failed: {e:?} - retrying...");
-            retry_state.after_attempt().await;
-            match retry_action(&e) {
-                RetryAction::Fail => return Err(e),
-                RetryAction::RetryOp => res = self.out_relay.write_message(&msg).await,
-                RetryAction::Reconnect => {
-                    res = async {
-                        self.out_relay = connect_to_server(
-                            &self.settings.server_address,
-                            &self.settings.client_name,
-                            &self.alumet.metrics_reader,
-                        )
-                        .await?;
-                        self.out_relay.write_message(&msg).await
-                    }
-                    .await;
-                }
+        }
+    }
+
+    /// Attempts to reconnect to the server, and sends the pending buffers if it succeeds.
+    async fn reconnect(&mut self) -> Result<(), protocol::Error> {
+        log::info!("Reconnecting to relay server {}...", self.settings.server_address);
+        match connect_to_server(&self.settings, &self.alumet.metrics_reader).await {
+            Ok((out_relay, compression)) => {
+                log::info!(
+                    "Successfully reconnected to relay server, sending {} pending measurements.",
+                    self.queue.pending_len()
+                );
+                self.out_relay = Some(out_relay);
+                self.compression = compression;
+                self.send_pending().await
             }
+            Err(e) => match retry_action(&e) {
+                RetryAction::Fail => Err(e),
+                RetryAction::RetryOp | RetryAction::Reconnect => {
+                    let delay = self.reconnect_retry.next_delay();
+                    log::error!("Reconnection failed: {e:?} - retrying in {delay:?}...");
+                    self.next_reconnect = tokio::time::Instant::now() + delay;
+                    Ok(())
+                }
+            },
         }
-        Ok(())
     }
 
     /// Continuously polls new measurements and metrics, and sends them via TCP.
@@ -204,6 +240,7 @@ impl TcpOutput {
                 // used to produce the measurements (= the registry known by the server is up to date for
                 // this measurement buffer).
                 let mut metrics_buf = Vec::with_capacity(8);
+                let disconnected = self.out_relay.is_none();
                 tokio::select! {
                     biased;
                     n_metrics = self.alumet.in_metrics.recv_many(&mut metrics_buf, 8) => {
@@ -213,6 +250,9 @@ impl TcpOutput {
                         }
                         self.send_metrics(&mut metrics_buf).await?;
                     }
+                    _ = tokio::time::sleep_until(self.next_reconnect), if disconnected => {
+                        self.reconnect().await?;
+                    }
                     measurements = self.alumet.in_measurements.0.next() => {
                         match measurements {
                             Some(Ok(buf)) => self.send_measurements(buf).await?,
@@ -231,6 +271,16 @@ impl TcpOutput {
                     },
                 };
             }
+
+            // Send what remains in the buffers, if possible.
+            self.queue.enqueue_buffer();
+            self.send_pending().await?;
+            if self.queue.pending_len() > 0 {
+                log::warn!(
+                    "{} measurements were lost because the relay server is unreachable.",
+                    self.queue.pending_len()
+                );
+            }
             Ok(())
         }
     }
@@ -263,17 +313,30 @@ fn retry_action(err: &protocol::Error) -> RetryAction {
 
 #[must_use]
 async fn connect_to_server(
-    server_addr: &str,
-    client_name: &str,
+    settings: &Settings,
     metrics_reader: &MetricReader,
-) -> Result<protocol::MessageStream<TcpStream>, protocol::Error> {
+) -> Result<(protocol::MessageStream<RelayStream>, Compression), protocol::Error> {
+    let client_name = &settings.client_name;
+
     // open the TCP connection
     log::debug!("Opening TCP connection...");
-    let stream = TcpStream::connect(server_addr).await?;
+    let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&settings.server_address))
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+
+    // encrypt it if required
+    let stream = match &settings.tls {
+        Some(tls) => {
+            log::debug!("Doing TLS handshake...");
+            let tls_stream = tls.connector.connect(tls.server_name.clone(), stream).await?;
+            RelayStream::Tls(Box::new(tls_stream.into()))
+        }
+        None => RelayStream::Plain(stream),
+    };
 
     // do the protocol handshake
     log::debug!("Doing protocol handshake...");
-    let mut stream = handshake_client2server(client_name.to_owned(), stream).await?;
+    let (mut stream, compression) = handshake_client2server(settings, stream).await?;
 
     // send the metric definitions (for metrics that are known at this point)
     log::debug!("Sending initial metrics...");
@@ -289,23 +352,30 @@ async fn connect_to_server(
     stream.write_message(&msg).await?;
 
     // done
-    Ok(stream)
+    Ok((stream, compression))
 }
 
 async fn handshake_client2server(
-    client_name: String,
-    stream: TcpStream,
-) -> Result<protocol::MessageStream<TcpStream>, protocol::Error> {
+    settings: &Settings,
+    stream: RelayStream,
+) -> Result<(protocol::MessageStream<RelayStream>, Compression), protocol::Error> {
     let mut out_relay = protocol::MessageStream::new(stream);
 
     // send greeting
     out_relay
         .write_message(&protocol::MessageBody {
-            sender: client_name,
+            sender: settings.client_name.clone(),
             content: protocol::MessageEnum::Greet(protocol::Greet {
                 alumet_core_version: String::from(alumet::VERSION),
                 relay_plugin_version: String::from(crate::PLUGIN_VERSION),
                 protocol_version: protocol::PROTOCOL_VERSION,
+                token: settings.auth_token.as_ref().map(|token| token.expose().to_owned()),
+                compression: match settings.compression {
+                    Compression::None => vec![],
+                    algorithm => vec![algorithm],
+                },
+                hostname: settings.hostname.clone(),
+                labels: settings.labels.clone(),
             }),
         })
         .await?;
@@ -322,7 +392,16 @@ async fn handshake_client2server(
                 response.server_relay_plugin_version,
                 response.protocol_version
             );
-            Ok(out_relay)
+            if response.compression != settings.compression {
+                log::warn!(
+                    "The server does not accept the compression algorithm {:?}, the measurements will not be compressed.",
+                    settings.compression
+                );
+            }
+            Ok((out_relay, response.compression))
+        } else if let Some(reason) = response.reason {
+            log::error!("Cannot connect: the server rejected the connection: {reason}");
+            Err(protocol::Error::Rejected(reason))
         } else {
             log::error!(
                 "Cannot connect: client and server are incompatible.
@@ -4,10 +4,14 @@ use alumet::plugin::{
     AlumetPluginStart, ConfigTable,
     rust::{AlumetPlugin, deserialize_config, serialize_config},
 };
-use anyhow::Context;
+use anyhow::{Context, anyhow};
 use tokio::sync::mpsc;
 
-use crate::client::output;
+use crate::{
+    Transport,
+    client::{grpc::GrpcOutput, output},
+    tls,
+};
 
 use super::retry::ExponentialRetryPolicy;
 
@@ -16,10 +20,13 @@ pub struct RelayClientPlugin {
 }
 
 mod config {
-    use std::time::Duration;
+    use std::{collections::BTreeMap, path::PathBuf, time::Duration};
 
+    use alumet::plugin::secret::Secret;
     use serde::{Deserialize, Serialize};
 
+    use crate::{Transport, compression::Compression};
+
     #[derive(Serialize, Deserialize)]
     #[serde(deny_unknown_fields)]
     pub struct Config {
@@ -32,6 +39,25 @@ mod config {
         #[serde(default = "default_relay_server_address")]
         pub relay_server: String,
 
+        /// How to communicate with the server: "tcp" or "grpc".
+        /// It must match the transport of the server.
+        #[serde(default)]
+        pub transport: Transport,
+
+        /// Secret token that this client will use to authenticate to the server, if the server requires it.
+        pub auth_token: Option<Secret>,
+
+        /// Labels of the node, for instance its rack or its role.
+        /// The server adds them, with the hostname of the node, to the attributes of the measurements of this client.
+        #[serde(default)]
+        pub labels: BTreeMap<String, String>,
+
+        /// Compression of the measurements: "none", "lz4" or "zstd".
+        /// If the server does not accept this algorithm, the measurements are not compressed.
+        /// The gRPC transport only supports zstd.
+        #[serde(default = "default_compression")]
+        pub compression: Compression,
+
         /// Maximum number of elements to keep in the output buffer before sending it.
         pub buffer_max_length: usize,
 
@@ -39,10 +65,39 @@ mod config {
         #[serde(with = "humantime_serde")]
         pub buffer_timeout: Duration,
 
+        /// Maximum number of measurements to keep in memory while the server is unreachable.
+        /// When this limit is reached, the oldest measurements are dropped.
+        #[serde(default = "default_buffer_max_pending")]
+        pub buffer_max_pending: usize,
+
         /// Parameter of the exponential backoff strategy that is applied when a network operation fails.
         ///
         /// The delay is multiplied by two after each attempt.
+        /// If the first connection fails more than `max_times`, the client fails.
+        /// If the connection is lost later, the client tries to reconnect forever.
         pub retry: RetryConfig,
+
+        /// Optional TLS encryption of the connection.
+        /// It must be enabled if and only if it is enabled on the server.
+        pub tls: Option<TlsConfig>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct TlsConfig {
+        /// Certificate authorities that sign the certificate of the server, in PEM format.
+        pub server_ca: PathBuf,
+
+        /// The name to check against the certificate of the server.
+        /// Defaults to the host of `relay_server`.
+        pub server_name: Option<String>,
+
+        /// Certificate chain of the client, in PEM format.
+        /// It is required if the server requires the clients to authenticate (mutual TLS).
+        pub certificate: Option<PathBuf>,
+
+        /// Private key of the client, in PEM format.
+        pub private_key: Option<PathBuf>,
     }
 
     #[derive(Serialize, Deserialize)]
@@ -65,9 +120,15 @@ mod config {
             Self {
                 client_name: default_client_name(),
                 relay_server: default_relay_server_address(),
+                transport: Transport::default(),
+                auth_token: None,
+                labels: BTreeMap::new(),
+                compression: default_compression(),
                 buffer_max_length: 4096,
                 buffer_timeout: Duration::from_secs(30),
+                buffer_max_pending: default_buffer_max_pending(),
                 retry: RetryConfig::default(),
+                tls: None,
             }
         }
     }
@@ -88,6 +149,14 @@ mod config {
         binding.to_string_lossy().to_string()
     }
 
+    fn default_buffer_max_pending() -> usize {
+        100_000
+    }
+
+    fn default_compression() -> Compression {
+        Compression::None
+    }
+
     fn default_relay_server_address() -> String {
         String::from("[::1]:50051")
     }
@@ -117,22 +186,40 @@ impl AlumetPlugin for RelayClientPlugin {
     fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
         // Prepare the values that will be moved to the closure.
         let config = self.config.take().unwrap();
+        let transport = config.transport;
+        let (tls, grpc_tls) = match (config.tls, transport) {
+            (Some(tls), Transport::Tcp) => (
+                Some(tls_settings(tls, &config.relay_server).context("invalid TLS config")?),
+                None,
+            ),
+            (Some(tls), Transport::Grpc) => (
+                None,
+                Some(grpc_tls_settings(tls, &config.relay_server).context("invalid TLS config")?),
+            ),
+            (None, _) => (None, None),
+        };
         let client_settings = output::Settings {
             client_name: config.client_name,
             server_address: config.relay_server,
+            auth_token: config.auth_token,
+            compression: config.compression,
+            hostname: node_hostname(),
+            labels: config.labels.into_iter().collect(),
+            tls,
             buffer: output::BufferSettings {
                 initial_capacity: 512,
                 max_length: config.buffer_max_length,
                 timeout: config.buffer_timeout,
+                max_pending: config.buffer_max_pending,
             },
-            msg_retry: ExponentialRetryPolicy {
+            init_retry: ExponentialRetryPolicy {
                 max_retrys: config.retry.max_times,
                 initial_delay: config.retry.initial_delay,
                 max_delay: config.retry.max_delay,
                 multiplier: 2,
             },
-            init_retry: ExponentialRetryPolicy {
-                max_retrys: config.retry.max_times,
+            reconnect_retry: ExponentialRetryPolicy {
+                max_retrys: u16::MAX,
                 initial_delay: config.retry.initial_delay,
                 max_delay: config.retry.max_delay,
                 multiplier: 2,
@@ -144,19 +231,33 @@ impl AlumetPlugin for RelayClientPlugin {
         let (metrics_tx, metrics_rx) = mpsc::unbounded_channel();
 
         // The output is async :)
-        alumet.add_async_output_builder("tcp_client", move |ctx, stream| {
+        let name = match transport {
+            Transport::Tcp => "tcp_client",
+            Transport::Grpc => "grpc_client",
+        };
+        alumet.add_async_output_builder(name, move |ctx, stream| {
             let alumet_link = output::AlumetLink {
                 in_measurements: stream,
                 in_metrics: metrics_rx,
                 metrics_reader: ctx.metrics_reader(),
             };
 
-            let tcp = ctx
-                .async_runtime()
-                .block_on(super::output::TcpOutput::connect(alumet_link, client_settings))
-                .context("relay connection error")?;
-
-            let output: BoxedAsyncOutput = Box::pin(tcp.send_loop());
+            let output: BoxedAsyncOutput = match transport {
+                Transport::Tcp => {
+                    let tcp = ctx
+                        .async_runtime()
+                        .block_on(output::TcpOutput::connect(alumet_link, client_settings))
+                        .context("relay connection error")?;
+                    Box::pin(tcp.send_loop())
+                }
+                Transport::Grpc => {
+                    let grpc = ctx
+                        .async_runtime()
+                        .block_on(GrpcOutput::connect(alumet_link, client_settings, grpc_tls))
+                        .context("relay connection error")?;
+                    Box::pin(grpc.send_loop())
+                }
+            };
             Ok(output)
         })?;
 
@@ -166,13 +267,13 @@ impl AlumetPlugin for RelayClientPlugin {
                 pre_start.metrics().iter().map(|(id, def)| (*id, def.clone())).collect();
             metrics_tx
                 .send(existing_metrics)
-                .context("failed to send the initial metrics to the TCP output")?;
+                .context("failed to send the initial metrics to the relay output")?;
 
             // hook to register the late metrics
             pre_start.add_metric_listener("late_metrics_hook", move |new_metrics| {
                 metrics_tx
                     .send(new_metrics)
-                    .context("failed to send late metrics to the TCP output")
+                    .context("failed to send late metrics to the relay output")
             })?;
             Ok(())
         });
@@ -183,3 +284,44 @@ impl AlumetPlugin for RelayClientPlugin {
         Ok(())
     }
 }
+
+/// Returns the hostname of the node, or an empty string if it cannot be retrieved.
+fn node_hostname() -> String {
+    match hostname::get() {
+        Ok(hostname) => hostname.to_string_lossy().to_string(),
+        Err(e) => {
+            log::warn!("Unable to retrieve the hostname of the node, it will not be sent to the relay server: {e}");
+            String::new()
+        }
+    }
+}
+
+fn tls_settings(config: config::TlsConfig, server_address: &str) -> anyhow::Result<output::TlsSettings> {
+    let client_auth = match (&config.certificate, &config.private_key) {
+        (Some(certificate), Some(private_key)) => Some((certificate.as_path(), private_key.as_path())),
+        (None, None) => None,
+        _ => return Err(anyhow!("certificate and private_key must be set together")),
+    };
+    let connector = tls::client_connector(&config.server_ca, client_auth)?;
+    let server_name = match config.server_name {
+        Some(name) => name.try_into().context("invalid server_name")?,
+        None => tls::server_name(server_address)?,
+    };
+    Ok(output::TlsSettings { connector, server_name })
+}
+
+fn grpc_tls_settings(
+    config: config::TlsConfig,
+    server_address: &str,
+) -> anyhow::Result<tonic::transport::ClientTlsConfig> {
+    let client_auth = match (&config.certificate, &config.private_key) {
+        (Some(certificate), Some(private_key)) => Some((certificate.as_path(), private_key.as_path())),
+        (None, None) => None,
+        _ => return Err(anyhow!("certificate and private_key must be set together")),
+    };
+    let server_name = match config.server_name {
+        Some(name) => name,
+        None => tls::server_name(server_address)?.to_str().into_owned(),
+    };
+    tls::grpc_client_config(&config.server_ca, &server_name, client_auth)
+}
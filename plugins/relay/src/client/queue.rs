@@ -0,0 +1,176 @@
+//! Buffering of the measurements on the client side, shared by all the transports.
+
+use std::{collections::VecDeque, time::Instant};
+
+use alumet::measurement::MeasurementBuffer;
+
+use super::output::BufferSettings;
+
+/// Batches the measurements and keeps them until they are sent to the server.
+pub struct SendQueue {
+    settings: BufferSettings,
+    /// The batch that is being filled.
+    buffer: MeasurementBuffer,
+    buffer_last_send: Instant,
+    /// Batches that are ready to be sent, but have not been sent yet (for instance because the server is unreachable).
+    pending: VecDeque<MeasurementBuffer>,
+    /// Number of measurements in `pending`.
+    pending_len: usize,
+}
+
+impl SendQueue {
+    pub fn new(settings: BufferSettings) -> Self {
+        Self {
+            buffer: MeasurementBuffer::with_capacity(settings.initial_capacity),
+            buffer_last_send: Instant::now(),
+            pending: VecDeque::new(),
+            pending_len: 0,
+            settings,
+        }
+    }
+
+    /// Adds measurements to the current batch.
+    ///
+    /// Returns `true` if the batch has been moved to the pending batches, because it is full or because
+    /// the timeout has expired. In that case, the pending batches should be sent.
+    pub fn push(&mut self, mut measurements: MeasurementBuffer) -> bool {
+        let now = Instant::now();
+        let size_limit_reached = self.buffer.len() + measurements.len() > self.settings.max_length;
+        let timeout_expired = (now - self.buffer_last_send) > self.settings.timeout;
+
+        log::trace!("size_limit_reached={size_limit_reached}, timeout_expired={timeout_expired}, now={now:?}");
+
+        if !size_limit_reached {
+            self.buffer.merge(&mut measurements);
+        }
+        // TODO it would be better to use a transform step for the buffering, wouldn't it?
+
+        if size_limit_reached || timeout_expired {
+            self.buffer_last_send = now;
+            self.enqueue_buffer();
+            if size_limit_reached {
+                self.buffer.merge(&mut measurements);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Moves the current batch to the queue of pending batches, dropping the oldest batches if the queue is full.
+    pub fn enqueue_buffer(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let buffer = std::mem::replace(
+            &mut self.buffer,
+            MeasurementBuffer::with_capacity(self.settings.initial_capacity),
+        );
+        self.pending_len += buffer.len();
+        self.pending.push_back(buffer);
+
+        let mut dropped = 0;
+        while self.pending_len > self.settings.max_pending {
+            let oldest = self.pending.pop_front().unwrap();
+            self.pending_len -= oldest.len();
+            dropped += oldest.len();
+        }
+        if dropped > 0 {
+            log::warn!(
+                "{dropped} measurements were lost because the relay server has been unreachable for too long (buffer limit: {} measurements).",
+                self.settings.max_pending
+            );
+        }
+    }
+
+    /// Returns the oldest pending batch.
+    pub fn front(&self) -> Option<&MeasurementBuffer> {
+        self.pending.front()
+    }
+
+    /// Removes the oldest pending batch.
+    pub fn pop_front(&mut self) -> Option<MeasurementBuffer> {
+        let buffer = self.pending.pop_front()?;
+        self.pending_len -= buffer.len();
+        Some(buffer)
+    }
+
+    /// Puts back batches that have not been received by the server, before the other pending batches.
+    pub fn requeue(&mut self, buffers: impl DoubleEndedIterator<Item = MeasurementBuffer>) {
+        for buffer in buffers.rev() {
+            self.pending_len += buffer.len();
+            self.pending.push_front(buffer);
+        }
+    }
+
+    /// Returns the number of measurements in the pending batches.
+    pub fn pending_len(&self) -> usize {
+        self.pending_len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alumet::{
+        measurement::{MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+        metrics::RawMetricId,
+        resources::{Resource, ResourceConsumer},
+    };
+
+    use super::SendQueue;
+    use crate::client::output::BufferSettings;
+
+    fn measurements(values: &[u64]) -> MeasurementBuffer {
+        let mut buf = MeasurementBuffer::new();
+        for v in values {
+            buf.push(MeasurementPoint::new_untyped(
+                Timestamp::now(),
+                RawMetricId::from_u64(0),
+                Resource::LocalMachine,
+                ResourceConsumer::LocalMachine,
+                WrappedMeasurementValue::U64(*v),
+            ));
+        }
+        buf
+    }
+
+    fn values(buf: &MeasurementBuffer) -> Vec<u64> {
+        buf.iter()
+            .map(|m| match m.value {
+                WrappedMeasurementValue::U64(v) => v,
+                WrappedMeasurementValue::F64(_) => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn drop_oldest_and_requeue() {
+        let mut queue = SendQueue::new(BufferSettings {
+            initial_capacity: 4,
+            max_length: 0,
+            timeout: Duration::ZERO,
+            max_pending: 4,
+        });
+        assert!(queue.push(measurements(&[1, 2])));
+        assert!(queue.push(measurements(&[3, 4])));
+        queue.enqueue_buffer();
+        assert_eq!(queue.pending_len(), 4);
+
+        // the queue is full: the oldest batch is dropped
+        assert!(queue.push(measurements(&[5])));
+        queue.enqueue_buffer();
+        assert_eq!(queue.pending_len(), 3);
+        assert_eq!(values(queue.front().unwrap()), vec![3, 4]);
+
+        // batches that have not been acknowledged go back to the front of the queue
+        let sent = vec![queue.pop_front().unwrap()];
+        assert_eq!(queue.pending_len(), 1);
+        queue.requeue(sent.into_iter());
+        assert_eq!(queue.pending_len(), 3);
+        assert_eq!(values(&queue.pop_front().unwrap()), vec![3, 4]);
+        assert_eq!(values(&queue.pop_front().unwrap()), vec![5]);
+        assert!(queue.pop_front().is_none());
+    }
+}
@@ -29,10 +29,17 @@ impl RetryState {
     }
 
     fn count_and_increase_delay(&mut self) {
-        self.n_retrys += 1;
+        self.n_retrys = self.n_retrys.saturating_add(1);
         self.delay = (self.delay * self.policy.multiplier.into()).min(self.policy.max_delay);
     }
 
+    /// Returns the delay to wait before the next attempt, and increases it, without sleeping.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.delay;
+        self.count_and_increase_delay();
+        delay
+    }
+
     pub async fn after_attempt(&mut self) {
         tokio::time::sleep(self.delay).await;
         self.count_and_increase_delay();
@@ -0,0 +1,83 @@
+//! Compression of the measurements sent over the relay.
+
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+/// Compression level of zstd, as a trade-off between the CPU usage of the client and the bandwidth.
+const ZSTD_LEVEL: i32 = 3;
+
+/// A compression algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// No compression.
+    None,
+    /// LZ4: fast, with a moderate compression ratio.
+    Lz4,
+    /// Zstandard: slower than LZ4, with a better compression ratio.
+    Zstd,
+}
+
+impl Compression {
+    /// Compresses `data`.
+    pub fn compress(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Lz4 => Ok(lz4_flex::block::compress(data)),
+            Compression::Zstd => zstd::bulk::compress(data, ZSTD_LEVEL),
+        }
+    }
+
+    /// Decompresses `data`, which must decompress to exactly `uncompressed_size` bytes.
+    pub fn decompress(self, data: &[u8], uncompressed_size: usize) -> io::Result<Vec<u8>> {
+        let res = match self {
+            Compression::None => data.to_vec(),
+            Compression::Lz4 => {
+                let mut res = vec![0; uncompressed_size];
+                let n = lz4_flex::block::decompress_into(data, &mut res)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                res.truncate(n);
+                res
+            }
+            Compression::Zstd => zstd::bulk::decompress(data, uncompressed_size)?,
+        };
+        if res.len() != uncompressed_size {
+            let msg = format!(
+                "invalid compressed data: expected {uncompressed_size} bytes after decompression, got {}",
+                res.len()
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compression;
+
+    #[test]
+    fn roundtrip() {
+        let data: Vec<u8> = b"rapl_consumed_energy;cpu_package;0;".repeat(100);
+        for algorithm in [Compression::None, Compression::Lz4, Compression::Zstd] {
+            let compressed = algorithm.compress(&data).unwrap();
+            if algorithm != Compression::None {
+                assert!(compressed.len() < data.len() / 10, "{algorithm:?} should compress well");
+            }
+            let decompressed = algorithm.decompress(&compressed, data.len()).unwrap();
+            assert_eq!(decompressed, data, "{algorithm:?}");
+        }
+    }
+
+    #[test]
+    fn wrong_size() {
+        let data: Vec<u8> = b"rapl_consumed_energy;cpu_package;0;".repeat(100);
+        for algorithm in [Compression::None, Compression::Lz4, Compression::Zstd] {
+            let compressed = algorithm.compress(&data).unwrap();
+            // the size is checked in order to prevent decompression bombs
+            algorithm.decompress(&compressed, data.len() - 1).unwrap_err();
+            algorithm.decompress(&compressed, data.len() + 1).unwrap_err();
+        }
+    }
+}
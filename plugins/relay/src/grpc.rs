@@ -0,0 +1,203 @@
+//! gRPC transport of the relay, as an alternative to the postcard-based protocol.
+//!
+//! The service is defined in `proto/relay.proto`, which can be used to implement collectors in other languages.
+
+use alumet::{
+    measurement::{AttributeValue, MeasurementBuffer, WrappedMeasurementValue},
+    metrics::RawMetricId,
+};
+
+#[allow(clippy::all)]
+pub mod proto {
+    tonic::include_proto!("alumet.relay.v1");
+}
+
+use proto::{attribute, measurement_point};
+
+/// Converts a metric definition of the client to its gRPC representation.
+#[cfg(feature = "client")]
+pub fn metric_to_proto(id: RawMetricId, metric: &alumet::metrics::Metric) -> proto::Metric {
+    use alumet::measurement::WrappedMeasurementType;
+
+    let value_type = match metric.value_type {
+        WrappedMeasurementType::F64 => proto::ValueType::F64,
+        WrappedMeasurementType::U64 => proto::ValueType::U64,
+    };
+    proto::Metric {
+        id: id.as_u64(),
+        name: metric.name.clone(),
+        value_type: value_type.into(),
+        unit: metric.unit.base_unit.unique_name().to_owned(),
+        unit_prefix: metric.unit.prefix.unique_name().to_owned(),
+    }
+}
+
+/// Converts a metric definition received by the server, and returns it with the id chosen by the client.
+#[cfg(feature = "server")]
+pub fn metric_from_proto(metric: proto::Metric) -> anyhow::Result<(u64, alumet::metrics::Metric)> {
+    use alumet::{measurement::WrappedMeasurementType, units::PrefixedUnit};
+    use anyhow::Context;
+
+    let value_type = match proto::ValueType::try_from(metric.value_type) {
+        Ok(proto::ValueType::F64) => WrappedMeasurementType::F64,
+        Ok(proto::ValueType::U64) => WrappedMeasurementType::U64,
+        Err(_) => return Err(anyhow::anyhow!("invalid value type {}", metric.value_type)),
+    };
+    let unit = PrefixedUnit {
+        base_unit: metric
+            .unit
+            .parse()
+            .with_context(|| format!("invalid base unit {}", metric.unit))?,
+        prefix: metric
+            .unit_prefix
+            .parse()
+            .with_context(|| format!("invalid unit prefix {}", metric.unit_prefix))?,
+    };
+    let def = alumet::metrics::Metric {
+        name: metric.name,
+        description: String::from("remote metric via plugin_relay"),
+        value_type,
+        unit,
+    };
+    Ok((metric.id, def))
+}
+
+/// Converts a buffer of measurements to a batch that can be sent over gRPC.
+#[cfg(feature = "client")]
+pub fn buffer_to_proto(sequence: u64, buffer: &MeasurementBuffer) -> proto::MeasurementBatch {
+    let points = buffer
+        .iter()
+        .map(|point| {
+            let (timestamp_secs, timestamp_nanos) = point.timestamp.to_unix_timestamp();
+            let value = match point.value {
+                WrappedMeasurementValue::F64(v) => measurement_point::Value::F64(v),
+                WrappedMeasurementValue::U64(v) => measurement_point::Value::U64(v),
+            };
+            let attributes = point
+                .attributes()
+                .map(|(key, value)| proto::Attribute {
+                    key: key.to_owned(),
+                    value: Some(match value {
+                        AttributeValue::F64(v) => attribute::Value::F64(*v),
+                        AttributeValue::U64(v) => attribute::Value::U64(*v),
+                        AttributeValue::Bool(v) => attribute::Value::Bool(*v),
+                        AttributeValue::Str(v) => attribute::Value::Str(v.to_string()),
+                        AttributeValue::String(v) => attribute::Value::Str(v.clone()),
+                        AttributeValue::ListU64(items) => {
+                            attribute::Value::ListU64(proto::ListU64 { items: items.clone() })
+                        }
+                    }),
+                })
+                .collect();
+            proto::MeasurementPoint {
+                metric_id: point.metric.as_u64(),
+                timestamp_secs,
+                timestamp_nanos,
+                value: Some(value),
+                resource_kind: point.resource.kind().to_owned(),
+                resource_id: point.resource.id_string().unwrap_or_default(),
+                consumer_kind: point.consumer.kind().to_owned(),
+                consumer_id: point.consumer.id_string().unwrap_or_default(),
+                attributes,
+            }
+        })
+        .collect();
+    proto::MeasurementBatch { sequence, points }
+}
+
+/// Converts a batch of measurements received over gRPC.
+///
+/// The metric ids are the ids chosen by the client, they must be converted before pushing the measurements to the pipeline.
+#[cfg(feature = "server")]
+pub fn buffer_from_proto(points: Vec<proto::MeasurementPoint>) -> anyhow::Result<MeasurementBuffer> {
+    use std::time::{Duration, SystemTime};
+
+    use alumet::{
+        measurement::MeasurementPoint,
+        resources::{Resource, ResourceConsumer},
+    };
+    use anyhow::Context;
+
+    let mut buffer = MeasurementBuffer::with_capacity(points.len());
+    for point in points {
+        let timestamp = SystemTime::UNIX_EPOCH
+            .checked_add(Duration::new(point.timestamp_secs, point.timestamp_nanos))
+            .context("invalid timestamp")?
+            .into();
+        let value = match point.value.context("missing value")? {
+            measurement_point::Value::F64(v) => WrappedMeasurementValue::F64(v),
+            measurement_point::Value::U64(v) => WrappedMeasurementValue::U64(v),
+        };
+        let resource = Resource::parse(point.resource_kind, point.resource_id)?;
+        let consumer = ResourceConsumer::parse(point.consumer_kind, point.consumer_id)?;
+        let attributes = point
+            .attributes
+            .into_iter()
+            .map(|attr| {
+                let value = match attr
+                    .value
+                    .with_context(|| format!("missing value of attribute {}", attr.key))?
+                {
+                    attribute::Value::F64(v) => AttributeValue::F64(v),
+                    attribute::Value::U64(v) => AttributeValue::U64(v),
+                    attribute::Value::Bool(v) => AttributeValue::Bool(v),
+                    attribute::Value::Str(v) => AttributeValue::String(v),
+                    attribute::Value::ListU64(list) => AttributeValue::ListU64(list.items),
+                };
+                Ok((attr.key, value))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let metric = RawMetricId::from_u64(point.metric_id);
+        buffer.push(
+            MeasurementPoint::new_untyped(timestamp, metric, resource, consumer, value).with_attr_vec(attributes),
+        );
+    }
+    Ok(buffer)
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use alumet::{
+        measurement::{AttributeValue, MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+        metrics::RawMetricId,
+        resources::{Resource, ResourceConsumer},
+    };
+
+    use super::{buffer_from_proto, buffer_to_proto};
+
+    #[test]
+    fn measurements_roundtrip() {
+        let mut buffer = MeasurementBuffer::new();
+        buffer.push(
+            MeasurementPoint::new_untyped(
+                Timestamp::now(),
+                RawMetricId::from_u64(1),
+                Resource::CpuPackage { id: 0 },
+                ResourceConsumer::LocalMachine,
+                WrappedMeasurementValue::F64(12.5),
+            )
+            .with_attr("domain", String::from("package"))
+            .with_attr("cores", AttributeValue::ListU64(vec![0, 1])),
+        );
+        buffer.push(MeasurementPoint::new_untyped(
+            Timestamp::now(),
+            RawMetricId::from_u64(2),
+            Resource::LocalMachine,
+            ResourceConsumer::Process { pid: 42 },
+            WrappedMeasurementValue::U64(7),
+        ));
+
+        let batch = buffer_to_proto(3, &buffer);
+        assert_eq!(batch.sequence, 3);
+        let converted = buffer_from_proto(batch.points).unwrap();
+        assert_eq!(converted.len(), buffer.len());
+        for (a, b) in converted.iter().zip(buffer.iter()) {
+            assert_eq!(a.metric, b.metric);
+            assert_eq!(a.timestamp, b.timestamp);
+            assert_eq!(a.value, b.value);
+            assert_eq!(a.resource, b.resource);
+            assert_eq!(a.consumer, b.consumer);
+            assert_eq!(a.attributes().collect::<Vec<_>>(), b.attributes().collect::<Vec<_>>());
+        }
+    }
+}
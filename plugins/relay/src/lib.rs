@@ -3,7 +3,25 @@ pub mod client;
 #[cfg(feature = "server")]
 pub mod server;
 
+mod compression;
+mod grpc;
 mod protocol;
 mod serde_impl;
+mod tls;
+
+use serde::{Deserialize, Serialize};
 
 pub const PLUGIN_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+/// Transport used between the relay clients and the relay server.
+///
+/// The client and the server must use the same transport.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Transport {
+    /// The relay protocol, over TCP.
+    #[default]
+    Tcp,
+    /// gRPC over HTTP/2, with the service defined in `proto/relay.proto`.
+    Grpc,
+}
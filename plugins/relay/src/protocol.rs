@@ -2,22 +2,25 @@
 
 use std::{io, time::Duration};
 
-use alumet::{measurement::WrappedMeasurementType, metrics::RawMetricId, units::PrefixedUnit};
+use alumet::{
+    measurement::{MeasurementBuffer, WrappedMeasurementType},
+    metrics::RawMetricId,
+    units::PrefixedUnit,
+};
 use anyhow::Context;
 use bytes::BytesMut;
 use serde::{Deserialize, Serialize};
 use tokio::{
     io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
-    net::TcpStream,
     time::error::Elapsed,
 };
 
-use crate::serde_impl;
+use crate::{compression::Compression, serde_impl, tls::RelayStream};
 
 /// Version number of the current protocol.
 ///
 /// IMPORTANT: you must increase this number when the protocol changes.
-pub const PROTOCOL_VERSION: u32 = 2;
+pub const PROTOCOL_VERSION: u32 = 5;
 
 /// Maximum size (in bytes) of a message body.
 ///
@@ -54,6 +57,10 @@ pub enum Error {
 
     #[error("received an unexpected response")]
     Unexpected,
+
+    /// The server refused the connection.
+    #[error("the server rejected the connection: {0}")]
+    Rejected(String),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -71,14 +78,38 @@ pub enum MessageEnum<'s> {
     GreetResponse(GreetResponse),
     RegisterMetrics(RegisterMetrics),
     SendMeasurements(SendMeasurements<'s>),
+    SendCompressedMeasurements(SendCompressedMeasurements),
 }
 
 /// Sent by the client at the beginning of the connection.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct Greet {
     pub alumet_core_version: String,
     pub relay_plugin_version: String,
     pub protocol_version: u32,
+    /// Secret token that authenticates the client, if any.
+    pub token: Option<String>,
+    /// Compression algorithms that the client can use, by order of preference.
+    pub compression: Vec<Compression>,
+    /// Hostname of the node that runs the client, empty if it is unknown.
+    pub hostname: String,
+    /// Labels of the node, set in the configuration of the client.
+    pub labels: Vec<(String, String)>,
+}
+
+// The token must not appear in the logs.
+impl std::fmt::Debug for Greet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Greet")
+            .field("alumet_core_version", &self.alumet_core_version)
+            .field("relay_plugin_version", &self.relay_plugin_version)
+            .field("protocol_version", &self.protocol_version)
+            .field("token", &self.token.as_ref().map(|_| "<redacted>"))
+            .field("compression", &self.compression)
+            .field("hostname", &self.hostname)
+            .field("labels", &self.labels)
+            .finish()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -87,6 +118,10 @@ pub struct GreetResponse {
     pub server_alumet_core_version: String,
     pub server_relay_plugin_version: String,
     pub protocol_version: u32,
+    /// Why the client has been rejected, if `accept` is false for another reason than the protocol version.
+    pub reason: Option<String>,
+    /// Compression algorithm that the client must use, chosen among the algorithms proposed in [`Greet`].
+    pub compression: Compression,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -119,6 +154,46 @@ pub struct SendMeasurements<'s> {
     pub buf: serde_impl::SerdeMeasurementBuffer<'s>,
 }
 
+/// Like [`SendMeasurements`], but the serialized buffer is compressed.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SendCompressedMeasurements {
+    pub algorithm: Compression,
+    /// Size of the serialized buffer, before compression.
+    pub uncompressed_size: u32,
+    pub data: Vec<u8>,
+}
+
+impl SendCompressedMeasurements {
+    pub fn compress(algorithm: Compression, buf: &MeasurementBuffer) -> Result<Self, Error> {
+        let serialized = postcard::to_allocvec(&serde_impl::SerdeMeasurementBuffer::Borrowed(buf))?;
+        let data = algorithm.compress(&serialized)?;
+        log::trace!(
+            "compressed {} bytes to {} bytes with {algorithm:?}",
+            serialized.len(),
+            data.len()
+        );
+        Ok(Self {
+            algorithm,
+            uncompressed_size: serialized.len() as u32,
+            data,
+        })
+    }
+
+    pub fn decompress(self) -> Result<MeasurementBuffer, Error> {
+        // Prevent decompression bombs.
+        if self.uncompressed_size > MAX_MESSAGE_BODY_SIZE {
+            let msg = format!(
+                "message too big: uncompressed size is {} but it should be less than the maximum allowed {MAX_MESSAGE_BODY_SIZE}",
+                self.uncompressed_size
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg).into());
+        }
+        let serialized = self.algorithm.decompress(&self.data, self.uncompressed_size as usize)?;
+        let buf: serde_impl::SerdeMeasurementBuffer = postcard::from_bytes(&serialized)?;
+        Ok(buf.owned())
+    }
+}
+
 /// Allows to read/write protocol messages from/to an asynchronous IO stream.
 ///
 /// # Coherency
@@ -263,14 +338,14 @@ impl<S: AsyncRead + AsyncWrite + Unpin> MessageStream<S> {
     }
 }
 
-impl MessageStream<TcpStream> {
+impl MessageStream<RelayStream> {
     pub fn peer_addr(&self) -> Result<std::net::SocketAddr, std::io::Error> {
-        self.stream.peer_addr()
+        self.stream.tcp().peer_addr()
     }
 
     #[allow(unused)]
     pub fn local_addr(&self) -> Result<std::net::SocketAddr, std::io::Error> {
-        self.stream.local_addr()
+        self.stream.tcp().local_addr()
     }
 
     pub async fn shutdown(&mut self) -> Result<(), std::io::Error> {
@@ -366,10 +441,48 @@ impl From<(RawMetricId, alumet::metrics::Metric)> for Metric {
 
 #[cfg(test)]
 mod tests {
+    use alumet::{
+        measurement::{MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+        metrics::RawMetricId,
+        resources::{Resource, ResourceConsumer},
+    };
+
+    use super::SendCompressedMeasurements;
+    use crate::compression::Compression;
 
     #[test]
     fn test_message_rw_simple() -> anyhow::Result<()> {
         // TODO
         Ok(())
     }
+
+    #[test]
+    fn test_compressed_measurements() -> anyhow::Result<()> {
+        let mut buf = MeasurementBuffer::new();
+        for i in 0..100 {
+            let point = MeasurementPoint::new_untyped(
+                Timestamp::now(),
+                RawMetricId::from_u64(i % 4),
+                Resource::CpuPackage { id: 0 },
+                ResourceConsumer::LocalMachine,
+                WrappedMeasurementValue::U64(i),
+            )
+            .with_attr("domain", String::from("package"));
+            buf.push(point);
+        }
+
+        for algorithm in [Compression::Lz4, Compression::Zstd] {
+            let msg = SendCompressedMeasurements::compress(algorithm, &buf)?;
+            assert!((msg.data.len() as u32) < msg.uncompressed_size);
+            let decompressed = msg.decompress()?;
+            assert_eq!(decompressed.len(), buf.len());
+            for (a, b) in decompressed.iter().zip(buf.iter()) {
+                assert_eq!(a.metric, b.metric);
+                assert_eq!(a.timestamp, b.timestamp);
+                assert_eq!(a.value, b.value);
+                assert_eq!(a.attributes().collect::<Vec<_>>(), b.attributes().collect::<Vec<_>>());
+            }
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,72 @@
+//! Authentication of the relay clients.
+
+use std::collections::HashMap;
+
+use alumet::plugin::secret::Secret;
+use anyhow::anyhow;
+
+/// The clients that are allowed to connect to the server, with their token.
+pub struct ClientTokens {
+    tokens: HashMap<String, Secret>,
+}
+
+impl ClientTokens {
+    /// Creates a new set of tokens, by client name.
+    pub fn new(tokens: HashMap<String, Secret>) -> anyhow::Result<Self> {
+        if let Some((client, _)) = tokens.iter().find(|(_, token)| token.expose().is_empty()) {
+            return Err(anyhow!("empty token for client {client}"));
+        }
+        Ok(Self { tokens })
+    }
+
+    /// Checks that `client` is allowed to connect with the given token.
+    pub fn verify(&self, client: &str, token: Option<&str>) -> bool {
+        match (self.tokens.get(client), token) {
+            (Some(expected), Some(token)) => constant_time_eq(expected.expose().as_bytes(), token.as_bytes()),
+            _ => false,
+        }
+    }
+}
+
+/// Compares two byte strings in a time that does not depend on their content,
+/// in order not to reveal the expected token to an attacker who measures the response time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alumet::plugin::secret::Secret;
+
+    use super::ClientTokens;
+
+    #[test]
+    fn verify() {
+        let tokens = ClientTokens::new(HashMap::from([
+            (String::from("node-1"), Secret::new("secret-1")),
+            (String::from("node-2"), Secret::new("secret-2")),
+        ]))
+        .unwrap();
+        assert!(tokens.verify("node-1", Some("secret-1")));
+        assert!(tokens.verify("node-2", Some("secret-2")));
+        // token of another client
+        assert!(!tokens.verify("node-1", Some("secret-2")));
+        // wrong or missing token
+        assert!(!tokens.verify("node-1", Some("secret")));
+        assert!(!tokens.verify("node-1", Some("")));
+        assert!(!tokens.verify("node-1", None));
+        // unknown client
+        assert!(!tokens.verify("node-3", Some("secret-1")));
+    }
+
+    #[test]
+    fn empty_token() {
+        let tokens = HashMap::from([(String::from("node-1"), Secret::new(""))]);
+        assert!(ClientTokens::new(tokens).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! gRPC server of the relay.
+
+use std::sync::Arc;
+
+use alumet::{measurement::MeasurementBuffer, metrics::online::MetricSender};
+use anyhow::anyhow;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::grpc::{
+    self,
+    proto::{
+        self, ClientMessage, ServerMessage, client_message,
+        relay_server::{Relay, RelayServer},
+        server_message,
+    },
+};
+
+use super::{auth::ClientTokens, metrics::MetricConverter, tenant::Tenants};
+
+/// Maximum number of responses that are waiting to be sent to a client.
+const RESPONSE_CHANNEL_CAPACITY: usize = 64;
+
+/// Implementation of the gRPC relay service.
+pub struct GrpcRelay {
+    cancel_token: CancellationToken,
+    auth: Option<Arc<ClientTokens>>,
+    tenants: Arc<Tenants>,
+    measurement_tx: mpsc::Sender<MeasurementBuffer>,
+    metrics_tx: MetricSender,
+}
+
+/// Receives the messages of one client.
+struct GrpcReceiver {
+    cancel_token: CancellationToken,
+    client: String,
+    incoming: Streaming<ClientMessage>,
+    responses: mpsc::Sender<Result<ServerMessage, Status>>,
+    out_tx: mpsc::Sender<MeasurementBuffer>,
+    metrics: MetricConverter,
+}
+
+impl GrpcRelay {
+    pub fn new(
+        cancel_token: CancellationToken,
+        auth: Option<ClientTokens>,
+        tenants: Arc<Tenants>,
+        measurement_tx: mpsc::Sender<MeasurementBuffer>,
+        metrics_tx: MetricSender,
+    ) -> Self {
+        Self {
+            cancel_token,
+            auth: auth.map(Arc::new),
+            tenants,
+            measurement_tx,
+            metrics_tx,
+        }
+    }
+
+    /// Wraps the service in a tonic server.
+    ///
+    /// If `accept_zstd` is true, the clients are allowed to compress their messages with zstd.
+    pub fn into_server(self, accept_zstd: bool) -> RelayServer<Self> {
+        let server = RelayServer::new(self).max_decoding_message_size(crate::protocol::MAX_MESSAGE_BODY_SIZE as usize);
+        if accept_zstd {
+            server.accept_compressed(tonic::codec::CompressionEncoding::Zstd)
+        } else {
+            server
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Relay for GrpcRelay {
+    type StreamStream = ReceiverStream<Result<ServerMessage, Status>>;
+
+    async fn stream(&self, request: Request<Streaming<ClientMessage>>) -> Result<Response<Self::StreamStream>, Status> {
+        let remote_addr = request
+            .remote_addr()
+            .map_or_else(|| String::from("?"), |s| s.to_string());
+        let mut incoming = request.into_inner();
+
+        // The first message must introduce the client.
+        let hello = match incoming.message().await? {
+            Some(ClientMessage {
+                content: Some(client_message::Content::Hello(hello)),
+            }) => hello,
+            _ => return Err(Status::invalid_argument("the first message must be a hello")),
+        };
+        let client = hello.client_name;
+        let (hostname, labels) = (hello.hostname, hello.labels);
+        if let Some(auth) = &self.auth {
+            if !auth.verify(&client, hello.token.as_deref()) {
+                log::warn!("Client {client} ({remote_addr}) failed to authenticate. Rejecting.");
+                return Err(Status::unauthenticated("authentication failed"));
+            }
+            log::info!("Client {client} ({remote_addr}) is authenticated.");
+        }
+        if let Err(e) = self.tenants.of_client(&client) {
+            log::warn!("Client {client} ({remote_addr}) does not belong to any tenant. Rejecting.");
+            return Err(Status::permission_denied(e.to_string()));
+        }
+        log::info!(
+            "Client {client} ({remote_addr}) connected with gRPC: Alumet v{}, relay plugin v{}",
+            hello.alumet_core_version,
+            hello.relay_plugin_version,
+        );
+
+        let (responses, rx) = mpsc::channel(RESPONSE_CHANNEL_CAPACITY);
+        let welcome = ServerMessage {
+            content: Some(server_message::Content::Welcome(proto::Welcome {
+                server_alumet_core_version: alumet::VERSION.to_string(),
+                server_relay_plugin_version: crate::PLUGIN_VERSION.to_string(),
+            })),
+        };
+        responses
+            .send(Ok(welcome))
+            .await
+            .map_err(|_| Status::internal("response channel closed"))?;
+
+        let mut metrics = MetricConverter::new(self.metrics_tx.clone(), self.tenants.clone());
+        metrics.set_node_metadata(hostname, labels);
+        let receiver = GrpcReceiver {
+            cancel_token: self.cancel_token.child_token(),
+            client,
+            incoming,
+            responses,
+            out_tx: self.measurement_tx.clone(),
+            metrics,
+        };
+        tokio::spawn(async move {
+            let client = receiver.client.clone();
+            if let Err(e) = receiver.receive_loop().await {
+                log::error!("Error in relay source connected to client {client} ({remote_addr}): {e:?}");
+            }
+            log::info!("Client disconnected: {client} ({remote_addr})");
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+impl GrpcReceiver {
+    async fn receive_loop(mut self) -> anyhow::Result<()> {
+        loop {
+            // When the server stops, dropping `self.responses` ends the stream of responses, which ends the RPC.
+            let message = tokio::select! {
+                biased;
+                _ = self.cancel_token.cancelled() => break,
+                message = self.incoming.message() => message?,
+            };
+            let Some(message) = message else {
+                // stop the loop normally
+                break;
+            };
+            if let Err(e) = self.process_message(message).await {
+                // tell the client why we close the stream
+                let _ = self.responses.send(Err(Status::invalid_argument(e.to_string()))).await;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn process_message(&mut self, message: ClientMessage) -> anyhow::Result<()> {
+        match message.content {
+            Some(client_message::Content::RegisterMetrics(register_metrics)) => {
+                let mut metric_ids = Vec::with_capacity(register_metrics.metrics.len());
+                let mut metric_defs = Vec::with_capacity(register_metrics.metrics.len());
+                for proto_metric in register_metrics.metrics {
+                    let (id, def) = grpc::metric_from_proto(proto_metric)?;
+                    metric_ids.push(id);
+                    metric_defs.push(def);
+                }
+                self.metrics
+                    .register_from_client(&self.client, metric_ids, metric_defs)
+                    .await?;
+            }
+            Some(client_message::Content::Measurements(batch)) => {
+                let mut measurements = grpc::buffer_from_proto(batch.points)?;
+                self.metrics.convert_all(&self.client, &mut measurements)?;
+                self.out_tx.send(measurements).await?;
+                // acknowledge the batch, so that the client can forget it
+                let ack = ServerMessage {
+                    content: Some(server_message::Content::Ack(proto::Ack {
+                        sequence: batch.sequence,
+                    })),
+                };
+                self.responses.send(Ok(ack)).await?;
+            }
+            Some(client_message::Content::Hello(_)) => return Err(anyhow!("unexpected hello")),
+            None => return Err(anyhow!("empty message")),
+        }
+        Ok(())
+    }
+}
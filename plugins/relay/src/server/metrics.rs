@@ -1,13 +1,27 @@
 //! Synchronization and conversion of metric ids between the clients and the server.
 
+use std::sync::Arc;
+
 use alumet::{
-    measurement::MeasurementBuffer,
+    measurement::{AttributeValue, MeasurementBuffer, MeasurementPoint},
     metrics::{Metric, RawMetricId, duplicate::DuplicateReaction, online::MetricSender},
 };
 use anyhow::{Context, anyhow};
 
+use super::tenant::{TENANT_ATTRIBUTE, Tenants};
+
 const MAX_METRIC_ID: usize = 65535;
 
+/// Attribute that contains the name of the client that produced the measurement.
+const CLIENT_ATTRIBUTE: &str = "relay_client";
+
+/// Attribute that contains the names of the intermediate collectors that forwarded the measurement,
+/// from the closest to the client to the closest to the server, separated by `/`.
+const PATH_ATTRIBUTE: &str = "relay_path";
+
+/// Attribute that contains the hostname of the node that produced the measurement.
+const HOSTNAME_ATTRIBUTE: &str = "hostname";
+
 /// Mapping between metric ids used by the client and metric ids used by the server.
 pub struct MetricIds {
     id_client_to_server: nohash_hasher::IntMap<u64, u64>,
@@ -15,28 +29,57 @@ pub struct MetricIds {
 }
 
 /// Converts metric ids from the client to the server ids.
-/// Also adds the client name as an attribute of the measurement points.
+/// Also adds the client name, its tenant (if any) and the metadata of its node as attributes of the measurement points.
 pub struct MetricConverter {
     inner: MetricSender,
     ids: MetricIds,
+    tenants: Arc<Tenants>,
+    /// Hostname and labels of the node of the client.
+    node_attributes: Vec<(String, AttributeValue)>,
 }
 
 impl MetricConverter {
-    pub fn new(tx: MetricSender) -> Self {
+    pub fn new(tx: MetricSender, tenants: Arc<Tenants>) -> Self {
         let ids = MetricIds {
             id_client_to_server: nohash_hasher::IntMap::with_capacity_and_hasher(64, Default::default()),
             id_server_to_client: nohash_hasher::IntMap::with_capacity_and_hasher(64, Default::default()),
         };
-        Self { inner: tx, ids }
+        Self {
+            inner: tx,
+            ids,
+            tenants,
+            node_attributes: Vec::new(),
+        }
+    }
+
+    /// Sets the metadata of the node of the client, which is sent by the client when it connects.
+    ///
+    /// The labels are sorted by key, so that the order of the attributes does not depend on the transport.
+    pub fn set_node_metadata(&mut self, hostname: String, labels: impl IntoIterator<Item = (String, String)>) {
+        let mut labels: Vec<_> = labels.into_iter().collect();
+        labels.sort_unstable();
+        let hostname = (!hostname.is_empty()).then(|| (HOSTNAME_ATTRIBUTE.to_owned(), hostname));
+        self.node_attributes = labels
+            .into_iter()
+            .chain(hostname)
+            .map(|(key, value)| (key, AttributeValue::String(value)))
+            .collect();
     }
 
     /// Registers new client metrics.
+    ///
+    /// If the client belongs to a tenant, the prefix of the tenant is added to the names of the metrics.
     pub async fn register_from_client(
         &mut self,
         client: &str,
         metric_ids: Vec<u64>,
-        metric_defs: Vec<Metric>,
+        mut metric_defs: Vec<Metric>,
     ) -> anyhow::Result<()> {
+        if let Some(tenant) = self.tenants.of_client(client)? {
+            for metric in &mut metric_defs {
+                metric.name.insert_str(0, &tenant.metric_prefix);
+            }
+        }
         let results = self
             .inner
             .create_metrics(
@@ -76,7 +119,13 @@ impl MetricConverter {
     }
 
     /// Converts the metric ids of all the points in the buffer, and adds the client name as an attribute.
+    ///
+    /// If the client is an intermediate collector, the points already have a client name: it is kept,
+    /// and the name of the intermediate collector is added to the path of the point instead.
+    /// The same goes for the tenant and the metadata of the node: the ones set by a lower-level collector,
+    /// or by the client itself, take precedence.
     pub fn convert_all(&self, client: &str, buffer: &mut MeasurementBuffer) -> anyhow::Result<()> {
+        let tenant = self.tenants.of_client(client)?;
         for m in buffer.iter_mut() {
             // convert id
             let converted_id = self
@@ -85,8 +134,83 @@ impl MetricConverter {
             m.metric = RawMetricId::from_u64(converted_id);
 
             // add attribute
-            m.add_attr("relay_client", client.to_owned());
+            if m.attributes_keys().any(|k| k == CLIENT_ATTRIBUTE) {
+                *m = forwarded_point(m, client);
+            } else {
+                m.add_attr(CLIENT_ATTRIBUTE, client.to_owned());
+            }
+            if let Some(tenant) = tenant
+                && !m.attributes_keys().any(|k| k == TENANT_ATTRIBUTE)
+            {
+                m.add_attr(TENANT_ATTRIBUTE, tenant.name.clone());
+            }
+            for (key, value) in &self.node_attributes {
+                if !m.attributes_keys().any(|k| k == key) {
+                    m.add_attr(key.clone(), value.clone());
+                }
+            }
         }
         Ok(())
     }
 }
+
+/// Returns a copy of a point forwarded by the intermediate collector `via`, with an updated path.
+fn forwarded_point(point: &MeasurementPoint, via: &str) -> MeasurementPoint {
+    let mut path = None;
+    let mut attributes = Vec::with_capacity(point.attributes_len() + 1);
+    for (key, value) in point.attributes() {
+        match (key, value) {
+            (PATH_ATTRIBUTE, AttributeValue::Str(p)) => path = Some(format!("{p}/{via}")),
+            (PATH_ATTRIBUTE, AttributeValue::String(p)) => path = Some(format!("{p}/{via}")),
+            _ => attributes.push((key.to_owned(), value.clone())),
+        }
+    }
+    attributes.push((
+        PATH_ATTRIBUTE.to_owned(),
+        AttributeValue::String(path.unwrap_or_else(|| via.to_owned())),
+    ));
+    MeasurementPoint::new_untyped(
+        point.timestamp,
+        point.metric,
+        point.resource.clone(),
+        point.consumer.clone(),
+        point.value.clone(),
+    )
+    .with_attr_vec(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use alumet::{
+        measurement::{AttributeValue, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+        metrics::RawMetricId,
+        resources::{Resource, ResourceConsumer},
+    };
+
+    use super::forwarded_point;
+
+    #[test]
+    fn forwarded_path() {
+        let point = MeasurementPoint::new_untyped(
+            Timestamp::now(),
+            RawMetricId::from_u64(0),
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            WrappedMeasurementValue::U64(1),
+        )
+        .with_attr("relay_client", String::from("node-1"))
+        .with_attr("domain", String::from("package"));
+
+        let point = forwarded_point(&point, "rack-1");
+        let point = forwarded_point(&point, "site-1");
+        let attributes: Vec<_> = point.attributes().collect();
+        assert_eq!(
+            attributes,
+            vec![
+                ("relay_client", &AttributeValue::String(String::from("node-1"))),
+                ("domain", &AttributeValue::String(String::from("package"))),
+                ("relay_path", &AttributeValue::String(String::from("rack-1/site-1"))),
+            ]
+        );
+    }
+}
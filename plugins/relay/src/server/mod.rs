@@ -1,5 +1,8 @@
+mod auth;
+mod grpc;
 mod metrics;
 mod plugin;
 mod source;
+mod tenant;
 
 pub use plugin::RelayServerPlugin;
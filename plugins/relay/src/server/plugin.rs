@@ -1,14 +1,30 @@
-use std::net::ToSocketAddrs;
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, ToSocketAddrs},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use alumet::plugin::{
     AlumetPluginStart, ConfigTable,
     rust::{AlumetPlugin, deserialize_config, serialize_config},
+    secret::Secret,
 };
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
 
-use crate::server::source;
+use crate::{
+    Transport,
+    compression::Compression,
+    server::{
+        auth::ClientTokens,
+        grpc::GrpcRelay,
+        source::{self, ConnectionSettings},
+        tenant::{Tenant, Tenants},
+    },
+    tls,
+};
 
 pub struct RelayServerPlugin {
     config: Config,
@@ -23,16 +39,88 @@ struct Config {
     /// For information, ip6-localhost is `::1`.
     /// To listen to all your network interfaces please use `0.0.0.0` or `::`.
     address: String,
+
+    /// How to communicate with the clients: "tcp" or "grpc".
+    /// The clients must use the same transport.
+    #[serde(default)]
+    transport: Transport,
+
+    /// Optional TLS encryption of the connections.
+    /// If it is not set, the measurements are sent in plain text.
+    tls: Option<TlsConfig>,
+
+    /// Optional authentication of the clients.
+    /// If it is not set, any client can connect.
+    auth: Option<AuthConfig>,
+
+    /// Compression algorithms that the clients are allowed to use.
+    /// Each client chooses its algorithm, and falls back to no compression if the server does not accept it.
+    /// The gRPC transport only supports zstd.
+    #[serde(default = "default_accepted_compression")]
+    accepted_compression: Vec<Compression>,
+
+    /// Optional partitioning of the clients into tenants, by tenant name.
+    /// If it is set, the clients that do not belong to any tenant are rejected, unless `default_tenant` is set.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    tenants: HashMap<String, TenantConfig>,
+
+    /// Tenant of the clients that are not listed in any tenant.
+    default_tenant: Option<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct TlsConfig {
+    /// Certificate chain of the server, in PEM format.
+    certificate: PathBuf,
+
+    /// Private key of the server, in PEM format.
+    private_key: PathBuf,
+
+    /// Certificate authorities that sign the certificates of the clients, in PEM format.
+    ///
+    /// If it is set, the clients must authenticate with a certificate signed by one of these authorities (mutual TLS).
+    /// Otherwise, any client can connect.
+    client_ca: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct AuthConfig {
+    /// Secret token of each client that is allowed to connect, by client name.
+    tokens: HashMap<String, Secret>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct TenantConfig {
+    /// Names of the clients that belong to the tenant.
+    #[serde(default)]
+    clients: Vec<String>,
+
+    /// Prefix added to the names of the metrics of the clients, for instance `"team_a."`.
+    #[serde(default)]
+    metric_prefix: String,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             address: String::from("[::]:50051"), // "any" on ipv6
+            transport: Transport::default(),
+            tls: None,
+            auth: None,
+            accepted_compression: default_accepted_compression(),
+            tenants: HashMap::new(),
+            default_tenant: None,
         }
     }
 }
 
+fn default_accepted_compression() -> Vec<Compression> {
+    vec![Compression::Lz4, Compression::Zstd]
+}
+
 impl AlumetPlugin for RelayServerPlugin {
     fn name() -> &'static str {
         "relay-server"
@@ -61,14 +149,72 @@ impl AlumetPlugin for RelayServerPlugin {
             .with_context(|| format!("invalid socket address: {addr}"))?
             .collect();
 
+        let tls_mode = match &self.config.tls {
+            Some(TlsConfig { client_ca: Some(_), .. }) => "with mutual TLS",
+            Some(_) => "with TLS",
+            None => "without TLS",
+        };
+
+        let auth = match self.config.auth.take() {
+            Some(auth) => Some(ClientTokens::new(auth.tokens).context("invalid auth config")?),
+            None => None,
+        };
+
+        let tenants = std::mem::take(&mut self.config.tenants)
+            .into_iter()
+            .map(|(name, tenant)| {
+                let metric_prefix = tenant.metric_prefix;
+                (Tenant { name, metric_prefix }, tenant.clients)
+            })
+            .collect();
+        let tenants = Tenants::new(tenants, self.config.default_tenant.as_deref()).context("invalid tenants config")?;
+        let tenants = Arc::new(tenants);
+
+        match self.config.transport {
+            Transport::Tcp => self.start_tcp(alumet, addr, tls_mode, auth, tenants),
+            Transport::Grpc => self.start_grpc(alumet, addr, tls_mode, auth, tenants),
+        }
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        // The autonomous source has already been stopped at this point.
+        Ok(())
+    }
+}
+
+impl RelayServerPlugin {
+    fn start_tcp(
+        &mut self,
+        alumet: &mut AlumetPluginStart,
+        addr: Vec<SocketAddr>,
+        tls_mode: &'static str,
+        auth: Option<ClientTokens>,
+        tenants: Arc<Tenants>,
+    ) -> anyhow::Result<()> {
+        // Load the certificates right now, too.
+        let tls = match &self.config.tls {
+            Some(tls) => Some(
+                tls::server_acceptor(&tls.certificate, &tls.private_key, tls.client_ca.as_deref())
+                    .context("invalid TLS config")?,
+            ),
+            None => None,
+        };
+
+        let settings = ConnectionSettings {
+            tls,
+            auth,
+            tenants,
+            accepted_compression: std::mem::take(&mut self.config.accepted_compression),
+        };
+
         // Register the source builder.
         alumet.add_autonomous_source_builder("tcp_server", move |ctx, cancel_token, out_tx| {
-            log::info!("Starting relay server on: {addr:?}");
+            log::info!("Starting relay server on: {addr:?} ({tls_mode})");
             let metrics_tx = ctx.metrics_sender();
             let source = Box::pin(async move {
                 // `bind` loops through all the addresses that correspond to the string
                 let listener = TcpListener::bind(addr.as_slice()).await.context("tcp binding failed")?;
-                let server = source::TcpServer::new(cancel_token, listener, out_tx, metrics_tx);
+                let server = source::TcpServer::new(cancel_token, listener, settings, out_tx, metrics_tx);
                 server.accept_loop().await
             });
             Ok(source)
@@ -76,8 +222,47 @@ impl AlumetPlugin for RelayServerPlugin {
         Ok(())
     }
 
-    fn stop(&mut self) -> anyhow::Result<()> {
-        // The autonomous source has already been stopped at this point.
+    fn start_grpc(
+        &mut self,
+        alumet: &mut AlumetPluginStart,
+        addr: Vec<SocketAddr>,
+        tls_mode: &'static str,
+        auth: Option<ClientTokens>,
+        tenants: Arc<Tenants>,
+    ) -> anyhow::Result<()> {
+        // Load the certificates right now, too.
+        let tls = match &self.config.tls {
+            Some(tls) => Some(
+                tls::grpc_server_config(&tls.certificate, &tls.private_key, tls.client_ca.as_deref())
+                    .context("invalid TLS config")?,
+            ),
+            None => None,
+        };
+        let accept_zstd = self.config.accepted_compression.contains(&Compression::Zstd);
+
+        // Register the source builder.
+        alumet.add_autonomous_source_builder("grpc_server", move |ctx, cancel_token, out_tx| {
+            log::info!("Starting gRPC relay server on: {addr:?} ({tls_mode})");
+            let metrics_tx = ctx.metrics_sender();
+            let source = Box::pin(async move {
+                // `bind` loops through all the addresses that correspond to the string
+                let listener = TcpListener::bind(addr.as_slice()).await.context("tcp binding failed")?;
+                let mut server = tonic::transport::Server::builder();
+                if let Some(tls) = tls {
+                    server = server.tls_config(tls).context("invalid TLS config")?;
+                }
+                let relay = GrpcRelay::new(cancel_token.clone(), auth, tenants, out_tx, metrics_tx);
+                server
+                    .add_service(relay.into_server(accept_zstd))
+                    .serve_with_incoming_shutdown(
+                        tonic::transport::server::TcpIncoming::from(listener),
+                        cancel_token.cancelled_owned(),
+                    )
+                    .await
+                    .context("gRPC server error")
+            });
+            Ok(source)
+        })?;
         Ok(())
     }
 }
@@ -1,30 +1,55 @@
-use std::{future::Future, net::SocketAddr};
+use std::{future::Future, net::SocketAddr, sync::Arc, time::Duration};
 
 use alumet::{measurement::MeasurementBuffer, metrics::Metric, metrics::online::MetricSender};
+use anyhow::anyhow;
 use tokio::{
     net::{TcpListener, TcpStream},
     sync::mpsc,
 };
+use tokio_rustls::TlsAcceptor;
 use tokio_util::sync::CancellationToken;
 
-use crate::protocol::{self, GreetResponse, MessageBody, MessageEnum, MessageStream, PROTOCOL_VERSION};
+use crate::{
+    compression::Compression,
+    protocol::{self, GreetResponse, MessageBody, MessageEnum, MessageStream, PROTOCOL_VERSION},
+    tls::RelayStream,
+};
+
+use super::{auth::ClientTokens, metrics::MetricConverter, tenant::Tenants};
 
-use super::metrics::MetricConverter;
+/// Maximum amount of time that a client can take to complete the TLS handshake.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct TcpSource {
     cancel_token: CancellationToken,
-    tcp: MessageStream<TcpStream>,
+    tcp: MessageStream<RelayStream>,
     out_tx: mpsc::Sender<MeasurementBuffer>,
     metrics: MetricConverter,
+    settings: Arc<ConnectionSettings>,
+    /// Name of the client, once it has authenticated.
+    identity: Option<String>,
 }
 
 pub struct TcpServer {
     cancel_token: CancellationToken,
     listener: TcpListener,
+    settings: Arc<ConnectionSettings>,
     measurement_tx: mpsc::Sender<MeasurementBuffer>,
     metrics_tx: MetricSender,
 }
 
+/// Settings of the connections between the server and its clients.
+pub struct ConnectionSettings {
+    /// TLS acceptor, if the connections must be encrypted.
+    pub tls: Option<TlsAcceptor>,
+    /// Tokens of the clients, if they must authenticate.
+    pub auth: Option<ClientTokens>,
+    /// Tenants of the clients.
+    pub tenants: Arc<Tenants>,
+    /// Compression algorithms that the clients can use.
+    pub accepted_compression: Vec<Compression>,
+}
+
 impl TcpSource {
     async fn process_message(&mut self, msg: MessageBody<'_>) -> anyhow::Result<()> {
         let remote_name = msg.sender;
@@ -52,22 +77,35 @@ impl TcpSource {
                     );
                     return Ok(());
                 }
-                self.tcp
-                    .write_message(&MessageBody {
-                        sender: String::from(""),
-                        content: MessageEnum::GreetResponse(GreetResponse {
-                            accept,
-                            server_alumet_core_version: alumet::VERSION.to_string(),
-                            server_relay_plugin_version: crate::PLUGIN_VERSION.to_string(),
-                            protocol_version: PROTOCOL_VERSION,
-                        }),
-                    })
-                    .await?;
-                if !accept {
+                if let Some(auth) = &self.settings.auth {
+                    if !auth.verify(&remote_name, greet.token.as_deref()) {
+                        log::warn!("Client {remote_name} ({remote_addr}) failed to authenticate. Rejecting.");
+                        let rejection = Some(String::from("authentication failed"));
+                        self.respond_to_greet(rejection, Compression::None).await?;
+                        self.tcp.shutdown().await?;
+                        return Err(anyhow!("client {remote_name} ({remote_addr}) failed to authenticate"));
+                    }
+                    log::info!("Client {remote_name} ({remote_addr}) is authenticated.");
+                    self.identity = Some(remote_name.clone());
+                }
+                if let Err(e) = self.settings.tenants.of_client(&remote_name) {
+                    log::warn!("Client {remote_name} ({remote_addr}) does not belong to any tenant. Rejecting.");
+                    self.respond_to_greet(Some(e.to_string()), Compression::None).await?;
                     self.tcp.shutdown().await?;
+                    return Err(e);
                 }
+                self.metrics.set_node_metadata(greet.hostname, greet.labels);
+                // Choose the preferred compression algorithm of the client, among the ones that we accept.
+                let compression = greet
+                    .compression
+                    .into_iter()
+                    .find(|c| self.settings.accepted_compression.contains(c))
+                    .unwrap_or(Compression::None);
+                log::debug!("Client {remote_name} ({remote_addr}) will use compression {compression:?}");
+                self.respond_to_greet(None, compression).await?;
             }
             MessageEnum::RegisterMetrics(register_metrics) => {
+                let remote_name = self.check_sender(remote_name)?;
                 let mut metric_ids = Vec::with_capacity(register_metrics.metrics.len());
                 let mut metric_defs = Vec::with_capacity(register_metrics.metrics.len());
                 for protocol_metric in register_metrics.metrics {
@@ -85,17 +123,67 @@ impl TcpSource {
                     .await?;
             }
             MessageEnum::SendMeasurements(send_measurements) => {
-                let mut alumet_measurements = send_measurements.buf.owned();
-                // convert the metrics
-                self.metrics.convert_all(&remote_name, &mut alumet_measurements)?;
-                // send them
-                self.out_tx.send(alumet_measurements).await?;
+                let remote_name = self.check_sender(remote_name)?;
+                let alumet_measurements = send_measurements.buf.owned();
+                self.forward_measurements(&remote_name, alumet_measurements).await?;
+            }
+            MessageEnum::SendCompressedMeasurements(send_measurements) => {
+                let remote_name = self.check_sender(remote_name)?;
+                let alumet_measurements = send_measurements.decompress()?;
+                self.forward_measurements(&remote_name, alumet_measurements).await?;
             }
             _ => unreachable!(),
         }
         Ok(())
     }
 
+    async fn forward_measurements(
+        &mut self,
+        remote_name: &str,
+        mut measurements: MeasurementBuffer,
+    ) -> anyhow::Result<()> {
+        // convert the metrics
+        self.metrics.convert_all(remote_name, &mut measurements)?;
+        // send them
+        self.out_tx.send(measurements).await?;
+        Ok(())
+    }
+
+    /// Accepts the client, or rejects it for the given reason.
+    async fn respond_to_greet(
+        &mut self,
+        rejection: Option<String>,
+        compression: Compression,
+    ) -> Result<(), protocol::Error> {
+        self.tcp
+            .write_message(&MessageBody {
+                sender: String::from(""),
+                content: MessageEnum::GreetResponse(GreetResponse {
+                    accept: rejection.is_none(),
+                    server_alumet_core_version: alumet::VERSION.to_string(),
+                    server_relay_plugin_version: crate::PLUGIN_VERSION.to_string(),
+                    protocol_version: PROTOCOL_VERSION,
+                    reason: rejection,
+                    compression,
+                }),
+            })
+            .await
+    }
+
+    /// If the clients must authenticate, checks that the sender of a message is the authenticated client.
+    fn check_sender(&self, sender: String) -> anyhow::Result<String> {
+        match (&self.settings.auth, &self.identity) {
+            (None, _) => Ok(sender),
+            (Some(_), Some(identity)) if *identity == sender => Ok(sender),
+            (Some(_), Some(identity)) => Err(anyhow!(
+                "client {identity} attempted to send a message on behalf of client {sender}"
+            )),
+            (Some(_), None) => Err(anyhow!(
+                "client {sender} attempted to send a message before authenticating"
+            )),
+        }
+    }
+
     pub fn receive_loop(mut self) -> impl Future<Output = anyhow::Result<()>> + Send {
         fn is_fatal_error(err: &protocol::Error) -> bool {
             match err {
@@ -111,6 +199,7 @@ impl TcpSource {
                 protocol::Error::Disconnected => false,
                 protocol::Error::VersionMismatch { .. } => true,
                 protocol::Error::Unexpected => true,
+                protocol::Error::Rejected(_) => true,
             }
         }
 
@@ -152,12 +241,14 @@ impl TcpServer {
     pub fn new(
         cancel_token: CancellationToken,
         listener: TcpListener,
+        settings: ConnectionSettings,
         measurement_tx: mpsc::Sender<MeasurementBuffer>,
         metrics_tx: MetricSender,
     ) -> Self {
         Self {
             cancel_token,
             listener,
+            settings: Arc::new(settings),
             measurement_tx,
             metrics_tx,
         }
@@ -165,13 +256,36 @@ impl TcpServer {
 
     fn start_receiving(&mut self, tcp_stream: TcpStream, remote_addr: SocketAddr) {
         log::info!("New incoming connection from {remote_addr}");
-        let source = TcpSource {
-            cancel_token: self.cancel_token.child_token(),
-            tcp: MessageStream::new(tcp_stream),
-            out_tx: self.measurement_tx.clone(),
-            metrics: MetricConverter::new(self.metrics_tx.clone()),
-        };
+        let cancel_token = self.cancel_token.child_token();
+        let out_tx = self.measurement_tx.clone();
+        let metrics = MetricConverter::new(self.metrics_tx.clone(), self.settings.tenants.clone());
+        let settings = self.settings.clone();
         tokio::spawn(async move {
+            // The TLS handshake is done here, in order not to block the accept loop.
+            let stream = match &settings.tls {
+                Some(acceptor) => {
+                    match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(tcp_stream)).await {
+                        Ok(Ok(tls_stream)) => RelayStream::Tls(Box::new(tls_stream.into())),
+                        Ok(Err(e)) => {
+                            log::error!("TLS handshake with client {remote_addr} failed: {e}");
+                            return;
+                        }
+                        Err(_) => {
+                            log::error!("TLS handshake with client {remote_addr} timed out");
+                            return;
+                        }
+                    }
+                }
+                None => RelayStream::Plain(tcp_stream),
+            };
+            let source = TcpSource {
+                cancel_token,
+                tcp: MessageStream::new(stream),
+                out_tx,
+                metrics,
+                settings,
+                identity: None,
+            };
             if let Err(e) = source.receive_loop().await {
                 log::error!("Error in relay source connected to client {remote_addr}: {e:?}");
             }
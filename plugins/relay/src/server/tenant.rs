@@ -0,0 +1,108 @@
+//! Partitioning of the relay clients into tenants.
+
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+
+/// Attribute that contains the name of the tenant of the client that produced the measurement.
+pub const TENANT_ATTRIBUTE: &str = "tenant";
+
+/// A group of clients, for instance the nodes of a team or of an experiment.
+pub struct Tenant {
+    pub name: String,
+    /// Prefix added to the names of the metrics of the clients, so that the tenants do not share metrics.
+    pub metric_prefix: String,
+}
+
+/// The tenants of the server, by client name.
+#[derive(Default)]
+pub struct Tenants {
+    by_client: HashMap<String, usize>,
+    tenants: Vec<Tenant>,
+    /// Index of the tenant of the clients that are not explicitly assigned to a tenant.
+    default: Option<usize>,
+}
+
+impl Tenants {
+    /// Creates the tenants from their clients.
+    ///
+    /// If `default` is set, the clients that are not assigned to any tenant belong to this tenant.
+    /// Otherwise, they are rejected.
+    pub fn new(tenants: Vec<(Tenant, Vec<String>)>, default: Option<&str>) -> anyhow::Result<Self> {
+        let mut res = Tenants::default();
+        for (i, (tenant, clients)) in tenants.into_iter().enumerate() {
+            for client in clients {
+                if let Some(other) = res.by_client.insert(client.clone(), i) {
+                    return Err(anyhow!(
+                        "client {client} cannot belong to both tenants {} and {}",
+                        res.tenants[other].name,
+                        tenant.name
+                    ));
+                }
+            }
+            res.tenants.push(tenant);
+        }
+        if let Some(default) = default {
+            let i = res
+                .tenants
+                .iter()
+                .position(|t| t.name == default)
+                .ok_or_else(|| anyhow!("unknown default tenant {default}"))?;
+            res.default = Some(i);
+        }
+        Ok(res)
+    }
+
+    /// Returns the tenant of a client.
+    ///
+    /// Returns `Ok(None)` if there is no tenant, and an error if the client does not belong to any tenant.
+    pub fn of_client(&self, client: &str) -> anyhow::Result<Option<&Tenant>> {
+        if self.tenants.is_empty() {
+            return Ok(None);
+        }
+        match self.by_client.get(client).copied().or(self.default) {
+            Some(i) => Ok(Some(&self.tenants[i])),
+            None => Err(anyhow!("client {client} does not belong to any tenant")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Tenant, Tenants};
+
+    fn tenant(name: &str, clients: &[&str]) -> (Tenant, Vec<String>) {
+        let tenant = Tenant {
+            name: name.to_owned(),
+            metric_prefix: format!("{name}."),
+        };
+        (tenant, clients.iter().map(|c| c.to_string()).collect())
+    }
+
+    #[test]
+    fn no_tenant() {
+        let tenants = Tenants::new(vec![], None).unwrap();
+        assert!(tenants.of_client("node-1").unwrap().is_none());
+    }
+
+    #[test]
+    fn clients_of_tenants() {
+        let tenants = Tenants::new(vec![tenant("a", &["node-1", "node-2"]), tenant("b", &["node-3"])], None).unwrap();
+        assert_eq!(tenants.of_client("node-1").unwrap().unwrap().name, "a");
+        assert_eq!(tenants.of_client("node-2").unwrap().unwrap().name, "a");
+        assert_eq!(tenants.of_client("node-3").unwrap().unwrap().name, "b");
+        assert!(tenants.of_client("node-4").is_err());
+
+        let tenants = Tenants::new(vec![tenant("a", &["node-1"]), tenant("b", &[])], Some("b")).unwrap();
+        assert_eq!(tenants.of_client("node-1").unwrap().unwrap().name, "a");
+        assert_eq!(tenants.of_client("node-4").unwrap().unwrap().name, "b");
+    }
+
+    #[test]
+    fn invalid_tenants() {
+        // a client in two tenants
+        assert!(Tenants::new(vec![tenant("a", &["node-1"]), tenant("b", &["node-1"])], None).is_err());
+        // unknown default tenant
+        assert!(Tenants::new(vec![tenant("a", &["node-1"])], Some("c")).is_err());
+    }
+}
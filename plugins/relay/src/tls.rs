@@ -0,0 +1,329 @@
+//! TLS layer of the relay: optional encryption and authentication of the connections.
+
+use std::{
+    io,
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+};
+
+use anyhow::Context;
+use rustls::{
+    RootCertStore,
+    crypto::CryptoProvider,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+};
+
+/// A connection between a relay client and a relay server, encrypted or not.
+pub enum RelayStream {
+    Plain(TcpStream),
+    Tls(Box<tokio_rustls::TlsStream<TcpStream>>),
+}
+
+impl RelayStream {
+    /// Returns the underlying TCP stream.
+    pub fn tcp(&self) -> &TcpStream {
+        match self {
+            RelayStream::Plain(tcp) => tcp,
+            RelayStream::Tls(tls) => tls.get_ref().0,
+        }
+    }
+}
+
+impl AsyncRead for RelayStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut TaskContext<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            RelayStream::Plain(tcp) => Pin::new(tcp).poll_read(cx, buf),
+            RelayStream::Tls(tls) => Pin::new(tls).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for RelayStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut TaskContext<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            RelayStream::Plain(tcp) => Pin::new(tcp).poll_write(cx, buf),
+            RelayStream::Tls(tls) => Pin::new(tls).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            RelayStream::Plain(tcp) => Pin::new(tcp).poll_flush(cx),
+            RelayStream::Tls(tls) => Pin::new(tls).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            RelayStream::Plain(tcp) => Pin::new(tcp).poll_shutdown(cx),
+            RelayStream::Tls(tls) => Pin::new(tls).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Creates the TLS acceptor of the relay server.
+///
+/// If `client_ca` is set, the clients must present a certificate signed by one of these authorities (mutual TLS).
+#[cfg(feature = "server")]
+pub fn server_acceptor(
+    certificate: &Path,
+    private_key: &Path,
+    client_ca: Option<&Path>,
+) -> anyhow::Result<tokio_rustls::TlsAcceptor> {
+    let provider = crypto_provider();
+    let builder = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .context("invalid TLS configuration")?;
+    let builder = match client_ca {
+        Some(ca) => {
+            let roots = load_roots(ca)?;
+            let verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .context("invalid client certificate verifier")?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder
+        .with_single_cert(load_certs(certificate)?, load_key(private_key)?)
+        .context("invalid server certificate or private key")?;
+    Ok(tokio_rustls::TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Creates the TLS connector of the relay client.
+///
+/// The server certificate must be signed by one of the authorities of `server_ca`.
+/// If `client_auth` is set, the client presents this certificate (mutual TLS).
+#[cfg(feature = "client")]
+pub fn client_connector(
+    server_ca: &Path,
+    client_auth: Option<(&Path, &Path)>,
+) -> anyhow::Result<tokio_rustls::TlsConnector> {
+    let builder = rustls::ClientConfig::builder_with_provider(crypto_provider())
+        .with_safe_default_protocol_versions()
+        .context("invalid TLS configuration")?
+        .with_root_certificates(load_roots(server_ca)?);
+    let config = match client_auth {
+        Some((certificate, private_key)) => builder
+            .with_client_auth_cert(load_certs(certificate)?, load_key(private_key)?)
+            .context("invalid client certificate or private key")?,
+        None => builder.with_no_client_auth(),
+    };
+    Ok(tokio_rustls::TlsConnector::from(Arc::new(config)))
+}
+
+/// Extracts the name to check against the server certificate from an address `host:port`.
+#[cfg(feature = "client")]
+pub fn server_name(address: &str) -> anyhow::Result<rustls::pki_types::ServerName<'static>> {
+    let host = match address.rsplit_once(':') {
+        Some((host, port)) if port.parse::<u16>().is_ok() => host,
+        _ => address,
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    rustls::pki_types::ServerName::try_from(host.to_owned()).with_context(|| format!("invalid server name: {host}"))
+}
+
+/// Creates the TLS configuration of the gRPC relay server.
+///
+/// It is equivalent to [`server_acceptor`], for the gRPC transport.
+#[cfg(feature = "server")]
+pub fn grpc_server_config(
+    certificate: &Path,
+    private_key: &Path,
+    client_ca: Option<&Path>,
+) -> anyhow::Result<tonic::transport::ServerTlsConfig> {
+    // check the files now, in order to report errors early
+    server_acceptor(certificate, private_key, client_ca)?;
+    let identity = tonic::transport::Identity::from_pem(read_pem(certificate)?, read_pem(private_key)?);
+    let mut config = tonic::transport::ServerTlsConfig::new().identity(identity);
+    if let Some(ca) = client_ca {
+        config = config.client_ca_root(tonic::transport::Certificate::from_pem(read_pem(ca)?));
+    }
+    Ok(config)
+}
+
+/// Creates the TLS configuration of the gRPC relay client.
+///
+/// It is equivalent to [`client_connector`], for the gRPC transport.
+#[cfg(feature = "client")]
+pub fn grpc_client_config(
+    server_ca: &Path,
+    server_name: &str,
+    client_auth: Option<(&Path, &Path)>,
+) -> anyhow::Result<tonic::transport::ClientTlsConfig> {
+    // check the files now, in order to report errors early
+    client_connector(server_ca, client_auth)?;
+    let mut config = tonic::transport::ClientTlsConfig::new()
+        .ca_certificate(tonic::transport::Certificate::from_pem(read_pem(server_ca)?))
+        .domain_name(server_name);
+    if let Some((certificate, private_key)) = client_auth {
+        let identity = tonic::transport::Identity::from_pem(read_pem(certificate)?, read_pem(private_key)?);
+        config = config.identity(identity);
+    }
+    Ok(config)
+}
+
+fn read_pem(path: &Path) -> anyhow::Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("failed to read {path:?}"))
+}
+
+fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn load_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|iter| iter.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("failed to read certificates from {path:?}"))?;
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!("no certificate found in {path:?}"));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> anyhow::Result<PrivateKeyDer<'static>> {
+    PrivateKeyDer::from_pem_file(path).with_context(|| format!("failed to read private key from {path:?}"))
+}
+
+fn load_roots(path: &Path) -> anyhow::Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots
+            .add(cert)
+            .with_context(|| format!("invalid certificate authority in {path:?}"))?;
+    }
+    Ok(roots)
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::{RelayStream, client_connector, server_acceptor, server_name};
+    use crate::protocol::{self, MessageBody, MessageEnum, MessageStream};
+
+    struct Pki {
+        dir: PathBuf,
+    }
+
+    impl Pki {
+        /// Generates a CA, a certificate for `localhost` and a client certificate, all signed by the CA.
+        fn generate(dir: &Path) -> Pki {
+            let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = CertifiedIssuer::self_signed(ca_params, KeyPair::generate().unwrap()).unwrap();
+            std::fs::write(dir.join("ca.crt"), ca.pem()).unwrap();
+
+            for name in ["server", "client"] {
+                let key = KeyPair::generate().unwrap();
+                let params = CertificateParams::new(vec![String::from("localhost")]).unwrap();
+                let cert = params.signed_by(&key, &ca).unwrap();
+                std::fs::write(dir.join(format!("{name}.crt")), cert.pem()).unwrap();
+                std::fs::write(dir.join(format!("{name}.key")), key.serialize_pem()).unwrap();
+            }
+            Pki { dir: dir.to_owned() }
+        }
+
+        fn path(&self, file: &str) -> PathBuf {
+            self.dir.join(file)
+        }
+    }
+
+    fn message(sender: &str) -> MessageBody<'static> {
+        MessageBody {
+            sender: sender.to_owned(),
+            content: MessageEnum::Greet(protocol::Greet {
+                alumet_core_version: String::from("test"),
+                relay_plugin_version: String::from("test"),
+                protocol_version: protocol::PROTOCOL_VERSION,
+                token: None,
+                compression: vec![],
+                hostname: String::new(),
+                labels: vec![],
+            }),
+        }
+    }
+
+    /// Accepts one connection, reads one message and returns its sender.
+    async fn serve_once(listener: TcpListener, acceptor: tokio_rustls::TlsAcceptor) -> anyhow::Result<String> {
+        let (tcp, _) = listener.accept().await?;
+        let tls = acceptor.accept(tcp).await?;
+        let mut stream = MessageStream::new(RelayStream::Tls(Box::new(tls.into())));
+        Ok(stream.read_message().await?.sender)
+    }
+
+    #[test]
+    fn test_server_name() {
+        assert_eq!(server_name("localhost:50051").unwrap().to_str(), "localhost");
+        assert_eq!(server_name("127.0.0.1:50051").unwrap().to_str(), "127.0.0.1");
+        assert_eq!(server_name("[::1]:50051").unwrap().to_str(), "::1");
+        assert_eq!(
+            server_name("collector.example.org").unwrap().to_str(),
+            "collector.example.org"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let pki = Pki::generate(tmp.path());
+        let acceptor = server_acceptor(
+            &pki.path("server.crt"),
+            &pki.path("server.key"),
+            Some(&pki.path("ca.crt")),
+        )?;
+        let connector = client_connector(
+            &pki.path("ca.crt"),
+            Some((&pki.path("client.crt"), &pki.path("client.key"))),
+        )?;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(serve_once(listener, acceptor));
+
+        let tcp = TcpStream::connect(addr).await?;
+        let tls = connector.connect(server_name("localhost:0")?, tcp).await?;
+        let mut stream = MessageStream::new(RelayStream::Tls(Box::new(tls.into())));
+        stream.write_message(&message("node-1")).await?;
+
+        assert_eq!(server.await??, "node-1");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls_rejects_anonymous_client() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let pki = Pki::generate(tmp.path());
+        let acceptor = server_acceptor(
+            &pki.path("server.crt"),
+            &pki.path("server.key"),
+            Some(&pki.path("ca.crt")),
+        )?;
+        let connector = client_connector(&pki.path("ca.crt"), None)?;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(serve_once(listener, acceptor));
+
+        let tcp = TcpStream::connect(addr).await?;
+        // With TLS 1.3, the client learns that it has been rejected after the handshake.
+        if let Ok(tls) = connector.connect(server_name("localhost:0")?, tcp).await {
+            let mut stream = MessageStream::new(RelayStream::Tls(Box::new(tls.into())));
+            let _ = stream.write_message(&message("intruder")).await;
+        }
+        assert!(
+            server.await?.is_err(),
+            "the server should reject a client without certificate"
+        );
+        Ok(())
+    }
+}
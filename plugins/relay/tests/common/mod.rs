@@ -0,0 +1,189 @@
+//! Utilities for running relay clients and servers in the tests.
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, LazyLock, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use alumet::{
+    agent::{
+        self, RunningAgent,
+        plugin::{PluginInfo, PluginSet},
+    },
+    measurement::{MeasurementAccumulator, MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+    metrics::TypedMetricId,
+    pipeline::{
+        self, Output, Source,
+        elements::{
+            error::{PollError, WriteError},
+            output::{OutputContext, builder::OutputBuilder},
+            source::trigger::TriggerSpec,
+        },
+        naming::PluginName,
+    },
+    plugin::{AlumetPluginStart, ConfigTable, PluginMetadata, rust::AlumetPlugin},
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Points received by a [`CollectingOutput`].
+pub type Received = Arc<Mutex<Vec<MeasurementPoint>>>;
+
+/// Returns a local address with a free port.
+pub fn free_address() -> String {
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    format!("127.0.0.1:{port}")
+}
+
+/// Starts an agent with the given plugins and an output that collects the measurements.
+pub fn start_collecting_agent(plugins: PluginSet) -> (RunningAgent, Received) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let output = CollectingOutput(received.clone());
+    let mut pipeline = pipeline::Builder::new();
+    pipeline
+        .add_output_builder(
+            PluginName(String::from("test")),
+            "collect",
+            OutputBuilder::Blocking(Box::new(move |_| Ok(Box::new(output)))),
+        )
+        .unwrap();
+
+    let agent = agent::Builder::from_pipeline(plugins, pipeline)
+        .build_and_start()
+        .unwrap();
+    (agent, received)
+}
+
+/// Stops an agent and waits for it to finish.
+pub fn stop(agent: RunningAgent) {
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+pub fn plugin_info<P: AlumetPlugin + 'static>(config: &str) -> PluginInfo {
+    PluginInfo {
+        metadata: PluginMetadata::from_static::<P>(),
+        enabled: true,
+        config: Some(toml::from_str(config).unwrap()),
+    }
+}
+
+/// Waits until the received points satisfy the condition, and returns a copy of them.
+pub fn wait_for(received: &Received, condition: impl Fn(&[MeasurementPoint]) -> bool) -> Vec<MeasurementPoint> {
+    let start = Instant::now();
+    loop {
+        let points = received.lock().unwrap().clone();
+        if condition(&points) {
+            return points;
+        }
+        assert!(
+            start.elapsed() < TIMEOUT,
+            "timeout: received only {} points",
+            points.len()
+        );
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Returns the values produced by a [`CounterPlugin`].
+pub fn values(points: &[MeasurementPoint]) -> Vec<u64> {
+    points
+        .iter()
+        .filter_map(|m| match m.value {
+            WrappedMeasurementValue::U64(v) => Some(v),
+            WrappedMeasurementValue::F64(_) => None,
+        })
+        .collect()
+}
+
+/// Returns the counter with the given name.
+///
+/// The tests of a file run in parallel, each test should use its own counter.
+pub fn counter(name: &str) -> &'static AtomicU64 {
+    static COUNTERS: LazyLock<Mutex<HashMap<String, &'static AtomicU64>>> = LazyLock::new(Default::default);
+    COUNTERS
+        .lock()
+        .unwrap()
+        .entry(name.to_owned())
+        .or_insert_with(|| Box::leak(Box::new(AtomicU64::new(0))))
+}
+
+/// Produces the values 1, 2, 3... of the counter named in its config.
+pub struct CounterPlugin {
+    counter: &'static AtomicU64,
+}
+
+struct CounterSource {
+    metric: TypedMetricId<u64>,
+    counter: &'static AtomicU64,
+}
+
+struct CollectingOutput(Received);
+
+impl AlumetPlugin for CounterPlugin {
+    fn name() -> &'static str {
+        "counter"
+    }
+
+    fn version() -> &'static str {
+        "0.1.0"
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        Ok(None)
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let name = config.0.get("counter").and_then(|t| t.as_str()).unwrap();
+        Ok(Box::new(CounterPlugin { counter: counter(name) }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        let metric = alumet.create_metric::<u64>("counter", Unit::Unity, "increasing counter")?;
+        alumet.add_source(
+            "counter",
+            Box::new(CounterSource {
+                metric,
+                counter: self.counter,
+            }),
+            TriggerSpec::at_interval(Duration::from_millis(10)),
+        )?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl Source for CounterSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let value = self.counter.fetch_add(1, Ordering::Relaxed) + 1;
+        measurements.push(MeasurementPoint::new(
+            timestamp,
+            self.metric,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            value,
+        ));
+        Ok(())
+    }
+}
+
+impl Output for CollectingOutput {
+    fn write(&mut self, measurements: &MeasurementBuffer, _ctx: &OutputContext) -> Result<(), WriteError> {
+        self.0.lock().unwrap().extend(measurements.iter().cloned());
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+//! Checks that a relay server can forward its measurements to an upper-level relay server.
+mod common;
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    measurement::AttributeValue,
+};
+use plugin_relay::{client::RelayClientPlugin, server::RelayServerPlugin};
+
+use common::{CounterPlugin, plugin_info, start_collecting_agent, stop, wait_for};
+
+const CLIENT_CONFIG: &str = "
+    buffer_max_length = 0
+    buffer_timeout = '0s'
+    [retry]
+    max_times = 3
+    initial_delay = '50ms'
+    max_delay = '100ms'
+";
+
+#[test]
+fn node_to_rack_to_site() {
+    let _ = env_logger::Builder::from_default_env().try_init();
+    let site_address = common::free_address();
+    let rack_address = common::free_address();
+
+    // site collector
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(plugin_info::<RelayServerPlugin>(&format!("address = '{site_address}'")));
+    let (site, received) = start_collecting_agent(plugins);
+
+    // rack collector: relay server and relay client in the same agent
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(plugin_info::<RelayServerPlugin>(&format!("address = '{rack_address}'")));
+    plugins.add_plugin(plugin_info::<RelayClientPlugin>(&format!(
+        "client_name = 'rack-1'\nrelay_server = '{site_address}'\n{CLIENT_CONFIG}\n[labels]\nrack = 'r1'"
+    )));
+    let rack = agent::Builder::new(plugins).build_and_start().unwrap();
+
+    // node
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(plugin_info::<RelayClientPlugin>(&format!(
+        "client_name = 'node-1'\nrelay_server = '{rack_address}'\n{CLIENT_CONFIG}\n[labels]\nrole = 'compute'"
+    )));
+    plugins.add_plugin(plugin_info::<CounterPlugin>("counter = 'hierarchy'"));
+    let node = agent::Builder::new(plugins).build_and_start().unwrap();
+
+    // the site collector should know where the measurements come from,
+    // and the metadata of the node should take precedence over the metadata of the rack collector
+    let hostname = hostname::get().unwrap().to_string_lossy().to_string();
+    let points = wait_for(&received, |points| points.len() >= 5);
+    for point in points {
+        let attributes: Vec<_> = point.attributes().collect();
+        assert_eq!(
+            attributes,
+            vec![
+                ("relay_client", &AttributeValue::String(String::from("node-1"))),
+                ("role", &AttributeValue::String(String::from("compute"))),
+                ("hostname", &AttributeValue::String(hostname.clone())),
+                ("relay_path", &AttributeValue::String(String::from("rack-1"))),
+                ("rack", &AttributeValue::String(String::from("r1"))),
+            ]
+        );
+    }
+
+    stop(node);
+    stop(rack);
+    stop(site);
+}
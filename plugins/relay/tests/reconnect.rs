@@ -0,0 +1,74 @@
+//! Checks that the relay client survives a restart of the relay server, with each transport.
+mod common;
+
+use std::{sync::atomic::Ordering, time::Duration};
+
+use alumet::agent::{self, RunningAgent, plugin::PluginSet};
+use plugin_relay::{client::RelayClientPlugin, server::RelayServerPlugin};
+
+use common::{CounterPlugin, Received, counter, plugin_info, start_collecting_agent, stop, values, wait_for};
+
+#[test]
+fn client_reconnects_after_server_restart() {
+    check_reconnection("tcp");
+}
+
+#[test]
+fn grpc_client_reconnects_after_server_restart() {
+    check_reconnection("grpc");
+}
+
+fn check_reconnection(transport: &str) {
+    let _ = env_logger::Builder::from_default_env().try_init();
+    let address = common::free_address();
+
+    // start the server, then the client
+    let (server, received) = start_server(&address, transport);
+    let client = start_client(&address, transport);
+    let before_restart = values(&wait_for(&received, |points| points.len() >= 5));
+
+    // stop the server and let the client produce measurements while the server is unreachable
+    stop(server);
+    let last_received = *before_restart.iter().max().unwrap();
+    std::thread::sleep(Duration::from_millis(500));
+
+    // restart the server: the client should reconnect and send the measurements produced in the meantime
+    let (server, received) = start_server(&address, transport);
+    let restart = counter(transport).load(Ordering::Relaxed);
+    let after_restart = values(&wait_for(&received, |points| {
+        values(points).iter().any(|v| *v > restart)
+    }));
+    assert!(
+        after_restart.iter().any(|v| *v > last_received + 1 && *v < restart),
+        "the measurements produced while the server was down should have been sent after the reconnection"
+    );
+
+    stop(client);
+    stop(server);
+}
+
+fn start_server(address: &str, transport: &str) -> (RunningAgent, Received) {
+    let config = format!("address = '{address}'\ntransport = '{transport}'");
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(plugin_info::<RelayServerPlugin>(&config));
+    start_collecting_agent(plugins)
+}
+
+fn start_client(address: &str, transport: &str) -> RunningAgent {
+    let config = format!(
+        "
+        relay_server = '{address}'
+        transport = '{transport}'
+        buffer_max_length = 0
+        buffer_timeout = '0s'
+        [retry]
+        max_times = 3
+        initial_delay = '50ms'
+        max_delay = '100ms'
+        "
+    );
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(plugin_info::<RelayClientPlugin>(&config));
+    plugins.add_plugin(plugin_info::<CounterPlugin>(&format!("counter = '{transport}'")));
+    agent::Builder::new(plugins).build_and_start().unwrap()
+}
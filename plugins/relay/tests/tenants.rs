@@ -0,0 +1,77 @@
+//! Checks that a relay server partitions its clients into tenants.
+mod common;
+
+use alumet::{
+    agent::{self, RunningAgent, plugin::PluginSet},
+    measurement::AttributeValue,
+};
+use plugin_relay::{client::RelayClientPlugin, server::RelayServerPlugin};
+
+use common::{CounterPlugin, plugin_info, start_collecting_agent, stop, wait_for};
+
+#[test]
+fn clients_of_tenants() {
+    let _ = env_logger::Builder::from_default_env().try_init();
+    let address = common::free_address();
+
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(plugin_info::<RelayServerPlugin>(&format!(
+        "
+        address = '{address}'
+        default_tenant = 'others'
+        [tenants.team-a]
+        clients = ['node-1']
+        metric_prefix = 'team_a.'
+        [tenants.others]
+        "
+    )));
+    let (server, received) = start_collecting_agent(plugins);
+    let node_1 = start_client(&address, "node-1");
+    let node_2 = start_client(&address, "node-2");
+
+    // each point should be tagged with the tenant of its client
+    let points = wait_for(&received, |points| {
+        let count = |tenant: &str| {
+            points
+                .iter()
+                .filter(|p| {
+                    p.attributes()
+                        .any(|(k, v)| k == "tenant" && v == &AttributeValue::String(tenant.to_owned()))
+                })
+                .count()
+        };
+        count("team-a") >= 5 && count("others") >= 5
+    });
+    for point in points {
+        let attributes: Vec<_> = point.attributes().collect();
+        let tenant = match &attributes[0] {
+            ("relay_client", AttributeValue::String(client)) if client == "node-1" => "team-a",
+            ("relay_client", _) => "others",
+            _ => panic!("missing relay_client attribute: {attributes:?}"),
+        };
+        assert_eq!(attributes[1], ("tenant", &AttributeValue::String(tenant.to_owned())));
+    }
+
+    stop(node_1);
+    stop(node_2);
+    stop(server);
+}
+
+fn start_client(address: &str, name: &str) -> RunningAgent {
+    let config = format!(
+        "
+        client_name = '{name}'
+        relay_server = '{address}'
+        buffer_max_length = 0
+        buffer_timeout = '0s'
+        [retry]
+        max_times = 3
+        initial_delay = '50ms'
+        max_delay = '100ms'
+        "
+    );
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(plugin_info::<RelayClientPlugin>(&config));
+    plugins.add_plugin(plugin_info::<CounterPlugin>(&format!("counter = 'tenants-{name}'")));
+    agent::Builder::new(plugins).build_and_start().unwrap()
+}
@@ -0,0 +1,432 @@
+//! Reading of the CSV files written by the `csv` plugin.
+
+use std::{
+    io::{self, BufRead},
+    time::SystemTime,
+};
+
+use alumet::{
+    measurement::{Timestamp, WrappedMeasurementType, WrappedMeasurementValue},
+    resources::{Resource, ResourceConsumer},
+    units::{PrefixedUnit, Unit, UnitPrefix},
+};
+use anyhow::{Context, anyhow};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+/// Name of the last column, which contains the attributes that were not in the header.
+const LATE_ATTRIBUTES: &str = "__late_attributes";
+
+pub struct CsvParams {
+    pub delimiter: char,
+    pub late_delimiter: char,
+}
+
+/// Reads CSV records, as specified by <https://www.ietf.org/rfc/rfc4180.txt>.
+pub struct CsvReader<R> {
+    reader: R,
+    params: CsvParams,
+    line: String,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    pub fn new(reader: R, params: CsvParams) -> Self {
+        Self {
+            reader,
+            params,
+            line: String::new(),
+        }
+    }
+
+    pub fn params(&self) -> &CsvParams {
+        &self.params
+    }
+
+    /// Reads the next record, or returns `None` at the end of the file.
+    ///
+    /// Empty lines are skipped.
+    pub fn read_record(&mut self) -> io::Result<Option<Vec<String>>> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut in_quotes = false;
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                if in_quotes {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "unterminated quoted field"));
+                }
+                return Ok(None);
+            }
+
+            let mut chars = self.line.chars().peekable();
+            while let Some(c) = chars.next() {
+                if in_quotes {
+                    if c != '"' {
+                        field.push(c);
+                    } else if chars.peek() == Some(&'"') {
+                        // escaped quote
+                        chars.next();
+                        field.push('"');
+                    } else {
+                        in_quotes = false;
+                    }
+                } else if c == '"' && field.is_empty() {
+                    in_quotes = true;
+                } else if c == self.params.delimiter {
+                    fields.push(std::mem::take(&mut field));
+                } else if c != '\n' && c != '\r' {
+                    field.push(c);
+                }
+            }
+
+            // A quoted field can contain line breaks: in that case, the record continues on the next line.
+            if !in_quotes {
+                if fields.is_empty() && field.is_empty() {
+                    continue;
+                }
+                fields.push(field);
+                return Ok(Some(fields));
+            }
+        }
+    }
+}
+
+/// Position of the columns in the CSV file, obtained from its header.
+pub struct Columns {
+    metric: usize,
+    timestamp: usize,
+    value: usize,
+    resource_kind: usize,
+    resource_id: usize,
+    consumer_kind: usize,
+    consumer_id: usize,
+    late_attributes: Option<usize>,
+    attributes: Vec<(usize, String)>,
+}
+
+/// A line of the CSV file.
+#[derive(Debug, PartialEq)]
+pub struct Record {
+    pub metric: String,
+    pub timestamp: Timestamp,
+    pub value: String,
+    pub resource: Resource,
+    pub consumer: ResourceConsumer,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Columns {
+    pub fn from_header(header: &[String]) -> anyhow::Result<Self> {
+        let find = |name: &str| {
+            header
+                .iter()
+                .position(|c| c == name)
+                .with_context(|| format!("missing column {name:?} in the CSV header"))
+        };
+        let mut columns = Columns {
+            metric: find("metric")?,
+            timestamp: find("timestamp")?,
+            value: find("value")?,
+            resource_kind: find("resource_kind")?,
+            resource_id: find("resource_id")?,
+            consumer_kind: find("consumer_kind")?,
+            consumer_id: find("consumer_id")?,
+            late_attributes: find(LATE_ATTRIBUTES).ok(),
+            attributes: Vec::new(),
+        };
+        let known = [
+            columns.metric,
+            columns.timestamp,
+            columns.value,
+            columns.resource_kind,
+            columns.resource_id,
+            columns.consumer_kind,
+            columns.consumer_id,
+        ];
+        columns.attributes = header
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !known.contains(i) && Some(*i) != columns.late_attributes)
+            .map(|(i, name)| (i, name.to_owned()))
+            .collect();
+        Ok(columns)
+    }
+
+    /// Parses a record of the CSV file.
+    pub fn parse(&self, mut fields: Vec<String>, params: &CsvParams) -> anyhow::Result<Record> {
+        if fields.len() <= self.max_index() {
+            return Err(anyhow!(
+                "expected at least {} columns, got {}",
+                self.max_index() + 1,
+                fields.len()
+            ));
+        }
+        let mut take = |i: usize| std::mem::take(&mut fields[i]);
+
+        let timestamp = take(self.timestamp);
+        let timestamp =
+            OffsetDateTime::parse(&timestamp, &Rfc3339).with_context(|| format!("invalid timestamp {timestamp:?}"))?;
+        let timestamp = Timestamp::from(SystemTime::from(timestamp));
+
+        let resource = Resource::parse(take(self.resource_kind), take(self.resource_id))?;
+        let consumer = ResourceConsumer::parse(take(self.consumer_kind), take(self.consumer_id))?;
+
+        let mut attributes = Vec::new();
+        for (i, key) in &self.attributes {
+            let value = take(*i);
+            if !value.is_empty() {
+                attributes.push((key.to_owned(), value));
+            }
+        }
+        if let Some(i) = self.late_attributes {
+            attributes.extend(parse_late_attributes(&take(i), params.late_delimiter)?);
+        }
+
+        Ok(Record {
+            metric: take(self.metric),
+            timestamp,
+            value: take(self.value),
+            resource,
+            consumer,
+            attributes,
+        })
+    }
+
+    fn max_index(&self) -> usize {
+        [
+            self.metric,
+            self.timestamp,
+            self.value,
+            self.resource_kind,
+            self.resource_id,
+            self.consumer_kind,
+            self.consumer_id,
+        ]
+        .into_iter()
+        .chain(self.late_attributes)
+        .chain(self.attributes.iter().map(|(i, _)| *i))
+        .max()
+        .unwrap_or_default()
+    }
+}
+
+/// Parses the content of the `__late_attributes` column, in the form `key1=value1,key2=value2`.
+fn parse_late_attributes(s: &str, delimiter: char) -> anyhow::Result<Vec<(String, String)>> {
+    let mut res = Vec::new();
+    let mut entry = String::new();
+    let mut chars = s.chars().peekable();
+    loop {
+        match chars.next() {
+            Some('\\') if chars.peek() == Some(&delimiter) => {
+                entry.push(delimiter);
+                chars.next();
+            }
+            Some(c) if c != delimiter => entry.push(c),
+            next => {
+                if !entry.is_empty() {
+                    let (key, value) = entry
+                        .split_once('=')
+                        .with_context(|| format!("invalid late attribute {entry:?}"))?;
+                    res.push((key.to_owned(), value.to_owned()));
+                    entry.clear();
+                }
+                if next.is_none() {
+                    return Ok(res);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the type of the values of the CSV file.
+///
+/// Integers are read as `U64`, other numbers as `F64`.
+pub fn value_type(value: &str) -> anyhow::Result<WrappedMeasurementType> {
+    if value.parse::<u64>().is_ok() {
+        Ok(WrappedMeasurementType::U64)
+    } else if value.parse::<f64>().is_ok() {
+        Ok(WrappedMeasurementType::F64)
+    } else {
+        Err(anyhow!("invalid value {value:?}"))
+    }
+}
+
+/// Parses a value of the CSV file.
+pub fn parse_value(value: &str, value_type: &WrappedMeasurementType) -> anyhow::Result<WrappedMeasurementValue> {
+    let res = match value_type {
+        WrappedMeasurementType::U64 => value.parse().map(WrappedMeasurementValue::U64).ok(),
+        WrappedMeasurementType::F64 => value.parse().map(WrappedMeasurementValue::F64).ok(),
+    };
+    res.with_context(|| format!("invalid value {value:?}"))
+}
+
+/// Splits the content of the `metric` column into the name of the metric and its unit.
+///
+/// The csv plugin can append the unit to the metric name, for instance `memory_usage_B`.
+/// If the last part of the name is not a known unit, the whole name is used, with no unit.
+pub fn parse_metric(metric: &str, with_unit: bool) -> (String, PrefixedUnit) {
+    if with_unit
+        && let Some((name, unit)) = metric.rsplit_once('_')
+        && let Some(unit) = parse_unit(unit)
+    {
+        return (name.to_owned(), unit);
+    }
+    (metric.to_owned(), PrefixedUnit::from(Unit::Unity))
+}
+
+/// Parses the unique name or the display name of a unit.
+fn parse_unit(s: &str) -> Option<PrefixedUnit> {
+    if let Ok(unit) = s.parse::<PrefixedUnit>() {
+        return Some(unit);
+    }
+    let prefixes = [
+        "giga", "mega", "kilo", "milli", "micro", "nano", "G", "M", "k", "m", "μ", "n", "",
+    ];
+    prefixes.into_iter().find_map(|prefix| {
+        let base_unit = match s.strip_prefix(prefix)? {
+            "B" => Unit::Byte,
+            "Wh" => Unit::WattHour,
+            "°C" => Unit::DegreeCelsius,
+            "°F" => Unit::DegreeFahrenheit,
+            _ => return None,
+        };
+        let prefix = prefix.parse::<UnitPrefix>().ok()?;
+        Some(PrefixedUnit { base_unit, prefix })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use alumet::{
+        measurement::{Timestamp, WrappedMeasurementType},
+        resources::{Resource, ResourceConsumer},
+        units::{PrefixedUnit, Unit},
+    };
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use super::{Columns, CsvParams, CsvReader, Record, parse_late_attributes, parse_metric, value_type};
+
+    fn params() -> CsvParams {
+        CsvParams {
+            delimiter: ';',
+            late_delimiter: ',',
+        }
+    }
+
+    #[test]
+    fn read_records() {
+        let input = indoc! {"
+            a;b;c
+            1;\"x;y\";\"multi
+            line \"\"quoted\"\"\"
+
+            ;;
+        "};
+        let mut reader = CsvReader::new(input.as_bytes(), params());
+        assert_eq!(reader.read_record().unwrap().unwrap(), vec!["a", "b", "c"]);
+        assert_eq!(
+            reader.read_record().unwrap().unwrap(),
+            vec!["1", "x;y", "multi\nline \"quoted\""]
+        );
+        assert_eq!(reader.read_record().unwrap().unwrap(), vec!["", "", ""]);
+        assert_eq!(reader.read_record().unwrap(), None);
+
+        let mut reader = CsvReader::new("a;\"b".as_bytes(), params());
+        assert!(reader.read_record().is_err());
+    }
+
+    #[test]
+    fn parse_records() {
+        let input = indoc! {"
+            metric;timestamp;value;resource_kind;resource_id;consumer_kind;consumer_id;domain;__late_attributes
+            rapl_consumed_energy_J;2025-01-01T12:00:00.5Z;12.5;cpu_package;0;local_machine;;package;
+            cpu_time_delta;2025-01-01T12:00:01Z;17;local_machine;;process;15;;kind=user,cmd=a\\,b
+        "};
+        let mut reader = CsvReader::new(input.as_bytes(), params());
+        let columns = Columns::from_header(&reader.read_record().unwrap().unwrap()).unwrap();
+
+        let t0 = Timestamp::from(UNIX_EPOCH + Duration::from_secs(1735732800));
+        let record = columns
+            .parse(reader.read_record().unwrap().unwrap(), &params())
+            .unwrap();
+        assert_eq!(
+            record,
+            Record {
+                metric: String::from("rapl_consumed_energy_J"),
+                timestamp: t0 + Duration::from_millis(500),
+                value: String::from("12.5"),
+                resource: Resource::CpuPackage { id: 0 },
+                consumer: ResourceConsumer::LocalMachine,
+                attributes: vec![(String::from("domain"), String::from("package"))],
+            }
+        );
+        let record = columns
+            .parse(reader.read_record().unwrap().unwrap(), &params())
+            .unwrap();
+        assert_eq!(
+            record,
+            Record {
+                metric: String::from("cpu_time_delta"),
+                timestamp: t0 + Duration::from_secs(1),
+                value: String::from("17"),
+                resource: Resource::LocalMachine,
+                consumer: ResourceConsumer::Process { pid: 15 },
+                attributes: vec![
+                    (String::from("kind"), String::from("user")),
+                    (String::from("cmd"), String::from("a,b")),
+                ],
+            }
+        );
+
+        assert!(Columns::from_header(&[String::from("metric")]).is_err());
+        assert!(columns.parse(vec![String::new(); 3], &params()).is_err());
+    }
+
+    #[test]
+    fn late_attributes() {
+        assert_eq!(parse_late_attributes("", ',').unwrap(), vec![]);
+        assert_eq!(
+            parse_late_attributes("a=1:b=x\\:y", ':').unwrap(),
+            vec![
+                (String::from("a"), String::from("1")),
+                (String::from("b"), String::from("x:y"))
+            ]
+        );
+        assert!(parse_late_attributes("a", ',').is_err());
+    }
+
+    #[test]
+    fn metrics_and_values() {
+        assert_eq!(
+            parse_metric("memory_usage_B", true),
+            (String::from("memory_usage"), PrefixedUnit::from(Unit::Byte))
+        );
+        assert_eq!(
+            parse_metric("memory_usage_By", true),
+            (String::from("memory_usage"), PrefixedUnit::from(Unit::Byte))
+        );
+        assert_eq!(
+            parse_metric("energy_mJ", true),
+            (String::from("energy"), PrefixedUnit::milli(Unit::Joule))
+        );
+        assert_eq!(
+            parse_metric("energy_kWh", true),
+            (String::from("energy"), PrefixedUnit::kilo(Unit::WattHour))
+        );
+        assert_eq!(
+            parse_metric("cpu_time_delta", true),
+            (String::from("cpu_time_delta"), PrefixedUnit::from(Unit::Unity))
+        );
+        assert_eq!(
+            parse_metric("energy_J", false),
+            (String::from("energy_J"), PrefixedUnit::from(Unit::Unity))
+        );
+
+        assert_eq!(value_type("12").unwrap(), WrappedMeasurementType::U64);
+        assert_eq!(value_type("-1").unwrap(), WrappedMeasurementType::F64);
+        assert_eq!(value_type("0.5").unwrap(), WrappedMeasurementType::F64);
+        assert!(value_type("abc").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+mod csv;
+mod replay;
+
+use std::{path::PathBuf, sync::Arc};
+
+use alumet::plugin::{
+    AlumetPluginStart, ConfigTable,
+    rust::{AlumetPlugin, deserialize_config, serialize_config},
+};
+use anyhow::{Context, anyhow};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::replay::ReplaySettings;
+
+pub struct ReplayPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for ReplayPlugin {
+    fn name() -> &'static str {
+        "replay"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        Ok(Some(serialize_config(Config::default())?))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config: Config = deserialize_config(config)?;
+        if config.speed.is_nan() || config.speed < 0.0 {
+            return Err(anyhow!("invalid speed {}: it must be positive, or zero", config.speed));
+        }
+        Ok(Box::new(ReplayPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        // The metrics must be registered now, before the measurements are replayed.
+        let found = replay::find_metrics(
+            &self.config.input_files,
+            self.config.csv_delimiter,
+            self.config.csv_late_delimiter,
+            self.config.metric_names_with_unit,
+        )?;
+        let mut metrics = FxHashMap::default();
+        for (column, metric) in found {
+            let id = alumet
+                .create_metric_untyped(
+                    &metric.name,
+                    metric.value_type.clone(),
+                    metric.unit,
+                    &format!("replayed metric {}", metric.name),
+                )
+                .with_context(|| format!("could not create the replayed metric {}", metric.name))?;
+            metrics.insert(column, (id, metric.value_type));
+        }
+        log::info!(
+            "Found {} metrics in {} files to replay.",
+            metrics.len(),
+            self.config.input_files.len()
+        );
+
+        let settings = Arc::new(ReplaySettings {
+            files: self.config.input_files.clone(),
+            delimiter: self.config.csv_delimiter,
+            late_delimiter: self.config.csv_late_delimiter,
+            speed: self.config.speed,
+            shift_timestamps: self.config.shift_timestamps,
+            metrics,
+        });
+        alumet.add_autonomous_source_builder("csv", move |_ctx, cancel_token, out_tx| {
+            Ok(Box::pin(replay::replay(settings, cancel_token, out_tx)))
+        })?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// CSV files to replay, one after the other, as written by the csv plugin.
+    pub input_files: Vec<PathBuf>,
+    /// Speed of the replay: 1 replays the measurements with their original timing, 2 is twice as fast, etc.
+    /// 0 replays the measurements as fast as possible.
+    pub speed: f64,
+    /// Do we shift the timestamps so that the replay of each file starts now (instead of keeping the original timestamps)?
+    pub shift_timestamps: bool,
+    /// Do the metric names end with their unit, as in the default config of the csv plugin?
+    pub metric_names_with_unit: bool,
+    /// The CSV delimiter, such as `;`
+    pub csv_delimiter: char,
+    /// The delimiter between the entries in `__late_attributes`.
+    pub csv_late_delimiter: char,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            input_files: vec![PathBuf::from("alumet-output.csv")],
+            speed: 1.0,
+            shift_timestamps: false,
+            metric_names_with_unit: true,
+            csv_delimiter: ';',
+            csv_late_delimiter: ',',
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use alumet::{
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementType},
+    metrics::RawMetricId,
+    units::PrefixedUnit,
+};
+use anyhow::{Context, anyhow};
+use rustc_hash::FxHashMap;
+use tokio::{sync::mpsc, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::csv::{self, Columns, CsvParams, CsvReader};
+
+/// Maximum number of measurements that are sent to the pipeline at once.
+const MAX_BATCH_SIZE: usize = 4096;
+
+/// A metric found in the recorded files.
+pub struct FoundMetric {
+    pub name: String,
+    pub unit: PrefixedUnit,
+    pub value_type: WrappedMeasurementType,
+}
+
+pub struct ReplaySettings {
+    pub files: Vec<PathBuf>,
+    pub delimiter: char,
+    pub late_delimiter: char,
+    pub speed: f64,
+    pub shift_timestamps: bool,
+    /// Metrics of the pipeline, by content of the `metric` column.
+    pub metrics: FxHashMap<String, (RawMetricId, WrappedMeasurementType)>,
+}
+
+fn open_csv(
+    path: &Path,
+    delimiter: char,
+    late_delimiter: char,
+) -> anyhow::Result<(CsvReader<BufReader<File>>, Columns)> {
+    let file = File::open(path).with_context(|| format!("failed to open file {path:?}"))?;
+    let params = CsvParams {
+        delimiter,
+        late_delimiter,
+    };
+    let mut reader = CsvReader::new(BufReader::new(file), params);
+    let header = reader.read_record()?.with_context(|| format!("empty file {path:?}"))?;
+    let columns = Columns::from_header(&header).with_context(|| format!("invalid header in {path:?}"))?;
+    Ok((reader, columns))
+}
+
+/// Reads the files once to find the metrics that they contain, and the type of their values.
+///
+/// Returns the metrics by content of the `metric` column.
+pub fn find_metrics(
+    files: &[PathBuf],
+    delimiter: char,
+    late_delimiter: char,
+    names_with_unit: bool,
+) -> anyhow::Result<FxHashMap<String, FoundMetric>> {
+    let mut metrics = FxHashMap::<String, FoundMetric>::default();
+    for path in files {
+        let (mut reader, columns) = open_csv(path, delimiter, late_delimiter)?;
+        let mut n = 0;
+        while let Some(fields) = reader.read_record()? {
+            n += 1;
+            let record = columns
+                .parse(fields, reader.params())
+                .with_context(|| format!("invalid record {n} in {path:?}"))?;
+            let value_type =
+                csv::value_type(&record.value).with_context(|| format!("invalid record {n} in {path:?}"))?;
+            match metrics.get_mut(&record.metric) {
+                Some(m) => {
+                    // a metric that has at least one non-integer value is a F64 metric
+                    if value_type == WrappedMeasurementType::F64 {
+                        m.value_type = value_type;
+                    }
+                }
+                None => {
+                    let (name, unit) = csv::parse_metric(&record.metric, names_with_unit);
+                    metrics.insert(record.metric, FoundMetric { name, unit, value_type });
+                }
+            }
+        }
+    }
+    Ok(metrics)
+}
+
+/// Replays the files, one after the other, until the end of the last file or until `cancel_token` is cancelled.
+pub async fn replay(
+    settings: Arc<ReplaySettings>,
+    cancel_token: CancellationToken,
+    tx: mpsc::Sender<MeasurementBuffer>,
+) -> anyhow::Result<()> {
+    for path in &settings.files {
+        if cancel_token.is_cancelled() {
+            break;
+        }
+        log::info!("Replaying {path:?}...");
+        replay_file(path.to_owned(), settings.clone(), &cancel_token, &tx)
+            .await
+            .with_context(|| format!("failed to replay {path:?}"))?;
+    }
+    log::info!("Replay finished.");
+    Ok(())
+}
+
+async fn replay_file(
+    path: PathBuf,
+    settings: Arc<ReplaySettings>,
+    cancel_token: &CancellationToken,
+    tx: &mpsc::Sender<MeasurementBuffer>,
+) -> anyhow::Result<()> {
+    // Read the file in a separate thread, to avoid blocking the async runtime.
+    let (points_tx, mut points_rx) = mpsc::channel(MAX_BATCH_SIZE);
+    let reader = tokio::task::spawn_blocking({
+        let settings = settings.clone();
+        move || read_file(&path, &settings, points_tx)
+    });
+
+    let replay_start = Instant::now();
+    let replay_start_time = Timestamp::now();
+    let mut first_timestamp = None;
+    let mut shift = Duration::ZERO;
+    let mut batch = MeasurementBuffer::new();
+    loop {
+        let point = tokio::select! {
+            biased;
+            _ = cancel_token.cancelled() => return Ok(()),
+            point = points_rx.recv() => point,
+        };
+        let Some(mut point) = point else {
+            break;
+        };
+
+        let first = *first_timestamp.get_or_insert_with(|| {
+            shift = replay_start_time.duration_since(point.timestamp).unwrap_or_default();
+            point.timestamp
+        });
+
+        // Wait until it's time to send the measurement. Measurements that are older than the previous ones
+        // have a deadline in the past, they don't wait.
+        if settings.speed > 0.0 {
+            let elapsed = point.timestamp.duration_since(first).unwrap_or_default();
+            let deadline = replay_start + elapsed.div_f64(settings.speed);
+            if deadline > Instant::now() {
+                send(&mut batch, tx).await?;
+                tokio::select! {
+                    biased;
+                    _ = cancel_token.cancelled() => return Ok(()),
+                    _ = tokio::time::sleep_until(deadline) => (),
+                };
+            }
+        }
+
+        if settings.shift_timestamps {
+            point.timestamp = point.timestamp + shift;
+        }
+        batch.push(point);
+        if batch.len() >= MAX_BATCH_SIZE {
+            send(&mut batch, tx).await?;
+        }
+    }
+    send(&mut batch, tx).await?;
+    reader.await?
+}
+
+async fn send(batch: &mut MeasurementBuffer, tx: &mpsc::Sender<MeasurementBuffer>) -> anyhow::Result<()> {
+    if !batch.is_empty() {
+        tx.send(std::mem::take(batch))
+            .await
+            .map_err(|_| anyhow!("the pipeline does not accept measurements anymore"))?;
+    }
+    Ok(())
+}
+
+/// Reads the measurements of a file and sends them to `tx`, until the end of the file or until `tx` is closed.
+fn read_file(path: &Path, settings: &ReplaySettings, tx: mpsc::Sender<MeasurementPoint>) -> anyhow::Result<()> {
+    let (mut reader, columns) = open_csv(path, settings.delimiter, settings.late_delimiter)?;
+    let mut n = 0;
+    while let Some(fields) = reader.read_record()? {
+        n += 1;
+        let point =
+            read_point(fields, &columns, reader.params(), settings).with_context(|| format!("invalid record {n}"))?;
+        if tx.blocking_send(point).is_err() {
+            // the replay has been stopped
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn read_point(
+    fields: Vec<String>,
+    columns: &Columns,
+    params: &CsvParams,
+    settings: &ReplaySettings,
+) -> anyhow::Result<MeasurementPoint> {
+    let record = columns.parse(fields, params)?;
+    let (metric, value_type) = settings
+        .metrics
+        .get(&record.metric)
+        .with_context(|| format!("unknown metric {:?}, has the file changed?", record.metric))?;
+    let value = csv::parse_value(&record.value, value_type)?;
+    let mut point = MeasurementPoint::new_untyped(record.timestamp, *metric, record.resource, record.consumer, value);
+    for (key, value) in record.attributes {
+        point.add_attr(key, value);
+    }
+    Ok(point)
+}
@@ -0,0 +1,102 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use alumet::{
+    agent::{
+        self,
+        plugin::{PluginInfo, PluginSet},
+    },
+    plugin::PluginMetadata,
+    test::StartupExpectations,
+    units::{PrefixedUnit, Unit},
+};
+use indoc::indoc;
+use plugin_csv::CsvPlugin;
+use plugin_replay::{Config, ReplayPlugin};
+use pretty_assertions::assert_eq;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const RECORDED: &str = indoc! {"
+    metric;timestamp;value;resource_kind;resource_id;consumer_kind;consumer_id;domain;__late_attributes
+    rapl_consumed_energy_J;2025-01-01T12:00:00Z;12.5;cpu_package;0;local_machine;;package;
+    rapl_consumed_energy_J;2025-01-01T12:00:00Z;3;dram;0;local_machine;;dram;
+    cpu_time_delta;2025-01-01T12:00:00.1Z;17;local_machine;;process;15;;kind=user
+    memory_usage_B;2025-01-01T12:00:00.2Z;1024;local_machine;;process;15;;
+    rapl_consumed_energy_J;2025-01-01T12:00:00.3Z;12;cpu_package;0;local_machine;;package;
+"};
+
+fn wait_for_lines(path: &Path, n: usize) -> String {
+    let start = Instant::now();
+    loop {
+        let content = std::fs::read_to_string(path).unwrap_or_default();
+        if content.lines().count() >= n || start.elapsed() > TIMEOUT {
+            return content;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn replay_to_csv() {
+    let _ = env_logger::Builder::from_default_env().try_init();
+
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("recorded.csv");
+    let output = tmp.path().join("replayed.csv");
+    std::fs::write(&input, RECORDED).unwrap();
+
+    let replay_config = Config {
+        input_files: vec![input],
+        speed: 2.0,
+        ..Default::default()
+    };
+    let csv_config = plugin_csv::Config {
+        output_path: output.clone(),
+        ..Default::default()
+    };
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<ReplayPlugin>(),
+        enabled: true,
+        config: Some(
+            toml::Value::try_from(replay_config)
+                .unwrap()
+                .as_table()
+                .unwrap()
+                .clone(),
+        ),
+    });
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<CsvPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(csv_config).unwrap().as_table().unwrap().clone()),
+    });
+
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("rapl_consumed_energy", Unit::Joule)
+        .expect_metric::<u64>("cpu_time_delta", Unit::Unity)
+        .expect_metric::<u64>("memory_usage", PrefixedUnit::from(Unit::Byte))
+        .expect_source("replay", "csv")
+        .expect_output("csv", "out");
+
+    let start = Instant::now();
+    let agent = agent::Builder::new(plugins)
+        .with_expectations(startup_expectation)
+        .build_and_start()
+        .unwrap();
+
+    // the output must be the same as the input, in at least 150ms because of the original timing
+    let replayed = wait_for_lines(&output, RECORDED.lines().count());
+    assert!(start.elapsed() >= Duration::from_millis(150));
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+
+    let mut lines: Vec<&str> = replayed.lines().collect();
+    lines[1..].sort();
+    let mut expected: Vec<&str> = RECORDED.lines().collect();
+    expected[1..].sort();
+    assert_eq!(lines, expected);
+}
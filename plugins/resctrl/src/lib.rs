@@ -0,0 +1,162 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+    resources::ResourceConsumer,
+};
+
+use crate::source::{MeasuredGroup, Metrics, ResctrlSource};
+
+mod resctrl;
+mod source;
+
+/// Measures the memory bandwidth and the L3 cache occupancy with resctrl (Intel RDT, AMD PQoS).
+pub struct ResctrlPlugin {
+    config: Config,
+    /// The monitoring groups created by the plugin, to remove them when it stops.
+    created_groups: Vec<PathBuf>,
+}
+
+impl AlumetPlugin for ResctrlPlugin {
+    fn name() -> &'static str {
+        "resctrl"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(ResctrlPlugin {
+            config,
+            created_groups: Vec::new(),
+        }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let resctrl_path = &self.config.resctrl_path;
+        resctrl::check_monitoring(resctrl_path)?;
+
+        // Create the monitoring groups requested in the config.
+        for cpu_group in &self.config.cpu_groups {
+            let path = resctrl::create_mon_group(resctrl_path, &format!("alumet-{}", cpu_group.name))?;
+            self.created_groups.push(path.clone());
+            resctrl::assign_cpus(&path, &cpu_group.cpus)?;
+        }
+        let mut cgroups_by_group = HashMap::new();
+        for cgroup in &self.config.cgroups {
+            let cgroup = format!("/{}", cgroup.trim_matches('/'));
+            let name = format!("alumet-cgroup{}", cgroup.replace('/', "_"));
+            let path = resctrl::create_mon_group(resctrl_path, &name)?;
+            self.created_groups.push(path.clone());
+            cgroups_by_group.insert(path, cgroup);
+        }
+
+        let mut groups = Vec::new();
+        for group in resctrl::explore(resctrl_path).context("could not find the resctrl groups")? {
+            if group.domains.is_empty() {
+                log::debug!("Ignoring resctrl group {}: no monitoring domain", group.name);
+                continue;
+            }
+            log::info!(
+                "Found resctrl group {} with {} domains",
+                group.name,
+                group.domains.len()
+            );
+            let measured = match cgroups_by_group.remove(&group.path) {
+                Some(cgroup) => {
+                    let fs_path = self.config.cgroupfs_path.join(cgroup.trim_start_matches('/'));
+                    let consumer = ResourceConsumer::ControlGroup { path: cgroup.into() };
+                    MeasuredGroup::new(group, consumer).with_cgroup(fs_path)
+                }
+                None if group.name == "/" => MeasuredGroup::new(group, ResourceConsumer::LocalMachine),
+                None => {
+                    let consumer = ResourceConsumer::Custom {
+                        kind: "resctrl_group".into(),
+                        id: group.name.clone().into(),
+                    };
+                    MeasuredGroup::new(group, consumer)
+                }
+            };
+            groups.push(measured);
+        }
+        if groups.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: no resctrl group found in {resctrl_path:?}"
+            ));
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = ResctrlSource::new(groups, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("resctrl", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        // Free the RMIDs of the groups that we have created.
+        for path in self.created_groups.drain(..) {
+            if let Err(e) = std::fs::remove_dir(&path) {
+                log::warn!("failed to remove the resctrl group {path:?}: {e}");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the resctrl filesystem.
+    pub resctrl_path: PathBuf,
+
+    /// Path to the cgroup (v2) filesystem.
+    pub cgroupfs_path: PathBuf,
+
+    /// Groups of CPUs to measure. A monitoring group is created for each of them.
+    #[serde(default)]
+    pub cpu_groups: Vec<CpuGroup>,
+
+    /// Cgroups to measure, for instance `/system.slice/docker.service`.
+    /// A monitoring group is created for each of them, and the threads of the cgroup are assigned to it.
+    #[serde(default)]
+    pub cgroups: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CpuGroup {
+    /// Name of the group.
+    pub name: String,
+    /// List of CPUs, for instance `0-7,16-23`.
+    pub cpus: String,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            resctrl_path: PathBuf::from("/sys/fs/resctrl"),
+            cgroupfs_path: PathBuf::from("/sys/fs/cgroup"),
+            cpu_groups: Vec::new(),
+            cgroups: Vec::new(),
+        }
+    }
+}
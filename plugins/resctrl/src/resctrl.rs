@@ -0,0 +1,291 @@
+//! Discovery and management of the monitoring groups of the resctrl filesystem.
+//!
+//! See the [kernel documentation](https://docs.kernel.org/arch/x86/resctrl.html).
+
+use std::{
+    collections::HashSet,
+    fs::OpenOptions,
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, anyhow};
+
+/// A group of the resctrl filesystem that can be monitored: the root group,
+/// a control group (`CTRL_MON`) or a monitoring group (`MON`).
+#[derive(Debug)]
+pub struct MonGroup {
+    /// Path of the group relative to the root of resctrl, like `/` or `/mon_groups/g1`.
+    pub name: String,
+    /// Path of the group in the filesystem.
+    pub path: PathBuf,
+    /// Monitoring domains (one per L3 cache).
+    pub domains: Vec<MonDomain>,
+}
+
+/// A monitoring domain of a group, such as `mon_data/mon_L3_00`.
+#[derive(Debug)]
+pub struct MonDomain {
+    /// Id of the L3 cache.
+    pub id: u32,
+    path: PathBuf,
+}
+
+/// Values of the monitoring counters of a domain.
+///
+/// A value is `None` if the feature is not supported by the hardware,
+/// or if the counter is temporarily unavailable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonValues {
+    /// Occupancy of the L3 cache, in bytes (CMT).
+    pub llc_occupancy: Option<u64>,
+    /// Total memory traffic since the creation of the group, in bytes (MBM).
+    pub mbm_total_bytes: Option<u64>,
+    /// Memory traffic to the local NUMA node since the creation of the group, in bytes (MBM).
+    pub mbm_local_bytes: Option<u64>,
+}
+
+impl MonGroup {
+    fn at_path(name: String, path: PathBuf) -> anyhow::Result<Self> {
+        let mon_data = path.join("mon_data");
+        let mut domains = Vec::new();
+        for entry in std::fs::read_dir(&mon_data).with_context(|| format!("failed to read dir {mon_data:?}"))? {
+            let entry = entry?;
+            let id = entry
+                .file_name()
+                .to_str()
+                .and_then(|n| n.strip_prefix("mon_L3_")?.parse().ok());
+            if let Some(id) = id {
+                domains.push(MonDomain { id, path: entry.path() });
+            }
+        }
+        domains.sort_by_key(|d| d.id);
+        Ok(Self { name, path, domains })
+    }
+}
+
+impl MonDomain {
+    pub fn read(&self) -> MonValues {
+        MonValues {
+            llc_occupancy: read_counter(&self.path.join("llc_occupancy")),
+            mbm_total_bytes: read_counter(&self.path.join("mbm_total_bytes")),
+            mbm_local_bytes: read_counter(&self.path.join("mbm_local_bytes")),
+        }
+    }
+}
+
+fn read_counter(path: &Path) -> Option<u64> {
+    // The files contain "Unavailable" or "Error" when the counter cannot be read.
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Checks that resctrl is mounted and that it supports the monitoring of the L3 cache and memory bandwidth.
+pub fn check_monitoring(resctrl_path: &Path) -> anyhow::Result<()> {
+    if !resctrl_path.join("info").is_dir() {
+        return Err(anyhow!(
+            "resctrl is not mounted on {resctrl_path:?}, mount it with `mount -t resctrl resctrl {}`",
+            resctrl_path.display()
+        ));
+    }
+    if !resctrl_path.join("info/L3_MON").is_dir() {
+        return Err(anyhow!(
+            "the CPU does not support the monitoring of the L3 cache and memory bandwidth (CMT/MBM)"
+        ));
+    }
+    Ok(())
+}
+
+/// Explores the groups of the resctrl filesystem that can be monitored.
+///
+/// ## Expected file layout
+///
+/// ```txt
+/// /sys/fs/resctrl/
+/// |− info
+/// |− mon_data
+///     |− mon_L3_00
+///         |− llc_occupancy
+///         |− mbm_total_bytes
+///         |− mbm_local_bytes
+///     |− …
+/// |− mon_groups
+///     |− g1
+///         |− mon_data
+///             |− …
+/// |− c1 (control group)
+///     |− mon_data
+///     |− mon_groups
+/// ```
+pub fn explore(resctrl_path: &Path) -> anyhow::Result<Vec<MonGroup>> {
+    let mut groups = Vec::new();
+    let mut add_group = |name: String, path: PathBuf| match MonGroup::at_path(name, path) {
+        Ok(group) => groups.push(group),
+        Err(e) => log::warn!("failed to analyze resctrl group: {e:#}"),
+    };
+
+    // the root group is also a control group
+    let mut ctrl_groups = vec![(String::new(), resctrl_path.to_path_buf())];
+    for entry in std::fs::read_dir(resctrl_path).with_context(|| format!("failed to read dir {resctrl_path:?}"))? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_dir() && !matches!(file_name.as_str(), "info" | "mon_data" | "mon_groups") {
+            ctrl_groups.push((format!("/{file_name}"), entry.path()));
+        }
+    }
+    ctrl_groups.sort();
+
+    for (ctrl_name, ctrl_path) in ctrl_groups {
+        let mon_groups = ctrl_path.join("mon_groups");
+        let mut mon_group_names = Vec::new();
+        if let Ok(ls) = std::fs::read_dir(&mon_groups) {
+            for entry in ls.filter_map(|e| e.ok()) {
+                mon_group_names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        mon_group_names.sort();
+
+        let name = if ctrl_name.is_empty() {
+            String::from("/")
+        } else {
+            ctrl_name.clone()
+        };
+        add_group(name, ctrl_path);
+        for mon_name in mon_group_names {
+            add_group(format!("{ctrl_name}/mon_groups/{mon_name}"), mon_groups.join(mon_name));
+        }
+    }
+    Ok(groups)
+}
+
+/// Creates a monitoring group in the root control group, and returns its path.
+///
+/// If the group already exists (for instance because the agent has been killed), it is reused.
+pub fn create_mon_group(resctrl_path: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    let path = resctrl_path.join("mon_groups").join(name);
+    match std::fs::create_dir(&path) {
+        Ok(()) => Ok(path),
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(path),
+        // ENOSPC: no more RMID available
+        Err(e) => Err(anyhow::Error::new(e).context(format!("failed to create the resctrl group {path:?}"))),
+    }
+}
+
+/// Assigns some CPUs to a monitoring group, for instance `0-7,16-23`.
+pub fn assign_cpus(group_path: &Path, cpus: &str) -> anyhow::Result<()> {
+    let path = group_path.join("cpus_list");
+    std::fs::write(&path, cpus).with_context(|| format!("failed to assign cpus {cpus} to {path:?}"))
+}
+
+/// Assigns some tasks (threads) to a monitoring group.
+///
+/// Returns the number of tasks that have been assigned. The tasks that no longer exist are ignored.
+pub fn assign_tasks(group_path: &Path, tids: impl IntoIterator<Item = u32>) -> anyhow::Result<usize> {
+    let path = group_path.join("tasks");
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("failed to open {path:?}"))?;
+    let mut n = 0;
+    for tid in tids {
+        // Only one task can be written at a time (on old kernels).
+        match file.write_all(format!("{tid}\n").as_bytes()) {
+            Ok(()) => n += 1,
+            Err(e) => log::debug!("failed to assign task {tid} to {path:?}: {e}"),
+        }
+    }
+    Ok(n)
+}
+
+/// Lists the threads that belong to a cgroup (v2) or to one of its descendants.
+pub fn cgroup_threads(cgroup_path: &Path) -> anyhow::Result<HashSet<u32>> {
+    let mut tids = HashSet::new();
+    let mut stack = vec![cgroup_path.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let threads = dir.join("cgroup.threads");
+        let content = std::fs::read_to_string(&threads).with_context(|| format!("failed to read {threads:?}"))?;
+        tids.extend(content.lines().filter_map(|l| l.trim().parse::<u32>().ok()));
+        for entry in std::fs::read_dir(&dir)?.filter_map(|e| e.ok()) {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                stack.push(entry.path());
+            }
+        }
+    }
+    Ok(tids)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn write_domain(group: &Path, id: &str, values: [&str; 3]) {
+        let dir = group.join("mon_data").join(format!("mon_L3_{id}"));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("llc_occupancy"), values[0]).unwrap();
+        std::fs::write(dir.join("mbm_total_bytes"), values[1]).unwrap();
+        std::fs::write(dir.join("mbm_local_bytes"), values[2]).unwrap();
+    }
+
+    #[test]
+    fn explore_groups() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let root = root.path();
+        assert!(check_monitoring(root).is_err());
+        std::fs::create_dir_all(root.join("info/L3_MON"))?;
+        check_monitoring(root)?;
+
+        write_domain(root, "00", ["1048576", "5000", "4000"]);
+        write_domain(root, "01", ["2097152", "Unavailable", "Unavailable"]);
+        write_domain(&root.join("mon_groups/g1"), "00", ["0", "0", "0"]);
+        write_domain(&root.join("c1"), "00", ["0", "0", "0"]);
+        write_domain(&root.join("c1/mon_groups/g2"), "00", ["0", "0", "0"]);
+
+        let groups = explore(root)?;
+        let names: Vec<_> = groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["/", "/mon_groups/g1", "/c1", "/c1/mon_groups/g2"]);
+
+        let domains = &groups[0].domains;
+        assert_eq!(domains.iter().map(|d| d.id).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(
+            domains[0].read(),
+            MonValues {
+                llc_occupancy: Some(1048576),
+                mbm_total_bytes: Some(5000),
+                mbm_local_bytes: Some(4000),
+            }
+        );
+        assert_eq!(
+            domains[1].read(),
+            MonValues {
+                llc_occupancy: Some(2097152),
+                mbm_total_bytes: None,
+                mbm_local_bytes: None,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn assign_cgroup_threads() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let cgroup = root.path().join("cgroup");
+        std::fs::create_dir_all(cgroup.join("child"))?;
+        std::fs::write(cgroup.join("cgroup.threads"), "10\n11\n")?;
+        std::fs::write(cgroup.join("child/cgroup.threads"), "20\n")?;
+        let tids = cgroup_threads(&cgroup)?;
+        assert_eq!(tids, HashSet::from([10, 11, 20]));
+
+        let resctrl = root.path().join("resctrl");
+        let group = create_mon_group(&resctrl, "g").err();
+        assert!(group.is_some(), "mon_groups does not exist");
+        std::fs::create_dir_all(resctrl.join("mon_groups"))?;
+        let group = create_mon_group(&resctrl, "g")?;
+        assert_eq!(create_mon_group(&resctrl, "g")?, group);
+
+        std::fs::write(group.join("tasks"), "")?;
+        assert_eq!(assign_tasks(&group, [10, 20])?, 2);
+        assert_eq!(std::fs::read_to_string(group.join("tasks"))?, "10\n20\n");
+        Ok(())
+    }
+}
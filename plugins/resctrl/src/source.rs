@@ -0,0 +1,145 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+
+use crate::resctrl::{self, MonGroup, MonValues};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    llc_occupancy: TypedMetricId<u64>,
+    memory_bytes: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            llc_occupancy: alumet.create_metric(
+                "resctrl_llc_occupancy",
+                Unit::Byte,
+                "Occupancy of the last level cache (L3) by the tasks of the resctrl group",
+            )?,
+            memory_bytes: alumet.create_metric(
+                "resctrl_memory_bytes",
+                Unit::Byte,
+                "Memory traffic (total or local to the NUMA node) of the tasks of the resctrl group since the previous measurement",
+            )?,
+        })
+    }
+}
+
+/// A resctrl group to measure.
+pub struct MeasuredGroup {
+    group: MonGroup,
+    consumer: ResourceConsumer,
+    /// The cgroup whose threads must be assigned to this group, if any.
+    cgroup: Option<CgroupBinding>,
+    /// The previous values of each domain, to compute the difference.
+    previous: Vec<Option<MonValues>>,
+}
+
+/// Keeps the tasks of a resctrl group in sync with the threads of a cgroup.
+struct CgroupBinding {
+    /// Path of the cgroup in the filesystem.
+    fs_path: PathBuf,
+    /// The threads that have already been assigned to the resctrl group.
+    assigned: HashSet<u32>,
+}
+
+impl MeasuredGroup {
+    pub fn new(group: MonGroup, consumer: ResourceConsumer) -> Self {
+        Self {
+            previous: vec![None; group.domains.len()],
+            group,
+            consumer,
+            cgroup: None,
+        }
+    }
+
+    /// Binds the resctrl group to a cgroup: the threads of the cgroup will be assigned
+    /// to the group before each measurement.
+    pub fn with_cgroup(mut self, fs_path: PathBuf) -> Self {
+        self.cgroup = Some(CgroupBinding {
+            fs_path,
+            assigned: HashSet::new(),
+        });
+        self
+    }
+
+    fn sync_tasks(&mut self) -> anyhow::Result<()> {
+        if let Some(binding) = &mut self.cgroup {
+            let threads = resctrl::cgroup_threads(&binding.fs_path)?;
+            // New threads inherit the group of their parent, but the processes that have been moved
+            // to the cgroup after their creation must be assigned explicitly.
+            let new_threads = threads.difference(&binding.assigned).copied();
+            resctrl::assign_tasks(&self.group.path, new_threads)?;
+            binding.assigned = threads;
+        }
+        Ok(())
+    }
+}
+
+/// Measurement source that reads the monitoring counters of the resctrl groups.
+pub struct ResctrlSource {
+    groups: Vec<MeasuredGroup>,
+    metrics: Metrics,
+}
+
+impl ResctrlSource {
+    pub fn new(groups: Vec<MeasuredGroup>, metrics: Metrics) -> Self {
+        Self { groups, metrics }
+    }
+}
+
+impl Source for ResctrlSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for g in &mut self.groups {
+            if let Err(e) = g.sync_tasks() {
+                // the cgroup may have been removed, we can still report the last values
+                log::warn!(
+                    "failed to assign the tasks of the resctrl group {}: {e:#}",
+                    g.group.name
+                );
+            }
+
+            for (domain, previous) in g.group.domains.iter().zip(g.previous.iter_mut()) {
+                let values = domain.read();
+                let point = |metric, value: u64| {
+                    MeasurementPoint::new(
+                        timestamp,
+                        metric,
+                        Resource::custom("l3_cache", domain.id.to_string()),
+                        g.consumer.clone(),
+                        value,
+                    )
+                    .with_attr("group", g.group.name.clone())
+                };
+
+                if let Some(occupancy) = values.llc_occupancy {
+                    measurements.push(point(self.metrics.llc_occupancy, occupancy));
+                }
+                // Only push deltas, not the baseline value before the plugin starts
+                if let Some(prev) = previous {
+                    let deltas = [
+                        ("total", values.mbm_total_bytes, prev.mbm_total_bytes),
+                        ("local", values.mbm_local_bytes, prev.mbm_local_bytes),
+                    ];
+                    for (scope, now, prev) in deltas {
+                        if let (Some(now), Some(prev)) = (now, prev) {
+                            let delta = now.saturating_sub(prev);
+                            measurements.push(point(self.metrics.memory_bytes, delta).with_attr("scope", scope));
+                        }
+                    }
+                }
+                *previous = Some(values);
+            }
+        }
+        Ok(())
+    }
+}
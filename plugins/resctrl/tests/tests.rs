@@ -0,0 +1,100 @@
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::{Resource, ResourceConsumer},
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_resctrl::{Config, ResctrlPlugin};
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_resctrl() {
+    let root = tempdir().unwrap();
+    let config = Config {
+        resctrl_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (resctrl not mounted)");
+}
+
+#[test]
+fn plugin_with_root_group() {
+    let root = tempdir().unwrap();
+    let resctrl_path = root.path().to_path_buf();
+    std::fs::create_dir_all(resctrl_path.join("info/L3_MON")).unwrap();
+    write_domain(&resctrl_path, 1024, 1000);
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        resctrl_path: resctrl_path.clone(),
+        ..Default::default()
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<u64>("resctrl_llc_occupancy", Unit::Byte)
+        .expect_metric::<u64>("resctrl_memory_bytes", Unit::Byte)
+        .expect_source("resctrl", "resctrl");
+
+    let source = SourceName::from_str("resctrl", "resctrl");
+    let runtime = RuntimeExpectations::new()
+        .test_source(
+            source.clone(),
+            || {},
+            |ctx| {
+                // first measurement: only the occupancy, because the bandwidth is a difference
+                let m = ctx.measurements();
+                assert_eq!(m.len(), 1);
+                let point = m.iter().next().unwrap();
+                assert_eq!(point.resource, Resource::custom("l3_cache", "0"));
+                assert_eq!(point.consumer, ResourceConsumer::LocalMachine);
+                assert_eq!(point.value.as_u64(), 1024);
+            },
+        )
+        .test_source(
+            source,
+            move || write_domain(&resctrl_path, 2048, 5000),
+            |ctx| {
+                let m = ctx.measurements();
+                let metric = ctx.metrics().by_name("resctrl_memory_bytes").unwrap().0;
+                let bytes: Vec<_> = m
+                    .iter()
+                    .filter(|p| p.metric == metric)
+                    .map(|p| p.value.as_u64())
+                    .collect();
+                assert_eq!(bytes, vec![4000]);
+            },
+        );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+/// Writes the counters of the domain 0 of the group, without local bandwidth.
+fn write_domain(group: &Path, llc_occupancy: u64, mbm_total_bytes: u64) {
+    let dir = group.join("mon_data/mon_L3_00");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("llc_occupancy"), llc_occupancy.to_string()).unwrap();
+    std::fs::write(dir.join("mbm_total_bytes"), mbm_total_bytes.to_string()).unwrap();
+    std::fs::write(dir.join("mbm_local_bytes"), "Unavailable").unwrap();
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<ResctrlPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -0,0 +1,162 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use alumet::plugin::{
+    AlumetPluginStart, ConfigTable,
+    rust::{AlumetPlugin, deserialize_config, serialize_config},
+    secret::Secret,
+};
+
+use crate::{
+    pdu::PduClient,
+    source::{Metrics, SourceSettings},
+};
+
+pub use pdu::Vendor;
+
+mod pdu;
+mod raritan;
+mod servertech;
+mod source;
+
+pub struct RestPduPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for RestPduPlugin {
+    fn name() -> &'static str {
+        "rest-pdu"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(RestPduPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        if self.config.pdus.is_empty() {
+            return Err(anyhow!("no PDU configured"));
+        }
+        let metrics = Metrics::new(alumet)?;
+        for pdu in &self.config.pdus {
+            let url = reqwest::Url::parse(&pdu.url).with_context(|| format!("invalid PDU url {}", pdu.url))?;
+            let name = match (url.host_str(), url.port()) {
+                (Some(host), Some(port)) => format!("{host}:{port}"),
+                (Some(host), None) => host.to_owned(),
+                (None, _) => return Err(anyhow!("invalid PDU url {}: no host", pdu.url)),
+            };
+            let http = reqwest::Client::builder()
+                .timeout(pdu.timeout)
+                .danger_accept_invalid_certs(pdu.accept_invalid_certs)
+                .build()
+                .context("could not build the HTTP client")?;
+            let client = PduClient::new(pdu.vendor, http, &pdu.url, pdu.username.clone(), pdu.password.clone());
+            let settings = SourceSettings {
+                poll_interval: self.config.poll_interval,
+                pdu: name.clone(),
+                outlets: pdu.outlets.iter().map(|o| (o.outlet, o.node.clone())).collect(),
+            };
+            log::info!(
+                "Measuring {} outlets of PDU {} ({:?})",
+                pdu.outlets.len(),
+                name,
+                pdu.vendor
+            );
+            alumet.add_autonomous_source_builder(&name, move |_ctx, cancel_token, out_tx| {
+                Ok(Box::pin(source::run(client, metrics, settings, cancel_token, out_tx)))
+            })?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// The PDUs to measure.
+    pub pdus: Vec<PduConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PduConfig {
+    /// Base URL of the PDU, for instance `https://10.0.0.2`.
+    pub url: String,
+
+    /// Vendor of the PDU.
+    pub vendor: Vendor,
+
+    /// Name of the user of the API.
+    pub username: String,
+
+    /// Password of the user of the API, which can reference a secret (`secret://...`).
+    pub password: Secret,
+
+    /// Accept the invalid TLS certificates, such as the self-signed certificates of most PDUs.
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+
+    /// Maximum time to wait for the response of the PDU.
+    #[serde(with = "humantime_serde", default = "default_timeout")]
+    pub timeout: Duration,
+
+    /// The outlets to measure, and the nodes that are plugged into them.
+    pub outlets: Vec<OutletConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutletConfig {
+    /// Number of the outlet, starting at 1.
+    pub outlet: u32,
+    /// Name of the node plugged into the outlet, used as the id of the measured resource.
+    pub node: String,
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            pdus: vec![PduConfig {
+                url: String::from("https://192.168.1.100"),
+                vendor: Vendor::Raritan,
+                username: String::from("admin"),
+                password: Secret::new(""),
+                accept_invalid_certs: false,
+                timeout: default_timeout(),
+                outlets: vec![
+                    OutletConfig {
+                        outlet: 1,
+                        node: String::from("node-1"),
+                    },
+                    OutletConfig {
+                        outlet: 2,
+                        node: String::from("node-2"),
+                    },
+                ],
+            }],
+        }
+    }
+}
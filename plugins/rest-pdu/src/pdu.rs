@@ -0,0 +1,50 @@
+//! Vendor-independent access to the outlets of a PDU.
+
+use alumet::plugin::secret::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{raritan::RaritanClient, servertech::ServerTechClient};
+
+/// Vendor of a PDU, which determines the API to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Vendor {
+    /// Raritan PX PDUs (JSON-RPC API), first PDU of the daisy chain.
+    Raritan,
+    /// ServerTech PRO PDUs (JAWS REST API), first tower.
+    ServerTech,
+}
+
+/// Measurements of an outlet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutletReading {
+    /// Number of the outlet, starting at 1.
+    pub outlet: u32,
+    /// Active power, if available.
+    pub power_watts: Option<f64>,
+    /// Energy counter, if available.
+    pub energy_watt_hours: Option<f64>,
+}
+
+/// A client of the API of a PDU.
+pub enum PduClient {
+    Raritan(RaritanClient),
+    ServerTech(ServerTechClient),
+}
+
+impl PduClient {
+    pub fn new(vendor: Vendor, http: reqwest::Client, url: &str, username: String, password: Secret) -> Self {
+        match vendor {
+            Vendor::Raritan => PduClient::Raritan(RaritanClient::new(http, url, username, password)),
+            Vendor::ServerTech => PduClient::ServerTech(ServerTechClient::new(http, url, username, password)),
+        }
+    }
+
+    /// Reads the power and energy of the given outlets.
+    pub async fn read_outlets(&mut self, outlets: &[u32]) -> anyhow::Result<Vec<OutletReading>> {
+        match self {
+            PduClient::Raritan(client) => client.read_outlets(outlets).await,
+            PduClient::ServerTech(client) => client.read_outlets(outlets).await,
+        }
+    }
+}
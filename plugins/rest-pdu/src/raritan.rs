@@ -0,0 +1,227 @@
+//! Client of the JSON-RPC API of the Raritan PX PDUs.
+//!
+//! See the [JSON-RPC SDK](https://help.raritan.com/json-rpc/pdu/) of Raritan.
+
+use std::collections::BTreeMap;
+
+use alumet::plugin::secret::Secret;
+use anyhow::{Context, anyhow};
+use reqwest::Client;
+use serde_json::{Value, json};
+
+use crate::pdu::OutletReading;
+
+/// Resource id of the first PDU of the daisy chain.
+const PDU_RID: &str = "/model/pdu/0";
+/// Resource id of the bulk service, which executes several requests at once.
+const BULK_RID: &str = "/bulk";
+
+/// A client of the JSON-RPC API of a Raritan PDU.
+pub struct RaritanClient {
+    http: Client,
+    /// Base URL of the PDU, without the trailing slash, for instance `https://10.0.0.2`.
+    base_url: String,
+    username: String,
+    password: Secret,
+    /// Resource ids of the sensors of each outlet, discovered on the first measurement.
+    sensors: Option<BTreeMap<u32, OutletSensors>>,
+}
+
+/// Resource ids of the sensors of an outlet.
+#[derive(Debug, PartialEq)]
+struct OutletSensors {
+    active_power: Option<String>,
+    active_energy: Option<String>,
+}
+
+impl RaritanClient {
+    pub fn new(http: Client, base_url: &str, username: String, password: Secret) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            username,
+            password,
+            sensors: None,
+        }
+    }
+
+    /// Calls a method of a resource and returns its result (`_ret_`).
+    async fn call(&self, rid: &str, method: &str, params: Option<Value>) -> anyhow::Result<Value> {
+        let mut request = json!({ "jsonrpc": "2.0", "method": method, "id": 1 });
+        if let Some(params) = params {
+            request["params"] = params;
+        }
+        let mut response: Value = self
+            .http
+            .post(format!("{}{rid}", self.base_url))
+            .basic_auth(&self.username, Some(self.password.expose()))
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("could not call {method} on {rid}"))?
+            .json()
+            .await
+            .with_context(|| format!("invalid response to {method} on {rid}"))?;
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("{method} on {rid} failed: {error}"));
+        }
+        Ok(response["result"]["_ret_"].take())
+    }
+
+    /// Finds the sensors of the outlets (numbered from 1).
+    async fn discover(&self, outlets: &[u32]) -> anyhow::Result<BTreeMap<u32, OutletSensors>> {
+        let outlet_rids = self.call(PDU_RID, "getOutlets", None).await?;
+        let outlet_rids = outlet_rids
+            .as_array()
+            .ok_or_else(|| anyhow!("getOutlets did not return an array"))?;
+        let mut sensors = BTreeMap::new();
+        for &number in outlets {
+            let rid = outlet_rids
+                .get((number as usize).wrapping_sub(1))
+                .and_then(|o| o["rid"].as_str())
+                .ok_or_else(|| anyhow!("outlet {number} does not exist (the PDU has {})", outlet_rids.len()))?;
+            let outlet_sensors = self.call(rid, "getSensors", None).await?;
+            let sensor_rid = |name: &str| outlet_sensors[name]["rid"].as_str().map(ToOwned::to_owned);
+            sensors.insert(
+                number,
+                OutletSensors {
+                    active_power: sensor_rid("activePower"),
+                    active_energy: sensor_rid("activeEnergy"),
+                },
+            );
+        }
+        Ok(sensors)
+    }
+
+    /// Reads the power and energy of the outlets.
+    pub async fn read_outlets(&mut self, outlets: &[u32]) -> anyhow::Result<Vec<OutletReading>> {
+        if self.sensors.is_none() {
+            let sensors = self
+                .discover(outlets)
+                .await
+                .context("could not discover the outlet sensors")?;
+            self.sensors = Some(sensors);
+        }
+        let sensors = self.sensors.as_ref().unwrap();
+
+        // read all the sensors with one bulk request
+        let rids: Vec<&str> = sensors
+            .values()
+            .flat_map(|s| [s.active_power.as_deref(), s.active_energy.as_deref()])
+            .flatten()
+            .collect();
+        let requests: Vec<Value> = rids
+            .iter()
+            .map(|rid| json!({ "rid": rid, "json": { "jsonrpc": "2.0", "method": "getReading", "id": 1 } }))
+            .collect();
+        let result = self
+            .call(BULK_RID, "performBulk", Some(json!({ "requests": requests })))
+            .await?;
+        let responses = result["responses"]
+            .as_array()
+            .ok_or_else(|| anyhow!("performBulk did not return the responses"))?;
+        if responses.len() != rids.len() {
+            return Err(anyhow!(
+                "performBulk returned {} responses instead of {}",
+                responses.len(),
+                rids.len()
+            ));
+        }
+        let readings: BTreeMap<&str, f64> = rids
+            .iter()
+            .zip(responses)
+            .filter_map(|(rid, response)| {
+                let reading = &response["json"]["result"]["_ret_"];
+                // the reading is invalid when the sensor is unavailable
+                let valid = reading["valid"].as_bool().unwrap_or(false);
+                Some((*rid, reading["value"].as_f64().filter(|_| valid)?))
+            })
+            .collect();
+
+        let value_of = |rid: &Option<String>| rid.as_deref().and_then(|rid| readings.get(rid).copied());
+        Ok(sensors
+            .iter()
+            .map(|(number, s)| OutletReading {
+                outlet: *number,
+                power_watts: value_of(&s.active_power),
+                energy_watt_hours: value_of(&s.active_energy),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::{Matcher, Server};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn read_raritan_outlets() {
+        let mut server = Server::new_async().await;
+        let rpc = |method: &str| Matcher::PartialJson(json!({ "method": method }));
+        let ok = |ret: Value| json!({ "jsonrpc": "2.0", "id": 1, "result": { "_ret_": ret } }).to_string();
+
+        let outlets = server
+            .mock("POST", "/model/pdu/0")
+            .match_body(rpc("getOutlets"))
+            .with_body(ok(json!([
+                { "rid": "/tfwopaque/outlet.0", "type": "pdumodel.Outlet:2.1.5" },
+                { "rid": "/tfwopaque/outlet.1", "type": "pdumodel.Outlet:2.1.5" },
+            ])))
+            .expect(1)
+            .create_async()
+            .await;
+        let sensors = server
+            .mock("POST", "/tfwopaque/outlet.1")
+            .match_body(rpc("getSensors"))
+            .with_body(ok(json!({
+                "voltage": null,
+                "activePower": { "rid": "/tfwopaque/power.1", "type": "sensors.NumericSensor:4.0.3" },
+                "activeEnergy": { "rid": "/tfwopaque/energy.1", "type": "sensors.AccumulatingNumericSensor:2.0.3" },
+            })))
+            .expect(1)
+            .create_async()
+            .await;
+        let reading = |value: f64, valid: bool| json!({ "statcode": 200, "json": { "jsonrpc": "2.0", "id": 1, "result": { "_ret_": { "valid": valid, "value": value } } } });
+        let bulk = server
+            .mock("POST", "/bulk")
+            .match_header("authorization", "Basic YWRtaW46c2VjcmV0")
+            .match_body(Matcher::PartialJson(json!({ "params": { "requests": [
+                { "rid": "/tfwopaque/power.1" },
+                { "rid": "/tfwopaque/energy.1" },
+            ] } })))
+            .with_body(ok(json!({ "responses": [reading(120.5, true), reading(0.0, false)] })))
+            .expect(2)
+            .create_async()
+            .await;
+
+        let mut client = RaritanClient::new(Client::new(), &server.url(), "admin".into(), Secret::new("secret"));
+        for _ in 0..2 {
+            let readings = client.read_outlets(&[2]).await.unwrap();
+            assert_eq!(
+                readings,
+                vec![OutletReading {
+                    outlet: 2,
+                    power_watts: Some(120.5),
+                    energy_watt_hours: None,
+                }]
+            );
+        }
+        // the sensors are only discovered once
+        outlets.assert_async().await;
+        sensors.assert_async().await;
+        bulk.assert_async().await;
+
+        let error = client_without_outlet(&server.url()).await;
+        assert!(error.contains("outlet 3 does not exist"), "unexpected error: {error}");
+    }
+
+    async fn client_without_outlet(url: &str) -> String {
+        let mut client = RaritanClient::new(Client::new(), url, "admin".into(), Secret::new("secret"));
+        format!("{:#}", client.read_outlets(&[3]).await.unwrap_err())
+    }
+}
@@ -0,0 +1,122 @@
+//! Client of the JAWS REST API of the ServerTech (Sentry) PRO PDUs.
+
+use alumet::plugin::secret::Secret;
+use anyhow::{Context, anyhow};
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::pdu::OutletReading;
+
+/// Path of the monitoring data of the outlets.
+const OUTLETS_PATH: &str = "/jaws/monitor/outlets";
+
+/// A client of the REST API of a ServerTech PDU.
+pub struct ServerTechClient {
+    http: Client,
+    /// Base URL of the PDU, without the trailing slash, for instance `https://10.0.0.3`.
+    base_url: String,
+    username: String,
+    password: Secret,
+}
+
+/// Monitoring data of an outlet (only the fields that we need).
+#[derive(Deserialize)]
+struct JawsOutlet {
+    /// Id of the outlet, like `AA1` (tower A, input feed A, outlet 1).
+    id: String,
+    /// Active power, in Watts.
+    active_power: Option<f64>,
+    /// Energy counter, in Watt-hours.
+    energy: Option<f64>,
+}
+
+impl ServerTechClient {
+    pub fn new(http: Client, base_url: &str, username: String, password: Secret) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            username,
+            password,
+        }
+    }
+
+    /// Reads the power and energy of the outlets of the first tower (numbered from 1).
+    pub async fn read_outlets(&mut self, outlets: &[u32]) -> anyhow::Result<Vec<OutletReading>> {
+        let all_outlets: Vec<JawsOutlet> = self
+            .http
+            .get(format!("{}{OUTLETS_PATH}", self.base_url))
+            .basic_auth(&self.username, Some(self.password.expose()))
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("could not get {OUTLETS_PATH}"))?
+            .json()
+            .await
+            .with_context(|| format!("invalid response to {OUTLETS_PATH}"))?;
+
+        outlets
+            .iter()
+            .map(|&number| {
+                let id = format!("AA{number}");
+                let outlet = all_outlets
+                    .iter()
+                    .find(|o| o.id == id)
+                    .ok_or_else(|| anyhow!("outlet {id} does not exist"))?;
+                Ok(OutletReading {
+                    outlet: number,
+                    power_watts: outlet.active_power,
+                    energy_watt_hours: outlet.energy,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Server;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn read_servertech_outlets() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/jaws/monitor/outlets")
+            .match_header("authorization", "Basic YWRtaW46c2VjcmV0")
+            .with_body(
+                json!([
+                    { "id": "AA1", "name": "Master_Outlet_1", "state": "On", "active_power": 85, "energy": 12000 },
+                    { "id": "AA2", "name": "Master_Outlet_2", "state": "Off", "active_power": null, "energy": 500 },
+                    { "id": "BA1", "name": "Link_Outlet_1", "state": "On", "active_power": 40, "energy": 100 },
+                ])
+                .to_string(),
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let mut client = ServerTechClient::new(Client::new(), &server.url(), "admin".into(), Secret::new("secret"));
+        let readings = client.read_outlets(&[1, 2]).await.unwrap();
+        assert_eq!(
+            readings,
+            vec![
+                OutletReading {
+                    outlet: 1,
+                    power_watts: Some(85.0),
+                    energy_watt_hours: Some(12000.0),
+                },
+                OutletReading {
+                    outlet: 2,
+                    power_watts: None,
+                    energy_watt_hours: Some(500.0),
+                },
+            ]
+        );
+        let error = client.read_outlets(&[3]).await.unwrap_err();
+        assert_eq!(error.to_string(), "outlet AA3 does not exist");
+        mock.assert_async().await;
+    }
+}
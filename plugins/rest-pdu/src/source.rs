@@ -0,0 +1,162 @@
+use std::{collections::HashMap, time::Duration};
+
+use alumet::{
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use tokio::{sync::mpsc, time::MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::pdu::{OutletReading, PduClient};
+
+/// Number of Joules in a Watt-hour.
+const JOULES_PER_WATT_HOUR: f64 = 3600.0;
+
+/// Contains the ids of the measured metrics.
+///
+/// The metrics are the same as the ones of the `snmp-pdu` plugin.
+#[derive(Clone, Copy)]
+pub struct Metrics {
+    power: TypedMetricId<f64>,
+    energy: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            power: alumet.create_metric("pdu_outlet_power", Unit::Watt, "Active power of a PDU outlet")?,
+            energy: alumet.create_metric(
+                "pdu_outlet_energy",
+                Unit::Joule,
+                "Energy consumed by a PDU outlet since the previous measurement",
+            )?,
+        })
+    }
+}
+
+pub struct SourceSettings {
+    pub poll_interval: Duration,
+    /// Name of the PDU, added to the measurements as an attribute.
+    pub pdu: String,
+    /// The outlets to measure, and the nodes that are plugged into them.
+    pub outlets: Vec<(u32, String)>,
+}
+
+/// Measures the outlets of the PDU at regular intervals, until `cancel_token` is cancelled.
+pub async fn run(
+    mut client: PduClient,
+    metrics: Metrics,
+    settings: SourceSettings,
+    cancel_token: CancellationToken,
+    tx: mpsc::Sender<MeasurementBuffer>,
+) -> anyhow::Result<()> {
+    let numbers: Vec<u32> = settings.outlets.iter().map(|(number, _)| *number).collect();
+    let nodes: HashMap<u32, String> = settings.outlets.into_iter().collect();
+    // Previous value of the energy counter of each outlet, in Wh.
+    let mut previous_energy: HashMap<u32, f64> = HashMap::new();
+
+    let mut interval = tokio::time::interval(settings.poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            biased;
+            _ = cancel_token.cancelled() => break,
+            _ = interval.tick() => (),
+        };
+        // The PDU may be temporarily unreachable: try again at the next measurement.
+        let readings = match client.read_outlets(&numbers).await {
+            Ok(readings) => readings,
+            Err(e) => {
+                log::warn!("Measurement of PDU {} failed: {e:#}", settings.pdu);
+                continue;
+            }
+        };
+        let timestamp = Timestamp::now();
+        let mut buffer = MeasurementBuffer::with_capacity(readings.len() * 2);
+        for reading in readings {
+            let node = &nodes[&reading.outlet];
+            for (metric, value) in convert(&reading, &mut previous_energy) {
+                let metric = match metric {
+                    OutletMetric::Power => metrics.power,
+                    OutletMetric::Energy => metrics.energy,
+                };
+                buffer.push(
+                    MeasurementPoint::new(
+                        timestamp,
+                        metric,
+                        Resource::custom("node", node.clone()),
+                        ResourceConsumer::LocalMachine,
+                        value,
+                    )
+                    .with_attr("pdu", settings.pdu.clone())
+                    .with_attr("outlet", reading.outlet as u64),
+                );
+            }
+        }
+        if !buffer.is_empty() {
+            tx.send(buffer).await?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum OutletMetric {
+    Power,
+    /// Energy since the previous measurement, in Joules.
+    Energy,
+}
+
+/// Converts the reading of an outlet to the values of the metrics.
+fn convert(reading: &OutletReading, previous_energy: &mut HashMap<u32, f64>) -> Vec<(OutletMetric, f64)> {
+    let mut values = Vec::with_capacity(2);
+    if let Some(power) = reading.power_watts {
+        values.push((OutletMetric::Power, power));
+    }
+    if let Some(energy) = reading.energy_watt_hours {
+        // the counter can be reset by the PDU: skip the measurement in that case
+        if let Some(previous) = previous_energy.insert(reading.outlet, energy)
+            && energy >= previous
+        {
+            values.push((OutletMetric::Energy, (energy - previous) * JOULES_PER_WATT_HOUR));
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn convert_energy_counter() {
+        let reading = |energy: f64| OutletReading {
+            outlet: 1,
+            power_watts: Some(100.0),
+            energy_watt_hours: Some(energy),
+        };
+        let mut previous = HashMap::new();
+        assert_eq!(
+            convert(&reading(10.0), &mut previous),
+            vec![(OutletMetric::Power, 100.0)]
+        );
+        assert_eq!(
+            convert(&reading(10.5), &mut previous),
+            vec![(OutletMetric::Power, 100.0), (OutletMetric::Energy, 1800.0)]
+        );
+        // reset of the counter
+        assert_eq!(
+            convert(&reading(0.0), &mut previous),
+            vec![(OutletMetric::Power, 100.0)]
+        );
+        assert_eq!(
+            convert(&reading(0.0), &mut previous),
+            vec![(OutletMetric::Power, 100.0), (OutletMetric::Energy, 0.0)]
+        );
+    }
+}
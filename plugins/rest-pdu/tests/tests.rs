@@ -0,0 +1,63 @@
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    plugin::{PluginMetadata, secret::Secret},
+    test::StartupExpectations,
+    units::Unit,
+};
+use plugin_rest_pdu::{Config, OutletConfig, PduConfig, RestPduPlugin, Vendor};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const PLUGIN_NAME: &str = "rest-pdu";
+
+#[test]
+fn plugin_with_unreachable_pdu() {
+    // nothing listens on the discard port: the measurements fail, but the agent keeps running
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        pdus: vec![PduConfig {
+            url: String::from("http://127.0.0.1:9"),
+            vendor: Vendor::ServerTech,
+            username: String::from("admin"),
+            password: Secret::new("admin"),
+            accept_invalid_certs: false,
+            timeout: Duration::from_secs(1),
+            outlets: vec![OutletConfig {
+                outlet: 1,
+                node: String::from("node-1"),
+            }],
+        }],
+    };
+
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("pdu_outlet_power", Unit::Watt)
+        .expect_metric::<f64>("pdu_outlet_energy", Unit::Joule)
+        .expect_source(PLUGIN_NAME, "127.0.0.1:9");
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup_expectation)
+        .build_and_start()
+        .unwrap();
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+#[test]
+fn plugin_without_pdu() {
+    let config = Config {
+        poll_interval: Duration::from_secs(1),
+        pdus: Vec::new(),
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no PDU)");
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<RestPduPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
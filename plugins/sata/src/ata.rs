@@ -0,0 +1,183 @@
+//! Access to the SMART data of the ATA disks through the `HDIO_DRIVE_CMD` ioctl (`/dev/sda`).
+//!
+//! The ioctl is implemented by libata for the SATA disks attached to the SCSI subsystem.
+
+use std::{
+    fs::File,
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+    path::{Path, PathBuf},
+};
+
+// Definitions from linux/hdreg.h and the ATA specification
+
+/// Request code of the ioctl that sends a command to the drive.
+const HDIO_DRIVE_CMD: u32 = 0x031f;
+/// Command "SMART".
+const ATA_SMART_CMD: u8 = 0xb0;
+/// Feature "SMART READ DATA".
+const ATA_SMART_READ_VALUES: u8 = 0xd0;
+/// Size of a sector, which contains the SMART data.
+pub const SMART_DATA_SIZE: usize = 512;
+/// Size of the header of the `HDIO_DRIVE_CMD` buffer.
+const DRIVE_CMD_HEADER_SIZE: usize = 4;
+/// Number of attributes in the SMART data.
+const SMART_ATTRIBUTES_COUNT: usize = 30;
+/// Size of an attribute in the SMART data.
+const SMART_ATTRIBUTE_SIZE: usize = 12;
+
+/// Ids of the SMART attributes that we use. They are not standardized, but widely used by the vendors.
+pub mod attribute {
+    pub const REALLOCATED_SECTORS: u8 = 5;
+    pub const POWER_ON_HOURS: u8 = 9;
+    pub const POWER_CYCLES: u8 = 12;
+    pub const AIRFLOW_TEMPERATURE: u8 = 190;
+    pub const TEMPERATURE: u8 = 194;
+    pub const PENDING_SECTORS: u8 = 197;
+}
+
+nix::ioctl_readwrite_bad!(
+    hdio_drive_cmd,
+    HDIO_DRIVE_CMD,
+    [u8; DRIVE_CMD_HEADER_SIZE + SMART_DATA_SIZE]
+);
+
+/// An ATA disk, like `/dev/sda`.
+pub struct AtaDisk {
+    /// Name of the disk, like `sda`.
+    pub name: String,
+    file: File,
+}
+
+/// Attributes of the SMART data: raw value of each attribute, by id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmartAttributes(Vec<(u8, u64)>);
+
+impl AtaDisk {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        // O_NONBLOCK allows to open the disk even if there is no medium
+        let file = File::options()
+            .read(true)
+            .custom_flags(nix::libc::O_NONBLOCK)
+            .open(path)?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        Ok(Self { name, file })
+    }
+
+    /// Reads the SMART attributes of the disk.
+    pub fn smart_attributes(&self) -> std::io::Result<SmartAttributes> {
+        let mut buf = [0u8; DRIVE_CMD_HEADER_SIZE + SMART_DATA_SIZE];
+        // command, sector number, feature, sector count
+        buf[..DRIVE_CMD_HEADER_SIZE].copy_from_slice(&[ATA_SMART_CMD, 0, ATA_SMART_READ_VALUES, 1]);
+        // SAFETY: the buffer has the size expected by the kernel for a command that returns one sector
+        unsafe { hdio_drive_cmd(self.file.as_raw_fd(), &mut buf) }.map_err(std::io::Error::from)?;
+        let data: &[u8; SMART_DATA_SIZE] = buf[DRIVE_CMD_HEADER_SIZE..].try_into().unwrap();
+        Ok(SmartAttributes::parse(data))
+    }
+}
+
+impl SmartAttributes {
+    /// Parses the SMART data (little endian).
+    pub fn parse(data: &[u8; SMART_DATA_SIZE]) -> Self {
+        // The data begins with a revision number (2 bytes), followed by the attributes.
+        let attributes = data[2..2 + SMART_ATTRIBUTES_COUNT * SMART_ATTRIBUTE_SIZE]
+            .chunks_exact(SMART_ATTRIBUTE_SIZE)
+            .filter(|attr| attr[0] != 0)
+            .map(|attr| {
+                // id, flags (2 bytes), normalized value, worst value, raw value (6 bytes), reserved
+                let mut raw = [0u8; 8];
+                raw[..6].copy_from_slice(&attr[5..11]);
+                (attr[0], u64::from_le_bytes(raw))
+            })
+            .collect();
+        Self(attributes)
+    }
+
+    /// Returns the raw value of an attribute, if the disk provides it.
+    pub fn raw(&self, id: u8) -> Option<u64> {
+        self.0.iter().find(|(attr_id, _)| *attr_id == id).map(|(_, raw)| *raw)
+    }
+
+    /// Returns the temperature of the disk, in °C.
+    pub fn temperature(&self) -> Option<u64> {
+        // Only the lowest byte contains the current temperature, the others may contain the min/max.
+        self.raw(attribute::TEMPERATURE)
+            .or_else(|| self.raw(attribute::AIRFLOW_TEMPERATURE))
+            .map(|raw| raw & 0xff)
+    }
+
+    /// Returns the number of hours during which the disk has been powered on.
+    pub fn power_on_hours(&self) -> Option<u64> {
+        // Some vendors store additional data (like minutes) in the highest bytes.
+        self.raw(attribute::POWER_ON_HOURS).map(|raw| raw & 0xffff_ffff)
+    }
+}
+
+/// Lists the ATA disks of the machine, with the sysfs.
+///
+/// The disks attached by libata are SCSI disks whose vendor is `ATA`.
+pub fn find_disks(sys_block: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut disks = Vec::new();
+    for entry in std::fs::read_dir(sys_block)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if !name.to_string_lossy().starts_with("sd") {
+            continue;
+        }
+        let vendor = std::fs::read_to_string(entry.path().join("device/vendor")).unwrap_or_default();
+        if vendor.trim() == "ATA" {
+            disks.push(Path::new("/dev").join(name));
+        }
+    }
+    disks.sort();
+    Ok(disks)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn write_attribute(data: &mut [u8; SMART_DATA_SIZE], index: usize, id: u8, raw: u64) {
+        let offset = 2 + index * SMART_ATTRIBUTE_SIZE;
+        data[offset] = id;
+        data[offset + 3] = 100; // normalized value, ignored
+        data[offset + 5..offset + 11].copy_from_slice(&raw.to_le_bytes()[..6]);
+    }
+
+    #[test]
+    fn parse_smart_data() {
+        let mut data = [0u8; SMART_DATA_SIZE];
+        write_attribute(&mut data, 0, attribute::REALLOCATED_SECTORS, 8);
+        write_attribute(&mut data, 1, attribute::POWER_ON_HOURS, 0x0012_0000_3039);
+        write_attribute(&mut data, 2, attribute::POWER_CYCLES, 1500);
+        // current temperature 36°C, min 20°C, max 51°C
+        write_attribute(&mut data, 3, attribute::TEMPERATURE, 0x0033_0014_0024);
+
+        let attributes = SmartAttributes::parse(&data);
+        assert_eq!(attributes.raw(attribute::REALLOCATED_SECTORS), Some(8));
+        assert_eq!(attributes.raw(attribute::POWER_CYCLES), Some(1500));
+        assert_eq!(attributes.raw(attribute::PENDING_SECTORS), None);
+        assert_eq!(attributes.power_on_hours(), Some(12345));
+        assert_eq!(attributes.temperature(), Some(36));
+    }
+
+    #[test]
+    fn parse_airflow_temperature() {
+        let mut data = [0u8; SMART_DATA_SIZE];
+        write_attribute(&mut data, 0, attribute::AIRFLOW_TEMPERATURE, 0x0000_2d13_001c);
+        assert_eq!(SmartAttributes::parse(&data).temperature(), Some(28));
+    }
+
+    #[test]
+    fn find_ata_disks() -> std::io::Result<()> {
+        let root = tempfile::tempdir()?;
+        for (disk, vendor) in [("sda", "ATA     \n"), ("sdb", "QEMU    \n"), ("nvme0n1", "")] {
+            let device = root.path().join(disk).join("device");
+            std::fs::create_dir_all(&device)?;
+            std::fs::write(device.join("vendor"), vendor)?;
+        }
+        assert_eq!(find_disks(root.path())?, vec![PathBuf::from("/dev/sda")]);
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::{
+    ata::AtaDisk,
+    source::{Metrics, SataSource},
+};
+
+mod ata;
+mod source;
+
+/// Directory that contains the block devices in the sysfs.
+const SYS_BLOCK: &str = "/sys/block";
+
+pub struct SataPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for SataPlugin {
+    fn name() -> &'static str {
+        "sata"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(SataPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let paths = if self.config.devices.is_empty() {
+            ata::find_disks(Path::new(SYS_BLOCK))
+                .with_context(|| format!("could not list the ATA disks in {SYS_BLOCK}"))?
+        } else {
+            self.config.devices.clone()
+        };
+
+        // open the disks and check that we can read their SMART data
+        let mut disks = Vec::with_capacity(paths.len());
+        for path in paths {
+            let disk = match AtaDisk::open(&path) {
+                Ok(d) => d,
+                Err(e) => {
+                    log::warn!("Could not open ATA disk {path:?}, it will not be measured: {e}");
+                    continue;
+                }
+            };
+            match disk.smart_attributes() {
+                Ok(_) => {
+                    log::info!("Found ATA disk {}", disk.name);
+                    disks.push(disk);
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Could not read the SMART data of {path:?} (root privileges are usually required, and SMART must be enabled), it will not be measured: {e}"
+                    );
+                }
+            }
+        }
+        if disks.is_empty() {
+            return Err(anyhow!("nothing to measure: no ATA disk is available"));
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = SataSource::new(disks, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("smart", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Paths to the ATA disks to measure, like `/dev/sda`.
+    /// If empty, all the ATA disks are measured.
+    pub devices: Vec<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(10),
+            devices: Vec::new(),
+        }
+    }
+}
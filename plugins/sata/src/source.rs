@@ -0,0 +1,106 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::Context;
+
+use crate::ata::{AtaDisk, attribute};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    temperature: TypedMetricId<u64>,
+    reallocated_sectors: TypedMetricId<u64>,
+    pending_sectors: TypedMetricId<u64>,
+    power_on_time: TypedMetricId<u64>,
+    power_cycles: TypedMetricId<u64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            temperature: alumet.create_metric("sata_temperature", Unit::DegreeCelsius, "Temperature of the disk")?,
+            reallocated_sectors: alumet.create_metric(
+                "sata_reallocated_sectors",
+                Unit::Unity,
+                "Number of sectors that have been reallocated because of errors",
+            )?,
+            pending_sectors: alumet.create_metric(
+                "sata_pending_sectors",
+                Unit::Unity,
+                "Number of unstable sectors that are waiting to be reallocated",
+            )?,
+            power_on_time: alumet.create_metric(
+                "sata_power_on_time",
+                Unit::Second,
+                "Time during which the disk has been powered on",
+            )?,
+            power_cycles: alumet.create_metric(
+                "sata_power_cycles",
+                Unit::Unity,
+                "Number of power cycles of the disk",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the SMART attributes of ATA disks.
+pub struct SataSource {
+    disks: Vec<AtaDisk>,
+    metrics: Metrics,
+}
+
+impl SataSource {
+    pub fn new(disks: Vec<AtaDisk>, metrics: Metrics) -> Self {
+        Self { disks, metrics }
+    }
+}
+
+impl Source for SataSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for disk in &self.disks {
+            let name = &disk.name;
+            let attributes = disk
+                .smart_attributes()
+                .with_context(|| format!("failed to read the SMART data of {name}"))?;
+
+            let resource = Resource::Custom {
+                kind: "sata".into(),
+                id: name.clone().into(),
+            };
+            let point = |metric, value: u64| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    resource.clone(),
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+            };
+
+            // The attributes are vendor-specific: only push the ones that the disk provides.
+            let values = [
+                (self.metrics.temperature, attributes.temperature()),
+                (
+                    self.metrics.reallocated_sectors,
+                    attributes.raw(attribute::REALLOCATED_SECTORS),
+                ),
+                (self.metrics.pending_sectors, attributes.raw(attribute::PENDING_SECTORS)),
+                (
+                    self.metrics.power_on_time,
+                    attributes.power_on_hours().map(|h| h * 3600),
+                ),
+                (self.metrics.power_cycles, attributes.raw(attribute::POWER_CYCLES)),
+            ];
+            for (metric, value) in values {
+                if let Some(value) = value {
+                    measurements.push(point(metric, value));
+                }
+            }
+        }
+        Ok(())
+    }
+}
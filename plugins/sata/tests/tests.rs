@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    plugin::PluginMetadata,
+};
+use plugin_sata::{Config, SataPlugin};
+
+#[test]
+fn plugin_without_device() {
+    let config = Config {
+        devices: vec![PathBuf::from("/dev/nonexistent-ata-disk")],
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no ATA disk)");
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<SataPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -0,0 +1,227 @@
+//! Conversion between the measurement points and the values of the scripts.
+//!
+//! In a script, a measurement point is an object map:
+//! ```text
+//! #{
+//!     metric: "cpu_time_delta",
+//!     value: 12,
+//!     timestamp: 1700000000123456789, // nanoseconds since the Unix epoch
+//!     resource: #{ kind: "cpu_core", id: "0" },
+//!     consumer: #{ kind: "local_machine", id: "" },
+//!     attributes: #{ domain: "package" },
+//! }
+//! ```
+
+use alumet::{
+    measurement::{AttributeValue, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+    metrics::RawMetricId,
+    resources::{Resource, ResourceConsumer},
+};
+use anyhow::{Context, anyhow};
+use rhai::{Dynamic, INT, Map};
+
+/// Converts a point to give it to a script.
+pub fn point_to_map(point: &MeasurementPoint, metric: &str) -> Map {
+    let (secs, nanos) = point.timestamp.to_unix_timestamp();
+    let timestamp = secs * 1_000_000_000 + u64::from(nanos);
+    let value = match point.value {
+        WrappedMeasurementValue::U64(v) => u64_to_dynamic(v),
+        WrappedMeasurementValue::F64(v) => Dynamic::from_float(v),
+    };
+    let attributes: Map = point
+        .attributes()
+        .map(|(key, value)| {
+            let value = match value {
+                AttributeValue::U64(v) => u64_to_dynamic(*v),
+                AttributeValue::F64(v) => Dynamic::from_float(*v),
+                AttributeValue::Bool(v) => Dynamic::from_bool(*v),
+                other => Dynamic::from(other.to_string()),
+            };
+            (key.into(), value)
+        })
+        .collect();
+
+    let mut map = Map::new();
+    map.insert("metric".into(), Dynamic::from(metric.to_owned()));
+    map.insert("value".into(), value);
+    map.insert("timestamp".into(), u64_to_dynamic(timestamp));
+    map.insert(
+        "resource".into(),
+        resource_map(point.resource.kind(), point.resource.id_string()),
+    );
+    map.insert(
+        "consumer".into(),
+        resource_map(point.consumer.kind(), point.consumer.id_string()),
+    );
+    map.insert("attributes".into(), Dynamic::from_map(attributes));
+    map
+}
+
+/// Converts a point returned by a script.
+///
+/// `metric_id` finds the id of a metric by its name.
+/// The resource, the consumer and the attributes are optional.
+pub fn point_from_map(map: Map, metric_id: impl Fn(&str) -> Option<RawMetricId>) -> anyhow::Result<MeasurementPoint> {
+    let metric = string_field(&map, "metric")?;
+    let metric = metric_id(&metric).with_context(|| format!("unknown metric {metric}"))?;
+    let timestamp =
+        dynamic_to_u64(map.get("timestamp").context("missing field timestamp")?).context("invalid timestamp")?;
+    let timestamp = Timestamp::from_unix_timestamp(timestamp / 1_000_000_000, (timestamp % 1_000_000_000) as u32);
+    let value = map.get("value").context("missing field value")?;
+    let value = if value.is_float() {
+        WrappedMeasurementValue::F64(value.as_float().unwrap())
+    } else {
+        WrappedMeasurementValue::U64(dynamic_to_u64(value).context("invalid value")?)
+    };
+
+    let resource = match map.get("resource") {
+        Some(r) => {
+            let (kind, id) = resource_from_dynamic(r).context("invalid resource")?;
+            Resource::parse(kind, id)?
+        }
+        None => Resource::LocalMachine,
+    };
+    let consumer = match map.get("consumer") {
+        Some(c) => {
+            let (kind, id) = resource_from_dynamic(c).context("invalid consumer")?;
+            ResourceConsumer::parse(kind, id)?
+        }
+        None => ResourceConsumer::LocalMachine,
+    };
+
+    let mut attributes = Vec::new();
+    if let Some(attrs) = map.get("attributes") {
+        let attrs = attrs
+            .read_lock::<Map>()
+            .context("invalid attributes: expected an object map")?;
+        for (key, value) in attrs.iter() {
+            let value = if value.is_bool() {
+                AttributeValue::Bool(value.as_bool().unwrap())
+            } else if value.is_float() {
+                AttributeValue::F64(value.as_float().unwrap())
+            } else if value.is_int() {
+                AttributeValue::U64(dynamic_to_u64(value).with_context(|| format!("invalid attribute {key}"))?)
+            } else if value.is_string() {
+                AttributeValue::String(value.clone().into_string().unwrap())
+            } else {
+                return Err(anyhow!(
+                    "invalid attribute {key}: unsupported type {}",
+                    value.type_name()
+                ));
+            };
+            attributes.push((key.to_string(), value));
+        }
+    }
+    Ok(MeasurementPoint::new_untyped(timestamp, metric, resource, consumer, value).with_attr_vec(attributes))
+}
+
+fn string_field(map: &Map, key: &str) -> anyhow::Result<String> {
+    let value = map.get(key).with_context(|| format!("missing field {key}"))?;
+    value
+        .clone()
+        .into_string()
+        .map_err(|t| anyhow!("invalid field {key}: expected a string, got {t}"))
+}
+
+fn resource_map(kind: &str, id: Option<String>) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("kind".into(), Dynamic::from(kind.to_owned()));
+    map.insert("id".into(), Dynamic::from(id.unwrap_or_default()));
+    Dynamic::from_map(map)
+}
+
+fn resource_from_dynamic(value: &Dynamic) -> anyhow::Result<(String, String)> {
+    let map = value.read_lock::<Map>().context("expected an object map")?;
+    let kind = string_field(&map, "kind")?;
+    // the id can be omitted, for instance for the local machine
+    let id = match map.get("id") {
+        Some(id) if id.is_int() => id.as_int().unwrap().to_string(),
+        Some(_) => string_field(&map, "id")?,
+        None => String::new(),
+    };
+    Ok((kind, id))
+}
+
+/// Scripts use signed integers: the (rare) values that do not fit are converted to floats.
+fn u64_to_dynamic(v: u64) -> Dynamic {
+    match INT::try_from(v) {
+        Ok(v) => Dynamic::from_int(v),
+        Err(_) => Dynamic::from_float(v as f64),
+    }
+}
+
+fn dynamic_to_u64(value: &Dynamic) -> anyhow::Result<u64> {
+    let v = value.as_int().map_err(|t| anyhow!("expected an integer, got {t}"))?;
+    u64::try_from(v).map_err(|_| anyhow!("expected a positive integer, got {v}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use alumet::{
+        measurement::{AttributeValue, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+        metrics::RawMetricId,
+        resources::{Resource, ResourceConsumer},
+    };
+    use rhai::{Dynamic, Map};
+
+    use super::{point_from_map, point_to_map};
+
+    #[test]
+    fn point_roundtrip() {
+        let id = RawMetricId::from_u64(7);
+        let point = MeasurementPoint::new_untyped(
+            Timestamp::from_unix_timestamp(1_700_000_000, 123_456_789),
+            id,
+            Resource::CpuPackage { id: 0 },
+            ResourceConsumer::Process { pid: 42 },
+            WrappedMeasurementValue::U64(12),
+        )
+        .with_attr("domain", "package")
+        .with_attr("ratio", 0.5)
+        .with_attr("rapl", true);
+
+        let map = point_to_map(&point, "energy");
+        assert_eq!(map["metric"].clone().into_string().unwrap(), "energy");
+        assert_eq!(map["value"].as_int().unwrap(), 12);
+        assert_eq!(map["timestamp"].as_int().unwrap(), 1_700_000_000_123_456_789);
+
+        let back = point_from_map(map, |name| (name == "energy").then_some(id)).unwrap();
+        assert_eq!(back.timestamp, point.timestamp);
+        assert_eq!(back.metric, id);
+        assert_eq!(back.resource, point.resource);
+        assert_eq!(back.consumer, point.consumer);
+        assert_eq!(back.value, point.value);
+        let mut attributes: Vec<_> = back.attributes().collect();
+        attributes.sort_by_key(|(k, _)| *k);
+        assert_eq!(
+            attributes,
+            vec![
+                ("domain", &AttributeValue::String(String::from("package"))),
+                ("rapl", &AttributeValue::Bool(true)),
+                ("ratio", &AttributeValue::F64(0.5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn minimal_point() {
+        let id = RawMetricId::from_u64(1);
+        let mut map = Map::new();
+        map.insert("metric".into(), Dynamic::from("power".to_owned()));
+        map.insert("value".into(), Dynamic::from_float(2.5));
+        map.insert("timestamp".into(), Dynamic::from_int(1_000_000_001));
+
+        let point = point_from_map(map.clone(), |_| Some(id)).unwrap();
+        assert_eq!(point.timestamp, Timestamp::from_unix_timestamp(1, 1));
+        assert_eq!(point.value, WrappedMeasurementValue::F64(2.5));
+        assert_eq!(point.resource, Resource::LocalMachine);
+        assert_eq!(point.consumer, ResourceConsumer::LocalMachine);
+        assert_eq!(point.attributes_len(), 0);
+
+        // unknown metric
+        assert!(point_from_map(map.clone(), |_| None).is_err());
+        // negative value
+        map.insert("value".into(), Dynamic::from_int(-1));
+        assert!(point_from_map(map, |_| Some(id)).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! Transforms written in [Rhai](https://rhai.rs), an embedded scripting language.
+//!
+//! Each transform is a script file that defines a function to apply to every point,
+//! or a function to apply to every buffer (see [`transform`]).
+//! The scripts are reloaded when they are modified, without restarting the agent.
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use alumet::{
+    measurement::WrappedMeasurementType,
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+    units::PrefixedUnit,
+};
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+
+mod convert;
+mod transform;
+
+use transform::ScriptTransform;
+
+pub struct ScriptPlugin {
+    config: Config,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// How often the scripts are checked for modifications.
+    #[serde(with = "humantime_serde")]
+    pub reload_interval: Duration,
+    /// Metrics created for the scripts, for instance to store derived values.
+    #[serde(default)]
+    pub metrics: Vec<MetricConfig>,
+    /// The transforms to add to the pipeline.
+    pub transforms: Vec<TransformConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MetricConfig {
+    pub name: String,
+    /// Unit of the metric, as a UCUM code such as `W` or `mJ`.
+    pub unit: String,
+    pub description: String,
+    pub value_type: ValueType,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    U64,
+    F64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TransformConfig {
+    /// Name of the transform.
+    pub name: String,
+    /// Path to the script (`.rhai` file).
+    pub script: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            reload_interval: Duration::from_secs(2),
+            metrics: Vec::new(),
+            transforms: Vec::new(),
+        }
+    }
+}
+
+impl From<ValueType> for WrappedMeasurementType {
+    fn from(value: ValueType) -> Self {
+        match value {
+            ValueType::U64 => WrappedMeasurementType::U64,
+            ValueType::F64 => WrappedMeasurementType::F64,
+        }
+    }
+}
+
+impl AlumetPlugin for ScriptPlugin {
+    fn name() -> &'static str {
+        "script"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        Ok(Some(serialize_config(Config::default())?))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(Self { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        if self.config.transforms.is_empty() {
+            log::warn!("No script to load.");
+        }
+        for metric in &self.config.metrics {
+            let unit = PrefixedUnit::from_str(&metric.unit)
+                .map_err(|e| anyhow!("invalid unit '{}' for metric {}: {e}", metric.unit, metric.name))?;
+            alumet.create_metric_untyped(&metric.name, metric.value_type.into(), unit, &metric.description)?;
+        }
+        for t in &self.config.transforms {
+            let transform = ScriptTransform::load(t.script.clone(), self.config.reload_interval)
+                .with_context(|| format!("failed to load the script of transform {}", t.name))?;
+            alumet.add_transform(&t.name, Box::new(transform))?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use alumet::{
+    measurement::MeasurementBuffer,
+    pipeline::{
+        Transform,
+        elements::{error::TransformError, transform::TransformContext},
+    },
+};
+use anyhow::{Context, anyhow};
+use rhai::{AST, Array, CallFnOptions, Dynamic, Engine, Map, Scope};
+
+use crate::convert::{point_from_map, point_to_map};
+
+/// Name of the function that processes the points one by one.
+pub const POINT_FUNCTION: &str = "process";
+/// Name of the function that processes whole buffers.
+pub const BUFFER_FUNCTION: &str = "process_buffer";
+
+/// A transform that applies a script to the measurements.
+///
+/// The script is reloaded when its file is modified.
+pub struct ScriptTransform {
+    engine: Engine,
+    script: Script,
+    reload_interval: Duration,
+    last_check: Instant,
+}
+
+/// A compiled script.
+struct Script {
+    path: PathBuf,
+    /// Modification time of the file, when it was loaded.
+    modified: Option<SystemTime>,
+    ast: AST,
+    /// Scope in which the top-level statements of the script ran.
+    scope: Scope<'static>,
+    mode: Mode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// `process(point)` returns the transformed point, `()` to remove it, or an array of points.
+    PerPoint,
+    /// `process_buffer(points)` returns an array of points.
+    PerBuffer,
+}
+
+impl ScriptTransform {
+    pub fn load(path: PathBuf, reload_interval: Duration) -> anyhow::Result<Self> {
+        let mut engine = Engine::new();
+        engine.on_print(|s| log::info!("{s}"));
+        engine.on_debug(|s, _, pos| log::debug!("{pos}: {s}"));
+        let script = Script::load(&engine, path)?;
+        Ok(Self {
+            engine,
+            script,
+            reload_interval,
+            last_check: Instant::now(),
+        })
+    }
+
+    /// Reloads the script if its file has been modified.
+    ///
+    /// If the new version of the script is invalid, the previous version is kept.
+    fn reload_if_modified(&mut self) {
+        if self.last_check.elapsed() < self.reload_interval {
+            return;
+        }
+        self.last_check = Instant::now();
+        let path = &self.script.path;
+        let modified = modification_time(path);
+        if modified == self.script.modified {
+            return;
+        }
+        match Script::load(&self.engine, path.clone()) {
+            Ok(script) => {
+                log::info!("Script {} reloaded.", path.display());
+                self.script = script;
+            }
+            Err(e) => {
+                log::error!(
+                    "Could not reload script {}, keeping the previous version: {e:#}",
+                    path.display()
+                );
+                self.script.modified = modified;
+            }
+        }
+    }
+
+    fn call(&mut self, function: &str, arg: Dynamic) -> anyhow::Result<Dynamic> {
+        // The top-level statements of the script have been executed when loading it, don't run them again.
+        let options = CallFnOptions::new().eval_ast(false);
+        let script = &mut self.script;
+        self.engine
+            .call_fn_with_options(options, &mut script.scope, &script.ast, function, (arg,))
+            .map_err(|e| anyhow!("error in {function} ({}): {e}", script.path.display()))
+    }
+}
+
+impl Script {
+    fn load(engine: &Engine, path: PathBuf) -> anyhow::Result<Self> {
+        let modified = modification_time(&path);
+        let ast = engine
+            .compile_file(path.clone())
+            .map_err(|e| anyhow!("failed to compile {}: {e}", path.display()))?;
+        let has_fn = |name: &str| ast.iter_functions().any(|f| f.name == name && f.params.len() == 1);
+        let mode = match (has_fn(POINT_FUNCTION), has_fn(BUFFER_FUNCTION)) {
+            (true, false) => Mode::PerPoint,
+            (false, true) => Mode::PerBuffer,
+            (true, true) => {
+                return Err(anyhow!(
+                    "{} defines both {POINT_FUNCTION}(point) and {BUFFER_FUNCTION}(points), only one of them is allowed",
+                    path.display()
+                ));
+            }
+            (false, false) => {
+                return Err(anyhow!(
+                    "{} must define {POINT_FUNCTION}(point) or {BUFFER_FUNCTION}(points)",
+                    path.display()
+                ));
+            }
+        };
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| anyhow!("failed to run {}: {e}", path.display()))?;
+        Ok(Self {
+            path,
+            modified,
+            ast,
+            scope,
+            mode,
+        })
+    }
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Appends the point(s) returned by a script to `output`.
+fn push_returned(returned: Dynamic, ctx: &TransformContext, output: &mut MeasurementBuffer) -> anyhow::Result<()> {
+    let metric_id = |name: &str| ctx.metrics.by_name(name).map(|(id, _)| id);
+    if returned.is_unit() {
+        Ok(())
+    } else if returned.is_map() {
+        output.push(point_from_map(returned.cast::<Map>(), metric_id)?);
+        Ok(())
+    } else if returned.is_array() {
+        for point in returned.cast::<Array>() {
+            let map = point
+                .try_cast::<Map>()
+                .context("invalid point: expected an object map")?;
+            output.push(point_from_map(map, metric_id)?);
+        }
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "unexpected value of type {} returned by the script",
+            returned.type_name()
+        ))
+    }
+}
+
+impl Transform for ScriptTransform {
+    fn apply(&mut self, measurements: &mut MeasurementBuffer, ctx: &TransformContext) -> Result<(), TransformError> {
+        self.reload_if_modified();
+
+        let mut maps = Vec::with_capacity(measurements.len());
+        for point in measurements.iter() {
+            let metric = ctx
+                .metrics
+                .by_id(&point.metric)
+                .with_context(|| format!("unknown metric {:?}", point.metric))
+                .map_err(TransformError::UnexpectedInput)?;
+            maps.push(Dynamic::from_map(point_to_map(point, &metric.name)));
+        }
+
+        let mut output = MeasurementBuffer::with_capacity(measurements.len());
+        let res = match self.script.mode {
+            Mode::PerPoint => maps.into_iter().try_for_each(|point| {
+                let returned = self.call(POINT_FUNCTION, point)?;
+                push_returned(returned, ctx, &mut output)
+            }),
+            Mode::PerBuffer => self
+                .call(BUFFER_FUNCTION, Dynamic::from_array(maps))
+                .and_then(|returned| push_returned(returned, ctx, &mut output)),
+        };
+        res.map_err(TransformError::UnexpectedInput)?;
+        *measurements = output;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use super::{Mode, ScriptTransform};
+
+    #[test]
+    fn reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.rhai");
+        fs::write(&path, "fn process(point) { point }").unwrap();
+
+        let mut transform = ScriptTransform::load(path.clone(), Duration::ZERO).unwrap();
+        assert_eq!(transform.script.mode, Mode::PerPoint);
+
+        // invalid script: the previous version is kept
+        fs::write(&path, "fn process_buffer(points) {").unwrap();
+        transform.script.modified = None;
+        transform.reload_if_modified();
+        assert_eq!(transform.script.mode, Mode::PerPoint);
+
+        fs::write(&path, "fn process_buffer(points) { points }").unwrap();
+        transform.script.modified = None;
+        transform.reload_if_modified();
+        assert_eq!(transform.script.mode, Mode::PerBuffer);
+    }
+
+    #[test]
+    fn invalid_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.rhai");
+        fs::write(&path, "fn other(point) { point }").unwrap();
+        assert!(ScriptTransform::load(path.clone(), Duration::ZERO).is_err());
+
+        fs::write(
+            &path,
+            "fn process(point) { point } fn process_buffer(points) { points }",
+        )
+        .unwrap();
+        assert!(ScriptTransform::load(path, Duration::ZERO).is_err());
+    }
+}
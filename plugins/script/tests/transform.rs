@@ -0,0 +1,79 @@
+//! Integration tests for the script transform.
+
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{
+        self,
+        plugin::{PluginInfo, PluginSet},
+    },
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+    metrics::RawMetricId,
+    pipeline::naming::TransformName,
+    plugin::PluginMetadata,
+    resources::{Resource, ResourceConsumer},
+    test::RuntimeExpectations,
+    units::Unit,
+};
+use plugin_script::ScriptPlugin;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[test]
+fn derive_and_filter() {
+    let _ = env_logger::Builder::from_default_env().try_init();
+    let transform = TransformName::from_str("script", "derive");
+    let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/derive.rhai");
+    let config = format!(
+        r#"
+        reload_interval = "1s"
+        metrics = [{{ name = "energy", unit = "J", description = "energy derived from the power", value_type = "f64" }}]
+        transforms = [{{ name = "derive", script = '{}' }}]
+        "#,
+        script.display()
+    );
+
+    fn point(metric: RawMetricId, pid: u32, value: f64) -> MeasurementPoint {
+        MeasurementPoint::new_untyped(
+            Timestamp::from_unix_timestamp(1_700_000_000, 0),
+            metric,
+            Resource::CpuPackage { id: 0 },
+            ResourceConsumer::Process { pid },
+            WrappedMeasurementValue::F64(value),
+        )
+        .with_attr("domain", String::from("package"))
+    }
+
+    let runtime = RuntimeExpectations::new()
+        .create_metric::<f64>("power", Unit::Watt)
+        .test_transform(
+            transform,
+            |input| {
+                let power = input.metrics().by_name("power").unwrap().0;
+                let mut buf = MeasurementBuffer::new();
+                buf.push(point(power, 0, 10.0));
+                buf.push(point(power, 1, 3.0));
+                buf
+            },
+            |output| {
+                let power = output.metrics().by_name("power").unwrap().0;
+                let energy = output.metrics().by_name("energy").unwrap().0;
+                assert_eq!(
+                    output.measurements().to_vec(),
+                    vec![point(power, 1, 3.0), point(energy, 1, 6.0)]
+                );
+            },
+        );
+
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<ScriptPlugin>(),
+        enabled: true,
+        config: Some(toml::from_str(&config).unwrap()),
+    });
+    let agent = agent::Builder::new(plugins)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
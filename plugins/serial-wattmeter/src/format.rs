@@ -0,0 +1,242 @@
+//! Parsers of the lines sent by the wattmeters.
+
+use anyhow::{Context, anyhow};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Format of the lines sent by a wattmeter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Watts up? PRO, in external logging mode.
+    WattsUp,
+    /// ODROID SmartPower3, in logging mode.
+    SmartPower3,
+    /// Regular expression, given in the configuration.
+    Custom,
+}
+
+/// Regular expression that extracts the values from a line, for the wattmeters that are not supported out of the box.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomFormat {
+    /// Regular expression with the named groups `power` (required), `voltage` and `current` (optional).
+    pub regex: String,
+    /// Factor that converts the power value to Watts.
+    #[serde(default = "default_scale")]
+    pub power_scale: f64,
+    /// Factor that converts the voltage value to Volts.
+    #[serde(default = "default_scale")]
+    pub voltage_scale: f64,
+    /// Factor that converts the current value to Amperes.
+    #[serde(default = "default_scale")]
+    pub current_scale: f64,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// Values measured by a wattmeter, on one of its channels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    /// Channel of the wattmeter, for the wattmeters that measure several outputs.
+    pub channel: Option<&'static str>,
+    pub power_watts: f64,
+    pub voltage_volts: Option<f64>,
+    pub current_amperes: Option<f64>,
+}
+
+/// Parses the lines of a wattmeter.
+pub trait LineParser: Send {
+    /// Command to send to the wattmeter after opening the port, to start the measurements.
+    fn init_command(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Parses a line, without the line terminator.
+    ///
+    /// Returns an empty vec if the line does not contain any measurement.
+    fn parse(&self, line: &str) -> Vec<Reading>;
+}
+
+/// Creates the parser of the given format.
+pub fn parser(format: Format, custom: Option<&CustomFormat>) -> anyhow::Result<Box<dyn LineParser>> {
+    match (format, custom) {
+        (Format::WattsUp, None) => Ok(Box::new(WattsUp)),
+        (Format::SmartPower3, None) => Ok(Box::new(SmartPower3)),
+        (Format::Custom, Some(custom)) => Ok(Box::new(CustomParser::new(custom)?)),
+        (Format::Custom, None) => Err(anyhow!("the custom format requires a `custom` definition")),
+        (_, Some(_)) => Err(anyhow!("the `custom` definition requires format = \"custom\"")),
+    }
+}
+
+/// Watts up? PRO: `#d,-,18,<W*10>,<V*10>,<mA>,…;`
+struct WattsUp;
+
+impl LineParser for WattsUp {
+    fn init_command(&self) -> Option<&[u8]> {
+        // external logging, every second
+        Some(b"#L,W,3,E,,1;")
+    }
+
+    fn parse(&self, line: &str) -> Vec<Reading> {
+        let fields: Vec<&str> = line.trim_end_matches(';').split(',').collect();
+        if fields.len() < 6 || fields[0] != "#d" {
+            return Vec::new();
+        }
+        let value = |i: usize, divisor: f64| fields[i].trim().parse::<f64>().ok().map(|v| v / divisor);
+        match value(3, 10.0) {
+            Some(power) => vec![Reading {
+                channel: None,
+                power_watts: power,
+                voltage_volts: value(4, 10.0),
+                current_amperes: value(5, 1000.0),
+            }],
+            None => Vec::new(),
+        }
+    }
+}
+
+/// ODROID SmartPower3: `<ms>,<mV>,<mA>,<mW>,<on/off>` for the input and for each of the two channels.
+struct SmartPower3;
+
+impl LineParser for SmartPower3 {
+    fn parse(&self, line: &str) -> Vec<Reading> {
+        const CHANNELS: [&str; 3] = ["input", "ch1", "ch2"];
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() < 1 + 4 * CHANNELS.len() {
+            return Vec::new();
+        }
+        let value = |i: usize| fields[i].parse::<f64>().ok().map(|v| v / 1000.0);
+        let mut readings = Vec::with_capacity(CHANNELS.len());
+        for (c, channel) in CHANNELS.into_iter().enumerate() {
+            let first = 1 + 4 * c;
+            if let Some(power) = value(first + 2) {
+                readings.push(Reading {
+                    channel: Some(channel),
+                    power_watts: power,
+                    voltage_volts: value(first),
+                    current_amperes: value(first + 1),
+                });
+            }
+        }
+        readings
+    }
+}
+
+struct CustomParser {
+    regex: Regex,
+    power_scale: f64,
+    voltage_scale: f64,
+    current_scale: f64,
+}
+
+impl CustomParser {
+    fn new(custom: &CustomFormat) -> anyhow::Result<Self> {
+        let regex = Regex::new(&custom.regex).with_context(|| format!("invalid regex {}", custom.regex))?;
+        if !regex.capture_names().any(|name| name == Some("power")) {
+            return Err(anyhow!("the regex {} has no group named `power`", custom.regex));
+        }
+        Ok(Self {
+            regex,
+            power_scale: custom.power_scale,
+            voltage_scale: custom.voltage_scale,
+            current_scale: custom.current_scale,
+        })
+    }
+}
+
+impl LineParser for CustomParser {
+    fn parse(&self, line: &str) -> Vec<Reading> {
+        let Some(captures) = self.regex.captures(line) else {
+            return Vec::new();
+        };
+        let value = |name: &str, scale: f64| {
+            captures
+                .name(name)
+                .and_then(|m| m.as_str().parse::<f64>().ok())
+                .map(|v| v * scale)
+        };
+        match value("power", self.power_scale) {
+            Some(power) => vec![Reading {
+                channel: None,
+                power_watts: power,
+                voltage_volts: value("voltage", self.voltage_scale),
+                current_amperes: value("current", self.current_scale),
+            }],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn wattsup() {
+        let parser = parser(Format::WattsUp, None).unwrap();
+        assert_eq!(
+            parser.parse("#d,-,18,1234,2301,536,42,0,0,0,0,0,0,0,0,0,0,0,0,0,0;"),
+            vec![Reading {
+                channel: None,
+                power_watts: 123.4,
+                voltage_volts: Some(230.1),
+                current_amperes: Some(0.536),
+            }]
+        );
+        // header and other messages
+        assert_eq!(parser.parse("#h,-,18,W,V,A,WH,Cost,WH/Mo;"), vec![]);
+        assert_eq!(parser.parse("#d,-,1"), vec![]);
+    }
+
+    #[test]
+    fn smartpower3() {
+        let parser = parser(Format::SmartPower3, None).unwrap();
+        let readings = parser.parse("0004564302,15000,0350,05250,1,05000,0800,04000,1,12000,0000,00000,0,6c,05");
+        assert_eq!(readings.len(), 3);
+        assert_eq!(
+            readings[1],
+            Reading {
+                channel: Some("ch1"),
+                power_watts: 4.0,
+                voltage_volts: Some(5.0),
+                current_amperes: Some(0.8),
+            }
+        );
+        assert_eq!(readings[0].power_watts, 5.25);
+        assert_eq!(readings[2].power_watts, 0.0);
+        assert_eq!(parser.parse("SmartPower3 v2.2"), vec![]);
+    }
+
+    #[test]
+    fn custom() {
+        let custom = CustomFormat {
+            regex: String::from(r"P=(?<power>[\d.]+)mW U=(?<voltage>[\d.]+)V"),
+            power_scale: 0.001,
+            voltage_scale: 1.0,
+            current_scale: 1.0,
+        };
+        let parser = parser(Format::Custom, Some(&custom)).unwrap();
+        assert_eq!(
+            parser.parse("P=2500mW U=5.1V"),
+            vec![Reading {
+                channel: None,
+                power_watts: 2.5,
+                voltage_volts: Some(5.1),
+                current_amperes: None,
+            }]
+        );
+        assert_eq!(parser.parse("garbage"), vec![]);
+
+        let no_power = CustomFormat {
+            regex: String::from(r"U=(?<voltage>[\d.]+)V"),
+            ..custom
+        };
+        assert!(super::parser(Format::Custom, Some(&no_power)).is_err());
+        assert!(super::parser(Format::Custom, None).is_err());
+    }
+}
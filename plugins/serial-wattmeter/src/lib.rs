@@ -0,0 +1,164 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{io::Write, path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+    resources::Resource,
+};
+
+pub use crate::{
+    format::{CustomFormat, Format},
+    serial::Parity,
+};
+use crate::{
+    serial::SerialSettings,
+    source::{Metrics, WattmeterSource},
+};
+
+mod format;
+mod serial;
+mod source;
+
+pub struct SerialWattmeterPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for SerialWattmeterPlugin {
+    fn name() -> &'static str {
+        "serial-wattmeter"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(SerialWattmeterPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        if self.config.devices.is_empty() {
+            return Err(anyhow!("no wattmeter configured"));
+        }
+        let metrics = Metrics::new(alumet)?;
+        for device in &self.config.devices {
+            let parser = format::parser(device.format, device.custom.as_ref())
+                .with_context(|| format!("invalid format for wattmeter {}", device.name))?;
+            let settings = SerialSettings {
+                baud_rate: device.baud_rate,
+                parity: device.parity,
+                stop_bits: device.stop_bits,
+            };
+            let mut port = serial::open(&device.port, &settings)
+                .with_context(|| format!("could not open serial port {:?}", device.port))?;
+            if let Some(command) = parser.init_command() {
+                port.write_all(command)
+                    .with_context(|| format!("could not start the measurements of wattmeter {}", device.name))?;
+            }
+
+            let resource = match &device.node {
+                Some(node) => Resource::custom("node", node.clone()),
+                None => Resource::LocalMachine,
+            };
+            let source = WattmeterSource::new(port, parser, device.name.clone(), resource, metrics);
+            let trigger = TriggerSpec::builder(self.config.poll_interval)
+                .flush_interval(self.config.flush_interval)
+                .build()?;
+            alumet.add_source(&device.name, Box::new(source), trigger)?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    /// Each measurement is the average of the values received since the previous one.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Interval between two flushing of the measurements.
+    #[serde(with = "humantime_serde")]
+    pub flush_interval: Duration,
+
+    /// The wattmeters to read.
+    pub devices: Vec<DeviceConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceConfig {
+    /// Name of the wattmeter, used as the name of its source.
+    pub name: String,
+
+    /// Path to the serial port, such as `/dev/ttyUSB0`.
+    pub port: PathBuf,
+
+    #[serde(default = "default_baud_rate")]
+    pub baud_rate: u32,
+
+    #[serde(default = "default_parity")]
+    pub parity: Parity,
+
+    #[serde(default = "default_stop_bits")]
+    pub stop_bits: u8,
+
+    /// Format of the lines sent by the wattmeter.
+    pub format: Format,
+
+    /// Regular expression that extracts the values, required if the format is `custom`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom: Option<CustomFormat>,
+
+    /// Name of the node measured by the wattmeter, if any, used as the id of the measured resource.
+    /// If not set, the resource is the local machine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+}
+
+fn default_baud_rate() -> u32 {
+    115200
+}
+
+fn default_parity() -> Parity {
+    Parity::None
+}
+
+fn default_stop_bits() -> u8 {
+    1
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            flush_interval: Duration::from_secs(5),
+            devices: vec![DeviceConfig {
+                name: String::from("wattmeter"),
+                port: PathBuf::from("/dev/ttyUSB0"),
+                baud_rate: default_baud_rate(),
+                parity: default_parity(),
+                stop_bits: default_stop_bits(),
+                format: Format::WattsUp,
+                custom: None,
+                node: None,
+            }],
+        }
+    }
+}
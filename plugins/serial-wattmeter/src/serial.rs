@@ -0,0 +1,74 @@
+//! Configuration of the serial port.
+
+use std::{
+    fs::{File, OpenOptions},
+    os::unix::fs::OpenOptionsExt,
+    path::Path,
+};
+
+use anyhow::{Context, anyhow};
+use nix::sys::termios::{self, BaudRate, ControlFlags, SetArg, SpecialCharacterIndices};
+use serde::{Deserialize, Serialize};
+
+/// Parity of the serial line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+/// Settings of the serial line.
+#[derive(Debug, Clone)]
+pub struct SerialSettings {
+    pub baud_rate: u32,
+    pub parity: Parity,
+    pub stop_bits: u8,
+}
+
+/// Opens a serial port in raw mode.
+///
+/// The reads are non-blocking: they return 0 immediately when no byte is available.
+pub fn open(path: &Path, settings: &SerialSettings) -> anyhow::Result<File> {
+    let port = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(nix::libc::O_NOCTTY)
+        .open(path)?;
+
+    let mut tty = termios::tcgetattr(&port)?;
+    termios::cfmakeraw(&mut tty);
+    let baud_rate =
+        baud_rate(settings.baud_rate).ok_or_else(|| anyhow!("unsupported baud rate {}", settings.baud_rate))?;
+    termios::cfsetspeed(&mut tty, baud_rate)?;
+    tty.control_flags |= ControlFlags::CLOCAL | ControlFlags::CREAD;
+    tty.control_flags
+        .set(ControlFlags::PARENB, settings.parity != Parity::None);
+    tty.control_flags
+        .set(ControlFlags::PARODD, settings.parity == Parity::Odd);
+    tty.control_flags.set(ControlFlags::CSTOPB, settings.stop_bits == 2);
+    tty.control_chars[SpecialCharacterIndices::VMIN as usize] = 0;
+    tty.control_chars[SpecialCharacterIndices::VTIME as usize] = 0;
+    termios::tcsetattr(&port, SetArg::TCSANOW, &tty).context("could not configure the serial port")?;
+    // discard the data received before the configuration
+    termios::tcflush(&port, termios::FlushArg::TCIFLUSH)?;
+    Ok(port)
+}
+
+fn baud_rate(value: u32) -> Option<BaudRate> {
+    Some(match value {
+        1200 => BaudRate::B1200,
+        2400 => BaudRate::B2400,
+        4800 => BaudRate::B4800,
+        9600 => BaudRate::B9600,
+        19200 => BaudRate::B19200,
+        38400 => BaudRate::B38400,
+        57600 => BaudRate::B57600,
+        115200 => BaudRate::B115200,
+        230400 => BaudRate::B230400,
+        460800 => BaudRate::B460800,
+        921600 => BaudRate::B921600,
+        _ => return None,
+    })
+}
@@ -0,0 +1,207 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{ErrorKind, Read},
+};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::Context;
+
+use crate::format::{LineParser, Reading};
+
+/// Maximum length of a line: if no line terminator is received, the data is probably not in the expected format.
+const MAX_LINE_LENGTH: usize = 4096;
+
+/// Contains the ids of the measured metrics.
+#[derive(Clone, Copy)]
+pub struct Metrics {
+    power: TypedMetricId<f64>,
+    voltage: TypedMetricId<f64>,
+    current: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            power: alumet.create_metric(
+                "wattmeter_power",
+                Unit::Watt,
+                "Average power measured by the wattmeter since the previous measurement",
+            )?,
+            voltage: alumet.create_metric(
+                "wattmeter_voltage",
+                Unit::Volt,
+                "Average voltage measured by the wattmeter since the previous measurement",
+            )?,
+            current: alumet.create_metric(
+                "wattmeter_current",
+                Unit::Ampere,
+                "Average current measured by the wattmeter since the previous measurement",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the lines sent by a wattmeter on a serial port.
+pub struct WattmeterSource {
+    port: File,
+    parser: Box<dyn LineParser>,
+    /// Name of the wattmeter, added to the measurements as an attribute.
+    device: String,
+    resource: Resource,
+    metrics: Metrics,
+    /// The bytes received after the last complete line.
+    pending: Vec<u8>,
+}
+
+impl WattmeterSource {
+    pub fn new(port: File, parser: Box<dyn LineParser>, device: String, resource: Resource, metrics: Metrics) -> Self {
+        Self {
+            port,
+            parser,
+            device,
+            resource,
+            metrics,
+            pending: Vec::with_capacity(MAX_LINE_LENGTH),
+        }
+    }
+
+    /// Reads the bytes that are available on the port, and parses the complete lines.
+    fn read_lines(&mut self) -> anyhow::Result<Vec<Reading>> {
+        let mut buf = [0u8; 1024];
+        loop {
+            match self.port.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => self.pending.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::Interrupted => break,
+                Err(e) => return Err(e).with_context(|| format!("failed to read from wattmeter {}", self.device)),
+            }
+        }
+
+        let mut readings = Vec::new();
+        while let Some(end) = self.pending.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            readings.extend(self.parser.parse(line.trim()));
+        }
+        if self.pending.len() > MAX_LINE_LENGTH {
+            log::warn!(
+                "Wattmeter {} sends data without line terminator, discarding it.",
+                self.device
+            );
+            self.pending.clear();
+        }
+        Ok(readings)
+    }
+}
+
+impl Source for WattmeterSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let readings = self.read_lines()?;
+        for reading in average(&readings) {
+            let point = |metric, value: f64| {
+                let point = MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    self.resource.clone(),
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("device", self.device.clone());
+                match reading.channel {
+                    Some(channel) => point.with_attr("channel", channel),
+                    None => point,
+                }
+            };
+            measurements.push(point(self.metrics.power, reading.power_watts));
+            if let Some(voltage) = reading.voltage_volts {
+                measurements.push(point(self.metrics.voltage, voltage));
+            }
+            if let Some(current) = reading.current_amperes {
+                measurements.push(point(self.metrics.current, current));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Computes the average of the readings of each channel.
+fn average(readings: &[Reading]) -> Vec<Reading> {
+    #[derive(Default)]
+    struct Sums {
+        power: (f64, u32),
+        voltage: (f64, u32),
+        current: (f64, u32),
+    }
+    fn add(sum: &mut (f64, u32), value: Option<f64>) {
+        if let Some(value) = value {
+            sum.0 += value;
+            sum.1 += 1;
+        }
+    }
+    fn mean(sum: (f64, u32)) -> Option<f64> {
+        (sum.1 > 0).then(|| sum.0 / f64::from(sum.1))
+    }
+
+    let mut by_channel: BTreeMap<Option<&'static str>, Sums> = BTreeMap::new();
+    for r in readings {
+        let sums = by_channel.entry(r.channel).or_default();
+        add(&mut sums.power, Some(r.power_watts));
+        add(&mut sums.voltage, r.voltage_volts);
+        add(&mut sums.current, r.current_amperes);
+    }
+    by_channel
+        .into_iter()
+        .filter_map(|(channel, sums)| {
+            Some(Reading {
+                channel,
+                power_watts: mean(sums.power)?,
+                voltage_volts: mean(sums.voltage),
+                current_amperes: mean(sums.current),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn average_by_channel() {
+        let reading = |channel, power, voltage| Reading {
+            channel,
+            power_watts: power,
+            voltage_volts: voltage,
+            current_amperes: None,
+        };
+        let readings = [
+            reading(Some("ch1"), 10.0, Some(5.0)),
+            reading(Some("ch2"), 1.0, None),
+            reading(Some("ch1"), 20.0, None),
+            reading(Some("ch1"), 30.0, Some(5.2)),
+        ];
+        assert_eq!(
+            average(&readings),
+            vec![
+                Reading {
+                    channel: Some("ch1"),
+                    power_watts: 20.0,
+                    voltage_volts: Some(5.1),
+                    current_amperes: None,
+                },
+                reading(Some("ch2"), 1.0, None),
+            ]
+        );
+        assert_eq!(average(&[]), vec![]);
+    }
+}
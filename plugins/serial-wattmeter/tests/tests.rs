@@ -0,0 +1,113 @@
+use std::{fs::File, io::Write, os::fd::AsRawFd, path::PathBuf, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use nix::pty::openpty;
+use plugin_serial_wattmeter::{Config, DeviceConfig, Format, Parity, SerialWattmeterPlugin};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_port() {
+    let config = Config {
+        devices: vec![device(PathBuf::from("/dev/does-not-exist"), Format::WattsUp)],
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no serial port)");
+}
+
+#[test]
+fn plugin_with_pty_wattmeter() {
+    // The pseudo-terminal plays the role of the USB-serial adapter.
+    let pty = openpty(None, None).unwrap();
+    let port = std::fs::read_link(format!("/proc/self/fd/{}", pty.slave.as_raw_fd())).unwrap();
+    let meter = File::from(pty.master);
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        flush_interval: Duration::from_millis(100),
+        devices: vec![DeviceConfig {
+            node: Some(String::from("node-1")),
+            ..device(port, Format::SmartPower3)
+        }],
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<f64>("wattmeter_power", Unit::Watt)
+        .expect_metric::<f64>("wattmeter_voltage", Unit::Volt)
+        .expect_metric::<f64>("wattmeter_current", Unit::Ampere)
+        .expect_source("serial-wattmeter", "meter");
+
+    let runtime = RuntimeExpectations::new().test_source(
+        SourceName::from_str("serial-wattmeter", "meter"),
+        move || {
+            let mut meter = &meter;
+            // two lines and an incomplete one, which is kept for the next measurement
+            meter
+                .write_all(
+                    b"0000001000,15000,0300,04500,1,05000,0800,04000,1,12000,0000,00000,0\r\n\
+                      0000001100,15000,0500,07500,1,05000,1000,05000,1,12000,0000,00000,0\r\n\
+                      0000001200,15000",
+                )
+                .unwrap();
+            // let the line discipline deliver the data
+            std::thread::sleep(Duration::from_millis(50));
+        },
+        |ctx| {
+            let m = ctx.measurements();
+            let power = ctx.metrics().by_name("wattmeter_power").unwrap().0;
+            let power_of = |channel: &str| {
+                let points: Vec<_> = m
+                    .iter()
+                    .filter(|p| {
+                        p.metric == power && p.attributes().any(|(k, v)| k == "channel" && v.to_string() == channel)
+                    })
+                    .collect();
+                assert_eq!(points.len(), 1, "one averaged point is expected for channel {channel}");
+                assert_eq!(points[0].resource, Resource::custom("node", "node-1"));
+                points[0].value.as_f64()
+            };
+            assert_eq!(power_of("input"), 6.0);
+            assert_eq!(power_of("ch1"), 4.5);
+            assert_eq!(power_of("ch2"), 0.0);
+        },
+    );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+    drop(pty.slave);
+}
+
+fn device(port: PathBuf, format: Format) -> DeviceConfig {
+    DeviceConfig {
+        name: String::from("meter"),
+        port,
+        baud_rate: 115200,
+        parity: Parity::None,
+        stop_bits: 1,
+        format,
+        custom: None,
+        node: None,
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<SerialWattmeterPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
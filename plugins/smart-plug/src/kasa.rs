@@ -0,0 +1,193 @@
+//! Client of the local protocol of the TP-Link Kasa plugs (HS110, KP115, etc.).
+//!
+//! The requests and responses are JSON documents, obfuscated with an autokey XOR cipher
+//! and preceded by their length (32 bits, big endian), on TCP port 9999.
+
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::plug::PlugReading;
+
+/// Port of the local protocol.
+const DEFAULT_PORT: u16 = 9999;
+/// Initial key of the cipher.
+const INITIAL_KEY: u8 = 171;
+/// Maximum length of a response, to protect ourselves from invalid data.
+const MAX_RESPONSE_LENGTH: usize = 64 * 1024;
+/// Request for the realtime measurements of the energy meter.
+const GET_REALTIME: &str = r#"{"emeter":{"get_realtime":{}}}"#;
+
+/// A client of the local protocol of a Kasa plug.
+pub struct KasaClient {
+    /// Host and port of the plug.
+    address: String,
+    timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    emeter: Emeter,
+}
+
+#[derive(Deserialize)]
+struct Emeter {
+    get_realtime: Realtime,
+}
+
+/// Realtime measurements. The first hardware versions use base units, the later ones use milli-units.
+#[derive(Deserialize)]
+struct Realtime {
+    err_code: i32,
+    power: Option<f64>,
+    power_mw: Option<f64>,
+    /// Energy counter, in kilo-Watt-hours.
+    total: Option<f64>,
+    total_wh: Option<f64>,
+    voltage: Option<f64>,
+    voltage_mv: Option<f64>,
+    current: Option<f64>,
+    current_ma: Option<f64>,
+}
+
+impl KasaClient {
+    pub fn new(address: &str, timeout: Duration) -> Self {
+        let address = if address.contains(':') {
+            address.to_owned()
+        } else {
+            format!("{address}:{DEFAULT_PORT}")
+        };
+        Self { address, timeout }
+    }
+
+    pub async fn read(&mut self) -> anyhow::Result<PlugReading> {
+        let response = tokio::time::timeout(self.timeout, self.request(GET_REALTIME))
+            .await
+            .map_err(|_| anyhow!("no response from {} after {:?}", self.address, self.timeout))??;
+        let response: Response = serde_json::from_slice(&response).context("invalid realtime measurements")?;
+        let realtime = response.emeter.get_realtime;
+        if realtime.err_code != 0 {
+            return Err(anyhow!("the plug returned the error code {}", realtime.err_code));
+        }
+        let milli = |value: Option<f64>| value.map(|v| v / 1000.0);
+        Ok(PlugReading {
+            power_watts: realtime.power.or(milli(realtime.power_mw)),
+            energy_watt_hours: realtime.total_wh.or(realtime.total.map(|e| e * 1000.0)),
+            voltage_volts: realtime.voltage.or(milli(realtime.voltage_mv)),
+            current_amperes: realtime.current.or(milli(realtime.current_ma)),
+        })
+    }
+
+    /// Sends a request and returns the decrypted response.
+    async fn request(&self, request: &str) -> anyhow::Result<Vec<u8>> {
+        let mut stream = TcpStream::connect(&self.address)
+            .await
+            .with_context(|| format!("could not connect to {}", self.address))?;
+        let mut message = (request.len() as u32).to_be_bytes().to_vec();
+        message.extend(encrypt(request.as_bytes()));
+        stream.write_all(&message).await?;
+
+        let length = stream.read_u32().await.context("no response")? as usize;
+        if length > MAX_RESPONSE_LENGTH {
+            return Err(anyhow!("response too long ({length} bytes)"));
+        }
+        let mut response = vec![0; length];
+        stream.read_exact(&mut response).await.context("incomplete response")?;
+        Ok(decrypt(&response))
+    }
+}
+
+fn encrypt(data: &[u8]) -> Vec<u8> {
+    let mut key = INITIAL_KEY;
+    data.iter()
+        .map(|b| {
+            key ^= b;
+            key
+        })
+        .collect()
+}
+
+fn decrypt(data: &[u8]) -> Vec<u8> {
+    let mut key = INITIAL_KEY;
+    data.iter()
+        .map(|&c| {
+            let b = key ^ c;
+            key = c;
+            b
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn cipher() {
+        assert_eq!(encrypt(b"{}"), vec![0xd0, 0xad]);
+        assert_eq!(decrypt(&encrypt(GET_REALTIME.as_bytes())), GET_REALTIME.as_bytes());
+    }
+
+    /// Answers one request like a Kasa plug.
+    async fn fake_plug(listener: TcpListener, response: &'static str) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let length = stream.read_u32().await.unwrap() as usize;
+        let mut request = vec![0; length];
+        stream.read_exact(&mut request).await.unwrap();
+        assert_eq!(decrypt(&request), GET_REALTIME.as_bytes());
+
+        let mut message = (response.len() as u32).to_be_bytes().to_vec();
+        message.extend(encrypt(response.as_bytes()));
+        stream.write_all(&message).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn read_kasa_plug() {
+        for (response, energy) in [
+            (
+                r#"{"emeter":{"get_realtime":{"voltage_mv":230500,"current_ma":80,"power_mw":12340,"total_wh":250,"err_code":0}}}"#,
+                250.0,
+            ),
+            (
+                r#"{"emeter":{"get_realtime":{"voltage":230.5,"current":0.08,"power":12.34,"total":0.25,"err_code":0}}}"#,
+                250.0,
+            ),
+        ] {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            let plug = tokio::spawn(fake_plug(listener, response));
+
+            let mut client = KasaClient::new(&address, Duration::from_secs(1));
+            assert_eq!(
+                client.read().await.unwrap(),
+                PlugReading {
+                    power_watts: Some(12.34),
+                    energy_watt_hours: Some(energy),
+                    voltage_volts: Some(230.5),
+                    current_amperes: Some(0.08),
+                }
+            );
+            plug.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn kasa_plug_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let plug = tokio::spawn(fake_plug(listener, r#"{"emeter":{"get_realtime":{"err_code":-1}}}"#));
+
+        let mut client = KasaClient::new(&address, Duration::from_secs(1));
+        let error = client.read().await.unwrap_err();
+        assert_eq!(error.to_string(), "the plug returned the error code -1");
+        plug.await.unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use alumet::{
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+        secret::Secret,
+    },
+    resources::Resource,
+};
+
+use crate::{
+    plug::{PlugClient, PlugSettings},
+    source::{Metrics, SourceSettings},
+};
+
+pub use plug::Model;
+
+mod kasa;
+mod plug;
+mod shelly;
+mod source;
+mod tasmota;
+
+pub struct SmartPlugPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for SmartPlugPlugin {
+    fn name() -> &'static str {
+        "smart-plug"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(SmartPlugPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        if self.config.plugs.is_empty() {
+            return Err(anyhow!("no smart plug configured"));
+        }
+        let metrics = Metrics::new(alumet)?;
+        for plug in &self.config.plugs {
+            let client = PlugClient::new(
+                plug.model,
+                PlugSettings {
+                    address: plug.address.clone(),
+                    channel: plug.channel,
+                    username: plug.username.clone(),
+                    password: plug.password.clone(),
+                    timeout: plug.timeout,
+                },
+            )
+            .with_context(|| format!("invalid configuration of smart plug {}", plug.name))?;
+            let settings = SourceSettings {
+                poll_interval: self.config.poll_interval,
+                plug: plug.name.clone(),
+                resource: match &plug.node {
+                    Some(node) => Resource::custom("node", node.clone()),
+                    None => Resource::LocalMachine,
+                },
+            };
+            log::info!(
+                "Measuring smart plug {} ({:?}) at {}",
+                plug.name,
+                plug.model,
+                plug.address
+            );
+            alumet.add_autonomous_source_builder(&plug.name, move |_ctx, cancel_token, out_tx| {
+                Ok(Box::pin(source::run(client, metrics, settings, cancel_token, out_tx)))
+            })?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// The smart plugs to measure.
+    pub plugs: Vec<PlugConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlugConfig {
+    /// Name of the plug, used as the name of its source.
+    pub name: String,
+
+    /// Model of the plug.
+    pub model: Model,
+
+    /// Host of the plug, with an optional port, for instance `192.168.1.20`.
+    pub address: String,
+
+    /// Switch (or relay) to measure, for the plugs that have several of them, starting at 0.
+    #[serde(default)]
+    pub channel: u32,
+
+    /// Name of the user of the web interface (Tasmota only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+
+    /// Password of the web interface (Tasmota only), which can reference a secret (`secret://...`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<Secret>,
+
+    /// Maximum time to wait for the response of the plug.
+    #[serde(with = "humantime_serde", default = "default_timeout")]
+    pub timeout: Duration,
+
+    /// Name of the node plugged into the plug, if any, used as the id of the measured resource.
+    /// If not set, the resource is the local machine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            plugs: vec![PlugConfig {
+                name: String::from("plug"),
+                model: Model::Shelly,
+                address: String::from("192.168.1.20"),
+                channel: 0,
+                username: None,
+                password: None,
+                timeout: default_timeout(),
+                node: None,
+            }],
+        }
+    }
+}
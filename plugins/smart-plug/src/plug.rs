@@ -0,0 +1,106 @@
+//! Model-independent access to the measurements of a smart plug.
+
+use std::time::Duration;
+
+use alumet::plugin::secret::Secret;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::{kasa::KasaClient, shelly::ShellyClient, tasmota::TasmotaClient};
+
+/// Model of a smart plug, which determines the API to use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Model {
+    /// Shelly plugs of the second generation and later (RPC API over HTTP).
+    Shelly,
+    /// Plugs running the Tasmota firmware (commands over HTTP).
+    Tasmota,
+    /// TP-Link Kasa plugs with energy monitoring (encrypted JSON over TCP).
+    Kasa,
+}
+
+/// Measurements of a smart plug.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlugReading {
+    /// Active power, if available.
+    pub power_watts: Option<f64>,
+    /// Energy counter, if available.
+    pub energy_watt_hours: Option<f64>,
+    pub voltage_volts: Option<f64>,
+    pub current_amperes: Option<f64>,
+}
+
+/// A client of the API of a smart plug.
+pub enum PlugClient {
+    Shelly(ShellyClient),
+    Tasmota(TasmotaClient),
+    Kasa(KasaClient),
+}
+
+/// Settings of the connection to a smart plug.
+pub struct PlugSettings {
+    /// Host of the plug, with an optional port.
+    pub address: String,
+    /// Switch (or relay) of the plug, for the plugs that have several of them, starting at 0.
+    pub channel: u32,
+    pub username: Option<String>,
+    pub password: Option<Secret>,
+    pub timeout: Duration,
+}
+
+impl PlugClient {
+    pub fn new(model: Model, settings: PlugSettings) -> anyhow::Result<Self> {
+        let http = || {
+            reqwest::Client::builder()
+                .timeout(settings.timeout)
+                .build()
+                .map_err(anyhow::Error::from)
+        };
+        Ok(match model {
+            Model::Shelly => PlugClient::Shelly(ShellyClient::new(http()?, &settings.address, settings.channel)),
+            Model::Tasmota => PlugClient::Tasmota(TasmotaClient::new(
+                http()?,
+                &settings.address,
+                settings.channel,
+                settings.username,
+                settings.password,
+            )),
+            Model::Kasa if settings.channel != 0 => {
+                return Err(anyhow!("the channel of the Kasa plugs cannot be chosen"));
+            }
+            Model::Kasa => PlugClient::Kasa(KasaClient::new(&settings.address, settings.timeout)),
+        })
+    }
+
+    /// Reads the power and energy of the plug.
+    pub async fn read(&mut self) -> anyhow::Result<PlugReading> {
+        match self {
+            PlugClient::Shelly(client) => client.read().await,
+            PlugClient::Tasmota(client) => client.read().await,
+            PlugClient::Kasa(client) => client.read().await,
+        }
+    }
+}
+
+/// Returns the base URL of the HTTP API of the plug at the given address.
+pub fn base_url(address: &str) -> String {
+    let address = address.trim_end_matches('/');
+    if address.starts_with("http://") || address.starts_with("https://") {
+        address.to_owned()
+    } else {
+        format!("http://{address}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_of_address() {
+        assert_eq!(base_url("192.168.1.20"), "http://192.168.1.20");
+        assert_eq!(base_url("plug.lan:8080"), "http://plug.lan:8080");
+        assert_eq!(base_url("https://plug.lan/"), "https://plug.lan");
+    }
+}
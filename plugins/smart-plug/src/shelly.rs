@@ -0,0 +1,96 @@
+//! Client of the RPC API of the Shelly plugs (second generation and later).
+
+use anyhow::Context;
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::plug::{PlugReading, base_url};
+
+/// A client of the RPC API of a Shelly plug.
+pub struct ShellyClient {
+    http: Client,
+    /// URL of the status of the switch, for instance `http://10.0.0.4/rpc/Switch.GetStatus?id=0`.
+    status_url: String,
+}
+
+/// Status of a switch (only the fields that we need).
+#[derive(Deserialize)]
+struct SwitchStatus {
+    /// Active power, in Watts.
+    apower: Option<f64>,
+    voltage: Option<f64>,
+    current: Option<f64>,
+    aenergy: Option<ActiveEnergy>,
+}
+
+#[derive(Deserialize)]
+struct ActiveEnergy {
+    /// Energy counter, in Watt-hours.
+    total: f64,
+}
+
+impl ShellyClient {
+    pub fn new(http: Client, address: &str, channel: u32) -> Self {
+        Self {
+            http,
+            status_url: format!("{}/rpc/Switch.GetStatus?id={channel}", base_url(address)),
+        }
+    }
+
+    pub async fn read(&mut self) -> anyhow::Result<PlugReading> {
+        let status: SwitchStatus = self
+            .http
+            .get(&self.status_url)
+            .send()
+            .await?
+            .error_for_status()
+            .context("could not get the status of the switch")?
+            .json()
+            .await
+            .context("invalid status of the switch")?;
+        Ok(PlugReading {
+            power_watts: status.apower,
+            energy_watt_hours: status.aenergy.map(|e| e.total),
+            voltage_volts: status.voltage,
+            current_amperes: status.current,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Server;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn read_shelly_switch() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/rpc/Switch.GetStatus?id=1")
+            .with_body(
+                json!({
+                    "id": 1, "source": "init", "output": true, "apower": 8.9, "voltage": 237.5, "current": 0.068,
+                    "aenergy": { "total": 6.532, "by_minute": [45.199, 47.141, 88.397], "minute_ts": 1654511700 },
+                    "temperature": { "tC": 23.5, "tF": 74.4 }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let mut client = ShellyClient::new(Client::new(), &server.url(), 1);
+        assert_eq!(
+            client.read().await.unwrap(),
+            PlugReading {
+                power_watts: Some(8.9),
+                energy_watt_hours: Some(6.532),
+                voltage_volts: Some(237.5),
+                current_amperes: Some(0.068),
+            }
+        );
+        mock.assert_async().await;
+    }
+}
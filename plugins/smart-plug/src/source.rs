@@ -0,0 +1,174 @@
+use std::time::Duration;
+
+use alumet::{
+    measurement::{MeasurementBuffer, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use tokio::{sync::mpsc, time::MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::plug::{PlugClient, PlugReading};
+
+/// Number of Joules in a Watt-hour.
+const JOULES_PER_WATT_HOUR: f64 = 3600.0;
+
+/// Contains the ids of the measured metrics.
+#[derive(Clone, Copy)]
+pub struct Metrics {
+    power: TypedMetricId<f64>,
+    energy: TypedMetricId<f64>,
+    voltage: TypedMetricId<f64>,
+    current: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            power: alumet.create_metric("smart_plug_power", Unit::Watt, "Active power measured by a smart plug")?,
+            energy: alumet.create_metric(
+                "smart_plug_energy",
+                Unit::Joule,
+                "Energy measured by a smart plug since the previous measurement",
+            )?,
+            voltage: alumet.create_metric("smart_plug_voltage", Unit::Volt, "Voltage measured by a smart plug")?,
+            current: alumet.create_metric("smart_plug_current", Unit::Ampere, "Current measured by a smart plug")?,
+        })
+    }
+}
+
+pub struct SourceSettings {
+    pub poll_interval: Duration,
+    /// Name of the plug, added to the measurements as an attribute.
+    pub plug: String,
+    /// The resource that is plugged into the plug.
+    pub resource: Resource,
+}
+
+/// Measures the plug at regular intervals, until `cancel_token` is cancelled.
+pub async fn run(
+    mut client: PlugClient,
+    metrics: Metrics,
+    settings: SourceSettings,
+    cancel_token: CancellationToken,
+    tx: mpsc::Sender<MeasurementBuffer>,
+) -> anyhow::Result<()> {
+    // Previous value of the energy counter, in Wh.
+    let mut previous_energy: Option<f64> = None;
+
+    let mut interval = tokio::time::interval(settings.poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            biased;
+            _ = cancel_token.cancelled() => break,
+            _ = interval.tick() => (),
+        };
+        // The plug may be temporarily unreachable (Wi-Fi): try again at the next measurement.
+        let reading = match client.read().await {
+            Ok(reading) => reading,
+            Err(e) => {
+                log::warn!("Measurement of smart plug {} failed: {e:#}", settings.plug);
+                continue;
+            }
+        };
+        let timestamp = Timestamp::now();
+        let mut buffer = MeasurementBuffer::with_capacity(4);
+        for (metric, value) in convert(&reading, &mut previous_energy) {
+            let metric = match metric {
+                PlugMetric::Power => metrics.power,
+                PlugMetric::Energy => metrics.energy,
+                PlugMetric::Voltage => metrics.voltage,
+                PlugMetric::Current => metrics.current,
+            };
+            buffer.push(
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    settings.resource.clone(),
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("plug", settings.plug.clone()),
+            );
+        }
+        if !buffer.is_empty() {
+            tx.send(buffer).await?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum PlugMetric {
+    Power,
+    /// Energy since the previous measurement, in Joules.
+    Energy,
+    Voltage,
+    Current,
+}
+
+/// Converts the reading of a plug to the values of the metrics.
+fn convert(reading: &PlugReading, previous_energy: &mut Option<f64>) -> Vec<(PlugMetric, f64)> {
+    let mut values = Vec::with_capacity(4);
+    if let Some(power) = reading.power_watts {
+        values.push((PlugMetric::Power, power));
+    }
+    if let Some(energy) = reading.energy_watt_hours {
+        // the counter is reset when the plug restarts: skip the measurement in that case
+        if let Some(previous) = previous_energy.replace(energy)
+            && energy >= previous
+        {
+            values.push((PlugMetric::Energy, (energy - previous) * JOULES_PER_WATT_HOUR));
+        }
+    }
+    if let Some(voltage) = reading.voltage_volts {
+        values.push((PlugMetric::Voltage, voltage));
+    }
+    if let Some(current) = reading.current_amperes {
+        values.push((PlugMetric::Current, current));
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn convert_energy_counter() {
+        let reading = |energy: f64| PlugReading {
+            power_watts: Some(10.0),
+            energy_watt_hours: Some(energy),
+            voltage_volts: None,
+            current_amperes: None,
+        };
+        let mut previous = None;
+        assert_eq!(convert(&reading(100.0), &mut previous), vec![(PlugMetric::Power, 10.0)]);
+        assert_eq!(
+            convert(&reading(100.5), &mut previous),
+            vec![(PlugMetric::Power, 10.0), (PlugMetric::Energy, 1800.0)]
+        );
+        // the plug has restarted
+        assert_eq!(convert(&reading(0.0), &mut previous), vec![(PlugMetric::Power, 10.0)]);
+
+        let full = PlugReading {
+            power_watts: Some(10.0),
+            energy_watt_hours: None,
+            voltage_volts: Some(230.0),
+            current_amperes: Some(0.05),
+        };
+        assert_eq!(
+            convert(&full, &mut previous),
+            vec![
+                (PlugMetric::Power, 10.0),
+                (PlugMetric::Voltage, 230.0),
+                (PlugMetric::Current, 0.05)
+            ]
+        );
+    }
+}
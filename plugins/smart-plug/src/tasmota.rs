@@ -0,0 +1,172 @@
+//! Client of the HTTP API of the plugs that run the Tasmota firmware.
+
+use alumet::plugin::secret::Secret;
+use anyhow::{Context, anyhow};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::plug::{PlugReading, base_url};
+
+/// Number of Watt-hours in a kilo-Watt-hour.
+const WATT_HOURS_PER_KILOWATT_HOUR: f64 = 1000.0;
+
+/// A client of the HTTP API of a Tasmota plug.
+pub struct TasmotaClient {
+    http: Client,
+    /// URL of the commands, for instance `http://10.0.0.5/cm`.
+    command_url: String,
+    /// Relay of the plug, starting at 0.
+    channel: usize,
+    username: Option<String>,
+    password: Option<Secret>,
+}
+
+/// Response to the `Status 8` command (sensors).
+#[derive(Deserialize)]
+struct Status {
+    #[serde(rename = "StatusSNS")]
+    sensors: Sensors,
+}
+
+#[derive(Deserialize)]
+struct Sensors {
+    #[serde(rename = "ENERGY")]
+    energy: Option<Energy>,
+}
+
+/// Energy sensor. On the plugs that have several relays, the values are arrays.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Energy {
+    /// Energy counter, in kilo-Watt-hours.
+    total: Option<Value>,
+    power: Option<Value>,
+    voltage: Option<Value>,
+    current: Option<Value>,
+}
+
+impl TasmotaClient {
+    pub fn new(http: Client, address: &str, channel: u32, username: Option<String>, password: Option<Secret>) -> Self {
+        Self {
+            http,
+            command_url: format!("{}/cm", base_url(address)),
+            channel: channel as usize,
+            username,
+            password,
+        }
+    }
+
+    pub async fn read(&mut self) -> anyhow::Result<PlugReading> {
+        let mut query = vec![("cmnd", "Status 8")];
+        if let Some(password) = &self.password {
+            query.push(("user", self.username.as_deref().unwrap_or("admin")));
+            query.push(("password", password.expose()));
+        }
+        let status: Status = self
+            .http
+            .get(&self.command_url)
+            .query(&query)
+            .send()
+            .await?
+            .error_for_status()
+            .context("could not get the status of the sensors")?
+            .json()
+            .await
+            .context("invalid status of the sensors (is the password correct?)")?;
+        let energy = status
+            .sensors
+            .energy
+            .ok_or_else(|| anyhow!("the plug has no energy sensor"))?;
+
+        let value = |v: &Option<Value>| match v {
+            Some(Value::Array(values)) => values.get(self.channel).and_then(Value::as_f64),
+            Some(v) => v.as_f64(),
+            None => None,
+        };
+        Ok(PlugReading {
+            power_watts: value(&energy.power),
+            energy_watt_hours: value(&energy.total).map(|e| e * WATT_HOURS_PER_KILOWATT_HOUR),
+            voltage_volts: value(&energy.voltage),
+            current_amperes: value(&energy.current),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::{Matcher, Server};
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn read_tasmota_plug() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/cm")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("cmnd".into(), "Status 8".into()),
+                Matcher::UrlEncoded("user".into(), "admin".into()),
+                Matcher::UrlEncoded("password".into(), "secret".into()),
+            ]))
+            .with_body(
+                json!({
+                    "StatusSNS": {
+                        "Time": "2024-03-01T10:00:00",
+                        "ENERGY": {
+                            "TotalStartTime": "2023-11-06T19:02:11", "Total": 1.25, "Yesterday": 0.1, "Today": 0.02,
+                            "Power": 42, "ApparentPower": 50, "ReactivePower": 27, "Factor": 0.84,
+                            "Voltage": 231, "Current": 0.216
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let mut client = TasmotaClient::new(Client::new(), &server.url(), 0, None, Some(Secret::new("secret")));
+        assert_eq!(
+            client.read().await.unwrap(),
+            PlugReading {
+                power_watts: Some(42.0),
+                energy_watt_hours: Some(1250.0),
+                voltage_volts: Some(231.0),
+                current_amperes: Some(0.216),
+            }
+        );
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn read_tasmota_second_relay() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/cm")
+            .match_query(Matcher::UrlEncoded("cmnd".into(), "Status 8".into()))
+            .with_body(
+                json!({
+                    "StatusSNS": {
+                        "ENERGY": { "Total": 3.5, "Power": [10, 20], "Voltage": 230, "Current": [0.05, 0.1] }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let mut client = TasmotaClient::new(Client::new(), &server.url(), 1, None, None);
+        assert_eq!(
+            client.read().await.unwrap(),
+            PlugReading {
+                power_watts: Some(20.0),
+                energy_watt_hours: Some(3500.0),
+                voltage_volts: Some(230.0),
+                current_amperes: Some(0.1),
+            }
+        );
+        mock.assert_async().await;
+    }
+}
@@ -0,0 +1,81 @@
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    plugin::PluginMetadata,
+    test::StartupExpectations,
+    units::Unit,
+};
+use plugin_smart_plug::{Config, Model, PlugConfig, SmartPlugPlugin};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const PLUGIN_NAME: &str = "smart-plug";
+
+#[test]
+fn plugin_with_unreachable_plug() {
+    // nothing listens on the discard port: the measurements fail, but the agent keeps running
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        plugs: vec![plug("desk", Model::Kasa, "127.0.0.1:9")],
+    };
+
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("smart_plug_power", Unit::Watt)
+        .expect_metric::<f64>("smart_plug_energy", Unit::Joule)
+        .expect_source(PLUGIN_NAME, "desk");
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup_expectation)
+        .build_and_start()
+        .unwrap();
+    agent.pipeline.control_handle().shutdown();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+#[test]
+fn plugin_with_kasa_channel() {
+    let config = Config {
+        poll_interval: Duration::from_secs(1),
+        plugs: vec![PlugConfig {
+            channel: 1,
+            ..plug("strip", Model::Kasa, "127.0.0.1")
+        }],
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(
+        agent.is_err(),
+        "the plugin should fail to start (channel of a Kasa plug)"
+    );
+}
+
+#[test]
+fn plugin_without_plug() {
+    let config = Config {
+        poll_interval: Duration::from_secs(1),
+        plugs: Vec::new(),
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no plug)");
+}
+
+fn plug(name: &str, model: Model, address: &str) -> PlugConfig {
+    PlugConfig {
+        name: String::from(name),
+        model,
+        address: String::from(address),
+        channel: 0,
+        username: None,
+        password: None,
+        timeout: Duration::from_secs(1),
+        node: Some(String::from("node-1")),
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<SmartPlugPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
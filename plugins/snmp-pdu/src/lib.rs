@@ -0,0 +1,169 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::{
+    profile::Profile,
+    snmp::SnmpClient,
+    source::{Metrics, PduSource},
+};
+
+pub use profile::{CustomProfile, Vendor};
+
+mod profile;
+mod snmp;
+mod source;
+
+/// Default port of the SNMP agents.
+const SNMP_PORT: u16 = 161;
+
+pub struct SnmpPduPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for SnmpPduPlugin {
+    fn name() -> &'static str {
+        "snmp-pdu"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(SnmpPduPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        if self.config.pdus.is_empty() {
+            return Err(anyhow!("no PDU configured"));
+        }
+        let metrics = Metrics::new(alumet)?;
+        for pdu in &self.config.pdus {
+            let profile = Profile::new(pdu.vendor, pdu.custom.as_ref())
+                .with_context(|| format!("invalid profile for PDU {}", pdu.host))?;
+            let addr = if pdu.host.contains(':') {
+                pdu.host.clone()
+            } else {
+                format!("{}:{SNMP_PORT}", pdu.host)
+            };
+            let client = SnmpClient::connect(&addr, pdu.community.clone(), pdu.timeout)
+                .with_context(|| format!("could not connect to PDU {addr}"))?;
+            let outlets = pdu.outlets.iter().map(|o| (o.outlet, o.node.clone())).collect();
+            let source = PduSource::new(client, pdu.host.clone(), profile, metrics, outlets)?;
+            log::info!(
+                "Measuring {} outlets of PDU {} ({:?})",
+                pdu.outlets.len(),
+                pdu.host,
+                pdu.vendor
+            );
+
+            let trigger = TriggerSpec::builder(self.config.poll_interval)
+                .flush_interval(self.config.flush_interval)
+                .build()?;
+            alumet.add_source(&pdu.host, Box::new(source), trigger)?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two SNMP measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Interval between two flushing of SNMP measurements.
+    #[serde(with = "humantime_serde")]
+    pub flush_interval: Duration,
+
+    /// The PDUs to measure.
+    pub pdus: Vec<PduConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PduConfig {
+    /// Address of the PDU, with an optional port (161 by default).
+    pub host: String,
+
+    /// SNMPv2c community.
+    #[serde(default = "default_community")]
+    pub community: String,
+
+    /// Vendor of the PDU.
+    pub vendor: Vendor,
+
+    /// OIDs to query, required if the vendor is `custom`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom: Option<CustomProfile>,
+
+    /// Maximum time to wait for the response of the PDU.
+    #[serde(with = "humantime_serde", default = "default_timeout")]
+    pub timeout: Duration,
+
+    /// The outlets to measure, and the nodes that are plugged into them.
+    pub outlets: Vec<OutletConfig>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutletConfig {
+    /// Number of the outlet, starting at 1.
+    pub outlet: u32,
+    /// Name of the node plugged into the outlet, used as the id of the measured resource.
+    pub node: String,
+}
+
+fn default_community() -> String {
+    String::from("public")
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(2)
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            flush_interval: Duration::from_secs(10),
+            pdus: vec![PduConfig {
+                host: String::from("192.168.1.100"),
+                community: default_community(),
+                vendor: Vendor::Apc,
+                custom: None,
+                timeout: default_timeout(),
+                outlets: vec![
+                    OutletConfig {
+                        outlet: 1,
+                        node: String::from("node-1"),
+                    },
+                    OutletConfig {
+                        outlet: 2,
+                        node: String::from("node-2"),
+                    },
+                ],
+            }],
+        }
+    }
+}
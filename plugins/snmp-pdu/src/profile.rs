@@ -0,0 +1,174 @@
+//! OIDs of the outlet measurements, for each PDU vendor.
+
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::snmp::Oid;
+
+/// Vendor of a PDU, which determines the OIDs to query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Vendor {
+    /// APC metered-by-outlet PDUs (PowerNet-MIB, `rPDU2OutletMeteredStatusTable`).
+    Apc,
+    /// Eaton ePDUs (EATON-EPDU-MIB, `outletTable`), first PDU of the daisy chain.
+    Eaton,
+    /// Raritan PX PDUs (PDU2-MIB, `measurementsOutletSensorTable`), first PDU.
+    Raritan,
+    /// Custom OIDs, given in the configuration.
+    Custom,
+}
+
+/// Custom OIDs, for the PDUs that are not supported out of the box.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomProfile {
+    /// OID of the active power of an outlet, where `{outlet}` is replaced by the outlet number.
+    pub power_oid: Option<String>,
+    /// Factor that converts the power value to Watts.
+    #[serde(default = "default_scale")]
+    pub power_scale: f64,
+    /// OID of the energy counter of an outlet, where `{outlet}` is replaced by the outlet number.
+    pub energy_oid: Option<String>,
+    /// Factor that converts the energy value to Watt-hours.
+    #[serde(default = "default_scale")]
+    pub energy_scale: f64,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// How to measure the outlets of a PDU.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    power_oid: Option<String>,
+    power_scale: f64,
+    energy_oid: Option<String>,
+    energy_scale: f64,
+}
+
+/// The OIDs of one outlet.
+#[derive(Debug, PartialEq)]
+pub struct OutletOids {
+    pub power: Option<Oid>,
+    pub energy: Option<Oid>,
+}
+
+impl Profile {
+    pub fn new(vendor: Vendor, custom: Option<&CustomProfile>) -> anyhow::Result<Self> {
+        let profile = match (vendor, custom) {
+            (Vendor::Custom, Some(custom)) => Profile {
+                power_oid: custom.power_oid.clone(),
+                power_scale: custom.power_scale,
+                energy_oid: custom.energy_oid.clone(),
+                energy_scale: custom.energy_scale,
+            },
+            (Vendor::Custom, None) => return Err(anyhow!("the custom vendor requires a `custom` profile")),
+            (_, Some(_)) => return Err(anyhow!("the `custom` profile requires vendor = \"custom\"")),
+            (Vendor::Apc, None) => Profile {
+                // rPDU2OutletMeteredStatusPower (W), rPDU2OutletMeteredStatusEnergy (0.1 kWh)
+                power_oid: Some(String::from("1.3.6.1.4.1.318.1.1.26.9.4.3.1.7.{outlet}")),
+                power_scale: 1.0,
+                energy_oid: Some(String::from("1.3.6.1.4.1.318.1.1.26.9.4.3.1.11.{outlet}")),
+                energy_scale: 100.0,
+            },
+            (Vendor::Eaton, None) => Profile {
+                // outletWatts (W), outletWh (Wh)
+                power_oid: Some(String::from("1.3.6.1.4.1.534.6.6.7.6.5.1.3.0.{outlet}")),
+                power_scale: 1.0,
+                energy_oid: Some(String::from("1.3.6.1.4.1.534.6.6.7.6.5.1.4.0.{outlet}")),
+                energy_scale: 1.0,
+            },
+            (Vendor::Raritan, None) => Profile {
+                // measurementsOutletSensorValue, sensor types activePower (5, W) and activeEnergy (8, Wh)
+                power_oid: Some(String::from("1.3.6.1.4.1.13742.6.5.4.3.1.4.1.{outlet}.5")),
+                power_scale: 1.0,
+                energy_oid: Some(String::from("1.3.6.1.4.1.13742.6.5.4.3.1.4.1.{outlet}.8")),
+                energy_scale: 1.0,
+            },
+        };
+        if profile.power_oid.is_none() && profile.energy_oid.is_none() {
+            return Err(anyhow!("the profile must contain a power OID, an energy OID, or both"));
+        }
+        Ok(profile)
+    }
+
+    /// Returns the OIDs to query for the given outlet.
+    pub fn outlet_oids(&self, outlet: u32) -> anyhow::Result<OutletOids> {
+        let oid = |template: &Option<String>| -> anyhow::Result<Option<Oid>> {
+            template
+                .as_ref()
+                .map(|t| {
+                    let oid = t.replace("{outlet}", &outlet.to_string());
+                    oid.parse().with_context(|| format!("invalid OID for outlet {outlet}"))
+                })
+                .transpose()
+        };
+        Ok(OutletOids {
+            power: oid(&self.power_oid)?,
+            energy: oid(&self.energy_oid)?,
+        })
+    }
+
+    /// Converts a power value to Watts.
+    pub fn power_watts(&self, value: f64) -> f64 {
+        value * self.power_scale
+    }
+
+    /// Converts an energy value to Watt-hours.
+    pub fn energy_watt_hours(&self, value: f64) -> f64 {
+        value * self.energy_scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn vendor_profiles() {
+        let apc = Profile::new(Vendor::Apc, None).unwrap();
+        let oids = apc.outlet_oids(3).unwrap();
+        assert_eq!(oids.power.unwrap().to_string(), "1.3.6.1.4.1.318.1.1.26.9.4.3.1.7.3");
+        assert_eq!(oids.energy.unwrap().to_string(), "1.3.6.1.4.1.318.1.1.26.9.4.3.1.11.3");
+        assert_eq!(apc.energy_watt_hours(12.0), 1200.0);
+
+        let raritan = Profile::new(Vendor::Raritan, None).unwrap();
+        let oids = raritan.outlet_oids(12).unwrap();
+        assert_eq!(oids.power.unwrap().to_string(), "1.3.6.1.4.1.13742.6.5.4.3.1.4.1.12.5");
+        assert_eq!(oids.energy.unwrap().to_string(), "1.3.6.1.4.1.13742.6.5.4.3.1.4.1.12.8");
+    }
+
+    #[test]
+    fn custom_profile() {
+        let custom = CustomProfile {
+            power_oid: Some(String::from("1.3.6.1.4.1.99999.1.{outlet}")),
+            power_scale: 0.5,
+            energy_oid: None,
+            energy_scale: 1.0,
+        };
+        let profile = Profile::new(Vendor::Custom, Some(&custom)).unwrap();
+        let oids = profile.outlet_oids(2).unwrap();
+        assert_eq!(
+            oids,
+            OutletOids {
+                power: Some("1.3.6.1.4.1.99999.1.2".parse().unwrap()),
+                energy: None,
+            }
+        );
+        assert_eq!(profile.power_watts(1234.0), 617.0);
+
+        assert!(Profile::new(Vendor::Custom, None).is_err());
+        assert!(Profile::new(Vendor::Apc, Some(&custom)).is_err());
+
+        let invalid = CustomProfile {
+            power_oid: Some(String::from("1.3.6.{port}")),
+            ..custom
+        };
+        let profile = Profile::new(Vendor::Custom, Some(&invalid)).unwrap();
+        assert!(profile.outlet_oids(1).is_err());
+    }
+}
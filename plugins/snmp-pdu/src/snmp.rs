@@ -0,0 +1,478 @@
+//! Minimal SNMPv2c client, which only supports the GetRequest.
+//!
+//! The messages are encoded with the Basic Encoding Rules (BER) of ASN.1.
+
+use std::{
+    fmt,
+    net::{ToSocketAddrs, UdpSocket},
+    str::FromStr,
+    time::Duration,
+};
+
+const SNMP_VERSION_2C: i64 = 1;
+
+// BER tags
+const TAG_INTEGER: u8 = 0x02;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_COUNTER32: u8 = 0x41;
+const TAG_GAUGE32: u8 = 0x42;
+const TAG_TIMETICKS: u8 = 0x43;
+const TAG_COUNTER64: u8 = 0x46;
+const TAG_NO_SUCH_OBJECT: u8 = 0x80;
+const TAG_NO_SUCH_INSTANCE: u8 = 0x81;
+const TAG_END_OF_MIB_VIEW: u8 = 0x82;
+const TAG_GET_REQUEST: u8 = 0xa0;
+const TAG_GET_RESPONSE: u8 = 0xa2;
+
+/// Maximum size of an SNMP message over UDP.
+const MAX_MESSAGE_SIZE: usize = 65507;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnmpError {
+    #[error("SNMP I/O error")]
+    Io(#[from] std::io::Error),
+    #[error("invalid SNMP message: {0}")]
+    InvalidMessage(&'static str),
+    #[error("the SNMP agent returned error {status} at index {index}")]
+    ErrorStatus { status: i64, index: i64 },
+}
+
+/// An object identifier, such as `1.3.6.1.2.1.1.3.0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Oid(Vec<u32>);
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid OID '{0}'")]
+pub struct InvalidOid(String);
+
+impl FromStr for Oid {
+    type Err = InvalidOid;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Result<Vec<u32>, _> = s.trim_start_matches('.').split('.').map(u32::from_str).collect();
+        match parts {
+            Ok(parts) if parts.len() >= 2 && parts[0] <= 2 => Ok(Oid(parts)),
+            _ => Err(InvalidOid(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Oid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self.0.iter().map(u32::to_string).collect();
+        f.write_str(&parts.join("."))
+    }
+}
+
+/// Value of a variable binding.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnmpValue {
+    Integer(i64),
+    Unsigned(u64),
+    OctetString(Vec<u8>),
+    /// The object does not exist on the agent (`noSuchObject`, `noSuchInstance`, `endOfMibView` or `NULL`).
+    Missing,
+}
+
+impl SnmpValue {
+    /// Returns the value as a number, if possible.
+    ///
+    /// Some agents return the numbers as strings, which are parsed.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            SnmpValue::Integer(v) => Some(*v as f64),
+            SnmpValue::Unsigned(v) => Some(*v as f64),
+            SnmpValue::OctetString(s) => std::str::from_utf8(s).ok()?.trim().parse().ok(),
+            SnmpValue::Missing => None,
+        }
+    }
+}
+
+/// A SNMPv2c client.
+pub struct SnmpClient {
+    socket: UdpSocket,
+    community: String,
+    next_request_id: i32,
+}
+
+impl SnmpClient {
+    /// Connects to an SNMP agent, for instance `10.0.0.5:161`.
+    pub fn connect(addr: impl ToSocketAddrs, community: String, timeout: Duration) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(addr)?;
+        socket.set_read_timeout(Some(timeout))?;
+        Ok(Self {
+            socket,
+            community,
+            next_request_id: 1,
+        })
+    }
+
+    /// Gets the values of several objects, in one request.
+    pub fn get(&mut self, oids: &[Oid]) -> Result<Vec<SnmpValue>, SnmpError> {
+        let request_id = self.next_request_id;
+        self.next_request_id = self.next_request_id.wrapping_add(1).max(1);
+        let request = encode_get_request(&self.community, request_id, oids);
+        self.socket.send(&request)?;
+
+        let mut buf = vec![0u8; MAX_MESSAGE_SIZE];
+        loop {
+            let n = self.socket.recv(&mut buf)?;
+            let response = decode_get_response(&buf[..n])?;
+            // ignore the late responses to the previous requests
+            if response.request_id != i64::from(request_id) {
+                continue;
+            }
+            if response.error_status != 0 {
+                return Err(SnmpError::ErrorStatus {
+                    status: response.error_status,
+                    index: response.error_index,
+                });
+            }
+            if response.values.len() != oids.len() {
+                return Err(SnmpError::InvalidMessage("wrong number of variable bindings"));
+            }
+            return Ok(response.values.into_iter().map(|(_, value)| value).collect());
+        }
+    }
+}
+
+// ---- encoding
+
+fn encode_length(len: usize, out: &mut Vec<u8>) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+}
+
+fn encode_tlv(tag: u8, content: &[u8], out: &mut Vec<u8>) {
+    out.push(tag);
+    encode_length(content.len(), out);
+    out.extend_from_slice(content);
+}
+
+fn encode_integer(value: i64, out: &mut Vec<u8>) {
+    let bytes = value.to_be_bytes();
+    // remove the redundant leading bytes, while keeping the sign bit
+    let mut start = 0;
+    while start < 7
+        && ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0))
+    {
+        start += 1;
+    }
+    encode_tlv(TAG_INTEGER, &bytes[start..], out);
+}
+
+fn encode_oid(oid: &Oid, out: &mut Vec<u8>) {
+    let mut content = Vec::new();
+    let arcs = &oid.0;
+    encode_subidentifier(arcs[0] * 40 + arcs[1], &mut content);
+    for arc in &arcs[2..] {
+        encode_subidentifier(*arc, &mut content);
+    }
+    encode_tlv(TAG_OID, &content, out);
+}
+
+fn encode_subidentifier(value: u32, out: &mut Vec<u8>) {
+    let mut groups = vec![(value & 0x7f) as u8];
+    let mut rest = value >> 7;
+    while rest > 0 {
+        groups.push(0x80 | (rest & 0x7f) as u8);
+        rest >>= 7;
+    }
+    out.extend(groups.iter().rev());
+}
+
+fn encode_get_request(community: &str, request_id: i32, oids: &[Oid]) -> Vec<u8> {
+    let mut varbinds = Vec::new();
+    for oid in oids {
+        let mut varbind = Vec::new();
+        encode_oid(oid, &mut varbind);
+        encode_tlv(TAG_NULL, &[], &mut varbind);
+        encode_tlv(TAG_SEQUENCE, &varbind, &mut varbinds);
+    }
+
+    let mut pdu = Vec::new();
+    encode_integer(i64::from(request_id), &mut pdu);
+    encode_integer(0, &mut pdu); // error-status
+    encode_integer(0, &mut pdu); // error-index
+    encode_tlv(TAG_SEQUENCE, &varbinds, &mut pdu);
+
+    let mut message = Vec::new();
+    encode_integer(SNMP_VERSION_2C, &mut message);
+    encode_tlv(TAG_OCTET_STRING, community.as_bytes(), &mut message);
+    encode_tlv(TAG_GET_REQUEST, &pdu, &mut message);
+
+    let mut out = Vec::new();
+    encode_tlv(TAG_SEQUENCE, &message, &mut out);
+    out
+}
+
+// ---- decoding
+
+struct GetResponse {
+    request_id: i64,
+    error_status: i64,
+    error_index: i64,
+    values: Vec<(Oid, SnmpValue)>,
+}
+
+/// Reads BER elements from a buffer.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Reads the next element, and returns its tag and content.
+    fn read_tlv(&mut self) -> Result<(u8, &'a [u8]), SnmpError> {
+        let [tag, first_len, rest @ ..] = self.data else {
+            return Err(SnmpError::InvalidMessage("truncated element"));
+        };
+        let (len, rest) = if first_len & 0x80 == 0 {
+            (*first_len as usize, rest)
+        } else {
+            let n = (first_len & 0x7f) as usize;
+            if n == 0 || n > size_of::<usize>() || rest.len() < n {
+                return Err(SnmpError::InvalidMessage("invalid length"));
+            }
+            let len = rest[..n].iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+            (len, &rest[n..])
+        };
+        if rest.len() < len {
+            return Err(SnmpError::InvalidMessage("truncated element"));
+        }
+        let (content, rest) = rest.split_at(len);
+        self.data = rest;
+        Ok((*tag, content))
+    }
+
+    fn read_expected(&mut self, expected_tag: u8) -> Result<&'a [u8], SnmpError> {
+        let (tag, content) = self.read_tlv()?;
+        if tag != expected_tag {
+            return Err(SnmpError::InvalidMessage("unexpected tag"));
+        }
+        Ok(content)
+    }
+
+    fn read_integer(&mut self) -> Result<i64, SnmpError> {
+        decode_integer(self.read_expected(TAG_INTEGER)?)
+    }
+}
+
+fn decode_integer(content: &[u8]) -> Result<i64, SnmpError> {
+    if content.is_empty() || content.len() > 8 {
+        return Err(SnmpError::InvalidMessage("invalid integer"));
+    }
+    // sign extension
+    let init = if content[0] & 0x80 != 0 { -1 } else { 0 };
+    Ok(content.iter().fold(init, |acc, b| (acc << 8) | i64::from(*b)))
+}
+
+fn decode_unsigned(content: &[u8]) -> Result<u64, SnmpError> {
+    // unsigned values can have a leading zero byte
+    if content.is_empty() || content.len() > 9 || (content.len() == 9 && content[0] != 0) {
+        return Err(SnmpError::InvalidMessage("invalid unsigned integer"));
+    }
+    Ok(content.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b)))
+}
+
+fn decode_oid(content: &[u8]) -> Result<Oid, SnmpError> {
+    let mut arcs = Vec::new();
+    let mut value: u32 = 0;
+    for b in content {
+        value = value
+            .checked_mul(128)
+            .ok_or(SnmpError::InvalidMessage("OID arc too large"))?
+            | u32::from(b & 0x7f);
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    if arcs.is_empty() {
+        return Err(SnmpError::InvalidMessage("empty OID"));
+    }
+    Ok(Oid(arcs))
+}
+
+fn decode_value(tag: u8, content: &[u8]) -> Result<SnmpValue, SnmpError> {
+    Ok(match tag {
+        TAG_INTEGER => SnmpValue::Integer(decode_integer(content)?),
+        TAG_COUNTER32 | TAG_GAUGE32 | TAG_TIMETICKS | TAG_COUNTER64 => SnmpValue::Unsigned(decode_unsigned(content)?),
+        TAG_OCTET_STRING => SnmpValue::OctetString(content.to_vec()),
+        TAG_NULL | TAG_NO_SUCH_OBJECT | TAG_NO_SUCH_INSTANCE | TAG_END_OF_MIB_VIEW => SnmpValue::Missing,
+        _ => return Err(SnmpError::InvalidMessage("unsupported value type")),
+    })
+}
+
+fn decode_get_response(data: &[u8]) -> Result<GetResponse, SnmpError> {
+    let mut message = Reader::new(Reader::new(data).read_expected(TAG_SEQUENCE)?);
+    let _version = message.read_integer()?;
+    let _community = message.read_expected(TAG_OCTET_STRING)?;
+    let mut pdu = Reader::new(message.read_expected(TAG_GET_RESPONSE)?);
+    let request_id = pdu.read_integer()?;
+    let error_status = pdu.read_integer()?;
+    let error_index = pdu.read_integer()?;
+    let mut varbinds = Reader::new(pdu.read_expected(TAG_SEQUENCE)?);
+    let mut values = Vec::new();
+    while !varbinds.is_empty() {
+        let mut varbind = Reader::new(varbinds.read_expected(TAG_SEQUENCE)?);
+        let oid = decode_oid(varbind.read_expected(TAG_OID)?)?;
+        let (tag, content) = varbind.read_tlv()?;
+        values.push((oid, decode_value(tag, content)?));
+    }
+    Ok(GetResponse {
+        request_id,
+        error_status,
+        error_index,
+        values,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn oid_parsing() {
+        let oid: Oid = "1.3.6.1.4.1.318.1.1.26.9.4.3.1.7.1".parse().unwrap();
+        assert_eq!(oid.to_string(), "1.3.6.1.4.1.318.1.1.26.9.4.3.1.7.1");
+        assert_eq!(".1.3.6".parse::<Oid>().unwrap().to_string(), "1.3.6");
+        assert!("1".parse::<Oid>().is_err());
+        assert!("1.3.x".parse::<Oid>().is_err());
+        assert!("5.3.6".parse::<Oid>().is_err());
+    }
+
+    #[test]
+    fn integer_encoding() {
+        let encode = |v| {
+            let mut out = Vec::new();
+            encode_integer(v, &mut out);
+            out
+        };
+        assert_eq!(encode(0), vec![0x02, 0x01, 0x00]);
+        assert_eq!(encode(127), vec![0x02, 0x01, 0x7f]);
+        assert_eq!(encode(128), vec![0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(encode(-1), vec![0x02, 0x01, 0xff]);
+        assert_eq!(encode(-129), vec![0x02, 0x02, 0xff, 0x7f]);
+        for v in [
+            0,
+            1,
+            127,
+            128,
+            255,
+            256,
+            65535,
+            -1,
+            -128,
+            -129,
+            i32::MAX as i64,
+            i64::MIN,
+        ] {
+            let encoded = encode(v);
+            assert_eq!(decode_integer(&encoded[2..]).unwrap(), v);
+        }
+    }
+
+    #[test]
+    fn get_request() {
+        // same message as `snmpget -v2c -c public host 1.3.6.1.2.1.1.3.0`, with request-id 1
+        let oid = "1.3.6.1.2.1.1.3.0".parse().unwrap();
+        let request = encode_get_request("public", 1, &[oid]);
+        #[rustfmt::skip]
+        let expected = vec![
+            0x30, 0x26,
+                0x02, 0x01, 0x01,
+                0x04, 0x06, b'p', b'u', b'b', b'l', b'i', b'c',
+                0xa0, 0x19,
+                    0x02, 0x01, 0x01,
+                    0x02, 0x01, 0x00,
+                    0x02, 0x01, 0x00,
+                    0x30, 0x0e,
+                        0x30, 0x0c,
+                            0x06, 0x08, 0x2b, 0x06, 0x01, 0x02, 0x01, 0x01, 0x03, 0x00,
+                            0x05, 0x00,
+        ];
+        assert_eq!(request, expected);
+    }
+
+    #[test]
+    fn get_response() {
+        #[rustfmt::skip]
+        let response = vec![
+            0x30, 0x4e,
+                0x02, 0x01, 0x01,
+                0x04, 0x06, b'p', b'u', b'b', b'l', b'i', b'c',
+                0xa2, 0x41,
+                    0x02, 0x02, 0x01, 0x2c,
+                    0x02, 0x01, 0x00,
+                    0x02, 0x01, 0x00,
+                    0x30, 0x35,
+                        // integer
+                        0x30, 0x0b,
+                            0x06, 0x06, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x01,
+                            0x02, 0x01, 0x2a,
+                        // gauge with a leading zero
+                        0x30, 0x0d,
+                            0x06, 0x06, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x02,
+                            0x42, 0x03, 0x00, 0x80, 0x00,
+                        // noSuchInstance
+                        0x30, 0x0a,
+                            0x06, 0x06, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x03,
+                            0x81, 0x00,
+                        // string, with a multi-byte arc (318)
+                        0x30, 0x0b,
+                            0x06, 0x05, 0x2b, 0x06, 0x82, 0x3e, 0x01,
+                            0x04, 0x02, b'1', b'2',
+        ];
+        let response = decode_get_response(&response).unwrap();
+        assert_eq!(response.request_id, 300);
+        assert_eq!(response.error_status, 0);
+        let oids: Vec<String> = response.values.iter().map(|(oid, _)| oid.to_string()).collect();
+        assert_eq!(
+            oids,
+            vec!["1.3.6.1.4.1.1", "1.3.6.1.4.1.2", "1.3.6.1.4.1.3", "1.3.6.318.1"]
+        );
+        let values: Vec<Option<f64>> = response.values.iter().map(|(_, v)| v.as_f64()).collect();
+        assert_eq!(values, vec![Some(42.0), Some(32768.0), None, Some(12.0)]);
+
+        // the OIDs are encoded in the same way
+        let mut encoded = Vec::new();
+        encode_oid(&"1.3.6.318.1".parse().unwrap(), &mut encoded);
+        assert_eq!(encoded, vec![0x06, 0x05, 0x2b, 0x06, 0x82, 0x3e, 0x01]);
+    }
+
+    #[test]
+    fn truncated_response() {
+        let response = [0x30, 0x42, 0x02, 0x01];
+        assert!(matches!(
+            decode_get_response(&response),
+            Err(SnmpError::InvalidMessage(_))
+        ));
+    }
+}
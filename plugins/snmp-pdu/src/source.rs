@@ -0,0 +1,152 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+use anyhow::Context;
+
+use crate::{
+    profile::Profile,
+    snmp::{Oid, SnmpClient, SnmpValue},
+};
+
+/// Maximum number of OIDs in one SNMP request, to keep the messages small.
+const MAX_OIDS_PER_REQUEST: usize = 24;
+
+/// Number of Joules in a Watt-hour.
+const JOULES_PER_WATT_HOUR: f64 = 3600.0;
+
+/// Contains the ids of the measured metrics.
+#[derive(Clone, Copy)]
+pub struct Metrics {
+    power: TypedMetricId<f64>,
+    energy: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            power: alumet.create_metric("pdu_outlet_power", Unit::Watt, "Active power of a PDU outlet")?,
+            energy: alumet.create_metric(
+                "pdu_outlet_energy",
+                Unit::Joule,
+                "Energy consumed by a PDU outlet since the previous measurement",
+            )?,
+        })
+    }
+}
+
+/// An outlet of the PDU, and the node that is plugged into it.
+struct Outlet {
+    number: u32,
+    node: String,
+    /// Index of the power OID in the list of OIDs to query.
+    power: Option<usize>,
+    /// Index of the energy OID in the list of OIDs to query.
+    energy: Option<usize>,
+    /// Previous value of the energy counter, in Wh.
+    previous_energy: Option<f64>,
+}
+
+/// Measurement source that queries a PDU with SNMP.
+pub struct PduSource {
+    client: SnmpClient,
+    /// Address of the PDU, added to the measurements as an attribute.
+    host: String,
+    profile: Profile,
+    metrics: Metrics,
+    outlets: Vec<Outlet>,
+    oids: Vec<Oid>,
+}
+
+impl PduSource {
+    pub fn new(
+        client: SnmpClient,
+        host: String,
+        profile: Profile,
+        metrics: Metrics,
+        outlets: Vec<(u32, String)>,
+    ) -> anyhow::Result<Self> {
+        let mut oids = Vec::new();
+        let mut push = |oid: Option<Oid>| {
+            oid.map(|oid| {
+                oids.push(oid);
+                oids.len() - 1
+            })
+        };
+        let mut source_outlets = Vec::with_capacity(outlets.len());
+        for (number, node) in outlets {
+            let outlet_oids = profile.outlet_oids(number)?;
+            source_outlets.push(Outlet {
+                number,
+                node,
+                power: push(outlet_oids.power),
+                energy: push(outlet_oids.energy),
+                previous_energy: None,
+            });
+        }
+        Ok(Self {
+            client,
+            host,
+            profile,
+            metrics,
+            outlets: source_outlets,
+            oids,
+        })
+    }
+
+    fn query(&mut self) -> anyhow::Result<Vec<SnmpValue>> {
+        let mut values = Vec::with_capacity(self.oids.len());
+        for oids in self.oids.chunks(MAX_OIDS_PER_REQUEST) {
+            let chunk = self
+                .client
+                .get(oids)
+                .with_context(|| format!("SNMP request to {} failed", self.host))?;
+            values.extend(chunk);
+        }
+        Ok(values)
+    }
+}
+
+impl Source for PduSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        // The PDU may be temporarily unreachable: try again at the next measurement.
+        let values = self.query().map_err(PollError::CanRetry)?;
+        let value = |index: Option<usize>| index.and_then(|i| values[i].as_f64());
+
+        for outlet in &mut self.outlets {
+            let resource = Resource::custom("node", outlet.node.clone());
+            let number = outlet.number as u64;
+            let point = |metric, value| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    resource.clone(),
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("pdu", self.host.clone())
+                .with_attr("outlet", number)
+            };
+
+            if let Some(power) = value(outlet.power) {
+                measurements.push(point(self.metrics.power, self.profile.power_watts(power)));
+            }
+            if let Some(energy) = value(outlet.energy) {
+                let energy = self.profile.energy_watt_hours(energy);
+                // the counter can be reset by the PDU: skip the measurement in that case
+                if let Some(previous) = outlet.previous_energy
+                    && energy >= previous
+                {
+                    let diff = (energy - previous) * JOULES_PER_WATT_HOUR;
+                    measurements.push(point(self.metrics.energy, diff));
+                }
+                outlet.previous_energy = Some(energy);
+            }
+        }
+        Ok(())
+    }
+}
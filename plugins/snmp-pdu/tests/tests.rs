@@ -0,0 +1,161 @@
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    measurement::{MeasurementBuffer, MeasurementPoint},
+    metrics::RawMetricId,
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_snmp_pdu::{Config, OutletConfig, PduConfig, SnmpPduPlugin, Vendor};
+use std::{net::UdpSocket, time::Duration};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const PLUGIN_NAME: &str = "snmp-pdu";
+
+#[test]
+fn plugin_with_fake_apc_pdu() {
+    let pdu = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let host = pdu.local_addr().unwrap().to_string();
+    std::thread::spawn(move || fake_apc_pdu(pdu));
+
+    let config = Config {
+        poll_interval: Duration::from_secs(1),
+        flush_interval: Duration::from_secs(1),
+        pdus: vec![PduConfig {
+            host: host.clone(),
+            community: String::from("public"),
+            vendor: Vendor::Apc,
+            custom: None,
+            timeout: Duration::from_secs(1),
+            outlets: vec![OutletConfig {
+                outlet: 1,
+                node: String::from("node-1"),
+            }],
+        }],
+    };
+
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("pdu_outlet_power", Unit::Watt)
+        .expect_metric::<f64>("pdu_outlet_energy", Unit::Joule)
+        .expect_source(PLUGIN_NAME, &host);
+
+    let source = SourceName::from_str(PLUGIN_NAME, &host);
+    let runtime_expectation = RuntimeExpectations::new()
+        // the energy counter needs two measurements
+        .test_source(
+            source.clone(),
+            || {},
+            |ctx| {
+                let m = ctx.measurements();
+                let power_metric = ctx.metrics().by_name("pdu_outlet_power").unwrap().0;
+                let power = find_point(m, power_metric);
+                assert_eq!(power.value.as_f64(), 120.0);
+                assert_eq!(power.resource, Resource::custom("node", "node-1"));
+                assert_eq!(m.len(), 1, "only the power should be measured on the first poll");
+            },
+        )
+        .test_source(
+            source,
+            || {},
+            |ctx| {
+                let m = ctx.measurements();
+                let energy_metric = ctx.metrics().by_name("pdu_outlet_energy").unwrap().0;
+                // +0.1 kWh
+                assert_eq!(find_point(m, energy_metric).value.as_f64(), 360000.0);
+            },
+        );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup_expectation)
+        .with_expectations(runtime_expectation)
+        .build_and_start()
+        .unwrap();
+
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+#[test]
+fn plugin_with_invalid_profile() {
+    let mut config = Config::default();
+    config.pdus[0].vendor = Vendor::Custom;
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no custom OIDs)");
+}
+
+/// Answers the SNMP requests like an APC PDU, whose energy counter increases at each request.
+fn fake_apc_pdu(socket: UdpSocket) {
+    let mut energy = 10;
+    let mut buf = [0u8; 1500];
+    loop {
+        let (n, peer) = socket.recv_from(&mut buf).unwrap();
+        // message: version, community, GetRequest
+        let (_, message, _) = read_tlv(&buf[..n]);
+        let (_, version, rest) = read_tlv(message);
+        let (_, community, rest) = read_tlv(rest);
+        let (tag, pdu, _) = read_tlv(rest);
+        assert_eq!(tag, 0xa0);
+        let (_, request_id, rest) = read_tlv(pdu);
+        let (_, _, rest) = read_tlv(rest);
+        let (_, _, rest) = read_tlv(rest);
+        let (_, mut varbinds, _) = read_tlv(rest);
+
+        let mut response_varbinds = Vec::new();
+        while !varbinds.is_empty() {
+            let (_, varbind, rest) = read_tlv(varbinds);
+            varbinds = rest;
+            let (_, oid, _) = read_tlv(varbind);
+            // rPDU2OutletMeteredStatusPower.1 and rPDU2OutletMeteredStatusEnergy.1
+            let value = match oid[oid.len() - 2..] {
+                [7, 1] => 120,
+                [11, 1] => {
+                    energy += 1;
+                    energy - 1
+                }
+                _ => panic!("unexpected OID {oid:?}"),
+            };
+            let mut varbind = tlv(0x06, oid);
+            varbind.extend(tlv(0x42, &[value]));
+            response_varbinds.extend(tlv(0x30, &varbind));
+        }
+
+        let mut pdu = tlv(0x02, request_id);
+        pdu.extend(tlv(0x02, &[0]));
+        pdu.extend(tlv(0x02, &[0]));
+        pdu.extend(tlv(0x30, &response_varbinds));
+        let mut message = tlv(0x02, version);
+        message.extend(tlv(0x04, community));
+        message.extend(tlv(0xa2, &pdu));
+        socket.send_to(&tlv(0x30, &message), peer).unwrap();
+    }
+}
+
+/// Reads a BER element with a short length, returns its tag, its content and the remaining bytes.
+fn read_tlv(data: &[u8]) -> (u8, &[u8], &[u8]) {
+    let len = data[1] as usize;
+    assert!(len < 0x80, "long lengths are not supported by the fake PDU");
+    (data[0], &data[2..2 + len], &data[2 + len..])
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut res = vec![tag, content.len() as u8];
+    res.extend_from_slice(content);
+    res
+}
+
+fn find_point(m: &MeasurementBuffer, metric: RawMetricId) -> &MeasurementPoint {
+    m.iter()
+        .find(|p| p.metric == metric)
+        .unwrap_or_else(|| panic!("missing measurement for metric {metric:?}"))
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<SnmpPduPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -4,8 +4,7 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use alumet::pipeline::control::AnonymousControlHandle;
-use alumet::pipeline::control::handle::DispatchError;
-use alumet::pipeline::control::request::{self, any::AnyAnonymousControlRequest};
+use alumet::pipeline::control::request::{self, ElementListFilter, any::AnyAnonymousControlRequest};
 use alumet::pipeline::elements::source::trigger::TriggerSpec;
 use alumet::pipeline::matching::{
     ElementNamePattern, OutputNamePattern, SourceNamePattern, StringPattern, TransformNamePattern,
@@ -21,21 +20,63 @@ const COMMAND_TIMEOUT: Duration = Duration::from_secs(1);
 #[derive(Debug)]
 pub enum Command {
     Control(Vec<AnyAnonymousControlRequest>),
+    List(ElementNamePattern),
+    Stats,
     Shutdown,
 }
 
 impl Command {
-    pub async fn run(self, handle: &AnonymousControlHandle) -> Result<(), DispatchError> {
+    /// Runs the command and returns its output, line by line.
+    pub async fn run(self, handle: &AnonymousControlHandle) -> anyhow::Result<Vec<String>> {
         match self {
             Command::Control(messages) => {
                 for msg in messages {
                     handle.dispatch(msg, COMMAND_TIMEOUT).await?;
                 }
-                Ok(())
+                Ok(Vec::new())
+            }
+            Command::List(pattern) => {
+                let request = request::list_elements(ElementListFilter::from(pattern));
+                let mut names: Vec<String> = handle
+                    .send_wait(request, COMMAND_TIMEOUT)
+                    .await?
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect();
+                names.sort();
+                Ok(names)
+            }
+            Command::Stats => {
+                let elements = handle
+                    .send_wait(request::list_elements(ElementListFilter::kind_any()), COMMAND_TIMEOUT)
+                    .await?;
+                let health = handle.send_wait(request::plugin_health(), COMMAND_TIMEOUT).await?;
+                let failures = handle.send_wait(request::element_failures(), COMMAND_TIMEOUT).await?;
+
+                let count = |kind| elements.iter().filter(|name| name.kind == kind).count();
+                let mut lines = vec![format!(
+                    "elements: {} sources, {} transforms, {} outputs",
+                    count(ElementKind::Source),
+                    count(ElementKind::Transform),
+                    count(ElementKind::Output)
+                )];
+                for (plugin, report) in health {
+                    match report.message {
+                        Some(msg) => lines.push(format!("plugin {}: {} ({msg})", plugin.0, report.status)),
+                        None => lines.push(format!("plugin {}: {}", plugin.0, report.status)),
+                    }
+                }
+                for (element, count) in failures {
+                    lines.push(format!(
+                        "{element}: {} recoverable errors, {} fatal errors",
+                        count.recoverable, count.fatal
+                    ));
+                }
+                Ok(lines)
             }
             Command::Shutdown => {
                 handle.shutdown();
-                Ok(())
+                Ok(Vec::new())
             }
         }
     }
@@ -47,6 +88,9 @@ impl Command {
 ///
 /// - `shutdown` or `stop`: shutdowns the measurement pipeline
 /// - `control <PATTERN> [ARGS...]`: reconfigures a part of the pipeline (see below)
+/// - `plugin <NAME> enable|disable`: enables or disables all the elements of a plugin
+/// - `list [PATTERN]`: lists the elements of the pipeline, or those that match the pattern
+/// - `stats`: shows the number of elements, the health of the plugins and the errors of the elements
 ///
 /// ### Control arguments
 ///
@@ -167,7 +211,10 @@ pub fn parse(command: &str) -> anyhow::Result<Command> {
     }
 
     let parts: Vec<&str> = command.split_ascii_whitespace().collect();
-    match parts[0] {
+    let Some(name) = parts.first() else {
+        return Err(anyhow!("empty command"));
+    };
+    match *name {
         "shutdown" | "stop" => Ok(Command::Shutdown),
         "control" => {
             let pat = parts
@@ -178,8 +225,24 @@ pub fn parse(command: &str) -> anyhow::Result<Command> {
                 parse_control_args(pattern, &parts[2..]).with_context(|| format!("invalid command '{command}'"))?;
             Ok(Command::Control(messages))
         }
+        "plugin" => match parts[1..] {
+            [plugin, "enable"] => Ok(Command::Control(vec![request::plugin(plugin).enable().into()])),
+            [plugin, "disable"] => Ok(Command::Control(vec![request::plugin(plugin).disable().into()])),
+            [_, _] => Err(anyhow!(
+                "invalid command '{command}': expected 'enable' or 'disable' after the plugin name"
+            )),
+            _ => Err(anyhow!(
+                "invalid command '{command}': expected 'plugin <NAME> enable|disable'"
+            )),
+        },
+        "list" => match parts[1..] {
+            [] => Ok(Command::List(parse_pattern("*")?)),
+            [pat] => Ok(Command::List(parse_pattern(pat)?)),
+            _ => Err(anyhow!("invalid command '{command}': expected 'list [PATTERN]'")),
+        },
+        "stats" => Ok(Command::Stats),
         _ => Err(anyhow!(
-            "unknown command '{command}'; available commands are 'shutdown' or 'control'"
+            "unknown command '{command}'; available commands are 'shutdown', 'control', 'plugin', 'list' or 'stats'"
         )),
     }
 }
@@ -373,6 +436,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn plugin_disable_enable() {
+        assert_control_eq(
+            parse("plugin rapl disable").unwrap(),
+            vec![request::plugin("rapl").disable().into()],
+        );
+        assert_control_eq(
+            parse("plugin rapl enable").unwrap(),
+            vec![request::plugin("rapl").enable().into()],
+        );
+        assert!(parse("plugin rapl").is_err());
+        assert!(parse("plugin rapl pause").is_err());
+    }
+
     #[test]
     fn parse_pattern_wrong_pattern() {
         assert_eq!(
@@ -381,6 +458,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn list_stats() {
+        match parse("list").unwrap() {
+            Command::List(pat) => assert_eq!(pat, parse_pattern("*").unwrap()),
+            cmd => panic!("wrong command {cmd:?}"),
+        }
+        match parse("list source/rapl/*").unwrap() {
+            Command::List(pat) => assert_eq!(pat, parse_pattern("source/rapl/*").unwrap()),
+            cmd => panic!("wrong command {cmd:?}"),
+        }
+        assert!(parse("list a b").is_err());
+        assert!(matches!(parse("stats").unwrap(), Command::Stats));
+        assert!(parse("").is_err());
+    }
+
     #[test]
     fn control_common_errors() {
         assert_eq!(
@@ -0,0 +1,191 @@
+//! Health and readiness endpoints, for Kubernetes probes and load balancers.
+//!
+//! - `GET /healthz` returns the state of the pipeline, the health of the plugins and the failure count
+//!   of the elements. The status code is `200` if the agent is healthy or degraded, `503` otherwise.
+//! - `GET /readyz` returns `200` once the pipeline is running, and `503` when it is shutting down.
+
+use std::time::Duration;
+
+use alumet::{
+    pipeline::control::{AnonymousControlHandle, request},
+    plugin::health::HealthStatus,
+};
+use anyhow::{Context, anyhow};
+use serde_json::{Map, Value, json};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::sync::CancellationToken;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_HEADER_SIZE: u64 = 8192;
+
+/// A response to a probe.
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+/// Accepts HTTP connections until `cancel_token` is cancelled.
+pub async fn serve(listener: TcpListener, alumet_handle: AnonymousControlHandle, cancel_token: CancellationToken) {
+    loop {
+        tokio::select! {
+            biased;
+
+            _ = cancel_token.cancelled() => break,
+            new_connection = listener.accept() => match new_connection {
+                Ok((stream, _addr)) => {
+                    let alumet_handle = alumet_handle.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, &alumet_handle).await {
+                            log::warn!("Error in HTTP health endpoint: {e:#}");
+                        }
+                    });
+                }
+                Err(e) => log::error!("Failed to accept new HTTP connection: {e:#}"),
+            }
+        }
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, alumet_handle: &AnonymousControlHandle) -> anyhow::Result<()> {
+    let (method, path) = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream))
+        .await
+        .map_err(|_| anyhow!("timeout expired while reading the request"))??;
+
+    let response = match (method.as_str(), path.as_str()) {
+        ("GET" | "HEAD", "/healthz") => healthz(alumet_handle).await,
+        ("GET" | "HEAD", "/readyz") => readyz(alumet_handle).await,
+        ("GET" | "HEAD", _) => Response {
+            status: 404,
+            body: json!({ "error": "not found" }),
+        },
+        _ => Response {
+            status: 405,
+            body: json!({ "error": "method not allowed" }),
+        },
+    };
+    write_response(&mut stream, &response, method == "HEAD").await
+}
+
+/// Reads the request line and the headers, returns the method and the path (without the query).
+async fn read_request(stream: &mut TcpStream) -> anyhow::Result<(String, String)> {
+    let mut reader = BufReader::new(stream.take(MAX_HEADER_SIZE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(anyhow!("invalid request line: {request_line:?}"));
+    };
+    let path = target.split('?').next().unwrap_or_default().to_owned();
+    let method = method.to_owned();
+
+    // skip the headers, we don't need them
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+    Ok((method, path))
+}
+
+async fn write_response(stream: &mut TcpStream, response: &Response, head_only: bool) -> anyhow::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Service Unavailable",
+    };
+    let body = response.body.to_string();
+    let mut buf = format!(
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        body.len()
+    );
+    if !head_only {
+        buf.push_str(&body);
+    }
+    stream
+        .write_all(buf.as_bytes())
+        .await
+        .context("could not write the response")?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Returns the state of the pipeline: `running`, `stopping` or `stopped`.
+fn pipeline_state(alumet_handle: &AnonymousControlHandle, responds: bool) -> &'static str {
+    if !responds {
+        "stopped"
+    } else if alumet_handle.is_shutdown_requested() {
+        "stopping"
+    } else {
+        "running"
+    }
+}
+
+async fn healthz(alumet_handle: &AnonymousControlHandle) -> Response {
+    let health = alumet_handle.send_wait(request::plugin_health(), CONTROL_TIMEOUT).await;
+    let failures = alumet_handle
+        .send_wait(request::element_failures(), CONTROL_TIMEOUT)
+        .await;
+    let (Ok(health), Ok(failures)) = (health, failures) else {
+        return Response {
+            status: 503,
+            body: json!({
+                "status": HealthStatus::Unhealthy.to_string(),
+                "pipeline": pipeline_state(alumet_handle, false),
+            }),
+        };
+    };
+
+    // The agent is as healthy as its least healthy plugin.
+    // An element that has stopped because of a fatal error makes the agent unhealthy.
+    let mut status = HealthStatus::Healthy;
+    let mut plugins = Map::new();
+    for (plugin, report) in health {
+        status = status.max(report.status);
+        plugins.insert(
+            plugin.0,
+            json!({ "status": report.status.to_string(), "message": report.message }),
+        );
+    }
+    let mut elements = Map::new();
+    for (element, count) in failures {
+        if count.fatal > 0 {
+            status = HealthStatus::Unhealthy;
+        }
+        elements.insert(
+            element.to_string(),
+            json!({ "recoverable": count.recoverable, "fatal": count.fatal }),
+        );
+    }
+    let pipeline = pipeline_state(alumet_handle, true);
+    Response {
+        status: if status == HealthStatus::Unhealthy { 503 } else { 200 },
+        body: json!({
+            "status": status.to_string(),
+            "pipeline": pipeline,
+            "plugins": plugins,
+            "failures": elements,
+        }),
+    }
+}
+
+async fn readyz(alumet_handle: &AnonymousControlHandle) -> Response {
+    // The control loop stops when the pipeline is shut down: use a request to check that it still responds.
+    let responds = alumet_handle
+        .send_wait(request::plugin_health(), CONTROL_TIMEOUT)
+        .await
+        .is_ok();
+    let pipeline = pipeline_state(alumet_handle, responds);
+    let ready = pipeline == "running";
+    Response {
+        status: if ready { 200 } else { 503 },
+        body: json!({ "ready": ready, "pipeline": pipeline }),
+    }
+}
@@ -1,4 +1,5 @@
 mod command;
+mod http;
 mod socket;
 
 use alumet::plugin::rust::{AlumetPlugin, deserialize_config, serialize_config};
@@ -10,6 +11,11 @@ use socket::SocketControl;
 #[serde(deny_unknown_fields)]
 pub struct Config {
     pub socket_path: String,
+    /// Address of the HTTP server that provides the `/healthz` and `/readyz` endpoints.
+    ///
+    /// The server is disabled if this is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_address: Option<String>,
 }
 
 pub struct SocketControlPlugin {
@@ -43,8 +49,14 @@ impl AlumetPlugin for SocketControlPlugin {
     fn post_pipeline_start(&mut self, alumet: &mut AlumetPostStart) -> anyhow::Result<()> {
         // Enable remote control via Unix socket.
         let control = SocketControl::start_new(alumet.pipeline_control(), &self.config.socket_path)?;
-        self.control = Some(control);
         log::info!("SocketControl enabled.");
+
+        // Enable the health endpoints.
+        if let Some(address) = &self.config.http_address {
+            control.serve_http(alumet.pipeline_control().anonymous(), address)?;
+            log::info!("Health endpoints available at http://{address}/healthz and http://{address}/readyz");
+        }
+        self.control = Some(control);
         Ok(())
     }
 
@@ -65,6 +77,7 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             socket_path: String::from("alumet-control.sock"),
+            http_address: None,
         }
     }
 }
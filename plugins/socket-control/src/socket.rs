@@ -9,7 +9,7 @@ use tokio::{
 };
 use tokio_util::sync::CancellationToken;
 
-use crate::command;
+use crate::{command, http};
 
 pub struct SocketControl {
     rt: Runtime,
@@ -79,6 +79,20 @@ impl SocketControl {
         Ok(SocketControl { rt, cancel_token })
     }
 
+    /// Serves the health and readiness endpoints on the given TCP address.
+    pub fn serve_http(&self, alumet_handle: AnonymousControlHandle, address: &str) -> anyhow::Result<()> {
+        // bind now, in order to report the errors on startup
+        let listener = std::net::TcpListener::bind(address).with_context(|| format!("could not bind to {address}"))?;
+        listener.set_nonblocking(true)?;
+        let listener = {
+            let _guard = self.rt.enter();
+            tokio::net::TcpListener::from_std(listener)?
+        };
+        self.rt
+            .spawn(http::serve(listener, alumet_handle, self.cancel_token.clone()));
+        Ok(())
+    }
+
     pub fn stop(&self) {
         self.cancel_token.cancel();
     }
@@ -88,21 +102,48 @@ impl SocketControl {
     }
 }
 
+/// Runs the commands received on the connection, one per line.
+///
+/// The output of each command is sent back, followed by a line that contains `ok` on success,
+/// or `error: <MESSAGE>` on failure.
 async fn handle_socket_connection(
     stream: UnixStream,
     _addr: SocketAddr,
     alumet_handle: &AnonymousControlHandle,
 ) -> anyhow::Result<()> {
-    use anyhow::anyhow;
-    use tokio::io::{AsyncBufReadExt, BufStream};
-
-    let buf = BufStream::new(stream);
-    let mut lines = buf.lines();
-    while let Some(line) = lines.next_line().await? {
-        let cmd = command::parse(&line)?;
-        cmd.run(alumet_handle)
-            .await
-            .map_err(|e| anyhow!("failed to run command {line}: {e}"))?;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufStream};
+
+    let mut stream = BufStream::new(stream);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if stream.read_line(&mut line).await? == 0 {
+            break; // connection closed
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let result = match command::parse(line) {
+            Ok(cmd) => cmd.run(alumet_handle).await,
+            Err(e) => Err(e),
+        };
+        let mut response = String::new();
+        match result {
+            Ok(output) => {
+                for out in output {
+                    response.push_str(&out);
+                    response.push('\n');
+                }
+                response.push_str("ok\n");
+            }
+            Err(e) => {
+                log::warn!("Failed to run command {line}: {e:#}");
+                response.push_str(&format!("error: {e:#}\n"));
+            }
+        }
+        stream.write_all(response.as_bytes()).await?;
+        stream.flush().await?;
     }
     Ok(())
 }
@@ -1,4 +1,9 @@
-use std::{io::Write, os::unix::net::UnixStream, time::Duration};
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    os::unix::net::UnixStream,
+    time::Duration,
+};
 
 use alumet::{
     agent::{
@@ -20,6 +25,7 @@ fn shutdown() {
 
     let plugin_config = serialize_config(Config {
         socket_path: socket_file.to_str().unwrap().to_owned(),
+        http_address: None,
     })
     .unwrap()
     .0;
@@ -49,6 +55,62 @@ fn shutdown() {
         .expect("alumet should stop");
 }
 
+#[test]
+fn health_endpoints() {
+    let tmp = tempfile::tempdir().unwrap();
+    let socket_file = tmp.path().join("control.sock");
+
+    // find a free port
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let http_address = format!("127.0.0.1:{port}");
+
+    let plugin_config = serialize_config(Config {
+        socket_path: socket_file.to_str().unwrap().to_owned(),
+        http_address: Some(http_address.clone()),
+    })
+    .unwrap()
+    .0;
+
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<SocketControlPlugin>(),
+        enabled: true,
+        config: Some(plugin_config),
+    });
+
+    let agent = agent::Builder::new(plugins)
+        .build_and_start()
+        .expect("alumet should start");
+
+    let response = http_get(&http_address, "/readyz");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(
+        response.ends_with(r#"{"pipeline":"running","ready":true}"#),
+        "{response}"
+    );
+
+    let response = http_get(&http_address, "/healthz");
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.contains(r#""status":"healthy""#), "{response}");
+    assert!(response.contains(r#""failures":{}"#), "{response}");
+
+    let response = http_get(&http_address, "/unknown");
+    assert!(response.starts_with("HTTP/1.1 404 Not Found"), "{response}");
+
+    agent.pipeline.control_handle().shutdown();
+    agent
+        .wait_for_shutdown(Duration::from_millis(250))
+        .expect("alumet should stop");
+}
+
+fn http_get(address: &str, path: &str) -> String {
+    let mut stream = TcpStream::connect(address).expect("I should be able to connect to the HTTP server");
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: {address}\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
 fn socket_write_line(stream: &mut UnixStream, line: &str) {
     let buf = format!("{line}\n").into_bytes();
     // the newline is important, because the plugin uses read_line() to parse the commands
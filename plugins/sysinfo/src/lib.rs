@@ -0,0 +1,80 @@
+//! Portable system metrics (CPU and memory usage), for Linux, Windows and macOS.
+use std::time::Duration;
+
+use alumet::{
+    pipeline::elements::source::trigger,
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+    units::{PrefixedUnit, Unit},
+};
+use serde::{Deserialize, Serialize};
+
+mod source;
+
+use source::{Metrics, SysinfoSource};
+
+pub struct SysinfoPlugin {
+    config: Config,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+    /// Interval between two flushes of the measurements.
+    #[serde(with = "humantime_serde")]
+    pub flush_interval: Duration,
+    /// If true, measures the usage of each CPU core, in addition to the global usage.
+    pub per_core: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            flush_interval: Duration::from_secs(5),
+            per_core: true,
+        }
+    }
+}
+
+impl AlumetPlugin for SysinfoPlugin {
+    fn name() -> &'static str {
+        "sysinfo"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        Ok(Some(serialize_config(Config::default())?))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(Self { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        let metrics = Metrics {
+            cpu_usage: alumet.create_metric("cpu_usage", Unit::Percent, "CPU usage")?,
+            memory_used: alumet.create_metric("memory_used", PrefixedUnit::from(Unit::Byte), "Used memory")?,
+            memory_total: alumet.create_metric("memory_total", PrefixedUnit::from(Unit::Byte), "Total memory")?,
+        };
+        let source = SysinfoSource::new(metrics, self.config.per_core);
+        let trigger = trigger::builder::time_interval(self.config.poll_interval)
+            .flush_interval(self.config.flush_interval)
+            .build()?;
+        alumet.add_source("system", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
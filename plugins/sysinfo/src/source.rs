@@ -0,0 +1,78 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::elements::source::{PollError, Source},
+    resources::{Resource, ResourceConsumer},
+};
+use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
+
+pub struct Metrics {
+    pub cpu_usage: TypedMetricId<f64>,
+    pub memory_used: TypedMetricId<u64>,
+    pub memory_total: TypedMetricId<u64>,
+}
+
+/// Measures the CPU and memory usage with the `sysinfo` crate.
+pub struct SysinfoSource {
+    system: System,
+    metrics: Metrics,
+    per_core: bool,
+}
+
+impl SysinfoSource {
+    pub fn new(metrics: Metrics, per_core: bool) -> Self {
+        // The CPU usage is computed between two refreshes, refresh a first time now.
+        let system = System::new_with_specifics(
+            RefreshKind::nothing()
+                .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
+                .with_memory(MemoryRefreshKind::nothing().with_ram()),
+        );
+        Self {
+            system,
+            metrics,
+            per_core,
+        }
+    }
+}
+
+impl Source for SysinfoSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        self.system.refresh_cpu_usage();
+        self.system
+            .refresh_memory_specifics(MemoryRefreshKind::nothing().with_ram());
+
+        measurements.push(MeasurementPoint::new(
+            timestamp,
+            self.metrics.cpu_usage,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            f64::from(self.system.global_cpu_usage()),
+        ));
+        if self.per_core {
+            for (id, cpu) in self.system.cpus().iter().enumerate() {
+                measurements.push(MeasurementPoint::new(
+                    timestamp,
+                    self.metrics.cpu_usage,
+                    Resource::CpuCore { id: id as u32 },
+                    ResourceConsumer::LocalMachine,
+                    f64::from(cpu.cpu_usage()),
+                ));
+            }
+        }
+        measurements.push(MeasurementPoint::new(
+            timestamp,
+            self.metrics.memory_used,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            self.system.used_memory(),
+        ));
+        measurements.push(MeasurementPoint::new(
+            timestamp,
+            self.metrics.memory_total,
+            Resource::LocalMachine,
+            ResourceConsumer::LocalMachine,
+            self.system.total_memory(),
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use alumet::{
+    agent::{
+        self,
+        plugin::{PluginInfo, PluginSet},
+    },
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    resources::Resource,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::{PrefixedUnit, Unit},
+};
+use plugin_sysinfo::{Config, SysinfoPlugin};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn measure_cpu_and_memory() {
+    let mut plugins = PluginSet::new();
+    let config = Config {
+        poll_interval: Duration::from_millis(500),
+        ..Default::default()
+    };
+    plugins.add_plugin(PluginInfo {
+        metadata: PluginMetadata::from_static::<SysinfoPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(config).unwrap().as_table().unwrap().clone()),
+    });
+
+    let startup_expectation = StartupExpectations::new()
+        .expect_metric::<f64>("cpu_usage", Unit::Percent)
+        .expect_metric::<u64>("memory_used", PrefixedUnit::from(Unit::Byte))
+        .expect_metric::<u64>("memory_total", PrefixedUnit::from(Unit::Byte))
+        .expect_source("sysinfo", "system");
+
+    let runtime_expectation = RuntimeExpectations::new().test_source(
+        SourceName::from_str("sysinfo", "system"),
+        || {},
+        |ctx| {
+            let m = ctx.measurements();
+            let cpu_usage = ctx.metrics().by_name("cpu_usage").unwrap().0;
+            let memory_used = ctx.metrics().by_name("memory_used").unwrap().0;
+            let memory_total = ctx.metrics().by_name("memory_total").unwrap().0;
+
+            let global_cpu = m
+                .iter()
+                .find(|p| p.metric == cpu_usage && p.resource == Resource::LocalMachine)
+                .expect("the global CPU usage should be measured");
+            assert!((0.0..=100.0).contains(&global_cpu.value.as_f64()));
+            assert!(
+                m.iter()
+                    .any(|p| p.metric == cpu_usage && matches!(p.resource, Resource::CpuCore { .. })),
+                "the usage of each core should be measured"
+            );
+
+            let used = m.iter().find(|p| p.metric == memory_used).unwrap().value.as_u64();
+            let total = m.iter().find(|p| p.metric == memory_total).unwrap().value.as_u64();
+            assert!(total > 0 && used <= total, "bad memory usage: {used}/{total}");
+        },
+    );
+
+    let agent = agent::Builder::new(plugins)
+        .with_expectations(startup_expectation)
+        .with_expectations(runtime_expectation)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
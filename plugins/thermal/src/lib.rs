@@ -0,0 +1,96 @@
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use alumet::{
+    pipeline::elements::source::trigger::TriggerSpec,
+    plugin::{
+        ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+
+use crate::source::{Metrics, ThermalSource};
+
+mod source;
+mod thermal;
+
+/// Measures the thermal zones and cooling devices, with the thermal framework of the Linux kernel.
+pub struct ThermalPlugin {
+    config: Config,
+}
+
+impl AlumetPlugin for ThermalPlugin {
+    fn name() -> &'static str {
+        "thermal"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        let config = serialize_config(Config::default())?;
+        Ok(Some(config))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(ThermalPlugin { config }))
+    }
+
+    fn start(&mut self, alumet: &mut alumet::plugin::AlumetPluginStart) -> anyhow::Result<()> {
+        let (zones, mut devices) =
+            thermal::explore(&self.config.thermal_path).context("could not find the thermal zones")?;
+        if !self.config.cooling_devices {
+            devices.clear();
+        }
+        if zones.is_empty() && devices.is_empty() {
+            return Err(anyhow!(
+                "nothing to measure: no thermal zone or cooling device found in {:?}",
+                self.config.thermal_path
+            ));
+        }
+        for zone in &zones {
+            log::info!("Found thermal zone {} ({})", zone.name, zone.zone_type);
+        }
+        for device in &devices {
+            log::info!("Found cooling device {} ({})", device.name, device.device_type);
+        }
+
+        let metrics = Metrics::new(alumet)?;
+        let source = ThermalSource::new(zones, devices, metrics);
+        let trigger = TriggerSpec::at_interval(self.config.poll_interval);
+        alumet.add_source("thermal", Box::new(source), trigger)?;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+
+    /// Path to the thermal zones and cooling devices.
+    pub thermal_path: PathBuf,
+
+    /// `true` to measure the state of the cooling devices, in addition to the thermal zones.
+    pub cooling_devices: bool,
+}
+
+impl Default for Config {
+    #[cfg_attr(tarpaulin, ignore)]
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            thermal_path: PathBuf::from("/sys/class/thermal"),
+            cooling_devices: true,
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::Unit,
+};
+
+use crate::thermal::{CoolingDevice, ThermalZone};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    temperature: TypedMetricId<f64>,
+    trip_margin: TypedMetricId<f64>,
+    cooling_state: TypedMetricId<u64>,
+    cooling_level: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            temperature: alumet.create_metric(
+                "thermal_zone_temperature",
+                Unit::DegreeCelsius,
+                "Temperature of a thermal zone",
+            )?,
+            trip_margin: alumet.create_metric(
+                "thermal_zone_trip_margin",
+                Unit::DegreeCelsius,
+                "Difference between the temperature of the trip points and the temperature of the zone (negative when the trip point has been crossed)",
+            )?,
+            cooling_state: alumet.create_metric(
+                "thermal_cooling_state",
+                Unit::Unity,
+                "Current cooling state of a cooling device (0 = no cooling)",
+            )?,
+            cooling_level: alumet.create_metric(
+                "thermal_cooling_level",
+                Unit::Percent,
+                "Current cooling state of a cooling device, relative to its maximum state",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that reads the thermal zones and cooling devices.
+pub struct ThermalSource {
+    zones: Vec<ThermalZone>,
+    devices: Vec<CoolingDevice>,
+    metrics: Metrics,
+}
+
+impl ThermalSource {
+    pub fn new(zones: Vec<ThermalZone>, devices: Vec<CoolingDevice>, metrics: Metrics) -> Self {
+        Self {
+            zones,
+            devices,
+            metrics,
+        }
+    }
+}
+
+impl Source for ThermalSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for zone in &self.zones {
+            // The temperature of some zones is temporarily unavailable: skip them this time.
+            let Some(temperature) = zone.read_temperature() else {
+                continue;
+            };
+            let point = |metric, value: f64| {
+                MeasurementPoint::new(
+                    timestamp,
+                    metric,
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    value,
+                )
+                .with_attr("zone", zone.name.clone())
+                .with_attr("zone_type", zone.zone_type.clone())
+            };
+            measurements.push(point(self.metrics.temperature, temperature));
+            for (trip_type, trip_temperature) in &zone.trip_points {
+                measurements.push(
+                    point(self.metrics.trip_margin, trip_temperature - temperature)
+                        .with_attr("trip_type", trip_type.clone()),
+                );
+            }
+        }
+
+        for device in &self.devices {
+            let Some(state) = device.read_state() else {
+                continue;
+            };
+            let attrs = [
+                ("device".to_owned(), device.name.clone().into()),
+                ("device_type".to_owned(), device.device_type.clone().into()),
+            ];
+            measurements.push(
+                MeasurementPoint::new(
+                    timestamp,
+                    self.metrics.cooling_state,
+                    Resource::LocalMachine,
+                    ResourceConsumer::LocalMachine,
+                    state,
+                )
+                .with_attr_slice(&attrs),
+            );
+            if device.max_state > 0 {
+                let level = state as f64 / device.max_state as f64 * 100.0;
+                measurements.push(
+                    MeasurementPoint::new(
+                        timestamp,
+                        self.metrics.cooling_level,
+                        Resource::LocalMachine,
+                        ResourceConsumer::LocalMachine,
+                        level,
+                    )
+                    .with_attr_slice(&attrs),
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+//! Discovery and reading of the thermal zones and cooling devices.
+//!
+//! See the [kernel documentation](https://docs.kernel.org/driver-api/thermal/sysfs-api.html).
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+/// A thermal zone, such as `/sys/class/thermal/thermal_zone0`.
+#[derive(Debug)]
+pub struct ThermalZone {
+    /// Name of the zone, like `thermal_zone0`.
+    pub name: String,
+    /// Type of the zone, like `x86_pkg_temp` or `acpitz`.
+    pub zone_type: String,
+    /// Lowest temperature of the trip points, by type (`passive`, `active`, `hot`, `critical`), in °C.
+    pub trip_points: BTreeMap<String, f64>,
+    path: PathBuf,
+}
+
+/// A cooling device, such as `/sys/class/thermal/cooling_device0`.
+#[derive(Debug)]
+pub struct CoolingDevice {
+    /// Name of the device, like `cooling_device0`.
+    pub name: String,
+    /// Type of the device, like `Processor`, `Fan` or `intel_powerclamp`.
+    pub device_type: String,
+    /// Maximum cooling state.
+    pub max_state: u64,
+    path: PathBuf,
+}
+
+impl ThermalZone {
+    pub fn at_sysfs(path: &Path) -> anyhow::Result<Self> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let zone_type = read_string(path, "type").with_context(|| format!("failed to read the type of {path:?}"))?;
+
+        // trip_point_{N}_type and trip_point_{N}_temp (in m°C)
+        let mut trip_points: BTreeMap<String, f64> = BTreeMap::new();
+        for n in 0.. {
+            let Some(trip_type) = read_string(path, &format!("trip_point_{n}_type")) else {
+                break;
+            };
+            let temp = read_int(path, &format!("trip_point_{n}_temp"));
+            // disabled trip points have a temperature of 0 or less (or an error)
+            if let Some(temp) = temp.filter(|t| *t > 0) {
+                let temp = temp as f64 / 1000.0;
+                trip_points
+                    .entry(trip_type)
+                    .and_modify(|t| *t = t.min(temp))
+                    .or_insert(temp);
+            }
+        }
+        Ok(Self {
+            name,
+            zone_type,
+            trip_points,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Reads the temperature of the zone, in °C.
+    pub fn read_temperature(&self) -> Option<f64> {
+        read_int(&self.path, "temp").map(|t| t as f64 / 1000.0)
+    }
+}
+
+impl CoolingDevice {
+    pub fn at_sysfs(path: &Path) -> anyhow::Result<Self> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let device_type = read_string(path, "type").with_context(|| format!("failed to read the type of {path:?}"))?;
+        let max_state = read_int(path, "max_state")
+            .and_then(|s| u64::try_from(s).ok())
+            .with_context(|| format!("failed to read the max state of {path:?}"))?;
+        Ok(Self {
+            name,
+            device_type,
+            max_state,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Reads the current cooling state of the device, between 0 (no cooling) and `max_state`.
+    pub fn read_state(&self) -> Option<u64> {
+        read_int(&self.path, "cur_state").and_then(|s| u64::try_from(s).ok())
+    }
+}
+
+fn read_string(dir: &Path, file: &str) -> Option<String> {
+    // Some files return an error when the value is not available (e.g. EAGAIN, ENODATA).
+    std::fs::read_to_string(dir.join(file))
+        .ok()
+        .map(|s| s.trim_ascii_end().to_owned())
+}
+
+fn read_int(dir: &Path, file: &str) -> Option<i64> {
+    read_string(dir, file)?.parse().ok()
+}
+
+/// Explores the thermal zones and cooling devices.
+///
+/// ## Expected file layout
+///
+/// ```txt
+/// /sys/class/thermal/
+/// |− thermal_zone0
+///     |− type
+///     |− temp
+///     |− trip_point_0_type
+///     |− trip_point_0_temp
+///     |− …
+/// |− cooling_device0
+///     |− type
+///     |− cur_state
+///     |− max_state
+/// ```
+pub fn explore(thermal_path: &Path) -> anyhow::Result<(Vec<ThermalZone>, Vec<CoolingDevice>)> {
+    let mut zones = Vec::new();
+    let mut devices = Vec::new();
+    for entry in std::fs::read_dir(thermal_path).with_context(|| format!("failed to read dir {thermal_path:?}"))? {
+        let path = entry?.path();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        if file_name.starts_with("thermal_zone") {
+            match ThermalZone::at_sysfs(&path) {
+                Ok(zone) => zones.push(zone),
+                Err(e) => log::warn!("failed to analyze thermal zone {path:?}: {e:#}"),
+            }
+        } else if file_name.starts_with("cooling_device") {
+            match CoolingDevice::at_sysfs(&path) {
+                Ok(device) => devices.push(device),
+                Err(e) => log::warn!("failed to analyze cooling device {path:?}: {e:#}"),
+            }
+        }
+    }
+    zones.sort_by(|a, b| a.name.cmp(&b.name));
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok((zones, devices))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn write_files(root: &Path, name: &str, files: &[(&str, &str)]) {
+        let path = root.join(name);
+        std::fs::create_dir(&path).unwrap();
+        for (file, content) in files {
+            std::fs::write(path.join(file), format!("{content}\n")).unwrap();
+        }
+    }
+
+    #[test]
+    fn explore_thermal() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        write_files(
+            root.path(),
+            "thermal_zone0",
+            &[
+                ("type", "acpitz"),
+                ("temp", "52000"),
+                ("trip_point_0_type", "critical"),
+                ("trip_point_0_temp", "105000"),
+                ("trip_point_1_type", "passive"),
+                ("trip_point_1_temp", "95000"),
+                ("trip_point_2_type", "passive"),
+                ("trip_point_2_temp", "90000"),
+                ("trip_point_3_type", "active"),
+                ("trip_point_3_temp", "0"),
+            ],
+        );
+        write_files(root.path(), "thermal_zone1", &[("type", "x86_pkg_temp")]);
+        write_files(
+            root.path(),
+            "cooling_device0",
+            &[("type", "Processor"), ("cur_state", "2"), ("max_state", "10")],
+        );
+        write_files(root.path(), "cooling_device1", &[("type", "Fan")]);
+
+        let (zones, devices) = explore(root.path())?;
+        assert_eq!(zones.len(), 2);
+        assert_eq!(zones[0].zone_type, "acpitz");
+        assert_eq!(zones[0].read_temperature(), Some(52.0));
+        assert_eq!(
+            zones[0].trip_points,
+            BTreeMap::from([(String::from("critical"), 105.0), (String::from("passive"), 90.0)])
+        );
+        assert_eq!(zones[1].zone_type, "x86_pkg_temp");
+        assert_eq!(zones[1].read_temperature(), None);
+        assert!(zones[1].trip_points.is_empty());
+
+        // cooling_device1 has no max_state
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].device_type, "Processor");
+        assert_eq!(devices[0].max_state, 10);
+        assert_eq!(devices[0].read_state(), Some(2));
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use std::{path::Path, time::Duration};
+
+use alumet::{
+    agent::{self, plugin::PluginSet},
+    pipeline::naming::SourceName,
+    plugin::PluginMetadata,
+    test::{RuntimeExpectations, StartupExpectations},
+    units::Unit,
+};
+use plugin_thermal::{Config, ThermalPlugin};
+use tempfile::tempdir;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn plugin_without_zone() {
+    let root = tempdir().unwrap();
+    let config = Config {
+        thermal_path: root.path().to_path_buf(),
+        ..Default::default()
+    };
+    let agent = agent::Builder::new(plugins(config)).build_and_start();
+    assert!(agent.is_err(), "the plugin should fail to start (no thermal zone)");
+}
+
+#[test]
+fn plugin_with_zone_and_cooling_device() {
+    let root = tempdir().unwrap();
+    write_files(
+        root.path(),
+        "thermal_zone0",
+        &[
+            ("type", "x86_pkg_temp"),
+            ("temp", "92000"),
+            ("trip_point_0_type", "passive"),
+            ("trip_point_0_temp", "90000"),
+        ],
+    );
+    write_files(
+        root.path(),
+        "cooling_device0",
+        &[("type", "Processor"), ("cur_state", "3"), ("max_state", "4")],
+    );
+
+    let config = Config {
+        poll_interval: Duration::from_millis(100),
+        thermal_path: root.path().to_path_buf(),
+        cooling_devices: true,
+    };
+
+    let startup = StartupExpectations::new()
+        .expect_metric::<f64>("thermal_zone_temperature", Unit::DegreeCelsius)
+        .expect_metric::<u64>("thermal_cooling_state", Unit::Unity)
+        .expect_source("thermal", "thermal");
+
+    let runtime = RuntimeExpectations::new().test_source(
+        SourceName::from_str("thermal", "thermal"),
+        || {},
+        |ctx| {
+            let m = ctx.measurements();
+            let value_of = |name: &str| {
+                let metric = ctx.metrics().by_name(name).unwrap().0;
+                m.iter().find(|p| p.metric == metric).map(|p| p.value.clone()).unwrap()
+            };
+            assert_eq!(value_of("thermal_zone_temperature").as_f64(), 92.0);
+            // the passive trip point has been crossed: throttling
+            assert_eq!(value_of("thermal_zone_trip_margin").as_f64(), -2.0);
+            assert_eq!(value_of("thermal_cooling_state").as_u64(), 3);
+            assert_eq!(value_of("thermal_cooling_level").as_f64(), 75.0);
+        },
+    );
+
+    let agent = agent::Builder::new(plugins(config))
+        .with_expectations(startup)
+        .with_expectations(runtime)
+        .build_and_start()
+        .unwrap();
+    agent.wait_for_shutdown(TIMEOUT).unwrap();
+}
+
+fn write_files(root: &Path, name: &str, files: &[(&str, &str)]) {
+    let path = root.join(name);
+    std::fs::create_dir_all(&path).unwrap();
+    for (file, content) in files {
+        std::fs::write(path.join(file), content).unwrap();
+    }
+}
+
+fn plugins(config: Config) -> PluginSet {
+    let mut plugins = PluginSet::new();
+    plugins.add_plugin(alumet::agent::plugin::PluginInfo {
+        metadata: PluginMetadata::from_static::<ThermalPlugin>(),
+        enabled: true,
+        config: Some(toml::Value::try_from(&config).unwrap().as_table().unwrap().clone()),
+    });
+    plugins
+}
@@ -0,0 +1,168 @@
+//! Conversion between the Alumet types and the types of the WIT interface.
+
+use std::str::FromStr;
+
+use alumet::{
+    measurement::{
+        AttributeValue, MeasurementBuffer, MeasurementPoint, Timestamp, WrappedMeasurementType, WrappedMeasurementValue,
+    },
+    metrics::{Metric, RawMetricId, registry::MetricRegistry},
+    resources::{Resource, ResourceConsumer},
+    units::PrefixedUnit,
+};
+use anyhow::{Context, anyhow};
+
+use crate::bindings::alumet::plugin::types as wit;
+
+/// Converts the definition of a metric.
+pub fn metric_from_wit(metric: wit::Metric) -> anyhow::Result<Metric> {
+    let unit = PrefixedUnit::from_str(&metric.unit)
+        .map_err(|e| anyhow!("invalid unit '{}' for metric {}: {e}", metric.unit, metric.name))?;
+    let value_type = match metric.value_type {
+        wit::ValueType::U64 => WrappedMeasurementType::U64,
+        wit::ValueType::F64 => WrappedMeasurementType::F64,
+    };
+    Ok(Metric {
+        name: metric.name,
+        description: metric.description,
+        value_type,
+        unit,
+    })
+}
+
+/// Converts a point returned by a plugin.
+///
+/// `metric_id` finds the id of a metric by its name.
+pub fn point_from_wit(
+    point: wit::MeasurementPoint,
+    metric_id: impl Fn(&str) -> Option<RawMetricId>,
+) -> anyhow::Result<MeasurementPoint> {
+    let metric = metric_id(&point.metric).with_context(|| format!("unknown metric {}", point.metric))?;
+    let timestamp = Timestamp::from_unix_timestamp(
+        point.timestamp / 1_000_000_000,
+        (point.timestamp % 1_000_000_000) as u32,
+    );
+    let value = match point.value {
+        wit::MeasurementValue::U64(v) => WrappedMeasurementValue::U64(v),
+        wit::MeasurementValue::F64(v) => WrappedMeasurementValue::F64(v),
+    };
+    let resource = Resource::parse(point.resource.kind, point.resource.id)?;
+    let consumer = ResourceConsumer::parse(point.consumer.kind, point.consumer.id)?;
+    let attributes = point
+        .attributes
+        .into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                wit::AttributeValue::U64(v) => AttributeValue::U64(v),
+                wit::AttributeValue::F64(v) => AttributeValue::F64(v),
+                wit::AttributeValue::Bool(v) => AttributeValue::Bool(v),
+                wit::AttributeValue::String(v) => AttributeValue::String(v),
+            };
+            (key, value)
+        })
+        .collect();
+    Ok(MeasurementPoint::new_untyped(timestamp, metric, resource, consumer, value).with_attr_vec(attributes))
+}
+
+/// Converts the points of a buffer to give them to a plugin.
+pub fn points_to_wit(
+    measurements: &MeasurementBuffer,
+    metrics: &MetricRegistry,
+) -> anyhow::Result<Vec<wit::MeasurementPoint>> {
+    measurements
+        .iter()
+        .map(|p| {
+            let metric = metrics
+                .by_id(&p.metric)
+                .with_context(|| format!("unknown metric {:?}", p.metric))?;
+            Ok(point_to_wit(p, metric.name.clone()))
+        })
+        .collect()
+}
+
+/// Converts a point to give it to a plugin.
+pub fn point_to_wit(point: &MeasurementPoint, metric: String) -> wit::MeasurementPoint {
+    let (secs, nanos) = point.timestamp.to_unix_timestamp();
+    let value = match point.value {
+        WrappedMeasurementValue::U64(v) => wit::MeasurementValue::U64(v),
+        WrappedMeasurementValue::F64(v) => wit::MeasurementValue::F64(v),
+    };
+    let attributes = point
+        .attributes()
+        .map(|(key, value)| {
+            let value = match value {
+                AttributeValue::U64(v) => wit::AttributeValue::U64(*v),
+                AttributeValue::F64(v) => wit::AttributeValue::F64(*v),
+                AttributeValue::Bool(v) => wit::AttributeValue::Bool(*v),
+                other => wit::AttributeValue::String(other.to_string()),
+            };
+            (key.to_owned(), value)
+        })
+        .collect();
+    wit::MeasurementPoint {
+        timestamp: secs * 1_000_000_000 + u64::from(nanos),
+        metric,
+        value,
+        resource: wit::ResourceId {
+            kind: point.resource.kind().to_owned(),
+            id: point.resource.id_string().unwrap_or_default(),
+        },
+        consumer: wit::ResourceId {
+            kind: point.consumer.kind().to_owned(),
+            id: point.consumer.id_string().unwrap_or_default(),
+        },
+        attributes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alumet::{
+        measurement::{AttributeValue, MeasurementPoint, Timestamp, WrappedMeasurementValue},
+        metrics::RawMetricId,
+        resources::{Resource, ResourceConsumer},
+    };
+
+    use super::{point_from_wit, point_to_wit};
+
+    #[test]
+    fn point_roundtrip() {
+        let id = RawMetricId::from_u64(7);
+        let point = MeasurementPoint::new_untyped(
+            Timestamp::from_unix_timestamp(1700000000, 123456789),
+            id,
+            Resource::CpuPackage { id: 0 },
+            ResourceConsumer::Process { pid: 42 },
+            WrappedMeasurementValue::F64(12.5),
+        )
+        .with_attr("domain", "package")
+        .with_attr("core_count", 8_u64);
+
+        let converted = point_to_wit(&point, String::from("energy"));
+        assert_eq!(converted.timestamp, 1_700_000_000_123_456_789);
+        assert_eq!(converted.metric, "energy");
+        assert_eq!(converted.resource.kind, "cpu_package");
+        assert_eq!(converted.resource.id, "0");
+        assert_eq!(converted.consumer.kind, "process");
+        assert_eq!(converted.consumer.id, "42");
+
+        let back = point_from_wit(converted, |name| (name == "energy").then_some(id)).unwrap();
+        assert_eq!(back.timestamp, point.timestamp);
+        assert_eq!(back.metric, id);
+        assert_eq!(back.resource, point.resource);
+        assert_eq!(back.consumer, point.consumer);
+        assert_eq!(back.value, point.value);
+        let attributes: Vec<_> = back.attributes().collect();
+        assert_eq!(
+            attributes,
+            vec![
+                ("domain", &AttributeValue::String(String::from("package"))),
+                ("core_count", &AttributeValue::U64(8)),
+            ]
+        );
+
+        // unknown metric
+        let converted = point_to_wit(&point, String::from("unknown"));
+        assert!(point_from_wit(converted, |_| None::<RawMetricId>).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Sources, transforms and outputs implemented by WebAssembly plugins.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementBuffer, Timestamp},
+    metrics::RawMetricId,
+    pipeline::{
+        Output, Source, Transform,
+        elements::{
+            error::{PollError, TransformError, WriteError},
+            output::OutputContext,
+            transform::TransformContext,
+        },
+    },
+};
+use anyhow::{Context, anyhow};
+
+use crate::{
+    convert::{point_from_wit, points_to_wit},
+    host::{WasmInstance, plugin_error},
+};
+
+/// A WebAssembly instance, shared by the elements of a plugin.
+pub type SharedInstance = Arc<Mutex<WasmInstance>>;
+
+pub struct WasmSource {
+    pub module: String,
+    pub name: String,
+    pub instance: SharedInstance,
+    /// Metrics created by the plugin.
+    pub metrics: Arc<HashMap<String, RawMetricId>>,
+}
+
+pub struct WasmTransform {
+    pub module: String,
+    pub name: String,
+    pub instance: SharedInstance,
+}
+
+pub struct WasmOutput {
+    pub module: String,
+    pub name: String,
+    pub instance: SharedInstance,
+}
+
+fn lock(instance: &SharedInstance) -> anyhow::Result<std::sync::MutexGuard<'_, WasmInstance>> {
+    instance
+        .lock()
+        .map_err(|_| anyhow!("the WebAssembly instance has been poisoned by a previous failure"))
+}
+
+impl Source for WasmSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let (secs, nanos) = timestamp.to_unix_timestamp();
+        let timestamp = secs * 1_000_000_000 + u64::from(nanos);
+        let points = lock(&self.instance)?
+            .poll(&self.name, timestamp)
+            .context("WebAssembly trap in poll")?
+            .map_err(|e| PollError::CanRetry(plugin_error(&self.module, "poll", e)))?;
+        for point in points {
+            let point = point_from_wit(point, |name| self.metrics.get(name).copied())
+                .with_context(|| format!("invalid point returned by source {}", self.name))?;
+            measurements.push(point);
+        }
+        Ok(())
+    }
+}
+
+impl Transform for WasmTransform {
+    fn apply(&mut self, measurements: &mut MeasurementBuffer, ctx: &TransformContext) -> Result<(), TransformError> {
+        let points = points_to_wit(measurements, ctx.metrics).map_err(TransformError::UnexpectedInput)?;
+        let transformed = lock(&self.instance)?
+            .apply(&self.name, &points)
+            .context("WebAssembly trap in apply")?
+            .map_err(|e| TransformError::UnexpectedInput(plugin_error(&self.module, "apply", e)))?;
+        measurements.clear();
+        for point in transformed {
+            let point = point_from_wit(point, |name| ctx.metrics.by_name(name).map(|(id, _)| id))
+                .with_context(|| format!("invalid point returned by transform {}", self.name))?;
+            measurements.push(point);
+        }
+        Ok(())
+    }
+}
+
+impl Output for WasmOutput {
+    fn write(&mut self, measurements: &MeasurementBuffer, ctx: &OutputContext) -> Result<(), WriteError> {
+        let points = points_to_wit(measurements, ctx.metrics)?;
+        lock(&self.instance)?
+            .write(&self.name, &points)
+            .context("WebAssembly trap in write")?
+            .map_err(|e| WriteError::CanRetry(plugin_error(&self.module, "write", e)))
+    }
+}
@@ -0,0 +1,131 @@
+//! Instantiation of the WebAssembly components.
+
+use std::path::Path;
+
+use anyhow::{Context, anyhow};
+use wasmtime::{
+    Config, Engine, Store, StoreLimits, StoreLimitsBuilder,
+    component::{Component, HasSelf, Linker, ResourceTable},
+};
+use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiCtxView, WasiView};
+
+use crate::bindings::{
+    Plugin,
+    alumet::plugin::{
+        host::{self, LogLevel},
+        types::{self, MeasurementPoint, Registrations},
+    },
+};
+
+/// State of a component, only accessible by the host.
+struct HostState {
+    /// Name of the module, for the logs.
+    module: String,
+    wasi: WasiCtx,
+    table: ResourceTable,
+    limits: StoreLimits,
+}
+
+/// An instance of a WebAssembly plugin.
+///
+/// The instance is not shared between threads: the elements of the plugin access it through a mutex.
+pub struct WasmInstance {
+    store: Store<HostState>,
+    plugin: Plugin,
+}
+
+impl WasiView for HostState {
+    fn ctx(&mut self) -> WasiCtxView<'_> {
+        WasiCtxView {
+            ctx: &mut self.wasi,
+            table: &mut self.table,
+        }
+    }
+}
+
+impl types::Host for HostState {}
+
+impl host::Host for HostState {
+    fn log(&mut self, level: LogLevel, message: String) {
+        let level = match level {
+            LogLevel::Error => log::Level::Error,
+            LogLevel::Warn => log::Level::Warn,
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Debug => log::Level::Debug,
+            LogLevel::Trace => log::Level::Trace,
+        };
+        log::log!(level, "[{}] {message}", self.module);
+    }
+}
+
+/// Compiles and instantiates the WebAssembly components.
+pub struct Runtime {
+    engine: Engine,
+    linker: Linker<HostState>,
+}
+
+impl Runtime {
+    pub fn new() -> anyhow::Result<Self> {
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        let engine = Engine::new(&config)?;
+
+        // The components only get the WASI interfaces that are necessary to run the standard library of
+        // most languages (clocks, random, stdio...). Since the context does not give them access to
+        // any directory or socket, they cannot use the filesystem or the network.
+        let mut linker = Linker::new(&engine);
+        wasmtime_wasi::p2::add_to_linker_sync(&mut linker)?;
+        Plugin::add_to_linker::<_, HasSelf<_>>(&mut linker, |state| state)?;
+        Ok(Self { engine, linker })
+    }
+
+    /// Loads the component stored in `file`.
+    ///
+    /// The memory of the component is limited to `max_memory` bytes.
+    pub fn instantiate(&self, module: &str, file: &Path, max_memory: usize) -> anyhow::Result<WasmInstance> {
+        let component = Component::from_file(&self.engine, file)
+            .with_context(|| format!("failed to compile WebAssembly component {}", file.display()))?;
+        let state = HostState {
+            module: module.to_owned(),
+            wasi: WasiCtxBuilder::new().inherit_stdout().inherit_stderr().build(),
+            table: ResourceTable::new(),
+            limits: StoreLimitsBuilder::new().memory_size(max_memory).build(),
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        let plugin = Plugin::instantiate(&mut store, &component, &self.linker)
+            .with_context(|| format!("failed to instantiate WebAssembly component {}", file.display()))?;
+        Ok(WasmInstance { store, plugin })
+    }
+}
+
+/// Calls of the functions exported by the plugins.
+///
+/// A trap (for instance, a panic in the plugin) is returned as an error of the outer `Result`,
+/// while an error returned by the plugin is returned as an error of the inner `Result`.
+impl WasmInstance {
+    pub fn start(&mut self, config: &str) -> anyhow::Result<Result<Registrations, String>> {
+        self.plugin.call_start(&mut self.store, config)
+    }
+
+    pub fn poll(&mut self, source: &str, timestamp: u64) -> anyhow::Result<Result<Vec<MeasurementPoint>, String>> {
+        self.plugin.call_poll_source(&mut self.store, source, timestamp)
+    }
+
+    pub fn apply(
+        &mut self,
+        transform: &str,
+        points: &[MeasurementPoint],
+    ) -> anyhow::Result<Result<Vec<MeasurementPoint>, String>> {
+        self.plugin.call_apply_transform(&mut self.store, transform, points)
+    }
+
+    pub fn write(&mut self, output: &str, points: &[MeasurementPoint]) -> anyhow::Result<Result<(), String>> {
+        self.plugin.call_write_output(&mut self.store, output, points)
+    }
+}
+
+/// Converts the error returned by a plugin.
+pub fn plugin_error(module: &str, function: &str, err: String) -> anyhow::Error {
+    anyhow!("{function} failed in WebAssembly plugin {module}: {err}")
+}
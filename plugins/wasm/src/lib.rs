@@ -0,0 +1,170 @@
+//! Host for WebAssembly plugins.
+//!
+//! WebAssembly plugins are components that implement the `plugin` world of `wit/plugin.wit`.
+//! They run in a sandbox, with a limited amount of memory and without access to the filesystem
+//! or to the network: unlike dynamic plugins, a bug in a WebAssembly plugin cannot corrupt the memory of the agent.
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use alumet::{
+    pipeline::elements::source::trigger,
+    plugin::{
+        AlumetPluginStart, ConfigTable,
+        rust::{AlumetPlugin, deserialize_config, serialize_config},
+    },
+};
+use anyhow::{Context, anyhow};
+use serde::{Deserialize, Serialize};
+
+mod convert;
+mod elements;
+mod host;
+
+use elements::{WasmOutput, WasmSource, WasmTransform};
+use host::{Runtime, plugin_error};
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        path: "wit",
+        world: "plugin",
+    });
+}
+
+pub struct WasmPlugin {
+    config: Config,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The WebAssembly components to load.
+    pub modules: Vec<ModuleConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ModuleConfig {
+    /// Path to the component (`.wasm` file).
+    pub path: PathBuf,
+    /// Name of the module, used as a prefix for the names of its elements.
+    /// Defaults to the name of the file, without its extension.
+    pub name: Option<String>,
+    /// Maximum amount of memory that the module can use, in bytes.
+    #[serde(default = "default_max_memory")]
+    pub max_memory: usize,
+    /// Configuration of the module, given to its `start` function as a JSON object.
+    #[serde(default)]
+    pub config: toml::Table,
+}
+
+fn default_max_memory() -> usize {
+    64 * 1024 * 1024
+}
+
+impl AlumetPlugin for WasmPlugin {
+    fn name() -> &'static str {
+        "wasm"
+    }
+
+    fn version() -> &'static str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+        Ok(Some(serialize_config(Config::default())?))
+    }
+
+    fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+        let config = deserialize_config(config)?;
+        Ok(Box::new(Self { config }))
+    }
+
+    fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+        if self.config.modules.is_empty() {
+            log::warn!("No WebAssembly module to load.");
+            return Ok(());
+        }
+        let runtime = Runtime::new().context("failed to initialize the WebAssembly runtime")?;
+        for module in &self.config.modules {
+            let name = match &module.name {
+                Some(name) => name.clone(),
+                None => module
+                    .path
+                    .file_stem()
+                    .with_context(|| format!("invalid module path {}", module.path.display()))?
+                    .to_string_lossy()
+                    .into_owned(),
+            };
+            start_module(&runtime, alumet, &name, module)
+                .with_context(|| format!("failed to start WebAssembly module {name}"))?;
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Instantiates a module and adds its elements to the pipeline.
+fn start_module(
+    runtime: &Runtime,
+    alumet: &mut AlumetPluginStart,
+    name: &str,
+    module: &ModuleConfig,
+) -> anyhow::Result<()> {
+    let mut instance = runtime.instantiate(name, &module.path, module.max_memory)?;
+    let config = serde_json::to_string(&module.config)?;
+    let registrations = instance
+        .start(&config)
+        .context("WebAssembly trap in start")?
+        .map_err(|e| plugin_error(name, "start", e))?;
+
+    let mut metrics = HashMap::with_capacity(registrations.metrics.len());
+    for metric in registrations.metrics {
+        let metric = convert::metric_from_wit(metric)?;
+        let id = alumet.create_metric_untyped(&metric.name, metric.value_type, metric.unit, &metric.description)?;
+        metrics.insert(metric.name, id);
+    }
+    let metrics = Arc::new(metrics);
+
+    let instance = Arc::new(Mutex::new(instance));
+    for source in registrations.sources {
+        if source.poll_interval_ms == 0 {
+            return Err(anyhow!("invalid poll interval for source {}", source.name));
+        }
+        let trigger = trigger::builder::time_interval(Duration::from_millis(source.poll_interval_ms))
+            .flush_interval(Duration::from_millis(
+                source.flush_interval_ms.max(source.poll_interval_ms),
+            ))
+            .build()?;
+        let element = WasmSource {
+            module: name.to_owned(),
+            name: source.name.clone(),
+            instance: instance.clone(),
+            metrics: metrics.clone(),
+        };
+        alumet.add_source(&format!("{name}-{}", source.name), Box::new(element), trigger)?;
+    }
+    for transform in registrations.transforms {
+        let element = WasmTransform {
+            module: name.to_owned(),
+            name: transform.clone(),
+            instance: instance.clone(),
+        };
+        alumet.add_transform(&format!("{name}-{transform}"), Box::new(element))?;
+    }
+    for output in registrations.outputs {
+        let element = WasmOutput {
+            module: name.to_owned(),
+            name: output.clone(),
+            instance: instance.clone(),
+        };
+        alumet.add_blocking_output(&format!("{name}-{output}"), Box::new(element))?;
+    }
+    Ok(())
+}
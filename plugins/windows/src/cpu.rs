@@ -0,0 +1,164 @@
+//! CPU utilization of the whole system and CPU time of the processes.
+//!
+//! Windows reports the times in units of 100 nanoseconds.
+
+use std::collections::HashMap;
+
+/// Number of nanoseconds in one unit of the Windows times.
+const NANOS_PER_TICK: u64 = 100;
+
+/// Cumulated times of all the processors, as returned by `GetSystemTimes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemTimes {
+    pub idle: u64,
+    /// Time spent in kernel mode, **including** the idle time.
+    pub kernel: u64,
+    pub user: u64,
+}
+
+/// Utilization of the processors, in percent of the total time of all the processors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Utilization {
+    pub user: f64,
+    pub system: f64,
+}
+
+impl Utilization {
+    pub fn total(&self) -> f64 {
+        self.user + self.system
+    }
+}
+
+impl SystemTimes {
+    /// Computes the utilization of the processors between `previous` and `self`.
+    ///
+    /// Returns `None` if no time has elapsed.
+    pub fn utilization_since(&self, previous: &SystemTimes) -> Option<Utilization> {
+        let idle = self.idle.saturating_sub(previous.idle);
+        let kernel = self.kernel.saturating_sub(previous.kernel);
+        let user = self.user.saturating_sub(previous.user);
+        let total = kernel + user;
+        if total == 0 {
+            return None;
+        }
+        let system = kernel.saturating_sub(idle);
+        Some(Utilization {
+            user: 100.0 * user as f64 / total as f64,
+            system: 100.0 * system as f64 / total as f64,
+        })
+    }
+}
+
+/// Cumulated CPU times of a process, as returned by `GetProcessTimes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessTimes {
+    pub pid: u32,
+    /// Creation time of the process, to detect the reuse of the pids.
+    pub creation: u64,
+    pub kernel: u64,
+    pub user: u64,
+}
+
+/// CPU time of a process since the previous measurement, in nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessCpuDelta {
+    pub pid: u32,
+    pub user: u64,
+    pub system: u64,
+}
+
+/// Keeps the previous times of the processes, to compute the deltas.
+#[derive(Default)]
+pub struct ProcessTracker {
+    previous: HashMap<u32, ProcessTimes>,
+}
+
+impl ProcessTracker {
+    /// Updates the tracker with the current times of the running processes and returns the deltas.
+    ///
+    /// The new processes (and the processes that reuse the pid of a terminated process) have no delta,
+    /// and the terminated processes are forgotten.
+    pub fn update(&mut self, processes: Vec<ProcessTimes>) -> Vec<ProcessCpuDelta> {
+        let mut deltas = Vec::with_capacity(processes.len());
+        let mut current = HashMap::with_capacity(processes.len());
+        for p in processes {
+            if let Some(prev) = self.previous.get(&p.pid)
+                && prev.creation == p.creation
+            {
+                deltas.push(ProcessCpuDelta {
+                    pid: p.pid,
+                    user: p.user.saturating_sub(prev.user) * NANOS_PER_TICK,
+                    system: p.kernel.saturating_sub(prev.kernel) * NANOS_PER_TICK,
+                });
+            }
+            current.insert(p.pid, p);
+        }
+        self.previous = current;
+        deltas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn system_utilization() {
+        let prev = SystemTimes {
+            idle: 1000,
+            kernel: 1500,
+            user: 500,
+        };
+        let now = SystemTimes {
+            idle: 1600,
+            kernel: 2300,
+            user: 700,
+        };
+        // 1000 ticks in total: 600 idle, 200 in kernel mode, 200 in user mode
+        let u = now.utilization_since(&prev).unwrap();
+        assert_eq!(
+            u,
+            Utilization {
+                user: 20.0,
+                system: 20.0
+            }
+        );
+        assert_eq!(u.total(), 40.0);
+        assert_eq!(now.utilization_since(&now), None);
+    }
+
+    #[test]
+    fn process_deltas() {
+        let mut tracker = ProcessTracker::default();
+        let p = |pid, creation, kernel, user| ProcessTimes {
+            pid,
+            creation,
+            kernel,
+            user,
+        };
+        assert_eq!(tracker.update(vec![p(4, 10, 100, 200), p(8, 20, 0, 50)]), vec![]);
+
+        // pid 8 has been reused by a new process, pid 12 is new
+        let deltas = tracker.update(vec![p(4, 10, 150, 230), p(8, 30, 0, 10), p(12, 40, 5, 5)]);
+        assert_eq!(
+            deltas,
+            vec![ProcessCpuDelta {
+                pid: 4,
+                user: 3000,
+                system: 5000
+            }]
+        );
+
+        let deltas = tracker.update(vec![p(8, 30, 1, 20)]);
+        assert_eq!(
+            deltas,
+            vec![ProcessCpuDelta {
+                pid: 8,
+                user: 1000,
+                system: 100
+            }]
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Interpretation of the data of the Energy Meter Interface (EMI) of Windows.
+//!
+//! The energy meters are exposed by the drivers of the platform, for instance the RAPL domains of the Intel CPUs
+//! (channels `RAPL_Package0_PKG`, `RAPL_Package0_DRAM`, ...) or the energy estimations of the Qualcomm SoCs.
+//! Each meter has one channel (EMI version 1) or several channels (EMI version 2).
+//!
+//! The layout of the structures is defined in `emi.h`, all the integers are little-endian.
+
+use alumet::resources::Resource;
+use anyhow::{Context, anyhow};
+
+/// The only measurement unit defined by EMI: picowatt-hours.
+const UNIT_PICOWATT_HOURS: i32 = 0;
+
+/// Size of the OEM and model fields, in UTF-16 units.
+const EMI_NAME_MAX: usize = 16;
+
+/// Size of `EMI_CHANNEL_MEASUREMENT_DATA`.
+const MEASUREMENT_SIZE: usize = 16;
+
+/// Description of an energy meter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    pub oem: String,
+    pub model: String,
+    /// Names of the channels, in the order of the measurements.
+    pub channels: Vec<String>,
+}
+
+/// Absolute value of a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelMeasurement {
+    /// Energy consumed since an arbitrary point in the past, in picowatt-hours.
+    pub energy_pwh: u64,
+    /// Time of the measurement, in units of 100 nanoseconds.
+    pub time: u64,
+}
+
+impl ChannelMeasurement {
+    /// Computes the energy (in Joules) and the duration (in seconds) between `previous` and `self`.
+    pub fn delta_since(&self, previous: &ChannelMeasurement) -> (f64, f64) {
+        let energy = picowatt_hours_to_joules(self.energy_pwh.saturating_sub(previous.energy_pwh));
+        let seconds = self.time.saturating_sub(previous.time) as f64 / 10_000_000.0;
+        (energy, seconds)
+    }
+}
+
+pub fn picowatt_hours_to_joules(pwh: u64) -> f64 {
+    pwh as f64 * 3600.0 / 1e12
+}
+
+/// Parses the metadata returned by `IOCTL_EMI_GET_METADATA`.
+pub fn parse_metadata(version: u16, data: &[u8]) -> anyhow::Result<Metadata> {
+    let mut r = Reader { data, offset: 0 };
+    match version {
+        1 => {
+            // EMI_METADATA_V1
+            check_unit(r.i32()?)?;
+            let oem = r.fixed_string()?;
+            let model = r.fixed_string()?;
+            let _revision = r.u16()?;
+            let name_size = r.u16()?;
+            let name = r.string(name_size)?;
+            Ok(Metadata {
+                oem,
+                model,
+                channels: vec![name],
+            })
+        }
+        2 => {
+            // EMI_METADATA_V2
+            let oem = r.fixed_string()?;
+            let model = r.fixed_string()?;
+            let _revision = r.u16()?;
+            let count = r.u16()?;
+            let mut channels = Vec::with_capacity(count as usize);
+            for i in 0..count {
+                // EMI_CHANNEL_V2, the next channel starts right after the name
+                check_unit(r.i32()?).with_context(|| format!("invalid channel {i}"))?;
+                let name_size = r.u16()?;
+                channels.push(r.string(name_size)?);
+            }
+            Ok(Metadata { oem, model, channels })
+        }
+        v => Err(anyhow!("unsupported EMI version {v}")),
+    }
+}
+
+/// Parses the measurements returned by `IOCTL_EMI_GET_MEASUREMENT`, one per channel.
+pub fn parse_measurements(data: &[u8], channel_count: usize) -> anyhow::Result<Vec<ChannelMeasurement>> {
+    if data.len() < channel_count * MEASUREMENT_SIZE {
+        return Err(anyhow!(
+            "expected {} bytes of measurements, got {}",
+            channel_count * MEASUREMENT_SIZE,
+            data.len()
+        ));
+    }
+    let mut r = Reader { data, offset: 0 };
+    (0..channel_count)
+        .map(|_| {
+            Ok(ChannelMeasurement {
+                energy_pwh: r.u64()?,
+                time: r.u64()?,
+            })
+        })
+        .collect()
+}
+
+/// Returns the resource and the RAPL domain (if any) that correspond to a channel.
+///
+/// The names of the RAPL channels are `RAPL_Package<N>_<DOMAIN>`.
+pub fn channel_resource(channel: &str) -> (Resource, Option<&'static str>) {
+    let rapl = channel.strip_prefix("RAPL_Package").and_then(|rest| {
+        let (pkg, domain) = rest.split_once('_')?;
+        Some((pkg.parse::<u32>().ok()?, domain))
+    });
+    match rapl {
+        Some((id, "PKG")) => (Resource::CpuPackage { id }, Some("package")),
+        Some((id, "PP0")) => (Resource::CpuPackage { id }, Some("pp0")),
+        Some((id, "PP1")) => (Resource::CpuPackage { id }, Some("pp1")),
+        Some((pkg_id, "DRAM")) => (Resource::Dram { pkg_id }, Some("dram")),
+        Some((_, "PSYS")) => (Resource::LocalMachine, Some("platform")),
+        _ => (Resource::LocalMachine, None),
+    }
+}
+
+fn check_unit(unit: i32) -> anyhow::Result<()> {
+    if unit == UNIT_PICOWATT_HOURS {
+        Ok(())
+    } else {
+        Err(anyhow!("unsupported measurement unit {unit}"))
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let bytes = self
+            .data
+            .get(self.offset..self.offset + N)
+            .ok_or_else(|| anyhow!("unexpected end of data at offset {}", self.offset))?;
+        self.offset += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        self.bytes().map(u16::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> anyhow::Result<i32> {
+        self.bytes().map(i32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    /// Reads a null-terminated UTF-16 string of `size` bytes.
+    fn string(&mut self, size: u16) -> anyhow::Result<String> {
+        let units = (0..size / 2)
+            .map(|_| self.u16())
+            .collect::<anyhow::Result<Vec<u16>>>()?;
+        Ok(decode_utf16(&units))
+    }
+
+    /// Reads a null-terminated UTF-16 string of [`EMI_NAME_MAX`] units.
+    fn fixed_string(&mut self) -> anyhow::Result<String> {
+        let units: [u8; EMI_NAME_MAX * 2] = self.bytes()?;
+        let units: Vec<u16> = units
+            .chunks_exact(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        Ok(decode_utf16(&units))
+    }
+}
+
+fn decode_utf16(units: &[u16]) -> String {
+    let end = units.iter().position(|u| *u == 0).unwrap_or(units.len());
+    String::from_utf16_lossy(&units[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn fixed(s: &str) -> Vec<u8> {
+        let mut units: Vec<u16> = s.encode_utf16().collect();
+        units.resize(EMI_NAME_MAX, 0);
+        units.iter().flat_map(|u| u.to_le_bytes()).collect()
+    }
+
+    fn string(s: &str) -> (u16, Vec<u8>) {
+        let bytes: Vec<u8> = s.encode_utf16().chain([0]).flat_map(|u| u.to_le_bytes()).collect();
+        (bytes.len() as u16, bytes)
+    }
+
+    #[test]
+    fn metadata_v1() {
+        let mut data = UNIT_PICOWATT_HOURS.to_le_bytes().to_vec();
+        data.extend(fixed("Qualcomm"));
+        data.extend(fixed("SC8280XP"));
+        data.extend(1u16.to_le_bytes());
+        let (size, name) = string("SYS");
+        data.extend(size.to_le_bytes());
+        data.extend(name);
+
+        let metadata = parse_metadata(1, &data).unwrap();
+        assert_eq!(
+            metadata,
+            Metadata {
+                oem: String::from("Qualcomm"),
+                model: String::from("SC8280XP"),
+                channels: vec![String::from("SYS")],
+            }
+        );
+    }
+
+    #[test]
+    fn metadata_v2() {
+        let mut data = fixed("Intel");
+        data.extend(fixed("RAPL_Package0"));
+        data.extend(1u16.to_le_bytes());
+        data.extend(2u16.to_le_bytes());
+        assert_eq!(data.len(), 68);
+        for channel in ["RAPL_Package0_PKG", "RAPL_Package0_DRAM"] {
+            let (size, name) = string(channel);
+            data.extend(UNIT_PICOWATT_HOURS.to_le_bytes());
+            data.extend(size.to_le_bytes());
+            data.extend(name);
+        }
+
+        let metadata = parse_metadata(2, &data).unwrap();
+        assert_eq!(metadata.oem, "Intel");
+        assert_eq!(metadata.model, "RAPL_Package0");
+        assert_eq!(metadata.channels, vec!["RAPL_Package0_PKG", "RAPL_Package0_DRAM"]);
+
+        // truncated data
+        parse_metadata(2, &data[..data.len() - 4]).unwrap_err();
+        parse_metadata(3, &data).unwrap_err();
+    }
+
+    #[test]
+    fn measurements() {
+        let data: Vec<u8> = [1_000_000_000u64, 20_000_000, 2_000_000_000, 20_000_000]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let m = parse_measurements(&data, 2).unwrap();
+        assert_eq!(
+            m,
+            vec![
+                ChannelMeasurement {
+                    energy_pwh: 1_000_000_000,
+                    time: 20_000_000
+                },
+                ChannelMeasurement {
+                    energy_pwh: 2_000_000_000,
+                    time: 20_000_000
+                },
+            ]
+        );
+        parse_measurements(&data, 3).unwrap_err();
+
+        let previous = ChannelMeasurement { energy_pwh: 0, time: 0 };
+        assert_eq!(m[0].delta_since(&previous), (3.6, 2.0));
+    }
+
+    #[test]
+    fn rapl_channels() {
+        assert_eq!(
+            channel_resource("RAPL_Package0_PKG"),
+            (Resource::CpuPackage { id: 0 }, Some("package"))
+        );
+        assert_eq!(
+            channel_resource("RAPL_Package1_DRAM"),
+            (Resource::Dram { pkg_id: 1 }, Some("dram"))
+        );
+        assert_eq!(
+            channel_resource("RAPL_Package0_PSYS"),
+            (Resource::LocalMachine, Some("platform"))
+        );
+        assert_eq!(channel_resource("CPU_CLUSTER_0"), (Resource::LocalMachine, None));
+    }
+}
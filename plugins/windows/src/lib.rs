@@ -0,0 +1,114 @@
+//! Measures the utilization of the processors, the CPU time of the processes and,
+//! where the platform provides them, the energy meters of Windows.
+//!
+//! On the other operating systems, this crate does not provide any plugin.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[cfg_attr(not(windows), allow(dead_code))]
+mod cpu;
+#[cfg_attr(not(windows), allow(dead_code))]
+mod emi;
+#[cfg(windows)]
+mod source;
+#[cfg(windows)]
+mod win32;
+
+#[cfg(windows)]
+pub use plugin::WindowsPlugin;
+
+#[cfg(windows)]
+mod plugin {
+    use alumet::{
+        pipeline::elements::source::trigger::TriggerSpec,
+        plugin::{
+            AlumetPluginStart, ConfigTable,
+            rust::{AlumetPlugin, deserialize_config, serialize_config},
+        },
+    };
+
+    use crate::{
+        Config,
+        source::{CpuSource, EnergyMeterSource, Metrics, ProcessSource},
+        win32::EnergyMeter,
+    };
+
+    pub struct WindowsPlugin {
+        config: Config,
+    }
+
+    impl AlumetPlugin for WindowsPlugin {
+        fn name() -> &'static str {
+            "windows"
+        }
+
+        fn version() -> &'static str {
+            env!("CARGO_PKG_VERSION")
+        }
+
+        fn default_config() -> anyhow::Result<Option<ConfigTable>> {
+            let config = serialize_config(Config::default())?;
+            Ok(Some(config))
+        }
+
+        fn init(config: ConfigTable) -> anyhow::Result<Box<Self>> {
+            let config = deserialize_config(config)?;
+            Ok(Box::new(WindowsPlugin { config }))
+        }
+
+        fn start(&mut self, alumet: &mut AlumetPluginStart) -> anyhow::Result<()> {
+            let metrics = Metrics::new(alumet)?;
+            let trigger = || TriggerSpec::at_interval(self.config.poll_interval);
+
+            alumet.add_source("cpu", Box::new(CpuSource::new(&metrics)), trigger())?;
+            if self.config.processes {
+                alumet.add_source("processes", Box::new(ProcessSource::new(&metrics)), trigger())?;
+            }
+            if self.config.energy_meters {
+                let meters = EnergyMeter::enumerate()?;
+                if meters.is_empty() {
+                    log::warn!("No energy meter found, the energy will not be measured.");
+                } else {
+                    for meter in &meters {
+                        log::info!(
+                            "Found energy meter {} {} with channels {:?}",
+                            meter.metadata.oem,
+                            meter.metadata.model,
+                            meter.metadata.channels
+                        );
+                    }
+                    let source = EnergyMeterSource::new(meters, &metrics);
+                    alumet.add_source("energy_meters", Box::new(source), trigger())?;
+                }
+            }
+            Ok(())
+        }
+
+        fn stop(&mut self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Interval between two measurements.
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+    /// Measure the CPU time of every process.
+    pub processes: bool,
+    /// Read the energy meters of the platform (Energy Meter Interface).
+    pub energy_meters: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            processes: true,
+            energy_meters: true,
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use alumet::{
+    measurement::{MeasurementAccumulator, MeasurementPoint, Timestamp},
+    metrics::TypedMetricId,
+    pipeline::{Source, elements::error::PollError},
+    plugin::AlumetPluginStart,
+    resources::{Resource, ResourceConsumer},
+    units::{PrefixedUnit, Unit},
+};
+use anyhow::Context;
+
+use crate::{
+    cpu::{ProcessTracker, SystemTimes},
+    emi::{self, ChannelMeasurement},
+    win32::{self, EnergyMeter},
+};
+
+/// Contains the ids of the measured metrics.
+pub struct Metrics {
+    cpu_utilization: TypedMetricId<f64>,
+    cpu_time_delta: TypedMetricId<u64>,
+    energy: TypedMetricId<f64>,
+    power: TypedMetricId<f64>,
+}
+
+impl Metrics {
+    pub fn new(alumet: &mut AlumetPluginStart) -> anyhow::Result<Self> {
+        Ok(Self {
+            cpu_utilization: alumet.create_metric(
+                "cpu_utilization",
+                Unit::Percent,
+                "Utilization of all the processors since the previous measurement",
+            )?,
+            cpu_time_delta: alumet.create_metric(
+                "cpu_time_delta",
+                PrefixedUnit::nano(Unit::Second),
+                "Time spent executing on the CPU since the previous measurement",
+            )?,
+            energy: alumet.create_metric(
+                "emi_consumed_energy",
+                Unit::Joule,
+                "Energy consumed since the previous measurement, according to an energy meter",
+            )?,
+            power: alumet.create_metric(
+                "emi_power",
+                Unit::Watt,
+                "Average power since the previous measurement, according to an energy meter",
+            )?,
+        })
+    }
+}
+
+/// Measurement source that computes the utilization of the processors.
+pub struct CpuSource {
+    metric: TypedMetricId<f64>,
+    previous: Option<SystemTimes>,
+}
+
+impl CpuSource {
+    pub fn new(metrics: &Metrics) -> Self {
+        Self {
+            metric: metrics.cpu_utilization,
+            previous: None,
+        }
+    }
+}
+
+impl Source for CpuSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let times = win32::system_times().context("GetSystemTimes failed")?;
+        if let Some(prev) = &self.previous
+            && let Some(utilization) = times.utilization_since(prev)
+        {
+            for (kind, value) in [
+                ("user", utilization.user),
+                ("system", utilization.system),
+                ("total", utilization.total()),
+            ] {
+                measurements.push(
+                    MeasurementPoint::new(
+                        timestamp,
+                        self.metric,
+                        Resource::LocalMachine,
+                        ResourceConsumer::LocalMachine,
+                        value,
+                    )
+                    .with_attr("kind", kind),
+                );
+            }
+        }
+        self.previous = Some(times);
+        Ok(())
+    }
+}
+
+/// Measurement source that measures the CPU time of every process.
+pub struct ProcessSource {
+    metric: TypedMetricId<u64>,
+    tracker: ProcessTracker,
+}
+
+impl ProcessSource {
+    pub fn new(metrics: &Metrics) -> Self {
+        Self {
+            metric: metrics.cpu_time_delta,
+            tracker: ProcessTracker::default(),
+        }
+    }
+}
+
+impl Source for ProcessSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        let processes = win32::process_times().context("failed to list the processes")?;
+        for delta in self.tracker.update(processes) {
+            let consumer = ResourceConsumer::Process { pid: delta.pid };
+            for (kind, value) in [("user", delta.user), ("system", delta.system)] {
+                measurements.push(
+                    MeasurementPoint::new(timestamp, self.metric, Resource::LocalMachine, consumer.clone(), value)
+                        .with_attr("kind", kind),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Measurement source that reads the energy meters of the Energy Meter Interface.
+pub struct EnergyMeterSource {
+    meters: Vec<MeasuredMeter>,
+    energy: TypedMetricId<f64>,
+    power: TypedMetricId<f64>,
+}
+
+struct MeasuredMeter {
+    meter: EnergyMeter,
+    /// The previous measurements, to compute the difference.
+    previous: Option<Vec<ChannelMeasurement>>,
+}
+
+impl EnergyMeterSource {
+    pub fn new(meters: Vec<EnergyMeter>, metrics: &Metrics) -> Self {
+        let meters = meters
+            .into_iter()
+            .map(|meter| MeasuredMeter { meter, previous: None })
+            .collect();
+        Self {
+            meters,
+            energy: metrics.energy,
+            power: metrics.power,
+        }
+    }
+}
+
+impl Source for EnergyMeterSource {
+    fn poll(&mut self, measurements: &mut MeasurementAccumulator, timestamp: Timestamp) -> Result<(), PollError> {
+        for m in &mut self.meters {
+            let current = m
+                .meter
+                .read()
+                .with_context(|| format!("failed to read the energy meter {}", m.meter.path))?;
+
+            // Only push deltas, not the baseline value before the plugin starts
+            if let Some(previous) = &m.previous {
+                for ((channel, now), prev) in m.meter.metadata.channels.iter().zip(&current).zip(previous) {
+                    let (joules, seconds) = now.delta_since(prev);
+                    let (resource, domain) = emi::channel_resource(channel);
+                    let point = |metric, value: f64| {
+                        let point = MeasurementPoint::new(
+                            timestamp,
+                            metric,
+                            resource.clone(),
+                            ResourceConsumer::LocalMachine,
+                            value,
+                        )
+                        .with_attr("meter", m.meter.metadata.model.clone())
+                        .with_attr("channel", channel.clone());
+                        match domain {
+                            Some(domain) => point.with_attr("domain", domain),
+                            None => point,
+                        }
+                    };
+                    measurements.push(point(self.energy, joules));
+                    if seconds > 0.0 {
+                        measurements.push(point(self.power, joules / seconds));
+                    }
+                }
+            }
+            m.previous = Some(current);
+        }
+        Ok(())
+    }
+}
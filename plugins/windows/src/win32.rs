@@ -0,0 +1,266 @@
+//! Calls to the Windows API.
+
+use std::{ffi::c_void, io, mem, ptr};
+
+use anyhow::Context;
+use windows_sys::Win32::{
+    Devices::DeviceAndDriverInstallation::{
+        DIGCF_DEVICEINTERFACE, DIGCF_PRESENT, HDEVINFO, SP_DEVICE_INTERFACE_DATA, SP_DEVICE_INTERFACE_DETAIL_DATA_W,
+        SetupDiDestroyDeviceInfoList, SetupDiEnumDeviceInterfaces, SetupDiGetClassDevsW,
+        SetupDiGetDeviceInterfaceDetailW,
+    },
+    Foundation::{CloseHandle, ERROR_NO_MORE_ITEMS, FILETIME, GENERIC_READ, HANDLE, INVALID_HANDLE_VALUE},
+    Storage::FileSystem::{CreateFileW, FILE_ATTRIBUTE_NORMAL, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING},
+    System::{
+        Diagnostics::ToolHelp::{
+            CreateToolhelp32Snapshot, PROCESSENTRY32W, Process32FirstW, Process32NextW, TH32CS_SNAPPROCESS,
+        },
+        IO::DeviceIoControl,
+        Power::{
+            EMI_METADATA_SIZE, EMI_VERSION, GUID_DEVICE_ENERGY_METER, IOCTL_EMI_GET_MEASUREMENT,
+            IOCTL_EMI_GET_METADATA, IOCTL_EMI_GET_METADATA_SIZE, IOCTL_EMI_GET_VERSION,
+        },
+        Threading::{GetProcessTimes, GetSystemTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
+    },
+};
+
+use crate::{
+    cpu::{ProcessTimes, SystemTimes},
+    emi::{self, ChannelMeasurement, Metadata},
+};
+
+/// A handle that is closed on drop.
+struct Handle(HANDLE);
+
+// The handles of the kernel objects can be used from any thread.
+unsafe impl Send for Handle {}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+fn filetime(t: FILETIME) -> u64 {
+    (u64::from(t.dwHighDateTime) << 32) | u64::from(t.dwLowDateTime)
+}
+
+pub fn system_times() -> io::Result<SystemTimes> {
+    let mut idle = FILETIME::default();
+    let mut kernel = FILETIME::default();
+    let mut user = FILETIME::default();
+    if unsafe { GetSystemTimes(&mut idle, &mut kernel, &mut user) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(SystemTimes {
+        idle: filetime(idle),
+        kernel: filetime(kernel),
+        user: filetime(user),
+    })
+}
+
+/// Returns the CPU times of all the processes that can be queried.
+///
+/// Some processes, like the protected processes of the system, cannot be queried by a normal user: they are ignored.
+pub fn process_times() -> io::Result<Vec<ProcessTimes>> {
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+    let snapshot = Handle(snapshot);
+
+    let mut res = Vec::new();
+    let mut entry = PROCESSENTRY32W {
+        dwSize: mem::size_of::<PROCESSENTRY32W>() as u32,
+        ..Default::default()
+    };
+    let mut ok = unsafe { Process32FirstW(snapshot.0, &mut entry) };
+    while ok != 0 {
+        // pid 0 is the "System Idle Process"
+        if entry.th32ProcessID != 0
+            && let Some(times) = times_of_process(entry.th32ProcessID)
+        {
+            res.push(times);
+        }
+        ok = unsafe { Process32NextW(snapshot.0, &mut entry) };
+    }
+    Ok(res)
+}
+
+fn times_of_process(pid: u32) -> Option<ProcessTimes> {
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if handle.is_null() {
+        return None;
+    }
+    let handle = Handle(handle);
+    let mut creation = FILETIME::default();
+    let mut exit = FILETIME::default();
+    let mut kernel = FILETIME::default();
+    let mut user = FILETIME::default();
+    if unsafe { GetProcessTimes(handle.0, &mut creation, &mut exit, &mut kernel, &mut user) } == 0 {
+        return None;
+    }
+    Some(ProcessTimes {
+        pid,
+        creation: filetime(creation),
+        kernel: filetime(kernel),
+        user: filetime(user),
+    })
+}
+
+/// An energy meter of the Energy Meter Interface.
+pub struct EnergyMeter {
+    pub path: String,
+    pub metadata: Metadata,
+    handle: Handle,
+}
+
+impl EnergyMeter {
+    /// Lists the energy meters of the machine.
+    pub fn enumerate() -> anyhow::Result<Vec<EnergyMeter>> {
+        let paths = device_interface_paths().context("failed to list the energy meters")?;
+        paths
+            .into_iter()
+            .map(|path| {
+                EnergyMeter::open(&path).with_context(|| {
+                    let path = String::from_utf16_lossy(&path[..path.len() - 1]);
+                    format!("failed to open the energy meter {path}")
+                })
+            })
+            .collect()
+    }
+
+    fn open(path: &[u16]) -> anyhow::Result<EnergyMeter> {
+        let handle = unsafe {
+            CreateFileW(
+                path.as_ptr(),
+                GENERIC_READ,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                ptr::null(),
+                OPEN_EXISTING,
+                FILE_ATTRIBUTE_NORMAL,
+                ptr::null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error().into());
+        }
+        let handle = Handle(handle);
+
+        let mut version = EMI_VERSION::default();
+        ioctl(&handle, IOCTL_EMI_GET_VERSION, as_bytes(&mut version)).context("IOCTL_EMI_GET_VERSION failed")?;
+        let mut size = EMI_METADATA_SIZE::default();
+        ioctl(&handle, IOCTL_EMI_GET_METADATA_SIZE, as_bytes(&mut size))
+            .context("IOCTL_EMI_GET_METADATA_SIZE failed")?;
+        let mut buf = vec![0u8; size.MetadataSize as usize];
+        let n = ioctl(&handle, IOCTL_EMI_GET_METADATA, &mut buf).context("IOCTL_EMI_GET_METADATA failed")?;
+        let metadata = emi::parse_metadata(version.EmiVersion, &buf[..n])?;
+
+        let path = String::from_utf16_lossy(&path[..path.len() - 1]);
+        Ok(EnergyMeter { path, metadata, handle })
+    }
+
+    /// Reads the absolute energy of each channel.
+    pub fn read(&self) -> anyhow::Result<Vec<ChannelMeasurement>> {
+        let count = self.metadata.channels.len();
+        let mut buf = vec![0u8; count * 16];
+        let n = ioctl(&self.handle, IOCTL_EMI_GET_MEASUREMENT, &mut buf).context("IOCTL_EMI_GET_MEASUREMENT failed")?;
+        emi::parse_measurements(&buf[..n], count)
+    }
+}
+
+fn as_bytes<T>(value: &mut T) -> &mut [u8] {
+    unsafe { std::slice::from_raw_parts_mut((value as *mut T).cast(), mem::size_of::<T>()) }
+}
+
+/// Sends an ioctl without input and returns the number of bytes written to `out`.
+fn ioctl(handle: &Handle, code: u32, out: &mut [u8]) -> io::Result<usize> {
+    let mut returned = 0u32;
+    let ok = unsafe {
+        DeviceIoControl(
+            handle.0,
+            code,
+            ptr::null(),
+            0,
+            out.as_mut_ptr().cast::<c_void>(),
+            out.len() as u32,
+            &mut returned,
+            ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(returned as usize)
+}
+
+/// A device information set, destroyed on drop.
+struct DeviceInfoSet(HDEVINFO);
+
+impl Drop for DeviceInfoSet {
+    fn drop(&mut self) {
+        unsafe { SetupDiDestroyDeviceInfoList(self.0) };
+    }
+}
+
+/// Returns the (null-terminated) paths of the energy meters.
+fn device_interface_paths() -> io::Result<Vec<Vec<u16>>> {
+    let set = unsafe {
+        SetupDiGetClassDevsW(
+            &GUID_DEVICE_ENERGY_METER,
+            ptr::null(),
+            ptr::null_mut(),
+            DIGCF_PRESENT | DIGCF_DEVICEINTERFACE,
+        )
+    };
+    if set == INVALID_HANDLE_VALUE as HDEVINFO {
+        return Err(io::Error::last_os_error());
+    }
+    let set = DeviceInfoSet(set);
+
+    let mut paths = Vec::new();
+    for index in 0.. {
+        let mut interface = SP_DEVICE_INTERFACE_DATA {
+            cbSize: mem::size_of::<SP_DEVICE_INTERFACE_DATA>() as u32,
+            ..Default::default()
+        };
+        let ok = unsafe {
+            SetupDiEnumDeviceInterfaces(set.0, ptr::null(), &GUID_DEVICE_ENERGY_METER, index, &mut interface)
+        };
+        if ok == 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(ERROR_NO_MORE_ITEMS as i32) {
+                break;
+            }
+            return Err(err);
+        }
+
+        // First call to get the size of the detail data, second call to get the data.
+        let mut size = 0u32;
+        unsafe { SetupDiGetDeviceInterfaceDetailW(set.0, &interface, ptr::null_mut(), 0, &mut size, ptr::null_mut()) };
+        if size == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Use a buffer of u32 to respect the alignment of SP_DEVICE_INTERFACE_DETAIL_DATA_W.
+        let mut buf = vec![0u32; (size as usize).div_ceil(4)];
+        let detail = buf.as_mut_ptr().cast::<SP_DEVICE_INTERFACE_DETAIL_DATA_W>();
+        unsafe { (*detail).cbSize = mem::size_of::<SP_DEVICE_INTERFACE_DETAIL_DATA_W>() as u32 };
+        let ok = unsafe {
+            SetupDiGetDeviceInterfaceDetailW(set.0, &interface, detail, size, ptr::null_mut(), ptr::null_mut())
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // DevicePath is a null-terminated string that starts after cbSize.
+        let path_offset = mem::offset_of!(SP_DEVICE_INTERFACE_DETAIL_DATA_W, DevicePath);
+        let path_len = (size as usize - path_offset) / 2;
+        let path =
+            unsafe { std::slice::from_raw_parts(buf.as_ptr().cast::<u8>().add(path_offset).cast::<u16>(), path_len) };
+        let end = path.iter().position(|c| *c == 0).unwrap_or(path.len());
+        let mut path = path[..end].to_vec();
+        path.push(0);
+        paths.push(path);
+    }
+    Ok(paths)
+}
@@ -1,10 +1,17 @@
 #include <inttypes.h>
 #include "output.h"
 
+typedef struct {
+    const StdOutput *output;
+    const FfiOutputContext *ctx;
+} WriteContext;
+
 void write_point(void *data, const MeasurementPoint *point);
 
-StdOutput *output_init() {
-    return malloc(sizeof(StdOutput));
+StdOutput *output_init(const char *prefix) {
+    StdOutput *output = malloc(sizeof(StdOutput));
+    output->prefix = prefix;
+    return output;
 }
 
 void output_drop(StdOutput *output) {
@@ -12,11 +19,18 @@ void output_drop(StdOutput *output) {
 }
 
 void output_write(StdOutput *output, const MeasurementBuffer *buffer, const FfiOutputContext *ctx) {
-    mbuffer_foreach(buffer, (void*)ctx, write_point);
+    WriteContext write_ctx = {.output = output, .ctx = ctx};
+    mbuffer_foreach(buffer, &write_ctx, write_point);
+}
+
+void output_lagged(StdOutput *output, uint64_t lost_buffers) {
+    fprintf(stderr, "%s%" PRIu64 " buffers lost\n", output->prefix, lost_buffers);
 }
 
 void write_point(void *data, const MeasurementPoint *point) {
-    const FfiOutputContext *ctx = data;
+    const WriteContext *write_ctx = data;
+    const FfiOutputContext *ctx = write_ctx->ctx;
+    const char *prefix = write_ctx->output->prefix;
     FfiMeasurementValue value = mpoint_value(point);
     Timestamp t = mpoint_timestamp(point);
     AStr metric = metric_name(mpoint_metric(point), ctx);
@@ -29,7 +43,8 @@ void write_point(void *data, const MeasurementPoint *point) {
 
     switch (value.tag) {
         case FfiMeasurementValue_U64: {
-            printf("[%lu] on %.*s %.*s by %.*s %.*s, %.*s(id %lu) = %" PRIu64 "\n",
+            printf("%s[%lu] on %.*s %.*s by %.*s %.*s, %.*s(id %lu) = %" PRIu64 "\n",
+                prefix,
                 t.secs,
                 (int)resource_kind.len, resource_kind.ptr,
                 (int)resource_id.len, resource_id.ptr,
@@ -42,7 +57,8 @@ void write_point(void *data, const MeasurementPoint *point) {
         }
         break;
         case FfiMeasurementValue_F64: {
-            printf("[%lu] on %.*s %.*s by %.*s %.*s, %.*s(id %lu) = %f\n",
+            printf("%s[%lu] on %.*s %.*s by %.*s %.*s, %.*s(id %lu) = %f\n",
+                prefix,
                 t.secs,
                 (int)resource_kind.len, resource_kind.ptr,
                 (int)resource_id.len, resource_id.ptr,
@@ -4,10 +4,13 @@
 #include <stdio.h>
 #include "alumet.h"
 
-typedef struct {} StdOutput;
+typedef struct {
+    const char *prefix; // printed before each measurement
+} StdOutput;
 
-StdOutput *output_init();
+StdOutput *output_init(const char *prefix);
 void output_drop(StdOutput *output);
 void output_write(StdOutput *output, const MeasurementBuffer *buffer, const FfiOutputContext *ctx);
+void output_lagged(StdOutput *output, uint64_t lost_buffers);
 
 #endif
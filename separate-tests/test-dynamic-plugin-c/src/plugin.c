@@ -2,12 +2,19 @@
 #include <string.h>
 #include "alumet.h"
 #include "source.h"
+#include "transform.h"
 #include "output.h"
 
 PLUGIN_API const char *PLUGIN_NAME = "test-dynamic-plugin-c";
 PLUGIN_API const char *PLUGIN_VERSION = "0.1.0";
 PLUGIN_API const char *ALUMET_VERSION = "0.9.0";
 
+// The ABI is not declared when building a broken plugin for the tests.
+#ifndef TEST_WITHOUT_ABI
+PLUGIN_API const uint32_t PLUGIN_ABI_VERSION = ALUMET_ABI_VERSION;
+PLUGIN_API const uint64_t PLUGIN_ABI_HASH = ALUMET_ABI_HASH;
+#endif
+
 typedef struct {
     AString custom_attribute;
 } PluginStruct;
@@ -41,9 +48,17 @@ PLUGIN_API void plugin_start(PluginStruct *plugin, AlumetPluginStart *alumet) {
     TimeDuration flush_interval = poll_interval;
     alumet_add_source(alumet, source, poll_interval, flush_interval, (SourcePollFn)source_poll, (NullableDropFn)source_drop);
 
-    // create and register the output
-    StdOutput *output = output_init();
+    // create and register the transform, which converts the measurements of the source to Watt-hours
+    FfiUnit wh = {.tag = FfiUnit_WattHour};
+    RawMetricId rapl_pkg_wh_metric = alumet_create_metric_c(alumet, "rapl_pkg_consumption_wh", WrappedMeasurementType_F64, wh, "Energy consumption of the RAPL domain `package`, since the previous measurement, in Watt-hours.");
+    WattHourTransform *transform = transform_init(rapl_pkg_metric, rapl_pkg_wh_metric);
+    alumet_add_transform(alumet, transform, (TransformApplyFn)transform_apply, (NullableDropFn)transform_drop);
+
+    // create and register the outputs: one blocking output and one async output
+    StdOutput *output = output_init("");
     alumet_add_output(alumet, output, (OutputWriteFn)output_write, (NullableDropFn)output_drop);
+    StdOutput *async_output = output_init("[async] ");
+    alumet_add_async_output(alumet, async_output, (OutputWriteFn)output_write, (NullableOutputLaggedFn)output_lagged, (NullableDropFn)output_drop);
 
     // ok!
     printf("plugin_start finished successfully\n");
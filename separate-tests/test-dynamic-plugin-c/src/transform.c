@@ -0,0 +1,44 @@
+#include "transform.h"
+
+static bool is_energy(void *data, const MeasurementPoint *point);
+static void convert_point(void *data, MeasurementPoint *point);
+
+/// @brief Creates a new WattHourTransform.
+/// @param joules_metric_id id of the metric to convert, in Joules
+/// @param wh_metric_id id of the metric to convert it to, in Watt-hours
+/// @return the new transform
+WattHourTransform *transform_init(RawMetricId joules_metric_id, RawMetricId wh_metric_id) {
+    WattHourTransform *transform = malloc(sizeof(WattHourTransform));
+    transform->joules_metric_id = joules_metric_id;
+    transform->wh_metric_id = wh_metric_id;
+    return transform;
+}
+
+/// @brief Destructor of the transform: frees the memory that transform points to.
+/// @param transform the transform to destruct
+void transform_drop(WattHourTransform *transform) {
+    free(transform);
+}
+
+/// @brief Transform.apply(buffer, ctx)
+/// @param transform the transform to apply
+/// @param buffer the measurements, which can be modified
+/// @param ctx the transform context
+void transform_apply(WattHourTransform *transform, MeasurementBuffer *buffer, const FfiTransformContext *ctx) {
+    // only keep the energy measurements, and convert them
+    mbuffer_retain(buffer, transform, is_energy);
+    mbuffer_foreach_mut(buffer, transform, convert_point);
+}
+
+bool is_energy(void *data, const MeasurementPoint *point) {
+    WattHourTransform *transform = data;
+    return mpoint_metric(point)._0 == transform->joules_metric_id._0;
+}
+
+void convert_point(void *data, MeasurementPoint *point) {
+    WattHourTransform *transform = data;
+    FfiMeasurementValue value = mpoint_value(point);
+    double joules = (value.tag == FfiMeasurementValue_F64) ? value.f64 : (double)value.u64;
+    mpoint_set_metric(point, transform->wh_metric_id);
+    mpoint_set_value_f64(point, joules / 3600.0);
+}
@@ -0,0 +1,16 @@
+#ifndef __TRANSFORM_H
+#define __TRANSFORM_H
+
+#include <stdio.h>
+#include "alumet.h"
+
+typedef struct {
+    RawMetricId joules_metric_id; // id of the metric to convert
+    RawMetricId wh_metric_id;     // id of the converted metric
+} WattHourTransform;
+
+WattHourTransform *transform_init(RawMetricId joules_metric_id, RawMetricId wh_metric_id);
+void transform_drop(WattHourTransform *transform);
+void transform_apply(WattHourTransform *transform, MeasurementBuffer *buffer, const FfiTransformContext *ctx);
+
+#endif
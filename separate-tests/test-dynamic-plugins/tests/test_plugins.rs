@@ -1,3 +1,5 @@
+use alumet_ffi::abi::Abi;
+use alumet_ffi::dynload::{LoadError, load_cdylib};
 use pretty_assertions::assert_str_eq;
 use std::path::{Path, PathBuf};
 use std::{io, process::Command};
@@ -41,6 +43,38 @@ fn test_plugin_c() {
     run_app_with_plugin(&plugin_lib, "test-dynamic-plugin-c", "0.1.0");
 }
 
+#[test]
+fn test_plugin_c_without_abi() {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let plugin_dir = crate_dir.join("../test-dynamic-plugin-c");
+    let plugin_lib = plugin_dir.join("target/plugin-without-abi.so");
+    let bindgen_out_dir = PathBuf::from(env!("ALUMET_H_BINDINGS_DIR"));
+
+    println!("make plugin-without-abi...");
+    let build_result = Command::new("make")
+        .arg("plugin-without-abi")
+        .current_dir(plugin_dir)
+        .env("ALUMET_H_BINDINGS_DIR", bindgen_out_dir)
+        .spawn()
+        .expect("Running `make` failed")
+        .wait()
+        .unwrap();
+    assert!(build_result.success(), "Building C plugin failed");
+
+    // the plugin does not declare its ABI, it must be rejected before calling any of its functions
+    match load_cdylib(&plugin_lib) {
+        Err(LoadError::IncompatibleAbi {
+            plugin_abi,
+            current_abi,
+        }) => {
+            assert_eq!(plugin_abi, None);
+            assert_eq!(current_abi, Abi::current());
+        }
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("the plugin should not be loaded"),
+    }
+}
+
 fn run_app_with_plugin(plugin_lib: &Path, plugin_name: &str, plugin_version: &str) {
     // check paths
     let plugin_lib_path = plugin_lib
@@ -105,12 +139,18 @@ fn check_app_output(output: String, plugin_name: &str, plugin_version: &str) {
     assert_str_eq!("[app] Starting the pipeline...", lines[7]);
     assert_str_eq!("[app] pipeline started", lines[8]);
 
+    // the measurements are converted to Watt-hours by the transform before reaching the outputs,
+    // and the async output prefixes them with "[async] "
     let measurement_output_regex = regex::Regex::new(
-        "\\[\\d+\\] on cpu_package 0 by local_machine , rapl_pkg_consumption\\(id \\d+\\) = \\d+\\.\\d+",
+        "(\\[async\\] )?\\[\\d+\\] on cpu_package 0 by local_machine , rapl_pkg_consumption_wh\\(id \\d+\\) = \\d+\\.\\d+",
     )
     .unwrap();
+    let mut async_measurements = 0;
     for i in 9..lines.len() - 3 {
         let line = lines[i];
+        if line.starts_with("[async] ") {
+            async_measurements += 1;
+        }
         let is_measurement = measurement_output_regex.is_match(line);
         assert!(
             is_measurement || line == "[app] shutting down...",
@@ -119,6 +159,10 @@ fn check_app_output(output: String, plugin_name: &str, plugin_version: &str) {
             measurement_output_regex
         );
     }
+    assert!(
+        async_measurements > 0,
+        "the async output should have written some measurements"
+    );
 
     let line_pstop = lines[lines.len() - 3];
     let line_pdrop = lines[lines.len() - 2];
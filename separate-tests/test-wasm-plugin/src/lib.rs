@@ -0,0 +1,91 @@
+//! Example of WebAssembly plugin, used to test the `wasm` plugin.
+//!
+//! Build it with `cargo build --target wasm32-wasip2 --release`.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+wit_bindgen::generate!({
+    path: "../../plugins/wasm/wit",
+    world: "plugin",
+});
+
+use alumet::plugin::{
+    host::{LogLevel, log},
+    types::{AttributeValue, MeasurementValue, Metric, ResourceId, Source, ValueType},
+};
+
+const METRIC: &str = "wasm_counter";
+
+/// Produces the values 1, 2, 3... and multiplies them by a factor (the only option of the config).
+struct CounterPlugin;
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+static FACTOR: AtomicU64 = AtomicU64::new(1);
+
+impl Guest for CounterPlugin {
+    fn start(config: String) -> Result<Registrations, String> {
+        // the config is tiny, no need to pull a JSON parser
+        let factor = config
+            .trim_matches(|c| c == '{' || c == '}')
+            .split_once(':')
+            .filter(|(key, _)| key.trim() == "\"factor\"")
+            .map(|(_, value)| value.trim().parse::<u64>().map_err(|e| format!("invalid factor: {e}")))
+            .transpose()?
+            .unwrap_or(1);
+        FACTOR.store(factor, Ordering::Relaxed);
+        log(LogLevel::Info, &format!("started with factor {factor}"));
+
+        Ok(Registrations {
+            metrics: vec![Metric {
+                name: METRIC.to_owned(),
+                value_type: ValueType::U64,
+                unit: String::from("1"),
+                description: String::from("increasing counter"),
+            }],
+            sources: vec![Source {
+                name: String::from("counter"),
+                poll_interval_ms: 10,
+                flush_interval_ms: 10,
+            }],
+            transforms: vec![String::from("multiply")],
+            outputs: vec![String::from("check")],
+        })
+    }
+
+    fn poll_source(_source: String, timestamp: u64) -> Result<Vec<MeasurementPoint>, String> {
+        let value = COUNTER.fetch_add(1, Ordering::Relaxed) + 1;
+        Ok(vec![MeasurementPoint {
+            timestamp,
+            metric: METRIC.to_owned(),
+            value: MeasurementValue::U64(value),
+            resource: ResourceId {
+                kind: String::from("local_machine"),
+                id: String::new(),
+            },
+            consumer: ResourceId {
+                kind: String::from("local_machine"),
+                id: String::new(),
+            },
+            attributes: Vec::new(),
+        }])
+    }
+
+    fn apply_transform(_transform: String, mut points: Vec<MeasurementPoint>) -> Result<Vec<MeasurementPoint>, String> {
+        let factor = FACTOR.load(Ordering::Relaxed);
+        for p in points.iter_mut().filter(|p| p.metric == METRIC) {
+            if let MeasurementValue::U64(v) = p.value {
+                p.value = MeasurementValue::U64(v * factor);
+                p.attributes.push((String::from("factor"), AttributeValue::U64(factor)));
+            }
+        }
+        Ok(points)
+    }
+
+    fn write_output(_output: String, points: Vec<MeasurementPoint>) -> Result<(), String> {
+        match points.iter().find(|p| p.metric == METRIC && p.attributes.is_empty()) {
+            Some(p) => Err(format!("point not transformed: {:?}", p.value)),
+            None => Ok(()),
+        }
+    }
+}
+
+export!(CounterPlugin);